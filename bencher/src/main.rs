@@ -75,4 +75,5 @@ fn main() {
 	benchmark!(database::reorg_short);
 	benchmark!(database::write_heavy);
 	benchmark!(verifier::main);
+	benchmark!(verifier::large_transactions);
 }
@@ -0,0 +1,250 @@
+//! pbtc-bench: replays stored blocks through the verifier and reports verification speed.
+//!
+//! Blocks are read from an existing pbtc database. Chain state is rolled back to the block before
+//! the first replayed one in the database overlay and blocks are verified while applying them again,
+//! so the database itself is never modified.
+//!
+//! Example:
+//!
+//! ```sh
+//! pbtc-bench --db ~/.local/share/pbtc/db --from 400000 --to 401000 --threads 4
+//! ```
+
+#[macro_use]
+extern crate log;
+extern crate clap;
+extern crate db;
+extern crate chain;
+extern crate logs;
+extern crate network;
+extern crate rayon;
+extern crate time;
+extern crate verification;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process;
+use clap::{App, Arg};
+use time::PreciseTime;
+use chain::IndexedBlock;
+use db::{BlockChainDatabase, BlockRef, SideChainOrigin, Forkable, Store, IndexedBlockProvider};
+use network::{Magic, ConsensusParams, ConsensusFork, BitcoinCashConsensusParams};
+use verification::{ChainVerifier, ChainAcceptor, CanonBlock, Deployments, BlockDeployments, VerificationLevel};
+
+/// Default database cache size in MB.
+const DEFAULT_DB_CACHE: usize = 512;
+/// Number of blocks, replayed by default.
+const DEFAULT_BLOCKS: u32 = 100;
+
+struct Options {
+	db: String,
+	db_cache: usize,
+	consensus: ConsensusParams,
+	level: VerificationLevel,
+	threads: Option<usize>,
+	from: Option<u32>,
+	to: Option<u32>,
+}
+
+/// Time spent in every stage of the replay, in nanoseconds.
+#[derive(Default)]
+struct Timings {
+	read: i64,
+	pre_verify: i64,
+	rollback: i64,
+	accept: i64,
+	canonize: i64,
+}
+
+fn main() {
+	if let Err(err) = logs::init("bench=info", logs::DateLogFormatter) {
+		eprintln!("Failed to initialize logger: {}", err);
+		process::exit(1);
+	}
+
+	if let Err(err) = run() {
+		error!(target: "bench", "{}", err);
+		process::exit(1);
+	}
+}
+
+fn run() -> Result<(), String> {
+	let options = parse_options()?;
+
+	if let Some(threads) = options.threads {
+		rayon::initialize(rayon::Configuration::new().num_threads(threads))
+			.map_err(|err| format!("Failed to initialize verification threads: {}", err))?;
+	}
+	logs::enable_spans();
+
+	if !Path::new(&options.db).exists() {
+		return Err(format!("Database {} doesn't exist", options.db));
+	}
+	let store = BlockChainDatabase::open_at_path(&options.db, options.db_cache)
+		.map_err(|err| format!("Failed to open database: {:?}", err))?;
+
+	let best_block = store.best_block();
+	let to = options.to.unwrap_or(best_block.number);
+	let from = options.from.unwrap_or_else(|| to.saturating_sub(DEFAULT_BLOCKS - 1));
+	if from == 0 || from > to || to > best_block.number {
+		return Err(format!("Invalid blocks range {}..{}: best block is {}, genesis block can't be replayed", from, to, best_block.number));
+	}
+
+	let mut timings = Timings::default();
+	let blocks = measure(&mut timings.read, || read_blocks(&store, from, to))?;
+	let transactions: usize = blocks.iter().map(|block| block.transactions.len()).sum();
+	info!(target: "bench", "Replaying blocks {}..{}: {} blocks, {} transactions, {:?} verification", from, to, blocks.len(), transactions, options.level);
+
+	let start = PreciseTime::now();
+	if options.level != VerificationLevel::NoVerification {
+		let current_time = ::time::get_time().sec as u32;
+		measure(&mut timings.pre_verify, || blocks.iter()
+			.map(|block| ChainVerifier::new(block, &options.consensus, current_time).check()
+				.map_err(|err| format!("Block {} pre-verification failed: {:?}", block.hash().to_reversed_str(), err)))
+			.collect::<Result<Vec<_>, _>>())?;
+	}
+
+	let origin = SideChainOrigin {
+		ancestor: from - 1,
+		canonized_route: Vec::new(),
+		decanonized_route: blocks.iter().map(|block| block.hash().clone()).collect(),
+		block_number: to,
+	};
+	let fork = measure(&mut timings.rollback, || store.fork(origin))
+		.map_err(|err| format!("Failed to rollback chain state: {:?}", err))?;
+
+	let deployments = Deployments::new();
+	for (number, block) in (from..).zip(blocks.iter()) {
+		if options.level != VerificationLevel::NoVerification {
+			let block_deployments = BlockDeployments::new(&deployments, number, fork.store().as_block_header_provider(), &options.consensus);
+			let acceptor = ChainAcceptor::new(fork.store(), &options.consensus, options.level, CanonBlock::new(block), number, &block_deployments);
+			measure(&mut timings.accept, || acceptor.check())
+				.map_err(|err| format!("Block {} verification failed: {:?}", block.hash().to_reversed_str(), err))?;
+		}
+
+		measure(&mut timings.canonize, || fork.store().canonize(block.hash()))
+			.map_err(|err| format!("Failed to canonize block {}: {:?}", block.hash().to_reversed_str(), err))?;
+	}
+	let total = start.to(PreciseTime::now()).num_nanoseconds().unwrap_or_default();
+
+	print_report(blocks.len(), transactions, total, &timings);
+	Ok(())
+}
+
+fn parse_options() -> Result<Options, String> {
+	let matches = App::new("pbtc-bench")
+		.about("Replays stored blocks through the verifier and reports verification speed.")
+		.arg(Arg::with_name("db").long("db").value_name("PATH").takes_value(true).required(true)
+			.help("Path to the pbtc database directory (e.g. ~/.local/share/pbtc/db)."))
+		.arg(Arg::with_name("db-cache").long("db-cache").value_name("SIZE").takes_value(true)
+			.help("Database cache size in MB (512 by default)."))
+		.arg(Arg::with_name("network").long("network").value_name("NETWORK").takes_value(true)
+			.possible_values(&["main", "test", "regtest"])
+			.help("Network the database belongs to (main by default)."))
+		.arg(Arg::with_name("bitcoin-cash").long("bitcoin-cash")
+			.help("Use Bitcoin Cash consensus rules."))
+		.arg(Arg::with_name("level").long("level").value_name("LEVEL").takes_value(true)
+			.possible_values(&["full", "header", "none"])
+			.help("Verification level: full (default), header (scripts are not verified), or none."))
+		.arg(Arg::with_name("threads").long("threads").value_name("N").takes_value(true)
+			.help("Number of verification threads (number of CPUs by default)."))
+		.arg(Arg::with_name("from").long("from").value_name("BLOCK").takes_value(true)
+			.help("Number of the first replayed block (100 blocks before --to by default)."))
+		.arg(Arg::with_name("to").long("to").value_name("BLOCK").takes_value(true)
+			.help("Number of the last replayed block (best block by default)."))
+		.get_matches();
+
+	let magic = match matches.value_of("network") {
+		Some("test") => Magic::Testnet,
+		Some("regtest") => Magic::Regtest,
+		_ => Magic::Mainnet,
+	};
+	let fork = if matches.is_present("bitcoin-cash") {
+		ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(magic))
+	} else {
+		ConsensusFork::NoFork
+	};
+
+	Ok(Options {
+		db: matches.value_of("db").expect("db is required; qed").to_owned(),
+		db_cache: match matches.value_of("db-cache") {
+			Some(s) => s.parse().map_err(|_| "Invalid database cache size".to_owned())?,
+			None => DEFAULT_DB_CACHE,
+		},
+		consensus: ConsensusParams::new(magic, fork),
+		level: match matches.value_of("level") {
+			Some("header") => VerificationLevel::Header,
+			Some("none") => VerificationLevel::NoVerification,
+			_ => VerificationLevel::Full,
+		},
+		threads: match matches.value_of("threads") {
+			Some(s) => Some(s.parse().map_err(|_| "Invalid number of threads".to_owned())?),
+			None => None,
+		},
+		from: match matches.value_of("from") {
+			Some(s) => Some(s.parse().map_err(|_| "Invalid first block number".to_owned())?),
+			None => None,
+		},
+		to: match matches.value_of("to") {
+			Some(s) => Some(s.parse().map_err(|_| "Invalid last block number".to_owned())?),
+			None => None,
+		},
+	})
+}
+
+fn read_blocks(store: &IndexedBlockProvider, from: u32, to: u32) -> Result<Vec<IndexedBlock>, String> {
+	(from..to + 1)
+		.map(|number| store.indexed_block(BlockRef::Number(number)).ok_or_else(|| format!("Block {} is missing", number)))
+		.collect()
+}
+
+/// Calls `f` and adds time spent in it to `elapsed`.
+fn measure<F, R>(elapsed: &mut i64, f: F) -> R where F: FnOnce() -> R {
+	let start = PreciseTime::now();
+	let result = f();
+	*elapsed += start.to(PreciseTime::now()).num_nanoseconds().unwrap_or_default();
+	result
+}
+
+/// Self time of the spans, recorded during verification, grouped by span name.
+fn spans_timings() -> BTreeMap<String, u64> {
+	let mut folded = Vec::new();
+	logs::write_folded_spans(&mut folded).expect("writing to Vec never fails; qed");
+
+	let mut timings = BTreeMap::new();
+	for line in String::from_utf8_lossy(&folded).lines() {
+		let mut parts = line.rsplitn(2, ' ');
+		let micros = parts.next().and_then(|micros| micros.parse::<u64>().ok());
+		let name = parts.next().and_then(|stack| stack.rsplit(';').next());
+		if let (Some(name), Some(micros)) = (name, micros) {
+			*timings.entry(name.to_owned()).or_insert(0) += micros;
+		}
+	}
+	timings
+}
+
+fn print_report(blocks: usize, transactions: usize, total: i64, timings: &Timings) {
+	let seconds = total as f64 / 1_000_000_000f64;
+	info!(target: "bench", "Total: {:.3} s, {:.2} blocks/s, {:.2} tx/s", seconds, blocks as f64 / seconds, transactions as f64 / seconds);
+	info!(target: "bench", "Stages:");
+	info!(target: "bench", "  read (not included in total): {:.3} s", timings.read as f64 / 1_000_000_000f64);
+	for &(name, elapsed) in &[("pre-verify", timings.pre_verify), ("rollback", timings.rollback), ("accept", timings.accept), ("canonize", timings.canonize)] {
+		info!(target: "bench", "  {}: {:.3} s ({:.1}%)", name, elapsed as f64 / 1_000_000_000f64, percent(elapsed as f64, total as f64));
+	}
+
+	let spans = spans_timings();
+	if !spans.is_empty() {
+		info!(target: "bench", "Checks (self time, summed over verification threads):");
+		for (name, micros) in spans {
+			info!(target: "bench", "  {}: {:.3} s", name, micros as f64 / 1_000_000f64);
+		}
+	}
+}
+
+fn percent(part: f64, total: f64) -> f64 {
+	if total == 0f64 {
+		0f64
+	} else {
+		part * 100f64 / total
+	}
+}
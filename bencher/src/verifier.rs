@@ -8,19 +8,25 @@ use byteorder::{LittleEndian, ByteOrder};
 
 use super::Benchmark;
 
-// 1. write BLOCKS_INITIAL blocks with 1 transaction each
-// 2. verify <BLOCKS> blocks that has <TRANSACTIONS> transaction each with <OUTPUTS> output each,
-//    spending outputs from last <BLOCKS*TRANSACTIONS*OUTPUTS> blocks
+const BLOCKS_INITIAL: usize = 200200;
+
+// verify blocks with many small transactions
 pub fn main(benchmark: &mut Benchmark) {
-	// params
-	const BLOCKS_INITIAL: usize = 200200;
-	const BLOCKS: usize = 10;
-	const TRANSACTIONS: usize = 2000;
-	const OUTPUTS: usize = 10;
+	verify_blocks(benchmark, 10, 2000, 10)
+}
 
-	benchmark.samples(BLOCKS);
+// verify blocks with a few large transactions: inputs of every transaction are verified on all script threads
+pub fn large_transactions(benchmark: &mut Benchmark) {
+	verify_blocks(benchmark, 10, 4, 5000)
+}
+
+// 1. write BLOCKS_INITIAL blocks with 1 transaction each
+// 2. verify <BLOCKS> blocks that has <TRANSACTIONS> transaction each with <INPUTS> input each,
+//    spending outputs from last <BLOCKS*TRANSACTIONS*INPUTS> blocks
+fn verify_blocks(benchmark: &mut Benchmark, blocks_count: usize, transactions_count: usize, inputs_count: usize) {
+	benchmark.samples(blocks_count);
 
-	assert!(BLOCKS_INITIAL - 100 > BLOCKS * OUTPUTS * TRANSACTIONS,
+	assert!(BLOCKS_INITIAL - 100 > blocks_count * inputs_count * transactions_count,
 		"There will be not enough initial blocks to continue this bench");
 
 	// test setup
@@ -57,7 +63,7 @@ pub fn main(benchmark: &mut Benchmark) {
 	}
 
 	let mut verification_blocks: Vec<IndexedBlock> = Vec::new();
-	for b in 0..BLOCKS {
+	for b in 0..blocks_count {
 		let mut coinbase_nonce = [0u8;8];
 		LittleEndian::write_u64(&mut coinbase_nonce[..], (b + BLOCKS_INITIAL) as u64);
 		let mut builder = test_data::block_builder()
@@ -66,11 +72,11 @@ pub fn main(benchmark: &mut Benchmark) {
 				.output().value(5000000000).build()
 				.build();
 
-		for t in 0..TRANSACTIONS {
+		for t in 0..transactions_count {
 			let mut tx_builder = builder.transaction();
 
-			for o in 0..OUTPUTS {
-				let parent_hash = blocks[(b*TRANSACTIONS*OUTPUTS + t * OUTPUTS + o)].transactions[0].hash.clone();
+			for i in 0..inputs_count {
+				let parent_hash = blocks[(b * transactions_count * inputs_count + t * inputs_count + i)].transactions[0].hash.clone();
 
 				tx_builder = tx_builder
 					.input()
@@ -1,11 +1,13 @@
 use hex::FromHex;
 use hash::H256;
-use ser::{deserialize};
+use ser::{Serializable, deserialize, SERIALIZE_TRANSACTION_WITNESS};
+use constants::WITNESS_SCALE_FACTOR;
 use merkle_root::merkle_root;
 use {BlockHeader, Transaction};
 use super::RepresentH256;
 
 #[derive(Debug, PartialEq, Clone, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Block {
 	pub block_header: BlockHeader,
 	pub transactions: Vec<Transaction>,
@@ -56,6 +58,21 @@ impl Block {
 	pub fn hash(&self) -> H256 {
 		self.block_header.hash()
 	}
+
+	/// Size of block, serialized without witness data.
+	pub fn stripped_size(&self) -> usize {
+		self.serialized_size()
+	}
+
+	/// Size of block, serialized with witness data.
+	pub fn total_size(&self) -> usize {
+		self.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS)
+	}
+
+	/// BIP141 block weight: stripped_size * 3 + total_size.
+	pub fn weight(&self) -> usize {
+		self.stripped_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+	}
 }
 
 #[cfg(test)]
@@ -73,5 +90,7 @@ mod tests {
 		let hash = H256::from_reversed_str("000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6");
 		assert_eq!(block.merkle_root(), merkle_root);
 		assert_eq!(block.hash(), hash);
+		assert_eq!(block.stripped_size(), block.total_size());
+		assert_eq!(block.weight(), block.stripped_size() * 4);
 	}
 }
@@ -1,11 +1,12 @@
 use std::fmt;
 use hex::FromHex;
 use ser::{deserialize, serialize};
-use crypto::dhash256;
+use crypto::{dhash256, Sha256Midstate, SHA256_CHUNK_SIZE};
 use compact::Compact;
 use hash::H256;
 
 #[derive(PartialEq, Clone, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct BlockHeader {
 	pub version: u32,
 	pub previous_header_hash: H256,
@@ -15,10 +16,65 @@ pub struct BlockHeader {
 	pub nonce: u32,
 }
 
+/// Size of serialized block header.
+pub const BLOCK_HEADER_SIZE: usize = 80;
+
 impl BlockHeader {
 	pub fn hash(&self) -> H256 {
 		dhash256(&serialize(self))
 	}
+
+	/// Serializes header into fixed-size array.
+	pub fn to_bytes(&self) -> [u8; BLOCK_HEADER_SIZE] {
+		let mut bytes = [0u8; BLOCK_HEADER_SIZE];
+		bytes.copy_from_slice(&serialize(self));
+		bytes
+	}
+}
+
+/// Hashes block header with different nonces, reusing SHA-256 state
+/// of the first 64 header bytes (version, previous hash and most of merkle root).
+#[derive(Debug, Clone)]
+pub struct BlockHeaderHasher {
+	midstate: Sha256Midstate,
+	/// Last bytes of merkle root, time, bits and nonce.
+	tail: [u8; BLOCK_HEADER_SIZE - SHA256_CHUNK_SIZE],
+}
+
+impl BlockHeaderHasher {
+	/// Precomputes midstate of the header. Header nonce is ignored.
+	pub fn new(header: &BlockHeader) -> Self {
+		let bytes = header.to_bytes();
+		let mut first_chunk = [0u8; SHA256_CHUNK_SIZE];
+		first_chunk.copy_from_slice(&bytes[..SHA256_CHUNK_SIZE]);
+		let mut tail = [0u8; BLOCK_HEADER_SIZE - SHA256_CHUNK_SIZE];
+		tail.copy_from_slice(&bytes[SHA256_CHUNK_SIZE..]);
+
+		BlockHeaderHasher {
+			midstate: Sha256Midstate::new(&first_chunk),
+			tail: tail,
+		}
+	}
+
+	/// SHA-256 state after the first 64 header bytes.
+	pub fn midstate(&self) -> &Sha256Midstate {
+		&self.midstate
+	}
+
+	/// Header bytes following the first 64 bytes. Nonce occupies the last 4 bytes.
+	pub fn tail(&self) -> &[u8] {
+		&self.tail
+	}
+
+	/// Hash of the header with given nonce.
+	pub fn hash(&self, nonce: u32) -> H256 {
+		let mut tail = self.tail;
+		tail[12] = nonce as u8;
+		tail[13] = (nonce >> 8) as u8;
+		tail[14] = (nonce >> 16) as u8;
+		tail[15] = (nonce >> 24) as u8;
+		self.midstate.dhash256(&tail)
+	}
 }
 
 impl fmt::Debug for BlockHeader {
@@ -43,7 +99,7 @@ impl From<&'static str> for BlockHeader {
 #[cfg(test)]
 mod tests {
 	use ser::{Reader, Error as ReaderError, Stream};
-	use super::BlockHeader;
+	use super::{BlockHeader, BlockHeaderHasher};
 
 	#[test]
 	fn test_block_header_stream() {
@@ -96,4 +152,16 @@ mod tests {
 		assert_eq!(expected, reader.read().unwrap());
 		assert_eq!(ReaderError::UnexpectedEnd, reader.read::<BlockHeader>().unwrap_err());
 	}
+
+	#[test]
+	fn test_block_header_hasher() {
+		// block 80000
+		let mut header: BlockHeader = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f57".into();
+		let hasher = BlockHeaderHasher::new(&header);
+		assert_eq!(hasher.hash(header.nonce), header.hash());
+
+		header.nonce = 0;
+		assert_eq!(hasher.hash(0), header.hash());
+		assert_eq!(&hasher.tail()[..12], &header.to_bytes()[64..76]);
+	}
 }
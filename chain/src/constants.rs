@@ -23,3 +23,12 @@ pub const LOCKTIME_THRESHOLD: u32 = 500000000; // Tue Nov  5 00:53:20 1985 UTC
 
 /// Number of Satoshis in single coin
 pub const SATOSHIS_IN_COIN: u64 = 100_000_000;
+
+/// Witness scale factor, used to compute BIP141 transaction and block weight.
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Maximal BIP141 weight of the block.
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+
+/// Size of the smallest possible transaction.
+pub const MIN_TRANSACTION_SIZE: usize = 60;
@@ -5,6 +5,7 @@ use ser::{Serializable, serialized_list_size, serialized_list_size_with_flags, d
 use block::Block;
 use transaction::Transaction;
 use merkle_root::merkle_root;
+use constants::WITNESS_SCALE_FACTOR;
 use indexed_header::IndexedBlockHeader;
 use indexed_transaction::IndexedTransaction;
 
@@ -44,23 +45,28 @@ impl IndexedBlock {
 	}
 
 	pub fn to_raw_block(self) -> Block {
-		Block::new(self.header.raw, self.transactions.into_iter().map(|tx| tx.raw).collect())
+		Block::new(self.header.raw, self.transactions.into_iter().map(IndexedTransaction::into_raw).collect())
 	}
 
 	pub fn size(&self) -> usize {
 		let header_size = self.header.raw.serialized_size();
-		let transactions = self.transactions.iter().map(|tx| &tx.raw).collect::<Vec<_>>();
+		let transactions = self.transactions.iter().map(|tx| &*tx.raw).collect::<Vec<_>>();
 		let txs_size = serialized_list_size::<Transaction, &Transaction>(&transactions);
 		header_size + txs_size
 	}
 
 	pub fn size_with_witness(&self) -> usize {
 		let header_size = self.header.raw.serialized_size();
-		let transactions = self.transactions.iter().map(|tx| &tx.raw).collect::<Vec<_>>();
+		let transactions = self.transactions.iter().map(|tx| &*tx.raw).collect::<Vec<_>>();
 		let txs_size = serialized_list_size_with_flags::<Transaction, &Transaction>(&transactions, SERIALIZE_TRANSACTION_WITNESS);
 		header_size + txs_size
 	}
 
+	/// BIP141 block weight: size * 3 + size_with_witness.
+	pub fn weight(&self) -> usize {
+		self.size() * (WITNESS_SCALE_FACTOR - 1) + self.size_with_witness()
+	}
+
 	pub fn merkle_root(&self) -> H256 {
 		merkle_root(&self.transactions.iter().map(|tx| &tx.hash).collect::<Vec<&H256>>())
 	}
@@ -70,7 +76,7 @@ impl IndexedBlock {
 			None => vec![],
 			Some((_, rest)) => {
 				let mut hashes = vec![H256::from(0)];
-				hashes.extend(rest.iter().map(|tx| tx.raw.witness_hash()));
+				hashes.extend(rest.iter().map(|tx| tx.witness_hash.clone()));
 				hashes
 			},
 		};
@@ -101,4 +107,13 @@ mod tests {
 		let block_with_witness: IndexedBlock = "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000".into();
 		assert!(block_with_witness.size() != block_with_witness.size_with_witness());
 	}
+
+	#[test]
+	fn weight_accounts_witness_once() {
+		let block_without_witness: IndexedBlock = "000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000".into();
+		assert_eq!(block_without_witness.weight(), block_without_witness.size() * 4);
+
+		let block_with_witness: IndexedBlock = "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000".into();
+		assert_eq!(block_with_witness.weight(), block_with_witness.size() * 3 + block_with_witness.size_with_witness());
+	}
 }
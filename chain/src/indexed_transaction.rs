@@ -1,4 +1,5 @@
 use std::{cmp, io, fmt};
+use std::sync::Arc;
 use hash::H256;
 use ser::{Deserializable, Reader, Error as ReaderError};
 use transaction::Transaction;
@@ -6,14 +7,19 @@ use read_and_hash::ReadAndHash;
 
 #[derive(Default, Clone)]
 pub struct IndexedTransaction {
+	/// Transaction hash (txid), computed over serialization without witness.
 	pub hash: H256,
-	pub raw: Transaction,
+	/// Transaction witness hash (wtxid). Equals to `hash` for transactions without witness.
+	pub witness_hash: H256,
+	/// Transaction is shared between sync, verification and memory pool, so it's never copied.
+	pub raw: Arc<Transaction>,
 }
 
 impl fmt::Debug for IndexedTransaction {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("IndexedTransaction")
 			.field("hash", &self.hash.reversed())
+			.field("witness_hash", &self.witness_hash.reversed())
 			.field("raw", &self.raw)
 			.finish()
 	}
@@ -22,20 +28,29 @@ impl fmt::Debug for IndexedTransaction {
 impl<T> From<T> for IndexedTransaction where Transaction: From<T> {
 	fn from(other: T) -> Self {
 		let tx = Transaction::from(other);
-		IndexedTransaction {
-			hash: tx.hash(),
-			raw: tx,
-		}
+		IndexedTransaction::new(tx.hash(), tx)
 	}
 }
 
 impl IndexedTransaction {
 	pub fn new(hash: H256, transaction: Transaction) -> Self {
+		let witness_hash = if transaction.has_witness() {
+			transaction.witness_hash()
+		} else {
+			hash.clone()
+		};
+
 		IndexedTransaction {
 			hash: hash,
-			raw: transaction,
+			witness_hash: witness_hash,
+			raw: Arc::new(transaction),
 		}
 	}
+
+	/// Returns owned transaction. Transaction is only copied if it is still shared.
+	pub fn into_raw(self) -> Transaction {
+		Arc::try_unwrap(self.raw).unwrap_or_else(|raw| (*raw).clone())
+	}
 }
 
 impl cmp::PartialEq for IndexedTransaction {
@@ -48,11 +63,39 @@ impl Deserializable for IndexedTransaction {
 	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
 		let data = try!(reader.read_and_hash::<Transaction>());
 		// TODO: use len
+		// hash of the serialized data is wtxid, which only equals to txid when there's no witness
+		let hash = if data.data.has_witness() {
+			data.data.hash()
+		} else {
+			data.hash.clone()
+		};
+
 		let tx = IndexedTransaction {
-			raw: data.data,
-			hash: data.hash,
+			hash: hash,
+			witness_hash: data.hash,
+			raw: Arc::new(data.data),
 		};
 
 		Ok(tx)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use ser::deserialize;
+	use transaction::Transaction;
+	use super::IndexedTransaction;
+
+	#[test]
+	fn test_indexed_transaction_hashes() {
+		let raw = "0000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000";
+		let tx: Transaction = raw.into();
+		let indexed: IndexedTransaction = deserialize(&raw.from_hex().unwrap() as &[u8]).unwrap();
+		assert_eq!(indexed.hash, tx.hash());
+		assert_eq!(indexed.witness_hash, tx.witness_hash());
+		assert!(indexed.hash != indexed.witness_hash);
+		assert_eq!(IndexedTransaction::new(tx.hash(), tx.clone()), indexed);
+		assert_eq!(IndexedTransaction::new(tx.hash(), tx).witness_hash, indexed.witness_hash);
+	}
+}
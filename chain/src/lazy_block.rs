@@ -0,0 +1,311 @@
+//! Block, which keeps its serialized form and decodes transactions on demand.
+//!
+//! Only transaction boundaries and hashes are computed when `LazyBlock` is constructed,
+//! so unmodified blocks can be relayed and indexed without full decode/re-encode round trip.
+
+use std::ops;
+use std::sync::Arc;
+use hash::H256;
+use bytes::Bytes;
+use crypto::{dhash256, DHash256, Digest};
+use ser::{deserialize, Reader, Error as ReaderError};
+use block_header::BLOCK_HEADER_SIZE;
+use indexed_block::IndexedBlock;
+use indexed_header::IndexedBlockHeader;
+use indexed_transaction::IndexedTransaction;
+use merkle_root::merkle_root;
+use transaction::Transaction;
+
+/// Must be nonzero.
+const WITNESS_FLAG: u8 = 1;
+
+/// Location of single transaction inside raw block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LazyTransaction {
+	/// Transaction hash (txid).
+	pub hash: H256,
+	/// Transaction witness hash (wtxid).
+	pub witness_hash: H256,
+	/// Offset of the transaction in the raw block.
+	pub offset: usize,
+	/// Size of the serialized transaction (with witness data, if any).
+	pub size: usize,
+	/// Is transaction serialized with witness data?
+	pub has_witness: bool,
+}
+
+impl LazyTransaction {
+	/// Range of the transaction bytes inside raw block.
+	pub fn range(&self) -> ops::Range<usize> {
+		self.offset..self.offset + self.size
+	}
+}
+
+/// Block, which keeps raw bytes and records transaction offsets.
+#[derive(Debug, Clone)]
+pub struct LazyBlock {
+	raw: Bytes,
+	header: IndexedBlockHeader,
+	transactions: Vec<LazyTransaction>,
+}
+
+impl LazyBlock {
+	/// Scans serialized block, recording transactions offsets and hashes.
+	pub fn new(raw: Bytes) -> Result<Self, ReaderError> {
+		if raw.len() < BLOCK_HEADER_SIZE {
+			return Err(ReaderError::UnexpectedEnd);
+		}
+
+		let header = Reader::new(&raw[0..BLOCK_HEADER_SIZE]).read::<IndexedBlockHeader>()?;
+		let transactions = {
+			let mut scanner = Scanner::new(&raw, BLOCK_HEADER_SIZE);
+			let transactions_count = scanner.read_compact()?;
+			let mut transactions = Vec::new();
+			for _ in 0..transactions_count {
+				transactions.push(scanner.scan_transaction()?);
+			}
+
+			if scanner.position != raw.len() {
+				return Err(ReaderError::UnreadData);
+			}
+
+			transactions
+		};
+
+		Ok(LazyBlock {
+			raw: raw,
+			header: header,
+			transactions: transactions,
+		})
+	}
+
+	/// Block hash.
+	pub fn hash(&self) -> &H256 {
+		&self.header.hash
+	}
+
+	/// Decoded block header.
+	pub fn header(&self) -> &IndexedBlockHeader {
+		&self.header
+	}
+
+	/// Raw block bytes, exactly as they were received.
+	pub fn raw(&self) -> &Bytes {
+		&self.raw
+	}
+
+	/// Locations of all block transactions.
+	pub fn transactions(&self) -> &[LazyTransaction] {
+		&self.transactions
+	}
+
+	/// Hashes of all block transactions.
+	pub fn transaction_hashes(&self) -> Vec<H256> {
+		self.transactions.iter().map(|tx| tx.hash.clone()).collect()
+	}
+
+	/// Merkle root, computed from transactions hashes.
+	pub fn merkle_root(&self) -> H256 {
+		merkle_root(&self.transactions.iter().map(|tx| &tx.hash).collect::<Vec<&H256>>())
+	}
+
+	/// Raw bytes of the transaction at given index.
+	pub fn raw_transaction(&self, index: usize) -> Option<&[u8]> {
+		self.transactions.get(index).map(|tx| &self.raw[tx.range()])
+	}
+
+	/// Decodes the transaction at given index.
+	pub fn transaction(&self, index: usize) -> Option<Result<IndexedTransaction, ReaderError>> {
+		self.transactions.get(index).map(|tx| self.decode_transaction(tx))
+	}
+
+	/// Decodes all block transactions.
+	pub fn to_indexed_block(&self) -> Result<IndexedBlock, ReaderError> {
+		let transactions = self.transactions.iter()
+			.map(|tx| self.decode_transaction(tx))
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(IndexedBlock::new(self.header.clone(), transactions))
+	}
+
+	fn decode_transaction(&self, tx: &LazyTransaction) -> Result<IndexedTransaction, ReaderError> {
+		let raw: Transaction = deserialize(&self.raw[tx.range()])?;
+		Ok(IndexedTransaction {
+			hash: tx.hash.clone(),
+			witness_hash: tx.witness_hash.clone(),
+			raw: Arc::new(raw),
+		})
+	}
+}
+
+/// Walks over serialized transactions without allocating.
+struct Scanner<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Scanner<'a> {
+	fn new(data: &'a [u8], position: usize) -> Self {
+		Scanner {
+			data: data,
+			position: position,
+		}
+	}
+
+	fn skip(&mut self, len: usize) -> Result<(), ReaderError> {
+		if self.data.len() - self.position < len {
+			return Err(ReaderError::UnexpectedEnd);
+		}
+
+		self.position += len;
+		Ok(())
+	}
+
+	fn read_u8(&mut self) -> Result<u8, ReaderError> {
+		let value = *self.data.get(self.position).ok_or(ReaderError::UnexpectedEnd)?;
+		self.position += 1;
+		Ok(value)
+	}
+
+	fn read_compact(&mut self) -> Result<u64, ReaderError> {
+		let len = match self.read_u8()? {
+			i @ 0...0xfc => return Ok(i as u64),
+			0xfd => 2,
+			0xfe => 4,
+			_ => 8,
+		};
+
+		let start = self.position;
+		self.skip(len)?;
+		Ok(self.data[start..self.position].iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+	}
+
+	fn skip_compact_sized(&mut self) -> Result<(), ReaderError> {
+		let len = self.read_compact()?;
+		if len > (self.data.len() - self.position) as u64 {
+			return Err(ReaderError::UnexpectedEnd);
+		}
+		self.skip(len as usize)
+	}
+
+	fn scan_transaction(&mut self) -> Result<LazyTransaction, ReaderError> {
+		let start = self.position;
+		// version
+		self.skip(4)?;
+
+		let mut body_start = self.position;
+		let mut inputs = self.read_compact()?;
+		let has_witness = if inputs == 0 {
+			if self.read_u8()? != WITNESS_FLAG {
+				return Err(ReaderError::MalformedData);
+			}
+
+			body_start = self.position;
+			inputs = self.read_compact()?;
+			true
+		} else {
+			false
+		};
+
+		for _ in 0..inputs {
+			// previous output, script_sig, sequence
+			self.skip(36)?;
+			self.skip_compact_sized()?;
+			self.skip(4)?;
+		}
+
+		let outputs = self.read_compact()?;
+		for _ in 0..outputs {
+			// value, script_pubkey
+			self.skip(8)?;
+			self.skip_compact_sized()?;
+		}
+
+		let body_end = self.position;
+		if has_witness {
+			for _ in 0..inputs {
+				let items = self.read_compact()?;
+				for _ in 0..items {
+					self.skip_compact_sized()?;
+				}
+			}
+		}
+
+		let lock_time_start = self.position;
+		self.skip(4)?;
+		let end = self.position;
+
+		let witness_hash = dhash256(&self.data[start..end]);
+		let hash = if has_witness {
+			let mut hasher = DHash256::new();
+			hasher.input(&self.data[start..start + 4]);
+			hasher.input(&self.data[body_start..body_end]);
+			hasher.input(&self.data[lock_time_start..end]);
+			hasher.finish()
+		} else {
+			witness_hash.clone()
+		};
+
+		Ok(LazyTransaction {
+			hash: hash,
+			witness_hash: witness_hash,
+			offset: start,
+			size: end - start,
+			has_witness: has_witness,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use bytes::Bytes;
+	use ser::{serialize, Error as ReaderError};
+	use block::Block;
+	use indexed_block::IndexedBlock;
+	use super::LazyBlock;
+
+	// Block 80000
+	const BLOCK_80000: &'static str = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f570201000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704e6ed5b1b014effffffff0100f2052a01000000434104b68a50eaa0287eff855189f949c1c6e5f58b37c88231373d8a59809cbae83059cc6469d65c665ccfd1cfeb75c6e8e19413bba7fbff9bc762419a76d87b16086eac000000000100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000";
+	// bip143 block
+	const BLOCK_WITH_WITNESS: &'static str = "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000";
+
+	fn raw(s: &'static str) -> Bytes {
+		s.from_hex().unwrap().into()
+	}
+
+	#[test]
+	fn lazy_block_matches_indexed_block() {
+		let lazy = LazyBlock::new(raw(BLOCK_80000)).unwrap();
+		let indexed: IndexedBlock = BLOCK_80000.into();
+
+		assert_eq!(lazy.hash(), indexed.hash());
+		assert_eq!(lazy.transactions().len(), 2);
+		assert_eq!(lazy.transaction_hashes(), indexed.transactions.iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>());
+		assert_eq!(lazy.merkle_root(), lazy.header().raw.merkle_root_hash);
+		assert_eq!(lazy.transaction(1).unwrap().unwrap(), indexed.transactions[1]);
+		assert_eq!(lazy.raw_transaction(1).unwrap(), &*serialize(&*indexed.transactions[1].raw));
+		assert_eq!(lazy.to_indexed_block().unwrap().to_raw_block(), Block::from(BLOCK_80000));
+	}
+
+	#[test]
+	fn lazy_block_computes_witness_hashes() {
+		let lazy = LazyBlock::new(raw(BLOCK_WITH_WITNESS)).unwrap();
+		let block: Block = BLOCK_WITH_WITNESS.into();
+
+		assert!(lazy.transactions()[0].has_witness);
+		assert_eq!(lazy.transactions()[0].hash, block.transactions[0].hash());
+		assert_eq!(lazy.transactions()[0].witness_hash, block.transactions[0].witness_hash());
+		assert_eq!(lazy.transactions()[0].size, lazy.raw().len() - 81);
+	}
+
+	#[test]
+	fn lazy_block_rejects_truncated_data() {
+		let mut data: Vec<u8> = raw(BLOCK_80000).into();
+		data.pop();
+		assert_eq!(LazyBlock::new(data.into()).unwrap_err(), ReaderError::UnexpectedEnd);
+
+		let mut data: Vec<u8> = raw(BLOCK_80000).into();
+		data.push(0);
+		assert_eq!(LazyBlock::new(data.into()).unwrap_err(), ReaderError::UnreadData);
+	}
+}
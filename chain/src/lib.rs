@@ -1,16 +1,25 @@
 extern crate rustc_serialize;
 extern crate heapsize;
+extern crate bit_vec;
 extern crate primitives;
 extern crate bitcrypto as crypto;
 extern crate serialization as ser;
 #[macro_use]
 extern crate serialization_derive;
+#[cfg(feature = "with-serde")]
+extern crate serde;
+#[cfg(feature = "with-serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(all(test, feature = "with-serde"))]
+extern crate serde_json;
 
 pub mod constants;
 
 mod block;
 mod block_header;
 mod merkle_root;
+mod partial_merkle_tree;
 mod transaction;
 
 /// `IndexedBlock` extension
@@ -18,6 +27,7 @@ mod read_and_hash;
 mod indexed_block;
 mod indexed_header;
 mod indexed_transaction;
+mod lazy_block;
 
 pub trait RepresentH256 {
 	fn h256(&self) -> hash::H256;
@@ -27,13 +37,15 @@ pub use rustc_serialize::hex;
 pub use primitives::{hash, bytes, bigint, compact};
 
 pub use block::Block;
-pub use block_header::BlockHeader;
-pub use merkle_root::{merkle_root, merkle_node_hash};
+pub use block_header::{BlockHeader, BlockHeaderHasher, BLOCK_HEADER_SIZE};
+pub use merkle_root::{merkle_root, merkle_branch, merkle_node_hash};
+pub use partial_merkle_tree::{PartialMerkleTree, ParsedPartialMerkleTree, Error as PartialMerkleTreeError};
 pub use transaction::{Transaction, TransactionInput, TransactionOutput, OutPoint};
 
 pub use read_and_hash::{ReadAndHash, HashedData};
 pub use indexed_block::IndexedBlock;
 pub use indexed_header::IndexedBlockHeader;
 pub use indexed_transaction::IndexedTransaction;
+pub use lazy_block::{LazyBlock, LazyTransaction};
 
 pub type ShortTransactionID = hash::H48;
@@ -32,6 +32,28 @@ pub fn merkle_root<T>(hashes: &[T]) -> H256 where T: AsRef<H256> {
 	merkle_root(&row)
 }
 
+/// Returns hashes of the merkle tree nodes, required to compute the root from the leaf at `index`,
+/// starting from the sibling of the leaf.
+pub fn merkle_branch<T>(hashes: &[T], mut index: usize) -> Vec<H256> where T: AsRef<H256> {
+	assert!(index < hashes.len(), "Merkle branch of the leaf outside of the tree");
+
+	let mut branch = Vec::new();
+	let mut row: Vec<H256> = hashes.iter().map(|hash| hash.as_ref().clone()).collect();
+	while row.len() > 1 {
+		// duplicate the last element if len is not even
+		if row.len() % 2 == 1 {
+			let last = row[row.len() - 1].clone();
+			row.push(last);
+		}
+
+		branch.push(row[index ^ 1].clone());
+		row = row.chunks(2).map(|pair| merkle_node_hash(&pair[0], &pair[1])).collect();
+		index /= 2;
+	}
+
+	branch
+}
+
 /// Calculate merkle tree node hash
 pub fn merkle_node_hash<T>(left: T, right: T) -> H256 where T: AsRef<H256> {
 	dhash256(&*concat(left, right))
@@ -40,7 +62,7 @@ pub fn merkle_node_hash<T>(left: T, right: T) -> H256 where T: AsRef<H256> {
 #[cfg(test)]
 mod tests {
 	use hash::H256;
-	use super::merkle_root;
+	use super::{merkle_root, merkle_branch, merkle_node_hash};
 
 	// block 80_000
 	// https://blockchain.info/block/000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6
@@ -55,4 +77,21 @@ mod tests {
 		assert_eq!(result, expected);
 		assert_eq!(result2, expected);
 	}
+
+	#[test]
+	fn test_merkle_branch() {
+		let hashes: Vec<H256> = (1u8..6).map(H256::from).collect();
+		let root = merkle_root(&hashes);
+		for index in 0..hashes.len() {
+			let branch = merkle_branch(&hashes, index);
+			assert_eq!(branch.len(), 3);
+			let (computed_root, _) = branch.iter().fold((hashes[index].clone(), index), |(hash, index), node| match index % 2 {
+				0 => (merkle_node_hash(&hash, node), index / 2),
+				_ => (merkle_node_hash(node, &hash), index / 2),
+			});
+			assert_eq!(computed_root, root);
+		}
+
+		assert!(merkle_branch(&hashes[..1], 0).is_empty());
+	}
 }
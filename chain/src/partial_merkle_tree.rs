@@ -0,0 +1,373 @@
+//! Partial merkle tree, used in `merkleblock` messages and transaction inclusion proofs.
+//! https://bitcoin.org/en/developer-reference#merkleblock
+
+use std::cmp::min;
+use std::collections::HashSet;
+use bit_vec::BitVec;
+use hash::H256;
+use constants::{MAX_BLOCK_WEIGHT, MIN_TRANSACTION_SIZE};
+use indexed_block::IndexedBlock;
+use merkle_root::merkle_node_hash;
+
+/// Upper bound of the number of transactions in the block. Also holds for 4MB blocks of non-segwit forks.
+const MAX_TRANSACTIONS_COUNT: usize = MAX_BLOCK_WEIGHT / MIN_TRANSACTION_SIZE;
+
+/// Partial merkle tree parse error
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// Tree has no transactions
+	NoTransactions,
+	/// Tree has more transactions, than could fit into the block
+	TooManyTransactions,
+	/// Tree has more hashes than transactions
+	TooManyHashes,
+	/// Tree has less flags than hashes
+	TooFewMatches,
+	/// All flags were used before tree was parsed
+	AllMatchesUsed,
+	/// All hashes were used before tree was parsed
+	AllHashesUsed,
+	/// Some flags were not used during parsing
+	NotAllMatchesUsed,
+	/// Some hashes were not used during parsing
+	NotAllHashesUsed,
+	/// Left and right node hashes are the same (CVE-2012-2459)
+	SameHashTwice,
+	/// Computed merkle root does not match expected
+	MerkleRootMismatch,
+}
+
+/// Partial merkle tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialMerkleTree {
+	/// Total number of transactions
+	pub tx_count: usize,
+	/// Nodes hashes
+	pub hashes: Vec<H256>,
+	/// Match flags
+	pub flags: BitVec,
+}
+
+/// Partial merkle tree parse result
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedPartialMerkleTree {
+	/// Merkle root
+	pub root: H256,
+	/// Matched hashes
+	pub hashes: Vec<H256>,
+	/// Match flags
+	pub flags: BitVec,
+}
+
+impl PartialMerkleTree {
+	/// Create new merkle tree with given data
+	pub fn new(tx_count: usize, hashes: Vec<H256>, flags: BitVec) -> Self {
+		PartialMerkleTree {
+			tx_count: tx_count,
+			hashes: hashes,
+			flags: flags,
+		}
+	}
+
+	/// Build partial merkle tree from hashes of all transactions and match flags.
+	pub fn build(tx_hashes: Vec<H256>, tx_matches: BitVec) -> Self {
+		PartialMerkleTreeBuilder::build(tx_hashes, tx_matches)
+	}
+
+	/// Build partial merkle tree, matching given transactions of the block.
+	pub fn from_block(block: &IndexedBlock, matched: &HashSet<H256>) -> Self {
+		let hashes: Vec<H256> = block.transactions.iter().map(|tx| tx.hash.clone()).collect();
+		let matches = hashes.iter().map(|hash| matched.contains(hash)).collect();
+		Self::build(hashes, matches)
+	}
+
+	/// Restore partial merkle tree from `merkleblock` message fields.
+	/// Protocol requires flags to be serialized starting from the least significant bit of every byte.
+	pub fn from_flags_bytes(tx_count: usize, hashes: Vec<H256>, flags: &[u8]) -> Self {
+		let flags = (0..flags.len() * 8).map(|i| (flags[i / 8] >> (i % 8)) & 1 == 1).collect();
+		Self::new(tx_count, hashes, flags)
+	}
+
+	/// Match flags, serialized as required by protocol.
+	pub fn flags_bytes(&self) -> Vec<u8> {
+		let mut result = vec![0u8; (self.flags.len() + 7) / 8];
+		for (i, flag) in self.flags.iter().enumerate() {
+			if flag {
+				result[i / 8] |= 1 << (i % 8);
+			}
+		}
+		result
+	}
+
+	/// Parse partial merkle tree, extracting merkle root and matched hashes.
+	pub fn parse(&self) -> Result<ParsedPartialMerkleTree, Error> {
+		PartialMerkleTreeBuilder::parse(self.clone())
+	}
+
+	/// Verify partial merkle tree against block merkle root, returning matched hashes.
+	pub fn verify(&self, merkle_root: &H256) -> Result<Vec<H256>, Error> {
+		let parsed = self.parse()?;
+		if &parsed.root != merkle_root {
+			return Err(Error::MerkleRootMismatch);
+		}
+
+		Ok(parsed.hashes)
+	}
+}
+
+impl ParsedPartialMerkleTree {
+	pub fn new(root: H256, hashes: Vec<H256>, flags: BitVec) -> Self {
+		ParsedPartialMerkleTree {
+			root: root,
+			hashes: hashes,
+			flags: flags,
+		}
+	}
+}
+
+/// Service structure to construct and parse partial merkle tree.
+struct PartialMerkleTreeBuilder {
+	/// All transactions length.
+	all_len: usize,
+	/// All transactions hashes.
+	all_hashes: Vec<H256>,
+	/// Match flags for all transactions.
+	all_matches: BitVec,
+	/// Partial hashes.
+	hashes: Vec<H256>,
+	/// Partial match flags.
+	matches: BitVec,
+}
+
+impl PartialMerkleTreeBuilder {
+	/// Build partial merkle tree as described here:
+	/// https://bitcoin.org/en/developer-reference#creating-a-merkleblock-message
+	fn build(all_hashes: Vec<H256>, all_matches: BitVec) -> PartialMerkleTree {
+		let mut partial_merkle_tree = PartialMerkleTreeBuilder {
+			all_len: all_hashes.len(),
+			all_hashes: all_hashes,
+			all_matches: all_matches,
+			hashes: Vec::new(),
+			matches: BitVec::new(),
+		};
+		partial_merkle_tree.build_tree();
+		PartialMerkleTree::new(partial_merkle_tree.all_len, partial_merkle_tree.hashes, partial_merkle_tree.matches)
+	}
+
+	/// Parse partial merkle tree as described here:
+	/// https://bitcoin.org/en/developer-reference#parsing-a-merkleblock-message
+	fn parse(tree: PartialMerkleTree) -> Result<ParsedPartialMerkleTree, Error> {
+		// transactions count comes from the untrusted source => check it before allocating flags
+		if tree.tx_count == 0 {
+			return Err(Error::NoTransactions);
+		}
+		if tree.tx_count > MAX_TRANSACTIONS_COUNT {
+			return Err(Error::TooManyTransactions);
+		}
+		if tree.hashes.len() > tree.tx_count {
+			return Err(Error::TooManyHashes);
+		}
+
+		let mut partial_merkle_tree = PartialMerkleTreeBuilder {
+			all_len: tree.tx_count,
+			all_hashes: Vec::new(),
+			all_matches: BitVec::from_elem(tree.tx_count, false),
+			hashes: tree.hashes,
+			matches: tree.flags,
+		};
+
+		let merkle_root = partial_merkle_tree.parse_tree()?;
+		Ok(ParsedPartialMerkleTree::new(merkle_root, partial_merkle_tree.all_hashes, partial_merkle_tree.all_matches))
+	}
+
+	fn build_tree(&mut self) {
+		let tree_height = self.tree_height();
+		self.build_branch(tree_height, 0)
+	}
+
+	fn parse_tree(&mut self) -> Result<H256, Error> {
+		if self.matches.len() < self.hashes.len() {
+			return Err(Error::TooFewMatches);
+		}
+
+		// parse tree
+		let mut matches_used = 0usize;
+		let mut hashes_used = 0usize;
+		let tree_height = self.tree_height();
+		let merkle_root = self.parse_branch(tree_height, 0, &mut matches_used, &mut hashes_used)?;
+
+		// flags are padded to the whole byte
+		if (matches_used + 7) / 8 != (self.matches.len() + 7) / 8 {
+			return Err(Error::NotAllMatchesUsed);
+		}
+		if hashes_used != self.hashes.len() {
+			return Err(Error::NotAllHashesUsed);
+		}
+
+		Ok(merkle_root)
+	}
+
+	fn build_branch(&mut self, height: usize, pos: usize) {
+		// determine whether this node is the parent of at least one matched txid
+		let transactions_begin = pos << height;
+		let transactions_end = min(self.all_len, (pos + 1) << height);
+		let flag = (transactions_begin..transactions_end).any(|idx| self.all_matches[idx]);
+		// remember flag
+		self.matches.push(flag);
+		// proceeed with descendants
+		if height == 0 || !flag {
+			// we're at the leaf level || there is no match
+			let hash = self.branch_hash(height, pos);
+			self.hashes.push(hash);
+		} else {
+			// proceed with left child
+			self.build_branch(height - 1, pos << 1);
+			// proceed with right child if any
+			if (pos << 1) + 1 < self.level_width(height - 1) {
+				self.build_branch(height - 1, (pos << 1) + 1);
+			}
+		}
+	}
+
+	fn parse_branch(&mut self, height: usize, pos: usize, matches_used: &mut usize, hashes_used: &mut usize) -> Result<H256, Error> {
+		if *matches_used >= self.matches.len() {
+			return Err(Error::AllMatchesUsed);
+		}
+
+		let flag = self.matches[*matches_used];
+		*matches_used += 1;
+
+		if height == 0 || !flag {
+			// we're at the leaf level || there is no match
+			if *hashes_used >= self.hashes.len() {
+				return Err(Error::AllHashesUsed);
+			}
+
+			// get node hash
+			let hash = self.hashes[*hashes_used].clone();
+			*hashes_used += 1;
+
+			// on leaf level && matched flag set => mark transaction as matched
+			if height == 0 && flag {
+				self.all_hashes.push(hash.clone());
+				self.all_matches.set(pos, true);
+			}
+
+			Ok(hash)
+		} else {
+			// proceed with left child
+			let left = self.parse_branch(height - 1, pos << 1, matches_used, hashes_used)?;
+			// proceed with right child if any
+			let has_right_child = (pos << 1) + 1 < self.level_width(height - 1);
+			let right = if has_right_child {
+				self.parse_branch(height - 1, (pos << 1) + 1, matches_used, hashes_used)?
+			} else {
+				left.clone()
+			};
+
+			if has_right_child && left == right {
+				Err(Error::SameHashTwice)
+			} else {
+				Ok(merkle_node_hash(&left, &right))
+			}
+		}
+	}
+
+	fn tree_height(&self) -> usize {
+		let mut height = 0usize;
+		while self.level_width(height) > 1 {
+			height += 1;
+		}
+		height
+	}
+
+	fn level_width(&self, height: usize) -> usize {
+		(self.all_len + (1 << height) - 1) >> height
+	}
+
+	fn branch_hash(&self, height: usize, pos: usize) -> H256 {
+		if height == 0 {
+			self.all_hashes[pos].clone()
+		} else {
+			let left = self.branch_hash(height - 1, pos << 1);
+			let right = if (pos << 1) + 1 < self.level_width(height - 1) {
+				self.branch_hash(height - 1, (pos << 1) + 1)
+			} else {
+				left.clone()
+			};
+
+			merkle_node_hash(&left, &right)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use bit_vec::BitVec;
+	use hash::H256;
+	use merkle_root::merkle_root;
+	use indexed_block::IndexedBlock;
+	use super::{PartialMerkleTree, Error};
+
+	fn hashes(count: usize) -> Vec<H256> {
+		(0..count).map(|n| H256::from(n as u8 + 1)).collect()
+	}
+
+	#[test]
+	// test from core implementation
+	// https://github.com/bitcoin/bitcoin/blob/master/src/test/pmt_tests.cpp
+	fn test_build_and_parse_partial_merkle_tree() {
+		let tx_counts: Vec<usize> = vec![1, 4, 7, 17, 56, 100, 127, 255];
+		for tx_count in tx_counts {
+			let hashes = hashes(tx_count);
+			let merkle_root = merkle_root(&hashes);
+
+			// mark different transactions as matched
+			for step in 1..10 {
+				let matches: BitVec = (0..tx_count).map(|i| i % step == 0).collect();
+				let matched_hashes: Vec<H256> = hashes.iter().enumerate()
+					.filter(|&(i, _)| i % step == 0)
+					.map(|(_, h)| h.clone())
+					.collect();
+
+				let tree = PartialMerkleTree::build(hashes.clone(), matches.clone());
+				let parsed_tree = tree.parse().expect("no error");
+
+				assert_eq!(matched_hashes, parsed_tree.hashes);
+				assert_eq!(matches, parsed_tree.flags);
+				assert_eq!(merkle_root, parsed_tree.root);
+				assert_eq!(tree.verify(&merkle_root), Ok(matched_hashes));
+			}
+		}
+	}
+
+	#[test]
+	fn test_flags_bytes_roundtrip() {
+		let hashes = hashes(7);
+		let tree = PartialMerkleTree::build(hashes.clone(), vec![true, false, true, false, false, false, false].into_iter().collect());
+		let restored = PartialMerkleTree::from_flags_bytes(tree.tx_count, tree.hashes.clone(), &tree.flags_bytes());
+		// first node is always matched when there is any match
+		assert_eq!(tree.flags_bytes()[0] & 1, 1);
+		assert_eq!(restored.parse().unwrap().hashes, vec![hashes[0].clone(), hashes[2].clone()]);
+	}
+
+	#[test]
+	fn test_partial_merkle_tree_from_block() {
+		let block: IndexedBlock = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f570201000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704e6ed5b1b014effffffff0100f2052a01000000434104b68a50eaa0287eff855189f949c1c6e5f58b37c88231373d8a59809cbae83059cc6469d65c665ccfd1cfeb75c6e8e19413bba7fbff9bc762419a76d87b16086eac000000000100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		let matched: HashSet<H256> = vec![block.transactions[1].hash.clone()].into_iter().collect();
+		let tree = PartialMerkleTree::from_block(&block, &matched);
+
+		assert_eq!(tree.verify(&block.header.raw.merkle_root_hash), Ok(vec![block.transactions[1].hash.clone()]));
+		assert_eq!(tree.verify(&H256::default()), Err(Error::MerkleRootMismatch));
+	}
+
+	#[test]
+	fn test_parse_rejects_malformed_tree() {
+		assert_eq!(PartialMerkleTree::new(0, vec![], BitVec::new()).parse(), Err(Error::NoTransactions));
+		assert_eq!(PartialMerkleTree::new(1, hashes(2), BitVec::from_elem(2, false)).parse(), Err(Error::TooManyHashes));
+		assert_eq!(PartialMerkleTree::new(::std::usize::MAX, hashes(1), BitVec::from_elem(8, false)).parse(), Err(Error::TooManyTransactions));
+		assert_eq!(PartialMerkleTree::new(2, hashes(2), BitVec::from_elem(1, true)).parse(), Err(Error::TooFewMatches));
+		assert_eq!(PartialMerkleTree::new(2, vec![H256::from(1), H256::from(1)], BitVec::from_elem(3, true)).parse(), Err(Error::SameHashTwice));
+	}
+}
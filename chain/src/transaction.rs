@@ -8,7 +8,7 @@ use bytes::Bytes;
 use ser::{deserialize, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
 use crypto::dhash256;
 use hash::H256;
-use constants::{SEQUENCE_FINAL, LOCKTIME_THRESHOLD};
+use constants::{SEQUENCE_FINAL, LOCKTIME_THRESHOLD, WITNESS_SCALE_FACTOR};
 use ser::{Error, Serializable, Deserializable, Stream, Reader};
 
 /// Must be zero.
@@ -16,7 +16,8 @@ const WITNESS_MARKER: u8 = 0;
 /// Must be nonzero.
 const WITNESS_FLAG: u8 = 1;
 
-#[derive(Debug, PartialEq, Eq, Clone, Default, Serializable, Deserializable)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct OutPoint {
 	pub hash: H256,
 	pub index: u32,
@@ -35,11 +36,15 @@ impl OutPoint {
 	}
 }
 
-#[derive(Debug, PartialEq, Default, Clone)]
+#[derive(Debug, PartialEq, Default, Clone, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct TransactionInput {
 	pub previous_output: OutPoint,
 	pub script_sig: Bytes,
 	pub sequence: u32,
+	/// Witness is serialized separately, as a part of transaction.
+	#[serialize(skip)]
+	#[cfg_attr(feature = "with-serde", serde(default))]
 	pub script_witness: Vec<Bytes>,
 }
 
@@ -70,6 +75,7 @@ impl HeapSizeOf for TransactionInput {
 }
 
 #[derive(Debug, PartialEq, Clone, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct TransactionOutput {
 	pub value: u64,
 	pub script_pubkey: Bytes,
@@ -91,6 +97,7 @@ impl HeapSizeOf for TransactionOutput {
 }
 
 #[derive(Debug, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
 	pub version: i32,
 	pub inputs: Vec<TransactionInput>,
@@ -171,6 +178,26 @@ impl Transaction {
 		self.inputs.iter().any(TransactionInput::has_witness)
 	}
 
+	/// Size of transaction, serialized without witness data (aka stripped size).
+	pub fn base_size(&self) -> usize {
+		self.serialized_size()
+	}
+
+	/// Size of transaction, serialized with witness data.
+	pub fn total_size(&self) -> usize {
+		self.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS)
+	}
+
+	/// BIP141 transaction weight: base_size * 3 + total_size.
+	pub fn weight(&self) -> usize {
+		self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+	}
+
+	/// BIP141 virtual transaction size: weight / 4, rounded up.
+	pub fn vsize(&self) -> usize {
+		(self.weight() + WITNESS_SCALE_FACTOR - 1) / WITNESS_SCALE_FACTOR
+	}
+
 	pub fn total_spends(&self) -> u64 {
 		let mut result = 0u64;
 		for output in self.outputs.iter() {
@@ -183,26 +210,6 @@ impl Transaction {
 	}
 }
 
-impl Serializable for TransactionInput {
-	fn serialize(&self, stream: &mut Stream) {
-		stream
-			.append(&self.previous_output)
-			.append(&self.script_sig)
-			.append(&self.sequence);
-	}
-}
-
-impl Deserializable for TransactionInput {
-	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, Error> where Self: Sized, T: io::Read {
-		Ok(TransactionInput {
-			previous_output: reader.read()?,
-			script_sig: reader.read()?,
-			sequence: reader.read()?,
-			script_witness: vec![],
-		})
-	}
-}
-
 impl Serializable for Transaction {
 	fn serialize(&self, stream: &mut Stream) {
 		let include_transaction_witness = stream.include_transaction_witness() && self.has_witness();
@@ -353,4 +360,33 @@ mod tests {
 		let transaction_with_witness: Transaction = "0000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000".into();
 		assert!(transaction_with_witness.hash() != transaction_with_witness.witness_hash());
 	}
+
+	#[test]
+	fn test_transaction_weight_and_vsize() {
+		let raw_tx: &'static str = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000";
+		let tx: Transaction = raw_tx.into();
+		assert_eq!(tx.base_size(), raw_tx.len() / 2);
+		assert_eq!(tx.total_size(), tx.base_size());
+		assert_eq!(tx.weight(), tx.base_size() * 4);
+		assert_eq!(tx.vsize(), tx.base_size());
+
+		// bip143 transaction with witness
+		let tx: Transaction = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000".into();
+		assert_eq!(tx.total_size(), 343);
+		assert_eq!(tx.base_size(), 233);
+		assert_eq!(tx.weight(), 233 * 3 + 343);
+		assert_eq!(tx.vsize(), 261);
+	}
+
+	#[cfg(feature = "with-serde")]
+	#[test]
+	fn test_transaction_serde() {
+		use serde_json;
+
+		let tx: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		let json = serde_json::to_value(&tx).unwrap();
+		assert_eq!(json["outputs"][0]["script_pubkey"], "76a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac");
+		assert_eq!(json["inputs"][0]["previous_output"]["hash"], "a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5");
+		assert_eq!(serde_json::from_value::<Transaction>(json).unwrap(), tx);
+	}
 }
@@ -2,13 +2,24 @@ extern crate crypto as rcrypto;
 extern crate primitives;
 extern crate siphasher;
 
+mod midstate;
+mod muhash;
+
 pub use rcrypto::digest::Digest;
+pub use midstate::{Sha256Midstate, SHA256_CHUNK_SIZE};
+pub use muhash::MuHash3072;
 use std::hash::Hasher;
 use rcrypto::sha1::Sha1;
-use rcrypto::sha2::Sha256;
+use rcrypto::sha2::{Sha256, Sha512};
+use rcrypto::hmac::Hmac;
+use rcrypto::mac::Mac;
+use rcrypto::sha3::Sha3;
 use rcrypto::ripemd160::Ripemd160;
+use rcrypto::aessafe::{AesSafe256Encryptor, AesSafe256Decryptor};
+use rcrypto::symmetriccipher::{BlockEncryptor, BlockDecryptor};
+use rcrypto::scrypt::{scrypt as rscrypt, ScryptParams};
 use siphasher::sip::SipHasher24;
-use primitives::hash::{H32, H160, H256};
+use primitives::hash::{H32, H160, H256, H512};
 
 pub struct DHash160 {
 	sha256: Sha256,
@@ -135,6 +146,16 @@ pub fn sha256(input: &[u8]) -> H256 {
 	result
 }
 
+/// SHA3-256
+#[inline]
+pub fn sha3_256(input: &[u8]) -> H256 {
+	let mut result = H256::default();
+	let mut hasher = Sha3::sha3_256();
+	hasher.input(input);
+	hasher.result(&mut *result);
+	result
+}
+
 /// SHA-256 and RIPEMD160
 #[inline]
 pub fn dhash160(input: &[u8]) -> H160 {
@@ -155,6 +176,38 @@ pub fn dhash256(input: &[u8]) -> H256 {
 	result
 }
 
+/// Tagged SHA-256 hash, as defined by BIP340: `sha256(sha256(tag) || sha256(tag) || input)`
+pub fn tagged_hash(tag: &[u8], input: &[u8]) -> H256 {
+	let tag_hash = sha256(tag);
+	let mut result = H256::default();
+	let mut hasher = Sha256::new();
+	hasher.input(&*tag_hash);
+	hasher.input(&*tag_hash);
+	hasher.input(input);
+	hasher.result(&mut *result);
+	result
+}
+
+/// HMAC-SHA256
+#[inline]
+pub fn hmac_sha256(key: &[u8], input: &[u8]) -> H256 {
+	let mut result = H256::default();
+	let mut hmac = Hmac::new(Sha256::new(), key);
+	hmac.input(input);
+	hmac.raw_result(&mut *result);
+	result
+}
+
+/// HMAC-SHA512
+#[inline]
+pub fn hmac_sha512(key: &[u8], input: &[u8]) -> H512 {
+	let mut result = H512::default();
+	let mut hmac = Hmac::new(Sha512::new(), key);
+	hmac.input(input);
+	hmac.raw_result(&mut *result);
+	result
+}
+
 /// SipHash-2-4
 #[inline]
 pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
@@ -163,6 +216,29 @@ pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
 	hasher.finish()
 }
 
+/// scrypt key derivation function, `n = 2 ^ log_n`
+#[inline]
+pub fn scrypt(password: &[u8], salt: &[u8], log_n: u8, r: u32, p: u32, output: &mut [u8]) {
+	let params = ScryptParams::new(log_n, r, p);
+	rscrypt(password, salt, &params, output);
+}
+
+/// AES-256 encryption of single 16-byte block
+#[inline]
+pub fn aes256_encrypt_block(key: &H256, input: &[u8; 16]) -> [u8; 16] {
+	let mut output = [0u8; 16];
+	AesSafe256Encryptor::new(&**key).encrypt_block(input, &mut output);
+	output
+}
+
+/// AES-256 decryption of single 16-byte block
+#[inline]
+pub fn aes256_decrypt_block(key: &H256, input: &[u8; 16]) -> [u8; 16] {
+	let mut output = [0u8; 16];
+	AesSafe256Decryptor::new(&**key).decrypt_block(input, &mut output);
+	output
+}
+
 /// Data checksum
 #[inline]
 pub fn checksum(data: &[u8]) -> H32 {
@@ -174,7 +250,8 @@ pub fn checksum(data: &[u8]) -> H32 {
 #[cfg(test)]
 mod tests {
 	use primitives::bytes::Bytes;
-	use super::{ripemd160, sha1, sha256, dhash160, dhash256, siphash24, checksum};
+	use super::{ripemd160, sha1, sha256, sha3_256, dhash160, dhash256, siphash24, checksum, scrypt,
+		aes256_encrypt_block, aes256_decrypt_block, tagged_hash, hmac_sha256, hmac_sha512};
 
 	#[test]
 	fn test_ripemd160() {
@@ -197,6 +274,13 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn test_sha3_256() {
+		let expected = "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532".into();
+		let result = sha3_256(b"abc");
+		assert_eq!(result, expected);
+	}
+
 	#[test]
 	fn test_dhash160() {
 		let expected = "b6a9c8c230722b7c748331a8b450f05566dc7d0f".into();
@@ -216,6 +300,13 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn test_tagged_hash() {
+		let expected = "cc0d501f9c9c9610e44edb939ad98d59a1a98739d0e26dbe932660ae33a8196e".into();
+		let result = tagged_hash(b"TapLeaf", b"hello");
+		assert_eq!(result, expected);
+	}
+
 	#[test]
 	fn test_siphash24() {
 		let expected = 0x74f839c593dc67fd_u64;
@@ -227,4 +318,37 @@ mod tests {
 	fn test_checksum() {
 		assert_eq!(checksum(b"hello"), "9595c9df".into());
 	}
+
+	#[test]
+	fn test_scrypt() {
+		// RFC 7914, test vector 2
+		let mut result = [0u8; 64];
+		scrypt(b"password", b"NaCl", 10, 8, 16, &mut result);
+		let expected: Bytes = "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640".into();
+		assert_eq!(&result[..], &*expected);
+	}
+
+	#[test]
+	fn test_hmac_sha256() {
+		// RFC 4231, test case 2
+		let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".into();
+		assert_eq!(hmac_sha256(b"Jefe", b"what do ya want for nothing?"), expected);
+	}
+
+	#[test]
+	fn test_hmac_sha512() {
+		// RFC 4231, test case 2
+		let expected = "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737".into();
+		assert_eq!(hmac_sha512(b"Jefe", b"what do ya want for nothing?"), expected);
+	}
+
+	#[test]
+	fn test_aes256_block() {
+		// FIPS-197, appendix C.3
+		let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".into();
+		let plain = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff];
+		let cipher = [0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49, 0x60, 0x89];
+		assert_eq!(aes256_encrypt_block(&key, &plain), cipher);
+		assert_eq!(aes256_decrypt_block(&key, &cipher), plain);
+	}
 }
@@ -0,0 +1,170 @@
+//! SHA-256 midstate.
+//!
+//! Block header is 80 bytes long, so its first SHA-256 pass consists of two 64-byte chunks.
+//! Only the second chunk depends on time, bits and nonce, so the state after the first chunk
+//! can be computed once and reused for every nonce.
+
+use primitives::hash::H256;
+
+/// Size of SHA-256 chunk.
+pub const SHA256_CHUNK_SIZE: usize = 64;
+
+const INITIAL_STATE: [u32; 8] = [
+	0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const ROUND_CONSTANTS: [u32; 64] = [
+	0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+	0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+	0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+	0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+	0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+	0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+	0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+	0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 state after processing the first 64 bytes of the message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sha256Midstate {
+	state: [u32; 8],
+}
+
+impl Sha256Midstate {
+	/// Processes the first chunk of the message.
+	pub fn new(first_chunk: &[u8; SHA256_CHUNK_SIZE]) -> Self {
+		let mut state = INITIAL_STATE;
+		compress(&mut state, first_chunk);
+		Sha256Midstate {
+			state: state,
+		}
+	}
+
+	/// Raw midstate words.
+	pub fn state(&self) -> [u32; 8] {
+		self.state
+	}
+
+	/// Midstate, serialized in big-endian word order (as used by external mining software).
+	pub fn to_h256(&self) -> H256 {
+		state_to_h256(&self.state)
+	}
+
+	/// SHA-256 of the first chunk followed by `tail`.
+	/// `tail` must fit into a single chunk with padding, i.e. be at most 55 bytes long.
+	pub fn sha256(&self, tail: &[u8]) -> H256 {
+		state_to_h256(&finalize(self.state, tail, SHA256_CHUNK_SIZE + tail.len()))
+	}
+
+	/// Double SHA-256 of the first chunk followed by `tail`.
+	/// `tail` must fit into a single chunk with padding, i.e. be at most 55 bytes long.
+	pub fn dhash256(&self, tail: &[u8]) -> H256 {
+		let first = self.sha256(tail);
+		state_to_h256(&finalize(INITIAL_STATE, &*first, first.len()))
+	}
+}
+
+/// Pads the last chunk of the message and processes it.
+fn finalize(mut state: [u32; 8], tail: &[u8], message_len: usize) -> [u32; 8] {
+	assert!(tail.len() < SHA256_CHUNK_SIZE - 8, "tail must fit into a single chunk");
+
+	let mut chunk = [0u8; SHA256_CHUNK_SIZE];
+	chunk[..tail.len()].copy_from_slice(tail);
+	chunk[tail.len()] = 0x80;
+	let bits_len = (message_len as u64) * 8;
+	for i in 0..8 {
+		chunk[SHA256_CHUNK_SIZE - 1 - i] = (bits_len >> (i * 8)) as u8;
+	}
+
+	compress(&mut state, &chunk);
+	state
+}
+
+fn state_to_h256(state: &[u32; 8]) -> H256 {
+	let mut result = H256::default();
+	for (i, word) in state.iter().enumerate() {
+		result[i * 4] = (word >> 24) as u8;
+		result[i * 4 + 1] = (word >> 16) as u8;
+		result[i * 4 + 2] = (word >> 8) as u8;
+		result[i * 4 + 3] = *word as u8;
+	}
+	result
+}
+
+/// SHA-256 compression function.
+fn compress(state: &mut [u32; 8], chunk: &[u8; SHA256_CHUNK_SIZE]) {
+	let mut w = [0u32; 64];
+	for i in 0..16 {
+		w[i] = (chunk[i * 4] as u32) << 24
+			| (chunk[i * 4 + 1] as u32) << 16
+			| (chunk[i * 4 + 2] as u32) << 8
+			| chunk[i * 4 + 3] as u32;
+	}
+
+	for i in 16..64 {
+		let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+		let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+		w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+	}
+
+	let (mut a, mut b, mut c, mut d) = (state[0], state[1], state[2], state[3]);
+	let (mut e, mut f, mut g, mut h) = (state[4], state[5], state[6], state[7]);
+
+	for i in 0..64 {
+		let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+		let ch = (e & f) ^ (!e & g);
+		let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(ROUND_CONSTANTS[i]).wrapping_add(w[i]);
+		let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+		let maj = (a & b) ^ (a & c) ^ (b & c);
+		let t2 = s0.wrapping_add(maj);
+
+		h = g;
+		g = f;
+		f = e;
+		e = d.wrapping_add(t1);
+		d = c;
+		c = b;
+		b = a;
+		a = t1.wrapping_add(t2);
+	}
+
+	state[0] = state[0].wrapping_add(a);
+	state[1] = state[1].wrapping_add(b);
+	state[2] = state[2].wrapping_add(c);
+	state[3] = state[3].wrapping_add(d);
+	state[4] = state[4].wrapping_add(e);
+	state[5] = state[5].wrapping_add(f);
+	state[6] = state[6].wrapping_add(g);
+	state[7] = state[7].wrapping_add(h);
+}
+
+#[cfg(test)]
+mod tests {
+	use {sha256, dhash256};
+	use super::{Sha256Midstate, SHA256_CHUNK_SIZE};
+
+	fn message() -> Vec<u8> {
+		(0..80u8).collect()
+	}
+
+	fn first_chunk(data: &[u8]) -> [u8; SHA256_CHUNK_SIZE] {
+		let mut chunk = [0u8; SHA256_CHUNK_SIZE];
+		chunk.copy_from_slice(&data[..SHA256_CHUNK_SIZE]);
+		chunk
+	}
+
+	#[test]
+	fn test_midstate_sha256() {
+		let data = message();
+		let midstate = Sha256Midstate::new(&first_chunk(&data));
+		assert_eq!(midstate.sha256(&data[SHA256_CHUNK_SIZE..]), sha256(&data));
+		assert_eq!(midstate.sha256(&[]), sha256(&data[..SHA256_CHUNK_SIZE]));
+	}
+
+	#[test]
+	fn test_midstate_dhash256() {
+		let data = message();
+		let midstate = Sha256Midstate::new(&first_chunk(&data));
+		assert_eq!(midstate.dhash256(&data[SHA256_CHUNK_SIZE..]), dhash256(&data));
+	}
+}
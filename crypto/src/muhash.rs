@@ -0,0 +1,278 @@
+//! MuHash3072: hash of the set of byte strings, which can be updated as elements are inserted and removed.
+//!
+//! Every element is mapped to the 3072-bit number by expanding its SHA-256 hash with ChaCha20.
+//! Hash of the set is the product of numbers of all elements modulo the prime 2^3072 - 1103717,
+//! so it doesn't depend on the order of insertions. Removed elements are multiplied into the
+//! denominator, which is divided out when the hash is finalized.
+
+use primitives::hash::H256;
+use sha256;
+
+/// Number of 32-bit limbs in the 3072-bit number.
+const LIMBS: usize = 96;
+/// Size of the serialized 3072-bit number.
+const NUM3072_SIZE: usize = LIMBS * 4;
+/// The modulus is 2^3072 - MAX_PRIME_DIFF.
+const MAX_PRIME_DIFF: u64 = 1103717;
+/// Size of the ChaCha20 keystream block.
+const CHACHA20_BLOCK_SIZE: usize = 64;
+/// "expand 32-byte k"
+const CHACHA20_CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// Number modulo 2^3072 - 1103717, stored as little-endian limbs.
+/// Results of multiplication are below 2^3072, but not necessarily below the modulus.
+struct Num3072([u32; LIMBS]);
+
+impl Clone for Num3072 {
+	fn clone(&self) -> Self {
+		Num3072(self.0)
+	}
+}
+
+impl Num3072 {
+	fn one() -> Self {
+		let mut limbs = [0u32; LIMBS];
+		limbs[0] = 1;
+		Num3072(limbs)
+	}
+
+	/// Maps element to the number.
+	fn from_element(data: &[u8]) -> Self {
+		let key = sha256(data);
+		let mut bytes = [0u8; NUM3072_SIZE];
+		for (counter, block) in bytes.chunks_mut(CHACHA20_BLOCK_SIZE).enumerate() {
+			chacha20_block(&key, counter as u32, block);
+		}
+
+		let mut limbs = [0u32; LIMBS];
+		for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(4)) {
+			*limb = read_u32_le(chunk);
+		}
+		Num3072(limbs)
+	}
+
+	fn is_one(&self) -> bool {
+		self.0[0] == 1 && self.0[1..].iter().all(|limb| *limb == 0)
+	}
+
+	/// Serializes number, reduced below the modulus, in little-endian order.
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut limbs = self.0;
+		let is_overflow = limbs[0] as u64 >= (1 << 32) - MAX_PRIME_DIFF && limbs[1..].iter().all(|limb| *limb == u32::max_value());
+		if is_overflow {
+			// subtracting 2^3072 - c is the same as adding c and dropping the carry
+			add_to_limbs(&mut limbs, MAX_PRIME_DIFF);
+		}
+
+		let mut result = Vec::with_capacity(NUM3072_SIZE);
+		for limb in limbs.iter() {
+			result.extend_from_slice(&[*limb as u8, (*limb >> 8) as u8, (*limb >> 16) as u8, (*limb >> 24) as u8]);
+		}
+		result
+	}
+
+	fn mul(&self, other: &Num3072) -> Num3072 {
+		let mut wide = [0u32; LIMBS * 2];
+		for i in 0..LIMBS {
+			let mut carry = 0u64;
+			for j in 0..LIMBS {
+				let t = self.0[i] as u64 * other.0[j] as u64 + wide[i + j] as u64 + carry;
+				wide[i + j] = t as u32;
+				carry = t >> 32;
+			}
+			wide[i + LIMBS] = carry as u32;
+		}
+
+		// 2^3072 is congruent to c, so the high half is multiplied by c and added to the low half
+		let mut limbs = [0u32; LIMBS];
+		let mut carry = 0u64;
+		for i in 0..LIMBS {
+			let t = wide[i] as u64 + wide[i + LIMBS] as u64 * MAX_PRIME_DIFF + carry;
+			limbs[i] = t as u32;
+			carry = t >> 32;
+		}
+
+		// the same is done with the carry out of the top limb, until there's nothing left
+		while carry != 0 {
+			carry = add_to_limbs(&mut limbs, carry * MAX_PRIME_DIFF);
+		}
+
+		Num3072(limbs)
+	}
+
+	/// Computes the inverse as self^(p - 2), which is valid, because the modulus p is prime.
+	fn inverse(&self) -> Num3072 {
+		// all limbs of p - 2 = 2^3072 - c - 2 are ones, except the lowest one
+		let mut exponent = [u32::max_value(); LIMBS];
+		exponent[0] = ((1u64 << 32) - MAX_PRIME_DIFF - 2) as u32;
+
+		let mut result = Num3072::one();
+		for limb in exponent.iter().rev() {
+			for bit in (0..32).rev() {
+				result = result.mul(&result);
+				if (*limb >> bit) & 1 == 1 {
+					result = result.mul(self);
+				}
+			}
+		}
+		result
+	}
+}
+
+/// Hash of the set of elements.
+#[derive(Clone)]
+pub struct MuHash3072 {
+	numerator: Num3072,
+	denominator: Num3072,
+}
+
+impl Default for MuHash3072 {
+	fn default() -> Self {
+		MuHash3072 {
+			numerator: Num3072::one(),
+			denominator: Num3072::one(),
+		}
+	}
+}
+
+impl MuHash3072 {
+	/// Inserts element into the set.
+	pub fn insert(&mut self, data: &[u8]) {
+		self.numerator = self.numerator.mul(&Num3072::from_element(data));
+	}
+
+	/// Removes element from the set.
+	pub fn remove(&mut self, data: &[u8]) {
+		self.denominator = self.denominator.mul(&Num3072::from_element(data));
+	}
+
+	/// Inserts all elements of the other set and removes all elements, removed from it.
+	pub fn combine(&mut self, other: &MuHash3072) {
+		self.numerator = self.numerator.mul(&other.numerator);
+		self.denominator = self.denominator.mul(&other.denominator);
+	}
+
+	/// Computes the hash of the set.
+	pub fn finalize(&self) -> H256 {
+		let result = if self.denominator.is_one() {
+			self.numerator.clone()
+		} else {
+			self.numerator.mul(&self.denominator.inverse())
+		};
+
+		sha256(&result.to_bytes())
+	}
+}
+
+/// Adds value to the number, returning the carry out of the top limb.
+fn add_to_limbs(limbs: &mut [u32; LIMBS], value: u64) -> u64 {
+	let mut carry = value;
+	for limb in limbs.iter_mut() {
+		if carry == 0 {
+			break;
+		}
+
+		let t = *limb as u64 + carry;
+		*limb = t as u32;
+		carry = t >> 32;
+	}
+	carry
+}
+
+fn read_u32_le(data: &[u8]) -> u32 {
+	data[0] as u32 | (data[1] as u32) << 8 | (data[2] as u32) << 16 | (data[3] as u32) << 24
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+	state[a] = state[a].wrapping_add(state[b]);
+	state[d] = (state[d] ^ state[a]).rotate_left(16);
+	state[c] = state[c].wrapping_add(state[d]);
+	state[b] = (state[b] ^ state[c]).rotate_left(12);
+	state[a] = state[a].wrapping_add(state[b]);
+	state[d] = (state[d] ^ state[a]).rotate_left(8);
+	state[c] = state[c].wrapping_add(state[d]);
+	state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// Writes ChaCha20 keystream block with zero nonce and given block counter.
+fn chacha20_block(key: &H256, counter: u32, output: &mut [u8]) {
+	let mut initial = [0u32; 16];
+	initial[0..4].copy_from_slice(&CHACHA20_CONSTANTS);
+	for i in 0..8 {
+		initial[4 + i] = read_u32_le(&key[i * 4..i * 4 + 4]);
+	}
+	initial[12] = counter;
+
+	let mut state = initial;
+	for _ in 0..10 {
+		quarter_round(&mut state, 0, 4, 8, 12);
+		quarter_round(&mut state, 1, 5, 9, 13);
+		quarter_round(&mut state, 2, 6, 10, 14);
+		quarter_round(&mut state, 3, 7, 11, 15);
+		quarter_round(&mut state, 0, 5, 10, 15);
+		quarter_round(&mut state, 1, 6, 11, 12);
+		quarter_round(&mut state, 2, 7, 8, 13);
+		quarter_round(&mut state, 3, 4, 9, 14);
+	}
+
+	for i in 0..16 {
+		let word = state[i].wrapping_add(initial[i]);
+		output[i * 4..i * 4 + 4].copy_from_slice(&[word as u8, (word >> 8) as u8, (word >> 16) as u8, (word >> 24) as u8]);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use primitives::hash::H256;
+	use super::{MuHash3072, chacha20_block};
+
+	fn element(i: u8) -> [u8; 32] {
+		let mut data = [0u8; 32];
+		data[0] = i;
+		data
+	}
+
+	#[test]
+	fn test_chacha20_block() {
+		// RFC 8439, appendix A.1, test vector 1
+		let mut output = [0u8; 64];
+		chacha20_block(&H256::default(), 0, &mut output);
+		assert_eq!(&output[..16], &[0x76u8, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90, 0x40, 0x5d, 0x6a, 0xe5, 0x53, 0x86, 0xbd, 0x28]);
+	}
+
+	#[test]
+	fn test_muhash() {
+		// test vector of Bitcoin Core
+		let mut muhash = MuHash3072::default();
+		muhash.insert(&element(0));
+		muhash.insert(&element(1));
+		muhash.remove(&element(2));
+		assert_eq!(muhash.finalize().reversed(), "10d312b100cbd32ada024a6646e40d3482fcff103668d2625f10002a607d5863".into());
+	}
+
+	#[test]
+	fn test_muhash_order_and_removal() {
+		let mut first = MuHash3072::default();
+		first.insert(&element(1));
+		first.insert(&element(2));
+
+		let mut second = MuHash3072::default();
+		second.insert(&element(2));
+		second.insert(&element(3));
+		second.insert(&element(1));
+		second.remove(&element(3));
+		assert_eq!(first.finalize(), second.finalize());
+
+		let mut combined = MuHash3072::default();
+		combined.insert(&element(2));
+		let mut other = MuHash3072::default();
+		other.insert(&element(1));
+		combined.combine(&other);
+		assert_eq!(first.finalize(), combined.finalize());
+
+		let mut empty = MuHash3072::default();
+		empty.insert(&element(1));
+		empty.remove(&element(1));
+		assert_eq!(empty.finalize(), MuHash3072::default().finalize());
+	}
+}
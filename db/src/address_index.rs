@@ -0,0 +1,131 @@
+//! Address index.
+//!
+//! When index is enabled, every output of the canon chain transactions is stored by the hash
+//! of its script and its outpoint, along with the transaction, which spends it. Outputs of the
+//! script are found by the script hash prefix, so block update only touches its own outputs. Index is updated when blocks
+//! are canonized and decanonized, so it always follows the canon chain. Blocks must be indexed
+//! in order, so blocks, canonized while index was disabled, must be indexed before any new block.
+
+use std::io;
+use hash::H256;
+use crypto::sha256;
+use ser::{Serializable, Deserializable, Error as ReaderError, Stream, Reader};
+
+/// Address index storage interface
+pub trait AddressIndexProvider {
+	/// Is address index enabled?
+	fn is_address_index_enabled(&self) -> bool;
+
+	/// get all canon chain outputs, paying to the script with given hash, ordered by height
+	fn address_outputs(&self, script_hash: &H256) -> Vec<AddressOutput>;
+}
+
+/// Canon chain transaction output, stored in the address index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressOutput {
+	/// Hash of the transaction
+	pub transaction_hash: H256,
+	/// Index of the output in the transaction
+	pub index: u32,
+	/// Value of the output
+	pub value: u64,
+	/// Height of the block, which includes the transaction
+	pub height: u32,
+	/// Canon chain transaction, spending the output
+	pub spent_by: Option<AddressSpend>,
+}
+
+/// Canon chain transaction, spending indexed output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressSpend {
+	/// Hash of the spending transaction
+	pub transaction_hash: H256,
+	/// Height of the block, which includes the spending transaction
+	pub height: u32,
+}
+
+/// Returns address index key of the output script.
+pub fn script_hash(script: &[u8]) -> H256 {
+	sha256(script)
+}
+
+/// Returns true if outputs, paying to this script, are indexed. Provably unspendable outputs are not.
+pub fn is_indexed_script(script: &[u8]) -> bool {
+	script.first() != Some(&0x6a)
+}
+
+impl Serializable for AddressOutput {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.transaction_hash)
+			.append(&self.index)
+			.append(&self.value)
+			.append(&self.height);
+		match self.spent_by {
+			Some(ref spend) => stream
+				.append(&1u8)
+				.append(&spend.transaction_hash)
+				.append(&spend.height),
+			None => stream.append(&0u8),
+		};
+	}
+}
+
+impl Deserializable for AddressOutput {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let transaction_hash = reader.read()?;
+		let index = reader.read()?;
+		let value = reader.read()?;
+		let height = reader.read()?;
+		let spent_by = match reader.read::<u8>()? {
+			0 => None,
+			1 => Some(AddressSpend {
+				transaction_hash: reader.read()?,
+				height: reader.read()?,
+			}),
+			_ => return Err(ReaderError::MalformedData),
+		};
+
+		Ok(AddressOutput {
+			transaction_hash: transaction_hash,
+			index: index,
+			value: value,
+			height: height,
+			spent_by: spent_by,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use ser::{serialize, deserialize};
+	use super::{AddressOutput, AddressSpend, is_indexed_script};
+
+	#[test]
+	fn test_address_output_serialization() {
+		let unspent = AddressOutput {
+			transaction_hash: H256::from(1),
+			index: 2,
+			value: 3,
+			height: 4,
+			spent_by: None,
+		};
+		let spent = AddressOutput {
+			spent_by: Some(AddressSpend {
+				transaction_hash: H256::from(5),
+				height: 6,
+			}),
+			..unspent.clone()
+		};
+		assert_eq!(deserialize::<_, AddressOutput>(serialize(&unspent).as_ref()).unwrap(), unspent);
+		assert_eq!(deserialize::<_, AddressOutput>(serialize(&spent).as_ref()).unwrap(), spent);
+	}
+
+	#[test]
+	fn test_is_indexed_script() {
+		assert!(is_indexed_script(&[]));
+		assert!(is_indexed_script(&[0x76, 0xa9]));
+		assert!(!is_indexed_script(&[0x6a, 0x01, 0x00]));
+	}
+}
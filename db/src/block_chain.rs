@@ -18,6 +18,9 @@ pub trait BlockChain {
 	/// Rollbacks single best block. Returns new best block hash
 	fn rollback_best(&self) -> Result<H256, Error>;
 
+	/// Rollbacks best blocks until block with given number becomes the best. Returns new best block hash
+	fn rollback_to(&self, block_number: u32) -> Result<H256, Error>;
+
 	/// Canonizes block with given hash
 	fn canonize(&self, block_hash: &H256) -> Result<(), Error>;
 
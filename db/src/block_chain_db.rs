@@ -1,15 +1,17 @@
+use std::cmp::{min, max};
 use std::collections::HashMap;
-use std::fs;
+use std::{fs, io, mem};
 use std::path::Path;
 use parking_lot::RwLock;
 use hash::H256;
 use bytes::Bytes;
+use primitives::bigint::U256;
 use chain::{
 	IndexedBlock, IndexedBlockHeader, IndexedTransaction, BlockHeader, Block, Transaction,
 	OutPoint, TransactionOutput
 };
 use ser::{
-	deserialize, serialize, List
+	deserialize, serialize, List, Stream, SERIALIZE_TRANSACTION_WITNESS
 };
 use kv::{
 	KeyValueDatabase, OverlayDatabase, Transaction as DBTransaction, Value, DiskDatabase,
@@ -17,23 +19,75 @@ use kv::{
 };
 use kv::{
 	COL_COUNT, COL_BLOCK_HASHES, COL_BLOCK_HEADERS, COL_BLOCK_TRANSACTIONS, COL_TRANSACTIONS,
-	COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS
+	COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS, COL_BLOCK_FILTER_HEADERS
 };
+use address_index::{AddressOutput, AddressSpend, script_hash, is_indexed_script};
+use prune::{PruneMode, MIN_BLOCKS_TO_KEEP};
 use best_block::BestBlock;
+use block_undo::{BlockUndo, SpentOutput, BlockUndoProvider};
+use transaction_index::{TransactionIndexProvider, TransactionLocation};
+use chain_stats::ChainStatsProvider;
+use block_invalidation::{BlockInvalidation, ChainReorganization};
+use header_tree::{HeaderTree, HeaderEntry, HeaderStatus};
+use cfindex::{basic_filter_elements, build_basic_filter, filter_header};
+use utxo_cache::UtxoCache;
+use utxo_snapshot::{UtxoSnapshot, UtxoSnapshotReader, UnspentOutput};
 use {
 	BlockRef, Error, BlockHeaderProvider, BlockProvider, BlockOrigin, TransactionMeta, IndexedBlockProvider,
 	TransactionMetaProvider, TransactionProvider, TransactionOutputProvider, BlockChain, Store,
-	SideChainOrigin, ForkChain, Forkable, CanonStore, ConfigStore
+	SideChainOrigin, ForkChain, Forkable, CanonStore, ConfigStore, WalletStore, BlockFilterProvider, AddressIndexProvider,
+	BlockPruning
 };
 
 const KEY_BEST_BLOCK_NUMBER: &'static str = "best_block_number";
 const KEY_BEST_BLOCK_HASH: &'static str = "best_block_hash";
+/// Number of canon blocks, indexed by the address index.
+/// Renamed when the index switched to per-output entries, so that the index of older databases is rebuilt.
+const KEY_ADDRESS_INDEX_BLOCKS: &'static str = "address_outputs_index_blocks";
+/// Number of canon blocks, indexed by the transaction index.
+const KEY_TRANSACTION_INDEX_BLOCKS: &'static str = "transaction_index_blocks";
+/// Number of pruned canon blocks.
+const KEY_PRUNED_BLOCKS: &'static str = "pruned_blocks";
+/// Total size of canon blocks, which are not pruned.
+const KEY_STORED_BLOCKS_SIZE: &'static str = "stored_blocks_size";
+/// Hashes of stored blocks without stored children.
+const KEY_CHAIN_TIPS: &'static str = "chain_tips";
+/// Hashes of blocks, marked invalid by the operator.
+const KEY_INVALID_BLOCKS: &'static str = "invalid_blocks";
+
+const KEY_HEADER_TIPS: &'static str = "header_tips";
 
 const MAX_FORK_ROUTE_PRESET: usize = 2048;
+/// Number of records, written in the single database transaction when the UTXO snapshot is loaded.
+const SNAPSHOT_LOAD_BATCH_SIZE: usize = 10_000;
+
+fn read_best_block<F>(get: F) -> Option<BestBlock> where F: Fn(&Key) -> Result<KeyState<Value>, String> {
+	let best_number = get(&Key::Meta(KEY_BEST_BLOCK_NUMBER)).map(KeyState::into_option).map(|x| x.and_then(Value::as_meta));
+	let best_hash = get(&Key::Meta(KEY_BEST_BLOCK_HASH)).map(KeyState::into_option).map(|x| x.and_then(Value::as_meta));
+
+	match (best_number, best_hash) {
+		(Ok(None), Ok(None)) => None,
+		(Ok(Some(number)), Ok(Some(hash))) => Some(BestBlock {
+			number: deserialize(&**number).expect("Inconsistent DB. Invalid best block number."),
+			hash: deserialize(&**hash).expect("Inconsistent DB. Invalid best block hash."),
+		}),
+		_ => panic!("Inconsistent DB"),
+	}
+}
 
 pub struct BlockChainDatabase<T> where T: KeyValueDatabase {
 	best_block: RwLock<BestBlock>,
 	db: T,
+	/// Are compact block filters indexed?
+	cfindex: bool,
+	/// Are transaction outputs indexed by address?
+	address_index: bool,
+	/// Are canon transactions indexed by hash?
+	transaction_index: bool,
+	/// Pruning mode of the canon blocks.
+	prune: PruneMode,
+	/// Cache of the canon chain unspent outputs.
+	utxo_cache: Option<UtxoCache>,
 }
 
 pub struct ForkChainDatabase<'a, T> where T: 'a + KeyValueDatabase {
@@ -53,6 +107,9 @@ impl<'a, T> ForkChain for ForkChainDatabase<'a, T> where T: KeyValueDatabase {
 impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<DiskDatabase>>> {
 	pub fn open_at_path<P>(path: P, total_cache: usize) -> Result<Self, Error> where P: AsRef<Path> {
 		fs::create_dir_all(path.as_ref()).map_err(|err| Error::DatabaseError(err.to_string()))?;
+		// half of the cache is used by unspent outputs, the rest is shared between database columns
+		let utxo_cache = total_cache / 2;
+		let total_cache = total_cache - utxo_cache;
 		let mut cfg = DatabaseConfig::with_columns(Some(COL_COUNT));
 
 		cfg.set_cache(Some(COL_TRANSACTIONS), total_cache / 4);
@@ -62,11 +119,12 @@ impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<DiskDatabase>>
 		cfg.set_cache(Some(COL_BLOCK_HASHES), total_cache / 12);
 		cfg.set_cache(Some(COL_BLOCK_TRANSACTIONS), total_cache / 12);
 		cfg.set_cache(Some(COL_BLOCK_NUMBERS), total_cache / 12);
+		cfg.set_cache(Some(COL_BLOCK_FILTER_HEADERS), total_cache / 12);
 
 		cfg.bloom_filters.insert(Some(COL_TRANSACTIONS_META), 32);
 
 		match DiskDatabase::open(cfg, path) {
-			Ok(db) => Ok(Self::open_with_cache(db)),
+			Ok(db) => Ok(Self::open_with_cache(db).with_utxo_cache(utxo_cache)),
 			Err(err) => Err(Error::DatabaseError(err))
 		}
 	}
@@ -92,23 +150,18 @@ impl<T> BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<T>>> where
 		BlockChainDatabase {
 			best_block: RwLock::new(best_block),
 			db: db,
+			cfindex: false,
+			address_index: false,
+			transaction_index: false,
+			prune: PruneMode::Disabled,
+			utxo_cache: None,
 		}
 	}
 }
 
 impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn read_best_block(db: &T) -> Option<BestBlock> {
-		let best_number = db.get(&Key::Meta(KEY_BEST_BLOCK_NUMBER)).map(KeyState::into_option).map(|x| x.and_then(Value::as_meta));
-		let best_hash = db.get(&Key::Meta(KEY_BEST_BLOCK_HASH)).map(KeyState::into_option).map(|x| x.and_then(Value::as_meta));
-
-		match (best_number, best_hash) {
-			(Ok(None), Ok(None)) => None,
-			(Ok(Some(number)), Ok(Some(hash))) => Some(BestBlock {
-				number: deserialize(&**number).expect("Inconsistent DB. Invalid best block number."),
-				hash: deserialize(&**hash).expect("Inconsistent DB. Invalid best block hash."),
-			}),
-			_ => panic!("Inconsistent DB"),
-		}
+		read_best_block(|key| db.get(key))
 	}
 
 	pub fn open(db: T) -> Self {
@@ -116,15 +169,58 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		BlockChainDatabase {
 			best_block: RwLock::new(best_block),
 			db: db,
+			cfindex: false,
+			address_index: false,
+			transaction_index: false,
+			prune: PruneMode::Disabled,
+			utxo_cache: None,
 		}
 	}
 
+	/// Enables compact block filters index. Filters of the blocks, canonized before,
+	/// must be indexed with `build_cfindex`.
+	pub fn with_cfindex(mut self, enabled: bool) -> Self {
+		self.cfindex = enabled;
+		self
+	}
+
+	/// Enables address index. Blocks, canonized before, must be indexed with `build_address_index`.
+	pub fn with_address_index(mut self, enabled: bool) -> Self {
+		self.address_index = enabled;
+		self
+	}
+
+	/// Enables transaction index. Blocks, canonized before, must be indexed with `build_transaction_index`.
+	pub fn with_transaction_index(mut self, enabled: bool) -> Self {
+		self.transaction_index = enabled;
+		self
+	}
+
+	/// Sets pruning mode. Blocks are pruned to the target by `init_pruning` on startup.
+	pub fn with_prune(mut self, prune: PruneMode) -> Self {
+		self.prune = prune;
+		self
+	}
+
+	/// Enables cache of the canon chain unspent outputs, using approximately `size` megabytes.
+	/// Cache is disabled when size is zero.
+	pub fn with_utxo_cache(mut self, size: usize) -> Self {
+		self.utxo_cache = match size {
+			0 => None,
+			size => Some(UtxoCache::new(size)),
+		};
+		self
+	}
+
 	pub fn best_block(&self) -> BestBlock {
 		self.best_block.read().clone()
 	}
 
 	pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, Error> {
-		let overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db));
+		let overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db))
+			.with_cfindex(self.cfindex)
+			.with_address_index(self.address_index)
+			.with_transaction_index(self.transaction_index);
 
 		for hash in side_chain.decanonized_route.into_iter().rev() {
 			let decanonized_hash = overlay.decanonize()?;
@@ -145,6 +241,7 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 	pub fn switch_to_fork(&self, fork: ForkChainDatabase<T>) -> Result<(), Error> {
 		let mut best_block = self.best_block.write();
 		*best_block = fork.blockchain.best_block.read().clone();
+		self.clear_utxo_cache();
 		fork.blockchain.db.flush().map_err(Error::DatabaseError)
 	}
 
@@ -166,6 +263,11 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			return Err(Error::UnknownParent)
 		}
 
+		// side chain becomes canon, when it has more work than the canon chain
+		let best_work = self.chain_work(&best_block.hash).unwrap_or_default();
+		let work = self.chain_work(&header.raw.previous_header_hash).unwrap_or_default() + header.raw.bits.block_work();
+
+		let invalid_blocks = self.invalid_blocks();
 		let mut sidechain_route = Vec::new();
 		let mut next_hash = header.raw.previous_header_hash.clone();
 
@@ -181,13 +283,16 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 							.collect(),
 						block_number: block_number,
 					};
-					if block_number > best_block.number {
+					if work > best_work {
 						return Ok(BlockOrigin::SideChainBecomingCanonChain(origin))
 					} else {
 						return Ok(BlockOrigin::SideChain(origin))
 					}
 				},
 				None => {
+					if invalid_blocks.contains(&next_hash) {
+						return Err(Error::InvalidParent)
+					}
 					sidechain_route.push(next_hash.clone());
 					next_hash = self.block_header(next_hash.into())
 						.expect("not to find orphaned side chain in database; qed")
@@ -209,13 +314,51 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			return Err(Error::UnknownParent);
 		}
 
+		let mut chain_tips = self.chain_tips();
+		chain_tips.retain(|tip| *tip != parent_hash);
+		chain_tips.push(block.hash().clone());
+
+		let (parent_work, parent_number) = if parent_hash.is_zero() {
+			(Some(U256::zero()), None)
+		} else {
+			(self.chain_work(&parent_hash), self.header_entry(&parent_hash).map(|entry| entry.number))
+		};
+		let number = match parent_number {
+			Some(parent_number) => Some(parent_number + 1),
+			None if parent_hash.is_zero() => Some(0),
+			None => None,
+		};
+		let chain_work = parent_work.map(|parent_work| parent_work + block.header.raw.bits.block_work());
+		let known_header = self.get(Key::HeaderEntry(block.hash().clone())).and_then(Value::as_header_entry);
+
 		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Meta(KEY_CHAIN_TIPS, serialize(&List::from(chain_tips))));
+		if let Some(ref chain_work) = chain_work {
+			update.insert(KeyValue::ChainWork(block.hash().clone(), chain_work.clone()));
+		}
+		if let (Some(number), Some(chain_work)) = (number, chain_work) {
+			if known_header.is_none() {
+				let mut header_tips = self.header_tips();
+				header_tips.retain(|tip| *tip != parent_hash);
+				header_tips.push(block.hash().clone());
+				update.insert(KeyValue::Meta(KEY_HEADER_TIPS, serialize(&List::from(header_tips))));
+			}
+			update.insert(KeyValue::HeaderEntry(block.hash().clone(), HeaderEntry {
+				header: block.header.raw.clone(),
+				number: number,
+				chain_work: chain_work,
+				status: HeaderStatus {
+					has_block: true,
+					invalid: known_header.map_or(false, |entry| entry.status.invalid),
+				},
+			}));
+		}
 		update.insert(KeyValue::BlockHeader(block.hash().clone(), block.header.raw));
 		let tx_hashes = block.transactions.iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>();
 		update.insert(KeyValue::BlockTransactions(block.header.hash.clone(), List::from(tx_hashes)));
 
 		for tx in block.transactions.into_iter() {
-			update.insert(KeyValue::Transaction(tx.hash, tx.raw));
+			update.insert(KeyValue::Transaction(tx.hash.clone(), tx.into_raw()));
 		}
 
 		self.db.write(update).map_err(Error::DatabaseError)
@@ -233,9 +376,26 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		// and now remove decanonized block from database
 		// all code currently works in assumption that origin of all blocks is one of:
 		// {CanonChain, SideChain, SideChainBecomingCanonChain}
+		let parent_hash = decanonized.block_header.previous_header_hash.clone();
+		let mut chain_tips = self.chain_tips();
+		chain_tips.retain(|tip| *tip != decanonized_hash && *tip != parent_hash);
+		if !parent_hash.is_zero() {
+			chain_tips.push(parent_hash);
+		}
+
 		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Meta(KEY_CHAIN_TIPS, serialize(&List::from(chain_tips))));
+		update.delete(Key::ChainTxCount(decanonized_hash.clone()));
+		update.delete(Key::ChainWork(decanonized_hash.clone()));
+		// header stays in the header tree
+		if let Some(mut entry) = self.get(Key::HeaderEntry(decanonized_hash.clone())).and_then(Value::as_header_entry) {
+			entry.status.has_block = false;
+			update.insert(KeyValue::HeaderEntry(decanonized_hash.clone(), entry));
+		}
 		update.delete(Key::BlockHeader(decanonized_hash.clone()));
 		update.delete(Key::BlockTransactions(decanonized_hash.clone()));
+		update.delete(Key::BlockFilter(decanonized_hash.clone()));
+		update.delete(Key::BlockFilterHeader(decanonized_hash.clone()));
 		for tx in decanonized.transactions.into_iter() {
 			update.delete(Key::Transaction(tx.hash()));
 		}
@@ -245,6 +405,18 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		Ok(self.best_block().hash)
 	}
 
+	/// Rollbacks best blocks until block with given number becomes the best block.
+	/// Returns new best block hash.
+	pub fn rollback_to(&self, block_number: u32) -> Result<H256, Error> {
+		let mut best_block = self.best_block();
+		while best_block.number > block_number {
+			self.rollback_best()?;
+			best_block = self.best_block();
+		}
+
+		Ok(best_block.hash)
+	}
+
 	/// Marks block as a new best block.
 	/// Block must be already inserted into db, and it's parent must be current best block.
 	/// Updates meta data.
@@ -304,15 +476,629 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			}
 		}
 
+		let undo = self.block_undo_of(&block, new_best_block.number, &modified_meta)?;
+
+		// body of the transaction from the pruned block is deleted when the block, spending its last output, is pruned
+		let pruned_blocks = self.pruned_blocks();
+		let mut spent_transactions = Vec::new();
 		for (hash, meta) in modified_meta.into_iter() {
+			if meta.height() < pruned_blocks && meta.is_fully_spent() {
+				spent_transactions.push(hash.clone());
+			}
 			update.insert(KeyValue::TransactionMeta(hash, meta));
 		}
+		self.add_spent_transactions(new_best_block.number, spent_transactions, &mut update);
+
+		if let Some(size) = self.stored_blocks_size() {
+			update.insert(KeyValue::Meta(KEY_STORED_BLOCKS_SIZE, serialize(&(size + block.size_with_witness() as u64))));
+		}
+
+		let parent_tx_count = if block.header.raw.previous_header_hash.is_zero() {
+			Some(0)
+		} else {
+			self.chain_tx_count(&block.header.raw.previous_header_hash)
+		};
+		if let Some(parent_tx_count) = parent_tx_count {
+			update.insert(KeyValue::ChainTxCount(hash.clone(), parent_tx_count + block.transactions.len() as u64));
+		}
+
+		if self.cfindex {
+			self.index_block_filter(&block, &mut update)?;
+		}
+
+		if self.address_index && self.address_index_blocks() == new_best_block.number {
+			self.index_block_addresses(&block, new_best_block.number, &undo, &mut update)?;
+		}
+
+		if self.transaction_index && self.transaction_index_blocks() == new_best_block.number {
+			self.index_block_transactions(&block, new_best_block.number, &mut update);
+		}
 
+		update.insert(KeyValue::BlockUndo(hash.clone(), undo));
 		self.db.write(update).map_err(Error::DatabaseError)?;
+		if let Some(ref utxo_cache) = self.utxo_cache {
+			utxo_cache.canonize(&block);
+		}
 		*best_block = new_best_block;
+
+		if let PruneMode::Target(target) = self.prune {
+			self.prune_to_target(&best_block, target)?;
+		}
+
+		Ok(())
+	}
+
+	/// Computes basic filter of the canon block and adds it to the update.
+	/// Nothing is added if filter of the parent block isn't indexed yet.
+	fn index_block_filter(&self, block: &IndexedBlock, update: &mut DBTransaction) -> Result<(), Error> {
+		let parent_hash = &block.header.raw.previous_header_hash;
+		let previous_header = if parent_hash.is_zero() {
+			H256::default()
+		} else {
+			// filters are chained by headers => blocks, canonized before the index has been enabled, must be indexed first
+			self.block_filter_header(parent_hash).ok_or(Error::UnindexedParentFilter)?
+		};
+
+		let mut spent_scripts = Vec::new();
+		for tx in block.transactions.iter().skip(1) {
+			for input in &tx.raw.inputs {
+				let output = self.transaction(&input.previous_output.hash)
+					.and_then(|tx| tx.outputs.into_iter().nth(input.previous_output.index as usize))
+					.ok_or(Error::CannotCanonize)?;
+				spent_scripts.push(output.script_pubkey);
+			}
+		}
+
+		let filter = build_basic_filter(block.hash(), &basic_filter_elements(block, spent_scripts));
+		let header = filter_header(&filter, &previous_header);
+		update.insert(KeyValue::BlockFilter(block.hash().clone(), filter));
+		update.insert(KeyValue::BlockFilterHeader(block.hash().clone(), header));
+		Ok(())
+	}
+
+	/// Indexes compact block filters of the canon blocks, which are not indexed yet.
+	/// Returns number of indexed blocks. Fails if some of these blocks are pruned.
+	pub fn build_cfindex(&self) -> Result<u32, Error> {
+		let best_block = self.best_block();
+		if !self.cfindex || self.block_hash(best_block.number).is_none() {
+			return Ok(0);
+		}
+
+		// filters are indexed in order, so first block without filter is found going backwards from the best block
+		let mut first_unindexed = best_block.number + 1;
+		while first_unindexed > 0 {
+			let hash = self.block_hash(first_unindexed - 1).expect("all blocks up to best block are canon; qed");
+			if self.block_filter_header(&hash).is_some() {
+				break;
+			}
+			first_unindexed -= 1;
+		}
+
+		// filter of the block is built from its body and scripts of outputs it spends
+		if first_unindexed <= best_block.number && first_unindexed < self.pruned_blocks() {
+			return Err(Error::PrunedBlocks);
+		}
+
+		for number in first_unindexed..best_block.number + 1 {
+			let block = self.indexed_block(number.into()).ok_or(Error::CannotCanonize)?;
+			let mut update = DBTransaction::new();
+			self.index_block_filter(&block, &mut update)?;
+			self.db.write(update).map_err(Error::DatabaseError)?;
+
+			if number % 10000 == 0 {
+				info!(target: "db", "Indexed compact filters of {} blocks out of {}", number - first_unindexed, best_block.number + 1 - first_unindexed);
+			}
+		}
+
+		Ok(best_block.number + 1 - first_unindexed)
+	}
+
+	/// Returns number of canon blocks, indexed by the address index.
+	fn address_index_blocks(&self) -> u32 {
+		self.get(Key::Meta(KEY_ADDRESS_INDEX_BLOCKS))
+			.and_then(Value::as_meta)
+			.map(|blocks| deserialize(&*blocks).expect("Inconsistent DB. Invalid address index blocks number."))
+			.unwrap_or_default()
+	}
+
+	/// Returns indexed output, either modified by the update, or read from the database.
+	/// None if output isn't indexed, or is removed from the index by the update.
+	fn modified_address_output<'a>(&self, modified: &'a mut HashMap<(H256, OutPoint), Option<AddressOutput>>, script: &[u8], outpoint: &OutPoint) -> &'a mut Option<AddressOutput> {
+		use std::collections::hash_map::Entry;
+
+		match modified.entry((script_hash(script), outpoint.clone())) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => {
+				let output = self.get(Key::AddressIndex(entry.key().0.clone(), outpoint.clone())).and_then(Value::as_address_index);
+				entry.insert(output)
+			},
+		}
+	}
+
+	/// Returns output, spent by the canon block input.
+	fn spent_output(&self, prevout: &OutPoint) -> Result<TransactionOutput, Error> {
+		self.transaction(&prevout.hash)
+			.and_then(|tx| tx.outputs.into_iter().nth(prevout.index as usize))
+			.ok_or(Error::CannotCanonize)
+	}
+
+	/// Builds undo data of the block, which is being canonized. Meta of all spent transactions must be in `modified_meta`.
+	fn block_undo_of(&self, block: &IndexedBlock, number: u32, modified_meta: &HashMap<H256, TransactionMeta>) -> Result<BlockUndo, Error> {
+		let mut spent_outputs = Vec::new();
+		for tx in block.transactions.iter().skip(1) {
+			for input in &tx.raw.inputs {
+				let prevout = &input.previous_output;
+				let meta = modified_meta.get(&prevout.hash).ok_or(Error::CannotCanonize)?;
+				// outputs of the same block transactions are not canon yet
+				let output = if meta.height() == number {
+					block.transactions.iter()
+						.find(|tx| tx.hash == prevout.hash)
+						.and_then(|tx| tx.raw.outputs.get(prevout.index as usize).cloned())
+						.ok_or(Error::CannotCanonize)?
+				} else {
+					self.transaction_output(prevout, 0).ok_or(Error::CannotCanonize)?
+				};
+
+				spent_outputs.push(SpentOutput {
+					output: output,
+					height: meta.height(),
+					is_coinbase: meta.is_coinbase(),
+				});
+			}
+		}
+
+		Ok(BlockUndo {
+			spent_outputs: spent_outputs,
+		})
+	}
+
+	/// Returns undo data of the canon block. Undo data of blocks, canonized before it has been introduced,
+	/// is built from bodies of the spent transactions.
+	fn canon_block_undo(&self, block: &IndexedBlock) -> Result<BlockUndo, Error> {
+		if let Some(undo) = self.block_undo(block.hash()) {
+			return Ok(undo);
+		}
+
+		let mut spent_outputs = Vec::new();
+		for tx in block.transactions.iter().skip(1) {
+			for input in &tx.raw.inputs {
+				let prevout = &input.previous_output;
+				let meta = self.transaction_meta(&prevout.hash).ok_or(Error::CannotCanonize)?;
+				spent_outputs.push(SpentOutput {
+					output: self.spent_output(prevout)?,
+					height: meta.height(),
+					is_coinbase: meta.is_coinbase(),
+				});
+			}
+		}
+
+		Ok(BlockUndo {
+			spent_outputs: spent_outputs,
+		})
+	}
+
+	/// Adds outputs of the canon block to the address index and marks outputs, spent by the block.
+	fn index_block_addresses(&self, block: &IndexedBlock, number: u32, undo: &BlockUndo, update: &mut DBTransaction) -> Result<(), Error> {
+		let mut modified = HashMap::new();
+		let mut spent_outputs = undo.spent_outputs.iter();
+		for (tx_index, tx) in block.transactions.iter().enumerate() {
+			if tx_index != 0 {
+				for input in &tx.raw.inputs {
+					let spent_output = &spent_outputs.next().ok_or(Error::CannotCanonize)?.output;
+					if !is_indexed_script(&spent_output.script_pubkey) {
+						continue;
+					}
+
+					if let Some(ref mut output) = *self.modified_address_output(&mut modified, &spent_output.script_pubkey, &input.previous_output) {
+						output.spent_by = Some(AddressSpend {
+							transaction_hash: tx.hash.clone(),
+							height: number,
+						});
+					}
+				}
+			}
+
+			for (index, output) in tx.raw.outputs.iter().enumerate() {
+				if !is_indexed_script(&output.script_pubkey) {
+					continue;
+				}
+
+				let outpoint = OutPoint {
+					hash: tx.hash.clone(),
+					index: index as u32,
+				};
+				modified.insert((script_hash(&output.script_pubkey), outpoint), Some(AddressOutput {
+					transaction_hash: tx.hash.clone(),
+					index: index as u32,
+					value: output.value,
+					height: number,
+					spent_by: None,
+				}));
+			}
+		}
+
+		Self::write_address_outputs(modified, update);
+		update.insert(KeyValue::Meta(KEY_ADDRESS_INDEX_BLOCKS, serialize(&(number + 1))));
 		Ok(())
 	}
 
+	/// Removes outputs of the decanonized block from the address index and marks outputs, spent by the block, as unspent.
+	fn unindex_block_addresses(&self, block: &IndexedBlock, number: u32, undo: &BlockUndo, update: &mut DBTransaction) -> Result<(), Error> {
+		let mut modified = HashMap::new();
+		let mut spent_outputs = undo.spent_outputs.iter().rev();
+		for (tx_index, tx) in block.transactions.iter().enumerate().rev() {
+			for (index, output) in tx.raw.outputs.iter().enumerate() {
+				if is_indexed_script(&output.script_pubkey) {
+					let outpoint = OutPoint {
+						hash: tx.hash.clone(),
+						index: index as u32,
+					};
+					modified.insert((script_hash(&output.script_pubkey), outpoint), None);
+				}
+			}
+
+			if tx_index != 0 {
+				for input in tx.raw.inputs.iter().rev() {
+					let spent_output = &spent_outputs.next().ok_or(Error::CannotCanonize)?.output;
+					if !is_indexed_script(&spent_output.script_pubkey) {
+						continue;
+					}
+
+					if let Some(ref mut output) = *self.modified_address_output(&mut modified, &spent_output.script_pubkey, &input.previous_output) {
+						output.spent_by = None;
+					}
+				}
+			}
+		}
+
+		Self::write_address_outputs(modified, update);
+		update.insert(KeyValue::Meta(KEY_ADDRESS_INDEX_BLOCKS, serialize(&number)));
+		Ok(())
+	}
+
+	fn write_address_outputs(modified: HashMap<(H256, OutPoint), Option<AddressOutput>>, update: &mut DBTransaction) {
+		for ((script_hash, outpoint), output) in modified {
+			match output {
+				Some(output) => update.insert(KeyValue::AddressIndex(script_hash, outpoint, output)),
+				None => update.delete(Key::AddressIndex(script_hash, outpoint)),
+			}
+		}
+	}
+
+	/// Indexes addresses of the canon blocks, which are not indexed yet.
+	/// Returns number of indexed blocks.
+	pub fn build_address_index(&self) -> Result<u32, Error> {
+		let best_block = self.best_block();
+		if !self.address_index || self.block_hash(best_block.number).is_none() {
+			return Ok(0);
+		}
+
+		let first_unindexed = self.address_index_blocks();
+		for number in first_unindexed..best_block.number + 1 {
+			let block = self.indexed_block(number.into()).ok_or(Error::CannotCanonize)?;
+			let undo = self.canon_block_undo(&block)?;
+			let mut update = DBTransaction::new();
+			self.index_block_addresses(&block, number, &undo, &mut update)?;
+			self.db.write(update).map_err(Error::DatabaseError)?;
+
+			if number % 10000 == 0 {
+				info!(target: "db", "Indexed addresses of {} blocks out of {}", number - first_unindexed, best_block.number + 1 - first_unindexed);
+			}
+		}
+
+		Ok(best_block.number + 1 - first_unindexed)
+	}
+
+	/// Returns number of canon blocks, indexed by the transaction index.
+	fn transaction_index_blocks(&self) -> u32 {
+		self.get(Key::Meta(KEY_TRANSACTION_INDEX_BLOCKS))
+			.and_then(Value::as_meta)
+			.map(|blocks| deserialize(&*blocks).expect("Inconsistent DB. Invalid transaction index blocks number."))
+			.unwrap_or_default()
+	}
+
+	/// Adds transactions of the canon block to the transaction index.
+	fn index_block_transactions(&self, block: &IndexedBlock, number: u32, update: &mut DBTransaction) {
+		for (index, tx) in block.transactions.iter().enumerate() {
+			update.insert(KeyValue::TransactionIndex(tx.hash.clone(), TransactionLocation {
+				block_hash: block.hash().clone(),
+				index: index as u32,
+			}));
+		}
+		update.insert(KeyValue::Meta(KEY_TRANSACTION_INDEX_BLOCKS, serialize(&(number + 1))));
+	}
+
+	/// Removes transactions of the decanonized block from the transaction index.
+	fn unindex_block_transactions(&self, block: &IndexedBlock, number: u32, update: &mut DBTransaction) {
+		for tx in &block.transactions {
+			update.delete(Key::TransactionIndex(tx.hash.clone()));
+		}
+		update.insert(KeyValue::Meta(KEY_TRANSACTION_INDEX_BLOCKS, serialize(&number)));
+	}
+
+	/// Indexes transactions of the canon blocks, which are not indexed yet. Unlike other indexes,
+	/// may be called while new blocks are canonized: every block is indexed under the best block lock,
+	/// so once all blocks are indexed, new blocks are indexed when canonized.
+	/// Returns number of indexed blocks.
+	pub fn build_transaction_index(&self) -> Result<u32, Error> {
+		let mut indexed = 0;
+		loop {
+			let best_block = self.best_block.read();
+			let number = self.transaction_index_blocks();
+			if !self.transaction_index || number > best_block.number || self.block_hash(best_block.number).is_none() {
+				return Ok(indexed);
+			}
+
+			let block = self.indexed_block(number.into()).ok_or(Error::CannotCanonize)?;
+			let mut update = DBTransaction::new();
+			self.index_block_transactions(&block, number, &mut update);
+			self.db.write(update).map_err(Error::DatabaseError)?;
+			indexed += 1;
+
+			if number % 10000 == 0 {
+				info!(target: "db", "Indexed transactions of {} blocks out of {}", number, best_block.number + 1);
+			}
+		}
+	}
+
+	/// Builds header tree entries of the blocks, stored before the header tree has been introduced.
+	/// Stored blocks without children become header tips. Returns number of blocks with new entries.
+	pub fn build_header_tree(&self) -> Result<u32, Error> {
+		let best_block = self.best_block();
+		if self.block_hash(best_block.number).is_none() || self.get(Key::HeaderEntry(best_block.hash.clone())).is_some() {
+			return Ok(0);
+		}
+
+		// entries of canon blocks are written in order, so first block without entry is found going backwards from the best block
+		let mut first_unindexed = best_block.number + 1;
+		let mut parent = None;
+		while first_unindexed > 0 {
+			let hash = self.block_hash(first_unindexed - 1).expect("all blocks up to best block are canon; qed");
+			if let Some(entry) = self.get(Key::HeaderEntry(hash)).and_then(Value::as_header_entry) {
+				parent = Some(entry);
+				break;
+			}
+			first_unindexed -= 1;
+		}
+
+		let mut indexed = 0;
+		let mut update = DBTransaction::new();
+		for number in first_unindexed..best_block.number + 1 {
+			let hash = self.block_hash(number).expect("all blocks up to best block are canon; qed");
+			let header = self.block_header(hash.clone().into()).ok_or(Error::UnknownBlock)?;
+			let entry = stored_header_entry(header, parent.as_ref());
+			update.insert(KeyValue::HeaderEntry(hash, entry.clone()));
+			parent = Some(entry);
+			indexed += 1;
+
+			if number % 10000 == 0 {
+				self.db.write(mem::replace(&mut update, DBTransaction::new())).map_err(Error::DatabaseError)?;
+				info!(target: "db", "Built header tree entries of {} blocks out of {}", number - first_unindexed, best_block.number + 1 - first_unindexed);
+			}
+		}
+		self.db.write(update).map_err(Error::DatabaseError)?;
+
+		// side chains are connected to the canon chain entries
+		let mut header_tips = self.header_tips();
+		if !header_tips.contains(&best_block.hash) {
+			header_tips.push(best_block.hash.clone());
+		}
+		let mut update = DBTransaction::new();
+		for tip in self.chain_tips() {
+			let (route, ancestor) = self.side_chain_route(&tip);
+			if route.is_empty() {
+				continue;
+			}
+
+			let mut parent = match ancestor.and_then(|ancestor| self.block_hash(ancestor)) {
+				Some(ancestor_hash) => self.get(Key::HeaderEntry(ancestor_hash)).and_then(Value::as_header_entry),
+				None => continue,
+			};
+
+			for hash in route.into_iter().rev() {
+				let entry = match self.get(Key::HeaderEntry(hash.clone())).and_then(Value::as_header_entry) {
+					Some(entry) => entry,
+					None => {
+						let header = self.block_header(hash.clone().into()).ok_or(Error::UnknownBlock)?;
+						let entry = stored_header_entry(header, parent.as_ref());
+						update.insert(KeyValue::HeaderEntry(hash, entry.clone()));
+						indexed += 1;
+						entry
+					},
+				};
+				parent = Some(entry);
+			}
+
+			if !header_tips.contains(&tip) {
+				header_tips.push(tip);
+			}
+		}
+		update.insert(KeyValue::Meta(KEY_HEADER_TIPS, serialize(&List::from(header_tips))));
+		self.db.write(update).map_err(Error::DatabaseError)?;
+
+		Ok(indexed)
+	}
+
+	/// Stores chain work of the blocks, stored before the chain work has been introduced, so that
+	/// `chain_work` never walks back to the genesis block. Returns number of blocks with new work.
+	pub fn build_chain_work(&self) -> Result<u32, Error> {
+		// work of the genesis block is written last => it is only stored when all other blocks have work
+		let genesis_hash = match self.block_hash(0) {
+			Some(hash) => hash,
+			None => return Ok(0),
+		};
+		if self.get(Key::ChainWork(genesis_hash.clone())).is_some() {
+			return Ok(0);
+		}
+
+		let best_block = self.best_block();
+		let mut indexed = 0;
+		let mut chain_work = U256::zero();
+		let mut update = DBTransaction::new();
+		for number in 0..best_block.number + 1 {
+			let hash = self.block_hash(number).expect("all blocks up to best block are canon; qed");
+			chain_work = match self.get(Key::ChainWork(hash.clone())).and_then(Value::as_chain_work) {
+				Some(stored_work) => stored_work,
+				None => {
+					let header = self.block_header(hash.clone().into()).ok_or(Error::UnknownBlock)?;
+					let chain_work = chain_work + header.bits.block_work();
+					if number != 0 {
+						update.insert(KeyValue::ChainWork(hash, chain_work.clone()));
+					}
+					indexed += 1;
+					chain_work
+				},
+			};
+
+			if number % 10000 == 0 {
+				self.db.write(mem::replace(&mut update, DBTransaction::new())).map_err(Error::DatabaseError)?;
+				info!(target: "db", "Stored chain work of {} blocks out of {}", number, best_block.number + 1);
+			}
+		}
+		self.db.write(mem::replace(&mut update, DBTransaction::new())).map_err(Error::DatabaseError)?;
+
+		let genesis_header = self.block_header(genesis_hash.clone().into()).ok_or(Error::UnknownBlock)?;
+		let genesis_work = genesis_header.bits.block_work();
+		for tip in self.chain_tips() {
+			let (route, ancestor) = self.side_chain_route(&tip);
+			let ancestor_work = match ancestor {
+				Some(0) => Some(genesis_work.clone()),
+				Some(ancestor) => self.block_hash(ancestor).and_then(|hash| self.get(Key::ChainWork(hash))).and_then(Value::as_chain_work),
+				None => None,
+			};
+			let mut chain_work = match ancestor_work {
+				Some(chain_work) => chain_work,
+				None => continue,
+			};
+
+			for hash in route.into_iter().rev() {
+				chain_work = match self.get(Key::ChainWork(hash.clone())).and_then(Value::as_chain_work) {
+					Some(stored_work) => stored_work,
+					None => {
+						let header = self.block_header(hash.clone().into()).ok_or(Error::UnknownBlock)?;
+						let chain_work = chain_work + header.bits.block_work();
+						update.insert(KeyValue::ChainWork(hash, chain_work.clone()));
+						indexed += 1;
+						chain_work
+					},
+				};
+			}
+		}
+
+		update.insert(KeyValue::ChainWork(genesis_hash, genesis_work));
+		self.db.write(update).map_err(Error::DatabaseError)?;
+
+		Ok(indexed)
+	}
+
+	/// Returns total size of canon blocks, which are not pruned, if it is known.
+	fn stored_blocks_size(&self) -> Option<u64> {
+		self.get(Key::Meta(KEY_STORED_BLOCKS_SIZE))
+			.and_then(Value::as_meta)
+			.map(|size| deserialize(&*size).expect("Inconsistent DB. Invalid stored blocks size."))
+	}
+
+	/// Returns transactions, which bodies are deleted when the canon block with given number is pruned.
+	fn spent_transactions(&self, number: u32) -> Vec<H256> {
+		self.get(Key::SpentTransactions(number))
+			.and_then(Value::as_spent_transactions)
+			.map(List::into)
+			.unwrap_or_default()
+	}
+
+	/// Schedules deletion of transactions bodies until the canon block with given number is pruned.
+	fn add_spent_transactions(&self, number: u32, transactions: Vec<H256>, update: &mut DBTransaction) {
+		if transactions.is_empty() {
+			return;
+		}
+
+		let mut spent_transactions = self.spent_transactions(number);
+		spent_transactions.extend(transactions);
+		update.insert(KeyValue::SpentTransactions(number, List::from(spent_transactions)));
+	}
+
+	/// Prunes the oldest canon block, which is not pruned yet.
+	fn prune_block(&self, best_block: &BestBlock, number: u32) -> Result<(), Error> {
+		let block = self.block_hash(number)
+			.and_then(|hash| self.indexed_block(hash.into()))
+			.ok_or_else(|| Error::DatabaseError(format!("Inconsistent DB. Missing canon block {}", number)))?;
+
+		// pruned blocks are never decanonized
+		let mut update = DBTransaction::new();
+		update.delete(Key::BlockTransactions(block.hash().clone()));
+		update.delete(Key::BlockUndo(block.hash().clone()));
+
+		// outputs of the block transactions may be spent by recent blocks, which may be decanonized
+		// => fully spent transactions are deleted when the best block is pruned
+		let fully_spent: Vec<_> = block.transactions.iter()
+			.filter(|tx| self.transaction_meta(&tx.hash).map_or(false, |meta| meta.is_fully_spent()))
+			.map(|tx| tx.hash.clone())
+			.collect();
+		self.add_spent_transactions(best_block.number, fully_spent, &mut update);
+
+		// transactions, spent by this block, can't be unspent anymore, unless they were unspent by the decanonized block
+		for hash in self.spent_transactions(number) {
+			if self.transaction_meta(&hash).map_or(false, |meta| meta.is_fully_spent()) {
+				update.delete(Key::Transaction(hash));
+			}
+		}
+		update.delete(Key::SpentTransactions(number));
+
+		if let Some(size) = self.stored_blocks_size() {
+			update.insert(KeyValue::Meta(KEY_STORED_BLOCKS_SIZE, serialize(&size.saturating_sub(block.size_with_witness() as u64))));
+		}
+		update.insert(KeyValue::Meta(KEY_PRUNED_BLOCKS, serialize(&(number + 1))));
+
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+
+	/// Prunes canon blocks below given height. Returns number of pruned blocks.
+	fn prune_blocks_below(&self, best_block: &BestBlock, height: u32) -> Result<u32, Error> {
+		// recent blocks may be decanonized => they are never pruned
+		let height = min(height, (best_block.number + 1).saturating_sub(MIN_BLOCKS_TO_KEEP));
+		let pruned_blocks = self.pruned_blocks();
+		for number in pruned_blocks..height {
+			self.prune_block(best_block, number)?;
+		}
+
+		Ok(max(pruned_blocks, height))
+	}
+
+	/// Prunes the oldest canon blocks until size of stored blocks fits the target. Returns number of pruned blocks.
+	fn prune_to_target(&self, best_block: &BestBlock, target: u64) -> Result<u32, Error> {
+		let max_pruned_blocks = (best_block.number + 1).saturating_sub(MIN_BLOCKS_TO_KEEP);
+		let mut pruned_blocks = self.pruned_blocks();
+		while pruned_blocks < max_pruned_blocks && self.stored_blocks_size().map_or(false, |size| size > target) {
+			self.prune_block(best_block, pruned_blocks)?;
+			pruned_blocks += 1;
+		}
+
+		Ok(pruned_blocks)
+	}
+
+	/// Computes size of the stored canon blocks, if it isn't known yet, and prunes blocks to the target.
+	/// Returns number of pruned blocks.
+	pub fn init_pruning(&self) -> Result<u32, Error> {
+		let best_block = self.best_block.write();
+		if !self.prune.is_enabled() || self.block_hash(best_block.number).is_none() {
+			return Ok(self.pruned_blocks());
+		}
+
+		if self.stored_blocks_size().is_none() {
+			let size = (self.pruned_blocks()..best_block.number + 1)
+				.filter_map(|number| self.block_bytes(number.into()))
+				.fold(0u64, |size, block| size + block.len() as u64);
+			let mut update = DBTransaction::new();
+			update.insert(KeyValue::Meta(KEY_STORED_BLOCKS_SIZE, serialize(&size)));
+			self.db.write(update).map_err(Error::DatabaseError)?;
+		}
+
+		match self.prune {
+			PruneMode::Target(target) => self.prune_to_target(&best_block, target),
+			_ => Ok(self.pruned_blocks()),
+		}
+	}
+
 	pub fn decanonize(&self) -> Result<H256, Error> {
 		let mut best_block = self.best_block.write();
 		let block = match self.indexed_block(best_block.hash.clone().into()) {
@@ -364,15 +1150,116 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			update.insert(KeyValue::TransactionMeta(hash, meta));
 		}
 
-		for tx in block.transactions {
-			update.delete(Key::TransactionMeta(tx.hash));
+		for tx in &block.transactions {
+			update.delete(Key::TransactionMeta(tx.hash.clone()));
+		}
+
+		if let Some(size) = self.stored_blocks_size() {
+			update.insert(KeyValue::Meta(KEY_STORED_BLOCKS_SIZE, serialize(&size.saturating_sub(block.size_with_witness() as u64))));
+		}
+
+		// spent outputs are restored from the undo data, so bodies of the spent transactions aren't read
+		let undo = self.canon_block_undo(&block)?;
+		update.delete(Key::BlockUndo(block_hash.clone()));
+
+		// indexed block is removed from the index, even if index is disabled now
+		if self.address_index_blocks() == block_number + 1 {
+			self.unindex_block_addresses(&block, block_number, &undo, &mut update)?;
+		}
+
+		if self.transaction_index_blocks() == block_number + 1 {
+			self.unindex_block_transactions(&block, block_number, &mut update);
 		}
 
 		self.db.write(update).map_err(Error::DatabaseError)?;
+		if let Some(ref utxo_cache) = self.utxo_cache {
+			utxo_cache.decanonize(&block, &undo);
+		}
 		*best_block = new_best_block;
 		Ok(block_hash)
 	}
 
+	/// Returns route from the block with given hash (inclusive) back to the canon chain (exclusive)
+	/// along with the number of canon block, where route starts. Number is None, if stored ancestors
+	/// of the block are not connected to the canon chain.
+	fn side_chain_route(&self, hash: &H256) -> (Vec<H256>, Option<u32>) {
+		let mut route = Vec::new();
+		let mut hash = hash.clone();
+		loop {
+			if let Some(number) = self.block_number(&hash) {
+				return (route, Some(number));
+			}
+
+			match self.block_header(hash.clone().into()) {
+				Some(header) => {
+					route.push(hash);
+					hash = header.previous_header_hash;
+				},
+				None => return (route, None),
+			}
+		}
+	}
+
+	fn write_invalid_blocks(&self, invalid_blocks: Vec<H256>) -> Result<(), Error> {
+		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Meta(KEY_INVALID_BLOCKS, serialize(&List::from(invalid_blocks))));
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+
+	/// Switches canon chain to the valid side chain with the most work, if it has more work than the canon chain.
+	fn activate_best_chain(&self, reorganization: ChainReorganization) -> Result<ChainReorganization, Error> {
+		let invalid_blocks = self.invalid_blocks();
+		let best_block = self.best_block();
+		let mut best_route: Option<(u32, Vec<H256>)> = None;
+		let mut best_work = self.chain_work(&best_block.hash).unwrap_or_default();
+		for tip in self.chain_tips() {
+			let (route, ancestor) = match self.side_chain_route(&tip) {
+				(route, Some(ancestor)) => (route, ancestor),
+				(_, None) => continue,
+			};
+			let work = self.chain_work(&tip).unwrap_or_default();
+			if work <= best_work || route.iter().any(|hash| invalid_blocks.contains(hash)) {
+				continue;
+			}
+
+			best_work = work;
+			best_route = Some((ancestor, route));
+		}
+
+		match best_route {
+			Some((ancestor, route)) => self.switch_to_side_chain(ancestor, route, reorganization),
+			None => Ok(reorganization),
+		}
+	}
+
+	/// Switches canon chain to the side chain, given by the route (starting with the side chain tip)
+	/// and the number of its canon ancestor.
+	fn switch_to_side_chain(&self, ancestor: u32, route: Vec<H256>, mut reorganization: ChainReorganization) -> Result<ChainReorganization, Error> {
+		let best_block = self.best_block();
+		let block_number = ancestor + route.len() as u32;
+		let origin = SideChainOrigin {
+			ancestor: ancestor,
+			canonized_route: route.into_iter().rev().collect(),
+			decanonized_route: (ancestor + 1..best_block.number + 1)
+				.filter_map(|decanonized_bn| self.block_hash(decanonized_bn))
+				.collect(),
+			block_number: block_number,
+		};
+		reorganization.decanonized.extend(origin.decanonized_route.iter().rev().cloned());
+		reorganization.canonized.extend(origin.canonized_route.iter().cloned());
+
+		let fork = self.fork(origin)?;
+		self.switch_to_fork(fork)?;
+		Ok(reorganization)
+	}
+
+	/// Outputs, cached before the fork is applied, may be spent on the fork.
+	fn clear_utxo_cache(&self) {
+		if let Some(ref utxo_cache) = self.utxo_cache {
+			utxo_cache.clear();
+		}
+	}
+
 	fn get(&self, key: Key) -> Option<Value> {
 		self.db.get(&key).expect("db value to be fine").into_option()
 	}
@@ -383,6 +1270,12 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			BlockRef::Hash(h) => Some(h),
 		}
 	}
+
+	/// Resolves hash of the block, which transactions are stored. Hashes of pruned blocks are not resolved.
+	fn resolve_stored_hash(&self, block_ref: BlockRef) -> Option<H256> {
+		self.resolve_hash(block_ref)
+			.and_then(|hash| self.get(Key::BlockTransactions(hash.clone())).map(|_| hash))
+	}
 }
 
 impl<T> BlockHeaderProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
@@ -409,7 +1302,7 @@ impl<T> BlockProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
 	}
 
 	fn block(&self, block_ref: BlockRef) -> Option<Block> {
-		self.resolve_hash(block_ref)
+		self.resolve_stored_hash(block_ref)
 			.and_then(|block_hash| {
 				self.block_header(block_hash.clone().into())
 					.map(|header| {
@@ -419,6 +1312,19 @@ impl<T> BlockProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
 			})
 	}
 
+	fn block_bytes(&self, block_ref: BlockRef) -> Option<Bytes> {
+		self.resolve_stored_hash(block_ref)
+			.and_then(|block_hash| {
+				self.block_header(block_hash.clone().into())
+					.map(|header| {
+						let transactions = self.block_transactions(block_hash.into());
+						let mut stream = Stream::with_flags(SERIALIZE_TRANSACTION_WITNESS);
+						stream.append(&header).append_list(&transactions);
+						stream.out()
+					})
+			})
+	}
+
 	fn contains_block(&self, block_ref: BlockRef) -> bool {
 		self.resolve_hash(block_ref)
 			.and_then(|hash| self.get(Key::BlockHeader(hash)))
@@ -453,7 +1359,7 @@ impl<T> IndexedBlockProvider for BlockChainDatabase<T> where T: KeyValueDatabase
 	}
 
 	fn indexed_block(&self, block_ref: BlockRef) -> Option<IndexedBlock> {
-		self.resolve_hash(block_ref)
+		self.resolve_stored_hash(block_ref)
 			.and_then(|block_hash| {
 				self.indexed_block_header(block_hash.clone().into())
 					.map(|header| {
@@ -495,6 +1401,12 @@ impl<T> TransactionProvider for BlockChainDatabase<T> where T: KeyValueDatabase
 
 impl<T> TransactionOutputProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn transaction_output(&self, prevout: &OutPoint, _transaction_index: usize) -> Option<TransactionOutput> {
+		if let Some(ref utxo_cache) = self.utxo_cache {
+			if let Some(output) = utxo_cache.output(prevout) {
+				return Some(output);
+			}
+		}
+
 		// return previous transaction outputs only for canon chain transactions
 		self.transaction_meta(&prevout.hash)
 			.and_then(|_| self.transaction(&prevout.hash))
@@ -502,6 +1414,13 @@ impl<T> TransactionOutputProvider for BlockChainDatabase<T> where T: KeyValueDat
 	}
 
 	fn is_spent(&self, prevout: &OutPoint) -> bool {
+		// cache only contains unspent outputs
+		if let Some(ref utxo_cache) = self.utxo_cache {
+			if utxo_cache.output(prevout).is_some() {
+				return false;
+			}
+		}
+
 		self.transaction_meta(&prevout.hash)
 			.and_then(|meta| meta.is_spent(prevout.index as usize))
 			.unwrap_or(false)
@@ -517,6 +1436,10 @@ impl<T> BlockChain for BlockChainDatabase<T> where T: KeyValueDatabase {
 		BlockChainDatabase::rollback_best(self)
 	}
 
+	fn rollback_to(&self, block_number: u32) -> Result<H256, Error> {
+		BlockChainDatabase::rollback_to(self, block_number)
+	}
+
 	fn canonize(&self, block_hash: &H256) -> Result<(), Error> {
 		BlockChainDatabase::canonize(self, block_hash)
 	}
@@ -542,6 +1465,7 @@ impl<T> Forkable for BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn switch_to_fork<'a>(&self, fork: Box<ForkChain + 'a>) -> Result<(), Error> {
 		let mut best_block = self.best_block.write();
 		*best_block = fork.store().best_block();
+		self.clear_utxo_cache();
 		fork.flush()
 	}
 }
@@ -550,6 +1474,437 @@ impl<T> CanonStore for BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn as_store(&self) -> &Store {
 		&*self
 	}
+
+	fn flush(&self) -> Result<(), Error> {
+		self.db.flush().map_err(Error::DatabaseError)
+	}
+
+	fn utxo_snapshot<'a>(&'a self) -> Result<UtxoSnapshot<'a>, Error> {
+		let snapshot = self.db.snapshot().ok_or_else(|| Error::DatabaseError("Database doesn't support snapshots".into()))?;
+		let best_block = read_best_block(|key| snapshot.get(key)).unwrap_or_default();
+		Ok(UtxoSnapshot::new(best_block, snapshot))
+	}
+
+	fn load_utxo_snapshot(&self, snapshot: &mut io::Read) -> Result<BestBlock, Error> {
+		let mut best_block = self.best_block.write();
+		if best_block.number != 0 || self.block_hash(0).is_none() {
+			return Err(Error::InvalidSnapshot("Snapshot can only be loaded into the database, which only contains the genesis block".into()));
+		}
+
+		let mut snapshot = UtxoSnapshotReader::new(snapshot)?;
+		let base_block = snapshot.metadata().base_block.clone();
+
+		let mut update = DBTransaction::new();
+		let mut number = 0;
+		let mut chain_work = U256::zero();
+		while let Some(header) = snapshot.read_header()? {
+			chain_work = chain_work + header.raw.bits.block_work();
+			if number == 0 {
+				if header.hash != best_block.hash {
+					return Err(Error::InvalidSnapshot("Snapshot is created for the chain with other genesis block".into()));
+				}
+			} else {
+				update.insert(KeyValue::ChainWork(header.hash.clone(), chain_work.clone()));
+				update.insert(KeyValue::BlockHash(number, header.hash.clone()));
+				update.insert(KeyValue::BlockNumber(header.hash.clone(), number));
+				update.insert(KeyValue::BlockHeader(header.hash, header.raw));
+			}
+
+			number += 1;
+			if update.operations.len() >= SNAPSHOT_LOAD_BATCH_SIZE {
+				self.db.write(mem::replace(&mut update, DBTransaction::new())).map_err(Error::DatabaseError)?;
+			}
+		}
+
+		// outputs are ordered by transaction hash => outputs of the same transaction follow each other
+		let mut outputs: Vec<UnspentOutput> = Vec::new();
+		loop {
+			let output = snapshot.read_output()?;
+			let is_next_transaction = match (outputs.last(), output.as_ref()) {
+				(Some(last), Some(output)) => last.outpoint.hash != output.outpoint.hash,
+				(Some(_), None) => true,
+				(None, _) => false,
+			};
+
+			if is_next_transaction {
+				let (transaction, meta) = snapshot_transaction(&outputs)?;
+				let hash = outputs[0].outpoint.hash.clone();
+				if let Some(ref output) = output {
+					if output.outpoint.hash[..] < hash[..] {
+						return Err(Error::InvalidSnapshot("Outputs are not ordered by transaction hash".into()));
+					}
+				}
+
+				update.insert(KeyValue::Transaction(hash.clone(), transaction));
+				update.insert(KeyValue::TransactionMeta(hash, meta));
+				outputs.clear();
+				if update.operations.len() >= SNAPSHOT_LOAD_BATCH_SIZE {
+					self.db.write(mem::replace(&mut update, DBTransaction::new())).map_err(Error::DatabaseError)?;
+				}
+			}
+
+			match output {
+				Some(output) => outputs.push(output),
+				None => break,
+			}
+		}
+
+		// blocks below the base block are never available => they are treated as pruned
+		update.insert(KeyValue::Meta(KEY_PRUNED_BLOCKS, serialize(&(base_block.number + 1))));
+		update.insert(KeyValue::Meta(KEY_BEST_BLOCK_HASH, serialize(&base_block.hash)));
+		update.insert(KeyValue::Meta(KEY_BEST_BLOCK_NUMBER, serialize(&base_block.number)));
+		self.db.write(update).map_err(Error::DatabaseError)?;
+
+		*best_block = base_block.clone();
+		self.clear_utxo_cache();
+		Ok(base_block)
+	}
+}
+
+/// Builds header tree entry of the stored block, which parent entry is given (None for genesis block).
+fn stored_header_entry(header: BlockHeader, parent: Option<&HeaderEntry>) -> HeaderEntry {
+	HeaderEntry {
+		number: parent.map_or(0, |parent| parent.number + 1),
+		chain_work: parent.map_or(U256::zero(), |parent| parent.chain_work) + header.bits.block_work(),
+		header: header,
+		status: HeaderStatus {
+			has_block: true,
+			invalid: false,
+		},
+	}
+}
+
+/// Builds transaction record from its unspent outputs of the snapshot. Inputs of the transaction are
+/// unknown, outputs, which are spent, are replaced by empty outputs.
+fn snapshot_transaction(outputs: &[UnspentOutput]) -> Result<(Transaction, TransactionMeta), Error> {
+	let first = &outputs[0];
+	let outputs_len = outputs[outputs.len() - 1].outpoint.index as usize + 1;
+	let mut transaction = Transaction::default();
+	transaction.outputs = vec![TransactionOutput::default(); outputs_len];
+	let mut meta = if first.is_coinbase {
+		TransactionMeta::new_coinbase(first.height, outputs_len)
+	} else {
+		TransactionMeta::new(first.height, outputs_len)
+	};
+	for index in 0..outputs_len {
+		meta.denote_used(index);
+	}
+
+	for (i, output) in outputs.iter().enumerate() {
+		let is_ordered = i == 0 || outputs[i - 1].outpoint.index < output.outpoint.index;
+		if !is_ordered || output.height != first.height || output.is_coinbase != first.is_coinbase {
+			return Err(Error::InvalidSnapshot(format!("Inconsistent outputs of the transaction {}", first.outpoint.hash.to_reversed_str())));
+		}
+
+		let index = output.outpoint.index as usize;
+		transaction.outputs[index] = output.output.clone();
+		meta.denote_unused(index);
+	}
+
+	Ok((transaction, meta))
+}
+
+impl<T> BlockFilterProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn is_cfindex_enabled(&self) -> bool {
+		self.cfindex
+	}
+
+	fn block_filter(&self, block_hash: &H256) -> Option<Bytes> {
+		self.get(Key::BlockFilter(block_hash.clone()))
+			.and_then(Value::as_block_filter)
+	}
+
+	fn block_filter_header(&self, block_hash: &H256) -> Option<H256> {
+		self.get(Key::BlockFilterHeader(block_hash.clone()))
+			.and_then(Value::as_block_filter_header)
+	}
+}
+
+impl<T> AddressIndexProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn is_address_index_enabled(&self) -> bool {
+		self.address_index
+	}
+
+	fn address_outputs(&self, script_hash: &H256) -> Vec<AddressOutput> {
+		let mut outputs: Vec<AddressOutput> = self.db.address_index(script_hash)
+			.expect("db value to be fine")
+			.into_iter()
+			.filter_map(|(_, output)| output.into_option())
+			.collect();
+		outputs.sort_by(|a, b| (a.height, &a.transaction_hash, a.index).cmp(&(b.height, &b.transaction_hash, b.index)));
+		outputs
+	}
+}
+
+impl<T> ChainStatsProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn chain_tips(&self) -> Vec<H256> {
+		self.get(Key::Meta(KEY_CHAIN_TIPS))
+			.and_then(Value::as_meta)
+			.map(|tips| deserialize::<_, List<H256>>(&*tips).expect("Inconsistent DB. Invalid chain tips.").into())
+			.unwrap_or_default()
+	}
+
+	fn chain_tx_count(&self, hash: &H256) -> Option<u64> {
+		self.get(Key::ChainTxCount(hash.clone()))
+			.and_then(Value::as_chain_tx_count)
+	}
+
+	fn chain_work(&self, hash: &H256) -> Option<U256> {
+		// work of the blocks without stored work is added to the work of the nearest ancestor with stored work
+		let mut work = U256::zero();
+		let mut hash = hash.clone();
+		loop {
+			if let Some(chain_work) = self.get(Key::ChainWork(hash.clone())).and_then(Value::as_chain_work) {
+				return Some(chain_work + work);
+			}
+
+			let header = match self.block_header(hash.into()) {
+				Some(header) => header,
+				None => return None,
+			};
+			work = work + header.bits.block_work();
+			if header.previous_header_hash.is_zero() {
+				return Some(work);
+			}
+			hash = header.previous_header_hash;
+		}
+	}
+}
+
+impl<T> BlockInvalidation for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn invalid_blocks(&self) -> Vec<H256> {
+		self.get(Key::Meta(KEY_INVALID_BLOCKS))
+			.and_then(Value::as_meta)
+			.map(|blocks| deserialize::<_, List<H256>>(&*blocks).expect("Inconsistent DB. Invalid blocks list.").into())
+			.unwrap_or_default()
+	}
+
+	fn is_block_invalid(&self, hash: &H256) -> bool {
+		let invalid_blocks = self.invalid_blocks();
+		if invalid_blocks.is_empty() {
+			return false;
+		}
+
+		// marked blocks are never canon, so only side chain ancestors are checked
+		let (route, _) = self.side_chain_route(hash);
+		route.iter().any(|hash| invalid_blocks.contains(hash))
+	}
+
+	fn invalidate_block(&self, hash: &H256) -> Result<ChainReorganization, Error> {
+		let header = self.block_header(hash.clone().into()).ok_or(Error::UnknownBlock)?;
+		if header.previous_header_hash.is_zero() {
+			return Err(Error::CannotInvalidateGenesis);
+		}
+
+		let mut invalid_blocks = self.invalid_blocks();
+		if !invalid_blocks.contains(hash) {
+			invalid_blocks.push(hash.clone());
+			self.write_invalid_blocks(invalid_blocks)?;
+		}
+
+		let mut reorganization = ChainReorganization::default();
+		if let Some(number) = self.block_number(hash) {
+			while self.best_block().number >= number {
+				reorganization.decanonized.push(self.decanonize()?);
+			}
+		}
+
+		self.activate_best_chain(reorganization)
+	}
+
+	fn reconsider_block(&self, hash: &H256) -> Result<ChainReorganization, Error> {
+		if self.block_header(hash.clone().into()).is_none() {
+			return Err(Error::UnknownBlock);
+		}
+
+		// side chain route of the block contains the block itself and all its marked ancestors
+		let (route, _) = self.side_chain_route(hash);
+		let canon_number = self.block_number(hash);
+		let invalid_blocks = self.invalid_blocks();
+		let remaining_blocks = invalid_blocks.iter()
+			.filter(|invalid| {
+				if route.contains(invalid) {
+					return false;
+				}
+
+				let (invalid_route, invalid_ancestor) = self.side_chain_route(invalid);
+				let is_descendant = invalid_route.contains(hash) || match (canon_number, invalid_ancestor) {
+					(Some(number), Some(ancestor)) => ancestor >= number,
+					_ => false,
+				};
+				!is_descendant
+			})
+			.cloned()
+			.collect::<Vec<_>>();
+
+		if remaining_blocks.len() != invalid_blocks.len() {
+			self.write_invalid_blocks(remaining_blocks)?;
+		}
+
+		self.activate_best_chain(ChainReorganization::default())
+	}
+
+	fn precious_block(&self, hash: &H256) -> Result<ChainReorganization, Error> {
+		if self.block_header(hash.clone().into()).is_none() {
+			return Err(Error::UnknownBlock);
+		}
+
+		// canon blocks and blocks of the chains with less work are ignored. Valid chain with more work is always canon
+		let (route, ancestor) = self.side_chain_route(hash);
+		let ancestor = match ancestor {
+			Some(ancestor) if !route.is_empty() => ancestor,
+			_ => return Ok(ChainReorganization::default()),
+		};
+		if self.chain_work(hash) != self.chain_work(&self.best_block().hash) || self.is_block_invalid(hash) {
+			return Ok(ChainReorganization::default());
+		}
+
+		self.switch_to_side_chain(ancestor, route, ChainReorganization::default())
+	}
+}
+
+impl<T> HeaderTree for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn header_entry(&self, hash: &H256) -> Option<HeaderEntry> {
+		self.get(Key::HeaderEntry(hash.clone()))
+			.and_then(Value::as_header_entry)
+			.map(|mut entry| {
+				entry.status.invalid = entry.status.invalid || (entry.status.has_block && self.is_block_invalid(hash));
+				entry
+			})
+	}
+
+	fn header_tips(&self) -> Vec<H256> {
+		self.get(Key::Meta(KEY_HEADER_TIPS))
+			.and_then(Value::as_meta)
+			.map(|tips| deserialize::<_, List<H256>>(&*tips).expect("Inconsistent DB. Invalid header tips.").into())
+			.unwrap_or_default()
+	}
+
+	fn best_header_entry(&self) -> HeaderEntry {
+		let mut best_entry = self.header_entry(&self.best_block().hash).expect("best block header should be in db; qed");
+		for tip in self.header_tips() {
+			if let Some(entry) = self.header_entry(&tip) {
+				if !entry.status.invalid && entry.chain_work > best_entry.chain_work {
+					best_entry = entry;
+				}
+			}
+		}
+		best_entry
+	}
+
+	fn insert_headers(&self, headers: &[IndexedBlockHeader]) -> Result<(), Error> {
+		let mut header_tips = self.header_tips();
+		let mut inserted: HashMap<H256, HeaderEntry> = HashMap::new();
+		let mut update = DBTransaction::new();
+		for header in headers {
+			if inserted.contains_key(&header.hash) || self.header_entry(&header.hash).is_some() {
+				continue;
+			}
+
+			let parent_hash = &header.raw.previous_header_hash;
+			let parent = match inserted.get(parent_hash).cloned().or_else(|| self.header_entry(parent_hash)) {
+				Some(parent) => parent,
+				None => return Err(Error::UnknownParent),
+			};
+			if parent.status.invalid {
+				return Err(Error::InvalidParent);
+			}
+
+			let entry = HeaderEntry {
+				header: header.raw.clone(),
+				number: parent.number + 1,
+				chain_work: parent.chain_work + header.raw.bits.block_work(),
+				status: HeaderStatus::default(),
+			};
+			header_tips.retain(|tip| tip != parent_hash);
+			header_tips.push(header.hash.clone());
+			update.insert(KeyValue::HeaderEntry(header.hash.clone(), entry.clone()));
+			inserted.insert(header.hash.clone(), entry);
+		}
+
+		if inserted.is_empty() {
+			return Ok(());
+		}
+
+		update.insert(KeyValue::Meta(KEY_HEADER_TIPS, serialize(&List::from(header_tips))));
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+
+	fn invalidate_header(&self, hash: &H256) -> Result<(), Error> {
+		let entry = self.header_entry(hash).ok_or(Error::UnknownBlock)?;
+		if entry.header.previous_header_hash.is_zero() {
+			return Err(Error::CannotInvalidateGenesis);
+		}
+
+		// descendants are found by walking back from the header tips to the height of the header
+		let number = entry.number;
+		let mut invalidated = vec![(hash.clone(), entry)];
+		for tip in self.header_tips() {
+			let mut route = Vec::new();
+			let mut next_hash = tip;
+			while let Some(next_entry) = self.header_entry(&next_hash) {
+				if next_entry.number <= number {
+					if next_hash == *hash {
+						invalidated.extend(route);
+					}
+					break;
+				}
+
+				let parent_hash = next_entry.header.previous_header_hash.clone();
+				route.push((next_hash, next_entry));
+				next_hash = parent_hash;
+			}
+		}
+
+		let mut update = DBTransaction::new();
+		for (hash, mut entry) in invalidated {
+			entry.status.invalid = true;
+			update.insert(KeyValue::HeaderEntry(hash, entry));
+		}
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+}
+
+impl<T> TransactionIndexProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn is_transaction_index_enabled(&self) -> bool {
+		self.transaction_index
+	}
+
+	fn transaction_location(&self, hash: &H256) -> Option<TransactionLocation> {
+		// index, built concurrently with the reorganization, may still reference decanonized block
+		self.get(Key::TransactionIndex(hash.clone()))
+			.and_then(Value::as_transaction_index)
+			.and_then(|location| self.block_number(&location.block_hash).map(|_| location))
+	}
+}
+
+impl<T> BlockPruning for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn is_prune_enabled(&self) -> bool {
+		self.prune.is_enabled()
+	}
+
+	fn pruned_blocks(&self) -> u32 {
+		self.get(Key::Meta(KEY_PRUNED_BLOCKS))
+			.and_then(Value::as_meta)
+			.map(|blocks| deserialize(&*blocks).expect("Inconsistent DB. Invalid pruned blocks number."))
+			.unwrap_or_default()
+	}
+
+	fn prune_blocks(&self, height: u32) -> Result<u32, Error> {
+		let best_block = self.best_block.write();
+		if !self.prune.is_enabled() {
+			return Ok(self.pruned_blocks());
+		}
+
+		self.prune_blocks_below(&best_block, height)
+	}
+}
+
+impl<T> BlockUndoProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn block_undo(&self, hash: &H256) -> Option<BlockUndo> {
+		self.get(Key::BlockUndo(hash.clone()))
+			.and_then(Value::as_block_undo)
+	}
 }
 
 impl<T> Store for BlockChainDatabase<T> where T: KeyValueDatabase {
@@ -587,3 +1942,18 @@ impl<T> ConfigStore for BlockChainDatabase<T> where T: KeyValueDatabase {
 		self.db.write(update).map_err(Error::DatabaseError)
 	}
 }
+
+impl<T> WalletStore for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn wallet_record(&self, name: &'static str) -> Option<Bytes> {
+		self.get(Key::Wallet(name))
+			.and_then(Value::as_wallet)
+	}
+
+	fn set_wallet_records(&self, records: Vec<(&'static str, Bytes)>) -> Result<(), Error> {
+		let mut update = DBTransaction::new();
+		for (name, record) in records {
+			update.insert(KeyValue::Wallet(name, record));
+		}
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+}
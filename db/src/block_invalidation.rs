@@ -0,0 +1,42 @@
+//! Manual blocks invalidation.
+//!
+//! Operator may mark any stored block (except genesis) invalid. Hashes of marked blocks are kept
+//! in the meta column. Marks aren't copied to descendants: block is invalid, if it is marked itself
+//! or if any of its ancestors is marked. Marked blocks are never canon, so only side chain ancestors
+//! are checked. Whenever blocks are marked or unmarked, the valid chain with the most work becomes canon.
+//!
+//! Of the chains with the same work, the chain, which has been seen first, stays canon. Operator
+//! may override this choice by declaring side chain block precious.
+
+use hash::H256;
+use Error;
+
+/// Changes of the canon chain, made by invalidating, reconsidering or preferring blocks.
+#[derive(Debug, Default, PartialEq)]
+pub struct ChainReorganization {
+	/// Hashes of decanonized blocks, starting with the old best block
+	pub decanonized: Vec<H256>,
+	/// Hashes of canonized blocks, ending with the new best block
+	pub canonized: Vec<H256>,
+}
+
+/// Manual blocks invalidation interface
+pub trait BlockInvalidation {
+	/// get hashes of blocks, marked invalid
+	fn invalid_blocks(&self) -> Vec<H256>;
+
+	/// is block marked invalid or does it descend from the marked block?
+	fn is_block_invalid(&self, hash: &H256) -> bool;
+
+	/// mark block invalid. If block is canon, canon chain is rolled back to its parent.
+	/// Then canon chain is switched to the valid chain with the most work
+	fn invalidate_block(&self, hash: &H256) -> Result<ChainReorganization, Error>;
+
+	/// remove marks of the block, its ancestors and descendants. Then canon chain is switched
+	/// to the valid chain with the most work
+	fn reconsider_block(&self, hash: &H256) -> Result<ChainReorganization, Error>;
+
+	/// treat block as if it has been seen before the canon block with the same chain work. If block is
+	/// valid side chain block and its chain has as much work as the canon chain, canon chain is switched to it
+	fn precious_block(&self, hash: &H256) -> Result<ChainReorganization, Error>;
+}
@@ -22,6 +22,9 @@ pub trait BlockProvider: BlockHeaderProvider {
 	/// resolves deserialized block body by block reference (number/hash)
 	fn block(&self, block_ref: BlockRef) -> Option<Block>;
 
+	/// resolves serialized block (with transactions witness) by block reference (number/hash)
+	fn block_bytes(&self, block_ref: BlockRef) -> Option<Bytes>;
+
 	/// returns true if store contains given block
 	fn contains_block(&self, block_ref: BlockRef) -> bool {
 		self.block_header_bytes(block_ref).is_some()
@@ -0,0 +1,102 @@
+//! Undo data of the canon blocks.
+//!
+//! Outputs, spent by the block, are stored when the block is canonized. When the block is
+//! decanonized, spent outputs are restored from the undo data instead of being read from
+//! bodies of the spent transactions. Undo data is deleted when the block is decanonized or pruned.
+
+use std::io;
+use chain::TransactionOutput;
+use hash::H256;
+use ser::{Serializable, Deserializable, Error as ReaderError, Stream, Reader};
+
+/// Output, spent by the canon block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpentOutput {
+	/// The spent output
+	pub output: TransactionOutput,
+	/// Height of the block, which includes transaction of the output
+	pub height: u32,
+	/// Is output created by the coinbase transaction?
+	pub is_coinbase: bool,
+}
+
+/// Undo data of the canon block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockUndo {
+	/// Outputs, spent by inputs of the block non-coinbase transactions, in the same order
+	pub spent_outputs: Vec<SpentOutput>,
+}
+
+/// Block undo data provider
+pub trait BlockUndoProvider {
+	/// get undo data of the canon block, if it is stored
+	fn block_undo(&self, hash: &H256) -> Option<BlockUndo>;
+}
+
+impl Serializable for SpentOutput {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.output)
+			.append(&self.height)
+			.append(&self.is_coinbase);
+	}
+}
+
+impl Deserializable for SpentOutput {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(SpentOutput {
+			output: reader.read()?,
+			height: reader.read()?,
+			is_coinbase: reader.read()?,
+		})
+	}
+}
+
+impl Serializable for BlockUndo {
+	fn serialize(&self, stream: &mut Stream) {
+		stream.append_list(&self.spent_outputs);
+	}
+}
+
+impl Deserializable for BlockUndo {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(BlockUndo {
+			spent_outputs: reader.read_list()?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::TransactionOutput;
+	use ser::{serialize, deserialize};
+	use super::{BlockUndo, SpentOutput};
+
+	#[test]
+	fn test_block_undo_serialization() {
+		let undo = BlockUndo {
+			spent_outputs: vec![
+				SpentOutput {
+					output: TransactionOutput {
+						value: 50,
+						script_pubkey: "51".into(),
+					},
+					height: 1,
+					is_coinbase: true,
+				},
+				SpentOutput {
+					output: TransactionOutput {
+						value: 10,
+						script_pubkey: "".into(),
+					},
+					height: 100,
+					is_coinbase: false,
+				},
+			],
+		};
+
+		let serialized = serialize(&undo);
+		assert_eq!(deserialize::<_, BlockUndo>(serialized.as_ref()).unwrap(), undo);
+		assert_eq!(deserialize::<_, BlockUndo>(serialize(&BlockUndo::default()).as_ref()).unwrap(), BlockUndo::default());
+	}
+}
@@ -0,0 +1,234 @@
+//! BIP158 compact block filters index.
+//!
+//! When index is enabled, basic filter and filter header are computed for every canonized block
+//! and stored by block hash. Filter header commits to the header of the parent block filter,
+//! so filters of the whole chain must be indexed before new blocks can be indexed.
+
+use bytes::Bytes;
+use hash::H256;
+use chain::IndexedBlock;
+use crypto::{dhash256, siphash24};
+use ser::{Reader, Serializable, Stream, CompactInteger};
+
+/// Type of the basic filter.
+pub const BASIC_FILTER_TYPE: u8 = 0;
+/// Golomb-Rice coding parameter of the basic filter.
+const BASIC_FILTER_P: u8 = 19;
+/// Inverse of the basic filter false positive rate.
+const BASIC_FILTER_M: u64 = 784931;
+
+/// Compact block filters storage interface
+pub trait BlockFilterProvider {
+	/// Is compact block filters index enabled?
+	fn is_cfindex_enabled(&self) -> bool;
+
+	/// get basic filter of the block
+	fn block_filter(&self, block_hash: &H256) -> Option<Bytes>;
+
+	/// get header of the basic filter of the block
+	fn block_filter_header(&self, block_hash: &H256) -> Option<H256>;
+}
+
+/// Returns basic filter elements: all output scripts, except OP_RETURN ones, and scripts of all spent outputs.
+pub fn basic_filter_elements(block: &IndexedBlock, spent_scripts: Vec<Bytes>) -> Vec<Bytes> {
+	let mut elements: Vec<Bytes> = block.transactions.iter()
+		.flat_map(|tx| tx.raw.outputs.iter())
+		.map(|output| output.script_pubkey.clone())
+		.filter(|script| !script.is_empty() && script[0] != 0x6a)
+		.chain(spent_scripts.into_iter().filter(|script| !script.is_empty()))
+		.collect();
+	elements.sort_by(|a, b| a[..].cmp(&b[..]));
+	elements.dedup();
+	elements
+}
+
+/// Builds basic filter of the block with given elements. Filter is prefixed with number of elements.
+pub fn build_basic_filter(block_hash: &H256, elements: &[Bytes]) -> Bytes {
+	let mut values: Vec<u64> = elements.iter()
+		.map(|element| hash_to_range(block_hash, elements.len() as u64, element))
+		.collect();
+	values.sort();
+
+	let mut writer = BitWriter::default();
+	let mut last = 0;
+	for value in values {
+		let delta = value - last;
+		last = value;
+		for _ in 0..delta >> BASIC_FILTER_P {
+			writer.write(1, 1);
+		}
+		writer.write(0, 1);
+		writer.write(delta, BASIC_FILTER_P);
+	}
+
+	let mut stream = Stream::default();
+	stream.append(&CompactInteger::from(elements.len()));
+	stream.append_slice(&writer.bytes);
+	stream.out()
+}
+
+/// Returns true if filter matches any of given elements. Returns false if filter is malformed.
+pub fn basic_filter_match_any(block_hash: &H256, filter: &[u8], elements: &[Bytes]) -> bool {
+	let count: CompactInteger = match Reader::new(filter).read() {
+		Ok(count) => count,
+		Err(_) => return false,
+	};
+	let count: u64 = count.into();
+	if count == 0 || elements.is_empty() {
+		return false;
+	}
+
+	let mut queries: Vec<u64> = elements.iter()
+		.map(|element| hash_to_range(block_hash, count, element))
+		.collect();
+	queries.sort();
+
+	let mut reader = BitReader::new(&filter[CompactInteger::from(count).serialized_size()..]);
+	let mut value = 0;
+	let mut queries = queries.into_iter().peekable();
+	for _ in 0..count {
+		let mut quotient = 0u64;
+		loop {
+			match reader.read(1) {
+				Some(1) => quotient += 1,
+				Some(_) => break,
+				None => return false,
+			}
+		}
+		let remainder = match reader.read(BASIC_FILTER_P) {
+			Some(remainder) => remainder,
+			None => return false,
+		};
+		value += (quotient << BASIC_FILTER_P) + remainder;
+
+		while let Some(&query) = queries.peek() {
+			if query == value {
+				return true;
+			} else if query > value {
+				break;
+			}
+			queries.next();
+		}
+		if queries.peek().is_none() {
+			return false;
+		}
+	}
+
+	false
+}
+
+/// Hash of the filter.
+pub fn filter_hash(filter: &[u8]) -> H256 {
+	dhash256(filter)
+}
+
+/// Header of the filter, committing to the previous filter header.
+pub fn filter_header(filter: &[u8], previous_header: &H256) -> H256 {
+	let mut data = [0u8; 64];
+	data[0..32].copy_from_slice(&*filter_hash(filter));
+	data[32..64].copy_from_slice(&**previous_header);
+	dhash256(&data)
+}
+
+/// Maps element to [0, count * M) range, using SipHash keyed with the block hash.
+fn hash_to_range(block_hash: &H256, count: u64, element: &[u8]) -> u64 {
+	let key0 = read_u64_le(&block_hash[0..8]);
+	let key1 = read_u64_le(&block_hash[8..16]);
+	let hash = siphash24(key0, key1, element);
+	mul_high(hash, count * BASIC_FILTER_M)
+}
+
+fn read_u64_le(bytes: &[u8]) -> u64 {
+	bytes.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+/// Returns high 64 bits of 128-bit product.
+fn mul_high(a: u64, b: u64) -> u64 {
+	let (a_high, a_low) = (a >> 32, a & 0xffff_ffff);
+	let (b_high, b_low) = (b >> 32, b & 0xffff_ffff);
+	let low = a_low * b_low;
+	let middle1 = a_high * b_low;
+	let middle2 = a_low * b_high;
+	let carry = ((low >> 32) + (middle1 & 0xffff_ffff) + (middle2 & 0xffff_ffff)) >> 32;
+	a_high * b_high + (middle1 >> 32) + (middle2 >> 32) + carry
+}
+
+#[derive(Default)]
+struct BitWriter {
+	bytes: Vec<u8>,
+	/// Number of used bits in the last byte.
+	used: u8,
+}
+
+impl BitWriter {
+	/// Writes `bits` low bits of the value, most significant bit first.
+	fn write(&mut self, value: u64, bits: u8) {
+		for shift in (0..bits).rev() {
+			if self.used % 8 == 0 {
+				self.bytes.push(0);
+				self.used = 0;
+			}
+			let bit = ((value >> shift) & 1) as u8;
+			*self.bytes.last_mut().expect("byte is pushed above; qed") |= bit << (7 - self.used);
+			self.used += 1;
+		}
+	}
+}
+
+struct BitReader<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl<'a> BitReader<'a> {
+	fn new(bytes: &'a [u8]) -> Self {
+		BitReader {
+			bytes: bytes,
+			position: 0,
+		}
+	}
+
+	/// Reads `bits` bits, most significant bit first.
+	fn read(&mut self, bits: u8) -> Option<u64> {
+		let mut value = 0;
+		for _ in 0..bits {
+			let byte = match self.bytes.get(self.position / 8) {
+				Some(byte) => *byte,
+				None => return None,
+			};
+			value = (value << 1) | ((byte >> (7 - self.position % 8)) & 1) as u64;
+			self.position += 1;
+		}
+		Some(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use hash::H256;
+	use super::{build_basic_filter, basic_filter_match_any, filter_header};
+
+	const GENESIS_SCRIPT: &'static str = "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac";
+
+	// BIP158 test vector: testnet genesis block
+	#[test]
+	fn test_testnet_genesis_filter() {
+		let block_hash = H256::from_reversed_str("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943");
+		let filter = build_basic_filter(&block_hash, &[GENESIS_SCRIPT.into()]);
+		assert_eq!(filter, "019dfca8".into());
+		assert_eq!(filter_header(&filter, &H256::default()),
+			H256::from_reversed_str("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"));
+	}
+
+	#[test]
+	fn test_basic_filter_match_any() {
+		let block_hash = H256::from(1);
+		let elements: Vec<Bytes> = (0..100u8).map(|i| vec![i; 25].into()).collect();
+		let filter = build_basic_filter(&block_hash, &elements);
+		assert!(elements.iter().all(|element| basic_filter_match_any(&block_hash, &filter, &[element.clone()])));
+		assert!(basic_filter_match_any(&block_hash, &filter, &[vec![200; 25].into(), elements[50].clone()]));
+		assert!(!basic_filter_match_any(&block_hash, &filter, &[vec![200; 25].into()]));
+		assert!(!basic_filter_match_any(&block_hash, &build_basic_filter(&block_hash, &[]), &elements));
+	}
+}
@@ -0,0 +1,29 @@
+//! Statistics of the blocks tree.
+//!
+//! Hashes of all stored blocks without stored children (chain tips) are kept in the meta column.
+//! Tips are updated when blocks are inserted and when the best block is rolled back. Side chain
+//! blocks, inserted before tips have been introduced, aren't reported.
+//!
+//! Total number of transactions in the chain up to and including the block is stored, when the
+//! block is canonized. It isn't removed when the block is decanonized, because it only depends on
+//! the block ancestors. Numbers of blocks, canonized before it has been introduced, are unknown
+//! until the database is reindexed.
+//!
+//! Total work of the chain up to and including the block is stored, when the block is inserted,
+//! so it is known for both canon and side chain blocks. Work of blocks, inserted before it has been
+//! introduced, is summed up starting from the nearest ancestor with the stored work.
+
+use hash::H256;
+use primitives::bigint::U256;
+
+/// Blocks tree statistics interface
+pub trait ChainStatsProvider {
+	/// get hashes of all stored blocks, which have no stored children
+	fn chain_tips(&self) -> Vec<H256>;
+
+	/// get total number of transactions in the chain up to and including the block with given hash
+	fn chain_tx_count(&self, hash: &H256) -> Option<u64>;
+
+	/// get total work of the chain up to and including the block with given hash
+	fn chain_work(&self, hash: &H256) -> Option<U256>;
+}
@@ -8,6 +8,18 @@ pub enum Error {
 	UnknownParent,
 	/// Ancient fork
 	AncientFork,
+	/// Unspent outputs snapshot can't be written or loaded
+	InvalidSnapshot(String),
+	/// Block is not found
+	UnknownBlock,
+	/// Block descends from the block, marked invalid
+	InvalidParent,
+	/// Genesis block can't be marked invalid
+	CannotInvalidateGenesis,
+	/// Blocks, required to build the index, are pruned
+	PrunedBlocks,
+	/// Compact filter of the parent block isn't indexed
+	UnindexedParentFilter,
 }
 
 impl From<Error> for String {
@@ -17,6 +29,12 @@ impl From<Error> for String {
 			Error::CannotCanonize => "Cannot canonize block".into(),
 			Error::UnknownParent => "Block parent is unknown".into(),
 			Error::AncientFork => "Fork is too long to proceed".into(),
+			Error::InvalidSnapshot(s) => format!("Invalid UTXO snapshot: {}", s),
+			Error::UnknownBlock => "Block not found".into(),
+			Error::InvalidParent => "Block descends from the invalid block".into(),
+			Error::CannotInvalidateGenesis => "Genesis block can't be invalidated".into(),
+			Error::PrunedBlocks => "Blocks, required to build the index, are pruned".into(),
+			Error::UnindexedParentFilter => "Compact filter of the parent block isn't indexed".into(),
 		}
 	}
 }
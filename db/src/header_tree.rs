@@ -0,0 +1,136 @@
+//! Tree of verified block headers.
+//!
+//! Headers are stored separately from blocks, so that headers of the whole chain could be downloaded
+//! and verified before blocks (headers-first synchronization). Every entry of the tree keeps the header,
+//! its height, total work of the chain up to and including the header and status flags. Entries are
+//! written when verified headers are inserted and when blocks are inserted. Entries of blocks, stored
+//! before the tree has been introduced, are built once on startup by `BlockChainDatabase::build_header_tree`.
+//!
+//! Hashes of all headers without known children (header tips) are kept in the meta column.
+
+use std::io;
+use hash::H256;
+use primitives::bigint::U256;
+use chain::{BlockHeader, IndexedBlockHeader};
+use ser::{Serializable, Deserializable, Error as ReaderError, Stream, Reader};
+use Error;
+
+/// Header has been verified and the block has been stored.
+const HEADER_STATUS_HAS_BLOCK: u8 = 0x01;
+/// Header has been marked invalid.
+const HEADER_STATUS_INVALID: u8 = 0x02;
+
+/// Header tree storage interface
+pub trait HeaderTree {
+	/// get entry of the known header with given hash
+	fn header_entry(&self, hash: &H256) -> Option<HeaderEntry>;
+
+	/// get hashes of all known headers, which have no known children
+	fn header_tips(&self) -> Vec<H256>;
+
+	/// get entry of the valid header tip with the most work. Best block is returned if no tip has more work
+	fn best_header_entry(&self) -> HeaderEntry;
+
+	/// insert verified headers in the single database transaction. Every header must be the child of the
+	/// known valid header or of the preceding header. Already known headers are skipped
+	fn insert_headers(&self, headers: &[IndexedBlockHeader]) -> Result<(), Error>;
+
+	/// mark header and all its known descendants invalid
+	fn invalidate_header(&self, hash: &H256) -> Result<(), Error>;
+}
+
+/// Status flags of the header tree entry.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HeaderStatus {
+	/// Is block stored? Stays set when the block is pruned
+	pub has_block: bool,
+	/// Is header marked invalid or does it descend from the invalid header?
+	pub invalid: bool,
+}
+
+/// Entry of the header tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderEntry {
+	/// Block header
+	pub header: BlockHeader,
+	/// Height of the header
+	pub number: u32,
+	/// Total work of the chain up to and including the header
+	pub chain_work: U256,
+	/// Status flags
+	pub status: HeaderStatus,
+}
+
+impl Serializable for HeaderStatus {
+	fn serialize(&self, stream: &mut Stream) {
+		let mut flags = 0u8;
+		if self.has_block {
+			flags |= HEADER_STATUS_HAS_BLOCK;
+		}
+		if self.invalid {
+			flags |= HEADER_STATUS_INVALID;
+		}
+		stream.append(&flags);
+	}
+}
+
+impl Deserializable for HeaderStatus {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let flags: u8 = reader.read()?;
+		Ok(HeaderStatus {
+			has_block: flags & HEADER_STATUS_HAS_BLOCK != 0,
+			invalid: flags & HEADER_STATUS_INVALID != 0,
+		})
+	}
+}
+
+impl Serializable for HeaderEntry {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.header)
+			.append(&self.number)
+			.append(&self.chain_work)
+			.append(&self.status);
+	}
+}
+
+impl Deserializable for HeaderEntry {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(HeaderEntry {
+			header: reader.read()?,
+			number: reader.read()?,
+			chain_work: reader.read()?,
+			status: reader.read()?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use primitives::bigint::U256;
+	use hash::H256;
+	use chain::BlockHeader;
+	use ser::{serialize, deserialize};
+	use super::{HeaderEntry, HeaderStatus};
+
+	#[test]
+	fn test_header_entry_serialization() {
+		let entry = HeaderEntry {
+			header: BlockHeader {
+				version: 1,
+				previous_header_hash: H256::from(1),
+				merkle_root_hash: H256::from(2),
+				time: 3,
+				bits: 4.into(),
+				nonce: 5,
+			},
+			number: 10,
+			chain_work: U256::from(0x100010001u64),
+			status: HeaderStatus {
+				has_block: false,
+				invalid: true,
+			},
+		};
+		assert_eq!(deserialize::<_, HeaderEntry>(serialize(&entry).as_ref()).unwrap(), entry);
+	}
+}
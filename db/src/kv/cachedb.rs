@@ -1,8 +1,9 @@
 use lru_cache::LruCache;
 use parking_lot::Mutex;
 use hash::H256;
-use chain::BlockHeader;
-use kv::{KeyValueDatabase, KeyState, Operation, KeyValue, Key, Value, Transaction};
+use chain::{BlockHeader, OutPoint};
+use kv::{KeyValueDatabase, KeyValueSnapshot, KeyState, Operation, KeyValue, Key, Value, Transaction};
+use AddressOutput;
 
 pub struct CacheDatabase<T> where T: KeyValueDatabase {
 	db: T,
@@ -44,4 +45,16 @@ impl<T> KeyValueDatabase for CacheDatabase<T> where T: KeyValueDatabase {
 		}
 		self.db.get(key)
 	}
+
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String> {
+		self.db.address_index(script_hash)
+	}
+
+	fn flush(&self) -> Result<(), String> {
+		self.db.flush()
+	}
+
+	fn snapshot<'a>(&'a self) -> Option<Box<KeyValueSnapshot + 'a>> {
+		self.db.snapshot()
+	}
 }
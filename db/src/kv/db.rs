@@ -1,7 +1,33 @@
+use hash::H256;
+use chain::OutPoint;
 use kv::{Transaction, KeyState, Key, Value};
+use {TransactionMeta, AddressOutput};
 
 pub trait KeyValueDatabase: Send + Sync {
 	fn write(&self, tx: Transaction) -> Result<(), String>;
 
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String>;
+
+	/// Returns address index records of all outputs of the script with given hash. Records, deleted
+	/// by this database, but possibly present in the underlying database, are returned as `KeyState::Delete`.
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String>;
+
+	/// Writes all buffered changes to the underlying database.
+	fn flush(&self) -> Result<(), String> {
+		Ok(())
+	}
+
+	/// Read-only view of the database state, as it was written to the underlying storage.
+	/// Returns None if database doesn't support snapshots.
+	fn snapshot<'a>(&'a self) -> Option<Box<KeyValueSnapshot + 'a>> {
+		None
+	}
+}
+
+/// Database state at the moment of snapshot creation. Not affected by later writes.
+pub trait KeyValueSnapshot {
+	fn get(&self, key: &Key) -> Result<KeyState<Value>, String>;
+
+	/// Iterates all transactions meta records in the order of transaction hashes.
+	fn transactions_meta<'a>(&'a self) -> Box<Iterator<Item = Result<(H256, TransactionMeta), String>> + 'a>;
 }
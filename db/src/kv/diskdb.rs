@@ -3,12 +3,16 @@
 use std::collections::HashMap;
 use std::path::Path;
 use rocksdb::{
-	DB, Writable, WriteBatch, WriteOptions, IteratorMode, DBIterator,
-	Options, DBCompactionStyle, BlockBasedOptions, Cache, Column, ReadOptions
+	DB, Writable, WriteBatch, WriteOptions, IteratorMode, Direction, DBIterator,
+	Options, DBCompactionStyle, BlockBasedOptions, Cache, Column, ReadOptions, Snapshot
 };
 use bytes::Bytes;
-use kv::{Transaction, RawTransaction, RawOperation, Location, Value, KeyValueDatabase, Key, KeyState, RawKeyValue,
-RawKey};
+use hash::H256;
+use chain::OutPoint;
+use ser::{serialize, deserialize};
+use kv::{Transaction, RawTransaction, RawOperation, Location, Value, KeyValueDatabase, KeyValueSnapshot, Key, KeyState, RawKeyValue,
+RawKey, COL_TRANSACTIONS_META, COL_ADDRESS_INDEX};
+use {TransactionMeta, AddressOutput};
 
 const DB_BACKGROUND_FLUSHES: i32 = 2;
 const DB_BACKGROUND_COMPACTIONS: i32 = 2;
@@ -132,6 +136,73 @@ impl KeyValueDatabase for Database {
 			None => Ok(KeyState::Unknown)
 		}
 	}
+
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String> {
+		let prefix = serialize(script_hash);
+		let DBAndColumns { ref db, ref cfs } = self.db;
+		let iter = db.iterator_cf_opt(cfs[COL_ADDRESS_INDEX as usize], IteratorMode::From(&prefix, Direction::Forward), &self.read_opts)?;
+		iter.take_while(|&(ref key, _)| key.starts_with(&prefix))
+			// records of the previous index version were keyed by script hash only
+			.filter(|&(ref key, _)| key.len() > prefix.len())
+			.map(|(key, value)| {
+				let outpoint = deserialize(&key[prefix.len()..]).map_err(|e| format!("{:?}", e))?;
+				let output = deserialize(&*value).map_err(|e| format!("{:?}", e))?;
+				Ok((outpoint, KeyState::Insert(output)))
+			})
+			.collect()
+	}
+
+	fn snapshot<'a>(&'a self) -> Option<Box<KeyValueSnapshot + 'a>> {
+		Some(Box::new(Database::snapshot(self)))
+	}
+}
+
+/// Consistent read-only view of the database.
+pub struct DatabaseSnapshot<'a> {
+	snapshot: Snapshot<'a>,
+	cfs: &'a [Column],
+}
+
+impl<'a> DatabaseSnapshot<'a> {
+	/// Get value by key.
+	pub fn get(&self, key: &RawKey) -> Result<Option<Bytes>, String> {
+		let value = match key.location {
+			Location::DB => self.snapshot.get(&key.key)?,
+			Location::Column(col) => self.snapshot.get_cf(self.cfs[col as usize], &key.key)?,
+		};
+		Ok(value.map(|v| (&*v).into()))
+	}
+
+	pub fn iter(&self, location: Location) -> DatabaseIterator {
+		match location {
+			Location::DB => DatabaseIterator {
+				iter: self.snapshot.iterator(IteratorMode::Start)
+			},
+			Location::Column(column) => DatabaseIterator {
+				iter: self.snapshot.iterator_cf(self.cfs[column as usize], IteratorMode::Start)
+					.expect("iterator params are valid; qed")
+			}
+		}
+	}
+}
+
+impl<'a> KeyValueSnapshot for DatabaseSnapshot<'a> {
+	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
+		match DatabaseSnapshot::get(self, &key.into())? {
+			Some(value) => Ok(KeyState::Insert(Value::for_key(key, &value)?)),
+			None => Ok(KeyState::Unknown)
+		}
+	}
+
+	fn transactions_meta<'b>(&'b self) -> Box<Iterator<Item = Result<(H256, TransactionMeta), String>> + 'b> {
+		Box::new(self.iter(Location::Column(COL_TRANSACTIONS_META)).map(|(key, value)| decode_transaction_meta(&key, &value)))
+	}
+}
+
+fn decode_transaction_meta(key: &[u8], value: &[u8]) -> Result<(H256, TransactionMeta), String> {
+	let hash = deserialize(key).map_err(|e| format!("{:?}", e))?;
+	let meta = deserialize(value).map_err(|e| format!("{:?}", e))?;
+	Ok((hash, meta))
 }
 
 impl Database {
@@ -280,6 +351,15 @@ impl Database {
 	/// Close the database
 	pub fn close(self) {}
 
+	/// Consistent view of the database. Later writes are not visible through the snapshot.
+	pub fn snapshot(&self) -> DatabaseSnapshot {
+		let DBAndColumns { ref db, ref cfs } = self.db;
+		DatabaseSnapshot {
+			snapshot: db.snapshot(),
+			cfs: cfs,
+		}
+	}
+
 	pub fn iter(&self, location: Location) -> DatabaseIterator {
 		let DBAndColumns { ref db, ref cfs } = self.db;
 		match location {
@@ -347,4 +427,38 @@ mod tests {
 		let _ = Database::open_default(tempdir.path()).unwrap();
 		test_db(DatabaseConfig::default());
 	}
+
+	#[test]
+	fn address_index_is_found_by_script_hash_prefix() {
+		use kv::{KeyValue, COL_COUNT};
+
+		let tempdir = TempDir::new("").unwrap();
+		let db = Database::open(DatabaseConfig::with_columns(Some(COL_COUNT)), tempdir.path()).unwrap();
+		let output = |index| AddressOutput {
+			transaction_hash: H256::from(1),
+			index: index,
+			value: 10,
+			height: 1,
+			spent_by: None,
+		};
+		let outpoint = |index| OutPoint {
+			hash: H256::from(1),
+			index: index,
+		};
+
+		let mut transaction = Transaction::new();
+		transaction.insert(KeyValue::AddressIndex(H256::from(2), outpoint(0), output(0)));
+		transaction.insert(KeyValue::AddressIndex(H256::from(2), outpoint(1), output(1)));
+		transaction.insert(KeyValue::AddressIndex(H256::from(3), outpoint(2), output(2)));
+		KeyValueDatabase::write(&db, transaction).unwrap();
+
+		let records: Vec<_> = db.address_index(&H256::from(2)).unwrap().into_iter()
+			.map(|(outpoint, output)| (outpoint, output.into_option()))
+			.collect();
+		assert_eq!(records, vec![
+			(outpoint(0), Some(output(0))),
+			(outpoint(1), Some(output(1))),
+		]);
+		assert!(db.address_index(&H256::from(4)).unwrap().is_empty());
+	}
 }
@@ -4,12 +4,13 @@ use std::mem::replace;
 use parking_lot::RwLock;
 use hash::H256;
 use bytes::Bytes;
+use primitives::bigint::U256;
 use ser::List;
-use chain::{Transaction as ChainTransaction, BlockHeader};
-use kv::{Transaction, Key, KeyState, Operation, Value, KeyValueDatabase, KeyValue};
-use {TransactionMeta};
+use chain::{Transaction as ChainTransaction, BlockHeader, OutPoint};
+use kv::{Transaction, Key, KeyState, Operation, Value, KeyValueDatabase, KeyValueSnapshot, KeyValue};
+use {TransactionMeta, AddressOutput, BlockUndo, TransactionLocation, HeaderEntry};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 struct InnerDatabase {
 	meta: HashMap<&'static str, KeyState<Bytes>>,
 	block_hash: HashMap<u32, KeyState<H256>>,
@@ -19,6 +20,16 @@ struct InnerDatabase {
 	transaction_meta: HashMap<H256, KeyState<TransactionMeta>>,
 	block_number: HashMap<H256, KeyState<u32>>,
 	configuration: HashMap<&'static str, KeyState<Bytes>>,
+	block_filter: HashMap<H256, KeyState<Bytes>>,
+	block_filter_header: HashMap<H256, KeyState<H256>>,
+	address_index: HashMap<H256, HashMap<OutPoint, KeyState<AddressOutput>>>,
+	spent_transactions: HashMap<u32, KeyState<List<H256>>>,
+	wallet: HashMap<&'static str, KeyState<Bytes>>,
+	block_undo: HashMap<H256, KeyState<BlockUndo>>,
+	transaction_index: HashMap<H256, KeyState<TransactionLocation>>,
+	chain_tx_count: HashMap<H256, KeyState<u64>>,
+	chain_work: HashMap<H256, KeyState<U256>>,
+	header_entry: HashMap<H256, KeyState<HeaderEntry>>,
 }
 
 #[derive(Default, Debug)]
@@ -53,6 +64,41 @@ impl MemoryDatabase {
 		let configuration = replace(&mut db.configuration, HashMap::default()).into_iter()
 			.flat_map(|(key, state)| state.into_operation(key, KeyValue::Configuration, Key::Configuration));
 
+		let block_filter = replace(&mut db.block_filter, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockFilter, Key::BlockFilter));
+
+		let block_filter_header = replace(&mut db.block_filter_header, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockFilterHeader, Key::BlockFilterHeader));
+
+		let address_index = replace(&mut db.address_index, HashMap::default()).into_iter()
+			.flat_map(|(key, outputs)| outputs.into_iter()
+				.flat_map(move |(outpoint, state)| state.into_operation(
+					(key.clone(), outpoint),
+					|(key, outpoint), output| KeyValue::AddressIndex(key, outpoint, output),
+					|(key, outpoint)| Key::AddressIndex(key, outpoint),
+				)));
+
+		let spent_transactions = replace(&mut db.spent_transactions, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::SpentTransactions, Key::SpentTransactions));
+
+		let wallet = replace(&mut db.wallet, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::Wallet, Key::Wallet));
+
+		let block_undo = replace(&mut db.block_undo, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockUndo, Key::BlockUndo));
+
+		let transaction_index = replace(&mut db.transaction_index, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::TransactionIndex, Key::TransactionIndex));
+
+		let chain_tx_count = replace(&mut db.chain_tx_count, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::ChainTxCount, Key::ChainTxCount));
+
+		let chain_work = replace(&mut db.chain_work, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::ChainWork, Key::ChainWork));
+
+		let header_entry = replace(&mut db.header_entry, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::HeaderEntry, Key::HeaderEntry));
+
 		Transaction {
 			operations: meta
 				.chain(block_hash)
@@ -62,6 +108,16 @@ impl MemoryDatabase {
 				.chain(transaction_meta)
 				.chain(block_number)
 				.chain(configuration)
+				.chain(block_filter)
+				.chain(block_filter_header)
+				.chain(address_index)
+				.chain(spent_transactions)
+				.chain(wallet)
+				.chain(block_undo)
+				.chain(transaction_index)
+				.chain(chain_tx_count)
+				.chain(chain_work)
+				.chain(header_entry)
 				.collect()
 		}
 	}
@@ -81,6 +137,16 @@ impl KeyValueDatabase for MemoryDatabase {
 					KeyValue::TransactionMeta(key, value) => { db.transaction_meta.insert(key, KeyState::Insert(value)); },
 					KeyValue::BlockNumber(key, value) => { db.block_number.insert(key, KeyState::Insert(value)); },
 					KeyValue::Configuration(key, value) => { db.configuration.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockFilter(key, value) => { db.block_filter.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockFilterHeader(key, value) => { db.block_filter_header.insert(key, KeyState::Insert(value)); },
+					KeyValue::AddressIndex(key, outpoint, value) => { db.address_index.entry(key).or_insert_with(HashMap::default).insert(outpoint, KeyState::Insert(value)); },
+					KeyValue::SpentTransactions(key, value) => { db.spent_transactions.insert(key, KeyState::Insert(value)); },
+					KeyValue::Wallet(key, value) => { db.wallet.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockUndo(key, value) => { db.block_undo.insert(key, KeyState::Insert(value)); },
+					KeyValue::TransactionIndex(key, value) => { db.transaction_index.insert(key, KeyState::Insert(value)); },
+					KeyValue::ChainTxCount(key, value) => { db.chain_tx_count.insert(key, KeyState::Insert(value)); },
+					KeyValue::ChainWork(key, value) => { db.chain_work.insert(key, KeyState::Insert(value)); },
+					KeyValue::HeaderEntry(key, value) => { db.header_entry.insert(key, KeyState::Insert(value)); },
 				},
 				Operation::Delete(delete) => match delete {
 					Key::Meta(key) => { db.meta.insert(key, KeyState::Delete); }
@@ -91,6 +157,16 @@ impl KeyValueDatabase for MemoryDatabase {
 					Key::TransactionMeta(key) => { db.transaction_meta.insert(key, KeyState::Delete); }
 					Key::BlockNumber(key) => { db.block_number.insert(key, KeyState::Delete); }
 					Key::Configuration(key) => { db.configuration.insert(key, KeyState::Delete); }
+					Key::BlockFilter(key) => { db.block_filter.insert(key, KeyState::Delete); }
+					Key::BlockFilterHeader(key) => { db.block_filter_header.insert(key, KeyState::Delete); }
+					Key::AddressIndex(key, outpoint) => { db.address_index.entry(key).or_insert_with(HashMap::default).insert(outpoint, KeyState::Delete); }
+					Key::SpentTransactions(key) => { db.spent_transactions.insert(key, KeyState::Delete); }
+					Key::Wallet(key) => { db.wallet.insert(key, KeyState::Delete); }
+					Key::BlockUndo(key) => { db.block_undo.insert(key, KeyState::Delete); }
+					Key::TransactionIndex(key) => { db.transaction_index.insert(key, KeyState::Delete); }
+					Key::ChainTxCount(key) => { db.chain_tx_count.insert(key, KeyState::Delete); }
+					Key::ChainWork(key) => { db.chain_work.insert(key, KeyState::Delete); }
+					Key::HeaderEntry(key) => { db.header_entry.insert(key, KeyState::Delete); }
 				}
 			}
 		}
@@ -108,10 +184,55 @@ impl KeyValueDatabase for MemoryDatabase {
 			Key::TransactionMeta(ref key) => db.transaction_meta.get(key).cloned().unwrap_or_default().map(Value::TransactionMeta),
 			Key::BlockNumber(ref key) => db.block_number.get(key).cloned().unwrap_or_default().map(Value::BlockNumber),
 			Key::Configuration(ref key) => db.configuration.get(key).cloned().unwrap_or_default().map(Value::Configuration),
+			Key::BlockFilter(ref key) => db.block_filter.get(key).cloned().unwrap_or_default().map(Value::BlockFilter),
+			Key::BlockFilterHeader(ref key) => db.block_filter_header.get(key).cloned().unwrap_or_default().map(Value::BlockFilterHeader),
+			Key::AddressIndex(ref key, ref outpoint) => db.address_index.get(key).and_then(|outputs| outputs.get(outpoint)).cloned().unwrap_or_default().map(Value::AddressIndex),
+			Key::SpentTransactions(ref key) => db.spent_transactions.get(key).cloned().unwrap_or_default().map(Value::SpentTransactions),
+			Key::Wallet(ref key) => db.wallet.get(key).cloned().unwrap_or_default().map(Value::Wallet),
+			Key::BlockUndo(ref key) => db.block_undo.get(key).cloned().unwrap_or_default().map(Value::BlockUndo),
+			Key::TransactionIndex(ref key) => db.transaction_index.get(key).cloned().unwrap_or_default().map(Value::TransactionIndex),
+			Key::ChainTxCount(ref key) => db.chain_tx_count.get(key).cloned().unwrap_or_default().map(Value::ChainTxCount),
+			Key::ChainWork(ref key) => db.chain_work.get(key).cloned().unwrap_or_default().map(Value::ChainWork),
+			Key::HeaderEntry(ref key) => db.header_entry.get(key).cloned().unwrap_or_default().map(Value::HeaderEntry),
 		};
 
 		Ok(result)
 	}
+
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String> {
+		Ok(self.db.read().address_index.get(script_hash)
+			.map(|outputs| outputs.iter().map(|(outpoint, state)| (outpoint.clone(), state.clone())).collect())
+			.unwrap_or_default())
+	}
+
+	fn snapshot<'a>(&'a self) -> Option<Box<KeyValueSnapshot + 'a>> {
+		let snapshot = MemoryDatabaseSnapshot {
+			db: MemoryDatabase {
+				db: RwLock::new(self.db.read().clone()),
+			},
+		};
+		Some(Box::new(snapshot))
+	}
+}
+
+/// Copy of the memory database contents.
+pub struct MemoryDatabaseSnapshot {
+	db: MemoryDatabase,
+}
+
+impl KeyValueSnapshot for MemoryDatabaseSnapshot {
+	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
+		self.db.get(key)
+	}
+
+	fn transactions_meta<'a>(&'a self) -> Box<Iterator<Item = Result<(H256, TransactionMeta), String>> + 'a> {
+		let mut transactions_meta: Vec<_> = self.db.db.read().transaction_meta.iter()
+			.filter_map(|(hash, state)| state.clone().into_option().map(|meta| (hash.clone(), meta)))
+			.collect();
+		// disk database iterates records in the order of keys
+		transactions_meta.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+		Box::new(transactions_meta.into_iter().map(Ok))
+	}
 }
 
 #[derive(Debug)]
@@ -143,4 +264,12 @@ impl KeyValueDatabase for SharedMemoryDatabase {
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
 		self.db.get(key)
 	}
+
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String> {
+		self.db.address_index(script_hash)
+	}
+
+	fn snapshot<'a>(&'a self) -> Option<Box<KeyValueSnapshot + 'a>> {
+		self.db.snapshot()
+	}
 }
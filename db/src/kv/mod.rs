@@ -6,13 +6,15 @@ mod overlaydb;
 mod transaction;
 
 pub use self::cachedb::CacheDatabase;
-pub use self::db::KeyValueDatabase;
-pub use self::diskdb::{Database as DiskDatabase, DatabaseConfig, CompactionProfile};
+pub use self::db::{KeyValueDatabase, KeyValueSnapshot};
+pub use self::diskdb::{Database as DiskDatabase, DatabaseSnapshot as DiskDatabaseSnapshot, DatabaseConfig, CompactionProfile};
 pub use self::memorydb::{MemoryDatabase, SharedMemoryDatabase};
 pub use self::overlaydb::{OverlayDatabase, AutoFlushingOverlayDatabase};
 pub use self::transaction::{
 	RawTransaction, Transaction, RawOperation, Operation, Location, KeyState,
-	Key, Value, KeyValue, RawKeyValue, RawKey,
+	Key, Value, KeyValue, RawKeyValue, RawKey, address_index_key,
 	COL_COUNT, COL_META, COL_BLOCK_HASHES, COL_BLOCK_HEADERS, COL_BLOCK_TRANSACTIONS,
-	COL_TRANSACTIONS, COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS
+	COL_TRANSACTIONS, COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS, COL_BLOCK_FILTERS, COL_BLOCK_FILTER_HEADERS,
+	COL_ADDRESS_INDEX, COL_SPENT_TRANSACTIONS, COL_WALLET, COL_BLOCK_UNDO, COL_TRANSACTION_INDEX, COL_CHAIN_TX_COUNTS,
+	COL_CHAIN_WORK, COL_HEADER_ENTRIES
 };
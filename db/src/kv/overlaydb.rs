@@ -1,5 +1,20 @@
+use std::collections::HashMap;
 use parking_lot::Mutex;
-use kv::{Transaction, Value, KeyValueDatabase, MemoryDatabase, KeyState, Key};
+use hash::H256;
+use chain::OutPoint;
+use kv::{Transaction, Value, KeyValueDatabase, KeyValueSnapshot, MemoryDatabase, KeyState, Key};
+use AddressOutput;
+
+/// Address index records of the overlay replace records of the underlying database.
+fn merge_address_index(records: Vec<(OutPoint, KeyState<AddressOutput>)>, overlay: Vec<(OutPoint, KeyState<AddressOutput>)>) -> Vec<(OutPoint, KeyState<AddressOutput>)> {
+	if overlay.is_empty() {
+		return records;
+	}
+
+	let mut records: HashMap<_, _> = records.into_iter().collect();
+	records.extend(overlay);
+	records.into_iter().collect()
+}
 
 pub struct OverlayDatabase<'a, T> where T: 'a + KeyValueDatabase {
 	db: &'a T,
@@ -13,10 +28,6 @@ impl<'a, T> OverlayDatabase<'a, T> where T: 'a + KeyValueDatabase {
 			overlay: MemoryDatabase::default(),
 		}
 	}
-
-	pub fn flush(&self) -> Result<(), String> {
-		self.db.write(self.overlay.drain_transaction())
-	}
 }
 
 impl<'a, T> KeyValueDatabase for OverlayDatabase<'a, T> where T: 'a + KeyValueDatabase {
@@ -30,6 +41,14 @@ impl<'a, T> KeyValueDatabase for OverlayDatabase<'a, T> where T: 'a + KeyValueDa
 			exists => Ok(exists)
 		}
 	}
+
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String> {
+		Ok(merge_address_index(self.db.address_index(script_hash)?, self.overlay.address_index(script_hash)?))
+	}
+
+	fn flush(&self) -> Result<(), String> {
+		self.db.write(self.overlay.drain_transaction())
+	}
 }
 
 pub struct AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
@@ -49,7 +68,7 @@ impl<T> AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
 		}
 	}
 
-	fn flush(&self) -> Result<(), String> {
+	fn flush_overlay(&self) -> Result<(), String> {
 		self.db.write(self.overlay.drain_transaction())
 	}
 }
@@ -60,7 +79,7 @@ impl<T> KeyValueDatabase for AutoFlushingOverlayDatabase<T> where T: KeyValueDat
 		*operations += 1;
 		self.overlay.write(tx)?;
 		if *operations == self.max_operations {
-			self.flush()?;
+			self.flush_overlay()?;
 			*operations = 0;
 		}
 		Ok(())
@@ -72,6 +91,23 @@ impl<T> KeyValueDatabase for AutoFlushingOverlayDatabase<T> where T: KeyValueDat
 			exists => Ok(exists)
 		}
 	}
+
+	fn address_index(&self, script_hash: &H256) -> Result<Vec<(OutPoint, KeyState<AddressOutput>)>, String> {
+		Ok(merge_address_index(self.db.address_index(script_hash)?, self.overlay.address_index(script_hash)?))
+	}
+
+	fn flush(&self) -> Result<(), String> {
+		let mut operations = self.operations.lock();
+		self.flush_overlay()?;
+		*operations = 0;
+		self.db.flush()
+	}
+
+	/// Snapshot of the underlying database. Overlay is flushed with whole transactions,
+	/// so the snapshot is always consistent.
+	fn snapshot<'a>(&'a self) -> Option<Box<KeyValueSnapshot + 'a>> {
+		self.db.snapshot()
+	}
 }
 
 impl<T> Drop for AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
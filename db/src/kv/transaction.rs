@@ -1,10 +1,11 @@
 use bytes::Bytes;
 use hash::H256;
-use ser::{serialize, List, deserialize};
-use chain::{Transaction as ChainTransaction, BlockHeader};
-use {TransactionMeta};
+use primitives::bigint::U256;
+use ser::{serialize, List, deserialize, Stream};
+use chain::{Transaction as ChainTransaction, BlockHeader, OutPoint};
+use {TransactionMeta, AddressOutput, BlockUndo, TransactionLocation, HeaderEntry};
 
-pub const COL_COUNT: u32 = 10;
+pub const COL_COUNT: u32 = 18;
 pub const COL_META: u32 = 0;
 pub const COL_BLOCK_HASHES: u32 = 1;
 pub const COL_BLOCK_HEADERS: u32 = 2;
@@ -13,6 +14,24 @@ pub const COL_TRANSACTIONS: u32 = 4;
 pub const COL_TRANSACTIONS_META: u32 = 5;
 pub const COL_BLOCK_NUMBERS: u32 = 6;
 pub const COL_CONFIGURATION: u32 = 7;
+pub const COL_BLOCK_FILTERS: u32 = 8;
+pub const COL_BLOCK_FILTER_HEADERS: u32 = 9;
+pub const COL_ADDRESS_INDEX: u32 = 10;
+pub const COL_SPENT_TRANSACTIONS: u32 = 11;
+pub const COL_WALLET: u32 = 12;
+pub const COL_BLOCK_UNDO: u32 = 13;
+pub const COL_TRANSACTION_INDEX: u32 = 14;
+pub const COL_CHAIN_TX_COUNTS: u32 = 15;
+pub const COL_CHAIN_WORK: u32 = 16;
+pub const COL_HEADER_ENTRIES: u32 = 17;
+
+/// Address index records are keyed by script hash, followed by the outpoint, so that all outputs
+/// of the script are found by the script hash prefix.
+pub fn address_index_key(script_hash: &H256, outpoint: &OutPoint) -> Bytes {
+	let mut stream = Stream::new();
+	stream.append(script_hash).append(outpoint);
+	stream.out()
+}
 
 #[derive(Debug)]
 pub enum Operation {
@@ -30,6 +49,16 @@ pub enum KeyValue {
 	TransactionMeta(H256, TransactionMeta),
 	BlockNumber(H256, u32),
 	Configuration(&'static str, Bytes),
+	BlockFilter(H256, Bytes),
+	BlockFilterHeader(H256, H256),
+	AddressIndex(H256, OutPoint, AddressOutput),
+	SpentTransactions(u32, List<H256>),
+	Wallet(&'static str, Bytes),
+	BlockUndo(H256, BlockUndo),
+	TransactionIndex(H256, TransactionLocation),
+	ChainTxCount(H256, u64),
+	ChainWork(H256, U256),
+	HeaderEntry(H256, HeaderEntry),
 }
 
 #[derive(Debug)]
@@ -42,6 +71,16 @@ pub enum Key {
 	TransactionMeta(H256),
 	BlockNumber(H256),
 	Configuration(&'static str),
+	BlockFilter(H256),
+	BlockFilterHeader(H256),
+	AddressIndex(H256, OutPoint),
+	SpentTransactions(u32),
+	Wallet(&'static str),
+	BlockUndo(H256),
+	TransactionIndex(H256),
+	ChainTxCount(H256),
+	ChainWork(H256),
+	HeaderEntry(H256),
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +93,16 @@ pub enum Value {
 	TransactionMeta(TransactionMeta),
 	BlockNumber(u32),
 	Configuration(Bytes),
+	BlockFilter(Bytes),
+	BlockFilterHeader(H256),
+	AddressIndex(AddressOutput),
+	SpentTransactions(List<H256>),
+	Wallet(Bytes),
+	BlockUndo(BlockUndo),
+	TransactionIndex(TransactionLocation),
+	ChainTxCount(u64),
+	ChainWork(U256),
+	HeaderEntry(HeaderEntry),
 }
 
 impl Value {
@@ -67,6 +116,16 @@ impl Value {
 			Key::TransactionMeta(_) => deserialize(bytes).map(Value::TransactionMeta),
 			Key::BlockNumber(_) => deserialize(bytes).map(Value::BlockNumber),
 			Key::Configuration(_) => deserialize(bytes).map(Value::Configuration),
+			Key::BlockFilter(_) => deserialize(bytes).map(Value::BlockFilter),
+			Key::BlockFilterHeader(_) => deserialize(bytes).map(Value::BlockFilterHeader),
+			Key::AddressIndex(_, _) => deserialize(bytes).map(Value::AddressIndex),
+			Key::SpentTransactions(_) => deserialize(bytes).map(Value::SpentTransactions),
+			Key::Wallet(_) => deserialize(bytes).map(Value::Wallet),
+			Key::BlockUndo(_) => deserialize(bytes).map(Value::BlockUndo),
+			Key::TransactionIndex(_) => deserialize(bytes).map(Value::TransactionIndex),
+			Key::ChainTxCount(_) => deserialize(bytes).map(Value::ChainTxCount),
+			Key::ChainWork(_) => deserialize(bytes).map(Value::ChainWork),
+			Key::HeaderEntry(_) => deserialize(bytes).map(Value::HeaderEntry),
 		}.map_err(|e| format!("{:?}", e))
 	}
 
@@ -125,6 +184,76 @@ impl Value {
 			_ => None,
 		}
 	}
+
+	pub fn as_block_filter(self) -> Option<Bytes> {
+		match self {
+			Value::BlockFilter(filter) => Some(filter),
+			_ => None,
+		}
+	}
+
+	pub fn as_block_filter_header(self) -> Option<H256> {
+		match self {
+			Value::BlockFilterHeader(header) => Some(header),
+			_ => None,
+		}
+	}
+
+	pub fn as_address_index(self) -> Option<AddressOutput> {
+		match self {
+			Value::AddressIndex(output) => Some(output),
+			_ => None,
+		}
+	}
+
+	pub fn as_spent_transactions(self) -> Option<List<H256>> {
+		match self {
+			Value::SpentTransactions(list) => Some(list),
+			_ => None,
+		}
+	}
+
+	pub fn as_wallet(self) -> Option<Bytes> {
+		match self {
+			Value::Wallet(bytes) => Some(bytes),
+			_ => None,
+		}
+	}
+
+	pub fn as_block_undo(self) -> Option<BlockUndo> {
+		match self {
+			Value::BlockUndo(undo) => Some(undo),
+			_ => None,
+		}
+	}
+
+	pub fn as_transaction_index(self) -> Option<TransactionLocation> {
+		match self {
+			Value::TransactionIndex(location) => Some(location),
+			_ => None,
+		}
+	}
+
+	pub fn as_chain_tx_count(self) -> Option<u64> {
+		match self {
+			Value::ChainTxCount(count) => Some(count),
+			_ => None,
+		}
+	}
+
+	pub fn as_chain_work(self) -> Option<U256> {
+		match self {
+			Value::ChainWork(work) => Some(work),
+			_ => None,
+		}
+	}
+
+	pub fn as_header_entry(self) -> Option<HeaderEntry> {
+		match self {
+			Value::HeaderEntry(entry) => Some(entry),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -228,6 +357,16 @@ impl<'a> From<&'a KeyValue> for RawKeyValue {
 			KeyValue::TransactionMeta(ref key, ref value) => (COL_TRANSACTIONS_META, serialize(key), serialize(value)),
 			KeyValue::BlockNumber(ref key, ref value) => (COL_BLOCK_NUMBERS, serialize(key), serialize(value)),
 			KeyValue::Configuration(ref key, ref value) => (COL_CONFIGURATION, serialize(key), serialize(value)),
+			KeyValue::BlockFilter(ref key, ref value) => (COL_BLOCK_FILTERS, serialize(key), serialize(value)),
+			KeyValue::BlockFilterHeader(ref key, ref value) => (COL_BLOCK_FILTER_HEADERS, serialize(key), serialize(value)),
+			KeyValue::AddressIndex(ref key, ref outpoint, ref value) => (COL_ADDRESS_INDEX, address_index_key(key, outpoint), serialize(value)),
+			KeyValue::SpentTransactions(ref key, ref value) => (COL_SPENT_TRANSACTIONS, serialize(key), serialize(value)),
+			KeyValue::Wallet(ref key, ref value) => (COL_WALLET, serialize(key), serialize(value)),
+			KeyValue::BlockUndo(ref key, ref value) => (COL_BLOCK_UNDO, serialize(key), serialize(value)),
+			KeyValue::TransactionIndex(ref key, ref value) => (COL_TRANSACTION_INDEX, serialize(key), serialize(value)),
+			KeyValue::ChainTxCount(ref key, ref value) => (COL_CHAIN_TX_COUNTS, serialize(key), serialize(value)),
+			KeyValue::ChainWork(ref key, ref value) => (COL_CHAIN_WORK, serialize(key), serialize(value)),
+			KeyValue::HeaderEntry(ref key, ref value) => (COL_HEADER_ENTRIES, serialize(key), serialize(value)),
 		};
 
 		RawKeyValue {
@@ -263,6 +402,16 @@ impl<'a> From<&'a Key> for RawKey {
 			Key::TransactionMeta(ref key) => (COL_TRANSACTIONS_META, serialize(key)),
 			Key::BlockNumber(ref key) => (COL_BLOCK_NUMBERS, serialize(key)),
 			Key::Configuration(ref key) => (COL_CONFIGURATION, serialize(key)),
+			Key::BlockFilter(ref key) => (COL_BLOCK_FILTERS, serialize(key)),
+			Key::BlockFilterHeader(ref key) => (COL_BLOCK_FILTER_HEADERS, serialize(key)),
+			Key::AddressIndex(ref key, ref outpoint) => (COL_ADDRESS_INDEX, address_index_key(key, outpoint)),
+			Key::SpentTransactions(ref key) => (COL_SPENT_TRANSACTIONS, serialize(key)),
+			Key::Wallet(ref key) => (COL_WALLET, serialize(key)),
+			Key::BlockUndo(ref key) => (COL_BLOCK_UNDO, serialize(key)),
+			Key::TransactionIndex(ref key) => (COL_TRANSACTION_INDEX, serialize(key)),
+			Key::ChainTxCount(ref key) => (COL_CHAIN_TX_COUNTS, serialize(key)),
+			Key::ChainWork(ref key) => (COL_CHAIN_WORK, serialize(key)),
+			Key::HeaderEntry(ref key) => (COL_HEADER_ENTRIES, serialize(key)),
 		};
 
 		RawKey {
@@ -7,36 +7,59 @@ extern crate bit_vec;
 extern crate lru_cache;
 
 extern crate primitives;
+extern crate bitcrypto as crypto;
 extern crate serialization as ser;
 extern crate chain;
 
 pub mod kv;
+mod address_index;
 mod best_block;
 mod block_ancestors;
 mod block_chain;
 mod block_chain_db;
 mod block_impls;
+mod block_invalidation;
 mod block_iterator;
 mod block_origin;
 mod block_provider;
 mod block_ref;
+mod block_undo;
+mod cfindex;
+mod chain_stats;
 mod error;
+mod header_tree;
+mod prune;
 mod store;
+mod transaction_index;
 mod transaction_meta;
 mod transaction_provider;
+mod utxo_cache;
+mod utxo_snapshot;
 
 pub use primitives::{hash, bytes};
 
+pub use address_index::{AddressIndexProvider, AddressOutput, AddressSpend, script_hash, is_indexed_script};
 pub use best_block::BestBlock;
 pub use block_ancestors::BlockAncestors;
 pub use block_chain::{BlockChain, ForkChain, Forkable};
 pub use block_chain_db::{BlockChainDatabase, ForkChainDatabase};
+pub use block_invalidation::{BlockInvalidation, ChainReorganization};
 pub use block_iterator::BlockIterator;
 pub use block_origin::{BlockOrigin, SideChainOrigin};
 pub use block_provider::{BlockHeaderProvider, BlockProvider, IndexedBlockProvider};
 pub use block_ref::BlockRef;
+pub use block_undo::{BlockUndo, SpentOutput, BlockUndoProvider};
+pub use cfindex::{
+	BlockFilterProvider, BASIC_FILTER_TYPE, basic_filter_elements, build_basic_filter, basic_filter_match_any,
+	filter_hash, filter_header
+};
+pub use chain_stats::ChainStatsProvider;
 pub use error::Error;
-pub use store::{AsSubstore, Store, SharedStore, CanonStore, ConfigStore};
+pub use header_tree::{HeaderTree, HeaderEntry, HeaderStatus};
+pub use prune::{BlockPruning, PruneMode, MIN_BLOCKS_TO_KEEP};
+pub use store::{AsSubstore, Store, SharedStore, CanonStore, ConfigStore, WalletStore};
+pub use transaction_index::{TransactionIndexProvider, TransactionLocation};
 pub use transaction_meta::TransactionMeta;
 pub use transaction_provider::{TransactionProvider, TransactionOutputProvider, TransactionMetaProvider};
+pub use utxo_snapshot::{UtxoSnapshot, UtxoSnapshotMetadata, UtxoSnapshotReader, UtxoScanProgress, UtxoStats, UnspentOutput};
 
@@ -0,0 +1,44 @@
+//! Block storage pruning.
+//!
+//! Data of the old canon blocks may be pruned: list of block transactions is removed and bodies
+//! of transactions, which outputs are all spent, are deleted. Headers, transactions meta and bodies
+//! of transactions with unspent outputs are kept, so pruned node is still able to verify new blocks.
+//! Body of the transaction, spent by the recent block, is only deleted when the spending block
+//! is pruned, so that recent blocks may always be decanonized.
+
+use Error;
+
+/// Number of the best blocks, which are never pruned. Equals to the number of blocks, which
+/// NODE_NETWORK_LIMITED peers are able to serve (BIP159).
+pub const MIN_BLOCKS_TO_KEEP: u32 = 288;
+
+/// Block storage pruning mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PruneMode {
+	/// All blocks are stored
+	Disabled,
+	/// Blocks are only pruned on request
+	Manual,
+	/// Oldest blocks are pruned when size of stored blocks exceeds given number of bytes
+	Target(u64),
+}
+
+impl PruneMode {
+	/// Is pruning enabled?
+	pub fn is_enabled(&self) -> bool {
+		*self != PruneMode::Disabled
+	}
+}
+
+/// Block storage pruning interface
+pub trait BlockPruning {
+	/// Is pruning enabled?
+	fn is_prune_enabled(&self) -> bool;
+
+	/// get number of pruned canon blocks. Data of blocks below this height is not available
+	fn pruned_blocks(&self) -> u32;
+
+	/// prune data of canon blocks below given height. MIN_BLOCKS_TO_KEEP best blocks are never pruned
+	/// Returns number of pruned canon blocks.
+	fn prune_blocks(&self, height: u32) -> Result<u32, Error>;
+}
@@ -1,12 +1,27 @@
+use std::io;
 use std::sync::Arc;
 use chain::BlockHeader;
+use bytes::Bytes;
 use {
 	BestBlock, BlockProvider, BlockHeaderProvider, TransactionProvider, TransactionMetaProvider,
-	TransactionOutputProvider, BlockChain, IndexedBlockProvider, Forkable, Error
+	TransactionOutputProvider, BlockChain, IndexedBlockProvider, Forkable, Error, UtxoSnapshot, BlockFilterProvider,
+	AddressIndexProvider, BlockPruning, BlockUndoProvider, TransactionIndexProvider, ChainStatsProvider,
+	BlockInvalidation, HeaderTree
 };
 
-pub trait CanonStore: Store + Forkable + ConfigStore {
+pub trait CanonStore: Store + Forkable + ConfigStore + WalletStore + BlockFilterProvider + AddressIndexProvider + BlockPruning + BlockUndoProvider + TransactionIndexProvider + ChainStatsProvider + BlockInvalidation + HeaderTree {
 	fn as_store(&self) -> &Store;
+
+	/// write all cached changes to the disk
+	fn flush(&self) -> Result<(), Error>;
+
+	/// get unspent outputs set, written to the disk
+	fn utxo_snapshot<'a>(&'a self) -> Result<UtxoSnapshot<'a>, Error>;
+
+	/// load unspent outputs set from the snapshot file into the database, which only contains the genesis block.
+	/// Blocks up to the snapshot base block are stored as pruned. Snapshot must be verified before it is loaded
+	/// (see `UtxoSnapshotReader::verify`). Returns the new best block
+	fn load_utxo_snapshot(&self, snapshot: &mut io::Read) -> Result<BestBlock, Error>;
 }
 
 /// Configuration storage interface
@@ -18,6 +33,15 @@ pub trait ConfigStore {
 	fn set_consensus_fork(&self, consensus_fork: &str) -> Result<(), Error>;
 }
 
+/// Wallet records storage interface
+pub trait WalletStore {
+	/// get wallet record with given name
+	fn wallet_record(&self, name: &'static str) -> Option<Bytes>;
+
+	/// write wallet records in the single database transaction
+	fn set_wallet_records(&self, records: Vec<(&'static str, Bytes)>) -> Result<(), Error>;
+}
+
 /// Blockchain storage interface
 pub trait Store: AsSubstore {
 	/// get best block
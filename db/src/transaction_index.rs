@@ -0,0 +1,62 @@
+//! Transaction index.
+//!
+//! When index is enabled, every canon chain transaction is stored by its hash along with the
+//! block, which includes it. Index is updated when blocks are canonized and decanonized, so it
+//! always follows the canon chain. Unlike transactions meta, index entries aren't affected by
+//! pruning, so it can't be used together with pruning. Blocks must be indexed in order, so blocks,
+//! canonized while index was disabled, must be indexed before any new block is indexed.
+
+use std::io;
+use hash::H256;
+use ser::{Serializable, Deserializable, Error as ReaderError, Stream, Reader};
+
+/// Transaction index storage interface
+pub trait TransactionIndexProvider {
+	/// Is transaction index enabled?
+	fn is_transaction_index_enabled(&self) -> bool;
+
+	/// get location of the canon chain transaction with given hash
+	fn transaction_location(&self, hash: &H256) -> Option<TransactionLocation>;
+}
+
+/// Location of the canon chain transaction, stored in the transaction index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionLocation {
+	/// Hash of the block, which includes the transaction
+	pub block_hash: H256,
+	/// Index of the transaction in the block
+	pub index: u32,
+}
+
+impl Serializable for TransactionLocation {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.block_hash)
+			.append(&self.index);
+	}
+}
+
+impl Deserializable for TransactionLocation {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(TransactionLocation {
+			block_hash: reader.read()?,
+			index: reader.read()?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use ser::{serialize, deserialize};
+	use super::TransactionLocation;
+
+	#[test]
+	fn test_transaction_location_serialization() {
+		let location = TransactionLocation {
+			block_hash: H256::from(1),
+			index: 2,
+		};
+		assert_eq!(deserialize::<_, TransactionLocation>(serialize(&location).as_ref()).unwrap(), location);
+	}
+}
@@ -0,0 +1,126 @@
+//! In-memory cache of the canon chain unspent transaction outputs.
+//!
+//! Outputs of canonized transactions are added to the cache and outputs, spent by canonized
+//! transactions, are removed from it, so inputs of the new blocks are mostly resolved without
+//! reading the database. Cache is write-through: all changes are still written to the underlying
+//! database, which buffers and periodically flushes them on its own. So flushing the database
+//! never requires evicting entries: cache size is bounded by its capacity alone and the least
+//! recently used outputs are evicted, when it is full.
+//!
+//! Cache is split into shards by the transaction hash, so that parallel verification threads
+//! rarely wait for each other.
+
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use hash::H256;
+use chain::{IndexedBlock, OutPoint, TransactionOutput};
+use block_undo::BlockUndo;
+use utxo_snapshot::is_unspendable;
+
+/// Approximate memory, used by single cache entry: outpoint, output value and average script.
+const ENTRY_SIZE: usize = 128;
+/// Number of independently locked cache shards.
+const SHARDS: usize = 16;
+
+type Shard = LruCache<(H256, u32), TransactionOutput>;
+
+pub struct UtxoCache {
+	shards: Vec<Mutex<Shard>>,
+}
+
+impl UtxoCache {
+	/// Creates cache, which uses approximately `size` megabytes of memory.
+	pub fn new(size: usize) -> Self {
+		let shard_capacity = ::std::cmp::max(1, size * 1024 * 1024 / ENTRY_SIZE / SHARDS);
+		UtxoCache {
+			shards: (0..SHARDS).map(|_| Mutex::new(LruCache::new(shard_capacity))).collect(),
+		}
+	}
+
+	/// Returns cached unspent output. None means that output isn't cached, not that it doesn't exist.
+	pub fn output(&self, outpoint: &OutPoint) -> Option<TransactionOutput> {
+		self.shard(&outpoint.hash).lock().get_mut(&(outpoint.hash.clone(), outpoint.index)).cloned()
+	}
+
+	/// Updates cache after block is canonized. Outputs, which can never be spent, aren't cached.
+	pub fn canonize(&self, block: &IndexedBlock) {
+		for tx in &block.transactions {
+			for input in &tx.raw.inputs {
+				self.remove(&input.previous_output.hash, input.previous_output.index);
+			}
+
+			let mut shard = self.shard(&tx.hash).lock();
+			for (index, output) in tx.raw.outputs.iter().enumerate() {
+				if !is_unspendable(output) {
+					shard.insert((tx.hash.clone(), index as u32), output.clone());
+				}
+			}
+		}
+	}
+
+	/// Updates cache after block is decanonized. Outputs, spent by the block, are restored from its undo data.
+	pub fn decanonize(&self, block: &IndexedBlock, undo: &BlockUndo) {
+		let inputs = block.transactions.iter()
+			.skip(1)
+			.flat_map(|tx| tx.raw.inputs.iter());
+		for (input, spent) in inputs.zip(undo.spent_outputs.iter()) {
+			let hash = &input.previous_output.hash;
+			self.shard(hash).lock().insert((hash.clone(), input.previous_output.index), spent.output.clone());
+		}
+
+		// outputs, spent by the same block transactions, are removed here too
+		for tx in &block.transactions {
+			let mut shard = self.shard(&tx.hash).lock();
+			for index in 0..tx.raw.outputs.len() {
+				shard.remove(&(tx.hash.clone(), index as u32));
+			}
+		}
+	}
+
+	/// Removes all cached outputs.
+	pub fn clear(&self) {
+		for shard in &self.shards {
+			shard.lock().clear();
+		}
+	}
+
+	fn remove(&self, hash: &H256, index: u32) {
+		self.shard(hash).lock().remove(&(hash.clone(), index));
+	}
+
+	fn shard(&self, hash: &H256) -> &Mutex<Shard> {
+		&self.shards[hash[0] as usize % SHARDS]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::{IndexedBlock, BlockHeader, Transaction, TransactionOutput, OutPoint};
+	use super::UtxoCache;
+
+	#[test]
+	fn unspendable_outputs_are_not_cached() {
+		let transaction = Transaction {
+			outputs: vec![
+				TransactionOutput { value: 10, script_pubkey: "6a0100".into() },
+				TransactionOutput { value: 20, script_pubkey: "51".into() },
+			],
+			..Default::default()
+		};
+		let hash = transaction.hash();
+		let header = BlockHeader {
+			version: 1,
+			previous_header_hash: 0.into(),
+			merkle_root_hash: hash.clone(),
+			time: 0,
+			bits: 0.into(),
+			nonce: 0,
+		};
+		let block = IndexedBlock::new(header.into(), vec![transaction.into()]);
+
+		let cache = UtxoCache::new(1);
+		cache.canonize(&block);
+		assert_eq!(cache.output(&OutPoint { hash: hash.clone(), index: 0 }), None);
+		assert_eq!(cache.output(&OutPoint { hash: hash, index: 1 }).map(|output| output.value), Some(20));
+	}
+}
@@ -0,0 +1,412 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use hash::H256;
+use crypto::MuHash3072;
+use chain::{OutPoint, Transaction, TransactionOutput, BlockHeader, IndexedBlockHeader};
+use ser::{Stream, Reader, Serializable, Deserializable, Error as ReaderError, serialize};
+use kv::{KeyValueSnapshot, Key, KeyState};
+use {BestBlock, Error, TransactionMeta};
+
+/// Size of the unspent output in the UTXO set, excluding the output script.
+const BOGO_OUTPUT_SIZE: u64 = 50;
+/// Outputs with longer scripts are unspendable.
+const MAX_SCRIPT_SIZE: usize = 10000;
+const OP_RETURN: u8 = 0x6a;
+/// Magic bytes of the unspent outputs snapshot file.
+const SNAPSHOT_FILE_MAGIC: [u8; 5] = [b'u', b't', b'x', b'o', 0xff];
+/// Version of the unspent outputs snapshot file format.
+const SNAPSHOT_FILE_VERSION: u16 = 1;
+
+/// Unspent transaction output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnspentOutput {
+	/// Output reference.
+	pub outpoint: OutPoint,
+	/// Height of the block, which includes the transaction.
+	pub height: u32,
+	/// Is output created by the coinbase transaction?
+	pub is_coinbase: bool,
+	/// The output itself.
+	pub output: TransactionOutput,
+}
+
+impl Serializable for UnspentOutput {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.outpoint)
+			.append(&(self.height * 2 + self.is_coinbase as u32))
+			.append(&self.output);
+	}
+}
+
+impl Deserializable for UnspentOutput {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let outpoint = reader.read()?;
+		let code: u32 = reader.read()?;
+		let output = reader.read()?;
+		Ok(UnspentOutput {
+			outpoint: outpoint,
+			height: code / 2,
+			is_coinbase: code & 1 == 1,
+			output: output,
+		})
+	}
+}
+
+/// Statistics of the unspent outputs set.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UtxoStats {
+	/// Number of transactions with unspent outputs.
+	pub transactions: u64,
+	/// Number of unspent outputs.
+	pub txouts: u64,
+	/// Database independent size of the set: 50 bytes per output plus size of its script.
+	pub bogosize: u64,
+	/// Total value of all unspent outputs.
+	pub total_amount: u64,
+	/// Size of transactions and their meta records, holding unspent outputs, in the database.
+	pub disk_size: u64,
+	/// MuHash3072 of the serialized unspent outputs.
+	pub muhash: H256,
+}
+
+/// Metadata of the unspent outputs snapshot file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtxoSnapshotMetadata {
+	/// Magic of the network, which blocks are in the snapshot.
+	pub network_magic: u32,
+	/// Best block of the snapshot. All outputs are unspent as of this block.
+	pub base_block: BestBlock,
+	/// Number of unspent outputs in the file.
+	pub coins: u64,
+}
+
+impl Serializable for UtxoSnapshotMetadata {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append_slice(&SNAPSHOT_FILE_MAGIC)
+			.append(&SNAPSHOT_FILE_VERSION)
+			.append(&self.network_magic)
+			.append(&self.base_block.hash)
+			.append(&self.base_block.number)
+			.append(&self.coins);
+	}
+}
+
+impl Deserializable for UtxoSnapshotMetadata {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let mut magic = [0u8; 5];
+		reader.read_slice(&mut magic)?;
+		let version: u16 = reader.read()?;
+		if magic != SNAPSHOT_FILE_MAGIC || version != SNAPSHOT_FILE_VERSION {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let network_magic = reader.read()?;
+		let hash = reader.read()?;
+		let number = reader.read()?;
+		let coins = reader.read()?;
+		Ok(UtxoSnapshotMetadata {
+			network_magic: network_magic,
+			base_block: BestBlock {
+				number: number,
+				hash: hash,
+			},
+			coins: coins,
+		})
+	}
+}
+
+/// Reader of the unspent outputs snapshot file, written by `UtxoSnapshot::write_to`.
+///
+/// The file consists of the metadata, headers of all blocks from the genesis to the base block and
+/// serialized outputs, which are read in this order.
+pub struct UtxoSnapshotReader<R> {
+	reader: Reader<R>,
+	metadata: UtxoSnapshotMetadata,
+	headers_read: u32,
+	coins_read: u64,
+	previous_header_hash: H256,
+}
+
+impl<R> UtxoSnapshotReader<R> where R: io::Read {
+	/// Reads metadata of the snapshot file.
+	pub fn new(read: R) -> Result<Self, Error> {
+		let mut reader = Reader::from_read(read);
+		let metadata = reader.read().map_err(snapshot_read_error)?;
+		Ok(UtxoSnapshotReader {
+			reader: reader,
+			metadata: metadata,
+			headers_read: 0,
+			coins_read: 0,
+			previous_header_hash: H256::default(),
+		})
+	}
+
+	/// Metadata of the snapshot file.
+	pub fn metadata(&self) -> &UtxoSnapshotMetadata {
+		&self.metadata
+	}
+
+	/// Reads the next header. Returns None after the base block header is read.
+	/// Headers are checked to form the chain, which ends with the base block.
+	pub fn read_header(&mut self) -> Result<Option<IndexedBlockHeader>, Error> {
+		if self.headers_read > self.metadata.base_block.number {
+			return Ok(None);
+		}
+
+		let header: IndexedBlockHeader = self.reader.read().map_err(snapshot_read_error)?;
+		if header.raw.previous_header_hash != self.previous_header_hash {
+			return Err(Error::InvalidSnapshot(format!("Header {} is not a child of the previous header", self.headers_read)));
+		}
+		if self.headers_read == self.metadata.base_block.number && header.hash != self.metadata.base_block.hash {
+			return Err(Error::InvalidSnapshot("Headers chain doesn't end with the base block".into()));
+		}
+
+		self.headers_read += 1;
+		self.previous_header_hash = header.hash.clone();
+		Ok(Some(header))
+	}
+
+	/// Reads the next unspent output. Returns None after all outputs are read.
+	/// Must be called after all headers are read.
+	pub fn read_output(&mut self) -> Result<Option<UnspentOutput>, Error> {
+		if self.headers_read <= self.metadata.base_block.number {
+			return Err(Error::InvalidSnapshot("Headers must be read before outputs".into()));
+		}
+
+		if self.coins_read == self.metadata.coins {
+			if !self.reader.is_finished() {
+				return Err(Error::InvalidSnapshot("Unexpected data after the last output".into()));
+			}
+			return Ok(None);
+		}
+
+		let output: UnspentOutput = self.reader.read().map_err(snapshot_read_error)?;
+		if output.height == 0 || output.height > self.metadata.base_block.number {
+			return Err(Error::InvalidSnapshot(format!("Invalid height of the output {}:{}", output.outpoint.hash.to_reversed_str(), output.outpoint.index)));
+		}
+
+		self.coins_read += 1;
+		Ok(Some(output))
+	}
+
+	/// Reads the rest of the file, computing MuHash of the outputs, which is compared with the
+	/// MuHash of the set of the node, which has created the snapshot.
+	pub fn verify(&mut self) -> Result<H256, Error> {
+		while self.read_header()?.is_some() {}
+
+		let mut muhash = MuHash3072::default();
+		while let Some(output) = self.read_output()? {
+			muhash.insert(&serialize(&output));
+		}
+		Ok(muhash.finalize())
+	}
+}
+
+/// Progress of the unspent outputs scan. It is shared with other threads to report the progress
+/// and to abort the scan.
+#[derive(Debug, Default)]
+pub struct UtxoScanProgress {
+	/// Two first bytes of the last scanned transaction hash. Transactions are iterated in the order of their hashes.
+	position: AtomicUsize,
+	/// True if scan should be stopped.
+	aborted: AtomicBool,
+}
+
+impl UtxoScanProgress {
+	/// Scanned part of the set, from 0 to 100.
+	pub fn percentage(&self) -> f64 {
+		self.position.load(Ordering::Relaxed) as f64 * 100.0 / 65536.0
+	}
+
+	/// Stops the scan before the next transaction.
+	pub fn abort(&self) {
+		self.aborted.store(true, Ordering::Relaxed);
+	}
+
+	/// Returns true if scan has been aborted.
+	pub fn is_aborted(&self) -> bool {
+		self.aborted.load(Ordering::Relaxed)
+	}
+
+	fn set_position(&self, hash: &H256) {
+		self.position.store((hash[0] as usize) << 8 | hash[1] as usize, Ordering::Relaxed);
+	}
+}
+
+/// Unspent transaction outputs set at the moment of the snapshot creation.
+pub struct UtxoSnapshot<'a> {
+	best_block: BestBlock,
+	snapshot: Box<KeyValueSnapshot + 'a>,
+}
+
+impl<'a> UtxoSnapshot<'a> {
+	pub fn new(best_block: BestBlock, snapshot: Box<KeyValueSnapshot + 'a>) -> Self {
+		UtxoSnapshot {
+			best_block: best_block,
+			snapshot: snapshot,
+		}
+	}
+
+	/// Best block of the snapshot. All outputs are unspent as of this block.
+	pub fn best_block(&self) -> &BestBlock {
+		&self.best_block
+	}
+
+	/// Iterates all unspent outputs. Order of outputs is not defined.
+	pub fn unspent_outputs<'b>(&'b self) -> Box<Iterator<Item = Result<UnspentOutput, Error>> + 'b> {
+		Box::new(self.snapshot.transactions_meta()
+			.flat_map(move |meta| match meta {
+				Ok((hash, meta)) => self.transaction_unspent_outputs(hash, meta),
+				Err(err) => vec![Err(Error::DatabaseError(err))],
+			}))
+	}
+
+	/// Iterates unspent outputs in the order of transaction hashes, updating the scan progress.
+	/// Iteration stops early when scan is aborted.
+	pub fn scan_unspent_outputs<'b>(&'b self, progress: &'b UtxoScanProgress) -> Box<Iterator<Item = Result<UnspentOutput, Error>> + 'b> {
+		Box::new(self.snapshot.transactions_meta()
+			.take_while(move |_| !progress.is_aborted())
+			.flat_map(move |meta| match meta {
+				Ok((hash, meta)) => {
+					progress.set_position(&hash);
+					self.transaction_unspent_outputs(hash, meta)
+				},
+				Err(err) => vec![Err(Error::DatabaseError(err))],
+			}))
+	}
+
+	/// Writes the snapshot file, which may be loaded by the other node: the metadata, headers of all
+	/// blocks up to the best block and all outputs, which are included into the set statistics.
+	/// Returns statistics of the written set.
+	pub fn write_to<W>(&self, writer: &mut W, network_magic: u32) -> Result<UtxoStats, Error> where W: io::Write {
+		let stats = self.stats()?;
+		let metadata = UtxoSnapshotMetadata {
+			network_magic: network_magic,
+			base_block: self.best_block.clone(),
+			coins: stats.txouts,
+		};
+		writer.write_all(&serialize(&metadata)).map_err(snapshot_write_error)?;
+
+		for number in 0..self.best_block.number + 1 {
+			let header = self.block_header(number)?;
+			writer.write_all(&serialize(&header)).map_err(snapshot_write_error)?;
+		}
+
+		for output in self.unspent_outputs() {
+			let output = output?;
+			if output.height == 0 || is_unspendable(&output.output) {
+				continue;
+			}
+
+			writer.write_all(&serialize(&output)).map_err(snapshot_write_error)?;
+		}
+
+		Ok(stats)
+	}
+
+	/// Computes statistics of the set.
+	///
+	/// Outputs, which are never added to the set by other implementations, are skipped: unspendable
+	/// outputs and output of the genesis block coinbase. Each output is added to MuHash in the same
+	/// serialization as other implementations are using: outpoint, height and coinbase flag as
+	/// `height * 2 + is_coinbase` and the output itself.
+	pub fn stats(&self) -> Result<UtxoStats, Error> {
+		let mut stats = UtxoStats::default();
+		let mut muhash = MuHash3072::default();
+		for meta in self.snapshot.transactions_meta() {
+			let (hash, meta) = meta.map_err(Error::DatabaseError)?;
+			if meta.is_fully_spent() || meta.height() == 0 {
+				continue;
+			}
+
+			let transaction = self.transaction(&hash)?;
+			let mut unspent_outputs = 0;
+			for (index, output) in transaction.outputs.iter().enumerate() {
+				if meta.is_spent(index) != Some(false) || is_unspendable(output) {
+					continue;
+				}
+
+				muhash.insert(&serialize(&UnspentOutput {
+					outpoint: OutPoint { hash: hash.clone(), index: index as u32 },
+					height: meta.height(),
+					is_coinbase: meta.is_coinbase(),
+					output: output.clone(),
+				}));
+
+				unspent_outputs += 1;
+				stats.bogosize += BOGO_OUTPUT_SIZE + output.script_pubkey.len() as u64;
+				stats.total_amount += output.value;
+			}
+
+			if unspent_outputs != 0 {
+				stats.transactions += 1;
+				stats.txouts += unspent_outputs;
+				// records are keyed by the transaction hash
+				stats.disk_size += (2 * hash.len() + serialize(&meta).len() + serialize(&transaction).len()) as u64;
+			}
+		}
+
+		stats.muhash = muhash.finalize();
+		Ok(stats)
+	}
+
+	fn block_header(&self, number: u32) -> Result<BlockHeader, Error> {
+		let get = |key| self.snapshot.get(&key)
+			.map(KeyState::into_option)
+			.map_err(Error::DatabaseError);
+		let hash = get(Key::BlockHash(number))?.and_then(|value| value.as_block_hash());
+		let header = match hash {
+			Some(hash) => get(Key::BlockHeader(hash))?.and_then(|value| value.as_block_header()),
+			None => None,
+		};
+		header.ok_or_else(|| Error::DatabaseError(format!("Inconsistent DB. Missing canon block header {}", number)))
+	}
+
+	fn transaction(&self, hash: &H256) -> Result<Transaction, Error> {
+		let transaction = self.snapshot.get(&Key::Transaction(hash.clone()))
+			.map(KeyState::into_option)
+			.map_err(Error::DatabaseError)?
+			.and_then(|value| value.as_transaction());
+		transaction.ok_or_else(|| Error::DatabaseError(format!("Inconsistent DB. Missing transaction {}", hash.to_reversed_str())))
+	}
+
+	fn transaction_unspent_outputs(&self, hash: H256, meta: TransactionMeta) -> Vec<Result<UnspentOutput, Error>> {
+		if meta.is_fully_spent() {
+			return Vec::new();
+		}
+
+		let transaction = match self.transaction(&hash) {
+			Ok(transaction) => transaction,
+			Err(err) => return vec![Err(err)],
+		};
+
+		transaction.outputs.into_iter()
+			.enumerate()
+			.filter(|&(index, _)| meta.is_spent(index) == Some(false))
+			.map(|(index, output)| Ok(UnspentOutput {
+				outpoint: OutPoint {
+					hash: hash.clone(),
+					index: index as u32,
+				},
+				height: meta.height(),
+				is_coinbase: meta.is_coinbase(),
+				output: output,
+			}))
+			.collect()
+	}
+}
+
+/// Returns true if output can never be spent, so it's not added to the set by other implementations.
+pub fn is_unspendable(output: &TransactionOutput) -> bool {
+	output.script_pubkey.len() > MAX_SCRIPT_SIZE || output.script_pubkey.first() == Some(&OP_RETURN)
+}
+
+fn snapshot_read_error(err: ReaderError) -> Error {
+	Error::InvalidSnapshot(format!("{:?}", err))
+}
+
+fn snapshot_write_error(err: io::Error) -> Error {
+	Error::InvalidSnapshot(err.to_string())
+}
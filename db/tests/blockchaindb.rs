@@ -1,10 +1,19 @@
 extern crate chain;
 extern crate db;
+extern crate serialization as ser;
 extern crate test_data;
 
-use chain::IndexedBlock;
-use db::kv::{MemoryDatabase, SharedMemoryDatabase};
-use db::{BlockChainDatabase, BlockProvider, SideChainOrigin, ForkChain};
+use std::collections::HashMap;
+use chain::{IndexedBlock, OutPoint};
+use chain::bigint::U256;
+use chain::compact::Compact;
+use ser::serialize;
+use db::kv::{MemoryDatabase, SharedMemoryDatabase, KeyValueDatabase, Transaction as DBTransaction, Key};
+use db::{
+	BlockChainDatabase, BlockProvider, BlockRef, BlockOrigin, SideChainOrigin, ForkChain, CanonStore, TransactionMetaProvider,
+	BlockChain, BlockFilterProvider, TransactionOutputProvider, AddressIndexProvider, BlockPruning, BlockHeaderProvider,
+	BlockUndoProvider, TransactionIndexProvider, ChainStatsProvider, BlockInvalidation, ChainReorganization, HeaderTree, Error, PruneMode, UtxoScanProgress, UtxoStats, UtxoSnapshotMetadata, UtxoSnapshotReader, MIN_BLOCKS_TO_KEEP, filter_header, script_hash
+};
 
 #[test]
 fn insert_block() {
@@ -46,6 +55,18 @@ fn insert_block() {
 	assert!(store.block_number(b2.hash()).is_none());
 }
 
+#[test]
+fn block_bytes() {
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	let b0: IndexedBlock = test_data::block_h0().into();
+	store.insert(b0.clone()).unwrap();
+	store.canonize(b0.hash()).unwrap();
+
+	assert_eq!(store.block_bytes(BlockRef::Number(0)), Some(serialize(&test_data::block_h0())));
+	assert_eq!(store.block_bytes(BlockRef::Hash(b0.hash().clone())), Some(serialize(&test_data::block_h0())));
+	assert!(store.block_bytes(BlockRef::Number(1)).is_none());
+}
+
 #[test]
 fn reopen_db() {
 	let shared_database = SharedMemoryDatabase::default();
@@ -73,6 +94,167 @@ fn reopen_db() {
 	}
 }
 
+#[test]
+fn rollback_to_block() {
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+	for block in vec![&b0, &b1, &b2] {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	// rollback to the block above the best block does nothing
+	assert_eq!(b2.hash(), &store.rollback_to(5).unwrap());
+	assert_eq!(2, store.best_block().number);
+
+	assert_eq!(b0.hash(), &store.rollback_to(0).unwrap());
+	assert_eq!(0, store.best_block().number);
+	assert!(store.block_hash(1).is_none());
+	assert!(!store.contains_block(BlockRef::Hash(b1.hash().clone())));
+	assert!(!store.contains_block(BlockRef::Hash(b2.hash().clone())));
+	assert!(store.transaction_meta(&b1.transactions[0].hash).is_none());
+	assert!(store.transaction_meta(&b0.transactions[0].hash).is_some());
+}
+
+#[test]
+fn utxo_snapshot() {
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+	for block in vec![&b0, &b1] {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	let snapshot = store.utxo_snapshot().unwrap();
+	// snapshot is not affected by later changes
+	store.insert(b2.clone()).unwrap();
+	store.canonize(b2.hash()).unwrap();
+
+	assert_eq!(snapshot.best_block().number, 1);
+	assert_eq!(b1.hash(), &snapshot.best_block().hash);
+
+	let mut outputs: Vec<_> = snapshot.unspent_outputs().map(Result::unwrap).collect();
+	outputs.sort_by_key(|output| output.height);
+	assert_eq!(outputs.len(), 2);
+	assert_eq!(outputs[0].outpoint.hash, b0.transactions[0].hash);
+	assert_eq!(outputs[1].outpoint.hash, b1.transactions[0].hash);
+	assert_eq!(outputs[1].height, 1);
+	assert!(outputs[1].is_coinbase);
+	assert_eq!(outputs[1].output, b1.transactions[0].raw.outputs[0]);
+}
+
+#[test]
+fn utxo_snapshot_scan() {
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	for block in vec![&b0, &b1] {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	let snapshot = store.utxo_snapshot().unwrap();
+	let progress = UtxoScanProgress::default();
+	let outputs: Vec<_> = snapshot.scan_unspent_outputs(&progress).map(Result::unwrap).collect();
+	assert_eq!(outputs.len(), 2);
+	assert!(outputs[0].outpoint.hash[..] < outputs[1].outpoint.hash[..]);
+	let last = &outputs[1].outpoint.hash;
+	assert_eq!(progress.percentage(), ((last[0] as usize) << 8 | last[1] as usize) as f64 * 100.0 / 65536.0);
+
+	progress.abort();
+	assert!(progress.is_aborted());
+	assert_eq!(snapshot.scan_unspent_outputs(&progress).count(), 0);
+}
+
+#[test]
+fn utxo_snapshot_stats() {
+	let store = BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]);
+	let snapshot = store.utxo_snapshot().unwrap();
+	// genesis coinbase is not a part of the set
+	assert_eq!(snapshot.stats().unwrap(), UtxoStats {
+		transactions: 2,
+		txouts: 2,
+		bogosize: 2 * (50 + 67),
+		total_amount: 10_000_000_000,
+		disk_size: 2 * (64 + 6 + 134),
+		muhash: "75c825c725abc4791ea245e81ce869d5e1417809404df098a8a0ba9541c3d1df".into(),
+	});
+
+	let empty = BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into()]);
+	assert_eq!(empty.utxo_snapshot().unwrap().stats().unwrap().muhash, "c85525462fdcf30a2c18d6f4b92923000974355c2477f59594d2c205a1d25add".into());
+}
+
+#[test]
+fn utxo_snapshot_load() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.outputs_per_transaction(2);
+	let store = BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]);
+	for block in generator.generate(20) {
+		let block: IndexedBlock = block.into();
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	let mut file = Vec::new();
+	let stats = store.utxo_snapshot().unwrap().write_to(&mut file, 0x0709110b).unwrap();
+
+	let mut reader = UtxoSnapshotReader::new(&file[..]).unwrap();
+	assert_eq!(reader.metadata(), &UtxoSnapshotMetadata {
+		network_magic: 0x0709110b,
+		base_block: store.best_block(),
+		coins: stats.txouts,
+	});
+	assert_eq!(reader.verify(), Ok(stats.muhash.clone()));
+
+	// snapshot is only loaded into the new database
+	assert!(store.load_utxo_snapshot(&mut &file[..]).is_err());
+	let other_chain = BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]);
+	assert!(other_chain.load_utxo_snapshot(&mut &file[..]).is_err());
+
+	let loaded = BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]);
+	assert_eq!(loaded.load_utxo_snapshot(&mut &file[..]), Ok(store.best_block()));
+	assert_eq!(loaded.best_block(), store.best_block());
+	assert_eq!(loaded.pruned_blocks(), 21);
+	assert_eq!(loaded.block_header(10.into()), store.block_header(10.into()));
+	assert!(loaded.block(10.into()).is_none());
+	assert_eq!(loaded.utxo_snapshot().unwrap().stats().unwrap().muhash, stats.muhash);
+
+	// new blocks are canonized on top of the snapshot base block
+	for block in generator.generate(5) {
+		let block: IndexedBlock = block.into();
+		for store in vec![&store, &loaded] {
+			store.insert(block.clone()).unwrap();
+			store.canonize(block.hash()).unwrap();
+		}
+	}
+	assert_eq!(loaded.best_block(), store.best_block());
+	assert_eq!(loaded.utxo_snapshot().unwrap().stats().unwrap().muhash, store.utxo_snapshot().unwrap().stats().unwrap().muhash);
+
+	// truncated snapshot is rejected
+	assert!(UtxoSnapshotReader::new(&file[..file.len() - 1]).unwrap().verify().is_err());
+}
+
+#[test]
+fn flush_cached_db() {
+	let shared_database = SharedMemoryDatabase::default();
+	let b0: IndexedBlock = test_data::block_h0().into();
+
+	let store = BlockChainDatabase::open_with_cache(shared_database.clone());
+	store.insert(b0.clone()).unwrap();
+	store.canonize(b0.hash()).unwrap();
+	assert!(BlockChainDatabase::open(shared_database.clone()).block_hash(0).is_none());
+
+	store.flush().unwrap();
+	let reopened = BlockChainDatabase::open(shared_database);
+	assert_eq!(b0.hash(), &reopened.block_hash(0).unwrap());
+	assert_eq!(b0.hash(), &reopened.best_block().hash);
+}
+
 #[test]
 fn switch_to_simple_fork() {
 	let store = BlockChainDatabase::open(MemoryDatabase::default());
@@ -126,3 +308,650 @@ fn switch_to_simple_fork() {
 	assert_eq!(store.best_block().hash, store.block_hash(2).unwrap());
 
 }
+
+#[test]
+fn apply_generated_chain_with_reorg() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let store = BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]);
+
+	for block in generator.generate(10) {
+		let block: IndexedBlock = block.into();
+		match store.block_origin(&block.header).unwrap() {
+			BlockOrigin::KnownBlock => unreachable!(),
+			BlockOrigin::CanonChain { .. } => {
+				store.insert(block.clone()).unwrap();
+				store.canonize(block.hash()).unwrap();
+			},
+			BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				let fork = store.fork(origin).unwrap();
+				fork.store().insert(block.clone()).unwrap();
+				fork.store().canonize(block.hash()).unwrap();
+				store.switch_to_fork(fork).unwrap();
+			},
+		}
+	}
+
+	assert_eq!(generator.height(), store.best_block().number);
+	assert_eq!(generator.best_block().hash(), store.best_block().hash);
+	for (number, block) in generator.blocks().iter().enumerate() {
+		assert_eq!(Some(block.hash()), store.block_hash(number as u32));
+		for transaction in block.transactions.iter().skip(1) {
+			assert!(store.transaction_meta(&transaction.hash()).is_some());
+		}
+	}
+}
+
+#[test]
+fn compact_block_filters_index() {
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	for block in vec![b0.clone(), b1.clone()] {
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+	assert!(store.block_filter(b0.hash()).is_none());
+
+	// blocks, canonized before index has been enabled, are indexed on request
+	let store = store.with_cfindex(true);
+	store.insert(b2.clone()).unwrap();
+	assert_eq!(store.canonize(b2.hash()), Err(Error::UnindexedParentFilter));
+	assert_eq!(store.build_cfindex(), Ok(2));
+	let filter0 = store.block_filter(b0.hash()).unwrap();
+	let filter1 = store.block_filter(b1.hash()).unwrap();
+	assert_eq!(filter0, "017fa880".into());
+	let header0 = store.block_filter_header(b0.hash()).unwrap();
+	assert_eq!(header0, filter_header(&filter0, &Default::default()));
+	assert_eq!(store.block_filter_header(b1.hash()), Some(filter_header(&filter1, &header0)));
+
+	// new blocks are indexed when canonized
+	store.canonize(b2.hash()).unwrap();
+	assert!(store.block_filter(b2.hash()).is_some());
+	assert_eq!(store.build_cfindex(), Ok(0));
+
+	store.rollback_best().unwrap();
+	assert!(store.block_filter(b2.hash()).is_none());
+	assert!(store.block_filter_header(b2.hash()).is_none());
+}
+
+#[test]
+fn utxo_cache_follows_canon_chain() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let genesis: IndexedBlock = generator.genesis().clone().into();
+	let store = BlockChainDatabase::init_test_chain(vec![genesis.clone()]).with_utxo_cache(1);
+	let uncached_store = BlockChainDatabase::init_test_chain(vec![genesis]);
+
+	for block in generator.generate(10) {
+		let block: IndexedBlock = block.into();
+		for store in vec![&store, &uncached_store] {
+			match store.block_origin(&block.header).unwrap() {
+				BlockOrigin::KnownBlock => unreachable!(),
+				BlockOrigin::CanonChain { .. } => {
+					store.insert(block.clone()).unwrap();
+					store.canonize(block.hash()).unwrap();
+				},
+				BlockOrigin::SideChain(_) => store.insert(block.clone()).unwrap(),
+				BlockOrigin::SideChainBecomingCanonChain(origin) => {
+					let fork = store.fork(origin).unwrap();
+					fork.store().insert(block.clone()).unwrap();
+					fork.store().canonize(block.hash()).unwrap();
+					store.switch_to_fork(fork).unwrap();
+				},
+			}
+		}
+	}
+
+	store.rollback_best().unwrap();
+	uncached_store.rollback_best().unwrap();
+
+	for block in generator.blocks() {
+		for transaction in &block.transactions {
+			for index in 0..transaction.outputs.len() {
+				let outpoint = OutPoint { hash: transaction.hash(), index: index as u32 };
+				assert_eq!(store.transaction_output(&outpoint, 0), uncached_store.transaction_output(&outpoint, 0));
+				assert_eq!(store.is_spent(&outpoint), uncached_store.is_spent(&outpoint));
+			}
+		}
+	}
+}
+
+#[test]
+fn address_index_follows_canon_chain() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let genesis: IndexedBlock = generator.genesis().clone().into();
+	let script = genesis.transactions[0].raw.outputs[0].script_pubkey.clone();
+
+	// blocks, canonized before index has been enabled, are indexed on request
+	let store = BlockChainDatabase::init_test_chain(vec![genesis]).with_address_index(true);
+	assert!(store.address_outputs(&script_hash(&script)).is_empty());
+	assert_eq!(store.build_address_index(), Ok(1));
+	assert_eq!(store.address_outputs(&script_hash(&script)).len(), 4);
+
+	for block in generator.generate(10) {
+		let block: IndexedBlock = block.into();
+		match store.block_origin(&block.header).unwrap() {
+			BlockOrigin::KnownBlock => unreachable!(),
+			BlockOrigin::CanonChain { .. } => {
+				store.insert(block.clone()).unwrap();
+				store.canonize(block.hash()).unwrap();
+			},
+			BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				let fork = store.fork(origin).unwrap();
+				fork.store().insert(block.clone()).unwrap();
+				fork.store().canonize(block.hash()).unwrap();
+				store.switch_to_fork(fork).unwrap();
+			},
+		}
+	}
+	store.rollback_best().unwrap();
+	assert_eq!(store.build_address_index(), Ok(0));
+
+	let mut canon_outputs = 0;
+	for number in 0..store.best_block().number + 1 {
+		let block = store.block(number.into()).unwrap();
+		canon_outputs += block.transactions.iter()
+			.flat_map(|tx| tx.outputs.iter())
+			.filter(|output| output.script_pubkey == script)
+			.count();
+	}
+
+	let outputs = store.address_outputs(&script_hash(&script));
+	assert_eq!(outputs.len(), canon_outputs);
+	for output in outputs {
+		let outpoint = OutPoint { hash: output.transaction_hash.clone(), index: output.index };
+		assert!(store.transaction_meta(&outpoint.hash).is_some());
+		assert_eq!(output.spent_by.is_some(), store.is_spent(&outpoint));
+	}
+}
+
+#[test]
+fn pruned_blocks_keep_unspent_outputs() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2);
+	let store = BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]);
+	for block in generator.generate(MIN_BLOCKS_TO_KEEP + 12) {
+		let block: IndexedBlock = block.into();
+		store.insert(block.clone()).unwrap();
+		store.canonize(block.hash()).unwrap();
+	}
+
+	// nothing is pruned until pruning is enabled
+	assert_eq!(store.prune_blocks(10), Ok(0));
+	assert!(store.block(0.into()).is_some());
+
+	// blocks are pruned to the target on startup, best blocks are never pruned
+	let store = store.with_prune(PruneMode::Target(0));
+	assert_eq!(store.init_pruning(), Ok(13));
+	assert_eq!(store.pruned_blocks(), 13);
+	assert!(store.block(12.into()).is_none());
+	assert!(store.block_header(12.into()).is_some());
+	assert!(store.block(13.into()).is_some());
+
+	// new blocks are pruned when canonized
+	let block: IndexedBlock = generator.generate(1).pop().unwrap().into();
+	store.insert(block.clone()).unwrap();
+	store.canonize(block.hash()).unwrap();
+	assert_eq!(store.pruned_blocks(), 14);
+
+	// all best blocks can be decanonized
+	store.rollback_to(14).unwrap();
+	assert!(store.rollback_best().is_ok());
+	assert!(store.decanonize().is_err());
+
+	for block in generator.blocks().iter().take(14) {
+		for transaction in &block.transactions {
+			for index in 0..transaction.outputs.len() {
+				let outpoint = OutPoint { hash: transaction.hash(), index: index as u32 };
+				if !store.is_spent(&outpoint) {
+					assert_eq!(store.transaction_output(&outpoint, 0).as_ref(), Some(&transaction.outputs[index]));
+				}
+			}
+		}
+	}
+
+	// filters of the pruned blocks can't be built
+	assert_eq!(store.with_cfindex(true).build_cfindex(), Err(Error::PrunedBlocks));
+}
+
+#[test]
+fn block_undo_follows_canon_chain() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let store = BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]);
+	let mut decanonized = Vec::new();
+
+	for block in generator.generate(10) {
+		let block: IndexedBlock = block.into();
+		match store.block_origin(&block.header).unwrap() {
+			BlockOrigin::KnownBlock => unreachable!(),
+			BlockOrigin::CanonChain { .. } => {
+				store.insert(block.clone()).unwrap();
+				store.canonize(block.hash()).unwrap();
+			},
+			BlockOrigin::SideChain(_) => store.insert(block.clone()).unwrap(),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				decanonized.extend(origin.decanonized_route.clone());
+				let fork = store.fork(origin).unwrap();
+				fork.store().insert(block.clone()).unwrap();
+				fork.store().canonize(block.hash()).unwrap();
+				store.switch_to_fork(fork).unwrap();
+			},
+		}
+	}
+
+	// undo data of the decanonized blocks is deleted
+	assert!(!decanonized.is_empty());
+	for hash in decanonized.iter().filter(|hash| store.block_number(hash).is_none()) {
+		assert_eq!(store.block_undo(hash), None);
+	}
+
+	// undo data of the canon blocks holds outputs, spent by the block inputs
+	let transactions: HashMap<_, _> = generator.blocks().iter()
+		.flat_map(|block| block.transactions.iter())
+		.map(|tx| (tx.hash(), tx.clone()))
+		.collect();
+	for number in 1..store.best_block().number + 1 {
+		let block = store.block(number.into()).unwrap();
+		let undo = store.block_undo(&block.hash()).unwrap();
+		let spent_outputs: Vec<_> = block.transactions.iter()
+			.skip(1)
+			.flat_map(|tx| tx.inputs.iter())
+			.map(|input| transactions[&input.previous_output.hash].outputs[input.previous_output.index as usize].clone())
+			.collect();
+		assert_eq!(undo.spent_outputs.iter().map(|spent| spent.output.clone()).collect::<Vec<_>>(), spent_outputs);
+	}
+
+	// spent outputs are restored when the block is decanonized
+	let best_block = store.best_block();
+	let block = store.block(best_block.number.into()).unwrap();
+	store.rollback_best().unwrap();
+	assert_eq!(store.block_undo(&best_block.hash), None);
+	for input in block.transactions.iter().skip(1).flat_map(|tx| tx.inputs.iter()) {
+		assert!(!store.is_spent(&input.previous_output));
+	}
+}
+
+#[test]
+fn transaction_index_follows_canon_chain() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let genesis: IndexedBlock = generator.genesis().clone().into();
+	let genesis_tx = genesis.transactions[0].hash.clone();
+
+	// blocks, canonized before index has been enabled, are indexed on request
+	let store = BlockChainDatabase::init_test_chain(vec![genesis.clone()]).with_transaction_index(true);
+	assert_eq!(store.transaction_location(&genesis_tx), None);
+	assert_eq!(store.build_transaction_index(), Ok(1));
+	let location = store.transaction_location(&genesis_tx).unwrap();
+	assert_eq!(location.block_hash, *genesis.hash());
+	assert_eq!(location.index, 0);
+
+	let mut decanonized_transactions = Vec::new();
+	for block in generator.generate(10) {
+		let block: IndexedBlock = block.into();
+		match store.block_origin(&block.header).unwrap() {
+			BlockOrigin::KnownBlock => unreachable!(),
+			BlockOrigin::CanonChain { .. } => {
+				store.insert(block.clone()).unwrap();
+				store.canonize(block.hash()).unwrap();
+			},
+			BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				for hash in &origin.decanonized_route {
+					decanonized_transactions.extend(store.block_transaction_hashes(hash.clone().into()));
+				}
+				let fork = store.fork(origin).unwrap();
+				fork.store().insert(block.clone()).unwrap();
+				fork.store().canonize(block.hash()).unwrap();
+				store.switch_to_fork(fork).unwrap();
+			},
+		}
+	}
+	let rolled_back = store.block_transaction_hashes(store.best_block().hash.into());
+	store.rollback_best().unwrap();
+	assert_eq!(store.build_transaction_index(), Ok(0));
+
+	for number in 0..store.best_block().number + 1 {
+		let hash = store.block_hash(number).unwrap();
+		for (index, tx_hash) in store.block_transaction_hashes(number.into()).into_iter().enumerate() {
+			let location = store.transaction_location(&tx_hash).unwrap();
+			assert_eq!(location.block_hash, hash);
+			assert_eq!(location.index, index as u32);
+		}
+	}
+
+	assert!(!decanonized_transactions.is_empty());
+	for tx_hash in decanonized_transactions.into_iter().chain(rolled_back) {
+		if store.transaction_meta(&tx_hash).is_none() {
+			assert_eq!(store.transaction_location(&tx_hash), None);
+		}
+	}
+}
+
+#[test]
+fn chain_stats_follow_blocks_tree() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let genesis: IndexedBlock = generator.genesis().clone().into();
+	let store = BlockChainDatabase::init_test_chain(vec![genesis.clone()]);
+	assert_eq!(store.chain_tips(), vec![genesis.hash().clone()]);
+	assert_eq!(store.chain_tx_count(genesis.hash()), Some(1));
+
+	let mut side_chain_tip = None;
+	for block in generator.generate(10) {
+		let block: IndexedBlock = block.into();
+		match store.block_origin(&block.header).unwrap() {
+			BlockOrigin::KnownBlock => unreachable!(),
+			BlockOrigin::CanonChain { .. } => {
+				store.insert(block.clone()).unwrap();
+				store.canonize(block.hash()).unwrap();
+			},
+			BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				side_chain_tip = origin.decanonized_route.last().cloned();
+				let fork = store.fork(origin).unwrap();
+				fork.store().insert(block.clone()).unwrap();
+				fork.store().canonize(block.hash()).unwrap();
+				store.switch_to_fork(fork).unwrap();
+			},
+		}
+	}
+
+	let side_chain_tip = side_chain_tip.unwrap();
+	let best_block = store.best_block();
+	let mut chain_tips = store.chain_tips();
+	chain_tips.sort();
+	let mut expected_tips = vec![best_block.hash.clone(), side_chain_tip.clone()];
+	expected_tips.sort();
+	assert_eq!(chain_tips, expected_tips);
+
+	// transactions of the decanonized blocks are still counted
+	assert!(store.chain_tx_count(&side_chain_tip).is_some());
+	let mut tx_count = 0;
+	for number in 0..best_block.number + 1 {
+		tx_count += store.block_transaction_hashes(number.into()).len() as u64;
+		assert_eq!(store.chain_tx_count(&store.block_hash(number).unwrap()), Some(tx_count));
+	}
+
+	store.rollback_best().unwrap();
+	let chain_tips = store.chain_tips();
+	assert_eq!(chain_tips.len(), 2);
+	assert!(chain_tips.contains(&store.best_block().hash));
+	assert!(chain_tips.contains(&side_chain_tip));
+	assert_eq!(store.chain_tx_count(&best_block.hash), None);
+}
+
+#[test]
+fn invalidate_and_reconsider_block() {
+	let mut generator = test_data::ChainGenerator::with_genesis_outputs(4, 1_000_000)
+		.transactions_per_block(2)
+		.reorg_at(8, 2);
+	let genesis: IndexedBlock = generator.genesis().clone().into();
+	let store = BlockChainDatabase::init_test_chain(vec![genesis.clone()]);
+
+	let mut old_canon_route = Vec::new();
+	for block in generator.generate(8) {
+		let block: IndexedBlock = block.into();
+		match store.block_origin(&block.header).unwrap() {
+			BlockOrigin::KnownBlock => unreachable!(),
+			BlockOrigin::CanonChain { .. } => {
+				store.insert(block.clone()).unwrap();
+				store.canonize(block.hash()).unwrap();
+			},
+			BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				old_canon_route = origin.decanonized_route.clone();
+				let fork = store.fork(origin).unwrap();
+				fork.store().insert(block.clone()).unwrap();
+				fork.store().canonize(block.hash()).unwrap();
+				store.switch_to_fork(fork).unwrap();
+			},
+		}
+	}
+
+	// canon chain is 0..6 -> 7' -> 8' -> 9', side chain is 6 -> 7 -> 8
+	let best_block = store.best_block();
+	assert_eq!(best_block.number, 9);
+	let new_canon_route = (7..10).map(|number| store.block_hash(number).unwrap()).collect::<Vec<_>>();
+
+	assert_eq!(store.invalidate_block(genesis.hash()), Err(Error::CannotInvalidateGenesis));
+	assert_eq!(store.invalidate_block(&Default::default()), Err(Error::UnknownBlock));
+
+	// when 7' is invalidated, the old canon chain becomes canon again
+	assert_eq!(store.invalidate_block(&new_canon_route[0]), Ok(ChainReorganization {
+		decanonized: new_canon_route.iter().rev().cloned().collect(),
+		canonized: old_canon_route.clone(),
+	}));
+	assert_eq!(store.best_block().hash, old_canon_route[1]);
+	assert_eq!(store.invalid_blocks(), vec![new_canon_route[0].clone()]);
+	assert!(store.is_block_invalid(&new_canon_route[2]));
+	assert!(!store.is_block_invalid(&old_canon_route[1]));
+
+	// descendants of the invalid block are rejected
+	let next_block: IndexedBlock = generator.generate(1).remove(0).into();
+	match store.block_origin(&next_block.header) {
+		Err(Error::InvalidParent) => (),
+		origin => panic!("unexpected block origin: {:?}", origin),
+	}
+
+	// reconsidering descendant of the invalid block removes the mark and restores the longest chain
+	assert_eq!(store.reconsider_block(&new_canon_route[1]), Ok(ChainReorganization {
+		decanonized: old_canon_route.iter().rev().cloned().collect(),
+		canonized: new_canon_route.clone(),
+	}));
+	assert_eq!(store.best_block(), best_block);
+	assert!(store.invalid_blocks().is_empty());
+	assert!(!store.is_block_invalid(&new_canon_route[2]));
+}
+
+#[test]
+fn precious_block() {
+	let b0: IndexedBlock = test_data::block_builder().header().build().build().into();
+	let b1: IndexedBlock = test_data::block_builder().header().nonce(1).parent(b0.hash().clone()).build().build().into();
+	let b2: IndexedBlock = test_data::block_builder().header().nonce(2).parent(b0.hash().clone()).build().build().into();
+	let store = BlockChainDatabase::init_test_chain(vec![b0.clone(), b1.clone()]);
+	store.insert(b2.clone()).unwrap();
+	assert_eq!(store.best_block().hash, *b1.hash());
+
+	assert_eq!(store.precious_block(&Default::default()), Err(Error::UnknownBlock));
+	assert_eq!(store.precious_block(b1.hash()), Ok(ChainReorganization::default()));
+	assert_eq!(store.precious_block(b0.hash()), Ok(ChainReorganization::default()));
+
+	// side chain of the same length becomes canon
+	assert_eq!(store.precious_block(b2.hash()), Ok(ChainReorganization {
+		decanonized: vec![b1.hash().clone()],
+		canonized: vec![b2.hash().clone()],
+	}));
+	assert_eq!(store.best_block().hash, *b2.hash());
+
+	// invalid side chain is ignored
+	store.invalidate_block(b1.hash()).unwrap();
+	assert_eq!(store.precious_block(b1.hash()), Ok(ChainReorganization::default()));
+	store.reconsider_block(b1.hash()).unwrap();
+	assert_eq!(store.best_block().hash, *b2.hash());
+
+	// and the previous canon chain may be preferred again
+	assert_eq!(store.precious_block(b1.hash()), Ok(ChainReorganization {
+		decanonized: vec![b2.hash().clone()],
+		canonized: vec![b1.hash().clone()],
+	}));
+	assert_eq!(store.best_block().hash, *b1.hash());
+}
+
+#[test]
+fn chain_with_most_work_becomes_canon() {
+	// blocks with the maximal target have work of 1
+	let b0: IndexedBlock = test_data::block_builder().header().build().build().into();
+	let b1: IndexedBlock = test_data::block_builder().header().parent(b0.hash().clone()).build().build().into();
+	let b2: IndexedBlock = test_data::block_builder().header().parent(b1.hash().clone()).build().build().into();
+	let store = BlockChainDatabase::init_test_chain(vec![b0.clone(), b1.clone(), b2.clone()]);
+	assert_eq!(store.chain_work(b2.hash()), Some(U256::from(3)));
+
+	// side chain with the same work stays side chain
+	let s1: IndexedBlock = test_data::block_builder().header().nonce(1).parent(b0.hash().clone()).build().build().into();
+	let s2: IndexedBlock = test_data::block_builder().header().nonce(1).parent(s1.hash().clone()).build().build().into();
+	store.insert(s1.clone()).unwrap();
+	match store.block_origin(&s2.header) {
+		Ok(BlockOrigin::SideChain(_)) => (),
+		origin => panic!("unexpected block origin: {:?}", origin),
+	}
+	store.insert(s2.clone()).unwrap();
+	assert_eq!(store.chain_work(s2.hash()), Some(U256::from(3)));
+
+	// shorter side chain with more work becomes canon
+	let bits = Compact::new(0x1d00ffff);
+	let h1: IndexedBlock = test_data::block_builder().header().bits(bits).parent(b0.hash().clone()).build().build().into();
+	match store.block_origin(&h1.header) {
+		Ok(BlockOrigin::SideChainBecomingCanonChain(origin)) => {
+			assert!(origin.canonized_route.is_empty());
+			assert_eq!(origin.decanonized_route, vec![b1.hash().clone(), b2.hash().clone()]);
+			let fork = store.fork(origin).unwrap();
+			fork.store().insert(h1.clone()).unwrap();
+			fork.store().canonize(h1.hash()).unwrap();
+			store.switch_to_fork(fork).unwrap();
+		},
+		origin => panic!("unexpected block origin: {:?}", origin),
+	}
+	assert_eq!(store.best_block().hash, *h1.hash());
+	assert_eq!(store.chain_work(h1.hash()), Some(U256::from(1) + bits.block_work()));
+
+	// when heavy block is invalidated, side chain, which has been seen first, becomes canon again
+	assert_eq!(store.invalidate_block(h1.hash()), Ok(ChainReorganization {
+		decanonized: vec![h1.hash().clone()],
+		canonized: vec![b1.hash().clone(), b2.hash().clone()],
+	}));
+}
+
+#[test]
+fn header_tree_tracks_headers_without_blocks() {
+	// blocks with the maximal target have work of 1
+	let b0: IndexedBlock = test_data::block_builder().header().build().build().into();
+	let b1: IndexedBlock = test_data::block_builder().header().parent(b0.hash().clone()).build().build().into();
+	let store = BlockChainDatabase::init_test_chain(vec![b0.clone(), b1.clone()]);
+	assert_eq!(store.header_tips(), vec![b1.hash().clone()]);
+
+	let h2: IndexedBlock = test_data::block_builder().header().parent(b1.hash().clone()).build().build().into();
+	let h3: IndexedBlock = test_data::block_builder().header().parent(h2.hash().clone()).build().build().into();
+	let h4: IndexedBlock = test_data::block_builder().header().parent(h3.hash().clone()).build().build().into();
+	assert_eq!(store.insert_headers(&[h3.header.clone()]), Err(Error::UnknownParent));
+	store.insert_headers(&[h2.header.clone(), h3.header.clone()]).unwrap();
+	assert_eq!(store.header_tips(), vec![h3.hash().clone()]);
+
+	let entry = store.header_entry(h3.hash()).unwrap();
+	assert_eq!(entry.number, 3);
+	assert_eq!(entry.chain_work, U256::from(4));
+	assert!(!entry.status.has_block);
+	assert_eq!(store.best_header_entry().header, h3.header.raw);
+	assert_eq!(store.best_block().hash, *b1.hash());
+
+	// header stays known, when its block is stored and rolled back
+	store.insert(h2.clone()).unwrap();
+	store.canonize(h2.hash()).unwrap();
+	assert!(store.header_entry(h2.hash()).unwrap().status.has_block);
+	assert_eq!(store.header_tips(), vec![h3.hash().clone()]);
+	store.rollback_best().unwrap();
+	assert!(!store.header_entry(h2.hash()).unwrap().status.has_block);
+
+	// headers of the side chain with more work become the best headers
+	let s2: IndexedBlock = test_data::block_builder().header().nonce(1).parent(b1.hash().clone()).build().build().into();
+	let s3: IndexedBlock = test_data::block_builder().header().nonce(1).parent(s2.hash().clone()).build().build().into();
+	let s4: IndexedBlock = test_data::block_builder().header().nonce(1).parent(s3.hash().clone()).build().build().into();
+	store.insert_headers(&[s2.header.clone(), s3.header.clone(), s4.header.clone()]).unwrap();
+	assert_eq!(store.header_tips(), vec![h3.hash().clone(), s4.hash().clone()]);
+	assert_eq!(store.best_header_entry().header, s4.header.raw);
+
+	// invalid headers and their descendants are never the best headers
+	store.insert_headers(&[h4.header.clone()]).unwrap();
+	let h5: IndexedBlock = test_data::block_builder().header().parent(h4.hash().clone()).build().build().into();
+	store.insert_headers(&[h5.header.clone()]).unwrap();
+	assert_eq!(store.best_header_entry().header, h5.header.raw);
+	store.invalidate_header(h3.hash()).unwrap();
+	assert!(!store.header_entry(h2.hash()).unwrap().status.invalid);
+	assert!(store.header_entry(h4.hash()).unwrap().status.invalid);
+	assert!(store.header_entry(h5.hash()).unwrap().status.invalid);
+	assert_eq!(store.best_header_entry().header, s4.header.raw);
+
+	let h6: IndexedBlock = test_data::block_builder().header().parent(h5.hash().clone()).build().build().into();
+	assert_eq!(store.insert_headers(&[h6.header.clone()]), Err(Error::InvalidParent));
+}
+
+#[test]
+fn header_tree_is_built_for_stored_blocks() {
+	let b0: IndexedBlock = test_data::block_builder().header().build().build().into();
+	let b1: IndexedBlock = test_data::block_builder().header().parent(b0.hash().clone()).build().build().into();
+	let b2: IndexedBlock = test_data::block_builder().header().parent(b1.hash().clone()).build().build().into();
+	let s2: IndexedBlock = test_data::block_builder().header().nonce(1).parent(b1.hash().clone()).build().build().into();
+	let shared_db = SharedMemoryDatabase::default();
+	let store = BlockChainDatabase::open(shared_db.clone());
+	for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+		let hash = block.hash().clone();
+		store.insert(block).unwrap();
+		store.canonize(&hash).unwrap();
+	}
+	store.insert(s2.clone()).unwrap();
+
+	// simulate database, created before the header tree has been introduced
+	let mut update = DBTransaction::new();
+	for block in &[&b0, &b1, &b2, &s2] {
+		update.delete(Key::HeaderEntry(block.hash().clone()));
+	}
+	update.delete(Key::Meta("header_tips"));
+	shared_db.write(update).unwrap();
+	assert_eq!(store.header_entry(b2.hash()), None);
+
+	assert_eq!(store.build_header_tree(), Ok(4));
+	assert_eq!(store.build_header_tree(), Ok(0));
+	let entry = store.header_entry(b2.hash()).unwrap();
+	assert_eq!(entry.number, 2);
+	assert_eq!(entry.chain_work, U256::from(3));
+	assert!(entry.status.has_block);
+	assert_eq!(store.header_entry(s2.hash()).unwrap().number, 2);
+	assert_eq!(store.header_tips(), vec![b2.hash().clone(), s2.hash().clone()]);
+}
+
+#[test]
+fn chain_work_is_built_for_stored_blocks() {
+	let b0: IndexedBlock = test_data::block_builder().header().build().build().into();
+	let b1: IndexedBlock = test_data::block_builder().header().parent(b0.hash().clone()).build().build().into();
+	let b2: IndexedBlock = test_data::block_builder().header().parent(b1.hash().clone()).build().build().into();
+	let s2: IndexedBlock = test_data::block_builder().header().nonce(1).parent(b1.hash().clone()).build().build().into();
+	let shared_db = SharedMemoryDatabase::default();
+	let store = BlockChainDatabase::open(shared_db.clone());
+	for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+		let hash = block.hash().clone();
+		store.insert(block).unwrap();
+		store.canonize(&hash).unwrap();
+	}
+	store.insert(s2.clone()).unwrap();
+	assert_eq!(store.build_chain_work(), Ok(0));
+
+	// simulate database, created before the chain work has been introduced
+	let mut update = DBTransaction::new();
+	for block in &[&b0, &b1, &b2, &s2] {
+		update.delete(Key::ChainWork(block.hash().clone()));
+	}
+	shared_db.write(update).unwrap();
+
+	assert_eq!(store.build_chain_work(), Ok(4));
+	assert_eq!(store.build_chain_work(), Ok(0));
+	// stored work is read without walking to the genesis block
+	let mut update = DBTransaction::new();
+	update.delete(Key::BlockHeader(b0.hash().clone()));
+	update.delete(Key::BlockHeader(b1.hash().clone()));
+	shared_db.write(update).unwrap();
+	assert_eq!(store.chain_work(b2.hash()), Some(U256::from(3)));
+	assert_eq!(store.chain_work(s2.hash()), Some(U256::from(3)));
+}
@@ -0,0 +1,80 @@
+//! Writer of bitcoind-compatible block files.
+
+use std::{io, fs, path};
+use std::io::Write;
+
+/// Maximal size of the single blk file (same as in bitcoind).
+pub const MAX_BLK_FILE_SIZE: u64 = 0x8000000; // 128 MiB
+
+/// Writes blocks records (network magic, block size and serialized block).
+pub struct BlkWriter {
+	magic: u32,
+	/// Directory of blk files, or `None` if all blocks are written to the single file.
+	dir: Option<path::PathBuf>,
+	file: io::BufWriter<fs::File>,
+	file_number: u32,
+	file_size: u64,
+}
+
+impl BlkWriter {
+	/// Writes blocks to blk00000.dat, blk00001.dat, ... files in given directory.
+	pub fn blk_dir<P>(dir: P, magic: u32) -> Result<Self, io::Error> where P: AsRef<path::Path> {
+		fs::create_dir_all(dir.as_ref())?;
+		let dir = dir.as_ref().to_path_buf();
+		let file = create_blk_file(&dir, 0)?;
+		Ok(BlkWriter {
+			magic: magic,
+			dir: Some(dir),
+			file: file,
+			file_number: 0,
+			file_size: 0,
+		})
+	}
+
+	/// Writes all blocks to the single file.
+	pub fn linear<P>(path: P, magic: u32) -> Result<Self, io::Error> where P: AsRef<path::Path> {
+		Ok(BlkWriter {
+			magic: magic,
+			dir: None,
+			file: io::BufWriter::new(fs::File::create(path)?),
+			file_number: 0,
+			file_size: 0,
+		})
+	}
+
+	/// Appends serialized block.
+	pub fn write_block(&mut self, block: &[u8]) -> Result<(), io::Error> {
+		let record_size = 8 + block.len() as u64;
+		if self.file_size != 0 && self.file_size + record_size > MAX_BLK_FILE_SIZE {
+			if let Some(ref dir) = self.dir {
+				self.file.flush()?;
+				self.file_number += 1;
+				self.file = create_blk_file(dir, self.file_number)?;
+				self.file_size = 0;
+			}
+		}
+
+		write_block_record(&mut self.file, self.magic, block)?;
+		self.file_size += record_size;
+		Ok(())
+	}
+
+	/// Flushes buffered data to the disk.
+	pub fn flush(&mut self) -> Result<(), io::Error> {
+		self.file.flush()
+	}
+}
+
+fn create_blk_file(dir: &path::Path, number: u32) -> Result<io::BufWriter<fs::File>, io::Error> {
+	let path = dir.join(format!("blk{:05}.dat", number));
+	trace!("Creating blk file: {:?}", path);
+	Ok(io::BufWriter::new(fs::File::create(path)?))
+}
+
+/// Writes single block record.
+pub fn write_block_record<W>(writer: &mut W, magic: u32, block: &[u8]) -> Result<(), io::Error> where W: Write {
+	let size = block.len() as u32;
+	writer.write_all(&[magic as u8, (magic >> 8) as u8, (magic >> 16) as u8, (magic >> 24) as u8])?;
+	writer.write_all(&[size as u8, (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8])?;
+	writer.write_all(block)
+}
@@ -7,9 +7,13 @@ extern crate serialization as ser;
 extern crate chain;
 
 mod blk;
+mod blk_writer;
 mod block;
 mod fs;
+mod parallel;
 
 pub use primitives::{hash, bytes};
 
 pub use blk::{open_blk_dir, BlkDir};
+pub use blk_writer::{BlkWriter, MAX_BLK_FILE_SIZE};
+pub use parallel::{open_blk_dir_parallel, ParallelBlkDir, BlkCursor, ImportedBlock, Error};
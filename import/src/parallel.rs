@@ -0,0 +1,346 @@
+//! Multi-threaded import of bitcoind blk files.
+//!
+//! Single reader thread splits blk files into raw blocks, worker threads deserialize and
+//! pre-verify them, and the resulting blocks are yielded in the original files order,
+//! so that they can be appended to the database sequentially.
+
+use std::{io, fs, path, fmt, thread};
+use std::io::{Read, Seek, SeekFrom};
+use std::str::FromStr;
+use std::collections::{BTreeSet, BTreeMap};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, SyncSender, Receiver};
+use ser::Error as ReaderError;
+use chain::{IndexedBlock, LazyBlock};
+use fs::{read_blk_dir, BlkEntry};
+
+/// Number of raw blocks, which may wait for deserialization, per worker thread.
+const QUEUE_SIZE_PER_WORKER: usize = 16;
+/// Blocks larger than this are certainly corrupted.
+const MAX_BLOCK_RECORD_SIZE: u32 = 32 * 1024 * 1024;
+
+/// Position of the block record in blk files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct BlkCursor {
+	/// Number of blk file (00012 in blk00012.dat).
+	pub file: u32,
+	/// Offset of the block record in the file.
+	pub offset: u64,
+}
+
+impl fmt::Display for BlkCursor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.file, self.offset)
+	}
+}
+
+impl FromStr for BlkCursor {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("Invalid blk cursor: {}. Expected FILE:OFFSET", s);
+		let mut parts = s.trim().split(':');
+		let cursor = BlkCursor {
+			file: parts.next().ok_or_else(&invalid)?.parse().map_err(|_| invalid())?,
+			offset: parts.next().ok_or_else(&invalid)?.parse().map_err(|_| invalid())?,
+		};
+		match parts.next() {
+			Some(_) => Err(invalid()),
+			None => Ok(cursor),
+		}
+	}
+}
+
+/// Block, read from blk files.
+#[derive(Debug)]
+pub struct ImportedBlock {
+	/// Position of the block record.
+	pub position: BlkCursor,
+	/// Position right after the block record. Import can be resumed from here.
+	pub next: BlkCursor,
+	/// The block itself.
+	pub block: IndexedBlock,
+}
+
+/// Import error.
+#[derive(Debug)]
+pub enum Error {
+	/// Blk file can't be read.
+	Io(io::Error),
+	/// Block record belongs to another network.
+	InvalidMagic(BlkCursor),
+	/// Block can't be deserialized.
+	Malformed(BlkCursor, ReaderError),
+	/// Block merkle root doesn't match its transactions.
+	InvalidMerkleRoot(BlkCursor),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::Io(ref err) => write!(f, "IO error: {}", err),
+			Error::InvalidMagic(ref position) => write!(f, "Invalid network magic of the block at {}", position),
+			Error::Malformed(ref position, ref err) => write!(f, "Malformed block at {}: {:?}", position, err),
+			Error::InvalidMerkleRoot(ref position) => write!(f, "Invalid merkle root of the block at {}", position),
+		}
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(err: io::Error) -> Self {
+		Error::Io(err)
+	}
+}
+
+/// Raw block record, waiting for deserialization.
+struct Task {
+	index: u64,
+	position: BlkCursor,
+	next: BlkCursor,
+	data: Result<Vec<u8>, Error>,
+}
+
+/// Creates multi-threaded iterator over bitcoind database blocks, starting at given position.
+/// Blocks are yielded in the same order, as they are stored in the files.
+pub fn open_blk_dir_parallel<P>(path: P, magic: u32, threads: usize, start: BlkCursor) -> Result<ParallelBlkDir, io::Error> where P: AsRef<path::Path> {
+	let files = read_blk_dir(path)?.collect::<Result<BTreeSet<_>, _>>()?;
+	let threads = if threads == 0 { 1 } else { threads };
+
+	let (tasks_tx, tasks_rx) = sync_channel(threads * QUEUE_SIZE_PER_WORKER);
+	let (results_tx, results_rx) = sync_channel(threads * QUEUE_SIZE_PER_WORKER);
+
+	thread::Builder::new()
+		.name("Import reader".into())
+		.spawn(move || read_blk_files(files, magic, start, tasks_tx))?;
+
+	let tasks_rx = Arc::new(Mutex::new(tasks_rx));
+	for index in 0..threads {
+		let tasks_rx = tasks_rx.clone();
+		let results_tx = results_tx.clone();
+		thread::Builder::new()
+			.name(format!("Import worker #{}", index))
+			.spawn(move || decode_blocks(tasks_rx, results_tx))?;
+	}
+
+	Ok(ParallelBlkDir {
+		results: results_rx,
+		pending: BTreeMap::new(),
+		next_index: 0,
+	})
+}
+
+/// Multi-threaded bitcoind database blocks iterator.
+pub struct ParallelBlkDir {
+	results: Receiver<(u64, Result<ImportedBlock, Error>)>,
+	/// Blocks, decoded ahead of their predecessors.
+	pending: BTreeMap<u64, Result<ImportedBlock, Error>>,
+	next_index: u64,
+}
+
+impl Iterator for ParallelBlkDir {
+	type Item = Result<ImportedBlock, Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(result) = self.pending.remove(&self.next_index) {
+				self.next_index += 1;
+				return Some(result);
+			}
+
+			match self.results.recv() {
+				Ok((index, result)) => {
+					self.pending.insert(index, result);
+				},
+				// all workers are done
+				Err(_) => return None,
+			}
+		}
+	}
+}
+
+fn blk_file_number(entry: &BlkEntry) -> u32 {
+	entry.path.file_name()
+		.and_then(|name| name.to_str())
+		.and_then(|name| name[3..8].parse().ok())
+		.expect("only blk?????.dat files are returned by read_blk_dir; qed")
+}
+
+fn read_blk_files(files: BTreeSet<BlkEntry>, magic: u32, start: BlkCursor, tasks: SyncSender<Task>) {
+	let mut index = 0;
+	for entry in files {
+		let file_number = blk_file_number(&entry);
+		if file_number < start.file {
+			continue;
+		}
+
+		let offset = if file_number == start.file { start.offset } else { 0 };
+		let mut reader = match open_at(&entry.path, offset) {
+			Ok(reader) => reader,
+			Err(err) => {
+				let position = BlkCursor { file: file_number, offset: offset };
+				let _ = tasks.send(Task { index: index, position: position, next: position, data: Err(err.into()) });
+				return;
+			},
+		};
+
+		let mut position = BlkCursor { file: file_number, offset: offset };
+		loop {
+			let (next, data) = match read_block_record(&mut reader, magic, position) {
+				Ok(Some((next, data))) => (next, Ok(data)),
+				// end of file (or its preallocated zeroed space)
+				Ok(None) => break,
+				Err(err) => (position, Err(err)),
+			};
+
+			let failed = data.is_err();
+			if tasks.send(Task { index: index, position: position, next: next, data: data }).is_err() || failed {
+				// iterator is dropped or files are corrupted
+				return;
+			}
+
+			index += 1;
+			position = next;
+		}
+	}
+}
+
+fn open_at(path: &path::Path, offset: u64) -> Result<io::BufReader<fs::File>, io::Error> {
+	trace!("Opening blk file: {:?}", path);
+	let mut file = fs::File::open(path)?;
+	file.seek(SeekFrom::Start(offset))?;
+	Ok(io::BufReader::new(file))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<Option<u32>, io::Error> {
+	let mut buffer = [0u8; 4];
+	let mut read = 0;
+	while read < buffer.len() {
+		match reader.read(&mut buffer[read..])? {
+			0 if read == 0 => return Ok(None),
+			0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+			n => read += n,
+		}
+	}
+
+	Ok(Some(buffer.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32)))
+}
+
+/// Reads single block record: magic, size and serialized block.
+fn read_block_record<R: Read>(reader: &mut R, magic: u32, position: BlkCursor) -> Result<Option<(BlkCursor, Vec<u8>)>, Error> {
+	let record_magic = match read_u32(reader)? {
+		None | Some(0) => return Ok(None),
+		Some(record_magic) => record_magic,
+	};
+
+	if record_magic != magic {
+		return Err(Error::InvalidMagic(position));
+	}
+
+	let size = match read_u32(reader)? {
+		Some(size) if size <= MAX_BLOCK_RECORD_SIZE => size,
+		Some(_) => return Err(Error::Malformed(position, ReaderError::MalformedData)),
+		None => return Err(Error::Malformed(position, ReaderError::UnexpectedEnd)),
+	};
+
+	let mut data = vec![0u8; size as usize];
+	reader.read_exact(&mut data).map_err(|_| Error::Malformed(position, ReaderError::UnexpectedEnd))?;
+
+	let next = BlkCursor {
+		file: position.file,
+		offset: position.offset + 8 + size as u64,
+	};
+
+	Ok(Some((next, data)))
+}
+
+fn decode_blocks(tasks: Arc<Mutex<Receiver<Task>>>, results: SyncSender<(u64, Result<ImportedBlock, Error>)>) {
+	loop {
+		let task = match tasks.lock().expect("worker threads do not panic while holding the lock; qed").recv() {
+			Ok(task) => task,
+			// reader is done
+			Err(_) => return,
+		};
+
+		let result = task.data.and_then(|data| decode_block(data, task.position)).map(|block| ImportedBlock {
+			position: task.position,
+			next: task.next,
+			block: block,
+		});
+
+		if results.send((task.index, result)).is_err() {
+			// iterator is dropped
+			return;
+		}
+	}
+}
+
+/// Deserializes block and performs context-free checks, which do not require the database.
+/// Merkle root is checked before transactions are decoded, as it only needs their hashes.
+fn decode_block(data: Vec<u8>, position: BlkCursor) -> Result<IndexedBlock, Error> {
+	let block = LazyBlock::new(data.into()).map_err(|err| Error::Malformed(position, err))?;
+	if block.merkle_root() != block.header().raw.merkle_root_hash {
+		return Err(Error::InvalidMerkleRoot(position));
+	}
+
+	block.to_indexed_block().map_err(|err| Error::Malformed(position, err))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use ser::Error as ReaderError;
+	use blk_writer::write_block_record;
+	use super::{BlkCursor, Error, read_block_record};
+
+	const MAGIC: u32 = 0xD9B4BEF9;
+
+	#[test]
+	fn test_blk_cursor_parse() {
+		assert_eq!("12:3456".parse::<BlkCursor>(), Ok(BlkCursor { file: 12, offset: 3456 }));
+		assert_eq!("12:3456\n".parse::<BlkCursor>(), Ok(BlkCursor { file: 12, offset: 3456 }));
+		assert!("12".parse::<BlkCursor>().is_err());
+		assert!("12:34:56".parse::<BlkCursor>().is_err());
+		assert_eq!(BlkCursor { file: 1, offset: 2 }.to_string(), "1:2");
+	}
+
+	#[test]
+	fn test_read_block_record() {
+		let data = vec![0xf9, 0xbe, 0xb4, 0xd9, 3, 0, 0, 0, 1, 2, 3, 0, 0, 0, 0];
+		let mut reader = Cursor::new(data);
+		let position = BlkCursor { file: 1, offset: 0 };
+
+		let (next, record) = read_block_record(&mut reader, MAGIC, position).unwrap().unwrap();
+		assert_eq!(next, BlkCursor { file: 1, offset: 11 });
+		assert_eq!(record, vec![1, 2, 3]);
+		// zeroed tail of the file
+		assert!(read_block_record(&mut reader, MAGIC, next).unwrap().is_none());
+	}
+
+	#[test]
+	fn test_written_block_record_is_read() {
+		let mut data = Vec::new();
+		write_block_record(&mut data, MAGIC, &[4, 5, 6, 7]).unwrap();
+		write_block_record(&mut data, MAGIC, &[8]).unwrap();
+
+		let mut reader = Cursor::new(data);
+		let (next, record) = read_block_record(&mut reader, MAGIC, BlkCursor::default()).unwrap().unwrap();
+		assert_eq!(record, vec![4, 5, 6, 7]);
+		let (next, record) = read_block_record(&mut reader, MAGIC, next).unwrap().unwrap();
+		assert_eq!(record, vec![8]);
+		assert_eq!(next, BlkCursor { file: 0, offset: 21 });
+		assert!(read_block_record(&mut reader, MAGIC, next).unwrap().is_none());
+	}
+
+	#[test]
+	fn test_read_block_record_errors() {
+		let position = BlkCursor::default();
+		match read_block_record(&mut Cursor::new(vec![0xfa, 0xbf, 0xb5, 0xda, 0, 0, 0, 0]), MAGIC, position) {
+			Err(Error::InvalidMagic(p)) => assert_eq!(p, position),
+			_ => panic!("expected invalid magic"),
+		}
+		match read_block_record(&mut Cursor::new(vec![0xf9, 0xbe, 0xb4, 0xd9, 3, 0, 0, 0, 1]), MAGIC, position) {
+			Err(Error::Malformed(_, ReaderError::UnexpectedEnd)) => (),
+			_ => panic!("expected truncated block"),
+		}
+	}
+}
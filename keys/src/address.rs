@@ -59,6 +59,8 @@ impl DisplayLayout for Address {
 			(Network::Mainnet, Type::P2SH) => 5,
 			(Network::Testnet, Type::P2PKH) => 111,
 			(Network::Testnet, Type::P2SH) => 196,
+			(Network::Other { p2pkh, .. }, Type::P2PKH) => p2pkh,
+			(Network::Other { p2sh, .. }, Type::P2SH) => p2sh,
 		};
 
 		result[1..21].copy_from_slice(&*self.hash);
@@ -145,4 +147,15 @@ mod tests {
 
 		assert_eq!(address, "16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".into());
 	}
+
+	#[test]
+	fn test_address_to_string_custom_network() {
+		let address = Address {
+			kind: Type::P2PKH,
+			network: Network::Other { p2pkh: 0, p2sh: 5, private: 128 },
+			hash: "3f4aa1fedf1f54eeb03b759deadb36676b184911".into(),
+		};
+
+		assert_eq!("16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".to_owned(), address.to_string());
+	}
 }
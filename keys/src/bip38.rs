@@ -0,0 +1,472 @@
+//! Passphrase-protected private keys.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0038.mediawiki
+//!
+//! Passphrases are used as given, callers are responsible for NFC normalization.
+
+use std::fmt;
+use std::str::FromStr;
+use secp256k1::key;
+use base58::{ToBase58, FromBase58};
+use crypto::{checksum, dhash256, scrypt, aes256_encrypt_block, aes256_decrypt_block};
+use hash::{H32, H256, H264};
+use network::Network;
+use {DisplayLayout, Error, Private, Public, KeyPair, Address, Type, Secret, SECP256K1};
+
+const NON_EC_PREFIX: [u8; 2] = [0x01, 0x42];
+const EC_PREFIX: [u8; 2] = [0x01, 0x43];
+const FLAG_NON_EC: u8 = 0xc0;
+const FLAG_COMPRESSED: u8 = 0x20;
+const FLAG_LOT_SEQUENCE: u8 = 0x04;
+const MAGIC_LOT_SEQUENCE: [u8; 8] = [0x2c, 0xe9, 0xb3, 0xe1, 0xff, 0x39, 0xe2, 0x51];
+const MAGIC_NO_LOT_SEQUENCE: [u8; 8] = [0x2c, 0xe9, 0xb3, 0xe1, 0xff, 0x39, 0xe2, 0x53];
+const MAX_LOT: u32 = 1048575;
+const MAX_SEQUENCE: u32 = 4095;
+
+/// Private key, encrypted with passphrase.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EncryptedPrivate {
+	/// True if key has been generated from intermediate code.
+	pub ec_multiply: bool,
+	/// True if this private key represents a compressed address.
+	pub compressed: bool,
+	/// True if owner entropy contains lot and sequence numbers. EC-multiply keys only.
+	pub lot_sequence: bool,
+	/// First 4 bytes of `dhash256` of key address.
+	pub address_hash: H32,
+	/// Two encrypted halves of the key, or owner entropy followed by encrypted seed for EC-multiply keys.
+	pub data: H256,
+}
+
+impl EncryptedPrivate {
+	/// Encrypts private key with passphrase.
+	pub fn encrypt(private: &Private, passphrase: &str) -> Result<Self, Error> {
+		let address = try!(private_address(&private.secret, private.compressed, private.network));
+		let address_hash = checksum(address.to_string().as_bytes());
+
+		let mut derived = [0u8; 64];
+		scrypt(passphrase.as_bytes(), &*address_hash, 14, 8, 8, &mut derived);
+		let key = H256::from(&derived[32..64]);
+
+		let mut data = H256::default();
+		data[0..16].copy_from_slice(&aes256_encrypt_block(&key, &xor(&private.secret[0..16], &derived[0..16])));
+		data[16..32].copy_from_slice(&aes256_encrypt_block(&key, &xor(&private.secret[16..32], &derived[16..32])));
+
+		let encrypted = EncryptedPrivate {
+			ec_multiply: false,
+			compressed: private.compressed,
+			lot_sequence: false,
+			address_hash: address_hash,
+			data: data,
+		};
+
+		Ok(encrypted)
+	}
+
+	/// Creates new encrypted key from intermediate code and random 24-byte seed.
+	/// Returns the key along with its address.
+	pub fn from_intermediate(code: &IntermediateCode, seed: &[u8; 24], compressed: bool, network: Network) -> Result<(Self, Address), Error> {
+		let context = &SECP256K1;
+		let factor = dhash256(seed);
+		let mut point = try!(key::PublicKey::from_slice(context, &*code.pass_point));
+		try!(point.mul_assign(context, &try!(key::SecretKey::from_slice(context, &*factor))));
+		let public = try!(Public::from_slice(&point.serialize_vec(context, compressed)));
+		let address = Address {
+			kind: Type::P2PKH,
+			network: network,
+			hash: public.address_hash(),
+		};
+		let address_hash = checksum(address.to_string().as_bytes());
+
+		let derived = ec_derived_key(&code.pass_point, &address_hash, &code.owner_entropy);
+		let key = H256::from(&derived[32..64]);
+		let part1 = aes256_encrypt_block(&key, &xor(&seed[0..16], &derived[0..16]));
+		let mut part2 = [0u8; 16];
+		part2[0..8].copy_from_slice(&part1[8..16]);
+		part2[8..16].copy_from_slice(&seed[16..24]);
+		let part2 = aes256_encrypt_block(&key, &xor(&part2, &derived[16..32]));
+
+		let mut data = H256::default();
+		data[0..8].copy_from_slice(&code.owner_entropy);
+		data[8..16].copy_from_slice(&part1[0..8]);
+		data[16..32].copy_from_slice(&part2);
+
+		let encrypted = EncryptedPrivate {
+			ec_multiply: true,
+			compressed: compressed,
+			lot_sequence: code.lot_sequence,
+			address_hash: address_hash,
+			data: data,
+		};
+
+		Ok((encrypted, address))
+	}
+
+	/// Decrypts private key. Returns `Error::InvalidPassphrase` if passphrase does not match.
+	pub fn decrypt(&self, passphrase: &str, network: Network) -> Result<Private, Error> {
+		let secret = if self.ec_multiply {
+			try!(self.decrypt_ec(passphrase))
+		} else {
+			self.decrypt_non_ec(passphrase)
+		};
+
+		let address = try!(private_address(&secret, self.compressed, network));
+		if checksum(address.to_string().as_bytes()) != self.address_hash {
+			return Err(Error::InvalidPassphrase);
+		}
+
+		let private = Private {
+			network: network,
+			secret: secret,
+			compressed: self.compressed,
+		};
+
+		Ok(private)
+	}
+
+	fn decrypt_non_ec(&self, passphrase: &str) -> Secret {
+		let mut derived = [0u8; 64];
+		scrypt(passphrase.as_bytes(), &*self.address_hash, 14, 8, 8, &mut derived);
+		let key = H256::from(&derived[32..64]);
+
+		let mut secret = Secret::default();
+		secret[0..16].copy_from_slice(&xor(&aes256_decrypt_block(&key, &block(&self.data[0..16])), &derived[0..16]));
+		secret[16..32].copy_from_slice(&xor(&aes256_decrypt_block(&key, &block(&self.data[16..32])), &derived[16..32]));
+		secret
+	}
+
+	fn decrypt_ec(&self, passphrase: &str) -> Result<Secret, Error> {
+		let context = &SECP256K1;
+		let mut owner_entropy = [0u8; 8];
+		owner_entropy.copy_from_slice(&self.data[0..8]);
+		let pass_factor = pass_factor(passphrase, &owner_entropy, self.lot_sequence);
+		let pass_point = try!(pass_point(&pass_factor));
+
+		let derived = ec_derived_key(&pass_point, &self.address_hash, &owner_entropy);
+		let key = H256::from(&derived[32..64]);
+		// encrypted part 1 (last 8 bytes) followed by seed (last 8 bytes)
+		let part2 = xor(&aes256_decrypt_block(&key, &block(&self.data[16..32])), &derived[16..32]);
+		let mut part1 = [0u8; 16];
+		part1[0..8].copy_from_slice(&self.data[8..16]);
+		part1[8..16].copy_from_slice(&part2[0..8]);
+
+		let mut seed = [0u8; 24];
+		seed[0..16].copy_from_slice(&xor(&aes256_decrypt_block(&key, &part1), &derived[0..16]));
+		seed[16..24].copy_from_slice(&part2[8..16]);
+		let factor = dhash256(&seed);
+
+		let mut secret_key = try!(key::SecretKey::from_slice(context, &*pass_factor));
+		try!(secret_key.mul_assign(context, &try!(key::SecretKey::from_slice(context, &*factor))));
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&secret_key[0..32]);
+		Ok(secret)
+	}
+}
+
+impl DisplayLayout for EncryptedPrivate {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		let mut result = Vec::with_capacity(43);
+		let mut flags = 0;
+		if self.compressed {
+			flags |= FLAG_COMPRESSED;
+		}
+
+		if self.ec_multiply {
+			result.extend_from_slice(&EC_PREFIX);
+			if self.lot_sequence {
+				flags |= FLAG_LOT_SEQUENCE;
+			}
+		} else {
+			result.extend_from_slice(&NON_EC_PREFIX);
+			flags |= FLAG_NON_EC;
+		}
+
+		result.push(flags);
+		result.extend_from_slice(&*self.address_hash);
+		result.extend_from_slice(&*self.data);
+		let cs = checksum(&result);
+		result.extend_from_slice(&*cs);
+		result
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		if data.len() != 43 {
+			return Err(Error::InvalidEncryptedPrivate);
+		}
+
+		let cs = checksum(&data[0..39]);
+		if &data[39..] != &*cs {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let flags = data[2];
+		let ec_multiply = if data[0..2] == NON_EC_PREFIX && flags & FLAG_NON_EC == FLAG_NON_EC {
+			false
+		} else if data[0..2] == EC_PREFIX && flags & FLAG_NON_EC == 0 {
+			true
+		} else {
+			return Err(Error::InvalidEncryptedPrivate);
+		};
+
+		let lot_sequence = flags & FLAG_LOT_SEQUENCE != 0;
+		let known_flags = FLAG_NON_EC | FLAG_COMPRESSED | if ec_multiply { FLAG_LOT_SEQUENCE } else { 0 };
+		if flags & !known_flags != 0 {
+			return Err(Error::InvalidEncryptedPrivate);
+		}
+
+		let encrypted = EncryptedPrivate {
+			ec_multiply: ec_multiply,
+			compressed: flags & FLAG_COMPRESSED != 0,
+			lot_sequence: lot_sequence,
+			address_hash: H32::from(&data[3..7]),
+			data: H256::from(&data[7..39]),
+		};
+
+		Ok(encrypted)
+	}
+}
+
+impl fmt::Display for EncryptedPrivate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for EncryptedPrivate {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let data = try!(s.from_base58().map_err(|_| Error::InvalidEncryptedPrivate));
+		EncryptedPrivate::from_layout(&data)
+	}
+}
+
+impl From<&'static str> for EncryptedPrivate {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+/// Intermediate passphrase code, which lets a third party generate
+/// encrypted keys without knowing the passphrase.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IntermediateCode {
+	/// True if owner entropy contains lot and sequence numbers.
+	pub lot_sequence: bool,
+	/// Owner salt, optionally followed by lot and sequence numbers.
+	pub owner_entropy: [u8; 8],
+	/// Compressed public key of passphrase factor.
+	pub pass_point: H264,
+}
+
+impl IntermediateCode {
+	/// Creates intermediate code from passphrase and random owner salt.
+	/// Owner salt must be 4 bytes long if lot and sequence numbers are given, 8 bytes otherwise.
+	pub fn new(passphrase: &str, owner_salt: &[u8], lot_sequence: Option<(u32, u32)>) -> Result<Self, Error> {
+		let mut owner_entropy = [0u8; 8];
+		match lot_sequence {
+			Some((lot, sequence)) => {
+				if owner_salt.len() != 4 || lot > MAX_LOT || sequence > MAX_SEQUENCE {
+					return Err(Error::InvalidIntermediateCode);
+				}
+
+				let number = lot * (MAX_SEQUENCE + 1) + sequence;
+				owner_entropy[0..4].copy_from_slice(owner_salt);
+				owner_entropy[4] = (number >> 24) as u8;
+				owner_entropy[5] = (number >> 16) as u8;
+				owner_entropy[6] = (number >> 8) as u8;
+				owner_entropy[7] = number as u8;
+			},
+			None => {
+				if owner_salt.len() != 8 {
+					return Err(Error::InvalidIntermediateCode);
+				}
+
+				owner_entropy.copy_from_slice(owner_salt);
+			},
+		}
+
+		let lot_sequence = lot_sequence.is_some();
+		let pass_factor = pass_factor(passphrase, &owner_entropy, lot_sequence);
+		let code = IntermediateCode {
+			lot_sequence: lot_sequence,
+			owner_entropy: owner_entropy,
+			pass_point: try!(pass_point(&pass_factor)),
+		};
+
+		Ok(code)
+	}
+}
+
+impl DisplayLayout for IntermediateCode {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		let mut result = Vec::with_capacity(53);
+		if self.lot_sequence {
+			result.extend_from_slice(&MAGIC_LOT_SEQUENCE);
+		} else {
+			result.extend_from_slice(&MAGIC_NO_LOT_SEQUENCE);
+		}
+		result.extend_from_slice(&self.owner_entropy);
+		result.extend_from_slice(&*self.pass_point);
+		let cs = checksum(&result);
+		result.extend_from_slice(&*cs);
+		result
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		if data.len() != 53 {
+			return Err(Error::InvalidIntermediateCode);
+		}
+
+		let cs = checksum(&data[0..49]);
+		if &data[49..] != &*cs {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let lot_sequence = if data[0..8] == MAGIC_LOT_SEQUENCE {
+			true
+		} else if data[0..8] == MAGIC_NO_LOT_SEQUENCE {
+			false
+		} else {
+			return Err(Error::InvalidIntermediateCode);
+		};
+
+		let mut owner_entropy = [0u8; 8];
+		owner_entropy.copy_from_slice(&data[8..16]);
+
+		let code = IntermediateCode {
+			lot_sequence: lot_sequence,
+			owner_entropy: owner_entropy,
+			pass_point: H264::from(&data[16..49]),
+		};
+
+		Ok(code)
+	}
+}
+
+impl fmt::Display for IntermediateCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for IntermediateCode {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let data = try!(s.from_base58().map_err(|_| Error::InvalidIntermediateCode));
+		IntermediateCode::from_layout(&data)
+	}
+}
+
+fn private_address(secret: &Secret, compressed: bool, network: Network) -> Result<Address, Error> {
+	let private = Private {
+		network: network,
+		secret: secret.clone(),
+		compressed: compressed,
+	};
+
+	Ok(try!(KeyPair::from_private(private)).address())
+}
+
+fn pass_factor(passphrase: &str, owner_entropy: &[u8; 8], lot_sequence: bool) -> H256 {
+	let owner_salt = if lot_sequence { &owner_entropy[0..4] } else { &owner_entropy[..] };
+	let mut prefactor = H256::default();
+	scrypt(passphrase.as_bytes(), owner_salt, 14, 8, 8, &mut *prefactor);
+	if !lot_sequence {
+		return prefactor;
+	}
+
+	let mut data = prefactor.to_vec();
+	data.extend_from_slice(owner_entropy);
+	dhash256(&data)
+}
+
+fn pass_point(pass_factor: &H256) -> Result<H264, Error> {
+	let context = &SECP256K1;
+	let secret = try!(key::SecretKey::from_slice(context, &**pass_factor));
+	let public = try!(key::PublicKey::from_secret_key(context, &secret));
+	Ok(H264::from(&public.serialize_vec(context, true)[..]))
+}
+
+fn ec_derived_key(pass_point: &H264, address_hash: &H32, owner_entropy: &[u8; 8]) -> [u8; 64] {
+	let mut salt = [0u8; 12];
+	salt[0..4].copy_from_slice(&**address_hash);
+	salt[4..12].copy_from_slice(owner_entropy);
+	let mut derived = [0u8; 64];
+	scrypt(&**pass_point, &salt, 10, 1, 1, &mut derived);
+	derived
+}
+
+fn block(data: &[u8]) -> [u8; 16] {
+	let mut result = [0u8; 16];
+	result.copy_from_slice(data);
+	result
+}
+
+fn xor(a: &[u8], b: &[u8]) -> [u8; 16] {
+	let mut result = [0u8; 16];
+	for i in 0..16 {
+		result[i] = a[i] ^ b[i];
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use network::Network;
+	use {Error, Private};
+	use super::{EncryptedPrivate, IntermediateCode};
+
+	#[test]
+	fn test_encrypted_private_layout() {
+		let s = "6PYNKZ1EAgYgmQfmNVamxyXVWHzK5s6DGhwP4J5o44cvXdoY7sRzhtpUeo";
+		let encrypted: EncryptedPrivate = s.into();
+		assert!(!encrypted.ec_multiply);
+		assert!(encrypted.compressed);
+		assert_eq!(encrypted.to_string(), s);
+
+		let encrypted: EncryptedPrivate = "6PfQu77ygVyJLZjfvMLyhLMQbYnu5uguoJJ4kMCLqWwPEdfpwANVS76gTX".into();
+		assert!(encrypted.ec_multiply);
+		assert!(!encrypted.compressed);
+		assert!(!encrypted.lot_sequence);
+	}
+
+	#[test]
+	fn test_encrypt_decrypt_non_ec() {
+		let private: Private = "5KN7MzqK5wt2TP1fQCYyHBtDrXdJuXbUzm4A9rKAteGu3Qi5CVR".into();
+		let encrypted = EncryptedPrivate::encrypt(&private, "TestingOneTwoThree").unwrap();
+		assert_eq!(encrypted.to_string(), "6PRVWUbkzzsbcVac2qwfssoUJAN1Xhrg6bNk8J7Nzm5H7kxEbn2Nh2ZoGg");
+		assert_eq!(encrypted.decrypt("TestingOneTwoThree", Network::Mainnet).unwrap(), private);
+		assert_eq!(encrypted.decrypt("TestingOneTwoFour", Network::Mainnet), Err(Error::InvalidPassphrase));
+	}
+
+	#[test]
+	fn test_decrypt_non_ec_compressed() {
+		let encrypted: EncryptedPrivate = "6PYLtMnXvfG3oJde97zRyLYFZCYizPU5T3LwgdYJz1fRhh16bU7u6PPmY7".into();
+		let private: Private = "KwYgW8gcxj1JWJXhPSu4Fqwzfhp5Yfi42mdYmMa4XqK7NJxXUSK7".into();
+		assert_eq!(encrypted.decrypt("Satoshi", Network::Mainnet).unwrap(), private);
+	}
+
+	#[test]
+	fn test_decrypt_ec_multiply() {
+		let encrypted: EncryptedPrivate = "6PfQu77ygVyJLZjfvMLyhLMQbYnu5uguoJJ4kMCLqWwPEdfpwANVS76gTX".into();
+		let private: Private = "5K4caxezwjGCGfnoPTZ8tMcJBLB7Jvyjv4xxeacadhq8nLisLR2".into();
+		assert_eq!(encrypted.decrypt("TestingOneTwoThree", Network::Mainnet).unwrap(), private);
+	}
+
+	#[test]
+	fn test_intermediate_code_roundtrip() {
+		let code = IntermediateCode::new("MOLON LABE", &[1, 2, 3, 4], Some((263183, 1))).unwrap();
+		assert_eq!(code, code.to_string().parse::<IntermediateCode>().unwrap());
+
+		let (encrypted, address) = EncryptedPrivate::from_intermediate(&code, &[7; 24], true, Network::Testnet).unwrap();
+		assert!(encrypted.ec_multiply && encrypted.lot_sequence && encrypted.compressed);
+		let private = encrypted.decrypt("MOLON LABE", Network::Testnet).unwrap();
+		assert_eq!(::KeyPair::from_private(private).unwrap().address(), address);
+	}
+}
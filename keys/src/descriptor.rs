@@ -0,0 +1,611 @@
+//! Output script descriptors (BIP380).
+//!
+//! - `pkh(KEY)`: P2PKH output (BIP381);
+//! - `wpkh(KEY)` and `sh(wpkh(KEY))`: P2WPKH output and P2WPKH nested in P2SH (BIP382);
+//! - `sh(multi(k,KEY,...))`, `wsh(multi(k,KEY,...))` and `sh(wsh(multi(k,KEY,...)))`: multisig outputs (BIP383);
+//! - `tr(KEY)`: taproot output, spendable by the key path only (BIP386);
+//! - `addr(ADDRESS)` and `raw(HEX)`: output with the script of the address or with the given script (BIP385).
+//!
+//! `KEY` is a hex-encoded public key or an extended public key, followed by the non-hardened derivation
+//! path. The last step of the path may be `*`, making the descriptor ranged: it describes
+//! one output for every child index. Key may be prefixed by `[fingerprint/path]` of its origin.
+//!
+//! Descriptor may be followed by `#` and the checksum, which is verified when present.
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use secp256k1::key;
+use crypto::{dhash160, sha256, tagged_hash};
+use hash::{H32, H256};
+use bytes::Bytes;
+use hex::{ToHex, FromHex};
+use extended::{ExtendedPublic, HARDENED_INDEX};
+use schnorr::{XOnlyPublic, tweak_public};
+use address::{Address, Type as AddressType};
+use {Error, SECP256K1};
+
+/// Characters, which may be used in descriptors. Position of the character is used by the checksum.
+const INPUT_CHARSET: &'static str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+/// Characters of the checksum.
+const CHECKSUM_CHARSET: &'static [u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// Generator of the checksum BCH code.
+const GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+
+/// Maximal number of keys in `multi` inside `sh`, so that redeem script fits into 520 bytes.
+const MAX_SH_MULTI_KEYS: usize = 15;
+/// Maximal number of keys in `multi` inside `wsh`.
+const MAX_WSH_MULTI_KEYS: usize = 20;
+
+const OP_0: u8 = 0x00;
+const OP_1: u8 = 0x51;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// `[fingerprint/path]` origin of the descriptor key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyOrigin {
+	/// Fingerprint of the master key.
+	pub fingerprint: H32,
+	/// Derivation path from the master key. Unlike the key path, it may contain hardened indexes.
+	pub path: Vec<u32>,
+}
+
+/// Key expression of the descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DescriptorKey {
+	/// Hex-encoded public key. Inside `tr` it may be 32 bytes long x-only key.
+	Single {
+		origin: Option<KeyOrigin>,
+		public: Bytes,
+	},
+	/// Extended public key, followed by the derivation path. Ranged, if path ends with `*`.
+	Extended {
+		origin: Option<KeyOrigin>,
+		key: ExtendedPublic,
+		path: Vec<u32>,
+		wildcard: bool,
+	},
+}
+
+/// Kinds of keys, which are allowed in the descriptor context.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyContext {
+	/// Compressed or uncompressed keys.
+	Legacy,
+	/// Compressed keys only.
+	Segwit,
+	/// Compressed or x-only keys.
+	Taproot,
+}
+
+impl DescriptorKey {
+	/// Returns true if key path ends with `*`.
+	pub fn is_range(&self) -> bool {
+		match *self {
+			DescriptorKey::Single { .. } => false,
+			DescriptorKey::Extended { wildcard, .. } => wildcard,
+		}
+	}
+
+	/// Serialized public key at the given child index. Index is ignored by non-ranged keys.
+	pub fn derive(&self, index: u32) -> Result<Bytes, Error> {
+		match *self {
+			DescriptorKey::Single { ref public, .. } => Ok(public.clone()),
+			DescriptorKey::Extended { ref key, ref path, wildcard, .. } => {
+				let mut derived = try!(key.derive_path(path));
+				if wildcard {
+					derived = try!(derived.derive(index));
+				}
+				Ok(derived.public[..].into())
+			},
+		}
+	}
+
+	fn origin(&self) -> Option<&KeyOrigin> {
+		match *self {
+			DescriptorKey::Single { ref origin, .. } | DescriptorKey::Extended { ref origin, .. } => origin.as_ref(),
+		}
+	}
+}
+
+impl fmt::Display for DescriptorKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let Some(origin) = self.origin() {
+			write!(f, "[{}{}]", origin.fingerprint, format_path(&origin.path))?;
+		}
+
+		match *self {
+			DescriptorKey::Single { ref public, .. } => public[..].to_hex().fmt(f),
+			DescriptorKey::Extended { ref key, ref path, wildcard, .. } => {
+				write!(f, "{}{}", key, format_path(path))?;
+				if wildcard {
+					f.write_str("/*")?;
+				}
+				Ok(())
+			},
+		}
+	}
+}
+
+/// Output script descriptor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Descriptor {
+	/// `pkh(KEY)`
+	Pkh(DescriptorKey),
+	/// `wpkh(KEY)`
+	Wpkh(DescriptorKey),
+	/// `sh(wpkh(KEY))`
+	ShWpkh(DescriptorKey),
+	/// `sh(multi(k,KEY,...))`
+	ShMulti(usize, Vec<DescriptorKey>),
+	/// `wsh(multi(k,KEY,...))`
+	WshMulti(usize, Vec<DescriptorKey>),
+	/// `sh(wsh(multi(k,KEY,...)))`
+	ShWshMulti(usize, Vec<DescriptorKey>),
+	/// `tr(KEY)`
+	Tr(DescriptorKey),
+	/// `addr(ADDRESS)`
+	Addr(Address),
+	/// `raw(HEX)`
+	Raw(Bytes),
+}
+
+impl Descriptor {
+	/// Returns true if descriptor describes an output for every child index.
+	pub fn is_range(&self) -> bool {
+		match *self {
+			Descriptor::Pkh(ref key) | Descriptor::Wpkh(ref key) | Descriptor::ShWpkh(ref key) | Descriptor::Tr(ref key) => key.is_range(),
+			Descriptor::ShMulti(_, ref keys) | Descriptor::WshMulti(_, ref keys) | Descriptor::ShWshMulti(_, ref keys) =>
+				keys.iter().any(DescriptorKey::is_range),
+			Descriptor::Addr(_) | Descriptor::Raw(_) => false,
+		}
+	}
+
+	/// Script of the output at the given child index. Index is ignored by non-ranged descriptors.
+	pub fn script_pubkey(&self, index: u32) -> Result<Bytes, Error> {
+		let script = match *self {
+			Descriptor::Pkh(ref key) => p2pkh(&try!(key.derive(index))),
+			Descriptor::Wpkh(ref key) => p2wpkh(&try!(key.derive(index))),
+			Descriptor::ShWpkh(ref key) => p2sh(&p2wpkh(&try!(key.derive(index)))),
+			Descriptor::ShMulti(k, ref keys) => p2sh(&try!(multi(k, keys, index))),
+			Descriptor::WshMulti(k, ref keys) => p2wsh(&try!(multi(k, keys, index))),
+			Descriptor::ShWshMulti(k, ref keys) => p2sh(&p2wsh(&try!(multi(k, keys, index)))),
+			Descriptor::Tr(ref key) => try!(p2tr(&try!(key.derive(index)))),
+			Descriptor::Addr(ref address) => match address.kind {
+				AddressType::P2PKH => pkh_script(&*address.hash),
+				AddressType::P2SH => sh_script(&*address.hash),
+			},
+			Descriptor::Raw(ref script) => script.clone(),
+		};
+
+		Ok(script)
+	}
+
+	/// Scripts of the outputs in the given range of child indexes.
+	/// Non-ranged descriptor is expanded into single script.
+	pub fn expand(&self, range: Range<u32>) -> Result<Vec<Bytes>, Error> {
+		if !self.is_range() {
+			return self.script_pubkey(0).map(|script| vec![script]);
+		}
+
+		range.map(|index| self.script_pubkey(index)).collect()
+	}
+
+	/// Descriptor without the checksum
+	fn body(&self) -> String {
+		match *self {
+			Descriptor::Pkh(ref key) => format!("pkh({})", key),
+			Descriptor::Wpkh(ref key) => format!("wpkh({})", key),
+			Descriptor::ShWpkh(ref key) => format!("sh(wpkh({}))", key),
+			Descriptor::ShMulti(k, ref keys) => format!("sh({})", format_multi(k, keys)),
+			Descriptor::WshMulti(k, ref keys) => format!("wsh({})", format_multi(k, keys)),
+			Descriptor::ShWshMulti(k, ref keys) => format!("sh(wsh({}))", format_multi(k, keys)),
+			Descriptor::Tr(ref key) => format!("tr({})", key),
+			Descriptor::Addr(ref address) => format!("addr({})", address),
+			Descriptor::Raw(ref script) => format!("raw({})", script[..].to_hex()),
+		}
+	}
+}
+
+impl fmt::Display for Descriptor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let body = self.body();
+		let checksum = descriptor_checksum(&body).expect("descriptor body only contains hex, base58 and descriptor syntax characters; qed");
+		write!(f, "{}#{}", body, checksum)
+	}
+}
+
+impl FromStr for Descriptor {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let descriptor = match s.find('#') {
+			Some(position) => {
+				let (descriptor, checksum) = (&s[..position], &s[position + 1..]);
+				if descriptor_checksum(descriptor).as_ref().map(String::as_str) != Some(checksum) {
+					return Err(invalid(format!("invalid checksum '{}'", checksum)));
+				}
+				descriptor
+			},
+			None => s,
+		};
+
+		let unsupported = || invalid(format!("unsupported descriptor '{}'", descriptor));
+		let (function, argument) = try!(split_function(descriptor).ok_or_else(&unsupported));
+		let result = match function {
+			"pkh" => Descriptor::Pkh(try!(parse_key(argument, KeyContext::Legacy))),
+			"wpkh" => Descriptor::Wpkh(try!(parse_key(argument, KeyContext::Segwit))),
+			"sh" => match split_function(argument) {
+				Some(("wpkh", key)) => Descriptor::ShWpkh(try!(parse_key(key, KeyContext::Segwit))),
+				Some(("multi", args)) => {
+					let (k, keys) = try!(parse_multi(args, KeyContext::Legacy, MAX_SH_MULTI_KEYS));
+					Descriptor::ShMulti(k, keys)
+				},
+				Some(("wsh", script)) => match split_function(script) {
+					Some(("multi", args)) => {
+						let (k, keys) = try!(parse_multi(args, KeyContext::Segwit, MAX_WSH_MULTI_KEYS));
+						Descriptor::ShWshMulti(k, keys)
+					},
+					_ => return Err(unsupported()),
+				},
+				_ => return Err(unsupported()),
+			},
+			"wsh" => match split_function(argument) {
+				Some(("multi", args)) => {
+					let (k, keys) = try!(parse_multi(args, KeyContext::Segwit, MAX_WSH_MULTI_KEYS));
+					Descriptor::WshMulti(k, keys)
+				},
+				_ => return Err(unsupported()),
+			},
+			"tr" => Descriptor::Tr(try!(parse_key(argument, KeyContext::Taproot))),
+			"addr" => Descriptor::Addr(try!(argument.parse().map_err(|_| invalid(format!("invalid address '{}'", argument))))),
+			"raw" => Descriptor::Raw(try!(argument.from_hex().map_err(|_| invalid(format!("invalid script '{}'", argument)))).into()),
+			_ => return Err(unsupported()),
+		};
+
+		Ok(result)
+	}
+}
+
+/// Computes BIP380 checksum of the descriptor. Returns None if descriptor contains invalid characters.
+pub fn descriptor_checksum(descriptor: &str) -> Option<String> {
+	let mut c = 1u64;
+	let mut class = 0u64;
+	let mut class_count = 0;
+	for ch in descriptor.chars() {
+		let position = INPUT_CHARSET.find(ch)? as u64;
+		c = poly_mod(c, position & 31);
+		class = class * 3 + (position >> 5);
+		class_count += 1;
+		if class_count == 3 {
+			c = poly_mod(c, class);
+			class = 0;
+			class_count = 0;
+		}
+	}
+	if class_count > 0 {
+		c = poly_mod(c, class);
+	}
+	for _ in 0..8 {
+		c = poly_mod(c, 0);
+	}
+	c ^= 1;
+
+	Some((0..8).map(|i| CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize] as char).collect())
+}
+
+fn poly_mod(c: u64, value: u64) -> u64 {
+	let c0 = c >> 35;
+	let mut c = ((c & 0x7_ffff_ffff) << 5) ^ value;
+	for (i, generator) in GENERATOR.iter().enumerate() {
+		if c0 & (1 << i) != 0 {
+			c ^= *generator;
+		}
+	}
+	c
+}
+
+fn invalid(reason: String) -> Error {
+	Error::InvalidDescriptor(reason)
+}
+
+/// Splits `function(argument)` into function name and argument.
+fn split_function(s: &str) -> Option<(&str, &str)> {
+	match s.find('(') {
+		Some(open) if s.ends_with(')') => Some((&s[..open], &s[open + 1..s.len() - 1])),
+		_ => None,
+	}
+}
+
+/// Parses `[fingerprint/path]KEY/path`.
+fn parse_key(s: &str, context: KeyContext) -> Result<DescriptorKey, Error> {
+	let (origin, encoded) = if s.starts_with('[') {
+		let end = try!(s.find(']').ok_or_else(|| invalid(format!("invalid key origin '{}'", s))));
+		(Some(try!(parse_origin(&s[1..end]))), &s[end + 1..])
+	} else {
+		(None, s)
+	};
+
+	let mut steps = encoded.split('/');
+	let encoded = steps.next().expect("split always returns at least one item; qed");
+	let invalid_key = || invalid(format!("invalid public key '{}'", encoded));
+
+	if encoded.chars().all(|c| c.is_digit(16)) {
+		if steps.next().is_some() {
+			return Err(invalid(format!("public key '{}' can't be derived", encoded)));
+		}
+
+		let public: Vec<u8> = try!(encoded.from_hex().map_err(|_| invalid_key()));
+		match (context, public.len()) {
+			(KeyContext::Legacy, 33) | (KeyContext::Legacy, 65) | (KeyContext::Segwit, 33) | (KeyContext::Taproot, 33) => {
+				try!(key::PublicKey::from_slice(&SECP256K1, &public).map_err(|_| invalid_key()));
+			},
+			(KeyContext::Taproot, 32) => {
+				// tweaking with zero checks that x-only key is a valid point
+				try!(tweak_public(&public[..].into(), &H256::default()).map_err(|_| invalid_key()));
+			},
+			_ => return Err(invalid_key()),
+		}
+
+		return Ok(DescriptorKey::Single {
+			origin: origin,
+			public: public.into(),
+		});
+	}
+
+	let extended: ExtendedPublic = try!(encoded.parse().map_err(|_| invalid_key()));
+	let mut path = Vec::new();
+	let mut wildcard = false;
+	for step in steps {
+		if wildcard {
+			return Err(invalid(format!("wildcard should be the last step of '{}'", s)));
+		}
+
+		if step == "*" {
+			wildcard = true;
+			continue;
+		}
+
+		match try!(parse_index(step)) {
+			index if index < HARDENED_INDEX => path.push(index),
+			_ => return Err(invalid(format!("hardened derivation of '{}' requires private key", s))),
+		}
+	}
+
+	Ok(DescriptorKey::Extended {
+		origin: origin,
+		key: extended,
+		path: path,
+		wildcard: wildcard,
+	})
+}
+
+/// Parses `fingerprint/path` of the key origin.
+fn parse_origin(s: &str) -> Result<KeyOrigin, Error> {
+	let mut steps = s.split('/');
+	let fingerprint = steps.next().expect("split always returns at least one item; qed");
+	if fingerprint.len() != 8 {
+		return Err(invalid(format!("invalid key fingerprint '{}'", fingerprint)));
+	}
+
+	let origin = KeyOrigin {
+		fingerprint: try!(fingerprint.parse().map_err(|_| invalid(format!("invalid key fingerprint '{}'", fingerprint)))),
+		path: try!(steps.map(parse_index).collect::<Result<Vec<_>, _>>()),
+	};
+
+	Ok(origin)
+}
+
+/// Parses derivation step, where hardened index is followed by `'` or `h`.
+fn parse_index(s: &str) -> Result<u32, Error> {
+	let (index, hardened) = if s.ends_with('\'') || s.ends_with('h') {
+		(&s[..s.len() - 1], true)
+	} else {
+		(s, false)
+	};
+
+	match index.parse::<u32>() {
+		Ok(index) if index < HARDENED_INDEX => Ok(if hardened { index + HARDENED_INDEX } else { index }),
+		_ => Err(invalid(format!("invalid derivation step '{}'", s))),
+	}
+}
+
+/// Parses `k,KEY1,...,KEYn` arguments of `multi`.
+fn parse_multi(s: &str, context: KeyContext, max_keys: usize) -> Result<(usize, Vec<DescriptorKey>), Error> {
+	let mut args = s.split(',');
+	let k = args.next().expect("split always returns at least one item; qed");
+	let k: usize = try!(k.parse().map_err(|_| invalid(format!("invalid multi threshold '{}'", k))));
+	let keys = try!(args.map(|key| parse_key(key, context)).collect::<Result<Vec<_>, _>>());
+	if k == 0 || k > keys.len() || keys.len() > max_keys {
+		return Err(invalid(format!("invalid multi threshold {} of {} keys", k, keys.len())));
+	}
+
+	Ok((k, keys))
+}
+
+fn format_path(path: &[u32]) -> String {
+	path.iter()
+		.map(|index| if *index >= HARDENED_INDEX {
+			format!("/{}'", index - HARDENED_INDEX)
+		} else {
+			format!("/{}", index)
+		})
+		.collect()
+}
+
+fn format_multi(k: usize, keys: &[DescriptorKey]) -> String {
+	let mut result = format!("multi({}", k);
+	for key in keys {
+		result.push_str(&format!(",{}", key));
+	}
+	result.push(')');
+	result
+}
+
+fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+	// descriptor scripts never push more than 75 bytes, so the length is the push opcode
+	script.push(data.len() as u8);
+	script.extend_from_slice(data);
+}
+
+/// Pushes number from 1 to 20.
+fn push_int(script: &mut Vec<u8>, n: usize) {
+	if n <= 16 {
+		script.push(OP_1 + n as u8 - 1);
+	} else {
+		push_data(script, &[n as u8]);
+	}
+}
+
+fn p2pkh(public: &[u8]) -> Bytes {
+	pkh_script(&*dhash160(public))
+}
+
+fn pkh_script(hash: &[u8]) -> Bytes {
+	let mut script = vec![OP_DUP, OP_HASH160];
+	push_data(&mut script, hash);
+	script.extend_from_slice(&[OP_EQUALVERIFY, OP_CHECKSIG]);
+	script.into()
+}
+
+fn p2wpkh(public: &[u8]) -> Bytes {
+	let mut script = vec![OP_0];
+	push_data(&mut script, &*dhash160(public));
+	script.into()
+}
+
+fn p2sh(redeem_script: &[u8]) -> Bytes {
+	sh_script(&*dhash160(redeem_script))
+}
+
+fn sh_script(hash: &[u8]) -> Bytes {
+	let mut script = vec![OP_HASH160];
+	push_data(&mut script, hash);
+	script.push(OP_EQUAL);
+	script.into()
+}
+
+fn p2wsh(witness_script: &[u8]) -> Bytes {
+	let mut script = vec![OP_0];
+	push_data(&mut script, &*sha256(witness_script));
+	script.into()
+}
+
+/// Taproot output without script path: internal key is tweaked with `hash_TapTweak(key)`.
+fn p2tr(public: &[u8]) -> Result<Bytes, Error> {
+	let internal: XOnlyPublic = match public.len() {
+		33 => public[1..33].into(),
+		_ => public.into(),
+	};
+	let (output, _) = try!(tweak_public(&internal, &tagged_hash(b"TapTweak", &*internal)));
+
+	let mut script = vec![OP_1];
+	push_data(&mut script, &*output);
+	Ok(script.into())
+}
+
+fn multi(k: usize, keys: &[DescriptorKey], index: u32) -> Result<Bytes, Error> {
+	let mut script = Vec::new();
+	push_int(&mut script, k);
+	for key in keys {
+		push_data(&mut script, &try!(key.derive(index)));
+	}
+	push_int(&mut script, keys.len());
+	script.push(OP_CHECKMULTISIG);
+	Ok(script.into())
+}
+
+#[cfg(test)]
+mod tests {
+	use Error;
+	use extended::HARDENED_INDEX;
+	use super::{Descriptor, DescriptorKey, descriptor_checksum};
+
+	// BIP32 test vector 1, chain m/0H
+	const XPUB: &'static str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+
+	fn descriptor(s: &str) -> Descriptor {
+		s.replace("XPUB", XPUB).parse().unwrap()
+	}
+
+	fn descriptor_err(s: &str) -> Error {
+		s.replace("XPUB", XPUB).parse::<Descriptor>().unwrap_err()
+	}
+
+	#[test]
+	fn test_descriptor_checksum() {
+		assert_eq!(descriptor_checksum("raw(deadbeef)"), Some("89f8spxm".to_owned()));
+		assert_eq!(descriptor_checksum("raw(\u{e9})"), None);
+	}
+
+	#[test]
+	fn test_descriptor_single_key() {
+		let wpkh = descriptor("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)");
+		assert!(!wpkh.is_range());
+		assert_eq!(wpkh.script_pubkey(0).unwrap(), "00147dd65592d0ab2fe0d0257d571abf032cd9db93dc".into());
+		assert_eq!(wpkh.to_string(), "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)#8zl0zxma");
+		assert_eq!(wpkh.to_string().parse::<Descriptor>(), Ok(wpkh.clone()));
+		assert_eq!(wpkh.expand(0..10).unwrap().len(), 1);
+
+		let sh_wsh_multi = descriptor("sh(wsh(multi(1,03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8,03499fdf9e895e719cfd64e67f07d38e3226aa7b63678949e6e49b241a60e823e4,02d7924d4f7d43ea965a465ae3095ff41131e5946f3c85f79e44adbcf8e27e080e)))");
+		assert_eq!(sh_wsh_multi.script_pubkey(0).unwrap(), "a914aec509e284f909f769bb7dda299a717c87cc97ac87".into());
+
+		let tr = descriptor("tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)");
+		assert_eq!(tr.script_pubkey(0).unwrap(), "512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11".into());
+	}
+
+	#[test]
+	fn test_descriptor_ranged() {
+		let wpkh = descriptor("wpkh([3442193e/0']XPUB/1/*)");
+		assert!(wpkh.is_range());
+		match wpkh {
+			Descriptor::Wpkh(DescriptorKey::Extended { ref origin, ref path, wildcard, .. }) => {
+				assert_eq!(origin.as_ref().unwrap().path, vec![HARDENED_INDEX]);
+				assert_eq!(path, &vec![1]);
+				assert!(wildcard);
+			},
+			_ => panic!("expected wpkh with extended key"),
+		}
+		assert_eq!(wpkh.expand(0..2).unwrap(), vec![
+			"0014bb653f150f4d080f2fea84c458f978d4a0bf522c".into(),
+			"00142ee48695dfc443836bd0e73e6cc4ea450c7eba2c".into(),
+		]);
+		assert_eq!(wpkh.to_string().parse::<Descriptor>(), Ok(wpkh.clone()));
+
+		let wsh_multi = descriptor("wsh(multi(1,XPUB/*,03499fdf9e895e719cfd64e67f07d38e3226aa7b63678949e6e49b241a60e823e4))");
+		assert_eq!(wsh_multi.expand(5..8).unwrap().len(), 3);
+	}
+
+	#[test]
+	fn test_descriptor_addr_and_raw() {
+		let addr = descriptor("addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)");
+		assert_eq!(addr.script_pubkey(0).unwrap(), "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac".into());
+		assert_eq!(addr.to_string(), "addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)#632p52jr");
+
+		let p2sh = descriptor("addr(3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy)");
+		assert_eq!(p2sh.script_pubkey(0).unwrap(), "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87".into());
+
+		let raw = descriptor("raw(deadbeef)#89f8spxm");
+		assert!(!raw.is_range());
+		assert_eq!(raw.expand(0..10).unwrap(), vec!["deadbeef".into()]);
+		assert_eq!(raw.to_string(), "raw(deadbeef)#89f8spxm");
+
+		assert!(descriptor_err("addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb)").to_string().contains("address"));
+		assert!(descriptor_err("raw(xx)").to_string().contains("script"));
+	}
+
+	#[test]
+	fn test_descriptor_errors() {
+		assert!(descriptor_err("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)#8zl0zxmb").to_string().contains("checksum"));
+		assert!(descriptor_err("wpkh(XPUB/1'/*)").to_string().contains("hardened"));
+		assert!(descriptor_err("wpkh(XPUB/*/1)").to_string().contains("wildcard"));
+		assert!(descriptor_err("wpkh(04a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7893aba425419bc27a3b6c7e693a24c696f794c2ed877a1593cbee53b037368d7)").to_string().contains("public key"));
+		assert!(descriptor_err("sh(wsh(multi(3,XPUB/*,XPUB/1/*)))").to_string().contains("threshold"));
+		assert!(descriptor_err("combo(XPUB)").to_string().contains("unsupported"));
+	}
+}
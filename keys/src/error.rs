@@ -11,6 +11,12 @@ pub enum Error {
 	InvalidChecksum,
 	InvalidPrivate,
 	InvalidAddress,
+	InvalidEncryptedPrivate,
+	InvalidIntermediateCode,
+	InvalidPassphrase,
+	InvalidExtendedPublic,
+	InvalidDerivation,
+	InvalidDescriptor(String),
 	FailedKeyGeneration,
 }
 
@@ -25,6 +31,12 @@ impl fmt::Display for Error {
 			Error::InvalidChecksum => "Invalid Checksum",
 			Error::InvalidPrivate => "Invalid Private",
 			Error::InvalidAddress => "Invalid Address",
+			Error::InvalidEncryptedPrivate => "Invalid Encrypted Private",
+			Error::InvalidIntermediateCode => "Invalid Intermediate Code",
+			Error::InvalidPassphrase => "Invalid Passphrase",
+			Error::InvalidExtendedPublic => "Invalid Extended Public",
+			Error::InvalidDerivation => "Hardened derivation requires private key",
+			Error::InvalidDescriptor(ref descriptor) => return write!(f, "Invalid Descriptor: {}", descriptor),
 			Error::FailedKeyGeneration => "Key generation failed",
 		};
 
@@ -0,0 +1,223 @@
+//! BIP32 extended public keys and non-hardened child key derivation.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+
+use std::fmt;
+use std::str::FromStr;
+use std::ops::Deref;
+use base58::{ToBase58, FromBase58};
+use secp256k1::key;
+use crypto::{checksum, dhash160, hmac_sha512};
+use hash::{H32, H256, H264};
+use network::Network;
+use {DisplayLayout, Error, Public, SECP256K1};
+
+/// Child indexes starting from this one are hardened and can't be derived from the public key.
+pub const HARDENED_INDEX: u32 = 0x8000_0000;
+/// Version bytes of mainnet extended public key (xpub).
+const MAINNET_VERSION: u32 = 0x0488_b21e;
+/// Version bytes of testnet extended public key (tpub).
+const TESTNET_VERSION: u32 = 0x0435_87cf;
+
+/// Extended public key (xpub)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPublic {
+	/// The network of the key.
+	pub network: Network,
+	/// Number of derivations from the master key.
+	pub depth: u8,
+	/// Fingerprint of the parent key.
+	pub parent_fingerprint: H32,
+	/// Index of this key in the parent key children.
+	pub child_number: u32,
+	/// Chain code.
+	pub chain_code: H256,
+	/// Compressed public key.
+	pub public: H264,
+}
+
+impl ExtendedPublic {
+	/// Public key
+	pub fn public(&self) -> Public {
+		Public::Compressed(self.public.clone())
+	}
+
+	/// First 4 bytes of the public key hash, identifying the key in the child keys and key origins.
+	pub fn fingerprint(&self) -> H32 {
+		let mut fingerprint = H32::default();
+		fingerprint.copy_from_slice(&dhash160(&*self.public)[0..4]);
+		fingerprint
+	}
+
+	/// Derives non-hardened child key.
+	pub fn derive(&self, index: u32) -> Result<Self, Error> {
+		if index >= HARDENED_INDEX {
+			return Err(Error::InvalidDerivation);
+		}
+
+		let context = &SECP256K1;
+		let mut data = Vec::with_capacity(37);
+		data.extend_from_slice(&*self.public);
+		data.extend_from_slice(&write_u32(index));
+		let i = hmac_sha512(&*self.chain_code, &data);
+
+		// fails if left half isn't a valid secret or if the child key is the point at infinity,
+		// which happens with the negligible probability
+		let tweak = try!(key::SecretKey::from_slice(context, &i[0..32]));
+		let mut point = try!(key::PublicKey::from_slice(context, &*self.public));
+		try!(point.add_exp_assign(context, &tweak));
+
+		let mut chain_code = H256::default();
+		chain_code.copy_from_slice(&i[32..64]);
+		let mut public = H264::default();
+		public.copy_from_slice(&point.serialize_vec(context, true)[0..33]);
+
+		Ok(ExtendedPublic {
+			network: self.network,
+			depth: self.depth.wrapping_add(1),
+			parent_fingerprint: self.fingerprint(),
+			child_number: index,
+			chain_code: chain_code,
+			public: public,
+		})
+	}
+
+	/// Derives the key at the given path of non-hardened indexes.
+	pub fn derive_path(&self, path: &[u32]) -> Result<Self, Error> {
+		let mut result = self.clone();
+		for index in path {
+			result = try!(result.derive(*index));
+		}
+		Ok(result)
+	}
+}
+
+pub struct ExtendedPublicDisplayLayout([u8; 82]);
+
+impl Deref for ExtendedPublicDisplayLayout {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DisplayLayout for ExtendedPublic {
+	type Target = ExtendedPublicDisplayLayout;
+
+	fn layout(&self) -> Self::Target {
+		let mut result = [0u8; 82];
+
+		// private networks are using testnet version, like regtest does
+		let version = match self.network {
+			Network::Mainnet => MAINNET_VERSION,
+			Network::Testnet | Network::Other { .. } => TESTNET_VERSION,
+		};
+		result[0..4].copy_from_slice(&write_u32(version));
+		result[4] = self.depth;
+		result[5..9].copy_from_slice(&*self.parent_fingerprint);
+		result[9..13].copy_from_slice(&write_u32(self.child_number));
+		result[13..45].copy_from_slice(&*self.chain_code);
+		result[45..78].copy_from_slice(&*self.public);
+		let cs = checksum(&result[0..78]);
+		result[78..82].copy_from_slice(&*cs);
+		ExtendedPublicDisplayLayout(result)
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		if data.len() != 82 {
+			return Err(Error::InvalidExtendedPublic);
+		}
+
+		let cs = checksum(&data[0..78]);
+		if &data[78..] != &*cs {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let network = match read_u32(&data[0..4]) {
+			MAINNET_VERSION => Network::Mainnet,
+			TESTNET_VERSION => Network::Testnet,
+			_ => return Err(Error::InvalidNetwork),
+		};
+
+		// public key should be valid compressed point
+		let context = &SECP256K1;
+		if data[45] != 0x02 && data[45] != 0x03 {
+			return Err(Error::InvalidPublic);
+		}
+		try!(key::PublicKey::from_slice(context, &data[45..78]));
+
+		let mut parent_fingerprint = H32::default();
+		parent_fingerprint.copy_from_slice(&data[5..9]);
+		let mut chain_code = H256::default();
+		chain_code.copy_from_slice(&data[13..45]);
+		let mut public = H264::default();
+		public.copy_from_slice(&data[45..78]);
+
+		let extended = ExtendedPublic {
+			network: network,
+			depth: data[4],
+			parent_fingerprint: parent_fingerprint,
+			child_number: read_u32(&data[9..13]),
+			chain_code: chain_code,
+			public: public,
+		};
+
+		Ok(extended)
+	}
+}
+
+impl fmt::Display for ExtendedPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPublic {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let hex = try!(s.from_base58().map_err(|_| Error::InvalidExtendedPublic));
+		ExtendedPublic::from_layout(&hex)
+	}
+}
+
+fn write_u32(value: u32) -> [u8; 4] {
+	[(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+	(data[0] as u32) << 24 | (data[1] as u32) << 16 | (data[2] as u32) << 8 | data[3] as u32
+}
+
+#[cfg(test)]
+mod tests {
+	use Error;
+	use super::{ExtendedPublic, HARDENED_INDEX};
+
+	// BIP32 test vector 1, chain m/0H
+	const XPUB: &'static str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+
+	#[test]
+	fn test_extended_public_from_str() {
+		let xpub: ExtendedPublic = XPUB.parse().unwrap();
+		assert_eq!(xpub.depth, 1);
+		assert_eq!(xpub.child_number, HARDENED_INDEX);
+		assert_eq!(xpub.parent_fingerprint, "3442193e".into());
+		assert_eq!(xpub.public, "035a784662a4a20a65bf6aab9ae98a6c068a81c52e4b032c0fb5400c706cfccc56".into());
+		assert_eq!(xpub.fingerprint(), "5c1bd648".into());
+		assert_eq!(xpub.to_string(), XPUB);
+
+		assert_eq!("xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnx".parse::<ExtendedPublic>(), Err(Error::InvalidChecksum));
+		assert_eq!("16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".parse::<ExtendedPublic>(), Err(Error::InvalidExtendedPublic));
+	}
+
+	#[test]
+	fn test_extended_public_derive() {
+		let xpub: ExtendedPublic = XPUB.parse().unwrap();
+		let child = xpub.derive(1).unwrap();
+		assert_eq!(child.to_string(), "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ");
+		assert_eq!(xpub.derive_path(&[1]), Ok(child));
+		assert_eq!(xpub.derive(HARDENED_INDEX), Err(Error::InvalidDerivation));
+	}
+}
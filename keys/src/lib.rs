@@ -11,23 +11,31 @@ extern crate primitives;
 
 pub mod generator;
 mod address;
+mod bip38;
+mod descriptor;
 mod display;
+mod extended;
 mod keypair;
 mod error;
 mod network;
 mod private;
 mod public;
+mod schnorr;
 mod signature;
 
 pub use rustc_serialize::hex;
 pub use primitives::{hash, bytes};
 
 pub use address::{Type, Address};
+pub use bip38::{EncryptedPrivate, IntermediateCode};
+pub use descriptor::{Descriptor, DescriptorKey, KeyOrigin, descriptor_checksum};
 pub use display::DisplayLayout;
+pub use extended::{ExtendedPublic, HARDENED_INDEX};
 pub use keypair::KeyPair;
 pub use error::Error;
 pub use private::Private;
 pub use public::Public;
+pub use schnorr::{XOnlyPublic, SchnorrSignature, verify_schnorr, sign_schnorr, x_only_public, tweak_public, tweak_secret};
 pub use signature::{Signature, CompactSignature};
 pub use network::Network;
 
@@ -2,4 +2,10 @@
 pub enum Network {
 	Mainnet,
 	Testnet,
+	/// Private network with custom address and private key prefixes.
+	Other {
+		p2pkh: u8,
+		p2sh: u8,
+		private: u8,
+	},
 }
@@ -58,6 +58,7 @@ impl DisplayLayout for Private {
 		let network_byte = match self.network {
 			Network::Mainnet => 128,
 			Network::Testnet => 239,
+			Network::Other { private, .. } => private,
 		};
 
 		result.push(network_byte);
@@ -0,0 +1,249 @@
+//! BIP340 Schnorr signatures and BIP341 x-only public key tweaking.
+
+use secp256k1::key;
+use crypto::tagged_hash;
+use hash::{H256, H512};
+use {Error, Message, Secret, SECP256K1};
+
+/// 32 bytes long x-only public key (BIP340)
+pub type XOnlyPublic = H256;
+/// 64 bytes long Schnorr signature (BIP340)
+pub type SchnorrSignature = H512;
+
+/// secp256k1 field size
+const FIELD_SIZE: [u8; 32] = [
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xfc, 0x2f,
+];
+
+/// secp256k1 curve order
+const CURVE_ORDER: [u8; 32] = [
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+	0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Verifies BIP340 Schnorr signature of the message.
+pub fn verify_schnorr(public: &XOnlyPublic, message: &Message, signature: &SchnorrSignature) -> Result<bool, Error> {
+	let context = &SECP256K1;
+	let public_key = try!(lift_x(public));
+
+	let mut r = [0u8; 32];
+	r.copy_from_slice(&signature[0..32]);
+	let mut s = [0u8; 32];
+	s.copy_from_slice(&signature[32..64]);
+	if !is_less(&r, &FIELD_SIZE) || !is_less(&s, &CURVE_ORDER) {
+		return Ok(false);
+	}
+
+	// R = s * G - e * P
+	let e = challenge(&r, public, message);
+	let mut terms = Vec::with_capacity(2);
+	if !is_zero(&s) {
+		let s = try!(key::SecretKey::from_slice(context, &s));
+		terms.push(try!(key::PublicKey::from_secret_key(context, &s)));
+	}
+	if !is_zero(&e) {
+		let negated_e = try!(key::SecretKey::from_slice(context, &sub(&CURVE_ORDER, &e)));
+		let mut e_p = public_key;
+		try!(e_p.mul_assign(context, &negated_e));
+		terms.push(e_p);
+	}
+
+	let mut terms = terms.into_iter();
+	let mut point = match terms.next() {
+		Some(point) => point,
+		None => return Ok(false),
+	};
+	if let Some(term) = terms.next() {
+		// fails when the sum is the point at infinity
+		if point.add_assign(context, &term).is_err() {
+			return Ok(false);
+		}
+	}
+
+	let serialized = point.serialize_vec(context, true);
+	Ok(serialized[0] == 0x02 && serialized[1..33] == r[..])
+}
+
+/// Signs the message with BIP340 Schnorr signature, using `aux` as auxiliary random data.
+pub fn sign_schnorr(secret: &Secret, message: &Message, aux: &H256) -> Result<SchnorrSignature, Error> {
+	let context = &SECP256K1;
+	let (d, public) = try!(even_secret(secret));
+
+	let aux_hash = tagged_hash(b"BIP0340/aux", &**aux);
+	let mut nonce_data = Vec::with_capacity(96);
+	nonce_data.extend(d.iter().zip(aux_hash.iter()).map(|(d, a)| d ^ a));
+	nonce_data.extend_from_slice(&*public);
+	nonce_data.extend_from_slice(&**message);
+	let k0 = reduce(&tagged_hash(b"BIP0340/nonce", &nonce_data).take());
+	let (k, r) = try!(even_secret(&k0.into()));
+
+	let e = challenge(&*r, &public, message);
+	let mut s = try!(key::SecretKey::from_slice(context, &e));
+	try!(s.mul_assign(context, &try!(key::SecretKey::from_slice(context, &d))));
+	try!(s.add_assign(context, &try!(key::SecretKey::from_slice(context, &k))));
+
+	let mut signature = SchnorrSignature::default();
+	signature[0..32].copy_from_slice(&*r);
+	signature[32..64].copy_from_slice(&s[0..32]);
+	Ok(signature)
+}
+
+/// Returns x-only public key of the secret.
+pub fn x_only_public(secret: &Secret) -> Result<XOnlyPublic, Error> {
+	even_secret(secret).map(|(_, public)| public)
+}
+
+/// Tweaks x-only public key: `Q = P + tweak * G`.
+/// Returns x-only tweaked key and true if its y coordinate is odd.
+pub fn tweak_public(public: &XOnlyPublic, tweak: &H256) -> Result<(XOnlyPublic, bool), Error> {
+	let context = &SECP256K1;
+	if !is_less(&**tweak, &CURVE_ORDER) {
+		return Err(Error::InvalidSecret);
+	}
+
+	let mut point = try!(lift_x(public));
+	if !tweak.is_zero() {
+		try!(point.add_exp_assign(context, &try!(key::SecretKey::from_slice(context, &**tweak))));
+	}
+
+	let serialized = point.serialize_vec(context, true);
+	let mut tweaked = XOnlyPublic::default();
+	tweaked.copy_from_slice(&serialized[1..33]);
+	Ok((tweaked, serialized[0] == 0x03))
+}
+
+/// Tweaks the secret, so that it matches `tweak_public` of its x-only public key.
+pub fn tweak_secret(secret: &Secret, tweak: &H256) -> Result<Secret, Error> {
+	let context = &SECP256K1;
+	let (d, _) = try!(even_secret(secret));
+	let mut tweaked = try!(key::SecretKey::from_slice(context, &d));
+	if !tweak.is_zero() {
+		try!(tweaked.add_assign(context, &try!(key::SecretKey::from_slice(context, &**tweak))));
+	}
+
+	let mut result = Secret::default();
+	result.copy_from_slice(&tweaked[0..32]);
+	Ok(result)
+}
+
+/// Returns point with the given x coordinate and even y coordinate.
+fn lift_x(x: &XOnlyPublic) -> Result<key::PublicKey, Error> {
+	let mut compressed = [0u8; 33];
+	compressed[0] = 0x02;
+	compressed[1..33].copy_from_slice(&**x);
+	key::PublicKey::from_slice(&SECP256K1, &compressed).map_err(|_| Error::InvalidPublic)
+}
+
+/// Returns secret (negated if needed), whose public key has even y coordinate, and x coordinate of this key.
+fn even_secret(secret: &Secret) -> Result<([u8; 32], XOnlyPublic), Error> {
+	let context = &SECP256K1;
+	let secret_key = try!(key::SecretKey::from_slice(context, &**secret));
+	let public = try!(key::PublicKey::from_secret_key(context, &secret_key));
+	let serialized = public.serialize_vec(context, true);
+
+	let mut x = XOnlyPublic::default();
+	x.copy_from_slice(&serialized[1..33]);
+	let d = if serialized[0] == 0x03 {
+		sub(&CURVE_ORDER, &**secret)
+	} else {
+		secret.clone().take()
+	};
+	Ok((d, x))
+}
+
+/// BIP340 challenge: `tagged_hash(r || P || m) mod n`.
+fn challenge(r: &[u8], public: &XOnlyPublic, message: &Message) -> [u8; 32] {
+	let mut data = Vec::with_capacity(96);
+	data.extend_from_slice(r);
+	data.extend_from_slice(&**public);
+	data.extend_from_slice(&**message);
+	reduce(&tagged_hash(b"BIP0340/challenge", &data).take())
+}
+
+/// Reduces 256-bit number modulo curve order. Numbers are never larger than twice the order.
+fn reduce(value: &[u8; 32]) -> [u8; 32] {
+	if is_less(value, &CURVE_ORDER) {
+		*value
+	} else {
+		sub(value, &CURVE_ORDER)
+	}
+}
+
+/// Compares big-endian 256-bit numbers.
+fn is_less(a: &[u8], b: &[u8]) -> bool {
+	a < b
+}
+
+fn is_zero(value: &[u8]) -> bool {
+	value.iter().all(|byte| *byte == 0)
+}
+
+/// Subtracts big-endian 256-bit numbers, `a` must not be less than `b`.
+fn sub(a: &[u8], b: &[u8]) -> [u8; 32] {
+	let mut result = [0u8; 32];
+	let mut borrow = 0i16;
+	for i in (0..32).rev() {
+		let mut diff = a[i] as i16 - b[i] as i16 - borrow;
+		borrow = if diff < 0 {
+			diff += 256;
+			1
+		} else {
+			0
+		};
+		result[i] = diff as u8;
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::{H256, H512};
+	use super::{verify_schnorr, sign_schnorr, x_only_public, tweak_public, tweak_secret};
+
+	// BIP340 test vector 0
+	#[test]
+	fn test_sign_and_verify_schnorr() {
+		let secret = "0000000000000000000000000000000000000000000000000000000000000003".into();
+		let public: H256 = "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9".into();
+		let signature: H512 = "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0".into();
+		assert_eq!(x_only_public(&secret).unwrap(), public);
+		assert_eq!(sign_schnorr(&secret, &H256::default(), &H256::default()).unwrap(), signature);
+		assert_eq!(verify_schnorr(&public, &H256::default(), &signature), Ok(true));
+	}
+
+	// BIP340 test vector 1
+	#[test]
+	fn test_sign_and_verify_schnorr_odd_public() {
+		let secret = "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef".into();
+		let public: H256 = "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659".into();
+		let message = "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89".into();
+		let aux = "0000000000000000000000000000000000000000000000000000000000000001".into();
+		let signature: H512 = "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a".into();
+		assert_eq!(x_only_public(&secret).unwrap(), public);
+		assert_eq!(sign_schnorr(&secret, &message, &aux).unwrap(), signature);
+		assert_eq!(verify_schnorr(&public, &message, &signature), Ok(true));
+		assert_eq!(verify_schnorr(&public, &H256::default(), &signature), Ok(false));
+	}
+
+	#[test]
+	fn test_verify_schnorr_invalid_public() {
+		// BIP340 test vector 5: public key is not on the curve
+		let public = "eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34".into();
+		assert!(verify_schnorr(&public, &H256::default(), &H512::default()).is_err());
+	}
+
+	#[test]
+	fn test_tweak_public_and_secret() {
+		let secret = "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef".into();
+		let public = x_only_public(&secret).unwrap();
+		let tweak = "543455a6f0394c380698aaf13d8a0e3fcd42e8d01128ba3b52f262f1cff96801".into();
+		let (tweaked, is_odd) = tweak_public(&public, &tweak).unwrap();
+		assert_eq!(tweaked, "7ad4375032c38eba4fc60deca75fa30a3a6bdf2fb38f7e617288e2d3776117cb".into());
+		assert!(is_odd);
+
+		let tweaked_secret = tweak_secret(&secret, &tweak).unwrap();
+		assert_eq!(tweaked_secret, "0c15a7097b2676a2c60a0371da7f0208757b21f89a94f4563aa4dd695153f6af".into());
+		assert_eq!(x_only_public(&tweaked_secret).unwrap(), tweaked);
+	}
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+use std::str::FromStr;
+use log::{LogLevel, LogLevelFilter};
+
+/// Log levels of the targets, in `env_logger` format: `info,sync=debug,p2p=trace`.
+/// Level without target is used for the targets, not mentioned in the filters.
+/// Target without level enables all messages of the target.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LogFilters {
+	default: Option<LogLevelFilter>,
+	targets: Vec<(String, LogLevelFilter)>,
+}
+
+impl LogFilters {
+	/// Should the message be logged? The longest target prefix, matching the message target, is used.
+	pub fn enabled(&self, level: LogLevel, target: &str) -> bool {
+		let filter = self.targets.iter()
+			.filter(|&&(ref name, _)| target.starts_with(name.as_str()))
+			.max_by_key(|&&(ref name, _)| name.len())
+			.map(|&(_, filter)| filter)
+			.or(self.default)
+			.unwrap_or(LogLevelFilter::Off);
+		level <= filter
+	}
+
+	/// The most verbose level of all targets.
+	pub fn max_level(&self) -> LogLevelFilter {
+		self.targets.iter()
+			.map(|&(_, filter)| filter)
+			.chain(self.default)
+			.max()
+			.unwrap_or(LogLevelFilter::Off)
+	}
+
+	/// Replaces levels of the targets, mentioned in `filters`.
+	pub fn update(&mut self, filters: LogFilters) {
+		if filters.default.is_some() {
+			self.default = filters.default;
+		}
+
+		for (name, filter) in filters.targets {
+			self.set_target(name, filter);
+		}
+	}
+
+	fn set_target(&mut self, name: String, filter: LogLevelFilter) {
+		match self.targets.iter().position(|&(ref target, _)| *target == name) {
+			Some(index) => self.targets[index].1 = filter,
+			None => self.targets.push((name, filter)),
+		}
+	}
+}
+
+impl FromStr for LogFilters {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut filters = LogFilters::default();
+		for directive in s.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+			let mut parts = directive.splitn(2, '=');
+			let name = parts.next().expect("splitn always yields at least one item; qed").trim();
+			match parts.next() {
+				Some(level) => {
+					let filter = level.trim().parse().map_err(|_| format!("Invalid log level: {}", level))?;
+					filters.set_target(name.to_owned(), filter);
+				},
+				None => match name.parse() {
+					Ok(filter) => filters.default = Some(filter),
+					Err(_) => filters.set_target(name.to_owned(), LogLevelFilter::Trace),
+				},
+			}
+		}
+
+		Ok(filters)
+	}
+}
+
+impl fmt::Display for LogFilters {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let default = self.default.map(|filter| filter.to_string().to_lowercase());
+		let targets = self.targets.iter().map(|&(ref name, filter)| format!("{}={}", name, filter.to_string().to_lowercase()));
+		let directives: Vec<_> = default.into_iter().chain(targets).collect();
+		f.write_str(&directives.join(","))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use log::{LogLevel, LogLevelFilter};
+	use super::LogFilters;
+
+	#[test]
+	fn test_log_filters_parse() {
+		let filters: LogFilters = "info, sync=debug,p2p".parse().unwrap();
+		assert_eq!(filters.to_string(), "info,sync=debug,p2p=trace");
+		assert_eq!(filters.max_level(), LogLevelFilter::Trace);
+		assert!("sync=verbose".parse::<LogFilters>().is_err());
+		assert_eq!("".parse::<LogFilters>().unwrap().max_level(), LogLevelFilter::Off);
+	}
+
+	#[test]
+	fn test_log_filters_enabled() {
+		let filters: LogFilters = "warn,sync=debug,sync::blocks=error".parse().unwrap();
+		assert!(filters.enabled(LogLevel::Debug, "sync"));
+		assert!(filters.enabled(LogLevel::Debug, "sync::synchronization_client"));
+		assert!(!filters.enabled(LogLevel::Trace, "sync"));
+		assert!(!filters.enabled(LogLevel::Warn, "sync::blocks"));
+		assert!(filters.enabled(LogLevel::Warn, "p2p"));
+		assert!(!filters.enabled(LogLevel::Info, "p2p"));
+
+		let filters: LogFilters = "sync=info".parse().unwrap();
+		assert!(!filters.enabled(LogLevel::Error, "p2p"));
+	}
+
+	#[test]
+	fn test_log_filters_update() {
+		let mut filters: LogFilters = "info,sync=debug".parse().unwrap();
+		filters.update("p2p=trace,sync=warn".parse().unwrap());
+		assert_eq!(filters.to_string(), "info,sync=warn,p2p=trace");
+		filters.update("error".parse().unwrap());
+		assert_eq!(filters.to_string(), "error,sync=warn,p2p=trace");
+	}
+}
@@ -1,12 +1,28 @@
 extern crate ansi_term;
+#[macro_use]
 extern crate log;
-extern crate env_logger;
+#[macro_use]
+extern crate lazy_static;
 extern crate time;
+extern crate parking_lot;
+
+#[cfg(test)]
+extern crate tempdir;
+
+mod filters;
+mod rotating_file;
+mod spans;
 
 use std::env;
+use std::io::{self, Write};
+use std::sync::Arc;
 use ansi_term::Colour as Color;
-use log::{LogRecord, LogLevel};
-use env_logger::LogBuilder;
+use log::{Log, LogRecord, LogLevel, LogMetadata, MaxLogLevelFilter};
+use parking_lot::{Mutex, RwLock};
+
+pub use filters::LogFilters;
+pub use rotating_file::RotatingFile;
+pub use spans::{Span, span, enable_spans, spans_enabled, write_folded_spans};
 
 fn strftime() -> String {
 	time::strftime("%Y-%m-%d %H:%M:%S %Z", &time::now()).expect("Time is incorrectly formatted")
@@ -45,14 +61,117 @@ impl LogFormatter for DateAndColorLogFormatter {
 	}
 }
 
-pub fn init<T>(filters: &str, formatter: T) where T: LogFormatter {
-	let mut builder = LogBuilder::new();
+/// Formats every message as a single line JSON object with `timestamp`, `level`, `target` and `message` fields.
+pub struct JsonLogFormatter;
+
+impl LogFormatter for JsonLogFormatter {
+	fn format(&self, record: &LogRecord) -> String {
+		format!(r#"{{"timestamp":"{}","level":"{}","target":"{}","message":"{}"}}"#
+			, time::now_utc().rfc3339()
+			, record.level()
+			, json_escape(record.target())
+			, json_escape(&record.args().to_string()))
+	}
+}
+
+fn json_escape(s: &str) -> String {
+	let mut escaped = String::with_capacity(s.len());
+	for c in s.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+struct Logger {
+	filters: Arc<RwLock<LogFilters>>,
+	formatter: Box<LogFormatter>,
+	output: Mutex<Box<Write + Send>>,
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &LogMetadata) -> bool {
+		self.filters.read().enabled(metadata.level(), metadata.target())
+	}
+
+	fn log(&self, record: &LogRecord) {
+		if self.enabled(record.metadata()) {
+			let mut line = self.formatter.format(record);
+			line.push('\n');
+			// there's nowhere to report failed writes
+			let _ = self.output.lock().write_all(line.as_bytes());
+		}
+	}
+}
+
+/// Allows changing log filters of the running logger.
+#[derive(Clone)]
+pub struct LogHandle {
+	filters: Arc<RwLock<LogFilters>>,
+	max_level: Option<Arc<MaxLogLevelFilter>>,
+}
+
+impl LogHandle {
+	/// Handle, which isn't connected to any logger.
+	pub fn new(filters: LogFilters) -> Self {
+		LogHandle {
+			filters: Arc::new(RwLock::new(filters)),
+			max_level: None,
+		}
+	}
+
+	/// Current filters.
+	pub fn filters(&self) -> LogFilters {
+		self.filters.read().clone()
+	}
+
+	/// Replaces levels of the targets, mentioned in `filters`. Returns updated filters.
+	pub fn update_filters(&self, filters: &str) -> Result<LogFilters, String> {
+		let filters = filters.parse()?;
+		let mut current = self.filters.write();
+		current.update(filters);
+		if let Some(ref max_level) = self.max_level {
+			max_level.set(current.max_level());
+		}
+		Ok(current.clone())
+	}
+}
+
+/// Initializes logger, writing to the standard error.
+pub fn init<T>(filters: &str, formatter: T) -> Result<LogHandle, String> where T: LogFormatter {
+	init_with_output(filters, formatter, Box::new(io::stderr()))
+}
+
+/// Initializes logger, writing to the given output. Filters from `RUST_LOG` environment variable
+/// take precedence over `filters`.
+pub fn init_with_output<T>(filters: &str, formatter: T, output: Box<Write + Send>) -> Result<LogHandle, String> where T: LogFormatter {
 	let filters = match env::var("RUST_LOG") {
-		Ok(env_filters) => format!("{},{}", env_filters, filters),
+		Ok(env_filters) => format!("{},{}", filters, env_filters),
 		Err(_) => filters.into(),
 	};
 
-	builder.parse(&filters);
-	builder.format(move |record| formatter.format(record));
-	builder.init().expect("Logger can be initialized only once");
+	let mut handle = LogHandle::new(filters.parse()?);
+	let logger = Logger {
+		filters: handle.filters.clone(),
+		formatter: Box::new(formatter),
+		output: Mutex::new(output),
+	};
+
+	let max_level = handle.filters.read().max_level();
+	let mut max_level_filter = None;
+	log::set_logger(|filter| {
+		filter.set(max_level);
+		max_level_filter = Some(Arc::new(filter));
+		Box::new(logger)
+	}).map_err(|_| "Logger can be initialized only once".to_owned())?;
+
+	handle.max_level = max_level_filter;
+	Ok(handle)
 }
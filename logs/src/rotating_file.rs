@@ -0,0 +1,107 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Log file, which is rotated when its size exceeds the limit.
+/// Rotated files are renamed to `PATH.1`, `PATH.2`, ..., so that `PATH.1` is always the most recent one.
+/// Only `max_files` rotated files are kept.
+pub struct RotatingFile {
+	path: PathBuf,
+	max_size: u64,
+	max_files: usize,
+	file: File,
+	size: u64,
+}
+
+impl RotatingFile {
+	pub fn open<P: AsRef<Path>>(path: P, max_size: u64, max_files: usize) -> io::Result<Self> {
+		let path = path.as_ref().to_path_buf();
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		let file = OpenOptions::new().create(true).append(true).open(&path)?;
+		let size = file.metadata()?.len();
+		Ok(RotatingFile {
+			path: path,
+			max_size: max_size,
+			max_files: max_files,
+			file: file,
+			size: size,
+		})
+	}
+
+	fn rotated_path(&self, index: usize) -> PathBuf {
+		let mut path = self.path.clone().into_os_string();
+		path.push(format!(".{}", index));
+		path.into()
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file.flush()?;
+		if self.max_files != 0 {
+			let oldest = self.rotated_path(self.max_files);
+			if oldest.exists() {
+				fs::remove_file(oldest)?;
+			}
+
+			for index in (1..self.max_files).rev() {
+				let path = self.rotated_path(index);
+				if path.exists() {
+					fs::rename(path, self.rotated_path(index + 1))?;
+				}
+			}
+
+			fs::rename(&self.path, self.rotated_path(1))?;
+		}
+
+		self.file = File::create(&self.path)?;
+		self.size = 0;
+		Ok(())
+	}
+}
+
+impl Write for RotatingFile {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if self.size != 0 && self.size + buf.len() as u64 > self.max_size {
+			self.rotate()?;
+		}
+
+		let written = self.file.write(buf)?;
+		self.size += written as u64;
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.file.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use std::io::{Read, Write};
+	use tempdir::TempDir;
+	use super::RotatingFile;
+
+	#[test]
+	fn test_rotating_file() {
+		let dir = TempDir::new("logs").unwrap();
+		let path = dir.path().join("pbtc.log");
+		let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+		for line in &["line 1\n", "line 2\n", "line 3\n", "line 4\n"] {
+			file.write_all(line.as_bytes()).unwrap();
+		}
+		file.flush().unwrap();
+
+		let read = |name: &str| {
+			let mut contents = String::new();
+			fs::File::open(dir.path().join(name)).and_then(|mut file| file.read_to_string(&mut contents)).unwrap();
+			contents
+		};
+		assert_eq!(read("pbtc.log"), "line 4\n");
+		assert_eq!(read("pbtc.log.1"), "line 3\n");
+		assert_eq!(read("pbtc.log.2"), "line 2\n");
+		assert!(!dir.path().join("pbtc.log.3").exists());
+	}
+}
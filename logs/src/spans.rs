@@ -0,0 +1,147 @@
+//! Lightweight timing spans.
+//!
+//! When enabled, every span measures time spent inside it, excluding nested spans, and
+//! aggregates it by the stack of enclosing spans of the current thread. Aggregated times can be
+//! written in the folded stacks format, accepted by flamegraph tools (`flamegraph.pl`, `inferno`).
+
+use std::cell::RefCell;
+use std::collections::{HashMap, BTreeMap};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Microseconds spent in every stack of spans.
+type Stacks = Arc<Mutex<HashMap<String, u64>>>;
+
+lazy_static! {
+	/// Stacks of all threads, which have ever entered a span.
+	static ref THREADS_STACKS: Mutex<Vec<Stacks>> = Mutex::new(Vec::new());
+}
+
+struct ActiveSpan {
+	name: &'static str,
+	start: Instant,
+	nested: Duration,
+}
+
+thread_local! {
+	static ACTIVE_SPANS: RefCell<Vec<ActiveSpan>> = RefCell::new(Vec::new());
+	static THREAD_STACKS: Stacks = {
+		let stacks = Stacks::default();
+		THREADS_STACKS.lock().push(stacks.clone());
+		stacks
+	};
+}
+
+/// Starts collecting spans timings.
+pub fn enable_spans() {
+	ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn spans_enabled() -> bool {
+	ENABLED.load(Ordering::Relaxed)
+}
+
+/// Enters the span. Span is exited when the returned guard is dropped.
+/// Does nothing, unless spans are enabled.
+pub fn span(name: &'static str) -> Span {
+	if !spans_enabled() {
+		return Span {
+			entered: false,
+			_not_send: PhantomData,
+		};
+	}
+
+	ACTIVE_SPANS.with(|spans| spans.borrow_mut().push(ActiveSpan {
+		name: name,
+		start: Instant::now(),
+		nested: Duration::default(),
+	}));
+
+	Span {
+		entered: true,
+		_not_send: PhantomData,
+	}
+}
+
+/// Guard of the entered span. Must be dropped on the same thread it was created.
+#[must_use]
+pub struct Span {
+	entered: bool,
+	_not_send: PhantomData<*const ()>,
+}
+
+impl Drop for Span {
+	fn drop(&mut self) {
+		if !self.entered {
+			return;
+		}
+
+		let (stack, self_time) = ACTIVE_SPANS.with(|spans| {
+			let mut spans = spans.borrow_mut();
+			let stack = spans.iter().map(|span| span.name).collect::<Vec<_>>().join(";");
+			let span = spans.pop().expect("span is entered on this thread; qed");
+			let elapsed = span.start.elapsed();
+			if let Some(parent) = spans.last_mut() {
+				parent.nested += elapsed;
+			}
+			(stack, elapsed.checked_sub(span.nested).unwrap_or_default())
+		});
+
+		let micros = self_time.as_secs() * 1_000_000 + self_time.subsec_nanos() as u64 / 1_000;
+		trace!(target: "spans", "{} {}us", stack, micros);
+		THREAD_STACKS.with(|stacks| *stacks.lock().entry(stack).or_insert(0) += micros);
+	}
+}
+
+/// Writes microseconds spent in every spans stack of all threads, in the folded stacks format.
+pub fn write_folded_spans<W: Write>(writer: &mut W) -> io::Result<()> {
+	let mut stacks = BTreeMap::new();
+	for thread_stacks in THREADS_STACKS.lock().iter() {
+		for (stack, micros) in thread_stacks.lock().iter() {
+			*stacks.entry(stack.clone()).or_insert(0) += *micros;
+		}
+	}
+
+	for (stack, micros) in stacks {
+		writeln!(writer, "{} {}", stack, micros)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+	use std::time::Duration;
+	use super::{enable_spans, span, write_folded_spans};
+
+	#[test]
+	fn test_folded_spans() {
+		enable_spans();
+		thread::spawn(|| {
+			let _outer = span("test_outer");
+			thread::sleep(Duration::from_millis(2));
+			{
+				let _inner = span("test_inner");
+				thread::sleep(Duration::from_millis(2));
+			}
+		}).join().unwrap();
+
+		let mut folded = Vec::new();
+		write_folded_spans(&mut folded).unwrap();
+		let folded = String::from_utf8(folded).unwrap();
+		let lines: Vec<_> = folded.lines().filter(|line| line.starts_with("test_outer")).collect();
+		assert_eq!(lines.len(), 2);
+		assert!(lines[0].starts_with("test_outer "));
+		assert!(lines[1].starts_with("test_outer;test_inner "));
+		for line in lines {
+			let micros: u64 = line.rsplit(' ').next().unwrap().parse().unwrap();
+			assert!(micros >= 2000);
+		}
+	}
+}
@@ -3,6 +3,7 @@ use ser::deserialize;
 use common::{Port, IpAddress, Services};
 
 #[derive(Debug, Default, PartialEq, Clone, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct NetAddress {
 	pub services: Services,
 	pub address: IpAddress,
@@ -0,0 +1,145 @@
+use std::{io, net};
+use bytes::Bytes;
+use hash::H256;
+use ser::{Serializable, Stream, Deserializable, Reader, Error as ReaderError, CompactInteger};
+
+/// Maximal length of the address, which is accepted from other nodes.
+pub const MAX_ADDRESS_V2_LEN: usize = 512;
+
+const NETWORK_IPV4: u8 = 1;
+const NETWORK_IPV6: u8 = 2;
+const NETWORK_TORV3: u8 = 4;
+const NETWORK_I2P: u8 = 5;
+const NETWORK_CJDNS: u8 = 6;
+
+/// Network address, as defined by BIP155.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub enum NetAddressV2 {
+	IpV4(net::Ipv4Addr),
+	IpV6(net::Ipv6Addr),
+	/// Public key of the Tor v3 onion service
+	TorV3(H256),
+	/// SHA256 hash of the I2P destination
+	I2p(H256),
+	/// CJDNS address, which is always in fc00::/8 range
+	Cjdns(net::Ipv6Addr),
+	/// Address in the network, unknown to this node
+	Unknown(u8, Bytes),
+}
+
+impl From<net::IpAddr> for NetAddressV2 {
+	fn from(ip: net::IpAddr) -> Self {
+		match ip {
+			net::IpAddr::V4(ip) => NetAddressV2::IpV4(ip),
+			net::IpAddr::V6(ip) => match ip.to_ipv4() {
+				// ipv4-mapped addresses are sent as ipv4
+				Some(ipv4) if ip.segments()[5] == 0xffff => NetAddressV2::IpV4(ipv4),
+				_ => NetAddressV2::IpV6(ip),
+			},
+		}
+	}
+}
+
+impl NetAddressV2 {
+	/// Returns IP address, if this is address in IPv4 or IPv6 network.
+	pub fn ip(&self) -> Option<net::IpAddr> {
+		match *self {
+			NetAddressV2::IpV4(ip) => Some(net::IpAddr::V4(ip)),
+			NetAddressV2::IpV6(ip) => Some(net::IpAddr::V6(ip)),
+			_ => None,
+		}
+	}
+}
+
+impl Serializable for NetAddressV2 {
+	fn serialize(&self, stream: &mut Stream) {
+		match *self {
+			NetAddressV2::IpV4(ref ip) => serialize_address(stream, NETWORK_IPV4, &ip.octets()),
+			NetAddressV2::IpV6(ref ip) => serialize_address(stream, NETWORK_IPV6, &ip.octets()),
+			NetAddressV2::TorV3(ref key) => serialize_address(stream, NETWORK_TORV3, &**key),
+			NetAddressV2::I2p(ref hash) => serialize_address(stream, NETWORK_I2P, &**hash),
+			NetAddressV2::Cjdns(ref ip) => serialize_address(stream, NETWORK_CJDNS, &ip.octets()),
+			NetAddressV2::Unknown(network, ref address) => serialize_address(stream, network, address),
+		}
+	}
+}
+
+fn serialize_address(stream: &mut Stream, network: u8, address: &[u8]) {
+	stream
+		.append(&network)
+		.append(&CompactInteger::from(address.len()))
+		.append_slice(address);
+}
+
+impl Deserializable for NetAddressV2 {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let network: u8 = try!(reader.read());
+		let len: usize = try!(reader.read::<CompactInteger>()).into();
+		if len > MAX_ADDRESS_V2_LEN {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut address = Bytes::new_with_len(len);
+		try!(reader.read_slice(&mut address));
+
+		// addresses of the known networks must have exact length
+		match (network, len) {
+			(NETWORK_IPV4, 4) => Ok(NetAddressV2::IpV4(net::Ipv4Addr::new(address[0], address[1], address[2], address[3]))),
+			(NETWORK_IPV6, 16) => {
+				let mut octets = [0u8; 16];
+				octets.copy_from_slice(&address);
+				Ok(NetAddressV2::IpV6(octets.into()))
+			},
+			(NETWORK_TORV3, 32) => Ok(NetAddressV2::TorV3(H256::from(&*address))),
+			(NETWORK_I2P, 32) => Ok(NetAddressV2::I2p(H256::from(&*address))),
+			(NETWORK_CJDNS, 16) if address[0] == 0xfc => {
+				let mut octets = [0u8; 16];
+				octets.copy_from_slice(&address);
+				Ok(NetAddressV2::Cjdns(octets.into()))
+			},
+			(NETWORK_IPV4, _) | (NETWORK_IPV6, _) | (NETWORK_TORV3, _) | (NETWORK_I2P, _) | (NETWORK_CJDNS, _) => Err(ReaderError::MalformedData),
+			(network, _) => Ok(NetAddressV2::Unknown(network, address)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net;
+	use ser::{serialize, deserialize, Error as ReaderError};
+	use super::NetAddressV2;
+
+	#[test]
+	fn test_net_address_v2_serialize() {
+		let ip: net::IpAddr = "10.0.0.1".parse().unwrap();
+		assert_eq!(serialize(&NetAddressV2::from(ip)), "01040a000001".into());
+		let ip: net::IpAddr = "::ffff:a00:1".parse().unwrap();
+		assert_eq!(serialize(&NetAddressV2::from(ip)), "01040a000001".into());
+		let ip: net::IpAddr = "2001:db8::1".parse().unwrap();
+		assert_eq!(serialize(&NetAddressV2::from(ip)), "021020010db8000000000000000000000001".into());
+		let tor = NetAddressV2::TorV3("79bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f".into());
+		assert_eq!(serialize(&tor), "042079bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f".into());
+		let i2p = NetAddressV2::I2p("79bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f".into());
+		assert_eq!(serialize(&i2p), "052079bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f".into());
+		let cjdns = NetAddressV2::Cjdns("fc00::1".parse().unwrap());
+		assert_eq!(serialize(&cjdns), "0610fc000000000000000000000000000001".into());
+	}
+
+	#[test]
+	fn test_net_address_v2_deserialize() {
+		let address: NetAddressV2 = deserialize(&[0x01u8, 0x04, 0x0a, 0x00, 0x00, 0x01] as &[u8]).unwrap();
+		assert_eq!(address, NetAddressV2::IpV4("10.0.0.1".parse().unwrap()));
+		let address: NetAddressV2 = deserialize(&[0x07u8, 0x02, 0x01, 0x02] as &[u8]).unwrap();
+		assert_eq!(address, NetAddressV2::Unknown(7, vec![1, 2].into()));
+		let mut cjdns = vec![0x06u8, 0x10, 0xfc];
+		cjdns.extend_from_slice(&[0u8; 14]);
+		cjdns.push(0x01);
+		assert_eq!(deserialize::<_, NetAddressV2>(&cjdns as &[u8]).unwrap(), NetAddressV2::Cjdns("fc00::1".parse().unwrap()));
+		cjdns[2] = 0xfd;
+		assert_eq!(deserialize::<_, NetAddressV2>(&cjdns as &[u8]), Err(ReaderError::MalformedData));
+		assert_eq!(deserialize::<_, NetAddressV2>(&[0x01u8, 0x03, 0x0a, 0x00, 0x00] as &[u8]), Err(ReaderError::MalformedData));
+		assert_eq!(deserialize::<_, NetAddressV2>(&[0x05u8, 0x02, 0x01, 0x02] as &[u8]), Err(ReaderError::MalformedData));
+		assert_eq!(deserialize::<_, NetAddressV2>(&[0x07u8, 0xfd, 0x01, 0x02] as &[u8]), Err(ReaderError::MalformedData));
+	}
+}
@@ -1,10 +1,96 @@
+use std::io;
+use ser::{Serializable, Stream, Deserializable, Reader, Error as ReaderError, CompactInteger};
 use chain::{BlockHeader, ShortTransactionID};
 use common::PrefilledTransaction;
+use super::block_transactions_request::MAX_TRANSACTION_INDEX;
 
-#[derive(Debug, PartialEq, Serializable, Deserializable)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct BlockHeaderAndIDs {
 	pub header: BlockHeader,
 	pub nonce: u64,
 	pub short_ids: Vec<ShortTransactionID>,
+	/// Prefilled transactions with absolute indexes. Must be sorted by index in ascending order.
 	pub prefilled_transactions: Vec<PrefilledTransaction>,
 }
+
+impl Serializable for BlockHeaderAndIDs {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.header)
+			.append(&self.nonce)
+			.append_list(&self.short_ids)
+			.append(&CompactInteger::from(self.prefilled_transactions.len()));
+
+		// indexes are differentially encoded
+		let mut next_index = 0;
+		for prefilled_transaction in &self.prefilled_transactions {
+			stream
+				.append(&CompactInteger::from(prefilled_transaction.index - next_index))
+				.append(&prefilled_transaction.transaction);
+			next_index = prefilled_transaction.index + 1;
+		}
+	}
+}
+
+impl Deserializable for BlockHeaderAndIDs {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let header = try!(reader.read());
+		let nonce = try!(reader.read());
+		let short_ids = try!(reader.read_list());
+		let len = try!(reader.read_len());
+		if len as u64 > MAX_TRANSACTION_INDEX + 1 {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut prefilled_transactions = Vec::with_capacity(len);
+		let mut next_index = 0u64;
+		for _ in 0..len {
+			let difference: CompactInteger = try!(reader.read());
+			let index = next_index + u64::from(difference);
+			if index > MAX_TRANSACTION_INDEX {
+				return Err(ReaderError::MalformedData);
+			}
+			prefilled_transactions.push(PrefilledTransaction {
+				index: index as usize,
+				transaction: try!(reader.read()),
+			});
+			next_index = index + 1;
+		}
+
+		Ok(BlockHeaderAndIDs {
+			header: header,
+			nonce: nonce,
+			short_ids: short_ids,
+			prefilled_transactions: prefilled_transactions,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ser::{serialize, deserialize};
+	use chain::Transaction;
+	use common::PrefilledTransaction;
+	use super::BlockHeaderAndIDs;
+
+	#[test]
+	fn test_block_header_and_ids_differential_indexes() {
+		let transaction: Transaction = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0104ffffffff0100f2052a0100000043410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac00000000".into();
+		let block = BlockHeaderAndIDs {
+			header: "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e36299".into(),
+			nonce: 5,
+			short_ids: vec!["010203040506".into()],
+			prefilled_transactions: vec![
+				PrefilledTransaction { index: 0, transaction: transaction.clone() },
+				PrefilledTransaction { index: 2, transaction: transaction.clone() },
+			],
+		};
+
+		let serialized = serialize(&block);
+		// second prefilled transaction index is encoded as difference with the first one, minus one
+		let transaction_size = serialize(&transaction).len();
+		assert_eq!(serialized[80 + 8 + 1 + 6 + 1 + 1 + transaction_size], 1);
+		assert_eq!(deserialize::<_, BlockHeaderAndIDs>(serialized.as_ref()).unwrap(), block);
+	}
+}
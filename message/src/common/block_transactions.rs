@@ -2,6 +2,7 @@ use hash::H256;
 use chain::Transaction;
 
 #[derive(Debug, PartialEq, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct BlockTransactions {
 	pub blockhash: H256,
 	pub transactions: Vec<Transaction>,
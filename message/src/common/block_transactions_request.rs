@@ -1,39 +1,81 @@
 use std::io;
 use hash::H256;
-use ser::{
-	Serializable, Stream, CompactInteger,
-	Deserializable, Reader, Error as ReaderError,
-};
+use ser::{Serializable, Stream, Deserializable, Reader, Error as ReaderError, CompactInteger};
+
+/// Maximal index of the transaction in block (BIP152)
+pub const MAX_TRANSACTION_INDEX: u64 = 0xffff;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct BlockTransactionsRequest {
 	pub blockhash: H256,
+	/// Absolute indexes of requested transactions. Must be sorted in ascending order.
 	pub indexes: Vec<usize>,
 }
 
 impl Serializable for BlockTransactionsRequest {
 	fn serialize(&self, stream: &mut Stream) {
-		let indexes: Vec<CompactInteger> = self.indexes
-			.iter()
-			.map(|x| (*x).into())
-			.collect();
-
 		stream
 			.append(&self.blockhash)
-			.append_list(&indexes);
+			.append(&CompactInteger::from(self.indexes.len()));
+
+		// indexes are differentially encoded
+		let mut next_index = 0;
+		for index in &self.indexes {
+			stream.append(&CompactInteger::from(index - next_index));
+			next_index = index + 1;
+		}
 	}
 }
 
 impl Deserializable for BlockTransactionsRequest {
 	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
 		let blockhash = try!(reader.read());
-		let indexes: Vec<CompactInteger> = try!(reader.read_list());
+		let len = try!(reader.read_len());
+		if len as u64 > MAX_TRANSACTION_INDEX + 1 {
+			return Err(ReaderError::MalformedData);
+		}
 
-		let request = BlockTransactionsRequest {
+		let mut indexes = Vec::with_capacity(len);
+		let mut next_index = 0u64;
+		for _ in 0..len {
+			let difference: CompactInteger = try!(reader.read());
+			let index = next_index + u64::from(difference);
+			if index > MAX_TRANSACTION_INDEX {
+				return Err(ReaderError::MalformedData);
+			}
+			indexes.push(index as usize);
+			next_index = index + 1;
+		}
+
+		Ok(BlockTransactionsRequest {
 			blockhash: blockhash,
-			indexes: indexes.into_iter().map(Into::into).collect(),
+			indexes: indexes,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use ser::{serialize, deserialize};
+	use super::BlockTransactionsRequest;
+
+	#[test]
+	fn test_block_transactions_request_differential_indexes() {
+		let request = BlockTransactionsRequest {
+			blockhash: 1u8.into(),
+			indexes: vec![1, 2, 5, 300],
 		};
+		let expected: Bytes = "01000000000000000000000000000000000000000000000000000000000000000401000200fd2601".into();
+
+		assert_eq!(serialize(&request), expected);
+		assert_eq!(deserialize::<_, BlockTransactionsRequest>(expected.as_ref()).unwrap(), request);
+	}
 
-		Ok(request)
+	#[test]
+	fn test_block_transactions_request_index_overflow() {
+		let raw: Bytes = "010000000000000000000000000000000000000000000000000000000000000002fdffff00".into();
+		assert!(deserialize::<_, BlockTransactionsRequest>(raw.as_ref()).is_err());
 	}
 }
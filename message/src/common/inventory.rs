@@ -3,6 +3,7 @@ use hash::H256;
 use ser::{Serializable, Stream, Deserializable, Reader, Error as ReaderError};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 #[repr(u32)]
 pub enum InventoryType {
 	Error = 0,
@@ -51,6 +52,7 @@ impl Deserializable for InventoryType {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct InventoryVector {
 	pub inv_type: InventoryType,
 	pub hash: H256,
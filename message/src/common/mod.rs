@@ -1,4 +1,5 @@
 mod address;
+mod address_v2;
 mod block_header_and_ids;
 mod block_transactions;
 mod block_transactions_request;
@@ -10,6 +11,7 @@ mod prefilled_transaction;
 mod service;
 
 pub use self::address::NetAddress;
+pub use self::address_v2::{NetAddressV2, MAX_ADDRESS_V2_LEN};
 pub use self::block_header_and_ids::BlockHeaderAndIDs;
 pub use self::block_transactions::BlockTransactions;
 pub use self::block_transactions_request::BlockTransactionsRequest;
@@ -1,4 +1,5 @@
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Services(u64);
 
 impl From<Services> for u64 {
@@ -68,6 +69,24 @@ impl Services {
 		self
 	}
 
+	pub fn compact_filters(&self) -> bool {
+		self.bit_at(6)
+	}
+
+	pub fn with_compact_filters(mut self, v: bool) -> Self {
+		self.set_bit(6, v);
+		self
+	}
+
+	pub fn network_limited(&self) -> bool {
+		self.bit_at(10)
+	}
+
+	pub fn with_network_limited(mut self, v: bool) -> Self {
+		self.set_bit(10, v);
+		self
+	}
+
 	pub fn includes(&self, other: &Self) -> bool {
 		self.0 & other.0 == other.0
 	}
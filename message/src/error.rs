@@ -15,6 +15,8 @@ pub enum Error {
 	InvalidChecksum,
 	/// Invalid version.
 	InvalidVersion,
+	/// Payload is larger than any valid message.
+	PayloadTooLarge,
 }
 
 impl From<ReaderError> for Error {
@@ -37,6 +39,7 @@ impl error::Error for Error {
 			Error::InvalidMagic => "Invalid Network Magic",
 			Error::InvalidChecksum => "Invalid message chacksum",
 			Error::InvalidVersion => "Unsupported protocol version",
+			Error::PayloadTooLarge => "Message payload is too large",
 		}
 	}
 }
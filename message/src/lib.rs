@@ -5,6 +5,11 @@ extern crate primitives;
 extern crate serialization as ser;
 #[macro_use]
 extern crate serialization_derive;
+#[cfg(feature = "with-serde")]
+extern crate serde;
+#[cfg(feature = "with-serde")]
+#[macro_use]
+extern crate serde_derive;
 extern crate network;
 
 pub mod common;
@@ -32,10 +32,11 @@ impl MessageHeader {
 
 		let mut reader = Reader::new(data);
 		let magic: u32 = try!(reader.read());
-		let magic = Magic::from(magic);
-		if expected != magic {
+		// compare raw values, since custom networks (e.g. signet) are not recognized by `Magic::from`
+		if u32::from(expected) != magic {
 			return Err(Error::InvalidMagic);
 		}
+		let magic = expected;
 
 		let header = MessageHeader {
 			magic: magic,
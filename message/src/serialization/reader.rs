@@ -19,7 +19,7 @@ pub struct PayloadReader<T> {
 impl<'a> PayloadReader<&'a [u8]> {
 	pub fn new(buffer: &'a [u8], version: u32) -> Self {
 		PayloadReader {
-			reader: Reader::new(buffer),
+			reader: Reader::new_strict(buffer),
 			version: version,
 		}
 	}
@@ -1,12 +1,10 @@
 use std::io;
-use ser::{
-	Serializable, Stream,
-	Deserializable, Reader, Error as ReaderError,
-};
+use ser::{Serializable, Stream, Reader};
 use common::NetAddress;
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub enum Addr {
 	V0(V0),
 	V31402(V31402),
@@ -55,73 +53,27 @@ impl Payload for Addr {
 	}
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct AddressEntry {
 	pub timestamp: u32,
 	pub address: NetAddress,
 }
 
-impl Serializable for AddressEntry {
-	fn serialize(&self, stream: &mut Stream) {
-		stream
-			.append(&self.timestamp)
-			.append(&self.address);
-	}
-}
-
-impl Deserializable for AddressEntry {
-	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
-		let entry = AddressEntry {
-			timestamp: try!(reader.read()),
-			address: try!(reader.read()),
-		};
-
-		Ok(entry)
-	}
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct V31402 {
+	#[serialize(max = "1000")]
 	pub addresses: Vec<AddressEntry>,
 }
 
-impl Serializable for V31402 {
-	fn serialize(&self, stream: &mut Stream) {
-		stream.append_list(&self.addresses);
-	}
-}
-
-impl Deserializable for V31402 {
-	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
-		let result = V31402 {
-			addresses: try!(reader.read_list_max(1000)),
-		};
-
-		Ok(result)
-	}
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct V0 {
+	#[serialize(max = "1000")]
 	pub addresses: Vec<NetAddress>,
 }
 
-impl Serializable for V0 {
-	fn serialize(&self, stream: &mut Stream) {
-		stream.append_list(&self.addresses);
-	}
-}
-
-impl Deserializable for V0 {
-	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
-		let result = V0 {
-			addresses: try!(reader.read_list_max(1000)),
-		};
-
-		Ok(result)
-	}
-}
-
 struct V31402AsV0<'a> {
 	v: &'a V31402,
 }
@@ -0,0 +1,101 @@
+use std::io;
+use ser::{Serializable, Deserializable, Stream, Reader, Error as ReaderError, CompactInteger};
+use common::{NetAddressV2, Port, Services};
+use {Payload, MessageResult};
+
+/// Addresses of the nodes in any network, including ones, which can't be sent in `addr` message (BIP155).
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct AddrV2 {
+	#[serialize(max = "1000")]
+	pub addresses: Vec<AddressEntryV2>,
+}
+
+impl AddrV2 {
+	pub fn new(addresses: Vec<AddressEntryV2>) -> Self {
+		AddrV2 {
+			addresses: addresses,
+		}
+	}
+}
+
+impl Payload for AddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"addrv2"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let addrv2 = try!(reader.read());
+		Ok(addrv2)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(self);
+		Ok(())
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct AddressEntryV2 {
+	pub timestamp: u32,
+	pub services: Services,
+	pub address: NetAddressV2,
+	pub port: Port,
+}
+
+impl Serializable for AddressEntryV2 {
+	fn serialize(&self, stream: &mut Stream) {
+		// services are compact-size encoded in addrv2
+		stream
+			.append(&self.timestamp)
+			.append(&CompactInteger::from(u64::from(self.services)))
+			.append(&self.address)
+			.append(&self.port);
+	}
+}
+
+impl Deserializable for AddressEntryV2 {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(AddressEntryV2 {
+			timestamp: try!(reader.read()),
+			services: u64::from(try!(reader.read::<CompactInteger>())).into(),
+			address: try!(reader.read()),
+			port: try!(reader.read()),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use ser::{serialize, deserialize};
+	use common::NetAddressV2;
+	use super::{AddrV2, AddressEntryV2};
+
+	#[test]
+	fn test_addrv2_serialize() {
+		let expected: Bytes = "02e215104d0901040a000001208de215104d0104200102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20208d".into();
+		let addrv2 = AddrV2::new(vec![
+			AddressEntryV2 {
+				timestamp: 0x4d1015e2,
+				services: 9u64.into(),
+				address: NetAddressV2::IpV4("10.0.0.1".parse().unwrap()),
+				port: 8333.into(),
+			},
+			AddressEntryV2 {
+				timestamp: 0x4d1015e2,
+				services: 1u64.into(),
+				address: NetAddressV2::TorV3("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20".into()),
+				port: 8333.into(),
+			},
+		]);
+
+		assert_eq!(serialize(&addrv2), expected);
+		assert_eq!(deserialize::<_, AddrV2>(expected.as_ref()).unwrap(), addrv2);
+	}
+}
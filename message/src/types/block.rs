@@ -4,6 +4,7 @@ use chain::Block as ChainBlock;
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Block {
 	pub block: ChainBlock,
 }
@@ -4,6 +4,7 @@ use common::BlockTransactions;
 use {MessageResult, Payload};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct BlockTxn {
 	pub request: BlockTransactions,
 }
@@ -0,0 +1,44 @@
+use std::io;
+use hash::H256;
+use ser::{Stream, Reader};
+use types::GETCFHEADERS_MAX_RESPONSE_HASHES;
+use {Payload, MessageResult};
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct CFHeaders {
+	pub filter_type: u8,
+	pub stop_hash: H256,
+	pub previous_filter_header: H256,
+	pub filter_hashes: Vec<H256>,
+}
+
+impl Payload for CFHeaders {
+	fn version() -> u32 {
+		70013
+	}
+
+	fn command() -> &'static str {
+		"cfheaders"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let cfheaders = CFHeaders {
+			filter_type: try!(reader.read()),
+			stop_hash: try!(reader.read()),
+			previous_filter_header: try!(reader.read()),
+			filter_hashes: try!(reader.read_list_max(GETCFHEADERS_MAX_RESPONSE_HASHES as usize)),
+		};
+
+		Ok(cfheaders)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.filter_type)
+			.append(&self.stop_hash)
+			.append(&self.previous_filter_header)
+			.append_list(&self.filter_hashes);
+		Ok(())
+	}
+}
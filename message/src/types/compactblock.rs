@@ -4,6 +4,7 @@ use common::BlockHeaderAndIDs;
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct CompactBlock {
 	pub header: BlockHeaderAndIDs,
 }
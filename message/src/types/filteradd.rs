@@ -6,6 +6,7 @@ use {Payload, MessageResult};
 pub const FILTERADD_MAX_DATA_LEN: usize = 520;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct FilterAdd {
 	// TODO: check how this should be serialized
 	pub data: Bytes,
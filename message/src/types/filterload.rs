@@ -7,6 +7,7 @@ pub const FILTERLOAD_MAX_FILTER_LEN: usize = 36_000;
 pub const FILTERLOAD_MAX_HASH_FUNCS: usize = 50;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 #[repr(u8)]
 /// Controls how the filter is updated after match is found.
 pub enum FilterFlags {
@@ -20,6 +21,7 @@ pub enum FilterFlags {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct FilterLoad {
 	// TODO: check how this should be serialized
 	pub filter: Bytes,
@@ -3,6 +3,7 @@ use ser::{Stream, Reader};
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct GetAddr;
 
 impl Payload for GetAddr {
@@ -6,6 +6,7 @@ use {Payload, MessageResult};
 pub const GETBLOCKS_MAX_RESPONSE_HASHES: usize = 500;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct GetBlocks {
 	pub version: u32,
 	pub block_locator_hashes: Vec<H256>,
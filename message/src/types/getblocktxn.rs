@@ -4,6 +4,7 @@ use common::BlockTransactionsRequest;
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct GetBlockTxn {
 	pub request: BlockTransactionsRequest,
 }
@@ -6,6 +6,7 @@ use {Payload, MessageResult};
 pub const GETDATA_MAX_INVENTORY_LEN: usize = 50_000;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct GetData {
 	pub inventory: Vec<InventoryVector>,
 }
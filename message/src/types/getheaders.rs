@@ -6,6 +6,7 @@ use {Payload, MessageResult};
 pub const GETHEADERS_MAX_RESPONSE_HEADERS: usize = 2_000;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct GetHeaders {
 	pub version: u32,
 	pub block_locator_hashes: Vec<H256>,
@@ -6,6 +6,7 @@ use {Payload, MessageResult};
 pub const HEADERS_MAX_HEADERS_LEN: usize = 2000;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Headers {
 	pub headers: Vec<BlockHeader>,
 }
@@ -2,10 +2,11 @@ use std::io;
 use hash::H256;
 use bytes::Bytes;
 use ser::{Stream, Reader};
-use chain::BlockHeader;
+use chain::{BlockHeader, PartialMerkleTree};
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct MerkleBlock {
 	pub block_header: BlockHeader,
 	pub total_transactions: u32,
@@ -13,6 +14,13 @@ pub struct MerkleBlock {
 	pub flags: Bytes,
 }
 
+impl MerkleBlock {
+	/// Partial merkle tree, encoded in this message.
+	pub fn partial_merkle_tree(&self) -> PartialMerkleTree {
+		PartialMerkleTree::from_flags_bytes(self.total_transactions as usize, self.hashes.clone(), &self.flags)
+	}
+}
+
 impl Payload for MerkleBlock {
 	fn version() -> u32 {
 		70014
@@ -4,6 +4,7 @@ use common::InventoryVector;
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct NotFound {
 	pub inventory: Vec<InventoryVector>,
 }
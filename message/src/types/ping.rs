@@ -3,6 +3,7 @@ use ser::{Stream, Reader};
 use {MessageResult, Payload};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Ping {
 	pub nonce: u64,
 }
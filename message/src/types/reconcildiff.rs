@@ -0,0 +1,39 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Finalizes transactions reconciliation round (BIP330).
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct ReconcilDiff {
+	/// True if sets difference has been decoded
+	pub success: bool,
+	/// Short ids of transactions, missing from the initiator reconciliation set
+	pub ask_shortids: Vec<u32>,
+}
+
+impl Payload for ReconcilDiff {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"reconcildiff"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let reconcildiff = ReconcilDiff {
+			success: try!(reader.read()),
+			ask_shortids: try!(reader.read_list()),
+		};
+
+		Ok(reconcildiff)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.success)
+			.append_list(&self.ask_shortids);
+		Ok(())
+	}
+}
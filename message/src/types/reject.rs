@@ -3,6 +3,7 @@ use ser::{Serializable, Stream, Deserializable, Reader, Error as ReaderError};
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 #[repr(u8)]
 pub enum RejectCode {
 	Malformed = 0x01,
@@ -53,6 +54,7 @@ impl Deserializable for RejectCode {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Reject {
 	pub message: String,
 	pub code: RejectCode,
@@ -0,0 +1,39 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Initiates transactions reconciliation round (BIP330).
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct ReqRecon {
+	/// Size of the initiator reconciliation set
+	pub set_size: u16,
+	/// Coefficient, used to estimate sets difference. Encoded as q * 2^15
+	pub q: u16,
+}
+
+impl Payload for ReqRecon {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"reqrecon"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let reqrecon = ReqRecon {
+			set_size: try!(reader.read()),
+			q: try!(reader.read()),
+		};
+
+		Ok(reqrecon)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.set_size)
+			.append(&self.q);
+		Ok(())
+	}
+}
@@ -0,0 +1,26 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Signals support of `addrv2` message (BIP155). Is sent before `verack`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct SendAddrV2;
+
+impl Payload for SendAddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sendaddrv2"
+	}
+
+	fn deserialize_payload<T>(_reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		Ok(SendAddrV2)
+	}
+
+	fn serialize_payload(&self, _stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		Ok(())
+	}
+}
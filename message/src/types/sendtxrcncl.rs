@@ -0,0 +1,39 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Signals support of transactions reconciliation (BIP330). Reconciliation is only used when both peers have sent it.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct SendTxRcncl {
+	/// Version of the reconciliation protocol
+	pub version: u32,
+	/// Salt, used to compute short ids of transactions
+	pub salt: u64,
+}
+
+impl Payload for SendTxRcncl {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sendtxrcncl"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let sendtxrcncl = SendTxRcncl {
+			version: try!(reader.read()),
+			salt: try!(reader.read()),
+		};
+
+		Ok(sendtxrcncl)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.version)
+			.append(&self.salt);
+		Ok(())
+	}
+}
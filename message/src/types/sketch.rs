@@ -0,0 +1,35 @@
+use std::io;
+use bytes::Bytes;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Sketch of the responder reconciliation set (BIP330).
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct Sketch {
+	/// Serialized PinSketch of transactions short ids
+	pub skdata: Bytes,
+}
+
+impl Payload for Sketch {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sketch"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let sketch = Sketch {
+			skdata: try!(reader.read()),
+		};
+
+		Ok(sketch)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.skdata);
+		Ok(())
+	}
+}
@@ -4,6 +4,7 @@ use chain::Transaction;
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct Tx {
 	pub transaction: Transaction,
 }
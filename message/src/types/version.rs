@@ -9,6 +9,7 @@ use {Payload, MessageResult};
 use serialization::deserialize_payload;
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub enum Version {
 	V0(V0),
 	V106(V0, V106),
@@ -85,6 +86,14 @@ impl Version {
 		}
 	}
 
+	pub fn timestamp(&self) -> i64 {
+		match *self {
+			Version::V0(ref s) |
+			Version::V106(ref s, _) |
+			Version::V70001(ref s, _, _) => s.timestamp,
+		}
+	}
+
 	pub fn services(&self) -> Services {
 		match *self {
 			Version::V0(ref s) |
@@ -101,6 +110,14 @@ impl Version {
 		}
 	}
 
+	pub fn start_height(&self) -> Option<i32> {
+		match *self {
+			Version::V0(_) => None,
+			Version::V106(_, ref v) |
+			Version::V70001(_, ref v, _) => Some(v.start_height),
+		}
+	}
+
 	pub fn user_agent(&self) -> Option<String> {
 		match *self {
 			Version::V0(_) => None,
@@ -111,6 +128,7 @@ impl Version {
 }
 
 #[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct V0 {
 	pub version: u32,
 	pub services: Services,
@@ -119,6 +137,7 @@ pub struct V0 {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct V106 {
 	pub from: NetAddress,
 	pub nonce: u64,
@@ -127,6 +146,7 @@ pub struct V106 {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
 pub struct V70001 {
 	pub relay: bool,
 }
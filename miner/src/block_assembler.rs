@@ -1,14 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, HashMap};
+use primitives::bytes::Bytes;
 use primitives::hash::H256;
 use primitives::compact::Compact;
-use chain::{OutPoint, TransactionOutput, IndexedTransaction};
+use chain::{OutPoint, TransactionOutput, IndexedTransaction, merkle_root};
+use crypto::dhash256;
 use db::{SharedStore, TransactionOutputProvider};
-use network::ConsensusParams;
+use network::{ConsensusParams, ConsensusFork, TransactionOrdering};
+use ser::Stream;
 use memory_pool::{MemoryPool, OrderingStrategy, Entry};
-use verification::{work_required, block_reward_satoshi, transaction_sigops};
+use verification::{work_required, block_reward_satoshi, transaction_sigops, transaction_sigops_cost,
+	median_timestamp_inclusive, Deployments, ThresholdState};
 
 const BLOCK_VERSION: u32 = 0x20000000;
 const BLOCK_HEADER_SIZE: u32 = 4 + 32 + 32 + 4 + 4 + 4;
+/// Witness commitment output script prefix: OP_RETURN, push 36 bytes and commitment header
+const WITNESS_COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+/// Witness reserved value, which is used to build default witness commitment
+pub const WITNESS_RESERVED_VALUE: [u8; 32] = [0u8; 32];
 
 /// Block template as described in [BIP0022](https://github.com/bitcoin/bips/blob/master/bip-0022.mediawiki#block-template-request)
 pub struct BlockTemplate {
@@ -18,18 +26,42 @@ pub struct BlockTemplate {
 	pub previous_header_hash: H256,
 	/// The current time as seen by the server
 	pub time: u32,
+	/// The minimal time of the block (median time past + 1)
+	pub min_time: u32,
 	/// The compressed difficulty
 	pub bits: Compact,
 	/// Block height
 	pub height: u32,
 	/// Block transactions (excluding coinbase)
-	pub transactions: Vec<IndexedTransaction>,
+	pub transactions: Vec<BlockTemplateTransaction>,
 	/// Total funds available for the coinbase (in Satoshis)
 	pub coinbase_value: u64,
 	/// Number of bytes allowed in the block
 	pub size_limit: u32,
-	/// Number of sigops allowed in the block
+	/// Number of sigops allowed in the block (sigops cost, when SegWit is active)
 	pub sigop_limit: u32,
+	/// Block weight allowed in the block, when SegWit is active
+	pub weight_limit: Option<u32>,
+	/// Names of active deployments
+	pub rules: Vec<String>,
+	/// Pending deployments, which are signalled by the block version (name => bit)
+	pub vbavailable: HashMap<String, u32>,
+	/// Witness commitment output script for the coinbase without witness data, when SegWit is active
+	pub default_witness_commitment: Option<Bytes>,
+}
+
+/// Block template transaction with data, which is required to select transactions for the block
+pub struct BlockTemplateTransaction {
+	/// Transaction
+	pub transaction: IndexedTransaction,
+	/// Transaction fee (in Satoshis)
+	pub fee: u64,
+	/// Number of transaction sigops (sigops cost, when SegWit is active)
+	pub sigops: u32,
+	/// Transaction weight
+	pub weight: u32,
+	/// 1-based indexes of block template transactions, which outputs are spent by this transaction
+	pub depends: Vec<usize>,
 }
 
 /// Block size and number of signatures opcodes is limited
@@ -120,7 +152,8 @@ pub struct BlockAssembler {
 	pub max_block_sigops: u32,
 }
 
-/// Iterator iterating over mempool transactions and yielding only those which fit the block
+/// Iterator iterating over mempool transactions and yielding only those which fit the block.
+/// When SegWit is active, block weight and sigops cost are limited instead of block size and sigops.
 struct FittingTransactionsIterator<'a, T> {
 	/// Shared store is used to query previous transaction outputs from database
 	store: &'a TransactionOutputProvider,
@@ -130,6 +163,8 @@ struct FittingTransactionsIterator<'a, T> {
 	block_height: u32,
 	/// New block time
 	block_time: u32,
+	/// Is SegWit active for the new block
+	segwit_active: bool,
 	/// Size policy decides if transactions size fits the block
 	block_size: SizePolicy,
 	/// Sigops policy decides if transactions sigops fits the block
@@ -143,15 +178,17 @@ struct FittingTransactionsIterator<'a, T> {
 }
 
 impl<'a, T> FittingTransactionsIterator<'a, T> where T: Iterator<Item = &'a Entry> {
-	fn new(store: &'a TransactionOutputProvider, iter: T, max_block_size: u32, max_block_sigops: u32, block_height: u32, block_time: u32) -> Self {
+	fn new(store: &'a TransactionOutputProvider, iter: T, max_block_size: u32, max_block_sigops: u32, block_height: u32, block_time: u32, segwit_active: bool) -> Self {
+		let scale = if segwit_active { ConsensusFork::witness_scale_factor() as u32 } else { 1 };
 		FittingTransactionsIterator {
 			store: store,
 			iter: iter,
 			block_height: block_height,
 			block_time: block_time,
+			segwit_active: segwit_active,
 			// reserve some space for header and transations len field
-			block_size: SizePolicy::new(BLOCK_HEADER_SIZE + 4, max_block_size, 1_000, 50),
-			sigops: SizePolicy::new(0, max_block_sigops, 8, 50),
+			block_size: SizePolicy::new((BLOCK_HEADER_SIZE + 4) * scale, max_block_size, 1_000 * scale, 50),
+			sigops: SizePolicy::new(0, max_block_sigops, 8 * scale, 50),
 			previous_entries: Vec::new(),
 			ignored: HashSet::new(),
 			finished: false,
@@ -165,7 +202,7 @@ impl<'a, T> TransactionOutputProvider for FittingTransactionsIterator<'a, T> whe
 			.or_else(|| {
 				self.previous_entries.iter()
 					.find(|e| e.hash == prevout.hash)
-					.and_then(|e| e.transaction.outputs.iter().nth(prevout.index as usize))
+					.and_then(|e| e.transaction.raw.outputs.iter().nth(prevout.index as usize))
 					.cloned()
 			})
 	}
@@ -176,7 +213,8 @@ impl<'a, T> TransactionOutputProvider for FittingTransactionsIterator<'a, T> whe
 }
 
 impl<'a, T> Iterator for FittingTransactionsIterator<'a, T> where T: Iterator<Item = &'a Entry> + Send + Sync {
-	type Item = &'a Entry;
+	/// Entry and its sigops (sigops cost, when SegWit is active)
+	type Item = (&'a Entry, u32);
 
 	fn next(&mut self) -> Option<Self::Item> {
 		while !self.finished {
@@ -188,20 +226,26 @@ impl<'a, T> Iterator for FittingTransactionsIterator<'a, T> where T: Iterator<It
 				}
 			};
 
-			let transaction_size = entry.size as u32;
 			let bip16_active = true;
-			let sigops_count = transaction_sigops(&entry.transaction, self, bip16_active) as u32;
+			let sigops = transaction_sigops(&entry.transaction.raw, self, bip16_active);
+			let (transaction_size, sigops_count) = if self.segwit_active {
+				(entry.transaction.raw.weight() as u32, transaction_sigops_cost(&entry.transaction.raw, self, sigops) as u32)
+			} else {
+				(entry.size as u32, sigops as u32)
+			};
 
 			let size_step = self.block_size.decide(transaction_size);
 			let sigops_step = self.sigops.decide(sigops_count);
 
 			// both next checks could be checked above, but then it will break finishing
 			// check if transaction is still not finalized in this block
-			if !entry.transaction.is_final_in_block(self.block_height, self.block_time) {
+			if !entry.transaction.raw.is_final_in_block(self.block_height, self.block_time) {
+				self.ignored.insert(entry.hash.clone());
 				continue;
 			}
 			// check if any parent transaction has been ignored
-			if !self.ignored.is_empty() && entry.transaction.inputs.iter().any(|input| self.ignored.contains(&input.previous_output.hash)) {
+			if !self.ignored.is_empty() && entry.transaction.raw.inputs.iter().any(|input| self.ignored.contains(&input.previous_output.hash)) {
+				self.ignored.insert(entry.hash.clone());
 				continue;
 			}
 
@@ -209,18 +253,20 @@ impl<'a, T> Iterator for FittingTransactionsIterator<'a, T> where T: Iterator<It
 			match size_step.and(sigops_step) {
 				NextStep::Append => {
 					self.block_size.apply(transaction_size);
-					self.sigops.apply(transaction_size);
+					self.sigops.apply(sigops_count);
 					self.previous_entries.push(entry);
-					return Some(entry);
+					return Some((entry, sigops_count));
 				},
 				NextStep::FinishAndAppend => {
 					self.finished = true;
 					self.block_size.apply(transaction_size);
-					self.sigops.apply(transaction_size);
+					self.sigops.apply(sigops_count);
 					self.previous_entries.push(entry);
-					return Some(entry);
+					return Some((entry, sigops_count));
+				},
+				NextStep::Ignore => {
+					self.ignored.insert(entry.hash.clone());
 				},
-				NextStep::Ignore => (),
 				NextStep::FinishAndIgnore => {
 					self.ignored.insert(entry.hash.clone());
 					self.finished = true;
@@ -233,45 +279,119 @@ impl<'a, T> Iterator for FittingTransactionsIterator<'a, T> where T: Iterator<It
 }
 
 impl BlockAssembler {
-	pub fn create_new_block(&self, store: &SharedStore, mempool: &MemoryPool, time: u32, consensus: &ConsensusParams) -> BlockTemplate {
+	pub fn create_new_block(&self, store: &SharedStore, mempool: &MemoryPool, deployments: &Deployments, time: u32, consensus: &ConsensusParams) -> BlockTemplate {
 		// get best block
 		// take it's hash && height
 		let best_block = store.best_block();
 		let previous_header_hash = best_block.hash;
 		let height = best_block.number + 1;
 		let bits = work_required(previous_header_hash.clone(), time, height, store.as_block_header_provider(), consensus);
-		let version = BLOCK_VERSION;
+
+		let mut version = BLOCK_VERSION;
+		let mut rules = Vec::new();
+		let mut vbavailable = HashMap::new();
+		for deployment in consensus.deployments() {
+			match deployments.threshold_state(&deployment, height, store.as_block_header_provider(), consensus) {
+				ThresholdState::Active => rules.push(deployment.name),
+				ThresholdState::Started | ThresholdState::LockedIn => {
+					version |= 1 << deployment.bit;
+					vbavailable.insert(deployment.name, deployment.bit as u32);
+				},
+				ThresholdState::Defined | ThresholdState::Failed => (),
+			}
+		}
+
+		// when SegWit is active, block weight and sigops cost are limited
+		let segwit_active = deployments.segwit(height, store.as_block_header_provider(), consensus);
+		let (max_block_size, max_block_sigops, weight_limit) = if segwit_active {
+			let max_block_weight = consensus.fork.max_block_weight(height) as u32;
+			let max_block_sigops_cost = consensus.fork.max_block_sigops_cost(height, self.max_block_size as usize) as u32;
+			(max_block_weight, max_block_sigops_cost, Some(max_block_weight))
+		} else {
+			(self.max_block_size, self.max_block_sigops, None)
+		};
 
 		let mut coinbase_value = block_reward_satoshi(height);
 		let mut transactions = Vec::new();
 
-		let mempool_iter = mempool.iter(OrderingStrategy::ByTransactionScore);
-		let tx_iter = FittingTransactionsIterator::new(store.as_transaction_output_provider(), mempool_iter, self.max_block_size, self.max_block_sigops, height, time);
-		for entry in tx_iter {
+		// transactions are selected by their ancestors score, so that child transactions may pay for their parents
+		let mempool_iter = mempool.iter(OrderingStrategy::ByAncestorScore);
+		let tx_iter = FittingTransactionsIterator::new(store.as_transaction_output_provider(), mempool_iter, max_block_size, max_block_sigops, height, time, segwit_active);
+		for (entry, sigops) in tx_iter {
 			// miner_fee is i64, but we can safely cast it to u64
 			// memory pool should restrict miner fee to be positive
 			coinbase_value += entry.miner_fee as u64;
-			let tx = IndexedTransaction::new(entry.hash.clone(), entry.transaction.clone());
-			transactions.push(tx);
+			transactions.push(BlockTemplateTransaction {
+				transaction: entry.transaction.clone(),
+				fee: entry.miner_fee as u64,
+				sigops: sigops,
+				weight: entry.transaction.raw.weight() as u32,
+				depends: Vec::new(),
+			});
+		}
+
+		let median_time_past = median_timestamp_inclusive(previous_header_hash.clone(), store.as_block_header_provider());
+		if consensus.fork.transaction_ordering(median_time_past) == TransactionOrdering::Canonical {
+			transactions.sort_by(|a, b| a.transaction.hash.cmp(&b.transaction.hash));
+		}
+
+		// dependencies are filled after transactions are ordered
+		let indexes: HashMap<H256, usize> = transactions.iter()
+			.enumerate()
+			.map(|(index, tx)| (tx.transaction.hash.clone(), index + 1))
+			.collect();
+		for tx in &mut transactions {
+			let mut depends: Vec<usize> = tx.transaction.raw.inputs.iter()
+				.filter_map(|input| indexes.get(&input.previous_output.hash).cloned())
+				.collect();
+			depends.sort();
+			depends.dedup();
+			tx.depends = depends;
 		}
 
+		let default_witness_commitment = if segwit_active {
+			Some(witness_commitment_script(&transactions))
+		} else {
+			None
+		};
+
 		BlockTemplate {
 			version: version,
 			previous_header_hash: previous_header_hash,
 			time: time,
+			min_time: median_time_past + 1,
 			bits: bits,
 			height: height,
 			transactions: transactions,
 			coinbase_value: coinbase_value,
 			size_limit: self.max_block_size,
-			sigop_limit: self.max_block_sigops,
+			sigop_limit: max_block_sigops,
+			weight_limit: weight_limit,
+			rules: rules,
+			vbavailable: vbavailable,
+			default_witness_commitment: default_witness_commitment,
 		}
 	}
 }
 
+/// Builds witness commitment output script, assuming that coinbase witness is the witness reserved value.
+fn witness_commitment_script(transactions: &[BlockTemplateTransaction]) -> Bytes {
+	// witness hash of the coinbase transaction is zero
+	let mut hashes = vec![H256::default()];
+	hashes.extend(transactions.iter().map(|tx| tx.transaction.raw.witness_hash()));
+
+	let mut stream = Stream::new();
+	stream.append(&merkle_root(&hashes));
+	stream.append_slice(&WITNESS_RESERVED_VALUE);
+
+	let mut script = WITNESS_COMMITMENT_HEADER.to_vec();
+	script.extend_from_slice(&*dhash256(&stream.out()));
+	script.into()
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{SizePolicy, NextStep};
+	use super::{SizePolicy, NextStep, witness_commitment_script};
 
 	#[test]
 	fn test_size_policy() {
@@ -317,4 +437,11 @@ mod tests {
 	fn test_fitting_transactions_iterator_locked_transaction() {
 		// TODO
 	}
+
+	#[test]
+	fn test_witness_commitment_script_without_transactions() {
+		// commitment to the witness merkle root of the block with only coinbase transaction
+		assert_eq!(witness_commitment_script(&[]),
+			"6a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9".into());
+	}
 }
@@ -1,65 +1,10 @@
-use byteorder::{WriteBytesExt, LittleEndian};
-use primitives::bytes::Bytes;
 use primitives::hash::H256;
 use primitives::bigint::{U256, Uint};
-use primitives::compact::Compact;
-use chain::{merkle_root, Transaction};
-use crypto::dhash256;
-use ser::Stream;
-use verification::is_valid_proof_of_work_hash;
+use chain::{merkle_root, Transaction, BlockHeader, BlockHeaderHasher, IndexedBlockHeader};
+use network::ConsensusParams;
+use verification::is_valid_proof_of_work;
 use block_assembler::BlockTemplate;
 
-/// Instead of serializing `BlockHeader` from scratch over and over again,
-/// let's keep it serialized in memory and replace needed bytes
-struct BlockHeaderBytes {
-	data: Bytes,
-}
-
-impl BlockHeaderBytes {
-	/// Creates new instance of block header bytes.
-	fn new(version: u32, previous_header_hash: H256, bits: Compact) -> Self {
-		let merkle_root_hash = H256::default();
-		let time = 0u32;
-		let nonce = 0u32;
-
-		let mut stream = Stream::default();
-		stream
-			.append(&version)
-			.append(&previous_header_hash)
-			.append(&merkle_root_hash)
-			.append(&time)
-			.append(&bits)
-			.append(&nonce);
-
-		BlockHeaderBytes {
-			data: stream.out(),
-		}
-	}
-
-	/// Set merkle root hash
-	fn set_merkle_root_hash(&mut self, hash: &H256) {
-		let mut merkle_bytes: &mut [u8] = &mut self.data[4 + 32..4 + 32 + 32];
-		merkle_bytes.copy_from_slice(&**hash);
-	}
-
-	/// Set block header time
-	fn set_time(&mut self, time: u32) {
-		let mut time_bytes: &mut [u8] = &mut self.data[4 + 32 + 32..];
-		time_bytes.write_u32::<LittleEndian>(time).unwrap();
-	}
-
-	/// Set block header nonce
-	fn set_nonce(&mut self, nonce: u32) {
-		let mut nonce_bytes: &mut [u8] = &mut self.data[4 + 32 + 32 + 4 + 4..];
-		nonce_bytes.write_u32::<LittleEndian>(nonce).unwrap();
-	}
-
-	/// Returns block header hash
-	fn hash(&self) -> H256 {
-		dhash256(&self.data)
-	}
-}
-
 /// This trait should be implemented by coinbase transaction.
 pub trait CoinbaseTransactionBuilder {
 	/// Should be used to increase number of hash possibities for miner
@@ -90,13 +35,18 @@ pub struct Solution {
 /// and solution still hasn't been found it returns None.
 /// It's possible to also experiment with time, but I find it pointless
 /// to implement on CPU.
-pub fn find_solution<T>(block: &BlockTemplate, mut coinbase_transaction_builder: T, max_extranonce: U256) -> Option<Solution> where T: CoinbaseTransactionBuilder {
+pub fn find_solution<T>(block: &BlockTemplate, mut coinbase_transaction_builder: T, max_extranonce: U256, consensus: &ConsensusParams) -> Option<Solution> where T: CoinbaseTransactionBuilder {
 	let mut extranonce = U256::default();
 	let mut extranonce_bytes = [0u8; 32];
 
-	let mut header_bytes = BlockHeaderBytes::new(block.version, block.previous_header_hash.clone(), block.bits);
-	// update header with time
-	header_bytes.set_time(block.time);
+	let mut header = BlockHeader {
+		version: block.version,
+		previous_header_hash: block.previous_header_hash.clone(),
+		merkle_root_hash: H256::default(),
+		time: block.time,
+		bits: block.bits,
+		nonce: 0,
+	};
 
 	while extranonce < max_extranonce {
 		extranonce.to_little_endian(&mut extranonce_bytes);
@@ -106,17 +56,17 @@ pub fn find_solution<T>(block: &BlockTemplate, mut coinbase_transaction_builder:
 		// recalculate merkle root hash
 		let coinbase_hash = coinbase_transaction_builder.hash();
 		let mut merkle_tree = vec![&coinbase_hash];
-		merkle_tree.extend(block.transactions.iter().map(|tx| &tx.hash));
-		let merkle_root_hash = merkle_root(&merkle_tree);
+		merkle_tree.extend(block.transactions.iter().map(|tx| &tx.transaction.hash));
+		header.merkle_root_hash = merkle_root(&merkle_tree);
 
-		// update header with new merkle root hash
-		header_bytes.set_merkle_root_hash(&merkle_root_hash);
+		// merkle root has changed => recalculate header midstate
+		let hasher = BlockHeaderHasher::new(&header);
 
 		for nonce in 0..(u32::max_value() as u64 + 1) {
-			// update §
-			header_bytes.set_nonce(nonce as u32);
-			let hash = header_bytes.hash();
-			if is_valid_proof_of_work_hash(block.bits, &hash) {
+			let hash = hasher.hash(nonce as u32);
+			let solution_header = IndexedBlockHeader::new(hash, BlockHeader { nonce: nonce as u32, ..header.clone() });
+			let pow_hash = consensus.proof_of_work.pow_hash(&solution_header);
+			if is_valid_proof_of_work(consensus.pow_limit, block.bits, &pow_hash) {
 				let solution = Solution {
 					nonce: nonce as u32,
 					extranonce: extranonce,
@@ -142,6 +92,7 @@ mod tests {
 	use block_assembler::BlockTemplate;
 	use chain::{Transaction, TransactionInput, TransactionOutput};
 	use keys::AddressHash;
+	use network::{ConsensusParams, ConsensusFork, Magic};
 	use script::Builder;
 	use super::{find_solution, CoinbaseTransactionBuilder};
 
@@ -189,17 +140,23 @@ mod tests {
 			version: 0,
 			previous_header_hash: 0.into(),
 			time: 0,
+			min_time: 0,
 			bits: U256::max_value().into(),
 			height: 0,
 			transactions: Vec::new(),
 			coinbase_value: 10,
 			size_limit: 1000,
-			sigop_limit: 100
+			sigop_limit: 100,
+			weight_limit: None,
+			rules: Vec::new(),
+			vbavailable: Default::default(),
+			default_witness_commitment: None,
 		};
 
 		let hash = Default::default();
 		let coinbase_builder = P2shCoinbaseTransactionBuilder::new(&hash, 10);
-		let solution = find_solution(&block_template, coinbase_builder, U256::max_value());
+		let consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+		let solution = find_solution(&block_template, coinbase_builder, U256::max_value(), &consensus);
 		assert!(solution.is_some());
 	}
 }
@@ -1,19 +1,23 @@
 use chain::Transaction;
-use ser::Serializable;
 use db::TransactionProvider;
 
 pub fn transaction_fee(store: &TransactionProvider, transaction: &Transaction) -> u64 {
-	let inputs_sum = transaction.inputs.iter().map(|input| {
-		let input_transaction = store.transaction(&input.previous_output.hash)
-			.expect("transaction must be verified by caller");
-		input_transaction.outputs[input.previous_output.index as usize].value
-	}).sum::<u64>();
+	checked_transaction_fee(store, transaction).expect("transaction must be verified by caller")
+}
+
+/// Returns fee of the transaction, or None if some of its inputs are unknown.
+pub fn checked_transaction_fee(store: &TransactionProvider, transaction: &Transaction) -> Option<u64> {
+	let mut inputs_sum = 0u64;
+	for input in &transaction.inputs {
+		let input_transaction = store.transaction(&input.previous_output.hash)?;
+		inputs_sum += input_transaction.outputs.get(input.previous_output.index as usize)?.value;
+	}
 	let outputs_sum = transaction.outputs.iter().map(|output| output.value).sum();
-	inputs_sum.saturating_sub(outputs_sum)
+	Some(inputs_sum.saturating_sub(outputs_sum))
 }
 
 pub fn transaction_fee_rate(store: &TransactionProvider, transaction: &Transaction) -> u64 {
-	transaction_fee(store, transaction) / transaction.serialized_size() as u64
+	transaction_fee(store, transaction) / transaction.vsize() as u64
 }
 
 #[cfg(test)]
@@ -47,6 +51,10 @@ mod tests {
 
 		assert_eq!(transaction_fee(db.as_transaction_provider(), &tx0), 0);
 		assert_eq!(transaction_fee(db.as_transaction_provider(), &tx2), 500_000);
+		assert_eq!(checked_transaction_fee(db.as_transaction_provider(), &tx2), Some(500_000));
+		let mut unknown_input = tx2.clone();
+		unknown_input.inputs[0].previous_output.index = 2;
+		assert_eq!(checked_transaction_fee(db.as_transaction_provider(), &unknown_input), None);
 
 		assert_eq!(transaction_fee_rate(db.as_transaction_provider(), &tx0), 0);
 		assert_eq!(transaction_fee_rate(db.as_transaction_provider(), &tx2), 4_901);
@@ -0,0 +1,759 @@
+//! Transaction fee estimation.
+//!
+//! Estimator follows memory pool transactions from the moment they are accepted until they are
+//! included into the canon block, or leave the memory pool otherwise. Transactions are grouped
+//! into exponentially spaced fee rate buckets and exponentially decaying confirmation statistics
+//! are collected for short, medium and long time horizons (like `CBlockPolicyEstimator` of Bitcoin Core).
+//! Estimate for the confirmation target is the fee rate of the lowest bucket range, in which
+//! required share of transactions has been confirmed within the target.
+
+use std::cmp::{min, max};
+use std::collections::HashMap;
+use std::io;
+use primitives::hash::H256;
+use ser::{Serializable, Deserializable, Stream, Reader, Error as ReaderError};
+
+/// Lowest bucket fee rate (in satoshis per 1000 virtual bytes).
+const MIN_BUCKET_FEE_RATE: f64 = 1000.0;
+/// Highest bucket fee rate (in satoshis per 1000 virtual bytes).
+const MAX_BUCKET_FEE_RATE: f64 = 10_000_000.0;
+/// Spacing of the bucket fee rates.
+const FEE_SPACING: f64 = 1.05;
+/// Upper bound of the last bucket.
+const INF_FEE_RATE: f64 = 1e99;
+
+/// Short horizon tracks up to 12 blocks.
+const SHORT_BLOCK_PERIODS: usize = 12;
+const SHORT_SCALE: u32 = 1;
+const SHORT_DECAY: f64 = 0.962;
+/// Medium horizon tracks up to 48 blocks.
+const MEDIUM_BLOCK_PERIODS: usize = 24;
+const MEDIUM_SCALE: u32 = 2;
+const MEDIUM_DECAY: f64 = 0.9952;
+/// Long horizon tracks up to 1008 blocks.
+const LONG_BLOCK_PERIODS: usize = 42;
+const LONG_SCALE: u32 = 24;
+const LONG_DECAY: f64 = 0.99931;
+
+/// Required share of transactions, confirmed within the half of the target.
+const HALF_SUCCESS_PCT: f64 = 0.6;
+/// Required share of transactions, confirmed within the target.
+const SUCCESS_PCT: f64 = 0.85;
+/// Required share of transactions, confirmed within the double target.
+const DOUBLE_SUCCESS_PCT: f64 = 0.95;
+/// Required number of transactions per block in the bucket range.
+const SUFFICIENT_FEE_TXS: f64 = 0.1;
+/// Required number of transactions per block in the bucket range of the short horizon.
+const SUFFICIENT_TXS_SHORT: f64 = 0.5;
+/// Statistics, saved more than this number of blocks ago, are not used to limit confirmation target.
+const OLDEST_ESTIMATE_HISTORY: u32 = 6 * 1008;
+
+/// Max confirmation target, which could be estimated.
+pub const MAX_CONFIRMATION_TARGET: u32 = LONG_BLOCK_PERIODS as u32 * LONG_SCALE;
+
+/// Version of the serialized estimator state.
+const STATE_VERSION: u32 = 1;
+
+/// Time horizon of the fee estimate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeEstimateHorizon {
+	/// Up to 12 blocks, quickly reacts to fee rate changes
+	Short,
+	/// Up to 48 blocks
+	Medium,
+	/// Up to 1008 blocks, slowly reacts to fee rate changes
+	Long,
+}
+
+/// Smart fee estimate
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeEstimate {
+	/// Estimated fee rate (in satoshis per 1000 virtual bytes)
+	pub fee_rate: u64,
+	/// Confirmation target, for which the estimate has been found
+	pub blocks: u32,
+}
+
+/// Statistics of fee rate buckets range
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeEstimateBucket {
+	/// Lowest fee rate of the range (in satoshis per 1000 virtual bytes)
+	pub start: f64,
+	/// Highest fee rate of the range (in satoshis per 1000 virtual bytes)
+	pub end: f64,
+	/// Decayed number of transactions, confirmed within the target
+	pub within_target: f64,
+	/// Decayed number of confirmed transactions
+	pub total_confirmed: f64,
+	/// Number of memory pool transactions, waiting longer than the target
+	pub in_mempool: f64,
+	/// Decayed number of transactions, which have left the memory pool unconfirmed after waiting longer than the target
+	pub left_mempool: f64,
+}
+
+/// Fee estimate of the single time horizon
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawFeeEstimate {
+	/// Estimated fee rate (in satoshis per 1000 virtual bytes), if there's enough data
+	pub fee_rate: Option<f64>,
+	/// Decay of the horizon statistics
+	pub decay: f64,
+	/// Number of blocks in the horizon statistics period
+	pub scale: u32,
+	/// Buckets range, which has met the threshold
+	pub pass: Option<FeeEstimateBucket>,
+	/// Highest buckets range, which has failed the threshold
+	pub fail: Option<FeeEstimateBucket>,
+}
+
+/// Transaction fee estimator
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeEstimator {
+	/// Upper bounds of the fee rate buckets
+	buckets: Vec<f64>,
+	/// Short horizon statistics
+	short: ConfirmStats,
+	/// Medium horizon statistics
+	medium: ConfirmStats,
+	/// Long horizon statistics
+	long: ConfirmStats,
+	/// Tracked memory pool transactions
+	transactions: HashMap<H256, TrackedTransaction>,
+	/// Height of the best processed block
+	best_seen_height: u32,
+	/// Height of the first block with confirmed tracked transactions
+	first_recorded_height: u32,
+	/// First recorded height of the loaded statistics
+	historical_first: u32,
+	/// Best seen height of the loaded statistics
+	historical_best: u32,
+}
+
+/// Tracked memory pool transaction
+#[derive(Debug, Clone, PartialEq)]
+struct TrackedTransaction {
+	/// Best block height at the moment transaction has entered the memory pool
+	height: u32,
+	/// Index of the fee rate bucket
+	bucket: usize,
+	/// Fee rate (in satoshis per 1000 virtual bytes)
+	fee_rate: f64,
+}
+
+/// Confirmation statistics of the single time horizon
+#[derive(Debug, Clone, PartialEq)]
+struct ConfirmStats {
+	/// Number of blocks in the statistics period
+	scale: u32,
+	/// Decay, applied to statistics on every block
+	decay: f64,
+	/// Decayed number of confirmed transactions by bucket
+	confirmed_total: Vec<f64>,
+	/// Decayed sum of fee rates of confirmed transactions by bucket
+	fee_rate_sum: Vec<f64>,
+	/// Decayed number of transactions, confirmed within `period + 1` periods, by period and bucket
+	confirmed: Vec<Vec<f64>>,
+	/// Decayed number of transactions, which have left the memory pool after waiting `period + 1` periods, by period and bucket
+	failed: Vec<Vec<f64>>,
+	/// Number of unconfirmed transactions by entry height (modulo max confirms) and bucket
+	unconfirmed: Vec<Vec<u32>>,
+	/// Number of unconfirmed transactions, waiting longer than max confirms, by bucket
+	old_unconfirmed: Vec<u32>,
+}
+
+impl ConfirmStats {
+	fn new(buckets: usize, periods: usize, scale: u32, decay: f64) -> Self {
+		ConfirmStats {
+			scale: scale,
+			decay: decay,
+			confirmed_total: vec![0f64; buckets],
+			fee_rate_sum: vec![0f64; buckets],
+			confirmed: vec![vec![0f64; buckets]; periods],
+			failed: vec![vec![0f64; buckets]; periods],
+			unconfirmed: vec![vec![0; buckets]; periods * scale as usize],
+			old_unconfirmed: vec![0; buckets],
+		}
+	}
+
+	/// Max number of blocks, tracked by the statistics
+	fn max_confirms(&self) -> u32 {
+		self.scale * self.confirmed.len() as u32
+	}
+
+	/// Moves unconfirmed transactions, which have been waiting for max confirms, to the old ones
+	fn clear_current(&mut self, height: u32) {
+		let index = (height % self.max_confirms()) as usize;
+		for bucket in 0..self.old_unconfirmed.len() {
+			self.old_unconfirmed[bucket] += self.unconfirmed[index][bucket];
+			self.unconfirmed[index][bucket] = 0;
+		}
+	}
+
+	fn update_moving_averages(&mut self) {
+		let decay = self.decay;
+		for value in self.confirmed_total.iter_mut()
+			.chain(self.fee_rate_sum.iter_mut())
+			.chain(self.confirmed.iter_mut().flat_map(|values| values.iter_mut()))
+			.chain(self.failed.iter_mut().flat_map(|values| values.iter_mut())) {
+			*value *= decay;
+		}
+	}
+
+	fn record(&mut self, blocks_to_confirm: u32, bucket: usize, fee_rate: f64) {
+		let periods_to_confirm = ((blocks_to_confirm + self.scale - 1) / self.scale) as usize;
+		for period in periods_to_confirm..self.confirmed.len() + 1 {
+			self.confirmed[period - 1][bucket] += 1.0;
+		}
+		self.confirmed_total[bucket] += 1.0;
+		self.fee_rate_sum[bucket] += fee_rate;
+	}
+
+	fn insert_unconfirmed(&mut self, height: u32, bucket: usize) {
+		let index = (height % self.max_confirms()) as usize;
+		self.unconfirmed[index][bucket] += 1;
+	}
+
+	/// Removes unconfirmed transaction. Transaction, which has failed to confirm within the period, is recorded as failed.
+	fn remove_unconfirmed(&mut self, entry_height: u32, best_height: u32, bucket: usize, failed: bool) {
+		if best_height < entry_height {
+			return;
+		}
+
+		let blocks_ago = best_height - entry_height;
+		if blocks_ago >= self.max_confirms() {
+			self.old_unconfirmed[bucket] = self.old_unconfirmed[bucket].saturating_sub(1);
+		} else {
+			let index = (entry_height % self.max_confirms()) as usize;
+			self.unconfirmed[index][bucket] = self.unconfirmed[index][bucket].saturating_sub(1);
+		}
+
+		if failed && blocks_ago >= self.scale {
+			let periods_ago = (blocks_ago / self.scale) as usize;
+			for period in 0..min(periods_ago, self.failed.len()) {
+				self.failed[period][bucket] += 1.0;
+			}
+		}
+	}
+
+	/// Finds the lowest buckets range, in which at least `success_threshold` share of transactions
+	/// has been confirmed within `target` blocks. Ranges are extended until they contain sufficient
+	/// number of transactions.
+	fn estimate_median(&self, buckets: &[f64], target: u32, sufficient_txs: f64, success_threshold: f64, best_height: u32) -> RawFeeEstimate {
+		let period_target = ((target + self.scale - 1) / self.scale) as usize;
+		let max_confirms = self.max_confirms();
+		let max_bucket = buckets.len() - 1;
+
+		let mut range = RangeStats::default();
+		let (mut near_bucket, mut far_bucket) = (max_bucket, max_bucket);
+		let (mut best_near_bucket, mut best_far_bucket) = (max_bucket, max_bucket);
+		let mut found = false;
+		let mut new_range = true;
+		let mut passing = true;
+		let mut pass = None;
+		let mut fail = None;
+
+		for bucket in (0..buckets.len()).rev() {
+			if new_range {
+				near_bucket = bucket;
+				new_range = false;
+			}
+			far_bucket = bucket;
+
+			range.within_target += self.confirmed[period_target - 1][bucket];
+			range.total_confirmed += self.confirmed_total[bucket];
+			range.left_mempool += self.failed[period_target - 1][bucket];
+			for confirms in target..max_confirms {
+				let index = ((best_height + max_confirms - confirms) % max_confirms) as usize;
+				range.in_mempool += self.unconfirmed[index][bucket] as f64;
+			}
+			range.in_mempool += self.old_unconfirmed[bucket] as f64;
+
+			// wait until there's enough transactions in the range
+			if range.total_confirmed < sufficient_txs / (1.0 - self.decay) {
+				continue;
+			}
+
+			let share = range.within_target / (range.total_confirmed + range.left_mempool + range.in_mempool);
+			if share < success_threshold {
+				if passing {
+					fail = Some(range.bucket(buckets, far_bucket, near_bucket));
+					passing = false;
+				}
+				continue;
+			}
+
+			fail = None;
+			pass = Some(range.bucket(buckets, far_bucket, near_bucket));
+			found = true;
+			passing = true;
+			range = RangeStats::default();
+			best_near_bucket = near_bucket;
+			best_far_bucket = far_bucket;
+			new_range = true;
+		}
+
+		// the lowest buckets have passed, but don't have enough transactions
+		if passing && !new_range {
+			fail = Some(range.bucket(buckets, far_bucket, near_bucket));
+		}
+
+		// fee rate of the median transaction of the passed range
+		let mut fee_rate = None;
+		let range_buckets = best_far_bucket..best_near_bucket + 1;
+		let range_total: f64 = range_buckets.clone().map(|bucket| self.confirmed_total[bucket]).sum();
+		if found && range_total != 0.0 {
+			let mut remaining = range_total / 2.0;
+			for bucket in range_buckets {
+				if self.confirmed_total[bucket] < remaining {
+					remaining -= self.confirmed_total[bucket];
+				} else {
+					fee_rate = Some(self.fee_rate_sum[bucket] / self.confirmed_total[bucket]);
+					break;
+				}
+			}
+		}
+
+		RawFeeEstimate {
+			fee_rate: fee_rate,
+			decay: self.decay,
+			scale: self.scale,
+			pass: if fee_rate.is_some() { pass } else { None },
+			fail: fail,
+		}
+	}
+}
+
+/// Statistics of the buckets range, accumulated by the estimation
+#[derive(Default)]
+struct RangeStats {
+	within_target: f64,
+	total_confirmed: f64,
+	in_mempool: f64,
+	left_mempool: f64,
+}
+
+impl RangeStats {
+	fn bucket(&self, buckets: &[f64], min_bucket: usize, max_bucket: usize) -> FeeEstimateBucket {
+		FeeEstimateBucket {
+			start: if min_bucket == 0 { 0.0 } else { buckets[min_bucket - 1] },
+			end: buckets[max_bucket],
+			within_target: self.within_target,
+			total_confirmed: self.total_confirmed,
+			in_mempool: self.in_mempool,
+			left_mempool: self.left_mempool,
+		}
+	}
+}
+
+impl Default for FeeEstimator {
+	fn default() -> Self {
+		let mut buckets = Vec::new();
+		let mut bound = MIN_BUCKET_FEE_RATE;
+		while bound <= MAX_BUCKET_FEE_RATE {
+			buckets.push(bound);
+			bound *= FEE_SPACING;
+		}
+		buckets.push(INF_FEE_RATE);
+
+		FeeEstimator {
+			short: ConfirmStats::new(buckets.len(), SHORT_BLOCK_PERIODS, SHORT_SCALE, SHORT_DECAY),
+			medium: ConfirmStats::new(buckets.len(), MEDIUM_BLOCK_PERIODS, MEDIUM_SCALE, MEDIUM_DECAY),
+			long: ConfirmStats::new(buckets.len(), LONG_BLOCK_PERIODS, LONG_SCALE, LONG_DECAY),
+			buckets: buckets,
+			transactions: HashMap::new(),
+			best_seen_height: 0,
+			first_recorded_height: 0,
+			historical_first: 0,
+			historical_best: 0,
+		}
+	}
+}
+
+impl FeeEstimator {
+	/// Creates new fee estimator without any statistics
+	pub fn new() -> Self {
+		FeeEstimator::default()
+	}
+
+	/// Starts tracking transaction, which has entered the memory pool, when the best block height was `height`.
+	/// Only transactions, accepted on top of the last processed block, are tracked.
+	pub fn process_transaction(&mut self, hash: H256, height: u32, fee: u64, size: usize) {
+		if height != self.best_seen_height || size == 0 || self.transactions.contains_key(&hash) {
+			return;
+		}
+
+		let fee_rate = fee as f64 * 1000.0 / size as f64;
+		let bucket = self.bucket_index(fee_rate);
+		for stats in self.stats_mut().iter_mut() {
+			stats.insert_unconfirmed(height, bucket);
+		}
+		self.transactions.insert(hash, TrackedTransaction {
+			height: height,
+			bucket: bucket,
+			fee_rate: fee_rate,
+		});
+	}
+
+	/// Stops tracking transaction, which has left the memory pool without being included into the block.
+	/// Transaction is recorded as failed to confirm if `failed` is true (e.g. it has been replaced).
+	/// Returns true if transaction has been tracked.
+	pub fn remove_transaction(&mut self, hash: &H256, failed: bool) -> bool {
+		match self.transactions.remove(hash) {
+			Some(transaction) => {
+				let best_height = self.best_seen_height;
+				for stats in self.stats_mut().iter_mut() {
+					stats.remove_unconfirmed(transaction.height, best_height, transaction.bucket, failed);
+				}
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Records confirmations of the tracked transactions, included into the new best block.
+	/// Blocks at or below the best processed height (reorganizations) are ignored.
+	pub fn process_block(&mut self, height: u32, transactions: &[H256]) {
+		if height <= self.best_seen_height {
+			return;
+		}
+
+		self.best_seen_height = height;
+		for stats in self.stats_mut().iter_mut() {
+			stats.clear_current(height);
+			stats.update_moving_averages();
+		}
+
+		let mut recorded = 0;
+		for hash in transactions {
+			let transaction = match self.transactions.get(hash) {
+				Some(transaction) => transaction.clone(),
+				None => continue,
+			};
+			self.remove_transaction(hash, false);
+
+			let blocks_to_confirm = height - transaction.height;
+			for stats in self.stats_mut().iter_mut() {
+				stats.record(blocks_to_confirm, transaction.bucket, transaction.fee_rate);
+			}
+			recorded += 1;
+		}
+
+		if self.first_recorded_height == 0 && recorded != 0 {
+			self.first_recorded_height = height;
+		}
+	}
+
+	/// Max confirmation target of the time horizon
+	pub fn max_target(&self, horizon: FeeEstimateHorizon) -> u32 {
+		self.stats(horizon).max_confirms()
+	}
+
+	/// Estimates fee rate, required for transaction to be confirmed within `target` blocks, using
+	/// statistics of the single time horizon. At least `threshold` share of transactions of
+	/// the estimated fee rate must have been confirmed within the target.
+	/// Returns None if target is out of the horizon range.
+	pub fn estimate_raw_fee(&self, target: u32, threshold: f64, horizon: FeeEstimateHorizon) -> Option<RawFeeEstimate> {
+		let stats = self.stats(horizon);
+		if target == 0 || target > stats.max_confirms() {
+			return None;
+		}
+
+		let sufficient_txs = match horizon {
+			FeeEstimateHorizon::Short => SUFFICIENT_TXS_SHORT,
+			FeeEstimateHorizon::Medium | FeeEstimateHorizon::Long => SUFFICIENT_FEE_TXS,
+		};
+		Some(stats.estimate_median(&self.buckets, target, sufficient_txs, threshold, self.best_seen_height))
+	}
+
+	/// Estimates fee rate, required for transaction to be confirmed within `target` blocks.
+	/// Conservative estimate is more robust to recent fee rate drops.
+	/// Target is reduced if there's not enough statistics for it.
+	pub fn estimate_smart_fee(&self, target: u32, conservative: bool) -> Option<FeeEstimate> {
+		if target == 0 || target > MAX_CONFIRMATION_TARGET {
+			return None;
+		}
+
+		// single block target is never estimated
+		let target = min(max(target, 2), self.max_usable_target());
+		if target <= 1 {
+			return None;
+		}
+
+		let half_estimate = self.estimate_combined_fee(target / 2, HALF_SUCCESS_PCT, true);
+		let estimate = self.estimate_combined_fee(target, SUCCESS_PCT, true);
+		let double_estimate = self.estimate_combined_fee(2 * target, DOUBLE_SUCCESS_PCT, !conservative);
+		let mut fee_rate = max_fee_rate(max_fee_rate(half_estimate, estimate), double_estimate);
+
+		if conservative || fee_rate.is_none() {
+			fee_rate = max_fee_rate(fee_rate, self.estimate_conservative_fee(2 * target));
+		}
+
+		fee_rate.map(|fee_rate| FeeEstimate {
+			fee_rate: fee_rate.round() as u64,
+			blocks: target,
+		})
+	}
+
+	/// Estimate, using the shortest horizon, which tracks the target. If `check_shorter_horizon`
+	/// is true, max targets of shorter horizons are also checked for lower estimates.
+	fn estimate_combined_fee(&self, target: u32, threshold: f64, check_shorter_horizon: bool) -> Option<f64> {
+		if target == 0 || target > self.long.max_confirms() {
+			return None;
+		}
+
+		let horizon = if target <= self.short.max_confirms() {
+			FeeEstimateHorizon::Short
+		} else if target <= self.medium.max_confirms() {
+			FeeEstimateHorizon::Medium
+		} else {
+			FeeEstimateHorizon::Long
+		};
+		let mut estimate = self.estimate_fee_rate(target, threshold, horizon);
+
+		if check_shorter_horizon {
+			for &shorter in &[FeeEstimateHorizon::Medium, FeeEstimateHorizon::Short] {
+				let shorter_target = self.stats(shorter).max_confirms();
+				if target <= shorter_target {
+					continue;
+				}
+				if let Some(shorter_estimate) = self.estimate_fee_rate(shorter_target, threshold, shorter) {
+					if estimate.map_or(true, |estimate| shorter_estimate < estimate) {
+						estimate = Some(shorter_estimate);
+					}
+				}
+			}
+		}
+
+		estimate
+	}
+
+	/// Estimate, which is reliable for the double target in both medium and long horizons.
+	fn estimate_conservative_fee(&self, double_target: u32) -> Option<f64> {
+		let mut estimate = None;
+		if double_target <= self.short.max_confirms() {
+			estimate = self.estimate_fee_rate(double_target, DOUBLE_SUCCESS_PCT, FeeEstimateHorizon::Medium);
+		}
+		if double_target <= self.medium.max_confirms() {
+			estimate = max_fee_rate(estimate, self.estimate_fee_rate(double_target, DOUBLE_SUCCESS_PCT, FeeEstimateHorizon::Long));
+		}
+		estimate
+	}
+
+	fn estimate_fee_rate(&self, target: u32, threshold: f64, horizon: FeeEstimateHorizon) -> Option<f64> {
+		self.estimate_raw_fee(target, threshold, horizon).and_then(|estimate| estimate.fee_rate)
+	}
+
+	/// Targets above the half of the tracked blocks number are not estimated.
+	fn max_usable_target(&self) -> u32 {
+		min(self.long.max_confirms(), max(self.block_span(), self.historical_block_span()) / 2)
+	}
+
+	/// Number of blocks, tracked since the start.
+	fn block_span(&self) -> u32 {
+		if self.first_recorded_height == 0 {
+			return 0;
+		}
+		self.best_seen_height.saturating_sub(self.first_recorded_height)
+	}
+
+	/// Number of blocks, tracked by the loaded statistics, if they're not too old.
+	fn historical_block_span(&self) -> u32 {
+		if self.historical_first == 0 || self.historical_best < self.historical_first {
+			return 0;
+		}
+		if self.best_seen_height.saturating_sub(self.historical_best) > OLDEST_ESTIMATE_HISTORY {
+			return 0;
+		}
+		self.historical_best - self.historical_first
+	}
+
+	fn bucket_index(&self, fee_rate: f64) -> usize {
+		self.buckets.iter()
+			.position(|bound| *bound >= fee_rate)
+			.unwrap_or(self.buckets.len() - 1)
+	}
+
+	fn stats(&self, horizon: FeeEstimateHorizon) -> &ConfirmStats {
+		match horizon {
+			FeeEstimateHorizon::Short => &self.short,
+			FeeEstimateHorizon::Medium => &self.medium,
+			FeeEstimateHorizon::Long => &self.long,
+		}
+	}
+
+	fn stats_mut(&mut self) -> [&mut ConfirmStats; 3] {
+		[&mut self.short, &mut self.medium, &mut self.long]
+	}
+}
+
+fn max_fee_rate(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(a.max(b)),
+		(a, None) => a,
+		(None, b) => b,
+	}
+}
+
+fn append_values(stream: &mut Stream, values: &[f64]) {
+	let bits: Vec<u64> = values.iter().map(|value| value.to_bits()).collect();
+	stream.append_list::<u64, u64>(&bits);
+}
+
+fn read_values<T>(reader: &mut Reader<T>, len: usize) -> Result<Vec<f64>, ReaderError> where T: io::Read {
+	let bits: Vec<u64> = reader.read_list()?;
+	if bits.len() != len {
+		return Err(ReaderError::MalformedData);
+	}
+	Ok(bits.into_iter().map(f64::from_bits).collect())
+}
+
+impl ConfirmStats {
+	/// Only decaying statistics are serialized, unconfirmed transactions are tracked again after restart.
+	fn serialize(&self, stream: &mut Stream) {
+		append_values(stream, &self.confirmed_total);
+		append_values(stream, &self.fee_rate_sum);
+		for values in self.confirmed.iter().chain(self.failed.iter()) {
+			append_values(stream, values);
+		}
+	}
+
+	fn deserialize<T>(&mut self, reader: &mut Reader<T>) -> Result<(), ReaderError> where T: io::Read {
+		let buckets = self.confirmed_total.len();
+		self.confirmed_total = read_values(reader, buckets)?;
+		self.fee_rate_sum = read_values(reader, buckets)?;
+		for values in self.confirmed.iter_mut().chain(self.failed.iter_mut()) {
+			*values = read_values(reader, buckets)?;
+		}
+		Ok(())
+	}
+}
+
+impl Serializable for FeeEstimator {
+	fn serialize(&self, stream: &mut Stream) {
+		// statistics of the longer period are preferred
+		let (first, best) = if self.block_span() > self.historical_block_span() / 2 {
+			(self.first_recorded_height, self.best_seen_height)
+		} else {
+			(self.historical_first, self.historical_best)
+		};
+
+		stream
+			.append(&STATE_VERSION)
+			.append(&self.best_seen_height)
+			.append(&first)
+			.append(&best);
+		for stats in &[&self.short, &self.medium, &self.long] {
+			stats.serialize(stream);
+		}
+	}
+}
+
+impl Deserializable for FeeEstimator {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		if reader.read::<u32>()? != STATE_VERSION {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut estimator = FeeEstimator::new();
+		estimator.best_seen_height = reader.read()?;
+		estimator.historical_first = reader.read()?;
+		estimator.historical_best = reader.read()?;
+		for stats in estimator.stats_mut().iter_mut() {
+			stats.deserialize(reader)?;
+		}
+		Ok(estimator)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use primitives::hash::H256;
+	use ser::{serialize, deserialize};
+	use super::{FeeEstimator, FeeEstimate, FeeEstimateHorizon};
+
+	/// Every block 10 transactions, paying 10 sat/vbyte, are confirmed in the next block
+	/// and 10 transactions, paying 2 sat/vbyte, are never confirmed.
+	fn estimator_with_blocks(blocks: u32) -> FeeEstimator {
+		let mut estimator = FeeEstimator::new();
+		estimator.process_block(1, &[]);
+		let mut counter = 0u32;
+		for height in 1..blocks + 1 {
+			let mut confirmed = Vec::new();
+			for _ in 0..10 {
+				counter += 1;
+				estimator.process_transaction(counter_hash(counter), height, 2500, 250);
+				confirmed.push(counter_hash(counter));
+				counter += 1;
+				estimator.process_transaction(counter_hash(counter), height, 500, 250);
+			}
+			estimator.process_block(height + 1, &confirmed);
+		}
+		estimator
+	}
+
+	fn counter_hash(counter: u32) -> H256 {
+		let mut hash = H256::default();
+		hash[0..4].copy_from_slice(serialize(&counter).as_ref());
+		hash
+	}
+
+	#[test]
+	fn test_no_estimate_without_statistics() {
+		let estimator = FeeEstimator::new();
+		assert_eq!(estimator.estimate_smart_fee(2, true), None);
+		assert_eq!(estimator.estimate_smart_fee(0, true), None);
+		assert_eq!(estimator.estimate_smart_fee(1009, true), None);
+		assert_eq!(estimator.estimate_raw_fee(1, 0.95, FeeEstimateHorizon::Short).unwrap().fee_rate, None);
+		assert_eq!(estimator.estimate_raw_fee(13, 0.95, FeeEstimateHorizon::Short), None);
+	}
+
+	#[test]
+	fn test_estimate_smart_fee() {
+		let estimator = estimator_with_blocks(100);
+		assert_eq!(estimator.estimate_smart_fee(1, false), Some(FeeEstimate { fee_rate: 10_000, blocks: 2 }));
+		assert_eq!(estimator.estimate_smart_fee(6, true), Some(FeeEstimate { fee_rate: 10_000, blocks: 6 }));
+		// only 100 blocks are tracked => target is reduced
+		assert_eq!(estimator.estimate_smart_fee(144, false), Some(FeeEstimate { fee_rate: 10_000, blocks: 49 }));
+
+		let estimate = estimator.estimate_raw_fee(1, 0.95, FeeEstimateHorizon::Short).unwrap();
+		assert!((estimate.fee_rate.unwrap() - 10_000.0).abs() < 0.001);
+		let pass = estimate.pass.unwrap();
+		assert!(pass.start < 10_000.0 && pass.end >= 10_000.0);
+		// lower buckets contain unconfirmed transactions only
+		let fail = estimate.fail.unwrap();
+		assert!(fail.end < 10_000.0 && fail.in_mempool > 0.0);
+	}
+
+	#[test]
+	fn test_transactions_are_tracked_on_top_of_the_best_block() {
+		let mut estimator = FeeEstimator::new();
+		estimator.process_block(10, &[]);
+		estimator.process_transaction(H256::from(1), 9, 1000, 100);
+		estimator.process_transaction(H256::from(2), 10, 1000, 100);
+		assert!(!estimator.remove_transaction(&H256::from(1), true));
+		assert!(estimator.remove_transaction(&H256::from(2), true));
+		assert!(!estimator.remove_transaction(&H256::from(2), true));
+	}
+
+	#[test]
+	fn test_fee_estimator_serialization() {
+		let estimator = estimator_with_blocks(20);
+		let restored: FeeEstimator = deserialize(serialize(&estimator).as_ref()).unwrap();
+		assert_eq!(restored.best_seen_height, 21);
+		assert_eq!(restored.historical_first, 2);
+		assert_eq!(restored.historical_best, 21);
+		assert_eq!(restored.short, {
+			let mut stats = estimator.short.clone();
+			for values in &mut stats.unconfirmed {
+				for value in values.iter_mut() {
+					*value = 0;
+				}
+			}
+			for value in &mut stats.old_unconfirmed {
+				*value = 0;
+			}
+			stats
+		});
+		assert_eq!(restored.estimate_smart_fee(6, true), estimator.estimate_smart_fee(6, true));
+	}
+}
@@ -1,5 +1,9 @@
-extern crate byteorder;
 extern crate heapsize;
+#[macro_use]
+extern crate log;
+extern crate parking_lot;
+#[macro_use]
+extern crate serde_json;
 
 extern crate bitcrypto as crypto;
 extern crate chain;
@@ -14,10 +18,16 @@ extern crate verification;
 mod block_assembler;
 mod cpu_miner;
 mod fee;
+mod fee_estimator;
 mod memory_pool;
+mod stratum;
 
-pub use block_assembler::{BlockAssembler, BlockTemplate};
+pub use block_assembler::{BlockAssembler, BlockTemplate, BlockTemplateTransaction};
 pub use cpu_miner::find_solution;
 pub use memory_pool::{MemoryPool, HashedOutPoint, Information as MemoryPoolInformation,
-	OrderingStrategy as MemoryPoolOrderingStrategy, DoubleSpendCheckResult, NonFinalDoubleSpendSet};
-pub use fee::{transaction_fee, transaction_fee_rate};
+	EntryInformation as MemoryPoolEntryInformation, OrderingStrategy as MemoryPoolOrderingStrategy, DoubleSpendCheckResult, NonFinalDoubleSpendSet,
+	signals_replacement, MAX_REPLACEMENT_EVICTIONS, INCREMENTAL_RELAY_FEE, DEFAULT_MAX_MEMORY_POOL_SIZE};
+pub use stratum::{StratumServer, StratumNotifier, StratumConfig, StratumCoreApi};
+pub use fee::{transaction_fee, checked_transaction_fee, transaction_fee_rate};
+pub use fee_estimator::{FeeEstimator, FeeEstimate, FeeEstimateBucket, FeeEstimateHorizon, RawFeeEstimate,
+	MAX_CONFIRMATION_TARGET};
@@ -5,18 +5,38 @@
 //! transactions.
 //! It also guarantees that ancestor-descendant relation won't break during ordered removal (ancestors always removed
 //! before descendants). Removal using `remove_by_hash` can break this rule.
+//! Transactions, which are signaling replaceability (BIP125), may be replaced by conflicting transactions,
+//! paying higher fee.
+//! When total size of transactions exceeds the limit, packages with the lowest fee rate are evicted and the minimum
+//! fee rate of entering transactions is raised.
 use db::{TransactionProvider, TransactionOutputProvider};
 use primitives::bytes::Bytes;
 use primitives::hash::H256;
 use chain::{IndexedTransaction, Transaction, OutPoint, TransactionOutput};
-use std::cmp::Ordering;
+use chain::constants::SEQUENCE_FINAL;
+use verification::TransactionError;
+use std::cmp::{self, Ordering};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::BTreeSet;
 use std::collections::VecDeque;
 use std::hash::{Hash, Hasher};
-use ser::{Serializable, serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ser::serialize;
 use heapsize::HeapSizeOf;
+use fee_estimator::FeeEstimator;
+
+/// Maximal number of memory pool transactions, which may be evicted by the single replacement transaction (BIP125)
+pub const MAX_REPLACEMENT_EVICTIONS: usize = 100;
+
+/// Fee rate (in satoshis per 1000 virtual bytes), which replacement transaction must additionally pay for its own relay (BIP125)
+pub const INCREMENTAL_RELAY_FEE: u64 = 1000;
+
+/// Default limit of the total virtual size of memory pool transactions (in bytes)
+pub const DEFAULT_MAX_MEMORY_POOL_SIZE: usize = 300_000_000;
+
+/// Half-life of the rolling minimum fee rate (in seconds)
+const ROLLING_FEE_HALF_LIFE: u32 = 12 * 60 * 60;
 
 /// Transactions ordering strategy
 #[cfg_attr(feature="cargo-clippy", allow(enum_variant_names))]
@@ -28,6 +48,9 @@ pub enum OrderingStrategy {
 	ByTransactionScore,
 	/// Order transactions by their in-pool package mining score (score for mining this transaction + all descendants transactions)
 	ByPackageScore,
+	/// Order transactions by their in-pool ancestors mining score (score for mining this transaction + all ancestors transactions).
+	/// Ancestors, which are not yet returned, are returned right before the transaction (child-pays-for-parent).
+	ByAncestorScore,
 }
 
 /// Information on current `MemoryPool` state
@@ -37,6 +60,45 @@ pub struct Information {
 	pub transactions_count: usize,
 	/// Total number of bytes occupied by transactions from the `MemoryPool`
 	pub transactions_size_in_bytes: usize,
+	/// Maximal number of bytes, which may be occupied by transactions from the `MemoryPool`
+	pub max_size_in_bytes: usize,
+	/// Minimal fee rate (in satoshis per 1000 virtual bytes) of transactions, entering the `MemoryPool`
+	pub minimum_fee_rate: u64,
+}
+
+/// Information on single `MemoryPool` entry
+#[derive(Debug, PartialEq)]
+pub struct EntryInformation {
+	/// Transaction
+	pub transaction: Transaction,
+	/// Transaction virtual size
+	pub size: usize,
+	/// Time, when transaction has entered the `MemoryPool` (in seconds since epoch)
+	pub time: u32,
+	/// Best block height, when transaction has entered the `MemoryPool`
+	pub height: u32,
+	/// Transaction fee
+	pub miner_fee: i64,
+	/// Virtual transaction fee
+	pub miner_virtual_fee: i64,
+	/// Number of in-pool ancestors + 1
+	pub ancestors_count: usize,
+	/// size + Sum(size) for all in-pool ancestors
+	pub ancestors_size: usize,
+	/// miner_fee + miner_virtual_fee + Sum(miner_fee + miner_virtual_fee) for all in-pool ancestors
+	pub ancestors_fee: i64,
+	/// Number of in-pool descendants + 1
+	pub descendants_count: usize,
+	/// size + Sum(size) for all in-pool descendants
+	pub descendants_size: usize,
+	/// miner_fee + miner_virtual_fee + Sum(miner_fee + miner_virtual_fee) for all in-pool descendants
+	pub descendants_fee: i64,
+	/// Hashes of in-pool transactions, which outputs are spent by this transaction
+	pub depends: Vec<H256>,
+	/// Hashes of in-pool transactions, which are spending outputs of this transaction
+	pub spent_by: Vec<H256>,
+	/// Is transaction replaceable (BIP125)?
+	pub replaceable: bool,
 }
 
 /// Transactions memory pool
@@ -44,21 +106,37 @@ pub struct Information {
 pub struct MemoryPool {
 	/// Transactions storage
 	storage: Storage,
+	/// Fee estimator, tracking memory pool transactions
+	fee_estimator: FeeEstimator,
+	/// Best block height, which is recorded as height of entering transactions
+	best_block_height: u32,
+	/// Maximal total size of transactions (in bytes)
+	max_size: usize,
+	/// Minimal fee rate (in satoshis per 1000 virtual bytes) of entering transactions. Raised when transactions are evicted
+	rolling_minimum_fee_rate: f64,
+	/// Time of the last `rolling_minimum_fee_rate` update (in seconds since epoch)
+	rolling_minimum_fee_rate_time: u32,
+	/// True if new best block has been processed since `rolling_minimum_fee_rate` has been raised
+	block_since_last_rolling_fee_bump: bool,
 }
 
 /// Single entry
 #[derive(Debug)]
 pub struct Entry {
-	/// Transaction
-	pub transaction: Transaction,
+	/// Transaction, shared with the rest of the node
+	pub transaction: IndexedTransaction,
 	/// In-pool ancestors hashes for this transaction
 	pub ancestors: HashSet<H256>,
 	/// Transaction hash (stored for effeciency)
 	pub hash: H256,
-	/// Transaction size (stored for effeciency)
+	/// Transaction virtual size (stored for effeciency)
 	pub size: usize,
 	/// Throughout index of this transaction in memory pool (non persistent)
 	pub storage_index: u64,
+	/// Time, when transaction has entered the memory pool (in seconds since epoch)
+	pub time: u32,
+	/// Best block height, when transaction has entered the memory pool
+	pub height: u32,
 	/// Transaction fee (stored for efficiency)
 	pub miner_fee: i64,
 	/// Virtual transaction fee (a way to prioritize/penalize transaction)
@@ -69,6 +147,14 @@ pub struct Entry {
 	pub package_miner_fee: i64,
 	/// miner_virtual_fee + Sum(miner_virtual_fee) for all in-pool descendants
 	pub package_miner_virtual_fee: i64,
+	/// 1 + number of in-pool descendants
+	pub package_count: usize,
+	/// size + Sum(size) for all in-pool ancestors
+	pub ancestors_size: usize,
+	/// miner_fee + Sum(miner_fee) for all in-pool ancestors
+	pub ancestors_miner_fee: i64,
+	/// miner_virtual_fee + Sum(miner_virtual_fee) for all in-pool ancestors
+	pub ancestors_miner_virtual_fee: i64,
 }
 
 /// Multi-index transactions storage
@@ -84,6 +170,10 @@ struct Storage {
 	by_previous_output: HashMap<HashedOutPoint, H256>,
 	/// References storage
 	references: ReferenceStorage,
+	/// By-ancestors-score storage. Unlike orderings from `references`, holds all entries
+	by_ancestor_score: BTreeSet<ByAncestorScoreOrderedEntry>,
+	/// By-descendants-score storage, used to evict entries when pool is full. Holds all entries
+	by_descendant_score: BTreeSet<ByDescendantScoreOrderedEntry>,
 }
 
 /// Multi-index storage which holds references to entries from `Storage::by_hash`
@@ -140,6 +230,36 @@ struct ByPackageScoreOrderedEntry {
 	package_miner_virtual_fee: i64,
 }
 
+#[derive(Debug, Eq, PartialEq, Clone)]
+struct ByAncestorScoreOrderedEntry {
+	/// Transaction hash
+	hash: H256,
+	/// size + Sum(size) for all in-pool ancestors
+	ancestors_size: usize,
+	/// miner_fee + Sum(miner_fee) for all in-pool ancestors
+	ancestors_miner_fee: i64,
+	/// miner_virtual_fee + Sum(miner_virtual_fee) for all in-pool ancestors
+	ancestors_miner_virtual_fee: i64,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+struct ByDescendantScoreOrderedEntry {
+	/// Transaction hash
+	hash: H256,
+	/// Transaction size
+	size: usize,
+	/// Transaction fee
+	miner_fee: i64,
+	/// Virtual transaction fee
+	miner_virtual_fee: i64,
+	/// size + Sum(size) for all in-pool descendants
+	package_size: usize,
+	/// miner_fee + Sum(miner_fee) for all in-pool descendants
+	package_miner_fee: i64,
+	/// miner_virtual_fee + Sum(miner_virtual_fee) for all in-pool descendants
+	package_miner_virtual_fee: i64,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct HashedOutPoint {
 	/// Transaction output point
@@ -151,14 +271,15 @@ pub struct HashedOutPoint {
 pub enum DoubleSpendCheckResult {
 	/// No double spend
 	NoDoubleSpend,
-	/// Input {self.1, self.2} of new transaction is already spent in previous final memory-pool transaction {self.0}
+	/// Input {self.1, self.2} of new transaction is already spent in previous final memory-pool transaction {self.0},
+	/// which isn't signaling replaceability
 	DoubleSpend(H256, H256, u32),
-	/// Some inputs of new transaction are already spent by non-final memory-pool transactions
+	/// Some inputs of new transaction are already spent by non-final, or replaceable memory-pool transactions
 	NonFinalDoubleSpend(NonFinalDoubleSpendSet),
 }
 
 /// Set of transaction outputs, which can be replaced if newer transaction
-/// replaces non-final transaction in memory pool
+/// replaces non-final, or replaceable transaction in memory pool
 #[derive(Debug, PartialEq)]
 pub struct NonFinalDoubleSpendSet {
 	/// Double-spend outputs (outputs of newer transaction, which are also spent by nonfinal transactions of mempool)
@@ -166,6 +287,11 @@ pub struct NonFinalDoubleSpendSet {
 	/// Outputs which also will be removed from memory pool in case of newer transaction insertion
 	/// (i.e. outputs of nonfinal transactions && their descendants)
 	pub dependent_spends: HashSet<HashedOutPoint>,
+	/// Memory pool transactions, which are spending the same outputs as newer transaction
+	pub conflicts: HashSet<H256>,
+	/// Memory pool transactions, which will be removed in case of newer transaction insertion
+	/// (i.e. conflicting transactions && their descendants)
+	pub replaced: HashSet<H256>,
 }
 
 impl From<OutPoint> for HashedOutPoint {
@@ -214,6 +340,44 @@ impl<'a> From<&'a Entry> for ByPackageScoreOrderedEntry {
 	}
 }
 
+impl<'a> From<&'a Entry> for ByAncestorScoreOrderedEntry {
+	fn from(entry: &'a Entry) -> Self {
+		ByAncestorScoreOrderedEntry {
+			hash: entry.hash.clone(),
+			ancestors_size: entry.ancestors_size,
+			ancestors_miner_fee: entry.ancestors_miner_fee,
+			ancestors_miner_virtual_fee: entry.ancestors_miner_virtual_fee,
+		}
+	}
+}
+
+impl<'a> From<&'a Entry> for ByDescendantScoreOrderedEntry {
+	fn from(entry: &'a Entry) -> Self {
+		ByDescendantScoreOrderedEntry {
+			hash: entry.hash.clone(),
+			size: entry.size,
+			miner_fee: entry.miner_fee,
+			miner_virtual_fee: entry.miner_virtual_fee,
+			package_size: entry.package_size,
+			package_miner_fee: entry.package_miner_fee,
+			package_miner_virtual_fee: entry.package_miner_virtual_fee,
+		}
+	}
+}
+
+impl ByDescendantScoreOrderedEntry {
+	/// (fee, size) of the greater of transaction own fee rate and fee rate of its package
+	fn score(&self) -> (i64, i64) {
+		let transaction = (self.miner_fee + self.miner_virtual_fee, self.size as i64);
+		let package = (self.package_miner_fee + self.package_miner_virtual_fee, self.package_size as i64);
+		if transaction.0 * package.1 >= package.0 * transaction.1 {
+			transaction
+		} else {
+			package
+		}
+	}
+}
+
 impl PartialOrd for ByTimestampOrderedEntry {
 	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
 		Some(self.cmp(other))
@@ -271,9 +435,49 @@ impl Ord for ByPackageScoreOrderedEntry {
 	}
 }
 
+impl PartialOrd for ByAncestorScoreOrderedEntry {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for ByAncestorScoreOrderedEntry {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// lesser miner score means later removal
+		let left = (self.ancestors_miner_fee + self.ancestors_miner_virtual_fee) * (other.ancestors_size as i64);
+		let right = (other.ancestors_miner_fee + other.ancestors_miner_virtual_fee) * (self.ancestors_size as i64);
+		let order = right.cmp(&left);
+		if order != Ordering::Equal {
+			return order
+		}
+
+		self.hash.cmp(&other.hash)
+	}
+}
+
+impl PartialOrd for ByDescendantScoreOrderedEntry {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for ByDescendantScoreOrderedEntry {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// lesser descendant score means earlier eviction
+		let (self_fee, self_size) = self.score();
+		let (other_fee, other_size) = other.score();
+		let order = (self_fee * other_size).cmp(&(other_fee * self_size));
+		if order != Ordering::Equal {
+			return order
+		}
+
+		self.hash.cmp(&other.hash)
+	}
+}
+
 impl HeapSizeOf for Entry {
 	fn heap_size_of_children(&self) -> usize {
-		self.transaction.heap_size_of_children() + self.ancestors.heap_size_of_children()
+		self.transaction.raw.heap_size_of_children() + self.ancestors.heap_size_of_children()
 	}
 }
 
@@ -293,6 +497,8 @@ impl Storage {
 					by_package_score: BTreeSet::new(),
 				},
 			},
+			by_ancestor_score: BTreeSet::new(),
+			by_descendant_score: BTreeSet::new(),
 		}
 	}
 
@@ -301,7 +507,7 @@ impl Storage {
 		self.transactions_size_in_bytes += entry.size;
 
 		// remember that this transactions depends on its inputs
-		for input_hash in entry.transaction.inputs.iter().map(|input| &input.previous_output.hash) {
+		for input_hash in entry.transaction.raw.inputs.iter().map(|input| &input.previous_output.hash) {
 			self.references.by_input.entry(input_hash.clone()).or_insert_with(HashSet::new).insert(entry.hash.clone());
 		}
 
@@ -309,11 +515,14 @@ impl Storage {
 		for ancestor_hash in &entry.ancestors {
 			if let Some(mut ancestor_entry) = self.by_hash.get_mut(ancestor_hash) {
 				let removed = self.references.ordered.by_package_score.remove(&(ancestor_entry as &Entry).into());
+				self.by_descendant_score.remove(&(ancestor_entry as &Entry).into());
 
 				ancestor_entry.package_size += entry.size;
 				ancestor_entry.package_miner_fee += entry.package_miner_fee;
 				ancestor_entry.package_miner_virtual_fee += entry.package_miner_virtual_fee;
+				ancestor_entry.package_count += 1;
 
+				self.by_descendant_score.insert((ancestor_entry as &Entry).into());
 				if removed {
 					self.references.ordered.by_package_score.insert((ancestor_entry as &Entry).into());
 				}
@@ -321,7 +530,7 @@ impl Storage {
 		}
 
 		// insert either to pending queue or to orderings
-		if self.references.has_in_pool_ancestors(None, &self.by_hash, &entry.transaction) {
+		if self.references.has_in_pool_ancestors(None, &self.by_hash, &entry.transaction.raw) {
 			self.references.pending.insert(entry.hash.clone());
 		}
 		else {
@@ -329,11 +538,15 @@ impl Storage {
 		}
 
 		// remember that all inputs of this transaction are spent
-		for input in &entry.transaction.inputs {
+		for input in &entry.transaction.raw.inputs {
 			let previous_tx = self.by_previous_output.insert(input.previous_output.clone().into(), entry.hash.clone());
 			assert_eq!(previous_tx, None); // transaction must be verified before => no double spend
 		}
 
+		// ancestors and descendants score orderings hold all entries
+		self.by_ancestor_score.insert((&entry).into());
+		self.by_descendant_score.insert((&entry).into());
+
 		// add to by_hash storage
 		self.by_hash.insert(entry.hash.clone(), entry);
 	}
@@ -359,6 +572,8 @@ impl Storage {
 		if let Some(mut entry) = self.by_hash.get_mut(h) {
 			let insert_to_package_score = self.references.ordered.by_package_score.remove(&(entry as &Entry).into());
 			let insert_to_transaction_score = self.references.ordered.by_transaction_score.remove(&(entry as &Entry).into());
+			self.by_ancestor_score.remove(&(entry as &Entry).into());
+			self.by_descendant_score.remove(&(entry as &Entry).into());
 
 			miner_virtual_fee_change = virtual_fee - entry.miner_virtual_fee;
 			if !entry.ancestors.is_empty() {
@@ -366,7 +581,11 @@ impl Storage {
 			}
 
 			entry.miner_virtual_fee = virtual_fee;
+			entry.package_miner_virtual_fee += miner_virtual_fee_change;
+			entry.ancestors_miner_virtual_fee += miner_virtual_fee_change;
 
+			self.by_ancestor_score.insert((entry as &Entry).into());
+			self.by_descendant_score.insert((entry as &Entry).into());
 			if insert_to_transaction_score {
 				self.references.ordered.by_transaction_score.insert((entry as &Entry).into());
 			}
@@ -381,18 +600,29 @@ impl Storage {
 				for ancestor_hash in ancestors {
 					if let Some(mut ancestor_entry) = self.by_hash.get_mut(&ancestor_hash) {
 						let insert_to_package_score = self.references.ordered.by_package_score.remove(&(ancestor_entry as &Entry).into());
+						self.by_descendant_score.remove(&(ancestor_entry as &Entry).into());
 						ancestor_entry.package_miner_virtual_fee += miner_virtual_fee_change;
+						self.by_descendant_score.insert((ancestor_entry as &Entry).into());
 						if insert_to_package_score {
 							self.references.ordered.by_package_score.insert((ancestor_entry as &Entry).into());
 						}
 					}
 				}
 			});
+
+			// and all descendant entries
+			for descendant_hash in self.descendants(h) {
+				if let Some(mut descendant_entry) = self.by_hash.get_mut(&descendant_hash) {
+					self.by_ancestor_score.remove(&(descendant_entry as &Entry).into());
+					descendant_entry.ancestors_miner_virtual_fee += miner_virtual_fee_change;
+					self.by_ancestor_score.insert((descendant_entry as &Entry).into());
+				}
+			}
 		}
 	}
 
 	pub fn read_by_hash(&self, h: &H256) -> Option<&Transaction> {
-		self.by_hash.get(h).map(|e| &e.transaction)
+		self.by_hash.get(h).map(|e| &*e.transaction.raw)
 	}
 
 	pub fn read_with_strategy(&self, strategy: OrderingStrategy) -> Option<H256> {
@@ -400,9 +630,22 @@ impl Storage {
 			OrderingStrategy::ByTimestamp => self.references.ordered.by_storage_index.iter().map(|entry| entry.hash.clone()).nth(0),
 			OrderingStrategy::ByTransactionScore => self.references.ordered.by_transaction_score.iter().map(|entry| entry.hash.clone()).nth(0),
 			OrderingStrategy::ByPackageScore => self.references.ordered.by_package_score.iter().map(|entry| entry.hash.clone()).nth(0),
+			OrderingStrategy::ByAncestorScore => self.top_by_ancestor_score(),
 		}
 	}
 
+	/// Returns hash of the first in-pool ancestor of the entry with the best ancestors score.
+	/// If this entry has no in-pool ancestors, returns its own hash.
+	fn top_by_ancestor_score(&self) -> Option<H256> {
+		self.by_ancestor_score.iter().nth(0).map(|top| {
+			let entry = self.by_hash.get(&top.hash).expect("missing hash is a sign of MemoryPool internal inconsistancy");
+			entry.ancestors.iter()
+				.filter_map(|ancestor_hash| self.by_hash.get(ancestor_hash))
+				.min_by_key(|ancestor_entry| (ancestor_entry.ancestors.len(), ancestor_entry.hash.clone()))
+				.map_or_else(|| entry.hash.clone(), |ancestor_entry| ancestor_entry.hash.clone())
+		})
+	}
+
 	pub fn remove_by_hash(&mut self, h: &H256) -> Option<Entry> {
 		self.by_hash.remove(h)
 			.map(|entry| {
@@ -410,34 +653,118 @@ impl Storage {
 				self.transactions_size_in_bytes -= entry.size;
 
 				// forget that all inputs of this transaction are spent
-				for input in &entry.transaction.inputs {
+				for input in &entry.transaction.raw.inputs {
 					let spent_in_tx = self.by_previous_output.remove(&input.previous_output.clone().into())
 						.expect("by_spent_output is filled for each incoming transaction inputs; so the drained value should exist; qed");
 					assert_eq!(&spent_in_tx, h);
 				}
 
+				// update packages of in-pool ancestors && descendants
+				self.remove_from_packages(&entry);
+
 				// remove from storage
 				self.references.remove(None, &self.by_hash, &entry);
+				self.by_ancestor_score.remove(&(&entry).into());
+				self.by_descendant_score.remove(&(&entry).into());
 
 				entry
 			})
 	}
 
+	/// Returns hashes of all in-pool descendants of the transaction
+	fn descendants(&self, h: &H256) -> HashSet<H256> {
+		let mut descendants: HashSet<H256> = HashSet::new();
+		let mut queue: VecDeque<H256> = VecDeque::new();
+		queue.push_back(h.clone());
+		while let Some(hash) = queue.pop_front() {
+			if let Some(children) = self.references.by_input.get(&hash) {
+				for child in children {
+					if self.by_hash.contains_key(child) && descendants.insert(child.clone()) {
+						queue.push_back(child.clone());
+					}
+				}
+			}
+		}
+		descendants
+	}
+
+	/// Returns hash of the entry with the lowest descendant score, i.e. the greater of its own fee rate
+	/// and fee rate of its package (entry + all in-pool descendants)
+	fn lowest_descendant_score(&self) -> Option<H256> {
+		self.by_descendant_score.iter().nth(0).map(|entry| entry.hash.clone())
+	}
+
+	/// Removes entry together with all its in-pool descendants. Ancestors are removed before descendants
+	fn remove_package(&mut self, h: &H256) -> Vec<Entry> {
+		let mut package: Vec<H256> = self.descendants(h).into_iter().collect();
+		package.push(h.clone());
+		// in-pool ancestors of the entry are also in-pool ancestors of its descendants
+		package.sort_by_key(|hash| self.by_hash.get(hash).map(|entry| entry.ancestors.len()));
+		package.into_iter()
+			.filter_map(|hash| self.remove_by_hash(&hash))
+			.collect()
+	}
+
+	/// Removes entry from the packages of its in-pool ancestors and descendants
+	fn remove_from_packages(&mut self, entry: &Entry) {
+		for ancestor_hash in &entry.ancestors {
+			if let Some(mut ancestor_entry) = self.by_hash.get_mut(ancestor_hash) {
+				let removed = self.references.ordered.by_package_score.remove(&(ancestor_entry as &Entry).into());
+				self.by_descendant_score.remove(&(ancestor_entry as &Entry).into());
+
+				ancestor_entry.package_size -= entry.size;
+				ancestor_entry.package_miner_fee -= entry.miner_fee;
+				ancestor_entry.package_miner_virtual_fee -= entry.miner_virtual_fee;
+				ancestor_entry.package_count -= 1;
+
+				self.by_descendant_score.insert((ancestor_entry as &Entry).into());
+				if removed {
+					self.references.ordered.by_package_score.insert((ancestor_entry as &Entry).into());
+				}
+			}
+		}
+
+		for descendant_hash in self.descendants(&entry.hash) {
+			if let Some(mut descendant_entry) = self.by_hash.get_mut(&descendant_hash) {
+				if !descendant_entry.ancestors.remove(&entry.hash) {
+					continue;
+				}
+
+				self.by_ancestor_score.remove(&(descendant_entry as &Entry).into());
+
+				descendant_entry.ancestors_size -= entry.size;
+				descendant_entry.ancestors_miner_fee -= entry.miner_fee;
+				descendant_entry.ancestors_miner_virtual_fee -= entry.miner_virtual_fee;
+
+				self.by_ancestor_score.insert((descendant_entry as &Entry).into());
+			}
+		}
+	}
+
+	pub fn is_replaceable(&self, entry: &Entry) -> bool {
+		signals_replacement(&entry.transaction.raw) || entry.ancestors.iter()
+			.filter_map(|ancestor_hash| self.by_hash.get(ancestor_hash))
+			.any(|ancestor_entry| signals_replacement(&ancestor_entry.transaction.raw))
+	}
+
 	pub fn check_double_spend(&self, transaction: &Transaction) -> DoubleSpendCheckResult {
 		let mut double_spends: HashSet<HashedOutPoint> = HashSet::new();
 		let mut dependent_spends: HashSet<HashedOutPoint> = HashSet::new();
+		let mut conflicts: HashSet<H256> = HashSet::new();
+		let mut replaced: HashSet<H256> = HashSet::new();
 
 		for input in &transaction.inputs {
 			// find transaction that spends the same output
 			let prevout: HashedOutPoint = input.previous_output.clone().into();
 			if let Some(entry_hash) = self.by_previous_output.get(&prevout).cloned() {
-				// check if this is final transaction. If so, that's a potential double-spend error
+				// check if this is final transaction, which isn't replaceable. If so, that's a potential double-spend error
 				let entry = self.by_hash.get(&entry_hash).expect("checked that it exists line above; qed");
-				if entry.transaction.is_final() {
+				if entry.transaction.raw.is_final() && !self.is_replaceable(entry) {
 					return DoubleSpendCheckResult::DoubleSpend(entry_hash,	 prevout.out_point.hash, prevout.out_point.index);
 				}
 				// else remember this double spend
 				double_spends.insert(prevout.clone());
+				conflicts.insert(entry_hash);
 				// and 'virtually' remove entry && all descendants from mempool
 				let mut queue: VecDeque<HashedOutPoint> = VecDeque::new();
 				queue.push_back(prevout);
@@ -445,12 +772,13 @@ impl Storage {
 					// if the same output is already spent with another in-pool transaction
 					if let Some(dependent_entry_hash) = self.by_previous_output.get(&dependent_prevout).cloned() {
 						let dependent_entry = self.by_hash.get(&dependent_entry_hash).expect("checked that it exists line above; qed");
-						let dependent_outputs: Vec<_> = dependent_entry.transaction.outputs.iter().enumerate().map(|(idx, _)| OutPoint {
+						let dependent_outputs: Vec<_> = dependent_entry.transaction.raw.outputs.iter().enumerate().map(|(idx, _)| OutPoint {
 							hash: dependent_entry_hash.clone(),
 							index: idx as u32,
 						}.into()).collect();
 						dependent_spends.extend(dependent_outputs.clone());
 						queue.extend(dependent_outputs);
+						replaced.insert(dependent_entry_hash);
 					}
 				}
 			}
@@ -462,6 +790,8 @@ impl Storage {
 			DoubleSpendCheckResult::NonFinalDoubleSpend(NonFinalDoubleSpendSet {
 				double_spends: double_spends,
 				dependent_spends: dependent_spends,
+				conflicts: conflicts,
+				replaced: replaced,
 			})
 		}
 	}
@@ -474,18 +804,18 @@ impl Storage {
 		while let Some(prevout) = queue.pop_front() {
 			if let Some(entry_hash) = self.by_previous_output.get(&prevout.clone().into()).cloned() {
 				let entry = self.remove_by_hash(&entry_hash).expect("checked that it exists line above; qed");
-				queue.extend(entry.transaction.outputs.iter().enumerate().map(|(idx, _)| OutPoint {
+				queue.extend(entry.transaction.raw.outputs.iter().enumerate().map(|(idx, _)| OutPoint {
 					hash: entry_hash.clone(),
 					index: idx as u32,
 				}));
-				removed.push(IndexedTransaction::new(entry.hash, entry.transaction));
+				removed.push(entry.transaction);
 			}
 		}
 
 		Some(removed)
 	}
 
-	pub fn remove_by_parent_hash(&mut self, h: &H256) -> Option<Vec<IndexedTransaction>> {
+	pub fn remove_by_parent_hash(&mut self, h: &H256) -> Option<Vec<Entry>> {
 		// this code will run only when ancestor transaction is inserted
 		// in memory pool after its descendants
 		if let Some(mut descendants) = self.references.by_input.get(h).map(|d| d.iter().cloned().collect::<Vec<H256>>()) {
@@ -520,7 +850,7 @@ impl Storage {
 
 			// move all descendants out of storage for later insertion
 			Some(all_descendants.into_iter()
-					.filter_map(|hash| self.remove_by_hash(hash).map(|entry| IndexedTransaction::new(entry.hash, entry.transaction)))
+					.filter_map(|hash| self.remove_by_hash(hash))
 					.collect())
 		}
 		else {
@@ -533,11 +863,12 @@ impl Storage {
 			OrderingStrategy::ByTimestamp => self.references.ordered.by_storage_index.iter().map(|entry| entry.hash.clone()).nth(0),
 			OrderingStrategy::ByTransactionScore => self.references.ordered.by_transaction_score.iter().map(|entry| entry.hash.clone()).nth(0),
 			OrderingStrategy::ByPackageScore => self.references.ordered.by_package_score.iter().map(|entry| entry.hash.clone()).nth(0),
+			OrderingStrategy::ByAncestorScore => self.top_by_ancestor_score(),
 		};
 		top_hash.map(|hash| {
 			let entry = self.remove_by_hash(&hash)
 				.expect("`hash` is read from `references`; entries in `references` have corresponging entries in `by_hash`; `remove_by_hash` removes entry from `by_hash`; qed");
-			IndexedTransaction::new(entry.hash, entry.transaction)
+			entry.transaction
 		})
 	}
 
@@ -576,7 +907,7 @@ impl ReferenceStorage {
 			for descendant in descendants {
 				// if there are no more ancestors of this transaction in the pool
 				// => can move from pending to orderings
-				if !self.has_in_pool_ancestors(removed, by_hash, &descendant.transaction) {
+				if !self.has_in_pool_ancestors(removed, by_hash, &descendant.transaction.raw) {
 					self.pending.remove(&descendant.hash);
 
 					if let Some(descendant_entry) = by_hash.get(&descendant.hash) {
@@ -638,6 +969,12 @@ impl Default for MemoryPool {
 	fn default() -> Self {
 		MemoryPool {
 			storage: Storage::new(),
+			fee_estimator: FeeEstimator::new(),
+			best_block_height: 0,
+			max_size: DEFAULT_MAX_MEMORY_POOL_SIZE,
+			rolling_minimum_fee_rate: 0f64,
+			rolling_minimum_fee_rate_time: 0,
+			block_since_last_rolling_fee_bump: false,
 		}
 	}
 }
@@ -648,14 +985,34 @@ impl MemoryPool {
 		MemoryPool::default()
 	}
 
+	/// Creates new memory pool, which holds transactions with total size up to `max_size` bytes
+	pub fn with_max_size(max_size: usize) -> Self {
+		MemoryPool {
+			max_size: max_size,
+			..MemoryPool::default()
+		}
+	}
+
 	/// Insert verified transaction to the `MemoryPool`
 	pub fn insert_verified(&mut self, t: IndexedTransaction) {
-		let entry = self.make_entry(t);
+		self.insert_verified_entry(t, None)
+	}
+
+	/// Insert verified transaction with known fee to the `MemoryPool`
+	pub fn insert_verified_with_fee(&mut self, t: IndexedTransaction, fee: u64) {
+		self.insert_verified_entry(t, Some(fee as i64))
+	}
+
+	fn insert_verified_entry(&mut self, t: IndexedTransaction, miner_fee: Option<i64>) {
+		let entry = self.make_entry(t, miner_fee);
 		let descendants = self.storage.remove_by_parent_hash(&entry.hash);
 		self.storage.insert(entry);
 		if let Some(descendants_iter) = descendants.map(|d| d.into_iter()) {
 			for descendant in descendants_iter {
-				let descendant_entry = self.make_entry(descendant);
+				let (miner_fee, time, height) = (descendant.miner_fee, descendant.time, descendant.height);
+				let mut descendant_entry = self.make_entry(descendant.transaction, Some(miner_fee));
+				descendant_entry.time = time;
+				descendant_entry.height = height;
 				self.storage.insert(descendant_entry);
 			}
 		}
@@ -668,7 +1025,7 @@ impl MemoryPool {
 
 	/// Removes single transaction by its hash.
 	/// All descedants remain in the pool.
-	pub fn remove_by_hash(&mut self, h: &H256) -> Option<Transaction> {
+	pub fn remove_by_hash(&mut self, h: &H256) -> Option<IndexedTransaction> {
 		self.storage.remove_by_hash(h).map(|entry| entry.transaction)
 	}
 
@@ -677,6 +1034,176 @@ impl MemoryPool {
 		self.storage.check_double_spend(transaction)
 	}
 
+	/// Checks if `transaction` may replace memory pool transactions, which are spending the same outputs.
+	/// Non-final transactions may always be replaced. Replacement of final transactions (which are signaling
+	/// replaceability) must follow BIP125 rules:
+	/// 1) replacement may only spend outputs of memory pool transactions, spent by replaced transactions;
+	/// 2) replacement must not evict more than `MAX_REPLACEMENT_EVICTIONS` transactions;
+	/// 3) replacement fee rate must be higher than fee rate of every conflicting transaction;
+	/// 4) replacement must pay fee of all evicted transactions + `INCREMENTAL_RELAY_FEE` for its own relay.
+	/// `output_value` must return value of the output from both the storage and the memory pool.
+	pub fn check_replacement<F>(&self, transaction: &Transaction, spends: &NonFinalDoubleSpendSet, output_value: F) -> Result<(), TransactionError>
+		where F: Fn(&OutPoint) -> Option<u64> {
+		let conflicts: Vec<&Entry> = spends.conflicts.iter()
+			.filter_map(|hash| self.storage.get_by_hash(hash))
+			.collect();
+		if conflicts.iter().all(|entry| !entry.transaction.raw.is_final()) {
+			return Ok(());
+		}
+
+		let conflicts_parents: HashSet<&H256> = conflicts.iter()
+			.flat_map(|entry| entry.transaction.raw.inputs.iter().map(|input| &input.previous_output.hash))
+			.collect();
+		if let Some(input) = transaction.inputs.iter()
+			.find(|input| self.storage.contains(&input.previous_output.hash) && !conflicts_parents.contains(&input.previous_output.hash)) {
+			return Err(TransactionError::ReplacementSpendsUnconfirmed(input.previous_output.hash.clone()));
+		}
+
+		if spends.replaced.len() > MAX_REPLACEMENT_EVICTIONS {
+			return Err(TransactionError::TooManyReplacements(spends.replaced.len()));
+		}
+
+		let transaction_fee = |transaction: &Transaction| transaction.inputs.iter()
+			.fold(Some(0u64), |sum, input| sum.and_then(|sum| output_value(&input.previous_output).map(|value| sum + value)))
+			.map(|inputs_sum| inputs_sum.saturating_sub(transaction.total_spends()));
+		let fee = match transaction_fee(transaction) {
+			Some(fee) => fee,
+			// transaction with unknown inputs is rejected by verification
+			None => return Ok(()),
+		};
+		let size = transaction.vsize() as u64;
+		let fee_rate = fee as f64 / size as f64;
+
+		let mut replaced_fee = 0u64;
+		for entry in spends.replaced.iter().filter_map(|hash| self.storage.get_by_hash(hash)) {
+			// inputs of memory pool transactions are always known
+			let entry_fee = transaction_fee(&entry.transaction.raw).unwrap_or_default();
+			if spends.conflicts.contains(&entry.hash) && fee_rate <= entry_fee as f64 / entry.size as f64 {
+				return Err(TransactionError::InsufficientReplacementFeeRate(entry.hash.clone()));
+			}
+			replaced_fee += entry_fee;
+		}
+
+		let required_fee = replaced_fee + INCREMENTAL_RELAY_FEE * size / 1000;
+		if fee < required_fee {
+			return Err(TransactionError::InsufficientReplacementFee(fee, required_fee));
+		}
+
+		Ok(())
+	}
+
+	/// Checks if `transaction` pays fee, required by the current minimum fee rate of the memory pool.
+	/// `output_value` must return value of the output from both the storage and the memory pool.
+	pub fn check_minimum_fee<F>(&self, transaction: &Transaction, output_value: F) -> Result<(), TransactionError>
+		where F: Fn(&OutPoint) -> Option<u64> {
+		let minimum_fee_rate = self.minimum_fee_rate();
+		if minimum_fee_rate == 0 {
+			return Ok(());
+		}
+
+		let inputs_sum = transaction.inputs.iter()
+			.fold(Some(0u64), |sum, input| sum.and_then(|sum| output_value(&input.previous_output).map(|value| sum + value)));
+		let fee = match inputs_sum {
+			Some(inputs_sum) => inputs_sum.saturating_sub(transaction.total_spends()),
+			// transaction with unknown inputs is rejected by verification
+			None => return Ok(()),
+		};
+
+		let required_fee = minimum_fee_rate * transaction.vsize() as u64 / 1000;
+		if fee < required_fee {
+			return Err(TransactionError::InsufficientMemoryPoolFee(fee, required_fee));
+		}
+
+		Ok(())
+	}
+
+	/// Evicts transactions with the lowest descendant score (together with their in-pool descendants) until total size
+	/// of transactions fits into the limit. Minimum fee rate is raised above fee rates of evicted packages.
+	/// Returns evicted transactions.
+	pub fn trim_to_size(&mut self) -> Vec<IndexedTransaction> {
+		self.trim_to_size_at(unix_time())
+	}
+
+	fn trim_to_size_at(&mut self, now: u32) -> Vec<IndexedTransaction> {
+		let mut evicted = Vec::new();
+		while self.storage.transactions_size_in_bytes > self.max_size {
+			let hash = match self.storage.lowest_descendant_score() {
+				Some(hash) => hash,
+				None => break,
+			};
+
+			let package_fee_rate = {
+				let entry = self.storage.get_by_hash(&hash).expect("hash is read from the storage line above; qed");
+				(entry.package_miner_fee + entry.package_miner_virtual_fee) as f64 * 1000f64 / entry.package_size as f64
+			};
+			self.raise_minimum_fee_rate(package_fee_rate + INCREMENTAL_RELAY_FEE as f64, now);
+
+			evicted.extend(self.storage.remove_package(&hash).into_iter()
+				.map(|entry| entry.transaction));
+		}
+		evicted
+	}
+
+	/// Returns minimal fee rate (in satoshis per 1000 virtual bytes), which entering transactions must pay.
+	/// It is raised when transactions are evicted and decays with 12 hours half-life (faster, when memory pool
+	/// is mostly empty) after next best block is processed. 0 means that any fee rate is accepted.
+	pub fn minimum_fee_rate(&self) -> u64 {
+		self.minimum_fee_rate_at(unix_time())
+	}
+
+	fn minimum_fee_rate_at(&self, now: u32) -> u64 {
+		let fee_rate = self.decayed_minimum_fee_rate(now);
+		if fee_rate == 0f64 {
+			return 0;
+		}
+
+		cmp::max(fee_rate.round() as u64, INCREMENTAL_RELAY_FEE)
+	}
+
+	fn decayed_minimum_fee_rate(&self, now: u32) -> f64 {
+		if !self.block_since_last_rolling_fee_bump || self.rolling_minimum_fee_rate == 0f64 {
+			return self.rolling_minimum_fee_rate;
+		}
+
+		let size = self.storage.transactions_size_in_bytes;
+		let half_life = if size < self.max_size / 4 {
+			ROLLING_FEE_HALF_LIFE / 4
+		} else if size < self.max_size / 2 {
+			ROLLING_FEE_HALF_LIFE / 2
+		} else {
+			ROLLING_FEE_HALF_LIFE
+		};
+		let elapsed = now.saturating_sub(self.rolling_minimum_fee_rate_time);
+		let fee_rate = self.rolling_minimum_fee_rate / 2f64.powf(elapsed as f64 / half_life as f64);
+		if fee_rate < INCREMENTAL_RELAY_FEE as f64 / 2f64 {
+			0f64
+		} else {
+			fee_rate
+		}
+	}
+
+	fn raise_minimum_fee_rate(&mut self, fee_rate: f64, now: u32) {
+		let current_fee_rate = self.decayed_minimum_fee_rate(now);
+		if fee_rate > current_fee_rate {
+			self.rolling_minimum_fee_rate = fee_rate;
+			self.block_since_last_rolling_fee_bump = false;
+		} else {
+			self.rolling_minimum_fee_rate = current_fee_rate;
+		}
+		self.rolling_minimum_fee_rate_time = now;
+	}
+
+	/// Notes that new best block has been processed. Minimum fee rate only decays after that
+	pub fn note_new_best_block(&mut self) {
+		self.note_new_best_block_at(unix_time())
+	}
+
+	fn note_new_best_block_at(&mut self, now: u32) {
+		self.rolling_minimum_fee_rate = self.decayed_minimum_fee_rate(now);
+		self.rolling_minimum_fee_rate_time = now;
+		self.block_since_last_rolling_fee_bump = true;
+	}
+
 	/// Removes transaction (and all its descendants) which has spent given output
 	pub fn remove_by_prevout(&mut self, prevout: &OutPoint) -> Option<Vec<IndexedTransaction>> {
 		self.storage.remove_by_prevout(prevout)
@@ -719,7 +1246,7 @@ impl MemoryPool {
 
 	/// Get transaction by hash
 	pub fn get(&self, hash: &H256) -> Option<&Transaction> {
-		self.storage.get_by_hash(hash).map(|entry| &entry.transaction)
+		self.storage.get_by_hash(hash).map(|entry| &*entry.transaction.raw)
 	}
 
 	/// Checks if transaction is in the mempool
@@ -733,7 +1260,77 @@ impl MemoryPool {
 		Information {
 			transactions_count: self.storage.by_hash.len(),
 			transactions_size_in_bytes: self.storage.transactions_size_in_bytes,
+			max_size_in_bytes: self.max_size,
+			minimum_fee_rate: self.minimum_fee_rate(),
+		}
+	}
+
+	/// Returns information on single `MemoryPool` entry (as in GetMemPoolEntry RPC)
+	/// https://bitcoin.org/en/developer-reference#getmempoolentry
+	pub fn entry_information(&self, hash: &H256) -> Option<EntryInformation> {
+		self.storage.get_by_hash(hash).map(|entry| {
+			let mut depends: Vec<H256> = entry.transaction.raw.inputs.iter()
+				.map(|input| input.previous_output.hash.clone())
+				.filter(|input_hash| self.storage.contains(input_hash))
+				.collect();
+			depends.sort();
+			depends.dedup();
+			let mut spent_by: Vec<H256> = self.storage.references.by_input.get(hash)
+				.map(|spent_by| spent_by.iter().filter(|spent_by| self.storage.contains(spent_by)).cloned().collect())
+				.unwrap_or_default();
+			spent_by.sort();
+
+			EntryInformation {
+				transaction: (*entry.transaction.raw).clone(),
+				size: entry.size,
+				time: entry.time,
+				height: entry.height,
+				miner_fee: entry.miner_fee,
+				miner_virtual_fee: entry.miner_virtual_fee,
+				ancestors_count: entry.ancestors.len() + 1,
+				ancestors_size: entry.ancestors_size,
+				ancestors_fee: entry.ancestors_miner_fee + entry.ancestors_miner_virtual_fee,
+				descendants_count: entry.package_count,
+				descendants_size: entry.package_size,
+				descendants_fee: entry.package_miner_fee + entry.package_miner_virtual_fee,
+				depends: depends,
+				spent_by: spent_by,
+				replaceable: self.storage.is_replaceable(entry),
+			}
+		})
+	}
+
+	/// Returns hashes of all in-pool ancestors of the transaction (as in GetMemPoolAncestors RPC)
+	/// https://bitcoin.org/en/developer-reference#getmempoolancestors
+	pub fn ancestors(&self, hash: &H256) -> Option<Vec<H256>> {
+		self.storage.get_by_hash(hash).map(|entry| {
+			let mut ancestors: Vec<H256> = entry.ancestors.iter().cloned().collect();
+			ancestors.sort();
+			ancestors
+		})
+	}
+
+	/// Returns hashes of all in-pool descendants of the transaction (as in GetMemPoolDescendants RPC)
+	/// https://bitcoin.org/en/developer-reference#getmempooldescendants
+	pub fn descendants(&self, hash: &H256) -> Option<Vec<H256>> {
+		if !self.storage.contains(hash) {
+			return None;
 		}
+
+		let mut descendants: Vec<H256> = self.storage.descendants(hash).into_iter().collect();
+		descendants.sort();
+		Some(descendants)
+	}
+
+	/// Sets best block height, which is recorded as height of transactions, entering the `MemoryPool`
+	pub fn set_best_block_height(&mut self, height: u32) {
+		self.best_block_height = height;
+	}
+
+	/// Returns number of transactions, ever inserted into the `MemoryPool`.
+	/// Changes, when new transaction is inserted, so block templates could be refreshed.
+	pub fn transactions_counter(&self) -> u64 {
+		self.storage.counter
 	}
 
 	/// Returns TXIDs of all transactions in `MemoryPool` (as in GetRawMemPool RPC)
@@ -747,16 +1344,42 @@ impl MemoryPool {
 		self.storage.is_output_spent(prevout)
 	}
 
-	fn make_entry(&mut self, t: IndexedTransaction) -> Entry {
+	/// Returns true if transaction, or any of its in-pool ancestors, signals replaceability (BIP125)
+	pub fn is_replaceable(&self, hash: &H256) -> bool {
+		self.storage.get_by_hash(hash).map_or(false, |entry| self.storage.is_replaceable(entry))
+	}
+
+	/// Fee estimator, tracking memory pool transactions
+	pub fn fee_estimator(&self) -> &FeeEstimator {
+		&self.fee_estimator
+	}
+
+	/// Mutable fee estimator. Transactions must be reported to estimator, when they enter and leave the pool
+	pub fn fee_estimator_mut(&mut self) -> &mut FeeEstimator {
+		&mut self.fee_estimator
+	}
+
+	fn make_entry(&mut self, t: IndexedTransaction, miner_fee: Option<i64>) -> Entry {
 		let ancestors = self.get_ancestors(&t.raw);
 		let size = self.get_transaction_size(&t.raw);
 		let storage_index = self.get_storage_index();
-		let miner_fee = self.get_transaction_miner_fee(&t.raw);
+		let miner_fee = miner_fee.unwrap_or_else(|| self.get_transaction_miner_fee(&t.raw));
+		let (ancestors_size, ancestors_miner_fee, ancestors_miner_virtual_fee) = ancestors.iter()
+			.filter_map(|ancestor_hash| self.storage.get_by_hash(ancestor_hash))
+			.fold((size, miner_fee, 0), |(size, miner_fee, miner_virtual_fee), ancestor_entry| (
+				size + ancestor_entry.size,
+				miner_fee + ancestor_entry.miner_fee,
+				miner_virtual_fee + ancestor_entry.miner_virtual_fee,
+			));
+		let hash = t.hash.clone();
+		let time = unix_time();
 		Entry {
-			transaction: t.raw,
-			hash: t.hash,
+			transaction: t,
+			hash: hash,
 			ancestors: ancestors,
 			storage_index: storage_index,
+			time: time,
+			height: self.best_block_height,
 			size: size,
 			miner_fee: miner_fee,
 			miner_virtual_fee: 0,
@@ -764,6 +1387,10 @@ impl MemoryPool {
 			package_size: size,
 			package_miner_fee: miner_fee,
 			package_miner_virtual_fee: 0,
+			package_count: 1,
+			ancestors_size: ancestors_size,
+			ancestors_miner_fee: ancestors_miner_fee,
+			ancestors_miner_virtual_fee: ancestors_miner_virtual_fee,
 		}
 	}
 
@@ -781,7 +1408,7 @@ impl MemoryPool {
 	}
 
 	fn get_transaction_size(&self, t: &Transaction) -> usize {
-		t.serialized_size()
+		t.vsize()
 	}
 
 	fn get_transaction_miner_fee(&self, t: &Transaction) -> i64 {
@@ -802,6 +1429,17 @@ impl MemoryPool {
 	}
 }
 
+/// Returns current time in seconds since epoch
+fn unix_time() -> u32 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as u32).unwrap_or_default()
+}
+
+/// Returns true if transaction explicitly signals replaceability (BIP125),
+/// i.e. if sequence number of any of its inputs is less than 0xfffffffe.
+pub fn signals_replacement(transaction: &Transaction) -> bool {
+	transaction.inputs.iter().any(|input| input.sequence < SEQUENCE_FINAL - 1)
+}
+
 impl TransactionProvider for MemoryPool {
 	fn transaction_bytes(&self, hash: &H256) -> Option<Bytes> {
 		self.get(hash).map(|t| serialize(t))
@@ -835,6 +1473,12 @@ pub struct MemoryPoolIterator<'a> {
 	references: ReferenceStorage,
 	removed: HashSet<H256>,
 	strategy: OrderingStrategy,
+	/// Ancestors scores of entries, which are not yet returned (`ByAncestorScore` strategy only)
+	by_ancestor_score: BTreeSet<ByAncestorScoreOrderedEntry>,
+	/// Ancestors scores of entries, which ancestors are already returned (`ByAncestorScore` strategy only)
+	modified: HashMap<H256, ByAncestorScoreOrderedEntry>,
+	/// Selected entries, which are not yet returned (`ByAncestorScore` strategy only)
+	package: VecDeque<&'a Entry>,
 }
 
 impl<'a> MemoryPoolIterator<'a> {
@@ -844,7 +1488,61 @@ impl<'a> MemoryPoolIterator<'a> {
 			references: memory_pool.storage.references.clone(),
 			removed: HashSet::new(),
 			strategy: strategy,
+			by_ancestor_score: match strategy {
+				OrderingStrategy::ByAncestorScore => memory_pool.storage.by_ancestor_score.clone(),
+				_ => BTreeSet::new(),
+			},
+			modified: HashMap::new(),
+			package: VecDeque::new(),
+		}
+	}
+
+	fn next_by_ancestor_score(&mut self) -> Option<&'a Entry> {
+		if self.package.is_empty() {
+			let memory_pool = self.memory_pool;
+			let storage = &memory_pool.storage;
+			let top_hash = match self.by_ancestor_score.iter().nth(0) {
+				Some(top) => top.hash.clone(),
+				None => return None,
+			};
+
+			// ancestors, which are not yet returned, are returned right before the entry itself
+			let entry = storage.by_hash.get(&top_hash).expect("missing hash is a sign of MemoryPool internal inconsistancy");
+			let mut package: Vec<&'a Entry> = entry.ancestors.iter()
+				.filter(|ancestor_hash| !self.removed.contains(*ancestor_hash))
+				.filter_map(|ancestor_hash| storage.by_hash.get(ancestor_hash))
+				.collect();
+			package.sort_by_key(|ancestor_entry| ancestor_entry.ancestors.len());
+			package.push(entry);
+
+			for package_entry in &package {
+				let ordered_entry = self.modified.remove(&package_entry.hash).unwrap_or_else(|| (*package_entry).into());
+				self.by_ancestor_score.remove(&ordered_entry);
+				self.removed.insert(package_entry.hash.clone());
+			}
+
+			// returned entries are no longer included in ancestors scores of their descendants
+			for package_entry in &package {
+				for descendant_hash in storage.descendants(&package_entry.hash) {
+					if self.removed.contains(&descendant_hash) {
+						continue;
+					}
+
+					let descendant_entry = storage.by_hash.get(&descendant_hash).expect("descendants are read from `by_hash`; qed");
+					let mut ordered_entry = self.modified.remove(&descendant_hash).unwrap_or_else(|| descendant_entry.into());
+					self.by_ancestor_score.remove(&ordered_entry);
+					ordered_entry.ancestors_size -= package_entry.size;
+					ordered_entry.ancestors_miner_fee -= package_entry.miner_fee;
+					ordered_entry.ancestors_miner_virtual_fee -= package_entry.miner_virtual_fee;
+					self.by_ancestor_score.insert(ordered_entry.clone());
+					self.modified.insert(descendant_hash, ordered_entry);
+				}
+			}
+
+			self.package.extend(package);
 		}
+
+		self.package.pop_front()
 	}
 }
 
@@ -856,6 +1554,7 @@ impl<'a> Iterator for MemoryPoolIterator<'a> {
 			OrderingStrategy::ByTimestamp => self.references.ordered.by_storage_index.iter().map(|entry| entry.hash.clone()).nth(0),
 			OrderingStrategy::ByTransactionScore => self.references.ordered.by_transaction_score.iter().map(|entry| entry.hash.clone()).nth(0),
 			OrderingStrategy::ByPackageScore => self.references.ordered.by_package_score.iter().map(|entry| entry.hash.clone()).nth(0),
+			OrderingStrategy::ByAncestorScore => return self.next_by_ancestor_score(),
 		};
 
 		top_hash.map(|top_hash| {
@@ -873,6 +1572,8 @@ mod tests {
 
 	use chain::{Transaction, OutPoint};
 	use heapsize::HeapSizeOf;
+	use primitives::hash::H256;
+	use verification::TransactionError;
 	use super::{MemoryPool, OrderingStrategy, DoubleSpendCheckResult};
 	use self::test_data::{ChainBuilder, TransactionBuilder};
 
@@ -953,7 +1654,7 @@ mod tests {
 		// remove and check remaining transactions
 		let removed = pool.remove_by_hash(&Transaction::default().hash());
 		assert!(removed.is_some());
-		assert_eq!(removed.unwrap(), Transaction::default());
+		assert_eq!(removed.unwrap(), Transaction::default().into());
 		assert_eq!(pool.get_transactions_ids().len(), 0);
 
 		// remove non-existant transaction
@@ -1243,6 +1944,109 @@ mod tests {
 		assert_eq!(pool.read_n_with_strategy(6, OrderingStrategy::ByPackageScore), expected);
 	}
 
+	#[test]
+	fn test_memory_pool_ancestor_score_ordering_strategy() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0).set_output(10).add_output(10).store(chain)	// transaction0
+			.into_input(0).set_output(10).store(chain);										// transaction0 -> transaction1
+		let transaction0 = chain.at(0);
+		TransactionBuilder::with_output(10).set_input(&transaction0, 1).store(chain)			// transaction0 -> transaction2
+			.set_default_input(1).store(chain);												// transaction3
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified_with_fee(chain.at(0).into(), 0);
+		pool.insert_verified_with_fee(chain.at(1).into(), 100);
+		pool.insert_verified_with_fee(chain.at(2).into(), 50);
+		pool.insert_verified_with_fee(chain.at(3).into(), 40);
+
+		// transaction0 is selected first, because its child pays for it:
+		// score({ transaction0, transaction1 }) = (0 + 100) / 129 ~ 0.775
+		// >
+		// score({ transaction3 }) = 40 / 60 ~ 0.667
+		// >
+		// score({ transaction0, transaction2 }) = (0 + 50) / 129 ~ 0.388
+		// once transaction0 is selected, transaction2 is no longer paying for it:
+		// score({ transaction2 }) = 50 / 60 ~ 0.833
+		let expected = vec![chain.hash(0), chain.hash(1), chain.hash(2), chain.hash(3)];
+		assert_eq!(pool.read_n_with_strategy(4, OrderingStrategy::ByAncestorScore), expected);
+
+		let transactions = pool.remove_n_with_strategy(4, OrderingStrategy::ByAncestorScore);
+		assert_eq!(transactions.into_iter().map(|t| t.hash).collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn test_memory_pool_entry_information() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0).set_output(10).add_output(10).store(chain)	// transaction0
+			.into_input(0).set_output(10).store(chain);										// transaction0 -> transaction1
+		let transaction0 = chain.at(0);
+		TransactionBuilder::with_output(10).set_input(&transaction0, 1).store(chain);			// transaction0 -> transaction2
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified_with_fee(chain.at(0).into(), 0);
+		pool.insert_verified_with_fee(chain.at(1).into(), 100);
+		pool.insert_verified_with_fee(chain.at(2).into(), 50);
+		pool.set_virtual_fee(&chain.hash(2), 10);
+
+		let info = pool.entry_information(&chain.hash(0)).unwrap();
+		assert_eq!(info.size, 69);
+		assert_eq!(info.miner_fee, 0);
+		assert_eq!((info.ancestors_count, info.ancestors_size, info.ancestors_fee), (1, 69, 0));
+		assert_eq!((info.descendants_count, info.descendants_size, info.descendants_fee), (3, 189, 160));
+		assert!(info.depends.is_empty());
+		let mut spent_by = vec![chain.hash(1), chain.hash(2)];
+		spent_by.sort();
+		assert_eq!(info.spent_by, spent_by);
+		assert!(!info.replaceable);
+
+		let info = pool.entry_information(&chain.hash(2)).unwrap();
+		assert_eq!((info.miner_fee, info.miner_virtual_fee), (50, 10));
+		assert_eq!((info.ancestors_count, info.ancestors_size, info.ancestors_fee), (2, 129, 60));
+		assert_eq!((info.descendants_count, info.descendants_size, info.descendants_fee), (1, 60, 60));
+		assert_eq!(info.depends, vec![chain.hash(0)]);
+		assert!(info.spent_by.is_empty());
+
+		// descendants information is updated, when transaction is removed
+		pool.remove_by_hash(&chain.hash(1));
+		let info = pool.entry_information(&chain.hash(0)).unwrap();
+		assert_eq!((info.descendants_count, info.descendants_size, info.descendants_fee), (2, 129, 60));
+		assert_eq!(info.spent_by, vec![chain.hash(2)]);
+
+		assert!(pool.entry_information(&chain.hash(1)).is_none());
+	}
+
+	#[test]
+	fn test_memory_pool_ancestors_and_descendants() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0).set_output(10).add_output(10).store(chain)	// transaction0
+			.into_input(0).set_output(10).store(chain)											// transaction0 -> transaction1
+			.into_input(0).set_output(10).store(chain);										// transaction0 -> transaction1 -> transaction2
+		let transaction0 = chain.at(0);
+		TransactionBuilder::with_output(10).set_input(&transaction0, 1).store(chain);			// transaction0 -> transaction3
+
+		let mut pool = MemoryPool::new();
+		pool.set_best_block_height(100);
+		pool.insert_verified(chain.at(0).into());
+		pool.insert_verified(chain.at(1).into());
+		pool.set_best_block_height(101);
+		pool.insert_verified(chain.at(2).into());
+		pool.insert_verified(chain.at(3).into());
+
+		let mut descendants = vec![chain.hash(1), chain.hash(2), chain.hash(3)];
+		descendants.sort();
+		assert_eq!(pool.descendants(&chain.hash(0)), Some(descendants));
+		assert_eq!(pool.descendants(&chain.hash(2)), Some(vec![]));
+		assert_eq!(pool.ancestors(&chain.hash(0)), Some(vec![]));
+		let mut ancestors = vec![chain.hash(0), chain.hash(1)];
+		ancestors.sort();
+		assert_eq!(pool.ancestors(&chain.hash(2)), Some(ancestors));
+		assert_eq!(pool.ancestors(&H256::default()), None);
+		assert_eq!(pool.descendants(&H256::default()), None);
+
+		assert_eq!(pool.entry_information(&chain.hash(1)).unwrap().height, 100);
+		assert_eq!(pool.entry_information(&chain.hash(3)).unwrap().height, 101);
+	}
+
 	#[test]
 	fn test_memory_pool_spent_transaction_output() {
 		let chain = &mut ChainBuilder::new();
@@ -1385,6 +2189,84 @@ mod tests {
 
 	}
 
+	#[test]
+	fn test_memory_pool_check_double_spend_replaceable() {
+		let chain = &mut ChainBuilder::new();
+
+		TransactionBuilder::with_output(10).add_output(10).store(chain)					// t0
+			.reset().set_input(&chain.at(0), 0).add_output(20).signal_replacement().store(chain)	// replaceable: t0[0] -> t1
+			.reset().set_input(&chain.at(1), 0).add_output(30).store(chain)					// inherits replaceability: t0[0] -> t1[0] -> t2
+			.reset().set_input(&chain.at(0), 0).add_output(40).store(chain)					// replacement: t0[0] -> t3
+			.reset().set_input(&chain.at(1), 0).add_output(50).store(chain)					// replacement of descendant: t1[0] -> t4
+			.reset().set_input(&chain.at(0), 1).add_output(60).store(chain)					// final: t0[1] -> t5
+			.reset().set_input(&chain.at(0), 1).add_output(70).store(chain);				// bad replacement: t0[1] -> t6
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(1).into());
+		pool.insert_verified(chain.at(2).into());
+		pool.insert_verified(chain.at(5).into());
+		assert!(pool.is_replaceable(&chain.at(1).hash()));
+		assert!(pool.is_replaceable(&chain.at(2).hash()));
+		assert!(!pool.is_replaceable(&chain.at(5).hash()));
+
+		match pool.check_double_spend(&chain.at(3)) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
+				assert_eq!(set.conflicts, vec![chain.at(1).hash()].into_iter().collect());
+				assert_eq!(set.replaced, vec![chain.at(1).hash(), chain.at(2).hash()].into_iter().collect());
+			},
+			_ => panic!("unexpected"),
+		}
+		match pool.check_double_spend(&chain.at(4)) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
+				assert_eq!(set.conflicts, vec![chain.at(2).hash()].into_iter().collect());
+				assert_eq!(set.replaced, vec![chain.at(2).hash()].into_iter().collect());
+			},
+			_ => panic!("unexpected"),
+		}
+		match pool.check_double_spend(&chain.at(6)) {
+			DoubleSpendCheckResult::DoubleSpend(inpool_hash, _, _) => assert_eq!(inpool_hash, chain.at(5).hash()),
+			_ => panic!("unexpected"),
+		}
+	}
+
+	#[test]
+	fn test_memory_pool_check_replacement() {
+		let chain = &mut ChainBuilder::new();
+
+		TransactionBuilder::with_output(10_000).add_output(10_000).store(chain)				// t0
+			.reset().set_input(&chain.at(0), 0).add_output(9_000).signal_replacement().store(chain)	// replaceable: t0[0] -> t1
+			.reset().set_input(&chain.at(1), 0).add_output(8_500).store(chain)					// descendant: t0[0] -> t1[0] -> t2
+			.reset().set_input(&chain.at(0), 1).add_output(9_000).store(chain)					// unrelated: t0[1] -> t3
+			.reset().set_input(&chain.at(0), 0).add_output(8_500).store(chain)					// pays less than t1 + t2 + relay fee: t0[0] -> t4
+			.reset().set_input(&chain.at(0), 0).add_output(7_000).store(chain)					// good replacement: t0[0] -> t5
+			.reset().set_input(&chain.at(0), 0).add_output(1_000).store(chain);					// t0[0] -> t6
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(1).into());
+		pool.insert_verified(chain.at(2).into());
+		pool.insert_verified(chain.at(3).into());
+
+		let t0 = chain.at(0);
+		let check_replacement = |transaction: &Transaction| match pool.check_double_spend(transaction) {
+			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => pool.check_replacement(transaction, &set, |prevout| pool
+				.get(&prevout.hash)
+				.or_else(|| if prevout.hash == t0.hash() { Some(&t0) } else { None })
+				.and_then(|tx| tx.outputs.get(prevout.index as usize))
+				.map(|output| output.value)),
+			_ => panic!("unexpected"),
+		};
+
+		// t1 fee is 1000, t2 fee is 500
+		assert_eq!(check_replacement(&chain.at(4)), Err(TransactionError::InsufficientReplacementFee(1_500, 1_560)));
+		assert_eq!(check_replacement(&chain.at(5)), Ok(()));
+
+		// replacement, spending output of another memory pool transaction
+		let mut t6 = chain.at(6);
+		t6.inputs.push(chain.at(6).inputs[0].clone());
+		t6.inputs[1].previous_output = OutPoint { hash: chain.at(3).hash(), index: 0 };
+		assert_eq!(check_replacement(&t6), Err(TransactionError::ReplacementSpendsUnconfirmed(chain.at(3).hash())));
+	}
+
 	#[test]
 	fn test_memory_poolis_spent() {
 		let tx1: Transaction = TransactionBuilder::with_default_input(0).into();
@@ -1396,4 +2278,85 @@ mod tests {
 		assert!(memory_pool.is_spent(&out1));
 		assert!(!memory_pool.is_spent(&out2));
 	}
+
+	const NOW: u32 = 1_500_000_000;
+
+	#[test]
+	fn test_memory_pool_trim_to_size() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).store(chain)		// t0
+			.into_input(0).add_output(20).store(chain)			// t0[0] -> t1
+			.reset().add_output(30).store(chain);				// t2
+
+		let mut pool = MemoryPool::with_max_size(chain.size(0) + chain.size(1) + chain.size(2) - 1);
+		pool.insert_verified_with_fee(chain.at(0).into(), 100);
+		pool.insert_verified_with_fee(chain.at(1).into(), 10_000);
+		pool.insert_verified_with_fee(chain.at(2).into(), 2_000);
+		assert_eq!(pool.minimum_fee_rate_at(NOW), 0);
+
+		// t0 pays the lowest fee, but its package (t0 + t1) fee rate is higher than t2 fee rate
+		let evicted: Vec<H256> = pool.trim_to_size_at(NOW).into_iter().map(|tx| tx.hash).collect();
+		assert_eq!(evicted, vec![chain.at(2).hash()]);
+		assert_eq!(pool.information().transactions_count, 2);
+		let expected_fee_rate = 2_000f64 * 1000f64 / chain.size(2) as f64 + 1000f64;
+		assert_eq!(pool.minimum_fee_rate_at(NOW), expected_fee_rate.round() as u64);
+
+		// nothing is evicted when pool fits into the limit
+		assert!(pool.trim_to_size_at(NOW).is_empty());
+
+		// ancestors are evicted together with descendants
+		pool.max_size = 0;
+		let evicted: Vec<H256> = pool.trim_to_size_at(NOW).into_iter().map(|tx| tx.hash).collect();
+		assert_eq!(evicted, vec![chain.at(0).hash(), chain.at(1).hash()]);
+		assert_eq!(pool.information().transactions_count, 0);
+		assert_eq!(pool.information().transactions_size_in_bytes, 0);
+	}
+
+	#[test]
+	fn test_memory_pool_minimum_fee_rate_decay() {
+		let mut pool = MemoryPool::new();
+		pool.raise_minimum_fee_rate(10_000f64, NOW);
+		assert_eq!(pool.minimum_fee_rate_at(NOW), 10_000);
+
+		// fee rate doesn't decay until next block
+		assert_eq!(pool.minimum_fee_rate_at(NOW + 24 * 60 * 60), 10_000);
+
+		// empty pool => 3 hours half-life
+		pool.note_new_best_block_at(NOW);
+		assert_eq!(pool.minimum_fee_rate_at(NOW + 3 * 60 * 60), 5_000);
+		assert_eq!(pool.minimum_fee_rate_at(NOW + 9 * 60 * 60), 1_250);
+
+		// decayed fee rate is never lower than incremental relay fee rate, and then drops to zero
+		pool.raise_minimum_fee_rate(700f64, NOW + 15 * 60 * 60);
+		assert_eq!(pool.minimum_fee_rate_at(NOW + 15 * 60 * 60), 1_000);
+		pool.note_new_best_block_at(NOW + 15 * 60 * 60);
+		assert_eq!(pool.minimum_fee_rate_at(NOW + 18 * 60 * 60), 0);
+
+		// lower fee rate doesn't replace higher one
+		pool.raise_minimum_fee_rate(100_000f64, NOW);
+		pool.raise_minimum_fee_rate(50_000f64, NOW);
+		assert_eq!(pool.minimum_fee_rate_at(NOW), 100_000);
+	}
+
+	#[test]
+	fn test_memory_pool_check_minimum_fee() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10_000).store(chain)	// t0
+			.into_input(0).add_output(9_000).store(chain);		// t0[0] -> t1, pays 1000
+
+		let mut pool = MemoryPool::new();
+		let t0 = chain.at(0);
+		let t1 = chain.at(1);
+		let check_minimum_fee = |pool: &MemoryPool| pool.check_minimum_fee(&t1, |prevout| if prevout.hash == t0.hash() {
+			t0.outputs.get(prevout.index as usize).map(|output| output.value)
+		} else {
+			None
+		});
+
+		assert_eq!(check_minimum_fee(&pool), Ok(()));
+
+		pool.raise_minimum_fee_rate(100_000f64, NOW);
+		let required_fee = 100 * t1.vsize() as u64;
+		assert_eq!(check_minimum_fee(&pool), Err(TransactionError::InsufficientMemoryPoolFee(1_000, required_fee)));
+	}
 }
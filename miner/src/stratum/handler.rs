@@ -0,0 +1,481 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use parking_lot::RwLock;
+use serde_json::{self, Value};
+use primitives::bytes::Bytes;
+use primitives::hex::{ToHex, FromHex};
+use chain::IndexedBlock;
+use network::ConsensusParams;
+use verification::is_valid_proof_of_work;
+use block_assembler::BlockTemplate;
+use super::job::{Job, EXTRANONCE2_SIZE, difficulty_target, hash_value};
+
+/// Unknown method or other error.
+const ERROR_OTHER: u32 = 20;
+/// Share of the unknown or stale job.
+const ERROR_JOB_NOT_FOUND: u32 = 21;
+/// Share has already been submitted.
+const ERROR_DUPLICATE_SHARE: u32 = 22;
+/// Share hash is above the share target.
+const ERROR_LOW_DIFFICULTY: u32 = 23;
+/// Share is submitted before `mining.authorize`.
+const ERROR_UNAUTHORIZED: u32 = 24;
+/// Share is submitted before `mining.subscribe`.
+const ERROR_NOT_SUBSCRIBED: u32 = 25;
+
+/// Maximal number of jobs, for which shares are accepted.
+const MAX_JOBS: usize = 8;
+/// Minimal share difficulty.
+const MIN_DIFFICULTY: f64 = 0.001;
+/// Share rate is checked after this interval.
+const RETARGET_INTERVAL_SECS: u64 = 60;
+/// Difficulty changes at most by this factor at once.
+const MAX_RETARGET_FACTOR: f64 = 4.0;
+/// Difficulty isn't changed if the share rate is this close to the target.
+const RETARGET_THRESHOLD: f64 = 0.25;
+
+/// Node interface, required by the Stratum server.
+pub trait StratumCoreApi: Send + Sync + 'static {
+	/// Block template for the next block.
+	fn block_template(&self) -> BlockTemplate;
+	/// Submits the mined block. Returns BIP22 reject reason if the block is rejected.
+	fn submit_block(&self, block: IndexedBlock) -> Option<String>;
+}
+
+/// Stratum server configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StratumConfig {
+	/// Address to listen on
+	pub address: SocketAddr,
+	/// Output script, the block reward is paid to
+	pub coinbase_script: Bytes,
+	/// Initial share difficulty of the connection
+	pub difficulty: f64,
+	/// Number of shares per minute, which is maintained by adjusting the connection difficulty
+	pub shares_per_minute: f64,
+}
+
+/// State of the miner connection.
+pub struct Session {
+	/// Extranonce, assigned to the connection
+	extranonce1: Bytes,
+	/// Is `mining.subscribe` received?
+	subscribed: bool,
+	/// Worker name from `mining.authorize`
+	worker: Option<String>,
+	/// Difficulty of the next job
+	difficulty: f64,
+	/// Difficulty, last sent to the miner
+	sent_difficulty: Option<f64>,
+	/// Share difficulty of the jobs, sent to the miner
+	jobs: HashMap<String, f64>,
+	/// Accepted shares: job, extranonce2, time and nonce
+	shares: HashSet<(String, Bytes, u32, u32)>,
+	/// Number of shares, accepted since the last difficulty adjustment
+	retarget_shares: u32,
+	/// Time of the last difficulty adjustment
+	retarget_time: Instant,
+}
+
+impl Session {
+	/// Can jobs be sent to the miner?
+	pub fn is_ready(&self) -> bool {
+		self.subscribed && self.worker.is_some()
+	}
+}
+
+/// Handles Stratum requests, shared by all connections.
+pub struct StratumHandler<T: StratumCoreApi> {
+	core: T,
+	config: StratumConfig,
+	/// Consensus parameters, used to check proof-of-work of the shares
+	consensus: ConsensusParams,
+	/// Recent jobs, the newest first
+	jobs: RwLock<VecDeque<Arc<Job>>>,
+	job_counter: AtomicUsize,
+	extranonce_counter: AtomicUsize,
+}
+
+impl<T> StratumHandler<T> where T: StratumCoreApi {
+	pub fn new(core: T, config: StratumConfig, consensus: ConsensusParams) -> Self {
+		StratumHandler {
+			core: core,
+			config: config,
+			consensus: consensus,
+			jobs: RwLock::new(VecDeque::new()),
+			job_counter: AtomicUsize::new(0),
+			extranonce_counter: AtomicUsize::new(0),
+		}
+	}
+
+	/// Creates session of the new connection, with the unique extranonce.
+	pub fn new_session(&self) -> Session {
+		let extranonce = self.extranonce_counter.fetch_add(1, Ordering::SeqCst) as u32;
+		Session {
+			extranonce1: vec![(extranonce >> 24) as u8, (extranonce >> 16) as u8, (extranonce >> 8) as u8, extranonce as u8].into(),
+			subscribed: false,
+			worker: None,
+			difficulty: self.config.difficulty.max(MIN_DIFFICULTY),
+			sent_difficulty: None,
+			jobs: HashMap::new(),
+			shares: HashSet::new(),
+			retarget_shares: 0,
+			retarget_time: Instant::now(),
+		}
+	}
+
+	/// Creates job from the new block template. Clean job invalidates all previous jobs.
+	pub fn update_job(&self, clean: bool) -> Arc<Job> {
+		let id = format!("{:x}", self.job_counter.fetch_add(1, Ordering::SeqCst));
+		let job = Arc::new(Job::new(id, self.core.block_template(), &self.config.coinbase_script));
+		let mut jobs = self.jobs.write();
+		if clean {
+			jobs.clear();
+		}
+		jobs.push_front(job.clone());
+		jobs.truncate(MAX_JOBS);
+		trace!(target: "stratum", "New job {} at height {}", job.id, job.height);
+		job
+	}
+
+	/// The newest job.
+	pub fn current_job(&self) -> Option<Arc<Job>> {
+		self.jobs.read().front().cloned()
+	}
+
+	fn job(&self, id: &str) -> Option<Arc<Job>> {
+		self.jobs.read().iter().find(|job| job.id == id).cloned()
+	}
+
+	/// Handles request line. Returns the response, followed by notifications.
+	pub fn handle(&self, session: &mut Session, request: &str) -> Vec<String> {
+		let request: Value = match serde_json::from_str(request) {
+			Ok(request) => request,
+			Err(_) => return vec![response(Value::Null, Err((ERROR_OTHER, "Invalid request")))],
+		};
+
+		let id = request.get("id").cloned().unwrap_or(Value::Null);
+		let params = match request.get("params") {
+			Some(&Value::Array(ref params)) => params.clone(),
+			_ => Vec::new(),
+		};
+		let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+		let result = match method {
+			"mining.subscribe" => {
+				session.subscribed = true;
+				let subscription = session.extranonce1.to_hex();
+				Ok(json!([
+					[["mining.set_difficulty", subscription], ["mining.notify", subscription]],
+					session.extranonce1.to_hex(),
+					EXTRANONCE2_SIZE,
+				]))
+			},
+			"mining.authorize" => {
+				let worker = params.get(0).and_then(Value::as_str).unwrap_or_default();
+				debug!(target: "stratum", "Worker {} is authorized", worker);
+				session.worker = Some(worker.to_owned());
+				Ok(Value::Bool(true))
+			},
+			"mining.extranonce.subscribe" => Ok(Value::Bool(true)),
+			"mining.suggest_difficulty" => match params.get(0).and_then(Value::as_f64) {
+				Some(difficulty) if difficulty > 0.0 => {
+					session.difficulty = difficulty.max(MIN_DIFFICULTY);
+					Ok(Value::Bool(true))
+				},
+				_ => Err((ERROR_OTHER, "Invalid difficulty")),
+			},
+			"mining.submit" => self.submit(session, &params).map(Value::Bool),
+			_ => Err((ERROR_OTHER, "Unknown method")),
+		};
+
+		let mut messages = vec![response(id, result)];
+		let job_required = match method {
+			"mining.subscribe" | "mining.authorize" => session.jobs.is_empty(),
+			"mining.suggest_difficulty" => session.sent_difficulty != Some(session.difficulty),
+			_ => false,
+		};
+		if job_required && session.is_ready() {
+			if let Some(job) = self.current_job() {
+				let clean = session.jobs.is_empty();
+				messages.extend(self.job_notifications(session, &job, clean));
+			}
+		}
+		messages.extend(self.retarget(session));
+		messages
+	}
+
+	/// Notifications, which send the job to the miner, preceded by the new difficulty.
+	pub fn job_notifications(&self, session: &mut Session, job: &Job, clean: bool) -> Vec<String> {
+		if clean {
+			session.jobs.clear();
+			session.shares.clear();
+		} else {
+			let jobs = self.jobs.read();
+			session.jobs.retain(|id, _| jobs.iter().any(|job| &job.id == id));
+			session.shares.retain(|share| jobs.iter().any(|job| job.id == share.0));
+		}
+
+		let mut messages = Vec::new();
+		if session.sent_difficulty != Some(session.difficulty) {
+			messages.push(notification("mining.set_difficulty", json!([session.difficulty])));
+			session.sent_difficulty = Some(session.difficulty);
+		}
+		session.jobs.insert(job.id.clone(), session.difficulty);
+		messages.push(notification("mining.notify", job.notify_params(clean)));
+		messages
+	}
+
+	/// Adjusts the difficulty of the next job to the rate of shares. Returns notifications
+	/// with the current job if difficulty has been changed.
+	pub fn retarget(&self, session: &mut Session) -> Vec<String> {
+		let elapsed = session.retarget_time.elapsed();
+		let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+		// difficulty is increased early, if shares are submitted too often
+		let max_shares = self.config.shares_per_minute * RETARGET_INTERVAL_SECS as f64 / 60.0 * MAX_RETARGET_FACTOR;
+		if !session.is_ready() || elapsed <= 0.0 || (elapsed < RETARGET_INTERVAL_SECS as f64 && (session.retarget_shares as f64) < max_shares) {
+			return Vec::new();
+		}
+
+		let shares_per_minute = session.retarget_shares as f64 * 60.0 / elapsed;
+		let factor = (shares_per_minute / self.config.shares_per_minute).max(1.0 / MAX_RETARGET_FACTOR).min(MAX_RETARGET_FACTOR);
+		session.retarget_shares = 0;
+		session.retarget_time = Instant::now();
+
+		let difficulty = (session.difficulty * factor).max(MIN_DIFFICULTY);
+		if (difficulty / session.difficulty - 1.0).abs() < RETARGET_THRESHOLD {
+			return Vec::new();
+		}
+
+		trace!(target: "stratum", "Difficulty of worker {:?} is changed from {} to {}", session.worker, session.difficulty, difficulty);
+		session.difficulty = difficulty;
+		match self.current_job() {
+			Some(job) => self.job_notifications(session, &job, false),
+			None => Vec::new(),
+		}
+	}
+
+	/// Checks the share and submits the block, if the share is its solution.
+	fn submit(&self, session: &mut Session, params: &[Value]) -> Result<bool, (u32, &'static str)> {
+		if !session.subscribed {
+			return Err((ERROR_NOT_SUBSCRIBED, "Not subscribed"));
+		}
+		if session.worker.is_none() {
+			return Err((ERROR_UNAUTHORIZED, "Unauthorized worker"));
+		}
+
+		let param = |index: usize| params.get(index).and_then(Value::as_str);
+		let (job_id, extranonce2, time, nonce) = match (param(1), param(2).and_then(parse_extranonce2), param(3).and_then(parse_u32), param(4).and_then(parse_u32)) {
+			(Some(job_id), Some(extranonce2), Some(time), Some(nonce)) => (job_id, extranonce2, time, nonce),
+			_ => return Err((ERROR_OTHER, "Invalid share")),
+		};
+
+		let (job, difficulty) = match (self.job(job_id), session.jobs.get(job_id)) {
+			(Some(job), Some(difficulty)) => (job, *difficulty),
+			_ => return Err((ERROR_JOB_NOT_FOUND, "Job not found")),
+		};
+		if !job.is_valid_time(time) {
+			return Err((ERROR_OTHER, "Invalid time"));
+		}
+
+		let share = (job.id.clone(), extranonce2, time, nonce);
+		if session.shares.contains(&share) {
+			return Err((ERROR_DUPLICATE_SHARE, "Duplicate share"));
+		}
+
+		let block = job.block(&session.extranonce1, &share.1, time, nonce);
+		let hash = block.hash().clone();
+		let pow_hash = self.consensus.proof_of_work.pow_hash(&block.header);
+		if is_valid_proof_of_work(self.consensus.pow_limit, job.bits, &pow_hash) {
+			match self.core.submit_block(block) {
+				None => info!(target: "stratum", "Block {} at height {} is mined by worker {:?}", hash.to_reversed_str(), job.height, session.worker),
+				Some(reason) => warn!(target: "stratum", "Block {} at height {}, mined by worker {:?}, is rejected: {}", hash.to_reversed_str(), job.height, session.worker, reason),
+			}
+		} else if hash_value(&pow_hash) > difficulty_target(difficulty) {
+			return Err((ERROR_LOW_DIFFICULTY, "Low difficulty share"));
+		}
+
+		session.shares.insert(share);
+		session.retarget_shares += 1;
+		Ok(true)
+	}
+}
+
+fn response(id: Value, result: Result<Value, (u32, &'static str)>) -> String {
+	let response = match result {
+		Ok(result) => json!({"id": id, "result": result, "error": null}),
+		Err((code, message)) => json!({"id": id, "result": null, "error": [code, message, null]}),
+	};
+	response.to_string()
+}
+
+fn notification(method: &str, params: Value) -> String {
+	json!({"id": null, "method": method, "params": params}).to_string()
+}
+
+fn parse_extranonce2(s: &str) -> Option<Bytes> {
+	match s.from_hex() {
+		Ok(ref extranonce2) if extranonce2.len() == EXTRANONCE2_SIZE => Some(extranonce2.clone().into()),
+		_ => None,
+	}
+}
+
+/// Parses big-endian hex number, as `ntime` and `nonce` are encoded.
+fn parse_u32(s: &str) -> Option<u32> {
+	if s.len() != 8 {
+		return None;
+	}
+
+	u32::from_str_radix(s, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use parking_lot::Mutex;
+	use serde_json::{self, Value};
+	use primitives::hex::ToHex;
+	use chain::IndexedBlock;
+	use network::{ConsensusParams, ConsensusFork, Magic};
+	use verification::is_valid_proof_of_work;
+	use block_assembler::BlockTemplate;
+	use super::super::job::tests::block_template;
+	use super::{StratumHandler, StratumCoreApi, StratumConfig, Session};
+
+	#[derive(Default)]
+	struct TestCore {
+		blocks: Arc<Mutex<Vec<IndexedBlock>>>,
+	}
+
+	impl StratumCoreApi for TestCore {
+		fn block_template(&self) -> BlockTemplate {
+			block_template(3, true)
+		}
+
+		fn submit_block(&self, block: IndexedBlock) -> Option<String> {
+			self.blocks.lock().push(block);
+			None
+		}
+	}
+
+	fn handler(difficulty: f64) -> (StratumHandler<TestCore>, Arc<Mutex<Vec<IndexedBlock>>>) {
+		let core = TestCore::default();
+		let blocks = core.blocks.clone();
+		let handler = StratumHandler::new(core, StratumConfig {
+			address: "127.0.0.1:3333".parse().unwrap(),
+			coinbase_script: "51".into(),
+			difficulty: difficulty,
+			shares_per_minute: 20.0,
+		}, ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork));
+		handler.update_job(true);
+		(handler, blocks)
+	}
+
+	fn messages(handler: &StratumHandler<TestCore>, session: &mut Session, request: &str) -> Vec<Value> {
+		handler.handle(session, request).into_iter()
+			.map(|message| serde_json::from_str(&message).unwrap())
+			.collect()
+	}
+
+	/// Finds nonce, which is (or isn't) the solution of the regtest block.
+	fn find_nonce(handler: &StratumHandler<TestCore>, session: &Session, solution: bool) -> String {
+		let job = handler.current_job().unwrap();
+		let nonce = (0u32..).find(|nonce| {
+			let block = job.block(&session.extranonce1, &[0, 0, 0, 1], 1_500_000_000, *nonce);
+			let pow_hash = handler.consensus.proof_of_work.pow_hash(&block.header);
+			is_valid_proof_of_work(handler.consensus.pow_limit, job.bits, &pow_hash) == solution
+		}).unwrap();
+		format!("{:08x}", nonce)
+	}
+
+	fn subscribed_session(handler: &StratumHandler<TestCore>) -> Session {
+		let mut session = handler.new_session();
+		let messages = messages(handler, &mut session, r#"{"id":1,"method":"mining.subscribe","params":["miner/1.0"]}"#);
+		assert_eq!(messages.len(), 1);
+		assert_eq!(messages[0], json!({"id":1,"result":[[["mining.set_difficulty","00000000"],["mining.notify","00000000"]],"00000000",4],"error":null}));
+		session
+	}
+
+	#[test]
+	fn stratum_subscribe_and_authorize() {
+		let (handler, _) = handler(2.0);
+		let mut session = subscribed_session(&handler);
+
+		let messages = messages(&handler, &mut session, r#"{"id":2,"method":"mining.authorize","params":["worker","x"]}"#);
+		assert_eq!(messages.len(), 3);
+		assert_eq!(messages[0], json!({"id":2,"result":true,"error":null}));
+		assert_eq!(messages[1], json!({"id":null,"method":"mining.set_difficulty","params":[2.0]}));
+		assert_eq!(messages[2]["method"], json!("mining.notify"));
+		let params = &messages[2]["params"];
+		assert_eq!(params[0], json!("0"));
+		assert_eq!(params[1], json!("0000000100000000000000000000000000000000000000000000000000000000"));
+		assert_eq!(params[4].as_array().unwrap().len(), 2);
+		assert_eq!(params[5], json!("20000000"));
+		assert_eq!(params[6], json!("207fffff"));
+		assert_eq!(params[7], json!(format!("{:08x}", 1_500_000_000)));
+		assert_eq!(params[8], json!(true));
+
+		// second subscription gets another extranonce
+		assert_eq!(handler.new_session().extranonce1.to_hex(), "00000001");
+	}
+
+	#[test]
+	fn stratum_submit() {
+		let (handler, blocks) = handler(1.0);
+		let mut session = subscribed_session(&handler);
+		let solution = find_nonce(&handler, &session, true);
+		let submit = format!(r#"{{"id":3,"method":"mining.submit","params":["worker","0","00000001","{:08x}","{}"]}}"#, 1_500_000_000, solution);
+
+		// shares are accepted after authorization
+		assert_eq!(messages(&handler, &mut session, &submit), vec![json!({"id":3,"result":null,"error":[24,"Unauthorized worker",null]})]);
+		messages(&handler, &mut session, r#"{"id":2,"method":"mining.authorize","params":["worker","x"]}"#);
+
+		assert_eq!(messages(&handler, &mut session, &submit), vec![json!({"id":3,"result":true,"error":null})]);
+		assert_eq!(blocks.lock().len(), 1);
+		assert_eq!(blocks.lock()[0].header.raw.nonce, u32::from_str_radix(&solution, 16).unwrap());
+		assert_eq!(messages(&handler, &mut session, &submit), vec![json!({"id":3,"result":null,"error":[22,"Duplicate share",null]})]);
+
+		// hash of the share, which isn't the block solution, is above the difficulty 1 target
+		let nonce = find_nonce(&handler, &session, false);
+		let low_difficulty = format!(r#"{{"id":4,"method":"mining.submit","params":["worker","0","00000001","{:08x}","{}"]}}"#, 1_500_000_000, nonce);
+		assert_eq!(messages(&handler, &mut session, &low_difficulty), vec![json!({"id":4,"result":null,"error":[23,"Low difficulty share",null]})]);
+
+		let invalid_time = format!(r#"{{"id":5,"method":"mining.submit","params":["worker","0","00000001","00000001","{}"]}}"#, solution);
+		assert_eq!(messages(&handler, &mut session, &invalid_time), vec![json!({"id":5,"result":null,"error":[20,"Invalid time",null]})]);
+
+		let invalid_extranonce = format!(r#"{{"id":6,"method":"mining.submit","params":["worker","0","01","{:08x}","{}"]}}"#, 1_500_000_000, solution);
+		assert_eq!(messages(&handler, &mut session, &invalid_extranonce), vec![json!({"id":6,"result":null,"error":[20,"Invalid share",null]})]);
+
+		// clean job invalidates previous jobs
+		let job = handler.update_job(true);
+		assert_eq!(job.id, "1");
+		assert_eq!(handler.job_notifications(&mut session, &job, true).len(), 1);
+		assert_eq!(messages(&handler, &mut session, &submit), vec![json!({"id":3,"result":null,"error":[21,"Job not found",null]})]);
+		assert_eq!(blocks.lock().len(), 1);
+	}
+
+	#[test]
+	fn stratum_suggest_difficulty() {
+		let (handler, _) = handler(1.0);
+		let mut session = subscribed_session(&handler);
+		messages(&handler, &mut session, r#"{"id":2,"method":"mining.authorize","params":["worker","x"]}"#);
+
+		let messages = messages(&handler, &mut session, r#"{"id":3,"method":"mining.suggest_difficulty","params":[0.00001]}"#);
+		assert_eq!(messages.len(), 3);
+		assert_eq!(messages[0], json!({"id":3,"result":true,"error":null}));
+		assert_eq!(messages[1], json!({"id":null,"method":"mining.set_difficulty","params":[0.001]}));
+		assert_eq!(messages[2]["params"][8], json!(false));
+		assert_eq!(session.jobs.get("0"), Some(&0.001));
+	}
+
+	#[test]
+	fn stratum_invalid_requests() {
+		let (handler, _) = handler(1.0);
+		let mut session = handler.new_session();
+		assert_eq!(messages(&handler, &mut session, "{"), vec![json!({"id":null,"result":null,"error":[20,"Invalid request",null]})]);
+		assert_eq!(messages(&handler, &mut session, r#"{"id":1,"method":"mining.unknown","params":[]}"#), vec![json!({"id":1,"result":null,"error":[20,"Unknown method",null]})]);
+		assert_eq!(messages(&handler, &mut session, r#"{"id":2,"method":"mining.submit","params":[]}"#), vec![json!({"id":2,"result":null,"error":[25,"Not subscribed",null]})]);
+	}
+}
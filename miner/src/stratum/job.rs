@@ -0,0 +1,300 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::Value;
+use primitives::bigint::U256;
+use primitives::bytes::Bytes;
+use primitives::compact::Compact;
+use primitives::hash::H256;
+use primitives::hex::ToHex;
+use chain::{BlockHeader, Transaction, TransactionInput, TransactionOutput, OutPoint, IndexedBlock,
+	IndexedTransaction, merkle_branch, merkle_node_hash};
+use crypto::dhash256;
+use script::Builder as ScriptBuilder;
+use ser::{Reader, serialize, deserialize};
+use verification::constants::BLOCK_MAX_FUTURE;
+use block_assembler::{BlockTemplate, WITNESS_RESERVED_VALUE};
+
+/// Size of the extranonce, assigned to the connection by the server.
+pub const EXTRANONCE1_SIZE: usize = 4;
+/// Size of the extranonce, rolled by the miner.
+pub const EXTRANONCE2_SIZE: usize = 4;
+/// Text, appended to the coinbase script.
+const COINBASE_TAG: &'static [u8] = b"/pbtc/";
+/// Size of the coinbase transaction fields before the script: version, inputs count, previous output and script length.
+const COINBASE_SCRIPT_OFFSET: usize = 4 + 1 + 36 + 1;
+/// Compact target of the share with difficulty 1.
+const DIFFICULTY_1_BITS: u32 = 0x1d00ffff;
+
+/// Mining job: block template, which coinbase transaction is split around the extranonce.
+pub struct Job {
+	/// Job identifier
+	pub id: String,
+	/// Height of the block
+	pub height: u32,
+	/// Block version
+	version: u32,
+	/// Hash of the previous block
+	previous_header_hash: H256,
+	/// Compact target of the block
+	pub bits: Compact,
+	/// Block time, proposed to the miner
+	time: u32,
+	/// The minimal time of the block
+	min_time: u32,
+	/// Coinbase transaction bytes before the extranonce
+	coinbase_prefix: Bytes,
+	/// Coinbase transaction bytes after the extranonce
+	coinbase_suffix: Bytes,
+	/// Hashes, required to compute the merkle root from the coinbase transaction hash
+	merkle_branch: Vec<H256>,
+	/// Block transactions (excluding coinbase)
+	transactions: Vec<IndexedTransaction>,
+	/// Is coinbase witness reserved value required?
+	segwit: bool,
+}
+
+impl Job {
+	/// Creates job, which pays the block reward to the given output script.
+	pub fn new(id: String, template: BlockTemplate, coinbase_script: &Bytes) -> Self {
+		let height_script = ScriptBuilder::default()
+			.push_num(template.height.into())
+			.into_bytes();
+		let script_sig = ScriptBuilder::default()
+			.push_num(template.height.into())
+			.push_data(&[0u8; EXTRANONCE1_SIZE + EXTRANONCE2_SIZE])
+			.push_data(COINBASE_TAG)
+			.into_bytes();
+
+		let mut outputs = vec![TransactionOutput {
+			value: template.coinbase_value,
+			script_pubkey: coinbase_script.clone(),
+		}];
+		if let Some(ref commitment) = template.default_witness_commitment {
+			outputs.push(TransactionOutput {
+				value: 0,
+				script_pubkey: commitment.clone(),
+			});
+		}
+
+		let coinbase = serialize(&Transaction {
+			version: 1,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint::null(),
+				script_sig: script_sig,
+				sequence: 0xffffffff,
+				script_witness: vec![],
+			}],
+			outputs: outputs,
+			lock_time: 0,
+		});
+
+		// script is shorter than 0xfd bytes, so its length is a single byte
+		// extranonce follows the height and the push opcode
+		let extranonce_offset = COINBASE_SCRIPT_OFFSET + height_script.len() + 1;
+		let coinbase_prefix: Bytes = coinbase[..extranonce_offset].to_vec().into();
+		let coinbase_suffix: Bytes = coinbase[extranonce_offset + EXTRANONCE1_SIZE + EXTRANONCE2_SIZE..].to_vec().into();
+
+		// branch doesn't depend on the coinbase hash
+		let mut hashes = vec![H256::default()];
+		hashes.extend(template.transactions.iter().map(|tx| tx.transaction.hash.clone()));
+		let merkle_branch = merkle_branch(&hashes, 0);
+
+		Job {
+			id: id,
+			height: template.height,
+			version: template.version,
+			previous_header_hash: template.previous_header_hash,
+			bits: template.bits,
+			time: template.time,
+			min_time: template.min_time,
+			coinbase_prefix: coinbase_prefix,
+			coinbase_suffix: coinbase_suffix,
+			merkle_branch: merkle_branch,
+			transactions: template.transactions.into_iter().map(|tx| tx.transaction).collect(),
+			segwit: template.default_witness_commitment.is_some(),
+		}
+	}
+
+	/// Parameters of the `mining.notify` notification.
+	pub fn notify_params(&self, clean_jobs: bool) -> Value {
+		json!([
+			self.id,
+			stratum_hash(&self.previous_header_hash),
+			self.coinbase_prefix.to_hex(),
+			self.coinbase_suffix.to_hex(),
+			self.merkle_branch.iter().map(|hash| hash.to_hex()).collect::<Vec<_>>(),
+			format!("{:08x}", self.version),
+			format!("{:08x}", u32::from(self.bits)),
+			format!("{:08x}", self.time),
+			clean_jobs,
+		])
+	}
+
+	/// Checks if the time, chosen by the miner, is valid for the block.
+	pub fn is_valid_time(&self, time: u32) -> bool {
+		let current_time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs() as u32).unwrap_or_default();
+		time >= self.min_time && time <= current_time.max(self.time) + BLOCK_MAX_FUTURE as u32
+	}
+
+	/// Builds the block from the miner solution.
+	pub fn block(&self, extranonce1: &[u8], extranonce2: &[u8], time: u32, nonce: u32) -> IndexedBlock {
+		let mut coinbase_data = Vec::with_capacity(self.coinbase_prefix.len() + EXTRANONCE1_SIZE + EXTRANONCE2_SIZE + self.coinbase_suffix.len());
+		coinbase_data.extend_from_slice(&self.coinbase_prefix);
+		coinbase_data.extend_from_slice(extranonce1);
+		coinbase_data.extend_from_slice(extranonce2);
+		coinbase_data.extend_from_slice(&self.coinbase_suffix);
+
+		let coinbase_hash = dhash256(&coinbase_data);
+		let mut coinbase: Transaction = deserialize(Reader::new(&coinbase_data))
+			.expect("coinbase is serialized by the job, extranonces have fixed sizes; qed");
+		if self.segwit {
+			coinbase.inputs[0].script_witness = vec![WITNESS_RESERVED_VALUE.to_vec().into()];
+		}
+
+		let merkle_root_hash = self.merkle_branch.iter()
+			.fold(coinbase_hash.clone(), |hash, sibling| merkle_node_hash(&hash, sibling));
+		let header = BlockHeader {
+			version: self.version,
+			previous_header_hash: self.previous_header_hash.clone(),
+			merkle_root_hash: merkle_root_hash,
+			time: time,
+			bits: self.bits,
+			nonce: nonce,
+		};
+
+		let mut transactions = Vec::with_capacity(self.transactions.len() + 1);
+		transactions.push(IndexedTransaction::new(coinbase_hash, coinbase));
+		transactions.extend(self.transactions.iter().cloned());
+		IndexedBlock::new(header.into(), transactions)
+	}
+}
+
+/// Target of the share with given difficulty. Difficulty 1 corresponds to the `0x1d00ffff` compact target.
+pub fn difficulty_target(difficulty: f64) -> U256 {
+	let difficulty_1_target = Compact::new(DIFFICULTY_1_BITS).to_u256()
+		.expect("DIFFICULTY_1_BITS is a valid compact target; qed");
+	// difficulty is scaled by 2^32 to support fractional difficulties. Scaled target is below 2^256,
+	// since the difficulty 1 target is below 2^224
+	let scaled_difficulty = (difficulty * 4294967296.0).max(1.0).min(u64::max_value() as f64) as u64;
+	(difficulty_1_target << 32) / U256::from(scaled_difficulty)
+}
+
+/// Hash value, compared with the target.
+pub fn hash_value(hash: &H256) -> U256 {
+	U256::from(&*hash.reversed() as &[u8])
+}
+
+/// Stratum encoding of the previous block hash: every 4-byte word of the hash is reversed.
+fn stratum_hash(hash: &H256) -> String {
+	let mut result = Vec::with_capacity(32);
+	for word in hash.chunks(4) {
+		result.extend(word.iter().rev());
+	}
+	result.to_hex()
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::collections::HashMap;
+	use primitives::bytes::Bytes;
+	use primitives::compact::Compact;
+	use primitives::hash::H256;
+	use chain::{Transaction, TransactionInput, OutPoint, merkle_root};
+	use script::Builder as ScriptBuilder;
+	use block_assembler::{BlockTemplate, BlockTemplateTransaction};
+	use super::{Job, EXTRANONCE1_SIZE, EXTRANONCE2_SIZE, difficulty_target, stratum_hash};
+
+	fn transaction(index: u8) -> BlockTemplateTransaction {
+		let transaction = Transaction {
+			version: 1,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint { hash: H256::from(index), index: 0 },
+				script_sig: Bytes::default(),
+				sequence: 0xffffffff,
+				script_witness: vec![],
+			}],
+			outputs: vec![],
+			lock_time: 0,
+		};
+
+		BlockTemplateTransaction {
+			transaction: transaction.into(),
+			fee: 0,
+			sigops: 0,
+			weight: 0,
+			depends: vec![],
+		}
+	}
+
+	pub fn block_template(transactions: u8, segwit: bool) -> BlockTemplate {
+		BlockTemplate {
+			version: 0x20000000,
+			previous_header_hash: H256::from(1),
+			time: 1_500_000_000,
+			min_time: 1_400_000_000,
+			bits: Compact::new(0x207fffff),
+			height: 300,
+			transactions: (0..transactions).map(transaction).collect(),
+			coinbase_value: 50_0000_0000,
+			size_limit: 1_000_000,
+			sigop_limit: 20_000,
+			weight_limit: None,
+			rules: vec![],
+			vbavailable: HashMap::new(),
+			default_witness_commitment: if segwit { Some("6a24aa21a9ed00".into()) } else { None },
+		}
+	}
+
+	#[test]
+	fn test_job_block() {
+		for transactions in 0..6 {
+			let template = block_template(transactions, transactions % 2 == 0);
+			let mut hashes = vec![H256::default()];
+			hashes.extend(template.transactions.iter().map(|tx| tx.transaction.hash.clone()));
+			let job = Job::new("1".into(), template, &"51".into());
+
+			let block = job.block(&[1; EXTRANONCE1_SIZE], &[2; EXTRANONCE2_SIZE], 1_500_000_001, 5);
+			hashes[0] = block.transactions[0].hash.clone();
+			assert_eq!(block.header.raw.merkle_root_hash, merkle_root(&hashes));
+			assert_eq!(block.header.raw.merkle_root_hash, block.merkle_root());
+			assert_eq!(block.header.raw.time, 1_500_000_001);
+			assert_eq!(block.header.raw.nonce, 5);
+
+			let coinbase = &block.transactions[0].raw;
+			assert!(coinbase.is_coinbase());
+			let expected_script_sig = ScriptBuilder::default()
+				.push_num(300u32.into())
+				.push_data(&[1, 1, 1, 1, 2, 2, 2, 2])
+				.push_data(b"/pbtc/")
+				.into_bytes();
+			assert_eq!(coinbase.inputs[0].script_sig, expected_script_sig);
+			assert_eq!(coinbase.outputs[0].value, 50_0000_0000);
+			assert_eq!(coinbase.outputs[0].script_pubkey, "51".into());
+			assert_eq!(coinbase.outputs.len(), if transactions % 2 == 0 { 2 } else { 1 });
+			assert_eq!(coinbase.has_witness(), transactions % 2 == 0);
+		}
+	}
+
+	#[test]
+	fn test_job_time() {
+		let job = Job::new("1".into(), block_template(0, false), &"51".into());
+		assert!(!job.is_valid_time(1_399_999_999));
+		assert!(job.is_valid_time(1_400_000_000));
+		assert!(job.is_valid_time(1_500_000_000));
+		assert!(!job.is_valid_time(u32::max_value()));
+	}
+
+	#[test]
+	fn test_difficulty_target() {
+		let difficulty_1_target = Compact::new(0x1d00ffff).to_u256().unwrap();
+		assert_eq!(difficulty_target(1.0), difficulty_1_target);
+		assert_eq!(difficulty_target(256.0), difficulty_1_target >> 8);
+		assert_eq!(difficulty_target(0.5), difficulty_1_target << 1);
+	}
+
+	#[test]
+	fn test_stratum_hash() {
+		// block 125552
+		let hash = H256::from_reversed_str("00000000000008a3a41b85b8b29ad444def299fee21793cd8b9e567eab02cd81");
+		assert_eq!(stratum_hash(&hash), "ab02cd818b9e567ee21793cddef299feb29ad444a41b85b8000008a300000000");
+	}
+}
@@ -0,0 +1,16 @@
+//! Stratum V1 mining server.
+//!
+//! Miners receive jobs, built from the block template, with the coinbase transaction, paying
+//! to the configured output script. Every connection is assigned unique extranonce, so miners
+//! never do the same work. Share difficulty of every connection is adjusted to keep the
+//! configured rate of shares. Shares, which are solutions of the block, are submitted to the node.
+//!
+//! New job is sent when the best block changes and periodically, to include new memory pool
+//! transactions.
+
+mod handler;
+mod job;
+mod server;
+
+pub use self::handler::{StratumConfig, StratumCoreApi};
+pub use self::server::{StratumServer, StratumNotifier};
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use network::ConsensusParams;
+use super::handler::{StratumHandler, StratumCoreApi, StratumConfig, Session};
+
+/// How often the server checks if it should be stopped or the job should be updated.
+const ACCEPT_INTERVAL_MS: u64 = 100;
+/// How often connections check if the server should be stopped.
+const READ_INTERVAL_MS: u64 = 1000;
+/// Time to wait for the response or notification to be written. Miners, which don't read them, are disconnected.
+const WRITE_TIMEOUT_MS: u64 = 5000;
+/// Miners, which haven't sent any request for this time, are disconnected.
+const IDLE_TIMEOUT_MS: u64 = 600_000;
+/// Maximal size of the single request line.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+/// Maximal number of concurrently served connections.
+const MAX_CONNECTIONS: usize = 256;
+/// Job with new memory pool transactions is sent after this interval.
+const JOB_REFRESH_INTERVAL_SECS: u64 = 30;
+
+/// Connected miner.
+struct Client {
+	stream: Mutex<TcpStream>,
+	session: Mutex<Session>,
+}
+
+impl Client {
+	/// Sends newline-terminated messages to the miner.
+	fn send(&self, messages: &[String]) -> Result<(), io::Error> {
+		let mut stream = self.stream.lock();
+		for message in messages {
+			stream.write_all(message.as_bytes())?;
+			stream.write_all(b"\n")?;
+		}
+		stream.flush()
+	}
+
+	fn disconnect(&self) {
+		let _ = self.stream.lock().shutdown(Shutdown::Both);
+	}
+}
+
+type Clients = Mutex<HashMap<usize, Arc<Client>>>;
+
+/// Notifies the Stratum server about the new best block.
+#[derive(Clone)]
+pub struct StratumNotifier {
+	new_block: Arc<AtomicBool>,
+}
+
+impl StratumNotifier {
+	/// Miners start working on the new job, built on top of the new best block.
+	pub fn notify_new_block(&self) {
+		self.new_block.store(true, Ordering::SeqCst);
+	}
+}
+
+/// Stratum V1 mining server, serving newline-delimited JSON-RPC requests over plain TCP.
+pub struct StratumServer {
+	new_block: Arc<AtomicBool>,
+	stop: Arc<AtomicBool>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StratumServer {
+	pub fn start<T>(core: T, config: StratumConfig, consensus: ConsensusParams) -> Result<Self, String> where T: StratumCoreApi {
+		let address = config.address;
+		let listener = TcpListener::bind(address)
+			.and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+			.map_err(|err| format!("Failed to start Stratum server at {}: {}", address, err))?;
+		let handler = Arc::new(StratumHandler::new(core, config, consensus));
+		handler.update_job(true);
+
+		let new_block = Arc::new(AtomicBool::new(false));
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread_new_block = new_block.clone();
+		let thread_stop = stop.clone();
+		let thread = thread::Builder::new()
+			.name("Stratum server".to_owned())
+			.spawn(move || StratumServer::worker(listener, handler, thread_new_block, thread_stop))
+			.map_err(|err| format!("Failed to start Stratum server thread: {}", err))?;
+
+		info!(target: "stratum", "Stratum server is listening on {}", address);
+		Ok(StratumServer {
+			new_block: new_block,
+			stop: stop,
+			thread: Some(thread),
+		})
+	}
+
+	pub fn notifier(&self) -> StratumNotifier {
+		StratumNotifier {
+			new_block: self.new_block.clone(),
+		}
+	}
+
+	fn worker<T>(listener: TcpListener, handler: Arc<StratumHandler<T>>, new_block: Arc<AtomicBool>, stop: Arc<AtomicBool>) where T: StratumCoreApi {
+		let clients: Arc<Clients> = Arc::default();
+		let client_counter = AtomicUsize::new(0);
+		let mut job_time = Instant::now();
+		while !stop.load(Ordering::SeqCst) {
+			if new_block.swap(false, Ordering::SeqCst) {
+				broadcast_job(&handler, &clients, true);
+				job_time = Instant::now();
+			} else if job_time.elapsed() >= Duration::from_secs(JOB_REFRESH_INTERVAL_SECS) {
+				broadcast_job(&handler, &clients, false);
+				job_time = Instant::now();
+			}
+
+			match listener.accept() {
+				Ok((stream, peer_address)) => {
+					if clients.lock().len() >= MAX_CONNECTIONS {
+						debug!(target: "stratum", "Stratum server rejected connection from {}: too many connections", peer_address);
+						continue;
+					}
+
+					let id = client_counter.fetch_add(1, Ordering::SeqCst);
+					let handler = handler.clone();
+					let clients = clients.clone();
+					let stop = stop.clone();
+					let spawn_result = thread::Builder::new()
+						.name("Stratum connection".to_owned())
+						.spawn(move || {
+							if let Err(err) = handle_connection(id, stream, &handler, &clients, &stop) {
+								debug!(target: "stratum", "Stratum connection from {} failed: {}", peer_address, err);
+							}
+							clients.lock().remove(&id);
+						});
+					if let Err(err) = spawn_result {
+						warn!(target: "stratum", "Failed to start Stratum connection thread: {}", err);
+					}
+				},
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(ACCEPT_INTERVAL_MS)),
+				Err(err) => warn!(target: "stratum", "Stratum server failed to accept connection: {}", err),
+			}
+		}
+	}
+}
+
+impl Drop for StratumServer {
+	fn drop(&mut self) {
+		if let Some(thread) = self.thread.take() {
+			self.stop.store(true, Ordering::SeqCst);
+			thread.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+/// Creates the new job and sends it to all miners.
+fn broadcast_job<T>(handler: &StratumHandler<T>, clients: &Clients, clean: bool) where T: StratumCoreApi {
+	let job = handler.update_job(clean);
+	let clients: Vec<_> = clients.lock().values().cloned().collect();
+	for client in clients {
+		let messages = {
+			let mut session = client.session.lock();
+			if !session.is_ready() {
+				continue;
+			}
+			handler.job_notifications(&mut session, &job, clean)
+		};
+		if client.send(&messages).is_err() {
+			client.disconnect();
+		}
+	}
+}
+
+fn handle_connection<T>(id: usize, stream: TcpStream, handler: &StratumHandler<T>, clients: &Clients, stop: &AtomicBool) -> Result<(), io::Error> where T: StratumCoreApi {
+	stream.set_nonblocking(false)?;
+	stream.set_write_timeout(Some(Duration::from_millis(WRITE_TIMEOUT_MS)))?;
+	// read timeout is shared by both handles of the socket, it only affects reads
+	let mut reader = stream.try_clone()?;
+	reader.set_read_timeout(Some(Duration::from_millis(READ_INTERVAL_MS)))?;
+	let client = Arc::new(Client {
+		stream: Mutex::new(stream),
+		session: Mutex::new(handler.new_session()),
+	});
+
+	clients.lock().insert(id, client.clone());
+	let result = session(&mut reader, &client, handler, stop);
+	client.disconnect();
+	result
+}
+
+fn session<T>(reader: &mut TcpStream, client: &Client, handler: &StratumHandler<T>, stop: &AtomicBool) -> Result<(), io::Error> where T: StratumCoreApi {
+	let mut data = Vec::new();
+	let mut buffer = [0u8; 4096];
+	let mut last_request = Instant::now();
+	loop {
+		while let Some(position) = data.iter().position(|byte| *byte == b'\n') {
+			let line: Vec<u8> = data.drain(..position + 1).collect();
+			let request = String::from_utf8_lossy(&line[..position]);
+			let request = request.trim();
+			if !request.is_empty() {
+				let messages = handler.handle(&mut client.session.lock(), request);
+				client.send(&messages)?;
+				last_request = Instant::now();
+			}
+		}
+
+		if data.len() > MAX_REQUEST_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "request is too large"));
+		}
+
+		if stop.load(Ordering::SeqCst) {
+			return Ok(());
+		}
+
+		if last_request.elapsed() > Duration::from_millis(IDLE_TIMEOUT_MS) {
+			return Err(io::Error::new(io::ErrorKind::TimedOut, "miner is idle"));
+		}
+
+		// difficulty is lowered, when the miner can't find shares
+		let messages = handler.retarget(&mut client.session.lock());
+		if !messages.is_empty() {
+			client.send(&messages)?;
+		}
+
+		match reader.read(&mut buffer) {
+			Ok(0) => return Ok(()),
+			Ok(read) => data.extend_from_slice(&buffer[..read]),
+			Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => (),
+			Err(err) => return Err(err),
+		}
+	}
+}
@@ -1,10 +1,26 @@
+use std::sync::Arc;
 use hash::H256;
-use {Magic, Deployment};
+use bytes::Bytes;
+use compact::Compact;
+use chain::constants::WITNESS_SCALE_FACTOR;
+use {Magic, Deployment, ProofOfWork, Sha256dProofOfWork};
 
-/// First block of SegWit2x fork.
-pub const SEGWIT2X_FORK_BLOCK: u32 = 494784; // https://segwit2x.github.io/segwit2x-announce.html
 /// First block of BitcoinCash fork.
 pub const BITCOIN_CASH_FORK_BLOCK: u32 = 478559; // https://blockchair.com/bitcoin-cash/block/478559
+/// First block, which difficulty is adjusted using BitcoinCash cw-144 algorithm (mainnet).
+pub const BITCOIN_CASH_DAA_MAINNET_BLOCK: u32 = 504032;
+/// First block, which difficulty is adjusted using BitcoinCash cw-144 algorithm (testnet).
+pub const BITCOIN_CASH_DAA_TESTNET_BLOCK: u32 = 1188698;
+/// First block of BitcoinCash fork (testnet).
+pub const BITCOIN_CASH_TESTNET_FORK_BLOCK: u32 = 1155876;
+/// Minimal size of BitcoinCash transaction after Magnetic Anomaly upgrade.
+pub const BITCOIN_CASH_MIN_TRANSACTION_SIZE: usize = 100;
+/// Maximal number of signature checks in single BitcoinCash transaction after Phonon upgrade.
+pub const BITCOIN_CASH_MAX_TRANSACTION_SIG_CHECKS: usize = 3_000;
+/// Maximal number of signature checks in BitcoinCash block is (block size limit / this ratio).
+pub const BITCOIN_CASH_BLOCK_SIZE_SIG_CHECKS_RATIO: usize = 141;
+/// Block challenge of the default signet (1-of-2 multisig).
+pub const DEFAULT_SIGNET_CHALLENGE: &'static str = "512103ad5e0edad18cb1f0fc0d28a3d4f1f3e445640337489abb10404f2d1e086be430210359ef5021964fe22d6f8e05b2463c9540ce96883fe3b278760f048f5189f2e6c452ae";
 
 #[derive(Debug, Clone)]
 /// Parameters that influence chain consensus.
@@ -33,6 +49,32 @@ pub struct ConsensusParams {
 	pub csv_deployment: Option<Deployment>,
 	/// BIP141, BIP143, BIP147 deployment
 	pub segwit_deployment: Option<Deployment>,
+	/// BIP340, BIP341, BIP342 deployment
+	pub taproot_deployment: Option<Deployment>,
+	/// Additional deployments, defined by the node configuration (test networks only)
+	pub custom_deployments: Vec<Deployment>,
+	/// Proof-of-work function.
+	pub proof_of_work: Arc<ProofOfWork>,
+	/// Easiest allowed proof of work.
+	pub pow_limit: Compact,
+	/// Block may be mined with minimal difficulty, if it is 20 minutes newer than its parent (testnet).
+	pub pow_allow_min_difficulty_blocks: bool,
+	/// Difficulty is never adjusted (regtest).
+	pub pow_no_retargeting: bool,
+	/// Retarget timespan is measured over all blocks of the interval. Original algorithm measures it
+	/// starting from the first block of the interval, i.e. over RETARGETING_INTERVAL - 1 spacings.
+	pub pow_retarget_full_interval: bool,
+	/// Retarget is based on the difficulty of the first block of the interval, rather than of the last one,
+	/// so that minimal difficulty blocks don't affect it (BIP94).
+	pub pow_retarget_first_block_bits: bool,
+	/// Script, which every signet block must satisfy.
+	/// See https://github.com/bitcoin/bips/blob/master/bip-0325.mediawiki
+	pub signet_challenge: Option<Bytes>,
+	/// Hashes of the main chain blocks at given heights. Other blocks at these heights are rejected.
+	pub checkpoints: Vec<(u32, H256)>,
+	/// Height and hash of the blocks with MuHash of the unspent outputs set at these blocks. Snapshots of these
+	/// sets may be loaded instead of downloading and verifying all blocks below.
+	pub assume_utxo: Vec<(u32, H256, H256)>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -40,21 +82,68 @@ pub struct ConsensusParams {
 pub enum ConsensusFork {
 	/// No fork.
 	NoFork,
-	/// SegWit2x (aka The New York Agreement).
-	/// `u32` is height of the first block, for which new consensus rules are applied.
-	/// Briefly: SegWit + blocks up to 2MB.
-	/// Technical specification:
-	/// Segregated Witness (Consensus layer) - https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki
-	/// Block size increase to 2MB - https://github.com/bitcoin/bips/blob/master/bip-0102.mediawiki
-	/// Readiness checklist - https://segwit2x.github.io/segwit2x-announce.html
-	SegWit2x(u32),
+	/// Custom block limits (for experimental networks).
+	/// Briefly: SegWit + block size and weight limits are changed starting from given height.
+	/// Sigops limits are scaled proportionally.
+	CustomLimits(BlockLimits),
 	/// Bitcoin Cash (aka UAHF).
-	/// `u32` is height of the first block, for which new consensus rules are applied.
-	/// Briefly: no SegWit + blocks up to 8MB + replay protection.
+	/// Briefly: no SegWit + blocks up to 8MB + replay protection + scheduled network upgrades.
 	/// Technical specification:
 	/// UAHF Technical Specification - https://github.com/Bitcoin-UAHF/spec/blob/master/uahf-technical-spec.md
 	/// BUIP-HF Digest for replay protected signature verification across hard forks - https://github.com/Bitcoin-UAHF/spec/blob/master/replay-protected-sighash.md
-	BitcoinCash(u32),
+	BitcoinCash(BitcoinCashConsensusParams),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Block limits of custom fork.
+pub struct BlockLimits {
+	/// Height of the first block, for which new limits are applied.
+	pub height: u32,
+	/// Maximal size of the block (without witness data).
+	pub max_block_size: usize,
+	/// Maximal weight of the block.
+	pub max_block_weight: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Activation points of BitcoinCash network upgrades.
+/// Upgrades, scheduled by time, are activated when median time past of the parent block reaches given time.
+pub struct BitcoinCashConsensusParams {
+	/// Height of the first block, for which UAHF rules are applied.
+	pub height: u32,
+	/// Height of the first block, which difficulty is adjusted using cw-144 algorithm.
+	/// See https://github.com/Bitcoin-UAHF/spec/blob/master/nov-13-hardfork-spec.md
+	pub difficulty_adjustion_height: u32,
+	/// Time of Magnetic Anomaly upgrade: canonical transactions ordering + minimal transaction size.
+	/// See https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/2018-nov-upgrade.md
+	pub magnetic_anomaly_time: u32,
+	/// Time of Phonon upgrade: signature checks limits replace sigops limits.
+	/// See https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/2020-05-15-upgrade.md
+	pub phonon_time: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Order of transactions in the block.
+pub enum TransactionOrdering {
+	/// Transaction is placed after all in-block transactions it spends.
+	Topological,
+	/// Transactions (except coinbase) are sorted by their hashes.
+	Canonical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Difficulty adjustment algorithm.
+pub enum DifficultyAlgorithm {
+	/// Difficulty never changes.
+	NoRetargeting,
+	/// Difficulty is adjusted every 2016 blocks.
+	Bitcoin,
+	/// Difficulty is adjusted every 2016 blocks, or decreased by 20% if blocks are mined too slowly.
+	/// Specification: https://github.com/Bitcoin-UAHF/spec/blob/master/uahf-technical-spec.md#difficulty-adjustment
+	BitcoinCashEmergency,
+	/// Difficulty is adjusted every block, using the work of last 144 blocks.
+	/// Specification: https://github.com/Bitcoin-UAHF/spec/blob/master/nov-13-hardfork-spec.md
+	BitcoinCashCw144,
 }
 
 impl ConsensusParams {
@@ -70,22 +159,48 @@ impl ConsensusParams {
 				rule_change_activation_threshold: 1916, // 95%
 				miner_confirmation_window: 2016,
 				csv_deployment: Some(Deployment {
-					name: "csv",
+					name: "csv".into(),
 					bit: 0,
 					start_time: 1462060800,
 					timeout: 1493596800,
+					min_activation_height: 0,
+					threshold: None,
 					activation: Some(419328),
 				}),
 				segwit_deployment: match fork {
-					ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) => Some(Deployment {
-						name: "segwit",
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "segwit".into(),
 						bit: 1,
 						start_time: 1479168000,
 						timeout: 1510704000,
+						min_activation_height: 0,
+						threshold: None,
+						activation: None,
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				taproot_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "taproot".into(),
+						bit: 2,
+						start_time: 1619222400,
+						timeout: 1628640000,
+						min_activation_height: 709632,
+						threshold: Some(1815), // 90%
 						activation: None,
 					}),
 					ConsensusFork::BitcoinCash(_) => None,
 				},
+				custom_deployments: Vec::new(),
+				proof_of_work: Arc::new(Sha256dProofOfWork),
+				pow_limit: magic.max_bits(),
+				pow_allow_min_difficulty_blocks: false,
+				pow_no_retargeting: false,
+				pow_retarget_full_interval: false,
+				pow_retarget_first_block_bits: false,
+				signet_challenge: None,
+				checkpoints: checkpoints(magic),
+				assume_utxo: assume_utxo(magic),
 			},
 			Magic::Testnet => ConsensusParams {
 				network: magic,
@@ -97,24 +212,50 @@ impl ConsensusParams {
 				rule_change_activation_threshold: 1512, // 75%
 				miner_confirmation_window: 2016,
 				csv_deployment: Some(Deployment {
-					name: "csv",
+					name: "csv".into(),
 					bit: 0,
 					start_time: 1456790400,
 					timeout: 1493596800,
+					min_activation_height: 0,
+					threshold: None,
 					activation: Some(770112),
 				}),
 				segwit_deployment: match fork {
-					ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) => Some(Deployment {
-						name: "segwit",
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "segwit".into(),
 						bit: 1,
 						start_time: 1462060800,
 						timeout: 1493596800,
+						min_activation_height: 0,
+						threshold: None,
+						activation: None,
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				taproot_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "taproot".into(),
+						bit: 2,
+						start_time: 1619222400,
+						timeout: 1628640000,
+						min_activation_height: 0,
+						threshold: None,
 						activation: None,
 					}),
 					ConsensusFork::BitcoinCash(_) => None,
 				},
+				custom_deployments: Vec::new(),
+				proof_of_work: Arc::new(Sha256dProofOfWork),
+				pow_limit: magic.max_bits(),
+				pow_allow_min_difficulty_blocks: true,
+				pow_no_retargeting: false,
+				pow_retarget_full_interval: false,
+				pow_retarget_first_block_bits: false,
+				signet_challenge: None,
+				checkpoints: checkpoints(magic),
+				assume_utxo: assume_utxo(magic),
 			},
-			Magic::Regtest | Magic::Unitest => ConsensusParams {
+			Magic::Regtest => ConsensusParams {
 				network: magic,
 				bip16_time: 1333238400,	// Apr 1 2012
 				bip34_height: 100000000,	// not activated on regtest
@@ -124,32 +265,280 @@ impl ConsensusParams {
 				rule_change_activation_threshold: 108, // 75%
 				miner_confirmation_window: 144,
 				csv_deployment: Some(Deployment {
-					name: "csv",
+					name: "csv".into(),
+					bit: 0,
+					start_time: 0,
+					timeout: 0,
+					min_activation_height: 0,
+					threshold: None,
+					activation: Some(0),
+				}),
+				segwit_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "segwit".into(),
+						bit: 1,
+						start_time: 0,
+						timeout: ::std::u32::MAX,
+						min_activation_height: 0,
+						threshold: None,
+						activation: Some(0),
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				taproot_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "taproot".into(),
+						bit: 2,
+						start_time: 0,
+						timeout: ::std::u32::MAX,
+						min_activation_height: 0,
+						threshold: None,
+						activation: Some(0),
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				custom_deployments: Vec::new(),
+				proof_of_work: Arc::new(Sha256dProofOfWork),
+				pow_limit: magic.max_bits(),
+				pow_allow_min_difficulty_blocks: false,
+				pow_no_retargeting: true,
+				pow_retarget_full_interval: false,
+				pow_retarget_first_block_bits: false,
+				signet_challenge: None,
+				checkpoints: checkpoints(magic),
+				assume_utxo: assume_utxo(magic),
+			},
+			Magic::Signet(_) => ConsensusParams {
+				network: magic,
+				bip16_time: 0,
+				bip34_height: 1,
+				bip65_height: 1,
+				bip66_height: 1,
+				fork: fork,
+				rule_change_activation_threshold: 1815, // 90%
+				miner_confirmation_window: 2016,
+				csv_deployment: Some(Deployment {
+					name: "csv".into(),
 					bit: 0,
 					start_time: 0,
 					timeout: 0,
+					min_activation_height: 0,
+					threshold: None,
 					activation: Some(0),
 				}),
 				segwit_deployment: match fork {
-					ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) => Some(Deployment {
-						name: "segwit",
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "segwit".into(),
 						bit: 1,
 						start_time: 0,
 						timeout: ::std::u32::MAX,
+						min_activation_height: 0,
+						threshold: None,
+						activation: Some(0),
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				taproot_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "taproot".into(),
+						bit: 2,
+						start_time: 0,
+						timeout: ::std::u32::MAX,
+						min_activation_height: 0,
+						threshold: None,
+						activation: Some(0),
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				custom_deployments: Vec::new(),
+				proof_of_work: Arc::new(Sha256dProofOfWork),
+				pow_limit: magic.max_bits(),
+				pow_allow_min_difficulty_blocks: false,
+				pow_no_retargeting: false,
+				pow_retarget_full_interval: false,
+				pow_retarget_first_block_bits: false,
+				signet_challenge: Some(DEFAULT_SIGNET_CHALLENGE.into()),
+				checkpoints: checkpoints(magic),
+				assume_utxo: assume_utxo(magic),
+			},
+			Magic::Unitest => ConsensusParams {
+				network: magic,
+				bip16_time: 1333238400,	// Apr 1 2012
+				bip34_height: 100000000,	// not activated on regtest
+				bip65_height: 1351,
+				bip66_height: 1251,		// used only in rpc tests
+				fork: fork,
+				rule_change_activation_threshold: 108, // 75%
+				miner_confirmation_window: 144,
+				csv_deployment: Some(Deployment {
+					name: "csv".into(),
+					bit: 0,
+					start_time: 0,
+					timeout: 0,
+					min_activation_height: 0,
+					threshold: None,
+					activation: Some(0),
+				}),
+				segwit_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "segwit".into(),
+						bit: 1,
+						start_time: 0,
+						timeout: ::std::u32::MAX,
+						min_activation_height: 0,
+						threshold: None,
 						activation: None,
 					}),
 					ConsensusFork::BitcoinCash(_) => None,
 				},
+				taproot_deployment: match fork {
+					ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => Some(Deployment {
+						name: "taproot".into(),
+						bit: 2,
+						start_time: 0,
+						timeout: ::std::u32::MAX,
+						min_activation_height: 0,
+						threshold: None,
+						activation: None,
+					}),
+					ConsensusFork::BitcoinCash(_) => None,
+				},
+				custom_deployments: Vec::new(),
+				proof_of_work: Arc::new(Sha256dProofOfWork),
+				pow_limit: magic.max_bits(),
+				pow_allow_min_difficulty_blocks: false,
+				pow_no_retargeting: false,
+				pow_retarget_full_interval: false,
+				pow_retarget_first_block_bits: false,
+				signet_challenge: None,
+				checkpoints: checkpoints(magic),
+				assume_utxo: assume_utxo(magic),
 			},
 		}
 	}
 
+	/// All versionbits deployments of the network.
+	pub fn deployments(&self) -> Vec<Deployment> {
+		self.csv_deployment.iter()
+			.chain(self.segwit_deployment.iter())
+			.chain(self.taproot_deployment.iter())
+			.chain(self.custom_deployments.iter())
+			.cloned()
+			.collect()
+	}
+
+	/// Replaces proof-of-work function (for chains, derived from Bitcoin).
+	pub fn with_proof_of_work(mut self, proof_of_work: Arc<ProofOfWork>) -> Self {
+		self.proof_of_work = proof_of_work;
+		self
+	}
+
+	/// Replaces signet block challenge (and network magic, which is derived from it).
+	pub fn with_signet_challenge(mut self, challenge: Bytes) -> Self {
+		self.network = Magic::custom_signet(&challenge);
+		self.signet_challenge = Some(challenge);
+		self
+	}
+
+	/// Difficulty adjustment algorithm, used to compute work of the block at given height.
+	pub fn difficulty_algorithm(&self, height: u32) -> DifficultyAlgorithm {
+		if self.pow_no_retargeting {
+			return DifficultyAlgorithm::NoRetargeting;
+		}
+
+		match self.fork {
+			ConsensusFork::BitcoinCash(ref fork) if height >= fork.difficulty_adjustion_height => DifficultyAlgorithm::BitcoinCashCw144,
+			ConsensusFork::BitcoinCash(ref fork) if height >= fork.height => DifficultyAlgorithm::BitcoinCashEmergency,
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) | ConsensusFork::BitcoinCash(_) => DifficultyAlgorithm::Bitcoin,
+		}
+	}
+
+	/// Hash of the main chain block at given height, if there's a checkpoint at this height.
+	pub fn checkpoint(&self, height: u32) -> Option<&H256> {
+		self.checkpoints.iter()
+			.find(|&&(checkpoint_height, _)| checkpoint_height == height)
+			.map(|&(_, ref hash)| hash)
+	}
+
+	/// MuHash of the unspent outputs set at given block, if snapshot of this set may be loaded.
+	pub fn assume_utxo_muhash(&self, height: u32, hash: &H256) -> Option<&H256> {
+		self.assume_utxo.iter()
+			.find(|&&(snapshot_height, ref snapshot_hash, _)| snapshot_height == height && snapshot_hash == hash)
+			.map(|&(_, _, ref muhash)| muhash)
+	}
+
 	pub fn is_bip30_exception(&self, hash: &H256, height: u32) -> bool {
 		(height == 91842 && hash == &H256::from_reversed_str("00000000000a4d0a398161ffc163c503763b1f4360639393e0e4c8e300e0caec")) ||
 		(height == 91880 && hash == &H256::from_reversed_str("00000000000743f190a18c5577a3c2d2a1f610ae9601ac046a38084ccb7cd721"))
 	}
 }
 
+/// Checkpoints of the network (see Bitcoin Core chainparams.cpp).
+fn checkpoints(magic: Magic) -> Vec<(u32, H256)> {
+	let checkpoints: &[(u32, &'static str)] = match magic {
+		Magic::Mainnet => &[
+			(11111, "0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d"),
+			(33333, "000000002dd5588a74784eaa7ab0507a18ad16a236e7b1ce69f00d7ddfb5d0a6"),
+			(74000, "0000000000573993a3c9e41ce34471c079dcf5f52a0e824a81e7f953b8661a20"),
+			(105000, "00000000000291ce28027faea320c8d2b054b2e0fe44a773f3eefb151d6bdc97"),
+			(134444, "00000000000005b12ffd4cd315cd34ffd4a594f430ac814c91184a0d42d2b0fe"),
+			(168000, "000000000000099e61ea72015e79632f216fe6cb33d7899acb35b75c8303b763"),
+			(193000, "000000000000059f452a5f7340de6682a977387c17010ff6e6c3bd83ca8b1317"),
+			(210000, "000000000000048b95347e83192f69cf0366076336c639f9b7228e9ba171342e"),
+			(216116, "00000000000001b4f4b433e81ee46494af945cf96014816a4e2370f11b23df4e"),
+			(225430, "00000000000001c108384350f74090433e7fcf79a606b8e797f065b130575932"),
+			(250000, "000000000000003887df1f29024b06fc2200b55f8af8f35453d7be294df2d214"),
+			(279000, "0000000000000001ae8c72a0b0c301f67e3afca10e819efa9041e458e9bd7e40"),
+			(295000, "00000000000000004d9b4ef50f0f9d686fd69db2e03af35a100370c64632a983"),
+		],
+		Magic::Testnet => &[
+			(546, "000000002a936ca763904c3c35fce2f3556c559c0214345d31b1bcebf76acb70"),
+		],
+		Magic::Regtest | Magic::Unitest | Magic::Signet(_) | Magic::Other(_) => &[],
+	};
+
+	checkpoints.iter()
+		.map(|&(height, hash)| (height, H256::from_reversed_str(hash)))
+		.collect()
+}
+
+/// Snapshots of the unspent outputs set, which may be loaded by the new node: (height, block hash, MuHash).
+/// Snapshot is only added once its MuHash is reproduced by independently synchronized nodes.
+fn assume_utxo(magic: Magic) -> Vec<(u32, H256, H256)> {
+	let snapshots: &[(u32, &'static str, &'static str)] = match magic {
+		Magic::Mainnet | Magic::Testnet | Magic::Regtest | Magic::Unitest | Magic::Signet(_) | Magic::Other(_) => &[],
+	};
+
+	snapshots.iter()
+		.map(|&(height, hash, muhash)| (height, H256::from_reversed_str(hash), H256::from_reversed_str(muhash)))
+		.collect()
+}
+
+impl BitcoinCashConsensusParams {
+	pub fn new(magic: Magic) -> Self {
+		match magic {
+			Magic::Mainnet => BitcoinCashConsensusParams {
+				height: BITCOIN_CASH_FORK_BLOCK,
+				difficulty_adjustion_height: BITCOIN_CASH_DAA_MAINNET_BLOCK,
+				magnetic_anomaly_time: 1542300000,	// Nov 15 2018
+				phonon_time: 1589544000,	// May 15 2020
+			},
+			Magic::Testnet => BitcoinCashConsensusParams {
+				height: BITCOIN_CASH_TESTNET_FORK_BLOCK,
+				difficulty_adjustion_height: BITCOIN_CASH_DAA_TESTNET_BLOCK,
+				magnetic_anomaly_time: 1542300000,	// Nov 15 2018
+				phonon_time: 1589544000,	// May 15 2020
+			},
+			Magic::Regtest | Magic::Unitest | Magic::Signet(_) | Magic::Other(_) => BitcoinCashConsensusParams {
+				height: 0,
+				difficulty_adjustion_height: ::std::u32::MAX,
+				magnetic_anomaly_time: 1542300000,	// Nov 15 2018
+				phonon_time: 1589544000,	// May 15 2020
+			},
+		}
+	}
+}
+
 impl ConsensusFork {
 	/// Absolute (across all forks) maximum block size. Currently is 8MB for post-HF BitcoinCash
 	pub fn absolute_maximum_block_size() -> usize {
@@ -163,7 +552,7 @@ impl ConsensusFork {
 
 	/// Witness scale factor (equal among all forks)
 	pub fn witness_scale_factor() -> usize {
-		4
+		WITNESS_SCALE_FACTOR
 	}
 
 	pub fn max_transaction_size(&self) -> usize {
@@ -172,30 +561,36 @@ impl ConsensusFork {
  		1_000_000
 	}
 
+	/// Maximal weight of transaction, which is computed from its size without witness data.
+	pub fn max_transaction_weight(&self) -> usize {
+		self.max_transaction_size() * Self::witness_scale_factor()
+	}
+
 	pub fn min_block_size(&self, height: u32) -> usize {
 		match *self {
 			// size of first fork block must be larger than 1MB
-			ConsensusFork::BitcoinCash(fork_height) if height == fork_height => 1_000_001,
-			ConsensusFork::NoFork | ConsensusFork::BitcoinCash(_) | ConsensusFork::SegWit2x(_) => 0,
+			ConsensusFork::BitcoinCash(ref fork) if height == fork.height => 1_000_001,
+			ConsensusFork::NoFork | ConsensusFork::BitcoinCash(_) | ConsensusFork::CustomLimits(_) => 0,
 		}
 	}
 
 	pub fn max_block_size(&self, height: u32) -> usize {
 		match *self {
-			ConsensusFork::SegWit2x(fork_height) if height >= fork_height => 2_000_000,
-			ConsensusFork::BitcoinCash(fork_height) if height >= fork_height => 8_000_000,
-			ConsensusFork::NoFork | ConsensusFork::BitcoinCash(_) | ConsensusFork::SegWit2x(_) => 1_000_000,
+			ConsensusFork::CustomLimits(ref limits) if height >= limits.height => limits.max_block_size,
+			ConsensusFork::BitcoinCash(ref fork) if height >= fork.height => 8_000_000,
+			ConsensusFork::NoFork | ConsensusFork::BitcoinCash(_) | ConsensusFork::CustomLimits(_) => 1_000_000,
 		}
 	}
 
 	pub fn max_block_sigops(&self, height: u32, block_size: usize) -> usize {
 		match *self {
 			// according to REQ-5: max_block_sigops = 20000 * ceil((max(blocksize_bytes, 1000000) / 1000000))
-			ConsensusFork::BitcoinCash(fork_height) if height >= fork_height =>
+			ConsensusFork::BitcoinCash(ref fork) if height >= fork.height =>
 				20_000 * (1 + (block_size - 1) / 1_000_000),
-			ConsensusFork::SegWit2x(fork_height) if height >= fork_height =>
-				40_000,
-			ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) | ConsensusFork::BitcoinCash(_) => 20_000,
+			// 20_000 sigops for each 1_000_000 bytes of block size limit
+			ConsensusFork::CustomLimits(ref limits) if height >= limits.height =>
+				20_000 * limits.max_block_size / 1_000_000,
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) | ConsensusFork::BitcoinCash(_) => 20_000,
 		}
 	}
 
@@ -203,29 +598,83 @@ impl ConsensusFork {
 		match *self {
 			ConsensusFork::BitcoinCash(_) =>
 				self.max_block_sigops(height, block_size) * Self::witness_scale_factor(),
-			ConsensusFork::SegWit2x(fork_height) if height >= fork_height =>
-				160_000,
-			ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) =>
+			// 80_000 sigops cost for each 4_000_000 units of block weight limit
+			ConsensusFork::CustomLimits(ref limits) if height >= limits.height =>
+				80_000 * limits.max_block_weight / 4_000_000,
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) =>
 				80_000,
 		}
 	}
 
 	pub fn max_block_weight(&self, height: u32) -> usize {
 		match *self {
-			ConsensusFork::SegWit2x(fork_height) if height >= fork_height =>
-				8_000_000,
-			ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) =>
+			ConsensusFork::CustomLimits(ref limits) if height >= limits.height =>
+				limits.max_block_weight,
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) =>
 				4_000_000,
 			ConsensusFork::BitcoinCash(_) =>
 				unreachable!("BitcoinCash has no SegWit; weight is only checked with SegWit activated; qed"),
 		}
 	}
+
+	/// Minimal size of transaction (without witness) in the block, which parent has given median time past.
+	pub fn min_transaction_size(&self, median_time_past: u32) -> usize {
+		match *self {
+			ConsensusFork::BitcoinCash(ref fork) if median_time_past >= fork.magnetic_anomaly_time =>
+				BITCOIN_CASH_MIN_TRANSACTION_SIZE,
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) | ConsensusFork::BitcoinCash(_) => 0,
+		}
+	}
+
+	/// Order of transactions in the block, which parent has given median time past.
+	pub fn transaction_ordering(&self, median_time_past: u32) -> TransactionOrdering {
+		match *self {
+			ConsensusFork::BitcoinCash(ref fork) if median_time_past >= fork.magnetic_anomaly_time =>
+				TransactionOrdering::Canonical,
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) | ConsensusFork::BitcoinCash(_) =>
+				TransactionOrdering::Topological,
+		}
+	}
+
+	/// Maximal number of signature checks in single transaction.
+	/// Returns None if number of signature checks is not limited (sigops limits are applied instead).
+	pub fn max_transaction_sig_checks(&self, median_time_past: u32) -> Option<usize> {
+		match *self {
+			ConsensusFork::BitcoinCash(ref fork) if median_time_past >= fork.phonon_time =>
+				Some(BITCOIN_CASH_MAX_TRANSACTION_SIG_CHECKS),
+			ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) | ConsensusFork::BitcoinCash(_) => None,
+		}
+	}
+
+	/// Maximal number of signature checks in the block.
+	/// Returns None if number of signature checks is not limited (sigops limits are applied instead).
+	pub fn max_block_sig_checks(&self, height: u32, median_time_past: u32) -> Option<usize> {
+		self.max_transaction_sig_checks(median_time_past)
+			.map(|_| self.max_block_size(height) / BITCOIN_CASH_BLOCK_SIZE_SIG_CHECKS_RATIO)
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use super::super::Magic;
-	use super::{ConsensusParams, ConsensusFork};
+	use hash::H256;
+	use super::super::{Magic, Deployment};
+	use super::{ConsensusParams, ConsensusFork, BlockLimits, BitcoinCashConsensusParams, DifficultyAlgorithm, TransactionOrdering,
+		DEFAULT_SIGNET_CHALLENGE};
+
+	fn custom_limits(height: u32) -> ConsensusFork {
+		ConsensusFork::CustomLimits(BlockLimits {
+			height: height,
+			max_block_size: 2_000_000,
+			max_block_weight: 8_000_000,
+		})
+	}
+
+	fn bitcoin_cash(height: u32) -> ConsensusFork {
+		ConsensusFork::BitcoinCash(BitcoinCashConsensusParams {
+			height: height,
+			..BitcoinCashConsensusParams::new(Magic::Mainnet)
+		})
+	}
 
 	#[test]
 	fn test_consensus_params_bip34_height() {
@@ -262,30 +711,146 @@ mod tests {
 		assert_eq!(ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork).miner_confirmation_window, 144);
 	}
 
+	#[test]
+	fn test_consensus_params_regtest() {
+		let regtest = ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork);
+		assert!(regtest.pow_no_retargeting);
+		assert_eq!(regtest.segwit_deployment.as_ref().unwrap().activation, Some(0));
+		assert!(!ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork).pow_no_retargeting);
+		assert!(!ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork).pow_no_retargeting);
+	}
+
+	#[test]
+	fn test_consensus_params_signet() {
+		let signet = ConsensusParams::new(Magic::signet(), ConsensusFork::NoFork);
+		assert_eq!(signet.signet_challenge, Some(DEFAULT_SIGNET_CHALLENGE.into()));
+		assert_eq!(signet.segwit_deployment.as_ref().unwrap().activation, Some(0));
+		assert_eq!(ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork).signet_challenge, None);
+
+		let custom = signet.with_signet_challenge("51".into());
+		assert_eq!(custom.signet_challenge, Some("51".into()));
+		assert!(custom.network != Magic::signet());
+		assert_eq!(custom.network, Magic::custom_signet(&"51".into()));
+	}
+
+	#[test]
+	fn test_consensus_params_taproot_deployment() {
+		let mainnet = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork).taproot_deployment.unwrap();
+		assert_eq!(mainnet.bit, 2);
+		assert_eq!(mainnet.min_activation_height, 709632);
+		assert_eq!(mainnet.activation, None);
+		let testnet = ConsensusParams::new(Magic::Testnet, ConsensusFork::NoFork).taproot_deployment.unwrap();
+		assert_eq!(testnet.min_activation_height, 0);
+		assert_eq!(testnet.activation, None);
+		assert_eq!(ConsensusParams::new(Magic::signet(), ConsensusFork::NoFork).taproot_deployment.unwrap().activation, Some(0));
+		assert_eq!(ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork).taproot_deployment.unwrap().activation, Some(0));
+		assert!(ConsensusParams::new(Magic::Mainnet, bitcoin_cash(100)).taproot_deployment.is_none());
+	}
+
+	#[test]
+	fn test_consensus_params_deployments() {
+		let mut regtest = ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork);
+		regtest.custom_deployments.push(Deployment {
+			name: "testdummy".into(),
+			bit: 28,
+			start_time: 0,
+			timeout: ::std::u32::MAX,
+			min_activation_height: 0,
+			threshold: Some(72),
+			activation: None,
+		});
+		let names: Vec<_> = regtest.deployments().into_iter().map(|d| d.name).collect();
+		assert_eq!(names, vec!["csv", "segwit", "taproot", "testdummy"]);
+
+		let names: Vec<_> = ConsensusParams::new(Magic::Mainnet, bitcoin_cash(100)).deployments().into_iter().map(|d| d.name).collect();
+		assert_eq!(names, vec!["csv"]);
+	}
+
+	#[test]
+	fn test_consensus_params_checkpoints() {
+		let mainnet = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		assert_eq!(mainnet.checkpoint(11111), Some(&H256::from_reversed_str("0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d")));
+		assert_eq!(mainnet.checkpoint(11112), None);
+		assert!(ConsensusParams::new(Magic::Other(1), ConsensusFork::NoFork).checkpoints.is_empty());
+		assert!(ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork).checkpoints.is_empty());
+	}
+
+	#[test]
+	fn test_consensus_params_assume_utxo() {
+		let mut consensus = ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork);
+		assert_eq!(consensus.assume_utxo_muhash(100, &H256::from(1)), None);
+		consensus.assume_utxo.push((100, H256::from(1), H256::from(2)));
+		assert_eq!(consensus.assume_utxo_muhash(100, &H256::from(1)), Some(&H256::from(2)));
+		assert_eq!(consensus.assume_utxo_muhash(101, &H256::from(1)), None);
+		assert_eq!(consensus.assume_utxo_muhash(100, &H256::from(3)), None);
+	}
+
+	#[test]
+	fn test_consensus_difficulty_algorithm() {
+		let mainnet = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		assert_eq!(mainnet.difficulty_algorithm(600_000), DifficultyAlgorithm::Bitcoin);
+
+		let regtest = ConsensusParams::new(Magic::Regtest, bitcoin_cash(100));
+		assert_eq!(regtest.difficulty_algorithm(200), DifficultyAlgorithm::NoRetargeting);
+
+		let cash = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(Magic::Mainnet)));
+		assert_eq!(cash.difficulty_algorithm(478558), DifficultyAlgorithm::Bitcoin);
+		assert_eq!(cash.difficulty_algorithm(478559), DifficultyAlgorithm::BitcoinCashEmergency);
+		assert_eq!(cash.difficulty_algorithm(504031), DifficultyAlgorithm::BitcoinCashEmergency);
+		assert_eq!(cash.difficulty_algorithm(504032), DifficultyAlgorithm::BitcoinCashCw144);
+	}
+
 	#[test]
 	fn test_consensus_fork_min_block_size() {
 		assert_eq!(ConsensusFork::NoFork.min_block_size(0), 0);
-		assert_eq!(ConsensusFork::SegWit2x(100).min_block_size(0), 0);
-		assert_eq!(ConsensusFork::SegWit2x(100).min_block_size(100), 0);
-		assert_eq!(ConsensusFork::BitcoinCash(100).min_block_size(0), 0);
-		assert_eq!(ConsensusFork::BitcoinCash(100).min_block_size(100), 1_000_001);
+		assert_eq!(custom_limits(100).min_block_size(0), 0);
+		assert_eq!(custom_limits(100).min_block_size(100), 0);
+		assert_eq!(bitcoin_cash(100).min_block_size(0), 0);
+		assert_eq!(bitcoin_cash(100).min_block_size(100), 1_000_001);
 	}
 
 	#[test]
 	fn test_consensus_fork_max_transaction_size() {
 		assert_eq!(ConsensusFork::NoFork.max_transaction_size(), 1_000_000);
-		assert_eq!(ConsensusFork::SegWit2x(100).max_transaction_size(), 1_000_000);
-		assert_eq!(ConsensusFork::BitcoinCash(100).max_transaction_size(), 1_000_000);
+		assert_eq!(custom_limits(100).max_transaction_size(), 1_000_000);
+		assert_eq!(bitcoin_cash(100).max_transaction_size(), 1_000_000);
+		assert_eq!(ConsensusFork::NoFork.max_transaction_weight(), 4_000_000);
 	}
 
 	#[test]
 	fn test_consensus_fork_max_block_sigops() {
 		assert_eq!(ConsensusFork::NoFork.max_block_sigops(0, 1_000_000), 20_000);
-		assert_eq!(ConsensusFork::SegWit2x(100).max_block_sigops(0, 1_000_000), 20_000);
-		assert_eq!(ConsensusFork::SegWit2x(100).max_block_sigops(100, 2_000_000), 40_000);
-		assert_eq!(ConsensusFork::SegWit2x(100).max_block_sigops(200, 3_000_000), 40_000);
-		assert_eq!(ConsensusFork::BitcoinCash(100).max_block_sigops(0, 1_000_000), 20_000);
-		assert_eq!(ConsensusFork::BitcoinCash(100).max_block_sigops(100, 2_000_000), 40_000);
-		assert_eq!(ConsensusFork::BitcoinCash(100).max_block_sigops(200, 3_000_000), 60_000);
+		assert_eq!(custom_limits(100).max_block_sigops(0, 1_000_000), 20_000);
+		assert_eq!(custom_limits(100).max_block_sigops(100, 2_000_000), 40_000);
+		assert_eq!(custom_limits(100).max_block_sigops(200, 3_000_000), 40_000);
+		assert_eq!(bitcoin_cash(100).max_block_sigops(0, 1_000_000), 20_000);
+		assert_eq!(bitcoin_cash(100).max_block_sigops(100, 2_000_000), 40_000);
+		assert_eq!(bitcoin_cash(100).max_block_sigops(200, 3_000_000), 60_000);
+	}
+
+	#[test]
+	fn test_consensus_fork_custom_limits() {
+		assert_eq!(custom_limits(100).max_block_size(99), 1_000_000);
+		assert_eq!(custom_limits(100).max_block_size(100), 2_000_000);
+		assert_eq!(custom_limits(100).max_block_weight(99), 4_000_000);
+		assert_eq!(custom_limits(100).max_block_weight(100), 8_000_000);
+		assert_eq!(custom_limits(100).max_block_sigops_cost(99, 1_000_000), 80_000);
+		assert_eq!(custom_limits(100).max_block_sigops_cost(100, 2_000_000), 160_000);
+	}
+
+	#[test]
+	fn test_consensus_fork_bitcoin_cash_upgrades() {
+		let fork = ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(Magic::Mainnet));
+		assert_eq!(fork.min_transaction_size(1542299999), 0);
+		assert_eq!(fork.min_transaction_size(1542300000), 100);
+		assert_eq!(fork.transaction_ordering(1542299999), TransactionOrdering::Topological);
+		assert_eq!(fork.transaction_ordering(1542300000), TransactionOrdering::Canonical);
+		assert_eq!(fork.max_transaction_sig_checks(1589543999), None);
+		assert_eq!(fork.max_transaction_sig_checks(1589544000), Some(3_000));
+		assert_eq!(fork.max_block_sig_checks(600_000, 1589544000), Some(8_000_000 / 141));
+
+		assert_eq!(ConsensusFork::NoFork.min_transaction_size(1589544000), 0);
+		assert_eq!(ConsensusFork::NoFork.transaction_ordering(1589544000), TransactionOrdering::Topological);
+		assert_eq!(ConsensusFork::NoFork.max_block_sig_checks(600_000, 1589544000), None);
 	}
 }
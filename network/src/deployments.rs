@@ -1,16 +1,21 @@
 const VERSIONBITS_TOP_MASK: u32 = 0xe0000000;
 const VERSIONBITS_TOP_BITS: u32 = 0x20000000;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Deployment {
 	/// Deployment's name
-	pub name: &'static str,
+	pub name: String,
 	/// Bit
 	pub bit: u8,
 	/// Start time
 	pub start_time: u32,
 	/// Timeout
 	pub timeout: u32,
+	/// Deployment can't become active before this block number (even if it is locked in earlier)
+	pub min_activation_height: u32,
+	/// Number of signalling blocks in the confirmation window, required to lock in the deployment
+	/// (network's rule change activation threshold is used if not set)
+	pub threshold: Option<u32>,
 	/// Activation block number (if already activated)
 	pub activation: Option<u32>,
 }
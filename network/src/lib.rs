@@ -1,3 +1,4 @@
+extern crate bitcrypto as crypto;
 extern crate chain;
 extern crate primitives;
 extern crate serialization as ser;
@@ -5,10 +6,14 @@ extern crate serialization as ser;
 mod consensus;
 mod deployments;
 mod magic;
+mod pow;
 
-pub use primitives::{hash, compact};
+pub use primitives::{hash, compact, bytes};
 
-pub use consensus::{ConsensusParams, ConsensusFork, SEGWIT2X_FORK_BLOCK, BITCOIN_CASH_FORK_BLOCK};
+pub use consensus::{ConsensusParams, ConsensusFork, BlockLimits, BitcoinCashConsensusParams, DifficultyAlgorithm, TransactionOrdering,
+	BITCOIN_CASH_FORK_BLOCK, BITCOIN_CASH_TESTNET_FORK_BLOCK, BITCOIN_CASH_DAA_MAINNET_BLOCK,
+	BITCOIN_CASH_DAA_TESTNET_BLOCK, DEFAULT_SIGNET_CHALLENGE};
 pub use deployments::Deployment;
 pub use magic::Magic;
+pub use pow::{ProofOfWork, Sha256dProofOfWork};
 
@@ -2,18 +2,23 @@
 //! https://www.anintegratedworld.com/unravelling-the-mysterious-block-chain-magic-number/
 
 use compact::Compact;
-use ser::{Stream, Serializable};
+use ser::{Stream, Serializable, serialize};
 use chain::Block;
 use primitives::hash::H256;
+use primitives::bigint::U256;
+use crypto::dhash256;
+use bytes::Bytes;
 
 const MAGIC_MAINNET: u32 = 0xD9B4BEF9;
 const MAGIC_TESTNET: u32 = 0x0709110B;
 const MAGIC_REGTEST: u32 = 0xDAB5BFFA;
+const MAGIC_SIGNET: u32 = 0x40CF030A;
 const MAGIC_UNITEST: u32 = 0x00000000;
 
 const MAX_BITS_MAINNET: u32 = 0x1d00ffff;
 const MAX_BITS_TESTNET: u32 = 0x1d00ffff;
 const MAX_BITS_REGTEST: u32 = 0x207fffff;
+const MAX_BITS_SIGNET: u32 = 0x1e0377ae;
 
 /// Bitcoin [network](https://bitcoin.org/en/glossary/mainnet)
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -24,6 +29,9 @@ pub enum Magic {
 	Testnet,
 	/// Bitcoin regtest network.
 	Regtest,
+	/// Bitcoin signet network (BIP325).
+	/// `u32` is the network magic, derived from the block challenge.
+	Signet(u32),
 	/// Testnet for unittests, proof of work difficulty is almost 0
 	Unitest,
 	/// Any other network. By default behaves like bitcoin mainnet.
@@ -37,7 +45,7 @@ impl From<Magic> for u32 {
 			Magic::Testnet => MAGIC_TESTNET,
 			Magic::Regtest => MAGIC_REGTEST,
 			Magic::Unitest => MAGIC_UNITEST,
-			Magic::Other(magic) => magic,
+			Magic::Signet(magic) | Magic::Other(magic) => magic,
 		}
 	}
 }
@@ -49,17 +57,31 @@ impl From<u32> for Magic {
 			MAGIC_TESTNET => Magic::Testnet,
 			MAGIC_REGTEST => Magic::Regtest,
 			MAGIC_UNITEST => Magic::Unitest,
+			MAGIC_SIGNET => Magic::Signet(MAGIC_SIGNET),
 			other => Magic::Other(other),
 		}
 	}
 }
 
 impl Magic {
+	/// Default signet network.
+	pub fn signet() -> Self {
+		Magic::Signet(MAGIC_SIGNET)
+	}
+
+	/// Custom signet network, whose blocks must satisfy given challenge script.
+	/// Magic is the first 4 bytes of the hash of the serialized challenge.
+	pub fn custom_signet(challenge: &Bytes) -> Self {
+		let hash = dhash256(&serialize(challenge));
+		Magic::Signet((hash[0] as u32) | (hash[1] as u32) << 8 | (hash[2] as u32) << 16 | (hash[3] as u32) << 24)
+	}
+
 	pub fn max_bits(&self) -> Compact {
 		match *self {
 			Magic::Mainnet | Magic::Other(_) => MAX_BITS_MAINNET.into(),
 			Magic::Testnet => MAX_BITS_TESTNET.into(),
 			Magic::Regtest => MAX_BITS_REGTEST.into(),
+			Magic::Signet(_) => MAX_BITS_SIGNET.into(),
 			Magic::Unitest => Compact::max_value(),
 		}
 	}
@@ -69,6 +91,7 @@ impl Magic {
 			Magic::Mainnet | Magic::Other(_)  => 8333,
 			Magic::Testnet => 18333,
 			Magic::Regtest | Magic::Unitest => 18444,
+			Magic::Signet(_) => 38333,
 		}
 	}
 
@@ -77,6 +100,7 @@ impl Magic {
 			Magic::Mainnet | Magic::Other(_) => 8332,
 			Magic::Testnet => 18332,
 			Magic::Regtest | Magic::Unitest => 18443,
+			Magic::Signet(_) => 38332,
 		}
 	}
 
@@ -85,6 +109,7 @@ impl Magic {
 			Magic::Mainnet | Magic::Other(_) => "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into(),
 			Magic::Testnet => "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff001d1aa4ae180101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into(),
 			Magic::Regtest | Magic::Unitest => "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f20020000000101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into(),
+			Magic::Signet(_) => "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a008f4d5fae77031e8ad222030101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into(),
 		}
 	}
 
@@ -95,6 +120,15 @@ impl Magic {
 			_ => self.genesis_block().hash(),
 		}
 	}
+
+	/// Minimal work of the best headers chain, required to skip scripts verification of the verification edge ancestors.
+	pub fn default_minimum_chain_work(&self) -> U256 {
+		match *self {
+			Magic::Mainnet => "f91c579d57cad4bc5278cc".parse().expect("hardcoded value should parse without errors"),
+			Magic::Testnet => "2830dab7f76dbb7d63".parse().expect("hardcoded value should parse without errors"),
+			_ => U256::zero(),
+		}
+	}
 }
 
 impl Serializable for Magic {
@@ -107,9 +141,12 @@ impl Serializable for Magic {
 mod tests {
 	use compact::Compact;
 	use super::{
-		Magic, MAGIC_MAINNET, MAGIC_TESTNET, MAGIC_REGTEST, MAGIC_UNITEST,
-		MAX_BITS_MAINNET, MAX_BITS_TESTNET, MAX_BITS_REGTEST,
+		Magic, MAGIC_MAINNET, MAGIC_TESTNET, MAGIC_REGTEST, MAGIC_UNITEST, MAGIC_SIGNET,
+		MAX_BITS_MAINNET, MAX_BITS_TESTNET, MAX_BITS_REGTEST, MAX_BITS_SIGNET,
 	};
+	use primitives::hash::H256;
+	use primitives::bigint::U256;
+	use consensus::DEFAULT_SIGNET_CHALLENGE;
 
 	#[test]
 	fn test_network_magic_number() {
@@ -122,6 +159,9 @@ mod tests {
 		assert_eq!(Magic::Regtest, MAGIC_REGTEST.into());
 		assert_eq!(Magic::Unitest, MAGIC_UNITEST.into());
 		assert_eq!(Magic::Other(1), 1.into());
+		assert_eq!(MAGIC_SIGNET, Magic::signet().into());
+		assert_eq!(Magic::signet(), MAGIC_SIGNET.into());
+		assert_eq!(Magic::custom_signet(&DEFAULT_SIGNET_CHALLENGE.into()), Magic::signet());
 	}
 
 	#[test]
@@ -130,6 +170,7 @@ mod tests {
 		assert_eq!(Magic::Testnet.max_bits(), MAX_BITS_TESTNET.into());
 		assert_eq!(Magic::Regtest.max_bits(), MAX_BITS_REGTEST.into());
 		assert_eq!(Magic::Unitest.max_bits(), Compact::max_value());
+		assert_eq!(Magic::signet().max_bits(), MAX_BITS_SIGNET.into());
 	}
 
 	#[test]
@@ -138,6 +179,7 @@ mod tests {
 		assert_eq!(Magic::Testnet.port(), 18333);
 		assert_eq!(Magic::Regtest.port(), 18444);
 		assert_eq!(Magic::Unitest.port(), 18444);
+		assert_eq!(Magic::signet().port(), 38333);
 	}
 
 	#[test]
@@ -146,5 +188,18 @@ mod tests {
 		assert_eq!(Magic::Testnet.rpc_port(), 18332);
 		assert_eq!(Magic::Regtest.rpc_port(), 18443);
 		assert_eq!(Magic::Unitest.rpc_port(), 18443);
+		assert_eq!(Magic::signet().rpc_port(), 38332);
+	}
+
+	#[test]
+	fn test_network_signet_genesis() {
+		assert_eq!(Magic::signet().genesis_block().hash(), H256::from_reversed_str("00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6"));
+	}
+
+	#[test]
+	fn test_network_minimum_chain_work() {
+		assert!(Magic::Mainnet.default_minimum_chain_work() > Magic::Testnet.default_minimum_chain_work());
+		assert!(Magic::Testnet.default_minimum_chain_work() > U256::zero());
+		assert_eq!(Magic::Regtest.default_minimum_chain_work(), U256::zero());
 	}
 }
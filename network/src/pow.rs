@@ -0,0 +1,25 @@
+//! Proof-of-work function of the chain.
+//!
+//! Bitcoin compares the block hash (double SHA-256 of the header) to the target. Derived chains
+//! may use another function (e.g. scrypt), while keeping the block hash as the block identifier.
+
+use std::fmt;
+use hash::H256;
+use chain::IndexedBlockHeader;
+
+/// Computes the header hash, which must be lower or equal than the target.
+pub trait ProofOfWork: fmt::Debug + Send + Sync {
+	/// Proof-of-work hash of the header, in the same (internal) byte order as the block hash.
+	fn pow_hash(&self, header: &IndexedBlockHeader) -> H256;
+}
+
+/// Double SHA-256 proof of work, used by Bitcoin.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sha256dProofOfWork;
+
+impl ProofOfWork for Sha256dProofOfWork {
+	fn pow_hash(&self, header: &IndexedBlockHeader) -> H256 {
+		// block hash is already double SHA-256 of the header
+		header.hash.clone()
+	}
+}
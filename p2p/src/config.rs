@@ -1,7 +1,8 @@
 use std::{net, path};
+use std::sync::Arc;
 use message::common::Services;
 use net::Config as NetConfig;
-use util::InternetProtocol;
+use util::{InternetProtocol, WhitelistEntry, WhiteBind, NetworkTime};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -9,18 +10,45 @@ pub struct Config {
 	pub threads: usize,
 	/// Number of inbound connections.
 	pub inbound_connections: u32,
-	/// Number of outbound connections.
+	/// Number of outbound full-relay connections.
 	pub outbound_connections: u32,
+	/// Number of outbound block-relay-only connections.
+	pub block_relay_connections: u32,
 	/// Configuration for every connection.
 	pub connection: NetConfig,
 	/// Connect only ot these nodes.
 	pub peers: Vec<net::SocketAddr>,
 	/// Connect to these nodes to retrieve peer addresses, and disconnect.
 	pub seeds: Vec<String>,
-	/// p2p/nodes.csv file path.
-	pub node_table_path: path::PathBuf,
-	/// Peers with this services will get a boost in node_table.
+	/// p2p/peers.dat file path.
+	pub address_manager_path: path::PathBuf,
+	/// p2p/banlist.csv file path.
+	pub ban_list_path: path::PathBuf,
+	/// Peers with this services are more likely to be selected for connection.
 	pub preferable_services: Services,
 	/// Internet protocol.
 	pub internet_protocol: InternetProtocol,
+	/// SOCKS5 proxy for all outbound connections.
+	pub proxy: Option<net::SocketAddr>,
+	/// SOCKS5 proxy for connections to onion services. Defaults to `proxy`.
+	pub onion: Option<net::SocketAddr>,
+	/// I2P SAM bridge for connections to I2P nodes and from them.
+	pub i2p_sam: Option<net::SocketAddr>,
+	/// p2p/i2p_private_key file path.
+	pub i2p_private_key_path: path::PathBuf,
+	/// Inbound peers from these subnets are granted permissions.
+	pub whitelist: Vec<WhitelistEntry>,
+	/// Also listen on these addresses, granting permissions to peers, connected to them.
+	pub whitebind: Vec<WhiteBind>,
+	/// Maximal number of bytes to send during 24 hours. Historical blocks aren't served, when it is reached.
+	pub max_upload_target: Option<u64>,
+	/// Network-adjusted time, sampled from `version` messages of outbound peers.
+	pub network_time: Arc<NetworkTime>,
+}
+
+impl Config {
+	/// Returns SOCKS5 proxy for connections to onion services, if they are reachable.
+	pub fn onion_proxy(&self) -> Option<net::SocketAddr> {
+		self.onion.or(self.proxy)
+	}
 }
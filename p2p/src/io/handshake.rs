@@ -1,10 +1,10 @@
 use std::{io, cmp};
 use futures::{Future, Poll, Async};
 use tokio_io::{AsyncRead, AsyncWrite};
-use message::{Message, MessageResult, Error};
-use message::types::{Version, Verack};
+use message::{Message, MessageResult, Error, Payload};
+use message::types::{Version, Verack, SendAddrV2};
 use network::Magic;
-use io::{write_message, WriteMessage, ReadMessage, read_message};
+use io::{write_message, WriteMessage, ReadMessage, read_message, read_header, ReadHeader, read_payload, ReadPayload};
 
 pub fn handshake<A>(a: A, magic: Magic, version: Version, min_version: u32) -> Handshake<A> where A: AsyncWrite + AsyncRead {
 	Handshake {
@@ -13,6 +13,7 @@ pub fn handshake<A>(a: A, magic: Magic, version: Version, min_version: u32) -> H
 		state: HandshakeState::SendVersion(write_message(a, version_message(magic, version))),
 		magic: magic,
 		min_version: min_version,
+		addrv2: false,
 	}
 }
 
@@ -37,6 +38,8 @@ pub fn negotiate_version(local: u32, other: u32) -> u32 {
 pub struct HandshakeResult {
 	pub version: Version,
 	pub negotiated_version: u32,
+	/// Has remote node sent `sendaddrv2` before `verack`?
+	pub addrv2: bool,
 }
 
 fn version_message(magic: Magic, version: Version) -> Message<Version> {
@@ -47,16 +50,32 @@ fn verack_message(magic: Magic) -> Message<Verack> {
 	Message::new(magic, 0, &Verack).expect("verack message should always be serialized correctly")
 }
 
+fn sendaddrv2_message(magic: Magic) -> Message<SendAddrV2> {
+	Message::new(magic, 0, &SendAddrV2).expect("sendaddrv2 message should always be serialized correctly")
+}
+
 enum HandshakeState<A> {
 	SendVersion(WriteMessage<Version, A>),
 	ReceiveVersion(ReadMessage<Version, A>),
+	SendAddrV2 {
+		version: Option<Version>,
+		future: WriteMessage<SendAddrV2, A>,
+	},
 	SendVerack {
 		version: Option<Version>,
 		future: WriteMessage<Verack, A>,
 	},
 	ReceiveVerack {
 		version: Option<Version>,
-		future: ReadMessage<Verack, A>,
+		future: ReadHeader<A>,
+	},
+	ReceiveAddrV2 {
+		version: Option<Version>,
+		future: ReadPayload<SendAddrV2, A>,
+	},
+	ReceiveVerackPayload {
+		version: Option<Version>,
+		future: ReadPayload<Verack, A>,
 	},
 }
 
@@ -69,6 +88,10 @@ enum AcceptHandshakeState<A> {
 		version: Option<Version>,
 		future: WriteMessage<Version, A>,
 	},
+	SendAddrV2 {
+		version: Option<Version>,
+		future: WriteMessage<SendAddrV2, A>,
+	},
 	SendVerack {
 		version: Option<Version>,
 		future: WriteMessage<Verack, A>,
@@ -81,6 +104,7 @@ pub struct Handshake<A> {
 	version: u32,
 	nonce: Option<u64>,
 	min_version: u32,
+	addrv2: bool,
 }
 
 pub struct AcceptHandshake<A> {
@@ -118,8 +142,16 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 						}
 					}
 
-					HandshakeState::SendVerack {
+					// BIP155: sendaddrv2 is sent after receiving version and before sending verack
+					HandshakeState::SendAddrV2 {
 						version: Some(version),
+						future: write_message(stream, sendaddrv2_message(self.magic)),
+					}
+				},
+				HandshakeState::SendAddrV2 { ref mut version, ref mut future } => {
+					let (stream, _) = try_ready!(future.poll());
+					HandshakeState::SendVerack {
+						version: version.take(),
 						future: write_message(stream, verack_message(self.magic)),
 					}
 				},
@@ -130,18 +162,48 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 
 					HandshakeState::ReceiveVerack {
 						version: Some(version),
-						future: read_message(stream, self.magic, 0),
+						future: read_header(stream, self.magic),
 					}
 				},
 				HandshakeState::ReceiveVerack { ref mut version, ref mut future } => {
+					let (stream, header) = try_ready!(future.poll());
+					match header {
+						// remote node may send sendaddrv2 before verack
+						Ok(ref header) if header.command == SendAddrV2::command() => HandshakeState::ReceiveAddrV2 {
+							version: version.take(),
+							future: read_payload(stream, 0, header.len as usize, header.checksum.clone()),
+						},
+						Ok(ref header) if header.command == Verack::command() => HandshakeState::ReceiveVerackPayload {
+							version: version.take(),
+							future: read_payload(stream, 0, header.len as usize, header.checksum.clone()),
+						},
+						_ => {
+							let version = version.take().expect("verack must be preceded by version");
+							let result = HandshakeResult {
+								negotiated_version: negotiate_version(self.version, version.version()),
+								version: version,
+								addrv2: self.addrv2,
+							};
+							return Ok(Async::Ready((stream, Ok(result))));
+						},
+					}
+				},
+				HandshakeState::ReceiveAddrV2 { ref mut version, ref mut future } => {
+					let (stream, _sendaddrv2) = try_ready!(future.poll());
+					self.addrv2 = true;
+					HandshakeState::ReceiveVerack {
+						version: version.take(),
+						future: read_header(stream, self.magic),
+					}
+				},
+				HandshakeState::ReceiveVerackPayload { ref mut version, ref mut future } => {
 					let (stream, _verack) = try_ready!(future.poll());
 					let version = version.take().expect("verack must be preceded by version");
-
 					let result = HandshakeResult {
 						negotiated_version: negotiate_version(self.version, version.version()),
 						version: version,
+						addrv2: self.addrv2,
 					};
-
 					return Ok(Async::Ready((stream, Ok(result))));
 				},
 			};
@@ -180,6 +242,13 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 					}
 				},
 				AcceptHandshakeState::SendVersion { ref mut version, ref mut future } => {
+					let (stream, _) = try_ready!(future.poll());
+					AcceptHandshakeState::SendAddrV2 {
+						version: version.take(),
+						future: write_message(stream, sendaddrv2_message(self.magic)),
+					}
+				},
+				AcceptHandshakeState::SendAddrV2 { ref mut version, ref mut future } => {
 					let (stream, _) = try_ready!(future.poll());
 					AcceptHandshakeState::SendVerack {
 						version: version.take(),
@@ -191,9 +260,11 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 
 					let version = version.take().expect("verack must be preceded by version");
 
+					// sendaddrv2 of the remote node is received after the handshake
 					let result = HandshakeResult {
 						negotiated_version: negotiate_version(self.version, version.version()),
 						version: version,
+						addrv2: false,
 					};
 
 					return Ok(Async::Ready((stream, Ok(result))));
@@ -213,7 +284,7 @@ mod tests {
 	use ser::Stream;
 	use network::Magic;
 	use message::{Message, Error};
-	use message::types::Verack;
+	use message::types::{Verack, SendAddrV2};
 	use message::types::version::{Version, V0, V106, V70001};
 	use super::{handshake, accept_handshake, HandshakeResult};
 
@@ -299,10 +370,12 @@ mod tests {
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addrv2: false,
 		};
 
 		let mut expected_stream = Stream::new();
 		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
 		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
 
 		let test_io = TestIo {
@@ -315,6 +388,34 @@ mod tests {
 		assert_eq!(hs.1.unwrap(), expected);
 	}
 
+	#[test]
+	fn test_handshake_with_addrv2() {
+		let magic = Magic::Mainnet;
+		let version = 70012;
+		let local_version = local_version();
+		let remote_version = remote_version();
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: 70001,
+			addrv2: true,
+		};
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = handshake(test_io, magic, local_version, 0).wait().unwrap();
+		assert_eq!(hs.1.unwrap(), expected);
+		assert_eq!(hs.0.read.position() as usize, hs.0.read.get_ref().len());
+	}
+
 	#[test]
 	fn test_accept_handshake() {
 		let magic = Magic::Mainnet;
@@ -333,10 +434,12 @@ mod tests {
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addrv2: false,
 		};
 
 		let mut expected_stream = Stream::new();
 		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
 		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
 
 		let hs = accept_handshake(test_io, magic, local_version, 0).wait().unwrap();
@@ -4,7 +4,9 @@ mod read_header;
 mod read_message;
 mod read_any_message;
 mod read_payload;
+mod sam;
 mod sharedtcpstream;
+mod socks5;
 mod write_message;
 
 pub use self::deadline::{deadline, Deadline, DeadlineStatus};
@@ -15,5 +17,7 @@ pub use self::read_header::{read_header, ReadHeader};
 pub use self::read_payload::{read_payload, ReadPayload};
 pub use self::read_message::{read_message, ReadMessage};
 pub use self::read_any_message::{read_any_message, ReadAnyMessage};
+pub use self::sam::{sam_create_session, sam_connect, sam_accept, SamCreateSession, SamConnect, SamAccept};
 pub use self::sharedtcpstream::SharedTcpStream;
+pub use self::socks5::{socks5_connect, Socks5Connect, Socks5Target};
 pub use self::write_message::{write_message, WriteMessage};
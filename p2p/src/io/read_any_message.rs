@@ -1,13 +1,21 @@
-use std::io;
+use std::{io, cmp, mem};
 use futures::{Future, Poll, Async};
-use tokio_io::io::{read_exact, ReadExact};
 use tokio_io::AsyncRead;
+use chain::BlockHeader;
 use crypto::checksum;
 use network::Magic;
-use message::{Error, MessageHeader, MessageResult, Command};
+use message::{Error, MessageHeader, MessageResult, Command, Payload, types};
+use ser::{Deserializable, Reader, Error as ReaderError, CompactInteger, IncrementalReader};
 use bytes::Bytes;
 use io::{read_header, ReadHeader};
 
+/// Maximal size of the message payload.
+pub const MAX_PAYLOAD_SIZE: usize = 0x02000000;
+/// Payload is read in chunks of this size, so memory is allocated only for received bytes.
+const READ_CHUNK_SIZE: usize = 0x10000;
+/// Version, inputs count, outputs count and lock time of the smallest transaction.
+const MIN_TRANSACTION_SIZE: usize = 10;
+
 pub fn read_any_message<A>(a: A, magic: Magic) -> ReadAnyMessage<A> where A: AsyncRead {
 	ReadAnyMessage {
 		state: ReadAnyMessageState::ReadHeader(read_header(a, magic)),
@@ -18,10 +26,44 @@ pub enum ReadAnyMessageState<A> {
 	ReadHeader(ReadHeader<A>),
 	ReadPayload {
 		header: MessageHeader,
-		future: ReadExact<A, Bytes>
+		stream: A,
+		payload: Vec<u8>,
+		/// Decodes the beginning of the `block` payload, until the transactions count is known.
+		block_prefix: Option<IncrementalReader>,
 	},
 }
 
+/// Block header and transactions count, starting the `block` payload.
+struct BlockPrefix {
+	transactions: CompactInteger,
+}
+
+impl Deserializable for BlockPrefix {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let _header: BlockHeader = try!(reader.read());
+		Ok(BlockPrefix {
+			transactions: try!(reader.read()),
+		})
+	}
+}
+
+/// Feeds received block bytes to the reader.
+/// Returns true when the prefix is decoded and the transactions count fits into the payload.
+fn check_block_prefix(reader: &mut IncrementalReader, data: &[u8], payload_len: usize) -> Result<bool, ReaderError> {
+	try!(reader.feed(data));
+	let prefix: BlockPrefix = match try!(reader.read()) {
+		Some(prefix) => prefix,
+		None => return Ok(false),
+	};
+
+	let remaining = payload_len - (reader.received() - reader.buffered());
+	if u64::from(prefix.transactions) > (remaining / MIN_TRANSACTION_SIZE) as u64 {
+		return Err(ReaderError::MalformedData);
+	}
+
+	Ok(true)
+}
+
 pub struct ReadAnyMessage<A> {
 	state: ReadAnyMessageState<A>,
 }
@@ -39,17 +81,58 @@ impl<A> Future for ReadAnyMessage<A> where A: AsyncRead {
 						Ok(header) => header,
 						Err(err) => return Ok(Err(err).into()),
 					};
+					if header.len as usize > MAX_PAYLOAD_SIZE {
+						return Ok(Err(Error::PayloadTooLarge).into());
+					}
+
+					let block_prefix = if header.command == types::Block::command() {
+						Some(IncrementalReader::new(header.len as usize))
+					} else {
+						None
+					};
+
 					ReadAnyMessageState::ReadPayload {
-						future: read_exact(stream, Bytes::new_with_len(header.len as usize)),
 						header: header,
+						stream: stream,
+						payload: Vec::new(),
+						block_prefix: block_prefix,
 					}
 				},
-				ReadAnyMessageState::ReadPayload { ref mut header, ref mut future } => {
-					let (_stream, bytes) = try_ready!(future.poll());
-					if checksum(&bytes) != header.checksum {
+				ReadAnyMessageState::ReadPayload { ref header, ref mut stream, ref mut payload, ref mut block_prefix } => {
+					let len = header.len as usize;
+					while payload.len() < len {
+						let filled = payload.len();
+						payload.resize(filled + cmp::min(READ_CHUNK_SIZE, len - filled), 0);
+						let read = match stream.read(&mut payload[filled..]) {
+							Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "early eof")),
+							Ok(read) => read,
+							Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+								payload.truncate(filled);
+								return Ok(Async::NotReady);
+							},
+							Err(err) => return Err(err),
+						};
+						payload.truncate(filled + read);
+
+						// malformed block is rejected before the rest of its payload is received
+						let prefix_checked = match *block_prefix {
+							Some(ref mut reader) => match check_block_prefix(reader, &payload[filled..], len) {
+								Ok(checked) => checked,
+								Err(err) => return Ok(Err(err.into()).into()),
+							},
+							None => false,
+						};
+
+						if prefix_checked {
+							*block_prefix = None;
+						}
+					}
+
+					if checksum(&payload[..]) != header.checksum {
 						return Ok(Err(Error::InvalidChecksum).into());
 					}
 
+					let bytes: Bytes = mem::replace(payload, Vec::new()).into();
 					return Ok(Async::Ready(Ok((header.command.clone(), bytes))));
 				},
 			};
@@ -90,4 +173,19 @@ mod tests {
 		let raw: Bytes = "f9beb4d970696e6700000000000000000800000083c01c765845303b6da97786".into();
 		assert_eq!(read_any_message(raw.as_ref(), Magic::Mainnet).wait().unwrap(), Err(Error::InvalidChecksum));
 	}
+
+	#[test]
+	fn test_read_any_message_with_too_large_payload() {
+		let raw: Bytes = "f9beb4d970696e670000000000000000010000025845303b".into();
+		assert_eq!(read_any_message(raw.as_ref(), Magic::Mainnet).wait().unwrap(), Err(Error::PayloadTooLarge));
+	}
+
+	#[test]
+	fn test_read_block_with_too_many_transactions() {
+		// only the header and transactions count of the 1000-bytes block are sent
+		let header = "f9beb4d9626c6f636b00000000000000e803000000000000";
+		let block_header = "00".repeat(80);
+		let raw: Bytes = format!("{}{}feffffffff", header, block_header).parse().unwrap();
+		assert_eq!(read_any_message(raw.as_ref(), Magic::Mainnet).wait().unwrap(), Err(Error::Deserialize));
+	}
 }
@@ -0,0 +1,319 @@
+use std::io;
+use futures::{Future, Poll, Async};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_io::io::{write_all, WriteAll, read_exact, ReadExact};
+use util::I2pAddress;
+
+const HELLO: &'static [u8] = b"HELLO VERSION MIN=3.1 MAX=3.1\n";
+/// Maximal length of the SAM bridge reply. Replies with private keys are about 1KB long.
+const MAX_REPLY_LEN: usize = 4096;
+/// Signature type of the new destinations (EdDSA_SHA512_Ed25519).
+const SIGNATURE_TYPE: u8 = 7;
+
+/// Asks SAM bridge, connected with the stream, to create streaming session with given id (SAM v3.1).
+/// New I2P destination is created if `private_key` is None. Resolves to the same stream, which
+/// must be kept open while the session is used, and private key of the session destination.
+pub fn sam_create_session<A>(a: A, session_id: &str, private_key: Option<&str>) -> SamCreateSession<A> where A: AsyncRead + AsyncWrite {
+	let command = format!("SESSION CREATE STYLE=STREAM ID={} DESTINATION={} SIGNATURE_TYPE={}\n",
+		session_id, private_key.unwrap_or("TRANSIENT"), SIGNATURE_TYPE);
+	SamCreateSession {
+		request: sam_request(a, command, "SESSION STATUS"),
+	}
+}
+
+/// Asks SAM bridge, connected with the stream, to connect to the I2P destination using the session.
+/// Resolves to the same stream, which is connected to the destination after that.
+pub fn sam_connect<A>(a: A, session_id: &str, destination: &I2pAddress) -> SamConnect<A> where A: AsyncRead + AsyncWrite {
+	let command = format!("STREAM CONNECT ID={} DESTINATION={} SILENT=false\n", session_id, destination);
+	SamConnect {
+		request: sam_request(a, command, "STREAM STATUS"),
+	}
+}
+
+/// Asks SAM bridge, connected with the stream, to accept the next incoming connection of the session.
+/// Resolves to the same stream, which is connected to the peer, and the peer address.
+pub fn sam_accept<A>(a: A, session_id: &str) -> SamAccept<A> where A: AsyncRead + AsyncWrite {
+	let command = format!("STREAM ACCEPT ID={} SILENT=false\n", session_id);
+	SamAccept {
+		state: SamAcceptState::Request(sam_request(a, command, "STREAM STATUS")),
+	}
+}
+
+fn sam_error(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, format!("I2P SAM bridge: {}", message))
+}
+
+/// Returns value of the `KEY=VALUE` field of the reply.
+fn reply_value<'a>(reply: &'a str, key: &str) -> Option<&'a str> {
+	reply.split_whitespace()
+		.filter_map(|field| {
+			let mut parts = field.splitn(2, '=');
+			match (parts.next(), parts.next()) {
+				(Some(field_key), Some(value)) if field_key == key => Some(value),
+				_ => None,
+			}
+		})
+		.next()
+}
+
+/// Checks that the reply has expected type and is successful.
+fn check_reply(reply: &str, kind: &str) -> Result<(), io::Error> {
+	if !reply.starts_with(kind) {
+		return Err(sam_error(&format!("unexpected reply `{}`", reply)));
+	}
+	if reply_value(reply, "RESULT") != Some("OK") {
+		return Err(sam_error(&format!("request failed with `{}`", reply)));
+	}
+	Ok(())
+}
+
+/// Reads the newline-terminated line. Bytes are read one by one, because everything after
+/// the line belongs to the I2P stream.
+fn read_line<A>(a: A) -> ReadLine<A> where A: AsyncRead {
+	ReadLine {
+		future: read_exact(a, [0u8; 1]),
+		line: Vec::new(),
+	}
+}
+
+struct ReadLine<A> {
+	future: ReadExact<A, [u8; 1]>,
+	line: Vec<u8>,
+}
+
+impl<A> Future for ReadLine<A> where A: AsyncRead {
+	type Item = (A, String);
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let (stream, byte) = try_ready!(self.future.poll());
+			if byte[0] == b'\n' {
+				let line = String::from_utf8(self.line.split_off(0)).map_err(|_| sam_error("invalid reply"))?;
+				return Ok(Async::Ready((stream, line)));
+			}
+
+			if self.line.len() == MAX_REPLY_LEN {
+				return Err(sam_error("reply is too long"));
+			}
+
+			self.line.push(byte[0]);
+			self.future = read_exact(stream, [0u8; 1]);
+		}
+	}
+}
+
+/// Sends greeting, then command, and resolves to the command reply.
+fn sam_request<A>(a: A, command: String, kind: &'static str) -> SamRequest<A> where A: AsyncRead + AsyncWrite {
+	SamRequest {
+		state: SamRequestState::SendHello(write_all(a, HELLO)),
+		command: Some(command.into_bytes()),
+		kind: kind,
+	}
+}
+
+enum SamRequestState<A> {
+	SendHello(WriteAll<A, &'static [u8]>),
+	ReceiveHello(ReadLine<A>),
+	SendCommand(WriteAll<A, Vec<u8>>),
+	ReceiveReply(ReadLine<A>),
+}
+
+struct SamRequest<A> {
+	state: SamRequestState<A>,
+	command: Option<Vec<u8>>,
+	/// Expected type of the command reply
+	kind: &'static str,
+}
+
+impl<A> Future for SamRequest<A> where A: AsyncRead + AsyncWrite {
+	type Item = (A, String);
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let next_state = match self.state {
+				SamRequestState::SendHello(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					SamRequestState::ReceiveHello(read_line(stream))
+				},
+				SamRequestState::ReceiveHello(ref mut future) => {
+					let (stream, reply) = try_ready!(future.poll());
+					check_reply(&reply, "HELLO REPLY")?;
+					let command = self.command.take().expect("command is sent once");
+					SamRequestState::SendCommand(write_all(stream, command))
+				},
+				SamRequestState::SendCommand(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					SamRequestState::ReceiveReply(read_line(stream))
+				},
+				SamRequestState::ReceiveReply(ref mut future) => {
+					let (stream, reply) = try_ready!(future.poll());
+					check_reply(&reply, self.kind)?;
+					return Ok(Async::Ready((stream, reply)));
+				},
+			};
+			self.state = next_state;
+		}
+	}
+}
+
+pub struct SamCreateSession<A> {
+	request: SamRequest<A>,
+}
+
+impl<A> Future for SamCreateSession<A> where A: AsyncRead + AsyncWrite {
+	type Item = (A, String);
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		let (stream, reply) = try_ready!(self.request.poll());
+		let private_key = reply_value(&reply, "DESTINATION").ok_or_else(|| sam_error("session destination is missing"))?;
+		Ok(Async::Ready((stream, private_key.to_owned())))
+	}
+}
+
+pub struct SamConnect<A> {
+	request: SamRequest<A>,
+}
+
+impl<A> Future for SamConnect<A> where A: AsyncRead + AsyncWrite {
+	type Item = A;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		let (stream, _) = try_ready!(self.request.poll());
+		Ok(Async::Ready(stream))
+	}
+}
+
+enum SamAcceptState<A> {
+	Request(SamRequest<A>),
+	/// Waiting for the destination of the connecting peer
+	ReceivePeer(ReadLine<A>),
+}
+
+pub struct SamAccept<A> {
+	state: SamAcceptState<A>,
+}
+
+impl<A> Future for SamAccept<A> where A: AsyncRead + AsyncWrite {
+	type Item = (A, I2pAddress);
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let next_state = match self.state {
+				SamAcceptState::Request(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					SamAcceptState::ReceivePeer(read_line(stream))
+				},
+				SamAcceptState::ReceivePeer(ref mut future) => {
+					// destination is followed by FROM_PORT and TO_PORT fields
+					let (stream, line) = try_ready!(future.poll());
+					let address = line.split_whitespace().next()
+						.and_then(I2pAddress::from_destination)
+						.ok_or_else(|| sam_error("invalid peer destination"))?;
+					return Ok(Async::Ready((stream, address)));
+				},
+			};
+			self.state = next_state;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+	use futures::{Future, Poll};
+	use tokio_io::{AsyncRead, AsyncWrite};
+	use util::I2pAddress;
+	use super::{sam_create_session, sam_connect, sam_accept, reply_value};
+
+	struct TestIo {
+		read: io::Cursor<Vec<u8>>,
+		write: Vec<u8>,
+	}
+
+	impl TestIo {
+		fn new(read: &str) -> Self {
+			TestIo {
+				read: io::Cursor::new(read.as_bytes().to_vec()),
+				write: Vec::new(),
+			}
+		}
+	}
+
+	impl io::Read for TestIo {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			io::Read::read(&mut self.read, buf)
+		}
+	}
+
+	impl AsyncRead for TestIo {}
+
+	impl io::Write for TestIo {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			io::Write::write(&mut self.write, buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			io::Write::flush(&mut self.write)
+		}
+	}
+
+	impl AsyncWrite for TestIo {
+		fn shutdown(&mut self) -> Poll<(), io::Error> {
+			Ok(().into())
+		}
+	}
+
+	/// Destination with all-zero keys and null certificate.
+	fn destination() -> String {
+		"A".repeat(516)
+	}
+
+	#[test]
+	fn test_reply_value() {
+		let reply = "SESSION STATUS RESULT=OK DESTINATION=abc==";
+		assert_eq!(reply_value(reply, "RESULT"), Some("OK"));
+		assert_eq!(reply_value(reply, "DESTINATION"), Some("abc=="));
+		assert_eq!(reply_value(reply, "MESSAGE"), None);
+	}
+
+	#[test]
+	fn test_sam_create_session() {
+		let test_io = TestIo::new("HELLO REPLY RESULT=OK VERSION=3.1\nSESSION STATUS RESULT=OK DESTINATION=privatekey\n");
+		let (test_io, private_key) = sam_create_session(test_io, "pbtc", None).wait().unwrap();
+		assert_eq!(private_key, "privatekey");
+		assert_eq!(test_io.write, b"HELLO VERSION MIN=3.1 MAX=3.1\nSESSION CREATE STYLE=STREAM ID=pbtc DESTINATION=TRANSIENT SIGNATURE_TYPE=7\n".to_vec());
+	}
+
+	#[test]
+	fn test_sam_connect() {
+		let address: I2pAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p".parse().unwrap();
+		let test_io = TestIo::new("HELLO REPLY RESULT=OK VERSION=3.1\nSTREAM STATUS RESULT=OK\nversion");
+		let test_io = sam_connect(test_io, "pbtc", &address).wait().unwrap();
+		let expected = "HELLO VERSION MIN=3.1 MAX=3.1\nSTREAM CONNECT ID=pbtc DESTINATION=pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p SILENT=false\n";
+		assert_eq!(test_io.write, expected.as_bytes().to_vec());
+		// data after the reply belongs to the peer
+		assert_eq!(test_io.read.position(), 58);
+	}
+
+	#[test]
+	fn test_sam_connect_failed() {
+		let address: I2pAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p".parse().unwrap();
+		let test_io = TestIo::new("HELLO REPLY RESULT=OK VERSION=3.1\nSTREAM STATUS RESULT=CANT_REACH_PEER\n");
+		assert!(sam_connect(test_io, "pbtc", &address).wait().is_err());
+		let test_io = TestIo::new("HELLO REPLY RESULT=NOVERSION\n");
+		assert!(sam_connect(test_io, "pbtc", &address).wait().is_err());
+	}
+
+	#[test]
+	fn test_sam_accept() {
+		let reply = format!("HELLO REPLY RESULT=OK VERSION=3.1\nSTREAM STATUS RESULT=OK\n{} FROM_PORT=0 TO_PORT=0\n", destination());
+		let (test_io, address) = sam_accept(TestIo::new(&reply), "pbtc").wait().unwrap();
+		assert_eq!(address, I2pAddress::from_destination(&destination()).unwrap());
+		assert_eq!(test_io.write, b"HELLO VERSION MIN=3.1 MAX=3.1\nSTREAM ACCEPT ID=pbtc SILENT=false\n".to_vec());
+	}
+}
@@ -0,0 +1,202 @@
+use std::io;
+use std::net::{SocketAddr, IpAddr};
+use futures::{Future, Poll, Async};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_io::io::{write_all, WriteAll, read_exact, ReadExact};
+
+const SOCKS_VERSION: u8 = 5;
+const METHOD_NO_AUTHENTICATION: u8 = 0;
+const COMMAND_CONNECT: u8 = 1;
+const ADDRESS_IPV4: u8 = 1;
+const ADDRESS_DOMAIN: u8 = 3;
+const ADDRESS_IPV6: u8 = 4;
+const REPLY_SUCCEEDED: u8 = 0;
+
+/// Destination of the connection, established by the SOCKS5 proxy.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Socks5Target {
+	Ip(SocketAddr),
+	/// Host name, resolved by the proxy, and port
+	Domain(String, u16),
+}
+
+/// Asks SOCKS5 proxy, connected with the stream, to connect to the target (RFC 1928).
+/// Resolves to the same stream, which is connected to the target after that.
+pub fn socks5_connect<A>(a: A, target: &Socks5Target) -> Socks5Connect<A> where A: AsyncRead + AsyncWrite {
+	Socks5Connect {
+		state: Socks5ConnectState::SendGreeting(write_all(a, [SOCKS_VERSION, 1, METHOD_NO_AUTHENTICATION])),
+		request: Some(connect_request(target)),
+	}
+}
+
+fn connect_request(target: &Socks5Target) -> Vec<u8> {
+	let mut request = vec![SOCKS_VERSION, COMMAND_CONNECT, 0];
+	let port = match *target {
+		Socks5Target::Ip(ref address) => {
+			match address.ip() {
+				IpAddr::V4(ip) => {
+					request.push(ADDRESS_IPV4);
+					request.extend_from_slice(&ip.octets());
+				},
+				IpAddr::V6(ip) => {
+					request.push(ADDRESS_IPV6);
+					request.extend_from_slice(&ip.octets());
+				},
+			}
+			address.port()
+		},
+		Socks5Target::Domain(ref host, port) => {
+			request.push(ADDRESS_DOMAIN);
+			request.push(host.len() as u8);
+			request.extend_from_slice(host.as_bytes());
+			port
+		},
+	};
+
+	request.push((port >> 8) as u8);
+	request.push(port as u8);
+	request
+}
+
+fn proxy_error(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, format!("SOCKS5 proxy: {}", message))
+}
+
+enum Socks5ConnectState<A> {
+	SendGreeting(WriteAll<A, [u8; 3]>),
+	ReceiveMethod(ReadExact<A, [u8; 2]>),
+	SendRequest(WriteAll<A, Vec<u8>>),
+	/// Version, reply code, reserved byte, address type and the first byte of the bound address
+	ReceiveReply(ReadExact<A, [u8; 5]>),
+	ReceiveBoundAddress(ReadExact<A, Vec<u8>>),
+}
+
+pub struct Socks5Connect<A> {
+	state: Socks5ConnectState<A>,
+	request: Option<Vec<u8>>,
+}
+
+impl<A> Future for Socks5Connect<A> where A: AsyncRead + AsyncWrite {
+	type Item = A;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let next_state = match self.state {
+				Socks5ConnectState::SendGreeting(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					Socks5ConnectState::ReceiveMethod(read_exact(stream, [0u8; 2]))
+				},
+				Socks5ConnectState::ReceiveMethod(ref mut future) => {
+					let (stream, method) = try_ready!(future.poll());
+					if method != [SOCKS_VERSION, METHOD_NO_AUTHENTICATION] {
+						return Err(proxy_error("authentication is required"));
+					}
+
+					let request = self.request.take().expect("request is sent once");
+					Socks5ConnectState::SendRequest(write_all(stream, request))
+				},
+				Socks5ConnectState::SendRequest(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					Socks5ConnectState::ReceiveReply(read_exact(stream, [0u8; 5]))
+				},
+				Socks5ConnectState::ReceiveReply(ref mut future) => {
+					let (stream, reply) = try_ready!(future.poll());
+					if reply[0] != SOCKS_VERSION {
+						return Err(proxy_error("invalid reply"));
+					}
+					if reply[1] != REPLY_SUCCEEDED {
+						return Err(proxy_error(&format!("connection failed with code {}", reply[1])));
+					}
+
+					// the rest of the bound address and port are read and ignored
+					let remaining = match reply[3] {
+						ADDRESS_IPV4 => 3 + 2,
+						ADDRESS_IPV6 => 15 + 2,
+						ADDRESS_DOMAIN => reply[4] as usize + 2,
+						_ => return Err(proxy_error("invalid bound address")),
+					};
+					Socks5ConnectState::ReceiveBoundAddress(read_exact(stream, vec![0u8; remaining]))
+				},
+				Socks5ConnectState::ReceiveBoundAddress(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					return Ok(Async::Ready(stream));
+				},
+			};
+			self.state = next_state;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+	use futures::{Future, Poll};
+	use tokio_io::{AsyncRead, AsyncWrite};
+	use super::{socks5_connect, connect_request, Socks5Target};
+
+	struct TestIo {
+		read: io::Cursor<Vec<u8>>,
+		write: Vec<u8>,
+	}
+
+	impl io::Read for TestIo {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			io::Read::read(&mut self.read, buf)
+		}
+	}
+
+	impl AsyncRead for TestIo {}
+
+	impl io::Write for TestIo {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			io::Write::write(&mut self.write, buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			io::Write::flush(&mut self.write)
+		}
+	}
+
+	impl AsyncWrite for TestIo {
+		fn shutdown(&mut self) -> Poll<(), io::Error> {
+			Ok(().into())
+		}
+	}
+
+	#[test]
+	fn test_connect_request() {
+		let target = Socks5Target::Ip("10.0.0.1:8333".parse().unwrap());
+		assert_eq!(connect_request(&target), vec![5, 1, 0, 1, 10, 0, 0, 1, 0x20, 0x8d]);
+		let target = Socks5Target::Domain("example.onion".into(), 8333);
+		let mut expected = vec![5, 1, 0, 3, 13];
+		expected.extend_from_slice(b"example.onion");
+		expected.extend_from_slice(&[0x20, 0x8d]);
+		assert_eq!(connect_request(&target), expected);
+	}
+
+	#[test]
+	fn test_socks5_connect() {
+		let test_io = TestIo {
+			read: io::Cursor::new(vec![5, 0, 5, 0, 0, 1, 127, 0, 0, 1, 0x20, 0x8d, 0xff]),
+			write: Vec::new(),
+		};
+
+		let target = Socks5Target::Ip("10.0.0.1:8333".parse().unwrap());
+		let test_io = socks5_connect(test_io, &target).wait().unwrap();
+		assert_eq!(test_io.write, vec![5, 1, 0, 5, 1, 0, 1, 10, 0, 0, 1, 0x20, 0x8d]);
+		// data after the reply belongs to the target
+		assert_eq!(test_io.read.position(), 12);
+	}
+
+	#[test]
+	fn test_socks5_connect_failed() {
+		let test_io = TestIo {
+			read: io::Cursor::new(vec![5, 0, 5, 4, 0, 1, 0, 0, 0, 0, 0, 0]),
+			write: Vec::new(),
+		};
+
+		let target = Socks5Target::Domain("example.onion".into(), 8333);
+		assert!(socks5_connect(test_io, &target).wait().is_err());
+	}
+}
@@ -13,10 +13,12 @@ extern crate ns_dns_tokio;
 extern crate csv;
 
 extern crate bitcrypto as crypto;
+extern crate chain;
 extern crate message;
 extern crate primitives;
 extern crate serialization as ser;
 extern crate network;
+extern crate logs;
 
 mod io;
 mod net;
@@ -30,8 +32,8 @@ mod p2p;
 pub use primitives::{hash, bytes};
 
 pub use config::Config;
-pub use net::Config as NetConfig;
+pub use net::{Config as NetConfig, PeerStats, TrafficCounter, UploadTarget};
 pub use p2p::{P2P, Context};
 pub use event_loop::{event_loop, forever};
-pub use util::{NodeTableError, PeerId, PeerInfo, InternetProtocol, Direction};
+pub use util::{AddressManagerError, PeerId, PeerInfo, InternetProtocol, Direction, ConnectionType, PeerPermissions, WhitelistEntry, WhiteBind, BanEntry, BanListError, DEFAULT_BAN_TIME, NetworkTime};
 pub use protocol::{InboundSyncConnection, InboundSyncConnectionRef, OutboundSyncConnection, OutboundSyncConnectionRef, LocalSyncNode, LocalSyncNodeRef};
@@ -10,7 +10,7 @@ use net::{Config, Connection};
 
 pub fn accept_connection(stream: TcpStream, handle: &Handle, config: &Config, address: net::SocketAddr) -> Deadline<AcceptConnection> {
 	let accept = AcceptConnection {
-		handshake: accept_handshake(stream, config.magic, config.version(&address), config.protocol_minimum),
+		handshake: accept_handshake(stream, config.magic, config.version(&address, config.relay), config.protocol_minimum),
 		magic: config.magic,
 		address: address,
 	};
@@ -41,6 +41,7 @@ impl Future for AcceptConnection {
 			version_message: result.version, 
 			magic: self.magic,
 			address: self.address,
+			addrv2: result.addrv2,
 		};
 		Ok(Ok(connection).into())
 	}
@@ -18,7 +18,8 @@ pub struct Config {
 }
 
 impl Config {
-	pub fn version(&self, to: &SocketAddr) -> Version {
+	/// Version message, sent to given peer. Peer is asked not to relay transactions, unless `relay` is set.
+	pub fn version(&self, to: &SocketAddr, relay: bool) -> Version {
 		Version::V70001(V0 {
 			version: self.protocol_version,
 			services: self.services,
@@ -38,7 +39,7 @@ impl Config {
 			user_agent: self.user_agent.clone(),
 			start_height: self.start_height,
 		}, V70001 {
-			relay: self.relay,
+			relay: relay,
 		})
 	}
 }
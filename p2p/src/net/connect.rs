@@ -7,21 +7,45 @@ use tokio_core::net::{TcpStream, TcpStreamNew};
 use network::Magic;
 use message::Error;
 use message::types::Version;
-use io::{handshake, Handshake, Deadline, deadline};
+use io::{handshake, Handshake, Deadline, deadline, socks5_connect, Socks5Connect, Socks5Target, sam_connect, SamConnect};
 use net::{Config, Connection};
+use util::I2pAddress;
+
+/// Connections through proxy (especially to onion services) take more time.
+const PROXY_CONNECT_TIMEOUT: u64 = 20;
+/// Connections to I2P nodes are established through tunnels, which take even more time to build.
+const I2P_CONNECT_TIMEOUT: u64 = 60;
+
+/// The way outbound connection is established.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Route {
+	Direct,
+	/// Through the SOCKS5 proxy
+	Proxy(SocketAddr, Socks5Target),
+	/// Through the I2P SAM bridge, using the session with given id
+	I2p(SocketAddr, String, I2pAddress),
+}
+
+/// Connects to the node using given route. Node is asked not to relay transactions, unless `relay` is set.
+pub fn connect(address: &SocketAddr, route: Route, handle: &Handle, config: &Config, relay: bool) -> Deadline<Connect> {
+	let (timeout, connect_to) = match route {
+		Route::Direct => (5, *address),
+		Route::Proxy(ref proxy, _) => (PROXY_CONNECT_TIMEOUT, *proxy),
+		Route::I2p(ref sam, _, _) => (I2P_CONNECT_TIMEOUT, *sam),
+	};
 
-pub fn connect(address: &SocketAddr, handle: &Handle, config: &Config) -> Deadline<Connect> {
 	let connect = Connect {
 		state: ConnectState::TcpConnect {
-			future: TcpStream::connect(address, handle),
-			version: Some(config.version(address)),
+			future: TcpStream::connect(&connect_to, handle),
+			version: Some(config.version(address, relay)),
 		},
 		magic: config.magic,
 		address: *address,
 		protocol_minimum: config.protocol_minimum,
+		route: route,
 	};
 
-	deadline(Duration::new(5, 0), handle, connect).expect("Failed to create timeout")
+	deadline(Duration::new(timeout, 0), handle, connect).expect("Failed to create timeout")
 }
 
 enum ConnectState {
@@ -29,6 +53,14 @@ enum ConnectState {
 		future: TcpStreamNew,
 		version: Option<Version>,
 	},
+	ProxyConnect {
+		future: Socks5Connect<TcpStream>,
+		version: Option<Version>,
+	},
+	SamConnect {
+		future: SamConnect<TcpStream>,
+		version: Option<Version>,
+	},
 	Handshake(Handshake<TcpStream>),
 	Connected,
 }
@@ -38,6 +70,7 @@ pub struct Connect {
 	magic: Magic,
 	address: SocketAddr,
 	protocol_minimum: u32,
+	route: Route,
 }
 
 impl Future for Connect {
@@ -49,6 +82,30 @@ impl Future for Connect {
 			ConnectState::TcpConnect { ref mut future, ref mut version } => {
 				let stream = try_ready!(future.poll());
 				let version = version.take().expect("state TcpConnect must have version");
+				match self.route {
+					Route::Direct => {
+						let handshake = handshake(stream, self.magic, version, self.protocol_minimum);
+						(ConnectState::Handshake(handshake), Async::NotReady)
+					},
+					Route::Proxy(_, ref target) => {
+						let future = socks5_connect(stream, target);
+						(ConnectState::ProxyConnect { future: future, version: Some(version) }, Async::NotReady)
+					},
+					Route::I2p(_, ref session_id, ref destination) => {
+						let future = sam_connect(stream, session_id, destination);
+						(ConnectState::SamConnect { future: future, version: Some(version) }, Async::NotReady)
+					},
+				}
+			},
+			ConnectState::ProxyConnect { ref mut future, ref mut version } => {
+				let stream = try_ready!(future.poll());
+				let version = version.take().expect("state ProxyConnect must have version");
+				let handshake = handshake(stream, self.magic, version, self.protocol_minimum);
+				(ConnectState::Handshake(handshake), Async::NotReady)
+			},
+			ConnectState::SamConnect { ref mut future, ref mut version } => {
+				let stream = try_ready!(future.poll());
+				let version = version.take().expect("state SamConnect must have version");
 				let handshake = handshake(stream, self.magic, version, self.protocol_minimum);
 				(ConnectState::Handshake(handshake), Async::NotReady)
 			},
@@ -65,6 +122,7 @@ impl Future for Connect {
 					version_message: result.version,
 					magic: self.magic,
 					address: self.address,
+					addrv2: result.addrv2,
 				};
 				(ConnectState::Connected, Async::Ready(Ok(connection)))
 			},
@@ -11,4 +11,5 @@ pub struct Connection {
 	pub magic: Magic,
 	pub services: Services,
 	pub address: net::SocketAddr,
+	pub addrv2: bool,
 }
@@ -1,112 +1,133 @@
 use std::cmp;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use util::ConnectionType;
 
-/// Counts number of open inbound and outbound connections.
+/// Maximum number of simultaneous short-lived (feeler and seednode) connections.
+const MAX_SHORT_LIVED_CONNECTIONS: u32 = 1;
+
+/// Counts number of open connections of every class.
 pub struct ConnectionCounter {
 	/// Current number of inbound connections.
 	current_inbound_connections: AtomicUsize,
-	/// Current number of outbound connections.
+	/// Current number of outbound full-relay connections.
 	current_outbound_connections: AtomicUsize,
+	/// Current number of outbound block-relay-only connections.
+	current_block_relay_connections: AtomicUsize,
+	/// Current number of feeler and seednode connections.
+	current_short_lived_connections: AtomicUsize,
 	/// Maximum number of inbound connections.
 	max_inbound_connections: u32,
-	/// Maximum number of outbound connections.
+	/// Maximum number of outbound full-relay connections.
 	max_outbound_connections: u32,
+	/// Maximum number of outbound block-relay-only connections.
+	max_block_relay_connections: u32,
 }
 
 impl ConnectionCounter {
-	pub fn new(max_inbound_connections: u32, max_outbound_connections: u32) -> Self {
+	pub fn new(max_inbound_connections: u32, max_outbound_connections: u32, max_block_relay_connections: u32) -> Self {
 		ConnectionCounter {
 			current_inbound_connections: AtomicUsize::new(0),
 			current_outbound_connections: AtomicUsize::new(0),
+			current_block_relay_connections: AtomicUsize::new(0),
+			current_short_lived_connections: AtomicUsize::new(0),
 			max_inbound_connections: max_inbound_connections,
 			max_outbound_connections: max_outbound_connections,
+			max_block_relay_connections: max_block_relay_connections,
 		}
 	}
 
-	/// Increases inbound connections counter by 1.
-	pub fn note_new_inbound_connection(&self) {
-		self.current_inbound_connections.fetch_add(1, Ordering::AcqRel);
-	}
-
-	/// Decreases inbound connections counter by 1.
-	/// If it underflows, it means, that there is a logic error.
-	pub fn note_close_inbound_connection(&self) {
-		self.current_inbound_connections.fetch_sub(1, Ordering::AcqRel);
+	fn counter(&self, connection_type: ConnectionType) -> &AtomicUsize {
+		match connection_type {
+			ConnectionType::Inbound => &self.current_inbound_connections,
+			ConnectionType::OutboundFullRelay => &self.current_outbound_connections,
+			ConnectionType::BlockRelayOnly => &self.current_block_relay_connections,
+			ConnectionType::Feeler | ConnectionType::AddrFetch => &self.current_short_lived_connections,
+		}
 	}
 
-	/// Increases outbound connections counter by 1.
-	pub fn note_new_outbound_connection(&self) {
-		self.current_outbound_connections.fetch_add(1, Ordering::AcqRel);
+	/// Increases counter of given connections class by 1.
+	pub fn note_new_connection(&self, connection_type: ConnectionType) {
+		self.counter(connection_type).fetch_add(1, Ordering::AcqRel);
 	}
 
-	/// Decreases outbound connections counter by 1.
+	/// Decreases counter of given connections class by 1.
 	/// If it underflows, it means, that there is a logic error.
-	pub fn note_close_outbound_connection(&self) {
-		self.current_outbound_connections.fetch_sub(1, Ordering::AcqRel);
-	}
-
-	/// Returns number of inbound connections needed to reach the maximum
-	pub fn inbound_connections_needed(&self) -> u32 {
-		let ic = self.inbound_connections();
-		ic.1 - cmp::min(ic.0, ic.1)
+	pub fn note_close_connection(&self, connection_type: ConnectionType) {
+		self.counter(connection_type).fetch_sub(1, Ordering::AcqRel);
 	}
 
-	/// Returns number of inbound connections needed to reach the maximum
-	pub fn outbound_connections_needed(&self) -> u32 {
-		let oc = self.outbound_connections();
-		oc.1 - cmp::min(oc.0, oc.1)
+	/// Returns number of connections of given class needed to reach the maximum
+	pub fn connections_needed(&self, connection_type: ConnectionType) -> u32 {
+		let c = self.connections(connection_type);
+		c.1 - cmp::min(c.0, c.1)
 	}
 
-	/// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
-	pub fn inbound_connections(&self) -> (u32, u32) {
-		let current = self.current_inbound_connections.load(Ordering::Acquire) as u32;
-		(current, self.max_inbound_connections)
-	}
-
-	/// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
-	pub fn outbound_connections(&self) -> (u32, u32) {
-		let current = self.current_outbound_connections.load(Ordering::Acquire) as u32;
-		(current, self.max_outbound_connections)
+	/// Returns a pair of unsigned integers where first element is current number of connections of given class and the second is max.
+	pub fn connections(&self, connection_type: ConnectionType) -> (u32, u32) {
+		let current = self.counter(connection_type).load(Ordering::Acquire) as u32;
+		let max = match connection_type {
+			ConnectionType::Inbound => self.max_inbound_connections,
+			ConnectionType::OutboundFullRelay => self.max_outbound_connections,
+			ConnectionType::BlockRelayOnly => self.max_block_relay_connections,
+			ConnectionType::Feeler | ConnectionType::AddrFetch => MAX_SHORT_LIVED_CONNECTIONS,
+		};
+		(current, max)
 	}
 }
 
 #[cfg(test)]
 mod tests {
+	use util::ConnectionType;
 	use super::ConnectionCounter;
 
 	#[test]
 	fn test_inbound_connection_counter() {
-		let cc = ConnectionCounter::new(5, 10);
-		assert_eq!(cc.inbound_connections_needed(), 5);
-		assert_eq!(cc.inbound_connections(), (0, 5));
-		cc.note_new_inbound_connection();
-		assert_eq!(cc.inbound_connections_needed(), 4);
-		assert_eq!(cc.inbound_connections(), (1, 5));
-		cc.note_new_inbound_connection();
-		cc.note_new_inbound_connection();
-		cc.note_new_inbound_connection();
-		cc.note_new_inbound_connection();
-		assert_eq!(cc.inbound_connections_needed(), 0);
+		let cc = ConnectionCounter::new(5, 10, 2);
+		assert_eq!(cc.connections_needed(ConnectionType::Inbound), 5);
+		assert_eq!(cc.connections(ConnectionType::Inbound), (0, 5));
+		cc.note_new_connection(ConnectionType::Inbound);
+		assert_eq!(cc.connections_needed(ConnectionType::Inbound), 4);
+		assert_eq!(cc.connections(ConnectionType::Inbound), (1, 5));
+		cc.note_new_connection(ConnectionType::Inbound);
+		cc.note_new_connection(ConnectionType::Inbound);
+		cc.note_new_connection(ConnectionType::Inbound);
+		cc.note_new_connection(ConnectionType::Inbound);
+		assert_eq!(cc.connections_needed(ConnectionType::Inbound), 0);
 		// it may exceed max
-		cc.note_new_inbound_connection();
-		assert_eq!(cc.inbound_connections_needed(), 0);
-		assert_eq!(cc.inbound_connections(), (6, 5));
-		cc.note_close_inbound_connection();
-		assert_eq!(cc.inbound_connections_needed(), 0);
-		assert_eq!(cc.inbound_connections(), (5, 5));
+		cc.note_new_connection(ConnectionType::Inbound);
+		assert_eq!(cc.connections_needed(ConnectionType::Inbound), 0);
+		assert_eq!(cc.connections(ConnectionType::Inbound), (6, 5));
+		cc.note_close_connection(ConnectionType::Inbound);
+		assert_eq!(cc.connections_needed(ConnectionType::Inbound), 0);
+		assert_eq!(cc.connections(ConnectionType::Inbound), (5, 5));
 	}
 
 	#[test]
 	fn test_outbound_connection_counter() {
-		let cc = ConnectionCounter::new(0, 4);
-		assert_eq!(cc.outbound_connections_needed(), 4);
-		assert_eq!(cc.outbound_connections(), (0, 4));
-		cc.note_new_outbound_connection();
-		cc.note_new_outbound_connection();
-		assert_eq!(cc.outbound_connections_needed(), 2);
-		assert_eq!(cc.outbound_connections(), (2, 4));
-		cc.note_close_outbound_connection();
-		assert_eq!(cc.outbound_connections_needed(), 3);
-		assert_eq!(cc.outbound_connections(), (1, 4));
+		let cc = ConnectionCounter::new(0, 4, 2);
+		assert_eq!(cc.connections_needed(ConnectionType::OutboundFullRelay), 4);
+		assert_eq!(cc.connections(ConnectionType::OutboundFullRelay), (0, 4));
+		cc.note_new_connection(ConnectionType::OutboundFullRelay);
+		cc.note_new_connection(ConnectionType::OutboundFullRelay);
+		assert_eq!(cc.connections_needed(ConnectionType::OutboundFullRelay), 2);
+		assert_eq!(cc.connections(ConnectionType::OutboundFullRelay), (2, 4));
+		cc.note_close_connection(ConnectionType::OutboundFullRelay);
+		assert_eq!(cc.connections_needed(ConnectionType::OutboundFullRelay), 3);
+		assert_eq!(cc.connections(ConnectionType::OutboundFullRelay), (1, 4));
+	}
+
+	#[test]
+	fn test_connection_classes_have_independent_slots() {
+		let cc = ConnectionCounter::new(0, 4, 2);
+		cc.note_new_connection(ConnectionType::BlockRelayOnly);
+		assert_eq!(cc.connections(ConnectionType::BlockRelayOnly), (1, 2));
+		assert_eq!(cc.connections(ConnectionType::OutboundFullRelay), (0, 4));
+
+		// feelers and seednodes are sharing the single slot
+		assert_eq!(cc.connections_needed(ConnectionType::Feeler), 1);
+		cc.note_new_connection(ConnectionType::AddrFetch);
+		assert_eq!(cc.connections_needed(ConnectionType::Feeler), 0);
+		cc.note_close_connection(ConnectionType::AddrFetch);
+		assert_eq!(cc.connections_needed(ConnectionType::Feeler), 1);
 	}
 }
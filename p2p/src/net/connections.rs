@@ -3,10 +3,10 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::collections::{HashMap, HashSet};
 use parking_lot::RwLock;
-use net::{Connection, Channel};
+use net::{Connection, Channel, PeerStats};
 use p2p::Context;
 use session::{SessionFactory};
-use util::{Direction, PeerInfo};
+use util::{ConnectionType, PeerInfo, PeerPermissions};
 use PeerId;
 
 const SYNCHRONOUS_RESPONSES: bool = true;
@@ -40,6 +40,11 @@ impl Connections {
 		self.channels().values().map(|channel| channel.peer_info()).collect()
 	}
 
+	/// Returns info and statistics of every peer
+	pub fn stats(&self) -> Vec<(PeerInfo, PeerStats)> {
+		self.channels().values().map(|channel| (channel.peer_info(), channel.session().stats().lock().clone())).collect()
+	}
+
 	/// Returns number of connections.
 	pub fn count(&self) -> usize {
 		self.channels.read().len()
@@ -47,17 +52,20 @@ impl Connections {
 
 	/// Stores new channel.
 	/// Returnes a shared pointer to it.
-	pub fn store<T>(&self, context: Arc<Context>, connection: Connection, direction: Direction) -> Arc<Channel> where T: SessionFactory {
+	pub fn store<T>(&self, context: Arc<Context>, connection: Connection, connection_type: ConnectionType, permissions: PeerPermissions) -> Arc<Channel> where T: SessionFactory {
 		let id = self.peer_counter.fetch_add(1, Ordering::AcqRel);
 
 		let peer_info = PeerInfo {
 			id: id,
 			address: connection.address,
 			user_agent: connection.version_message.user_agent().unwrap_or("unknown".into()),
-			direction: direction,
+			direction: connection_type.direction(),
+			connection_type: connection_type,
 			version: connection.version,
 			version_message: connection.version_message,
 			magic: connection.magic,
+			addrv2: connection.addrv2,
+			permissions: permissions,
 		};
 
 		let session = T::new_session(context, peer_info.clone(), SYNCHRONOUS_RESPONSES);
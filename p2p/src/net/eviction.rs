@@ -0,0 +1,154 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use crypto::siphash24;
+use util::PeerId;
+
+/// Number of inbound peers, protected from eviction by their keyed network group
+const PROTECTED_BY_NETWORK_GROUP: usize = 4;
+/// Number of inbound peers with the lowest ping, protected from eviction
+const PROTECTED_BY_PING: usize = 8;
+/// Number of inbound peers, which have sent us transactions recently, protected from eviction
+const PROTECTED_BY_TRANSACTIONS: usize = 4;
+/// Number of inbound peers, which have sent us blocks recently, protected from eviction
+const PROTECTED_BY_BLOCKS: usize = 4;
+/// Minimal duration of block-relay-only connection (in seconds) before it could be evicted
+const MINIMUM_CONNECT_TIME: u32 = 30;
+
+/// Connection, considered for eviction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvictionCandidate {
+	pub id: PeerId,
+	pub network_group: Vec<u8>,
+	pub min_ping: Option<f64>,
+	pub connected_time: u32,
+	pub last_block_time: u32,
+	pub last_transaction_time: u32,
+}
+
+/// Selects inbound peer to evict in favor of the new inbound connection.
+/// Peers are protected by several independent characteristics, so that an attacker must be better than
+/// honest peers in all of them to occupy all inbound slots. Youngest peer of the network group with
+/// most connections is evicted from the rest. Returns None if all peers are protected.
+pub fn select_inbound_eviction(mut candidates: Vec<EvictionCandidate>, key: (u64, u64)) -> Option<PeerId> {
+	// keyed hash of network group can't be predicted by an attacker
+	protect(&mut candidates, PROTECTED_BY_NETWORK_GROUP, |a, b| {
+		siphash24(key.0, key.1, &a.network_group).cmp(&siphash24(key.0, key.1, &b.network_group))
+	});
+	protect(&mut candidates, PROTECTED_BY_PING, |a, b| ping(b).partial_cmp(&ping(a)).unwrap_or(Ordering::Equal));
+	protect(&mut candidates, PROTECTED_BY_TRANSACTIONS, |a, b| a.last_transaction_time.cmp(&b.last_transaction_time));
+	protect(&mut candidates, PROTECTED_BY_BLOCKS, |a, b| a.last_block_time.cmp(&b.last_block_time));
+	// half of the rest with the longest uptime
+	let protected_by_uptime = candidates.len() / 2;
+	protect(&mut candidates, protected_by_uptime, |a, b| b.connected_time.cmp(&a.connected_time));
+
+	let mut groups: HashMap<&[u8], Vec<&EvictionCandidate>> = HashMap::new();
+	for candidate in &candidates {
+		groups.entry(&candidate.network_group).or_insert_with(Vec::new).push(candidate);
+	}
+
+	groups.values()
+		.filter_map(|group| group.iter().max_by_key(|c| (c.connected_time, c.id)).map(|youngest| (group.len(), youngest)))
+		.max_by_key(|&(len, youngest)| (len, youngest.connected_time, youngest.id))
+		.map(|(_, youngest)| youngest.id)
+}
+
+/// Selects block-relay-only peer to evict, when there are more connections than slots. Extra connection is
+/// periodically opened to rotate block-relay-only peers. Of two youngest peers, the one which has sent us
+/// block least recently is evicted (the youngest on ties), so that peers relaying blocks in time are kept.
+pub fn select_block_relay_eviction(mut candidates: Vec<EvictionCandidate>, max_connections: usize, now: u32) -> Option<PeerId> {
+	if candidates.len() <= max_connections {
+		return None;
+	}
+
+	candidates.sort_by_key(|c| (c.connected_time, c.id));
+	let youngest = match candidates.pop() {
+		Some(youngest) => youngest,
+		None => return None,
+	};
+	let evicted = match candidates.pop() {
+		Some(next_youngest) => if next_youngest.last_block_time < youngest.last_block_time { next_youngest } else { youngest },
+		None => youngest,
+	};
+
+	// give new peer a chance to send us something
+	if now.saturating_sub(evicted.connected_time) < MINIMUM_CONNECT_TIME {
+		return None;
+	}
+
+	Some(evicted.id)
+}
+
+fn ping(candidate: &EvictionCandidate) -> f64 {
+	candidate.min_ping.unwrap_or(::std::f64::MAX)
+}
+
+/// Removes `count` best candidates. Comparator is ordering candidates from the worst to the best.
+fn protect<F>(candidates: &mut Vec<EvictionCandidate>, count: usize, compare: F) where F: FnMut(&EvictionCandidate, &EvictionCandidate) -> Ordering {
+	candidates.sort_by(compare);
+	let len = candidates.len().saturating_sub(count);
+	candidates.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{EvictionCandidate, select_inbound_eviction, select_block_relay_eviction};
+
+	fn candidate(id: usize, network_group: u8, connected_time: u32) -> EvictionCandidate {
+		EvictionCandidate {
+			id: id,
+			network_group: vec![network_group],
+			min_ping: None,
+			connected_time: connected_time,
+			last_block_time: 0,
+			last_transaction_time: 0,
+		}
+	}
+
+	#[test]
+	fn test_inbound_eviction_protects_everyone_when_there_are_few_peers() {
+		let candidates = (0..8).map(|id| candidate(id, id as u8, 100)).collect();
+		assert_eq!(select_inbound_eviction(candidates, (1, 2)), None);
+	}
+
+	#[test]
+	fn test_inbound_eviction_evicts_youngest_peer_of_largest_network_group() {
+		// many peers from the same network group, with same characteristics
+		let mut candidates: Vec<_> = (0..30).map(|id| candidate(id, 1, 200 - id as u32)).collect();
+		// a few old peers from distinct network groups
+		candidates.extend((30..34).map(|id| candidate(id, id as u8, 10)));
+		assert_eq!(select_inbound_eviction(candidates, (1, 2)), Some(0));
+	}
+
+	#[test]
+	fn test_inbound_eviction_protects_peers_relaying_blocks_and_transactions() {
+		let mut candidates: Vec<_> = (0..40).map(|id| candidate(id, 1, 100)).collect();
+		// youngest peers are relaying blocks and transactions && have the lowest ping
+		candidates[0].connected_time = 200;
+		candidates[0].last_block_time = 300;
+		candidates[1].connected_time = 199;
+		candidates[1].last_transaction_time = 300;
+		candidates[2].connected_time = 198;
+		candidates[2].min_ping = Some(0.01);
+
+		let evicted = select_inbound_eviction(candidates, (1, 2));
+		assert!(evicted.is_some());
+		assert!(evicted.unwrap() > 2);
+	}
+
+	#[test]
+	fn test_block_relay_eviction() {
+		let mut candidates = vec![candidate(0, 1, 100), candidate(1, 2, 200), candidate(2, 3, 300)];
+		// there is a slot for every peer
+		assert_eq!(select_block_relay_eviction(candidates.clone(), 3, 1000), None);
+		// youngest peer is too young to be evicted
+		assert_eq!(select_block_relay_eviction(candidates.clone(), 2, 310), None);
+		// on ties, youngest peer is evicted
+		assert_eq!(select_block_relay_eviction(candidates.clone(), 2, 1000), Some(2));
+		// youngest peer has sent us a block, next youngest has not
+		candidates[2].last_block_time = 500;
+		assert_eq!(select_block_relay_eviction(candidates.clone(), 2, 1000), Some(1));
+		// next youngest peer has sent us a block more recently
+		candidates[1].last_block_time = 600;
+		assert_eq!(select_block_relay_eviction(candidates, 2, 1000), Some(2));
+	}
+}
@@ -5,15 +5,19 @@ mod connect;
 mod connection;
 mod connection_counter;
 mod connections;
+mod eviction;
 mod peer_context;
 mod stats;
+mod traffic_counter;
 
 pub use self::accept_connection::{AcceptConnection, accept_connection};
 pub use self::channel::Channel;
 pub use self::config::Config;
-pub use self::connect::{Connect, connect};
+pub use self::connect::{Connect, Route, connect};
 pub use self::connection::Connection;
 pub use self::connection_counter::ConnectionCounter;
 pub use self::connections::Connections;
+pub use self::eviction::{EvictionCandidate, select_inbound_eviction, select_block_relay_eviction};
 pub use self::peer_context::PeerContext;
 pub use self::stats::PeerStats;
+pub use self::traffic_counter::{TrafficCounter, UploadTarget};
@@ -16,12 +16,15 @@ pub struct PeerContext {
 
 impl PeerContext {
 	pub fn new(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Self {
+		let mut stats = PeerStats::default();
+		stats.connected_time = ::time::get_time().sec as u32;
+
 		PeerContext {
 			context: context,
 			info: info,
 			synchronizer: Mutex::new(ConfigurableSynchronizer::new(synchronous)),
 			response_queue: Mutex::default(),
-			stats: Mutex::default(),
+			stats: Mutex::new(stats),
 		}
 	}
 
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use util::interval::{Interval, RealInterval};
 
 use message::{Command, Payload};
-use message::types::{Ping, Pong};
+use message::types::{Ping, Pong, Tx, Block, CompactBlock, BlockTxn, Headers};
 
 // delay somewhere near communication timeout
 const ENORMOUS_PING_DELAY: f64 = 10f64;
@@ -53,6 +53,20 @@ pub struct PeerStats<T: Interval = RealInterval> {
 
 	pub avg_ping: f64,
 	pub min_ping: Option<f64>,
+	/// Duration of the last ping-pong round.
+	pub last_ping_delay: Option<f64>,
+
+	/// Total bytes sent per message type.
+	pub total_send_per_command: HashMap<Command, u64>,
+	/// Total bytes received per message type.
+	pub total_recv_per_command: HashMap<Command, u64>,
+
+	/// Time when connection has been established.
+	pub connected_time: u32,
+	/// Last time when peer has sent us block or non-empty headers.
+	pub last_block_recv: u32,
+	/// Last time when peer has sent us transaction.
+	pub last_transaction_recv: u32,
 
 	send_avg: HashMap<Command, RunningAverage>,
 	recv_avg: HashMap<Command, RunningAverage>,
@@ -80,6 +94,8 @@ impl<I: Interval> PeerStats<I> {
 			self.report_ping_send();
 		}
 
+		*self.total_send_per_command.entry(command.clone()).or_insert(0) += bytes as u64;
+
 		match self.send_avg.entry(command) {
 			Entry::Occupied(mut avg) => {
 				avg.get_mut().add(bytes);
@@ -106,6 +122,7 @@ impl<I: Interval> PeerStats<I> {
 				f64::from(dur.as_secs() as u32) + f64::from(dur.subsec_nanos()) / 1e9
 			};
 			self.min_ping = Some(self.min_ping.unwrap_or(ENORMOUS_PING_DELAY).min(update));
+			self.last_ping_delay = Some(update);
 			self.avg_ping += (update - self.avg_ping) / (self.ping_count as f64);
 		}
 	}
@@ -118,6 +135,16 @@ impl<I: Interval> PeerStats<I> {
 			self.report_pong_recv();
 		}
 
+		// empty headers message consists of single zero-length byte
+		let is_headers = command == Headers::command() && bytes > 1;
+		if is_headers || command == Block::command() || command == CompactBlock::command() || command == BlockTxn::command() {
+			self.last_block_recv = self.last_recv;
+		} else if command == Tx::command() {
+			self.last_transaction_recv = self.last_recv;
+		}
+
+		*self.total_recv_per_command.entry(command.clone()).or_insert(0) += bytes as u64;
+
 		match self.recv_avg.entry(command) {
 			Entry::Occupied(mut avg) => {
 				avg.get_mut().add(bytes);
@@ -173,6 +200,22 @@ mod tests {
 		stats.report_recv("pong".into(), 50);
 		assert!(stats.avg_ping > 0.03);
 		assert!(stats.avg_ping < 0.1);
+		assert_eq!(stats.last_ping_delay, Some(stats.avg_ping));
+	}
+
+	#[test]
+	fn total_per_command() {
+		let mut stats = PeerStats::<RealInterval>::default();
+		stats.report_send("inv".into(), 200);
+		stats.report_send("inv".into(), 300);
+		stats.report_send("tx".into(), 250);
+		stats.report_recv("inv".into(), 2000);
+
+		assert_eq!(stats.total_send, 750);
+		assert_eq!(stats.total_send_per_command[&"inv".into()], 500);
+		assert_eq!(stats.total_send_per_command[&"tx".into()], 250);
+		assert_eq!(stats.total_recv_per_command[&"inv".into()], 2000);
+		assert!(!stats.total_recv_per_command.contains_key(&"tx".into()));
 	}
 
 	#[test]
@@ -189,4 +232,16 @@ mod tests {
 
 		assert_eq!(stats.avg(Flow::Receive, "inv"), 2500);
 	}
+
+	#[test]
+	fn last_block_and_transaction_recv() {
+		let mut stats = PeerStats::<RealInterval>::default();
+		stats.report_recv("headers".into(), 1);
+		assert_eq!(stats.last_block_recv, 0);
+		stats.report_recv("tx".into(), 250);
+		assert_eq!(stats.last_block_recv, 0);
+		assert!(stats.last_transaction_recv != 0);
+		stats.report_recv("cmpctblock".into(), 2000);
+		assert!(stats.last_block_recv != 0);
+	}
 }
@@ -0,0 +1,179 @@
+use std::cmp;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use parking_lot::Mutex;
+
+/// Duration of the upload target cycle (in seconds).
+pub const UPLOAD_TARGET_TIMEFRAME: u32 = 24 * 60 * 60;
+
+/// Upload target state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadTarget {
+	/// Duration of the cycle (in seconds).
+	pub timeframe: u32,
+	/// Maximal number of bytes to send during the cycle. 0 if there is no limit.
+	pub target: u64,
+	/// True if the target is reached.
+	pub target_reached: bool,
+	/// True if historical blocks are still served.
+	pub serve_historical_blocks: bool,
+	/// Number of bytes, which could be sent till the end of the cycle.
+	pub bytes_left_in_cycle: u64,
+	/// Number of seconds till the end of the cycle.
+	pub time_left_in_cycle: u32,
+}
+
+/// Bytes, sent during current upload target cycle.
+#[derive(Debug, Default)]
+struct UploadCycle {
+	/// Cycle start time (in seconds since epoch).
+	start: u32,
+	/// Number of bytes, sent during the cycle.
+	bytes_sent: u64,
+}
+
+/// Counts bytes, sent to and received from all peers.
+pub struct TrafficCounter {
+	/// Total number of received bytes.
+	total_bytes_recv: AtomicUsize,
+	/// Total number of sent bytes.
+	total_bytes_sent: AtomicUsize,
+	/// Maximal number of bytes to send during the cycle.
+	max_upload_target: Option<u64>,
+	/// Current upload target cycle.
+	upload_cycle: Mutex<UploadCycle>,
+}
+
+impl TrafficCounter {
+	pub fn new(max_upload_target: Option<u64>) -> Self {
+		TrafficCounter {
+			total_bytes_recv: AtomicUsize::new(0),
+			total_bytes_sent: AtomicUsize::new(0),
+			max_upload_target: max_upload_target,
+			upload_cycle: Mutex::new(UploadCycle::default()),
+		}
+	}
+
+	/// Increases number of received bytes.
+	pub fn note_recv(&self, bytes: usize) {
+		self.total_bytes_recv.fetch_add(bytes, Ordering::AcqRel);
+	}
+
+	/// Increases number of sent bytes. New upload target cycle is started, if the previous one has ended.
+	pub fn note_send(&self, bytes: usize, now: u32) {
+		self.total_bytes_sent.fetch_add(bytes, Ordering::AcqRel);
+
+		let mut upload_cycle = self.upload_cycle.lock();
+		if now >= upload_cycle.start + UPLOAD_TARGET_TIMEFRAME {
+			upload_cycle.start = now;
+			upload_cycle.bytes_sent = 0;
+		}
+		upload_cycle.bytes_sent += bytes as u64;
+	}
+
+	/// Returns total number of received and sent bytes.
+	pub fn totals(&self) -> (u64, u64) {
+		(self.total_bytes_recv.load(Ordering::Acquire) as u64, self.total_bytes_sent.load(Ordering::Acquire) as u64)
+	}
+
+	/// Returns true if the upload target of the current cycle is reached.
+	pub fn is_upload_target_reached(&self, now: u32) -> bool {
+		match self.max_upload_target {
+			Some(max_upload_target) => self.bytes_sent_in_cycle(now) >= max_upload_target,
+			None => false,
+		}
+	}
+
+	/// Returns upload target state.
+	pub fn upload_target(&self, now: u32) -> UploadTarget {
+		let max_upload_target = match self.max_upload_target {
+			Some(max_upload_target) => max_upload_target,
+			None => return UploadTarget {
+				timeframe: UPLOAD_TARGET_TIMEFRAME,
+				target: 0,
+				target_reached: false,
+				serve_historical_blocks: true,
+				bytes_left_in_cycle: 0,
+				time_left_in_cycle: 0,
+			},
+		};
+
+		let (cycle_start, bytes_sent) = {
+			let upload_cycle = self.upload_cycle.lock();
+			if now >= upload_cycle.start + UPLOAD_TARGET_TIMEFRAME {
+				(now, 0)
+			} else {
+				(upload_cycle.start, upload_cycle.bytes_sent)
+			}
+		};
+
+		let target_reached = bytes_sent >= max_upload_target;
+		UploadTarget {
+			timeframe: UPLOAD_TARGET_TIMEFRAME,
+			target: max_upload_target,
+			target_reached: target_reached,
+			serve_historical_blocks: !target_reached,
+			bytes_left_in_cycle: max_upload_target.saturating_sub(bytes_sent),
+			time_left_in_cycle: cycle_start + UPLOAD_TARGET_TIMEFRAME - cmp::max(now, cycle_start),
+		}
+	}
+
+	/// Returns number of bytes, sent during the current cycle.
+	fn bytes_sent_in_cycle(&self, now: u32) -> u64 {
+		let upload_cycle = self.upload_cycle.lock();
+		if now >= upload_cycle.start + UPLOAD_TARGET_TIMEFRAME {
+			0
+		} else {
+			upload_cycle.bytes_sent
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{TrafficCounter, UploadTarget, UPLOAD_TARGET_TIMEFRAME};
+
+	#[test]
+	fn test_traffic_counter_totals() {
+		let counter = TrafficCounter::new(None);
+		counter.note_recv(100);
+		counter.note_send(20, 1000);
+		counter.note_recv(50);
+		counter.note_send(30, 2000);
+		assert_eq!(counter.totals(), (150, 50));
+		assert!(!counter.is_upload_target_reached(2000));
+		assert_eq!(counter.upload_target(2000), UploadTarget {
+			timeframe: UPLOAD_TARGET_TIMEFRAME,
+			target: 0,
+			target_reached: false,
+			serve_historical_blocks: true,
+			bytes_left_in_cycle: 0,
+			time_left_in_cycle: 0,
+		});
+	}
+
+	#[test]
+	fn test_traffic_counter_upload_target() {
+		let counter = TrafficCounter::new(Some(100));
+		counter.note_send(60, UPLOAD_TARGET_TIMEFRAME);
+		assert!(!counter.is_upload_target_reached(UPLOAD_TARGET_TIMEFRAME + 10));
+		assert_eq!(counter.upload_target(UPLOAD_TARGET_TIMEFRAME + 10), UploadTarget {
+			timeframe: UPLOAD_TARGET_TIMEFRAME,
+			target: 100,
+			target_reached: false,
+			serve_historical_blocks: true,
+			bytes_left_in_cycle: 40,
+			time_left_in_cycle: UPLOAD_TARGET_TIMEFRAME - 10,
+		});
+
+		counter.note_send(40, UPLOAD_TARGET_TIMEFRAME + 20);
+		assert!(counter.is_upload_target_reached(UPLOAD_TARGET_TIMEFRAME + 20));
+		assert_eq!(counter.upload_target(UPLOAD_TARGET_TIMEFRAME + 20).bytes_left_in_cycle, 0);
+		assert!(!counter.upload_target(UPLOAD_TARGET_TIMEFRAME + 20).serve_historical_blocks);
+
+		// next cycle starts with the clean state
+		assert!(!counter.is_upload_target_reached(2 * UPLOAD_TARGET_TIMEFRAME));
+		counter.note_send(10, 2 * UPLOAD_TARGET_TIMEFRAME);
+		assert_eq!(counter.upload_target(2 * UPLOAD_TARGET_TIMEFRAME).bytes_left_in_cycle, 90);
+		assert_eq!(counter.totals(), (0, 110));
+	}
+}
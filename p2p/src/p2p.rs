@@ -1,7 +1,10 @@
-use std::{io, net, error, time};
+use std::{io, net, error, time, fs, path};
+use std::io::{Read, Write};
 use std::sync::Arc;
-use std::net::SocketAddr;
-use parking_lot::RwLock;
+use std::net::{SocketAddr, IpAddr};
+use std::collections::HashSet;
+use parking_lot::{RwLock, Mutex};
+use rand;
 use futures::{Future, finished, failed, BoxFuture};
 use futures::stream::Stream;
 use futures_cpupool::CpuPool;
@@ -13,30 +16,63 @@ use ns_dns_tokio::DnsResolver;
 use message::{Payload, MessageResult, Message};
 use message::common::Services;
 use message::types::addr::AddressEntry;
-use net::{connect, Connections, Channel, Config as NetConfig, accept_connection, ConnectionCounter};
-use util::{NodeTable, Node, NodeTableError, Direction};
-use session::{SessionFactory, SeednodeSessionFactory, NormalSessionFactory};
+use message::types::addrv2::AddressEntryV2;
+use net::{connect, Route, Connections, Channel, Config as NetConfig, accept_connection, ConnectionCounter, TrafficCounter,
+	EvictionCandidate, select_inbound_eviction, select_block_relay_eviction};
+use util::{AddressManager, Node, AddressManagerError, BanList, BanEntry, BanListError, ConnectionType, I2pAddress, OverlayAddress,
+	PeerPermissions, NetworkTime, is_onion, is_i2p, address_group};
+use util::time::{Time, RealTime};
+use session::{SeednodeSessionFactory, NormalSessionFactory};
 use {Config, PeerId};
 use protocol::{LocalSyncNodeRef, InboundSyncConnectionRef, OutboundSyncConnectionRef};
-use io::DeadlineStatus;
+use io::{DeadlineStatus, Socks5Target, sam_create_session, sam_accept};
 
 pub type BoxedEmptyFuture = BoxFuture<(), ()>;
 
+/// How often outbound connections are checked (in seconds).
+const AUTOCONNECT_INTERVAL_SECS: u64 = 10;
+/// Feeler connection is opened every 2 minutes, when all outbound connections are established.
+const FEELER_INTERVAL_TICKS: u64 = 12;
+/// Extra block-relay-only connection is opened every 5 minutes to rotate block-relay-only peers.
+const BLOCK_RELAY_ROTATION_INTERVAL_TICKS: u64 = 30;
+/// Address manager is saved to the disk every 15 minutes.
+const SAVE_INTERVAL_TICKS: u64 = 90;
+/// I2P session is recreated after this delay, when SAM bridge fails (in seconds).
+const I2P_SESSION_RETRY_SECS: u64 = 60;
+
+/// Streaming session, created by the I2P SAM bridge.
+struct I2pSession {
+	/// Session id, used in SAM bridge requests.
+	id: String,
+	/// I2P address of this node.
+	address: I2pAddress,
+	/// Control connection to the SAM bridge. Session is closed, when it is dropped.
+	_control: TcpStream,
+}
+
 /// Network context.
 pub struct Context {
 	/// Connections.
 	connections: Connections,
 	/// Connection counter.
 	connection_counter: ConnectionCounter,
-	/// Node Table.
-	node_table: RwLock<NodeTable>,
+	/// Bytes, sent to and received from all peers.
+	traffic_counter: TrafficCounter,
+	/// Known addresses of other nodes.
+	address_manager: RwLock<AddressManager>,
+	/// Banned addresses.
+	ban_list: RwLock<BanList>,
+	/// I2P session, if I2P nodes are reachable.
+	i2p_session: Mutex<Option<I2pSession>>,
+	/// Secret key of the inbound connections eviction, so that an attacker can't predict which peers are protected.
+	eviction_key: (u64, u64),
 	/// Thread pool handle.
 	pool: CpuPool,
 	/// Remote event loop handle.
 	remote: Remote,
 	/// Local synchronization node.
 	local_sync_node: LocalSyncNodeRef,
-	/// Node table path.
+	/// P2P config.
 	config: Config,
 }
 
@@ -45,8 +81,12 @@ impl Context {
 	pub fn new(local_sync_node: LocalSyncNodeRef, pool_handle: CpuPool, remote: Remote, config: Config) -> Result<Self, Box<error::Error>> {
 		let context = Context {
 			connections: Default::default(),
-			connection_counter: ConnectionCounter::new(config.inbound_connections, config.outbound_connections),
-			node_table: RwLock::new(try!(NodeTable::from_file(config.preferable_services, &config.node_table_path))),
+			connection_counter: ConnectionCounter::new(config.inbound_connections, config.outbound_connections, config.block_relay_connections),
+			traffic_counter: TrafficCounter::new(config.max_upload_target),
+			address_manager: RwLock::new(Context::load_address_manager(&config)),
+			ban_list: RwLock::new(try!(BanList::from_file(&config.ban_list_path))),
+			i2p_session: Mutex::new(None),
+			eviction_key: (rand::random(), rand::random()),
 			pool: pool_handle,
 			remote: remote,
 			local_sync_node: local_sync_node,
@@ -79,69 +119,367 @@ impl Context {
 		});
 	}
 
-	/// Returns addresses of recently active nodes. Sorted and limited to 1000.
-	pub fn node_table_entries(&self) -> Vec<Node> {
-		self.node_table.read().recently_active_nodes(self.config.internet_protocol)
+	/// Loads address manager from `peers.dat`. Empty address manager is used if the file is corrupted.
+	fn load_address_manager(config: &Config) -> AddressManager {
+		match AddressManager::from_file(config.preferable_services, &config.address_manager_path) {
+			Ok(address_manager) => address_manager,
+			Err(err) => {
+				warn!("Failed to load {}: {}. Starting with empty address manager", config.address_manager_path.display(), err);
+				AddressManager::new(config.preferable_services)
+			},
+		}
+	}
+
+	/// Saves address manager to `peers.dat`.
+	pub fn save_address_manager(&self) {
+		if let Err(_err) = self.address_manager.read().save_to_file(&self.config.address_manager_path) {
+			error!("Saving address manager to disk failed");
+		}
+	}
+
+	/// Returns random addresses of known nodes to send in response to `getaddr`.
+	pub fn getaddr_nodes(&self) -> Vec<Node> {
+		self.address_manager.read().getaddr_nodes(self.config.internet_protocol)
+	}
+
+	/// Adds addresses from `addr` message, received from the peer with `source` address.
+	pub fn add_addresses(&self, nodes: Vec<AddressEntry>, source: &SocketAddr) {
+		trace!("Adding {} addresses from {}", nodes.len(), source);
+		self.address_manager.write().insert_many(nodes, source.ip());
+	}
+
+	/// Adds addresses from `addrv2` message, received from the peer with `source` address.
+	pub fn add_addresses_v2(&self, nodes: Vec<AddressEntryV2>, source: &SocketAddr) {
+		trace!("Adding {} addrv2 addresses from {}", nodes.len(), source);
+		self.address_manager.write().insert_many_v2(nodes, source.ip());
+	}
+
+	/// Returns addresses of this node, which are advertised to peers in `addrv2` messages.
+	/// Nodes in I2P network can only learn about this node from its peers.
+	pub fn local_addresses_v2(&self) -> Vec<AddressEntryV2> {
+		let i2p = self.i2p_session.lock().as_ref().map(|session| session.address);
+		i2p.into_iter()
+			.map(|address| AddressEntryV2 {
+				timestamp: RealTime.get().sec as u32,
+				services: self.config.connection.services,
+				address: OverlayAddress::I2p(address).into(),
+				// I2P streams have no ports
+				port: 0.into(),
+			})
+			.collect()
+	}
+
+	/// Returns the way outbound connection to the node is established.
+	/// Fails if node is the onion service or I2P node and it is unreachable.
+	fn route(&self, socket: &net::SocketAddr) -> Result<Route, ()> {
+		if is_i2p(socket) {
+			let i2p = self.address_manager.read().i2p(socket);
+			let session = self.i2p_session.lock();
+			return match (self.config.i2p_sam, session.as_ref(), i2p) {
+				(Some(sam), Some(session), Some(i2p)) => Ok(Route::I2p(sam, session.id.clone(), i2p)),
+				_ => Err(()),
+			};
+		}
+
+		if !is_onion(socket) {
+			return Ok(match self.config.proxy {
+				Some(proxy) => Route::Proxy(proxy, Socks5Target::Ip(*socket)),
+				None => Route::Direct,
+			});
+		}
+
+		let onion = self.address_manager.read().onion(socket);
+		match (self.config.onion_proxy(), onion) {
+			(Some(proxy), Some(onion)) => Ok(Route::Proxy(proxy, Socks5Target::Domain(onion.to_string(), socket.port()))),
+			_ => Err(()),
+		}
+	}
+
+	/// Creates I2P session through the SAM bridge and starts accepting connections from I2P nodes.
+	/// Session destination is persisted, so that this node keeps its I2P address after restart.
+	/// Creation is retried later, if SAM bridge is unavailable.
+	pub fn create_i2p_session(context: Arc<Context>, sam: net::SocketAddr) {
+		let private_key = load_i2p_private_key(&context.config.i2p_private_key_path);
+		let session_id = format!("pbtc-{:08x}", rand::random::<u32>());
+		context.remote.clone().spawn(move |handle| {
+			let pool = context.pool.clone();
+			let id = session_id.clone();
+			let loaded_private_key = private_key.clone();
+			let session = TcpStream::connect(&sam, handle)
+				.and_then(move |stream| sam_create_session(stream, &id, loaded_private_key.as_ref().map(String::as_str)))
+				.then(move |result| {
+					match result {
+						Ok((control, new_private_key)) => match I2pAddress::from_destination(&new_private_key) {
+							Some(address) => {
+								if private_key.as_ref() != Some(&new_private_key) {
+									save_i2p_private_key(&context.config.i2p_private_key_path, &new_private_key);
+								}
+
+								info!("Accepting connections from I2P nodes at {}", address);
+								*context.i2p_session.lock() = Some(I2pSession {
+									id: session_id,
+									address: address,
+									_control: control,
+								});
+								Context::accept_i2p_connection(context, sam);
+							},
+							None => error!("I2P SAM bridge has created session with invalid destination"),
+						},
+						Err(err) => {
+							warn!("Failed to create I2P session: {}. Retrying in {} seconds", err, I2P_SESSION_RETRY_SECS);
+							let c = context.clone();
+							context.execute_after(time::Duration::new(I2P_SESSION_RETRY_SECS, 0), move || Context::create_i2p_session(c, sam));
+						},
+					}
+					finished::<(), ()>(())
+				});
+			pool.spawn(session)
+		})
 	}
 
-	/// Updates node table.
-	pub fn update_node_table(&self, nodes: Vec<AddressEntry>) {
-		trace!("Updating node table with {} entries", nodes.len());
-		self.node_table.write().insert_many(nodes);
+	/// Waits for the next connection from I2P node. Session is recreated, if SAM bridge fails.
+	fn accept_i2p_connection(context: Arc<Context>, sam: net::SocketAddr) {
+		let session_id = match context.i2p_session.lock().as_ref() {
+			Some(session) => session.id.clone(),
+			None => return,
+		};
+
+		context.remote.clone().spawn(move |handle| {
+			let pool = context.pool.clone();
+			let accept = TcpStream::connect(&sam, handle)
+				.and_then(move |stream| sam_accept(stream, &session_id))
+				.then(move |result| {
+					match result {
+						Ok((stream, address)) => {
+							Context::accept_i2p_connection(context.clone(), sam);
+							let socket = address.socket_addr(0);
+							let permissions = context.whitelist_permissions(&socket.ip());
+							if context.is_banned(&socket.ip()) && !permissions.noban {
+								trace!("Rejecting connection from banned node {}", address);
+								let _ = stream.shutdown(net::Shutdown::Both);
+							} else if context.inbound_slot_available() {
+								let config = context.config.connection.clone();
+								Context::accept_connection(context, stream, socket, config, permissions);
+							} else {
+								let _ = stream.shutdown(net::Shutdown::Both);
+							}
+						},
+						Err(err) => {
+							warn!("Failed to accept I2P connection: {}. Recreating I2P session in {} seconds", err, I2P_SESSION_RETRY_SECS);
+							*context.i2p_session.lock() = None;
+							let c = context.clone();
+							context.execute_after(time::Duration::new(I2P_SESSION_RETRY_SECS, 0), move || Context::create_i2p_session(c, sam));
+						},
+					}
+					finished::<(), ()>(())
+				});
+			pool.spawn(accept)
+		})
 	}
 
 	/// Penalize node.
 	pub fn penalize_node(&self, addr: &SocketAddr) {
 		trace!("Penalizing node {}", addr);
-		self.node_table.write().note_failure(addr);
+		self.address_manager.write().note_failure(addr);
+	}
+
+	/// Notes misbehavior of the node. Node is banned once its misbehavior score reaches the threshold.
+	pub fn note_misbehavior(&self, addr: &SocketAddr, score: u32) {
+		if self.ban_list.write().note_misbehavior(addr.ip(), score) {
+			warn!("Banning node {} for misbehavior", addr);
+			let _ = self.address_manager.write().remove(addr);
+			self.save_ban_list();
+		}
+	}
+
+	/// Is node with given address banned?
+	pub fn is_banned(&self, addr: &IpAddr) -> bool {
+		self.ban_list.read().is_banned(addr)
+	}
+
+	/// Returns permissions, granted to inbound peers with given address by the whitelist.
+	pub fn whitelist_permissions(&self, addr: &IpAddr) -> PeerPermissions {
+		self.config.whitelist.iter()
+			.filter(|entry| entry.subnet.contains(addr))
+			.fold(PeerPermissions::default(), |permissions, entry| permissions.union(&entry.permissions))
+	}
+
+	/// Bans address for `duration` seconds and disconnects from all nodes with this address.
+	pub fn ban(&self, addr: IpAddr, duration: i64) -> Result<(), BanListError> {
+		trace!("Banning address {}", addr);
+		try!(self.ban_list.write().ban(addr, duration));
+		self.save_ban_list();
+		for info in self.connections.info().into_iter().filter(|info| info.address.ip() == addr) {
+			self.close_channel(info.id);
+		}
+		Ok(())
+	}
+
+	/// Removes address from the ban list.
+	pub fn unban(&self, addr: &IpAddr) -> Result<(), BanListError> {
+		trace!("Unbanning address {}", addr);
+		try!(self.ban_list.write().unban(addr));
+		self.save_ban_list();
+		Ok(())
 	}
 
-	/// Adds node to table.
-	pub fn add_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
-		trace!("Adding node {} to node table", &addr);
-		self.node_table.write().add(addr, self.config.connection.services)
+	/// Removes all addresses from the ban list.
+	pub fn clear_banned(&self) {
+		trace!("Clearing ban list");
+		self.ban_list.write().clear();
+		self.save_ban_list();
 	}
 
-	/// Removes node from table.
-	pub fn remove_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
-		trace!("Removing node {} from node table", &addr);
-		self.node_table.write().remove(&addr)
+	/// Returns all banned addresses.
+	pub fn banned(&self) -> Vec<BanEntry> {
+		self.ban_list.read().entries()
 	}
 
-	/// Every 10 seconds check if we have reached maximum number of outbound connections.
-	/// If not, connect to best peers.
+	fn save_ban_list(&self) {
+		if let Err(_err) = self.ban_list.read().save_to_file(&self.config.ban_list_path) {
+			error!("Saving ban list to disk failed");
+		}
+	}
+
+	/// Adds node to the address manager.
+	pub fn add_node(&self, addr: SocketAddr) -> Result<(), AddressManagerError> {
+		trace!("Adding node {} to address manager", &addr);
+		self.address_manager.write().add(addr, self.config.connection.services)
+	}
+
+	/// Removes node from the address manager.
+	pub fn remove_node(&self, addr: SocketAddr) -> Result<(), AddressManagerError> {
+		trace!("Removing node {} from address manager", &addr);
+		self.address_manager.write().remove(&addr)
+	}
+
+	/// Returns candidates for eviction among connections of given class.
+	fn eviction_candidates(&self, connection_type: ConnectionType) -> Vec<EvictionCandidate> {
+		self.connections.channels().values()
+			.filter(|channel| channel.peer_info().connection_type == connection_type)
+			// whitelisted peers are never evicted
+			.filter(|channel| !channel.peer_info().permissions.noban)
+			.map(|channel| {
+				let info = channel.peer_info();
+				let stats = channel.session().stats().lock();
+				EvictionCandidate {
+					id: info.id,
+					network_group: address_group(&info.address),
+					min_ping: stats.min_ping,
+					connected_time: stats.connected_time,
+					last_block_time: stats.last_block_recv,
+					last_transaction_time: stats.last_transaction_recv,
+				}
+			})
+			.collect()
+	}
+
+	/// Checks if new inbound connection could be accepted. When all inbound slots are occupied,
+	/// tries to evict one of inbound peers to free the slot.
+	fn inbound_slot_available(&self) -> bool {
+		// because we acquire atomic value twice,
+		// it may happen that accept slightly more connections than we need
+		// we don't mind
+		if self.connection_counter.connections_needed(ConnectionType::Inbound) > 0 {
+			return true;
+		}
+
+		match select_inbound_eviction(self.eviction_candidates(ConnectionType::Inbound), self.eviction_key) {
+			Some(id) => {
+				trace!("Evicting inbound peer#{} in favor of new inbound connection", id);
+				self.close_channel(id);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Disconnects from block-relay-only peer, when extra block-relay-only connection has been opened.
+	fn evict_extra_block_relay_connection(&self) {
+		let max_connections = self.connection_counter.connections(ConnectionType::BlockRelayOnly).1 as usize;
+		let candidates = self.eviction_candidates(ConnectionType::BlockRelayOnly);
+		if let Some(id) = select_block_relay_eviction(candidates, max_connections, RealTime.get().sec as u32) {
+			trace!("Evicting block-relay-only peer#{}", id);
+			self.close_channel(id);
+		}
+	}
+
+	/// Selects addresses of nodes for new outbound connections.
+	fn select_outbound_addresses(&self, needed: usize, onion: bool, i2p: bool, used_addresses: &HashSet<SocketAddr>) -> Vec<SocketAddr> {
+		// TODO: pass Services::with_bitcoin_cash(true) after HF block
+		let peers = self.address_manager.read().select(&Services::default(), self.config.internet_protocol, onion, i2p, used_addresses, needed);
+		peers.into_iter()
+			.map(|peer| peer.address())
+			.filter(|address| !self.is_banned(&address.ip()))
+			.collect()
+	}
+
+	/// Every 10 seconds check if we have reached maximum number of outbound full-relay and
+	/// block-relay-only connections. If not, connect to randomly selected peers. Otherwise, periodically
+	/// open short-lived feeler connection to the address from the new table, to test it and to ask it
+	/// for more addresses, and periodically open extra block-relay-only connection to rotate these peers.
 	pub fn autoconnect(context: Arc<Context>, handle: &Handle) {
 		let c = context.clone();
+		let mut ticks = 0u64;
 		// every 10 seconds connect to new peers (if needed)
-		let interval: BoxedEmptyFuture = Interval::new_at(time::Instant::now(), time::Duration::new(10, 0), handle).expect("Failed to create interval")
+		let interval: BoxedEmptyFuture = Interval::new_at(time::Instant::now(), time::Duration::new(AUTOCONNECT_INTERVAL_SECS, 0), handle).expect("Failed to create interval")
 			.and_then(move |_| {
+				ticks += 1;
 				// print traces
-				let ic = context.connection_counter.inbound_connections();
-				let oc = context.connection_counter.outbound_connections();
+				let ic = context.connection_counter.connections(ConnectionType::Inbound);
+				let oc = context.connection_counter.connections(ConnectionType::OutboundFullRelay);
+				let bc = context.connection_counter.connections(ConnectionType::BlockRelayOnly);
 				info!("Inbound connections: ({}/{})", ic.0, ic.1);
 				info!("Outbound connections: ({}/{})", oc.0, oc.1);
+				info!("Block-relay-only connections: ({}/{})", bc.0, bc.1);
 
 				for channel in context.connections.channels().values() {
 					channel.session().maintain();
 				}
 
-				let needed = context.connection_counter.outbound_connections_needed() as usize;
-				if needed != 0 {
-					// TODO: pass Services::with_bitcoin_cash(true) after HF block
-					let used_addresses = context.connections.addresses();
-					let peers = context.node_table.read().nodes_with_services(&Services::default(), context.config.internet_protocol, &used_addresses, needed);
-					let addresses = peers.into_iter()
-						.map(|peer| peer.address())
-						.collect::<Vec<_>>();
+				context.evict_extra_block_relay_connection();
+
+				let mut used_addresses = context.connections.addresses();
+				let onion = context.config.onion_proxy().is_some();
+				let i2p = context.i2p_session.lock().is_some();
+				let full_relay_needed = context.connection_counter.connections_needed(ConnectionType::OutboundFullRelay) as usize;
+				let block_relay_needed = context.connection_counter.connections_needed(ConnectionType::BlockRelayOnly) as usize;
+				for &(connection_type, needed) in &[(ConnectionType::OutboundFullRelay, full_relay_needed), (ConnectionType::BlockRelayOnly, block_relay_needed)] {
+					if needed == 0 {
+						continue;
+					}
 
-					trace!("Creating {} more outbound connections", addresses.len());
+					let addresses = context.select_outbound_addresses(needed, onion, i2p, &used_addresses);
+					trace!("Creating {} more {:?} connections", addresses.len(), connection_type);
 					for address in addresses {
-						Context::connect::<NormalSessionFactory>(context.clone(), address);
+						used_addresses.insert(address);
+						Context::connect(context.clone(), address, connection_type);
 					}
 				}
 
-				if let Err(_err) = context.node_table.read().save_to_file(&context.config.node_table_path) {
-					error!("Saving node table to disk failed");
+				if full_relay_needed == 0 && block_relay_needed == 0 {
+					if ticks % FEELER_INTERVAL_TICKS == 0 && context.connection_counter.connections_needed(ConnectionType::Feeler) != 0 {
+						let feeler = context.address_manager.read().select_feeler(context.config.internet_protocol, onion, i2p, &used_addresses)
+							.map(|node| node.address())
+							.filter(|address| !context.is_banned(&address.ip()));
+						if let Some(address) = feeler {
+							trace!("Opening feeler connection to {}", address);
+							Context::connect(context.clone(), address, ConnectionType::Feeler);
+						}
+					}
+
+					if ticks % BLOCK_RELAY_ROTATION_INTERVAL_TICKS == 0 && bc.1 != 0 {
+						if let Some(address) = context.select_outbound_addresses(1, onion, i2p, &used_addresses).pop() {
+							trace!("Opening extra block-relay-only connection to {}", address);
+							Context::connect(context.clone(), address, ConnectionType::BlockRelayOnly);
+						}
+					}
+				}
+
+				if ticks % SAVE_INTERVAL_TICKS == 0 {
+					let (new, tried) = context.address_manager.read().len();
+					trace!("Saving address manager with {} new and {} tried addresses", new, tried);
+					context.save_address_manager();
 				}
 
 				Ok(())
@@ -153,16 +491,39 @@ impl Context {
 	}
 
 	/// Connect to socket using given context and handle.
-	fn connect_future<T>(context: Arc<Context>, socket: net::SocketAddr, handle: &Handle, config: &NetConfig) -> BoxedEmptyFuture where T: SessionFactory {
+	fn connect_future(context: Arc<Context>, socket: net::SocketAddr, connection_type: ConnectionType, handle: &Handle, config: &NetConfig) -> BoxedEmptyFuture {
 		trace!("Trying to connect to: {}", socket);
-		let connection = connect(&socket, handle, config);
+		if context.is_banned(&socket.ip()) {
+			trace!("Node {} is banned", socket);
+			context.connection_counter.note_close_connection(connection_type);
+			return finished(()).boxed();
+		}
+
+		let route = match context.route(&socket) {
+			Ok(route) => route,
+			Err(_) => {
+				trace!("Node {} is unreachable", socket);
+				context.connection_counter.note_close_connection(connection_type);
+				return finished(()).boxed();
+			},
+		};
+
+		// transactions are not relayed over block-relay-only connections
+		let relay = config.relay && connection_type != ConnectionType::BlockRelayOnly;
+		let connection = connect(&socket, route, handle, config, relay);
 		connection.then(move |result| {
 			match result {
 				Ok(DeadlineStatus::Meet(Ok(connection))) => {
 					// successfull hanshake
 					trace!("Connected to {}", connection.address);
-					context.node_table.write().insert(connection.address, connection.services);
-					let channel = context.connections.store::<T>(context.clone(), connection, Direction::Outbound);
+					context.address_manager.write().insert(connection.address, connection.services);
+					// clocks of inbound peers aren't sampled, so that attacker can't shift the adjusted time by connecting to us
+					context.config.network_time.add_sample(connection.address.ip(), connection.version_message.timestamp(), RealTime.get().sec);
+					let channel = match connection_type {
+						ConnectionType::Feeler | ConnectionType::AddrFetch =>
+							context.connections.store::<SeednodeSessionFactory>(context.clone(), connection, connection_type, PeerPermissions::default()),
+						_ => context.connections.store::<NormalSessionFactory>(context.clone(), connection, connection_type, PeerPermissions::default()),
+					};
 
 					// initialize session and then start reading messages
 					channel.session().initialize();
@@ -172,23 +533,23 @@ impl Context {
 					// protocol error
 					trace!("Handshake with {} failed", socket);
 					// TODO: close socket
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_outbound_connection();
+					context.address_manager.write().note_failure(&socket);
+					context.connection_counter.note_close_connection(connection_type);
 					finished(Ok(())).boxed()
 				},
 				Ok(DeadlineStatus::Timeout) => {
 					// connection time out
 					trace!("Handshake with {} timed out", socket);
 					// TODO: close socket
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_outbound_connection();
+					context.address_manager.write().note_failure(&socket);
+					context.connection_counter.note_close_connection(connection_type);
 					finished(Ok(())).boxed()
 				},
 				Err(_) => {
 					// network error
 					trace!("Unable to connect to {}", socket);
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_outbound_connection();
+					context.address_manager.write().note_failure(&socket);
+					context.connection_counter.note_close_connection(connection_type);
 					finished(Ok(())).boxed()
 				}
 			}
@@ -197,27 +558,27 @@ impl Context {
 		.boxed()
 	}
 
-	/// Connect to socket using given context.
-	pub fn connect<T>(context: Arc<Context>, socket: net::SocketAddr) where T: SessionFactory {
-		context.connection_counter.note_new_outbound_connection();
+	/// Open connection of given class to socket using given context.
+	pub fn connect(context: Arc<Context>, socket: net::SocketAddr, connection_type: ConnectionType) {
+		context.connection_counter.note_new_connection(connection_type);
 		context.remote.clone().spawn(move |handle| {
 			let config = context.config.clone();
-			context.pool.clone().spawn(Context::connect_future::<T>(context, socket, handle, &config.connection))
+			context.pool.clone().spawn(Context::connect_future(context, socket, connection_type, handle, &config.connection))
 		})
 	}
 
 	pub fn connect_normal(context: Arc<Context>, socket: net::SocketAddr) {
-		Self::connect::<NormalSessionFactory>(context, socket)
+		Self::connect(context, socket, ConnectionType::OutboundFullRelay)
 	}
 
-	pub fn accept_connection_future(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, handle: &Handle, config: NetConfig) -> BoxedEmptyFuture {
+	pub fn accept_connection_future(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, handle: &Handle, config: NetConfig, permissions: PeerPermissions) -> BoxedEmptyFuture {
 		accept_connection(stream, handle, &config, socket).then(move |result| {
 			match result {
 				Ok(DeadlineStatus::Meet(Ok(connection))) => {
 					// successfull hanshake
+					// address of the inbound connection isn't added to the address manager, because its port is random
 					trace!("Accepted connection from {}", connection.address);
-					context.node_table.write().insert(connection.address, connection.services);
-					let channel = context.connections.store::<NormalSessionFactory>(context.clone(), connection, Direction::Inbound);
+					let channel = context.connections.store::<NormalSessionFactory>(context.clone(), connection, ConnectionType::Inbound, permissions);
 
 					// initialize session and then start reading messages
 					channel.session().initialize();
@@ -227,23 +588,23 @@ impl Context {
 					// protocol error
 					trace!("Accepting handshake from {} failed with error: {}", socket, err);
 					// TODO: close socket
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_inbound_connection();
+					context.address_manager.write().note_failure(&socket);
+					context.connection_counter.note_close_connection(ConnectionType::Inbound);
 					finished(Ok(())).boxed()
 				},
 				Ok(DeadlineStatus::Timeout) => {
 					// connection time out
 					trace!("Accepting handshake from {} timed out", socket);
 					// TODO: close socket
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_inbound_connection();
+					context.address_manager.write().note_failure(&socket);
+					context.connection_counter.note_close_connection(ConnectionType::Inbound);
 					finished(Ok(())).boxed()
 				},
 				Err(_) => {
 					// network error
 					trace!("Accepting handshake from {} failed with network error", socket);
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_inbound_connection();
+					context.address_manager.write().note_failure(&socket);
+					context.connection_counter.note_close_connection(ConnectionType::Inbound);
 					finished(Ok(())).boxed()
 				}
 			}
@@ -252,24 +613,26 @@ impl Context {
 		.boxed()
 	}
 
-	pub fn accept_connection(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, config: NetConfig) {
-		context.connection_counter.note_new_inbound_connection();
+	pub fn accept_connection(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, config: NetConfig, permissions: PeerPermissions) {
+		context.connection_counter.note_new_connection(ConnectionType::Inbound);
 		context.remote.clone().spawn(move |handle| {
-			context.pool.clone().spawn(Context::accept_connection_future(context, stream, socket, handle, config))
+			context.pool.clone().spawn(Context::accept_connection_future(context, stream, socket, handle, config, permissions))
 		})
 	}
 
 	/// Starts tcp server and listens for incomming connections.
-	pub fn listen(context: Arc<Context>, handle: &Handle, config: NetConfig) -> Result<BoxedEmptyFuture, io::Error> {
-		trace!("Starting tcp server");
+	/// Peers, connected to this server, are granted `bind_permissions` in addition to whitelisted permissions.
+	pub fn listen(context: Arc<Context>, handle: &Handle, config: NetConfig, bind_permissions: PeerPermissions) -> Result<BoxedEmptyFuture, io::Error> {
+		trace!("Starting tcp server at {}", config.local_address);
 		let server = try!(TcpListener::bind(&config.local_address, handle));
 		let server = server.incoming()
 			.and_then(move |(stream, socket)| {
-				// because we acquire atomic value twice,
-				// it may happen that accept slightly more connections than we need
-				// we don't mind
-				if context.connection_counter.inbound_connections_needed() > 0 {
-					Context::accept_connection(context.clone(), stream, socket, config.clone());
+				let permissions = bind_permissions.union(&context.whitelist_permissions(&socket.ip()));
+				if context.is_banned(&socket.ip()) && !permissions.noban {
+					trace!("Rejecting connection from banned node {}", socket);
+					let _ = stream.shutdown(net::Shutdown::Both);
+				} else if context.inbound_slot_available() {
+					Context::accept_connection(context.clone(), stream, socket, config.clone(), permissions);
 				} else {
 					// ignore result
 					let _ = stream.shutdown(net::Shutdown::Both);
@@ -289,10 +652,11 @@ impl Context {
 				Ok(Ok((command, payload))) => {
 					// successful read
 					trace!("Received {} message from {}", command, channel.peer_info().address);
+					context.traffic_counter.note_recv(payload.len());
 					// handle message and read the next one
 					match channel.session().on_message(command, payload) {
 						Ok(_) => {
-							context.node_table.write().note_used(&channel.peer_info().address);
+							context.address_manager.write().note_used(&channel.peer_info().address);
 							let on_message = Context::on_message(context.clone(), channel);
 							context.spawn(on_message);
 							finished(Ok(())).boxed()
@@ -348,8 +712,9 @@ impl Context {
 	}
 
 	/// Send message using given channel.
-	pub fn send<T>(_context: Arc<Context>, channel: Arc<Channel>, message: T) -> IoFuture<()> where T: AsRef<[u8]> + Send + 'static {
+	pub fn send<T>(context: Arc<Context>, channel: Arc<Channel>, message: T) -> IoFuture<()> where T: AsRef<[u8]> + Send + 'static {
 		//trace!("Sending {} message to {}", T::command(), channel.peer_info().address);
+		context.traffic_counter.note_send(message.as_ref().len(), RealTime.get().sec as u32);
 		channel.write_message(message).then(move |result| {
 			match result {
 				Ok(_) => {
@@ -373,10 +738,7 @@ impl Context {
 			channel.session().on_close();
 			trace!("Disconnecting from {}", info.address);
 			channel.shutdown();
-			match info.direction {
-				Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-				Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
-			}
+			self.connection_counter.note_close_connection(info.connection_type);
 		}
 	}
 
@@ -387,24 +749,29 @@ impl Context {
 			channel.session().on_close();
 			trace!("Disconnecting from {} caused by {}", info.address, error.description());
 			channel.shutdown();
-			self.node_table.write().note_failure(&info.address);
-			match info.direction {
-				Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-				Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
-			}
+			self.address_manager.write().note_failure(&info.address);
+			self.connection_counter.note_close_connection(info.connection_type);
 		}
 	}
 
-	pub fn create_sync_session(&self, start_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
-		self.local_sync_node.create_sync_session(start_height, services, outbound_connection)
+	pub fn create_sync_session(&self, peer_id: PeerId, start_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
+		self.local_sync_node.create_sync_session(peer_id, start_height, services, outbound_connection)
 	}
 
 	pub fn connections(&self) -> &Connections {
 		&self.connections
 	}
 
+	pub fn traffic_counter(&self) -> &TrafficCounter {
+		&self.traffic_counter
+	}
+
+	pub fn network_time(&self) -> &NetworkTime {
+		&self.config.network_time
+	}
+
 	pub fn nodes(&self) -> Vec<Node> {
-		self.node_table.read().nodes()
+		self.address_manager.read().nodes()
 	}
 }
 
@@ -429,6 +796,8 @@ impl Drop for P2P {
 			// done, now let's finish on_message
 			channel.shutdown();
 		}
+
+		self.context.save_address_manager();
 	}
 }
 
@@ -450,7 +819,7 @@ impl P2P {
 
 	pub fn run(&self) -> Result<(), Box<error::Error>> {
 		for peer in &self.config.peers {
-			self.connect::<NormalSessionFactory>(*peer);
+			self.connect(*peer, ConnectionType::OutboundFullRelay);
 		}
 
 		let resolver = try!(DnsResolver::system_config(&self.event_loop_handle));
@@ -460,12 +829,16 @@ impl P2P {
 
 		Context::autoconnect(self.context.clone(), &self.event_loop_handle);
 		try!(self.listen());
+
+		if let Some(sam) = self.config.i2p_sam {
+			Context::create_i2p_session(self.context.clone(), sam);
+		}
 		Ok(())
 	}
 
 	/// Attempts to connect to the specified node
-	pub fn connect<T>(&self, addr: net::SocketAddr) where T: SessionFactory {
-		Context::connect::<T>(self.context.clone(), addr);
+	pub fn connect(&self, addr: net::SocketAddr, connection_type: ConnectionType) {
+		Context::connect(self.context.clone(), addr, connection_type);
 	}
 
 	pub fn connect_to_seednode(&self, resolver: &Resolver, seednode: &str) {
@@ -476,7 +849,7 @@ impl P2P {
 				Ok(address) => match address.pick_one() {
 					Some(socket) => {
 						trace!("Dns lookup of seednode {} finished. Connecting to {}", owned_seednode, socket);
-						Context::connect::<SeednodeSessionFactory>(context, socket);
+						Context::connect(context, socket, ConnectionType::AddrFetch);
 					},
 					None => {
 						trace!("Dns lookup of seednode {} resolved with no results", owned_seednode);
@@ -493,8 +866,15 @@ impl P2P {
 	}
 
 	fn listen(&self) -> Result<(), Box<error::Error>> {
-		let server = try!(Context::listen(self.context.clone(), &self.event_loop_handle, self.config.connection.clone()));
+		let server = try!(Context::listen(self.context.clone(), &self.event_loop_handle, self.config.connection.clone(), PeerPermissions::default()));
 		self.event_loop_handle.spawn(server);
+
+		for whitebind in &self.config.whitebind {
+			let mut config = self.config.connection.clone();
+			config.local_address = whitebind.address;
+			let server = try!(Context::listen(self.context.clone(), &self.event_loop_handle, config, whitebind.permissions));
+			self.event_loop_handle.spawn(server);
+		}
 		Ok(())
 	}
 
@@ -502,3 +882,22 @@ impl P2P {
 		&self.context
 	}
 }
+
+/// Loads private key of the I2P destination. Returns None if new destination must be created.
+fn load_i2p_private_key(path: &path::Path) -> Option<String> {
+	let mut private_key = String::new();
+	match fs::File::open(path).and_then(|mut file| file.read_to_string(&mut private_key)) {
+		Ok(_) => Some(private_key.trim().to_owned()),
+		Err(ref err) if err.kind() == io::ErrorKind::NotFound => None,
+		Err(err) => {
+			warn!("Failed to load I2P private key from {}: {}. New I2P address is created", path.display(), err);
+			None
+		},
+	}
+}
+
+fn save_i2p_private_key(path: &path::Path, private_key: &str) {
+	if let Err(err) = fs::File::create(path).and_then(|mut file| file.write_all(private_key.as_bytes())) {
+		error!("Saving I2P private key to {} failed: {}", path.display(), err);
+	}
+}
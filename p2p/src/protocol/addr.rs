@@ -2,7 +2,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use bytes::Bytes;
 use message::{Error, Command, deserialize_payload, Payload};
-use message::types::{GetAddr, Addr};
+use message::types::{GetAddr, Addr, AddrV2, SendAddrV2};
 use protocol::Protocol;
 use net::PeerContext;
 use util::Direction;
@@ -12,15 +12,28 @@ pub struct AddrProtocol {
 	context: Arc<PeerContext>,
 	/// True if this is a connection to the seednode && we should disconnect after receiving addr message
 	is_seed_node_connection: bool,
+	/// True if peer has asked for addrv2 messages
+	addrv2: bool,
 }
 
 impl AddrProtocol {
 	pub fn new(context: Arc<PeerContext>, is_seed_node_connection: bool) -> Self {
 		AddrProtocol {
+			addrv2: context.info().addrv2,
 			context: context,
 			is_seed_node_connection: is_seed_node_connection,
 		}
 	}
+
+	/// Seednodes are disconnected after they have sent their addresses.
+	fn on_addresses(&self, nodes_len: usize) {
+		// seednodes are currently responding with two addr messages:
+		// 1) addr message with single address - seednode itself
+		// 2) addr message with 1000 addresses (seednode address manager contents)
+		if self.is_seed_node_connection && nodes_len > 1 {
+			self.context.close();
+		}
+	}
 }
 
 impl Protocol for AddrProtocol {
@@ -28,6 +41,14 @@ impl Protocol for AddrProtocol {
 		if let Direction::Outbound = self.context.info().direction {
 			self.context.send_request(&GetAddr);
 		}
+
+		// addresses, which can't be sent in `addr` message, are only advertised to addrv2 peers
+		if self.addrv2 {
+			let local_addresses = self.context.global().local_addresses_v2();
+			if !local_addresses.is_empty() {
+				self.context.send_request(&AddrV2::new(local_addresses));
+			}
+		}
 	}
 
 	fn on_message(&mut self, command: &Command, payload: &Bytes) -> Result<(), Error> {
@@ -35,9 +56,24 @@ impl Protocol for AddrProtocol {
 		// meanwhile seednodes, surprisingly, send addr message even before they are asked for it
 		if command == &GetAddr::command() {
 			let _: GetAddr = try!(deserialize_payload(payload, self.context.info().version));
-			let entries = self.context.global().node_table_entries().into_iter().map(Into::into).collect();
-			let addr = Addr::new(entries);
-			self.context.send_response_inline(&addr);
+			let nodes = self.context.global().getaddr_nodes();
+			if self.addrv2 {
+				let addrv2 = AddrV2::new(nodes.into_iter().map(Into::into).collect());
+				self.context.send_response_inline(&addrv2);
+			} else {
+				// only IPv4 and IPv6 addresses can be sent in addr message
+				let entries = nodes.into_iter().filter(|node| node.is_ip()).map(Into::into).collect();
+				let addr = Addr::new(entries);
+				self.context.send_response_inline(&addr);
+			}
+		} else if command == &SendAddrV2::command() {
+			let _: SendAddrV2 = try!(deserialize_payload(payload, self.context.info().version));
+			self.addrv2 = true;
+		} else if command == &AddrV2::command() {
+			let addrv2: AddrV2 = try!(deserialize_payload(payload, self.context.info().version));
+			let nodes_len = addrv2.addresses.len();
+			self.context.global().add_addresses_v2(addrv2.addresses, &self.context.info().address);
+			self.on_addresses(nodes_len);
 		} else if command == &Addr::command() {
 			let addr: Addr = try!(deserialize_payload(payload, self.context.info().version));
 			match addr {
@@ -46,13 +82,8 @@ impl Protocol for AddrProtocol {
 				},
 				Addr::V31402(addr) => {
 					let nodes_len = addr.addresses.len();
-					self.context.global().update_node_table(addr.addresses);
-					// seednodes are currently responding with two addr messages:
-					// 1) addr message with single address - seednode itself
-					// 2) addr message with 1000 addresses (seednode node_table contents)
-					if self.is_seed_node_connection && nodes_len > 1 {
-						self.context.close();
-					}
+					self.context.global().add_addresses(addr.addresses, &self.context.info().address);
+					self.on_addresses(nodes_len);
 				},
 			}
 		}
@@ -80,7 +111,7 @@ impl Protocol for SeednodeProtocol {
 	fn on_message(&mut self, command: &Command, _payload: &Bytes) -> Result<(), Error> {
 		// Seednodes send addr message more than once with different addresses.
 		// We can't disconenct after first read. Let's delay it by 60 seconds.
-		if !self.disconnecting && command == &Addr::command() {
+		if !self.disconnecting && (command == &Addr::command() || command == &AddrV2::command()) {
 			self.disconnecting = true;
 			let context = self.context.global().clone();
 			let peer = self.context.info().id;
@@ -1,8 +1,12 @@
 use std::sync::Arc;
 use bytes::Bytes;
+use time;
+use chain::{IndexedBlock, LazyBlock};
+use logs;
 use message::{Command, Error, Payload, Services, types, deserialize_payload};
 use protocol::Protocol;
 use net::PeerContext;
+use util::{PeerId, ConnectionType, PeerPermissions};
 use ser::SERIALIZE_TRANSACTION_WITNESS;
 
 pub type InboundSyncConnectionRef = Box<InboundSyncConnection>;
@@ -10,18 +14,18 @@ pub type OutboundSyncConnectionRef = Arc<OutboundSyncConnection>;
 pub type LocalSyncNodeRef = Box<LocalSyncNode>;
 
 pub trait LocalSyncNode : Send + Sync {
-	fn create_sync_session(&self, height: i32, services: Services, outbound: OutboundSyncConnectionRef) -> InboundSyncConnectionRef;
+	fn create_sync_session(&self, peer_id: PeerId, height: i32, services: Services, outbound: OutboundSyncConnectionRef) -> InboundSyncConnectionRef;
 }
 
 pub trait InboundSyncConnection : Send + Sync {
-	fn start_sync_session(&self, peer_name: String, version: types::Version);
+	fn start_sync_session(&self, peer_name: String, version: types::Version, connection_type: ConnectionType, permissions: PeerPermissions);
 	fn close_session(&self);
 	fn on_inventory(&self, message: types::Inv);
 	fn on_getdata(&self, message: types::GetData);
 	fn on_getblocks(&self, message: types::GetBlocks);
 	fn on_getheaders(&self, message: types::GetHeaders, id: u32);
 	fn on_transaction(&self, message: types::Tx);
-	fn on_block(&self, message: types::Block);
+	fn on_block(&self, block: IndexedBlock);
 	fn on_headers(&self, message: types::Headers);
 	fn on_mempool(&self, message: types::MemPool);
 	fn on_filterload(&self, message: types::FilterLoad);
@@ -35,6 +39,13 @@ pub trait InboundSyncConnection : Send + Sync {
 	fn on_get_block_txn(&self, message: types::GetBlockTxn);
 	fn on_block_txn(&self, message: types::BlockTxn);
 	fn on_notfound(&self, message: types::NotFound);
+	fn on_get_cfilters(&self, message: types::GetCFilters);
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders);
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt);
+	fn on_sendtxrcncl(&self, message: types::SendTxRcncl);
+	fn on_reqrecon(&self, message: types::ReqRecon);
+	fn on_sketch(&self, message: types::Sketch);
+	fn on_reconcildiff(&self, message: types::ReconcilDiff);
 }
 
 pub trait OutboundSyncConnection : Send + Sync {
@@ -60,8 +71,19 @@ pub trait OutboundSyncConnection : Send + Sync {
 	fn send_get_block_txn(&self, message: &types::GetBlockTxn);
 	fn send_block_txn(&self, message: &types::BlockTxn);
 	fn send_notfound(&self, message: &types::NotFound);
+	fn send_cfilter(&self, message: &types::CFilter);
+	fn send_cfheaders(&self, message: &types::CFHeaders);
+	fn send_cfcheckpt(&self, message: &types::CFCheckpt);
+	fn send_sendtxrcncl(&self, message: &types::SendTxRcncl);
+	fn send_reqrecon(&self, message: &types::ReqRecon);
+	fn send_sketch(&self, message: &types::Sketch);
+	fn send_reconcildiff(&self, message: &types::ReconcilDiff);
+	/// Returns true if the upload target is reached and historical blocks shouldn't be served.
+	fn is_upload_target_reached(&self) -> bool;
 	fn ignored(&self, id: u32);
 	fn close(&self);
+	/// Closes connection, adding `score` to the misbehavior score of the peer.
+	fn misbehaving(&self, score: u32);
 }
 
 struct OutboundSync {
@@ -165,6 +187,38 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.send_request(message);
 	}
 
+	fn send_cfilter(&self, message: &types::CFilter) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfheaders(&self, message: &types::CFHeaders) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfcheckpt(&self, message: &types::CFCheckpt) {
+		self.context.send_request(message);
+	}
+
+	fn send_sendtxrcncl(&self, message: &types::SendTxRcncl) {
+		self.context.send_request(message);
+	}
+
+	fn send_reqrecon(&self, message: &types::ReqRecon) {
+		self.context.send_request(message);
+	}
+
+	fn send_sketch(&self, message: &types::Sketch) {
+		self.context.send_request(message);
+	}
+
+	fn send_reconcildiff(&self, message: &types::ReconcilDiff) {
+		self.context.send_request(message);
+	}
+
+	fn is_upload_target_reached(&self) -> bool {
+		self.context.global().traffic_counter().is_upload_target_reached(time::get_time().sec as u32)
+	}
+
 	fn ignored(&self, id: u32) {
 		self.context.ignore_response(id);
 	}
@@ -173,6 +227,11 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.global().penalize_node(&self.context.info().address);
 		self.context.close()
 	}
+
+	fn misbehaving(&self, score: u32) {
+		self.context.global().note_misbehavior(&self.context.info().address, score);
+		self.close()
+	}
 }
 
 pub struct SyncProtocol {
@@ -183,7 +242,7 @@ pub struct SyncProtocol {
 impl SyncProtocol {
 	pub fn new(context: Arc<PeerContext>) -> Self {
 		let outbound_connection = Arc::new(OutboundSync::new(context.clone()));
-		let inbound_connection = context.global().create_sync_session(0, context.info().version_message.services(), outbound_connection);
+		let inbound_connection = context.global().create_sync_session(context.info().id, 0, context.info().version_message.services(), outbound_connection);
 		SyncProtocol {
 			inbound_connection: inbound_connection,
 			context: context,
@@ -196,7 +255,9 @@ impl Protocol for SyncProtocol {
 		let info = self.context.info();
 		self.inbound_connection.start_sync_session(
 			format!("{}/{}", info.address, info.user_agent),
-			info.version_message.clone()
+			info.version_message.clone(),
+			info.connection_type,
+			info.permissions
 		);
 	}
 
@@ -225,8 +286,12 @@ impl Protocol for SyncProtocol {
 			self.inbound_connection.on_transaction(message);
 		}
 		else if command == &types::Block::command() {
-			let message: types::Block = try!(deserialize_payload(payload, version));
-			self.inbound_connection.on_block(message);
+			// transactions hashes are computed over the received bytes, so the block is never re-encoded
+			let block = {
+				let _span = logs::span("deserialize_block");
+				try!(LazyBlock::new(payload.clone()).and_then(|block| block.to_indexed_block()))
+			};
+			self.inbound_connection.on_block(block);
 		}
 		else if command == &types::MemPool::command() {
 			let message: types::MemPool = try!(deserialize_payload(payload, version));
@@ -280,6 +345,34 @@ impl Protocol for SyncProtocol {
 			let message: types::NotFound = try!(deserialize_payload(payload, version));
 			self.inbound_connection.on_notfound(message);
 		}
+		else if command == &types::GetCFilters::command() {
+			let message: types::GetCFilters = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_get_cfilters(message);
+		}
+		else if command == &types::GetCFHeaders::command() {
+			let message: types::GetCFHeaders = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_get_cfheaders(message);
+		}
+		else if command == &types::GetCFCheckpt::command() {
+			let message: types::GetCFCheckpt = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_get_cfcheckpt(message);
+		}
+		else if command == &types::SendTxRcncl::command() {
+			let message: types::SendTxRcncl = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_sendtxrcncl(message);
+		}
+		else if command == &types::ReqRecon::command() {
+			let message: types::ReqRecon = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_reqrecon(message);
+		}
+		else if command == &types::Sketch::command() {
+			let message: types::Sketch = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_sketch(message);
+		}
+		else if command == &types::ReconcilDiff::command() {
+			let message: types::ReconcilDiff = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_reconcildiff(message);
+		}
 		Ok(())
 	}
 
@@ -5,7 +5,7 @@ use message::{Command, Error};
 use p2p::Context;
 use net::{PeerContext, PeerStats};
 use protocol::{Protocol, PingProtocol, SyncProtocol, AddrProtocol, SeednodeProtocol};
-use util::PeerInfo;
+use util::{PeerInfo, ConnectionType};
 
 pub trait SessionFactory {
 	fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session;
@@ -27,10 +27,16 @@ pub struct NormalSessionFactory;
 
 impl SessionFactory for NormalSessionFactory {
 	fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session {
+		let block_relay_only = info.connection_type == ConnectionType::BlockRelayOnly;
 		let peer_context = Arc::new(PeerContext::new(context, info, synchronous));
 		let ping = PingProtocol::new(peer_context.clone()).boxed();
-		let addr = AddrProtocol::new(peer_context.clone(), false).boxed();
 		let sync = SyncProtocol::new(peer_context.clone()).boxed();
+		// addresses are not relayed over block-relay-only connections
+		if block_relay_only {
+			return Session::new(peer_context, vec![ping, sync]);
+		}
+
+		let addr = AddrProtocol::new(peer_context.clone(), false).boxed();
 		Session::new(peer_context, vec![ping, addr, sync])
 	}
 }
@@ -0,0 +1,953 @@
+use std::{cmp, io, path, fs, net};
+use std::collections::{HashSet, HashMap};
+use std::net::{SocketAddr, IpAddr};
+use rand::{self, Rng};
+use crypto::siphash24;
+use message::common::{Services, NetAddress, NetAddressV2, IpAddress};
+use message::types::addr::AddressEntry;
+use message::types::addrv2::AddressEntryV2;
+use ser::{Serializable, Deserializable, Stream, Reader, Error as ReaderError};
+use util::time::{Time, RealTime};
+use util::{InternetProtocol, OnionAddress, I2pAddress, OverlayAddress, is_onion, is_i2p, is_cjdns};
+
+/// Version of the `peers.dat` file format.
+const FILE_VERSION: u8 = 1;
+/// Number of buckets in the new table.
+const NEW_BUCKET_COUNT: u64 = 1024;
+/// Number of buckets in the tried table.
+const TRIED_BUCKET_COUNT: u64 = 256;
+/// Number of entries in the single bucket.
+const BUCKET_SIZE: u64 = 64;
+/// Number of new buckets, addresses from the single source group may be put to.
+const NEW_BUCKETS_PER_SOURCE_GROUP: u64 = 64;
+/// Number of tried buckets, addresses from the single group may be put to.
+const TRIED_BUCKETS_PER_GROUP: u64 = 8;
+/// Addresses, which haven't been seen for this time, are considered terrible (in seconds).
+const HORIZON: i64 = 30 * 24 * 60 * 60;
+/// Never connected addresses are terrible after this number of failed attempts.
+const RETRIES: u32 = 3;
+/// Addresses are terrible after this number of failed attempts since the last success...
+const MAX_FAILURES: u32 = 10;
+/// ...if the last success has been earlier than this time ago (in seconds).
+const MIN_FAIL_TIME: i64 = 7 * 24 * 60 * 60;
+/// Timestamps of addresses, relayed by other nodes, are decreased by this penalty (in seconds).
+const TIME_PENALTY: i64 = 2 * 60 * 60;
+/// Timestamp of the connected node is only updated once in this interval (in seconds).
+const CONNECTED_UPDATE_INTERVAL: i64 = 20 * 60;
+/// Connection to the recently tried address is less likely to be attempted again (in seconds).
+const RECENT_TRY_INTERVAL: i64 = 10 * 60;
+/// Percentage of addresses, returned in response to `getaddr` message.
+const GETADDR_MAX_PERCENT: usize = 23;
+/// Maximal number of addresses, returned in response to `getaddr` message.
+const GETADDR_MAX: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node {
+	/// Node address.
+	addr: SocketAddr,
+	/// Last time, the node has been seen or advertised.
+	time: i64,
+	/// Services supported by the node.
+	services: Services,
+	/// Is preferable node?
+	is_preferable: bool,
+	/// Address of the node in Tor or I2P network.
+	overlay: Option<OverlayAddress>,
+	/// Address of the peer, which has told us about this node.
+	source: IpAddr,
+	/// Last connection attempt time.
+	last_try: i64,
+	/// Last successful connection time.
+	last_success: i64,
+	/// Failed connection attempts since the last success.
+	attempts: u32,
+	/// Is node in the tried table?
+	tried: bool,
+}
+
+impl Node {
+	pub fn address(&self) -> SocketAddr {
+		self.addr
+	}
+
+	pub fn overlay(&self) -> Option<OverlayAddress> {
+		self.overlay
+	}
+
+	pub fn onion(&self) -> Option<OnionAddress> {
+		match self.overlay {
+			Some(OverlayAddress::Onion(onion)) => Some(onion),
+			_ => None,
+		}
+	}
+
+	pub fn i2p(&self) -> Option<I2pAddress> {
+		match self.overlay {
+			Some(OverlayAddress::I2p(i2p)) => Some(i2p),
+			_ => None,
+		}
+	}
+
+	/// Can the node address be sent in `addr` message?
+	pub fn is_ip(&self) -> bool {
+		self.overlay.is_none() && !is_cjdns(&self.addr)
+	}
+
+	/// Is the node not worth keeping?
+	fn is_terrible(&self, now: i64) -> bool {
+		// never remove recently tried node
+		if self.last_try != 0 && now - self.last_try < 60 {
+			return false;
+		}
+
+		// came from the future or not seen recently
+		if self.time > now + 10 * 60 || self.time <= 0 || now - self.time > HORIZON {
+			return true;
+		}
+
+		// tried several times and never succeeded
+		if self.last_success == 0 && self.attempts >= RETRIES {
+			return true;
+		}
+
+		// failed too many times since the last success
+		now - self.last_success > MIN_FAIL_TIME && self.attempts >= MAX_FAILURES
+	}
+
+	/// Relative chance of the node to be selected for connection.
+	fn chance(&self, now: i64) -> f64 {
+		let mut chance = 1.0;
+		if now - self.last_try < RECENT_TRY_INTERVAL {
+			chance *= 0.01;
+		}
+		chance *= 0.66f64.powi(cmp::min(self.attempts, 8) as i32);
+		if !self.is_preferable {
+			chance *= 0.5;
+		}
+		chance
+	}
+
+	/// Converts `addrv2` entry. Returns None if the address is from unknown network.
+	fn from_v2(entry: AddressEntryV2, preferable_services: &Services, source: IpAddr) -> Option<Self> {
+		let port = entry.port.into();
+		let overlay = match entry.address {
+			NetAddressV2::TorV3(ref public) => Some(OverlayAddress::Onion(OnionAddress::new(public.clone()))),
+			NetAddressV2::I2p(ref hash) => Some(OverlayAddress::I2p(I2pAddress::new(hash.clone()))),
+			_ => None,
+		};
+		let addr = match (overlay, entry.address) {
+			(Some(overlay), _) => overlay.socket_addr(port),
+			(None, NetAddressV2::Cjdns(ip)) => SocketAddr::new(ip.into(), port),
+			(None, address) => match address.ip() {
+				Some(ip) => SocketAddr::new(ip, port),
+				None => return None,
+			},
+		};
+
+		Some(Node::new(addr, overlay, entry.timestamp as i64, entry.services, preferable_services, source))
+	}
+
+	fn new(addr: SocketAddr, overlay: Option<OverlayAddress>, time: i64, services: Services, preferable_services: &Services, source: IpAddr) -> Self {
+		Node {
+			addr: addr,
+			time: time,
+			services: services,
+			is_preferable: services.includes(preferable_services),
+			overlay: overlay,
+			source: source,
+			last_try: 0,
+			last_success: 0,
+			attempts: 0,
+			tried: false,
+		}
+	}
+}
+
+impl From<Node> for AddressEntry {
+	fn from(node: Node) -> Self {
+		AddressEntry {
+			timestamp: node.time as u32,
+			address: NetAddress {
+				services: node.services,
+				address: node.addr.ip().into(),
+				port: node.addr.port().into(),
+			}
+		}
+	}
+}
+
+impl From<Node> for AddressEntryV2 {
+	fn from(node: Node) -> Self {
+		AddressEntryV2 {
+			timestamp: node.time as u32,
+			services: node.services,
+			address: match (node.overlay, node.addr) {
+				(Some(overlay), _) => overlay.into(),
+				(None, SocketAddr::V6(ref addr)) if is_cjdns(&node.addr) => NetAddressV2::Cjdns(*addr.ip()),
+				(None, addr) => addr.ip().into(),
+			},
+			port: node.addr.port().into(),
+		}
+	}
+}
+
+/// Node, as it is stored in the `peers.dat` file.
+struct NodeRecord {
+	entry: AddressEntryV2,
+	source: IpAddress,
+	last_try: i64,
+	last_success: i64,
+	attempts: u32,
+	tried: bool,
+}
+
+impl Serializable for NodeRecord {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.entry)
+			.append(&self.source)
+			.append(&self.last_try)
+			.append(&self.last_success)
+			.append(&self.attempts)
+			.append(&self.tried);
+	}
+}
+
+impl Deserializable for NodeRecord {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(NodeRecord {
+			entry: try!(reader.read()),
+			source: try!(reader.read()),
+			last_try: try!(reader.read()),
+			last_success: try!(reader.read()),
+			attempts: try!(reader.read()),
+			tried: try!(reader.read()),
+		})
+	}
+}
+
+#[derive(Debug)]
+pub enum AddressManagerError { AddressAlreadyAdded, NoAddressInTable }
+
+/// Address manager, modelled after the one of Bitcoin Core.
+///
+/// Addresses are kept in two tables. The new table holds addresses, which we've heard about, but never
+/// connected to. It is split into buckets by the network group of the address and the group of the peer,
+/// which has advertised it, so a single peer can only fill a small part of the table. The tried table
+/// holds addresses, we've successfully connected to, bucketed by their own group. Position of the address
+/// in the bucket is derived from the secret key, so attackers can't predict which entries they evict.
+#[derive(Default, Debug)]
+pub struct AddressManager<T = RealTime> where T: Time {
+	/// Time source.
+	time: T,
+	/// Secret key of the bucket positions.
+	key: (u64, u64),
+	/// Preferable services.
+	preferable_services: Services,
+	/// Nodes by socket address.
+	nodes: HashMap<SocketAddr, Node>,
+	/// Addresses in the new table by position.
+	new_table: HashMap<u64, SocketAddr>,
+	/// Addresses in the tried table by position.
+	tried_table: HashMap<u64, SocketAddr>,
+}
+
+impl AddressManager {
+	/// Creates empty address manager with random key.
+	pub fn new(preferable_services: Services) -> Self {
+		AddressManager {
+			key: (rand::random(), rand::random()),
+			preferable_services: preferable_services,
+			..Default::default()
+		}
+	}
+
+	/// Loads address manager from the file. Empty address manager is created if file doesn't exist.
+	pub fn from_file<P>(preferable_services: Services, path: P) -> Result<Self, io::Error> where P: AsRef<path::Path> {
+		match fs::File::open(path) {
+			Ok(file) => Self::load(preferable_services, io::BufReader::new(file)),
+			Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::new(preferable_services)),
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Saves address manager to the file.
+	pub fn save_to_file<P>(&self, path: P) -> Result<(), io::Error> where P: AsRef<path::Path> {
+		fs::File::create(path).and_then(|file| self.save(io::BufWriter::new(file)))
+	}
+}
+
+impl<T> AddressManager<T> where T: Time {
+	/// Inserts address of the node, we've successfully connected to, and moves it to the tried table.
+	pub fn insert(&mut self, addr: SocketAddr, services: Services) {
+		let now = self.time.get().sec;
+		if !self.nodes.contains_key(&addr) {
+			let node = Node::new(addr, None, now, services, &self.preferable_services, addr.ip());
+			self.insert_new(node, now, true);
+		}
+
+		let tried = {
+			let node = self.nodes.get_mut(&addr).expect("node is inserted above; qed");
+			node.time = now;
+			node.last_try = now;
+			node.last_success = now;
+			node.attempts = 0;
+			node.services = services;
+			node.is_preferable = services.includes(&self.preferable_services);
+			node.tried
+		};
+
+		if !tried {
+			self.make_tried(addr);
+		}
+	}
+
+	/// Returns onion service address of the node, if node is only reachable over Tor.
+	pub fn onion(&self, addr: &SocketAddr) -> Option<OnionAddress> {
+		self.nodes.get(addr).and_then(Node::onion)
+	}
+
+	/// Returns I2P address of the node, if node is only reachable over I2P.
+	pub fn i2p(&self, addr: &SocketAddr) -> Option<I2pAddress> {
+		self.nodes.get(addr).and_then(Node::i2p)
+	}
+
+	pub fn exists(&self, addr: SocketAddr) -> bool {
+		self.nodes.contains_key(&addr)
+	}
+
+	/// Manually adds node to the new table, evicting node at its position.
+	pub fn add(&mut self, addr: SocketAddr, services: Services) -> Result<(), AddressManagerError> {
+		if self.exists(addr) {
+			return Err(AddressManagerError::AddressAlreadyAdded);
+		}
+
+		let now = self.time.get().sec;
+		let node = Node::new(addr, None, now, services, &self.preferable_services, addr.ip());
+		self.insert_new(node, now, true);
+		Ok(())
+	}
+
+	/// Removes node with the specified socket address.
+	pub fn remove(&mut self, addr: &SocketAddr) -> Result<(), AddressManagerError> {
+		match self.nodes.remove(addr) {
+			Some(node) => {
+				let position = self.position(&node);
+				if node.tried {
+					self.tried_table.remove(&position);
+				} else {
+					self.new_table.remove(&position);
+				}
+				Ok(())
+			},
+			None => Err(AddressManagerError::NoAddressInTable),
+		}
+	}
+
+	/// Inserts addresses from `addr` message, received from the peer with `source` address.
+	pub fn insert_many(&mut self, addresses: Vec<AddressEntry>, source: IpAddr) {
+		let nodes = addresses.into_iter()
+			.map(|addr| Node::new(
+				SocketAddr::new(addr.address.address.into(), addr.address.port.into()),
+				None,
+				addr.timestamp as i64,
+				addr.address.services,
+				&self.preferable_services,
+				source,
+			))
+			.collect();
+		self.insert_nodes(nodes);
+	}
+
+	/// Inserts addresses from `addrv2` message, received from the peer with `source` address.
+	/// Addresses from unknown networks are discarded.
+	pub fn insert_many_v2(&mut self, addresses: Vec<AddressEntryV2>, source: IpAddr) {
+		let nodes = addresses.into_iter()
+			.filter_map(|addr| Node::from_v2(addr, &self.preferable_services, source))
+			.collect();
+		self.insert_nodes(nodes);
+	}
+
+	fn insert_nodes(&mut self, nodes: Vec<Node>) {
+		let now = self.time.get().sec;
+		// discard IPv6 nodes, pretending to be onion or I2P nodes
+		let iter = nodes.into_iter()
+			.filter(|node| node.overlay.is_some() || !(is_onion(&node.addr) || is_i2p(&node.addr)));
+
+		for mut node in iter {
+			// nodes with invalid timestamps are considered seen 5 days ago
+			if node.time <= 100_000_000 || node.time > now + 10 * 60 {
+				node.time = now - 5 * 24 * 60 * 60;
+			}
+			// nodes, advertising themselves, aren't penalized
+			if node.addr.ip() != node.source {
+				node.time = cmp::max(0, node.time - TIME_PENALTY);
+			}
+
+			self.insert_new(node, now, false);
+		}
+	}
+
+	/// Inserts node to the new table. If position of the node is taken, the old node is only evicted
+	/// if it is terrible or if `force` is true.
+	fn insert_new(&mut self, node: Node, now: i64, force: bool) {
+		if let Some(existing) = self.nodes.get_mut(&node.addr) {
+			if existing.time < node.time {
+				existing.time = node.time;
+				existing.services = node.services;
+				existing.is_preferable = node.is_preferable;
+			}
+			return;
+		}
+
+		let position = self.new_position(&node.addr, &node.source);
+		let evicted = match self.new_table.get(&position) {
+			Some(occupant) if force || self.nodes.get(occupant).map_or(true, |occupant| occupant.is_terrible(now)) => Some(*occupant),
+			Some(_) => return,
+			None => None,
+		};
+
+		if let Some(evicted) = evicted {
+			trace!("Evicting node {} from the new table", evicted);
+			self.nodes.remove(&evicted);
+		}
+
+		self.new_table.insert(position, node.addr);
+		self.nodes.insert(node.addr, node);
+	}
+
+	/// Moves node from the new table to the tried table. Node, taking its position in the tried
+	/// table, is moved back to the new table.
+	fn make_tried(&mut self, addr: SocketAddr) {
+		let new_position = match self.nodes.get(&addr) {
+			Some(node) => self.new_position(&node.addr, &node.source),
+			None => return,
+		};
+		if self.new_table.get(&new_position) == Some(&addr) {
+			self.new_table.remove(&new_position);
+		}
+
+		let tried_position = self.tried_position(&addr);
+		if let Some(evicted) = self.tried_table.insert(tried_position, addr) {
+			trace!("Moving node {} back to the new table", evicted);
+			let evicted_source = match self.nodes.get_mut(&evicted) {
+				Some(node) => {
+					node.tried = false;
+					Some(node.source)
+				},
+				None => None,
+			};
+
+			if let Some(evicted_source) = evicted_source {
+				let evicted_position = self.new_position(&evicted, &evicted_source);
+				if let Some(occupant) = self.new_table.insert(evicted_position, evicted) {
+					self.nodes.remove(&occupant);
+				}
+			}
+		}
+
+		if let Some(node) = self.nodes.get_mut(&addr) {
+			node.tried = true;
+		}
+	}
+
+	/// Returns position of the node in its table.
+	fn position(&self, node: &Node) -> u64 {
+		if node.tried {
+			self.tried_position(&node.addr)
+		} else {
+			self.new_position(&node.addr, &node.source)
+		}
+	}
+
+	fn new_position(&self, addr: &SocketAddr, source: &IpAddr) -> u64 {
+		let group = address_group(addr);
+		let source_group = ip_group(source);
+		let mut input = group.clone();
+		input.extend_from_slice(&source_group);
+		let source_bucket = self.hash(&input) % NEW_BUCKETS_PER_SOURCE_GROUP;
+
+		let mut input = source_group;
+		input.extend_from_slice(&u64_bytes(source_bucket));
+		let bucket = self.hash(&input) % NEW_BUCKET_COUNT;
+		bucket * BUCKET_SIZE + self.bucket_position(b'N', bucket, addr)
+	}
+
+	fn tried_position(&self, addr: &SocketAddr) -> u64 {
+		let address_bucket = self.hash(&address_key(addr)) % TRIED_BUCKETS_PER_GROUP;
+		let mut input = address_group(addr);
+		input.extend_from_slice(&u64_bytes(address_bucket));
+		let bucket = self.hash(&input) % TRIED_BUCKET_COUNT;
+		bucket * BUCKET_SIZE + self.bucket_position(b'T', bucket, addr)
+	}
+
+	fn bucket_position(&self, table: u8, bucket: u64, addr: &SocketAddr) -> u64 {
+		let mut input = vec![table];
+		input.extend_from_slice(&u64_bytes(bucket));
+		input.extend_from_slice(&address_key(addr));
+		self.hash(&input) % BUCKET_SIZE
+	}
+
+	fn hash(&self, input: &[u8]) -> u64 {
+		siphash24(self.key.0, self.key.1, input)
+	}
+
+	/// Randomly selects nodes with desired services to connect to. Nodes from both tables are selected
+	/// with equal probability, recently tried and failing nodes are less likely to be selected.
+	/// Onion services are only returned if `onion` is true, I2P nodes are only returned if `i2p` is true.
+	/// CJDNS nodes are never returned, because this node can't connect to them.
+	pub fn select(&self, services: &Services, protocol: InternetProtocol, onion: bool, i2p: bool, except: &HashSet<net::SocketAddr>, limit: usize) -> Vec<Node> {
+		let now = self.time.get().sec;
+		let (mut tried, mut new): (Vec<&Node>, Vec<&Node>) = self.nodes.values()
+			.filter(|node| protocol.is_allowed(&node.addr))
+			.filter(|node| match node.overlay {
+				Some(OverlayAddress::Onion(_)) => onion,
+				Some(OverlayAddress::I2p(_)) => i2p,
+				None => !is_cjdns(&node.addr),
+			})
+			.filter(|node| node.services.includes(services))
+			.filter(|node| !is_excepted(&node.addr, except))
+			.partition(|node| node.tried);
+
+		let mut rng = rand::thread_rng();
+		let mut selected = Vec::new();
+		let mut chance_factor = 1.0;
+		while selected.len() < limit && !(tried.is_empty() && new.is_empty()) {
+			let use_tried = !tried.is_empty() && (new.is_empty() || rng.gen());
+			let table = if use_tried { &mut tried } else { &mut new };
+			let index = rng.gen_range(0, table.len());
+			if rng.gen::<f64>() < chance_factor * table[index].chance(now) {
+				selected.push(table.swap_remove(index).clone());
+				chance_factor = 1.0;
+			} else {
+				chance_factor *= 1.2;
+			}
+		}
+		selected
+	}
+
+	/// Randomly selects node from the new table to test it with short-lived feeler connection.
+	pub fn select_feeler(&self, protocol: InternetProtocol, onion: bool, i2p: bool, except: &HashSet<net::SocketAddr>) -> Option<Node> {
+		let now = self.time.get().sec;
+		let candidates: Vec<&Node> = self.nodes.values()
+			.filter(|node| !node.tried && (node.is_ip() || (onion && node.onion().is_some()) || (i2p && node.i2p().is_some())))
+			.filter(|node| protocol.is_allowed(&node.addr))
+			.filter(|node| now - node.last_try >= RECENT_TRY_INTERVAL)
+			.filter(|node| !is_excepted(&node.addr, except))
+			.collect();
+		rand::thread_rng().choose(&candidates).map(|node| (*node).clone())
+	}
+
+	/// Returns all nodes.
+	pub fn nodes(&self) -> Vec<Node> {
+		self.nodes.values().cloned().collect()
+	}
+
+	/// Returns random subset of not terrible nodes, which is sent in response to `getaddr` message.
+	pub fn getaddr_nodes(&self, protocol: InternetProtocol) -> Vec<Node> {
+		let now = self.time.get().sec;
+		let mut nodes: Vec<Node> = self.nodes.values()
+			.filter(|node| protocol.is_allowed(&node.addr))
+			.filter(|node| !node.is_terrible(now))
+			.cloned()
+			.collect();
+		let limit = cmp::min(nodes.len() * GETADDR_MAX_PERCENT / 100, GETADDR_MAX);
+		rand::thread_rng().shuffle(&mut nodes);
+		nodes.truncate(limit);
+		nodes
+	}
+
+	/// Number of nodes in the new and the tried tables.
+	pub fn len(&self) -> (usize, usize) {
+		(self.new_table.len(), self.tried_table.len())
+	}
+
+	/// Notes that the message has been received from the connected node.
+	pub fn note_used(&mut self, addr: &SocketAddr) {
+		let now = self.time.get().sec;
+		if let Some(node) = self.nodes.get_mut(addr) {
+			if now - node.time > CONNECTED_UPDATE_INTERVAL {
+				node.time = now;
+			}
+		}
+	}
+
+	/// Notes failed connection attempt.
+	pub fn note_failure(&mut self, addr: &SocketAddr) {
+		let now = self.time.get().sec;
+		if let Some(node) = self.nodes.get_mut(addr) {
+			node.last_try = now;
+			node.attempts += 1;
+		}
+	}
+
+	/// Saves address manager in `peers.dat` format.
+	pub fn save<W>(&self, mut write: W) -> Result<(), io::Error> where W: io::Write {
+		// tried nodes go first, so they are restored before new nodes are inserted
+		let mut nodes: Vec<&Node> = self.nodes.values().collect();
+		nodes.sort_by_key(|node| !node.tried);
+		let records: Vec<NodeRecord> = nodes.into_iter()
+			.map(|node| NodeRecord {
+				entry: node.clone().into(),
+				source: node.source.into(),
+				last_try: node.last_try,
+				last_success: node.last_success,
+				attempts: node.attempts,
+				tried: node.tried,
+			})
+			.collect();
+
+		let mut stream = Stream::new();
+		stream
+			.append(&FILE_VERSION)
+			.append(&self.key.0)
+			.append(&self.key.1)
+			.append_list(&records);
+		try!(write.write_all(&stream.out()));
+		write.flush()
+	}
+
+	/// Loads address manager from `peers.dat` format. Bucket positions are recomputed from the saved key.
+	pub fn load<R>(preferable_services: Services, read: R) -> Result<Self, io::Error> where R: io::Read, T: Default {
+		let err = |err: ReaderError| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err));
+		let mut reader = Reader::from_read(read);
+		let version: u8 = try!(reader.read().map_err(err));
+		if version != FILE_VERSION {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsupported peers.dat version {}", version)));
+		}
+
+		let mut address_manager = AddressManager::<T>::default();
+		address_manager.preferable_services = preferable_services;
+		address_manager.key = (try!(reader.read().map_err(err)), try!(reader.read().map_err(err)));
+		let max_records = ((NEW_BUCKET_COUNT + TRIED_BUCKET_COUNT) * BUCKET_SIZE) as usize;
+		let records: Vec<NodeRecord> = try!(reader.read_list_max(max_records).map_err(err));
+		let now = address_manager.time.get().sec;
+		for record in records {
+			let mut node = match Node::from_v2(record.entry, &preferable_services, record.source.into()) {
+				Some(node) => node,
+				None => continue,
+			};
+			node.last_try = record.last_try;
+			node.last_success = record.last_success;
+			node.attempts = record.attempts;
+
+			let addr = node.addr;
+			if record.tried {
+				// node is only moved to the tried table if it's not taken by another tried node
+				let position = address_manager.tried_position(&addr);
+				if address_manager.tried_table.contains_key(&position) {
+					continue;
+				}
+				node.tried = true;
+				address_manager.tried_table.insert(position, addr);
+				address_manager.nodes.insert(addr, node);
+			} else {
+				address_manager.insert_new(node, now, false);
+			}
+		}
+
+		Ok(address_manager)
+	}
+}
+
+/// Is address (or its IPv4/IPv6 twin) in the set?
+fn is_excepted(addr: &SocketAddr, except: &HashSet<SocketAddr>) -> bool {
+	except.contains(addr) || match *addr {
+		SocketAddr::V4(v4) => except.contains(&SocketAddr::V6(net::SocketAddrV6::new(v4.ip().to_ipv6_compatible(), v4.port(), 0, 0))),
+		SocketAddr::V6(v6) => v6.ip().to_ipv4()
+			.map(|v4| except.contains(&SocketAddr::V4(net::SocketAddrV4::new(v4, v6.port()))))
+			.unwrap_or(false),
+	}
+}
+
+/// Returns network group of the address: /16 for IPv4 and /32 for IPv6. Tor, I2P and CJDNS
+/// addresses are grouped by first 4 bits of their network-specific part.
+fn ip_group(ip: &IpAddr) -> Vec<u8> {
+	match *ip {
+		IpAddr::V4(ref v4) => vec![1, v4.octets()[0], v4.octets()[1]],
+		IpAddr::V6(ref v6) => match v6.to_ipv4() {
+			Some(v4) => ip_group(&IpAddr::V4(v4)),
+			None => {
+				let octets = v6.octets();
+				let socket = SocketAddr::new(*ip, 0);
+				if is_onion(&socket) {
+					vec![3, octets[6] >> 4]
+				} else if is_i2p(&socket) {
+					vec![4, octets[6] >> 4]
+				} else if is_cjdns(&socket) {
+					vec![5, octets[1] >> 4]
+				} else {
+					vec![2, octets[0], octets[1], octets[2], octets[3]]
+				}
+			},
+		},
+	}
+}
+
+pub fn address_group(addr: &SocketAddr) -> Vec<u8> {
+	ip_group(&addr.ip())
+}
+
+/// Returns bytes, uniquely identifying the socket address.
+fn address_key(addr: &SocketAddr) -> Vec<u8> {
+	let mut key = match addr.ip() {
+		IpAddr::V4(v4) => v4.to_ipv6_mapped().octets().to_vec(),
+		IpAddr::V6(v6) => v6.octets().to_vec(),
+	};
+	key.push((addr.port() >> 8) as u8);
+	key.push(addr.port() as u8);
+	key
+}
+
+fn u64_bytes(value: u64) -> [u8; 8] {
+	let mut bytes = [0u8; 8];
+	for (index, byte) in bytes.iter_mut().enumerate() {
+		*byte = (value >> (8 * index)) as u8;
+	}
+	bytes
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+	use std::net::{SocketAddr, IpAddr};
+	use std::collections::HashSet;
+	use time;
+	use message::common::{Services, NetAddress, NetAddressV2};
+	use message::types::addr::AddressEntry;
+	use message::types::addrv2::AddressEntryV2;
+	use util::{InternetProtocol, OnionAddress, I2pAddress};
+	use util::time::Time;
+	use super::{AddressManager, TIME_PENALTY, HORIZON, NEW_BUCKET_COUNT, BUCKET_SIZE};
+
+	const NOW: i64 = 1_600_000_000;
+
+	struct TestTime(Cell<i64>);
+
+	impl Default for TestTime {
+		fn default() -> Self {
+			TestTime(Cell::new(NOW))
+		}
+	}
+
+	impl Time for TestTime {
+		fn get(&self) -> time::Timespec {
+			time::Timespec::new(self.0.get(), 0)
+		}
+	}
+
+	fn entry(addr: &str, timestamp: u32) -> AddressEntry {
+		let addr: SocketAddr = addr.parse().unwrap();
+		AddressEntry {
+			timestamp: timestamp,
+			address: NetAddress {
+				services: Services::default(),
+				address: addr.ip().into(),
+				port: addr.port().into(),
+			},
+		}
+	}
+
+	fn select_all(manager: &AddressManager<TestTime>, onion: bool, i2p: bool) -> HashSet<SocketAddr> {
+		manager.select(&Services::default(), InternetProtocol::default(), onion, i2p, &HashSet::new(), usize::max_value())
+			.into_iter()
+			.map(|node| node.address())
+			.collect()
+	}
+
+	#[test]
+	fn test_address_manager_insert_many() {
+		let source: IpAddr = "10.0.0.1".parse().unwrap();
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.insert_many(vec![
+			entry("1.2.3.4:8333", NOW as u32 - 100),
+			// timestamp in the future
+			entry("1.2.3.5:8333", NOW as u32 + 3600),
+			// advertises itself
+			entry("10.0.0.1:8333", NOW as u32 - 100),
+		], source);
+
+		assert_eq!(manager.len(), (3, 0));
+		assert_eq!(manager.nodes[&"1.2.3.4:8333".parse().unwrap()].time, NOW - 100 - TIME_PENALTY);
+		assert_eq!(manager.nodes[&"1.2.3.5:8333".parse().unwrap()].time, NOW - 5 * 24 * 60 * 60 - TIME_PENALTY);
+		assert_eq!(manager.nodes[&"10.0.0.1:8333".parse().unwrap()].time, NOW - 100);
+
+		// newer timestamp is remembered, older is ignored
+		manager.insert_many(vec![entry("1.2.3.4:8333", NOW as u32 - 10)], "1.2.3.4".parse().unwrap());
+		manager.insert_many(vec![entry("1.2.3.4:8333", NOW as u32 - 1000)], "1.2.3.4".parse().unwrap());
+		assert_eq!(manager.nodes[&"1.2.3.4:8333".parse().unwrap()].time, NOW - 10);
+		assert_eq!(manager.len(), (3, 0));
+	}
+
+	#[test]
+	fn test_address_manager_single_source_is_limited() {
+		let source: IpAddr = "10.0.0.1".parse().unwrap();
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.key = (1, 2);
+		// addresses from the single source group are put to at most 64 buckets
+		for i in 0..100u32 {
+			let entries = (0..250u32).map(|j| entry(&format!("{}.{}.{}.1:8333", 1 + i, j, i % 7), NOW as u32)).collect();
+			manager.insert_many(entries, source);
+		}
+		let (new, tried) = manager.len();
+		assert_eq!(tried, 0);
+		assert!(new <= 64 * BUCKET_SIZE as usize);
+		assert!(new < (NEW_BUCKET_COUNT * BUCKET_SIZE) as usize);
+		assert_eq!(manager.nodes.len(), new);
+	}
+
+	#[test]
+	fn test_address_manager_tried() {
+		let s0: SocketAddr = "1.2.3.4:8333".parse().unwrap();
+		let s1: SocketAddr = "5.6.7.8:8333".parse().unwrap();
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.insert_many(vec![entry("1.2.3.4:8333", NOW as u32)], "10.0.0.1".parse().unwrap());
+		manager.note_failure(&s0);
+		assert_eq!(manager.nodes[&s0].attempts, 1);
+
+		// successful connection moves node to the tried table
+		manager.insert(s0, Services::default().with_network(true));
+		manager.insert(s1, Services::default());
+		assert_eq!(manager.len(), (0, 2));
+		assert!(manager.nodes[&s0].tried);
+		assert_eq!(manager.nodes[&s0].attempts, 0);
+		assert_eq!(manager.nodes[&s0].last_success, NOW);
+		assert_eq!(manager.nodes[&s0].services, Services::default().with_network(true));
+		assert_eq!(select_all(&manager, false), vec![s0, s1].into_iter().collect());
+
+		assert!(manager.remove(&s0).is_ok());
+		assert!(manager.remove(&s0).is_err());
+		assert_eq!(manager.len(), (0, 1));
+	}
+
+	#[test]
+	fn test_address_manager_terrible() {
+		let s0: SocketAddr = "1.2.3.4:8333".parse().unwrap();
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.insert_many(vec![entry("1.2.3.4:8333", NOW as u32)], "1.2.3.4".parse().unwrap());
+		assert!(!manager.nodes[&s0].is_terrible(NOW));
+		assert!(manager.nodes[&s0].is_terrible(NOW + HORIZON + 1));
+
+		for _ in 0..3 {
+			manager.note_failure(&s0);
+		}
+		// recently tried node is never terrible
+		assert!(!manager.nodes[&s0].is_terrible(NOW + 30));
+		assert!(manager.nodes[&s0].is_terrible(NOW + 60));
+
+		// terrible nodes are not sent in response to getaddr
+		manager.time.0.set(NOW + 60);
+		assert!(manager.getaddr_nodes(InternetProtocol::default()).is_empty());
+	}
+
+	#[test]
+	fn test_address_manager_getaddr() {
+		let mut manager = AddressManager::<TestTime>::default();
+		let entries = (0..100u32).map(|i| entry(&format!("1.2.{}.4:8333", i), NOW as u32)).collect();
+		manager.insert_many(entries, "10.0.0.1".parse().unwrap());
+		let count = manager.len().0;
+		assert_eq!(manager.getaddr_nodes(InternetProtocol::default()).len(), count * 23 / 100);
+		assert!(manager.getaddr_nodes(InternetProtocol::IpV6).is_empty());
+	}
+
+	#[test]
+	fn test_address_manager_add() {
+		let mut manager = AddressManager::<TestTime>::default();
+		assert!(manager.add("127.0.0.1:8001".parse().unwrap(), Services::default()).is_ok());
+		assert!(manager.add("127.0.0.1:8001".parse().unwrap(), Services::default()).is_err());
+		assert!(manager.remove(&"127.0.0.1:8001".parse().unwrap()).is_ok());
+		assert!(manager.remove(&"127.0.0.1:8001".parse().unwrap()).is_err());
+		assert_eq!(manager.len(), (0, 0));
+		assert!(manager.nodes.is_empty());
+	}
+
+	#[test]
+	fn test_address_manager_select() {
+		let s0: SocketAddr = "1.2.3.4:8333".parse().unwrap();
+		let s1: SocketAddr = "5.6.7.8:8333".parse().unwrap();
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.insert_many(vec![entry("1.2.3.4:8333", NOW as u32), entry("5.6.7.8:8333", NOW as u32)], "10.0.0.1".parse().unwrap());
+		manager.insert(s1, Services::default());
+
+		let except = vec![s1].into_iter().collect();
+		let nodes = manager.select(&Services::default(), InternetProtocol::default(), false, false, &except, 10);
+		assert_eq!(nodes.len(), 1);
+		assert_eq!(nodes[0].address(), s0);
+		assert_eq!(manager.select(&Services::default(), InternetProtocol::default(), false, false, &HashSet::new(), 1).len(), 1);
+		assert!(manager.select(&Services::default().with_witness(true), InternetProtocol::default(), false, false, &HashSet::new(), 10).is_empty());
+
+		// feelers only test nodes from the new table
+		assert_eq!(manager.select_feeler(InternetProtocol::default(), false, false, &HashSet::new()).map(|node| node.address()), Some(s0));
+		manager.note_failure(&s0);
+		assert_eq!(manager.select_feeler(InternetProtocol::default(), false, false, &HashSet::new()), None);
+	}
+
+	#[test]
+	fn test_save_and_load() {
+		let s0: SocketAddr = "1.2.3.4:8333".parse().unwrap();
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.key = (7, 11);
+		let entries = (0..50u32).map(|i| entry(&format!("{}.2.3.4:8333", i + 1), NOW as u32)).collect();
+		manager.insert_many(entries, "10.0.0.1".parse().unwrap());
+		manager.insert(s0, Services::default());
+		manager.note_failure(&"2.2.3.4:8333".parse().unwrap());
+
+		let mut db = Vec::new();
+		manager.save(&mut db).unwrap();
+		let loaded = AddressManager::<TestTime>::load(Services::default(), &db as &[u8]).unwrap();
+		assert_eq!(loaded.key, manager.key);
+		assert_eq!(loaded.nodes, manager.nodes);
+		assert_eq!(loaded.new_table, manager.new_table);
+		assert_eq!(loaded.tried_table, manager.tried_table);
+
+		db[0] = 2;
+		assert!(AddressManager::<TestTime>::load(Services::default(), &db as &[u8]).is_err());
+	}
+
+	#[test]
+	fn test_overlay_nodes() {
+		let onion: OnionAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion".parse().unwrap();
+		let i2p: I2pAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p".parse().unwrap();
+		let s0: SocketAddr = "127.0.0.1:8000".parse().unwrap();
+		let s1 = onion.socket_addr(8333);
+		let s2 = i2p.socket_addr(0);
+		let s3: SocketAddr = "[fc00::1]:8333".parse().unwrap();
+		let address_entry = |address| AddressEntryV2 {
+			timestamp: NOW as u32,
+			services: Services::default(),
+			address: address,
+			port: 8333.into(),
+		};
+
+		let mut manager = AddressManager::<TestTime>::default();
+		manager.insert_many_v2(vec![
+			AddressEntryV2 { port: 8000.into(), ..address_entry(NetAddressV2::IpV4("127.0.0.1".parse().unwrap())) },
+			address_entry(NetAddressV2::TorV3(onion.public().clone())),
+			AddressEntryV2 { port: 0.into(), ..address_entry(NetAddressV2::I2p(i2p.hash().clone())) },
+			address_entry(NetAddressV2::Cjdns("fc00::1".parse().unwrap())),
+			address_entry(NetAddressV2::IpV6("fd87:d87e:eb43::1".parse().unwrap())),
+			address_entry(NetAddressV2::Unknown(7, vec![0; 32].into())),
+		], "10.0.0.1".parse().unwrap());
+
+		// fake onion address and address from unknown network are not stored
+		let addresses = manager.nodes().iter().map(|node| node.address()).collect::<HashSet<_>>();
+		assert_eq!(addresses, vec![s0, s1, s2, s3].into_iter().collect());
+
+		// onion services and I2P nodes are only returned when they're reachable, CJDNS nodes are never returned
+		assert_eq!(select_all(&manager, false, false), vec![s0].into_iter().collect());
+		assert_eq!(select_all(&manager, true, false), vec![s0, s1].into_iter().collect());
+		assert_eq!(select_all(&manager, false, true), vec![s0, s2].into_iter().collect());
+		assert_eq!(manager.onion(&s1), Some(onion));
+		assert_eq!(manager.i2p(&s2), Some(i2p));
+
+		// all nodes are sent back in addrv2 message
+		let entries: Vec<AddressEntryV2> = manager.nodes().into_iter().map(Into::into).collect();
+		assert!(entries.iter().any(|entry| entry.address == NetAddressV2::I2p(i2p.hash().clone())));
+		assert!(entries.iter().any(|entry| entry.address == NetAddressV2::Cjdns("fc00::1".parse().unwrap())));
+
+		let mut db = Vec::new();
+		manager.save(&mut db).unwrap();
+		let loaded = AddressManager::<TestTime>::load(Services::default(), &db as &[u8]).unwrap();
+		assert_eq!(loaded.nodes, manager.nodes);
+	}
+}
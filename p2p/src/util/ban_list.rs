@@ -0,0 +1,240 @@
+use std::{io, path, fs};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use csv;
+use util::time::{Time, RealTime};
+
+/// Misbehavior score, after which node is banned.
+pub const BAN_SCORE_THRESHOLD: u32 = 100;
+/// Default ban duration in seconds (24 hours).
+pub const DEFAULT_BAN_TIME: i64 = 24 * 60 * 60;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BanEntry {
+	/// Banned address.
+	pub address: IpAddr,
+	/// Timestamp of the ban.
+	pub created: i64,
+	/// Timestamp, after which address is not banned anymore.
+	pub until: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BanListError { AlreadyBanned, NotBanned }
+
+/// Banned addresses and misbehavior scores of the nodes.
+#[derive(Default, Debug)]
+pub struct BanList<T = RealTime> where T: Time {
+	/// Time source.
+	time: T,
+	/// Banned addresses.
+	banned: HashMap<IpAddr, BanEntry>,
+	/// Misbehavior scores of not-yet-banned addresses.
+	scores: HashMap<IpAddr, u32>,
+}
+
+impl BanList {
+	/// Opens a file and loads ban list from it.
+	pub fn from_file<P>(path: P) -> Result<Self, io::Error> where P: AsRef<path::Path> {
+		fs::OpenOptions::new()
+			.create(true)
+			.read(true)
+			// without opening for write, mac os returns os error 22
+			.write(true)
+			.open(path)
+			.and_then(Self::load)
+	}
+
+	/// Saves ban list to file
+	pub fn save_to_file<P>(&self, path: P) -> Result<(), io::Error> where P: AsRef<path::Path> {
+		fs::File::create(path).and_then(|file| self.save(file))
+	}
+}
+
+impl<T> BanList<T> where T: Time {
+	/// Is address currently banned?
+	pub fn is_banned(&self, address: &IpAddr) -> bool {
+		let now = self.time.get().sec;
+		self.banned.get(address).map(|entry| entry.until > now).unwrap_or(false)
+	}
+
+	/// Bans address for `duration` seconds.
+	pub fn ban(&mut self, address: IpAddr, duration: i64) -> Result<(), BanListError> {
+		let now = self.time.get().sec;
+		if self.banned.get(&address).map(|entry| entry.until > now).unwrap_or(false) {
+			return Err(BanListError::AlreadyBanned);
+		}
+
+		self.scores.remove(&address);
+		self.banned.insert(address, BanEntry {
+			address: address,
+			created: now,
+			until: now + duration,
+		});
+		Ok(())
+	}
+
+	/// Removes address from the ban list.
+	pub fn unban(&mut self, address: &IpAddr) -> Result<(), BanListError> {
+		let is_banned = self.is_banned(address);
+		match self.banned.remove(address) {
+			Some(_) if is_banned => Ok(()),
+			_ => Err(BanListError::NotBanned),
+		}
+	}
+
+	/// Removes all addresses from the ban list.
+	pub fn clear(&mut self) {
+		self.banned.clear();
+	}
+
+	/// Returns all currently banned addresses, sorted by ban time.
+	pub fn entries(&self) -> Vec<BanEntry> {
+		let now = self.time.get().sec;
+		let mut entries: Vec<_> = self.banned.values()
+			.filter(|entry| entry.until > now)
+			.cloned()
+			.collect();
+		entries.sort_by_key(|entry| entry.created);
+		entries
+	}
+
+	/// Adds score to the misbehavior score of the address.
+	/// Once score reaches the threshold, address is banned for the default ban time.
+	/// Returns true if address has been banned.
+	pub fn note_misbehavior(&mut self, address: IpAddr, score: u32) -> bool {
+		if score == 0 || self.is_banned(&address) {
+			return false;
+		}
+
+		let total_score = {
+			let total_score = self.scores.entry(address).or_insert(0);
+			*total_score += score;
+			*total_score
+		};
+
+		if total_score < BAN_SCORE_THRESHOLD {
+			return false;
+		}
+
+		self.ban(address, DEFAULT_BAN_TIME).is_ok()
+	}
+
+	/// Saves ban list in csv format. Expired bans are not saved.
+	pub fn save<W>(&self, write: W) -> Result<(), io::Error> where W: io::Write {
+		let mut writer = csv::Writer::from_writer(write)
+			.delimiter(b' ');
+
+		let err = || io::Error::new(io::ErrorKind::Other, "Write csv error");
+
+		for entry in self.entries() {
+			let record = (entry.address.to_string(), entry.created, entry.until);
+			try!(writer.encode(record).map_err(|_| err()));
+		}
+
+		Ok(())
+	}
+
+	/// Loads ban list from a csv source.
+	pub fn load<R>(read: R) -> Result<Self, io::Error> where R: io::Read, T: Default {
+		let mut rdr = csv::Reader::from_reader(read)
+			.has_headers(false)
+			.delimiter(b' ');
+
+		let mut ban_list = BanList::default();
+
+		let err = || io::Error::new(io::ErrorKind::Other, "Load csv error");
+
+		for row in rdr.decode() {
+			let (address, created, until): (String, i64, i64) = try!(row.map_err(|_| err()));
+			let address: IpAddr = try!(address.parse().map_err(|_| err()));
+			ban_list.banned.insert(address, BanEntry {
+				address: address,
+				created: created,
+				until: until,
+			});
+		}
+
+		Ok(ban_list)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::IpAddr;
+	use util::time::{IncrementalTime, ZeroTime};
+	use super::{BanList, BanListError, BAN_SCORE_THRESHOLD, DEFAULT_BAN_TIME};
+
+	#[test]
+	fn test_ban_list_ban_and_unban() {
+		let a0: IpAddr = "127.0.0.1".parse().unwrap();
+		let a1: IpAddr = "::1".parse().unwrap();
+		let mut ban_list = BanList::<ZeroTime>::default();
+		assert!(!ban_list.is_banned(&a0));
+		assert_eq!(ban_list.ban(a0, 100), Ok(()));
+		assert_eq!(ban_list.ban(a0, 100), Err(BanListError::AlreadyBanned));
+		assert_eq!(ban_list.ban(a1, 100), Ok(()));
+		assert!(ban_list.is_banned(&a0));
+		assert!(ban_list.is_banned(&a1));
+		assert_eq!(ban_list.entries().len(), 2);
+
+		assert_eq!(ban_list.unban(&a0), Ok(()));
+		assert_eq!(ban_list.unban(&a0), Err(BanListError::NotBanned));
+		assert!(!ban_list.is_banned(&a0));
+
+		ban_list.clear();
+		assert!(!ban_list.is_banned(&a1));
+		assert!(ban_list.entries().is_empty());
+	}
+
+	#[test]
+	fn test_ban_list_ban_expires() {
+		let a0: IpAddr = "127.0.0.1".parse().unwrap();
+		let mut ban_list = BanList::<IncrementalTime>::default();
+		// banned at 0 until 2
+		ban_list.ban(a0, 2).unwrap();
+		// checked at 1
+		assert!(ban_list.is_banned(&a0));
+		// checked at 2
+		assert!(!ban_list.is_banned(&a0));
+		assert_eq!(ban_list.unban(&a0), Err(BanListError::NotBanned));
+	}
+
+	#[test]
+	fn test_ban_list_misbehavior() {
+		let a0: IpAddr = "127.0.0.1".parse().unwrap();
+		let a1: IpAddr = "127.0.0.2".parse().unwrap();
+		let mut ban_list = BanList::<ZeroTime>::default();
+		assert!(!ban_list.note_misbehavior(a0, 0));
+		assert!(!ban_list.note_misbehavior(a0, BAN_SCORE_THRESHOLD - 10));
+		assert!(!ban_list.note_misbehavior(a1, 10));
+		assert!(!ban_list.is_banned(&a0));
+		assert!(ban_list.note_misbehavior(a0, 10));
+		assert!(ban_list.is_banned(&a0));
+		assert!(!ban_list.is_banned(&a1));
+		assert!(!ban_list.note_misbehavior(a0, BAN_SCORE_THRESHOLD));
+
+		let entries = ban_list.entries();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].address, a0);
+		assert_eq!(entries[0].until, DEFAULT_BAN_TIME);
+	}
+
+	#[test]
+	fn test_ban_list_save_and_load() {
+		let a0: IpAddr = "127.0.0.1".parse().unwrap();
+		let a1: IpAddr = "::1".parse().unwrap();
+		let mut ban_list = BanList::<ZeroTime>::default();
+		ban_list.ban(a0, 100).unwrap();
+		ban_list.ban(a1, 200).unwrap();
+
+		let mut db = Vec::new();
+		assert_eq!(ban_list.save(&mut db).unwrap(), ());
+		let loaded_ban_list = BanList::<ZeroTime>::load(&db as &[u8]).unwrap();
+		assert_eq!(ban_list.banned, loaded_ban_list.banned);
+
+		let s = String::from_utf8(db).unwrap();
+		assert!(s.contains("127.0.0.1 0 100\n"));
+		assert!(s.contains("::1 0 200\n"));
+	}
+}
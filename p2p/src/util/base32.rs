@@ -0,0 +1,62 @@
+//! Lowercase base32 encoding without padding (RFC 4648), used by onion and I2P host names.
+
+const ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+pub fn encode(data: &[u8]) -> String {
+	let mut encoded = String::with_capacity((data.len() * 8 + 4) / 5);
+	let mut bits = 0u32;
+	let mut bits_len = 0;
+	for byte in data {
+		bits = (bits << 8) | *byte as u32;
+		bits_len += 8;
+		while bits_len >= 5 {
+			bits_len -= 5;
+			encoded.push(ALPHABET[(bits >> bits_len) as usize & 0x1f] as char);
+		}
+	}
+
+	// the last character is padded with zero bits
+	if bits_len > 0 {
+		encoded.push(ALPHABET[(bits << (5 - bits_len)) as usize & 0x1f] as char);
+	}
+
+	encoded
+}
+
+/// Decodes lowercase base32 string. Trailing bits, which don't form the whole byte, are ignored.
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+	let mut data = Vec::with_capacity(encoded.len() * 5 / 8);
+	let mut bits = 0u32;
+	let mut bits_len = 0;
+	for c in encoded.bytes() {
+		let value = match ALPHABET.iter().position(|a| *a == c) {
+			Some(value) => value,
+			None => return None,
+		};
+
+		bits = (bits << 5) | value as u32;
+		bits_len += 5;
+		if bits_len >= 8 {
+			bits_len -= 8;
+			data.push((bits >> bits_len) as u8);
+		}
+	}
+
+	Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{encode, decode};
+
+	#[test]
+	fn test_base32() {
+		assert_eq!(encode(b""), "");
+		assert_eq!(encode(b"f"), "my");
+		assert_eq!(encode(b"foobar"), "mzxw6ytboi");
+		assert_eq!(decode("mzxw6ytboi"), Some(b"foobar".to_vec()));
+		assert_eq!(decode("my"), Some(b"f".to_vec()));
+		assert_eq!(decode("MY"), None);
+		assert_eq!(decode("m1"), None);
+	}
+}
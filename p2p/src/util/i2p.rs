@@ -0,0 +1,175 @@
+use std::{fmt, str, net};
+use crypto::sha256;
+use hash::H256;
+use util::base32;
+
+/// Prefix of the GarliCat IPv6 addresses.
+const GARLICAT_PREFIX: [u8; 6] = [0xfd, 0x60, 0xdb, 0x4d, 0xdd, 0xb5];
+/// I2P flavour of base64 alphabet, used by SAM bridge for destinations and private keys.
+const BASE64_ALPHABET: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-~";
+/// Size of the public keys, preceding certificate in the destination.
+const DESTINATION_KEYS_LEN: usize = 384;
+/// Size of the certificate header: type and payload length.
+const CERTIFICATE_HEADER_LEN: usize = 3;
+
+/// I2P address, which is SHA256 hash of the I2P destination.
+///
+/// Like onion services, every I2P address is represented by the GarliCat IPv6 address,
+/// built from the first 10 bytes of the hash.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct I2pAddress(H256);
+
+impl I2pAddress {
+	pub fn new(hash: H256) -> Self {
+		I2pAddress(hash)
+	}
+
+	/// Returns address of the base64-encoded destination, as it is reported by SAM bridge.
+	/// Private key of the destination could also be passed, since it starts with the destination.
+	pub fn from_destination(destination: &str) -> Option<Self> {
+		let data = match decode_base64(destination) {
+			Some(data) => data,
+			None => return None,
+		};
+		if data.len() < DESTINATION_KEYS_LEN + CERTIFICATE_HEADER_LEN {
+			return None;
+		}
+
+		let certificate_len = ((data[DESTINATION_KEYS_LEN + 1] as usize) << 8) | data[DESTINATION_KEYS_LEN + 2] as usize;
+		let destination_len = DESTINATION_KEYS_LEN + CERTIFICATE_HEADER_LEN + certificate_len;
+		if data.len() < destination_len {
+			return None;
+		}
+
+		Some(I2pAddress(sha256(&data[..destination_len])))
+	}
+
+	/// Hash of the I2P destination.
+	pub fn hash(&self) -> &H256 {
+		&self.0
+	}
+
+	/// Returns socket address, representing the I2P address.
+	pub fn socket_addr(&self, port: u16) -> net::SocketAddr {
+		let mut octets = [0u8; 16];
+		octets[..6].copy_from_slice(&GARLICAT_PREFIX);
+		octets[6..].copy_from_slice(&self.0[..10]);
+		net::SocketAddr::V6(net::SocketAddrV6::new(octets.into(), port, 0, 0))
+	}
+}
+
+/// Is this socket address representing the I2P address?
+pub fn is_i2p(address: &net::SocketAddr) -> bool {
+	match *address {
+		net::SocketAddr::V6(ref address) => address.ip().octets()[..6] == GARLICAT_PREFIX,
+		net::SocketAddr::V4(_) => false,
+	}
+}
+
+/// Decodes base64 string with I2P alphabet. Padding is optional.
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+	let mut data = Vec::with_capacity(encoded.len() * 3 / 4);
+	let mut bits = 0u32;
+	let mut bits_len = 0;
+	for c in encoded.bytes().take_while(|c| *c != b'=') {
+		let value = match BASE64_ALPHABET.iter().position(|a| *a == c) {
+			Some(value) => value,
+			None => return None,
+		};
+
+		bits = (bits << 6) | value as u32;
+		bits_len += 6;
+		if bits_len >= 8 {
+			bits_len -= 8;
+			data.push((bits >> bits_len) as u8);
+		}
+	}
+
+	Some(data)
+}
+
+impl fmt::Display for I2pAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.b32.i2p", base32::encode(&*self.0))
+	}
+}
+
+impl str::FromStr for I2pAddress {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.to_lowercase();
+		let encoded = match s.len() {
+			60 if s.ends_with(".b32.i2p") => &s[..52],
+			_ => return Err("Invalid I2P address"),
+		};
+
+		// 52 characters are decoded to 32 bytes and 4 unused bits
+		let data = base32::decode(encoded).ok_or("Invalid I2P address")?;
+		Ok(I2pAddress(H256::from(&data[..])))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::SocketAddr;
+	use crypto::sha256;
+	use super::{I2pAddress, is_i2p, decode_base64, BASE64_ALPHABET};
+
+	fn encode_base64(data: &[u8]) -> String {
+		let mut encoded = String::new();
+		for chunk in data.chunks(3) {
+			let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| bits | (*byte as u32) << (16 - 8 * i));
+			for i in 0..chunk.len() + 1 {
+				encoded.push(BASE64_ALPHABET[(bits >> (18 - 6 * i)) as usize & 0x3f] as char);
+			}
+		}
+		encoded
+	}
+
+	#[test]
+	fn test_i2p_address() {
+		let host = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p";
+		let address: I2pAddress = host.parse().unwrap();
+		assert_eq!(address.hash(), &"79bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f".into());
+		assert_eq!(address.to_string(), host);
+		assert_eq!(host.to_uppercase().parse::<I2pAddress>(), Ok(address));
+		assert!("pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.i2p".parse::<I2pAddress>().is_err());
+		assert!("pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4p1.b32.i2p".parse::<I2pAddress>().is_err());
+	}
+
+	#[test]
+	fn test_i2p_socket_addr() {
+		let address: I2pAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p".parse().unwrap();
+		let socket = address.socket_addr(0);
+		assert_eq!(socket, "[fd60:db4d:ddb5:79bc:c625:184b:519:4975]:0".parse::<SocketAddr>().unwrap());
+		assert!(is_i2p(&socket));
+		assert!(!is_i2p(&"[fd87:d87e:eb43:79bc:c625:184b:519:4975]:0".parse().unwrap()));
+		assert!(!is_i2p(&"10.0.0.1:8333".parse().unwrap()));
+	}
+
+	#[test]
+	fn test_decode_base64() {
+		assert_eq!(decode_base64("AAEC"), Some(vec![0, 1, 2]));
+		assert_eq!(decode_base64("-~--"), Some(vec![0xfb, 0xff, 0xbe]));
+		assert_eq!(decode_base64("AQ=="), Some(vec![1]));
+		assert_eq!(decode_base64("+/=="), None);
+	}
+
+	#[test]
+	fn test_i2p_address_from_destination() {
+		// keys are followed by the key certificate with 4-bytes payload
+		let mut destination = vec![0xfe; 384];
+		destination.extend_from_slice(&[5, 0, 4, 0, 7, 0, 4]);
+		let address = I2pAddress::new(sha256(&destination));
+		assert_eq!(I2pAddress::from_destination(&encode_base64(&destination)), Some(address));
+
+		// private key starts with the destination
+		let mut private_key = destination.clone();
+		private_key.extend_from_slice(&[0xab; 64]);
+		assert_eq!(I2pAddress::from_destination(&encode_base64(&private_key)), Some(address));
+
+		assert_eq!(I2pAddress::from_destination(&encode_base64(&destination[..390])), None);
+		assert_eq!(I2pAddress::from_destination("invalid destination"), None);
+	}
+}
@@ -41,9 +41,19 @@ impl InternetProtocol {
 	}
 }
 
+/// Is this socket address in CJDNS network?
+///
+/// CJDNS addresses are in fc00::/8 range, which isn't routable in IPv6 network.
+pub fn is_cjdns(addr: &net::SocketAddr) -> bool {
+	match *addr {
+		net::SocketAddr::V6(ref addr) => addr.ip().octets()[0] == 0xfc,
+		net::SocketAddr::V4(_) => false,
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::InternetProtocol;
+	use super::{InternetProtocol, is_cjdns};
 
 	#[test]
 	fn test_default_internet_protocol() {
@@ -56,4 +66,11 @@ mod tests {
 		assert_eq!(InternetProtocol::IpV6, "ipv6".parse().unwrap());
 		assert!("sa".parse::<InternetProtocol>().is_err());
 	}
+
+	#[test]
+	fn test_is_cjdns() {
+		assert!(is_cjdns(&"[fc00::1]:8333".parse().unwrap()));
+		assert!(!is_cjdns(&"[fd00::1]:8333".parse().unwrap()));
+		assert!(!is_cjdns(&"127.0.0.1:8333".parse().unwrap()));
+	}
 }
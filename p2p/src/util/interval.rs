@@ -10,7 +10,7 @@ pub trait Interval : Default {
 	}
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct RealInterval;
 
 impl Interval for RealInterval { }
@@ -1,14 +1,27 @@
 pub mod nonce;
 pub mod time;
 pub mod interval;
+mod address_manager;
+mod ban_list;
+mod base32;
+mod i2p;
 mod internet_protocol;
-mod node_table;
+mod network_time;
+mod onion;
+mod overlay;
 mod peer;
+mod permissions;
 mod response_queue;
 mod synchronizer;
 
-pub use self::internet_protocol::InternetProtocol;
-pub use self::node_table::{NodeTable, NodeTableError, Node};
-pub use self::peer::{PeerId, PeerInfo, Direction};
+pub use self::address_manager::{AddressManager, AddressManagerError, Node, address_group};
+pub use self::ban_list::{BanList, BanEntry, BanListError, DEFAULT_BAN_TIME};
+pub use self::i2p::{I2pAddress, is_i2p};
+pub use self::internet_protocol::{InternetProtocol, is_cjdns};
+pub use self::network_time::NetworkTime;
+pub use self::onion::{OnionAddress, is_onion};
+pub use self::overlay::OverlayAddress;
+pub use self::peer::{PeerId, PeerInfo, Direction, ConnectionType};
+pub use self::permissions::{PeerPermissions, Subnet, WhitelistEntry, WhiteBind};
 pub use self::response_queue::{ResponseQueue, Responses};
 pub use self::synchronizer::{Synchronizer, ConfigurableSynchronizer};
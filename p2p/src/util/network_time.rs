@@ -0,0 +1,147 @@
+use std::cmp;
+use std::net::IpAddr;
+use parking_lot::Mutex;
+use time;
+
+/// Maximal number of peers, which clocks are sampled.
+pub const MAX_TIME_SAMPLES: usize = 200;
+/// Minimal number of samples, required to adjust the local time.
+pub const MIN_TIME_SAMPLES: usize = 5;
+/// Local time is never adjusted by more than this offset (in seconds).
+pub const MAX_TIME_ADJUSTMENT: i64 = 70 * 60;
+/// Sampled offsets are clamped to this range (in seconds), so that median is always computed without overflows.
+const MAX_SAMPLED_OFFSET: i64 = 2 * MAX_TIME_ADJUSTMENT;
+/// Local clock is considered correct if it is this close to the clock of any peer (in seconds).
+const CLOCK_WARNING_THRESHOLD: i64 = 5 * 60;
+
+/// Offsets of peers clocks, sampled from their `version` messages.
+#[derive(Debug, Default)]
+struct TimeSamples {
+	/// Offsets (peer time - local time) by peer address, in order of arrival.
+	offsets: Vec<(IpAddr, i64)>,
+	/// Current adjustment of the local time.
+	adjustment: i64,
+	/// True if user has been warned that the local clock is wrong.
+	warned: bool,
+}
+
+/// Network-adjusted time: local time plus median offset of peers clocks.
+///
+/// Every peer address is sampled once. Adjustment is only changed when the number of samples is odd
+/// and isn't less than `MIN_TIME_SAMPLES`. When median offset exceeds `MAX_TIME_ADJUSTMENT`, local time
+/// isn't adjusted at all, and the warning is logged, if no peer clock is close to the local clock.
+#[derive(Debug, Default)]
+pub struct NetworkTime {
+	samples: Mutex<TimeSamples>,
+}
+
+impl NetworkTime {
+	/// Remembers offset of the peer clock, reported in the `version` message.
+	pub fn add_sample(&self, address: IpAddr, peer_time: i64, local_time: i64) {
+		let mut samples = self.samples.lock();
+		if samples.offsets.len() >= MAX_TIME_SAMPLES || samples.offsets.iter().any(|&(sampled, _)| sampled == address) {
+			return;
+		}
+
+		let offset = peer_time.saturating_sub(local_time);
+		let offset = cmp::max(-MAX_SAMPLED_OFFSET, cmp::min(MAX_SAMPLED_OFFSET, offset));
+		samples.offsets.push((address, offset));
+		let count = samples.offsets.len();
+		if count < MIN_TIME_SAMPLES || count % 2 == 0 {
+			return;
+		}
+
+		let mut offsets: Vec<_> = samples.offsets.iter().map(|&(_, offset)| offset).collect();
+		offsets.sort();
+		let median = offsets[count / 2];
+		if median.abs() <= MAX_TIME_ADJUSTMENT {
+			samples.adjustment = median;
+			return;
+		}
+
+		samples.adjustment = 0;
+		if !samples.warned && !offsets.iter().any(|offset| offset.abs() < CLOCK_WARNING_THRESHOLD) {
+			samples.warned = true;
+			warn!("Please check that your computer's date and time are correct! Clocks of all peers differ from the local clock by more than {} minutes", CLOCK_WARNING_THRESHOLD / 60);
+		}
+	}
+
+	/// Returns current adjustment of the local time (in seconds).
+	pub fn offset(&self) -> i64 {
+		self.samples.lock().adjustment
+	}
+
+	/// Returns number of sampled peers clocks.
+	pub fn samples_count(&self) -> usize {
+		self.samples.lock().offsets.len()
+	}
+
+	/// Returns network-adjusted time (in seconds since epoch).
+	pub fn adjusted_time(&self) -> u32 {
+		(time::get_time().sec + self.offset()) as u32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::IpAddr;
+	use super::{NetworkTime, MAX_TIME_ADJUSTMENT, MAX_TIME_SAMPLES};
+
+	fn address(n: u32) -> IpAddr {
+		IpAddr::V4(n.into())
+	}
+
+	#[test]
+	fn test_network_time_median_offset() {
+		let network_time = NetworkTime::default();
+		for (n, offset) in [10i64, -20, 30, 40].iter().enumerate() {
+			network_time.add_sample(address(n as u32), 1000 + offset, 1000);
+		}
+		// not enough samples
+		assert_eq!(network_time.offset(), 0);
+
+		// same peer is sampled once
+		network_time.add_sample(address(0), 1100, 1000);
+		assert_eq!(network_time.samples_count(), 4);
+
+		network_time.add_sample(address(4), 1050, 1000);
+		assert_eq!(network_time.offset(), 30);
+
+		// even number of samples doesn't change adjustment
+		network_time.add_sample(address(5), 1000, 1000);
+		assert_eq!(network_time.offset(), 30);
+		network_time.add_sample(address(6), 990, 1000);
+		assert_eq!(network_time.offset(), 10);
+	}
+
+	#[test]
+	fn test_network_time_ignores_large_offset() {
+		let network_time = NetworkTime::default();
+		for n in 0..5 {
+			network_time.add_sample(address(n), 1000 + MAX_TIME_ADJUSTMENT + 1, 1000);
+		}
+		assert_eq!(network_time.offset(), 0);
+	}
+
+	#[test]
+	fn test_network_time_extreme_offsets() {
+		let network_time = NetworkTime::default();
+		for n in 0..3 {
+			network_time.add_sample(address(n), i64::min_value(), i64::max_value());
+		}
+		for n in 3..5 {
+			network_time.add_sample(address(n), i64::max_value(), i64::min_value());
+		}
+		assert_eq!(network_time.samples_count(), 5);
+		assert_eq!(network_time.offset(), 0);
+	}
+
+	#[test]
+	fn test_network_time_max_samples() {
+		let network_time = NetworkTime::default();
+		for n in 0..MAX_TIME_SAMPLES as u32 + 10 {
+			network_time.add_sample(address(n), 1000, 1000);
+		}
+		assert_eq!(network_time.samples_count(), MAX_TIME_SAMPLES);
+	}
+}
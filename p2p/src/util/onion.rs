@@ -0,0 +1,107 @@
+use std::{fmt, str, net};
+use crypto::sha3_256;
+use hash::H256;
+use util::base32;
+
+/// Prefix of the OnionCat IPv6 addresses.
+const ONIONCAT_PREFIX: [u8; 6] = [0xfd, 0x87, 0xd8, 0x7e, 0xeb, 0x43];
+const ONION_VERSION: u8 = 3;
+
+/// Tor v3 onion service address.
+///
+/// Node table and connections are keyed by socket addresses, so every onion service is
+/// represented by the OnionCat IPv6 address, built from the first 10 bytes of its public key.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct OnionAddress(H256);
+
+impl OnionAddress {
+	pub fn new(public: H256) -> Self {
+		OnionAddress(public)
+	}
+
+	/// Public key of the onion service.
+	pub fn public(&self) -> &H256 {
+		&self.0
+	}
+
+	/// Returns socket address, representing the onion service.
+	pub fn socket_addr(&self, port: u16) -> net::SocketAddr {
+		let mut octets = [0u8; 16];
+		octets[..6].copy_from_slice(&ONIONCAT_PREFIX);
+		octets[6..].copy_from_slice(&self.0[..10]);
+		net::SocketAddr::V6(net::SocketAddrV6::new(octets.into(), port, 0, 0))
+	}
+
+	fn checksum(&self) -> [u8; 2] {
+		let mut input = b".onion checksum".to_vec();
+		input.extend_from_slice(&*self.0);
+		input.push(ONION_VERSION);
+		let hash = sha3_256(&input);
+		[hash[0], hash[1]]
+	}
+}
+
+/// Is this socket address representing the onion service?
+pub fn is_onion(address: &net::SocketAddr) -> bool {
+	match *address {
+		net::SocketAddr::V6(ref address) => address.ip().octets()[..6] == ONIONCAT_PREFIX,
+		net::SocketAddr::V4(_) => false,
+	}
+}
+
+impl fmt::Display for OnionAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut data = self.0.to_vec();
+		data.extend_from_slice(&self.checksum());
+		data.push(ONION_VERSION);
+		write!(f, "{}.onion", base32::encode(&data))
+	}
+}
+
+impl str::FromStr for OnionAddress {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.to_lowercase();
+		let encoded = match s.len() {
+			62 if s.ends_with(".onion") => &s[..56],
+			_ => return Err("Invalid onion address"),
+		};
+
+		// 56 characters are decoded to exactly 35 bytes
+		let data = base32::decode(encoded).ok_or("Invalid onion address")?;
+		let address = OnionAddress(H256::from(&data[..32]));
+		if data[34] != ONION_VERSION || data[32..34] != address.checksum() {
+			return Err("Invalid onion address");
+		}
+
+		Ok(address)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::SocketAddr;
+	use super::{OnionAddress, is_onion};
+
+	#[test]
+	fn test_onion_address() {
+		let host = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+		let address: OnionAddress = host.parse().unwrap();
+		assert_eq!(address.public(), &"79bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f".into());
+		assert_eq!(address.to_string(), host);
+		assert_eq!(host.to_uppercase().parse::<OnionAddress>(), Ok(address));
+		assert!("pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscrye.onion".parse::<OnionAddress>().is_err());
+		assert!("pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd".parse::<OnionAddress>().is_err());
+	}
+
+	#[test]
+	fn test_onion_socket_addr() {
+		let address: OnionAddress = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion".parse().unwrap();
+		let socket = address.socket_addr(8333);
+		assert_eq!(socket, "[fd87:d87e:eb43:79bc:c625:184b:519:4975]:8333".parse::<SocketAddr>().unwrap());
+		assert!(is_onion(&socket));
+		assert!(!is_onion(&"[2001:db8::1]:8333".parse().unwrap()));
+		assert!(!is_onion(&"10.0.0.1:8333".parse().unwrap()));
+	}
+}
@@ -0,0 +1,64 @@
+use std::{fmt, str, net};
+use message::common::NetAddressV2;
+use util::{OnionAddress, I2pAddress};
+
+/// Address of the node in the network, where nodes can't be addressed by IP addresses.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum OverlayAddress {
+	Onion(OnionAddress),
+	I2p(I2pAddress),
+}
+
+impl OverlayAddress {
+	/// Returns socket address, representing the node in the address manager.
+	pub fn socket_addr(&self, port: u16) -> net::SocketAddr {
+		match *self {
+			OverlayAddress::Onion(ref onion) => onion.socket_addr(port),
+			OverlayAddress::I2p(ref i2p) => i2p.socket_addr(port),
+		}
+	}
+}
+
+impl From<OverlayAddress> for NetAddressV2 {
+	fn from(address: OverlayAddress) -> Self {
+		match address {
+			OverlayAddress::Onion(onion) => NetAddressV2::TorV3(onion.public().clone()),
+			OverlayAddress::I2p(i2p) => NetAddressV2::I2p(i2p.hash().clone()),
+		}
+	}
+}
+
+impl fmt::Display for OverlayAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			OverlayAddress::Onion(ref onion) => fmt::Display::fmt(onion, f),
+			OverlayAddress::I2p(ref i2p) => fmt::Display::fmt(i2p, f),
+		}
+	}
+}
+
+impl str::FromStr for OverlayAddress {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.parse().map(OverlayAddress::Onion)
+			.or_else(|_| s.parse().map(OverlayAddress::I2p))
+			.map_err(|_| "Invalid overlay network address")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::OverlayAddress;
+
+	#[test]
+	fn test_parse_overlay_address() {
+		let onion = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+		let i2p = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pq.b32.i2p";
+		assert_eq!(onion.parse(), Ok(OverlayAddress::Onion(onion.parse().unwrap())));
+		assert_eq!(i2p.parse(), Ok(OverlayAddress::I2p(i2p.parse().unwrap())));
+		assert_eq!(onion.parse::<OverlayAddress>().unwrap().to_string(), onion);
+		assert_eq!(i2p.parse::<OverlayAddress>().unwrap().to_string(), i2p);
+		assert!("127.0.0.1".parse::<OverlayAddress>().is_err());
+	}
+}
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use message::types;
 use network::Magic;
+use util::PeerPermissions;
 
 pub type PeerId = usize;
 
@@ -10,14 +11,43 @@ pub enum Direction {
 	Outbound,
 }
 
+/// Class of the connection. Connections of every class have their own slots and eviction policy.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ConnectionType {
+	Inbound,
+	/// Outbound connection, relaying blocks, transactions and addresses.
+	OutboundFullRelay,
+	/// Outbound connection, relaying blocks only. These connections can't be detected by watching
+	/// transactions and addresses relay, so they make eclipse attacks harder.
+	BlockRelayOnly,
+	/// Short-lived outbound connection, testing the address from the new table.
+	Feeler,
+	/// Short-lived outbound connection to the seednode, fetching addresses.
+	AddrFetch,
+}
+
+impl ConnectionType {
+	pub fn direction(&self) -> Direction {
+		match *self {
+			ConnectionType::Inbound => Direction::Inbound,
+			_ => Direction::Outbound,
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct PeerInfo {
 	pub id: PeerId,
 	pub address: SocketAddr,
 	pub user_agent: String,
 	pub direction: Direction,
+	pub connection_type: ConnectionType,
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
+	/// Has peer asked for `addrv2` messages during handshake?
+	pub addrv2: bool,
+	/// Permissions, granted to the whitelisted peer.
+	pub permissions: PeerPermissions,
 }
 
@@ -0,0 +1,232 @@
+use std::{str, net};
+
+/// Permissions, granted to whitelisted peers.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PeerPermissions {
+	/// Peer is never banned or disconnected for misbehavior. Banned peer can still connect.
+	pub noban: bool,
+	/// Transactions from the peer are relayed even if they are already in the memory pool.
+	pub forcerelay: bool,
+	/// Peer may request content of the memory pool with `mempool` message.
+	pub mempool: bool,
+	/// Peer may set bloom filters (BIP37), even if the node doesn't serve them to other peers.
+	pub bloomfilter: bool,
+}
+
+impl PeerPermissions {
+	/// Permissions of peers, which are whitelisted without explicit permissions.
+	pub fn implicit() -> Self {
+		PeerPermissions {
+			noban: true,
+			forcerelay: false,
+			mempool: true,
+			bloomfilter: false,
+		}
+	}
+
+	/// Returns permissions, granted by either of permission sets.
+	pub fn union(&self, other: &PeerPermissions) -> Self {
+		PeerPermissions {
+			noban: self.noban || other.noban,
+			forcerelay: self.forcerelay || other.forcerelay,
+			mempool: self.mempool || other.mempool,
+			bloomfilter: self.bloomfilter || other.bloomfilter,
+		}
+	}
+}
+
+impl str::FromStr for PeerPermissions {
+	type Err = &'static str;
+
+	/// Parses comma-separated list of permissions.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut permissions = PeerPermissions::default();
+		for permission in s.split(',') {
+			match permission {
+				"noban" => permissions.noban = true,
+				"forcerelay" => permissions.forcerelay = true,
+				"mempool" => permissions.mempool = true,
+				"bloomfilter" => permissions.bloomfilter = true,
+				_ => return Err("Invalid permission - should be one of noban, forcerelay, mempool, bloomfilter"),
+			}
+		}
+		Ok(permissions)
+	}
+}
+
+/// Splits `[permissions@]value` string. Implicit permissions are granted, when permissions aren't specified.
+fn split_permissions(s: &str) -> Result<(PeerPermissions, &str), &'static str> {
+	match s.find('@') {
+		Some(position) => Ok((try!(s[..position].parse()), &s[position + 1..])),
+		None => Ok((PeerPermissions::implicit(), s)),
+	}
+}
+
+/// Range of IP addresses, sharing the same prefix.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Subnet {
+	/// Network address.
+	address: net::IpAddr,
+	/// Length of the prefix (in bits).
+	prefix_len: u8,
+}
+
+impl Subnet {
+	/// Returns true if address belongs to the subnet.
+	pub fn contains(&self, address: &net::IpAddr) -> bool {
+		match (self.address, *address) {
+			(net::IpAddr::V4(ref subnet), net::IpAddr::V4(ref address)) =>
+				prefix_matches(&subnet.octets(), &address.octets(), self.prefix_len),
+			(net::IpAddr::V6(ref subnet), net::IpAddr::V6(ref address)) =>
+				prefix_matches(&subnet.octets(), &address.octets(), self.prefix_len),
+			_ => false,
+		}
+	}
+}
+
+impl str::FromStr for Subnet {
+	type Err = &'static str;
+
+	/// Parses single address (`1.2.3.4`) or subnet in CIDR notation (`1.2.3.0/24`).
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (address, prefix_len) = match s.find('/') {
+			Some(position) => (&s[..position], Some(&s[position + 1..])),
+			None => (s, None),
+		};
+
+		let address: net::IpAddr = try!(address.parse().map_err(|_| "Invalid subnet address"));
+		let max_prefix_len = match address {
+			net::IpAddr::V4(_) => 32,
+			net::IpAddr::V6(_) => 128,
+		};
+		let prefix_len = match prefix_len {
+			Some(prefix_len) => try!(prefix_len.parse().map_err(|_| "Invalid subnet prefix length")),
+			None => max_prefix_len,
+		};
+		if prefix_len > max_prefix_len {
+			return Err("Invalid subnet prefix length");
+		}
+
+		Ok(Subnet {
+			address: address,
+			prefix_len: prefix_len,
+		})
+	}
+}
+
+fn prefix_matches(subnet: &[u8], address: &[u8], prefix_len: u8) -> bool {
+	let full_bytes = prefix_len as usize / 8;
+	if subnet[..full_bytes] != address[..full_bytes] {
+		return false;
+	}
+
+	let remaining_bits = prefix_len % 8;
+	if remaining_bits == 0 {
+		return true;
+	}
+
+	let mask = 0xffu8 << (8 - remaining_bits);
+	subnet[full_bytes] & mask == address[full_bytes] & mask
+}
+
+/// Peers from the subnet are granted given permissions (`--whitelist`).
+#[derive(Debug, PartialEq, Clone)]
+pub struct WhitelistEntry {
+	pub permissions: PeerPermissions,
+	pub subnet: Subnet,
+}
+
+impl str::FromStr for WhitelistEntry {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (permissions, subnet) = try!(split_permissions(s));
+		Ok(WhitelistEntry {
+			permissions: permissions,
+			subnet: try!(subnet.parse()),
+		})
+	}
+}
+
+/// Peers, connected to the address, are granted given permissions (`--whitebind`).
+#[derive(Debug, PartialEq, Clone)]
+pub struct WhiteBind {
+	pub permissions: PeerPermissions,
+	pub address: net::SocketAddr,
+}
+
+impl str::FromStr for WhiteBind {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (permissions, address) = try!(split_permissions(s));
+		Ok(WhiteBind {
+			permissions: permissions,
+			address: try!(address.parse().map_err(|_| "Invalid bind address - should be ip:port")),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PeerPermissions, Subnet, WhitelistEntry, WhiteBind};
+
+	#[test]
+	fn test_parse_permissions() {
+		assert_eq!("noban,bloomfilter".parse::<PeerPermissions>(), Ok(PeerPermissions {
+			noban: true,
+			forcerelay: false,
+			mempool: false,
+			bloomfilter: true,
+		}));
+		assert!("noban,relay".parse::<PeerPermissions>().is_err());
+	}
+
+	#[test]
+	fn test_subnet_contains() {
+		let subnet: Subnet = "192.168.0.0/23".parse().unwrap();
+		assert!(subnet.contains(&"192.168.0.1".parse().unwrap()));
+		assert!(subnet.contains(&"192.168.1.255".parse().unwrap()));
+		assert!(!subnet.contains(&"192.168.2.0".parse().unwrap()));
+		assert!(!subnet.contains(&"::1".parse().unwrap()));
+
+		let subnet: Subnet = "127.0.0.1".parse().unwrap();
+		assert!(subnet.contains(&"127.0.0.1".parse().unwrap()));
+		assert!(!subnet.contains(&"127.0.0.2".parse().unwrap()));
+
+		let subnet: Subnet = "2001:db8::/32".parse().unwrap();
+		assert!(subnet.contains(&"2001:db8::1".parse().unwrap()));
+		assert!(!subnet.contains(&"2001:db9::1".parse().unwrap()));
+
+		let subnet: Subnet = "0.0.0.0/0".parse().unwrap();
+		assert!(subnet.contains(&"8.8.8.8".parse().unwrap()));
+
+		assert!("192.168.0.0/33".parse::<Subnet>().is_err());
+		assert!("192.168.0/24".parse::<Subnet>().is_err());
+	}
+
+	#[test]
+	fn test_parse_whitelist_entry() {
+		let entry: WhitelistEntry = "10.0.0.0/8".parse().unwrap();
+		assert_eq!(entry.permissions, PeerPermissions::implicit());
+		assert!(entry.subnet.contains(&"10.1.2.3".parse().unwrap()));
+
+		let entry: WhitelistEntry = "forcerelay,mempool@10.0.0.1".parse().unwrap();
+		assert_eq!(entry.permissions, PeerPermissions {
+			noban: false,
+			forcerelay: true,
+			mempool: true,
+			bloomfilter: false,
+		});
+		assert!(entry.subnet.contains(&"10.0.0.1".parse().unwrap()));
+	}
+
+	#[test]
+	fn test_parse_whitebind() {
+		let bind: WhiteBind = "bloomfilter@127.0.0.1:8334".parse().unwrap();
+		assert_eq!(bind.address, "127.0.0.1:8334".parse().unwrap());
+		assert!(bind.permissions.bloomfilter);
+		assert!(!bind.permissions.noban);
+		assert!("noban@127.0.0.1".parse::<WhiteBind>().is_err());
+	}
+}
@@ -0,0 +1,54 @@
+use clap::ArgMatches;
+use config::{Config, parse_check_level, DEFAULT_CHECK_BLOCKS};
+use util::init_db;
+use verification::{check_chain, CheckLevel};
+
+/// Checks last `blocks` canon chain blocks, logging all found problems.
+/// Returns the number of the first bad block, if any.
+fn find_bad_block(cfg: &Config, blocks: u32, level: CheckLevel) -> Option<u32> {
+	info!(target: "pbtc", "Checking {} blocks at level {:?}", if blocks == 0 { "all".to_owned() } else { blocks.to_string() }, level);
+	let bad_blocks = check_chain(&*cfg.db, &cfg.consensus, blocks, level);
+	for bad_block in &bad_blocks {
+		error!(target: "pbtc", "Block {} ({}): {}", bad_block.number, bad_block.hash.to_reversed_str(), bad_block.error);
+	}
+
+	bad_blocks.first().map(|bad_block| bad_block.number)
+}
+
+/// Startup database check.
+pub fn check_db(cfg: &Config, blocks: u32, level: CheckLevel) -> Result<(), String> {
+	match find_bad_block(cfg, blocks, level) {
+		Some(number) => Err(format!("Database is corrupted starting from block {}. Run `pbtc check --rollback` to revert to the last good block", number)),
+		None => Ok(()),
+	}
+}
+
+pub fn check(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	try!(init_db(&cfg));
+
+	let blocks = match matches.value_of("blocks") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of blocks to check".to_owned())?,
+		None => DEFAULT_CHECK_BLOCKS,
+	};
+	let level = match matches.value_of("level") {
+		Some(s) => parse_check_level(s)?,
+		None => CheckLevel::Full,
+	};
+
+	let bad_block = match find_bad_block(&cfg, blocks, level) {
+		Some(number) => number,
+		None => {
+			info!(target: "pbtc", "No problems found");
+			return Ok(());
+		},
+	};
+
+	if !matches.is_present("rollback") {
+		return Err(format!("Database is corrupted starting from block {}. Pass --rollback to revert to the last good block", bad_block));
+	}
+
+	cfg.db.rollback_to(bad_block - 1).map_err(|e| format!("Failed to rollback block {}: {:?}", cfg.db.best_block().number, e))?;
+
+	info!(target: "pbtc", "Reverted to block {}", bad_block - 1);
+	Ok(())
+}
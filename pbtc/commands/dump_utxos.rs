@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use clap::ArgMatches;
+use ethcore_rpc::v1::helpers::utxo_dump::{UtxoDumpFormat, write_utxo_dump};
+use config::Config;
+use util::init_db;
+
+/// Format of the dumped unspent outputs.
+enum DumpFormat {
+	/// CSV or binary dump, also written by the `dumputxos` RPC call.
+	Dump(UtxoDumpFormat),
+	/// Snapshot, which can be loaded by the load-utxos command.
+	Snapshot,
+}
+
+pub fn dump_utxos(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	try!(init_db(&cfg));
+	// snapshot only includes changes, written to the disk
+	cfg.db.flush().map_err(String::from)?;
+
+	let path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
+	let format = match matches.value_of("format").unwrap_or("csv") {
+		"snapshot" => DumpFormat::Snapshot,
+		format => DumpFormat::Dump(UtxoDumpFormat::from_name(format).ok_or_else(|| format!("Invalid dump format: {}", format))?),
+	};
+
+	let file = File::create(path).map_err(|err| format!("Cannot create dump file {}: {}", path, err))?;
+	let mut writer = BufWriter::new(file);
+	let write_error = |err| format!("Cannot write dump file {}: {}", path, err);
+
+	let snapshot = cfg.db.utxo_snapshot().map_err(String::from)?;
+	let best_block = snapshot.best_block().clone();
+	match format {
+		DumpFormat::Snapshot => {
+			let stats = snapshot.write_to(&mut writer, cfg.magic.into()).map_err(String::from)?;
+			writer.flush().map_err(&write_error)?;
+			info!(target: "pbtc", "Dumped {} unspent outputs at block {} ({}). MuHash of the set: {}",
+				stats.txouts, best_block.number, best_block.hash.to_reversed_str(), stats.muhash.to_reversed_str());
+		},
+		DumpFormat::Dump(format) => {
+			let dumped = write_utxo_dump(&mut writer, &snapshot, format, cfg.address_network)
+				.map_err(|err| format!("Cannot write dump file {}: {}", path, err))?;
+			info!(target: "pbtc", "Dumped {} unspent outputs at block {} ({})", dumped, best_block.number, best_block.hash.to_reversed_str());
+		},
+	}
+
+	Ok(())
+}
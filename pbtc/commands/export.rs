@@ -0,0 +1,46 @@
+use clap::ArgMatches;
+use db::BlockRef;
+use config::Config;
+use util::init_db;
+use import::BlkWriter;
+
+pub fn export(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	try!(init_db(&cfg));
+
+	let path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
+	let best_block_number = cfg.db.best_block().number;
+	let from = match matches.value_of("from") {
+		Some(s) => s.parse().map_err(|_| "Invalid first block number".to_owned())?,
+		None => 0,
+	};
+	let to = match matches.value_of("to") {
+		Some(s) => s.parse().map_err(|_| "Invalid last block number".to_owned())?,
+		None => best_block_number,
+	};
+	if from > to || to > best_block_number {
+		return Err(format!("Invalid blocks range {}..{}. Best block is {}", from, to, best_block_number));
+	}
+
+	let magic: u32 = cfg.magic.into();
+	let mut writer = match matches.value_of("format") {
+		None | Some("blk") => BlkWriter::blk_dir(path, magic),
+		Some("linear") => BlkWriter::linear(path, magic),
+		Some(format) => return Err(format!("Invalid export format: {}", format)),
+	}.map_err(|err| format!("Cannot create export files: {}", err))?;
+
+	for number in from..to + 1 {
+		let block = cfg.db.block_bytes(BlockRef::Number(number))
+			.ok_or_else(|| format!("Block {} is missing in the database", number))?;
+		writer.write_block(&block).map_err(|err| format!("Cannot write block {}: {}", number, err))?;
+
+		let exported = number - from + 1;
+		if exported % 1000 == 0 {
+			info!(target: "sync", "Exported {} blocks", exported);
+		}
+	}
+
+	writer.flush().map_err(|err| format!("Cannot write export files: {}", err))?;
+	info!("Finished export of {} blocks", to - from + 1);
+
+	Ok(())
+}
@@ -1,23 +1,43 @@
+use std::fs;
+use std::io::Write;
 use clap::ArgMatches;
 use sync::{create_sync_blocks_writer, Error};
 use config::Config;
 use util::init_db;
+use import::BlkCursor;
+
+/// Default number of threads, used to decode blocks.
+const DEFAULT_IMPORT_THREADS: usize = 4;
+/// Import cursor is saved after this number of imported blocks.
+const CURSOR_SAVE_INTERVAL: usize = 1000;
 
 pub fn import(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 	try!(init_db(&cfg));
 
 	let blk_path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
+	let threads = match matches.value_of("threads") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of import threads".to_owned())?,
+		None => DEFAULT_IMPORT_THREADS,
+	};
+	let cursor_path = matches.value_of("cursor");
+	let start = match cursor_path {
+		Some(path) => load_cursor(path)?,
+		None => BlkCursor::default(),
+	};
 
 	let mut writer = create_sync_blocks_writer(cfg.db, cfg.consensus, cfg.verification_params);
 
-	let blk_dir = try!(::import::open_blk_dir(blk_path).map_err(|_| "Import directory does not exist".to_owned()));
+	let blk_dir = try!(::import::open_blk_dir_parallel(blk_path, cfg.magic.into(), threads, start)
+		.map_err(|_| "Import directory does not exist".to_owned()));
 	let mut counter = 0;
+	let mut unsaved = 0;
+	let mut position = start;
 	for blk in blk_dir {
-		// TODO: verify magic!
-		let blk = try!(blk.map_err(|_| "Cannot read block".to_owned()));
+		let blk = try!(blk.map_err(|err| format!("Cannot read block: {}", err)));
 		match writer.append_block(blk.block) {
 			Ok(_) => {
 				counter += 1;
+				unsaved += 1;
 				if counter % 1000 == 0 {
 					info!(target: "sync", "Imported {} blocks", counter);
 				}
@@ -25,9 +45,40 @@ pub fn import(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 			Err(Error::TooManyOrphanBlocks) => return Err("Too many orphan (unordered) blocks".into()),
 			Err(_) => return Err("Cannot append block".into()),
 		}
+		position = blk.next;
+
+		// blocks, waiting for their parents, would be skipped if import is resumed from the later position
+		if let Some(path) = cursor_path {
+			if unsaved >= CURSOR_SAVE_INTERVAL && writer.orphaned_blocks_len() == 0 {
+				save_cursor(path, &position)?;
+				unsaved = 0;
+			}
+		}
+	}
+
+	let orphans = writer.orphaned_blocks_len();
+	if orphans != 0 {
+		warn!("{} blocks were not imported, because their parents are unknown", orphans);
+	} else if let Some(path) = cursor_path {
+		if unsaved != 0 {
+			save_cursor(path, &position)?;
+		}
 	}
 
 	info!("Finished import of {} blocks", counter);
 
 	Ok(())
 }
+
+fn load_cursor(path: &str) -> Result<BlkCursor, String> {
+	match fs::read_to_string(path) {
+		Ok(cursor) => cursor.parse(),
+		// first run
+		Err(_) => Ok(BlkCursor::default()),
+	}
+}
+
+fn save_cursor(path: &str, cursor: &BlkCursor) -> Result<(), String> {
+	let mut file = fs::File::create(path).map_err(|err| format!("Cannot save import cursor: {}", err))?;
+	write!(file, "{}", cursor).map_err(|err| format!("Cannot save import cursor: {}", err))
+}
@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::BufReader;
+use clap::ArgMatches;
+use db::UtxoSnapshotReader;
+use network::Magic;
+use primitives::hash::H256;
+use config::Config;
+use util::init_db;
+
+pub fn load_utxos(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	try!(init_db(&cfg));
+
+	let path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
+	let open = || File::open(path)
+		.map(BufReader::new)
+		.map_err(|err| format!("Cannot open snapshot file {}: {}", path, err));
+
+	// outputs are loaded as is => the whole file is verified before anything is written to the database
+	let mut snapshot = UtxoSnapshotReader::new(open()?).map_err(String::from)?;
+	let metadata = snapshot.metadata().clone();
+	if metadata.network_magic != u32::from(cfg.magic) {
+		return Err("Snapshot is created for the other network".into());
+	}
+
+	// only hardcoded snapshots are trusted on the main network
+	let muhash = match (cfg.consensus.assume_utxo_muhash(metadata.base_block.number, &metadata.base_block.hash), matches.value_of("muhash")) {
+		(Some(muhash), _) => muhash.clone(),
+		(None, Some(_)) if cfg.magic == Magic::Mainnet => return Err("Snapshot block is not a known snapshot block of the main network".into()),
+		(None, Some(muhash)) => {
+			let muhash: H256 = muhash.parse().map_err(|e| format!("Invalid MuHash: {}", e))?;
+			muhash.reversed()
+		},
+		(None, None) => return Err("Snapshot block is not a known snapshot block. MuHash of the snapshot may be given with --muhash on test networks".into()),
+	};
+
+	info!(target: "pbtc", "Verifying snapshot of {} unspent outputs at block {} ({})",
+		metadata.coins, metadata.base_block.number, metadata.base_block.hash.to_reversed_str());
+	let snapshot_muhash = snapshot.verify().map_err(String::from)?;
+	if snapshot_muhash != muhash {
+		return Err(format!("MuHash of the snapshot ({}) doesn't match the expected one", snapshot_muhash.to_reversed_str()));
+	}
+
+	let best_block = cfg.db.load_utxo_snapshot(&mut open()?).map_err(String::from)?;
+	cfg.db.flush().map_err(String::from)?;
+
+	// blocks below the snapshot base block are never downloaded => database can only be used by the pruned node
+	info!(target: "pbtc", "Loaded {} unspent outputs at block {} ({}). Use --prune to start the node with this database",
+		metadata.coins, best_block.number, best_block.hash.to_reversed_str());
+	Ok(())
+}
@@ -1,7 +1,16 @@
+mod check;
+mod dump_utxos;
+mod export;
 mod import;
+mod load_utxos;
+mod reindex;
 mod start;
 mod rollback;
 
+pub use self::check::check;
+pub use self::dump_utxos::dump_utxos;
+pub use self::export::export;
 pub use self::import::import;
+pub use self::load_utxos::load_utxos;
 pub use self::start::start;
 pub use self::rollback::rollback;
\ No newline at end of file
@@ -0,0 +1,66 @@
+use std::fs;
+use db::BlockRef;
+use primitives::hash::H256;
+use sync::{create_sync_blocks_writer, Error, VerificationParameters};
+use verification::VerificationLevel;
+use config::{Config, ReindexMode};
+use daemon;
+use util::{open_reindex_db, reindex_db_path};
+
+/// Replays canon chain blocks of the reindexed database through the verifier, building the new database.
+/// Reindexed database is removed once all its blocks are replayed.
+pub fn reindex_db(cfg: &Config, mode: ReindexMode) -> Result<(), String> {
+	let source = open_reindex_db(&cfg.data_dir, cfg.magic)?;
+	let source_best_block = source.best_block();
+	let best_block = cfg.db.best_block();
+	// reindex is resumed from the best block of the new database
+	match source.block_hash(best_block.number) {
+		Some(ref hash) if *hash != best_block.hash =>
+			return Err(format!("Database diverges from the reindexed database at block {}", best_block.number)),
+		_ => (),
+	}
+
+	let verification_params = match mode {
+		ReindexMode::Full => VerificationParameters {
+			verification_level: cfg.verification_params.verification_level,
+			verification_edge: cfg.verification_params.verification_edge.clone(),
+		},
+		// scripts were verified when blocks have been accepted for the first time
+		ReindexMode::ChainState => VerificationParameters {
+			verification_level: VerificationLevel::Header,
+			verification_edge: H256::default(),
+		},
+	};
+	let mut writer = create_sync_blocks_writer(cfg.db.clone(), cfg.consensus.clone(), verification_params);
+
+	info!(target: "pbtc", "Reindexing blocks {} to {} ({:?})", best_block.number + 1, source_best_block.number, mode);
+	let mut counter = 0;
+	for number in best_block.number + 1..source_best_block.number + 1 {
+		if daemon::shutdown_signal_received() {
+			cfg.db.flush().map_err(|err| format!("Failed to flush database: {:?}", err))?;
+			info!(target: "pbtc", "Reindex is interrupted after {} blocks. It is resumed on the next start", counter);
+			return Ok(());
+		}
+
+		let block = source.indexed_block(BlockRef::Number(number))
+			.ok_or_else(|| format!("Cannot read block {} of the reindexed database", number))?;
+		match writer.append_block(block) {
+			Ok(_) => (),
+			Err(Error::Verification(err)) => return Err(format!("Reindexed block {} is invalid: {}", number, err)),
+			Err(err) => return Err(format!("Cannot append reindexed block {}: {:?}", number, err)),
+		}
+
+		counter += 1;
+		if counter % 1000 == 0 {
+			info!(target: "pbtc", "Reindexed {} blocks", counter);
+		}
+	}
+
+	cfg.db.flush().map_err(|err| format!("Failed to flush database: {:?}", err))?;
+	drop(source);
+	let source_path = reindex_db_path(&cfg.data_dir, cfg.magic);
+	fs::remove_dir_all(&source_path).map_err(|err| format!("Cannot remove reindexed database {}: {}", source_path.display(), err))?;
+
+	info!(target: "pbtc", "Finished reindex of {} blocks", counter);
+	Ok(())
+}
@@ -10,29 +10,24 @@ pub fn rollback(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 	let block_ref = matches.value_of("BLOCK").expect("BLOCK is required in cli.yml; qed");
 	let block_ref = if block_ref.len() == 64 {
 		BlockRef::Hash({
-			let hash: H256 = block_ref.parse().map_err(|e| format!("Invalid block number: {}", e))?;
+			let hash: H256 = block_ref.parse().map_err(|e| format!("Invalid block hash: {}", e))?;
 			hash.reversed()
 		})
 	} else {
-		BlockRef::Number(block_ref.parse().map_err(|e| format!("Invalid block hash: {}", e))?)
+		BlockRef::Number(block_ref.parse().map_err(|e| format!("Invalid block number: {}", e))?)
 	};
 
-	let required_block_hash = cfg.db.block_header(block_ref.clone()).ok_or(format!("Block {:?} is unknown", block_ref))?.hash();
-	let genesis_hash = cfg.magic.genesis_block().hash();
+	// only canon chain blocks can become the best block
+	let required_block_number = match block_ref {
+		BlockRef::Number(number) => cfg.db.block_hash(number).map(|_| number),
+		BlockRef::Hash(ref hash) => cfg.db.block_number(hash),
+	}.ok_or(format!("Block {:?} is not in the canonical chain", block_ref))?;
 
-	let mut best_block_hash = cfg.db.best_block().hash;
-	debug_assert!(best_block_hash != H256::default()); // genesis inserted in init_db
+	let best_block = cfg.db.best_block();
+	debug_assert!(best_block.hash != H256::default()); // genesis inserted in init_db
+	info!("Reverting {} blocks", best_block.number - required_block_number);
 
-	loop {
-		if best_block_hash == required_block_hash {
-			info!("Reverted to block {:?}", block_ref);
-			return Ok(());
-		} 
-
-		if best_block_hash == genesis_hash {
-			return Err(format!("Failed to revert to block {:?}. Reverted to genesis", block_ref));
-		}
-
-		best_block_hash = cfg.db.rollback_best().map_err(|e| format!("{:?}", e))?;
-	}
+	let best_block_hash = cfg.db.rollback_to(required_block_number).map_err(|e| format!("{:?}", e))?;
+	info!("Reverted to block {} ({})", required_block_number, best_block_hash.to_reversed_str());
+	Ok(())
 }
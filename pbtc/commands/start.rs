@@ -1,13 +1,33 @@
+use std::fs::File;
+use std::io::{self, Write, BufWriter};
 use std::net::SocketAddr;
+use std::path::Path;
 use std::thread;
+use std::time::Duration;
 use std::sync::Arc;
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::sync::atomic::{AtomicBool, Ordering};
-use sync::{create_sync_peers, create_local_sync_node, create_sync_connection_factory, SyncListener};
+use sync::{create_sync_peers, create_local_sync_node, create_sync_connection_factory, SyncListener, LocalNodeRef};
+use chain::Transaction;
 use primitives::hash::H256;
-use util::{init_db, node_table_path};
-use {config, p2p, PROTOCOL_VERSION, PROTOCOL_MINIMUM};
+use ser::{deserialize, deserialize_iterator, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use util::{init_db, address_manager_path, ban_list_path, i2p_private_key_path, memory_pool_path, fee_estimates_path, pidfile_path, rpc_cookie_path};
+use daemon::{self, PidFile};
+use health::HealthHandler;
+use zmq_notifier::ZmqNotifier;
+use wallet_notifier::WalletNotifier;
+use stratum::{StratumCore, StratumListener};
+use miner::StratumServer;
+use wallet::Wallet;
+use ethcore_rpc::v1::HealthClientCore;
+use ethcore_rpc::{HttpServer, RestHandler, WsServer, WsHandler, WsNotifier, ElectrumServer, ElectrumHandler, ElectrumCore, ElectrumNotifier};
+use {config, logs, p2p, PROTOCOL_VERSION, PROTOCOL_MINIMUM};
 use super::super::rpc;
+use super::check::check_db;
+use super::reindex::reindex_db;
+
+/// How often event loop checks if shutdown has been requested.
+const SHUTDOWN_CHECK_INTERVAL_MS: u64 = 100;
 
 enum BlockNotifierTask {
 	NewBlock(H256),
@@ -81,17 +101,92 @@ impl Drop for BlockNotifier {
 	}
 }
 
-pub fn start(cfg: config::Config) -> Result<(), String> {
+/// Writes memory pool transactions to the file, ancestors first.
+fn save_memory_pool(local_sync_node: &LocalNodeRef, path: &Path) -> Result<usize, io::Error> {
+	let transactions = local_sync_node.memory_pool_transactions();
+	let mut file = BufWriter::new(File::create(path)?);
+	for transaction in &transactions {
+		file.write_all(&serialize_with_flags(transaction, SERIALIZE_TRANSACTION_WITNESS))?;
+	}
+	file.flush()?;
+	Ok(transactions.len())
+}
+
+/// Verifies transactions, saved by `save_memory_pool`, and puts valid ones back to the memory pool.
+fn load_memory_pool(local_sync_node: &LocalNodeRef, path: &Path) -> Result<usize, io::Error> {
+	let file = File::open(path)?;
+	let mut accepted = 0;
+	for transaction in deserialize_iterator::<_, Transaction>(file) {
+		let transaction = transaction.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+		let hash = transaction.hash();
+		match local_sync_node.accept_transaction(transaction) {
+			Ok(_) => accepted += 1,
+			Err(err) => trace!(target: "pbtc", "Saved transaction {} is rejected: {}", hash.to_reversed_str(), err),
+		}
+	}
+	Ok(accepted)
+}
+
+/// Writes fee estimator statistics to the file.
+fn save_fee_estimates(local_sync_node: &LocalNodeRef, path: &Path) -> Result<(), io::Error> {
+	let mut file = BufWriter::new(File::create(path)?);
+	file.write_all(&serialize(&local_sync_node.fee_estimator()))?;
+	file.flush()
+}
+
+/// Restores fee estimator statistics, saved by `save_fee_estimates`.
+fn load_fee_estimates(local_sync_node: &LocalNodeRef, path: &Path) -> Result<(), io::Error> {
+	let file = File::open(path)?;
+	let fee_estimator = deserialize(file).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+	local_sync_node.set_fee_estimator(fee_estimator);
+	Ok(())
+}
+
+/// Writes collected timing spans in the folded stacks format.
+fn save_spans(path: &Path) -> Result<(), io::Error> {
+	let mut file = BufWriter::new(File::create(path)?);
+	logs::write_folded_spans(&mut file)?;
+	file.flush()
+}
+
+pub fn start(cfg: config::Config, logger: logs::LogHandle) -> Result<(), String> {
+	daemon::handle_shutdown_signals();
+	let _pidfile = if cfg.daemon || cfg.pidfile.is_some() {
+		Some(PidFile::create(pidfile_path(&cfg))?)
+	} else {
+		None
+	};
+
+	if cfg.trace_spans.is_some() {
+		logs::enable_spans();
+	}
+
 	let mut el = p2p::event_loop();
 
 	init_db(&cfg)?;
+	if let Some(mode) = cfg.reindex {
+		reindex_db(&cfg, mode)?;
+		if daemon::shutdown_signal_received() {
+			return Ok(());
+		}
+	}
+	if let Some(blocks) = cfg.check_blocks {
+		check_db(&cfg, blocks, cfg.check_level)?;
+	}
 
-	let nodes_path = node_table_path(&cfg);
+	let peers_path = address_manager_path(&cfg);
+	let banned_path = ban_list_path(&cfg);
+	let i2p_key_path = i2p_private_key_path(&cfg);
+	let mempool_path = memory_pool_path(&cfg);
+	let fee_estimates_path = fee_estimates_path(&cfg);
+	// shared by p2p, which samples clocks of peers, and sync, which verifies and mines blocks
+	let network_time = Arc::new(p2p::NetworkTime::default());
 
 	let p2p_cfg = p2p::Config {
 		threads: cfg.p2p_threads,
 		inbound_connections: cfg.inbound_connections,
 		outbound_connections: cfg.outbound_connections,
+		block_relay_connections: cfg.block_relay_connections,
 		connection: p2p::NetConfig {
 			protocol_version: PROTOCOL_VERSION,
 			protocol_minimum: PROTOCOL_MINIMUM,
@@ -104,30 +199,146 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		},
 		peers: cfg.connect.map_or_else(|| vec![], |x| vec![x]),
 		seeds: cfg.seednodes,
-		node_table_path: nodes_path,
-		preferable_services: cfg.services,
+		address_manager_path: peers_path,
+		ban_list_path: banned_path,
+		// pruned node still prefers peers, serving all blocks
+		preferable_services: cfg.services.with_network(true).with_network_limited(false),
 		internet_protocol: cfg.internet_protocol,
+		proxy: cfg.proxy,
+		onion: cfg.onion,
+		i2p_sam: cfg.i2p_sam,
+		i2p_private_key_path: i2p_key_path,
+		whitelist: cfg.whitelist,
+		whitebind: cfg.whitebind,
+		max_upload_target: cfg.max_upload_target,
+		network_time: network_time.clone(),
 	};
 
 	let sync_peers = create_sync_peers();
-	let local_sync_node = create_local_sync_node(cfg.consensus, cfg.db.clone(), sync_peers.clone(), cfg.verification_params);
+	let local_sync_node = create_local_sync_node(cfg.consensus.clone(), cfg.db.clone(), sync_peers.clone(), cfg.verification_params, cfg.max_memory_pool_size, cfg.transaction_reconciliation, cfg.peer_bloom_filters, network_time);
 	let sync_connection_factory = create_sync_connection_factory(sync_peers.clone(), local_sync_node.clone());
 
 	if let Some(block_notify_command) = cfg.block_notify_command {
 		local_sync_node.install_sync_listener(Box::new(BlockNotifier::new(block_notify_command)));
 	}
 
+	if !cfg.zmq_notifications.is_empty() {
+		local_sync_node.install_sync_listener(Box::new(ZmqNotifier::new(cfg.zmq_notifications, cfg.db.clone())?));
+	}
+
+	let ws_server = match cfg.ws_address {
+		Some(address) => {
+			let ws_server = WsServer::start(address, WsHandler::new(cfg.db.clone()))?;
+			local_sync_node.install_sync_listener(Box::new(WsNotifier::new(&ws_server)?));
+			Some(ws_server)
+		},
+		None => None,
+	};
+
+	let electrum_server = match cfg.electrum_address {
+		Some(address) => {
+			let electrum_core = ElectrumCore::new(local_sync_node.clone());
+			let electrum_server = ElectrumServer::start(address, ElectrumHandler::new(cfg.db.clone(), electrum_core))?;
+			local_sync_node.install_sync_listener(Box::new(ElectrumNotifier::new(&electrum_server)?));
+			Some(electrum_server)
+		},
+		None => None,
+	};
+
+	let stratum_server = match cfg.stratum {
+		Some(stratum) => {
+			let stratum_server = StratumServer::start(StratumCore::new(local_sync_node.clone()), stratum, cfg.consensus.clone())?;
+			local_sync_node.install_sync_listener(Box::new(StratumListener::new(stratum_server.notifier())));
+			Some(stratum_server)
+		},
+		None => None,
+	};
+
+	let wallet = if cfg.wallet {
+		let wallet = Arc::new(Wallet::open(cfg.db.clone(), cfg.address_network)?);
+		for descriptor in cfg.wallet_descriptors {
+			wallet.import_descriptor(descriptor)?;
+		}
+		local_sync_node.install_sync_listener(Box::new(WalletNotifier::new(wallet.clone())?));
+		Some(wallet)
+	} else {
+		None
+	};
+
+	// estimator must be restored before memory pool transactions are accepted
+	if fee_estimates_path.exists() {
+		match load_fee_estimates(&local_sync_node, &fee_estimates_path) {
+			Ok(_) => info!(target: "pbtc", "Loaded fee estimates"),
+			Err(err) => warn!(target: "pbtc", "Failed to load fee estimates: {}", err),
+		}
+	}
+
+	if mempool_path.exists() {
+		match load_memory_pool(&local_sync_node, &mempool_path) {
+			Ok(count) => info!(target: "pbtc", "Loaded {} memory pool transactions", count),
+			Err(err) => warn!(target: "pbtc", "Failed to load memory pool: {}", err),
+		}
+	}
+
+	let shutdown = Arc::new(AtomicBool::new(false));
+	let rest_consensus = cfg.consensus.clone();
 	let p2p = try!(p2p::P2P::new(p2p_cfg, sync_connection_factory, el.handle()).map_err(|x| x.to_string()));
 	let rpc_deps = rpc::Dependencies {
-		network: cfg.magic,
-		storage: cfg.db,
-		local_sync_node: local_sync_node,
+		address_network: cfg.address_network,
+		storage: cfg.db.clone(),
+		consensus: cfg.consensus,
+		local_sync_node: local_sync_node.clone(),
 		p2p_context: p2p.context().clone(),
 		remote: el.remote(),
+		shutdown: shutdown.clone(),
+		logger: logger,
+		wallet: wallet,
+	};
+	let rpc_cookie_path = rpc_cookie_path(&cfg);
+	let rpc_server = try!(rpc::new_http(cfg.rpc_config, rpc_cookie_path, rpc_deps));
+	let health_server = match cfg.health_address {
+		Some(address) => Some(HttpServer::start("Health check", address, HealthHandler::new(HealthClientCore::new(local_sync_node.clone(), cfg.db.clone(), p2p.context().clone())))?),
+		None => None,
+	};
+	let rest_server = match cfg.rest_address {
+		Some(address) => Some(HttpServer::start("REST", address, RestHandler::new(cfg.address_network, cfg.db.clone(), rest_consensus))?),
+		None => None,
 	};
-	let _rpc_server = try!(rpc::new_http(cfg.rpc_config, rpc_deps));
 
 	try!(p2p.run().map_err(|_| "Failed to start p2p module"));
-	el.run(p2p::forever()).unwrap();
+	while !shutdown.load(Ordering::SeqCst) && !daemon::shutdown_signal_received() {
+		el.turn(Some(Duration::from_millis(SHUTDOWN_CHECK_INTERVAL_MS)));
+	}
+
+	info!(target: "pbtc", "Shutting down");
+	drop(stratum_server);
+	drop(electrum_server);
+	drop(ws_server);
+	drop(rest_server);
+	drop(health_server);
+	drop(rpc_server);
+	// closes all peers connections
+	drop(p2p);
+
+	match save_memory_pool(&local_sync_node, &mempool_path) {
+		Ok(count) => info!(target: "pbtc", "Saved {} memory pool transactions", count),
+		Err(err) => error!(target: "pbtc", "Failed to save memory pool: {}", err),
+	}
+
+	match save_fee_estimates(&local_sync_node, &fee_estimates_path) {
+		Ok(_) => info!(target: "pbtc", "Saved fee estimates"),
+		Err(err) => error!(target: "pbtc", "Failed to save fee estimates: {}", err),
+	}
+
+	cfg.db.flush().map_err(|err| format!("Failed to flush database: {:?}", err))?;
+
+	if let Some(ref path) = cfg.trace_spans {
+		match save_spans(Path::new(path)) {
+			Ok(_) => info!(target: "pbtc", "Saved timing spans to {}", path),
+			Err(err) => error!(target: "pbtc", "Failed to save timing spans: {}", err),
+		}
+	}
+
+	info!(target: "pbtc", "Shutdown complete");
 	Ok(())
 }
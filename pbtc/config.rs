@@ -1,130 +1,468 @@
 use std::net;
-use clap;
 use db;
+use keys::Network as AddressNetwork;
+use chain::Block;
 use message::Services;
-use network::{Magic, ConsensusParams, ConsensusFork, SEGWIT2X_FORK_BLOCK, BITCOIN_CASH_FORK_BLOCK};
-use p2p::InternetProtocol;
-use seednodes::{mainnet_seednodes, testnet_seednodes, segwit2x_seednodes};
+use network::{Magic, ConsensusParams, ConsensusFork, BlockLimits, BitcoinCashConsensusParams, Deployment};
+use p2p::{InternetProtocol, WhitelistEntry, WhiteBind};
+use seednodes::{mainnet_seednodes, testnet_seednodes, signet_seednodes};
 use rpc_apis::ApiSet;
 use {USER_AGENT, REGTEST_USER_AGENT};
+
+/// Prefix of the custom block limits fork name, stored in the database.
+const BLOCK_LIMITS_FORK_PREFIX: &'static str = "block-limits:";
 use primitives::hash::H256;
+use primitives::bytes::Bytes;
 use rpc::HttpConfiguration as RpcHttpConfig;
-use verification::VerificationLevel;
+use verification::{VerificationLevel, CheckLevel};
 use sync::VerificationParameters;
-use util::open_db;
+use util::{open_db, reindex_db_path, begin_reindex, open_reindex_db, init_reindex_db};
+use network_file::CustomNetwork;
+use config_file::Arguments;
+use zmq_notifier::Topic as ZmqTopic;
+use wallet::Descriptor;
+use miner::StratumConfig;
 
 pub struct Config {
 	pub magic: Magic,
 	pub consensus: ConsensusParams,
+	pub genesis_block: Block,
+	pub address_network: AddressNetwork,
 	pub services: Services,
 	pub port: u16,
 	pub connect: Option<net::SocketAddr>,
 	pub seednodes: Vec<String>,
 	pub quiet: bool,
 	pub inbound_connections: u32,
+	/// Number of outbound full-relay connections.
 	pub outbound_connections: u32,
+	/// Number of outbound block-relay-only connections.
+	pub block_relay_connections: u32,
 	pub p2p_threads: usize,
 	pub db_cache: usize,
+	/// Maximal total size of memory pool transactions (in bytes).
+	pub max_memory_pool_size: usize,
+	/// Reconcile transactions with peers (BIP330).
+	pub transaction_reconciliation: bool,
+	/// Maximal number of bytes to send during 24 hours.
+	pub max_upload_target: Option<u64>,
+	/// Build compact block filters index and serve filters to peers (BIP157/BIP158).
+	pub cfindex: bool,
+	/// Index transaction outputs by address.
+	pub address_index: bool,
+	/// Index canon transactions by hash.
+	pub transaction_index: bool,
+	/// Enable the wallet.
+	pub wallet: bool,
+	/// Descriptors, imported to the wallet on startup.
+	pub wallet_descriptors: Vec<Descriptor>,
+	/// Pruning mode of the block storage.
+	pub prune: db::PruneMode,
+	/// Rebuild the database from blocks of the old database.
+	pub reindex: Option<ReindexMode>,
 	pub data_dir: Option<String>,
 	pub user_agent: String,
 	pub internet_protocol: InternetProtocol,
+	/// SOCKS5 proxy for outbound connections.
+	pub proxy: Option<net::SocketAddr>,
+	/// SOCKS5 proxy for connections to onion services.
+	pub onion: Option<net::SocketAddr>,
+	/// I2P SAM bridge.
+	pub i2p_sam: Option<net::SocketAddr>,
+	/// Inbound peers from these subnets are granted permissions.
+	pub whitelist: Vec<WhitelistEntry>,
+	/// Additional listen addresses, granting permissions to peers.
+	pub whitebind: Vec<WhiteBind>,
+	/// Serve bloom filters (BIP37) to all peers.
+	pub peer_bloom_filters: bool,
 	pub rpc_config: RpcHttpConfig,
+	pub health_address: Option<net::SocketAddr>,
+	pub rest_address: Option<net::SocketAddr>,
+	/// Address of the WebSocket subscriptions server.
+	pub ws_address: Option<net::SocketAddr>,
+	/// Address of the Electrum protocol server.
+	pub electrum_address: Option<net::SocketAddr>,
+	/// Stratum mining server configuration.
+	pub stratum: Option<StratumConfig>,
 	pub block_notify_command: Option<String>,
+	/// ZeroMQ notifications topics and addresses of sockets, they are published to.
+	pub zmq_notifications: Vec<(ZmqTopic, String)>,
+	pub daemon: bool,
+	pub pidfile: Option<String>,
+	pub check_blocks: Option<u32>,
+	pub check_level: CheckLevel,
+	pub log: LogConfig,
+	pub trace_spans: Option<String>,
 	pub verification_params: VerificationParameters,
+	/// Number of script verification threads, same as `bitcoind -par`.
+	pub verification_threads: i32,
 	pub db: db::SharedStore,
 }
 
+/// Logging configuration.
+#[derive(Debug, PartialEq)]
+pub struct LogConfig {
+	/// Log filters, applied on top of the default ones.
+	pub filters: Option<String>,
+	/// Write every message as JSON object.
+	pub json: bool,
+	/// Write logs to the file instead of the standard error.
+	pub file: Option<String>,
+	/// Log file is rotated when its size exceeds this limit (in bytes).
+	pub file_size: u64,
+	/// Number of rotated log files to keep.
+	pub files: usize,
+}
+
+/// How blocks are verified, when the database is reindexed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReindexMode {
+	/// Blocks are verified at the configured verification level.
+	Full,
+	/// Scripts aren't verified, only the chain state and indexes are rebuilt.
+	ChainState,
+}
+
 pub const DEFAULT_DB_CACHE: usize = 512;
+/// Default limit of the memory pool size (in MB).
+pub const DEFAULT_MAX_MEMPOOL: usize = 300;
+pub const DEFAULT_CHECK_BLOCKS: u32 = 6;
+pub const DEFAULT_LOG_FILE_SIZE: u64 = 100;
+pub const DEFAULT_LOG_FILES: usize = 5;
+/// Minimal size of stored blocks (in MB), when blocks are pruned automatically.
+pub const MIN_PRUNE_TARGET: u64 = 550;
+/// Initial share difficulty of Stratum miners.
+pub const DEFAULT_STRATUM_DIFFICULTY: f64 = 1024.0;
+/// Share rate of every Stratum miner, targeted by the difficulty adjustment.
+pub const STRATUM_SHARES_PER_MINUTE: f64 = 20.0;
 
-pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
+pub fn parse(matches: &Arguments) -> Result<Config, String> {
 	let db_cache = match matches.value_of("db-cache") {
 		Some(s) => s.parse().map_err(|_| "Invalid cache size - should be number in MB".to_owned())?,
 		None => DEFAULT_DB_CACHE,
 	};
 
+	let max_memory_pool_size = match matches.value_of("maxmempool") {
+		Some(s) => s.parse::<usize>().map_err(|_| "Invalid memory pool size - should be number in MB".to_owned())?,
+		None => DEFAULT_MAX_MEMPOOL,
+	} * 1_000_000;
+
+	let transaction_reconciliation = matches.is_present("txreconciliation");
+
+	let max_upload_target = match matches.value_of("maxuploadtarget") {
+		Some(s) => match s.parse::<u64>().map_err(|_| "Invalid upload target - should be number in MiB".to_owned())? {
+			0 => None,
+			target => Some(target * 1024 * 1024),
+		},
+		None => None,
+	};
+
 	let data_dir = match matches.value_of("data-dir") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid data-dir".to_owned())?),
 		None => None,
 	};
 
-	let db = open_db(&data_dir, db_cache);
-
 	let quiet = matches.is_present("quiet");
-	let magic = match (matches.is_present("testnet"), matches.is_present("regtest")) {
-		(true, false) => Magic::Testnet,
-		(false, true) => Magic::Regtest,
-		(false, false) => Magic::Mainnet,
-		(true, true) => return Err("Only one testnet option can be used".into()),
+	let magic = match (matches.is_present("testnet"), matches.is_present("regtest"), matches.is_present("signet")) {
+		(true, false, false) => Magic::Testnet,
+		(false, true, false) => Magic::Regtest,
+		(false, false, true) => Magic::signet(),
+		(false, false, false) => Magic::Mainnet,
+		_ => return Err("Only one testnet option can be used".into()),
 	};
 
-	let consensus_fork = parse_consensus_fork(&db, &matches)?;
-	let consensus = ConsensusParams::new(magic, consensus_fork);
+	let signet_challenge: Option<Bytes> = match matches.value_of("signet-challenge") {
+		Some(_) if !matches.is_present("signet") => return Err("Signet challenge can only be used with --signet".into()),
+		Some(s) => Some(s.parse().map_err(|_| "Invalid signet challenge".to_owned())?),
+		None => None,
+	};
+	let magic = match signet_challenge {
+		Some(ref challenge) => Magic::custom_signet(challenge),
+		None => magic,
+	};
 
-	let (in_connections, out_connections) = match magic {
-		Magic::Testnet | Magic::Mainnet | Magic::Other(_) => (10, 10),
-		Magic::Regtest | Magic::Unitest => (1, 0),
+	let custom_network = match matches.value_of("network-file") {
+		Some(_) if magic != Magic::Mainnet => return Err("Network file can't be used together with other network options".into()),
+		Some(path) => Some(CustomNetwork::load(path)?),
+		None => None,
+	};
+	let magic = custom_network.as_ref().map_or(magic, |network| network.magic);
+
+	let cfindex = matches.is_present("cfindex");
+	let address_index = matches.is_present("addressindex");
+	let transaction_index = matches.is_present("txindex");
+	let prune = match matches.value_of("prune") {
+		Some(s) => match s.parse::<u64>().map_err(|_| "Invalid prune target - should be number in MB".to_owned())? {
+			0 => db::PruneMode::Disabled,
+			1 => db::PruneMode::Manual,
+			target if target < MIN_PRUNE_TARGET => return Err(format!("Prune target must be at least {} MB", MIN_PRUNE_TARGET)),
+			target => db::PruneMode::Target(target * 1024 * 1024),
+		},
+		None => db::PruneMode::Disabled,
+	};
+	if prune.is_enabled() && address_index {
+		return Err("Address index can't be used together with pruning".into());
+	}
+	if prune.is_enabled() && transaction_index {
+		return Err("Transaction index can't be used together with pruning".into());
+	}
+
+	let reindex = match (matches.is_present("reindex"), matches.is_present("reindex-chainstate")) {
+		(true, true) => return Err("Only one reindex option can be used".into()),
+		(true, false) => Some(ReindexMode::Full),
+		(false, true) => Some(ReindexMode::ChainState),
+		// interrupted reindex is resumed on the next start
+		(false, false) if reindex_db_path(&data_dir, magic).exists() => Some(ReindexMode::Full),
+		(false, false) => None,
+	};
+	if reindex.is_some() {
+		begin_reindex(&data_dir, magic)?;
+	}
+
+	let db = open_db(&data_dir, magic, db_cache, cfindex, address_index, transaction_index, prune)?;
+	if reindex.is_some() {
+		init_reindex_db(&db, &open_reindex_db(&data_dir, magic)?)?;
+	}
+	if !prune.is_enabled() && db.pruned_blocks() != 0 {
+		return Err("Database is pruned. Use --prune to start the node with this database".into());
+	}
+
+	let consensus_fork = parse_consensus_fork(magic, &db, matches)?;
+	let mut consensus = match (signet_challenge, custom_network.as_ref()) {
+		(Some(challenge), _) => ConsensusParams::new(magic, consensus_fork).with_signet_challenge(challenge),
+		(None, Some(network)) => network.consensus_params(consensus_fork),
+		(None, None) => ConsensusParams::new(magic, consensus_fork),
+	};
+	if let Some(deployments) = matches.values_of("deployment") {
+		if magic == Magic::Mainnet {
+			return Err("Custom deployments can only be used on test networks".into());
+		}
+		for deployment in deployments {
+			let deployment = parse_deployment(deployment, &consensus)?;
+			consensus.custom_deployments.push(deployment);
+		}
+	}
+
+	let genesis_block = custom_network.as_ref().map_or_else(|| magic.genesis_block(), |network| network.genesis_block.clone());
+	let address_network = match custom_network {
+		Some(ref network) => network.address_network,
+		None => match magic {
+			Magic::Mainnet => AddressNetwork::Mainnet,
+			// there's no correct choices for Regtests && Other networks
+			// => let's just make Testnet key
+			_ => AddressNetwork::Testnet,
+		},
+	};
+	let default_port = custom_network.as_ref().map_or(magic.port(), |network| network.port);
+	let default_rpc_port = custom_network.as_ref().map_or(magic.rpc_port(), |network| network.rpc_port);
+
+	let (in_connections, out_connections, block_relay_connections) = match magic {
+		Magic::Testnet | Magic::Mainnet | Magic::Signet(_) | Magic::Other(_) => (10, 8, 2),
+		Magic::Regtest | Magic::Unitest => (1, 0, 0),
+	};
+
+	let in_connections = match matches.value_of("inboundconnections") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of inbound connections".to_owned())?,
+		None => in_connections,
+	};
+
+	let out_connections = match matches.value_of("outboundconnections") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of outbound connections".to_owned())?,
+		None => out_connections,
+	};
+
+	let block_relay_connections = match matches.value_of("blockrelayconnections") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of block-relay-only connections".to_owned())?,
+		None => block_relay_connections,
 	};
 
 	let p2p_threads = match magic {
-		Magic::Testnet | Magic::Mainnet | Magic::Other(_) => 4,
+		Magic::Testnet | Magic::Mainnet | Magic::Signet(_) | Magic::Other(_) => 4,
 		Magic::Regtest | Magic::Unitest => 1,
 	};
 
 	// to skip idiotic 30 seconds delay in test-scripts
 	let user_agent_suffix = match consensus_fork {
 		ConsensusFork::NoFork => "",
-		ConsensusFork::SegWit2x(_) => "/SegWit2x",
+		ConsensusFork::CustomLimits(_) => "/CustomLimits",
 		ConsensusFork::BitcoinCash(_) => "/UAHF",
 	};
 	let user_agent = match magic {
-		Magic::Testnet | Magic::Mainnet | Magic::Unitest | Magic::Other(_) => format!("{}{}", USER_AGENT, user_agent_suffix),
+		Magic::Testnet | Magic::Mainnet | Magic::Signet(_) | Magic::Unitest | Magic::Other(_) => format!("{}{}", USER_AGENT, user_agent_suffix),
 		Magic::Regtest => REGTEST_USER_AGENT.into(),
 	};
 
 	let port = match matches.value_of("port") {
 		Some(port) => port.parse().map_err(|_| "Invalid port".to_owned())?,
-		None => magic.port(),
+		None => default_port,
 	};
 
 	let connect = match matches.value_of("connect") {
 		Some(s) => Some(match s.parse::<net::SocketAddr>() {
 			Err(_) => s.parse::<net::IpAddr>()
-				.map(|ip| net::SocketAddr::new(ip, magic.port()))
+				.map(|ip| net::SocketAddr::new(ip, default_port))
 				.map_err(|_| "Invalid connect".to_owned()),
 			Ok(a) => Ok(a),
 		}?),
 		None => None,
 	};
 
-	let mut seednodes: Vec<String> = match matches.value_of("seednode") {
+	let seednodes: Vec<String> = match matches.value_of("seednode") {
 		Some(s) => vec![s.parse().map_err(|_| "Invalid seednode".to_owned())?],
-		None => match magic {
-			Magic::Mainnet => mainnet_seednodes().into_iter().map(Into::into).collect(),
-			Magic::Testnet => testnet_seednodes().into_iter().map(Into::into).collect(),
-			Magic::Other(_) | Magic::Regtest | Magic::Unitest => Vec::new(),
+		None => match custom_network {
+			Some(ref network) => network.seednodes.clone(),
+			None => match magic {
+				Magic::Mainnet => mainnet_seednodes().into_iter().map(Into::into).collect(),
+				Magic::Testnet => testnet_seednodes().into_iter().map(Into::into).collect(),
+				magic if magic == Magic::signet() => signet_seednodes().into_iter().map(Into::into).collect(),
+				Magic::Signet(_) | Magic::Other(_) | Magic::Regtest | Magic::Unitest => Vec::new(),
+			},
 		},
 	};
-	match consensus_fork {
-		ConsensusFork::SegWit2x(_) => seednodes.extend(segwit2x_seednodes().into_iter().map(Into::into)),
-		_ => (),
-	}
 
 	let only_net = match matches.value_of("only-net") {
 		Some(s) => s.parse()?,
 		None => InternetProtocol::default(),
 	};
 
-	let rpc_config = parse_rpc_config(magic, matches)?;
+	let proxy = match matches.value_of("proxy") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid proxy address - should be ip:port".to_owned())?),
+		None => None,
+	};
+
+	let onion = match matches.value_of("onion") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid onion proxy address - should be ip:port".to_owned())?),
+		None => None,
+	};
+
+	let i2p_sam = match matches.value_of("i2psam") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid I2P SAM bridge address - should be ip:port".to_owned())?),
+		None => None,
+	};
+
+	let whitelist = match matches.values_of("whitelist") {
+		Some(entries) => entries.into_iter().map(|entry| entry.parse()).collect::<Result<_, _>>()?,
+		None => Vec::new(),
+	};
+
+	let whitebind = match matches.values_of("whitebind") {
+		Some(binds) => binds.into_iter().map(|bind| bind.parse()).collect::<Result<_, _>>()?,
+		None => Vec::new(),
+	};
+
+	let rpc_config = parse_rpc_config(default_rpc_port, matches)?;
+	let health_address = match matches.value_of("health-address") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid health check address - should be ip:port".to_owned())?),
+		None => None,
+	};
+	let rest_address = match matches.value_of("rest-address") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid REST address - should be ip:port".to_owned())?),
+		None => None,
+	};
+	let ws_address = match matches.value_of("ws-address") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid WebSocket address - should be ip:port".to_owned())?),
+		None => None,
+	};
+	match ws_address {
+		// subscriptions are not authenticated
+		Some(ref address) if !address.ip().is_loopback() && !matches.is_present("ws-allow-remote") =>
+			return Err("WebSocket server may only listen on non-loopback address with --ws-allow-remote".into()),
+		_ => (),
+	}
+	let electrum_address = match matches.value_of("electrum-address") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid Electrum server address - should be ip:port".to_owned())?),
+		None => None,
+	};
+	if electrum_address.is_some() && !address_index {
+		return Err("Electrum server requires address index (--addressindex)".into());
+	}
+
+	let stratum = match matches.value_of("stratum-address") {
+		Some(s) => {
+			let address = s.parse().map_err(|_| "Invalid Stratum server address - should be ip:port".to_owned())?;
+			let payout = match matches.value_of("stratum-payout") {
+				Some(descriptor) => descriptor.parse::<Descriptor>()?,
+				None => return Err("Stratum server requires payout descriptor (--stratum-payout)".into()),
+			};
+			if let Descriptor::Addr(ref payout_address) = payout {
+				if payout_address.network != address_network {
+					return Err("Stratum payout address belongs to another network".into());
+				}
+			}
+			let difficulty = match matches.value_of("stratum-difficulty") {
+				Some(s) => match s.parse::<f64>() {
+					Ok(difficulty) if difficulty > 0.0 => difficulty,
+					_ => return Err("Invalid Stratum difficulty - should be positive number".into()),
+				},
+				None => DEFAULT_STRATUM_DIFFICULTY,
+			};
+			Some(StratumConfig {
+				address: address,
+				coinbase_script: payout.script_pubkey(),
+				difficulty: difficulty,
+				shares_per_minute: STRATUM_SHARES_PER_MINUTE,
+			})
+		},
+		None => None,
+	};
 
 	let block_notify_command = match matches.value_of("blocknotify") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid blocknotify commmand".to_owned())?),
 		None => None,
 	};
 
-	let services = Services::default().with_network(true);
+	let wallet_descriptors = match matches.values_of("wallet-descriptor") {
+		Some(descriptors) => descriptors.into_iter()
+			.map(|descriptor| descriptor.parse::<Descriptor>())
+			.collect::<Result<Vec<_>, _>>()?,
+		None => Vec::new(),
+	};
+	let wallet = matches.is_present("wallet") || !wallet_descriptors.is_empty();
+
+	let zmq_notifications = ZmqTopic::all().iter()
+		.filter_map(|topic| matches.value_of(&format!("zmqpub{}", topic.name())).map(|address| (*topic, address.to_owned())))
+		.collect();
+
+	let daemon = matches.is_present("daemon");
+	let pidfile = matches.value_of("pidfile").map(ToOwned::to_owned);
+
+	let check_level = match matches.value_of("check-level") {
+		Some(s) => parse_check_level(s)?,
+		None => CheckLevel::Full,
+	};
+	let check_blocks = match matches.value_of("check-blocks") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid number of blocks to check".to_owned())?),
+		None if matches.value_of("check-level").is_some() => Some(DEFAULT_CHECK_BLOCKS),
+		None => None,
+	};
+
+	let log = LogConfig {
+		filters: matches.value_of("log").map(ToOwned::to_owned),
+		json: matches.is_present("log-json"),
+		file: matches.value_of("log-file").map(ToOwned::to_owned),
+		file_size: match matches.value_of("log-file-size") {
+			Some(s) => s.parse::<u64>().map_err(|_| "Invalid log file size - should be number in MB".to_owned())? * 1024 * 1024,
+			None => DEFAULT_LOG_FILE_SIZE * 1024 * 1024,
+		},
+		files: match matches.value_of("log-files") {
+			Some(s) => s.parse().map_err(|_| "Invalid number of log files".to_owned())?,
+			None => DEFAULT_LOG_FILES,
+		},
+	};
+
+	let trace_spans = matches.value_of("trace-spans").map(ToOwned::to_owned);
+
+	// pruned node only serves recent blocks (BIP159)
+	// light clients only use bloom filters with nodes, which are advertising NODE_BLOOM (BIP111)
+	let peer_bloom_filters = matches.is_present("peerbloomfilters");
+	let services = Services::default()
+		.with_network(!prune.is_enabled())
+		.with_network_limited(prune.is_enabled())
+		.with_bloom(peer_bloom_filters)
+		.with_compact_filters(cfindex);
 	let services = match consensus.fork {
 		ConsensusFork::BitcoinCash(_) => services.with_bitcoin_cash(true),
-		ConsensusFork::NoFork | ConsensusFork::SegWit2x(_) => services.with_witness(true),
+		ConsensusFork::NoFork | ConsensusFork::CustomLimits(_) => services.with_witness(true),
 	};
 
 	let verification_level = match matches.value_of("verification-level") {
@@ -136,70 +474,183 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 	};
 
 	let verification_edge = match matches.value_of("verification-edge") {
-		Some(s) if verification_level != VerificationLevel::Full => {
+		// with full verification level, scripts of all blocks are verified
+		Some("0") => H256::default(),
+		Some(s) => {
 			let edge: H256 = s.parse().map_err(|_| "Invalid verification edge".to_owned())?;
 			edge.reversed()
 		},
+		_ if custom_network.is_some() => genesis_block.hash(),
 		_ => magic.default_verification_edge(),
 	};
 
+	let verification_threads = match matches.value_of("verification-threads") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of verification threads".to_owned())?,
+		None => 0,
+	};
+
 	let config = Config {
 		quiet: quiet,
 		magic: magic,
 		consensus: consensus,
+		genesis_block: genesis_block,
+		address_network: address_network,
 		services: services,
 		port: port,
 		connect: connect,
 		seednodes: seednodes,
 		inbound_connections: in_connections,
 		outbound_connections: out_connections,
+		block_relay_connections: block_relay_connections,
 		p2p_threads: p2p_threads,
 		db_cache: db_cache,
+		max_memory_pool_size: max_memory_pool_size,
+		transaction_reconciliation: transaction_reconciliation,
+		max_upload_target: max_upload_target,
+		cfindex: cfindex,
+		address_index: address_index,
+		transaction_index: transaction_index,
+		wallet: wallet,
+		wallet_descriptors: wallet_descriptors,
+		prune: prune,
+		reindex: reindex,
 		data_dir: data_dir,
 		user_agent: user_agent,
 		internet_protocol: only_net,
+		proxy: proxy,
+		onion: onion,
+		i2p_sam: i2p_sam,
+		whitelist: whitelist,
+		whitebind: whitebind,
+		peer_bloom_filters: peer_bloom_filters,
 		rpc_config: rpc_config,
+		health_address: health_address,
+		rest_address: rest_address,
+		ws_address: ws_address,
+		electrum_address: electrum_address,
+		stratum: stratum,
 		block_notify_command: block_notify_command,
+		zmq_notifications: zmq_notifications,
+		daemon: daemon,
+		pidfile: pidfile,
+		check_blocks: check_blocks,
+		check_level: check_level,
+		log: log,
+		trace_spans: trace_spans,
 		verification_params: VerificationParameters {
 			verification_level: verification_level,
 			verification_edge: verification_edge,
 		},
+		verification_threads: verification_threads,
 		db: db,
 	};
 
 	Ok(config)
 }
 
-fn parse_consensus_fork(db: &db::SharedStore, matches: &clap::ArgMatches) -> Result<ConsensusFork, String> {
+fn parse_consensus_fork(magic: Magic, db: &db::SharedStore, matches: &Arguments) -> Result<ConsensusFork, String> {
 	let old_consensus_fork = db.consensus_fork()?;
-	let new_consensus_fork = match (matches.is_present("segwit"), matches.is_present("segwit2x"), matches.is_present("bitcoin-cash")) {
-		(false, false, false) => match &old_consensus_fork {
-			&Some(ref old_consensus_fork) => old_consensus_fork,
-			&None => return Err("You must select fork on first run: --segwit, --segwit2x, --bitcoin-cash".into()),
+	let new_consensus_fork = match (matches.is_present("segwit"), matches.value_of("block-limits"), matches.is_present("bitcoin-cash")) {
+		(false, None, false) => match &old_consensus_fork {
+			&Some(ref old_consensus_fork) => old_consensus_fork.clone(),
+			&None => return Err("You must select fork on first run: --segwit, --block-limits, --bitcoin-cash".into()),
 		},
-		(true, false, false) => "segwit",
-		(false, true, false) => "segwit2x",
-		(false, false, true) => "bitcoin-cash",
-		_ => return Err("You can only pass single fork argument: --segwit, --segwit2x, --bitcoin-cash".into()),
+		(true, None, false) => "segwit".to_owned(),
+		(false, Some(limits), false) => format!("{}{}", BLOCK_LIMITS_FORK_PREFIX, limits),
+		(false, None, true) => "bitcoin-cash".to_owned(),
+		_ => return Err("You can only pass single fork argument: --segwit, --block-limits, --bitcoin-cash".into()),
 	};
 
 	match &old_consensus_fork {
-		&None => db.set_consensus_fork(new_consensus_fork)?,
-		&Some(ref old_consensus_fork) if old_consensus_fork == new_consensus_fork => (),
+		&None => db.set_consensus_fork(&new_consensus_fork)?,
+		&Some(ref old_consensus_fork) if *old_consensus_fork == new_consensus_fork => (),
 		&Some(ref old_consensus_fork) =>
 			return Err(format!("Cannot select '{}' fork with non-empty database of '{}' fork", new_consensus_fork, old_consensus_fork)),
 	}
 
-	Ok(match new_consensus_fork {
+	Ok(match new_consensus_fork.as_ref() {
 		"segwit" => ConsensusFork::NoFork,
-		"segwit2x" => ConsensusFork::SegWit2x(SEGWIT2X_FORK_BLOCK),
-		"bitcoin-cash" => ConsensusFork::BitcoinCash(BITCOIN_CASH_FORK_BLOCK),
-		_ => unreachable!("hardcoded above"),
+		"bitcoin-cash" => ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(magic)),
+		limits if limits.starts_with(BLOCK_LIMITS_FORK_PREFIX) =>
+			ConsensusFork::CustomLimits(parse_block_limits(&limits[BLOCK_LIMITS_FORK_PREFIX.len()..])?),
+		_ => return Err(format!("Unknown fork '{}' in the database", new_consensus_fork)),
+	})
+}
+
+/// Parses block limits in HEIGHT:SIZE:WEIGHT format.
+fn parse_block_limits(limits: &str) -> Result<BlockLimits, String> {
+	let invalid = || format!("Invalid block limits: {}. Expected HEIGHT:SIZE:WEIGHT", limits);
+	let values: Vec<&str> = limits.split(':').collect();
+	if values.len() != 3 {
+		return Err(invalid());
+	}
+
+	let limits = BlockLimits {
+		height: values[0].parse().map_err(|_| invalid())?,
+		max_block_size: values[1].parse().map_err(|_| invalid())?,
+		max_block_weight: values[2].parse().map_err(|_| invalid())?,
+	};
+
+	// blocks above absolute limits are rejected before consensus rules are checked
+	if limits.max_block_size > ConsensusFork::absolute_maximum_block_size() ||
+		limits.max_block_weight > ConsensusFork::absolute_maximum_block_size() * ConsensusFork::witness_scale_factor() {
+		return Err(format!("Block limits must not exceed {} bytes", ConsensusFork::absolute_maximum_block_size()));
+	}
+
+	Ok(limits)
+}
+
+/// Parses versionbits deployment in NAME:BIT:START:TIMEOUT[:THRESHOLD] format.
+fn parse_deployment(deployment: &str, consensus: &ConsensusParams) -> Result<Deployment, String> {
+	let invalid = || format!("Invalid deployment: {}. Expected NAME:BIT:START:TIMEOUT[:THRESHOLD]", deployment);
+	let values: Vec<&str> = deployment.split(':').collect();
+	if values.len() != 4 && values.len() != 5 {
+		return Err(invalid());
+	}
+
+	let bit: u8 = values[1].parse().map_err(|_| invalid())?;
+	// top 3 bits of the version are reserved by BIP9
+	if bit > 28 {
+		return Err(format!("Deployment bit must be in 0..28 range: {}", deployment));
+	}
+
+	let threshold = match values.get(4) {
+		Some(threshold) => Some(threshold.parse().map_err(|_| invalid())?),
+		None => None,
+	};
+	if threshold.map_or(false, |threshold| threshold == 0 || threshold > consensus.miner_confirmation_window) {
+		return Err(format!("Deployment threshold must be in 1..{} range: {}", consensus.miner_confirmation_window, deployment));
+	}
+
+	let known_deployments = consensus.deployments();
+	if known_deployments.iter().any(|known| known.name == values[0] || known.bit == bit) {
+		return Err(format!("Deployment name or bit is already used: {}", deployment));
+	}
+
+	Ok(Deployment {
+		name: values[0].to_owned(),
+		bit: bit,
+		start_time: values[2].parse().map_err(|_| invalid())?,
+		timeout: values[3].parse().map_err(|_| invalid())?,
+		min_activation_height: 0,
+		threshold: threshold,
+		activation: None,
 	})
 }
 
-fn parse_rpc_config(magic: Magic, matches: &clap::ArgMatches) -> Result<RpcHttpConfig, String> {
-	let mut config = RpcHttpConfig::with_port(magic.rpc_port());
+/// Parses database check level: 0 (read blocks), 1 (verify blocks), 2 (check indexes) or 3 (verify against chain state).
+pub fn parse_check_level(level: &str) -> Result<CheckLevel, String> {
+	match level {
+		"0" => Ok(CheckLevel::Read),
+		"1" => Ok(CheckLevel::Block),
+		"2" => Ok(CheckLevel::Index),
+		"3" => Ok(CheckLevel::Full),
+		_ => Err(format!("Invalid check level: {}. Expected 0, 1, 2 or 3", level)),
+	}
+}
+
+fn parse_rpc_config(default_port: u16, matches: &Arguments) -> Result<RpcHttpConfig, String> {
+	let mut config = RpcHttpConfig::with_port(default_port);
 	config.enabled = !matches.is_present("no-jsonrpc");
 	if !config.enabled {
 		return Ok(config);
@@ -220,6 +671,10 @@ fn parse_rpc_config(magic: Magic, matches: &clap::ArgMatches) -> Result<RpcHttpC
 	if let Some(hosts) = matches.value_of("jsonrpc-hosts") {
 		config.hosts = Some(vec![hosts.parse().map_err(|_| "Invalid JSON RPC hosts".to_owned())?]);
 	}
+	if let Some(users) = matches.values_of("rpcauth") {
+		config.users = users.into_iter().map(|user| user.parse()).collect::<Result<_, _>>()?;
+	}
+	config.cookie_file = matches.value_of("rpccookiefile").map(ToOwned::to_owned);
 
 	Ok(config)
 }
@@ -0,0 +1,301 @@
+//! Configuration file in `bitcoin.conf` format.
+//!
+//! Example:
+//!
+//! ```ini
+//! # options, used on every network
+//! segwit=1
+//! dbcache=1024
+//!
+//! # options, used only on the testnet
+//! [test]
+//! port=18444
+//! rpcport=18443
+//! ```
+//!
+//! Options have the same names as command line options. Common `bitcoind` option names
+//! (`datadir`, `rpcport`, ...) are also accepted. Flags are enabled with `1` and disabled with `0`.
+//! Options, passed through the command line, always take precedence over the file.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use clap;
+use util::default_config_path;
+
+/// Options, which are enabled by `1` and disabled by `0`.
+const FLAGS: &'static [&'static str] = &[
+	"testnet", "regtest", "signet", "segwit", "bitcoin-cash", "quiet", "no-jsonrpc", "daemon", "log-json", "cfindex",
+	"addressindex", "txindex", "wallet", "peerbloomfilters", "ws-allow-remote", "txreconciliation",
+];
+
+/// Options with value.
+const OPTIONS: &'static [&'static str] = &[
+	"signet-challenge", "network-file", "block-limits", "deployment", "connect", "seednode", "port",
+	"inboundconnections", "outboundconnections", "blockrelayconnections", "maxuploadtarget", "maxmempool",
+	"data-dir", "db-cache", "only-net", "proxy", "onion", "i2psam", "whitelist", "whitebind", "jsonrpc-port", "jsonrpc-interface", "jsonrpc-cors",
+	"jsonrpc-apis", "jsonrpc-hosts", "rpcauth", "rpccookiefile", "health-address", "rest-address", "ws-address", "electrum-address", "stratum-address", "stratum-payout", "stratum-difficulty", "blocknotify", "pidfile", "verification-level", "verification-edge",
+	"check-blocks", "check-level", "log", "log-file", "log-file-size", "log-files",
+	"trace-spans", "verification-threads", "prune", "zmqpubhashblock", "zmqpubhashtx", "zmqpubrawblock", "zmqpubrawtx",
+	"wallet-descriptor",
+];
+
+/// Options, which select the network. Can't be used in network sections.
+const NETWORK_FLAGS: &'static [&'static str] = &["testnet", "regtest", "signet"];
+
+/// Network sections names, as used by `bitcoind`.
+const SECTIONS: &'static [&'static str] = &["main", "test", "regtest", "signet"];
+
+/// Maps `bitcoind` option name to our command line option name.
+fn option_name(name: &str) -> &str {
+	match name {
+		"datadir" => "data-dir",
+		"dbcache" => "db-cache",
+		"onlynet" => "only-net",
+		"signetchallenge" => "signet-challenge",
+		"rpcport" => "jsonrpc-port",
+		"rpcbind" => "jsonrpc-interface",
+		"rpccorsdomain" => "jsonrpc-cors",
+		"pid" => "pidfile",
+		"checkblocks" => "check-blocks",
+		"checklevel" => "check-level",
+		"debuglogfile" => "log-file",
+		"par" => "verification-threads",
+		name => name,
+	}
+}
+
+/// Parsed configuration file.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigFile {
+	/// Options from the top of the file, used on every network.
+	global: HashMap<String, Vec<String>>,
+	/// Options from the network sections.
+	sections: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+impl ConfigFile {
+	/// Reads configuration file.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+		let mut contents = String::new();
+		File::open(path.as_ref())
+			.and_then(|mut file| file.read_to_string(&mut contents))
+			.map_err(|err| format!("Cannot read configuration file {}: {}", path.as_ref().display(), err))?;
+		contents.parse()
+	}
+
+	/// Options, used on the given network. Network section options override global ones.
+	fn network_options(&self, section: &str) -> HashMap<String, Vec<String>> {
+		let mut options = self.global.clone();
+		if let Some(section) = self.sections.get(section) {
+			options.extend(section.clone());
+		}
+		options
+	}
+}
+
+impl ::std::str::FromStr for ConfigFile {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut file = ConfigFile::default();
+		let mut section: Option<String> = None;
+		for (index, line) in s.lines().enumerate() {
+			let invalid = |reason: &str| format!("Invalid configuration file line {}: {}", index + 1, reason);
+			let line = match line.find('#') {
+				Some(comment) => &line[..comment],
+				None => line,
+			}.trim();
+
+			if line.is_empty() {
+				continue;
+			}
+
+			if line.starts_with('[') && line.ends_with(']') {
+				let name = line[1..line.len() - 1].trim();
+				if !SECTIONS.contains(&name) {
+					return Err(invalid("unknown section, expected one of main, test, regtest, signet"));
+				}
+				section = Some(name.to_owned());
+				continue;
+			}
+
+			let (key, value) = match line.find('=') {
+				Some(eq) => (line[..eq].trim(), line[eq + 1..].trim()),
+				None => return Err(invalid("expected key=value")),
+			};
+
+			// `bitcoind` also allows `section.key=value` lines
+			let (key_section, key) = match key.find('.') {
+				Some(dot) => (Some(&key[..dot]), &key[dot + 1..]),
+				None => (None, key),
+			};
+			let key_section = match key_section {
+				Some(name) if !SECTIONS.contains(&name) => return Err(invalid("unknown section, expected one of main, test, regtest, signet")),
+				Some(name) => Some(name.to_owned()),
+				None => section.clone(),
+			};
+
+			let key = option_name(key);
+			if FLAGS.contains(&key) {
+				if value != "0" && value != "1" {
+					return Err(invalid("flag value must be either 0, or 1"));
+				}
+			} else if !OPTIONS.contains(&key) {
+				return Err(invalid(&format!("unknown option '{}'", key)));
+			}
+
+			let options = match key_section {
+				Some(_) if NETWORK_FLAGS.contains(&key) => return Err(invalid("network can only be selected at the top of the file")),
+				Some(name) => file.sections.entry(name).or_insert_with(HashMap::new),
+				None => &mut file.global,
+			};
+			options.entry(key.to_owned()).or_insert_with(Vec::new).push(value.to_owned());
+		}
+
+		Ok(file)
+	}
+}
+
+/// Command line arguments, merged with the configuration file.
+pub struct Arguments<'a> {
+	matches: &'a clap::ArgMatches<'a>,
+	file: HashMap<String, Vec<String>>,
+}
+
+impl<'a> Arguments<'a> {
+	/// Reads configuration file from the `--conf` path, or from the data directory, if it exists there.
+	pub fn load(matches: &'a clap::ArgMatches<'a>) -> Result<Self, String> {
+		let file = match matches.value_of("conf") {
+			Some(path) => Some(ConfigFile::load(path)?),
+			None => {
+				let path = default_config_path(matches.value_of("data-dir"));
+				if path.exists() {
+					Some(ConfigFile::load(path)?)
+				} else {
+					None
+				}
+			},
+		};
+
+		Ok(Arguments::new(matches, file.unwrap_or_default()))
+	}
+
+	pub fn new(matches: &'a clap::ArgMatches<'a>, file: ConfigFile) -> Self {
+		let section = {
+			let is_network_selected = |name: &str| matches.is_present(name) ||
+				file.global.get(name).and_then(|values| values.last()).map_or(false, |value| value == "1");
+			match (is_network_selected("testnet"), is_network_selected("regtest"), is_network_selected("signet")) {
+				(true, _, _) => "test",
+				(_, true, _) => "regtest",
+				(_, _, true) => "signet",
+				_ => "main",
+			}
+		};
+
+		Arguments {
+			matches: matches,
+			file: file.network_options(section),
+		}
+	}
+
+	/// Is flag passed through the command line, or enabled in the configuration file?
+	pub fn is_present(&self, name: &str) -> bool {
+		self.matches.is_present(name) || self.file_value(name).map_or(false, |value| value != "0")
+	}
+
+	/// Value of the option. Command line value is preferred.
+	pub fn value_of(&self, name: &str) -> Option<&str> {
+		self.matches.value_of(name).or_else(|| self.file_value(name))
+	}
+
+	/// All values of the option. Configuration file values are only used, if option isn't passed through the command line.
+	pub fn values_of(&self, name: &str) -> Option<Vec<&str>> {
+		match self.matches.values_of(name) {
+			Some(values) => Some(values.collect()),
+			None => self.file.get(name).map(|values| values.iter().map(String::as_str).collect()),
+		}
+	}
+
+	fn file_value(&self, name: &str) -> Option<&str> {
+		self.file.get(name).and_then(|values| values.last()).map(String::as_str)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use clap;
+	use super::{ConfigFile, Arguments};
+
+	const CONFIG_FILE: &'static str = r#"
+		# comment
+		segwit=1
+		txreconciliation=1
+		dbcache=1024 # trailing comment
+		maxmempool=100
+		port=8000
+		deployment=a:1:0:100
+		deployment=b:2:0:100
+
+		[test]
+		port=18000
+		rpcport=18001
+
+		[regtest]
+		port=28000
+	"#;
+
+	#[test]
+	fn test_config_file_options() {
+		let file: ConfigFile = CONFIG_FILE.parse().unwrap();
+		let yaml = load_yaml!("cli.yml");
+		let matches = clap::App::from_yaml(yaml).get_matches_from(vec!["pbtc"]);
+		let args = Arguments::new(&matches, file);
+		assert!(args.is_present("segwit"));
+		assert!(!args.is_present("bitcoin-cash"));
+		assert!(args.is_present("txreconciliation"));
+		assert_eq!(args.value_of("db-cache"), Some("1024"));
+		assert_eq!(args.value_of("maxmempool"), Some("100"));
+		assert_eq!(args.value_of("port"), Some("8000"));
+		assert_eq!(args.value_of("jsonrpc-port"), None);
+		assert_eq!(args.values_of("deployment"), Some(vec!["a:1:0:100", "b:2:0:100"]));
+	}
+
+	#[test]
+	fn test_config_file_network_section() {
+		let file: ConfigFile = CONFIG_FILE.parse().unwrap();
+		let yaml = load_yaml!("cli.yml");
+		let matches = clap::App::from_yaml(yaml).get_matches_from(vec!["pbtc", "--testnet"]);
+		let args = Arguments::new(&matches, file);
+		assert_eq!(args.value_of("port"), Some("18000"));
+		assert_eq!(args.value_of("jsonrpc-port"), Some("18001"));
+		assert_eq!(args.value_of("db-cache"), Some("1024"));
+
+		let file: ConfigFile = "regtest=1\n[regtest]\nport=28000\n".parse().unwrap();
+		let matches = clap::App::from_yaml(yaml).get_matches_from(vec!["pbtc"]);
+		let args = Arguments::new(&matches, file);
+		assert!(args.is_present("regtest"));
+		assert_eq!(args.value_of("port"), Some("28000"));
+	}
+
+	#[test]
+	fn test_command_line_overrides_config_file() {
+		let file: ConfigFile = CONFIG_FILE.parse().unwrap();
+		let yaml = load_yaml!("cli.yml");
+		let matches = clap::App::from_yaml(yaml).get_matches_from(vec!["pbtc", "--port", "9000", "--deployment", "c:3:0:100"]);
+		let args = Arguments::new(&matches, file);
+		assert_eq!(args.value_of("port"), Some("9000"));
+		assert_eq!(args.values_of("deployment"), Some(vec!["c:3:0:100"]));
+	}
+
+	#[test]
+	fn test_invalid_config_file() {
+		assert!("port".parse::<ConfigFile>().is_err());
+		assert!("unknown=1".parse::<ConfigFile>().is_err());
+		assert!("segwit=yes".parse::<ConfigFile>().is_err());
+		assert!("[mainnet]\nport=1".parse::<ConfigFile>().is_err());
+		assert!("[test]\nregtest=1".parse::<ConfigFile>().is_err());
+		assert!("test.port=1".parse::<ConfigFile>().is_ok());
+	}
+}
@@ -0,0 +1,92 @@
+//! Running the node in the background and stopping it gracefully.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use libc;
+
+/// Set from the signal handler, when SIGINT or SIGTERM is received.
+static SHUTDOWN_SIGNAL: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_shutdown_signal(signal: libc::c_int) {
+	SHUTDOWN_SIGNAL.store(true, Ordering::SeqCst);
+	// second signal terminates the process immediately
+	unsafe {
+		libc::signal(signal, libc::SIG_DFL);
+	}
+}
+
+/// Makes SIGINT and SIGTERM request graceful shutdown instead of terminating the process.
+pub fn handle_shutdown_signals() {
+	unsafe {
+		libc::signal(libc::SIGINT, on_shutdown_signal as libc::sighandler_t);
+		libc::signal(libc::SIGTERM, on_shutdown_signal as libc::sighandler_t);
+	}
+}
+
+/// Has SIGINT or SIGTERM been received?
+pub fn shutdown_signal_received() -> bool {
+	SHUTDOWN_SIGNAL.load(Ordering::SeqCst)
+}
+
+/// Detaches the process from the terminal. Must be called before any other thread is spawned.
+/// Standard output and error are kept, so that logs could be redirected by the caller.
+#[cfg(unix)]
+pub fn daemonize() -> Result<(), String> {
+	unsafe {
+		match libc::fork() {
+			-1 => return Err(format!("Failed to fork the process: {}", io::Error::last_os_error())),
+			0 => (),
+			_ => libc::_exit(0),
+		}
+
+		if libc::setsid() == -1 {
+			return Err(format!("Failed to create new session: {}", io::Error::last_os_error()));
+		}
+
+		let dev_null = libc::open(b"/dev/null\0".as_ptr() as *const libc::c_char, libc::O_RDWR);
+		if dev_null == -1 {
+			return Err(format!("Failed to open /dev/null: {}", io::Error::last_os_error()));
+		}
+
+		libc::dup2(dev_null, libc::STDIN_FILENO);
+		if dev_null != libc::STDIN_FILENO {
+			libc::close(dev_null);
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn daemonize() -> Result<(), String> {
+	Err("Daemon mode is not supported on this platform".into())
+}
+
+/// File with the process id, removed when the node is stopped.
+pub struct PidFile {
+	path: PathBuf,
+}
+
+impl PidFile {
+	pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+		let path = path.as_ref().to_path_buf();
+		let pid = unsafe { libc::getpid() };
+		fs::File::create(&path)
+			.and_then(|mut file| writeln!(file, "{}", pid))
+			.map_err(|err| format!("Failed to write pidfile {}: {}", path.display(), err))?;
+
+		Ok(PidFile {
+			path: path,
+		})
+	}
+}
+
+impl Drop for PidFile {
+	fn drop(&mut self) {
+		if let Err(err) = fs::remove_file(&self.path) {
+			warn!(target: "pbtc", "Failed to remove pidfile {}: {}", self.path.display(), err);
+		}
+	}
+}
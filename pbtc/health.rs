@@ -0,0 +1,74 @@
+//! HTTP health check endpoint for load balancers and orchestration systems.
+//!
+//! `GET /health` responds with the `healthcheck` RPC result and `200 OK` status when the node
+//! is healthy, or `503 Service Unavailable` status otherwise.
+
+use serde_json;
+use ethcore_rpc::HttpHandler;
+use ethcore_rpc::http_server::{Response, STATUS_OK, STATUS_NOT_FOUND, STATUS_METHOD_NOT_ALLOWED, STATUS_SERVICE_UNAVAILABLE};
+use ethcore_rpc::v1::{HealthClientCore, HealthClientCoreApi};
+
+/// Handles health check requests of the `HttpServer`.
+pub struct HealthHandler {
+	core: HealthClientCore,
+}
+
+impl HealthHandler {
+	pub fn new(core: HealthClientCore) -> Self {
+		HealthHandler {
+			core: core,
+		}
+	}
+}
+
+impl HttpHandler for HealthHandler {
+	fn handle(&self, method: &str, path: &str) -> Response {
+		response(method, path, || {
+			let health = self.core.health();
+			let body = serde_json::to_string(&health).expect("health response is always serializable; qed");
+			(health.healthy, body)
+		})
+	}
+}
+
+/// Returns response to the request with given method and path.
+fn response<F>(method: &str, path: &str, health: F) -> Response where F: FnOnce() -> (bool, String) {
+	match (method, path) {
+		("GET", "/health") | ("GET", "/") => {
+			let (healthy, body) = health();
+			Response {
+				status: if healthy { STATUS_OK } else { STATUS_SERVICE_UNAVAILABLE },
+				content_type: "application/json",
+				body: body.into_bytes(),
+			}
+		},
+		("GET", _) => error(STATUS_NOT_FOUND, "not found"),
+		_ => error(STATUS_METHOD_NOT_ALLOWED, "method not allowed"),
+	}
+}
+
+fn error(status: &'static str, message: &str) -> Response {
+	Response {
+		status: status,
+		content_type: "application/json",
+		body: format!(r#"{{"error":"{}"}}"#, message).into_bytes(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::response;
+
+	#[test]
+	fn test_health_response_status() {
+		let healthy = || (true, "{}".to_owned());
+		let unhealthy = || (false, "{}".to_owned());
+		assert_eq!(response("GET", "/health", healthy).status, "200 OK");
+		assert_eq!(response("GET", "/health", healthy).body, b"{}".to_vec());
+		assert_eq!(response("GET", "/", healthy).status, "200 OK");
+		assert_eq!(response("GET", "/health", unhealthy).status, "503 Service Unavailable");
+		assert_eq!(response("GET", "/status", healthy).status, "404 Not Found");
+		assert_eq!(response("GET", "/status", healthy).body, br#"{"error":"not found"}"#.to_vec());
+		assert_eq!(response("POST", "/health", healthy).status, "405 Method Not Allowed");
+	}
+}
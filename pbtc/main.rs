@@ -4,9 +4,13 @@
 extern crate clap;
 #[macro_use]
 extern crate log;
-extern crate env_logger;
 extern crate app_dirs;
 extern crate libc;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate zmq;
 
 extern crate db;
 extern crate chain;
@@ -20,15 +24,26 @@ extern crate sync;
 extern crate import;
 extern crate rpc as ethcore_rpc;
 extern crate primitives;
+extern crate serialization as ser;
 extern crate verification;
+extern crate wallet;
+extern crate miner;
 
 mod commands;
 mod config;
+mod config_file;
+mod daemon;
+mod health;
+mod network_file;
 mod seednodes;
 mod util;
 mod rpc;
 mod rpc_apis;
+mod stratum;
+mod wallet_notifier;
+mod zmq_notifier;
 
+use std::io::{self, Write};
 use app_dirs::AppInfo;
 
 pub const APP_INFO: AppInfo = AppInfo { name: "pbtc", author: "Parity" };
@@ -37,6 +52,7 @@ pub const PROTOCOL_MINIMUM: u32 = 70_001;
 pub const USER_AGENT: &'static str = "pbtc";
 pub const REGTEST_USER_AGENT: &'static str = "/Satoshi:0.12.1/";
 pub const LOG_INFO: &'static str = "sync=info";
+pub const LOG_QUIET: &'static str = "error";
 
 fn main() {
 	// Always print backtrace on panic.
@@ -50,21 +66,47 @@ fn main() {
 fn run() -> Result<(), String> {
 	let yaml = load_yaml!("cli.yml");
 	let matches = clap::App::from_yaml(yaml).get_matches();
-	let cfg = try!(config::parse(&matches));
-
-	if !cfg.quiet {
-		if cfg!(windows) {
-			logs::init(LOG_INFO, logs::DateLogFormatter);
-		} else {
-			logs::init(LOG_INFO, logs::DateAndColorLogFormatter);
-		}
-	} else {
-		env_logger::init().expect("Logger can be initialized only once");
+	let args = config_file::Arguments::load(&matches)?;
+	// fork before database (and its threads) is opened
+	if matches.subcommand_name().is_none() && args.is_present("daemon") {
+		daemon::daemonize()?;
 	}
 
+	let cfg = try!(config::parse(&args));
+
+	let logger = try!(init_logs(&cfg));
+	let verification_threads = verification::init_script_threads(cfg.verification_threads)?;
+	info!(target: "pbtc", "Verifying scripts using {} threads", verification_threads);
+
 	match matches.subcommand() {
 		("import", Some(import_matches)) => commands::import(cfg, import_matches),
+		("export", Some(export_matches)) => commands::export(cfg, export_matches),
+		("check", Some(check_matches)) => commands::check(cfg, check_matches),
+		("dump-utxos", Some(dump_matches)) => commands::dump_utxos(cfg, dump_matches),
+		("load-utxos", Some(load_matches)) => commands::load_utxos(cfg, load_matches),
 		("rollback", Some(rollback_matches)) => commands::rollback(cfg, rollback_matches),
-		_ => commands::start(cfg),
+		_ => commands::start(cfg, logger),
+	}
+}
+
+fn init_logs(cfg: &config::Config) -> Result<logs::LogHandle, String> {
+	let default_filters = if cfg.quiet { LOG_QUIET } else { LOG_INFO };
+	let filters = match cfg.log.filters {
+		Some(ref filters) => format!("{},{}", default_filters, filters),
+		None => default_filters.to_owned(),
+	};
+
+	let output: Box<Write + Send> = match cfg.log.file {
+		Some(ref path) => Box::new(logs::RotatingFile::open(path, cfg.log.file_size, cfg.log.files)
+			.map_err(|err| format!("Cannot open log file {}: {}", path, err))?),
+		None => Box::new(io::stderr()),
+	};
+
+	if cfg.log.json {
+		logs::init_with_output(&filters, logs::JsonLogFormatter, output)
+	} else if cfg!(windows) || cfg.log.file.is_some() {
+		logs::init_with_output(&filters, logs::DateLogFormatter, output)
+	} else {
+		logs::init_with_output(&filters, logs::DateAndColorLogFormatter, output)
 	}
 }
@@ -0,0 +1,203 @@
+//! Custom network, defined in JSON file.
+//!
+//! Example:
+//!
+//! ```json
+//! {
+//!     "magic": "f9beb4d9",
+//!     "genesis": "0100000000000000...",
+//!     "port": 28333,
+//!     "rpc_port": 28332,
+//!     "address_prefixes": { "p2pkh": 111, "p2sh": 196, "private": 239 },
+//!     "pow_limit": 545259519,
+//!     "pow_no_retargeting": true,
+//!     "bip16_time": 0,
+//!     "bip34_height": 1,
+//!     "bip65_height": 1,
+//!     "bip66_height": 1,
+//!     "csv_height": 0,
+//!     "segwit_height": 0,
+//!     "taproot_height": 0,
+//!     "seeds": ["127.0.0.1:28333"]
+//! }
+//! ```
+
+use std::fs::File;
+use std::path::Path;
+use serde_json;
+use chain::Block;
+use keys::Network as AddressNetwork;
+use network::{Magic, ConsensusParams, ConsensusFork, Deployment};
+use primitives::bytes::Bytes;
+use ser::deserialize;
+
+/// Address and private key prefixes.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct AddressPrefixes {
+	pub p2pkh: u8,
+	pub p2sh: u8,
+	pub private: u8,
+}
+
+/// Network definition, as it is stored in the file.
+/// Softforks without activation height are never activated.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct NetworkFile {
+	/// Message start bytes, hex-encoded in network order.
+	pub magic: String,
+	/// Serialized genesis block, hex-encoded.
+	pub genesis: String,
+	pub port: u16,
+	pub rpc_port: u16,
+	pub address_prefixes: AddressPrefixes,
+	/// Easiest allowed proof of work, in compact form.
+	pub pow_limit: u32,
+	#[serde(default)]
+	pub pow_allow_min_difficulty_blocks: bool,
+	#[serde(default)]
+	pub pow_no_retargeting: bool,
+	#[serde(default)]
+	pub pow_retarget_full_interval: bool,
+	#[serde(default)]
+	pub pow_retarget_first_block_bits: bool,
+	pub bip16_time: u32,
+	pub bip34_height: u32,
+	pub bip65_height: u32,
+	pub bip66_height: u32,
+	#[serde(default)]
+	pub csv_height: Option<u32>,
+	#[serde(default)]
+	pub segwit_height: Option<u32>,
+	#[serde(default)]
+	pub taproot_height: Option<u32>,
+	#[serde(default)]
+	pub seeds: Vec<String>,
+}
+
+/// Custom network, ready to be used by the node.
+pub struct CustomNetwork {
+	pub magic: Magic,
+	pub genesis_block: Block,
+	pub port: u16,
+	pub rpc_port: u16,
+	pub address_network: AddressNetwork,
+	pub seednodes: Vec<String>,
+	definition: NetworkFile,
+}
+
+impl CustomNetwork {
+	/// Reads network definition from the file.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+		let file = File::open(path).map_err(|e| format!("Cannot open network file: {}", e))?;
+		let definition: NetworkFile = serde_json::from_reader(file).map_err(|e| format!("Invalid network file: {}", e))?;
+		CustomNetwork::new(definition)
+	}
+
+	/// Validates network definition.
+	pub fn new(definition: NetworkFile) -> Result<Self, String> {
+		let magic: Bytes = definition.magic.parse().map_err(|_| "Invalid network magic".to_owned())?;
+		if magic.len() != 4 {
+			return Err("Network magic must be 4 bytes long".into());
+		}
+		let magic = (magic[0] as u32) | (magic[1] as u32) << 8 | (magic[2] as u32) << 16 | (magic[3] as u32) << 24;
+
+		let genesis: Bytes = definition.genesis.parse().map_err(|_| "Invalid genesis block".to_owned())?;
+		let genesis_block: Block = deserialize(&*genesis).map_err(|_| "Invalid genesis block".to_owned())?;
+
+		Ok(CustomNetwork {
+			magic: Magic::Other(magic),
+			genesis_block: genesis_block,
+			port: definition.port,
+			rpc_port: definition.rpc_port,
+			address_network: AddressNetwork::Other {
+				p2pkh: definition.address_prefixes.p2pkh,
+				p2sh: definition.address_prefixes.p2sh,
+				private: definition.address_prefixes.private,
+			},
+			seednodes: definition.seeds.clone(),
+			definition: definition,
+		})
+	}
+
+	/// Consensus parameters of the network.
+	pub fn consensus_params(&self, fork: ConsensusFork) -> ConsensusParams {
+		let mut consensus = ConsensusParams::new(self.magic, fork);
+		consensus.bip16_time = self.definition.bip16_time;
+		consensus.bip34_height = self.definition.bip34_height;
+		consensus.bip65_height = self.definition.bip65_height;
+		consensus.bip66_height = self.definition.bip66_height;
+		consensus.pow_limit = self.definition.pow_limit.into();
+		consensus.pow_allow_min_difficulty_blocks = self.definition.pow_allow_min_difficulty_blocks;
+		consensus.pow_no_retargeting = self.definition.pow_no_retargeting;
+		consensus.pow_retarget_full_interval = self.definition.pow_retarget_full_interval;
+		consensus.pow_retarget_first_block_bits = self.definition.pow_retarget_first_block_bits;
+		consensus.csv_deployment = activated_deployment(consensus.csv_deployment, self.definition.csv_height);
+		consensus.segwit_deployment = activated_deployment(consensus.segwit_deployment, self.definition.segwit_height);
+		consensus.taproot_deployment = activated_deployment(consensus.taproot_deployment, self.definition.taproot_height);
+		consensus
+	}
+}
+
+fn activated_deployment(deployment: Option<Deployment>, height: Option<u32>) -> Option<Deployment> {
+	match (deployment, height) {
+		(Some(mut deployment), Some(height)) => {
+			deployment.activation = Some(height);
+			Some(deployment)
+		},
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use keys::Network as AddressNetwork;
+	use network::{Magic, ConsensusFork};
+	use super::{CustomNetwork, NetworkFile};
+
+	const NETWORK_FILE: &'static str = r#"{
+		"magic": "fabfb5da",
+		"genesis": "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4adae5494dffff7f20020000000101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000",
+		"port": 28333,
+		"rpc_port": 28332,
+		"address_prefixes": { "p2pkh": 50, "p2sh": 55, "private": 178 },
+		"pow_limit": 545259519,
+		"pow_no_retargeting": true,
+		"bip16_time": 0,
+		"bip34_height": 1,
+		"bip65_height": 2,
+		"bip66_height": 3,
+		"segwit_height": 10,
+		"seeds": ["127.0.0.1:28333"]
+	}"#;
+
+	#[test]
+	fn test_custom_network_load() {
+		let definition: NetworkFile = serde_json::from_str(NETWORK_FILE).unwrap();
+		let network = CustomNetwork::new(definition).unwrap();
+		assert_eq!(network.magic, Magic::Other(0xDAB5BFFA));
+		assert_eq!(network.genesis_block, Magic::Regtest.genesis_block());
+		assert_eq!(network.port, 28333);
+		assert_eq!(network.rpc_port, 28332);
+		assert_eq!(network.address_network, AddressNetwork::Other { p2pkh: 50, p2sh: 55, private: 178 });
+		assert_eq!(network.seednodes, vec!["127.0.0.1:28333".to_owned()]);
+
+		let consensus = network.consensus_params(ConsensusFork::NoFork);
+		assert_eq!(consensus.network, Magic::Other(0xDAB5BFFA));
+		assert_eq!(consensus.pow_limit, 0x207fffff.into());
+		assert!(consensus.pow_no_retargeting);
+		assert!(!consensus.pow_allow_min_difficulty_blocks);
+		assert!(!consensus.pow_retarget_full_interval);
+		assert!(!consensus.pow_retarget_first_block_bits);
+		assert_eq!(consensus.bip65_height, 2);
+		assert!(consensus.csv_deployment.is_none());
+		assert_eq!(consensus.segwit_deployment.as_ref().unwrap().activation, Some(10));
+		assert!(consensus.taproot_deployment.is_none());
+	}
+
+	#[test]
+	fn test_custom_network_invalid_magic() {
+		let definition: NetworkFile = serde_json::from_str(&NETWORK_FILE.replace("fabfb5da", "fabfb5")).unwrap();
+		assert!(CustomNetwork::new(definition).is_err());
+	}
+}
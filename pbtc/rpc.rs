@@ -1,19 +1,30 @@
+use std::fs;
+use std::io::{self, Write};
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use rpc_apis::{self, ApiSet};
-use ethcore_rpc::{Server, Error, start_http, MetaIoHandler, Compatibility, Remote};
-use network::Magic;
-use std::io;
+use ethcore_rpc::{Server, Error, start_http, MetaIoHandler, Compatibility, Remote, Metadata, AuthMiddleware, MethodTiers,
+	Authenticator, RpcAuth, COOKIE_USER, generate_cookie};
+use keys;
+use logs;
 use sync;
 use db;
 use p2p;
+use network::ConsensusParams;
+use wallet;
 
 pub struct Dependencies {
-	pub network: Magic,
+	pub address_network: keys::Network,
 	pub local_sync_node: sync::LocalNodeRef,
 	pub storage: db::SharedStore,
+	pub consensus: ConsensusParams,
 	pub p2p_context: Arc<p2p::Context>,
 	pub remote: Remote,
+	pub shutdown: Arc<AtomicBool>,
+	pub logger: logs::LogHandle,
+	pub wallet: Option<Arc<wallet::Wallet>>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -24,6 +35,24 @@ pub struct HttpConfiguration {
 	pub apis: ApiSet,
 	pub cors: Option<Vec<String>>,
 	pub hosts: Option<Vec<String>>,
+	/// Users, defined with `--rpcauth`.
+	pub users: Vec<RpcAuth>,
+	/// Path of the cookie file, if it differs from the default one.
+	pub cookie_file: Option<String>,
+}
+
+/// JSON-RPC server. Cookie file is removed when the server is stopped.
+pub struct RpcServer {
+	_server: Server,
+	cookie_path: PathBuf,
+}
+
+impl Drop for RpcServer {
+	fn drop(&mut self) {
+		if let Err(err) = fs::remove_file(&self.cookie_path) {
+			warn!(target: "pbtc", "Failed to remove RPC cookie file {}: {}", self.cookie_path.display(), err);
+		}
+	}
 }
 
 impl HttpConfiguration {
@@ -35,18 +64,55 @@ impl HttpConfiguration {
 			apis: ApiSet::default(),
 			cors: None,
 			hosts: Some(Vec::new()),
+			users: Vec::new(),
+			cookie_file: None,
 		}
 	}
 }
 
-pub fn new_http(conf: HttpConfiguration, deps: Dependencies) -> Result<Option<Server>, String> {
+/// Starts the server, writing the cookie of the local clients to `cookie_path`.
+pub fn new_http(conf: HttpConfiguration, cookie_path: PathBuf, deps: Dependencies) -> Result<Option<RpcServer>, String> {
 	if !conf.enabled {
 		return Ok(None);
 	}
 
 	let url = format!("{}:{}", conf.interface, conf.port);
 	let addr = try!(url.parse().map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url)));
-	Ok(Some(try!(setup_http_rpc_server(&addr, conf.cors, conf.hosts, conf.apis, deps))))
+	let cookie = generate_cookie()?;
+	write_cookie_file(&cookie_path, &cookie)
+		.map_err(|err| format!("Failed to write RPC cookie file {}: {}", cookie_path.display(), err))?;
+	let authenticator = Arc::new(Authenticator::new(Some(cookie), conf.users));
+	let server = setup_http_rpc_server(&addr, conf.cors, conf.hosts, conf.apis, authenticator, deps);
+	match server {
+		Ok(server) => Ok(Some(RpcServer {
+			_server: server,
+			cookie_path: cookie_path,
+		})),
+		Err(err) => {
+			let _ = fs::remove_file(&cookie_path);
+			Err(err)
+		},
+	}
+}
+
+fn write_cookie_file(path: &Path, cookie: &str) -> Result<(), io::Error> {
+	let mut file = cookie_file_options().write(true).create(true).truncate(true).open(path)?;
+	write!(file, "{}:{}", COOKIE_USER, cookie)
+}
+
+/// Cookie file is only readable by the user, running the node.
+#[cfg(unix)]
+fn cookie_file_options() -> fs::OpenOptions {
+	use std::os::unix::fs::OpenOptionsExt;
+
+	let mut options = fs::OpenOptions::new();
+	options.mode(0o600);
+	options
+}
+
+#[cfg(not(unix))]
+fn cookie_file_options() -> fs::OpenOptions {
+	fs::OpenOptions::new()
 }
 
 pub fn setup_http_rpc_server(
@@ -54,10 +120,11 @@ pub fn setup_http_rpc_server(
 	cors_domains: Option<Vec<String>>,
 	allowed_hosts: Option<Vec<String>>,
 	apis: ApiSet,
+	authenticator: Arc<Authenticator>,
 	deps: Dependencies,
 ) -> Result<Server, String> {
 	let server = setup_rpc_server(apis, deps);
-	let start_result = start_http(url, cors_domains, allowed_hosts, server);
+	let start_result = start_http(url, cors_domains, allowed_hosts, server, authenticator);
 	match start_result {
 		Err(Error::Io(ref err)) if err.kind() == io::ErrorKind::AddrInUse => {
 			Err(format!("RPC address {} is already in use, make sure that another instance of a Bitcoin node is not running or change the address using the --jsonrpc-port and --jsonrpc-interface options.", url))
@@ -67,6 +134,7 @@ pub fn setup_http_rpc_server(
 	}
 }
 
-fn setup_rpc_server(apis: ApiSet, deps: Dependencies) -> MetaIoHandler<()> {
-	rpc_apis::setup_rpc(MetaIoHandler::with_compatibility(Compatibility::Both), apis, deps)
+fn setup_rpc_server(apis: ApiSet, deps: Dependencies) -> MetaIoHandler<Metadata, AuthMiddleware> {
+	let tiers = MethodTiers::default();
+	rpc_apis::setup_rpc(MetaIoHandler::new(Compatibility::Both, AuthMiddleware::new(tiers.clone())), &tiers, apis, deps)
 }
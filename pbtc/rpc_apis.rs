@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use std::collections::HashSet;
 use rpc::Dependencies;
-use ethcore_rpc::MetaIoHandler;
+use ethcore_rpc::{MetaIoHandler, Metadata, AuthMiddleware, MethodTiers};
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Api {
@@ -13,6 +13,14 @@ pub enum Api {
 	BlockChain,
 	/// Network
 	Network,
+	/// Node control
+	Control,
+	/// Node health
+	Health,
+	/// Address index
+	Address,
+	/// Wallet
+	Wallet,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -22,7 +30,7 @@ pub enum ApiSet {
 
 impl Default for ApiSet {
 	fn default() -> Self {
-		ApiSet::List(vec![Api::Raw, Api::Miner, Api::BlockChain, Api::Network].into_iter().collect())
+		ApiSet::List(vec![Api::Raw, Api::Miner, Api::BlockChain, Api::Network, Api::Health, Api::Address, Api::Wallet].into_iter().collect())
 	}
 }
 
@@ -35,6 +43,10 @@ impl FromStr for Api {
 			"miner" => Ok(Api::Miner),
 			"blockchain" => Ok(Api::BlockChain),
 			"network" => Ok(Api::Network),
+			"control" => Ok(Api::Control),
+			"health" => Ok(Api::Health),
+			"address" => Ok(Api::Address),
+			"wallet" => Ok(Api::Wallet),
 			api => Err(format!("Unknown api: {}", api)),
 		}
 	}
@@ -48,15 +60,23 @@ impl ApiSet {
 	}
 }
 
-pub fn setup_rpc(mut handler: MetaIoHandler<()>, apis: ApiSet, deps: Dependencies) -> MetaIoHandler<()> {
+pub fn setup_rpc(mut handler: MetaIoHandler<Metadata, AuthMiddleware>, tiers: &MethodTiers, apis: ApiSet, deps: Dependencies) -> MetaIoHandler<Metadata, AuthMiddleware> {
 	use ethcore_rpc::v1::*;
 
 	for api in apis.list_apis() {
 		match api {
-			Api::Raw => handler.extend_with(RawClient::new(RawClientCore::new(deps.local_sync_node.clone())).to_delegate()),
-			Api::Miner => handler.extend_with(MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone())).to_delegate()),
-			Api::BlockChain => handler.extend_with(BlockChainClient::new(BlockChainClientCore::new(deps.network, deps.storage.clone())).to_delegate()),
-			Api::Network => handler.extend_with(NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone())).to_delegate()),
+			Api::Raw => tiers.register(&mut handler, RawClient::new(RawClientCore::new(deps.address_network, deps.local_sync_node.clone(), deps.storage.clone())).to_delegate(), RAW_METHOD_TIERS),
+			Api::Miner => tiers.register(&mut handler, MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone())).to_delegate(), MINER_METHOD_TIERS),
+			Api::BlockChain => tiers.register(&mut handler, BlockChainClient::new(BlockChainClientCore::new(deps.address_network, deps.storage.clone(), deps.consensus.clone())
+				.with_local_sync_node(deps.local_sync_node.clone())).to_delegate(), BLOCKCHAIN_METHOD_TIERS),
+			Api::Network => tiers.register(&mut handler, NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone(), deps.local_sync_node.clone())).to_delegate(), NETWORK_METHOD_TIERS),
+			Api::Control => tiers.register(&mut handler, ControlClient::new(ControlClientCore::new(deps.shutdown.clone(), deps.logger.clone())).to_delegate(), CONTROL_METHOD_TIERS),
+			Api::Health => tiers.register(&mut handler, HealthClient::new(HealthClientCore::new(deps.local_sync_node.clone(), deps.storage.clone(), deps.p2p_context.clone())).to_delegate(), HEALTH_METHOD_TIERS),
+			Api::Address => tiers.register(&mut handler, AddressClient::new(AddressClientCore::new(deps.storage.clone())).to_delegate(), ADDRESS_METHOD_TIERS),
+			// wallet methods are only available when the wallet is enabled
+			Api::Wallet => if let Some(ref wallet) = deps.wallet {
+				tiers.register(&mut handler, WalletClient::new(WalletClientCore::new(wallet.clone())).to_delegate(), WALLET_METHOD_TIERS)
+			},
 		}
 	}
 
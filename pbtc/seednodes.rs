@@ -28,11 +28,9 @@ pub fn testnet_seednodes() -> Vec<&'static str> {
 	]
 }
 
-pub fn segwit2x_seednodes() -> Vec<&'static str> {
+pub fn signet_seednodes() -> Vec<&'static str> {
 	vec![
-		"seed.mainnet.b-pay.net:8333",
-		"seed.ob1.io:8333",
-		"seed.blockchain.info:8333",
-		"bitcoin.bloqseeds.net:8333",
+		"seed.signet.bitcoin.sprovoost.nl:38333",
+		"178.128.221.177:38333",
 	]
 }
@@ -0,0 +1,51 @@
+//! Stratum mining server integration.
+
+use chain::IndexedBlock;
+use miner::{BlockTemplate, StratumCoreApi, StratumNotifier};
+use primitives::hash::H256;
+use sync::{LocalNodeRef, SyncListener};
+
+/// Serves block templates and accepts mined blocks of the local node.
+pub struct StratumCore {
+	local_sync_node: LocalNodeRef,
+}
+
+impl StratumCore {
+	pub fn new(local_sync_node: LocalNodeRef) -> Self {
+		StratumCore {
+			local_sync_node: local_sync_node,
+		}
+	}
+}
+
+impl StratumCoreApi for StratumCore {
+	fn block_template(&self) -> BlockTemplate {
+		self.local_sync_node.get_block_template()
+	}
+
+	fn submit_block(&self, block: IndexedBlock) -> Option<String> {
+		self.local_sync_node.submit_block(block)
+	}
+}
+
+/// Sends new jobs to miners, when the best block changes.
+pub struct StratumListener {
+	notifier: StratumNotifier,
+}
+
+impl StratumListener {
+	pub fn new(notifier: StratumNotifier) -> Self {
+		StratumListener {
+			notifier: notifier,
+		}
+	}
+}
+
+impl SyncListener for StratumListener {
+	fn synchronization_state_switched(&self, _is_synchronizing: bool) {
+	}
+
+	fn best_storage_block_inserted(&self, _block_hash: &H256) {
+		self.notifier.notify_new_block();
+	}
+}
@@ -1,31 +1,152 @@
 use std::sync::Arc;
+use std::thread;
 use std::path::PathBuf;
-use std::fs::create_dir_all;
-use app_dirs::{app_dir, AppDataType};
+use std::fs::{self, create_dir_all};
+use app_dirs::{app_dir, app_root, AppDataType};
 use {db, APP_INFO};
+use db::BlockPruning;
 use config::Config;
 use chain::IndexedBlock;
+use network::Magic;
+use wallet::WALLET_RECORDS;
 
-pub fn open_db(data_dir: &Option<String>, db_cache: usize) -> db::SharedStore {
-	let db_path = match *data_dir {
-		Some(ref data_dir) => custom_path(&data_dir, "db"),
-		None => app_dir(AppDataType::UserData, &APP_INFO, "db").expect("Failed to get app dir"),
-	};
-	Arc::new(db::BlockChainDatabase::open_at_path(db_path, db_cache).expect("Failed to open database"))
+/// Cache size (in MB) of the database, which blocks are replayed by the reindex.
+const REINDEX_SOURCE_DB_CACHE: usize = 64;
+
+pub fn open_db(data_dir: &Option<String>, magic: Magic, db_cache: usize, cfindex: bool, address_index: bool, transaction_index: bool, prune: db::PruneMode) -> Result<db::SharedStore, String> {
+	let db = db::BlockChainDatabase::open_at_path(db_path(data_dir, magic), db_cache)
+		.map_err(|err| format!("Failed to open database: {:?}", err))?
+		.with_cfindex(cfindex)
+		.with_address_index(address_index)
+		.with_transaction_index(transaction_index)
+		.with_prune(prune);
+	// blocks, canonized while indexes were disabled, are indexed before any new block
+	db.build_header_tree().map_err(|err| format!("Failed to build header tree: {:?}", err))?;
+	db.build_chain_work().map_err(|err| format!("Failed to store chain work: {:?}", err))?;
+	match db.build_cfindex() {
+		Ok(_) => (),
+		Err(db::Error::PrunedBlocks) => return Err("Compact block filters index needs all blocks, which are not indexed yet, but some of them are pruned. Start the node without --cfindex, or sync the new database without --prune".into()),
+		Err(err) => return Err(format!("Failed to build compact block filters index: {:?}", err)),
+	}
+	db.build_address_index().map_err(|err| format!("Failed to build address index: {:?}", err))?;
+	db.init_pruning().map_err(|err| format!("Failed to prune blocks: {:?}", err))?;
+
+	let db = Arc::new(db);
+	if transaction_index {
+		// transaction index may take hours to build => it is built while the node is running
+		let index_db = db.clone();
+		thread::Builder::new()
+			.name("Transaction index".to_owned())
+			.spawn(move || match index_db.build_transaction_index() {
+				Ok(0) => (),
+				Ok(blocks) => info!(target: "pbtc", "Transaction index is built. {} blocks indexed", blocks),
+				Err(err) => error!(target: "pbtc", "Failed to build transaction index: {:?}", err),
+			})
+			.expect("Failed to start transaction index thread");
+	}
+	Ok(db)
+}
+
+/// Directory of the database, which blocks are replayed by the reindex.
+pub fn reindex_db_path(data_dir: &Option<String>, magic: Magic) -> PathBuf {
+	db_path(data_dir, magic).with_file_name("db-reindex")
 }
 
-pub fn node_table_path(cfg: &Config) -> PathBuf {
-	let mut node_table = match cfg.data_dir {
-		Some(ref data_dir) => custom_path(&data_dir, "p2p"),
-		None => app_dir(AppDataType::UserData, &APP_INFO, "p2p").expect("Failed to get app dir"),
+/// Opens the database, which blocks are replayed by the reindex.
+pub fn open_reindex_db(data_dir: &Option<String>, magic: Magic) -> Result<db::SharedStore, String> {
+	let db = db::BlockChainDatabase::open_at_path(reindex_db_path(data_dir, magic), REINDEX_SOURCE_DB_CACHE)
+		.map_err(|err| format!("Failed to open reindexed database: {:?}", err))?;
+	Ok(Arc::new(db))
+}
+
+/// Moves the database aside, so that the new database is built from its blocks.
+/// Does nothing if reindex of the previously moved database isn't finished yet.
+pub fn begin_reindex(data_dir: &Option<String>, magic: Magic) -> Result<(), String> {
+	let reindex_path = reindex_db_path(data_dir, magic);
+	if reindex_path.exists() {
+		info!(target: "pbtc", "Resuming interrupted reindex");
+		return Ok(());
+	}
+
+	let path = db_path(data_dir, magic);
+	{
+		let db = db::BlockChainDatabase::open_at_path(&path, REINDEX_SOURCE_DB_CACHE)
+			.map_err(|err| format!("Failed to open database: {:?}", err))?;
+		if db.pruned_blocks() != 0 {
+			return Err("Database is pruned. Pruned blocks can't be reindexed".into());
+		}
+	}
+	fs::rename(&path, &reindex_path).map_err(|err| format!("Cannot move database to {}: {}", reindex_path.display(), err))
+}
+
+/// Copies data, which can't be rebuilt from blocks, from the reindexed database to the new database.
+pub fn init_reindex_db(db: &db::SharedStore, source: &db::SharedStore) -> Result<(), String> {
+	// data is copied before the first block is replayed
+	if db.block_hash(0).is_some() {
+		return Ok(());
+	}
+
+	if let Some(consensus_fork) = source.consensus_fork()? {
+		db.set_consensus_fork(&consensus_fork)?;
+	}
+
+	let wallet_records: Vec<_> = WALLET_RECORDS.iter()
+		.filter_map(|name| source.wallet_record(*name).map(|record| (*name, record)))
+		.collect();
+	if !wallet_records.is_empty() {
+		db.set_wallet_records(wallet_records)?;
+	}
+
+	Ok(())
+}
+
+/// Configuration file is looked up in the data directory.
+pub fn default_config_path(data_dir: Option<&str>) -> PathBuf {
+	let mut path = match data_dir {
+		Some(data_dir) => PathBuf::from(data_dir),
+		None => app_root(AppDataType::UserData, &APP_INFO).expect("Failed to get app dir"),
 	};
-	node_table.push("nodes.csv");
-	node_table
+	path.push("pbtc.conf");
+	path
+}
+
+pub fn address_manager_path(cfg: &Config) -> PathBuf {
+	network_file_path(cfg, "p2p", "peers.dat")
+}
+
+pub fn ban_list_path(cfg: &Config) -> PathBuf {
+	network_file_path(cfg, "p2p", "banlist.csv")
+}
+
+pub fn i2p_private_key_path(cfg: &Config) -> PathBuf {
+	network_file_path(cfg, "p2p", "i2p_private_key")
+}
+
+pub fn memory_pool_path(cfg: &Config) -> PathBuf {
+	network_file_path(cfg, "mempool", "mempool.dat")
+}
+
+pub fn fee_estimates_path(cfg: &Config) -> PathBuf {
+	network_file_path(cfg, "mempool", "fee_estimates.dat")
+}
+
+pub fn pidfile_path(cfg: &Config) -> PathBuf {
+	match cfg.pidfile {
+		Some(ref pidfile) => PathBuf::from(pidfile),
+		None => network_file_path(cfg, "run", "pbtc.pid"),
+	}
+}
+
+pub fn rpc_cookie_path(cfg: &Config) -> PathBuf {
+	match cfg.rpc_config.cookie_file {
+		Some(ref cookie_file) => PathBuf::from(cookie_file),
+		None => network_file_path(cfg, "run", ".cookie"),
+	}
 }
 
 pub fn init_db(cfg: &Config) -> Result<(), String> {
 	// insert genesis block if db is empty
-	let genesis_block: IndexedBlock = cfg.magic.genesis_block().into();
+	let genesis_block: IndexedBlock = cfg.genesis_block.clone().into();
 	match cfg.db.block_hash(0) {
 		Some(ref db_genesis_block_hash) if db_genesis_block_hash != genesis_block.hash() => Err("Trying to open database with incompatible genesis block".into()),
 		Some(_) => Ok(()),
@@ -38,6 +159,34 @@ pub fn init_db(cfg: &Config) -> Result<(), String> {
 	}
 }
 
+fn db_path(data_dir: &Option<String>, magic: Magic) -> PathBuf {
+	let sub_dir = network_sub_dir(magic, "db");
+	match *data_dir {
+		Some(ref data_dir) => custom_path(&data_dir, &sub_dir),
+		None => app_dir(AppDataType::UserData, &APP_INFO, &sub_dir).expect("Failed to get app dir"),
+	}
+}
+
+/// Regtest, signet and custom networks data is kept separately, so that test chains never mix with real ones.
+fn network_sub_dir(magic: Magic, sub_dir: &str) -> String {
+	match magic {
+		Magic::Regtest => format!("regtest/{}", sub_dir),
+		Magic::Signet(magic) => format!("signet/{:08x}/{}", magic, sub_dir),
+		Magic::Other(magic) => format!("custom/{:08x}/{}", magic, sub_dir),
+		Magic::Mainnet | Magic::Testnet | Magic::Unitest => sub_dir.into(),
+	}
+}
+
+fn network_file_path(cfg: &Config, sub_dir: &str, file_name: &str) -> PathBuf {
+	let sub_dir = network_sub_dir(cfg.magic, sub_dir);
+	let mut path = match cfg.data_dir {
+		Some(ref data_dir) => custom_path(&data_dir, &sub_dir),
+		None => app_dir(AppDataType::UserData, &APP_INFO, &sub_dir).expect("Failed to get app dir"),
+	};
+	path.push(file_name);
+	path
+}
+
 fn custom_path(data_dir: &str, sub_dir: &str) -> PathBuf {
 	let mut path = PathBuf::from(data_dir);
 	path.push(sub_dir);
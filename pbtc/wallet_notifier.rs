@@ -0,0 +1,98 @@
+//! Wallet synchronization.
+//!
+//! Wallet applies canon chain changes in the background thread, in the order they have happened.
+//! Changes, received while the previous changes are applied, are applied together. Blocks, inserted
+//! while the wallet was disabled, are scanned before any changes are applied.
+
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use primitives::hash::H256;
+use sync::SyncListener;
+use wallet::{Wallet, ChainChange};
+
+enum WalletNotifierTask {
+	ChainChange(ChainChange),
+	Stop,
+}
+
+/// Synchronizes the wallet with the canon chain.
+pub struct WalletNotifier {
+	tx: Sender<WalletNotifierTask>,
+	worker_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WalletNotifier {
+	/// Starts synchronization thread. Blocks, inserted while the wallet was disabled, are scanned immediately.
+	pub fn new(wallet: Arc<Wallet>) -> Result<Self, String> {
+		let (tx, rx) = channel();
+		let worker_thread = thread::Builder::new()
+			.name("Wallet synchronization thread".to_owned())
+			.spawn(move || WalletNotifier::worker(rx, wallet))
+			.map_err(|err| format!("Failed to start wallet synchronization thread: {}", err))?;
+
+		Ok(WalletNotifier {
+			tx: tx,
+			worker_thread: Some(worker_thread),
+		})
+	}
+
+	fn worker(rx: Receiver<WalletNotifierTask>, wallet: Arc<Wallet>) {
+		if let Err(err) = wallet.synchronize() {
+			warn!(target: "pbtc", "Failed to synchronize wallet: {}", err);
+		}
+
+		for task in rx.iter() {
+			let mut changes = match task {
+				WalletNotifierTask::ChainChange(change) => vec![change],
+				WalletNotifierTask::Stop => break,
+			};
+
+			// all changes, received so far, are applied together
+			let mut stop = false;
+			for task in rx.try_iter() {
+				match task {
+					WalletNotifierTask::ChainChange(change) => changes.push(change),
+					WalletNotifierTask::Stop => {
+						stop = true;
+						break;
+					},
+				}
+			}
+			if let Err(err) = wallet.apply_chain_changes(&changes) {
+				warn!(target: "pbtc", "Failed to synchronize wallet: {}", err);
+			}
+			if stop {
+				break;
+			}
+		}
+		trace!(target: "pbtc", "Wallet synchronization thread stopped");
+	}
+}
+
+impl SyncListener for WalletNotifier {
+	fn synchronization_state_switched(&self, _is_synchronizing: bool) {
+	}
+
+	fn best_storage_block_inserted(&self, _block_hash: &H256) {
+	}
+
+	fn block_connected(&self, block_hash: &H256) {
+		self.tx.send(WalletNotifierTask::ChainChange(ChainChange::Connected(block_hash.clone())))
+			.expect("wallet synchronization thread have the same lifetime as `WalletNotifier`")
+	}
+
+	fn block_disconnected(&self, block_hash: &H256) {
+		self.tx.send(WalletNotifierTask::ChainChange(ChainChange::Disconnected(block_hash.clone())))
+			.expect("wallet synchronization thread have the same lifetime as `WalletNotifier`")
+	}
+}
+
+impl Drop for WalletNotifier {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			let _ = self.tx.send(WalletNotifierTask::Stop);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
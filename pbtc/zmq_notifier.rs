@@ -0,0 +1,187 @@
+//! ZeroMQ notifications publisher.
+//!
+//! Notifications are published in `bitcoind` format: multipart message of the topic, the body
+//! and the 4-byte little-endian sequence number of the notification. Supported topics are:
+//!
+//! - `hashblock`: hash of the block, connected to the canon chain;
+//! - `hashtx`: hash of the transaction, accepted to the memory pool;
+//! - `rawblock`: serialized block, connected to the canon chain;
+//! - `rawtx`: serialized transaction, accepted to the memory pool.
+//!
+//! When canon chain is reorganized, every block of the new canon chain is published, starting with
+//! the oldest block. Block notifications are not published while the node is synchronizing.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use zmq;
+use chain::IndexedTransaction;
+use db::{self, BlockRef};
+use primitives::bytes::Bytes;
+use primitives::hash::H256;
+use ser::{serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use sync::SyncListener;
+
+/// ZeroMQ notification topic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topic {
+	HashBlock,
+	HashTx,
+	RawBlock,
+	RawTx,
+}
+
+impl Topic {
+	/// All supported topics.
+	pub fn all() -> [Topic; 4] {
+		[Topic::HashBlock, Topic::HashTx, Topic::RawBlock, Topic::RawTx]
+	}
+
+	/// Topic name. Notifications of the topic are enabled with `--zmqpub<name>=<address>` option.
+	pub fn name(&self) -> &'static str {
+		match *self {
+			Topic::HashBlock => "hashblock",
+			Topic::HashTx => "hashtx",
+			Topic::RawBlock => "rawblock",
+			Topic::RawTx => "rawtx",
+		}
+	}
+}
+
+enum ZmqNotifierTask {
+	NewBlock(H256),
+	NewTransaction(IndexedTransaction),
+	Stop,
+}
+
+/// Publisher of the single topic.
+struct Publisher {
+	topic: Topic,
+	/// Index of the socket, notifications are published to.
+	socket: usize,
+	/// Sequence number of the next notification.
+	sequence: u32,
+}
+
+/// Publishes notifications of the synchronization events to the ZeroMQ sockets.
+pub struct ZmqNotifier {
+	tx: Sender<ZmqNotifierTask>,
+	is_synchronizing: Arc<AtomicBool>,
+	worker_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ZmqNotifier {
+	/// Binds sockets to the given addresses. Topics with the same address are published to the single socket.
+	pub fn new(endpoints: Vec<(Topic, String)>, storage: db::SharedStore) -> Result<Self, String> {
+		let context = zmq::Context::new();
+		let mut addresses: Vec<String> = Vec::new();
+		let mut sockets: Vec<zmq::Socket> = Vec::new();
+		let mut publishers: Vec<Publisher> = Vec::new();
+		for (topic, address) in endpoints {
+			let socket = match addresses.iter().position(|socket_address| *socket_address == address) {
+				Some(socket) => socket,
+				None => {
+					let socket = context.socket(zmq::PUB)
+						.and_then(|socket| socket.bind(&address).map(|_| socket))
+						.map_err(|err| format!("Failed to bind ZeroMQ socket to {}: {}", address, err))?;
+					info!(target: "pbtc", "Publishing ZeroMQ notifications at {}", address);
+					sockets.push(socket);
+					addresses.push(address);
+					sockets.len() - 1
+				},
+			};
+
+			publishers.push(Publisher {
+				topic: topic,
+				socket: socket,
+				sequence: 0,
+			});
+		}
+
+		let (tx, rx) = channel();
+		let worker_thread = thread::Builder::new()
+			.name("ZeroMQ notification thread".to_owned())
+			.spawn(move || ZmqNotifier::worker(rx, context, sockets, publishers, storage))
+			.map_err(|err| format!("Failed to start ZeroMQ notification thread: {}", err))?;
+
+		Ok(ZmqNotifier {
+			tx: tx,
+			is_synchronizing: Arc::new(AtomicBool::default()),
+			worker_thread: Some(worker_thread),
+		})
+	}
+
+	fn worker(rx: Receiver<ZmqNotifierTask>, _context: zmq::Context, sockets: Vec<zmq::Socket>, mut publishers: Vec<Publisher>, storage: db::SharedStore) {
+		let has_topic = |publishers: &[Publisher], topic: Topic| publishers.iter().any(|publisher| publisher.topic == topic);
+		for task in rx {
+			let notifications: Vec<(Topic, Bytes)> = match task {
+				ZmqNotifierTask::NewBlock(hash) => {
+					let mut notifications = vec![(Topic::HashBlock, Bytes::from(&hash.reversed()[..]))];
+					if has_topic(&publishers, Topic::RawBlock) {
+						match storage.block_bytes(BlockRef::Hash(hash.clone())) {
+							Some(block) => notifications.push((Topic::RawBlock, block)),
+							None => warn!(target: "pbtc", "Block {} is not found in the storage", hash.to_reversed_str()),
+						}
+					}
+					notifications
+				},
+				ZmqNotifierTask::NewTransaction(transaction) => {
+					let mut notifications = vec![(Topic::HashTx, Bytes::from(&transaction.hash.reversed()[..]))];
+					if has_topic(&publishers, Topic::RawTx) {
+						notifications.push((Topic::RawTx, serialize_with_flags(&transaction.raw, SERIALIZE_TRANSACTION_WITNESS)));
+					}
+					notifications
+				},
+				ZmqNotifierTask::Stop => break,
+			};
+
+			for (topic, body) in notifications {
+				for publisher in publishers.iter_mut().filter(|publisher| publisher.topic == topic) {
+					if let Err(err) = publish(&sockets[publisher.socket], topic, &body, publisher.sequence) {
+						warn!(target: "pbtc", "Failed to publish ZeroMQ {} notification: {}", topic.name(), err);
+					}
+					publisher.sequence = publisher.sequence.wrapping_add(1);
+				}
+			}
+		}
+		trace!(target: "pbtc", "ZeroMQ notification thread stopped");
+	}
+}
+
+/// Sends single notification as multipart message.
+fn publish(socket: &zmq::Socket, topic: Topic, body: &[u8], sequence: u32) -> Result<(), zmq::Error> {
+	socket.send(topic.name().as_bytes(), zmq::SNDMORE)?;
+	socket.send(body, zmq::SNDMORE)?;
+	socket.send(&serialize(&sequence)[..], 0)
+}
+
+impl SyncListener for ZmqNotifier {
+	fn synchronization_state_switched(&self, is_synchronizing: bool) {
+		self.is_synchronizing.store(is_synchronizing, Ordering::SeqCst);
+	}
+
+	fn best_storage_block_inserted(&self, _block_hash: &H256) {
+	}
+
+	fn block_connected(&self, block_hash: &H256) {
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			self.tx.send(ZmqNotifierTask::NewBlock(block_hash.clone()))
+				.expect("ZeroMQ notification thread have the same lifetime as `ZmqNotifier`")
+		}
+	}
+
+	fn transaction_accepted(&self, transaction: &IndexedTransaction) {
+		self.tx.send(ZmqNotifierTask::NewTransaction(transaction.clone()))
+			.expect("ZeroMQ notification thread have the same lifetime as `ZmqNotifier`")
+	}
+}
+
+impl Drop for ZmqNotifier {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			let _ = self.tx.send(ZmqNotifierTask::Stop);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
@@ -65,6 +65,36 @@ impl Compact {
 		}
 	}
 
+	/// Returns the target, represented by compact value.
+	/// Returns `None` if target is negative, overflows or is zero.
+	pub fn to_target(&self) -> Option<U256> {
+		match self.to_u256() {
+			Ok(target) if !target.is_zero() => Some(target),
+			_ => None,
+		}
+	}
+
+	/// Returns the expected number of hashes required to produce block with this target:
+	/// 2^256 / (target + 1). Invalid targets require no work.
+	pub fn block_work(&self) -> U256 {
+		match self.to_target() {
+			// 2^256 can't be represented as U256, but 2^256 / (target + 1) == ~target / (target + 1) + 1
+			Some(target) => (!target / (target + U256::one())) + U256::one(),
+			None => U256::zero(),
+		}
+	}
+
+	/// Returns difficulty of this target, relative to the maximal (easiest) target.
+	pub fn difficulty(&self, max_bits: Compact) -> f64 {
+		let max_target = U256::from(max_bits);
+		let target = match self.to_target() {
+			Some(target) => target,
+			None => return 0f64,
+		};
+
+		u256_to_f64(max_target) / u256_to_f64(target)
+	}
+
 	pub fn from_u256(val: U256) -> Self {
 		let mut size = (val.bits() + 7) / 8;
 		let mut compact = if size <= 3 {
@@ -93,6 +123,11 @@ impl Compact {
 	}
 }
 
+/// Converts `U256` to the nearest `f64`.
+fn u256_to_f64(value: U256) -> f64 {
+	(0..4).rev().fold(0f64, |acc, i| acc * 18446744073709551616f64 + value.0[i] as f64)
+}
+
 #[cfg(test)]
 mod tests {
 	use bigint::{U256, Uint};
@@ -134,5 +169,29 @@ mod tests {
 	fn difficulty() {
 		let nbits = Compact::new(0x1b0404cb);
 		assert_eq!(nbits.to_f64(), 16307.420938523994f64);
+		assert!((nbits.difficulty(Compact::new(0x1d00ffff)) - 16307.420938523994f64).abs() < 0.000001);
+		assert_eq!(Compact::new(0x1d00ffff).difficulty(Compact::new(0x1d00ffff)), 1f64);
+		assert_eq!(Compact::new(0x04923456).difficulty(Compact::new(0x1d00ffff)), 0f64);
+	}
+
+	#[test]
+	fn test_to_target() {
+		assert_eq!(Compact::new(0x1d00ffff).to_target(), Some(Compact::new(0x1d00ffff).to_u256().unwrap()));
+		// zero
+		assert_eq!(Compact::new(0x01003456).to_target(), None);
+		// negative
+		assert_eq!(Compact::new(0x04923456).to_target(), None);
+		// overflow
+		assert_eq!(Compact::new(0xff123456).to_target(), None);
+	}
+
+	#[test]
+	fn test_block_work() {
+		// genesis block work
+		assert_eq!(Compact::new(0x1d00ffff).block_work(), U256::from(0x100010001u64));
+		// regtest work
+		assert_eq!(Compact::new(0x207fffff).block_work(), U256::from(2));
+		// invalid target
+		assert_eq!(Compact::new(0x04923456).block_work(), U256::zero());
 	}
 }
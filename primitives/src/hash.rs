@@ -121,6 +121,14 @@ macro_rules! impl_hash {
 			}
 		}
 
+		impl cmp::Ord for $name {
+			fn cmp(&self, other: &Self) -> cmp::Ordering {
+				let self_ref: &[u8] = &self.0;
+				let other_ref: &[u8] = &other.0;
+				self_ref.cmp(other_ref)
+			}
+		}
+
 
 		impl Hash for $name {
 			fn hash<H>(&self, state: &mut H) where H: Hasher {
@@ -3,9 +3,13 @@ extern crate byteorder;
 extern crate heapsize;
 extern crate rustc_serialize;
 pub extern crate bigint;
+#[cfg(feature = "with-serde")]
+extern crate serde;
 
 pub mod bytes;
 pub mod compact;
 pub mod hash;
+#[cfg(feature = "with-serde")]
+mod serde_impls;
 
 pub use rustc_serialize::hex;
@@ -0,0 +1,50 @@
+//! Serde support for primitive types.
+//!
+//! Bytes and hashes are represented as hex strings (hashes in internal byte order),
+//! compact bits are represented as numbers.
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use hex::ToHex;
+use bytes::Bytes;
+use compact::Compact;
+use hash::{H32, H48, H96, H160, H256, H264, H512, H520};
+
+macro_rules! impl_serde_for_hex {
+	($name: ident) => {
+		impl Serialize for $name {
+			fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+				serializer.serialize_str(&self.to_hex())
+			}
+		}
+
+		impl<'de> Deserialize<'de> for $name {
+			fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+				let s = try!(String::deserialize(deserializer));
+				s.parse().map_err(|_| D::Error::custom("invalid hex"))
+			}
+		}
+	}
+}
+
+impl_serde_for_hex!(Bytes);
+impl_serde_for_hex!(H32);
+impl_serde_for_hex!(H48);
+impl_serde_for_hex!(H96);
+impl_serde_for_hex!(H160);
+impl_serde_for_hex!(H256);
+impl_serde_for_hex!(H264);
+impl_serde_for_hex!(H512);
+impl_serde_for_hex!(H520);
+
+impl Serialize for Compact {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		serializer.serialize_u32(u32::from(*self))
+	}
+}
+
+impl<'de> Deserialize<'de> for Compact {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+		u32::deserialize(deserializer).map(Compact::new)
+	}
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+use primitives::bytes::Bytes;
+use ser::Error as ReaderError;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// PSBT doesn't start with `psbt\xff`
+	InvalidMagic,
+	/// PSBT string isn't valid base64
+	InvalidBase64,
+	/// PSBT can't be deserialized
+	Malformed,
+	/// The same key is used twice in a single map
+	DuplicateKey(Bytes),
+	/// Key of the given type has invalid key data
+	InvalidKey(u8),
+	/// Value of the given key type can't be parsed
+	InvalidValue(u8),
+	/// Global map has no unsigned transaction
+	MissingUnsignedTransaction,
+	/// Unsigned transaction has non-empty scriptSig or witness
+	UnsignedTransactionHasScripts,
+	/// Non-witness UTXO of the input isn't the spent transaction
+	NonWitnessUtxoMismatch(usize),
+	/// Combined PSBTs have different unsigned transactions
+	DifferentTransactions,
+	/// Input isn't finalized yet
+	NotFinalized(usize),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::InvalidMagic => "Invalid PSBT magic bytes".fmt(f),
+			Error::InvalidBase64 => "Invalid PSBT base64 encoding".fmt(f),
+			Error::Malformed => "Malformed PSBT".fmt(f),
+			Error::DuplicateKey(ref key) => write!(f, "Duplicate PSBT key {:?}", key),
+			Error::InvalidKey(key_type) => write!(f, "Invalid PSBT key of type {}", key_type),
+			Error::InvalidValue(key_type) => write!(f, "Invalid PSBT value of type {}", key_type),
+			Error::MissingUnsignedTransaction => "PSBT has no unsigned transaction".fmt(f),
+			Error::UnsignedTransactionHasScripts => "Unsigned transaction has scriptSig or witness".fmt(f),
+			Error::NonWitnessUtxoMismatch(index) => write!(f, "Non-witness UTXO of input {} doesn't match the spent transaction", index),
+			Error::DifferentTransactions => "PSBTs have different unsigned transactions".fmt(f),
+			Error::NotFinalized(index) => write!(f, "Input {} isn't finalized", index),
+		}
+	}
+}
+
+impl From<ReaderError> for Error {
+	fn from(_: ReaderError) -> Self {
+		Error::Malformed
+	}
+}
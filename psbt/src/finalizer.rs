@@ -0,0 +1,143 @@
+//! PSBT finalizer.
+//!
+//! Builds final scriptSig and witness of inputs, spending P2PK, P2PKH, bare multisig, P2WPKH
+//! and P2SH/P2WSH outputs, wrapping any of the former. Partial signatures are not verified here:
+//! signers are expected to provide valid signatures and the signed transaction is verified on relay.
+
+use crypto::{dhash160, sha256};
+use primitives::bytes::Bytes;
+use script::{Script, ScriptType, Builder};
+use map::PsbtInput;
+
+/// Finalizes the input. Returns false if the input doesn't have enough data to be finalized.
+pub fn finalize_input(input: &mut PsbtInput, script_pubkey: &[u8]) -> bool {
+	let (script_sig, witness) = match solve_input(input, script_pubkey) {
+		Some(solution) => solution,
+		None => return false,
+	};
+
+	input.final_script_sig = if script_sig.is_empty() && !witness.is_empty() { None } else { Some(script_sig) };
+	input.final_script_witness = if witness.is_empty() { None } else { Some(witness) };
+	input.partial_signatures.clear();
+	input.sighash_type = None;
+	input.redeem_script = None;
+	input.witness_script = None;
+	input.bip32_derivations.clear();
+	true
+}
+
+/// Returns scriptSig and witness, spending the given script.
+fn solve_input(input: &PsbtInput, script_pubkey: &[u8]) -> Option<(Bytes, Vec<Bytes>)> {
+	let script: Script = script_pubkey.to_vec().into();
+	if script.is_pay_to_script_hash() {
+		let redeem_script = match input.redeem_script {
+			Some(ref redeem_script) if dhash160(redeem_script)[..] == script[2..22] => redeem_script,
+			_ => return None,
+		};
+
+		let redeem: Script = redeem_script.clone().into();
+		if redeem.parse_witness_program().is_some() {
+			solve_witness_program(input, &redeem)
+				.map(|witness| (push_items(&[redeem_script.clone()]), witness))
+		} else {
+			solve_script(input, &redeem)
+				.map(|mut items| {
+					items.push(redeem_script.clone());
+					(push_items(&items), Vec::new())
+				})
+		}
+	} else if script.parse_witness_program().is_some() {
+		solve_witness_program(input, &script)
+			.map(|witness| (Bytes::new(), witness))
+	} else {
+		solve_script(input, &script)
+			.map(|items| (push_items(&items), Vec::new()))
+	}
+}
+
+/// Returns witness, spending version 0 witness program.
+fn solve_witness_program(input: &PsbtInput, program: &Script) -> Option<Vec<Bytes>> {
+	if program.is_pay_to_witness_key_hash() {
+		signature_by_key_hash(input, &program[2..22])
+			.map(|(signature, public)| vec![signature, public])
+	} else if program.is_pay_to_witness_script_hash() {
+		let witness_script = match input.witness_script {
+			Some(ref witness_script) if sha256(witness_script)[..] == program[2..34] => witness_script,
+			_ => return None,
+		};
+
+		solve_script(input, &witness_script.clone().into())
+			.map(|mut items| {
+				items.push(witness_script.clone());
+				items
+			})
+	} else {
+		None
+	}
+}
+
+/// Returns stack items, satisfying the script.
+fn solve_script(input: &PsbtInput, script: &Script) -> Option<Vec<Bytes>> {
+	match script.script_type() {
+		ScriptType::PubKey => signature(input, &script[1..script.len() - 1])
+			.map(|signature| vec![signature.clone()]),
+		ScriptType::PubKeyHash => signature_by_key_hash(input, &script[3..23])
+			.map(|(signature, public)| vec![signature, public]),
+		ScriptType::Multisig => {
+			// OP_CHECKMULTISIG pops one extra item
+			let required = script.num_signatures_required() as usize;
+			let mut items = vec![Bytes::new()];
+			for public in multisig_keys(script) {
+				if items.len() == required + 1 {
+					break;
+				}
+				if let Some(signature) = signature(input, public) {
+					items.push(signature.clone());
+				}
+			}
+
+			if items.len() == required + 1 {
+				Some(items)
+			} else {
+				None
+			}
+		},
+		_ => None,
+	}
+}
+
+fn signature<'a>(input: &'a PsbtInput, public: &[u8]) -> Option<&'a Bytes> {
+	input.partial_signatures.iter()
+		.find(|&&(ref key, _)| key[..] == *public)
+		.map(|&(_, ref signature)| signature)
+}
+
+fn signature_by_key_hash(input: &PsbtInput, key_hash: &[u8]) -> Option<(Bytes, Bytes)> {
+	input.partial_signatures.iter()
+		.find(|&&(ref key, _)| dhash160(key)[..] == *key_hash)
+		.map(|&(ref key, ref signature)| (signature.clone(), key.clone()))
+}
+
+/// Public keys of `m <keys> n OP_CHECKMULTISIG` script.
+fn multisig_keys(script: &Script) -> Vec<&[u8]> {
+	let mut keys = Vec::new();
+	let mut pc = 1;
+	while pc < script.len() - 2 {
+		match script.get_instruction(pc) {
+			Ok(instruction) => {
+				if let Some(data) = instruction.data {
+					keys.push(data);
+				}
+				pc += instruction.step;
+			},
+			Err(_) => break,
+		}
+	}
+	keys
+}
+
+fn push_items(items: &[Bytes]) -> Bytes {
+	items.iter()
+		.fold(Builder::default(), |builder, item| builder.push_data(item))
+		.into_bytes()
+}
@@ -0,0 +1,18 @@
+//! Partially signed bitcoin transactions (BIP174).
+
+extern crate rustc_serialize;
+extern crate bitcrypto as crypto;
+extern crate chain;
+extern crate keys;
+extern crate primitives;
+extern crate script;
+extern crate serialization as ser;
+
+mod error;
+mod finalizer;
+mod map;
+mod psbt;
+
+pub use error::Error;
+pub use map::{PsbtInput, PsbtOutput, KeySource, Pairs};
+pub use psbt::Psbt;
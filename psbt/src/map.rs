@@ -0,0 +1,310 @@
+//! PSBT input and output maps.
+//!
+//! Every map is a list of key-value pairs, terminated by the empty key. The first byte of the key
+//! is the key type, the rest is the key data. Pairs of unknown types are kept as is.
+
+use std::io;
+use chain::{Transaction, TransactionOutput, OutPoint};
+use keys::Public;
+use primitives::bytes::Bytes;
+use primitives::hash::H32;
+use ser::{Stream, Reader, Deserializable, List, deserialize, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use Error;
+
+pub const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+
+pub const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+pub const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+pub const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+pub const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+pub const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+pub const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+pub const PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+pub const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+pub const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+
+pub const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+pub const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+pub const PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+
+/// Raw key-value pairs. Key includes the key type byte.
+pub type Pairs = Vec<(Bytes, Bytes)>;
+
+/// Origin of the public key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySource {
+	/// Fingerprint of the master key
+	pub fingerprint: H32,
+	/// Derivation path from the master key
+	pub path: Vec<u32>,
+}
+
+/// Per-input PSBT map.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PsbtInput {
+	/// Transaction, spent by the input. Used for non-witness inputs.
+	pub non_witness_utxo: Option<Transaction>,
+	/// Output, spent by the input. Used for witness inputs.
+	pub witness_utxo: Option<TransactionOutput>,
+	/// Signatures, keyed by public keys
+	pub partial_signatures: Vec<(Bytes, Bytes)>,
+	pub sighash_type: Option<u32>,
+	pub redeem_script: Option<Bytes>,
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys, required to sign the input
+	pub bip32_derivations: Vec<(Bytes, KeySource)>,
+	pub final_script_sig: Option<Bytes>,
+	pub final_script_witness: Option<Vec<Bytes>>,
+	pub unknown: Pairs,
+}
+
+/// Per-output PSBT map.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PsbtOutput {
+	pub redeem_script: Option<Bytes>,
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys, used in the output script
+	pub bip32_derivations: Vec<(Bytes, KeySource)>,
+	pub unknown: Pairs,
+}
+
+impl KeySource {
+	fn parse(key_type: u8, value: &[u8]) -> Result<Self, Error> {
+		if value.len() < 4 || value.len() % 4 != 0 {
+			return Err(Error::InvalidValue(key_type));
+		}
+
+		Ok(KeySource {
+			fingerprint: H32::from(&value[0..4]),
+			path: value[4..].chunks(4)
+				.map(|index| index.iter().rev().fold(0u32, |acc, byte| (acc << 8) | *byte as u32))
+				.collect(),
+		})
+	}
+
+	fn to_bytes(&self) -> Bytes {
+		let mut stream = Stream::new();
+		stream.append(&self.fingerprint);
+		for index in &self.path {
+			stream.append(index);
+		}
+		stream.out()
+	}
+}
+
+impl PsbtInput {
+	/// Is the input finalized (has final scriptSig or witness)?
+	pub fn is_finalized(&self) -> bool {
+		self.final_script_sig.is_some() || self.final_script_witness.is_some()
+	}
+
+	/// Output, spent by the input, if it's known.
+	pub fn spent_output(&self, previous_output: &OutPoint) -> Option<&TransactionOutput> {
+		if let Some(ref output) = self.witness_utxo {
+			return Some(output);
+		}
+
+		self.non_witness_utxo.as_ref()
+			.and_then(|transaction| transaction.outputs.get(previous_output.index as usize))
+	}
+
+	/// Combiner: fills fields, missing in this input, from the other input.
+	pub fn combine(&mut self, other: PsbtInput) {
+		self.non_witness_utxo = self.non_witness_utxo.take().or(other.non_witness_utxo);
+		self.witness_utxo = self.witness_utxo.take().or(other.witness_utxo);
+		merge_pairs(&mut self.partial_signatures, other.partial_signatures);
+		self.sighash_type = self.sighash_type.or(other.sighash_type);
+		self.redeem_script = self.redeem_script.take().or(other.redeem_script);
+		self.witness_script = self.witness_script.take().or(other.witness_script);
+		merge_pairs(&mut self.bip32_derivations, other.bip32_derivations);
+		self.final_script_sig = self.final_script_sig.take().or(other.final_script_sig);
+		self.final_script_witness = self.final_script_witness.take().or(other.final_script_witness);
+		merge_pairs(&mut self.unknown, other.unknown);
+	}
+
+	pub fn from_pairs(pairs: Pairs) -> Result<Self, Error> {
+		let mut input = PsbtInput::default();
+		for (key, value) in pairs {
+			let key_type = key[0];
+			match key_type {
+				PSBT_IN_NON_WITNESS_UTXO => {
+					check_empty_key(key_type, &key)?;
+					input.non_witness_utxo = Some(parse_value(key_type, &value)?);
+				},
+				PSBT_IN_WITNESS_UTXO => {
+					check_empty_key(key_type, &key)?;
+					input.witness_utxo = Some(parse_value(key_type, &value)?);
+				},
+				PSBT_IN_PARTIAL_SIG => {
+					check_public_key(key_type, &key)?;
+					input.partial_signatures.push((Bytes::from(&key[1..]), value));
+				},
+				PSBT_IN_SIGHASH_TYPE => {
+					check_empty_key(key_type, &key)?;
+					input.sighash_type = Some(parse_value(key_type, &value)?);
+				},
+				PSBT_IN_REDEEM_SCRIPT => {
+					check_empty_key(key_type, &key)?;
+					input.redeem_script = Some(value);
+				},
+				PSBT_IN_WITNESS_SCRIPT => {
+					check_empty_key(key_type, &key)?;
+					input.witness_script = Some(value);
+				},
+				PSBT_IN_BIP32_DERIVATION => {
+					check_public_key(key_type, &key)?;
+					input.bip32_derivations.push((Bytes::from(&key[1..]), KeySource::parse(key_type, &value)?));
+				},
+				PSBT_IN_FINAL_SCRIPTSIG => {
+					check_empty_key(key_type, &key)?;
+					input.final_script_sig = Some(value);
+				},
+				PSBT_IN_FINAL_SCRIPTWITNESS => {
+					check_empty_key(key_type, &key)?;
+					let witness: List<Bytes> = parse_value(key_type, &value)?;
+					input.final_script_witness = Some(witness.into());
+				},
+				_ => input.unknown.push((key, value)),
+			}
+		}
+
+		Ok(input)
+	}
+
+	pub fn write(&self, stream: &mut Stream) {
+		if let Some(ref transaction) = self.non_witness_utxo {
+			write_pair(stream, PSBT_IN_NON_WITNESS_UTXO, &[], &serialize_with_flags(transaction, SERIALIZE_TRANSACTION_WITNESS));
+		}
+		if let Some(ref output) = self.witness_utxo {
+			write_pair(stream, PSBT_IN_WITNESS_UTXO, &[], &serialize(output));
+		}
+		for &(ref public, ref signature) in &self.partial_signatures {
+			write_pair(stream, PSBT_IN_PARTIAL_SIG, public, signature);
+		}
+		if let Some(ref sighash_type) = self.sighash_type {
+			write_pair(stream, PSBT_IN_SIGHASH_TYPE, &[], &serialize(sighash_type));
+		}
+		if let Some(ref script) = self.redeem_script {
+			write_pair(stream, PSBT_IN_REDEEM_SCRIPT, &[], script);
+		}
+		if let Some(ref script) = self.witness_script {
+			write_pair(stream, PSBT_IN_WITNESS_SCRIPT, &[], script);
+		}
+		for &(ref public, ref source) in &self.bip32_derivations {
+			write_pair(stream, PSBT_IN_BIP32_DERIVATION, public, &source.to_bytes());
+		}
+		if let Some(ref script) = self.final_script_sig {
+			write_pair(stream, PSBT_IN_FINAL_SCRIPTSIG, &[], script);
+		}
+		if let Some(ref witness) = self.final_script_witness {
+			let mut value = Stream::new();
+			value.append_list::<Bytes, Bytes>(witness);
+			write_pair(stream, PSBT_IN_FINAL_SCRIPTWITNESS, &[], &value.out());
+		}
+		write_unknown(stream, &self.unknown);
+	}
+}
+
+impl PsbtOutput {
+	/// Combiner: fills fields, missing in this output, from the other output.
+	pub fn combine(&mut self, other: PsbtOutput) {
+		self.redeem_script = self.redeem_script.take().or(other.redeem_script);
+		self.witness_script = self.witness_script.take().or(other.witness_script);
+		merge_pairs(&mut self.bip32_derivations, other.bip32_derivations);
+		merge_pairs(&mut self.unknown, other.unknown);
+	}
+
+	pub fn from_pairs(pairs: Pairs) -> Result<Self, Error> {
+		let mut output = PsbtOutput::default();
+		for (key, value) in pairs {
+			let key_type = key[0];
+			match key_type {
+				PSBT_OUT_REDEEM_SCRIPT => {
+					check_empty_key(key_type, &key)?;
+					output.redeem_script = Some(value);
+				},
+				PSBT_OUT_WITNESS_SCRIPT => {
+					check_empty_key(key_type, &key)?;
+					output.witness_script = Some(value);
+				},
+				PSBT_OUT_BIP32_DERIVATION => {
+					check_public_key(key_type, &key)?;
+					output.bip32_derivations.push((Bytes::from(&key[1..]), KeySource::parse(key_type, &value)?));
+				},
+				_ => output.unknown.push((key, value)),
+			}
+		}
+
+		Ok(output)
+	}
+
+	pub fn write(&self, stream: &mut Stream) {
+		if let Some(ref script) = self.redeem_script {
+			write_pair(stream, PSBT_OUT_REDEEM_SCRIPT, &[], script);
+		}
+		if let Some(ref script) = self.witness_script {
+			write_pair(stream, PSBT_OUT_WITNESS_SCRIPT, &[], script);
+		}
+		for &(ref public, ref source) in &self.bip32_derivations {
+			write_pair(stream, PSBT_OUT_BIP32_DERIVATION, public, &source.to_bytes());
+		}
+		write_unknown(stream, &self.unknown);
+	}
+}
+
+/// Reads key-value pairs of the single map, including the separator.
+pub fn read_pairs<T>(reader: &mut Reader<T>) -> Result<Pairs, Error> where T: io::Read {
+	let mut pairs: Pairs = Vec::new();
+	loop {
+		let key: Bytes = reader.read()?;
+		if key.is_empty() {
+			return Ok(pairs);
+		}
+
+		let value: Bytes = reader.read()?;
+		if pairs.iter().any(|&(ref known, _)| *known == key) {
+			return Err(Error::DuplicateKey(key));
+		}
+		pairs.push((key, value));
+	}
+}
+
+pub fn write_pair(stream: &mut Stream, key_type: u8, key_data: &[u8], value: &[u8]) {
+	let mut key = Vec::with_capacity(key_data.len() + 1);
+	key.push(key_type);
+	key.extend_from_slice(key_data);
+	stream.append(&Bytes::from(key)).append(&Bytes::from(value));
+}
+
+/// Writes unknown pairs and the map separator.
+pub fn write_unknown(stream: &mut Stream, pairs: &Pairs) {
+	for &(ref key, ref value) in pairs {
+		stream.append(key).append(value);
+	}
+	stream.append(&0u8);
+}
+
+pub fn parse_value<T>(key_type: u8, value: &[u8]) -> Result<T, Error> where T: Deserializable {
+	deserialize(value).map_err(|_| Error::InvalidValue(key_type))
+}
+
+pub fn check_empty_key(key_type: u8, key: &[u8]) -> Result<(), Error> {
+	match key.len() {
+		1 => Ok(()),
+		_ => Err(Error::InvalidKey(key_type)),
+	}
+}
+
+fn check_public_key(key_type: u8, key: &[u8]) -> Result<(), Error> {
+	Public::from_slice(&key[1..])
+		.map(|_| ())
+		.map_err(|_| Error::InvalidKey(key_type))
+}
+
+fn merge_pairs<T>(pairs: &mut Vec<(Bytes, T)>, other: Vec<(Bytes, T)>) {
+	for (key, value) in other {
+		if !pairs.iter().any(|&(ref known, _)| *known == key) {
+			pairs.push((key, value));
+		}
+	}
+}
@@ -0,0 +1,367 @@
+use std::{fmt, str};
+use rustc_serialize::base64::{ToBase64, FromBase64, STANDARD};
+use chain::Transaction;
+use primitives::bytes::Bytes;
+use ser::{Stream, Reader, serialize};
+use map::{PsbtInput, PsbtOutput, Pairs, PSBT_GLOBAL_UNSIGNED_TX, read_pairs, write_pair, write_unknown, parse_value, check_empty_key};
+use finalizer::finalize_input;
+use Error;
+
+/// PSBT magic bytes: `psbt` and 0xff separator.
+const PSBT_MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+
+/// Partially signed bitcoin transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Psbt {
+	/// Transaction with empty scriptSigs and witnesses
+	pub unsigned_transaction: Transaction,
+	/// Unknown global pairs
+	pub unknown: Pairs,
+	/// Maps of transaction inputs, in the same order
+	pub inputs: Vec<PsbtInput>,
+	/// Maps of transaction outputs, in the same order
+	pub outputs: Vec<PsbtOutput>,
+}
+
+impl Psbt {
+	/// Creator: PSBT with empty input and output maps.
+	pub fn from_unsigned_transaction(transaction: Transaction) -> Result<Self, Error> {
+		if transaction.inputs.iter().any(|input| !input.script_sig.is_empty() || !input.script_witness.is_empty()) {
+			return Err(Error::UnsignedTransactionHasScripts);
+		}
+
+		Ok(Psbt {
+			inputs: vec![PsbtInput::default(); transaction.inputs.len()],
+			outputs: vec![PsbtOutput::default(); transaction.outputs.len()],
+			unsigned_transaction: transaction,
+			unknown: Vec::new(),
+		})
+	}
+
+	pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+		if data.len() < PSBT_MAGIC.len() || data[0..PSBT_MAGIC.len()] != PSBT_MAGIC {
+			return Err(Error::InvalidMagic);
+		}
+
+		let mut reader = Reader::new(&data[PSBT_MAGIC.len()..]);
+		let mut unsigned_transaction: Option<Transaction> = None;
+		let mut unknown = Vec::new();
+		for (key, value) in read_pairs(&mut reader)? {
+			match key[0] {
+				PSBT_GLOBAL_UNSIGNED_TX => {
+					check_empty_key(PSBT_GLOBAL_UNSIGNED_TX, &key)?;
+					unsigned_transaction = Some(parse_value(PSBT_GLOBAL_UNSIGNED_TX, &value)?);
+				},
+				_ => unknown.push((key, value)),
+			}
+		}
+
+		let mut psbt = Psbt::from_unsigned_transaction(unsigned_transaction.ok_or(Error::MissingUnsignedTransaction)?)?;
+		psbt.unknown = unknown;
+		for input in &mut psbt.inputs {
+			*input = PsbtInput::from_pairs(read_pairs(&mut reader)?)?;
+		}
+		for output in &mut psbt.outputs {
+			*output = PsbtOutput::from_pairs(read_pairs(&mut reader)?)?;
+		}
+		if !reader.is_finished() {
+			return Err(Error::Malformed);
+		}
+
+		for (index, (input, psbt_input)) in psbt.unsigned_transaction.inputs.iter().zip(psbt.inputs.iter()).enumerate() {
+			if let Some(ref transaction) = psbt_input.non_witness_utxo {
+				if transaction.hash() != input.previous_output.hash {
+					return Err(Error::NonWitnessUtxoMismatch(index));
+				}
+			}
+		}
+
+		Ok(psbt)
+	}
+
+	pub fn to_bytes(&self) -> Bytes {
+		let mut stream = Stream::new();
+		stream.append_slice(&PSBT_MAGIC);
+		write_pair(&mut stream, PSBT_GLOBAL_UNSIGNED_TX, &[], &serialize(&self.unsigned_transaction));
+		write_unknown(&mut stream, &self.unknown);
+		for input in &self.inputs {
+			input.write(&mut stream);
+		}
+		for output in &self.outputs {
+			output.write(&mut stream);
+		}
+		stream.out()
+	}
+
+	/// Combiner: merges maps of the other PSBT of the same transaction into this one.
+	pub fn combine(&mut self, other: Psbt) -> Result<(), Error> {
+		if self.unsigned_transaction.hash() != other.unsigned_transaction.hash() {
+			return Err(Error::DifferentTransactions);
+		}
+
+		for (input, other) in self.inputs.iter_mut().zip(other.inputs) {
+			input.combine(other);
+		}
+		for (output, other) in self.outputs.iter_mut().zip(other.outputs) {
+			output.combine(other);
+		}
+		for (key, value) in other.unknown {
+			if !self.unknown.iter().any(|&(ref known, _)| *known == key) {
+				self.unknown.push((key, value));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Finalizer: builds final scriptSig and witness of every input, which has enough signatures.
+	/// Returns true if all inputs are finalized.
+	pub fn finalize(&mut self) -> bool {
+		for (input, psbt_input) in self.unsigned_transaction.inputs.iter().zip(self.inputs.iter_mut()) {
+			if psbt_input.is_finalized() {
+				continue;
+			}
+
+			let script_pubkey = match psbt_input.spent_output(&input.previous_output) {
+				Some(output) => output.script_pubkey.clone(),
+				None => continue,
+			};
+			finalize_input(psbt_input, &script_pubkey);
+		}
+
+		self.is_finalized()
+	}
+
+	pub fn is_finalized(&self) -> bool {
+		self.inputs.iter().all(PsbtInput::is_finalized)
+	}
+
+	/// Extractor: signed transaction, if all inputs are finalized.
+	pub fn extract_transaction(&self) -> Result<Transaction, Error> {
+		let mut transaction = self.unsigned_transaction.clone();
+		for (index, (input, psbt_input)) in transaction.inputs.iter_mut().zip(self.inputs.iter()).enumerate() {
+			if !psbt_input.is_finalized() {
+				return Err(Error::NotFinalized(index));
+			}
+
+			input.script_sig = psbt_input.final_script_sig.clone().unwrap_or_default();
+			input.script_witness = psbt_input.final_script_witness.clone().unwrap_or_default();
+		}
+
+		Ok(transaction)
+	}
+
+	/// Transaction fee, if all spent outputs are known.
+	pub fn fee(&self) -> Option<u64> {
+		let input_value = self.unsigned_transaction.inputs.iter().zip(self.inputs.iter())
+			.map(|(input, psbt_input)| psbt_input.spent_output(&input.previous_output).map(|output| output.value))
+			.fold(Some(0u64), |acc, value| match (acc, value) {
+				(Some(acc), Some(value)) => acc.checked_add(value),
+				_ => None,
+			});
+
+		let output_value = self.unsigned_transaction.outputs.iter().map(|output| output.value).sum();
+		input_value.and_then(|input_value| input_value.checked_sub(output_value))
+	}
+}
+
+impl str::FromStr for Psbt {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let data = s.from_base64().map_err(|_| Error::InvalidBase64)?;
+		Psbt::from_bytes(&data)
+	}
+}
+
+impl fmt::Display for Psbt {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.to_bytes().to_base64(STANDARD))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint};
+	use crypto::{dhash160, sha256};
+	use primitives::bytes::Bytes;
+	use primitives::hash::H32;
+	use script::{Builder, Opcode};
+	use map::KeySource;
+	use Error;
+	use super::Psbt;
+
+	const PUBLIC1: &'static str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+	const PUBLIC2: &'static str = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+	// finalizer doesn't verify signatures
+	const SIGNATURE1: &'static str = "30060201010201010101";
+	const SIGNATURE2: &'static str = "30060201020201020101";
+
+	fn previous_transaction(script_pubkey: Bytes) -> Transaction {
+		Transaction {
+			version: 1,
+			inputs: vec![TransactionInput::default()],
+			outputs: vec![TransactionOutput {
+				value: 100_000,
+				script_pubkey: script_pubkey,
+			}],
+			lock_time: 0,
+		}
+	}
+
+	fn unsigned_transaction(previous: &[Transaction]) -> Transaction {
+		Transaction {
+			version: 2,
+			inputs: previous.iter().map(|transaction| TransactionInput {
+				previous_output: OutPoint {
+					hash: transaction.hash(),
+					index: 0,
+				},
+				script_sig: Bytes::new(),
+				sequence: 0xffffffff,
+				script_witness: vec![],
+			}).collect(),
+			outputs: vec![TransactionOutput {
+				value: 90_000,
+				script_pubkey: "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac".into(),
+			}],
+			lock_time: 0,
+		}
+	}
+
+	#[test]
+	fn test_psbt_round_trip() {
+		let previous = previous_transaction(Builder::build_p2pkh(&dhash160(&Bytes::from(PUBLIC1))).to_bytes());
+		let mut psbt = Psbt::from_unsigned_transaction(unsigned_transaction(&[previous.clone()])).unwrap();
+		psbt.unknown.push(("0f0102".into(), "03".into()));
+		psbt.inputs[0].non_witness_utxo = Some(previous);
+		psbt.inputs[0].partial_signatures.push((PUBLIC1.into(), SIGNATURE1.into()));
+		psbt.inputs[0].sighash_type = Some(1);
+		psbt.inputs[0].bip32_derivations.push((PUBLIC1.into(), KeySource {
+			fingerprint: H32::from(0xd9),
+			path: vec![0x8000002c, 0x80000000, 0x80000000, 0, 5],
+		}));
+		psbt.inputs[0].unknown.push(("0f".into(), "".into()));
+		psbt.outputs[0].bip32_derivations.push((PUBLIC2.into(), KeySource {
+			fingerprint: H32::from(0xd9),
+			path: vec![],
+		}));
+
+		let encoded = psbt.to_string();
+		assert!(encoded.starts_with("cHNidP8"));
+		assert_eq!(encoded.parse::<Psbt>(), Ok(psbt.clone()));
+		assert_eq!(Psbt::from_bytes(&psbt.to_bytes()), Ok(psbt));
+	}
+
+	#[test]
+	fn test_psbt_invalid() {
+		let previous = previous_transaction(Builder::build_p2pkh(&dhash160(&Bytes::from(PUBLIC1))).to_bytes());
+		let psbt = Psbt::from_unsigned_transaction(unsigned_transaction(&[previous.clone()])).unwrap();
+
+		assert_eq!(Psbt::from_bytes(b"psbx\xff\x00"), Err(Error::InvalidMagic));
+		assert_eq!(Psbt::from_bytes(b"psbt\xff\x00"), Err(Error::MissingUnsignedTransaction));
+		assert_eq!("psbt?".parse::<Psbt>(), Err(Error::InvalidBase64));
+
+		let mut truncated = psbt.to_bytes().take();
+		truncated.pop();
+		assert_eq!(Psbt::from_bytes(&truncated), Err(Error::Malformed));
+
+		let mut extended = psbt.to_bytes().take();
+		extended.push(0);
+		assert_eq!(Psbt::from_bytes(&extended), Err(Error::Malformed));
+
+		let mut duplicate = psbt.clone();
+		duplicate.unknown.push(("0f".into(), "01".into()));
+		duplicate.unknown.push(("0f".into(), "02".into()));
+		assert_eq!(Psbt::from_bytes(&duplicate.to_bytes()), Err(Error::DuplicateKey("0f".into())));
+
+		let mut invalid_key = psbt.clone();
+		invalid_key.inputs[0].partial_signatures.push(("0102".into(), SIGNATURE1.into()));
+		assert_eq!(Psbt::from_bytes(&invalid_key.to_bytes()), Err(Error::InvalidKey(0x02)));
+
+		let mut mismatch = psbt.clone();
+		mismatch.inputs[0].non_witness_utxo = Some(previous_transaction("51".into()));
+		assert_eq!(Psbt::from_bytes(&mismatch.to_bytes()), Err(Error::NonWitnessUtxoMismatch(0)));
+
+		let mut signed = unsigned_transaction(&[previous]);
+		signed.inputs[0].script_sig = "51".into();
+		assert_eq!(Psbt::from_unsigned_transaction(signed), Err(Error::UnsignedTransactionHasScripts));
+	}
+
+	#[test]
+	fn test_psbt_combine_finalize_multisig() {
+		let witness_script = Builder::default()
+			.push_opcode(Opcode::OP_2)
+			.push_data(&Bytes::from(PUBLIC1))
+			.push_data(&Bytes::from(PUBLIC2))
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_bytes();
+		let script_pubkey = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_data(&*sha256(&witness_script))
+			.into_bytes();
+		let previous = previous_transaction(script_pubkey.clone());
+		let mut psbt = Psbt::from_unsigned_transaction(unsigned_transaction(&[previous.clone()])).unwrap();
+		psbt.inputs[0].witness_utxo = Some(previous.outputs[0].clone());
+		psbt.inputs[0].witness_script = Some(witness_script.clone());
+		assert_eq!(psbt.fee(), Some(10_000));
+
+		// every signer adds its own signature
+		let mut psbt2 = psbt.clone();
+		psbt.inputs[0].partial_signatures.push((PUBLIC2.into(), SIGNATURE2.into()));
+		psbt2.inputs[0].partial_signatures.push((PUBLIC1.into(), SIGNATURE1.into()));
+		assert!(!psbt.clone().finalize());
+
+		let mut other = Psbt::from_unsigned_transaction(unsigned_transaction(&[previous_transaction("51".into())])).unwrap();
+		assert_eq!(other.combine(psbt2.clone()), Err(Error::DifferentTransactions));
+
+		psbt.combine(psbt2).unwrap();
+		assert_eq!(psbt.inputs[0].partial_signatures.len(), 2);
+		assert_eq!(psbt.extract_transaction(), Err(Error::NotFinalized(0)));
+
+		assert!(psbt.finalize());
+		assert!(psbt.inputs[0].partial_signatures.is_empty());
+		assert_eq!(psbt.inputs[0].witness_script, None);
+
+		let transaction = psbt.extract_transaction().unwrap();
+		assert_eq!(transaction.inputs[0].script_sig, Bytes::new());
+		// signatures are ordered as public keys
+		assert_eq!(transaction.inputs[0].script_witness, vec![Bytes::new(), SIGNATURE1.into(), SIGNATURE2.into(), witness_script]);
+	}
+
+	#[test]
+	fn test_psbt_finalize_p2pkh_and_p2sh_p2wpkh() {
+		let p2pkh = previous_transaction(Builder::build_p2pkh(&dhash160(&Bytes::from(PUBLIC1))).to_bytes());
+		let redeem_script = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_data(&*dhash160(&Bytes::from(PUBLIC2)))
+			.into_bytes();
+		let p2sh_p2wpkh = previous_transaction(Builder::build_p2sh(&dhash160(&redeem_script)).to_bytes());
+		let mut psbt = Psbt::from_unsigned_transaction(unsigned_transaction(&[p2pkh.clone(), p2sh_p2wpkh.clone()])).unwrap();
+		psbt.inputs[0].non_witness_utxo = Some(p2pkh);
+		psbt.inputs[1].witness_utxo = Some(p2sh_p2wpkh.outputs[0].clone());
+		psbt.inputs[1].redeem_script = Some(redeem_script.clone());
+		assert!(!psbt.finalize());
+
+		psbt.inputs[0].partial_signatures.push((PUBLIC1.into(), SIGNATURE1.into()));
+		assert!(!psbt.finalize());
+		assert_eq!(psbt.inputs[0].final_script_sig, Some(Builder::default()
+			.push_data(&Bytes::from(SIGNATURE1))
+			.push_data(&Bytes::from(PUBLIC1))
+			.into_bytes()));
+		assert_eq!(psbt.extract_transaction(), Err(Error::NotFinalized(1)));
+
+		// signature of the other key doesn't satisfy the script
+		psbt.inputs[1].partial_signatures.push((PUBLIC1.into(), SIGNATURE1.into()));
+		assert!(!psbt.finalize());
+
+		psbt.inputs[1].partial_signatures.push((PUBLIC2.into(), SIGNATURE2.into()));
+		assert!(psbt.finalize());
+		assert_eq!(psbt.fee(), Some(110_000));
+
+		let transaction = psbt.extract_transaction().unwrap();
+		assert_eq!(transaction.inputs[1].script_sig, Builder::default().push_data(&redeem_script).into_bytes());
+		assert_eq!(transaction.inputs[1].script_witness, vec![SIGNATURE2.into(), PUBLIC2.into()]);
+	}
+}
@@ -0,0 +1,429 @@
+//! RPC authentication and per-method permissions.
+//!
+//! Every request must carry HTTP basic authentication credentials of either:
+//! - the cookie user (`__cookie__`), which password is randomly generated on every start and written
+//!   to the cookie file, readable by local clients only;
+//! - one of `--rpcauth USER:SALT$HASH[:TIER]` users, where `HASH` is hex of the HMAC-SHA256 of the
+//!   password, keyed by `SALT` (same as `bitcoind -rpcauth`).
+//!
+//! Requests with invalid or missing credentials are rejected with `401 Unauthorized` after a short delay.
+//!
+//! Methods are split into permission tiers: `read` methods only read the node state, `write` methods
+//! also submit transactions and blocks, and `admin` methods control the node. Tier of every method is
+//! given when the method is registered (see `MethodTiers::register`). The user is permitted to call
+//! methods of its own and lower tiers. Cookie user has `admin` tier.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use parking_lot::RwLock;
+use jsonrpc_core::{self, Request, Response, Call, Output, Failure, Error, MetaIoHandler, RemoteProcedure};
+use jsonrpc_core::futures::{future, Future};
+use jsonrpc_http_server::{MetaExtractor, RequestMiddleware, RequestMiddlewareAction};
+use jsonrpc_http_server::hyper::{Method, StatusCode};
+use jsonrpc_http_server::hyper::server::{Request as HttpRequest, Response as HttpResponse};
+use jsonrpc_http_server::hyper::header::{Authorization, Basic};
+use rand::{Rng, OsRng};
+use crypto::hmac_sha256;
+use hex::ToHex;
+use primitives::hash::H256;
+use v1::helpers::errors;
+
+/// Name of the user, authenticated by the cookie.
+pub const COOKIE_USER: &'static str = "__cookie__";
+
+/// Response to the request with invalid credentials is delayed by this time, to slow down password guessing.
+const FAILED_AUTH_DELAY_MS: u64 = 250;
+
+/// Permission tier of the RPC method or user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionTier {
+	/// Methods, which only read the node state.
+	Read,
+	/// Methods, which submit transactions and blocks.
+	Write,
+	/// All methods, including the node control.
+	Admin,
+}
+
+impl FromStr for PermissionTier {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"read" => Ok(PermissionTier::Read),
+			"write" => Ok(PermissionTier::Write),
+			"admin" => Ok(PermissionTier::Admin),
+			tier => Err(format!("Unknown permission tier: {}. Expected read, write or admin", tier)),
+		}
+	}
+}
+
+/// Permission tiers, required to call the registered RPC methods.
+#[derive(Debug, Default, Clone)]
+pub struct MethodTiers {
+	tiers: Arc<RwLock<HashMap<String, PermissionTier>>>,
+}
+
+impl MethodTiers {
+	/// Adds methods to the handler. Tier of every added method must be given in `tiers`.
+	pub fn register<F>(&self, handler: &mut MetaIoHandler<Metadata, AuthMiddleware>, methods: F, tiers: &[(&str, PermissionTier)])
+		where F: Into<HashMap<String, RemoteProcedure<Metadata>>> {
+		let methods = methods.into();
+		{
+			let mut registered = self.tiers.write();
+			for method in methods.keys() {
+				let tier = tiers.iter()
+					.find(|&&(name, _)| name == method)
+					.map(|&(_, tier)| tier)
+					.unwrap_or_else(|| panic!("Permission tier of RPC method {} is not defined", method));
+				registered.insert(method.clone(), tier);
+			}
+		}
+		handler.extend_with(methods);
+	}
+
+	/// Returns permission tier, required to call the method, or None if method isn't registered.
+	pub fn tier(&self, method: &str) -> Option<PermissionTier> {
+		self.tiers.read().get(method).cloned()
+	}
+}
+
+/// RPC user, defined in `USER:SALT$HASH[:TIER]` format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcAuth {
+	/// User name
+	pub user: String,
+	/// Salt, used as HMAC key
+	pub salt: String,
+	/// HMAC-SHA256 of the password
+	pub hash: H256,
+	/// Permission tier of the user
+	pub tier: PermissionTier,
+}
+
+impl FromStr for RpcAuth {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("Invalid RPC user: {}. Expected USER:SALT$HASH[:TIER]", s);
+		let values: Vec<&str> = s.split(':').collect();
+		if values.len() != 2 && values.len() != 3 {
+			return Err(invalid());
+		}
+
+		let mut salt_and_hash = values[1].splitn(2, '$');
+		let salt = salt_and_hash.next().ok_or_else(invalid)?;
+		let hash = salt_and_hash.next().ok_or_else(invalid)?;
+		if values[0].is_empty() || values[0] == COOKIE_USER || salt.is_empty() {
+			return Err(invalid());
+		}
+
+		Ok(RpcAuth {
+			user: values[0].to_owned(),
+			salt: salt.to_owned(),
+			hash: hash.parse().map_err(|_| invalid())?,
+			tier: match values.get(2) {
+				Some(tier) => tier.parse()?,
+				None => PermissionTier::Admin,
+			},
+		})
+	}
+}
+
+impl RpcAuth {
+	/// Checks the password of the user.
+	pub fn verify(&self, password: &str) -> bool {
+		constant_time_eq(&*hmac_sha256(self.salt.as_bytes(), password.as_bytes()), &*self.hash)
+	}
+}
+
+/// Checks credentials of the RPC requests.
+#[derive(Debug, Default)]
+pub struct Authenticator {
+	/// Password of the cookie user
+	cookie: Option<String>,
+	/// Users, defined with `--rpcauth`
+	users: Vec<RpcAuth>,
+}
+
+impl Authenticator {
+	pub fn new(cookie: Option<String>, users: Vec<RpcAuth>) -> Self {
+		Authenticator {
+			cookie: cookie,
+			users: users,
+		}
+	}
+
+	/// Returns permission tier of the user, if HTTP request carries valid basic authentication credentials.
+	pub fn authenticate_request(&self, request: &HttpRequest) -> Option<PermissionTier> {
+		request.headers().get::<Authorization<Basic>>()
+			.and_then(|auth| self.authenticate(&auth.0.username, auth.0.password.as_ref().map_or("", |password| password.as_str())))
+	}
+
+	/// Returns permission tier of the user, if credentials are valid.
+	pub fn authenticate(&self, user: &str, password: &str) -> Option<PermissionTier> {
+		if user == COOKIE_USER {
+			return match self.cookie {
+				Some(ref cookie) if constant_time_eq(cookie.as_bytes(), password.as_bytes()) => Some(PermissionTier::Admin),
+				_ => None,
+			};
+		}
+
+		self.users.iter()
+			.find(|auth| auth.user == user && auth.verify(password))
+			.map(|auth| auth.tier)
+	}
+}
+
+/// Generates random password of the cookie user.
+pub fn generate_cookie() -> Result<String, String> {
+	let mut rng = OsRng::new().map_err(|err| format!("Failed to generate RPC cookie: {}", err))?;
+	let mut cookie = [0u8; 32];
+	rng.fill_bytes(&mut cookie);
+	Ok(cookie.to_hex())
+}
+
+/// Compares secrets in time, which doesn't depend on their contents.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// RPC request metadata.
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+	/// Permission tier of the authenticated user, None if credentials are invalid or missing.
+	pub tier: Option<PermissionTier>,
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+
+/// Authenticates HTTP requests, using their basic authentication credentials.
+pub struct AuthMetaExtractor {
+	authenticator: Arc<Authenticator>,
+}
+
+impl AuthMetaExtractor {
+	pub fn new(authenticator: Arc<Authenticator>) -> Self {
+		AuthMetaExtractor {
+			authenticator: authenticator,
+		}
+	}
+}
+
+impl MetaExtractor<Metadata> for AuthMetaExtractor {
+	fn read_metadata(&self, request: &HttpRequest) -> Metadata {
+		Metadata {
+			tier: self.authenticator.authenticate_request(request),
+		}
+	}
+}
+
+/// Responds with `401 Unauthorized` to HTTP requests with invalid or missing credentials.
+pub struct AuthRequestMiddleware {
+	authenticator: Arc<Authenticator>,
+}
+
+impl AuthRequestMiddleware {
+	pub fn new(authenticator: Arc<Authenticator>) -> Self {
+		AuthRequestMiddleware {
+			authenticator: authenticator,
+		}
+	}
+}
+
+impl RequestMiddleware for AuthRequestMiddleware {
+	fn on_request(&self, request: HttpRequest) -> RequestMiddlewareAction {
+		// CORS preflight requests never carry credentials
+		if *request.method() == Method::Options || self.authenticator.authenticate_request(&request).is_some() {
+			return RequestMiddlewareAction::Proceed {
+				should_continue_on_invalid_cors: false,
+				request: request,
+			};
+		}
+
+		// server threads are blocked on purpose: this limits the rate of password guessing
+		thread::sleep(Duration::from_millis(FAILED_AUTH_DELAY_MS));
+		RequestMiddlewareAction::Respond {
+			should_validate_hosts: true,
+			response: Box::new(future::ok(unauthorized_response())),
+		}
+	}
+}
+
+fn unauthorized_response() -> HttpResponse {
+	let mut response = HttpResponse::new().with_status(StatusCode::Unauthorized);
+	response.headers_mut().set_raw("WWW-Authenticate", "Basic realm=\"jsonrpc\"");
+	response
+}
+
+/// Rejects calls of methods, which aren't permitted for the authenticated user.
+#[derive(Debug, Default)]
+pub struct AuthMiddleware {
+	tiers: MethodTiers,
+}
+
+impl AuthMiddleware {
+	pub fn new(tiers: MethodTiers) -> Self {
+		AuthMiddleware {
+			tiers: tiers,
+		}
+	}
+}
+
+impl jsonrpc_core::Middleware<Metadata> for AuthMiddleware {
+	type Future = Box<Future<Item = Option<Response>, Error = ()> + Send>;
+
+	fn on_request<F, X>(&self, request: Request, meta: Metadata, process: F) -> Self::Future where
+		F: FnOnce(Request, Metadata) -> X + Send,
+		X: Future<Item = Option<Response>, Error = ()> + Send + 'static {
+		match check_permissions(&request, meta.tier, &self.tiers) {
+			Ok(_) => Box::new(process(request, meta)),
+			Err(response) => Box::new(future::ok(response)),
+		}
+	}
+}
+
+/// Checks that all calls of the request are permitted. Otherwise returns the response, which
+/// rejects all calls: batch is either executed as a whole or not executed at all.
+/// Calls of unknown methods are left to the handler, which rejects them.
+fn check_permissions(request: &Request, tier: Option<PermissionTier>, tiers: &MethodTiers) -> Result<(), Option<Response>> {
+	let calls: Vec<&Call> = match *request {
+		Request::Single(ref call) => vec![call],
+		Request::Batch(ref calls) => calls.iter().collect(),
+	};
+
+	let error = match tier {
+		None => errors::unauthorized(),
+		Some(tier) => match calls.iter().filter_map(|call| call_method(call)).find(|method| tiers.tier(method).map_or(false, |method_tier| method_tier > tier)) {
+			Some(method) => errors::method_not_permitted(method),
+			None => return Ok(()),
+		},
+	};
+
+	let outputs: Vec<Output> = calls.into_iter().filter_map(|call| rejected_call(call, &error)).collect();
+	Err(match *request {
+		Request::Single(_) => outputs.into_iter().next().map(Response::Single),
+		Request::Batch(_) if outputs.is_empty() => None,
+		Request::Batch(_) => Some(Response::Batch(outputs)),
+	})
+}
+
+fn call_method(call: &Call) -> Option<&str> {
+	match *call {
+		Call::MethodCall(ref call) => Some(&call.method),
+		Call::Notification(ref notification) => Some(&notification.method),
+		Call::Invalid(_) => None,
+	}
+}
+
+/// Notifications are rejected silently.
+fn rejected_call(call: &Call, error: &Error) -> Option<Output> {
+	match *call {
+		Call::MethodCall(ref call) => Some(Output::Failure(Failure {
+			jsonrpc: call.jsonrpc.clone(),
+			error: error.clone(),
+			id: call.id.clone(),
+		})),
+		Call::Notification(_) => None,
+		Call::Invalid(ref id) => Some(Output::Failure(Failure {
+			jsonrpc: None,
+			error: error.clone(),
+			id: id.clone(),
+		})),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use jsonrpc_core::Request;
+	use super::*;
+
+	// password is `password`
+	const RPC_AUTH: &'static str = "user:cb77f0957de88ff388cf817ddbc7273$9565c5c6ed9bb1f0f0f3207e04b8a36129e92c0569f97ed0293919de56aece06";
+
+	fn test_tiers() -> MethodTiers {
+		let tiers = MethodTiers::default();
+		for &(method, tier) in &[("getblockcount", PermissionTier::Read), ("sendrawtransaction", PermissionTier::Write), ("stop", PermissionTier::Admin)] {
+			tiers.tiers.write().insert(method.to_owned(), tier);
+		}
+		tiers
+	}
+
+	#[test]
+	fn rpc_auth_parse() {
+		let auth: RpcAuth = RPC_AUTH.parse().unwrap();
+		assert_eq!(auth.user, "user");
+		assert_eq!(auth.salt, "cb77f0957de88ff388cf817ddbc7273");
+		assert_eq!(auth.tier, PermissionTier::Admin);
+		assert!(auth.verify("password"));
+		assert!(!auth.verify("Password"));
+		assert_eq!(format!("{}:read", RPC_AUTH).parse::<RpcAuth>().unwrap().tier, PermissionTier::Read);
+		assert!(format!("{}:root", RPC_AUTH).parse::<RpcAuth>().is_err());
+		assert!("user:salt".parse::<RpcAuth>().is_err());
+		assert!("user:salt$01".parse::<RpcAuth>().is_err());
+		assert!(RPC_AUTH.replace("user", COOKIE_USER).parse::<RpcAuth>().is_err());
+	}
+
+	#[test]
+	fn authenticate_users() {
+		let auth = RpcAuth {
+			user: "user".into(),
+			salt: "salt".into(),
+			hash: hmac_sha256(b"salt", b"password"),
+			tier: PermissionTier::Write,
+		};
+		let authenticator = Authenticator::new(Some("cookie".into()), vec![auth]);
+		assert_eq!(authenticator.authenticate("user", "password"), Some(PermissionTier::Write));
+		assert_eq!(authenticator.authenticate("user", "cookie"), None);
+		assert_eq!(authenticator.authenticate(COOKIE_USER, "cookie"), Some(PermissionTier::Admin));
+		assert_eq!(authenticator.authenticate(COOKIE_USER, "password"), None);
+		assert_eq!(authenticator.authenticate("other", "password"), None);
+		assert_eq!(Authenticator::default().authenticate(COOKIE_USER, ""), None);
+	}
+
+	#[test]
+	fn method_tiers() {
+		let tiers = test_tiers();
+		assert_eq!(tiers.tier("getblockcount"), Some(PermissionTier::Read));
+		assert_eq!(tiers.tier("sendrawtransaction"), Some(PermissionTier::Write));
+		assert_eq!(tiers.tier("stop"), Some(PermissionTier::Admin));
+		assert_eq!(tiers.tier("unknownmethod"), None);
+	}
+
+	#[test]
+	fn unauthorized_response_requests_credentials() {
+		let response = unauthorized_response();
+		assert_eq!(response.status(), StatusCode::Unauthorized);
+		assert_eq!(response.headers().get_raw("WWW-Authenticate").and_then(|value| value.one()), Some(&b"Basic realm=\"jsonrpc\""[..]));
+	}
+
+	#[test]
+	fn permissions_check() {
+		let tiers = test_tiers();
+		let request: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"stop","params":[],"id":1}"#).unwrap();
+		assert_eq!(check_permissions(&request, Some(PermissionTier::Admin), &tiers), Ok(()));
+
+		// unknown methods are rejected by the handler
+		let unknown: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"unknownmethod","params":[],"id":1}"#).unwrap();
+		assert_eq!(check_permissions(&unknown, Some(PermissionTier::Read), &tiers), Ok(()));
+
+		let response = check_permissions(&request, Some(PermissionTier::Write), &tiers).unwrap_err().unwrap();
+		assert_eq!(serde_json::to_string(&response).unwrap(),
+			r#"{"jsonrpc":"2.0","error":{"code":-32161,"message":"Method is not permitted for this RPC user","data":"stop"},"id":1}"#);
+
+		let response = check_permissions(&request, None, &tiers).unwrap_err().unwrap();
+		assert_eq!(serde_json::to_string(&response).unwrap(),
+			r#"{"jsonrpc":"2.0","error":{"code":-32160,"message":"Invalid or missing RPC credentials"},"id":1}"#);
+
+		let batch: Request = serde_json::from_str(r#"[{"jsonrpc":"2.0","method":"getblockcount","params":[],"id":1},{"jsonrpc":"2.0","method":"sendrawtransaction","params":[],"id":2}]"#).unwrap();
+		assert_eq!(check_permissions(&batch, Some(PermissionTier::Write), &tiers), Ok(()));
+		match check_permissions(&batch, Some(PermissionTier::Read), &tiers) {
+			Err(Some(Response::Batch(outputs))) => assert_eq!(outputs.len(), 2),
+			_ => panic!("batch must be rejected"),
+		}
+	}
+}
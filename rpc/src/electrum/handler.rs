@@ -0,0 +1,752 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use jsonrpc_core::{Response, Output, Success, Failure, Error, Id, Version, Value};
+use chain::{IndexedTransaction, Transaction, merkle_branch, merkle_root};
+use db::{self, BlockRef, script_hash};
+use miner::INCREMENTAL_RELAY_FEE;
+use primitives::hash::H256;
+use ser::{Reader, deserialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use sync;
+use v1::helpers::errors::{execution, invalid_params, transaction_not_found, block_at_height_not_found};
+use v1::types::Bytes;
+use super::script_hash::{MemoryPoolTransaction, MemoryPoolIndex, ScriptHashState, status};
+use super::server::Client;
+
+/// Name and version of the server, returned by `server.version`.
+const SERVER_VERSION: &'static str = concat!("pbtc ", env!("CARGO_PKG_VERSION"));
+/// Supported version of the Electrum protocol.
+const PROTOCOL_VERSION: &'static str = "1.4";
+/// Maximal number of headers, returned by `blockchain.block.headers`.
+const MAX_HEADERS: u32 = 2016;
+/// Maximal number of script hashes, the single client may subscribe to.
+const MAX_SCRIPT_HASH_SUBSCRIPTIONS: usize = 100_000;
+/// Size (in virtual bytes) of the first fee histogram bin. Every next bin is 10% larger.
+const FEE_HISTOGRAM_BIN_SIZE: f64 = 100_000.0;
+const SATOSHIS_IN_COIN: f64 = 100_000_000.0;
+
+const HEADERS_SUBSCRIBE: &'static str = "blockchain.headers.subscribe";
+const SCRIPT_HASH_SUBSCRIBE: &'static str = "blockchain.scripthash.subscribe";
+
+pub trait ElectrumCoreApi: Send + Sync + 'static {
+	fn memory_pool_transactions(&self) -> Vec<MemoryPoolTransaction>;
+	fn memory_pool_transaction(&self, hash: &H256) -> Option<Transaction>;
+	fn accept_transaction(&self, transaction: Transaction) -> Result<H256, String>;
+	/// Returns fee rate (in satoshis per 1000 virtual bytes), required for transaction to be confirmed within `target` blocks.
+	fn estimate_fee(&self, target: u32) -> Option<u64>;
+}
+
+pub struct ElectrumCore {
+	local_sync_node: sync::LocalNodeRef,
+}
+
+impl ElectrumCore {
+	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
+		ElectrumCore {
+			local_sync_node: local_sync_node,
+		}
+	}
+}
+
+impl ElectrumCoreApi for ElectrumCore {
+	fn memory_pool_transactions(&self) -> Vec<MemoryPoolTransaction> {
+		self.local_sync_node.memory_pool_hashes().into_iter()
+			.filter_map(|hash| self.local_sync_node.memory_pool_entry(&hash).map(|entry| MemoryPoolTransaction {
+				hash: hash,
+				transaction: entry.transaction,
+				fee: entry.miner_fee,
+				size: entry.size,
+				has_unconfirmed_inputs: !entry.depends.is_empty(),
+			}))
+			.collect()
+	}
+
+	fn memory_pool_transaction(&self, hash: &H256) -> Option<Transaction> {
+		self.local_sync_node.memory_pool_transaction(hash)
+	}
+
+	fn accept_transaction(&self, transaction: Transaction) -> Result<H256, String> {
+		self.local_sync_node.accept_transaction(transaction)
+	}
+
+	fn estimate_fee(&self, target: u32) -> Option<u64> {
+		self.local_sync_node.estimate_smart_fee(target, false).map(|estimate| estimate.fee_rate)
+	}
+}
+
+/// Subscriptions of the single client.
+#[derive(Debug)]
+pub struct ClientSubscriptions {
+	headers: AtomicBool,
+	/// Subscribed script hashes and their last known statuses
+	script_hashes: Mutex<HashMap<H256, Option<String>>>,
+}
+
+impl Default for ClientSubscriptions {
+	fn default() -> Self {
+		ClientSubscriptions {
+			headers: AtomicBool::new(false),
+			script_hashes: Mutex::new(HashMap::new()),
+		}
+	}
+}
+
+/// Electrum request. Parameters are always positional.
+#[derive(Debug, Deserialize)]
+struct ElectrumRequest {
+	id: Id,
+	method: String,
+	#[serde(default)]
+	params: Vec<Value>,
+}
+
+/// Notification of the subscribed client.
+#[derive(Debug, Serialize)]
+struct Notification<'a, T> {
+	jsonrpc: &'static str,
+	method: &'a str,
+	params: T,
+}
+
+/// Block header, returned by `blockchain.headers.subscribe`.
+#[derive(Debug, Serialize, PartialEq)]
+struct HeaderNotification {
+	height: u32,
+	hex: Bytes,
+}
+
+/// Block header with the proof of its inclusion to the merkle tree of the headers up to the checkpoint.
+#[derive(Debug, Serialize, PartialEq)]
+struct HeaderProof {
+	branch: Vec<String>,
+	header: Bytes,
+	root: String,
+}
+
+/// Server description, returned by `server.features`.
+#[derive(Debug, Serialize, PartialEq)]
+struct ServerFeatures {
+	genesis_hash: String,
+	/// Server doesn't know its public addresses, so no hosts are announced
+	hosts: HashMap<String, Value>,
+	protocol_max: &'static str,
+	protocol_min: &'static str,
+	/// Address index can't be built for the pruned database, so history is never pruned
+	pruning: Option<u32>,
+	server_version: &'static str,
+	hash_function: &'static str,
+}
+
+/// Consecutive block headers, returned by `blockchain.block.headers`.
+#[derive(Debug, Serialize, PartialEq)]
+struct HeadersChunk {
+	count: u32,
+	hex: Bytes,
+	max: u32,
+	/// Proof of the last header inclusion, if checkpoint is requested
+	#[serde(skip_serializing_if = "Option::is_none")]
+	branch: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	root: Option<String>,
+}
+
+/// Proof of the transaction inclusion to the block.
+#[derive(Debug, Serialize, PartialEq)]
+struct TransactionProof {
+	block_height: u32,
+	merkle: Vec<String>,
+	pos: usize,
+}
+
+/// Handles Electrum requests, reading data from the database and the memory pool, and notifies subscribed clients.
+pub struct ElectrumHandler<T: ElectrumCoreApi> {
+	storage: db::SharedStore,
+	core: T,
+	clients: Mutex<HashMap<usize, Arc<Client>>>,
+	next_client_id: AtomicUsize,
+}
+
+impl<T> ElectrumHandler<T> where T: ElectrumCoreApi {
+	pub fn new(storage: db::SharedStore, core: T) -> Self {
+		ElectrumHandler {
+			storage: storage,
+			core: core,
+			clients: Mutex::new(HashMap::new()),
+			next_client_id: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns response to the single request or the batch of requests, received from the client with given subscriptions.
+	pub fn handle(&self, subscriptions: &ClientSubscriptions, request: &str) -> String {
+		let response = match serde_json::from_str::<Value>(request) {
+			Ok(Value::Array(ref requests)) if requests.is_empty() => Response::Single(failure(Id::Null, Error::invalid_request())),
+			Ok(Value::Array(requests)) => Response::Batch(requests.into_iter()
+				.map(|request| self.handle_request(subscriptions, request))
+				.collect()),
+			Ok(request) => Response::Single(self.handle_request(subscriptions, request)),
+			Err(_) => Response::Single(failure(Id::Null, Error::parse_error())),
+		};
+
+		serde_json::to_string(&response).expect("Electrum responses are always serializable; qed")
+	}
+
+	fn handle_request(&self, subscriptions: &ClientSubscriptions, request: Value) -> Output {
+		let request: ElectrumRequest = match serde_json::from_value(request) {
+			Ok(request) => request,
+			Err(_) => return failure(Id::Null, Error::invalid_request()),
+		};
+
+		match self.call(subscriptions, &request.method, &request.params) {
+			Ok(result) => Output::Success(Success {
+				jsonrpc: Some(Version::V2),
+				result: result,
+				id: request.id,
+			}),
+			Err(error) => failure(request.id, error),
+		}
+	}
+
+	fn call(&self, subscriptions: &ClientSubscriptions, method: &str, params: &[Value]) -> Result<Value, Error> {
+		match method {
+			"server.version" => to_value(&[SERVER_VERSION, PROTOCOL_VERSION]),
+			"server.banner" => to_value(&SERVER_VERSION),
+			"server.donation_address" => to_value(&""),
+			"server.features" => to_value(&ServerFeatures {
+				genesis_hash: self.storage.block_hash(0).ok_or_else(|| block_at_height_not_found(0))?.to_reversed_str(),
+				hosts: HashMap::new(),
+				protocol_max: PROTOCOL_VERSION,
+				protocol_min: PROTOCOL_VERSION,
+				pruning: None,
+				server_version: SERVER_VERSION,
+				hash_function: "sha256",
+			}),
+			"server.peers.subscribe" => to_value(&Vec::<Value>::new()),
+			"server.ping" => Ok(Value::Null),
+			"blockchain.headers.subscribe" => {
+				subscriptions.headers.store(true, Ordering::SeqCst);
+				let best_block = self.storage.best_block();
+				to_value(&self.header(best_block.number)?)
+			},
+			"blockchain.block.header" => self.block_header(param(params, 0, "height")?, optional_param(params, 1, "cp_height")?.unwrap_or(0)),
+			"blockchain.block.headers" => self.block_headers(param(params, 0, "start_height")?, param(params, 1, "count")?,
+				optional_param(params, 2, "cp_height")?.unwrap_or(0)),
+			"blockchain.estimatefee" => match self.core.estimate_fee(param(params, 0, "number")?) {
+				Some(fee_rate) => to_value(&(fee_rate as f64 / SATOSHIS_IN_COIN)),
+				None => to_value(&-1),
+			},
+			"blockchain.relayfee" => to_value(&(INCREMENTAL_RELAY_FEE as f64 / SATOSHIS_IN_COIN)),
+			"blockchain.scripthash.get_balance" => {
+				let memory_pool = self.memory_pool_index();
+				to_value(&self.script_hash_state(&script_hash_param(params)?, &memory_pool)?.balance())
+			},
+			"blockchain.scripthash.get_history" => {
+				let memory_pool = self.memory_pool_index();
+				to_value(&self.script_hash_state(&script_hash_param(params)?, &memory_pool)?.history())
+			},
+			"blockchain.scripthash.get_mempool" => {
+				let memory_pool = self.memory_pool_index();
+				to_value(&self.script_hash_state(&script_hash_param(params)?, &memory_pool)?.memory_pool_history())
+			},
+			"blockchain.scripthash.listunspent" => {
+				let memory_pool = self.memory_pool_index();
+				to_value(&self.script_hash_state(&script_hash_param(params)?, &memory_pool)?.unspent())
+			},
+			"blockchain.scripthash.subscribe" => self.subscribe_script_hash(subscriptions, script_hash_param(params)?),
+			"blockchain.scripthash.unsubscribe" => {
+				let script_hash = script_hash_param(params)?;
+				Ok(Value::Bool(subscriptions.script_hashes.lock().remove(&script_hash).is_some()))
+			},
+			"blockchain.transaction.broadcast" => {
+				let raw_transaction: Bytes = param(params, 0, "raw_tx")?;
+				let transaction: Transaction = deserialize(Reader::new(&raw_transaction.0)).map_err(|err| invalid_params("raw_tx", err))?;
+				let hash = self.core.accept_transaction(transaction).map_err(execution)?;
+				to_value(&hash.to_reversed_str())
+			},
+			"blockchain.transaction.get" => self.transaction(hash_param(params, 0, "tx_hash")?, optional_param(params, 1, "verbose")?.unwrap_or(false)),
+			"blockchain.transaction.get_merkle" => self.transaction_proof(hash_param(params, 0, "tx_hash")?, param(params, 1, "height")?),
+			"mempool.get_fee_histogram" => {
+				let transactions = self.core.memory_pool_transactions().into_iter()
+					.map(|transaction| (transaction.fee, transaction.size))
+					.collect();
+				to_value(&fee_histogram(transactions))
+			},
+			_ => Err(Error::method_not_found()),
+		}
+	}
+
+	fn header(&self, height: u32) -> Result<HeaderNotification, Error> {
+		let header = self.storage.block_header_bytes(BlockRef::Number(height))
+			.ok_or_else(|| block_at_height_not_found(height))?;
+		Ok(HeaderNotification {
+			height: height,
+			hex: Bytes::new(header.take()),
+		})
+	}
+
+	fn block_header(&self, height: u32, cp_height: u32) -> Result<Value, Error> {
+		let header = self.header(height)?.hex;
+		if cp_height == 0 {
+			return to_value(&header);
+		}
+
+		let (branch, root) = self.header_proof(height, cp_height)?;
+		to_value(&HeaderProof {
+			branch: branch,
+			header: header,
+			root: root,
+		})
+	}
+
+	fn block_headers(&self, start_height: u32, count: u32, cp_height: u32) -> Result<Value, Error> {
+		let mut headers = Vec::new();
+		let mut returned = 0;
+		for height in start_height..start_height.saturating_add(cmp::min(count, MAX_HEADERS)) {
+			match self.storage.block_header_bytes(BlockRef::Number(height)) {
+				Some(header) => headers.extend_from_slice(&header),
+				None => break,
+			}
+			returned += 1;
+		}
+
+		let (branch, root) = match (cp_height, returned) {
+			(0, _) | (_, 0) => (None, None),
+			_ => {
+				let (branch, root) = self.header_proof(start_height + returned - 1, cp_height)?;
+				(Some(branch), Some(root))
+			},
+		};
+
+		to_value(&HeadersChunk {
+			count: returned,
+			hex: Bytes::new(headers),
+			max: MAX_HEADERS,
+			branch: branch,
+			root: root,
+		})
+	}
+
+	/// Returns merkle branch of the header at `height` and merkle root of all headers up to `cp_height`.
+	fn header_proof(&self, height: u32, cp_height: u32) -> Result<(Vec<String>, String), Error> {
+		if height > cp_height || cp_height > self.storage.best_block().number {
+			return Err(invalid_params("cp_height", format!("Checkpoint {} is above the best block or below the header {}", cp_height, height)));
+		}
+
+		let mut hashes = Vec::with_capacity(cp_height as usize + 1);
+		for number in 0..cp_height + 1 {
+			hashes.push(self.storage.block_hash(number).ok_or_else(|| block_at_height_not_found(number))?);
+		}
+
+		let branch = merkle_branch(&hashes, height as usize).into_iter().map(|hash| hash.to_reversed_str()).collect();
+		Ok((branch, merkle_root(&hashes).to_reversed_str()))
+	}
+
+	fn transaction(&self, hash: H256, verbose: bool) -> Result<Value, Error> {
+		if verbose {
+			return Err(invalid_params("verbose", "Verbose transactions are not supported"));
+		}
+
+		let transaction = self.core.memory_pool_transaction(&hash)
+			.or_else(|| self.storage.transaction(&hash))
+			.ok_or_else(|| transaction_not_found(hash.reversed()))?;
+		to_value(&Bytes::new(serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).take()))
+	}
+
+	fn transaction_proof(&self, hash: H256, height: u32) -> Result<Value, Error> {
+		let hashes = self.storage.block_transaction_hashes(BlockRef::Number(height));
+		let position = hashes.iter().position(|transaction_hash| *transaction_hash == hash)
+			.ok_or_else(|| transaction_not_found(hash.reversed()))?;
+		to_value(&TransactionProof {
+			block_height: height,
+			merkle: merkle_branch(&hashes, position).into_iter().map(|hash| hash.to_reversed_str()).collect(),
+			pos: position,
+		})
+	}
+
+	fn memory_pool_index(&self) -> MemoryPoolIndex {
+		MemoryPoolIndex::new(self.core.memory_pool_transactions())
+	}
+
+	fn script_hash_state<'a>(&self, script_hash: &H256, memory_pool: &'a MemoryPoolIndex) -> Result<ScriptHashState<'a>, Error> {
+		if !self.storage.is_address_index_enabled() {
+			return Err(execution("Address index is not enabled"));
+		}
+
+		Ok(ScriptHashState::new(script_hash, self.storage.address_outputs(script_hash), memory_pool))
+	}
+
+	fn subscribe_script_hash(&self, subscriptions: &ClientSubscriptions, script_hash: H256) -> Result<Value, Error> {
+		let memory_pool = self.memory_pool_index();
+		let status = status(&self.script_hash_state(&script_hash, &memory_pool)?.history());
+		let mut script_hashes = subscriptions.script_hashes.lock();
+		if !script_hashes.contains_key(&script_hash) && script_hashes.len() >= MAX_SCRIPT_HASH_SUBSCRIPTIONS {
+			return Err(execution("Too many script hash subscriptions"));
+		}
+
+		script_hashes.insert(script_hash, status.clone());
+		to_value(&status)
+	}
+
+	pub fn add_client(&self, client: Arc<Client>) -> usize {
+		let id = self.next_client_id.fetch_add(1, Ordering::SeqCst);
+		self.clients.lock().insert(id, client);
+		id
+	}
+
+	pub fn remove_client(&self, id: usize) {
+		self.clients.lock().remove(&id);
+	}
+
+	fn clients(&self) -> Vec<(usize, Arc<Client>)> {
+		self.clients.lock().iter().map(|(id, client)| (*id, client.clone())).collect()
+	}
+
+	/// Notifies clients about the new best block. Statuses of all subscribed script hashes are updated.
+	pub fn notify_block(&self, hash: &H256) {
+		let clients = self.clients();
+		match self.storage.block_number(hash).map(|number| self.header(number)) {
+			Some(Ok(header)) => {
+				let notification = notification(HEADERS_SUBSCRIBE, [header]);
+				for &(id, ref client) in clients.iter().filter(|&&(_, ref client)| client.subscriptions.headers.load(Ordering::SeqCst)) {
+					self.send(id, client, &notification);
+				}
+			},
+			_ => warn!(target: "rpc", "Block {} is not found in the storage", hash.to_reversed_str()),
+		}
+
+		self.notify_script_hashes(clients, None);
+	}
+
+	/// Notifies clients about changed statuses of script hashes, affected by the memory pool transaction.
+	pub fn notify_transaction(&self, transaction: &IndexedTransaction) {
+		let clients: Vec<_> = self.clients().into_iter()
+			.filter(|&(_, ref client)| !client.subscriptions.script_hashes.lock().is_empty())
+			.collect();
+		if clients.is_empty() {
+			return;
+		}
+
+		let affected = self.affected_script_hashes(&transaction.raw);
+		self.notify_script_hashes(clients, Some(&affected));
+	}
+
+	/// Returns script hashes of the transaction outputs and of the outputs, it spends.
+	fn affected_script_hashes(&self, transaction: &Transaction) -> HashSet<H256> {
+		let mut script_hashes: HashSet<H256> = transaction.outputs.iter()
+			.map(|output| script_hash(&output.script_pubkey))
+			.collect();
+		for input in &transaction.inputs {
+			let previous_output = &input.previous_output;
+			let output = self.storage.as_transaction_output_provider().transaction_output(previous_output, usize::max_value())
+				.or_else(|| self.core.memory_pool_transaction(&previous_output.hash)
+					.and_then(|transaction| transaction.outputs.into_iter().nth(previous_output.index as usize)));
+			if let Some(output) = output {
+				script_hashes.insert(script_hash(&output.script_pubkey));
+			}
+		}
+		script_hashes
+	}
+
+	/// Sends notifications about changed statuses of subscribed script hashes. If `affected` is given,
+	/// statuses of other script hashes are not checked.
+	fn notify_script_hashes(&self, clients: Vec<(usize, Arc<Client>)>, affected: Option<&HashSet<H256>>) {
+		// memory pool is indexed once, when status of the first script hash is computed
+		let mut memory_pool = None;
+		for (id, client) in clients {
+			let mut notifications = Vec::new();
+			{
+				let mut script_hashes = client.subscriptions.script_hashes.lock();
+				for (script_hash, last_status) in script_hashes.iter_mut() {
+					if affected.map_or(false, |affected| !affected.contains(script_hash)) {
+						continue;
+					}
+
+					if memory_pool.is_none() {
+						memory_pool = Some(self.memory_pool_index());
+					}
+					let memory_pool = memory_pool.as_ref().expect("memory pool is indexed above; qed");
+					let state = ScriptHashState::new(script_hash, self.storage.address_outputs(script_hash), memory_pool);
+					let status = status(&state.history());
+					if status != *last_status {
+						*last_status = status.clone();
+						notifications.push(notification(SCRIPT_HASH_SUBSCRIBE, (script_hash.to_reversed_str(), status)));
+					}
+				}
+			}
+
+			for notification in notifications {
+				if !self.send(id, &client, &notification) {
+					break;
+				}
+			}
+		}
+	}
+
+	/// Sends notification to the client. Client, which fails to receive it, is disconnected.
+	fn send(&self, id: usize, client: &Client, notification: &str) -> bool {
+		match client.send(notification) {
+			Ok(_) => true,
+			Err(err) => {
+				debug!(target: "rpc", "Disconnecting Electrum client, which failed to receive notification: {}", err);
+				self.remove_client(id);
+				client.disconnect();
+				false
+			},
+		}
+	}
+}
+
+fn failure(id: Id, error: Error) -> Output {
+	Output::Failure(Failure {
+		jsonrpc: Some(Version::V2),
+		error: error,
+		id: id,
+	})
+}
+
+fn to_value<S>(value: &S) -> Result<Value, Error> where S: Serialize {
+	Ok(serde_json::to_value(value).expect("Electrum responses are always serializable; qed"))
+}
+
+fn notification<P>(method: &str, params: P) -> String where P: Serialize {
+	let notification = Notification {
+		jsonrpc: "2.0",
+		method: method,
+		params: params,
+	};
+	serde_json::to_string(&notification).expect("Electrum notifications are always serializable; qed")
+}
+
+/// Returns positional parameter of the request.
+fn param<P>(params: &[Value], index: usize, name: &str) -> Result<P, Error> where P: DeserializeOwned {
+	match params.get(index) {
+		Some(value) => serde_json::from_value(value.clone()).map_err(|err| invalid_params(name, err)),
+		None => Err(invalid_params(name, "Missing parameter")),
+	}
+}
+
+fn optional_param<P>(params: &[Value], index: usize, name: &str) -> Result<Option<P>, Error> where P: DeserializeOwned {
+	match params.get(index) {
+		None | Some(&Value::Null) => Ok(None),
+		Some(_) => param(params, index, name).map(Some),
+	}
+}
+
+/// Parses reversed hex hash parameter.
+fn hash_param(params: &[Value], index: usize, name: &str) -> Result<H256, Error> {
+	let hash: String = param(params, index, name)?;
+	hash.parse::<H256>()
+		.map(|hash| hash.reversed())
+		.map_err(|_| invalid_params(name, format!("Invalid hash: {}", hash)))
+}
+
+/// Script hash is sha256 of the output script, sent as the reversed hex.
+fn script_hash_param(params: &[Value]) -> Result<H256, Error> {
+	hash_param(params, 0, "scripthash")
+}
+
+/// Returns `[fee rate, virtual size]` pairs of the memory pool, sorted by decreasing fee rate (in satoshis
+/// per virtual byte). Every pair is the total size of transactions, paying at least the fee rate, but
+/// less than the fee rate of the previous pair.
+fn fee_histogram(mut transactions: Vec<(i64, usize)>) -> Vec<(f64, usize)> {
+	let fee_rate = |fee: i64, size: usize| fee as f64 / cmp::max(size, 1) as f64;
+	transactions.sort_by(|&(fee1, size1), &(fee2, size2)| fee_rate(fee2, size2).partial_cmp(&fee_rate(fee1, size1)).unwrap_or(cmp::Ordering::Equal));
+
+	let mut histogram = Vec::new();
+	let mut bin_size = FEE_HISTOGRAM_BIN_SIZE;
+	let mut size = 0;
+	let mut last_fee_rate = 0f64;
+	for (fee, transaction_size) in transactions {
+		last_fee_rate = fee_rate(fee, transaction_size);
+		size += transaction_size;
+		if size as f64 > bin_size {
+			histogram.push((last_fee_rate, size));
+			size = 0;
+			bin_size *= 1.1;
+		}
+	}
+
+	if size > 0 {
+		histogram.push((last_fee_rate, size));
+	}
+	histogram
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
+	use serde_json;
+	use chain::{Transaction, merkle_root};
+	use db::{self, BlockChainDatabase};
+	use primitives::hash::H256;
+	use ser::serialize;
+	use hex::ToHex;
+	use super::super::script_hash::MemoryPoolTransaction;
+	use super::{ElectrumHandler, ElectrumCoreApi, ClientSubscriptions, fee_histogram};
+
+	#[derive(Default)]
+	struct TestElectrumCore;
+
+	impl ElectrumCoreApi for TestElectrumCore {
+		fn memory_pool_transactions(&self) -> Vec<MemoryPoolTransaction> {
+			Vec::new()
+		}
+
+		fn memory_pool_transaction(&self, _hash: &H256) -> Option<Transaction> {
+			None
+		}
+
+		fn accept_transaction(&self, transaction: Transaction) -> Result<H256, String> {
+			Ok(transaction.hash())
+		}
+
+		fn estimate_fee(&self, target: u32) -> Option<u64> {
+			if target <= 2 { Some(2000) } else { None }
+		}
+	}
+
+	fn handler(address_index: bool) -> ElectrumHandler<TestElectrumCore> {
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), test_data::block_h1().into(), test_data::block_h2().into()])
+			.with_address_index(address_index);
+		if address_index {
+			storage.build_address_index().unwrap();
+		}
+		ElectrumHandler::new(Arc::new(storage), TestElectrumCore::default())
+	}
+
+	fn call(handler: &ElectrumHandler<TestElectrumCore>, subscriptions: &ClientSubscriptions, method: &str, params: &str) -> serde_json::Value {
+		let request = format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params);
+		serde_json::from_str(&handler.handle(subscriptions, &request)).unwrap()
+	}
+
+	/// Electrum script hash of the block 1 coinbase output.
+	fn coinbase_script_hash() -> String {
+		db::script_hash(&test_data::block_h1().transactions[0].outputs[0].script_pubkey).to_reversed_str()
+	}
+
+	#[test]
+	fn electrum_server_methods() {
+		let handler = handler(false);
+		let subscriptions = ClientSubscriptions::default();
+
+		assert_eq!(call(&handler, &subscriptions, "server.version", r#"["Electrum 4.0", "1.4"]"#)["result"][1], "1.4");
+		assert_eq!(call(&handler, &subscriptions, "server.ping", "[]")["result"], serde_json::Value::Null);
+		let features = call(&handler, &subscriptions, "server.features", "[]");
+		assert_eq!(features["result"]["genesis_hash"], test_data::genesis().hash().to_reversed_str());
+		assert_eq!(features["result"]["protocol_max"], "1.4");
+		assert_eq!(features["result"]["hash_function"], "sha256");
+		assert_eq!(call(&handler, &subscriptions, "blockchain.relayfee", "[]")["result"], 0.00001);
+		assert_eq!(call(&handler, &subscriptions, "blockchain.estimatefee", "[2]")["result"], 0.00002);
+		assert_eq!(call(&handler, &subscriptions, "blockchain.estimatefee", "[10]")["result"], -1);
+		assert_eq!(call(&handler, &subscriptions, "blockchain.estimatefee", "[]")["error"]["code"], -32602);
+		assert_eq!(call(&handler, &subscriptions, "getbestblockhash", "[]")["error"]["code"], -32601);
+
+		let response: serde_json::Value = serde_json::from_str(&handler.handle(&subscriptions, "{")).unwrap();
+		assert_eq!(response["error"]["code"], -32700);
+
+		let response: serde_json::Value = serde_json::from_str(&handler.handle(&subscriptions, r#"[
+			{"jsonrpc": "2.0", "method": "server.ping", "params": [], "id": 1},
+			{"jsonrpc": "2.0", "method": "server.banner", "params": [], "id": 2}
+		]"#)).unwrap();
+		assert_eq!(response[1]["id"], 2);
+	}
+
+	#[test]
+	fn electrum_headers() {
+		let handler = handler(false);
+		let subscriptions = ClientSubscriptions::default();
+
+		let response = call(&handler, &subscriptions, "blockchain.headers.subscribe", "[]");
+		assert_eq!(response["result"]["height"], 2);
+		assert_eq!(response["result"]["hex"], serialize(&test_data::block_h2().block_header).to_hex());
+		assert!(subscriptions.headers.load(::std::sync::atomic::Ordering::SeqCst));
+
+		let response = call(&handler, &subscriptions, "blockchain.block.header", "[1]");
+		assert_eq!(response["result"], serialize(&test_data::block_h1().block_header).to_hex());
+
+		let hashes: Vec<H256> = vec![test_data::genesis().hash(), test_data::block_h1().hash(), test_data::block_h2().hash()];
+		let response = call(&handler, &subscriptions, "blockchain.block.header", "[1, 2]");
+		assert_eq!(response["result"]["root"], merkle_root(&hashes).to_reversed_str());
+		assert_eq!(response["result"]["branch"][0], hashes[0].to_reversed_str());
+		assert_eq!(call(&handler, &subscriptions, "blockchain.block.header", "[1, 3]")["error"]["code"], -32602);
+
+		// only headers up to the best block are returned
+		let response = call(&handler, &subscriptions, "blockchain.block.headers", "[1, 5]");
+		assert_eq!(response["result"]["count"], 2);
+		assert_eq!(response["result"]["hex"].as_str().unwrap().len(), 320);
+		assert_eq!(response["result"]["max"], 2016);
+		assert_eq!(response["result"]["root"], serde_json::Value::Null);
+	}
+
+	#[test]
+	fn electrum_script_hash() {
+		let handler = handler(true);
+		let subscriptions = ClientSubscriptions::default();
+		let params = format!(r#"["{}"]"#, coinbase_script_hash());
+
+		let response = call(&handler, &subscriptions, "blockchain.scripthash.get_balance", &params);
+		assert_eq!(response["result"]["confirmed"], 5000000000u64);
+		assert_eq!(response["result"]["unconfirmed"], 0);
+
+		let response = call(&handler, &subscriptions, "blockchain.scripthash.get_history", &params);
+		assert_eq!(response["result"][0]["tx_hash"], test_data::block_h1().transactions[0].hash().to_reversed_str());
+		assert_eq!(response["result"][0]["height"], 1);
+
+		let response = call(&handler, &subscriptions, "blockchain.scripthash.listunspent", &params);
+		assert_eq!(response["result"][0]["tx_pos"], 0);
+		assert_eq!(response["result"][0]["value"], 5000000000u64);
+
+		let response = call(&handler, &subscriptions, "blockchain.scripthash.subscribe", &params);
+		assert_eq!(response["result"].as_str().unwrap().len(), 64);
+		assert_eq!(subscriptions.script_hashes.lock().len(), 1);
+		assert_eq!(call(&handler, &subscriptions, "blockchain.scripthash.unsubscribe", &params)["result"], true);
+		assert!(subscriptions.script_hashes.lock().is_empty());
+
+		let unknown = format!(r#"["{}"]"#, H256::from(1).to_reversed_str());
+		assert_eq!(call(&handler, &subscriptions, "blockchain.scripthash.subscribe", &unknown)["result"], serde_json::Value::Null);
+		assert_eq!(call(&handler, &subscriptions, "blockchain.scripthash.get_history", r#"["00"]"#)["error"]["code"], -32602);
+
+		let handler = self::handler(false);
+		assert_eq!(call(&handler, &subscriptions, "blockchain.scripthash.get_balance", &params)["error"]["code"], -32015);
+	}
+
+	#[test]
+	fn electrum_transactions() {
+		let handler = handler(false);
+		let subscriptions = ClientSubscriptions::default();
+		let transaction = test_data::block_h1().transactions[0].clone();
+		let hash = transaction.hash().to_reversed_str();
+
+		let response = call(&handler, &subscriptions, "blockchain.transaction.get", &format!(r#"["{}"]"#, hash));
+		assert_eq!(response["result"], serialize(&transaction).to_hex());
+		let response = call(&handler, &subscriptions, "blockchain.transaction.get", &format!(r#"["{}", true]"#, hash));
+		assert_eq!(response["error"]["code"], -32602);
+		let response = call(&handler, &subscriptions, "blockchain.transaction.get", &format!(r#"["{}"]"#, H256::from(1).to_reversed_str()));
+		assert_eq!(response["error"]["code"], -32096);
+
+		let response = call(&handler, &subscriptions, "blockchain.transaction.get_merkle", &format!(r#"["{}", 1]"#, hash));
+		assert_eq!(response["result"]["block_height"], 1);
+		assert_eq!(response["result"]["pos"], 0);
+		assert_eq!(response["result"]["merkle"], serde_json::Value::Array(Vec::new()));
+		let response = call(&handler, &subscriptions, "blockchain.transaction.get_merkle", &format!(r#"["{}", 2]"#, hash));
+		assert_eq!(response["error"]["code"], -32096);
+
+		let response = call(&handler, &subscriptions, "blockchain.transaction.broadcast", &format!(r#"["{}"]"#, serialize(&transaction).to_hex()));
+		assert_eq!(response["result"], transaction.hash().to_reversed_str());
+		assert_eq!(call(&handler, &subscriptions, "blockchain.transaction.broadcast", r#"["00"]"#)["error"]["code"], -32602);
+	}
+
+	#[test]
+	fn electrum_fee_histogram() {
+		assert!(fee_histogram(Vec::new()).is_empty());
+		// fee rates are 10, 1 and 5 satoshis per byte
+		let histogram = fee_histogram(vec![(600_000, 60_000), (50_000, 50_000), (250_000, 50_000)]);
+		assert_eq!(histogram, vec![(5.0, 110_000), (1.0, 50_000)]);
+	}
+}
@@ -0,0 +1,20 @@
+//! Electrum protocol server, so that Electrum wallets can use the node directly.
+//!
+//! Clients connect over plain TCP and send newline-delimited JSON-RPC 2.0 requests. Supported methods:
+//! - `server.version`, `server.banner`, `server.donation_address`, `server.peers.subscribe`, `server.ping`;
+//! - `blockchain.headers.subscribe`, `blockchain.block.header`, `blockchain.block.headers`;
+//! - `blockchain.estimatefee`, `blockchain.relayfee`, `mempool.get_fee_histogram`;
+//! - `blockchain.scripthash.get_balance`, `blockchain.scripthash.get_history`, `blockchain.scripthash.get_mempool`,
+//!   `blockchain.scripthash.listunspent`, `blockchain.scripthash.subscribe`, `blockchain.scripthash.unsubscribe`;
+//! - `blockchain.transaction.broadcast`, `blockchain.transaction.get`, `blockchain.transaction.get_merkle`.
+//!
+//! Script hash methods are served from the address index, which must be enabled. Notifications are not
+//! sent while the node is synchronizing.
+
+mod handler;
+mod script_hash;
+mod server;
+
+pub use self::handler::{ElectrumHandler, ElectrumCore, ElectrumCoreApi};
+pub use self::script_hash::MemoryPoolTransaction;
+pub use self::server::{ElectrumServer, ElectrumNotifier};
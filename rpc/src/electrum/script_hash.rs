@@ -0,0 +1,337 @@
+//! Balance, history and unspent outputs of the script hash, combined from the address index and the memory pool.
+
+use std::collections::HashMap;
+use chain::{OutPoint, Transaction};
+use crypto::sha256;
+use db::{self, is_indexed_script, script_hash};
+use hex::ToHex;
+use primitives::hash::H256;
+
+/// Memory pool transaction, as seen by the Electrum server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPoolTransaction {
+	/// Hash of the transaction
+	pub hash: H256,
+	/// The transaction
+	pub transaction: Transaction,
+	/// Fee, paid by the transaction
+	pub fee: i64,
+	/// Virtual size of the transaction
+	pub size: usize,
+	/// Does the transaction spend outputs of other memory pool transactions?
+	pub has_unconfirmed_inputs: bool,
+}
+
+/// Memory pool transactions, indexed by script hashes of their outputs and by outputs, they spend.
+pub struct MemoryPoolIndex {
+	transactions: HashMap<H256, MemoryPoolTransaction>,
+	/// Outputs of memory pool transactions and their values by script hash
+	outputs: HashMap<H256, Vec<(OutPoint, u64)>>,
+	/// Memory pool transactions by outputs, they spend
+	spends: HashMap<OutPoint, H256>,
+}
+
+impl MemoryPoolIndex {
+	pub fn new(transactions: Vec<MemoryPoolTransaction>) -> Self {
+		let mut outputs = HashMap::new();
+		let mut spends = HashMap::new();
+		for transaction in &transactions {
+			for (index, output) in transaction.transaction.outputs.iter().enumerate() {
+				if is_indexed_script(&output.script_pubkey) {
+					let outpoint = OutPoint {
+						hash: transaction.hash.clone(),
+						index: index as u32,
+					};
+					outputs.entry(script_hash(&output.script_pubkey)).or_insert_with(Vec::new).push((outpoint, output.value));
+				}
+			}
+
+			for input in &transaction.transaction.inputs {
+				spends.insert(input.previous_output.clone(), transaction.hash.clone());
+			}
+		}
+
+		MemoryPoolIndex {
+			transactions: transactions.into_iter().map(|transaction| (transaction.hash.clone(), transaction)).collect(),
+			outputs: outputs,
+			spends: spends,
+		}
+	}
+
+	/// Returns all memory pool transactions.
+	pub fn transactions(&self) -> Vec<&MemoryPoolTransaction> {
+		self.transactions.values().collect()
+	}
+}
+
+/// Transaction of the script hash history.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HistoryItem {
+	/// Hash of the transaction
+	pub tx_hash: String,
+	/// Height of the block, which includes the transaction. 0 for memory pool transactions and -1 for
+	/// memory pool transactions, spending outputs of other memory pool transactions
+	pub height: i64,
+	/// Fee of the memory pool transaction
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<i64>,
+}
+
+/// Balance of the script hash.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Balance {
+	/// Value of the canon chain unspent outputs
+	pub confirmed: u64,
+	/// Change of the balance by memory pool transactions
+	pub unconfirmed: i64,
+}
+
+/// Unspent output of the script hash.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct UnspentItem {
+	/// Hash of the transaction
+	pub tx_hash: String,
+	/// Index of the output in the transaction
+	pub tx_pos: u32,
+	/// Height of the block, which includes the transaction. 0 for memory pool transactions
+	pub height: u32,
+	/// Value of the output
+	pub value: u64,
+}
+
+/// Outputs and transactions of the single script hash.
+pub struct ScriptHashState<'a> {
+	/// Canon chain outputs, paying to the script
+	confirmed: Vec<db::AddressOutput>,
+	/// Memory pool outputs, paying to the script
+	unconfirmed: &'a [(OutPoint, u64)],
+	memory_pool: &'a MemoryPoolIndex,
+}
+
+impl<'a> ScriptHashState<'a> {
+	pub fn new(script_hash: &H256, confirmed: Vec<db::AddressOutput>, memory_pool: &'a MemoryPoolIndex) -> Self {
+		ScriptHashState {
+			confirmed: confirmed,
+			unconfirmed: memory_pool.outputs.get(script_hash).map(Vec::as_slice).unwrap_or(&[]),
+			memory_pool: memory_pool,
+		}
+	}
+
+	/// Canon chain outputs, which are not spent by canon chain transactions, along with their outpoints.
+	fn confirmed_unspent(&self) -> Vec<(OutPoint, &db::AddressOutput)> {
+		self.confirmed.iter()
+			.filter(|output| output.spent_by.is_none())
+			.map(|output| (OutPoint { hash: output.transaction_hash.clone(), index: output.index }, output))
+			.collect()
+	}
+
+	/// Confirmed transactions, sorted by height, followed by memory pool transactions.
+	pub fn history(&self) -> Vec<HistoryItem> {
+		let mut confirmed: Vec<(u32, &H256)> = self.confirmed.iter()
+			.flat_map(|output| {
+				let spend = output.spent_by.as_ref().map(|spend| (spend.height, &spend.transaction_hash));
+				Some((output.height, &output.transaction_hash)).into_iter().chain(spend)
+			})
+			.collect();
+		confirmed.sort();
+		confirmed.dedup();
+
+		let mut history: Vec<HistoryItem> = confirmed.into_iter()
+			.map(|(height, hash)| HistoryItem {
+				tx_hash: hash.to_reversed_str(),
+				height: height as i64,
+				fee: None,
+			})
+			.collect();
+		history.extend(self.memory_pool_history());
+		history
+	}
+
+	/// Memory pool transactions, paying to the script or spending its outputs.
+	pub fn memory_pool_history(&self) -> Vec<HistoryItem> {
+		let spends = &self.memory_pool.spends;
+		let confirmed_unspent = self.confirmed_unspent();
+		let mut hashes: Vec<&H256> = self.unconfirmed.iter()
+			.map(|&(ref outpoint, _)| &outpoint.hash)
+			.chain(confirmed_unspent.iter().filter_map(|&(ref outpoint, _)| spends.get(outpoint)))
+			.chain(self.unconfirmed.iter().filter_map(|&(ref outpoint, _)| spends.get(outpoint)))
+			.collect();
+		hashes.sort();
+		hashes.dedup();
+
+		hashes.into_iter()
+			.filter_map(|hash| self.memory_pool.transactions.get(hash))
+			.map(|transaction| HistoryItem {
+				tx_hash: transaction.hash.to_reversed_str(),
+				height: if transaction.has_unconfirmed_inputs { -1 } else { 0 },
+				fee: Some(transaction.fee),
+			})
+			.collect()
+	}
+
+	pub fn balance(&self) -> Balance {
+		let spends = &self.memory_pool.spends;
+		let confirmed_unspent = self.confirmed_unspent();
+		let received: u64 = self.unconfirmed.iter().map(|&(_, value)| value).sum();
+		let spent: u64 = confirmed_unspent.iter()
+			.filter(|&&(ref outpoint, _)| spends.contains_key(outpoint))
+			.map(|&(_, output)| output.value)
+			.chain(self.unconfirmed.iter()
+				.filter(|&&(ref outpoint, _)| spends.contains_key(outpoint))
+				.map(|&(_, value)| value))
+			.sum();
+
+		Balance {
+			confirmed: confirmed_unspent.iter().map(|&(_, output)| output.value).sum(),
+			unconfirmed: received as i64 - spent as i64,
+		}
+	}
+
+	/// Outputs, which are not spent by neither canon chain, nor memory pool transactions.
+	pub fn unspent(&self) -> Vec<UnspentItem> {
+		let spends = &self.memory_pool.spends;
+		let mut confirmed: Vec<(u32, &H256, u32, u64)> = self.confirmed_unspent().into_iter()
+			.filter(|&(ref outpoint, _)| !spends.contains_key(outpoint))
+			.map(|(_, output)| (output.height, &output.transaction_hash, output.index, output.value))
+			.collect();
+		confirmed.sort();
+
+		let unconfirmed = self.unconfirmed.iter()
+			.filter(|&&(ref outpoint, _)| !spends.contains_key(outpoint))
+			.map(|&(ref outpoint, value)| (0, &outpoint.hash, outpoint.index, value));
+
+		confirmed.into_iter()
+			.chain(unconfirmed)
+			.map(|(height, hash, index, value)| UnspentItem {
+				tx_hash: hash.to_reversed_str(),
+				tx_pos: index,
+				height: height,
+				value: value,
+			})
+			.collect()
+	}
+}
+
+/// Returns status of the script hash: hex of the sha256 of its history, None if the history is empty.
+pub fn status(history: &[HistoryItem]) -> Option<String> {
+	if history.is_empty() {
+		return None;
+	}
+
+	let status: String = history.iter().map(|item| format!("{}:{}:", item.tx_hash, item.height)).collect();
+	Some(sha256(status.as_bytes())[..].to_hex())
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+	use db::{AddressOutput, AddressSpend, script_hash};
+	use primitives::hash::H256;
+	use super::{MemoryPoolIndex, MemoryPoolTransaction, ScriptHashState, HistoryItem, Balance, status};
+
+	const SCRIPT: &'static [u8] = &[0x51];
+
+	fn memory_pool_transaction(hash: u8, inputs: Vec<OutPoint>, outputs: Vec<u64>) -> MemoryPoolTransaction {
+		let transaction = Transaction {
+			version: 1,
+			inputs: inputs.into_iter().map(|previous_output| TransactionInput {
+				previous_output: previous_output,
+				..Default::default()
+			}).collect(),
+			outputs: outputs.into_iter().map(|value| TransactionOutput {
+				value: value,
+				script_pubkey: SCRIPT.into(),
+			}).collect(),
+			lock_time: 0,
+		};
+
+		MemoryPoolTransaction {
+			hash: H256::from(hash),
+			transaction: transaction,
+			fee: 100,
+			size: 200,
+			has_unconfirmed_inputs: false,
+		}
+	}
+
+	fn outpoint(hash: u8, index: u32) -> OutPoint {
+		OutPoint {
+			hash: H256::from(hash),
+			index: index,
+		}
+	}
+
+	fn confirmed_outputs() -> Vec<AddressOutput> {
+		vec![
+			AddressOutput {
+				transaction_hash: H256::from(1),
+				index: 0,
+				value: 1000,
+				height: 10,
+				spent_by: Some(AddressSpend {
+					transaction_hash: H256::from(2),
+					height: 11,
+				}),
+			},
+			AddressOutput {
+				transaction_hash: H256::from(2),
+				index: 0,
+				value: 900,
+				height: 11,
+				spent_by: None,
+			},
+			AddressOutput {
+				transaction_hash: H256::from(2),
+				index: 1,
+				value: 50,
+				height: 11,
+				spent_by: None,
+			},
+		]
+	}
+
+	#[test]
+	fn confirmed_state() {
+		let memory_pool = MemoryPoolIndex::new(Vec::new());
+		let state = ScriptHashState::new(&script_hash(SCRIPT), confirmed_outputs(), &memory_pool);
+
+		let history = state.history();
+		assert_eq!(history, vec![
+			HistoryItem { tx_hash: H256::from(1).to_reversed_str(), height: 10, fee: None },
+			HistoryItem { tx_hash: H256::from(2).to_reversed_str(), height: 11, fee: None },
+		]);
+		assert_eq!(state.balance(), Balance { confirmed: 950, unconfirmed: 0 });
+		assert_eq!(state.unspent().iter().map(|output| output.value).collect::<Vec<_>>(), vec![900, 50]);
+		assert!(state.memory_pool_history().is_empty());
+	}
+
+	#[test]
+	fn memory_pool_state() {
+		// 3 spends confirmed output of 2 and pays 800 to the script, 4 spends output of 3
+		let mut child = memory_pool_transaction(4, vec![outpoint(3, 0)], vec![]);
+		child.has_unconfirmed_inputs = true;
+		let memory_pool = MemoryPoolIndex::new(vec![
+			memory_pool_transaction(3, vec![outpoint(2, 0)], vec![800]),
+			child,
+		]);
+		let state = ScriptHashState::new(&script_hash(SCRIPT), confirmed_outputs(), &memory_pool);
+
+		let history = state.memory_pool_history();
+		assert_eq!(history, vec![
+			HistoryItem { tx_hash: H256::from(3).to_reversed_str(), height: 0, fee: Some(100) },
+			HistoryItem { tx_hash: H256::from(4).to_reversed_str(), height: -1, fee: Some(100) },
+		]);
+		assert_eq!(state.history().len(), 4);
+		assert_eq!(state.balance(), Balance { confirmed: 950, unconfirmed: -900 });
+		assert_eq!(state.unspent().iter().map(|output| (output.height, output.value)).collect::<Vec<_>>(), vec![(11, 50)]);
+	}
+
+	#[test]
+	fn script_hash_status() {
+		assert_eq!(status(&[]), None);
+
+		// sha256("0000000000000000000000000000000000000000000000000000000000000001:10:")
+		let history = vec![HistoryItem { tx_hash: H256::from(1).to_reversed_str(), height: 10, fee: None }];
+		assert_eq!(status(&history), Some("d4385b89b93c3c319c04a488bf0d69601245623394300d7653d382d5264569e0".to_owned()));
+	}
+}
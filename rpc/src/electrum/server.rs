@@ -0,0 +1,239 @@
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use chain::IndexedTransaction;
+use primitives::hash::H256;
+use sync::SyncListener;
+use super::handler::{ElectrumHandler, ElectrumCoreApi, ClientSubscriptions};
+
+/// How often the server checks if it should be stopped.
+const ACCEPT_INTERVAL_MS: u64 = 100;
+/// How often connections check if the server should be stopped.
+const READ_INTERVAL_MS: u64 = 1000;
+/// Time to wait for the response or notification to be written. Clients, which don't read them, are disconnected.
+const WRITE_TIMEOUT_MS: u64 = 5000;
+/// Clients, which haven't sent any request for this time, are disconnected.
+const IDLE_TIMEOUT_MS: u64 = 600_000;
+/// Maximal size of the single request line.
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+/// Maximal number of concurrently served connections.
+const MAX_CONNECTIONS: usize = 256;
+
+/// Connected Electrum client.
+pub struct Client {
+	stream: Mutex<TcpStream>,
+	pub subscriptions: ClientSubscriptions,
+}
+
+impl Client {
+	/// Sends newline-terminated message to the client.
+	pub fn send(&self, message: &str) -> Result<(), io::Error> {
+		let mut stream = self.stream.lock();
+		stream.write_all(message.as_bytes())?;
+		stream.write_all(b"\n")?;
+		stream.flush()
+	}
+
+	pub fn disconnect(&self) {
+		let _ = self.stream.lock().shutdown(Shutdown::Both);
+	}
+}
+
+/// Electrum server, serving newline-delimited JSON-RPC requests over plain TCP.
+pub struct ElectrumServer<T: ElectrumCoreApi> {
+	handler: Arc<ElectrumHandler<T>>,
+	stop: Arc<AtomicBool>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl<T> ElectrumServer<T> where T: ElectrumCoreApi {
+	pub fn start(address: SocketAddr, handler: ElectrumHandler<T>) -> Result<Self, String> {
+		let listener = TcpListener::bind(address)
+			.and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+			.map_err(|err| format!("Failed to start Electrum server at {}: {}", address, err))?;
+		let handler = Arc::new(handler);
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread_handler = handler.clone();
+		let thread_stop = stop.clone();
+		let thread = thread::Builder::new()
+			.name("Electrum server".to_owned())
+			.spawn(move || ElectrumServer::worker(listener, thread_handler, thread_stop))
+			.map_err(|err| format!("Failed to start Electrum server thread: {}", err))?;
+
+		Ok(ElectrumServer {
+			handler: handler,
+			stop: stop,
+			thread: Some(thread),
+		})
+	}
+
+	fn worker(listener: TcpListener, handler: Arc<ElectrumHandler<T>>, stop: Arc<AtomicBool>) {
+		let active_connections = Arc::new(AtomicUsize::new(0));
+		while !stop.load(Ordering::SeqCst) {
+			match listener.accept() {
+				Ok((stream, _)) => {
+					if active_connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+						active_connections.fetch_sub(1, Ordering::SeqCst);
+						debug!(target: "rpc", "Electrum server rejected connection: too many connections");
+						continue;
+					}
+
+					let handler = handler.clone();
+					let stop = stop.clone();
+					let active_connections = active_connections.clone();
+					let spawn_result = thread::Builder::new()
+						.name("Electrum connection".to_owned())
+						.spawn(move || {
+							if let Err(err) = handle_connection(stream, &handler, &stop) {
+								debug!(target: "rpc", "Electrum connection failed: {}", err);
+							}
+							active_connections.fetch_sub(1, Ordering::SeqCst);
+						});
+					if let Err(err) = spawn_result {
+						warn!(target: "rpc", "Failed to start Electrum connection thread: {}", err);
+					}
+				},
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(ACCEPT_INTERVAL_MS)),
+				Err(err) => warn!(target: "rpc", "Electrum server failed to accept connection: {}", err),
+			}
+		}
+	}
+}
+
+impl<T> Drop for ElectrumServer<T> where T: ElectrumCoreApi {
+	fn drop(&mut self) {
+		if let Some(thread) = self.thread.take() {
+			self.stop.store(true, Ordering::SeqCst);
+			thread.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+fn handle_connection<T>(stream: TcpStream, handler: &ElectrumHandler<T>, stop: &AtomicBool) -> Result<(), io::Error> where T: ElectrumCoreApi {
+	stream.set_nonblocking(false)?;
+	stream.set_write_timeout(Some(Duration::from_millis(WRITE_TIMEOUT_MS)))?;
+	// read timeout is shared by both handles of the socket, it only affects reads
+	let mut reader = stream.try_clone()?;
+	reader.set_read_timeout(Some(Duration::from_millis(READ_INTERVAL_MS)))?;
+	let client = Arc::new(Client {
+		stream: Mutex::new(stream),
+		subscriptions: ClientSubscriptions::default(),
+	});
+
+	let id = handler.add_client(client.clone());
+	let result = session(&mut reader, &client, handler, stop);
+	handler.remove_client(id);
+	client.disconnect();
+	result
+}
+
+fn session<T>(reader: &mut TcpStream, client: &Client, handler: &ElectrumHandler<T>, stop: &AtomicBool) -> Result<(), io::Error> where T: ElectrumCoreApi {
+	let mut data = Vec::new();
+	let mut buffer = [0u8; 4096];
+	let mut last_request = Instant::now();
+	loop {
+		while let Some(position) = data.iter().position(|byte| *byte == b'\n') {
+			let line: Vec<u8> = data.drain(..position + 1).collect();
+			let request = String::from_utf8_lossy(&line[..position]);
+			let request = request.trim();
+			if !request.is_empty() {
+				client.send(&handler.handle(&client.subscriptions, request))?;
+				last_request = Instant::now();
+			}
+		}
+
+		if data.len() > MAX_REQUEST_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "request is too large"));
+		}
+
+		if stop.load(Ordering::SeqCst) {
+			return Ok(());
+		}
+
+		if last_request.elapsed() > Duration::from_millis(IDLE_TIMEOUT_MS) {
+			return Err(io::Error::new(io::ErrorKind::TimedOut, "client is idle"));
+		}
+
+		match reader.read(&mut buffer) {
+			Ok(0) => return Ok(()),
+			Ok(read) => data.extend_from_slice(&buffer[..read]),
+			Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => (),
+			Err(err) => return Err(err),
+		}
+	}
+}
+
+enum ElectrumNotifierTask {
+	NewBlock(H256),
+	NewTransaction(IndexedTransaction),
+	Stop,
+}
+
+/// Forwards synchronization events to the clients of the Electrum server.
+pub struct ElectrumNotifier {
+	tx: Sender<ElectrumNotifierTask>,
+	is_synchronizing: AtomicBool,
+	worker_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ElectrumNotifier {
+	pub fn new<T>(server: &ElectrumServer<T>) -> Result<Self, String> where T: ElectrumCoreApi {
+		let (tx, rx) = channel();
+		let handler = server.handler.clone();
+		let worker_thread = thread::Builder::new()
+			.name("Electrum notification thread".to_owned())
+			.spawn(move || ElectrumNotifier::worker(rx, handler))
+			.map_err(|err| format!("Failed to start Electrum notification thread: {}", err))?;
+
+		Ok(ElectrumNotifier {
+			tx: tx,
+			is_synchronizing: AtomicBool::default(),
+			worker_thread: Some(worker_thread),
+		})
+	}
+
+	fn worker<T>(rx: Receiver<ElectrumNotifierTask>, handler: Arc<ElectrumHandler<T>>) where T: ElectrumCoreApi {
+		for task in rx {
+			match task {
+				ElectrumNotifierTask::NewBlock(hash) => handler.notify_block(&hash),
+				ElectrumNotifierTask::NewTransaction(transaction) => handler.notify_transaction(&transaction),
+				ElectrumNotifierTask::Stop => break,
+			}
+		}
+		trace!(target: "rpc", "Electrum notification thread stopped");
+	}
+}
+
+impl SyncListener for ElectrumNotifier {
+	fn synchronization_state_switched(&self, is_synchronizing: bool) {
+		self.is_synchronizing.store(is_synchronizing, Ordering::SeqCst);
+	}
+
+	fn best_storage_block_inserted(&self, block_hash: &H256) {
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			self.tx.send(ElectrumNotifierTask::NewBlock(block_hash.clone()))
+				.expect("Electrum notification thread have the same lifetime as `ElectrumNotifier`")
+		}
+	}
+
+	fn transaction_accepted(&self, transaction: &IndexedTransaction) {
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			self.tx.send(ElectrumNotifierTask::NewTransaction(transaction.clone()))
+				.expect("Electrum notification thread have the same lifetime as `ElectrumNotifier`")
+		}
+	}
+}
+
+impl Drop for ElectrumNotifier {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			let _ = self.tx.send(ElectrumNotifierTask::Stop);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
@@ -0,0 +1,192 @@
+//! Minimal HTTP/1.1 server for the unauthenticated GET endpoints (REST interface, health check).
+//!
+//! Every request is served by its own thread, and the connection is closed after the response is written.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use serde::Serialize;
+use serde_json;
+
+/// How often the server checks if it should be stopped.
+const ACCEPT_INTERVAL_MS: u64 = 100;
+/// Time to wait for the request head and for the response to be written.
+const REQUEST_TIMEOUT_MS: u64 = 5000;
+/// Maximal size of the request head.
+const MAX_REQUEST_SIZE: usize = 8192;
+/// Maximal number of concurrently served requests.
+const MAX_ACTIVE_REQUESTS: usize = 16;
+
+pub const STATUS_OK: &'static str = "200 OK";
+pub const STATUS_BAD_REQUEST: &'static str = "400 Bad Request";
+pub const STATUS_NOT_FOUND: &'static str = "404 Not Found";
+pub const STATUS_METHOD_NOT_ALLOWED: &'static str = "405 Method Not Allowed";
+pub const STATUS_SERVICE_UNAVAILABLE: &'static str = "503 Service Unavailable";
+
+/// Response to the HTTP request.
+#[derive(Debug, PartialEq)]
+pub struct Response {
+	/// Status code and reason phrase
+	pub status: &'static str,
+	/// Value of the Content-Type header
+	pub content_type: &'static str,
+	/// Response body
+	pub body: Vec<u8>,
+}
+
+impl Response {
+	pub fn json<T>(status: &'static str, value: &T) -> Self where T: Serialize {
+		let mut body = serde_json::to_vec(value).expect("HTTP responses are always serializable; qed");
+		body.push(b'\n');
+		Response {
+			status: status,
+			content_type: "application/json",
+			body: body,
+		}
+	}
+
+	pub fn error(status: &'static str, message: &str) -> Self {
+		Response {
+			status: status,
+			content_type: "text/plain",
+			body: format!("{}\r\n", message).into_bytes(),
+		}
+	}
+}
+
+/// Handles requests of the `HttpServer`.
+pub trait HttpHandler: Send + Sync + 'static {
+	/// Returns response to the request with given method and path.
+	fn handle(&self, method: &str, path: &str) -> Response;
+}
+
+/// HTTP server, stopped when dropped.
+pub struct HttpServer {
+	stop: Arc<AtomicBool>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl HttpServer {
+	/// Starts serving requests at given address. `name` is used in thread names and log messages.
+	pub fn start<H>(name: &'static str, address: SocketAddr, handler: H) -> Result<Self, String> where H: HttpHandler {
+		let listener = TcpListener::bind(address)
+			.and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+			.map_err(|err| format!("Failed to start {} server at {}: {}", name, address, err))?;
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread_stop = stop.clone();
+		let thread = thread::Builder::new()
+			.name(format!("{} server", name))
+			.spawn(move || HttpServer::worker(name, listener, Arc::new(handler), thread_stop))
+			.map_err(|err| format!("Failed to start {} server thread: {}", name, err))?;
+
+		Ok(HttpServer {
+			stop: stop,
+			thread: Some(thread),
+		})
+	}
+
+	fn worker<H>(name: &'static str, listener: TcpListener, handler: Arc<H>, stop: Arc<AtomicBool>) where H: HttpHandler {
+		let active_requests = Arc::new(AtomicUsize::new(0));
+		while !stop.load(Ordering::SeqCst) {
+			match listener.accept() {
+				Ok((stream, _)) => {
+					// every request is served by its own thread, so a slow client never delays other requests
+					if active_requests.fetch_add(1, Ordering::SeqCst) >= MAX_ACTIVE_REQUESTS {
+						active_requests.fetch_sub(1, Ordering::SeqCst);
+						let response = Response::error(STATUS_SERVICE_UNAVAILABLE, "Too many requests");
+						if let Err(err) = write_response(stream, &response) {
+							debug!(target: "rpc", "{} response failed: {}", name, err);
+						}
+						continue;
+					}
+
+					let handler = handler.clone();
+					let active_requests = active_requests.clone();
+					let spawn_result = thread::Builder::new()
+						.name(format!("{} request", name))
+						.spawn(move || {
+							if let Err(err) = handle_connection(stream, &*handler) {
+								debug!(target: "rpc", "{} request failed: {}", name, err);
+							}
+							active_requests.fetch_sub(1, Ordering::SeqCst);
+						});
+					if let Err(err) = spawn_result {
+						warn!(target: "rpc", "Failed to start {} request thread: {}", name, err);
+					}
+				},
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(ACCEPT_INTERVAL_MS)),
+				Err(err) => warn!(target: "rpc", "{} server failed to accept connection: {}", name, err),
+			}
+		}
+	}
+}
+
+impl Drop for HttpServer {
+	fn drop(&mut self) {
+		if let Some(thread) = self.thread.take() {
+			self.stop.store(true, Ordering::SeqCst);
+			thread.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+fn handle_connection<H>(mut stream: TcpStream, handler: &H) -> Result<(), io::Error> where H: HttpHandler {
+	stream.set_nonblocking(false)?;
+	stream.set_read_timeout(Some(Duration::from_millis(REQUEST_TIMEOUT_MS)))?;
+
+	let head = read_request_head(&mut stream)?;
+	let head = String::from_utf8_lossy(&head);
+	let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+	let response = match (request_line.next(), request_line.next()) {
+		(Some(method), Some(path)) => handler.handle(method, path),
+		_ => Response::error(STATUS_BAD_REQUEST, "Invalid request"),
+	};
+
+	write_response(stream, &response)
+}
+
+/// Reads the request until the end of its head, at most `MAX_REQUEST_SIZE` bytes.
+fn read_request_head<R>(reader: &mut R) -> Result<Vec<u8>, io::Error> where R: Read {
+	let mut head = Vec::new();
+	let mut buffer = [0u8; 1024];
+	while !head.windows(4).any(|window| window == b"\r\n\r\n") && head.len() < MAX_REQUEST_SIZE {
+		let read = reader.read(&mut buffer)?;
+		if read == 0 {
+			break;
+		}
+		head.extend_from_slice(&buffer[..read]);
+	}
+
+	Ok(head)
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> Result<(), io::Error> {
+	stream.set_nonblocking(false)?;
+	stream.set_write_timeout(Some(Duration::from_millis(REQUEST_TIMEOUT_MS)))?;
+	write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		response.status, response.content_type, response.body.len())?;
+	stream.write_all(&response.body)?;
+	stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{MAX_REQUEST_SIZE, read_request_head};
+
+	#[test]
+	fn request_head_is_read_until_empty_line() {
+		let request = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\nbody";
+		let head = read_request_head(&mut &request[..]).unwrap();
+		assert!(head.starts_with(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"));
+	}
+
+	#[test]
+	fn request_head_size_is_limited() {
+		let request = vec![b'a'; 4 * MAX_REQUEST_SIZE];
+		let head = read_request_head(&mut &request[..]).unwrap();
+		assert!(head.len() < MAX_REQUEST_SIZE + 1024);
+	}
+}
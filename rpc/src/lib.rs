@@ -1,3 +1,4 @@
+#[macro_use]
 extern crate log;
 extern crate rustc_serialize;
 extern crate serde;
@@ -20,9 +21,20 @@ extern crate miner;
 extern crate verification;
 extern crate script as global_script;
 extern crate keys;
+extern crate logs;
+extern crate psbt;
+extern crate wallet;
+extern crate bitcrypto as crypto;
+extern crate rand;
+extern crate parking_lot;
 
 pub mod v1;
 pub mod rpc_server;
+pub mod http_server;
+pub mod rest;
+pub mod auth;
+pub mod ws;
+pub mod electrum;
 
 pub use rustc_serialize::hex;
 
@@ -31,3 +43,8 @@ pub use jsonrpc_http_server::tokio_core::reactor::{Remote};
 
 pub use jsonrpc_http_server::{Server, Error};
 pub use rpc_server::start_http;
+pub use http_server::{HttpServer, HttpHandler};
+pub use rest::RestHandler;
+pub use ws::{WsServer, WsHandler, WsNotifier};
+pub use electrum::{ElectrumServer, ElectrumHandler, ElectrumCore, ElectrumNotifier};
+pub use auth::{Authenticator, RpcAuth, PermissionTier, MethodTiers, Metadata, AuthMiddleware, AuthMetaExtractor, COOKIE_USER, generate_cookie};
@@ -0,0 +1,328 @@
+//! Unauthenticated REST interface for high-volume read access to the blockchain data (like `bitcoind -rest`).
+//!
+//! Supported requests:
+//! - `GET /rest/block/<hash>.<bin|hex|json>`: block with given hash
+//! - `GET /rest/tx/<hash>.<bin|hex|json>`: transaction with given hash
+//! - `GET /rest/headers/<count>/<hash>.<bin|hex|json>`: up to `count` main chain headers, starting from given block
+//! - `GET /rest/chaininfo.json`: same as `getblockchaininfo` RPC result
+
+use hex::ToHex;
+use chain::{self, BlockHeader};
+use db;
+use keys;
+use network::ConsensusParams;
+use primitives::hash::H256 as GlobalH256;
+use ser::{serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use verification;
+use v1::helpers::transaction::{verbose_transaction, TransactionBlock};
+use v1::impls::{BlockChainClientCore, BlockChainClientCoreApi};
+use v1::types::{VerboseBlockHeader, BlockTransactions, Transaction};
+use http_server::{HttpHandler, Response, STATUS_OK, STATUS_BAD_REQUEST, STATUS_NOT_FOUND, STATUS_METHOD_NOT_ALLOWED};
+
+/// Maximal number of headers, returned by the single request.
+const MAX_HEADERS: u32 = 2000;
+
+/// Format of the response data, selected by the extension of the requested path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+	Binary,
+	Hex,
+	Json,
+}
+
+/// Response with the data in the requested format.
+fn data_response(format: Format, data: Vec<u8>) -> Response {
+	match format {
+		Format::Hex => Response {
+			status: STATUS_OK,
+			content_type: "text/plain",
+			body: format!("{}\n", data.to_hex()).into_bytes(),
+		},
+		Format::Binary | Format::Json => Response {
+			status: STATUS_OK,
+			content_type: "application/octet-stream",
+			body: data,
+		},
+	}
+}
+
+/// Handles REST requests, reading data from the database.
+pub struct RestHandler {
+	network: keys::Network,
+	storage: db::SharedStore,
+	core: BlockChainClientCore,
+}
+
+impl RestHandler {
+	/// `network` defines prefixes of the addresses, returned in transactions outputs.
+	pub fn new(network: keys::Network, storage: db::SharedStore, consensus: ConsensusParams) -> Self {
+		RestHandler {
+			network: network,
+			storage: storage.clone(),
+			core: BlockChainClientCore::new(network, storage, consensus),
+		}
+	}
+
+	fn chain_info(&self) -> Response {
+		let mut info = self.core.blockchain_info();
+		info.bestblockhash = info.bestblockhash.reversed();
+		Response::json(STATUS_OK, &info)
+	}
+
+	fn block(&self, param: &str) -> Result<Response, Response> {
+		let (hash, format) = parse_hash_param(param)?;
+		let not_found = || Response::error(STATUS_NOT_FOUND, &format!("{} not found", hash.reversed()));
+		match format {
+			Format::Json => {
+				let mut block = self.core.verbose_block(hash.clone()).ok_or_else(&not_found)?;
+				block.hash = block.hash.reversed();
+				block.merkleroot = block.merkleroot.reversed();
+				block.previousblockhash = block.previousblockhash.map(|h| h.reversed());
+				block.nextblockhash = block.nextblockhash.map(|h| h.reversed());
+				block.tx = match block.tx {
+					BlockTransactions::Hashes(hashes) => BlockTransactions::Hashes(hashes.into_iter().map(|h| h.reversed()).collect()),
+					transactions => transactions,
+				};
+				Ok(Response::json(STATUS_OK, &block))
+			},
+			_ => {
+				let block = self.storage.block_bytes(hash.clone().into()).ok_or_else(&not_found)?;
+				Ok(data_response(format, block.take()))
+			},
+		}
+	}
+
+	fn transaction(&self, param: &str) -> Result<Response, Response> {
+		let (hash, format) = parse_hash_param(param)?;
+		let transaction = self.storage.transaction(&hash)
+			.ok_or_else(|| Response::error(STATUS_NOT_FOUND, &format!("{} not found", hash.reversed())))?;
+		match format {
+			Format::Json => Ok(Response::json(STATUS_OK, &self.verbose_transaction(transaction))),
+			_ => Ok(data_response(format, serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).take())),
+		}
+	}
+
+	fn headers(&self, param: &str) -> Result<Response, Response> {
+		let mut parts = param.splitn(2, '/');
+		let (count, param) = match (parts.next(), parts.next()) {
+			(Some(count), Some(param)) => (count, param),
+			_ => return Err(Response::error(STATUS_BAD_REQUEST, "Invalid URI format. Expected /rest/headers/<count>/<hash>.<ext>")),
+		};
+
+		let count = match count.parse::<u32>() {
+			Ok(count) if count >= 1 && count <= MAX_HEADERS => count,
+			_ => return Err(Response::error(STATUS_BAD_REQUEST, &format!("Header count out of range: {}", count))),
+		};
+
+		// headers of the side chain blocks are not returned
+		let (hash, format) = parse_hash_param(param)?;
+		let mut headers = Vec::new();
+		if let Some(number) = self.storage.block_number(&hash) {
+			for number in number..number.saturating_add(count) {
+				match self.storage.block_header(number.into()) {
+					Some(header) => headers.push((number, header)),
+					None => break,
+				}
+			}
+		}
+
+		match format {
+			Format::Json => {
+				let best_block_number = self.storage.best_block().number;
+				let headers: Vec<_> = headers.into_iter()
+					.map(|(number, header)| self.verbose_header(number, header, best_block_number))
+					.collect();
+				Ok(Response::json(STATUS_OK, &headers))
+			},
+			_ => {
+				let data = headers.into_iter()
+					.flat_map(|(_, header)| serialize(&header).take())
+					.collect();
+				Ok(data_response(format, data))
+			},
+		}
+	}
+
+	fn verbose_header(&self, number: u32, header: BlockHeader, best_block_number: u32) -> VerboseBlockHeader {
+		VerboseBlockHeader {
+			hash: header.hash().reversed().into(),
+			confirmations: (best_block_number - number + 1) as i64,
+			height: Some(number),
+			version: header.version,
+			version_hex: format!("{:x}", header.version),
+			merkleroot: header.merkle_root_hash.reversed().into(),
+			time: header.time,
+			mediantime: verification::median_timestamp(&header, self.storage.as_block_header_provider()),
+			nonce: header.nonce,
+			bits: header.bits.into(),
+			difficulty: header.bits.to_f64(),
+			previousblockhash: Some(header.previous_header_hash.reversed().into()),
+			nextblockhash: self.storage.block_hash(number + 1).map(|h| h.reversed().into()),
+		}
+	}
+
+	fn verbose_transaction(&self, transaction: chain::Transaction) -> Transaction {
+		let best_block_number = self.storage.best_block().number;
+		let block = self.storage.transaction_meta(&transaction.hash())
+			.and_then(|meta| self.storage.block_header(meta.height().into()).map(|header| (meta.height(), header)))
+			.map(|(height, header)| TransactionBlock {
+				hash: header.hash(),
+				time: header.time,
+				confirmations: best_block_number - height + 1,
+			});
+		verbose_transaction(self.network, &transaction, block)
+	}
+}
+
+impl HttpHandler for RestHandler {
+	fn handle(&self, method: &str, path: &str) -> Response {
+		if method != "GET" {
+			return Response::error(STATUS_METHOD_NOT_ALLOWED, "Only GET requests are supported");
+		}
+
+		// query string is ignored
+		let path = path.split('?').next().unwrap_or_default();
+		if !path.starts_with("/rest/") {
+			return Response::error(STATUS_NOT_FOUND, "Not found");
+		}
+
+		let mut parts = path["/rest/".len()..].splitn(2, '/');
+		let result = match (parts.next(), parts.next()) {
+			(Some("chaininfo.json"), None) => Ok(self.chain_info()),
+			(Some("block"), Some(param)) => self.block(param),
+			(Some("tx"), Some(param)) => self.transaction(param),
+			(Some("headers"), Some(param)) => self.headers(param),
+			_ => Err(Response::error(STATUS_NOT_FOUND, "Not found")),
+		};
+
+		match result {
+			Ok(response) | Err(response) => response,
+		}
+	}
+}
+
+/// Parses `<hash>.<ext>` parameter of the request.
+fn parse_hash_param(param: &str) -> Result<(GlobalH256, Format), Response> {
+	let (hash, extension) = match param.rfind('.') {
+		Some(dot) => (&param[..dot], &param[dot + 1..]),
+		None => (param, ""),
+	};
+
+	let format = match extension {
+		"bin" => Format::Binary,
+		"hex" => Format::Hex,
+		"json" => Format::Json,
+		_ => return Err(Response::error(STATUS_NOT_FOUND, "Output format not found (available: bin, hex, json)")),
+	};
+
+	let hash: GlobalH256 = hash.parse()
+		.map_err(|_| Response::error(STATUS_BAD_REQUEST, &format!("Invalid hash: {}", hash)))?;
+	Ok((hash.reversed(), format))
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
+	use serde_json;
+	use hex::ToHex;
+	use db::BlockChainDatabase;
+	use keys;
+	use network::{ConsensusParams, ConsensusFork, Magic};
+	use ser::serialize;
+	use http_server::{HttpHandler, STATUS_OK, STATUS_BAD_REQUEST, STATUS_NOT_FOUND, STATUS_METHOD_NOT_ALLOWED};
+	use super::RestHandler;
+
+	fn handler() -> RestHandler {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		RestHandler::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork))
+	}
+
+	fn json(body: &[u8]) -> serde_json::Value {
+		serde_json::from_slice(body).unwrap()
+	}
+
+	#[test]
+	fn rest_block() {
+		let handler = handler();
+		let path = format!("/rest/block/{}", test_data::block_h1().hash().to_reversed_str());
+
+		let response = handler.handle("GET", &format!("{}.bin", path));
+		assert_eq!(response.status, STATUS_OK);
+		assert_eq!(response.content_type, "application/octet-stream");
+		assert_eq!(response.body, serialize(&test_data::block_h1()).take());
+
+		let response = handler.handle("GET", &format!("{}.hex", path));
+		assert_eq!(response.body, format!("{}\n", serialize(&test_data::block_h1()).to_hex()).into_bytes());
+
+		let response = handler.handle("GET", &format!("{}.json?verbose", path));
+		assert_eq!(response.content_type, "application/json");
+		let block = json(&response.body);
+		assert_eq!(block["hash"], "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048");
+		assert_eq!(block["height"], 1);
+		assert_eq!(block["previousblockhash"], "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+
+		assert_eq!(handler.handle("GET", &format!("{}.xml", path)).status, STATUS_NOT_FOUND);
+		assert_eq!(handler.handle("GET", "/rest/block/00.bin").status, STATUS_BAD_REQUEST);
+		let unknown = format!("/rest/block/{}.bin", test_data::block_h3().hash().to_reversed_str());
+		assert_eq!(handler.handle("GET", &unknown).status, STATUS_NOT_FOUND);
+	}
+
+	#[test]
+	fn rest_transaction() {
+		let handler = handler();
+		let transaction = test_data::block_h1().transactions[0].clone();
+		let path = format!("/rest/tx/{}", transaction.hash().to_reversed_str());
+
+		let response = handler.handle("GET", &format!("{}.bin", path));
+		assert_eq!(response.body, serialize(&transaction).take());
+
+		let response = handler.handle("GET", &format!("{}.json", path));
+		let transaction_json = json(&response.body);
+		assert_eq!(transaction_json["txid"], "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098");
+		assert_eq!(transaction_json["blockhash"], "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048");
+		assert_eq!(transaction_json["confirmations"], 2);
+		assert_eq!(transaction_json["vout"][0]["value"], 50.0);
+	}
+
+	#[test]
+	fn rest_headers() {
+		let handler = handler();
+		let path = format!("/rest/headers/{{}}/{}", test_data::block_h1().hash().to_reversed_str());
+		let path = |count: &str, extension: &str| format!("{}.{}", path.replace("{}", count), extension);
+
+		let response = handler.handle("GET", &path("1", "bin"));
+		assert_eq!(response.body, serialize(&test_data::block_h1().block_header).take());
+
+		// only headers up to the best block are returned
+		let response = handler.handle("GET", &path("5", "bin"));
+		assert_eq!(response.body.len(), 160);
+
+		let response = handler.handle("GET", &path("2", "json"));
+		let headers = json(&response.body);
+		assert_eq!(headers[0]["height"], 1);
+		assert_eq!(headers[0]["nextblockhash"], "000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd");
+		assert_eq!(headers[1]["height"], 2);
+		assert_eq!(headers[1]["confirmations"], 1);
+
+		assert_eq!(handler.handle("GET", &path("0", "bin")).status, STATUS_BAD_REQUEST);
+		assert_eq!(handler.handle("GET", &path("2001", "bin")).status, STATUS_BAD_REQUEST);
+		assert_eq!(handler.handle("GET", "/rest/headers/1.bin").status, STATUS_BAD_REQUEST);
+	}
+
+	#[test]
+	fn rest_chain_info() {
+		let handler = handler();
+		let response = handler.handle("GET", "/rest/chaininfo.json");
+		assert_eq!(response.status, STATUS_OK);
+		let info = json(&response.body);
+		assert_eq!(info["chain"], "main");
+		assert_eq!(info["blocks"], 2);
+		assert_eq!(info["bestblockhash"], "000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd");
+
+		assert_eq!(handler.handle("GET", "/rest/chaininfo.bin").status, STATUS_NOT_FOUND);
+		assert_eq!(handler.handle("GET", "/chaininfo.json").status, STATUS_NOT_FOUND);
+		assert_eq!(handler.handle("POST", "/rest/chaininfo.json").status, STATUS_METHOD_NOT_ALLOWED);
+	}
+}
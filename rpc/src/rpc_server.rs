@@ -1,14 +1,18 @@
 // TODO: panic handler
 use std::net::SocketAddr;
+use std::sync::Arc;
 use jsonrpc_core;
 use jsonrpc_http_server::{self, ServerBuilder, Server, Error, Host};
+use auth::{Authenticator, Metadata, AuthMiddleware, AuthMetaExtractor, AuthRequestMiddleware};
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
-pub fn start_http<M: jsonrpc_core::Metadata>(
+/// Requests are authenticated by `authenticator`.
+pub fn start_http(
 	addr: &SocketAddr,
 	cors_domains: Option<Vec<String>>,
 	allowed_hosts: Option<Vec<String>>,
-	handler: jsonrpc_core::MetaIoHandler<M>,
+	handler: jsonrpc_core::MetaIoHandler<Metadata, AuthMiddleware>,
+	authenticator: Arc<Authenticator>,
 	) -> Result<Server, Error> {
 
 	let cors_domains = cors_domains.map(|domains| {
@@ -22,6 +26,8 @@ pub fn start_http<M: jsonrpc_core::Metadata>(
 	});
 
 	ServerBuilder::new(handler)
+		.request_middleware(AuthRequestMiddleware::new(authenticator.clone()))
+		.meta_extractor(AuthMetaExtractor::new(authenticator))
 		.cors(cors_domains.into())
 		.allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into())
 		.start_http(addr)
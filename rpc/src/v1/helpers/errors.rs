@@ -10,15 +10,15 @@ mod codes {
 	pub const BLOCK_NOT_FOUND: i64 = -32099;
 	pub const NODE_ALREADY_ADDED: i64 = -32150;
 	pub const NODE_NOT_ADDED: i64 = -32151;
+	pub const NODE_ALREADY_BANNED: i64 = -32152;
+	pub const NODE_NOT_BANNED: i64 = -32153;
+	pub const UNAUTHORIZED: i64 = -32160;
+	pub const METHOD_NOT_PERMITTED: i64 = -32161;
 }
 
 use std::fmt;
 use jsonrpc_core::{Error, ErrorCode, Value};
 
-macro_rules! rpc_unimplemented {
-	() => (Err(::v1::helpers::errors::unimplemented(None)))
-}
-
 pub fn unimplemented(details: Option<String>) -> Error {
 	Error {
 		code: ErrorCode::InternalError,
@@ -99,6 +99,38 @@ pub fn node_not_added() -> Error {
 	}
 }
 
+pub fn node_already_banned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NODE_ALREADY_BANNED),
+		message: "Node is already banned".into(),
+		data: None,
+	}
+}
+
+pub fn node_not_banned() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NODE_NOT_BANNED),
+		message: "Node is not banned".into(),
+		data: None,
+	}
+}
+
+pub fn unauthorized() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::UNAUTHORIZED),
+		message: "Invalid or missing RPC credentials".into(),
+		data: None,
+	}
+}
+
+pub fn method_not_permitted(method: &str) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::METHOD_NOT_PERMITTED),
+		message: "Method is not permitted for this RPC user".into(),
+		data: Some(Value::String(method.to_owned())),
+	}
+}
+
 pub fn unknown() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::UNKNOWN),
@@ -1,2 +1,4 @@
 #[macro_use]
 pub mod errors;
+pub mod transaction;
+pub mod utxo_dump;
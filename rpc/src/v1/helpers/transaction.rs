@@ -0,0 +1,183 @@
+use hex::ToHex;
+use chain;
+use chain::constants::SATOSHIS_IN_COIN;
+use db::SpentOutput;
+use global_script::Script;
+use keys::{self, Address};
+use primitives::bytes::Bytes as GlobalBytes;
+use primitives::hash::H256 as GlobalH256;
+use ser::{serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use v1::types::{H256, Transaction, BlockTransaction, SignedTransactionInput, SignedTransactionOutput, TransactionInputScript,
+	TransactionOutputScript, TransactionPrevout};
+
+/// Main chain block, which includes the transaction.
+pub struct TransactionBlock {
+	/// Hash of the block
+	pub hash: GlobalH256,
+	/// Timestamp of the block
+	pub time: u32,
+	/// Number of confirmations of the block
+	pub confirmations: u32,
+}
+
+/// Builds verbose transaction. Unlike other core methods results, all hashes are already reversed.
+pub fn verbose_transaction(network: keys::Network, transaction: &chain::Transaction, block: Option<TransactionBlock>) -> Transaction {
+	let (blockhash, confirmations, blocktime): (H256, u32, u32) = match block {
+		Some(block) => (block.hash.reversed().into(), block.confirmations, block.time),
+		None => Default::default(),
+	};
+
+	Transaction {
+		hex: serialize_with_flags(transaction, SERIALIZE_TRANSACTION_WITNESS).into(),
+		txid: transaction.hash().reversed().into(),
+		hash: transaction.witness_hash().reversed().into(),
+		size: transaction.total_size(),
+		vsize: transaction.vsize(),
+		weight: transaction.weight(),
+		version: transaction.version,
+		locktime: transaction.lock_time as i32,
+		vin: verbose_inputs(network, transaction, None),
+		vout: verbose_outputs(network, transaction),
+		blockhash: blockhash,
+		confirmations: confirmations,
+		time: blocktime,
+		blocktime: blocktime,
+	}
+}
+
+/// Builds verbose transaction of the `getblock` response. `spent_outputs` are outputs, spent by the
+/// transaction inputs, if they're known. Fee is reported if spent outputs are known and spent outputs
+/// are reported if `with_prevouts` is true. All hashes are already reversed.
+pub fn block_transaction(network: keys::Network, transaction: &chain::Transaction, spent_outputs: Option<&[SpentOutput]>, with_prevouts: bool) -> BlockTransaction {
+	let fee = spent_outputs.map(|spent_outputs| {
+		let input_value: u64 = spent_outputs.iter().map(|spent| spent.output.value).sum();
+		let output_value: u64 = transaction.outputs.iter().map(|output| output.value).sum();
+		input_value.saturating_sub(output_value) as f64 / SATOSHIS_IN_COIN as f64
+	});
+
+	BlockTransaction {
+		hex: serialize_with_flags(transaction, SERIALIZE_TRANSACTION_WITNESS).into(),
+		txid: transaction.hash().reversed().into(),
+		hash: transaction.witness_hash().reversed().into(),
+		size: transaction.total_size(),
+		vsize: transaction.vsize(),
+		weight: transaction.weight(),
+		version: transaction.version,
+		locktime: transaction.lock_time as i32,
+		vin: verbose_inputs(network, transaction, if with_prevouts { spent_outputs } else { None }),
+		vout: verbose_outputs(network, transaction),
+		fee: fee,
+	}
+}
+
+/// Builds verbose transaction inputs, with spent outputs if they're given.
+fn verbose_inputs(network: keys::Network, transaction: &chain::Transaction, spent_outputs: Option<&[SpentOutput]>) -> Vec<SignedTransactionInput> {
+	transaction.inputs.iter().enumerate().map(|(index, input)| SignedTransactionInput {
+		txid: input.previous_output.hash.reversed().into(),
+		vout: input.previous_output.index,
+		script_sig: TransactionInputScript {
+			asm: format!("{}", Script::from(input.script_sig.clone())),
+			hex: input.script_sig.clone().into(),
+		},
+		sequence: input.sequence,
+		txinwitness: input.script_witness.iter().map(|item| item.to_hex()).collect(),
+		prevout: spent_outputs.and_then(|spent_outputs| spent_outputs.get(index)).map(|spent| TransactionPrevout {
+			generated: spent.is_coinbase,
+			height: spent.height,
+			value: spent.output.value as f64 / SATOSHIS_IN_COIN as f64,
+			script: output_script(network, &spent.output.script_pubkey),
+		}),
+	}).collect()
+}
+
+/// Builds verbose transaction outputs.
+fn verbose_outputs(network: keys::Network, transaction: &chain::Transaction) -> Vec<SignedTransactionOutput> {
+	transaction.outputs.iter().enumerate().map(|(n, output)| SignedTransactionOutput {
+		value: output.value as f64 / SATOSHIS_IN_COIN as f64,
+		n: n as u32,
+		script: output_script(network, &output.script_pubkey),
+	}).collect()
+}
+
+/// Builds verbose output script.
+pub fn output_script(network: keys::Network, script_bytes: &GlobalBytes) -> TransactionOutputScript {
+	let script: Script = script_bytes.clone().into();
+	let addresses = script.extract_destinations().unwrap_or(vec![]);
+	TransactionOutputScript {
+		asm: format!("{}", script),
+		hex: script_bytes.clone().into(),
+		req_sigs: script.num_signatures_required() as u32,
+		script_type: script.script_type().into(),
+		addresses: addresses.into_iter().map(|a| Address {
+			network: network,
+			hash: a.hash,
+			kind: a.kind,
+		}).collect(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain;
+	use db::SpentOutput;
+	use keys;
+	use super::{verbose_transaction, block_transaction, TransactionBlock};
+
+	#[test]
+	fn verbose_transaction_contents() {
+		let transaction = test_data::block_h1().transactions[0].clone();
+		let block = TransactionBlock {
+			hash: test_data::block_h1().hash(),
+			time: 1231469665,
+			confirmations: 2,
+		};
+
+		let verbose = verbose_transaction(keys::Network::Mainnet, &transaction, Some(block));
+		assert_eq!(verbose.txid, "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098".into());
+		assert_eq!(verbose.blockhash, "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048".into());
+		assert_eq!(verbose.confirmations, 2);
+		assert_eq!(verbose.blocktime, 1231469665);
+		assert_eq!(verbose.vin.len(), 1);
+		assert_eq!(verbose.vout[0].value, 50.0);
+		assert_eq!(verbose.size, 134);
+		assert_eq!(verbose.vsize, 134);
+		assert_eq!(verbose.weight, 536);
+
+		let verbose = verbose_transaction(keys::Network::Mainnet, &transaction, None);
+		assert_eq!(verbose.confirmations, 0);
+		assert_eq!(verbose.blockhash, Default::default());
+		assert_eq!(verbose.vin[0].prevout, None);
+	}
+
+	#[test]
+	fn block_transaction_contents() {
+		let transaction = test_data::block_h170().transactions[1].clone();
+		let output_value: u64 = transaction.outputs.iter().map(|output| output.value).sum();
+		let spent_outputs = vec![SpentOutput {
+			output: chain::TransactionOutput {
+				value: output_value + 1000,
+				script_pubkey: "410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac".into(),
+			},
+			height: 9,
+			is_coinbase: true,
+		}];
+
+		let verbose = block_transaction(keys::Network::Mainnet, &transaction, None, true);
+		assert_eq!(verbose.txid, transaction.hash().reversed().into());
+		assert_eq!(verbose.fee, None);
+		assert_eq!(verbose.vin[0].prevout, None);
+
+		let verbose = block_transaction(keys::Network::Mainnet, &transaction, Some(&spent_outputs), false);
+		assert_eq!(verbose.fee, Some(0.00001));
+		assert_eq!(verbose.vin[0].prevout, None);
+
+		let verbose = block_transaction(keys::Network::Mainnet, &transaction, Some(&spent_outputs), true);
+		let prevout = verbose.vin[0].prevout.as_ref().unwrap();
+		assert!(prevout.generated);
+		assert_eq!(prevout.height, 9);
+		assert_eq!(prevout.value, (output_value + 1000) as f64 / 100_000_000f64);
+		assert_eq!(prevout.script.hex, spent_outputs[0].output.script_pubkey.clone().into());
+	}
+}
@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+use db::{UtxoSnapshot, UnspentOutput};
+use global_script::Script;
+use hex::ToHex;
+use keys::{self, Address};
+use ser::{Stream, serialize};
+
+/// Format of the dumped unspent outputs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UtxoDumpFormat {
+	/// `txid,vout,value,height,coinbase,script,address` lines.
+	Csv,
+	/// Best block hash and number, followed by serialized outpoint, `height * 2 + coinbase` and output of every record.
+	Binary,
+}
+
+impl UtxoDumpFormat {
+	/// Format by its name, `csv` or `binary`.
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"csv" => Some(UtxoDumpFormat::Csv),
+			"binary" => Some(UtxoDumpFormat::Binary),
+			_ => None,
+		}
+	}
+}
+
+/// Writes all unspent outputs of the snapshot. Returns number of written outputs.
+pub fn write_utxo_dump<W>(writer: &mut W, snapshot: &UtxoSnapshot, format: UtxoDumpFormat, network: keys::Network) -> Result<u64, String> where W: Write {
+	let best_block = snapshot.best_block();
+	match format {
+		UtxoDumpFormat::Csv => writeln!(writer, "txid,vout,value,height,coinbase,script,address"),
+		UtxoDumpFormat::Binary => {
+			let mut stream = Stream::new();
+			stream.append(&best_block.hash).append(&best_block.number);
+			writer.write_all(&stream.out())
+		},
+	}.map_err(|err| err.to_string())?;
+
+	let mut written = 0u64;
+	for output in snapshot.unspent_outputs() {
+		let output = output.map_err(String::from)?;
+		match format {
+			UtxoDumpFormat::Csv => write_csv_record(writer, &output, network),
+			UtxoDumpFormat::Binary => writer.write_all(&serialize(&output)),
+		}.map_err(|err| err.to_string())?;
+		written += 1;
+	}
+
+	writer.flush().map_err(|err| err.to_string())?;
+	Ok(written)
+}
+
+fn write_csv_record<W>(writer: &mut W, output: &UnspentOutput, network: keys::Network) -> io::Result<()> where W: Write {
+	let script: Script = output.output.script_pubkey.clone().into();
+	// only outputs with single destination have an address
+	let address = match script.extract_destinations() {
+		Ok(ref destinations) if destinations.len() == 1 => Address {
+			kind: destinations[0].kind,
+			network: network,
+			hash: destinations[0].hash.clone(),
+		}.to_string(),
+		_ => String::new(),
+	};
+
+	writeln!(writer, "{},{},{},{},{},{},{}",
+		output.outpoint.hash.to_reversed_str(),
+		output.outpoint.index,
+		output.output.value,
+		output.height,
+		output.is_coinbase as u8,
+		output.output.script_pubkey.to_hex(),
+		address)
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::{OutPoint, TransactionOutput};
+	use db::UnspentOutput;
+	use keys;
+	use super::{UtxoDumpFormat, write_csv_record};
+
+	#[test]
+	fn utxo_dump_format_from_name() {
+		assert_eq!(UtxoDumpFormat::from_name("csv"), Some(UtxoDumpFormat::Csv));
+		assert_eq!(UtxoDumpFormat::from_name("binary"), Some(UtxoDumpFormat::Binary));
+		assert_eq!(UtxoDumpFormat::from_name("snapshot"), None);
+	}
+
+	#[test]
+	fn csv_record_with_address() {
+		let output = UnspentOutput {
+			outpoint: OutPoint {
+				hash: "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098".into(),
+				index: 0,
+			},
+			height: 1,
+			is_coinbase: true,
+			output: TransactionOutput {
+				value: 5000000000,
+				script_pubkey: "76a914010966776006953d5567439e5e39f86a0d273bee88ac".into(),
+			},
+		};
+
+		let mut record = Vec::new();
+		write_csv_record(&mut record, &output, keys::Network::Mainnet).unwrap();
+		assert_eq!(String::from_utf8(record).unwrap(),
+			"982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e,0,5000000000,1,1,76a914010966776006953d5567439e5e39f86a0d273bee88ac,16UwLL9Risc3QfPqBUvKofHmBQ7wMtjvM\n");
+	}
+}
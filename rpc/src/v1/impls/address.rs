@@ -0,0 +1,245 @@
+use jsonrpc_core::Error;
+use v1::traits::Address;
+use v1::types::{AddressesRequest, AddressBalance, AddressUnspentOutput, H256};
+use v1::helpers::errors::execution;
+use global_script::Builder as ScriptBuilder;
+use primitives::bytes::Bytes as GlobalBytes;
+use db;
+use keys;
+
+pub struct AddressClient<T: AddressClientCoreApi> {
+	core: T,
+}
+
+pub trait AddressClientCoreApi: Send + Sync + 'static {
+	fn is_enabled(&self) -> bool;
+	fn address_outputs(&self, script: &[u8]) -> Vec<db::AddressOutput>;
+}
+
+pub struct AddressClientCore {
+	storage: db::SharedStore,
+}
+
+impl AddressClientCore {
+	pub fn new(storage: db::SharedStore) -> Self {
+		AddressClientCore {
+			storage: storage,
+		}
+	}
+}
+
+impl AddressClientCoreApi for AddressClientCore {
+	fn is_enabled(&self) -> bool {
+		self.storage.is_address_index_enabled()
+	}
+
+	fn address_outputs(&self, script: &[u8]) -> Vec<db::AddressOutput> {
+		self.storage.address_outputs(&db::script_hash(script))
+	}
+}
+
+impl<T> AddressClient<T> where T: AddressClientCoreApi {
+	pub fn new(core: T) -> Self {
+		AddressClient {
+			core: core,
+		}
+	}
+
+	/// Returns outputs of every requested address, along with the address and its script.
+	fn outputs(&self, request: AddressesRequest) -> Result<Vec<(keys::Address, GlobalBytes, Vec<db::AddressOutput>)>, Error> {
+		if !self.core.is_enabled() {
+			return Err(execution("Address index is not enabled"));
+		}
+
+		Ok(request.addresses.into_iter()
+			.map(|address| {
+				let script = address_script(&address);
+				let outputs = self.core.address_outputs(&script);
+				(address, script, outputs)
+			})
+			.collect())
+	}
+}
+
+impl<T> Address for AddressClient<T> where T: AddressClientCoreApi {
+	fn balance(&self, request: AddressesRequest) -> Result<AddressBalance, Error> {
+		let outputs = self.outputs(request)?;
+		let all_outputs = || outputs.iter().flat_map(|&(_, _, ref outputs)| outputs.iter());
+		Ok(AddressBalance {
+			balance: all_outputs().filter(|output| output.spent_by.is_none()).map(|output| output.value).sum(),
+			received: all_outputs().map(|output| output.value).sum(),
+		})
+	}
+
+	fn unspent_outputs(&self, request: AddressesRequest) -> Result<Vec<AddressUnspentOutput>, Error> {
+		let mut unspent: Vec<_> = self.outputs(request)?.into_iter()
+			.flat_map(|(address, script, outputs)| outputs.into_iter()
+				.filter(|output| output.spent_by.is_none())
+				.map(move |output| AddressUnspentOutput {
+					address: address.clone(),
+					txid: output.transaction_hash.reversed().into(),
+					output_index: output.index,
+					script: script.clone().into(),
+					satoshis: output.value,
+					height: output.height,
+				}))
+			.collect();
+		unspent.sort_by_key(|output| output.height);
+		Ok(unspent)
+	}
+
+	fn transaction_ids(&self, request: AddressesRequest) -> Result<Vec<H256>, Error> {
+		let start = request.start.unwrap_or(0);
+		let end = request.end.unwrap_or(u32::max_value());
+		let mut transactions: Vec<_> = self.outputs(request)?.into_iter()
+			.flat_map(|(_, _, outputs)| outputs.into_iter())
+			.flat_map(|output| {
+				let spend = output.spent_by.map(|spend| (spend.height, spend.transaction_hash));
+				Some((output.height, output.transaction_hash)).into_iter().chain(spend)
+			})
+			.filter(|&(height, _)| height >= start && height <= end)
+			.collect();
+		transactions.sort();
+		transactions.dedup();
+		Ok(transactions.into_iter().map(|(_, hash)| hash.reversed().into()).collect())
+	}
+}
+
+/// Output script, paying to the address.
+fn address_script(address: &keys::Address) -> GlobalBytes {
+	match address.kind {
+		keys::Type::P2PKH => ScriptBuilder::build_p2pkh(&address.hash),
+		keys::Type::P2SH => ScriptBuilder::build_p2sh(&address.hash),
+	}.to_bytes()
+}
+
+#[cfg(test)]
+pub mod tests {
+	use jsonrpc_core::IoHandler;
+	use primitives::hash::H256;
+	use db::{AddressOutput, AddressSpend};
+	use v1::traits::Address;
+	use super::*;
+
+	#[derive(Default)]
+	struct SuccessAddressClientCore;
+
+	#[derive(Default)]
+	struct DisabledAddressClientCore;
+
+	impl AddressClientCoreApi for SuccessAddressClientCore {
+		fn is_enabled(&self) -> bool {
+			true
+		}
+
+		fn address_outputs(&self, _script: &[u8]) -> Vec<AddressOutput> {
+			vec![
+				AddressOutput {
+					transaction_hash: H256::from(2),
+					index: 1,
+					value: 100,
+					height: 20,
+					spent_by: None,
+				},
+				AddressOutput {
+					transaction_hash: H256::from(1),
+					index: 0,
+					value: 50,
+					height: 10,
+					spent_by: Some(AddressSpend {
+						transaction_hash: H256::from(2),
+						height: 20,
+					}),
+				},
+			]
+		}
+	}
+
+	impl AddressClientCoreApi for DisabledAddressClientCore {
+		fn is_enabled(&self) -> bool {
+			false
+		}
+
+		fn address_outputs(&self, _script: &[u8]) -> Vec<AddressOutput> {
+			unreachable!()
+		}
+	}
+
+	#[test]
+	fn getaddressbalance_success() {
+		let client = AddressClient::new(SuccessAddressClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddressbalance",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"balance":100,"received":150},"id":1}"#);
+	}
+
+	#[test]
+	fn getaddressutxos_success() {
+		let client = AddressClient::new(SuccessAddressClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddressutxos",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","txid":"0000000000000000000000000000000000000000000000000000000000000002","outputIndex":1,"script":"76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac","satoshis":100,"height":20}],"id":1}"#);
+	}
+
+	#[test]
+	fn getaddresstxids_success() {
+		let client = AddressClient::new(SuccessAddressClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddresstxids",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000001","0000000000000000000000000000000000000000000000000000000000000002"],"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddresstxids",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"], "start": 15}],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000002"],"id":1}"#);
+	}
+
+	#[test]
+	fn getaddressbalance_index_disabled() {
+		let client = AddressClient::new(DisabledAddressClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getaddressbalance",
+				"params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Address index is not enabled\""},"id":1}"#);
+	}
+}
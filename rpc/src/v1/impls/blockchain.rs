@@ -1,21 +1,52 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use parking_lot::Mutex;
 use v1::traits::BlockChain;
-use v1::types::{GetBlockResponse, VerboseBlock, RawBlock};
+use v1::types::{GetBlockResponse, BlockVerbosity, VerboseBlock, BlockTransactions, BlockTransaction, RawBlock};
+use v1::types::{GetBlockHeaderResponse, VerboseBlockHeader, RawBlockHeader};
+use v1::types::{ChainTip, GetChainTxStatsResponse};
+use v1::types::{HashOrHeight, GetBlockStatsResponse};
 use v1::types::{GetTxOutResponse, TransactionOutputScript};
-use v1::types::GetTxOutSetInfoResponse;
-use v1::types::H256;
-use v1::types::U256;
+use v1::types::{GetTxOutSetInfoResponse, DumpTxOutSetResponse, DumpUtxosResponse};
+use v1::types::{ScanTxOutSetAction, ScanObject, ScanTxOutSetResult, ScanTxOutSetResponse, ScanTxOutSetUnspent,
+	ScanTxOutSetStatus};
+use v1::types::{GetBlockchainInfoResponse, Bip9SoftforkInfo};
+use v1::types::{H256, Bytes};
 use keys::{self, Address};
+use v1::helpers::transaction::block_transaction;
+use v1::helpers::utxo_dump::{UtxoDumpFormat, write_utxo_dump};
 use v1::helpers::errors::{block_not_found, block_at_height_not_found, transaction_not_found,
-	transaction_output_not_found, transaction_of_side_branch};
+	transaction_output_not_found, transaction_of_side_branch, execution, invalid_params};
 use jsonrpc_macros::Trailing;
 use jsonrpc_core::Error;
-use {db, chain};
+use {db, chain, sync};
 use global_script::Script;
 use chain::OutPoint;
-use verification;
-use ser::serialize;
-use network::Magic;
+use chain::constants::{WITNESS_SCALE_FACTOR, SATOSHIS_IN_COIN};
+use verification::{self, Deployments, ThresholdState, CheckLevel, BadBlock};
+use network::{ConsensusParams, Magic};
+use ser::{serialize, Stream};
 use primitives::hash::H256 as GlobalH256;
+use primitives::bytes::Bytes as GlobalBytes;
+
+/// Default check level of the `verifychain` call.
+const DEFAULT_CHECK_LEVEL: u32 = 3;
+/// Default number of blocks, checked by the `verifychain` call.
+const DEFAULT_CHECK_BLOCKS: u32 = 6;
+/// Size of the unspent output in the UTXO set, excluding the serialized output itself.
+const PER_UTXO_OVERHEAD: i64 = 41;
+/// Percentiles of the `getblockstats` fee rates.
+const FEE_RATE_PERCENTILES: [(u64, u64); 5] = [(1, 10), (1, 4), (1, 2), (3, 4), (9, 10)];
+/// Last child index of ranged descriptors, scanned by `scantxoutset` by default.
+const DEFAULT_SCAN_RANGE_END: u32 = 1000;
+/// Maximal number of child indexes of the single ranged descriptor, scanned by `scantxoutset`.
+const MAX_SCAN_RANGE_SIZE: u32 = 1_000_000;
+/// Number of blocks in the `getchaintxstats` window by default (one month of blocks).
+const DEFAULT_CHAIN_TX_STATS_BLOCKS: u32 = 30 * 24 * 6;
 
 pub struct BlockChainClient<T: BlockChainClientCoreApi> {
 	core: T,
@@ -26,24 +57,126 @@ pub trait BlockChainClientCoreApi: Send + Sync + 'static {
 	fn block_count(&self) -> u32;
 	fn block_hash(&self, height: u32) -> Option<GlobalH256>;
 	fn difficulty(&self) -> f64;
+	fn blockchain_info(&self) -> GetBlockchainInfoResponse;
 	fn raw_block(&self, hash: GlobalH256) -> Option<RawBlock>;
 	fn verbose_block(&self, hash: GlobalH256) -> Option<VerboseBlock>;
+	fn verbose_block_transactions(&self, hash: GlobalH256, with_prevouts: bool) -> Option<Vec<BlockTransaction>>;
+	fn raw_block_header(&self, hash: GlobalH256) -> Option<RawBlockHeader>;
+	fn verbose_block_header(&self, hash: GlobalH256) -> Option<VerboseBlockHeader>;
+	fn chain_tips(&self) -> Vec<ChainTip>;
+	fn chain_tx_stats(&self, blocks: Option<u32>, hash: GlobalH256) -> Result<GetChainTxStatsResponse, Error>;
+	fn block_stats(&self, hash: GlobalH256) -> Result<GetBlockStatsResponse, Error>;
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error>;
+	fn transaction_out_proof(&self, hashes: Vec<GlobalH256>, block_hash: Option<GlobalH256>) -> Result<GlobalBytes, Error>;
+	fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error>;
+	fn dump_tx_out_set(&self, path: &str) -> Result<DumpTxOutSetResponse, Error>;
+	fn dump_utxos(&self, path: &str, format: UtxoDumpFormat) -> Result<DumpUtxosResponse, Error>;
+	fn prune_blocks(&self, height: u32) -> Result<u32, Error>;
+	fn verify_chain(&self, level: CheckLevel, blocks: u32) -> Vec<BadBlock>;
+	fn scan_tx_out_set(&self, scripts: HashMap<GlobalBytes, String>) -> Result<ScanTxOutSetResponse, Error>;
+	fn scan_tx_out_set_progress(&self) -> Option<f64>;
+	fn abort_scan_tx_out_set(&self) -> bool;
+	fn invalidate_block(&self, hash: GlobalH256) -> Result<(), Error>;
+	fn reconsider_block(&self, hash: GlobalH256) -> Result<(), Error>;
+	fn precious_block(&self, hash: GlobalH256) -> Result<(), Error>;
 }
 
 pub struct BlockChainClientCore {
-	network: Magic,
+	network: keys::Network,
 	storage: db::SharedStore,
+	consensus: ConsensusParams,
+	deployments: Deployments,
+	/// Progress of the running `scantxoutset` scan.
+	scan: Mutex<Option<Arc<db::UtxoScanProgress>>>,
+	/// Synchronization node, which switches to the best valid chain after blocks are invalidated or reconsidered.
+	local_sync_node: Option<sync::LocalNodeRef>,
 }
 
 impl BlockChainClientCore {
-	pub fn new(network: Magic, storage: db::SharedStore) -> Self {
+	/// `network` defines prefixes of the addresses, returned by the RPC.
+	pub fn new(network: keys::Network, storage: db::SharedStore, consensus: ConsensusParams) -> Self {
 
 		BlockChainClientCore {
 			network: network,
 			storage: storage,
+			consensus: consensus,
+			deployments: Deployments::new(),
+			scan: Mutex::new(None),
+			local_sync_node: None,
 		}
 	}
+
+	/// Enables `invalidateblock` and `reconsiderblock` calls.
+	pub fn with_local_sync_node(mut self, local_sync_node: sync::LocalNodeRef) -> Self {
+		self.local_sync_node = Some(local_sync_node);
+		self
+	}
+
+	fn local_sync_node(&self) -> Result<&sync::LocalNodeRef, Error> {
+		self.local_sync_node.as_ref().ok_or(execution("Synchronization is not available"))
+	}
+
+	/// Reads outputs, spent by the block non-coinbase transactions, from the block undo data or
+	/// from bodies of the spent transactions.
+	fn spent_outputs(&self, block: &chain::IndexedBlock) -> Result<Vec<db::SpentOutput>, Error> {
+		// undo data is missing for blocks, which were canonized before it has been introduced
+		if let Some(undo) = self.storage.block_undo(block.hash()) {
+			return Ok(undo.spent_outputs);
+		}
+
+		block.transactions.iter()
+			.skip(1)
+			.flat_map(|tx| tx.raw.inputs.iter())
+			.map(|input| {
+				let prevout = &input.previous_output;
+				let output = self.storage.transaction(&prevout.hash)
+					.and_then(|tx| tx.outputs.get(prevout.index as usize).cloned());
+				match (output, self.storage.transaction_meta(&prevout.hash)) {
+					(Some(output), Some(meta)) => Ok(db::SpentOutput {
+						output: output,
+						height: meta.height(),
+						is_coinbase: meta.is_coinbase(),
+					}),
+					_ => Err(transaction_output_not_found(prevout.clone())),
+				}
+			})
+			.collect()
+	}
+
+	/// Scans unspent outputs, written to the disk, for outputs with given scripts.
+	fn scan_unspent_outputs(&self, scripts: &HashMap<GlobalBytes, String>, progress: &db::UtxoScanProgress) -> Result<ScanTxOutSetResponse, Error> {
+		self.storage.flush().map_err(execution)?;
+		let snapshot = self.storage.utxo_snapshot().map_err(execution)?;
+
+		let mut txouts = 0;
+		let mut total_amount = 0;
+		let mut unspents = Vec::new();
+		for unspent in snapshot.scan_unspent_outputs(progress) {
+			let unspent = unspent.map_err(execution)?;
+			txouts += 1;
+			if let Some(desc) = scripts.get(&unspent.output.script_pubkey) {
+				total_amount += unspent.output.value;
+				unspents.push(ScanTxOutSetUnspent {
+					txid: unspent.outpoint.hash.into(),
+					vout: unspent.outpoint.index,
+					script_pub_key: unspent.output.script_pubkey.into(),
+					desc: desc.clone(),
+					amount: unspent.output.value as f64 / SATOSHIS_IN_COIN as f64,
+					coinbase: unspent.is_coinbase,
+					height: unspent.height,
+				});
+			}
+		}
+
+		Ok(ScanTxOutSetResponse {
+			success: !progress.is_aborted(),
+			txouts: txouts,
+			height: snapshot.best_block().number,
+			bestblock: snapshot.best_block().hash.clone().into(),
+			unspents: unspents,
+			total_amount: total_amount as f64 / SATOSHIS_IN_COIN as f64,
+		})
+	}
 }
 
 impl BlockChainClientCoreApi for BlockChainClientCore {
@@ -63,6 +196,34 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 		self.storage.difficulty()
 	}
 
+	fn blockchain_info(&self) -> GetBlockchainInfoResponse {
+		let best_block = self.storage.best_block();
+		let headers = self.storage.as_block_header_provider();
+		// deployments states are reported for the next block
+		let next_height = best_block.number + 1;
+		let bip9_softforks = self.consensus.deployments().into_iter()
+			.map(|deployment| {
+				let state = self.deployments.threshold_state(&deployment, next_height, headers, &self.consensus);
+				(deployment.name, Bip9SoftforkInfo {
+					status: threshold_state_name(state).to_owned(),
+					bit: if state == ThresholdState::Started { Some(deployment.bit) } else { None },
+					start_time: deployment.start_time,
+					timeout: deployment.timeout,
+					min_activation_height: deployment.min_activation_height,
+				})
+			})
+			.collect();
+
+		GetBlockchainInfoResponse {
+			chain: chain_name(self.consensus.network).to_owned(),
+			blocks: best_block.number,
+			bestblockhash: best_block.hash.clone().into(),
+			difficulty: self.storage.difficulty(),
+			mediantime: verification::median_timestamp_inclusive(best_block.hash, headers),
+			bip9_softforks: bip9_softforks,
+		}
+	}
+
 	fn raw_block(&self, hash: GlobalH256) -> Option<RawBlock> {
 		self.storage.block(hash.into())
 			.map(|block| {
@@ -79,7 +240,9 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 					Some(block_number) => (self.storage.best_block().number - block_number + 1) as i64,
 					None => -1,
 				};
-				let block_size = block.size();
+				let block_size = block.size_with_witness();
+				let block_stripped_size = block.size();
+				let block_weight = block.weight();
 				let median_time = verification::median_timestamp(
 					&block.header.raw,
 					self.storage.as_block_header_provider()
@@ -88,12 +251,12 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 				VerboseBlock {
 					confirmations: confirmations,
 					size: block_size as u32,
-					strippedsize: block_size as u32, // TODO: segwit
-					weight: block_size as u32, // TODO: segwit
+					strippedsize: block_stripped_size as u32,
+					weight: block_weight as u32,
 					height: height,
 					mediantime: Some(median_time),
 					difficulty: block.header.raw.bits.to_f64(),
-					chainwork: U256::default(), // TODO: read from storage
+					chainwork: self.storage.chain_work(block.hash()).unwrap_or_default().into(),
 					previousblockhash: Some(block.header.raw.previous_header_hash.clone().into()),
 					nextblockhash: height.and_then(|h| self.storage.block_hash(h + 1).map(|h| h.into())),
 					bits: block.header.raw.bits.into(),
@@ -101,13 +264,210 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 					merkleroot: block.header.raw.merkle_root_hash.clone().into(),
 					nonce: block.header.raw.nonce,
 					time: block.header.raw.time,
-					tx: block.transactions.into_iter().map(|t| t.hash.into()).collect(),
+					tx: BlockTransactions::Hashes(block.transactions.into_iter().map(|t| t.hash.into()).collect()),
 					version: block.header.raw.version,
 					version_hex: format!("{:x}", &block.header.raw.version),
 				}
 			})
 	}
 
+	fn verbose_block_transactions(&self, hash: GlobalH256, with_prevouts: bool) -> Option<Vec<BlockTransaction>> {
+		self.storage.indexed_block(hash.into())
+			.map(|block| {
+				// fee and spent outputs are omitted if spent transactions are unknown
+				let spent_outputs = self.spent_outputs(&block).ok();
+				let mut spent_offset = 0;
+				block.transactions.iter().enumerate().map(|(index, tx)| {
+					let tx_spent_outputs = match spent_outputs {
+						Some(ref spent_outputs) if index != 0 => {
+							let tx_spent_outputs = spent_outputs.get(spent_offset..spent_offset + tx.raw.inputs.len());
+							spent_offset += tx.raw.inputs.len();
+							tx_spent_outputs
+						},
+						_ => None,
+					};
+					block_transaction(self.network, &tx.raw, tx_spent_outputs, with_prevouts)
+				}).collect()
+			})
+	}
+
+	fn raw_block_header(&self, hash: GlobalH256) -> Option<RawBlockHeader> {
+		self.storage.block_header_bytes(hash.into())
+			.map(|header| header.into())
+	}
+
+	fn verbose_block_header(&self, hash: GlobalH256) -> Option<VerboseBlockHeader> {
+		self.storage.block_header(hash.clone().into())
+			.map(|header| {
+				let height = self.storage.block_number(&hash);
+				let confirmations = match height {
+					Some(block_number) => (self.storage.best_block().number - block_number + 1) as i64,
+					None => -1,
+				};
+
+				VerboseBlockHeader {
+					hash: hash.into(),
+					confirmations: confirmations,
+					height: height,
+					version: header.version,
+					version_hex: format!("{:x}", header.version),
+					merkleroot: header.merkle_root_hash.clone().into(),
+					time: header.time,
+					mediantime: verification::median_timestamp(&header, self.storage.as_block_header_provider()),
+					nonce: header.nonce,
+					bits: header.bits.into(),
+					difficulty: header.bits.to_f64(),
+					previousblockhash: Some(header.previous_header_hash.clone().into()),
+					nextblockhash: height.and_then(|h| self.storage.block_hash(h + 1).map(|h| h.into())),
+				}
+			})
+	}
+
+	fn chain_tips(&self) -> Vec<ChainTip> {
+		let best_block = self.storage.best_block();
+		let mut tips = vec![ChainTip {
+			height: best_block.number,
+			hash: best_block.hash.into(),
+			branchlen: 0,
+			status: "active".to_owned(),
+		}];
+
+		for hash in self.storage.chain_tips() {
+			if self.storage.block_number(&hash).is_some() {
+				continue;
+			}
+
+			// side chain blocks are fully verified before they're inserted, so the branch is only invalid,
+			// when one of its blocks is marked invalid by the operator
+			let mut branchlen = 0;
+			let mut ancestor = hash.clone();
+			let fork_height = loop {
+				if let Some(number) = self.storage.block_number(&ancestor) {
+					break Some(number);
+				}
+				match self.storage.block_header(ancestor.into()) {
+					Some(header) => {
+						ancestor = header.previous_header_hash;
+						branchlen += 1;
+					},
+					None => break None,
+				}
+			};
+
+			if let Some(fork_height) = fork_height {
+				let status = if self.storage.is_block_invalid(&hash) { "invalid" } else { "valid-fork" };
+				tips.push(ChainTip {
+					height: fork_height + branchlen,
+					hash: hash.into(),
+					branchlen: branchlen,
+					status: status.to_owned(),
+				});
+			}
+		}
+
+		// branches of the verified headers, which blocks aren't stored yet
+		for hash in self.storage.header_tips() {
+			let entry = match self.storage.header_entry(&hash) {
+				Some(ref entry) if entry.status.has_block => continue,
+				Some(entry) => entry,
+				None => continue,
+			};
+
+			let mut ancestor = hash.clone();
+			let fork_height = loop {
+				if let Some(number) = self.storage.block_number(&ancestor) {
+					break Some(number);
+				}
+				match self.storage.header_entry(&ancestor) {
+					Some(ancestor_entry) => ancestor = ancestor_entry.header.previous_header_hash,
+					None => break None,
+				}
+			};
+
+			if let Some(fork_height) = fork_height {
+				let status = if entry.status.invalid { "invalid" } else { "headers-only" };
+				tips.push(ChainTip {
+					height: entry.number,
+					hash: hash.into(),
+					branchlen: entry.number - fork_height,
+					status: status.to_owned(),
+				});
+			}
+		}
+
+		tips.sort_by(|a, b| b.height.cmp(&a.height));
+		tips
+	}
+
+	fn chain_tx_stats(&self, blocks: Option<u32>, hash: GlobalH256) -> Result<GetChainTxStatsResponse, Error> {
+		let height = match self.storage.block_number(&hash) {
+			Some(height) => height,
+			None => return Err(invalid_params("blockhash", "Block is not in main chain")),
+		};
+		let header = self.storage.block_header(hash.clone().into())
+			.ok_or(block_not_found(hash.reversed()))?;
+		let tx_count = self.storage.chain_tx_count(&hash)
+			.ok_or(execution("Number of transactions in the chain is unknown. Reindex is required"))?;
+
+		let blocks = match blocks {
+			Some(blocks) if blocks != 0 && blocks >= height =>
+				return Err(invalid_params("nblocks", "Invalid block count: should be between 0 and the block's height - 1")),
+			Some(blocks) => blocks,
+			None => cmp::min(DEFAULT_CHAIN_TX_STATS_BLOCKS, height.saturating_sub(1)),
+		};
+
+		let mut stats = GetChainTxStatsResponse {
+			time: header.time,
+			txcount: tx_count,
+			window_final_block_hash: hash.clone().into(),
+			window_final_block_height: height,
+			window_block_count: blocks,
+			window_tx_count: None,
+			window_interval: None,
+			txrate: None,
+		};
+		if blocks == 0 {
+			return Ok(stats);
+		}
+
+		let window_start = self.storage.block_hash(height - blocks)
+			.expect("window start is below the canon block; qed");
+		let window_start_tx_count = self.storage.chain_tx_count(&window_start)
+			.ok_or(execution("Number of transactions in the chain is unknown. Reindex is required"))?;
+		let headers = self.storage.as_block_header_provider();
+		let window_interval = verification::median_timestamp_inclusive(hash, headers)
+			.saturating_sub(verification::median_timestamp_inclusive(window_start, headers));
+		let window_tx_count = tx_count - window_start_tx_count;
+
+		stats.window_tx_count = Some(window_tx_count);
+		stats.window_interval = Some(window_interval);
+		if window_interval != 0 {
+			stats.txrate = Some(window_tx_count as f64 / window_interval as f64);
+		}
+		Ok(stats)
+	}
+
+	fn block_stats(&self, hash: GlobalH256) -> Result<GetBlockStatsResponse, Error> {
+		let height = match self.storage.block_number(&hash) {
+			Some(height) => height,
+			// not in the main branch => no response
+			None => return Err(block_not_found(hash.reversed())),
+		};
+
+		let block = match self.storage.indexed_block(hash.clone().into()) {
+			Some(block) => block,
+			None => return Err(execution("Block data is pruned")),
+		};
+
+		let spent_outputs: Vec<_> = self.spent_outputs(&block)?.into_iter().map(|spent| spent.output).collect();
+
+		let mut stats = block_stats(&block, &spent_outputs);
+		stats.height = Some(height);
+		stats.mediantime = Some(verification::median_timestamp_inclusive(hash, self.storage.as_block_header_provider()));
+		stats.subsidy = Some(verification::block_reward_satoshi(height));
+		Ok(stats)
+	}
+
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
 		let transaction = match self.storage.transaction(&prev_out.hash) {
 			Some(transaction) => transaction,
@@ -152,12 +512,7 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 				req_sigs: script.num_signatures_required() as u32,
 				script_type: script.script_type().into(),
 				addresses: script_addresses.into_iter().map(|a| Address {
-					network: match self.network {
-						Magic::Mainnet => keys::Network::Mainnet,
-						// there's no correct choices for Regtests && Other networks
-						// => let's just make Testnet key
-						_ => keys::Network::Testnet,
-					},
+					network: self.network,
 					hash: a.hash,
 					kind: a.kind,
 				}).collect(),
@@ -166,6 +521,157 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 			coinbase: transaction.is_coinbase(),
 		})
 	}
+
+	fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error> {
+		// snapshot only contains data, written to the disk
+		self.storage.flush().map_err(execution)?;
+		let snapshot = self.storage.utxo_snapshot().map_err(execution)?;
+		let stats = snapshot.stats().map_err(execution)?;
+
+		Ok(GetTxOutSetInfoResponse {
+			height: snapshot.best_block().number,
+			bestblock: snapshot.best_block().hash.clone().into(),
+			transactions: stats.transactions,
+			txouts: stats.txouts,
+			bogosize: stats.bogosize,
+			muhash: stats.muhash.into(),
+			disk_size: stats.disk_size,
+			total_amount: stats.total_amount as f64 / SATOSHIS_IN_COIN as f64,
+		})
+	}
+
+	fn dump_tx_out_set(&self, path: &str) -> Result<DumpTxOutSetResponse, Error> {
+		let path = Path::new(path);
+		self.storage.flush().map_err(execution)?;
+		let snapshot = self.storage.utxo_snapshot().map_err(execution)?;
+		let stats = write_new_file(path, |writer| snapshot.write_to(writer, self.consensus.network.into()).map_err(execution))?;
+
+		Ok(DumpTxOutSetResponse {
+			coins_written: stats.txouts,
+			base_hash: snapshot.best_block().hash.clone().into(),
+			base_height: snapshot.best_block().number,
+			path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).display().to_string(),
+			muhash: stats.muhash.into(),
+		})
+	}
+
+	fn dump_utxos(&self, path: &str, format: UtxoDumpFormat) -> Result<DumpUtxosResponse, Error> {
+		let path = Path::new(path);
+		// outputs are read from the database snapshot, so new blocks are imported while the dump is written
+		self.storage.flush().map_err(execution)?;
+		let snapshot = self.storage.utxo_snapshot().map_err(execution)?;
+		let written = write_new_file(path, |writer| write_utxo_dump(writer, &snapshot, format, self.network).map_err(execution))?;
+
+		Ok(DumpUtxosResponse {
+			coins_written: written,
+			base_hash: snapshot.best_block().hash.clone().into(),
+			base_height: snapshot.best_block().number,
+			path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).display().to_string(),
+		})
+	}
+
+	fn transaction_out_proof(&self, hashes: Vec<GlobalH256>, block_hash: Option<GlobalH256>) -> Result<GlobalBytes, Error> {
+		let block_hash = match block_hash {
+			Some(block_hash) => block_hash,
+			// without transaction index, only transactions with unspent outputs are found
+			None => self.storage.transaction_location(&hashes[0])
+				.map(|location| location.block_hash)
+				.or_else(|| self.storage.transaction_meta(&hashes[0])
+					.and_then(|meta| if meta.is_fully_spent() { None } else { self.storage.block_hash(meta.height()) }))
+				.ok_or(transaction_not_found(hashes[0].reversed()))?,
+		};
+
+		if !self.storage.contains_block(block_hash.clone().into()) {
+			return Err(block_not_found(block_hash.reversed()));
+		}
+
+		let block = match self.storage.indexed_block(block_hash.into()) {
+			Some(block) => block,
+			None => return Err(execution("Block data is pruned")),
+		};
+
+		let matched: HashSet<GlobalH256> = hashes.into_iter().collect();
+		if block.transactions.iter().filter(|tx| matched.contains(&tx.hash)).count() != matched.len() {
+			return Err(execution("Not all transactions are found in the block"));
+		}
+
+		// same serialization as of the `merkleblock` message
+		let tree = chain::PartialMerkleTree::from_block(&block, &matched);
+		let mut stream = Stream::new();
+		stream
+			.append(&block.header.raw)
+			.append(&(tree.tx_count as u32))
+			.append_list(&tree.hashes)
+			.append(&GlobalBytes::from(tree.flags_bytes()));
+		Ok(stream.out())
+	}
+
+	fn prune_blocks(&self, height: u32) -> Result<u32, Error> {
+		if !self.storage.is_prune_enabled() {
+			return Err(execution("Pruning is not enabled"));
+		}
+
+		self.storage.prune_blocks(height).map_err(execution)
+	}
+
+	fn verify_chain(&self, level: CheckLevel, blocks: u32) -> Vec<BadBlock> {
+		verification::check_chain(&*self.storage, &self.consensus, blocks, level)
+	}
+
+	fn scan_tx_out_set(&self, scripts: HashMap<GlobalBytes, String>) -> Result<ScanTxOutSetResponse, Error> {
+		let progress = {
+			let mut scan = self.scan.lock();
+			if scan.is_some() {
+				return Err(execution("Scan already in progress, use action \"abort\" or \"status\""));
+			}
+
+			let progress = Arc::new(db::UtxoScanProgress::default());
+			*scan = Some(progress.clone());
+			progress
+		};
+
+		let result = self.scan_unspent_outputs(&scripts, &progress);
+		*self.scan.lock() = None;
+		result
+	}
+
+	fn scan_tx_out_set_progress(&self) -> Option<f64> {
+		self.scan.lock().as_ref().map(|progress| progress.percentage())
+	}
+
+	fn abort_scan_tx_out_set(&self) -> bool {
+		match *self.scan.lock() {
+			Some(ref progress) => {
+				progress.abort();
+				true
+			},
+			None => false,
+		}
+	}
+
+	fn invalidate_block(&self, hash: GlobalH256) -> Result<(), Error> {
+		if !self.storage.contains_block(hash.clone().into()) {
+			return Err(block_not_found(hash.reversed()));
+		}
+
+		self.local_sync_node()?.invalidate_block(&hash).map_err(execution)
+	}
+
+	fn reconsider_block(&self, hash: GlobalH256) -> Result<(), Error> {
+		if !self.storage.contains_block(hash.clone().into()) {
+			return Err(block_not_found(hash.reversed()));
+		}
+
+		self.local_sync_node()?.reconsider_block(&hash).map_err(execution)
+	}
+
+	fn precious_block(&self, hash: GlobalH256) -> Result<(), Error> {
+		if !self.storage.contains_block(hash.clone().into()) {
+			return Err(block_not_found(hash.reversed()));
+		}
+
+		self.local_sync_node()?.precious_block(&hash).map_err(execution)
+	}
 }
 
 impl<T> BlockChainClient<T> where T: BlockChainClientCoreApi {
@@ -195,25 +701,96 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 		Ok(self.core.difficulty())
 	}
 
-	fn block(&self, hash: H256, verbose: Trailing<bool>) -> Result<GetBlockResponse, Error> {
+	fn blockchain_info(&self) -> Result<GetBlockchainInfoResponse, Error> {
+		let mut info = self.core.blockchain_info();
+		info.bestblockhash = info.bestblockhash.reversed();
+		Ok(info)
+	}
+
+	fn block(&self, hash: H256, verbosity: Trailing<BlockVerbosity>) -> Result<GetBlockResponse, Error> {
 		let global_hash: GlobalH256 = hash.clone().into();
-		if verbose.unwrap_or_default() {
-			let verbose_block = self.core.verbose_block(global_hash.reversed());
-			if let Some(mut verbose_block) = verbose_block {
-				verbose_block.previousblockhash = verbose_block.previousblockhash.map(|h| h.reversed());
-				verbose_block.nextblockhash = verbose_block.nextblockhash.map(|h| h.reversed());
-				verbose_block.hash = verbose_block.hash.reversed();
-				verbose_block.merkleroot = verbose_block.merkleroot.reversed();
-				verbose_block.tx = verbose_block.tx.into_iter().map(|h| h.reversed()).collect();
-				Some(GetBlockResponse::Verbose(verbose_block))
-			} else {
-				None
-			}
-		} else {
-			self.core.raw_block(global_hash.reversed())
+		let verbosity = verbosity.unwrap_or_default();
+		if verbosity == BlockVerbosity::Raw {
+			return self.core.raw_block(global_hash.reversed())
 				.map(|block| GetBlockResponse::Raw(block))
+				.ok_or(block_not_found(hash));
+		}
+
+		let mut verbose_block = self.core.verbose_block(global_hash.reversed())
+			.ok_or(block_not_found(hash.clone()))?;
+		verbose_block.previousblockhash = verbose_block.previousblockhash.map(|h| h.reversed());
+		verbose_block.nextblockhash = verbose_block.nextblockhash.map(|h| h.reversed());
+		verbose_block.hash = verbose_block.hash.reversed();
+		verbose_block.merkleroot = verbose_block.merkleroot.reversed();
+		verbose_block.tx = match verbosity {
+			BlockVerbosity::VerboseTransactions | BlockVerbosity::VerboseTransactionsWithPrevouts => {
+				// hashes of decoded transactions are already reversed
+				let with_prevouts = verbosity == BlockVerbosity::VerboseTransactionsWithPrevouts;
+				BlockTransactions::Verbose(self.core.verbose_block_transactions(global_hash.reversed(), with_prevouts)
+					.ok_or(block_not_found(hash))?)
+			},
+			_ => match verbose_block.tx {
+				BlockTransactions::Hashes(hashes) => BlockTransactions::Hashes(hashes.into_iter().map(|h| h.reversed()).collect()),
+				transactions => transactions,
+			},
+		};
+		Ok(GetBlockResponse::Verbose(verbose_block))
+	}
+
+	fn block_header(&self, hash: H256, verbose: Trailing<bool>) -> Result<GetBlockHeaderResponse, Error> {
+		let global_hash: GlobalH256 = hash.clone().into();
+		if !verbose.unwrap_or(true) {
+			return self.core.raw_block_header(global_hash.reversed())
+				.map(|header| GetBlockHeaderResponse::Raw(header))
+				.ok_or(block_not_found(hash));
+		}
+
+		let mut verbose_header = self.core.verbose_block_header(global_hash.reversed())
+			.ok_or(block_not_found(hash))?;
+		verbose_header.previousblockhash = verbose_header.previousblockhash.map(|h| h.reversed());
+		verbose_header.nextblockhash = verbose_header.nextblockhash.map(|h| h.reversed());
+		verbose_header.hash = verbose_header.hash.reversed();
+		verbose_header.merkleroot = verbose_header.merkleroot.reversed();
+		Ok(GetBlockHeaderResponse::Verbose(verbose_header))
+	}
+
+	fn chain_tips(&self) -> Result<Vec<ChainTip>, Error> {
+		Ok(self.core.chain_tips().into_iter()
+			.map(|mut tip| {
+				tip.hash = tip.hash.reversed();
+				tip
+			})
+			.collect())
+	}
+
+	fn chain_tx_stats(&self, blocks: Trailing<u32>, hash: Trailing<H256>) -> Result<GetChainTxStatsResponse, Error> {
+		let hash: Option<H256> = hash.into();
+		let global_hash: GlobalH256 = match hash {
+			Some(hash) => hash.reversed().into(),
+			None => self.core.best_block_hash(),
+		};
+
+		let mut stats = self.core.chain_tx_stats(blocks.into(), global_hash)?;
+		stats.window_final_block_hash = stats.window_final_block_hash.reversed();
+		Ok(stats)
+	}
+
+	fn block_stats(&self, hash_or_height: HashOrHeight, stats: Trailing<Vec<String>>) -> Result<GetBlockStatsResponse, Error> {
+		let global_hash: GlobalH256 = match hash_or_height {
+			HashOrHeight::Hash(hash) => hash.reversed().into(),
+			HashOrHeight::Height(height) => self.core.block_hash(height).ok_or(block_at_height_not_found(height))?,
+		};
+
+		let mut response = self.core.block_stats(global_hash)?;
+		response.blockhash = response.blockhash.map(|h| h.reversed());
+
+		let stats = stats.unwrap_or_default();
+		if stats.is_empty() {
+			return Ok(response);
 		}
-		.ok_or(block_not_found(hash))
+
+		response.select(&stats)
+			.map_err(|stat| invalid_params("stats", format!("Invalid selected statistic {}", stat)))
 	}
 
 	fn transaction_out(&self, transaction_hash: H256, out_index: u32, _include_mempool: Trailing<bool>) -> Result<GetTxOutResponse, Error> {
@@ -226,8 +803,295 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 			})
 	}
 
+	fn transaction_out_proof(&self, hashes: Vec<H256>, block_hash: Trailing<H256>) -> Result<Bytes, Error> {
+		let hashes: Vec<GlobalH256> = hashes.into_iter()
+			.map(|hash| {
+				let hash: GlobalH256 = hash.into();
+				hash.reversed()
+			})
+			.collect();
+		if hashes.is_empty() {
+			return Err(invalid_params("txids", "At least one transaction is required"));
+		}
+		if hashes.iter().collect::<HashSet<_>>().len() != hashes.len() {
+			return Err(invalid_params("txids", "Duplicated transaction"));
+		}
+
+		let block_hash: Option<H256> = block_hash.into();
+		let block_hash = block_hash.map(|hash| {
+			let hash: GlobalH256 = hash.into();
+			hash.reversed()
+		});
+		self.core.transaction_out_proof(hashes, block_hash).map(Into::into)
+	}
+
 	fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error> {
-		rpc_unimplemented!()
+		self.core.transaction_out_set_info()
+			.map(|mut response| {
+				response.bestblock = response.bestblock.reversed();
+				response.muhash = response.muhash.reversed();
+				response
+			})
+	}
+
+	fn dump_tx_out_set(&self, path: String) -> Result<DumpTxOutSetResponse, Error> {
+		self.core.dump_tx_out_set(&path)
+			.map(|mut response| {
+				response.base_hash = response.base_hash.reversed();
+				response.muhash = response.muhash.reversed();
+				response
+			})
+	}
+
+	fn dump_utxos(&self, path: String, format: Trailing<String>) -> Result<DumpUtxosResponse, Error> {
+		let format: Option<String> = format.into();
+		let format = match format {
+			None => UtxoDumpFormat::Csv,
+			Some(name) => UtxoDumpFormat::from_name(&name).ok_or_else(|| invalid_params("format", "Expected csv or binary"))?,
+		};
+
+		self.core.dump_utxos(&path, format)
+			.map(|mut response| {
+				response.base_hash = response.base_hash.reversed();
+				response
+			})
+	}
+
+	fn prune_blockchain(&self, height: u32) -> Result<i64, Error> {
+		// core returns number of pruned blocks, but RPC reports height of the last pruned block
+		self.core.prune_blocks(height).map(|pruned| pruned as i64 - 1)
+	}
+
+	fn verify_chain(&self, level: Trailing<u32>, blocks: Trailing<u32>) -> Result<bool, Error> {
+		// level 4 of bitcoind also reconnects blocks, which is already done at level 3
+		let level = match level.unwrap_or(DEFAULT_CHECK_LEVEL) {
+			0 => CheckLevel::Read,
+			1 => CheckLevel::Block,
+			2 => CheckLevel::Index,
+			3 | 4 => CheckLevel::Full,
+			level => return Err(invalid_params("checklevel", format!("Invalid check level {}. Expected 0-4", level))),
+		};
+
+		Ok(self.core.verify_chain(level, blocks.unwrap_or(DEFAULT_CHECK_BLOCKS)).is_empty())
+	}
+
+	fn scan_tx_out_set(&self, action: ScanTxOutSetAction, objects: Trailing<Vec<ScanObject>>) -> Result<ScanTxOutSetResult, Error> {
+		match action {
+			ScanTxOutSetAction::Status => Ok(ScanTxOutSetResult::Status(self.core.scan_tx_out_set_progress()
+				.map(|progress| ScanTxOutSetStatus { progress: progress }))),
+			ScanTxOutSetAction::Abort => Ok(ScanTxOutSetResult::Abort(self.core.abort_scan_tx_out_set())),
+			ScanTxOutSetAction::Start => {
+				let objects = objects.unwrap_or_default();
+				if objects.is_empty() {
+					return Err(invalid_params("scanobjects", "Scan objects are required to start the scan"));
+				}
+
+				let mut response = self.core.scan_tx_out_set(scan_scripts(&objects)?)?;
+				response.bestblock = response.bestblock.reversed();
+				for unspent in &mut response.unspents {
+					unspent.txid = unspent.txid.reversed();
+				}
+				Ok(ScanTxOutSetResult::Scan(response))
+			},
+		}
+	}
+
+	fn invalidate_block(&self, hash: H256) -> Result<(), Error> {
+		let global_hash: GlobalH256 = hash.into();
+		self.core.invalidate_block(global_hash.reversed())
+	}
+
+	fn reconsider_block(&self, hash: H256) -> Result<(), Error> {
+		let global_hash: GlobalH256 = hash.into();
+		self.core.reconsider_block(global_hash.reversed())
+	}
+
+	fn precious_block(&self, hash: H256) -> Result<(), Error> {
+		let global_hash: GlobalH256 = hash.into();
+		self.core.precious_block(global_hash.reversed())
+	}
+}
+
+/// Expands scan objects into output scripts, mapped to the descriptors they are derived from.
+fn scan_scripts(objects: &[ScanObject]) -> Result<HashMap<GlobalBytes, String>, Error> {
+	let mut scripts = HashMap::new();
+	for object in objects {
+		let descriptor: keys::Descriptor = object.desc.parse()
+			.map_err(|err: keys::Error| invalid_params("scanobjects", err.to_string()))?;
+		let (begin, end) = object.range.unwrap_or((0, DEFAULT_SCAN_RANGE_END));
+		if end < begin || end >= keys::HARDENED_INDEX || end - begin >= MAX_SCAN_RANGE_SIZE {
+			return Err(invalid_params("range", format!("Invalid range [{}, {}]", begin, end)));
+		}
+
+		// range of the scan object includes its end
+		let expanded = descriptor.expand(begin..end + 1)
+			.map_err(|err| invalid_params("scanobjects", err.to_string()))?;
+		let desc = descriptor.to_string();
+		for script in expanded {
+			scripts.insert(script, desc.clone());
+		}
+	}
+
+	Ok(scripts)
+}
+
+/// Writes the file, which doesn't exist yet. Data is written to the temporary file first,
+/// so the incomplete file is never left at `path`.
+fn write_new_file<F, T>(path: &Path, write: F) -> Result<T, Error> where F: FnOnce(&mut BufWriter<File>) -> Result<T, Error> {
+	if path.exists() {
+		return Err(execution(format!("{} already exists. Move it out of the way first", path.display())));
+	}
+
+	let temp_path = PathBuf::from(format!("{}.incomplete", path.display()));
+	let result = File::create(&temp_path)
+		.map_err(|err| execution(err.to_string()))
+		.and_then(|file| {
+			let mut writer = BufWriter::new(file);
+			let result = write(&mut writer)?;
+			writer.flush().map_err(|err| execution(err.to_string()))?;
+			Ok(result)
+		})
+		.and_then(|result| fs::rename(&temp_path, path).map(|_| result).map_err(|err| execution(err.to_string())));
+	if result.is_err() {
+		let _ = fs::remove_file(&temp_path);
+	}
+
+	result
+}
+
+fn chain_name(network: Magic) -> &'static str {
+	match network {
+		Magic::Mainnet => "main",
+		Magic::Testnet => "test",
+		Magic::Regtest | Magic::Unitest => "regtest",
+		Magic::Signet(_) => "signet",
+		Magic::Other(_) => "custom",
+	}
+}
+
+/// Computes statistics of the block, given outputs, spent by inputs of its non-coinbase transactions.
+fn block_stats(block: &chain::IndexedBlock, spent_outputs: &[chain::TransactionOutput]) -> GetBlockStatsResponse {
+	let mut spent_outputs = spent_outputs.iter();
+	let mut fees = Vec::new();
+	let mut sizes = Vec::new();
+	// (fee rate, weight) of every transaction
+	let mut fee_rates = Vec::new();
+	let (mut ins, mut outs, mut total_out, mut total_size, mut total_weight) = (0u64, 0u64, 0u64, 0u64, 0u64);
+	let (mut swtxs, mut swtotal_size, mut swtotal_weight) = (0u64, 0u64, 0u64);
+	let mut utxo_size_inc = 0i64;
+
+	for (tx_index, tx) in block.transactions.iter().enumerate() {
+		outs += tx.raw.outputs.len() as u64;
+		utxo_size_inc += tx.raw.outputs.iter().map(utxo_size).sum::<i64>();
+		if tx_index == 0 {
+			continue;
+		}
+
+		let mut spent_value = 0u64;
+		for spent_output in spent_outputs.by_ref().take(tx.raw.inputs.len()) {
+			spent_value += spent_output.value;
+			utxo_size_inc -= utxo_size(spent_output);
+		}
+
+		let fee = spent_value.saturating_sub(tx.raw.total_spends());
+		let size = tx.raw.total_size() as u64;
+		let weight = tx.raw.weight() as u64;
+		ins += tx.raw.inputs.len() as u64;
+		total_out += tx.raw.total_spends();
+		total_size += size;
+		total_weight += weight;
+		if tx.raw.has_witness() {
+			swtxs += 1;
+			swtotal_size += size;
+			swtotal_weight += weight;
+		}
+
+		fees.push(fee);
+		sizes.push(size);
+		fee_rates.push((fee / tx.raw.vsize() as u64, weight));
+	}
+
+	let totalfee = fees.iter().sum::<u64>();
+	let transactions = fees.len() as u64;
+	fee_rates.sort();
+
+	GetBlockStatsResponse {
+		avgfee: Some(if transactions != 0 { totalfee / transactions } else { 0 }),
+		avgfeerate: Some(if total_weight != 0 { totalfee * WITNESS_SCALE_FACTOR as u64 / total_weight } else { 0 }),
+		avgtxsize: Some(if transactions != 0 { total_size / transactions } else { 0 }),
+		blockhash: Some(block.hash().clone().into()),
+		feerate_percentiles: Some(fee_rate_percentiles(&fee_rates, total_weight)),
+		height: None,
+		ins: Some(ins),
+		maxfee: Some(fees.iter().cloned().max().unwrap_or_default()),
+		maxfeerate: Some(fee_rates.last().map(|&(fee_rate, _)| fee_rate).unwrap_or_default()),
+		maxtxsize: Some(sizes.iter().cloned().max().unwrap_or_default()),
+		medianfee: Some(median(&mut fees)),
+		mediantime: None,
+		mediantxsize: Some(median(&mut sizes)),
+		minfee: Some(fees.iter().cloned().min().unwrap_or_default()),
+		minfeerate: Some(fee_rates.first().map(|&(fee_rate, _)| fee_rate).unwrap_or_default()),
+		mintxsize: Some(sizes.iter().cloned().min().unwrap_or_default()),
+		outs: Some(outs),
+		subsidy: None,
+		swtotal_size: Some(swtotal_size),
+		swtotal_weight: Some(swtotal_weight),
+		swtxs: Some(swtxs),
+		time: Some(block.header.raw.time),
+		total_out: Some(total_out),
+		total_size: Some(total_size),
+		total_weight: Some(total_weight),
+		totalfee: Some(totalfee),
+		txs: Some(block.transactions.len() as u64),
+		utxo_increase: Some(outs as i64 - ins as i64),
+		utxo_size_inc: Some(utxo_size_inc),
+	}
+}
+
+/// Size of the output in the UTXO set.
+fn utxo_size(output: &chain::TransactionOutput) -> i64 {
+	serialize(output).len() as i64 + PER_UTXO_OVERHEAD
+}
+
+/// Median of the values. Average of two middle values is returned when number of values is even.
+fn median(values: &mut [u64]) -> u64 {
+	values.sort();
+	match values.len() {
+		0 => 0,
+		len if len % 2 == 0 => (values[len / 2 - 1] + values[len / 2]) / 2,
+		len => values[len / 2],
+	}
+}
+
+/// Fee rates at FEE_RATE_PERCENTILES of the total weight, given (fee rate, weight) pairs, sorted by fee rate.
+fn fee_rate_percentiles(fee_rates: &[(u64, u64)], total_weight: u64) -> Vec<u64> {
+	let mut percentiles = Vec::with_capacity(FEE_RATE_PERCENTILES.len());
+	let mut cumulative_weight = 0;
+	for &(fee_rate, weight) in fee_rates {
+		cumulative_weight += weight;
+		while percentiles.len() < FEE_RATE_PERCENTILES.len() {
+			let (numerator, denominator) = FEE_RATE_PERCENTILES[percentiles.len()];
+			if cumulative_weight < total_weight * numerator / denominator {
+				break;
+			}
+
+			percentiles.push(fee_rate);
+		}
+	}
+
+	// percentiles of the empty block are zero
+	let last_fee_rate = fee_rates.last().map(|&(fee_rate, _)| fee_rate).unwrap_or_default();
+	percentiles.resize(FEE_RATE_PERCENTILES.len(), last_fee_rate);
+	percentiles
+}
+
+fn threshold_state_name(state: ThresholdState) -> &'static str {
+	match state {
+		ThresholdState::Defined => "defined",
+		ThresholdState::Started => "started",
+		ThresholdState::LockedIn => "locked_in",
+		ThresholdState::Active => "active",
+		ThresholdState::Failed => "failed",
 	}
 }
 
@@ -236,20 +1100,23 @@ pub mod tests {
 	extern crate test_data;
 
 	use std::sync::Arc;
+	use serde_json;
 	use jsonrpc_core::IoHandler;
 	use jsonrpc_core::Error;
-	use db::{BlockChainDatabase};
+	use db::{BlockChainDatabase, BlockInvalidation, HeaderTree};
 	use primitives::bytes::Bytes as GlobalBytes;
 	use primitives::hash::H256 as GlobalH256;
 	use v1::types::{VerboseBlock, RawBlock};
 	use v1::traits::BlockChain;
 	use v1::types::{GetTxOutResponse, TransactionOutputScript};
-	use v1::helpers::errors::block_not_found;
+	use v1::helpers::errors::{block_not_found, transaction_not_found, execution};
 	use v1::types::Bytes;
 	use v1::types::H256;
 	use v1::types::ScriptType;
 	use chain::OutPoint;
-	use network::Magic;
+	use network::{ConsensusParams, ConsensusFork, Magic, Deployment};
+	use verification::CheckError;
+	use keys;
 	use super::*;
 
 	#[derive(Default)]
@@ -274,6 +1141,23 @@ pub mod tests {
 			1f64
 		}
 
+		fn blockchain_info(&self) -> GetBlockchainInfoResponse {
+			GetBlockchainInfoResponse {
+				chain: "main".to_owned(),
+				blocks: 1,
+				bestblockhash: test_data::genesis().hash().into(),
+				difficulty: 1f64,
+				mediantime: 1231006505,
+				bip9_softforks: vec![("csv".to_owned(), Bip9SoftforkInfo {
+					status: "started".to_owned(),
+					bit: Some(0),
+					start_time: 1462060800,
+					timeout: 1493596800,
+					min_activation_height: 0,
+				})].into_iter().collect(),
+			}
+		}
+
 		fn raw_block(&self, _hash: GlobalH256) -> Option<RawBlock> {
 			let b2_bytes: GlobalBytes = "010000004860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9bb0bc6649ffff001d08d2bd610101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d010bffffffff0100f2052a010000004341047211a824f55b505228e4c3d5194c1fcfaa15a456abdf37f9b9d97a4040afc073dee6c89064984f03385237d92167c13e236446b417ab79a0fcae412ae3316b77ac00000000".into();
 			Some(RawBlock::from(b2_bytes))
@@ -293,7 +1177,7 @@ pub mod tests {
 				version: 1,
 				version_hex: "1".to_owned(),
 				merkleroot: "d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into(),
-				tx: vec!["d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into()],
+				tx: BlockTransactions::Hashes(vec!["d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into()]),
 				time: 1231469744,
 				mediantime: None,
 				nonce: 1639830024,
@@ -305,26 +1189,173 @@ pub mod tests {
 			})
 		}
 
-		fn verbose_transaction_out(&self, _prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
-			Ok(GetTxOutResponse {
-				bestblock: H256::from(0x56),
-				confirmations: 777,
-				value: 100000.56,
-				script: TransactionOutputScript {
-					asm: "Hello, world!!!".to_owned(),
-					hex: Bytes::new(vec![1, 2, 3, 4]),
-					req_sigs: 777,
-					script_type: ScriptType::Multisig,
-					addresses: vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into(), "1H5m1XzvHsjWX3wwU781ubctznEpNACrNC".into()],
-				},
-				version: 33,
-				coinbase: false,
-			})
+		fn verbose_block_transactions(&self, _hash: GlobalH256, _with_prevouts: bool) -> Option<Vec<BlockTransaction>> {
+			Some(vec![block_transaction(keys::Network::Mainnet, &test_data::block_h2().transactions[0], None, false)])
 		}
-	}
 
-	impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
-		fn best_block_hash(&self) -> GlobalH256 {
+		fn raw_block_header(&self, _hash: GlobalH256) -> Option<RawBlockHeader> {
+			Some(serialize(&test_data::block_h2().block_header).into())
+		}
+
+		fn verbose_block_header(&self, _hash: GlobalH256) -> Option<VerboseBlockHeader> {
+			Some(VerboseBlockHeader {
+				hash: "bddd99ccfda39da1b108ce1a5d70038d0a967bacb68b6b63065f626a00000000".into(),
+				confirmations: 1, // h2
+				height: Some(2),
+				version: 1,
+				version_hex: "1".to_owned(),
+				merkleroot: "d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into(),
+				time: 1231469744,
+				mediantime: 1231469665,
+				nonce: 1639830024,
+				bits: 486604799,
+				difficulty: 1.0,
+				previousblockhash: Some("4860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000".into()),
+				nextblockhash: None,
+			})
+		}
+
+		fn chain_tips(&self) -> Vec<ChainTip> {
+			vec![ChainTip {
+				height: 2,
+				hash: "bddd99ccfda39da1b108ce1a5d70038d0a967bacb68b6b63065f626a00000000".into(),
+				branchlen: 0,
+				status: "active".to_owned(),
+			}, ChainTip {
+				height: 1,
+				hash: "0100000000000000000000000000000000000000000000000000000000000000".into(),
+				branchlen: 1,
+				status: "valid-fork".to_owned(),
+			}]
+		}
+
+		fn chain_tx_stats(&self, blocks: Option<u32>, hash: GlobalH256) -> Result<GetChainTxStatsResponse, Error> {
+			Ok(GetChainTxStatsResponse {
+				time: 1231469744,
+				txcount: 3,
+				window_final_block_hash: hash.into(),
+				window_final_block_height: 2,
+				window_block_count: blocks.unwrap_or(1),
+				window_tx_count: Some(1),
+				window_interval: Some(100),
+				txrate: Some(0.01),
+			})
+		}
+
+		fn block_stats(&self, hash: GlobalH256) -> Result<GetBlockStatsResponse, Error> {
+			Ok(GetBlockStatsResponse {
+				blockhash: Some(hash.into()),
+				height: Some(0),
+				txs: Some(1),
+				totalfee: Some(0),
+				..Default::default()
+			})
+		}
+
+		fn verbose_transaction_out(&self, _prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
+			Ok(GetTxOutResponse {
+				bestblock: H256::from(0x56),
+				confirmations: 777,
+				value: 100000.56,
+				script: TransactionOutputScript {
+					asm: "Hello, world!!!".to_owned(),
+					hex: Bytes::new(vec![1, 2, 3, 4]),
+					req_sigs: 777,
+					script_type: ScriptType::Multisig,
+					addresses: vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into(), "1H5m1XzvHsjWX3wwU781ubctznEpNACrNC".into()],
+				},
+				version: 33,
+				coinbase: false,
+			})
+		}
+
+		fn transaction_out_proof(&self, _hashes: Vec<GlobalH256>, _block_hash: Option<GlobalH256>) -> Result<GlobalBytes, Error> {
+			Ok(vec![1, 2, 3].into())
+		}
+
+		fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error> {
+			Ok(GetTxOutSetInfoResponse {
+				height: 2,
+				bestblock: H256::from(0x56),
+				transactions: 2,
+				txouts: 2,
+				bogosize: 234,
+				muhash: H256::from(0x57),
+				disk_size: 408,
+				total_amount: 100.0,
+			})
+		}
+
+		fn dump_tx_out_set(&self, path: &str) -> Result<DumpTxOutSetResponse, Error> {
+			Ok(DumpTxOutSetResponse {
+				coins_written: 2,
+				base_hash: H256::from(0x56),
+				base_height: 2,
+				path: path.to_owned(),
+				muhash: H256::from(0x57),
+			})
+		}
+
+		fn dump_utxos(&self, path: &str, _format: UtxoDumpFormat) -> Result<DumpUtxosResponse, Error> {
+			Ok(DumpUtxosResponse {
+				coins_written: 2,
+				base_hash: H256::from(0x56),
+				base_height: 2,
+				path: path.to_owned(),
+			})
+		}
+
+		fn prune_blocks(&self, height: u32) -> Result<u32, Error> {
+			Ok(height + 1)
+		}
+
+		fn verify_chain(&self, _level: CheckLevel, _blocks: u32) -> Vec<BadBlock> {
+			vec![]
+		}
+
+		fn scan_tx_out_set(&self, scripts: HashMap<GlobalBytes, String>) -> Result<ScanTxOutSetResponse, Error> {
+			let (script, desc) = scripts.into_iter().next().unwrap();
+			Ok(ScanTxOutSetResponse {
+				success: true,
+				txouts: 3,
+				height: 2,
+				bestblock: H256::from(0x56),
+				unspents: vec![ScanTxOutSetUnspent {
+					txid: H256::from(0x57),
+					vout: 0,
+					script_pub_key: script.into(),
+					desc: desc,
+					amount: 0.5,
+					coinbase: false,
+					height: 1,
+				}],
+				total_amount: 0.5,
+			})
+		}
+
+		fn scan_tx_out_set_progress(&self) -> Option<f64> {
+			Some(25.0)
+		}
+
+		fn abort_scan_tx_out_set(&self) -> bool {
+			true
+		}
+
+		fn invalidate_block(&self, _hash: GlobalH256) -> Result<(), Error> {
+			Ok(())
+		}
+
+		fn reconsider_block(&self, _hash: GlobalH256) -> Result<(), Error> {
+			Ok(())
+		}
+
+		fn precious_block(&self, _hash: GlobalH256) -> Result<(), Error> {
+			Ok(())
+		}
+	}
+
+	impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
+		fn best_block_hash(&self) -> GlobalH256 {
 			test_data::genesis().hash()
 		}
 
@@ -340,6 +1371,17 @@ pub mod tests {
 			1f64
 		}
 
+		fn blockchain_info(&self) -> GetBlockchainInfoResponse {
+			GetBlockchainInfoResponse {
+				chain: "main".to_owned(),
+				blocks: 1,
+				bestblockhash: test_data::genesis().hash().into(),
+				difficulty: 1f64,
+				mediantime: 1231006505,
+				bip9_softforks: Default::default(),
+			}
+		}
+
 		fn raw_block(&self, _hash: GlobalH256) -> Option<RawBlock> {
 			None
 		}
@@ -348,9 +1390,85 @@ pub mod tests {
 			None
 		}
 
+		fn verbose_block_transactions(&self, _hash: GlobalH256, _with_prevouts: bool) -> Option<Vec<BlockTransaction>> {
+			None
+		}
+
+		fn raw_block_header(&self, _hash: GlobalH256) -> Option<RawBlockHeader> {
+			None
+		}
+
+		fn verbose_block_header(&self, _hash: GlobalH256) -> Option<VerboseBlockHeader> {
+			None
+		}
+
+		fn chain_tips(&self) -> Vec<ChainTip> {
+			vec![]
+		}
+
+		fn chain_tx_stats(&self, _blocks: Option<u32>, _hash: GlobalH256) -> Result<GetChainTxStatsResponse, Error> {
+			Err(invalid_params("blockhash", "Block is not in main chain"))
+		}
+
+		fn block_stats(&self, hash: GlobalH256) -> Result<GetBlockStatsResponse, Error> {
+			Err(block_not_found(hash.reversed()))
+		}
+
 		fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
 			Err(block_not_found(prev_out.hash))
 		}
+
+		fn transaction_out_proof(&self, hashes: Vec<GlobalH256>, _block_hash: Option<GlobalH256>) -> Result<GlobalBytes, Error> {
+			Err(transaction_not_found(hashes[0].reversed()))
+		}
+
+		fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error> {
+			Err(execution("Database doesn't support snapshots"))
+		}
+
+		fn dump_tx_out_set(&self, path: &str) -> Result<DumpTxOutSetResponse, Error> {
+			Err(execution(format!("{} already exists. Move it out of the way first", path)))
+		}
+
+		fn dump_utxos(&self, path: &str, _format: UtxoDumpFormat) -> Result<DumpUtxosResponse, Error> {
+			Err(execution(format!("{} already exists. Move it out of the way first", path)))
+		}
+
+		fn prune_blocks(&self, _height: u32) -> Result<u32, Error> {
+			Err(execution("Pruning is not enabled"))
+		}
+
+		fn verify_chain(&self, _level: CheckLevel, _blocks: u32) -> Vec<BadBlock> {
+			vec![BadBlock {
+				number: 1,
+				hash: test_data::block_h1().hash(),
+				error: CheckError::MissingBlock,
+			}]
+		}
+
+		fn scan_tx_out_set(&self, _scripts: HashMap<GlobalBytes, String>) -> Result<ScanTxOutSetResponse, Error> {
+			Err(execution("Scan already in progress, use action \"abort\" or \"status\""))
+		}
+
+		fn scan_tx_out_set_progress(&self) -> Option<f64> {
+			None
+		}
+
+		fn abort_scan_tx_out_set(&self) -> bool {
+			false
+		}
+
+		fn invalidate_block(&self, hash: GlobalH256) -> Result<(), Error> {
+			Err(block_not_found(hash.reversed()))
+		}
+
+		fn reconsider_block(&self, hash: GlobalH256) -> Result<(), Error> {
+			Err(block_not_found(hash.reversed()))
+		}
+
+		fn precious_block(&self, hash: GlobalH256) -> Result<(), Error> {
+			Err(block_not_found(hash.reversed()))
+		}
 	}
 
 	#[test]
@@ -442,6 +1560,57 @@ pub mod tests {
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1.0,"id":1}"#);
 	}
 
+	#[test]
+	fn blockchain_info_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockchaininfo",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bestblockhash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","bip9_softforks":{"csv":{"bit":0,"min_activation_height":0,"startTime":1462060800,"status":"started","timeout":1493596800}},"blocks":1,"chain":"main","difficulty":1.0,"mediantime":1231006505},"id":1}"#);
+	}
+
+	#[test]
+	fn blockchain_info_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(
+			vec![
+				test_data::genesis().into(),
+				test_data::block_h1().into(),
+				test_data::block_h2().into(),
+			]
+		));
+
+		let mut consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		consensus.custom_deployments.push(Deployment {
+			name: "testdummy".into(),
+			bit: 28,
+			start_time: 0,
+			timeout: ::std::u32::MAX,
+			min_activation_height: 0,
+			threshold: None,
+			activation: None,
+		});
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, consensus);
+
+		let info = core.blockchain_info();
+		assert_eq!(info.chain, "main");
+		assert_eq!(info.blocks, 2);
+		assert_eq!(info.bestblockhash, test_data::block_h2().hash().into());
+		assert_eq!(info.bip9_softforks.len(), 4);
+		assert_eq!(info.bip9_softforks["csv"].status, "defined");
+		assert_eq!(info.bip9_softforks["segwit"].status, "defined");
+		assert_eq!(info.bip9_softforks["taproot"].status, "defined");
+		assert_eq!(info.bip9_softforks["testdummy"].status, "defined");
+		assert_eq!(info.bip9_softforks["testdummy"].bit, None);
+	}
+
 	#[test]
 	fn verbose_block_contents() {
 		let storage = Arc::new(BlockChainDatabase::init_test_chain(
@@ -452,7 +1621,7 @@ pub mod tests {
 			]
 		));
 
-		let core = BlockChainClientCore::new(Magic::Mainnet, storage);
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
 
 		// get info on block #1:
 		// https://blockexplorer.com/block/00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048
@@ -469,13 +1638,13 @@ pub mod tests {
 			version: 1,
 			version_hex: "1".to_owned(),
 			merkleroot: "982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e".into(),
-			tx: vec!["982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e".into()],
+			tx: BlockTransactions::Hashes(vec!["982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e".into()]),
 			time: 1231469665,
 			mediantime: Some(1231006505),
 			nonce: 2573394689,
 			bits: 486604799,
 			difficulty: 1.0,
-			chainwork: 0.into(),
+			chainwork: 0x200020002u64.into(),
 			previousblockhash: Some("6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000".into()),
 			nextblockhash: Some("bddd99ccfda39da1b108ce1a5d70038d0a967bacb68b6b63065f626a00000000".into()),
 		}));
@@ -495,13 +1664,13 @@ pub mod tests {
 			version: 1,
 			version_hex: "1".to_owned(),
 			merkleroot: "d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into(),
-			tx: vec!["d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into()],
+			tx: BlockTransactions::Hashes(vec!["d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into()]),
 			time: 1231469744,
 			mediantime: Some(1231469665),
 			nonce: 1639830024,
 			bits: 486604799,
 			difficulty: 1.0,
-			chainwork: 0.into(),
+			chainwork: 0x300030003u64.into(),
 			previousblockhash: Some("4860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000".into()),
 			nextblockhash: None,
 		}));
@@ -569,6 +1738,37 @@ pub mod tests {
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bits":486604799,"chainwork":"0","confirmations":1,"difficulty":1.0,"hash":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd","height":2,"mediantime":null,"merkleroot":"9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5","nextblockhash":null,"nonce":1639830024,"previousblockhash":"00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048","size":215,"strippedsize":215,"time":1231469744,"tx":["9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5"],"version":1,"versionHex":"1","weight":215},"id":1}"#);
 	}
 
+	#[test]
+	fn verbose_block_transactions_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", 2],
+				"id": 1
+			}"#)).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		assert_eq!(sample["result"]["hash"], "000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd");
+		assert_eq!(sample["result"]["tx"][0]["txid"], "9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5");
+		assert_eq!(sample["result"]["tx"][0]["vout"][0]["value"], 50.0);
+		assert!(sample["result"]["tx"][0].get("fee").is_none());
+
+		// verbosity above 3 is rejected
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", 4],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains(r#""error""#));
+	}
+
 	#[test]
 	fn verbose_block_error() {
 		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
@@ -589,7 +1789,7 @@ pub mod tests {
 	#[test]
 	fn verbose_transaction_out_contents() {
 		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
-		let core = BlockChainClientCore::new(Magic::Mainnet, storage);
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
 
 		// get info on tx from genesis block:
 		// https://blockchain.info/ru/tx/4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b
@@ -646,4 +1846,931 @@ pub mod tests {
 
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a"},"id":1}"#);
 	}
+
+	#[test]
+	fn transaction_out_proof_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "gettxoutproof",
+				"params": [["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"010203","id":1}"#);
+	}
+
+	#[test]
+	fn transaction_out_proof_duplicated_transactions() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "gettxoutproof",
+				"params": [["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert!(sample.contains("Duplicated transaction"));
+	}
+
+	#[test]
+	fn transaction_out_proof_contents() {
+		use ser::Reader;
+
+		let genesis: chain::IndexedBlock = test_data::genesis().into();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		let coinbase_hash = genesis.transactions[0].hash.clone();
+		assert_eq!(core.transaction_out_proof(vec![GlobalH256::from(1)], None).unwrap_err(),
+			transaction_not_found(GlobalH256::from(1).reversed()));
+
+		let proof = core.transaction_out_proof(vec![coinbase_hash.clone()], None).unwrap();
+		let mut reader = Reader::new(&proof);
+		let header: chain::BlockHeader = reader.read().unwrap();
+		let tx_count: u32 = reader.read().unwrap();
+		let hashes: Vec<GlobalH256> = reader.read_list().unwrap();
+		let flags: GlobalBytes = reader.read().unwrap();
+		assert!(reader.is_finished());
+		assert_eq!(header, genesis.header.raw);
+		let tree = chain::PartialMerkleTree::from_flags_bytes(tx_count as usize, hashes, &flags);
+		assert_eq!(tree.verify(&header.merkle_root_hash), Ok(vec![coinbase_hash.clone()]));
+
+		assert_eq!(core.transaction_out_proof(vec![coinbase_hash], Some(GlobalH256::from(1))).unwrap_err(),
+			block_not_found(GlobalH256::from(1).reversed()));
+	}
+
+	#[test]
+	fn prune_blockchain_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "pruneblockchain",
+				"params": [1000],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1000,"id":1}"#);
+	}
+
+	#[test]
+	fn prune_blockchain_failure() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "pruneblockchain",
+				"params": [1000],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Pruning is not enabled\""},"id":1}"#);
+	}
+
+	#[test]
+	fn verify_chain_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "verifychain",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+
+	#[test]
+	fn verify_chain_failure() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "verifychain",
+				"params": [4, 100],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn verify_chain_invalid_level() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "verifychain",
+				"params": [5],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: checklevel","data":"\"Invalid check level 5. Expected 0-4\""},"id":1}"#);
+	}
+
+	#[test]
+	fn verify_chain_checks_stored_blocks() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+		assert_eq!(core.verify_chain(CheckLevel::Full, 0), vec![]);
+		assert_eq!(core.verify_chain(CheckLevel::Index, 1), vec![]);
+	}
+
+	#[test]
+	fn block_stats_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockstats",
+				"params": [0, ["blockhash", "txs"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blockhash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","txs":1},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockstats",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blockhash":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f","height":0,"totalfee":0,"txs":1},"id":1}"#);
+	}
+
+	#[test]
+	fn block_stats_invalid_stat() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockstats",
+				"params": [0, ["txs", "fee"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: stats","data":"\"Invalid selected statistic fee\""},"id":1}"#);
+	}
+
+	#[test]
+	fn block_stats_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockstats",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#);
+	}
+
+	#[test]
+	fn block_stats_contents() {
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.transaction()
+				.output().value(1000).build()
+				.build()
+			.transaction()
+				.output().value(2000).build()
+				.build()
+			.merkled_header().build()
+			.build();
+		let block = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.transaction()
+				.input().hash(genesis.transactions()[1].hash()).build()
+				.output().value(900).build()
+				.build()
+			.transaction()
+				.input().hash(genesis.transactions()[2].hash()).build()
+				.output().value(1500).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+		let block_hash = block.hash();
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into(), block.into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork));
+		let stats = core.block_stats(block_hash.clone()).unwrap();
+
+		// every non-coinbase transaction is 61 bytes long
+		assert_eq!(stats, GetBlockStatsResponse {
+			avgfee: Some(300),
+			avgfeerate: Some(4),
+			avgtxsize: Some(61),
+			blockhash: Some(block_hash.into()),
+			feerate_percentiles: Some(vec![1, 1, 1, 8, 8]),
+			height: Some(1),
+			ins: Some(2),
+			maxfee: Some(500),
+			maxfeerate: Some(8),
+			maxtxsize: Some(61),
+			medianfee: Some(300),
+			mediantime: stats.mediantime,
+			mediantxsize: Some(61),
+			minfee: Some(100),
+			minfeerate: Some(1),
+			mintxsize: Some(61),
+			outs: Some(3),
+			subsidy: Some(5_000_000_000),
+			swtotal_size: Some(0),
+			swtotal_weight: Some(0),
+			swtxs: Some(0),
+			time: stats.time,
+			total_out: Some(2400),
+			total_size: Some(122),
+			total_weight: Some(488),
+			totalfee: Some(600),
+			txs: Some(3),
+			utxo_increase: Some(1),
+			utxo_size_inc: Some(51),
+		});
+
+		assert!(core.block_stats(GlobalH256::from(1)).is_err());
+	}
+
+	#[test]
+	fn verbose_block_transactions_contents() {
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.transaction()
+				.output().value(1000).build()
+				.build()
+			.merkled_header().build()
+			.build();
+		let block = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.transaction()
+				.input().hash(genesis.transactions()[1].hash()).build()
+				.output().value(900).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+		let block_hash = block.hash();
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into(), block.into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork));
+
+		let transactions = core.verbose_block_transactions(block_hash.clone(), false).unwrap();
+		assert_eq!(transactions.len(), 2);
+		assert_eq!(transactions[0].fee, None);
+		assert_eq!(transactions[0].vin[0].prevout, None);
+		assert_eq!(transactions[1].fee, Some(0.000001));
+		assert_eq!(transactions[1].vin[0].prevout, None);
+
+		let transactions = core.verbose_block_transactions(block_hash, true).unwrap();
+		assert_eq!(transactions[0].vin[0].prevout, None);
+		let prevout = transactions[1].vin[0].prevout.as_ref().unwrap();
+		assert!(!prevout.generated);
+		assert_eq!(prevout.height, 0);
+		assert_eq!(prevout.value, 0.00001);
+
+		assert_eq!(core.verbose_block_transactions(GlobalH256::from(1), true), None);
+	}
+
+	#[test]
+	fn scan_tx_out_set_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start", ["raw(51)"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bestblock":"0000000000000000000000000000000000000000000000000000000000000056","height":2,"success":true,"total_amount":0.5,"txouts":3,"unspents":[{"amount":0.5,"coinbase":false,"desc":"raw(51)#8lvh9jxk","height":1,"scriptPubKey":"51","txid":"0000000000000000000000000000000000000000000000000000000000000057","vout":0}]},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["status"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"progress":25.0},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["abort"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+
+	#[test]
+	fn scan_tx_out_set_failure() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start", ["raw(51)"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Scan already in progress, use action \\\"abort\\\" or \\\"status\\\"\""},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["status"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["abort"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn scan_tx_out_set_invalid_objects() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start", [{"desc": "raw(51)", "range": [5, 2]}]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: range","data":"\"Invalid range [5, 2]\""},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: scanobjects","data":"\"Scan objects are required to start the scan\""},"id":1}"#);
+	}
+
+	#[test]
+	fn scan_tx_out_set_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		let genesis_script: GlobalBytes = "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac".into();
+		let scripts = vec![(genesis_script.clone(), "raw".to_owned())].into_iter().collect();
+		assert_eq!(core.scan_tx_out_set(scripts), Ok(ScanTxOutSetResponse {
+			success: true,
+			txouts: 3,
+			height: 2,
+			bestblock: test_data::block_h2().hash().into(),
+			unspents: vec![ScanTxOutSetUnspent {
+				txid: test_data::genesis().transactions[0].hash().into(),
+				vout: 0,
+				script_pub_key: genesis_script.into(),
+				desc: "raw".to_owned(),
+				amount: 50.0,
+				coinbase: true,
+				height: 0,
+			}],
+			total_amount: 50.0,
+		}));
+
+		// scan is finished
+		assert_eq!(core.scan_tx_out_set_progress(), None);
+		assert!(!core.abort_scan_tx_out_set());
+	}
+
+	#[test]
+	fn transaction_out_set_info_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "gettxoutsetinfo",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bestblock":"0000000000000000000000000000000000000000000000000000000000000056","bogosize":234,"disk_size":408,"height":2,"muhash":"0000000000000000000000000000000000000000000000000000000000000057","total_amount":100.0,"transactions":2,"txouts":2},"id":1}"#);
+	}
+
+	#[test]
+	fn transaction_out_set_info_failure() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "gettxoutsetinfo",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Database doesn't support snapshots\""},"id":1}"#);
+	}
+
+	#[test]
+	fn dump_tx_out_set_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumptxoutset",
+				"params": ["/tmp/utxo.dat"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"base_hash":"0000000000000000000000000000000000000000000000000000000000000056","base_height":2,"coins_written":2,"muhash":"0000000000000000000000000000000000000000000000000000000000000057","path":"/tmp/utxo.dat"},"id":1}"#);
+	}
+
+	#[test]
+	fn dump_tx_out_set_failure() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumptxoutset",
+				"params": ["/tmp/utxo.dat"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"/tmp/utxo.dat already exists. Move it out of the way first\""},"id":1}"#);
+	}
+
+	#[test]
+	fn dump_tx_out_set_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+		let path = ::std::env::temp_dir().join(format!("pbtc-dumptxoutset-{}.dat", ::std::process::id()));
+		let path_str = path.display().to_string();
+
+		let muhash: GlobalH256 = "75c825c725abc4791ea245e81ce869d5e1417809404df098a8a0ba9541c3d1df".into();
+		assert_eq!(core.dump_tx_out_set(&path_str), Ok(DumpTxOutSetResponse {
+			coins_written: 2,
+			base_hash: test_data::block_h2().hash().into(),
+			base_height: 2,
+			path: fs::canonicalize(&path).unwrap().display().to_string(),
+			muhash: muhash.clone().into(),
+		}));
+
+		// existing file is never overwritten
+		assert!(core.dump_tx_out_set(&path_str).is_err());
+
+		let mut snapshot = db::UtxoSnapshotReader::new(File::open(&path).unwrap()).unwrap();
+		assert_eq!(snapshot.metadata().network_magic, u32::from(Magic::Mainnet));
+		assert_eq!(snapshot.verify(), Ok(muhash));
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn dump_utxos_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumputxos",
+				"params": ["/tmp/utxo.csv", "csv"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"base_hash":"0000000000000000000000000000000000000000000000000000000000000056","base_height":2,"coins_written":2,"path":"/tmp/utxo.csv"},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumputxos",
+				"params": ["/tmp/utxo.dat", "snapshot"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: format","data":"\"Expected csv or binary\""},"id":1}"#);
+	}
+
+	#[test]
+	fn dump_utxos_failure() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "dumputxos",
+				"params": ["/tmp/utxo.csv"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"/tmp/utxo.csv already exists. Move it out of the way first\""},"id":1}"#);
+	}
+
+	#[test]
+	fn dump_utxos_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+		let path = ::std::env::temp_dir().join(format!("pbtc-dumputxos-{}.csv", ::std::process::id()));
+		let path_str = path.display().to_string();
+
+		assert_eq!(core.dump_utxos(&path_str, UtxoDumpFormat::Csv), Ok(DumpUtxosResponse {
+			coins_written: 2,
+			base_hash: test_data::block_h2().hash().into(),
+			base_height: 2,
+			path: fs::canonicalize(&path).unwrap().display().to_string(),
+		}));
+
+		// existing file is never overwritten
+		assert!(core.dump_utxos(&path_str, UtxoDumpFormat::Csv).is_err());
+
+		let contents = fs::read_to_string(&path).unwrap();
+		let lines: Vec<_> = contents.lines().collect();
+		assert_eq!(lines.len(), 3);
+		assert_eq!(lines[0], "txid,vout,value,height,coinbase,script,address");
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn transaction_out_set_info_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		assert_eq!(core.transaction_out_set_info(), Ok(GetTxOutSetInfoResponse {
+			height: 2,
+			bestblock: test_data::block_h2().hash().into(),
+			transactions: 2,
+			txouts: 2,
+			bogosize: 234,
+			muhash: "75c825c725abc4791ea245e81ce869d5e1417809404df098a8a0ba9541c3d1df".into(),
+			disk_size: 408,
+			total_amount: 100.0,
+		}));
+	}
+
+	#[test]
+	fn block_header_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		// verbose header is returned by default
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bits":486604799,"confirmations":1,"difficulty":1.0,"hash":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd","height":2,"mediantime":1231469665,"merkleroot":"9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5","nextblockhash":null,"nonce":1639830024,"previousblockhash":"00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048","time":1231469744,"version":1,"versionHex":"1"},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", false],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"010000004860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9bb0bc6649ffff001d08d2bd61","id":1}"#);
+	}
+
+	#[test]
+	fn block_header_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", true],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#);
+	}
+
+	#[test]
+	fn block_header_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		assert_eq!(core.verbose_block_header(test_data::block_h1().hash()), Some(VerboseBlockHeader {
+			hash: "4860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000".into(),
+			confirmations: 2, // h1 + h2
+			height: Some(1),
+			version: 1,
+			version_hex: "1".to_owned(),
+			merkleroot: "982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e".into(),
+			time: 1231469665,
+			mediantime: 1231006505,
+			nonce: 2573394689,
+			bits: 486604799,
+			difficulty: 1.0,
+			previousblockhash: Some("6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000".into()),
+			nextblockhash: Some("bddd99ccfda39da1b108ce1a5d70038d0a967bacb68b6b63065f626a00000000".into()),
+		}));
+		assert_eq!(core.raw_block_header(test_data::block_h1().hash()), Some(serialize(&test_data::block_h1().block_header).into()));
+		assert_eq!(core.verbose_block_header(GlobalH256::default()), None);
+	}
+
+	#[test]
+	fn chain_tips_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getchaintips",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[{"branchlen":0,"hash":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd","height":2,"status":"active"},{"branchlen":1,"hash":"0000000000000000000000000000000000000000000000000000000000000001","height":1,"status":"valid-fork"}],"id":1}"#);
+	}
+
+	#[test]
+	fn chain_tips_contents() {
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]);
+		let side_block: chain::IndexedBlock = test_data::block_builder()
+			.header().parent(test_data::block_h0().hash()).build()
+			.build()
+			.into();
+		storage.insert(side_block.clone()).unwrap();
+		let storage = Arc::new(storage);
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage.clone(), ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		assert_eq!(core.chain_tips(), vec![ChainTip {
+			height: 2,
+			hash: test_data::block_h2().hash().into(),
+			branchlen: 0,
+			status: "active".to_owned(),
+		}, ChainTip {
+			height: 1,
+			hash: side_block.hash().clone().into(),
+			branchlen: 1,
+			status: "valid-fork".to_owned(),
+		}]);
+
+		storage.invalidate_block(side_block.hash()).unwrap();
+		assert_eq!(core.chain_tips()[1].status, "invalid");
+
+		let header: chain::IndexedBlockHeader = test_data::block_builder()
+			.header().parent(test_data::block_h2().hash()).build()
+			.build()
+			.block_header
+			.into();
+		storage.insert_headers(&[header.clone()]).unwrap();
+		assert_eq!(core.chain_tips()[0], ChainTip {
+			height: 3,
+			hash: header.hash.clone().into(),
+			branchlen: 1,
+			status: "headers-only".to_owned(),
+		});
+	}
+
+	#[test]
+	fn chain_tx_stats_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getchaintxstats",
+				"params": [1, "000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"time":1231469744,"txcount":3,"txrate":0.01,"window_block_count":1,"window_final_block_hash":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd","window_final_block_height":2,"window_interval":100,"window_tx_count":1},"id":1}"#);
+	}
+
+	#[test]
+	fn chain_tx_stats_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getchaintxstats",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: blockhash","data":"\"Block is not in main chain\""},"id":1}"#);
+	}
+
+	#[test]
+	fn chain_tx_stats_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, storage, ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		// window is limited by the block height by default
+		assert_eq!(core.chain_tx_stats(None, test_data::block_h2().hash()), Ok(GetChainTxStatsResponse {
+			time: 1231469744,
+			txcount: 3,
+			window_final_block_hash: test_data::block_h2().hash().into(),
+			window_final_block_height: 2,
+			window_block_count: 1,
+			window_tx_count: Some(1),
+			window_interval: Some(0),
+			txrate: None,
+		}));
+		assert_eq!(core.chain_tx_stats(Some(0), test_data::block_h1().hash()), Ok(GetChainTxStatsResponse {
+			time: 1231469665,
+			txcount: 2,
+			window_final_block_hash: test_data::block_h1().hash().into(),
+			window_final_block_height: 1,
+			window_block_count: 0,
+			window_tx_count: None,
+			window_interval: None,
+			txrate: None,
+		}));
+		assert!(core.chain_tx_stats(Some(2), test_data::block_h2().hash()).is_err());
+		assert!(core.chain_tx_stats(None, GlobalH256::default()).is_err());
+	}
+
+	#[test]
+	fn invalidate_block_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "invalidateblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn invalidate_block_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "invalidateblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#);
+	}
+
+	#[test]
+	fn reconsider_block_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "reconsiderblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn invalidate_block_contents() {
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::block_h0().into(), test_data::block_h1().into()]);
+		let core = BlockChainClientCore::new(keys::Network::Mainnet, Arc::new(storage), ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork));
+
+		assert_eq!(core.invalidate_block(GlobalH256::default()).unwrap_err().message, "Block with given hash is not found");
+		// canon chain can't be switched without synchronization node
+		assert_eq!(core.invalidate_block(test_data::block_h1().hash()).unwrap_err().data, execution("Synchronization is not available").data);
+		assert_eq!(core.reconsider_block(test_data::block_h1().hash()).unwrap_err().data, execution("Synchronization is not available").data);
+		assert_eq!(core.precious_block(test_data::block_h1().hash()).unwrap_err().data, execution("Synchronization is not available").data);
+	}
+
+	#[test]
+	fn precious_block_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "preciousblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn precious_block_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "preciousblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#);
+	}
 }
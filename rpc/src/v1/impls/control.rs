@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use v1::traits::Control;
+use v1::helpers::errors;
+use jsonrpc_macros::Trailing;
+use jsonrpc_core::Error;
+use logs::LogHandle;
+
+pub struct ControlClient<T: ControlClientCoreApi> {
+	core: T,
+}
+
+pub trait ControlClientCoreApi: Send + Sync + 'static {
+	fn stop(&self);
+	fn log_filters(&self) -> String;
+	fn update_log_filters(&self, filters: &str) -> Result<String, String>;
+}
+
+pub struct ControlClientCore {
+	shutdown: Arc<AtomicBool>,
+	logger: LogHandle,
+}
+
+impl ControlClientCore {
+	pub fn new(shutdown: Arc<AtomicBool>, logger: LogHandle) -> Self {
+		ControlClientCore {
+			shutdown: shutdown,
+			logger: logger,
+		}
+	}
+}
+
+impl ControlClientCoreApi for ControlClientCore {
+	fn stop(&self) {
+		self.shutdown.store(true, Ordering::SeqCst);
+	}
+
+	fn log_filters(&self) -> String {
+		self.logger.filters().to_string()
+	}
+
+	fn update_log_filters(&self, filters: &str) -> Result<String, String> {
+		self.logger.update_filters(filters).map(|filters| filters.to_string())
+	}
+}
+
+impl<T> ControlClient<T> where T: ControlClientCoreApi {
+	pub fn new(core: T) -> Self {
+		ControlClient {
+			core: core,
+		}
+	}
+}
+
+impl<T> Control for ControlClient<T> where T: ControlClientCoreApi {
+	fn stop(&self) -> Result<String, Error> {
+		self.core.stop();
+		Ok("Parity bitcoin server stopping".into())
+	}
+
+	fn logging(&self, filters: Trailing<String>) -> Result<String, Error> {
+		let filters: Option<String> = filters.into();
+		match filters {
+			Some(filters) => self.core.update_log_filters(&filters).map_err(|err| errors::invalid_params("filters", err)),
+			None => Ok(self.core.log_filters()),
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use jsonrpc_core::IoHandler;
+	use logs::LogHandle;
+	use v1::traits::Control;
+	use super::*;
+
+	fn default_client(shutdown: Arc<AtomicBool>) -> ControlClient<ControlClientCore> {
+		let logger = LogHandle::new("sync=info".parse().unwrap());
+		ControlClient::new(ControlClientCore::new(shutdown, logger))
+	}
+
+	#[test]
+	fn stop_requests_shutdown() {
+		let shutdown = Arc::new(AtomicBool::new(false));
+		let client = default_client(shutdown.clone());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "stop",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"Parity bitcoin server stopping","id":1}"#);
+		assert!(shutdown.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn logging_updates_filters() {
+		let client = default_client(Arc::new(AtomicBool::new(false)));
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "logging",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"sync=info","id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "logging",
+				"params": ["sync=debug,p2p=trace"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"sync=debug,p2p=trace","id":1}"#);
+	}
+}
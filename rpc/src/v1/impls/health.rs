@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use jsonrpc_core::Error;
+use v1::traits::Health;
+use v1::types::HealthResponse;
+use db;
+use p2p;
+use sync;
+
+/// Node is considered synchronized, if it is not further than this number of blocks behind the best header,
+/// known to the node or reported by its peers.
+pub const MAX_BLOCKS_BEHIND: u32 = 2;
+
+pub struct HealthClient<T: HealthClientCoreApi> {
+	core: T,
+}
+
+pub trait HealthClientCoreApi: Send + Sync + 'static {
+	fn health(&self) -> HealthResponse;
+}
+
+pub struct HealthClientCore {
+	local_sync_node: sync::LocalNodeRef,
+	storage: db::SharedStore,
+	p2p: Arc<p2p::Context>,
+}
+
+impl HealthClientCore {
+	pub fn new(local_sync_node: sync::LocalNodeRef, storage: db::SharedStore, p2p: Arc<p2p::Context>) -> Self {
+		HealthClientCore {
+			local_sync_node: local_sync_node,
+			storage: storage,
+			p2p: p2p,
+		}
+	}
+}
+
+/// Builds health response from the synchronization status.
+/// `peers_best_height` is the best height, reported by connected peers in their `version` messages.
+/// `tip_time` is the timestamp of the best block, or `None` if its header can't be read from the database.
+pub fn health_response(status: &sync::SyncStatus, peers_best_height: u32, tip_time: Option<u32>, current_time: u32, peers: usize) -> HealthResponse {
+	let best_known_height = ::std::cmp::max(status.best_header.number, peers_best_height);
+	let blocks_behind = best_known_height.saturating_sub(status.best_block.number);
+	let synced = !status.is_synchronizing && blocks_behind <= MAX_BLOCKS_BEHIND;
+	let database = match tip_time {
+		Some(_) => "ok".to_owned(),
+		None => "best block header is missing".to_owned(),
+	};
+
+	HealthResponse {
+		healthy: synced && peers != 0 && tip_time.is_some(),
+		synced: synced,
+		blocks: status.best_block.number,
+		headers: status.best_header.number,
+		blocks_behind: blocks_behind,
+		tip_age: tip_time.map_or(0, |tip_time| current_time.saturating_sub(tip_time)),
+		peers: peers,
+		database: database,
+	}
+}
+
+impl HealthClientCoreApi for HealthClientCore {
+	fn health(&self) -> HealthResponse {
+		let status = self.local_sync_node.sync_status();
+		let tip_time = self.storage.block_header(status.best_block.hash.clone().into())
+			.map(|header| header.time);
+		let current_time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs() as u32).unwrap_or_default();
+		let peers = self.p2p.connections().info();
+		let peers_best_height = peers.iter()
+			.filter_map(|peer| peer.version_message.start_height())
+			.filter(|height| *height > 0)
+			.max()
+			.unwrap_or_default() as u32;
+		health_response(&status, peers_best_height, tip_time, current_time, peers.len())
+	}
+}
+
+impl<T> HealthClient<T> where T: HealthClientCoreApi {
+	pub fn new(core: T) -> Self {
+		HealthClient {
+			core: core,
+		}
+	}
+}
+
+impl<T> Health for HealthClient<T> where T: HealthClientCoreApi {
+	fn health(&self) -> Result<HealthResponse, Error> {
+		Ok(self.core.health())
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use jsonrpc_core::IoHandler;
+	use db::BestBlock;
+	use primitives::hash::H256;
+	use sync::SyncStatus;
+	use v1::traits::Health;
+	use v1::types::HealthResponse;
+	use super::*;
+
+	#[derive(Default)]
+	struct SuccessHealthClientCore;
+
+	impl HealthClientCoreApi for SuccessHealthClientCore {
+		fn health(&self) -> HealthResponse {
+			HealthResponse {
+				healthy: true,
+				synced: true,
+				blocks: 10,
+				headers: 10,
+				blocks_behind: 0,
+				tip_age: 60,
+				peers: 8,
+				database: "ok".to_owned(),
+			}
+		}
+	}
+
+	fn sync_status(is_synchronizing: bool, best_block: u32, best_header: u32) -> SyncStatus {
+		SyncStatus {
+			is_synchronizing: is_synchronizing,
+			best_block: BestBlock { number: best_block, hash: H256::from(best_block as u8) },
+			best_header: BestBlock { number: best_header, hash: H256::from(best_header as u8) },
+		}
+	}
+
+	#[test]
+	fn health_response_synced() {
+		let health = health_response(&sync_status(false, 100, 101), 0, Some(1000), 1600, 8);
+		assert!(health.healthy);
+		assert!(health.synced);
+		assert_eq!(health.blocks_behind, 1);
+		assert_eq!(health.tip_age, 600);
+	}
+
+	#[test]
+	fn health_response_not_synced() {
+		assert!(!health_response(&sync_status(true, 100, 100), 0, Some(1000), 1600, 8).synced);
+		assert!(!health_response(&sync_status(false, 100, 110), 0, Some(1000), 1600, 8).synced);
+		// no peers
+		let health = health_response(&sync_status(false, 100, 100), 0, Some(1000), 1600, 0);
+		assert!(health.synced && !health.healthy);
+		// unreadable database
+		let health = health_response(&sync_status(false, 100, 100), 90, None, 1600, 8);
+		assert!(!health.healthy);
+		assert_eq!(&health.database, "best block header is missing");
+	}
+
+	#[test]
+	fn health_response_peers_are_ahead() {
+		// headers are only requested from the lagging peers
+		let health = health_response(&sync_status(false, 100, 100), 150, Some(1000), 1600, 8);
+		assert!(!health.synced);
+		assert_eq!(health.headers, 100);
+		assert_eq!(health.blocks_behind, 50);
+		assert!(health_response(&sync_status(false, 100, 100), 101, Some(1000), 1600, 8).synced);
+	}
+
+	#[test]
+	fn healthcheck_success() {
+		let client = HealthClient::new(SuccessHealthClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "healthcheck",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"healthy":true,"synced":true,"blocks":10,"headers":10,"blocks_behind":0,"tip_age":60,"peers":8,"database":"ok"},"id":1}"#);
+	}
+}
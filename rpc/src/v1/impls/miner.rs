@@ -1,25 +1,89 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use parking_lot::{Mutex, Condvar};
 use v1::traits::Miner;
-use v1::types::{BlockTemplate, BlockTemplateRequest};
+use v1::types::{RawBlock, BlockTemplate, BlockTemplateRequest, BlockTemplateRequestMode, GetBlockTemplateResponse,
+	EstimateSmartFeeResponse, EstimateRawFeeResponse};
+use v1::helpers::errors::invalid_params;
+use jsonrpc_macros::Trailing;
 use jsonrpc_core::Error;
-use sync;
+use ser::{Reader, deserialize};
+use chain::{Block, IndexedBlock, IndexedTransaction};
+use primitives::hash::H256 as GlobalH256;
+use sync::{self, SyncListener};
 use miner;
 
+/// Memory pool changes finish long polling only after this timeout
+const LONGPOLL_MEMPOOL_TIMEOUT_SECS: u64 = 60;
+/// Length of the best block hash prefix of the long polling identifier
+const LONGPOLL_HASH_LEN: usize = 64;
+/// Default share of transactions, confirmed within the target, required by estimaterawfee
+const DEFAULT_RAW_FEE_THRESHOLD: f64 = 0.95;
+
 pub struct MinerClient<T: MinerClientCoreApi> {
 	core: T,
 }
 
 pub trait MinerClientCoreApi: Send + Sync + 'static {
 	fn get_block_template(&self) -> miner::BlockTemplate;
+	fn verify_block_proposal(&self, block: IndexedBlock) -> Option<String>;
+	fn submit_block(&self, block: IndexedBlock) -> Option<String>;
+	/// Identifier of the current template state: best block hash, followed by memory pool transactions counter
+	fn longpoll_id(&self) -> String;
+	/// Waits until template state differs from `longpollid`, or timeout expires. Returns identifier of the current state
+	fn wait_longpoll_id_change(&self, longpollid: &str, timeout: Duration) -> String;
+	fn estimate_smart_fee(&self, target: u32, conservative: bool) -> Option<miner::FeeEstimate>;
+	fn estimate_raw_fee(&self, target: u32, threshold: f64, horizon: miner::FeeEstimateHorizon) -> Option<miner::RawFeeEstimate>;
 }
 
 pub struct MinerClientCore {
 	local_sync_node: sync::LocalNodeRef,
+	template_changes: Arc<TemplateChanges>,
+}
+
+/// Wakes up long polling requests, when block template may have changed.
+#[derive(Default)]
+struct TemplateChanges {
+	/// Number of changes so far
+	counter: Mutex<u64>,
+	changed: Condvar,
+}
+
+impl TemplateChanges {
+	fn notify(&self) {
+		*self.counter.lock() += 1;
+		self.changed.notify_all();
+	}
+}
+
+/// Reports best block and memory pool changes to the long polling requests.
+struct TemplateChangesNotifier {
+	changes: Arc<TemplateChanges>,
+}
+
+impl SyncListener for TemplateChangesNotifier {
+	fn synchronization_state_switched(&self, _is_synchronizing: bool) {
+	}
+
+	fn best_storage_block_inserted(&self, _block_hash: &GlobalH256) {
+		self.changes.notify();
+	}
+
+	fn transaction_accepted(&self, _transaction: &IndexedTransaction) {
+		self.changes.notify();
+	}
 }
 
 impl MinerClientCore {
 	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
+		let template_changes = Arc::new(TemplateChanges::default());
+		local_sync_node.install_sync_listener(Box::new(TemplateChangesNotifier {
+			changes: template_changes.clone(),
+		}));
+
 		MinerClientCore {
 			local_sync_node: local_sync_node,
+			template_changes: template_changes,
 		}
 	}
 }
@@ -28,6 +92,45 @@ impl MinerClientCoreApi for MinerClientCore {
 	fn get_block_template(&self) -> miner::BlockTemplate {
 		self.local_sync_node.get_block_template()
 	}
+
+	fn verify_block_proposal(&self, block: IndexedBlock) -> Option<String> {
+		self.local_sync_node.verify_block_proposal(block)
+	}
+
+	fn submit_block(&self, block: IndexedBlock) -> Option<String> {
+		self.local_sync_node.submit_block(block)
+	}
+
+	fn longpoll_id(&self) -> String {
+		let (best_block_hash, transactions_counter) = self.local_sync_node.block_template_state();
+		format!("{}{}", best_block_hash.to_reversed_str(), transactions_counter)
+	}
+
+	fn wait_longpoll_id_change(&self, longpollid: &str, timeout: Duration) -> String {
+		let deadline = Instant::now() + timeout;
+		loop {
+			// changes counter is read before the state, so that changes, made after the state is read, aren't missed
+			let counter = *self.template_changes.counter.lock();
+			let current = self.longpoll_id();
+			let now = Instant::now();
+			if current != longpollid || now >= deadline {
+				return current;
+			}
+
+			let mut changes = self.template_changes.counter.lock();
+			if *changes == counter {
+				self.template_changes.changed.wait_for(&mut changes, deadline - now);
+			}
+		}
+	}
+
+	fn estimate_smart_fee(&self, target: u32, conservative: bool) -> Option<miner::FeeEstimate> {
+		self.local_sync_node.estimate_smart_fee(target, conservative)
+	}
+
+	fn estimate_raw_fee(&self, target: u32, threshold: f64, horizon: miner::FeeEstimateHorizon) -> Option<miner::RawFeeEstimate> {
+		self.local_sync_node.estimate_raw_fee(target, threshold, horizon)
+	}
 }
 
 impl<T> MinerClient<T> where T: MinerClientCoreApi {
@@ -36,14 +139,107 @@ impl<T> MinerClient<T> where T: MinerClientCoreApi {
 			core: core,
 		}
 	}
+
+	/// Waits until template, identified by `longpollid`, is outdated (BIP22 long polling).
+	fn wait_template_change(&self, longpollid: &str) {
+		let started = Instant::now();
+		let mempool_timeout = Duration::from_secs(LONGPOLL_MEMPOOL_TIMEOUT_SECS);
+		let mut current = longpollid.to_owned();
+		loop {
+			// new best block
+			if current.get(..LONGPOLL_HASH_LEN) != longpollid.get(..LONGPOLL_HASH_LEN) {
+				return;
+			}
+			// new transactions, but template isn't refreshed too often
+			let elapsed = started.elapsed();
+			if current != longpollid && elapsed >= mempool_timeout {
+				return;
+			}
+
+			let timeout = if elapsed < mempool_timeout { mempool_timeout - elapsed } else { mempool_timeout };
+			current = self.core.wait_longpoll_id_change(&current, timeout);
+		}
+	}
 }
 
 impl<T> Miner for MinerClient<T> where T: MinerClientCoreApi {
-	fn get_block_template(&self, _request: BlockTemplateRequest) -> Result<BlockTemplate, Error> {
-		Ok(self.core.get_block_template().into())
+	fn get_block_template(&self, request: BlockTemplateRequest) -> Result<GetBlockTemplateResponse, Error> {
+		if request.mode == Some(BlockTemplateRequestMode::Proposal) {
+			let data: Vec<u8> = match request.data {
+				Some(data) => data.into(),
+				None => return Err(invalid_params("data", "block data is required in proposal mode")),
+			};
+			let block: Block = try!(deserialize(Reader::new(&data)).map_err(|e| invalid_params("data", e)));
+			return Ok(GetBlockTemplateResponse::Proposal(self.core.verify_block_proposal(block.into())));
+		}
+
+		if let Some(ref longpollid) = request.longpollid {
+			self.wait_template_change(longpollid);
+		}
+
+		let longpollid = self.core.longpoll_id();
+		let template = self.core.get_block_template();
+		let client_rules = request.rules.unwrap_or_default();
+		if let Some(rule) = template.rules.iter().find(|rule| BlockTemplate::is_required_rule(rule) && !client_rules.contains(*rule)) {
+			return Err(invalid_params("rules", format!("getblocktemplate must be called with the {} rule set", rule)));
+		}
+
+		let mut template: BlockTemplate = template.into();
+		template.longpollid = Some(longpollid);
+		Ok(GetBlockTemplateResponse::Template(template))
+	}
+
+	fn submit_block(&self, raw_block: RawBlock) -> Result<Option<String>, Error> {
+		let data: Vec<u8> = raw_block.into();
+		let block: Block = try!(deserialize(Reader::new(&data)).map_err(|e| invalid_params("block", e)));
+		Ok(self.core.submit_block(block.into()))
+	}
+
+	fn estimate_smart_fee(&self, target: u32, estimate_mode: Trailing<String>) -> Result<EstimateSmartFeeResponse, Error> {
+		check_confirmation_target(target)?;
+		let estimate_mode: Option<String> = estimate_mode.into();
+		let conservative = match estimate_mode.map(|mode| mode.to_uppercase()) {
+			None => true,
+			Some(ref mode) if mode == "UNSET" || mode == "CONSERVATIVE" => true,
+			Some(ref mode) if mode == "ECONOMICAL" => false,
+			Some(mode) => return Err(invalid_params("estimate_mode", format!("Invalid estimate_mode parameter {}", mode))),
+		};
+
+		Ok(match self.core.estimate_smart_fee(target, conservative) {
+			Some(estimate) => estimate.into(),
+			None => EstimateSmartFeeResponse {
+				feerate: None,
+				errors: Some(vec!["Insufficient data or no feerate found".to_owned()]),
+				blocks: 0,
+			},
+		})
+	}
+
+	fn estimate_raw_fee(&self, target: u32, threshold: Trailing<f64>) -> Result<EstimateRawFeeResponse, Error> {
+		check_confirmation_target(target)?;
+		let threshold: Option<f64> = threshold.into();
+		let threshold = threshold.unwrap_or(DEFAULT_RAW_FEE_THRESHOLD);
+		if threshold < 0.0 || threshold > 1.0 {
+			return Err(invalid_params("threshold", "Invalid threshold, must be between 0 and 1"));
+		}
+
+		// horizons, which don't track the target, are omitted
+		let estimate = |horizon| self.core.estimate_raw_fee(target, threshold, horizon).map(Into::into);
+		Ok(EstimateRawFeeResponse {
+			short: estimate(miner::FeeEstimateHorizon::Short),
+			medium: estimate(miner::FeeEstimateHorizon::Medium),
+			long: estimate(miner::FeeEstimateHorizon::Long),
+		})
 	}
 }
 
+fn check_confirmation_target(target: u32) -> Result<(), Error> {
+	if target == 0 || target > miner::MAX_CONFIRMATION_TARGET {
+		return Err(invalid_params("conf_target", format!("Invalid conf_target, must be between 1 and {}", miner::MAX_CONFIRMATION_TARGET)));
+	}
+	Ok(())
+}
+
 #[cfg(test)]
 pub mod tests {
 	use jsonrpc_core::IoHandler;
@@ -54,23 +250,100 @@ pub mod tests {
 	use super::*;
 
 	#[derive(Default)]
-	struct SuccessMinerClientCore;
+	struct SuccessMinerClientCore {
+		rules: Vec<String>,
+	}
 
 	impl MinerClientCoreApi for SuccessMinerClientCore {
 		fn get_block_template(&self) -> miner::BlockTemplate {
 			let tx: chain::Transaction = "00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000".into();
+			let mut rules = vec!["csv".to_owned()];
+			rules.extend(self.rules.iter().cloned());
 			miner::BlockTemplate {
 				version: 777,
 				previous_header_hash: H256::from(1),
 				time: 33,
+				min_time: 22,
 				bits: 44.into(),
 				height: 55,
 				transactions: vec![
-					tx.into(),
+					miner::BlockTemplateTransaction {
+						transaction: tx.into(),
+						fee: 10,
+						sigops: 4,
+						weight: 240,
+						depends: vec![],
+					},
 				],
 				coinbase_value: 66,
 				size_limit: 77,
 				sigop_limit: 88,
+				weight_limit: Some(99),
+				rules: rules,
+				vbavailable: vec![("taproot".to_owned(), 2)].into_iter().collect(),
+				default_witness_commitment: None,
+			}
+		}
+
+		fn verify_block_proposal(&self, block: chain::IndexedBlock) -> Option<String> {
+			if block.transactions.is_empty() {
+				Some("bad-blk-length".to_owned())
+			} else {
+				None
+			}
+		}
+
+		fn submit_block(&self, block: chain::IndexedBlock) -> Option<String> {
+			if block.header.raw.nonce == 0 {
+				Some("high-hash".to_owned())
+			} else {
+				None
+			}
+		}
+
+		fn longpoll_id(&self) -> String {
+			format!("{}{}", H256::from(1).to_reversed_str(), 5)
+		}
+
+		fn wait_longpoll_id_change(&self, _longpollid: &str, _timeout: Duration) -> String {
+			self.longpoll_id()
+		}
+
+		fn estimate_smart_fee(&self, target: u32, conservative: bool) -> Option<miner::FeeEstimate> {
+			if target > 100 {
+				return None;
+			}
+
+			Some(miner::FeeEstimate {
+				fee_rate: if conservative { 20_000 } else { 10_000 },
+				blocks: target,
+			})
+		}
+
+		fn estimate_raw_fee(&self, _target: u32, _threshold: f64, horizon: miner::FeeEstimateHorizon) -> Option<miner::RawFeeEstimate> {
+			match horizon {
+				miner::FeeEstimateHorizon::Short => Some(miner::RawFeeEstimate {
+					fee_rate: Some(10_000.4),
+					decay: 0.962,
+					scale: 1,
+					pass: Some(miner::FeeEstimateBucket {
+						start: 9_500.0,
+						end: 10_500.0,
+						within_target: 10.0,
+						total_confirmed: 10.0,
+						in_mempool: 0.0,
+						left_mempool: 0.0,
+					}),
+					fail: None,
+				}),
+				miner::FeeEstimateHorizon::Medium => Some(miner::RawFeeEstimate {
+					fee_rate: None,
+					decay: 0.9952,
+					scale: 2,
+					pass: None,
+					fail: None,
+				}),
+				miner::FeeEstimateHorizon::Long => None,
 			}
 		}
 	}
@@ -91,6 +364,196 @@ pub mod tests {
 
 		// direct hash is 0100000000000000000000000000000000000000000000000000000000000000
 		// but client expects reverse hash
-		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bits":44,"coinbaseaux":null,"coinbasetxn":null,"coinbasevalue":66,"curtime":33,"height":55,"mintime":null,"mutable":null,"noncerange":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","rules":null,"sigoplimit":88,"sizelimit":77,"target":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","depends":null,"fee":null,"hash":null,"required":false,"sigops":null,"txid":null,"weight":null}],"vbavailable":null,"vbrequired":null,"version":777,"weightlimit":null},"id":1}"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bits":44,"capabilities":["proposal"],"coinbaseaux":{},"coinbasetxn":null,"coinbasevalue":66,"curtime":33,"height":55,"longpollid":"00000000000000000000000000000000000000000000000000000000000000015","mintime":22,"mutable":["time","transactions","prevblock"],"noncerange":"00000000ffffffff","previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","rules":["csv"],"sigoplimit":88,"sizelimit":77,"target":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","depends":[],"fee":10,"hash":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","required":false,"sigops":4,"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","weight":240}],"vbavailable":{"taproot":2},"vbrequired":0,"version":777,"weightlimit":99},"id":1}"#);
+	}
+
+	#[test]
+	fn getblocktemplate_requires_segwit_rule() {
+		let client = MinerClient::new(SuccessMinerClientCore { rules: vec!["segwit".to_owned()] });
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{}],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains("getblocktemplate must be called with the segwit rule set"));
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{"rules": ["segwit"]}],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains(r#""rules":["csv","!segwit"]"#));
+	}
+
+	#[test]
+	fn getblocktemplate_longpoll_returns_changed_template() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		// best block has changed since the previous template => template is returned immediately
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{"longpollid": "00000000000000000000000000000000000000000000000000000000000000025"}],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains(r#""longpollid":"00000000000000000000000000000000000000000000000000000000000000015""#));
+	}
+
+	#[test]
+	fn getblocktemplate_proposal() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		// block with single coinbase transaction
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{"mode": "proposal", "data": "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"}],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+
+		// block without transactions
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{"mode": "proposal", "data": "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c00"}],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"bad-blk-length","id":1}"#);
+	}
+
+	#[test]
+	fn submitblock() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		// genesis block
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitblock",
+				"params": ["0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+
+		// genesis block with zero nonce
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitblock",
+				"params": ["0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d000000000101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"high-hash","id":1}"#);
+
+		// truncated block
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitblock",
+				"params": ["0100000000"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: block","data":"UnexpectedEnd"},"id":1}"#);
+	}
+
+	#[test]
+	fn estimatesmartfee_success() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatesmartfee",
+				"params": [6],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blocks":6,"feerate":0.0002},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatesmartfee",
+				"params": [6, "economical"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blocks":6,"feerate":0.0001},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatesmartfee",
+				"params": [200],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"blocks":0,"errors":["Insufficient data or no feerate found"]},"id":1}"#);
+	}
+
+	#[test]
+	fn estimatesmartfee_invalid_params() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatesmartfee",
+				"params": [1009],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains("Invalid conf_target, must be between 1 and 1008"));
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatesmartfee",
+				"params": [6, "fast"],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains("Invalid estimate_mode parameter FAST"));
+	}
+
+	#[test]
+	fn estimaterawfee_success() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimaterawfee",
+				"params": [6],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"medium":{"decay":0.9952,"errors":["Insufficient data or no feerate found which meets threshold"],"scale":2},"short":{"decay":0.962,"feerate":0.0001,"pass":{"endrange":10500.0,"inmempool":0.0,"leftmempool":0.0,"startrange":9500.0,"totalconfirmed":10.0,"withintarget":10.0},"scale":1}},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimaterawfee",
+				"params": [6, 1.5],
+				"id": 1
+			}"#)).unwrap();
+		assert!(sample.contains("Invalid threshold, must be between 0 and 1"));
 	}
 }
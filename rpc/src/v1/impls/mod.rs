@@ -2,8 +2,16 @@ mod blockchain;
 mod miner;
 mod raw;
 mod network;
+mod control;
+mod health;
+mod address;
+mod wallet;
 
-pub use self::blockchain::{BlockChainClient, BlockChainClientCore};
+pub use self::blockchain::{BlockChainClient, BlockChainClientCore, BlockChainClientCoreApi};
 pub use self::miner::{MinerClient, MinerClientCore};
 pub use self::raw::{RawClient, RawClientCore};
 pub use self::network::{NetworkClient, NetworkClientCore};
+pub use self::control::{ControlClient, ControlClientCore};
+pub use self::health::{HealthClient, HealthClientCore, HealthClientCoreApi};
+pub use self::address::{AddressClient, AddressClientCore, AddressClientCoreApi};
+pub use self::wallet::{WalletClient, WalletClientCore, WalletClientCoreApi};
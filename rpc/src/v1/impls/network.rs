@@ -1,19 +1,27 @@
 use std::sync::Arc;
 use std::net::{SocketAddr, IpAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
 use v1::traits::Network as NetworkRpc;
-use v1::types::{AddNodeOperation, NodeInfo};
+use v1::types::{AddNodeOperation, NodeInfo, SetBanOperation, BannedNode, GetPeerInfoResponse, GetNetTotalsResponse};
 use jsonrpc_core::Error;
 use jsonrpc_macros::Trailing;
 use v1::helpers::errors;
 use p2p;
+use sync;
 
 pub trait NetworkApi : Send + Sync + 'static {
-	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
-	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
+	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::AddressManagerError>;
+	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::AddressManagerError>;
 	fn connect(&self, socket_addr: SocketAddr);
-	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError>;
+	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::AddressManagerError>;
 	fn nodes_info(&self) -> Vec<NodeInfo>;
 	fn connection_count(&self) -> usize;
+	fn peers_info(&self) -> Vec<GetPeerInfoResponse>;
+	fn net_totals(&self) -> GetNetTotalsResponse;
+	fn ban(&self, node_addr: IpAddr, duration: i64) -> Result<(), p2p::BanListError>;
+	fn unban(&self, node_addr: IpAddr) -> Result<(), p2p::BanListError>;
+	fn banned(&self) -> Vec<p2p::BanEntry>;
+	fn clear_banned(&self);
 }
 
 impl<T> NetworkRpc for NetworkClient<T> where T: NetworkApi {
@@ -55,6 +63,41 @@ impl<T> NetworkRpc for NetworkClient<T> where T: NetworkApi {
 	fn connection_count(&self) -> Result<usize, Error> {
 		Ok(self.api.connection_count())
 	}
+
+	fn peer_info(&self) -> Result<Vec<GetPeerInfoResponse>, Error> {
+		Ok(self.api.peers_info())
+	}
+
+	fn net_totals(&self) -> Result<GetNetTotalsResponse, Error> {
+		Ok(self.api.net_totals())
+	}
+
+	fn set_ban(&self, node_addr: String, operation: SetBanOperation, ban_time: Trailing<i64>) -> Result<(), Error> {
+		let addr = try!(node_addr.parse().map_err(
+			|_| errors::invalid_params("node", "Invalid ip address format, should be ip address (127.0.0.1)")));
+		match operation {
+			SetBanOperation::Add => {
+				let ban_time: Option<i64> = ban_time.into();
+				let ban_time = match ban_time {
+					Some(ban_time) if ban_time > 0 => ban_time,
+					_ => p2p::DEFAULT_BAN_TIME,
+				};
+				self.api.ban(addr, ban_time).map_err(|_| errors::node_already_banned())
+			},
+			SetBanOperation::Remove => {
+				self.api.unban(addr).map_err(|_| errors::node_not_banned())
+			},
+		}
+	}
+
+	fn list_banned(&self) -> Result<Vec<BannedNode>, Error> {
+		Ok(self.api.banned().into_iter().map(Into::into).collect())
+	}
+
+	fn clear_banned(&self) -> Result<(), Error> {
+		self.api.clear_banned();
+		Ok(())
+	}
 }
 
 pub struct NetworkClient<T: NetworkApi> {
@@ -71,20 +114,24 @@ impl<T> NetworkClient<T> where T: NetworkApi {
 
 pub struct NetworkClientCore {
 	p2p: Arc<p2p::Context>,
+	local_sync_node: sync::LocalNodeRef,
 }
 
 impl NetworkClientCore {
-	pub fn new(p2p: Arc<p2p::Context>) -> Self {
-		NetworkClientCore { p2p: p2p }
+	pub fn new(p2p: Arc<p2p::Context>, local_sync_node: sync::LocalNodeRef) -> Self {
+		NetworkClientCore {
+			p2p: p2p,
+			local_sync_node: local_sync_node,
+		}
 	}
 }
 
 impl NetworkApi for NetworkClientCore {
-	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
+	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::AddressManagerError> {
 		self.p2p.add_node(socket_addr)
 	}
 
-	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
+	fn remove_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::AddressManagerError> {
 		self.p2p.remove_node(socket_addr)
 	}
 
@@ -92,13 +139,13 @@ impl NetworkApi for NetworkClientCore {
 		p2p::Context::connect_normal(self.p2p.clone(), socket_addr);
 	}
 
-	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError> {
+	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::AddressManagerError> {
 		let exact_node = try!(
 			self.p2p.nodes()
 				.iter()
 				.find(|n| n.address().ip() == node_addr)
 				.cloned()
-				.ok_or(p2p::NodeTableError::NoAddressInTable)
+				.ok_or(p2p::AddressManagerError::NoAddressInTable)
 		);
 
 		let peers: Vec<p2p::PeerInfo> = self.p2p.connections().info()
@@ -130,4 +177,42 @@ impl NetworkApi for NetworkClientCore {
 	fn connection_count(&self) -> usize {
 		self.p2p.connections().count()
 	}
+
+	fn peers_info(&self) -> Vec<GetPeerInfoResponse> {
+		let mut peers: Vec<_> = self.p2p.connections().stats()
+			.into_iter()
+			.map(|(info, stats)| {
+				let sync_information = self.local_sync_node.peer_sync_information(info.id);
+				GetPeerInfoResponse::new(info, stats, sync_information)
+			})
+			.collect();
+		peers.sort_by_key(|peer| peer.id);
+		peers
+	}
+
+	fn net_totals(&self) -> GetNetTotalsResponse {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+		let traffic_counter = self.p2p.traffic_counter();
+		GetNetTotalsResponse::new(
+			traffic_counter.totals(),
+			traffic_counter.upload_target(now.as_secs() as u32),
+			now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1_000_000,
+		)
+	}
+
+	fn ban(&self, node_addr: IpAddr, duration: i64) -> Result<(), p2p::BanListError> {
+		self.p2p.ban(node_addr, duration)
+	}
+
+	fn unban(&self, node_addr: IpAddr) -> Result<(), p2p::BanListError> {
+		self.p2p.unban(&node_addr)
+	}
+
+	fn banned(&self) -> Vec<p2p::BanEntry> {
+		self.p2p.banned()
+	}
+
+	fn clear_banned(&self) {
+		self.p2p.clear_banned()
+	}
 }
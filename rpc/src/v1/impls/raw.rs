@@ -1,14 +1,22 @@
 use jsonrpc_core::Error;
 use jsonrpc_macros::Trailing;
-use ser::{Reader, serialize, deserialize};
+use ser::{Reader, serialize, serialize_with_flags, deserialize, SERIALIZE_TRANSACTION_WITNESS};
 use v1::traits::Raw;
-use v1::types::{RawTransaction, TransactionInput, TransactionOutput, TransactionOutputs, Transaction, GetRawTransactionResponse};
+use v1::types::{RawTransaction, TransactionInput, TransactionOutput, TransactionOutputs, Transaction, GetRawTransactionResponse,
+	GetMemPoolEntryResponse, GetMemPoolInfoResponse, GetRawMemPoolResponse, DecodePsbtResponse, FinalizePsbtResponse,
+	Bytes, DebugScriptResponse, DebugScriptStep, TestMemPoolAcceptResult, SubmitPackageResponse};
 use v1::types::H256;
-use v1::helpers::errors::{execution, invalid_params};
+use v1::helpers::errors::{execution, invalid_params, transaction_not_found};
+use v1::helpers::transaction::{verbose_transaction, TransactionBlock};
 use chain::Transaction as GlobalTransaction;
 use primitives::bytes::Bytes as GlobalBytes;
 use primitives::hash::H256 as GlobalH256;
-use sync;
+use global_script::{Script, Stack, Interpreter, VerificationFlags, NoopSignatureChecker, SignatureVersion};
+use miner::{MemoryPoolEntryInformation, MemoryPoolInformation};
+use psbt::Psbt;
+use db;
+use keys;
+use sync::{self, MAX_PACKAGE_COUNT};
 
 pub struct RawClient<T: RawClientCoreApi> {
 	core: T,
@@ -16,20 +24,57 @@ pub struct RawClient<T: RawClientCoreApi> {
 
 pub trait RawClientCoreApi: Send + Sync + 'static {
 	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, String>;
+	fn test_accept_transactions(&self, transactions: Vec<GlobalTransaction>) -> Vec<sync::TestAcceptResult>;
+	fn accept_package(&self, transactions: Vec<GlobalTransaction>) -> sync::PackageAcceptResult;
 	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<GlobalTransaction, String>;
+	fn memory_pool_hashes(&self) -> Vec<GlobalH256>;
+	fn memory_pool_entry(&self, hash: &GlobalH256) -> Option<MemoryPoolEntryInformation>;
+	fn memory_pool_information(&self) -> MemoryPoolInformation;
+	fn memory_pool_ancestors(&self, hash: &GlobalH256) -> Option<Vec<GlobalH256>>;
+	fn memory_pool_descendants(&self, hash: &GlobalH256) -> Option<Vec<GlobalH256>>;
+	fn transaction(&self, hash: &GlobalH256) -> Option<GlobalTransaction>;
+	fn verbose_transaction(&self, hash: &GlobalH256) -> Option<Transaction>;
+	fn decode_transaction(&self, transaction: GlobalTransaction) -> Transaction;
 }
 
 pub struct RawClientCore {
+	network: keys::Network,
 	local_sync_node: sync::LocalNodeRef,
+	storage: db::SharedStore,
 }
 
 impl RawClientCore {
-	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
+	pub fn new(network: keys::Network, local_sync_node: sync::LocalNodeRef, storage: db::SharedStore) -> Self {
 		RawClientCore {
+			network: network,
 			local_sync_node: local_sync_node,
+			storage: storage,
 		}
 	}
 
+	/// Returns memory pool or main chain transaction and the block, which includes it.
+	fn find_transaction(&self, hash: &GlobalH256) -> Option<(GlobalTransaction, Option<TransactionBlock>)> {
+		if let Some(transaction) = self.local_sync_node.memory_pool_transaction(hash) {
+			return Some((transaction, None));
+		}
+
+		// without transaction index, only transactions with unspent outputs are found
+		let block_hash = self.storage.transaction_location(hash)
+			.map(|location| location.block_hash)
+			.or_else(|| self.storage.transaction_meta(hash)
+				.and_then(|meta| if meta.is_fully_spent() { None } else { self.storage.block_hash(meta.height()) }));
+
+		let best_block_number = self.storage.best_block().number;
+		block_hash
+			.and_then(|block_hash| self.storage.block_number(&block_hash).map(|number| (block_hash, number)))
+			.and_then(|(block_hash, number)| self.storage.block_header(number.into()).map(|header| TransactionBlock {
+				hash: block_hash,
+				time: header.time,
+				confirmations: best_block_number - number + 1,
+			}))
+			.and_then(|block| self.storage.transaction(hash).map(|transaction| (transaction, Some(block))))
+	}
+
 	pub fn do_create_raw_transaction(inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<GlobalTransaction, String> {
 		use chain;
 		use keys;
@@ -95,9 +140,49 @@ impl RawClientCoreApi for RawClientCore {
 		self.local_sync_node.accept_transaction(transaction)
 	}
 
+	fn test_accept_transactions(&self, transactions: Vec<GlobalTransaction>) -> Vec<sync::TestAcceptResult> {
+		self.local_sync_node.test_accept_transactions(transactions)
+	}
+
+	fn accept_package(&self, transactions: Vec<GlobalTransaction>) -> sync::PackageAcceptResult {
+		self.local_sync_node.accept_package(transactions)
+	}
+
 	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<GlobalTransaction, String> {
 		RawClientCore::do_create_raw_transaction(inputs, outputs, lock_time)
 	}
+
+	fn memory_pool_hashes(&self) -> Vec<GlobalH256> {
+		self.local_sync_node.memory_pool_hashes()
+	}
+
+	fn memory_pool_entry(&self, hash: &GlobalH256) -> Option<MemoryPoolEntryInformation> {
+		self.local_sync_node.memory_pool_entry(hash)
+	}
+
+	fn memory_pool_information(&self) -> MemoryPoolInformation {
+		self.local_sync_node.memory_pool_information()
+	}
+
+	fn memory_pool_ancestors(&self, hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+		self.local_sync_node.memory_pool_ancestors(hash)
+	}
+
+	fn memory_pool_descendants(&self, hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+		self.local_sync_node.memory_pool_descendants(hash)
+	}
+
+	fn transaction(&self, hash: &GlobalH256) -> Option<GlobalTransaction> {
+		self.find_transaction(hash).map(|(transaction, _)| transaction)
+	}
+
+	fn verbose_transaction(&self, hash: &GlobalH256) -> Option<Transaction> {
+		self.find_transaction(hash).map(|(transaction, block)| verbose_transaction(self.network, &transaction, block))
+	}
+
+	fn decode_transaction(&self, transaction: GlobalTransaction) -> Transaction {
+		verbose_transaction(self.network, &transaction, None)
+	}
 }
 
 impl<T> RawClient<T> where T: RawClientCoreApi {
@@ -106,6 +191,43 @@ impl<T> RawClient<T> where T: RawClientCoreApi {
 			core: core,
 		}
 	}
+
+	fn create_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<GlobalTransaction, Error> {
+		// reverse hashes of inputs
+		let inputs: Vec<_> = inputs.into_iter()
+			.map(|mut input| {
+				input.txid = input.txid.reversed();
+				input
+			}).collect();
+
+		self.core.create_raw_transaction(inputs, outputs, lock_time).map_err(|e| execution(e))
+	}
+
+	/// Deserializes transactions of the package.
+	fn package_transactions(&self, raw_transactions: Vec<RawTransaction>) -> Result<Vec<GlobalTransaction>, Error> {
+		if raw_transactions.is_empty() || raw_transactions.len() > MAX_PACKAGE_COUNT {
+			return Err(invalid_params("rawtxs", format!("Array must contain between 1 and {} transactions", MAX_PACKAGE_COUNT)));
+		}
+
+		raw_transactions.into_iter()
+			.map(|raw_transaction| {
+				let raw_transaction_data: Vec<u8> = raw_transaction.into();
+				deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("rawtxs", e))
+			})
+			.collect()
+	}
+
+	/// Converts memory pool transactions hashes to the response. Transactions, which have left
+	/// the memory pool in the meantime, are omitted from the verbose response.
+	fn memory_pool_response(&self, hashes: Vec<GlobalH256>, verbose: bool) -> GetRawMemPoolResponse {
+		if verbose {
+			GetRawMemPoolResponse::Verbose(hashes.into_iter()
+				.filter_map(|hash| self.core.memory_pool_entry(&hash).map(|entry| (hash.reversed().into(), entry.into())))
+				.collect())
+		} else {
+			GetRawMemPoolResponse::Hashes(hashes.into_iter().map(|hash| hash.reversed().into()).collect())
+		}
+	}
 }
 
 impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
@@ -117,25 +239,154 @@ impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
 			.map_err(|e| execution(e))
 	}
 
-	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<RawTransaction, Error> {
-		// reverse hashes of inputs
-		let inputs: Vec<_> = inputs.into_iter()
-			.map(|mut input| {
-				input.txid = input.txid.reversed();
-				input
-			}).collect();
+	fn test_memory_pool_accept(&self, raw_transactions: Vec<RawTransaction>) -> Result<Vec<TestMemPoolAcceptResult>, Error> {
+		let transactions = self.package_transactions(raw_transactions)?;
+		Ok(self.core.test_accept_transactions(transactions).into_iter().map(Into::into).collect())
+	}
+
+	fn submit_package(&self, raw_transactions: Vec<RawTransaction>) -> Result<SubmitPackageResponse, Error> {
+		let transactions = self.package_transactions(raw_transactions)?;
+		Ok(self.core.accept_package(transactions).into())
+	}
 
-		let transaction = try!(self.core.create_raw_transaction(inputs, outputs, lock_time).map_err(|e| execution(e)));
+	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<RawTransaction, Error> {
+		let transaction = try!(self.create_transaction(inputs, outputs, lock_time));
 		let transaction = serialize(&transaction);
 		Ok(transaction.into())
 	}
 
-	fn decode_raw_transaction(&self, _transaction: RawTransaction) -> Result<Transaction, Error> {
-		rpc_unimplemented!()
+	fn decode_raw_transaction(&self, raw_transaction: RawTransaction) -> Result<Transaction, Error> {
+		let raw_transaction_data: Vec<u8> = raw_transaction.into();
+		let transaction = deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("hexstring", e))?;
+		Ok(self.core.decode_transaction(transaction))
+	}
+
+	fn get_raw_transaction(&self, hash: H256, verbose: Trailing<bool>) -> Result<GetRawTransactionResponse, Error> {
+		let global_hash: GlobalH256 = hash.clone().into();
+		let global_hash = global_hash.reversed();
+		if verbose.unwrap_or_default() {
+			self.core.verbose_transaction(&global_hash)
+				.map(GetRawTransactionResponse::Verbose)
+		} else {
+			self.core.transaction(&global_hash)
+				.map(|transaction| GetRawTransactionResponse::Raw(serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).into()))
+		}
+		.ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn get_memory_pool_entry(&self, hash: H256) -> Result<GetMemPoolEntryResponse, Error> {
+		let global_hash: GlobalH256 = hash.clone().into();
+		self.core.memory_pool_entry(&global_hash.reversed())
+			.map(Into::into)
+			.ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn get_memory_pool_info(&self) -> Result<GetMemPoolInfoResponse, Error> {
+		Ok(self.core.memory_pool_information().into())
+	}
+
+	fn get_raw_memory_pool(&self, verbose: Trailing<bool>) -> Result<GetRawMemPoolResponse, Error> {
+		Ok(self.memory_pool_response(self.core.memory_pool_hashes(), verbose.unwrap_or_default()))
+	}
+
+	fn get_memory_pool_ancestors(&self, hash: H256, verbose: Trailing<bool>) -> Result<GetRawMemPoolResponse, Error> {
+		let global_hash: GlobalH256 = hash.clone().into();
+		self.core.memory_pool_ancestors(&global_hash.reversed())
+			.map(|ancestors| self.memory_pool_response(ancestors, verbose.unwrap_or_default()))
+			.ok_or_else(|| transaction_not_found(hash))
 	}
 
-	fn get_raw_transaction(&self, _hash: H256, _verbose: Trailing<bool>) -> Result<GetRawTransactionResponse, Error> {
-		rpc_unimplemented!()
+	fn get_memory_pool_descendants(&self, hash: H256, verbose: Trailing<bool>) -> Result<GetRawMemPoolResponse, Error> {
+		let global_hash: GlobalH256 = hash.clone().into();
+		self.core.memory_pool_descendants(&global_hash.reversed())
+			.map(|descendants| self.memory_pool_response(descendants, verbose.unwrap_or_default()))
+			.ok_or_else(|| transaction_not_found(hash))
+	}
+
+	fn create_psbt(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<String, Error> {
+		let transaction = self.create_transaction(inputs, outputs, lock_time)?;
+		Psbt::from_unsigned_transaction(transaction)
+			.map(|psbt| psbt.to_string())
+			.map_err(|e| execution(e.to_string()))
+	}
+
+	fn decode_psbt(&self, psbt: String) -> Result<DecodePsbtResponse, Error> {
+		psbt.parse::<Psbt>()
+			.map(Into::into)
+			.map_err(|e| invalid_params("psbt", e.to_string()))
+	}
+
+	fn combine_psbt(&self, psbts: Vec<String>) -> Result<String, Error> {
+		let mut psbts = psbts.into_iter()
+			.map(|psbt| psbt.parse::<Psbt>().map_err(|e| invalid_params("txs", e.to_string())));
+		let mut combined = match psbts.next() {
+			Some(psbt) => psbt?,
+			None => return Err(invalid_params("txs", "at least one PSBT is required")),
+		};
+
+		for psbt in psbts {
+			combined.combine(psbt?).map_err(|e| invalid_params("txs", e.to_string()))?;
+		}
+
+		Ok(combined.to_string())
+	}
+
+	fn finalize_psbt(&self, psbt: String, extract: Trailing<bool>) -> Result<FinalizePsbtResponse, Error> {
+		let extract: Option<bool> = extract.into();
+		let mut psbt = psbt.parse::<Psbt>().map_err(|e| invalid_params("psbt", e.to_string()))?;
+		let complete = psbt.finalize();
+		if !complete || !extract.unwrap_or(true) {
+			return Ok(FinalizePsbtResponse {
+				psbt: Some(psbt.to_string()),
+				hex: None,
+				complete: complete,
+			});
+		}
+
+		let transaction = psbt.extract_transaction().map_err(|e| execution(e.to_string()))?;
+		Ok(FinalizePsbtResponse {
+			psbt: None,
+			hex: Some(serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).into()),
+			complete: true,
+		})
+	}
+
+	fn debug_script(&self, script: Bytes, stack: Trailing<Vec<Bytes>>) -> Result<DebugScriptResponse, Error> {
+		let script: Script = script.to_vec().into();
+		let stack: Option<Vec<Bytes>> = stack.into();
+		let mut stack: Stack<GlobalBytes> = stack.unwrap_or_default().into_iter()
+			.map(|item| item.to_vec().into())
+			.collect::<Vec<_>>()
+			.into();
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_locktime(true)
+			.verify_checksequence(true)
+			.verify_dersig(true)
+			.verify_nulldummy(true);
+		let checker = NoopSignatureChecker;
+		let mut interpreter = Interpreter::new(&mut stack, &script, &flags, &checker, SignatureVersion::Base);
+
+		let mut steps = Vec::new();
+		let result = loop {
+			match interpreter.step() {
+				Ok(Some(step)) => steps.push(DebugScriptStep {
+					offset: step.offset,
+					opcode: step.opcode.to_string(),
+					executing: step.executing,
+					stack: interpreter.stack().iter().cloned().map(Into::into).collect(),
+					altstack: interpreter.altstack().iter().cloned().map(Into::into).collect(),
+				}),
+				Ok(None) => break interpreter.finish(),
+				Err(err) => break Err(err),
+			}
+		};
+
+		Ok(DebugScriptResponse {
+			steps: steps,
+			success: result == Ok(true),
+			error: result.err().map(|err| err.to_string()),
+		})
 	}
 }
 
@@ -143,25 +394,101 @@ impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
 pub mod tests {
 	use jsonrpc_macros::Trailing;
 	use jsonrpc_core::IoHandler;
+	use serde_json;
 	use chain::Transaction;
 	use primitives::hash::H256 as GlobalH256;
 	use v1::traits::Raw;
-	use v1::types::{TransactionInput, TransactionOutputs};
+	use v1::types::{TransactionInput, TransactionOutputs, Transaction as VerboseTransaction};
 	use super::*;
 
 	#[derive(Default)]
 	struct SuccessRawClientCore;
 	#[derive(Default)]
 	struct ErrorRawClientCore;
+	#[derive(Default)]
+	struct UnsignedRawClientCore;
 
 	impl RawClientCoreApi for SuccessRawClientCore {
 		fn accept_transaction(&self, transaction: Transaction) -> Result<GlobalH256, String> {
 			Ok(transaction.hash())
 		}
 
+		fn test_accept_transactions(&self, transactions: Vec<Transaction>) -> Vec<sync::TestAcceptResult> {
+			transactions.into_iter().map(|transaction| sync::TestAcceptResult {
+				hash: transaction.hash(),
+				witness_hash: transaction.witness_hash(),
+				vsize: transaction.vsize(),
+				result: Ok(10000),
+			}).collect()
+		}
+
+		fn accept_package(&self, transactions: Vec<Transaction>) -> sync::PackageAcceptResult {
+			sync::PackageAcceptResult {
+				transactions: self.test_accept_transactions(transactions),
+				reject_reason: None,
+			}
+		}
+
 		fn create_raw_transaction(&self, _inputs: Vec<TransactionInput>, _outputs: TransactionOutputs, _lock_time: Trailing<u32>) -> Result<Transaction, String> {
 			Ok("0100000001ad9d38823d95f31dc6c0cb0724c11a3cf5a466ca4147254a10cd94aade6eb5b3230000006b483045022100b7683165c3ecd57b0c44bf6a0fb258dc08c328458321c8fadc2b9348d4e66bd502204fd164c58d1a949a4d39bb380f8f05c9f6b3e9417f06bf72e5c068428ca3578601210391c35ac5ee7cf82c5015229dcff89507f83f9b8c952b8fecfa469066c1cb44ccffffffff0170f30500000000001976a914801da3cb2ed9e44540f4b982bde07cd3fbae264288ac00000000".into())
 		}
+
+		fn memory_pool_entry(&self, _hash: &GlobalH256) -> Option<MemoryPoolEntryInformation> {
+			Some(MemoryPoolEntryInformation {
+				transaction: "010000000100000000000000000000000000000000000000000000000000000000000000000000000000000000000100000000000000000000000000".into(),
+				size: 60,
+				time: 1500000000,
+				height: 500000,
+				miner_fee: 10000,
+				miner_virtual_fee: 0,
+				ancestors_count: 1,
+				ancestors_size: 60,
+				ancestors_fee: 10000,
+				descendants_count: 2,
+				descendants_size: 120,
+				descendants_fee: 30000,
+				depends: vec![],
+				spent_by: vec![GlobalH256::from(1)],
+				replaceable: false,
+			})
+		}
+
+		fn memory_pool_information(&self) -> MemoryPoolInformation {
+			MemoryPoolInformation {
+				transactions_count: 1,
+				transactions_size_in_bytes: 60,
+				max_size_in_bytes: 300000000,
+				minimum_fee_rate: 1500,
+			}
+		}
+
+		fn memory_pool_hashes(&self) -> Vec<GlobalH256> {
+			vec!["594f0b4b9bd579874053d879a3ab45985b49ecff4ee8d20f22d3e2e5d47bfa50".into()]
+		}
+
+		fn memory_pool_ancestors(&self, _hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+			Some(vec![])
+		}
+
+		fn memory_pool_descendants(&self, _hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+			Some(vec!["594f0b4b9bd579874053d879a3ab45985b49ecff4ee8d20f22d3e2e5d47bfa50".into()])
+		}
+
+		fn transaction(&self, _hash: &GlobalH256) -> Option<Transaction> {
+			Some("00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000".into())
+		}
+
+		fn verbose_transaction(&self, hash: &GlobalH256) -> Option<VerboseTransaction> {
+			self.transaction(hash).map(|transaction| verbose_transaction(keys::Network::Mainnet, &transaction, Some(TransactionBlock {
+				hash: GlobalH256::from(1),
+				time: 1500000000,
+				confirmations: 10,
+			})))
+		}
+
+		fn decode_transaction(&self, transaction: Transaction) -> VerboseTransaction {
+			verbose_transaction(keys::Network::Mainnet, &transaction, None)
+		}
 	}
 
 	impl RawClientCoreApi for ErrorRawClientCore {
@@ -169,9 +496,107 @@ pub mod tests {
 			Err("error".to_owned())
 		}
 
+		fn test_accept_transactions(&self, transactions: Vec<Transaction>) -> Vec<sync::TestAcceptResult> {
+			transactions.into_iter().map(|transaction| sync::TestAcceptResult {
+				hash: transaction.hash(),
+				witness_hash: transaction.witness_hash(),
+				vsize: transaction.vsize(),
+				result: Err("bad-txns-inputs-missingorspent".into()),
+			}).collect()
+		}
+
+		fn accept_package(&self, transactions: Vec<Transaction>) -> sync::PackageAcceptResult {
+			sync::PackageAcceptResult {
+				transactions: self.test_accept_transactions(transactions),
+				reject_reason: Some("transaction failed".into()),
+			}
+		}
+
 		fn create_raw_transaction(&self, _inputs: Vec<TransactionInput>, _outputs: TransactionOutputs, _lock_time: Trailing<u32>) -> Result<Transaction, String> {
 			Err("error".to_owned())
 		}
+
+		fn memory_pool_hashes(&self) -> Vec<GlobalH256> {
+			vec![]
+		}
+
+		fn memory_pool_entry(&self, _hash: &GlobalH256) -> Option<MemoryPoolEntryInformation> {
+			None
+		}
+
+		fn memory_pool_information(&self) -> MemoryPoolInformation {
+			unreachable!()
+		}
+
+		fn memory_pool_ancestors(&self, _hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+			None
+		}
+
+		fn memory_pool_descendants(&self, _hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+			None
+		}
+
+		fn transaction(&self, _hash: &GlobalH256) -> Option<Transaction> {
+			None
+		}
+
+		fn verbose_transaction(&self, _hash: &GlobalH256) -> Option<VerboseTransaction> {
+			None
+		}
+
+		fn decode_transaction(&self, _transaction: Transaction) -> VerboseTransaction {
+			unreachable!()
+		}
+	}
+
+	impl RawClientCoreApi for UnsignedRawClientCore {
+		fn accept_transaction(&self, _transaction: Transaction) -> Result<GlobalH256, String> {
+			unreachable!()
+		}
+
+		fn test_accept_transactions(&self, _transactions: Vec<Transaction>) -> Vec<sync::TestAcceptResult> {
+			unreachable!()
+		}
+
+		fn accept_package(&self, _transactions: Vec<Transaction>) -> sync::PackageAcceptResult {
+			unreachable!()
+		}
+
+		fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<Transaction, String> {
+			RawClientCore::do_create_raw_transaction(inputs, outputs, lock_time)
+		}
+
+		fn memory_pool_hashes(&self) -> Vec<GlobalH256> {
+			unreachable!()
+		}
+
+		fn memory_pool_entry(&self, _hash: &GlobalH256) -> Option<MemoryPoolEntryInformation> {
+			unreachable!()
+		}
+
+		fn memory_pool_information(&self) -> MemoryPoolInformation {
+			unreachable!()
+		}
+
+		fn memory_pool_ancestors(&self, _hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+			unreachable!()
+		}
+
+		fn memory_pool_descendants(&self, _hash: &GlobalH256) -> Option<Vec<GlobalH256>> {
+			unreachable!()
+		}
+
+		fn transaction(&self, _hash: &GlobalH256) -> Option<Transaction> {
+			unreachable!()
+		}
+
+		fn verbose_transaction(&self, _hash: &GlobalH256) -> Option<VerboseTransaction> {
+			unreachable!()
+		}
+
+		fn decode_transaction(&self, _transaction: Transaction) -> VerboseTransaction {
+			unreachable!()
+		}
 	}
 
 	#[test]
@@ -212,6 +637,89 @@ pub mod tests {
 		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#, &sample);
 	}
 
+	#[test]
+	fn testmempoolaccept_allowed() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","allowed":true,"vsize":60,"fees":{"base":0.0001}}],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn testmempoolaccept_rejected() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","allowed":false,"reject-reason":"bad-txns-inputs-missingorspent"}],"id":1}"#, &sample);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [[]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: rawtxs","data":"\"Array must contain between 1 and 25 transactions\""},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn submitpackage_accepted() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitpackage",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"package_msg":"success","tx-results":{"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107":{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","vsize":60,"fees":{"base":0.0001}}}},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn submitpackage_rejected() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitpackage",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"package_msg":"transaction failed","tx-results":{"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107":{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","error":"bad-txns-inputs-missingorspent"}}},"id":1}"#, &sample);
+	}
+
 	#[test]
 	fn createrawtransaction_success() {
 		let client = RawClient::new(SuccessRawClientCore::default());
@@ -247,4 +755,440 @@ pub mod tests {
 
 		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#, &sample);
 	}
+
+	#[test]
+	fn getmempoolentry_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolentry",
+				"params": ["50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"ancestorcount":1,"ancestorfees":10000,"ancestorsize":60,"bip125-replaceable":false,"depends":[],"descendantcount":2,"descendantfees":30000,"descendantsize":120,"fee":0.0001,"height":500000,"modifiedfee":0.0001,"spentby":["0000000000000000000000000000000000000000000000000000000000000001"],"time":1500000000,"vsize":60,"weight":240,"wtxid":"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getmempoolentry_not_found() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolentry",
+				"params": ["50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getrawtransaction_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawtransaction",
+				"params": ["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getrawtransaction_verbose_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawtransaction",
+				"params": ["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107", true],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		assert_eq!(sample["result"]["txid"], "34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107");
+		assert_eq!(sample["result"]["blockhash"], "0000000000000000000000000000000000000000000000000000000000000001");
+		assert_eq!(sample["result"]["confirmations"], 10);
+		assert_eq!(sample["result"]["blocktime"], 1500000000);
+		assert_eq!(sample["result"]["vout"][0]["value"], 0.00000001);
+	}
+
+	#[test]
+	fn decoderawtransaction_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "decoderawtransaction",
+				"params": ["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		assert_eq!(sample["result"]["txid"], "34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107");
+		assert_eq!(sample["result"]["size"], 60);
+		assert_eq!(sample["result"]["vsize"], 60);
+		assert_eq!(sample["result"]["weight"], 240);
+		assert_eq!(sample["result"]["confirmations"], 0);
+	}
+
+	#[test]
+	fn decoderawtransaction_error() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "decoderawtransaction",
+				"params": ["00000000"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		assert_eq!(sample["error"]["code"], -32602);
+	}
+
+	#[test]
+	fn getrawtransaction_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawtransaction",
+				"params": ["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107", true],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getmempoolinfo_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolinfo",
+				"params": [],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"size":1,"bytes":60,"maxmempool":300000000,"mempoolminfee":0.000015,"incrementalrelayfee":0.00001},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getrawmempool_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawmempool",
+				"params": [],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":["50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getrawmempool_verbose_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawmempool",
+				"params": [true],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59":{"ancestorcount":1,"ancestorfees":10000,"ancestorsize":60,"bip125-replaceable":false,"depends":[],"descendantcount":2,"descendantfees":30000,"descendantsize":120,"fee":0.0001,"height":500000,"modifiedfee":0.0001,"spentby":["0000000000000000000000000000000000000000000000000000000000000001"],"time":1500000000,"vsize":60,"weight":240,"wtxid":"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"}},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getmempoolancestors_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempoolancestors",
+				"params": ["50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getmempooldescendants_verbose_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempooldescendants",
+				"params": ["50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59", true],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59":{"ancestorcount":1,"ancestorfees":10000,"ancestorsize":60,"bip125-replaceable":false,"depends":[],"descendantcount":2,"descendantfees":30000,"descendantsize":120,"fee":0.0001,"height":500000,"modifiedfee":0.0001,"spentby":["0000000000000000000000000000000000000000000000000000000000000001"],"time":1500000000,"vsize":60,"weight":240,"wtxid":"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"}},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getmempooldescendants_not_found() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getmempooldescendants",
+				"params": ["50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"50fa7bd4e5e2d3220fd2e84effec495b9845aba379d853408779d59a4b0b4f59"},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn createpsbt_success() {
+		let client = RawClient::new(UnsignedRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "createpsbt",
+				"params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":"cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA","id":1}"#, &sample);
+	}
+
+	#[test]
+	fn createpsbt_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "createpsbt",
+				"params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn decodepsbt_success() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "decodepsbt",
+				"params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAw8BAgEDAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswiAgLGBH+UQe19bTBFQG6VwHzYXHeOS4zvPKerrAm5XHCe5QowBgIBAQIBAQEBAQMEAQAAACIGAsYEf5RB7X1tMEVAbpXAfNhcd45LjO88p6usCblccJ7lGNkMak9UAACAAAAAgAAAAIAAAAAABQAAAAEPAaoAAA=="],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"tx":"01000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000ffffffff0140420f00000000001976a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac00000000","txid":"8ac607008d95e6e712be9986370c0b6ead7b972fcd93f039383682152397aa55","unknown":{"0f0102":"03"},"inputs":[{"witness_utxo":{"amount":0.02,"scriptPubKey":"001406afd46bcdfd22ef94ac122aa11f241244a37ecc"},"partial_signatures":{"02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5":"30060201010201010101"},"sighash":"ALL","bip32_derivs":[{"pubkey":"02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5","master_fingerprint":"d90c6a4f","path":"m/84'/0'/0'/0/5"}],"unknown":{"0f":"aa"}}],"outputs":[{}],"fee":0.01},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn decodepsbt_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "decodepsbt",
+				"params": ["cHNidP8?"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: psbt","data":"\"Invalid PSBT base64 encoding\""},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn combinepsbt_success() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "combinepsbt",
+				"params": [["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswiAgLGBH+UQe19bTBFQG6VwHzYXHeOS4zvPKerrAm5XHCe5QowBgIBAQIBAQEBAAA=", "cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswiAgL5MIoBkljDEEk0T4X4nVIptTHIRYNvmbCGAfETvOA2+QowBgIBAgIBAgEBAAA="]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":"cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswiAgLGBH+UQe19bTBFQG6VwHzYXHeOS4zvPKerrAm5XHCe5QowBgIBAQIBAQEBIgIC+TCKAZJYwxBJNE+F+J1SKbUxyEWDb5mwhgHxE7zgNvkKMAYCAQICAQIBAQAA","id":1}"#, &sample);
+	}
+
+	#[test]
+	fn combinepsbt_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "combinepsbt",
+				"params": [["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA", "cHNidP8BAFUCAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: txs","data":"\"PSBTs have different unsigned transactions\""},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn finalizepsbt_success() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "finalizepsbt",
+				"params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswiAgLGBH+UQe19bTBFQG6VwHzYXHeOS4zvPKerrAm5XHCe5QowBgIBAQIBAQEBAAA="],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"hex":"010000000001013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000ffffffff0140420f00000000001976a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac020a300602010102010101012102c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee500000000","complete":true},"id":1}"#, &sample);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "finalizepsbt",
+				"params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswiAgLGBH+UQe19bTBFQG6VwHzYXHeOS4zvPKerrAm5XHCe5QowBgIBAQIBAQEBAAA=", false],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"psbt":"cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswBCC4CCjAGAgEBAgEBAQEhAsYEf5RB7X1tMEVAbpXAfNhcd45LjO88p6usCblccJ7lAAA=","complete":true},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn finalizepsbt_incomplete() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "finalizepsbt",
+				"params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswAAA=="],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"psbt":"cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAEBH4CEHgAAAAAAFgAUBq/Ua839Iu+UrBIqoR8kEkSjfswAAA==","complete":false},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn debugscript_success() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "debugscript",
+				"params": ["5152935387"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"steps":[{"offset":0,"opcode":"OP_1","executing":true,"stack":["01"],"altstack":[]},{"offset":1,"opcode":"OP_2","executing":true,"stack":["01","02"],"altstack":[]},{"offset":2,"opcode":"OP_ADD","executing":true,"stack":["03"],"altstack":[]},{"offset":3,"opcode":"OP_3","executing":true,"stack":["03","03"],"altstack":[]},{"offset":4,"opcode":"OP_EQUAL","executing":true,"stack":["01"],"altstack":[]}],"success":true,"error":null},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn debugscript_failure() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "debugscript",
+				"params": ["516a", ["02"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"steps":[{"offset":0,"opcode":"OP_1","executing":true,"stack":["02","01"],"altstack":[]}],"success":false,"error":"Used return opcode"},"id":1}"#, &sample);
+	}
 }
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use jsonrpc_core::Error;
+use jsonrpc_macros::Trailing;
+use chain::constants::SATOSHIS_IN_COIN;
+use v1::traits::Wallet;
+use v1::types::WalletUnspentOutput;
+use v1::helpers::errors::execution;
+use keys;
+use wallet;
+
+/// Default number of confirmations of outputs, returned by listunspent
+const DEFAULT_LIST_UNSPENT_MIN_CONFIRMATIONS: u32 = 1;
+
+pub struct WalletClient<T: WalletClientCoreApi> {
+	core: T,
+}
+
+pub trait WalletClientCoreApi: Send + Sync + 'static {
+	fn balance(&self, min_confirmations: u32) -> u64;
+	fn unspent(&self, min_confirmations: u32) -> Vec<wallet::UnspentOutput>;
+	fn new_address(&self) -> Result<keys::Address, String>;
+}
+
+pub struct WalletClientCore {
+	wallet: Arc<wallet::Wallet>,
+}
+
+impl WalletClientCore {
+	pub fn new(wallet: Arc<wallet::Wallet>) -> Self {
+		WalletClientCore {
+			wallet: wallet,
+		}
+	}
+}
+
+impl WalletClientCoreApi for WalletClientCore {
+	fn balance(&self, min_confirmations: u32) -> u64 {
+		self.wallet.balance(min_confirmations)
+	}
+
+	fn unspent(&self, min_confirmations: u32) -> Vec<wallet::UnspentOutput> {
+		self.wallet.unspent(min_confirmations)
+	}
+
+	fn new_address(&self) -> Result<keys::Address, String> {
+		self.wallet.new_address()
+	}
+}
+
+impl<T> WalletClient<T> where T: WalletClientCoreApi {
+	pub fn new(core: T) -> Self {
+		WalletClient {
+			core: core,
+		}
+	}
+}
+
+impl<T> Wallet for WalletClient<T> where T: WalletClientCoreApi {
+	fn balance(&self, min_confirmations: Trailing<u32>) -> Result<f64, Error> {
+		Ok(self.core.balance(min_confirmations.unwrap_or_default()) as f64 / SATOSHIS_IN_COIN as f64)
+	}
+
+	fn unspent_outputs(&self, min_confirmations: Trailing<u32>) -> Result<Vec<WalletUnspentOutput>, Error> {
+		let min_confirmations: Option<u32> = min_confirmations.into();
+		let min_confirmations = min_confirmations.unwrap_or(DEFAULT_LIST_UNSPENT_MIN_CONFIRMATIONS);
+		Ok(self.core.unspent(min_confirmations).into_iter().map(Into::into).collect())
+	}
+
+	fn new_address(&self) -> Result<String, Error> {
+		self.core.new_address()
+			.map(|address| address.to_string())
+			.map_err(execution)
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use jsonrpc_core::IoHandler;
+	use chain::OutPoint;
+	use primitives::hash::H256;
+	use keys;
+	use wallet::{Descriptor, UnspentOutput};
+	use v1::traits::Wallet;
+	use super::*;
+
+	#[derive(Default)]
+	struct SuccessWalletClientCore;
+
+	#[derive(Default)]
+	struct ErrorWalletClientCore;
+
+	impl WalletClientCoreApi for SuccessWalletClientCore {
+		fn balance(&self, min_confirmations: u32) -> u64 {
+			self.unspent(min_confirmations).into_iter().map(|output| output.value).sum()
+		}
+
+		fn unspent(&self, min_confirmations: u32) -> Vec<UnspentOutput> {
+			let descriptor: Descriptor = "addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)".parse().unwrap();
+			vec![
+				UnspentOutput {
+					outpoint: OutPoint {
+						hash: H256::from(2),
+						index: 1,
+					},
+					value: 150_000_000,
+					script_pubkey: descriptor.script_pubkey(),
+					address: descriptor.address(keys::Network::Mainnet),
+					descriptor: descriptor,
+					confirmations: 6,
+					spendable: false,
+				},
+				UnspentOutput {
+					outpoint: OutPoint {
+						hash: H256::from(3),
+						index: 0,
+					},
+					value: 50_000_000,
+					script_pubkey: "00".into(),
+					address: None,
+					descriptor: Descriptor::Raw("00".into()),
+					confirmations: 0,
+					spendable: false,
+				},
+			].into_iter().filter(|output| output.confirmations >= min_confirmations).collect()
+		}
+
+		fn new_address(&self) -> Result<keys::Address, String> {
+			Ok("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into())
+		}
+	}
+
+	impl WalletClientCoreApi for ErrorWalletClientCore {
+		fn balance(&self, _min_confirmations: u32) -> u64 {
+			unreachable!()
+		}
+
+		fn unspent(&self, _min_confirmations: u32) -> Vec<UnspentOutput> {
+			unreachable!()
+		}
+
+		fn new_address(&self) -> Result<keys::Address, String> {
+			Err("Database error: failure".into())
+		}
+	}
+
+	#[test]
+	fn getbalance_success() {
+		let client = WalletClient::new(SuccessWalletClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getbalance",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":2.0,"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getbalance",
+				"params": [1],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1.5,"id":1}"#);
+	}
+
+	#[test]
+	fn listunspent_success() {
+		let client = WalletClient::new(SuccessWalletClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "listunspent",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[{"txid":"0000000000000000000000000000000000000000000000000000000000000002","vout":1,"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","scriptPubKey":"76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac","amount":1.5,"confirmations":6,"spendable":false,"desc":"addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)#632p52jr"}],"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "listunspent",
+				"params": [0],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[{"txid":"0000000000000000000000000000000000000000000000000000000000000002","vout":1,"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","scriptPubKey":"76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac","amount":1.5,"confirmations":6,"spendable":false,"desc":"addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)#632p52jr"},{"txid":"0000000000000000000000000000000000000000000000000000000000000003","vout":0,"scriptPubKey":"00","amount":0.5,"confirmations":0,"spendable":false,"desc":"raw(00)#qwfjgwf6"}],"id":1}"#);
+	}
+
+	#[test]
+	fn getnewaddress_success() {
+		let client = WalletClient::new(SuccessWalletClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getnewaddress",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","id":1}"#);
+	}
+
+	#[test]
+	fn getnewaddress_error() {
+		let client = WalletClient::new(ErrorWalletClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getnewaddress",
+				"params": [],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Database error: failure\""},"id":1}"#);
+	}
+}
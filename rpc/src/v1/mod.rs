@@ -4,11 +4,19 @@ pub mod impls;
 pub mod traits;
 pub mod types;
 
-pub use self::traits::Raw;
-pub use self::traits::Miner;
-pub use self::traits::BlockChain;
-pub use self::traits::Network;
+pub use self::traits::{Raw, RAW_METHOD_TIERS};
+pub use self::traits::{Miner, MINER_METHOD_TIERS};
+pub use self::traits::{BlockChain, BLOCKCHAIN_METHOD_TIERS};
+pub use self::traits::{Network, NETWORK_METHOD_TIERS};
+pub use self::traits::{Control, CONTROL_METHOD_TIERS};
+pub use self::traits::{Health, HEALTH_METHOD_TIERS};
+pub use self::traits::{Address, ADDRESS_METHOD_TIERS};
+pub use self::traits::{Wallet, WALLET_METHOD_TIERS};
 pub use self::impls::{RawClient, RawClientCore};
 pub use self::impls::{MinerClient, MinerClientCore};
-pub use self::impls::{BlockChainClient, BlockChainClientCore};
+pub use self::impls::{BlockChainClient, BlockChainClientCore, BlockChainClientCoreApi};
 pub use self::impls::{NetworkClient, NetworkClientCore};
+pub use self::impls::{ControlClient, ControlClientCore};
+pub use self::impls::{HealthClient, HealthClientCore, HealthClientCoreApi};
+pub use self::impls::{AddressClient, AddressClientCore, AddressClientCoreApi};
+pub use self::impls::{WalletClient, WalletClientCore, WalletClientCoreApi};
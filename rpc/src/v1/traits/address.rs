@@ -0,0 +1,29 @@
+use jsonrpc_core::Error;
+use auth::PermissionTier;
+use v1::types::H256;
+use v1::types::{AddressesRequest, AddressBalance, AddressUnspentOutput};
+
+build_rpc_trait! {
+	/// Parity-bitcoin address index interface.
+	pub trait Address {
+		/// Get balance of the addresses. Requires address index.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddressbalance", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getaddressbalance")]
+		fn balance(&self, AddressesRequest) -> Result<AddressBalance, Error>;
+		/// Get unspent outputs of the addresses. Requires address index.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddressutxos", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getaddressutxos")]
+		fn unspent_outputs(&self, AddressesRequest) -> Result<Vec<AddressUnspentOutput>, Error>;
+		/// Get hashes of the transactions, paying to or spending from the addresses. Requires address index.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getaddresstxids", "params": [{"addresses": ["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"], "start": 0, "end": 1000}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getaddresstxids")]
+		fn transaction_ids(&self, AddressesRequest) -> Result<Vec<H256>, Error>;
+	}
+}
+
+/// Permission tiers, required to call the address index methods.
+pub const ADDRESS_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("getaddressbalance", PermissionTier::Read),
+	("getaddressutxos", PermissionTier::Read),
+	("getaddresstxids", PermissionTier::Read),
+];
@@ -1,10 +1,17 @@
 use jsonrpc_macros::Trailing;
 use jsonrpc_core::Error;
+use auth::PermissionTier;
 
-use v1::types::H256;
-use v1::types::GetBlockResponse;
+use v1::types::{H256, Bytes};
+use v1::types::{GetBlockResponse, BlockVerbosity};
+use v1::types::GetBlockHeaderResponse;
+use v1::types::{ChainTip, GetChainTxStatsResponse};
+use v1::types::{HashOrHeight, GetBlockStatsResponse};
+use v1::types::GetBlockchainInfoResponse;
 use v1::types::GetTxOutResponse;
 use v1::types::GetTxOutSetInfoResponse;
+use v1::types::{DumpTxOutSetResponse, DumpUtxosResponse};
+use v1::types::{ScanTxOutSetAction, ScanObject, ScanTxOutSetResult};
 
 
 build_rpc_trait! {
@@ -26,17 +33,110 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdifficulty", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "getdifficulty")]
 		fn difficulty(&self) -> Result<f64, Error>;
-		/// Get information on given block.
-		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblock", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		/// Get state of the blockchain and its versionbits deployments.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockchaininfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getblockchaininfo")]
+		fn blockchain_info(&self) -> Result<GetBlockchainInfoResponse, Error>;
+		/// Get information on given block. Verbosity is either boolean or number: 0 (hex-encoded block, default),
+		/// 1 (block information with transactions ids), 2 (with decoded transactions and their fees) or 3 (with outputs,
+		/// spent by transactions inputs).
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblock", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", 2], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "getblock")]
-		fn block(&self, H256, Trailing<bool>) -> Result<GetBlockResponse, Error>;
+		fn block(&self, H256, Trailing<BlockVerbosity>) -> Result<GetBlockResponse, Error>;
+		/// Get information on given block header. Returns hex-encoded header if verbose is false.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockheader", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getblockheader")]
+		fn block_header(&self, H256, Trailing<bool>) -> Result<GetBlockHeaderResponse, Error>;
+		/// Get tips of all known branches of the blocks tree, including the main chain.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getchaintips", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getchaintips")]
+		fn chain_tips(&self) -> Result<Vec<ChainTip>, Error>;
+		/// Get statistics about the total number and rate of transactions in the main chain.
+		/// The window ends at the given block (best block by default) and contains one month of blocks by default.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getchaintxstats", "params": [2016], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getchaintxstats")]
+		fn chain_tx_stats(&self, Trailing<u32>, Trailing<H256>) -> Result<GetChainTxStatsResponse, Error>;
+		/// Get fee, size and transaction statistics of main chain block with given hash or height.
+		/// All statistics are returned by default, or only those, which names are given.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockstats", "params": [100000, ["txs", "totalfee"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getblockstats")]
+		fn block_stats(&self, HashOrHeight, Trailing<Vec<String>>) -> Result<GetBlockStatsResponse, Error>;
 		/// Get details about an unspent transaction output.
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "gettxout", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", 0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "gettxout")]
 		fn transaction_out(&self, H256, u32, Trailing<bool>) -> Result<GetTxOutResponse, Error>;
-		/// Get statistics about the unspent transaction output set.
+		/// Get serialized proof (header and partial merkle tree of the block), that transactions are included in the block.
+		/// Without block hash, block is found by the first transaction, if it has unspent outputs or transaction index is enabled.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "gettxoutproof", "params": [["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "gettxoutproof")]
+		fn transaction_out_proof(&self, Vec<H256>, Trailing<H256>) -> Result<Bytes, Error>;
+		/// Get statistics about the unspent transaction output set, including its MuHash3072.
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "gettxoutsetinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "gettxoutsetinfo")]
 		fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error>;
+		/// Write the unspent transaction output set snapshot to the file, which doesn't exist yet.
+		/// The snapshot may be loaded into the new node database with the `load-utxos` command.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "dumptxoutset", "params": ["/tmp/utxo.dat"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "dumptxoutset")]
+		fn dump_tx_out_set(&self, String) -> Result<DumpTxOutSetResponse, Error>;
+		/// Write all unspent transaction outputs to the file, which doesn't exist yet, while the node keeps running.
+		/// Format is either "csv" (`txid,vout,value,height,coinbase,script,address` lines, default) or "binary".
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "dumputxos", "params": ["/tmp/utxo.csv", "csv"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "dumputxos")]
+		fn dump_utxos(&self, String, Trailing<String>) -> Result<DumpUtxosResponse, Error>;
+		/// Scan the unspent transaction output set for outputs, matching given descriptors.
+		/// Action is either "start", "abort" or "status". Ranged descriptors are expanded to indexes 0-1000 by default.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "scantxoutset", "params": ["start", ["addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)", {"desc": "wpkh(xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw/0/*)", "range": 100}]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "scantxoutset")]
+		fn scan_tx_out_set(&self, ScanTxOutSetAction, Trailing<Vec<ScanObject>>) -> Result<ScanTxOutSetResult, Error>;
+		/// Prune blockchain data up to given height. Returns height of the last pruned block.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "pruneblockchain", "params": [100000], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "pruneblockchain")]
+		fn prune_blockchain(&self, u32) -> Result<i64, Error>;
+		/// Verify last blocks of the blockchain database at given check level (0-4, 3 by default).
+		/// Checks last 6 blocks by default, 0 checks all blocks. Pruned blocks are never checked. Returns true if no problems are found.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "verifychain", "params": [3, 6], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "verifychain")]
+		fn verify_chain(&self, Trailing<u32>, Trailing<u32>) -> Result<bool, Error>;
+		/// Mark block invalid, as if it violated a consensus rule. Chain is switched to the chain with
+		/// the most work, which doesn't contain the block or its descendants.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "invalidateblock", "params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "invalidateblock")]
+		fn invalidate_block(&self, H256) -> Result<(), Error>;
+		/// Remove invalidity marks of the block, its ancestors and descendants, set by `invalidateblock`.
+		/// Chain is switched to the valid chain with the most work.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "reconsiderblock", "params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "reconsiderblock")]
+		fn reconsider_block(&self, H256) -> Result<(), Error>;
+		/// Treat block as if it has been received before the canon block with the same chain work.
+		/// If the chain of the block has as much work as the canon chain, chain is switched to it.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "preciousblock", "params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "preciousblock")]
+		fn precious_block(&self, H256) -> Result<(), Error>;
 	}
 }
+
+/// Permission tiers, required to call the blockchain methods.
+pub const BLOCKCHAIN_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("getbestblockhash", PermissionTier::Read),
+	("getblockcount", PermissionTier::Read),
+	("getblockhash", PermissionTier::Read),
+	("getdifficulty", PermissionTier::Read),
+	("getblockchaininfo", PermissionTier::Read),
+	("getblock", PermissionTier::Read),
+	("getblockheader", PermissionTier::Read),
+	("getchaintips", PermissionTier::Read),
+	("getchaintxstats", PermissionTier::Read),
+	("getblockstats", PermissionTier::Read),
+	("gettxout", PermissionTier::Read),
+	("gettxoutproof", PermissionTier::Read),
+	("gettxoutsetinfo", PermissionTier::Read),
+	("dumptxoutset", PermissionTier::Admin),
+	("dumputxos", PermissionTier::Admin),
+	("scantxoutset", PermissionTier::Read),
+	("pruneblockchain", PermissionTier::Admin),
+	("verifychain", PermissionTier::Admin),
+	("invalidateblock", PermissionTier::Admin),
+	("reconsiderblock", PermissionTier::Admin),
+	("preciousblock", PermissionTier::Admin),
+];
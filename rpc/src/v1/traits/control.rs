@@ -0,0 +1,23 @@
+use jsonrpc_macros::Trailing;
+use jsonrpc_core::Error;
+use auth::PermissionTier;
+
+build_rpc_trait! {
+	/// Parity-bitcoin node control interface.
+	pub trait Control {
+		/// Stop the node.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "stop", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "stop")]
+		fn stop(&self) -> Result<String, Error>;
+		/// Change log levels of the given targets. Returns current log filters.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "logging", "params": ["sync=debug,p2p=trace"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "logging")]
+		fn logging(&self, Trailing<String>) -> Result<String, Error>;
+	}
+}
+
+/// Permission tiers, required to call the node control methods.
+pub const CONTROL_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("stop", PermissionTier::Admin),
+	("logging", PermissionTier::Admin),
+];
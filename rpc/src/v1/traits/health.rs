@@ -0,0 +1,18 @@
+use jsonrpc_core::Error;
+use auth::PermissionTier;
+use v1::types::HealthResponse;
+
+build_rpc_trait! {
+	/// Parity-bitcoin node health interface.
+	pub trait Health {
+		/// Get node health: synchronization status, peers count and database status.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "healthcheck", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "healthcheck")]
+		fn health(&self) -> Result<HealthResponse, Error>;
+	}
+}
+
+/// Permission tiers, required to call the health methods.
+pub const HEALTH_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("healthcheck", PermissionTier::Read),
+];
@@ -1,13 +1,37 @@
+use jsonrpc_macros::Trailing;
 use jsonrpc_core::Error;
+use auth::PermissionTier;
 
-use v1::types::{BlockTemplate, BlockTemplateRequest};
+use v1::types::{RawBlock, BlockTemplateRequest, GetBlockTemplateResponse, EstimateSmartFeeResponse, EstimateRawFeeResponse};
 
 build_rpc_trait! {
 	/// Parity-bitcoin miner data interface.
 	pub trait Miner {
-		/// Get block template for mining.
-		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocktemplate", "params": [{"capabilities": ["coinbasetxn", "workid", "coinbase/append"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		/// Get block template for mining, or check block proposal.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocktemplate", "params": [{"capabilities": ["coinbasetxn", "workid", "coinbase/append"], "rules": ["segwit"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "getblocktemplate")]
-		fn get_block_template(&self, BlockTemplateRequest) -> Result<BlockTemplate, Error>;
+		fn get_block_template(&self, BlockTemplateRequest) -> Result<GetBlockTemplateResponse, Error>;
+		/// Submit new block to the node. Returns null if the block is accepted, or the BIP22 reason of rejection.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitblock", "params": ["0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "submitblock")]
+		fn submit_block(&self, RawBlock) -> Result<Option<String>, Error>;
+		/// Estimate fee rate, required for transaction to be confirmed within given number of blocks.
+		/// Estimate mode is either CONSERVATIVE (default), or ECONOMICAL.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "estimatesmartfee", "params": [6, "CONSERVATIVE"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "estimatesmartfee")]
+		fn estimate_smart_fee(&self, u32, Trailing<String>) -> Result<EstimateSmartFeeResponse, Error>;
+		/// Estimate fee rate, required for transaction to be confirmed within given number of blocks, for every time horizon.
+		/// Threshold is the required share of transactions, confirmed within given number of blocks (0.95 by default).
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "estimaterawfee", "params": [6, 0.95], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "estimaterawfee")]
+		fn estimate_raw_fee(&self, u32, Trailing<f64>) -> Result<EstimateRawFeeResponse, Error>;
 	}
 }
+
+/// Permission tiers, required to call the miner methods.
+pub const MINER_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("getblocktemplate", PermissionTier::Write),
+	("submitblock", PermissionTier::Write),
+	("estimatesmartfee", PermissionTier::Read),
+	("estimaterawfee", PermissionTier::Read),
+];
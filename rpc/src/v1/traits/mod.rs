@@ -2,8 +2,16 @@ mod blockchain;
 mod miner;
 mod raw;
 mod network;
+mod control;
+mod health;
+mod address;
+mod wallet;
 
-pub use self::blockchain::BlockChain;
-pub use self::miner::Miner;
-pub use self::raw::Raw;
-pub use self::network::Network;
+pub use self::blockchain::{BlockChain, BLOCKCHAIN_METHOD_TIERS};
+pub use self::miner::{Miner, MINER_METHOD_TIERS};
+pub use self::raw::{Raw, RAW_METHOD_TIERS};
+pub use self::network::{Network, NETWORK_METHOD_TIERS};
+pub use self::control::{Control, CONTROL_METHOD_TIERS};
+pub use self::health::{Health, HEALTH_METHOD_TIERS};
+pub use self::address::{Address, ADDRESS_METHOD_TIERS};
+pub use self::wallet::{Wallet, WALLET_METHOD_TIERS};
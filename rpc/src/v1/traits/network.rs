@@ -1,6 +1,7 @@
 use jsonrpc_core::Error;
+use auth::PermissionTier;
 use jsonrpc_macros::Trailing;
-use v1::types::{AddNodeOperation, NodeInfo};
+use v1::types::{AddNodeOperation, NodeInfo, SetBanOperation, BannedNode, GetPeerInfoResponse, GetNetTotalsResponse};
 
 build_rpc_trait! {
 	/// Parity-bitcoin network interface
@@ -20,5 +21,38 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getconnectioncount", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "getconnectioncount")]
 		fn connection_count(&self) -> Result<usize, Error>;
+		/// Get statistics of every connected peer
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getpeerinfo", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getpeerinfo")]
+		fn peer_info(&self) -> Result<Vec<GetPeerInfoResponse>, Error>;
+		/// Get network traffic statistics
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getnettotals", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getnettotals")]
+		fn net_totals(&self) -> Result<GetNetTotalsResponse, Error>;
+		/// Ban/unban the node. Optional ban time is in seconds, 24 hours by default.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "setban", "params": ["192.168.0.6", "add", 86400], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "setban", "params": ["192.168.0.6", "remove"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "setban")]
+		fn set_ban(&self, String, SetBanOperation, Trailing<i64>) -> Result<(), Error>;
+		/// List all banned nodes
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "listbanned", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "listbanned")]
+		fn list_banned(&self) -> Result<Vec<BannedNode>, Error>;
+		/// Clear all banned nodes
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "clearbanned", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "clearbanned")]
+		fn clear_banned(&self) -> Result<(), Error>;
 	}
 }
+
+/// Permission tiers, required to call the network methods.
+pub const NETWORK_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("addnode", PermissionTier::Admin),
+	("getaddednodeinfo", PermissionTier::Read),
+	("getconnectioncount", PermissionTier::Read),
+	("getpeerinfo", PermissionTier::Read),
+	("getnettotals", PermissionTier::Read),
+	("setban", PermissionTier::Admin),
+	("listbanned", PermissionTier::Read),
+	("clearbanned", PermissionTier::Admin),
+];
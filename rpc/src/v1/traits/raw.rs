@@ -1,12 +1,22 @@
 use jsonrpc_macros::Trailing;
 use jsonrpc_core::Error;
+use auth::PermissionTier;
 
 use v1::types::H256;
+use v1::types::Bytes;
 use v1::types::RawTransaction;
 use v1::types::Transaction;
 use v1::types::TransactionInput;
 use v1::types::TransactionOutputs;
 use v1::types::GetRawTransactionResponse;
+use v1::types::GetMemPoolEntryResponse;
+use v1::types::GetMemPoolInfoResponse;
+use v1::types::GetRawMemPoolResponse;
+use v1::types::DecodePsbtResponse;
+use v1::types::FinalizePsbtResponse;
+use v1::types::DebugScriptResponse;
+use v1::types::TestMemPoolAcceptResult;
+use v1::types::SubmitPackageResponse;
 
 build_rpc_trait! {
 	/// Parity-bitcoin raw data interface.
@@ -15,6 +25,16 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "sendrawtransaction", "params": ["01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "sendrawtransaction")]
 		fn send_raw_transaction(&self, RawTransaction) -> Result<H256, Error>;
+		/// Verifies transactions, as if they were sent with `sendrawtransaction`, but never adds them to the memory pool.
+		/// Transactions are tested as the package: every transaction may spend outputs of preceding transactions.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "testmempoolaccept", "params": [["01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "testmempoolaccept")]
+		fn test_memory_pool_accept(&self, Vec<RawTransaction>) -> Result<Vec<TestMemPoolAcceptResult>, Error>;
+		/// Adds the package of the child transaction and its parents to the memory pool && relays it to the peers.
+		/// Package is accepted only if all transactions are valid. Memory pool minimum fee is paid by the whole package.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitpackage", "params": [["01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "submitpackage")]
+		fn submit_package(&self, Vec<RawTransaction>) -> Result<SubmitPackageResponse, Error>;
 		/// Create a transaction spending the given inputs and creating new outputs.
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "createrawtransaction", "params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "createrawtransaction")]
@@ -23,9 +43,71 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "decoderawtransaction", "params": ["01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "decoderawtransaction")]
 		fn decode_raw_transaction(&self, RawTransaction) -> Result<Transaction, Error>;
-		/// Return the raw transaction data.
-		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrawtransaction", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		/// Return the raw transaction data. Verbose transaction object is returned, if `verbose` is true.
+		/// Main chain transactions are only found with transaction index, or while they have unspent outputs.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrawtransaction", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "getrawtransaction")]
 		fn get_raw_transaction(&self, H256, Trailing<bool>) -> Result<GetRawTransactionResponse, Error>;
+		/// Return memory pool data of the transaction, including its in-pool ancestors and descendants packages.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolentry", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getmempoolentry")]
+		fn get_memory_pool_entry(&self, H256) -> Result<GetMemPoolEntryResponse, Error>;
+		/// Return memory pool state, including its size limit and the minimal fee rate of accepted transactions.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getmempoolinfo")]
+		fn get_memory_pool_info(&self) -> Result<GetMemPoolInfoResponse, Error>;
+		/// Return hashes of all memory pool transactions. Memory pool data of transactions is returned, if `verbose` is true.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrawmempool", "params": [true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getrawmempool")]
+		fn get_raw_memory_pool(&self, Trailing<bool>) -> Result<GetRawMemPoolResponse, Error>;
+		/// Return hashes of in-pool ancestors of the memory pool transaction. Memory pool data of ancestors is returned, if `verbose` is true.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempoolancestors", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getmempoolancestors")]
+		fn get_memory_pool_ancestors(&self, H256, Trailing<bool>) -> Result<GetRawMemPoolResponse, Error>;
+		/// Return hashes of in-pool descendants of the memory pool transaction. Memory pool data of descendants is returned, if `verbose` is true.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmempooldescendants", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getmempooldescendants")]
+		fn get_memory_pool_descendants(&self, H256, Trailing<bool>) -> Result<GetRawMemPoolResponse, Error>;
+		/// Create a base64-encoded PSBT (BIP174) of the transaction, spending the given inputs and creating new outputs.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "createpsbt", "params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "createpsbt")]
+		fn create_psbt(&self, Vec<TransactionInput>, TransactionOutputs, Trailing<u32>) -> Result<String, Error>;
+		/// Return an object representing the base64-encoded PSBT.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "decodepsbt", "params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "decodepsbt")]
+		fn decode_psbt(&self, String) -> Result<DecodePsbtResponse, Error>;
+		/// Combine multiple PSBTs of the same transaction into one.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "combinepsbt", "params": [["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA", "cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "combinepsbt")]
+		fn combine_psbt(&self, Vec<String>) -> Result<String, Error>;
+		/// Finalize inputs of the PSBT. If all inputs are finalized and `extract` is true (default), return the signed transaction.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "finalizepsbt", "params": ["cHNidP8BAFUBAAAAATuj7f16exKyescsPmd2j2F/yBvDiIpRMjqfuKpLHl5KAAAAAAD/////AUBCDwAAAAAAGXapFGLpB7FcvyfVQlOZ6/bw+1DruI8YiKwAAAAAAAAA", true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "finalizepsbt")]
+		fn finalize_psbt(&self, String, Trailing<bool>) -> Result<FinalizePsbtResponse, Error>;
+		/// Execute the hex-encoded script step by step, starting with the given stack, and return stacks after each executed instruction.
+		/// Signature checks always fail.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "debugscript", "params": ["5152935387", []], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "debugscript")]
+		fn debug_script(&self, Bytes, Trailing<Vec<Bytes>>) -> Result<DebugScriptResponse, Error>;
 	}
 }
+
+/// Permission tiers, required to call the raw methods.
+pub const RAW_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("sendrawtransaction", PermissionTier::Write),
+	("testmempoolaccept", PermissionTier::Read),
+	("submitpackage", PermissionTier::Write),
+	("createrawtransaction", PermissionTier::Read),
+	("decoderawtransaction", PermissionTier::Read),
+	("getrawtransaction", PermissionTier::Read),
+	("getmempoolentry", PermissionTier::Read),
+	("getmempoolinfo", PermissionTier::Read),
+	("getrawmempool", PermissionTier::Read),
+	("getmempoolancestors", PermissionTier::Read),
+	("getmempooldescendants", PermissionTier::Read),
+	("createpsbt", PermissionTier::Read),
+	("decodepsbt", PermissionTier::Read),
+	("combinepsbt", PermissionTier::Read),
+	("finalizepsbt", PermissionTier::Read),
+	("debugscript", PermissionTier::Read),
+];
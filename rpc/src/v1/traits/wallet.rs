@@ -0,0 +1,29 @@
+use jsonrpc_core::Error;
+use auth::PermissionTier;
+use jsonrpc_macros::Trailing;
+use v1::types::WalletUnspentOutput;
+
+build_rpc_trait! {
+	/// Parity-bitcoin wallet interface.
+	pub trait Wallet {
+		/// Get total value (in BTC) of the wallet unspent outputs with at least `minconf` (default 0) confirmations. Requires wallet.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getbalance", "params": [1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getbalance")]
+		fn balance(&self, Trailing<u32>) -> Result<f64, Error>;
+		/// Get wallet unspent outputs with at least `minconf` (default 1) confirmations. Requires wallet.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listunspent", "params": [6], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "listunspent")]
+		fn unspent_outputs(&self, Trailing<u32>) -> Result<Vec<WalletUnspentOutput>, Error>;
+		/// Generate new key and get its P2PKH address. Requires wallet.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getnewaddress", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getnewaddress")]
+		fn new_address(&self) -> Result<String, Error>;
+	}
+}
+
+/// Permission tiers, required to call the wallet methods.
+pub const WALLET_METHOD_TIERS: &'static [(&'static str, PermissionTier)] = &[
+	("getbalance", PermissionTier::Read),
+	("listunspent", PermissionTier::Read),
+	("getnewaddress", PermissionTier::Write),
+];
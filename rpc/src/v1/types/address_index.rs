@@ -0,0 +1,81 @@
+use keys::Address;
+use v1::types;
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// Addresses, passed to the address index methods
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressesRequest {
+	/// Queried addresses
+	#[serde(with = "types::address::vec")]
+	pub addresses: Vec<Address>,
+	/// Only transactions, included into blocks at this or higher height are returned by `getaddresstxids`
+	#[serde(default)]
+	pub start: Option<u32>,
+	/// Only transactions, included into blocks at this or lower height are returned by `getaddresstxids`
+	#[serde(default)]
+	pub end: Option<u32>,
+}
+
+/// getaddressbalance response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressBalance {
+	/// Sum of the unspent outputs values (in satoshis)
+	pub balance: u64,
+	/// Sum of all received outputs values (in satoshis)
+	pub received: u64,
+}
+
+/// Unspent output, returned by getaddressutxos
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AddressUnspentOutput {
+	/// Address, the output is paying to
+	#[serde(with = "types::address")]
+	pub address: Address,
+	/// Hash of the transaction
+	pub txid: H256,
+	/// Index of the output in the transaction
+	#[serde(rename = "outputIndex")]
+	pub output_index: u32,
+	/// Output script
+	pub script: Bytes,
+	/// Output value (in satoshis)
+	pub satoshis: u64,
+	/// Height of the block, which includes the transaction
+	pub height: u32,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::bytes::Bytes;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn addresses_request_deserialize() {
+		assert_eq!(serde_json::from_str::<AddressesRequest>(r#"{"addresses":["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"]}"#).unwrap(), AddressesRequest {
+			addresses: vec!["1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into()],
+			start: None,
+			end: None,
+		});
+		assert_eq!(serde_json::from_str::<AddressesRequest>(r#"{"addresses":[],"start":10,"end":20}"#).unwrap(), AddressesRequest {
+			addresses: vec![],
+			start: Some(10),
+			end: Some(20),
+		});
+	}
+
+	#[test]
+	fn address_unspent_output_serialize() {
+		let output = AddressUnspentOutput {
+			address: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".into(),
+			txid: H256::from(1),
+			output_index: 2,
+			script: Bytes::new(vec![0x51]),
+			satoshis: 3,
+			height: 4,
+		};
+		assert_eq!(serde_json::to_string(&output).unwrap(), r#"{"address":"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa","txid":"0100000000000000000000000000000000000000000000000000000000000000","outputIndex":2,"script":"51","satoshis":3,"height":4}"#);
+	}
+}
@@ -2,3 +2,6 @@ use super::bytes::Bytes;
 
 /// Hex-encoded block
 pub type RawBlock = Bytes;
+
+/// Hex-encoded block header
+pub type RawBlockHeader = Bytes;
@@ -0,0 +1,199 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer};
+use serde::de::{Visitor, Unexpected};
+use super::hash::H256;
+
+/// Block, referenced either by its hash or by its height in the main chain
+#[derive(Debug, PartialEq, Clone)]
+pub enum HashOrHeight {
+	Hash(H256),
+	Height(u32),
+}
+
+impl<'a> Deserialize<'a> for HashOrHeight {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		struct HashOrHeightVisitor;
+
+		impl<'b> Visitor<'b> for HashOrHeightVisitor {
+			type Value = HashOrHeight;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a block hash string or a block height")
+			}
+
+			fn visit_u64<E>(self, value: u64) -> Result<HashOrHeight, E> where E: ::serde::de::Error {
+				if value > u32::max_value() as u64 {
+					return Err(E::invalid_value(Unexpected::Unsigned(value), &self));
+				}
+
+				Ok(HashOrHeight::Height(value as u32))
+			}
+
+			fn visit_i64<E>(self, value: i64) -> Result<HashOrHeight, E> where E: ::serde::de::Error {
+				if value < 0 {
+					return Err(E::invalid_value(Unexpected::Signed(value), &self));
+				}
+
+				self.visit_u64(value as u64)
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<HashOrHeight, E> where E: ::serde::de::Error {
+				value.parse()
+					.map(HashOrHeight::Hash)
+					.map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+			}
+		}
+
+		deserializer.deserialize_any(HashOrHeightVisitor)
+	}
+}
+
+/// getblockstats response. Fee rates are in satoshis per virtual byte, sizes are in bytes.
+/// Coinbase transaction is excluded from all transaction statistics, except `txs` and `outs`.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GetBlockStatsResponse {
+	/// Average fee in the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub avgfee: Option<u64>,
+	/// Average fee rate
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub avgfeerate: Option<u64>,
+	/// Average transaction size
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub avgtxsize: Option<u64>,
+	/// Hash of the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blockhash: Option<H256>,
+	/// Fee rates at the 10th, 25th, 50th, 75th and 90th percentiles, weighted by transaction weight
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub feerate_percentiles: Option<Vec<u64>>,
+	/// Height of the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub height: Option<u32>,
+	/// Number of inputs
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub ins: Option<u64>,
+	/// Maximal fee in the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub maxfee: Option<u64>,
+	/// Maximal fee rate
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub maxfeerate: Option<u64>,
+	/// Maximal transaction size
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub maxtxsize: Option<u64>,
+	/// Median fee in the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub medianfee: Option<u64>,
+	/// Median time past of the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mediantime: Option<u32>,
+	/// Median transaction size
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mediantxsize: Option<u64>,
+	/// Minimal fee in the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub minfee: Option<u64>,
+	/// Minimal fee rate
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub minfeerate: Option<u64>,
+	/// Minimal transaction size
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub mintxsize: Option<u64>,
+	/// Number of outputs
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub outs: Option<u64>,
+	/// Block subsidy
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub subsidy: Option<u64>,
+	/// Total size of all segwit transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub swtotal_size: Option<u64>,
+	/// Total weight of all segwit transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub swtotal_weight: Option<u64>,
+	/// Number of segwit transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub swtxs: Option<u64>,
+	/// Timestamp of the block
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub time: Option<u32>,
+	/// Total amount of all outputs (in satoshis)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub total_out: Option<u64>,
+	/// Total size of all transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub total_size: Option<u64>,
+	/// Total weight of all transactions
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub total_weight: Option<u64>,
+	/// Sum of all fees (in satoshis)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub totalfee: Option<u64>,
+	/// Number of transactions, including coinbase
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub txs: Option<u64>,
+	/// Increase of the number of unspent outputs
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub utxo_increase: Option<i64>,
+	/// Increase of the unspent outputs set size
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub utxo_size_inc: Option<i64>,
+}
+
+macro_rules! select_block_stats {
+	($from: ident, $to: ident, $stat: ident, $($field: ident),*) => {
+		match $stat {
+			$(stringify!($field) => $to.$field = $from.$field.take(),)*
+			_ => return Err($stat.to_owned()),
+		}
+	}
+}
+
+impl GetBlockStatsResponse {
+	/// Leaves only given statistics in the response. Returns name of the first unknown statistic on error.
+	pub fn select(mut self, stats: &[String]) -> Result<Self, String> {
+		let mut selected = GetBlockStatsResponse::default();
+		for stat in stats {
+			let stat = stat.as_str();
+			select_block_stats!(self, selected, stat,
+				avgfee, avgfeerate, avgtxsize, blockhash, feerate_percentiles, height, ins, maxfee, maxfeerate,
+				maxtxsize, medianfee, mediantime, mediantxsize, minfee, minfeerate, mintxsize, outs, subsidy,
+				swtotal_size, swtotal_weight, swtxs, time, total_out, total_size, total_weight, totalfee, txs,
+				utxo_increase, utxo_size_inc);
+		}
+
+		Ok(selected)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn hash_or_height_deserialize() {
+		assert_eq!(serde_json::from_str::<HashOrHeight>("1000").unwrap(), HashOrHeight::Height(1000));
+		assert_eq!(serde_json::from_str::<HashOrHeight>(r#""0100000000000000000000000000000000000000000000000000000000000000""#).unwrap(),
+			HashOrHeight::Hash(H256::from(1)));
+		assert!(serde_json::from_str::<HashOrHeight>("-1").is_err());
+		assert!(serde_json::from_str::<HashOrHeight>("4294967296").is_err());
+		assert!(serde_json::from_str::<HashOrHeight>(r#""01""#).is_err());
+	}
+
+	#[test]
+	fn block_stats_select() {
+		let stats = GetBlockStatsResponse {
+			height: Some(10),
+			txs: Some(2),
+			totalfee: Some(1000),
+			..Default::default()
+		};
+
+		let selected = stats.select(&["txs".into(), "height".into()]).unwrap();
+		assert_eq!(serde_json::to_string(&selected).unwrap(), r#"{"height":10,"txs":2}"#);
+		assert_eq!(GetBlockStatsResponse::default().select(&["fee".into()]), Err("fee".to_owned()));
+	}
+}
@@ -1,9 +1,19 @@
 use std::collections::HashMap;
+use serde::{Serialize, Serializer};
+use primitives::bigint::Uint;
 use super::hash::H256;
-use chain;
+use super::bytes::Bytes;
 use super::transaction::RawTransaction;
+use ser::{serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
 use miner;
 
+/// Mining client capabilities, supported by the server
+const CAPABILITIES: &'static [&'static str] = &["proposal"];
+/// Ways the block template may be changed by the mining client
+const MUTABLE: &'static [&'static str] = &["time", "transactions", "prevblock"];
+/// Rules, which mining client must understand to use the template (BIP9)
+const REQUIRED_RULES: &'static [&'static str] = &["segwit"];
+
 /// Block template as described in:
 /// https://github.com/bitcoin/bips/blob/master/bip-0022.mediawiki
 /// https://github.com/bitcoin/bips/blob/master/bip-0023.mediawiki
@@ -13,8 +23,12 @@ use miner;
 pub struct BlockTemplate {
 	/// The preferred block version
 	pub version: u32,
-	/// Specific block rules that are to be enforced
+	/// Specific block rules that are to be enforced.
+	/// Rules, which must be understood by the mining client, are prefixed with '!'
 	pub rules: Option<Vec<String>>,
+	/// Optional features, supported by the server (e.g. 'proposal')
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub capabilities: Option<Vec<String>>,
 	/// Set of pending, supported versionbit (BIP 9) softfork deployments
 	/// Keys: named softfork rules
 	/// Values: identifies the bit number as indicating acceptance and readiness for given key
@@ -35,6 +49,9 @@ pub struct BlockTemplate {
 	pub coinbasetxn: Option<BlockTemplateTransaction>,
 	/// The hash target
 	pub target: H256,
+	/// Identifier of the template for long polling. Request with this identifier returns when template changes
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub longpollid: Option<String>,
 	/// The minimum timestamp appropriate for next block time in seconds since epoch (Jan 1 1970 GMT)
 	pub mintime: Option<i64>,
 	/// List of ways the block template may be changed, e.g. 'time', 'transactions', 'prevblock'
@@ -53,6 +70,9 @@ pub struct BlockTemplate {
 	pub bits: u32,
 	/// The height of the next block
 	pub height: u32,
+	/// Witness commitment output script for the coinbase without witness data, present when SegWit is active
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub default_witness_commitment: Option<Bytes>,
 }
 
 /// Transaction data as included in `BlockTemplate`
@@ -79,30 +99,78 @@ pub struct BlockTemplateTransaction {
 	pub required: bool,
 }
 
+/// Response to getblocktemplate RPC request
+#[derive(Debug, PartialEq)]
+pub enum GetBlockTemplateResponse {
+	/// When asking for block template
+	Template(BlockTemplate),
+	/// When checking block proposal (BIP23): null if block is valid, or reason of rejection
+	Proposal(Option<String>),
+}
+
+impl Serialize for GetBlockTemplateResponse {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match *self {
+			GetBlockTemplateResponse::Template(ref template) => template.serialize(serializer),
+			GetBlockTemplateResponse::Proposal(ref reason) => reason.serialize(serializer),
+		}
+	}
+}
+
+impl BlockTemplate {
+	/// Returns true if mining client must support the rule to use the template
+	pub fn is_required_rule(rule: &str) -> bool {
+		REQUIRED_RULES.contains(&rule)
+	}
+}
+
 impl From<miner::BlockTemplate> for BlockTemplate {
 	fn from(block: miner::BlockTemplate) -> Self {
+		let mut target = [0u8; 32];
+		block.bits.to_target().unwrap_or_else(|| 0.into()).to_big_endian(&mut target);
+		let rules = block.rules.into_iter()
+			.map(|rule| if BlockTemplate::is_required_rule(&rule) { format!("!{}", rule) } else { rule })
+			.collect();
+
 		BlockTemplate {
 			version: block.version,
+			rules: Some(rules),
+			capabilities: Some(CAPABILITIES.iter().map(|capability| capability.to_string()).collect()),
+			vbavailable: Some(block.vbavailable),
+			vbrequired: Some(0),
 			previousblockhash: block.previous_header_hash.reversed().into(),
-			curtime: block.time,
-			bits: block.bits.into(),
-			height: block.height,
 			transactions: block.transactions.into_iter().map(Into::into).collect(),
+			coinbaseaux: Some(HashMap::new()),
 			coinbasevalue: Some(block.coinbase_value),
-			sizelimit: Some(block.size_limit),
+			coinbasetxn: None,
+			target: target.into(),
+			longpollid: None,
+			mintime: Some(block.min_time as i64),
+			mutable: Some(MUTABLE.iter().map(|mutable| mutable.to_string()).collect()),
+			noncerange: Some("00000000ffffffff".to_owned()),
 			sigoplimit: Some(block.sigop_limit),
-			..Default::default()
+			sizelimit: Some(block.size_limit),
+			weightlimit: block.weight_limit,
+			curtime: block.time,
+			bits: block.bits.into(),
+			height: block.height,
+			default_witness_commitment: block.default_witness_commitment.map(Into::into),
 		}
 	}
 }
 
-impl From<chain::IndexedTransaction> for BlockTemplateTransaction {
-	fn from(transaction: chain::IndexedTransaction) -> Self {
-		use ser::serialize;
-		let serialize = serialize(&transaction.raw);
+impl From<miner::BlockTemplateTransaction> for BlockTemplateTransaction {
+	fn from(transaction: miner::BlockTemplateTransaction) -> Self {
+		let data = serialize_with_flags(&*transaction.transaction.raw, SERIALIZE_TRANSACTION_WITNESS);
 		BlockTemplateTransaction {
-			data: RawTransaction::new(Vec::from((*serialize).clone())),
-			..Default::default()
+			data: RawTransaction::new(data.take()),
+			txid: Some(transaction.transaction.hash.reversed().into()),
+			hash: Some(transaction.transaction.raw.witness_hash().reversed().into()),
+			depends: Some(transaction.depends.into_iter().map(|index| index as u64).collect()),
+			fee: Some(transaction.fee as i64),
+			sigops: Some(transaction.sigops as i64),
+			weight: Some(transaction.weight as i64),
+			required: false,
 		}
 	}
 }
@@ -116,6 +184,18 @@ mod tests {
 	use hex::FromHex;
 	use super::*;
 
+	#[test]
+	fn get_block_template_response_serialize() {
+		assert_eq!(serde_json::to_string(&GetBlockTemplateResponse::Proposal(None)).unwrap(), "null");
+		assert_eq!(serde_json::to_string(&GetBlockTemplateResponse::Proposal(Some("duplicate".to_owned()))).unwrap(), r#""duplicate""#);
+		assert_eq!(serde_json::to_string(&GetBlockTemplateResponse::Template(BlockTemplate {
+			capabilities: Some(vec!["proposal".to_owned()]),
+			longpollid: Some("id".to_owned()),
+			default_witness_commitment: Some(Bytes("6a24aa21a9ed".from_hex().unwrap())),
+			..Default::default()
+		})).unwrap(), r#"{"version":0,"rules":null,"capabilities":["proposal"],"vbavailable":null,"vbrequired":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[],"coinbaseaux":null,"coinbasevalue":null,"coinbasetxn":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","longpollid":"id","mintime":null,"mutable":null,"noncerange":null,"sigoplimit":null,"sizelimit":null,"weightlimit":null,"curtime":0,"bits":0,"height":0,"default_witness_commitment":"6a24aa21a9ed"}"#);
+	}
+
 	#[test]
 	fn block_template_transaction_serialize() {
 		assert_eq!(serde_json::to_string(&BlockTemplateTransaction {
@@ -173,6 +253,7 @@ mod tests {
 		assert_eq!(serde_json::to_string(&BlockTemplate {
 			version: 0,
 			rules: None,
+			capabilities: None,
 			vbavailable: None,
 			vbrequired: None,
 			previousblockhash: H256::default(),
@@ -181,6 +262,7 @@ mod tests {
 			coinbasevalue: None,
 			coinbasetxn: None,
 			target: H256::default(),
+			longpollid: None,
 			mintime: None,
 			mutable: None,
 			noncerange: None,
@@ -190,10 +272,12 @@ mod tests {
 			curtime: 100,
 			bits: 200,
 			height: 300,
+			default_witness_commitment: None,
 		}).unwrap(), r#"{"version":0,"rules":null,"vbavailable":null,"vbrequired":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[],"coinbaseaux":null,"coinbasevalue":null,"coinbasetxn":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","mintime":null,"mutable":null,"noncerange":null,"sigoplimit":null,"sizelimit":null,"weightlimit":null,"curtime":100,"bits":200,"height":300}"#);
 		assert_eq!(serde_json::to_string(&BlockTemplate {
 			version: 0,
 			rules: Some(vec!["a".to_owned()]),
+			capabilities: None,
 			vbavailable: Some(vec![("b".to_owned(), 5)].into_iter().collect()),
 			vbrequired: Some(10),
 			previousblockhash: H256::from(10),
@@ -220,6 +304,7 @@ mod tests {
 				required: true,
 			}),
 			target: H256::from(100),
+			longpollid: None,
 			mintime: Some(7),
 			mutable: Some(vec!["afg".to_owned()]),
 			noncerange: Some("00000000ffffffff".to_owned()),
@@ -229,6 +314,7 @@ mod tests {
 			curtime: 100,
 			bits: 200,
 			height: 300,
+			default_witness_commitment: None,
 		}).unwrap(), r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300}"#);
 	}
 
@@ -239,6 +325,7 @@ mod tests {
 			BlockTemplate {
 				version: 0,
 				rules: None,
+				capabilities: None,
 				vbavailable: None,
 				vbrequired: None,
 				previousblockhash: H256::default(),
@@ -247,6 +334,7 @@ mod tests {
 				coinbasevalue: None,
 				coinbasetxn: None,
 				target: H256::default(),
+				longpollid: None,
 				mintime: None,
 				mutable: None,
 				noncerange: None,
@@ -256,12 +344,14 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				default_witness_commitment: None,
 			});
 		assert_eq!(
 			serde_json::from_str::<BlockTemplate>(r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300}"#).unwrap(),
 			BlockTemplate {
 				version: 0,
 				rules: Some(vec!["a".to_owned()]),
+				capabilities: None,
 				vbavailable: Some(vec![("b".to_owned(), 5)].into_iter().collect()),
 				vbrequired: Some(10),
 				previousblockhash: H256::from(10),
@@ -288,6 +378,7 @@ mod tests {
 					required: true,
 				}),
 				target: H256::from(100),
+				longpollid: None,
 				mintime: Some(7),
 				mutable: Some(vec!["afg".to_owned()]),
 				noncerange: Some("00000000ffffffff".to_owned()),
@@ -297,6 +388,7 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				default_witness_commitment: None,
 			});
 	}
 }
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use super::block::RawBlock;
 
 /// Block template request mode
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
@@ -26,11 +27,16 @@ pub struct BlockTemplateRequest {
 	pub capabilities: Option<HashSet<String>>,
 	/// Softfork deployments, supported by client
 	pub rules: Option<HashSet<String>>,
+	/// Identifier of the previously received template. Request returns when template changes
+	pub longpollid: Option<String>,
+	/// Proposed block, when mode is 'proposal'
+	pub data: Option<RawBlock>,
 }
 
 #[cfg(test)]
 mod tests {
 	use serde_json;
+	use super::super::bytes::Bytes;
 	use super::*;
 
 	#[test]
@@ -47,12 +53,14 @@ mod tests {
 
 	#[test]
 	fn block_template_request_serialize() {
-		assert_eq!(serde_json::to_string(&BlockTemplateRequest::default()).unwrap(), r#"{"mode":null,"capabilities":null,"rules":null}"#);
+		assert_eq!(serde_json::to_string(&BlockTemplateRequest::default()).unwrap(), r#"{"mode":null,"capabilities":null,"rules":null,"longpollid":null,"data":null}"#);
 		assert_eq!(serde_json::to_string(&BlockTemplateRequest {
 			mode: Some(BlockTemplateRequestMode::Template),
 			capabilities: Some(vec!["a".to_owned()].into_iter().collect()),
 			rules: Some(vec!["b".to_owned()].into_iter().collect()),
-		}).unwrap(), r#"{"mode":"template","capabilities":["a"],"rules":["b"]}"#);
+			longpollid: Some("c".to_owned()),
+			data: None,
+		}).unwrap(), r#"{"mode":"template","capabilities":["a"],"rules":["b"],"longpollid":"c","data":null}"#);
 	}
 
 	#[test]
@@ -63,6 +71,8 @@ mod tests {
 				mode: None,
 				capabilities: None,
 				rules: None,
+				longpollid: None,
+				data: None,
 			});
 		assert_eq!(
 			serde_json::from_str::<BlockTemplateRequest>(r#"{"mode":"template","capabilities":["a"],"rules":["b"]}"#).unwrap(),
@@ -70,6 +80,17 @@ mod tests {
 				mode: Some(BlockTemplateRequestMode::Template),
 				capabilities: Some(vec!["a".to_owned()].into_iter().collect()),
 				rules: Some(vec!["b".to_owned()].into_iter().collect()),
+				longpollid: None,
+				data: None,
+			});
+		assert_eq!(
+			serde_json::from_str::<BlockTemplateRequest>(r#"{"mode":"proposal","data":"00010203"}"#).unwrap(),
+			BlockTemplateRequest {
+				mode: Some(BlockTemplateRequestMode::Proposal),
+				capabilities: None,
+				rules: None,
+				longpollid: None,
+				data: Some(Bytes(vec![0, 1, 2, 3])),
 			});
 	}
 }
@@ -0,0 +1,33 @@
+use super::hash::H256;
+
+/// Tip of the blocks tree branch (getchaintips response item)
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChainTip {
+	/// Height of the tip
+	pub height: u32,
+	/// Hash of the tip
+	pub hash: H256,
+	/// Number of blocks between the tip and the main chain. 0 for the main chain tip
+	pub branchlen: u32,
+	/// Status of the branch: `active` for the main chain, `valid-fork` for fully verified side chain,
+	/// `headers-only` for the branch of verified headers, which blocks aren't stored yet
+	pub status: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::ChainTip;
+
+	#[test]
+	fn chain_tip_serialize() {
+		let tip = ChainTip {
+			height: 100,
+			hash: H256::from(1),
+			branchlen: 2,
+			status: "valid-fork".to_owned(),
+		};
+		assert_eq!(serde_json::to_string(&tip).unwrap(), r#"{"height":100,"hash":"0100000000000000000000000000000000000000000000000000000000000000","branchlen":2,"status":"valid-fork"}"#);
+	}
+}
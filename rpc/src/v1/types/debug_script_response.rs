@@ -0,0 +1,50 @@
+use super::bytes::Bytes;
+
+/// Single instruction of the debugged script
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DebugScriptStep {
+	/// Offset of the instruction in the script
+	pub offset: usize,
+	/// Opcode of the instruction
+	pub opcode: String,
+	/// False if the instruction is a part of the unexecuted branch of conditional
+	pub executing: bool,
+	/// Main stack after the instruction is executed
+	pub stack: Vec<Bytes>,
+	/// Alternative stack after the instruction is executed
+	pub altstack: Vec<Bytes>,
+}
+
+/// debugscript response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DebugScriptResponse {
+	/// Executed instructions
+	pub steps: Vec<DebugScriptStep>,
+	/// True if script has been executed successfully and has left true value on top of the stack
+	pub success: bool,
+	/// Script execution error, if any
+	pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use v1::types::Bytes;
+	use super::{DebugScriptResponse, DebugScriptStep};
+
+	#[test]
+	fn debug_script_response_serialize() {
+		let response = DebugScriptResponse {
+			steps: vec![DebugScriptStep {
+				offset: 0,
+				opcode: "OP_1".into(),
+				executing: true,
+				stack: vec![Bytes::new(vec![1])],
+				altstack: vec![],
+			}],
+			success: true,
+			error: None,
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"steps":[{"offset":0,"opcode":"OP_1","executing":true,"stack":["01"],"altstack":[]}],"success":true,"error":null}"#);
+	}
+}
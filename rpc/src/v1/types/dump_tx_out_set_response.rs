@@ -0,0 +1,35 @@
+use super::hash::H256;
+
+/// dumptxoutset response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DumpTxOutSetResponse {
+	/// Number of unspent outputs in the snapshot
+	pub coins_written: u64,
+	/// Hash of the block, which outputs are unspent
+	pub base_hash: H256,
+	/// Height of the block, which outputs are unspent
+	pub base_height: u32,
+	/// Absolute path of the snapshot file
+	pub path: String,
+	/// MuHash3072 of the written set, which is checked when the snapshot is loaded
+	pub muhash: H256,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn dump_tx_out_set_response_serialize() {
+		let response = DumpTxOutSetResponse {
+			coins_written: 3,
+			base_hash: H256::from(0x56),
+			base_height: 2,
+			path: "/tmp/utxo.dat".to_owned(),
+			muhash: H256::from(0x57),
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"coins_written":3,"base_hash":"5600000000000000000000000000000000000000000000000000000000000000","base_height":2,"path":"/tmp/utxo.dat","muhash":"5700000000000000000000000000000000000000000000000000000000000000"}"#);
+	}
+}
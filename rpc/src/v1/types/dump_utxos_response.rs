@@ -0,0 +1,32 @@
+use super::hash::H256;
+
+/// dumputxos response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DumpUtxosResponse {
+	/// Number of written unspent outputs
+	pub coins_written: u64,
+	/// Hash of the block, which outputs are unspent
+	pub base_hash: H256,
+	/// Height of the block, which outputs are unspent
+	pub base_height: u32,
+	/// Absolute path of the dump file
+	pub path: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn dump_utxos_response_serialize() {
+		let response = DumpUtxosResponse {
+			coins_written: 3,
+			base_hash: H256::from(0x56),
+			base_height: 2,
+			path: "/tmp/utxo.csv".to_owned(),
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"coins_written":3,"base_hash":"5600000000000000000000000000000000000000000000000000000000000000","base_height":2,"path":"/tmp/utxo.csv"}"#);
+	}
+}
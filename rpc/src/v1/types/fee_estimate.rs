@@ -0,0 +1,149 @@
+use miner;
+
+/// Number of satoshis in one bitcoin
+const SATOSHIS_IN_COIN: f64 = 100_000_000.0;
+
+/// estimatesmartfee response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EstimateSmartFeeResponse {
+	/// Estimated fee rate (in BTC per 1000 virtual bytes)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub feerate: Option<f64>,
+	/// Errors, encountered during estimation
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub errors: Option<Vec<String>>,
+	/// Confirmation target, for which the estimate has been found
+	pub blocks: u32,
+}
+
+/// estimaterawfee response
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct EstimateRawFeeResponse {
+	/// Estimate of the short time horizon
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub short: Option<HorizonFeeEstimate>,
+	/// Estimate of the medium time horizon
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub medium: Option<HorizonFeeEstimate>,
+	/// Estimate of the long time horizon
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub long: Option<HorizonFeeEstimate>,
+}
+
+/// Fee estimate of the single time horizon
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct HorizonFeeEstimate {
+	/// Estimated fee rate (in BTC per 1000 virtual bytes)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub feerate: Option<f64>,
+	/// Decay of the horizon statistics
+	pub decay: f64,
+	/// Number of blocks in the horizon statistics period
+	pub scale: u32,
+	/// Fee rate range, which has met the threshold
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pass: Option<FeeRateRange>,
+	/// Highest fee rate range, which has failed the threshold
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fail: Option<FeeRateRange>,
+	/// Errors, encountered during estimation
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub errors: Option<Vec<String>>,
+}
+
+/// Statistics of the fee rate range
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FeeRateRange {
+	/// Lowest fee rate of the range (in satoshis per 1000 virtual bytes)
+	pub startrange: f64,
+	/// Highest fee rate of the range (in satoshis per 1000 virtual bytes)
+	pub endrange: f64,
+	/// Decayed number of transactions, confirmed within the target
+	pub withintarget: f64,
+	/// Decayed number of confirmed transactions
+	pub totalconfirmed: f64,
+	/// Number of memory pool transactions, waiting longer than the target
+	pub inmempool: f64,
+	/// Decayed number of transactions, which have left the memory pool unconfirmed after waiting longer than the target
+	pub leftmempool: f64,
+}
+
+impl From<miner::FeeEstimate> for EstimateSmartFeeResponse {
+	fn from(estimate: miner::FeeEstimate) -> Self {
+		EstimateSmartFeeResponse {
+			feerate: Some(estimate.fee_rate as f64 / SATOSHIS_IN_COIN),
+			errors: None,
+			blocks: estimate.blocks,
+		}
+	}
+}
+
+impl From<miner::RawFeeEstimate> for HorizonFeeEstimate {
+	fn from(estimate: miner::RawFeeEstimate) -> Self {
+		let errors = match estimate.fee_rate {
+			Some(_) => None,
+			None => Some(vec!["Insufficient data or no feerate found which meets threshold".to_owned()]),
+		};
+
+		HorizonFeeEstimate {
+			feerate: estimate.fee_rate.map(|fee_rate| fee_rate.round() / SATOSHIS_IN_COIN),
+			decay: estimate.decay,
+			scale: estimate.scale,
+			pass: estimate.pass.map(Into::into),
+			fail: estimate.fail.map(Into::into),
+			errors: errors,
+		}
+	}
+}
+
+impl From<miner::FeeEstimateBucket> for FeeRateRange {
+	fn from(bucket: miner::FeeEstimateBucket) -> Self {
+		FeeRateRange {
+			startrange: bucket.start.round(),
+			endrange: bucket.end.round(),
+			withintarget: (bucket.within_target * 100.0).round() / 100.0,
+			totalconfirmed: (bucket.total_confirmed * 100.0).round() / 100.0,
+			inmempool: (bucket.in_mempool * 100.0).round() / 100.0,
+			leftmempool: (bucket.left_mempool * 100.0).round() / 100.0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use miner;
+	use super::*;
+
+	#[test]
+	fn estimate_smart_fee_response_serialize() {
+		let response: EstimateSmartFeeResponse = miner::FeeEstimate { fee_rate: 12_000, blocks: 6 }.into();
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"feerate":0.00012,"blocks":6}"#);
+
+		let response = EstimateSmartFeeResponse {
+			feerate: None,
+			errors: Some(vec!["Insufficient data or no feerate found".to_owned()]),
+			blocks: 0,
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"errors":["Insufficient data or no feerate found"],"blocks":0}"#);
+	}
+
+	#[test]
+	fn horizon_fee_estimate_serialize() {
+		let estimate: HorizonFeeEstimate = miner::RawFeeEstimate {
+			fee_rate: None,
+			decay: 0.962,
+			scale: 1,
+			pass: None,
+			fail: Some(miner::FeeEstimateBucket {
+				start: 0.0,
+				end: 1000.0,
+				within_target: 1.0,
+				total_confirmed: 2.0,
+				in_mempool: 3.0,
+				left_mempool: 0.123,
+			}),
+		}.into();
+		assert_eq!(serde_json::to_string(&estimate).unwrap(), r#"{"decay":0.962,"scale":1,"fail":{"startrange":0.0,"endrange":1000.0,"withintarget":1.0,"totalconfirmed":2.0,"inmempool":3.0,"leftmempool":0.12},"errors":["Insufficient data or no feerate found which meets threshold"]}"#);
+	}
+}
@@ -1,7 +1,23 @@
-use serde::{Serialize, Serializer};
+use std::fmt;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{Visitor, Unexpected};
 use super::hash::H256;
 use super::uint::U256;
-use super::block::RawBlock;
+use super::block::{RawBlock, RawBlockHeader};
+use super::transaction::{RawTransaction, SignedTransactionInput, SignedTransactionOutput};
+
+/// Verbosity of getblock RPC response: either boolean (raw or verbose) or number (0-3)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockVerbosity {
+	/// Hex-encoded block (0 or false)
+	Raw,
+	/// Block information with transactions ids (1 or true)
+	Verbose,
+	/// Block information with decoded transactions (2)
+	VerboseTransactions,
+	/// Block information with decoded transactions and outputs, spent by their inputs (3)
+	VerboseTransactionsWithPrevouts,
+}
 
 /// Response to getblock RPC request
 #[derive(Debug)]
@@ -12,6 +28,15 @@ pub enum GetBlockResponse {
 	Verbose(VerboseBlock),
 }
 
+/// Response to getblockheader RPC request
+#[derive(Debug)]
+pub enum GetBlockHeaderResponse {
+	/// When asking for short response
+	Raw(RawBlockHeader),
+	/// When asking for verbose response
+	Verbose(VerboseBlockHeader),
+}
+
 /// Verbose block information
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct VerboseBlock {
@@ -35,8 +60,8 @@ pub struct VerboseBlock {
 	pub version_hex: String,
 	/// Merkle root of this block
 	pub merkleroot: H256,
-	/// Transactions ids
-	pub tx: Vec<H256>,
+	/// Transactions ids or decoded transactions
+	pub tx: BlockTransactions,
 	/// Block time in seconds since epoch (Jan 1 1970 GMT)
 	pub time: u32,
 	/// Median block time in seconds since epoch (Jan 1 1970 GMT)
@@ -56,6 +81,126 @@ pub struct VerboseBlock {
 	pub nextblockhash: Option<H256>,
 }
 
+/// Transactions of the verbose block
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum BlockTransactions {
+	/// Transactions ids
+	Hashes(Vec<H256>),
+	/// Decoded transactions
+	Verbose(Vec<BlockTransaction>),
+}
+
+/// Decoded transaction of the verbose block
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockTransaction {
+	/// Raw transaction
+	pub hex: RawTransaction,
+	/// The transaction id
+	pub txid: H256,
+	/// The transaction hash (differs from txid for witness transactions)
+	pub hash: H256,
+	/// The serialized transaction size
+	pub size: usize,
+	/// The virtual transaction size (differs from size for witness transactions)
+	pub vsize: usize,
+	/// The transaction weight (between vsize * 4 - 3 and vsize * 4)
+	pub weight: usize,
+	/// The version
+	pub version: i32,
+	/// The lock time
+	pub locktime: i32,
+	/// Transaction inputs
+	pub vin: Vec<SignedTransactionInput>,
+	/// Transaction outputs
+	pub vout: Vec<SignedTransactionOutput>,
+	/// Transaction fee in BTC. Omitted for coinbase transaction and when spent outputs are unknown
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<f64>,
+}
+
+/// Verbose block header information
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct VerboseBlockHeader {
+	/// Block hash
+	pub hash: H256,
+	/// Number of confirmations. -1 if block is on the side chain
+	pub confirmations: i64,
+	/// Block height
+	pub height: Option<u32>,
+	/// Block version
+	pub version: u32,
+	/// Block version as hex
+	#[serde(rename = "versionHex")]
+	pub version_hex: String,
+	/// Merkle root of this block
+	pub merkleroot: H256,
+	/// Block time in seconds since epoch (Jan 1 1970 GMT)
+	pub time: u32,
+	/// Median block time in seconds since epoch (Jan 1 1970 GMT)
+	pub mediantime: u32,
+	/// Block nonce
+	pub nonce: u32,
+	/// Block nbits
+	pub bits: u32,
+	/// Block difficulty
+	pub difficulty: f64,
+	/// Hash of previous block
+	pub previousblockhash: Option<H256>,
+	/// Hash of next block
+	pub nextblockhash: Option<H256>,
+}
+
+impl Default for BlockVerbosity {
+	fn default() -> Self {
+		BlockVerbosity::Raw
+	}
+}
+
+impl<'a> Deserialize<'a> for BlockVerbosity {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		struct BlockVerbosityVisitor;
+
+		impl<'b> Visitor<'b> for BlockVerbosityVisitor {
+			type Value = BlockVerbosity;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a boolean or a verbosity level between 0 and 3")
+			}
+
+			fn visit_bool<E>(self, value: bool) -> Result<BlockVerbosity, E> where E: ::serde::de::Error {
+				Ok(if value { BlockVerbosity::Verbose } else { BlockVerbosity::Raw })
+			}
+
+			fn visit_u64<E>(self, value: u64) -> Result<BlockVerbosity, E> where E: ::serde::de::Error {
+				match value {
+					0 => Ok(BlockVerbosity::Raw),
+					1 => Ok(BlockVerbosity::Verbose),
+					2 => Ok(BlockVerbosity::VerboseTransactions),
+					3 => Ok(BlockVerbosity::VerboseTransactionsWithPrevouts),
+					_ => Err(E::invalid_value(Unexpected::Unsigned(value), &self)),
+				}
+			}
+
+			fn visit_i64<E>(self, value: i64) -> Result<BlockVerbosity, E> where E: ::serde::de::Error {
+				if value < 0 {
+					return Err(E::invalid_value(Unexpected::Signed(value), &self));
+				}
+
+				self.visit_u64(value as u64)
+			}
+		}
+
+		deserializer.deserialize_any(BlockVerbosityVisitor)
+	}
+}
+
+impl Default for BlockTransactions {
+	fn default() -> Self {
+		BlockTransactions::Hashes(Vec::new())
+	}
+}
+
 impl Serialize for GetBlockResponse {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
 		match *self {
@@ -65,6 +210,15 @@ impl Serialize for GetBlockResponse {
 	}
 }
 
+impl Serialize for GetBlockHeaderResponse {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match *self {
+			GetBlockHeaderResponse::Raw(ref raw_header) => raw_header.serialize(serializer),
+			GetBlockHeaderResponse::Verbose(ref verbose_header) => verbose_header.serialize(serializer),
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::super::bytes::Bytes;
@@ -88,7 +242,7 @@ mod tests {
 			version: 1,
 			version_hex: "01".to_owned(),
 			merkleroot: H256::from(2),
-			tx: vec![H256::from(3), H256::from(4)],
+			tx: BlockTransactions::Hashes(vec![H256::from(3), H256::from(4)]),
 			time: 111,
 			mediantime: Some(100),
 			nonce: 124,
@@ -118,7 +272,7 @@ mod tests {
 			version: 1,
 			version_hex: "01".to_owned(),
 			merkleroot: H256::from(2),
-			tx: vec![H256::from(3), H256::from(4)],
+			tx: BlockTransactions::Hashes(vec![H256::from(3), H256::from(4)]),
 			time: 111,
 			mediantime: Some(100),
 			nonce: 124,
@@ -133,6 +287,59 @@ mod tests {
 			block);
 	}
 
+	#[test]
+	fn verbose_block_header_serialize() {
+		let header = VerboseBlockHeader {
+			hash: H256::from(1),
+			confirmations: 2,
+			height: Some(3),
+			version: 4,
+			version_hex: "4".to_owned(),
+			merkleroot: H256::from(5),
+			time: 111,
+			mediantime: 100,
+			nonce: 124,
+			bits: 13513,
+			difficulty: 555.555,
+			previousblockhash: Some(H256::from(6)),
+			nextblockhash: None,
+		};
+		assert_eq!(serde_json::to_string(&header).unwrap(), r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","confirmations":2,"height":3,"version":4,"versionHex":"4","merkleroot":"0500000000000000000000000000000000000000000000000000000000000000","time":111,"mediantime":100,"nonce":124,"bits":13513,"difficulty":555.555,"previousblockhash":"0600000000000000000000000000000000000000000000000000000000000000","nextblockhash":null}"#);
+	}
+
+	#[test]
+	fn block_transactions_serialize() {
+		let transactions = BlockTransactions::Verbose(vec![BlockTransaction {
+			hex: "DEADBEEF".into(),
+			txid: H256::from(4),
+			hash: H256::from(5),
+			size: 33,
+			vsize: 44,
+			weight: 176,
+			version: 55,
+			locktime: 66,
+			vin: vec![],
+			vout: vec![],
+			fee: Some(0.0001),
+		}]);
+		assert_eq!(serde_json::to_string(&transactions).unwrap(), r#"[{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"weight":176,"version":55,"locktime":66,"vin":[],"vout":[],"fee":0.0001}]"#);
+
+		let transactions = BlockTransactions::Hashes(vec![H256::from(4)]);
+		assert_eq!(serde_json::to_string(&transactions).unwrap(), r#"["0400000000000000000000000000000000000000000000000000000000000000"]"#);
+	}
+
+	#[test]
+	fn block_verbosity_deserialize() {
+		assert_eq!(serde_json::from_str::<BlockVerbosity>("false").unwrap(), BlockVerbosity::Raw);
+		assert_eq!(serde_json::from_str::<BlockVerbosity>("true").unwrap(), BlockVerbosity::Verbose);
+		assert_eq!(serde_json::from_str::<BlockVerbosity>("0").unwrap(), BlockVerbosity::Raw);
+		assert_eq!(serde_json::from_str::<BlockVerbosity>("1").unwrap(), BlockVerbosity::Verbose);
+		assert_eq!(serde_json::from_str::<BlockVerbosity>("2").unwrap(), BlockVerbosity::VerboseTransactions);
+		assert_eq!(serde_json::from_str::<BlockVerbosity>("3").unwrap(), BlockVerbosity::VerboseTransactionsWithPrevouts);
+		assert!(serde_json::from_str::<BlockVerbosity>("4").is_err());
+		assert!(serde_json::from_str::<BlockVerbosity>("-1").is_err());
+	}
+
 	#[test]
 	fn get_block_response_raw_serialize() {
 		let raw_response = GetBlockResponse::Raw(Bytes::new(vec![0]));
@@ -145,4 +352,13 @@ mod tests {
 		let verbose_response = GetBlockResponse::Verbose(block);
 		assert_eq!(serde_json::to_string(&verbose_response).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","confirmations":0,"size":0,"strippedsize":0,"weight":0,"height":null,"version":0,"versionHex":"","merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","tx":[],"time":0,"mediantime":null,"nonce":0,"bits":0,"difficulty":0.0,"chainwork":"0","previousblockhash":null,"nextblockhash":null}"#);
 	}
+
+	#[test]
+	fn get_block_header_response_serialize() {
+		let raw_response = GetBlockHeaderResponse::Raw(Bytes::new(vec![0]));
+		assert_eq!(serde_json::to_string(&raw_response).unwrap(), r#""00""#);
+
+		let verbose_response = GetBlockHeaderResponse::Verbose(VerboseBlockHeader::default());
+		assert_eq!(serde_json::to_string(&verbose_response).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","confirmations":0,"height":null,"version":0,"versionHex":"","merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","time":0,"mediantime":0,"nonce":0,"bits":0,"difficulty":0.0,"previousblockhash":null,"nextblockhash":null}"#);
+	}
 }
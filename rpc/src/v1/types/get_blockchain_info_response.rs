@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use super::hash::H256;
+
+/// getblockchaininfo response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetBlockchainInfoResponse {
+	/// Current network name (main, test, regtest, signet or custom)
+	pub chain: String,
+	/// Number of blocks in the best chain
+	pub blocks: u32,
+	/// Hash of the best block
+	pub bestblockhash: H256,
+	/// Proof-of-work difficulty of the best block
+	pub difficulty: f64,
+	/// Median time of the best block
+	pub mediantime: u32,
+	/// Status of versionbits (BIP9) deployments
+	pub bip9_softforks: HashMap<String, Bip9SoftforkInfo>,
+}
+
+/// Status of single versionbits deployment
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Bip9SoftforkInfo {
+	/// One of: defined, started, locked_in, active, failed
+	pub status: String,
+	/// Version bit, used to signal the deployment (only while it is started)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub bit: Option<u8>,
+	/// Median time past, at which signalling starts
+	#[serde(rename = "startTime")]
+	pub start_time: u32,
+	/// Median time past, at which the deployment fails if not locked in
+	pub timeout: u32,
+	/// Minimal height at which the deployment may become active
+	pub min_activation_height: u32,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn blockchain_info_response_serialize() {
+		let info = GetBlockchainInfoResponse {
+			chain: "regtest".to_owned(),
+			blocks: 10,
+			bestblockhash: H256::from(0x56),
+			difficulty: 1.0,
+			mediantime: 100,
+			bip9_softforks: vec![("testdummy".to_owned(), Bip9SoftforkInfo {
+				status: "started".to_owned(),
+				bit: Some(28),
+				start_time: 0,
+				timeout: 200,
+				min_activation_height: 0,
+			})].into_iter().collect(),
+		};
+		assert_eq!(serde_json::to_string(&info).unwrap(), r#"{"chain":"regtest","blocks":10,"bestblockhash":"5600000000000000000000000000000000000000000000000000000000000000","difficulty":1.0,"mediantime":100,"bip9_softforks":{"testdummy":{"status":"started","bit":28,"startTime":0,"timeout":200,"min_activation_height":0}}}"#);
+	}
+
+	#[test]
+	fn bip9_softfork_info_skips_bit() {
+		let info = Bip9SoftforkInfo {
+			status: "active".to_owned(),
+			bit: None,
+			start_time: 0,
+			timeout: 200,
+			min_activation_height: 0,
+		};
+		assert_eq!(serde_json::to_string(&info).unwrap(), r#"{"status":"active","startTime":0,"timeout":200,"min_activation_height":0}"#);
+	}
+}
@@ -0,0 +1,56 @@
+use super::hash::H256;
+
+/// getchaintxstats response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetChainTxStatsResponse {
+	/// Timestamp of the final block of the window
+	pub time: u32,
+	/// Total number of transactions in the chain up to the final block of the window
+	pub txcount: u64,
+	/// Hash of the final block of the window
+	pub window_final_block_hash: H256,
+	/// Height of the final block of the window
+	pub window_final_block_height: u32,
+	/// Size of the window in number of blocks
+	pub window_block_count: u32,
+	/// Number of transactions in the window. Omitted if window is empty
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub window_tx_count: Option<u64>,
+	/// Elapsed time (in seconds) in the window. Omitted if window is empty
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub window_interval: Option<u32>,
+	/// Average number of transactions per second in the window. Omitted if window interval is 0
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub txrate: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::GetChainTxStatsResponse;
+
+	#[test]
+	fn chain_tx_stats_response_serialize() {
+		let stats = GetChainTxStatsResponse {
+			time: 1000,
+			txcount: 500,
+			window_final_block_hash: H256::from(1),
+			window_final_block_height: 100,
+			window_block_count: 0,
+			window_tx_count: None,
+			window_interval: None,
+			txrate: None,
+		};
+		assert_eq!(serde_json::to_string(&stats).unwrap(), r#"{"time":1000,"txcount":500,"window_final_block_hash":"0100000000000000000000000000000000000000000000000000000000000000","window_final_block_height":100,"window_block_count":0}"#);
+
+		let stats = GetChainTxStatsResponse {
+			window_block_count: 10,
+			window_tx_count: Some(50),
+			window_interval: Some(100),
+			txrate: Some(0.5),
+			..stats
+		};
+		assert_eq!(serde_json::to_string(&stats).unwrap(), r#"{"time":1000,"txcount":500,"window_final_block_hash":"0100000000000000000000000000000000000000000000000000000000000000","window_final_block_height":100,"window_block_count":10,"window_tx_count":50,"window_interval":100,"txrate":0.5}"#);
+	}
+}
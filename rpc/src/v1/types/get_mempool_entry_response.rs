@@ -0,0 +1,94 @@
+use chain::constants::SATOSHIS_IN_COIN;
+use miner;
+use super::hash::H256;
+
+/// getmempoolentry response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetMemPoolEntryResponse {
+	/// Transaction virtual size
+	pub vsize: usize,
+	/// Transaction weight
+	pub weight: usize,
+	/// Time, when transaction has entered the memory pool (in seconds since epoch)
+	pub time: u32,
+	/// Best block height, when transaction has entered the memory pool
+	pub height: u32,
+	/// Transaction fee (in BTC)
+	pub fee: f64,
+	/// Transaction fee with virtual fee, which is used to prioritize transaction for mining (in BTC)
+	pub modifiedfee: f64,
+	/// Number of in-pool descendants (including this transaction)
+	pub descendantcount: usize,
+	/// Virtual size of in-pool descendants (including this transaction)
+	pub descendantsize: usize,
+	/// Modified fees of in-pool descendants (including this transaction, in satoshis)
+	pub descendantfees: i64,
+	/// Number of in-pool ancestors (including this transaction)
+	pub ancestorcount: usize,
+	/// Virtual size of in-pool ancestors (including this transaction)
+	pub ancestorsize: usize,
+	/// Modified fees of in-pool ancestors (including this transaction, in satoshis)
+	pub ancestorfees: i64,
+	/// Hash of the transaction, including witness data
+	pub wtxid: H256,
+	/// Hashes of in-pool transactions, which outputs are spent by this transaction
+	pub depends: Vec<H256>,
+	/// Hashes of in-pool transactions, which are spending outputs of this transaction
+	pub spentby: Vec<H256>,
+	/// Could this transaction be replaced (BIP125)?
+	#[serde(rename = "bip125-replaceable")]
+	pub bip125_replaceable: bool,
+}
+
+impl From<miner::MemoryPoolEntryInformation> for GetMemPoolEntryResponse {
+	fn from(entry: miner::MemoryPoolEntryInformation) -> Self {
+		GetMemPoolEntryResponse {
+			vsize: entry.size,
+			weight: entry.transaction.weight(),
+			time: entry.time,
+			height: entry.height,
+			fee: entry.miner_fee as f64 / SATOSHIS_IN_COIN as f64,
+			modifiedfee: (entry.miner_fee + entry.miner_virtual_fee) as f64 / SATOSHIS_IN_COIN as f64,
+			descendantcount: entry.descendants_count,
+			descendantsize: entry.descendants_size,
+			descendantfees: entry.descendants_fee,
+			ancestorcount: entry.ancestors_count,
+			ancestorsize: entry.ancestors_size,
+			ancestorfees: entry.ancestors_fee,
+			wtxid: entry.transaction.witness_hash().reversed().into(),
+			depends: entry.depends.into_iter().map(|hash| hash.reversed().into()).collect(),
+			spentby: entry.spent_by.into_iter().map(|hash| hash.reversed().into()).collect(),
+			bip125_replaceable: entry.replaceable,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::GetMemPoolEntryResponse;
+
+	#[test]
+	fn get_mempool_entry_response_serialize() {
+		let response = GetMemPoolEntryResponse {
+			vsize: 100,
+			weight: 400,
+			time: 1500000000,
+			height: 500000,
+			fee: 0.0001,
+			modifiedfee: 0.0002,
+			descendantcount: 2,
+			descendantsize: 200,
+			descendantfees: 30000,
+			ancestorcount: 1,
+			ancestorsize: 100,
+			ancestorfees: 20000,
+			wtxid: H256::from(1),
+			depends: vec![],
+			spentby: vec![H256::from(2)],
+			bip125_replaceable: true,
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"vsize":100,"weight":400,"time":1500000000,"height":500000,"fee":0.0001,"modifiedfee":0.0002,"descendantcount":2,"descendantsize":200,"descendantfees":30000,"ancestorcount":1,"ancestorsize":100,"ancestorfees":20000,"wtxid":"0100000000000000000000000000000000000000000000000000000000000000","depends":[],"spentby":["0200000000000000000000000000000000000000000000000000000000000000"],"bip125-replaceable":true}"#);
+	}
+}
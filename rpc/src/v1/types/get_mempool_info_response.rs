@@ -0,0 +1,47 @@
+use chain::constants::SATOSHIS_IN_COIN;
+use miner;
+
+/// getmempoolinfo response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetMemPoolInfoResponse {
+	/// Number of transactions in the memory pool
+	pub size: usize,
+	/// Total virtual size of memory pool transactions
+	pub bytes: usize,
+	/// Maximal total virtual size of memory pool transactions
+	pub maxmempool: usize,
+	/// Minimal fee rate (in BTC per 1000 virtual bytes) of transactions, accepted to the memory pool
+	pub mempoolminfee: f64,
+	/// Fee rate (in BTC per 1000 virtual bytes), which is added to the fee rate of evicted transactions to get the minimal fee rate
+	pub incrementalrelayfee: f64,
+}
+
+impl From<miner::MemoryPoolInformation> for GetMemPoolInfoResponse {
+	fn from(information: miner::MemoryPoolInformation) -> Self {
+		GetMemPoolInfoResponse {
+			size: information.transactions_count,
+			bytes: information.transactions_size_in_bytes,
+			maxmempool: information.max_size_in_bytes,
+			mempoolminfee: information.minimum_fee_rate as f64 / SATOSHIS_IN_COIN as f64,
+			incrementalrelayfee: miner::INCREMENTAL_RELAY_FEE as f64 / SATOSHIS_IN_COIN as f64,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use miner;
+	use super::GetMemPoolInfoResponse;
+
+	#[test]
+	fn get_mempool_info_response_serialize() {
+		let response: GetMemPoolInfoResponse = miner::MemoryPoolInformation {
+			transactions_count: 2,
+			transactions_size_in_bytes: 500,
+			max_size_in_bytes: 300000000,
+			minimum_fee_rate: 2000,
+		}.into();
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"size":2,"bytes":500,"maxmempool":300000000,"mempoolminfee":0.00002,"incrementalrelayfee":0.00001}"#);
+	}
+}
@@ -0,0 +1,69 @@
+use p2p::UploadTarget;
+
+/// getnettotals response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetNetTotalsResponse {
+	/// Total bytes received from all peers
+	pub totalbytesrecv: u64,
+	/// Total bytes sent to all peers
+	pub totalbytessent: u64,
+	/// Current time (milliseconds since epoch)
+	pub timemillis: u64,
+	/// Upload target state
+	pub uploadtarget: UploadTargetInfo,
+}
+
+/// Upload target state
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct UploadTargetInfo {
+	/// Duration of the upload target cycle (in seconds)
+	pub timeframe: u32,
+	/// Maximal number of bytes to send during the cycle. 0 if there is no limit
+	pub target: u64,
+	/// True if the target is reached
+	pub target_reached: bool,
+	/// True if historical blocks are still served
+	pub serve_historical_blocks: bool,
+	/// Bytes, which could be sent till the end of the cycle
+	pub bytes_left_in_cycle: u64,
+	/// Seconds till the end of the cycle
+	pub time_left_in_cycle: u32,
+}
+
+impl GetNetTotalsResponse {
+	pub fn new(totals: (u64, u64), upload_target: UploadTarget, timemillis: u64) -> Self {
+		GetNetTotalsResponse {
+			totalbytesrecv: totals.0,
+			totalbytessent: totals.1,
+			timemillis: timemillis,
+			uploadtarget: UploadTargetInfo {
+				timeframe: upload_target.timeframe,
+				target: upload_target.target,
+				target_reached: upload_target.target_reached,
+				serve_historical_blocks: upload_target.serve_historical_blocks,
+				bytes_left_in_cycle: upload_target.bytes_left_in_cycle,
+				time_left_in_cycle: upload_target.time_left_in_cycle,
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use p2p::UploadTarget;
+	use super::GetNetTotalsResponse;
+
+	#[test]
+	fn get_net_totals_response_serialize() {
+		let response = GetNetTotalsResponse::new((1000, 2000), UploadTarget {
+			timeframe: 86400,
+			target: 5000,
+			target_reached: false,
+			serve_historical_blocks: true,
+			bytes_left_in_cycle: 3000,
+			time_left_in_cycle: 600,
+		}, 1500000000000);
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"totalbytesrecv":1000,"totalbytessent":2000,"timemillis":1500000000000,"uploadtarget":{"timeframe":86400,"target":5000,"target_reached":false,"serve_historical_blocks":true,"bytes_left_in_cycle":3000,"time_left_in_cycle":600}}"#);
+	}
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use chain::constants::SATOSHIS_IN_COIN;
+use p2p::{PeerInfo, PeerStats, ConnectionType, Direction};
+use sync::PeerSyncInformation;
+
+/// getpeerinfo response item
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetPeerInfoResponse {
+	/// Peer index
+	pub id: usize,
+	/// Address of the peer
+	pub addr: String,
+	/// Services, offered by the peer (hex-encoded)
+	pub services: String,
+	/// Are transactions relayed to the peer?
+	pub relaytxes: bool,
+	/// Time of the last message, sent to the peer (seconds since epoch)
+	pub lastsend: u32,
+	/// Time of the last message, received from the peer (seconds since epoch)
+	pub lastrecv: u32,
+	/// Total bytes sent to the peer
+	pub bytessent: u64,
+	/// Total bytes received from the peer
+	pub bytesrecv: u64,
+	/// Time of connection (seconds since epoch)
+	pub conntime: u32,
+	/// Duration of the last ping (in seconds)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pingtime: Option<f64>,
+	/// Minimal ping duration (in seconds)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub minping: Option<f64>,
+	/// Protocol version of the peer
+	pub version: u32,
+	/// User agent of the peer
+	pub subver: String,
+	/// Is connection inbound?
+	pub inbound: bool,
+	/// Connection class: inbound, outbound-full-relay, block-relay-only, feeler or addr-fetch
+	pub connection_type: String,
+	/// Permissions, granted to the whitelisted peer
+	pub permissions: Vec<String>,
+	/// Best block height of the peer at the moment of connection
+	pub startingheight: i32,
+	/// Number of blocks, requested from the peer, but not yet received
+	pub inflight: usize,
+	/// Minimal fee rate (in BTC per 1000 bytes) of transactions, announced to the peer
+	pub minfeefilter: f64,
+	/// Total bytes sent to the peer per message type
+	pub bytessent_per_msg: BTreeMap<String, u64>,
+	/// Total bytes received from the peer per message type
+	pub bytesrecv_per_msg: BTreeMap<String, u64>,
+}
+
+impl GetPeerInfoResponse {
+	pub fn new(info: PeerInfo, stats: PeerStats, sync_information: PeerSyncInformation) -> Self {
+		let services: u64 = info.version_message.services().into();
+		GetPeerInfoResponse {
+			id: info.id,
+			addr: info.address.to_string(),
+			services: format!("{:016x}", services),
+			relaytxes: info.version_message.relay_transactions() && info.connection_type != ConnectionType::BlockRelayOnly,
+			lastsend: stats.last_send,
+			lastrecv: stats.last_recv,
+			bytessent: stats.total_send,
+			bytesrecv: stats.total_recv,
+			conntime: stats.connected_time,
+			pingtime: stats.last_ping_delay,
+			minping: stats.min_ping,
+			version: info.version_message.version(),
+			subver: info.user_agent,
+			inbound: info.direction == Direction::Inbound,
+			connection_type: match info.connection_type {
+				ConnectionType::Inbound => "inbound",
+				ConnectionType::OutboundFullRelay => "outbound-full-relay",
+				ConnectionType::BlockRelayOnly => "block-relay-only",
+				ConnectionType::Feeler => "feeler",
+				ConnectionType::AddrFetch => "addr-fetch",
+			}.to_owned(),
+			permissions: vec![
+				(info.permissions.noban, "noban"),
+				(info.permissions.forcerelay, "forcerelay"),
+				(info.permissions.mempool, "mempool"),
+				(info.permissions.bloomfilter, "bloomfilter"),
+			].into_iter().filter(|&(granted, _)| granted).map(|(_, permission)| permission.to_owned()).collect(),
+			startingheight: info.version_message.start_height().unwrap_or(-1),
+			inflight: sync_information.blocks_in_flight,
+			minfeefilter: sync_information.min_fee_rate as f64 / SATOSHIS_IN_COIN as f64,
+			bytessent_per_msg: stats.total_send_per_command.iter().map(|(command, bytes)| (command.to_string(), *bytes)).collect(),
+			bytesrecv_per_msg: stats.total_recv_per_command.iter().map(|(command, bytes)| (command.to_string(), *bytes)).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::GetPeerInfoResponse;
+
+	#[test]
+	fn get_peer_info_response_serialize() {
+		let response = GetPeerInfoResponse {
+			id: 3,
+			addr: "127.0.0.1:8333".to_owned(),
+			services: "0000000000000409".to_owned(),
+			relaytxes: false,
+			lastsend: 1000,
+			lastrecv: 1001,
+			bytessent: 500,
+			bytesrecv: 700,
+			conntime: 900,
+			pingtime: Some(0.5),
+			minping: None,
+			version: 70015,
+			subver: "/Satoshi:0.21.0/".to_owned(),
+			inbound: false,
+			connection_type: "block-relay-only".to_owned(),
+			permissions: vec!["noban".to_owned(), "mempool".to_owned()],
+			startingheight: 600000,
+			inflight: 2,
+			minfeefilter: 0.00001,
+			bytessent_per_msg: vec![("getdata".to_owned(), 300), ("ping".to_owned(), 200)].into_iter().collect(),
+			bytesrecv_per_msg: vec![("block".to_owned(), 700)].into_iter().collect(),
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"id":3,"addr":"127.0.0.1:8333","services":"0000000000000409","relaytxes":false,"lastsend":1000,"lastrecv":1001,"bytessent":500,"bytesrecv":700,"conntime":900,"pingtime":0.5,"version":70015,"subver":"/Satoshi:0.21.0/","inbound":false,"connection_type":"block-relay-only","permissions":["noban","mempool"],"startingheight":600000,"inflight":2,"minfeefilter":0.00001,"bytessent_per_msg":{"getdata":300,"ping":200},"bytesrecv_per_msg":{"block":700}}"#);
+	}
+}
@@ -0,0 +1,22 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Serializer};
+use super::hash::H256;
+use super::get_mempool_entry_response::GetMemPoolEntryResponse;
+
+/// getrawmempool, getmempoolancestors and getmempooldescendants response
+#[derive(Debug, PartialEq)]
+pub enum GetRawMemPoolResponse {
+	/// Return value when asking for transactions hashes
+	Hashes(Vec<H256>),
+	/// Return value when asking for verbose memory pool entries
+	Verbose(BTreeMap<H256, GetMemPoolEntryResponse>),
+}
+
+impl Serialize for GetRawMemPoolResponse {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match *self {
+			GetRawMemPoolResponse::Hashes(ref hashes) => hashes.serialize(serializer),
+			GetRawMemPoolResponse::Verbose(ref entries) => entries.serialize(serializer),
+		}
+	}
+}
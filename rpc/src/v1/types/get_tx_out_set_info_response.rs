@@ -1,3 +1,44 @@
-#[derive(Debug, Serialize, Deserialize)]
+use super::hash::H256;
+
+/// gettxoutsetinfo response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct GetTxOutSetInfoResponse {
+	/// Height of the best block
+	pub height: u32,
+	/// Hash of the best block
+	pub bestblock: H256,
+	/// Number of transactions with unspent outputs
+	pub transactions: u64,
+	/// Number of unspent outputs
+	pub txouts: u64,
+	/// Database independent size of the set
+	pub bogosize: u64,
+	/// MuHash3072 of the set, comparable with the hash, computed by other implementations
+	pub muhash: H256,
+	/// Size of the set in the database
+	pub disk_size: u64,
+	/// Total value of all unspent outputs in BTC
+	pub total_amount: f64,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn tx_out_set_info_response_serialize() {
+		let info = GetTxOutSetInfoResponse {
+			height: 2,
+			bestblock: H256::from(0x56),
+			transactions: 2,
+			txouts: 3,
+			bogosize: 250,
+			muhash: H256::from(0x57),
+			disk_size: 400,
+			total_amount: 100.5,
+		};
+		assert_eq!(serde_json::to_string(&info).unwrap(), r#"{"height":2,"bestblock":"5600000000000000000000000000000000000000000000000000000000000000","transactions":2,"txouts":3,"bogosize":250,"muhash":"5700000000000000000000000000000000000000000000000000000000000000","disk_size":400,"total_amount":100.5}"#);
+	}
 }
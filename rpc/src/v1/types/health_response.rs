@@ -0,0 +1,41 @@
+/// healthcheck response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct HealthResponse {
+	/// Is node synchronized, connected to peers and is database readable?
+	pub healthy: bool,
+	/// Is node synchronized with the network?
+	pub synced: bool,
+	/// Number of blocks in the best chain
+	pub blocks: u32,
+	/// Number of the best known block header
+	pub headers: u32,
+	/// Number of blocks, known to the node or reported by its peers, which are not stored yet
+	pub blocks_behind: u32,
+	/// Seconds since the best block timestamp
+	pub tip_age: u32,
+	/// Number of connected peers
+	pub peers: usize,
+	/// Database status: `ok`, or the error description
+	pub database: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::HealthResponse;
+
+	#[test]
+	fn health_response_serialize() {
+		let health = HealthResponse {
+			healthy: false,
+			synced: false,
+			blocks: 100,
+			headers: 110,
+			blocks_behind: 10,
+			tip_age: 600,
+			peers: 8,
+			database: "ok".to_owned(),
+		};
+		assert_eq!(serde_json::to_string(&health).unwrap(), r#"{"healthy":false,"synced":false,"blocks":100,"headers":110,"blocks_behind":10,"tip_age":600,"peers":8,"database":"ok"}"#);
+	}
+}
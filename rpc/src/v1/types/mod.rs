@@ -1,29 +1,72 @@
 pub mod address;
+mod address_index;
 mod block;
+mod block_stats;
 mod block_template;
 mod block_template_request;
 mod bytes;
+mod chain_tips;
+mod debug_script_response;
+mod fee_estimate;
+mod dump_tx_out_set_response;
+mod dump_utxos_response;
 mod get_block_response;
+mod get_blockchain_info_response;
+mod get_chain_tx_stats_response;
+mod get_mempool_entry_response;
+mod get_mempool_info_response;
+mod get_net_totals_response;
+mod get_peer_info_response;
+mod get_raw_mempool_response;
 mod get_tx_out_response;
 mod get_tx_out_set_info_response;
 mod hash;
+mod health_response;
 mod script;
 mod transaction;
 mod uint;
 mod nodes;
+mod psbt;
+mod scan_tx_out_set;
+mod submit_package_response;
+mod test_mempool_accept_response;
+mod wallet;
 
-pub use self::block::RawBlock;
-pub use self::block_template::{BlockTemplate, BlockTemplateTransaction};
+pub use self::address_index::{AddressesRequest, AddressBalance, AddressUnspentOutput};
+pub use self::block::{RawBlock, RawBlockHeader};
+pub use self::block_stats::{HashOrHeight, GetBlockStatsResponse};
+pub use self::block_template::{BlockTemplate, BlockTemplateTransaction, GetBlockTemplateResponse};
 pub use self::block_template_request::{BlockTemplateRequest, BlockTemplateRequestMode};
 pub use self::bytes::Bytes;
-pub use self::get_block_response::{GetBlockResponse, VerboseBlock};
+pub use self::chain_tips::ChainTip;
+pub use self::debug_script_response::{DebugScriptResponse, DebugScriptStep};
+pub use self::fee_estimate::{EstimateSmartFeeResponse, EstimateRawFeeResponse, HorizonFeeEstimate, FeeRateRange};
+pub use self::get_block_response::{GetBlockResponse, BlockVerbosity, VerboseBlock, GetBlockHeaderResponse, VerboseBlockHeader,
+	BlockTransactions, BlockTransaction};
+pub use self::dump_tx_out_set_response::DumpTxOutSetResponse;
+pub use self::dump_utxos_response::DumpUtxosResponse;
+pub use self::get_blockchain_info_response::{GetBlockchainInfoResponse, Bip9SoftforkInfo};
+pub use self::get_chain_tx_stats_response::GetChainTxStatsResponse;
+pub use self::get_mempool_entry_response::GetMemPoolEntryResponse;
+pub use self::get_mempool_info_response::GetMemPoolInfoResponse;
+pub use self::get_net_totals_response::{GetNetTotalsResponse, UploadTargetInfo};
+pub use self::get_peer_info_response::GetPeerInfoResponse;
+pub use self::get_raw_mempool_response::GetRawMemPoolResponse;
 pub use self::get_tx_out_response::GetTxOutResponse;
 pub use self::get_tx_out_set_info_response::GetTxOutSetInfoResponse;
 pub use self::hash::{H160, H256};
+pub use self::health_response::HealthResponse;
 pub use self::script::ScriptType;
 pub use self::transaction::{RawTransaction, Transaction, TransactionInput, TransactionOutput,
 	TransactionOutputWithAddress, TransactionOutputWithScriptData, TransactionInputScript,
-	TransactionOutputScript, SignedTransactionInput, GetRawTransactionResponse,
+	TransactionOutputScript, SignedTransactionInput, TransactionPrevout, GetRawTransactionResponse,
 	SignedTransactionOutput, TransactionOutputs};
 pub use self::uint::U256;
-pub use self::nodes::{AddNodeOperation, NodeInfo};
+pub use self::nodes::{AddNodeOperation, NodeInfo, SetBanOperation, BannedNode};
+pub use self::psbt::{DecodePsbtResponse, PsbtInput, PsbtOutput, PsbtWitnessUtxo, PsbtKeySource,
+	FinalizePsbtResponse};
+pub use self::scan_tx_out_set::{ScanTxOutSetAction, ScanObject, ScanTxOutSetUnspent, ScanTxOutSetResponse,
+	ScanTxOutSetStatus, ScanTxOutSetResult};
+pub use self::submit_package_response::{SubmitPackageResponse, SubmitPackageTransactionResult};
+pub use self::test_mempool_accept_response::{TestMemPoolAcceptResult, TestMemPoolAcceptFees};
+pub use self::wallet::WalletUnspentOutput;
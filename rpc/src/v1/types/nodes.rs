@@ -1,7 +1,7 @@
 use std::fmt;
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::de::Unexpected;
-use p2p::{Direction, PeerInfo};
+use p2p::{Direction, PeerInfo, BanEntry};
 
 #[derive(Debug, PartialEq)]
 pub enum AddNodeOperation {
@@ -37,6 +37,55 @@ impl<'a> Deserialize<'a> for AddNodeOperation {
 	}
 }
 
+#[derive(Debug, PartialEq)]
+pub enum SetBanOperation {
+	Add,
+	Remove,
+}
+
+impl<'a> Deserialize<'a> for SetBanOperation {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		use serde::de::Visitor;
+
+		struct DummyVisitor;
+
+		impl<'b> Visitor<'b> for DummyVisitor {
+			type Value = SetBanOperation;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a ban operation string")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<SetBanOperation, E> where E: ::serde::de::Error {
+				match value {
+					"add" => Ok(SetBanOperation::Add),
+					"remove" => Ok(SetBanOperation::Remove),
+					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+				}
+			}
+		}
+
+		deserializer.deserialize_identifier(DummyVisitor)
+	}
+}
+
+#[derive(Serialize)]
+pub struct BannedNode {
+	pub address: String,
+	pub ban_created: i64,
+	pub banned_until: i64,
+}
+
+impl From<BanEntry> for BannedNode {
+	fn from(entry: BanEntry) -> Self {
+		BannedNode {
+			address: format!("{}", entry.address),
+			ban_created: entry.created,
+			banned_until: entry.until,
+		}
+	}
+}
+
 #[derive(Serialize)]
 pub struct NodeInfoAddress {
 	address: String,
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use rustc_serialize::hex::ToHex;
+use chain::constants::SATOSHIS_IN_COIN;
+use ser::{serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use psbt;
+use super::bytes::Bytes;
+use super::hash::H256;
+use super::transaction::RawTransaction;
+
+/// decodepsbt response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DecodePsbtResponse {
+	/// Serialized unsigned transaction
+	pub tx: RawTransaction,
+	/// Hash of the unsigned transaction
+	pub txid: H256,
+	/// Unknown global key-value pairs
+	pub unknown: BTreeMap<String, String>,
+	/// Input maps
+	pub inputs: Vec<PsbtInput>,
+	/// Output maps
+	pub outputs: Vec<PsbtOutput>,
+	/// Transaction fee (in BTC), if values of all spent outputs are known
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub fee: Option<f64>,
+}
+
+/// PSBT input map
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct PsbtInput {
+	/// Serialized transaction, spent by the input
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub non_witness_utxo: Option<RawTransaction>,
+	/// Output, spent by the input
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub witness_utxo: Option<PsbtWitnessUtxo>,
+	/// Signatures, keyed by public keys
+	#[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+	pub partial_signatures: BTreeMap<String, String>,
+	/// Signature hash type
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub sighash: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub redeem_script: Option<Bytes>,
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub bip32_derivs: Vec<PsbtKeySource>,
+	#[serde(rename = "final_scriptSig", skip_serializing_if = "Option::is_none", default)]
+	pub final_script_sig: Option<Bytes>,
+	#[serde(rename = "final_scriptwitness", skip_serializing_if = "Option::is_none", default)]
+	pub final_script_witness: Option<Vec<Bytes>>,
+	/// Unknown key-value pairs
+	#[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+	pub unknown: BTreeMap<String, String>,
+}
+
+/// PSBT output map
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct PsbtOutput {
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub redeem_script: Option<Bytes>,
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub witness_script: Option<Bytes>,
+	/// Origins of public keys
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub bip32_derivs: Vec<PsbtKeySource>,
+	/// Unknown key-value pairs
+	#[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+	pub unknown: BTreeMap<String, String>,
+}
+
+/// Output, spent by the witness input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtWitnessUtxo {
+	/// Output value (in BTC)
+	pub amount: f64,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pub_key: Bytes,
+}
+
+/// Origin of the public key
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PsbtKeySource {
+	/// Public key
+	pub pubkey: Bytes,
+	/// Fingerprint of the master key
+	pub master_fingerprint: String,
+	/// Derivation path, like m/0'/1
+	pub path: String,
+}
+
+/// finalizepsbt response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FinalizePsbtResponse {
+	/// Base64-encoded PSBT, if the transaction isn't extracted
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub psbt: Option<String>,
+	/// Serialized signed transaction, if it is extracted
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub hex: Option<RawTransaction>,
+	/// Are all inputs finalized?
+	pub complete: bool,
+}
+
+impl From<psbt::Psbt> for DecodePsbtResponse {
+	fn from(psbt: psbt::Psbt) -> Self {
+		DecodePsbtResponse {
+			tx: serialize(&psbt.unsigned_transaction).into(),
+			txid: psbt.unsigned_transaction.hash().reversed().into(),
+			fee: psbt.fee().map(|fee| fee as f64 / SATOSHIS_IN_COIN as f64),
+			unknown: pairs_map(psbt.unknown),
+			inputs: psbt.inputs.into_iter().map(Into::into).collect(),
+			outputs: psbt.outputs.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+impl From<psbt::PsbtInput> for PsbtInput {
+	fn from(input: psbt::PsbtInput) -> Self {
+		PsbtInput {
+			non_witness_utxo: input.non_witness_utxo.map(|transaction| serialize_with_flags(&transaction, SERIALIZE_TRANSACTION_WITNESS).into()),
+			witness_utxo: input.witness_utxo.map(|output| PsbtWitnessUtxo {
+				amount: output.value as f64 / SATOSHIS_IN_COIN as f64,
+				script_pub_key: output.script_pubkey.into(),
+			}),
+			partial_signatures: pairs_map(input.partial_signatures),
+			sighash: input.sighash_type.map(sighash_name),
+			redeem_script: input.redeem_script.map(Into::into),
+			witness_script: input.witness_script.map(Into::into),
+			bip32_derivs: input.bip32_derivations.into_iter().map(Into::into).collect(),
+			final_script_sig: input.final_script_sig.map(Into::into),
+			final_script_witness: input.final_script_witness.map(|witness| witness.into_iter().map(Into::into).collect()),
+			unknown: pairs_map(input.unknown),
+		}
+	}
+}
+
+impl From<psbt::PsbtOutput> for PsbtOutput {
+	fn from(output: psbt::PsbtOutput) -> Self {
+		PsbtOutput {
+			redeem_script: output.redeem_script.map(Into::into),
+			witness_script: output.witness_script.map(Into::into),
+			bip32_derivs: output.bip32_derivations.into_iter().map(Into::into).collect(),
+			unknown: pairs_map(output.unknown),
+		}
+	}
+}
+
+impl From<(::primitives::bytes::Bytes, psbt::KeySource)> for PsbtKeySource {
+	fn from((public, source): (::primitives::bytes::Bytes, psbt::KeySource)) -> Self {
+		let path = source.path.iter()
+			.map(|index| match *index {
+				index if index >= 0x80000000 => format!("/{}'", index - 0x80000000),
+				index => format!("/{}", index),
+			})
+			.collect::<String>();
+
+		PsbtKeySource {
+			pubkey: public.into(),
+			master_fingerprint: source.fingerprint.to_hex(),
+			path: format!("m{}", path),
+		}
+	}
+}
+
+fn pairs_map(pairs: psbt::Pairs) -> BTreeMap<String, String> {
+	pairs.into_iter()
+		.map(|(key, value)| (key.to_hex(), value.to_hex()))
+		.collect()
+}
+
+fn sighash_name(sighash: u32) -> String {
+	match sighash {
+		0x01 => "ALL".into(),
+		0x02 => "NONE".into(),
+		0x03 => "SINGLE".into(),
+		0x81 => "ALL|ANYONECANPAY".into(),
+		0x82 => "NONE|ANYONECANPAY".into(),
+		0x83 => "SINGLE|ANYONECANPAY".into(),
+		sighash => sighash.to_string(),
+	}
+}
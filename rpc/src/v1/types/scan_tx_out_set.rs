@@ -0,0 +1,269 @@
+use std::fmt;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{Visitor, MapAccess, SeqAccess, Unexpected, Error as DeError};
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// Action of the scantxoutset RPC request
+#[derive(Debug, PartialEq)]
+pub enum ScanTxOutSetAction {
+	/// Start the scan
+	Start,
+	/// Abort the running scan
+	Abort,
+	/// Get progress of the running scan
+	Status,
+}
+
+impl<'a> Deserialize<'a> for ScanTxOutSetAction {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		struct DummyVisitor;
+
+		impl<'b> Visitor<'b> for DummyVisitor {
+			type Value = ScanTxOutSetAction;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a scan action string")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<ScanTxOutSetAction, E> where E: DeError {
+				match value {
+					"start" => Ok(ScanTxOutSetAction::Start),
+					"abort" => Ok(ScanTxOutSetAction::Abort),
+					"status" => Ok(ScanTxOutSetAction::Status),
+					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+				}
+			}
+		}
+
+		deserializer.deserialize_identifier(DummyVisitor)
+	}
+}
+
+/// Scan object: either descriptor string or `{"desc": descriptor, "range": n or [begin, end]}` object.
+#[derive(Debug, PartialEq)]
+pub struct ScanObject {
+	/// Output descriptor
+	pub desc: String,
+	/// Inclusive range of child indexes of the ranged descriptor
+	pub range: Option<(u32, u32)>,
+}
+
+/// Inclusive range of child indexes: `n` is the same as `[0, n]`.
+struct ScanRange(u32, u32);
+
+impl<'a> Deserialize<'a> for ScanRange {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		struct ScanRangeVisitor;
+
+		impl<'b> Visitor<'b> for ScanRangeVisitor {
+			type Value = ScanRange;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("an end of the range or a [begin, end] pair")
+			}
+
+			fn visit_u64<E>(self, value: u64) -> Result<ScanRange, E> where E: DeError {
+				if value > u32::max_value() as u64 {
+					return Err(E::invalid_value(Unexpected::Unsigned(value), &self));
+				}
+
+				Ok(ScanRange(0, value as u32))
+			}
+
+			fn visit_i64<E>(self, value: i64) -> Result<ScanRange, E> where E: DeError {
+				if value < 0 {
+					return Err(E::invalid_value(Unexpected::Signed(value), &self));
+				}
+
+				self.visit_u64(value as u64)
+			}
+
+			fn visit_seq<V>(self, mut visitor: V) -> Result<ScanRange, V::Error> where V: SeqAccess<'b> {
+				let begin = match try!(visitor.next_element()) {
+					Some(begin) => begin,
+					None => return Err(V::Error::invalid_length(0, &self)),
+				};
+				let end = match try!(visitor.next_element()) {
+					Some(end) => end,
+					None => return Err(V::Error::invalid_length(1, &self)),
+				};
+				if try!(visitor.next_element::<u32>()).is_some() {
+					return Err(V::Error::invalid_length(3, &self));
+				}
+
+				Ok(ScanRange(begin, end))
+			}
+		}
+
+		deserializer.deserialize_any(ScanRangeVisitor)
+	}
+}
+
+impl<'a> Deserialize<'a> for ScanObject {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		struct ScanObjectVisitor;
+
+		impl<'b> Visitor<'b> for ScanObjectVisitor {
+			type Value = ScanObject;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a descriptor string or a scan object")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<ScanObject, E> where E: DeError {
+				Ok(ScanObject {
+					desc: value.to_owned(),
+					range: None,
+				})
+			}
+
+			fn visit_map<V>(self, mut visitor: V) -> Result<ScanObject, V::Error> where V: MapAccess<'b> {
+				let mut desc = None;
+				let mut range = None;
+				while let Some(key) = try!(visitor.next_key::<String>()) {
+					match key.as_str() {
+						"desc" => desc = Some(try!(visitor.next_value::<String>())),
+						"range" => range = Some(try!(visitor.next_value::<ScanRange>())),
+						_ => return Err(V::Error::unknown_field(&key, &["desc", "range"])),
+					}
+				}
+
+				match desc {
+					Some(desc) => Ok(ScanObject {
+						desc: desc,
+						range: range.map(|range| (range.0, range.1)),
+					}),
+					None => Err(V::Error::missing_field("desc")),
+				}
+			}
+		}
+
+		deserializer.deserialize_any(ScanObjectVisitor)
+	}
+}
+
+/// Unspent output, found by the scantxoutset RPC
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScanTxOutSetUnspent {
+	/// Transaction hash
+	pub txid: H256,
+	/// Index of the output in the transaction
+	pub vout: u32,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pub_key: Bytes,
+	/// Descriptor, which matches the output script
+	pub desc: String,
+	/// Output value in BTC
+	pub amount: f64,
+	/// Is output created by the coinbase transaction?
+	pub coinbase: bool,
+	/// Height of the block, which includes the transaction
+	pub height: u32,
+}
+
+/// Result of the finished scan
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScanTxOutSetResponse {
+	/// False if scan has been aborted
+	pub success: bool,
+	/// Number of scanned unspent outputs
+	pub txouts: u64,
+	/// Height of the best block at the moment of the scan
+	pub height: u32,
+	/// Hash of the best block at the moment of the scan
+	pub bestblock: H256,
+	/// Unspent outputs, matching the scan objects
+	pub unspents: Vec<ScanTxOutSetUnspent>,
+	/// Total value of the found outputs in BTC
+	pub total_amount: f64,
+}
+
+/// Progress of the running scan
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScanTxOutSetStatus {
+	/// Scanned part of the UTXO set, from 0 to 100
+	pub progress: f64,
+}
+
+/// Response to scantxoutset RPC request
+#[derive(Debug, PartialEq)]
+pub enum ScanTxOutSetResult {
+	/// When starting the scan
+	Scan(ScanTxOutSetResponse),
+	/// When asking for status. None if there's no running scan
+	Status(Option<ScanTxOutSetStatus>),
+	/// When aborting the scan. False if there's no running scan
+	Abort(bool),
+}
+
+impl Serialize for ScanTxOutSetResult {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match *self {
+			ScanTxOutSetResult::Scan(ref response) => response.serialize(serializer),
+			ScanTxOutSetResult::Status(ref status) => status.serialize(serializer),
+			ScanTxOutSetResult::Abort(aborted) => aborted.serialize(serializer),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::bytes::Bytes;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn scan_tx_out_set_action_deserialize() {
+		assert_eq!(serde_json::from_str::<ScanTxOutSetAction>(r#""start""#).unwrap(), ScanTxOutSetAction::Start);
+		assert_eq!(serde_json::from_str::<ScanTxOutSetAction>(r#""abort""#).unwrap(), ScanTxOutSetAction::Abort);
+		assert_eq!(serde_json::from_str::<ScanTxOutSetAction>(r#""status""#).unwrap(), ScanTxOutSetAction::Status);
+		assert!(serde_json::from_str::<ScanTxOutSetAction>(r#""stop""#).is_err());
+	}
+
+	#[test]
+	fn scan_object_deserialize() {
+		assert_eq!(serde_json::from_str::<ScanObject>(r#""raw(00)""#).unwrap(), ScanObject {
+			desc: "raw(00)".to_owned(),
+			range: None,
+		});
+		assert_eq!(serde_json::from_str::<ScanObject>(r#"{"desc":"raw(00)","range":10}"#).unwrap(), ScanObject {
+			desc: "raw(00)".to_owned(),
+			range: Some((0, 10)),
+		});
+		assert_eq!(serde_json::from_str::<ScanObject>(r#"{"range":[5,7],"desc":"raw(00)"}"#).unwrap(), ScanObject {
+			desc: "raw(00)".to_owned(),
+			range: Some((5, 7)),
+		});
+		assert!(serde_json::from_str::<ScanObject>(r#"{"range":10}"#).is_err());
+		assert!(serde_json::from_str::<ScanObject>(r#"{"desc":"raw(00)","range":[1]}"#).is_err());
+		assert!(serde_json::from_str::<ScanObject>(r#"{"desc":"raw(00)","range":-1}"#).is_err());
+	}
+
+	#[test]
+	fn scan_tx_out_set_result_serialize() {
+		let response = ScanTxOutSetResult::Scan(ScanTxOutSetResponse {
+			success: true,
+			txouts: 10,
+			height: 2,
+			bestblock: H256::from(1),
+			unspents: vec![ScanTxOutSetUnspent {
+				txid: H256::from(2),
+				vout: 1,
+				script_pub_key: Bytes::new(vec![0x51]),
+				desc: "raw(51)#8lvh9jxk".to_owned(),
+				amount: 0.5,
+				coinbase: false,
+				height: 1,
+			}],
+			total_amount: 0.5,
+		});
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"success":true,"txouts":10,"height":2,"bestblock":"0100000000000000000000000000000000000000000000000000000000000000","unspents":[{"txid":"0200000000000000000000000000000000000000000000000000000000000000","vout":1,"scriptPubKey":"51","desc":"raw(51)#8lvh9jxk","amount":0.5,"coinbase":false,"height":1}],"total_amount":0.5}"#);
+
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResult::Status(Some(ScanTxOutSetStatus { progress: 50.0 }))).unwrap(), r#"{"progress":50.0}"#);
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResult::Status(None)).unwrap(), "null");
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResult::Abort(true)).unwrap(), "true");
+	}
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use chain::constants::SATOSHIS_IN_COIN;
+use sync;
+use super::hash::H256;
+use super::test_mempool_accept_response::TestMemPoolAcceptFees;
+
+/// submitpackage response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubmitPackageResponse {
+	/// "success" if the package is accepted, or the reason of its rejection
+	pub package_msg: String,
+	/// Results of package transactions, keyed by their witness hashes
+	#[serde(rename = "tx-results")]
+	pub tx_results: BTreeMap<H256, SubmitPackageTransactionResult>,
+}
+
+/// submitpackage result of the single transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubmitPackageTransactionResult {
+	/// Hash of the transaction
+	pub txid: H256,
+	/// Transaction virtual size, present only if the transaction is valid
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub vsize: Option<usize>,
+	/// Transaction fees, present only if the transaction is valid
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fees: Option<TestMemPoolAcceptFees>,
+	/// Reason of the transaction rejection, present only if the transaction is invalid
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl From<sync::PackageAcceptResult> for SubmitPackageResponse {
+	fn from(result: sync::PackageAcceptResult) -> Self {
+		SubmitPackageResponse {
+			package_msg: result.reject_reason.unwrap_or_else(|| "success".into()),
+			tx_results: result.transactions.into_iter()
+				.map(|transaction| {
+					let (vsize, fees, error) = match transaction.result {
+						Ok(fee) => (Some(transaction.vsize), Some(TestMemPoolAcceptFees { base: fee as f64 / SATOSHIS_IN_COIN as f64 }), None),
+						Err(error) => (None, None, Some(error)),
+					};
+					(transaction.witness_hash.reversed().into(), SubmitPackageTransactionResult {
+						txid: transaction.hash.reversed().into(),
+						vsize: vsize,
+						fees: fees,
+						error: error,
+					})
+				})
+				.collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+	use serde_json;
+	use super::super::hash::H256;
+	use super::super::test_mempool_accept_response::TestMemPoolAcceptFees;
+	use super::*;
+
+	#[test]
+	fn submit_package_response_serialize() {
+		let mut tx_results = BTreeMap::new();
+		tx_results.insert(H256::from(2), SubmitPackageTransactionResult {
+			txid: H256::from(1),
+			vsize: Some(110),
+			fees: Some(TestMemPoolAcceptFees { base: 0.0001 }),
+			error: None,
+		});
+		tx_results.insert(H256::from(4), SubmitPackageTransactionResult {
+			txid: H256::from(3),
+			vsize: None,
+			fees: None,
+			error: Some("bad-txns-inputs-missingorspent".into()),
+		});
+		let response = SubmitPackageResponse {
+			package_msg: "transaction failed".into(),
+			tx_results: tx_results,
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"package_msg":"transaction failed","tx-results":{"0200000000000000000000000000000000000000000000000000000000000000":{"txid":"0100000000000000000000000000000000000000000000000000000000000000","vsize":110,"fees":{"base":0.0001}},"0400000000000000000000000000000000000000000000000000000000000000":{"txid":"0300000000000000000000000000000000000000000000000000000000000000","error":"bad-txns-inputs-missingorspent"}}}"#);
+	}
+}
@@ -0,0 +1,78 @@
+use chain::constants::SATOSHIS_IN_COIN;
+use sync;
+use super::hash::H256;
+
+/// testmempoolaccept result of the single transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TestMemPoolAcceptResult {
+	/// Hash of the transaction
+	pub txid: H256,
+	/// Hash of the transaction, including witness data
+	pub wtxid: H256,
+	/// Would the transaction be accepted to the memory pool?
+	pub allowed: bool,
+	/// Transaction virtual size, present only if the transaction is allowed
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub vsize: Option<usize>,
+	/// Transaction fees, present only if the transaction is allowed
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fees: Option<TestMemPoolAcceptFees>,
+	/// Reason of the transaction rejection, present only if the transaction isn't allowed
+	#[serde(rename = "reject-reason", skip_serializing_if = "Option::is_none")]
+	pub reject_reason: Option<String>,
+}
+
+/// Fees of the tested transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TestMemPoolAcceptFees {
+	/// Transaction fee (in BTC)
+	pub base: f64,
+}
+
+impl From<sync::TestAcceptResult> for TestMemPoolAcceptResult {
+	fn from(result: sync::TestAcceptResult) -> Self {
+		let (fees, reject_reason) = match result.result {
+			Ok(fee) => (Some(TestMemPoolAcceptFees { base: fee as f64 / SATOSHIS_IN_COIN as f64 }), None),
+			Err(reject_reason) => (None, Some(reject_reason)),
+		};
+
+		TestMemPoolAcceptResult {
+			txid: result.hash.reversed().into(),
+			wtxid: result.witness_hash.reversed().into(),
+			allowed: fees.is_some(),
+			vsize: fees.as_ref().map(|_| result.vsize),
+			fees: fees,
+			reject_reason: reject_reason,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn test_mempool_accept_result_serialize() {
+		let allowed = TestMemPoolAcceptResult {
+			txid: H256::from(1),
+			wtxid: H256::from(2),
+			allowed: true,
+			vsize: Some(110),
+			fees: Some(TestMemPoolAcceptFees { base: 0.0001 }),
+			reject_reason: None,
+		};
+		assert_eq!(serde_json::to_string(&allowed).unwrap(), r#"{"txid":"0100000000000000000000000000000000000000000000000000000000000000","wtxid":"0200000000000000000000000000000000000000000000000000000000000000","allowed":true,"vsize":110,"fees":{"base":0.0001}}"#);
+
+		let rejected = TestMemPoolAcceptResult {
+			txid: H256::from(1),
+			wtxid: H256::from(2),
+			allowed: false,
+			vsize: None,
+			fees: None,
+			reject_reason: Some("txn-already-in-mempool".into()),
+		};
+		assert_eq!(serde_json::to_string(&rejected).unwrap(), r#"{"txid":"0100000000000000000000000000000000000000000000000000000000000000","wtxid":"0200000000000000000000000000000000000000000000000000000000000000","allowed":false,"reject-reason":"txn-already-in-mempool"}"#);
+	}
+}
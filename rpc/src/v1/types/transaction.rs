@@ -93,6 +93,23 @@ pub struct SignedTransactionInput {
 	pub sequence: u32,
 	/// Hex-encoded witness data (if any)
 	pub txinwitness: Vec<String>,
+	/// Output, spent by the input (only in `getblock` response with verbosity 3)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub prevout: Option<TransactionPrevout>,
+}
+
+/// Output, spent by the transaction input
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransactionPrevout {
+	/// True if the output is created by the coinbase transaction
+	pub generated: bool,
+	/// Height of the block, which includes transaction of the output
+	pub height: u32,
+	/// Output value in BTC
+	pub value: f64,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script: TransactionOutputScript,
 }
 
 /// Signed transaction output
@@ -120,6 +137,8 @@ pub struct Transaction {
 	pub size: usize,
 	/// The virtual transaction size (differs from size for witness transactions)
 	pub vsize: usize,
+	/// The transaction weight (between vsize * 4 - 3 and vsize * 4)
+	pub weight: usize,
 	/// The version
 	pub version: i32,
 	/// The lock time
@@ -358,6 +377,7 @@ mod tests {
 			},
 			sequence: 123,
 			txinwitness: vec![],
+			prevout: None,
 		};
 		assert_eq!(serde_json::to_string(&txin).unwrap(), r#"{"txid":"4d00000000000000000000000000000000000000000000000000000000000000","vout":13,"script_sig":{"asm":"Hello, world!!!","hex":"01020304"},"sequence":123,"txinwitness":[]}"#);
 	}
@@ -373,6 +393,7 @@ mod tests {
 			},
 			sequence: 123,
 			txinwitness: vec![],
+			prevout: None,
 		};
 		assert_eq!(
 			serde_json::from_str::<SignedTransactionInput>(r#"{"txid":"4d00000000000000000000000000000000000000000000000000000000000000","vout":13,"script_sig":{"asm":"Hello, world!!!","hex":"01020304"},"sequence":123,"txinwitness":[]}"#).unwrap(),
@@ -421,6 +442,7 @@ mod tests {
 			hash: H256::from(5),
 			size: 33,
 			vsize: 44,
+			weight: 176,
 			version: 55,
 			locktime: 66,
 			vin: vec![],
@@ -430,7 +452,7 @@ mod tests {
 			time: 88,
 			blocktime: 99,
 		};
-		assert_eq!(serde_json::to_string(&tx).unwrap(), r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#);
+		assert_eq!(serde_json::to_string(&tx).unwrap(), r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"weight":176,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#);
 	}
 
 	#[test]
@@ -441,6 +463,7 @@ mod tests {
 			hash: H256::from(5),
 			size: 33,
 			vsize: 44,
+			weight: 176,
 			version: 55,
 			locktime: 66,
 			vin: vec![],
@@ -451,7 +474,7 @@ mod tests {
 			blocktime: 99,
 		};
 		assert_eq!(
-			serde_json::from_str::<Transaction>(r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#).unwrap(),
+			serde_json::from_str::<Transaction>(r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"vsize":44,"weight":176,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#).unwrap(),
 			tx);
 	}
 }
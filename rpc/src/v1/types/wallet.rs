@@ -0,0 +1,42 @@
+use chain::constants::SATOSHIS_IN_COIN;
+use wallet;
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// Unspent output, returned by listunspent
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct WalletUnspentOutput {
+	/// Hash of the transaction
+	pub txid: H256,
+	/// Index of the output in the transaction
+	pub vout: u32,
+	/// Address, the output is paying to
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub address: Option<String>,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pub_key: Bytes,
+	/// Output value (in BTC)
+	pub amount: f64,
+	/// Number of confirmations
+	pub confirmations: u32,
+	/// Is private key of the output known to the wallet?
+	pub spendable: bool,
+	/// Descriptor, the output is paying to
+	pub desc: String,
+}
+
+impl From<wallet::UnspentOutput> for WalletUnspentOutput {
+	fn from(output: wallet::UnspentOutput) -> Self {
+		WalletUnspentOutput {
+			txid: output.outpoint.hash.reversed().into(),
+			vout: output.outpoint.index,
+			address: output.address.map(|address| address.to_string()),
+			script_pub_key: output.script_pubkey.into(),
+			amount: output.value as f64 / SATOSHIS_IN_COIN as f64,
+			confirmations: output.confirmations,
+			spendable: output.spendable,
+			desc: output.descriptor.to_string(),
+		}
+	}
+}
@@ -0,0 +1,886 @@
+//! WebSocket subscriptions to the new blocks and memory pool transactions, so that clients don't
+//! have to poll `getbestblockhash`.
+//!
+//! JSON-RPC 2.0 requests are sent in text frames of the WebSocket connection, opened at `/`. Supported methods:
+//! - `blockchain.headers.subscribe`: returns the best block header as `{"height": <height>, "hex": <header>}`
+//!   and then notifies about every new best block with the same object;
+//! - `mempool.tx.subscribe`: returns `true` and then notifies about hashes of transactions, accepted to the memory pool;
+//! - `blockchain.headers.unsubscribe`, `mempool.tx.unsubscribe`: cancel the subscription, returning `true` if it was active.
+//!
+//! Notifications are JSON-RPC requests without id, named after the subscribe method:
+//! `{"jsonrpc":"2.0","method":"blockchain.headers.subscribe","params":[{"height":1,"hex":"..."}]}`.
+//!
+//! Clients, which can't use WebSocket, may long-poll instead: `GET /longpoll/<hash>` responds with the
+//! best block header once the best block differs from the block with given hash, or after 30 seconds.
+//!
+//! Notifications are not sent while the node is synchronizing.
+//!
+//! The server has no authentication, so it only listens on non-loopback addresses if explicitly allowed.
+//! Every connection has at most two subscriptions, and the number of connections from the same IP address is limited.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use parking_lot::{Mutex, Condvar};
+use serde::Serialize;
+use serde_json;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use jsonrpc_core::{Request, Response, Call, Output, Success, Failure, Error, Id, Version, Value};
+use chain::IndexedTransaction;
+use crypto::sha1;
+use db::{self, BlockRef};
+use primitives::hash::H256 as GlobalH256;
+use sync::SyncListener;
+use v1::types::Bytes;
+
+/// How often the server checks if it should be stopped.
+const ACCEPT_INTERVAL_MS: u64 = 100;
+/// How often connections check if the server should be stopped.
+const READ_INTERVAL_MS: u64 = 1000;
+/// Time to wait for the request head.
+const REQUEST_TIMEOUT_MS: u64 = 5000;
+/// Time to wait for the frame to be written. Clients, which don't read notifications, are disconnected.
+const WRITE_TIMEOUT_MS: u64 = 5000;
+/// Time to wait for the new best block in the long-polling request.
+const LONGPOLL_TIMEOUT_MS: u64 = 30000;
+/// Maximal size of the request head.
+const MAX_REQUEST_SIZE: usize = 8192;
+/// Maximal size of the message, received from the client.
+const MAX_MESSAGE_SIZE: usize = 65536;
+/// Maximal number of concurrently served connections.
+const MAX_CONNECTIONS: usize = 64;
+/// Maximal number of concurrently served connections from the same IP address.
+const MAX_CONNECTIONS_PER_IP: usize = 8;
+/// Appended to the `Sec-WebSocket-Key` to compute `Sec-WebSocket-Accept` (RFC 6455).
+const WEBSOCKET_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const STATUS_OK: &'static str = "200 OK";
+const STATUS_BAD_REQUEST: &'static str = "400 Bad Request";
+const STATUS_NOT_FOUND: &'static str = "404 Not Found";
+const STATUS_METHOD_NOT_ALLOWED: &'static str = "405 Method Not Allowed";
+const STATUS_SERVICE_UNAVAILABLE: &'static str = "503 Service Unavailable";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+const CLOSE_GOING_AWAY: u16 = 1001;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+const CLOSE_INVALID_DATA: u16 = 1007;
+const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
+const HEADERS_SUBSCRIBE: &'static str = "blockchain.headers.subscribe";
+const HEADERS_UNSUBSCRIBE: &'static str = "blockchain.headers.unsubscribe";
+const TRANSACTIONS_SUBSCRIBE: &'static str = "mempool.tx.subscribe";
+const TRANSACTIONS_UNSUBSCRIBE: &'static str = "mempool.tx.unsubscribe";
+
+/// Block header, returned by `blockchain.headers.subscribe` and long-polling requests.
+#[derive(Debug, Serialize, PartialEq)]
+struct HeaderNotification {
+	/// Height of the block
+	height: u32,
+	/// Serialized block header
+	hex: Bytes,
+}
+
+/// Notification of the subscribed client.
+#[derive(Debug, Serialize)]
+struct SubscriptionNotification<'a, T> {
+	jsonrpc: &'static str,
+	method: &'a str,
+	params: [T; 1],
+}
+
+/// Subscriptions of the single client.
+#[derive(Debug, Default)]
+struct ClientSubscriptions {
+	headers: AtomicBool,
+	transactions: AtomicBool,
+}
+
+/// Connected WebSocket client.
+struct Client {
+	stream: Mutex<TcpStream>,
+	subscriptions: ClientSubscriptions,
+}
+
+impl Client {
+	fn send(&self, opcode: u8, payload: &[u8]) -> Result<(), io::Error> {
+		let mut stream = self.stream.lock();
+		stream.write_all(&encode_frame(opcode, payload))?;
+		stream.flush()
+	}
+
+	fn close(&self, code: u16) -> Result<(), io::Error> {
+		self.send(OPCODE_CLOSE, &[(code >> 8) as u8, code as u8])
+	}
+}
+
+/// Handles subscription requests and notifies subscribed clients.
+pub struct WsHandler {
+	storage: db::SharedStore,
+	clients: Mutex<HashMap<usize, Arc<Client>>>,
+	next_client_id: AtomicUsize,
+	/// Long-polling requests wait on this condvar for the new best block.
+	new_block: Condvar,
+	new_block_lock: Mutex<()>,
+}
+
+impl WsHandler {
+	pub fn new(storage: db::SharedStore) -> Self {
+		WsHandler {
+			storage: storage,
+			clients: Mutex::new(HashMap::new()),
+			next_client_id: AtomicUsize::new(0),
+			new_block: Condvar::new(),
+			new_block_lock: Mutex::new(()),
+		}
+	}
+
+	/// Returns response to the JSON-RPC request, received from the client with given subscriptions.
+	fn handle(&self, subscriptions: &ClientSubscriptions, request: &str) -> Option<String> {
+		let response = match serde_json::from_str::<Request>(request) {
+			Ok(Request::Single(call)) => self.handle_call(subscriptions, call).map(Response::Single),
+			Ok(Request::Batch(calls)) => {
+				let outputs: Vec<_> = calls.into_iter().filter_map(|call| self.handle_call(subscriptions, call)).collect();
+				if outputs.is_empty() { None } else { Some(Response::Batch(outputs)) }
+			},
+			Err(_) => Some(Response::Single(Output::Failure(Failure {
+				jsonrpc: Some(Version::V2),
+				error: Error::parse_error(),
+				id: Id::Null,
+			}))),
+		};
+
+		response.map(|response| serde_json::to_string(&response).expect("JSON-RPC responses are always serializable; qed"))
+	}
+
+	fn handle_call(&self, subscriptions: &ClientSubscriptions, call: Call) -> Option<Output> {
+		match call {
+			Call::MethodCall(call) => Some(match self.call_method(subscriptions, &call.method) {
+				Ok(result) => Output::Success(Success {
+					jsonrpc: call.jsonrpc,
+					result: result,
+					id: call.id,
+				}),
+				Err(error) => Output::Failure(Failure {
+					jsonrpc: call.jsonrpc,
+					error: error,
+					id: call.id,
+				}),
+			}),
+			Call::Notification(notification) => {
+				let _ = self.call_method(subscriptions, &notification.method);
+				None
+			},
+			Call::Invalid(id) => Some(Output::Failure(Failure {
+				jsonrpc: Some(Version::V2),
+				error: Error::invalid_request(),
+				id: id,
+			})),
+		}
+	}
+
+	/// Parameters of the subscription methods are ignored.
+	fn call_method(&self, subscriptions: &ClientSubscriptions, method: &str) -> Result<Value, Error> {
+		match method {
+			HEADERS_SUBSCRIBE => {
+				subscriptions.headers.store(true, Ordering::SeqCst);
+				let header = self.header(&self.storage.best_block().hash);
+				Ok(serde_json::to_value(header).expect("Block headers are always serializable; qed"))
+			},
+			HEADERS_UNSUBSCRIBE => Ok(Value::Bool(subscriptions.headers.swap(false, Ordering::SeqCst))),
+			TRANSACTIONS_SUBSCRIBE => {
+				subscriptions.transactions.store(true, Ordering::SeqCst);
+				Ok(Value::Bool(true))
+			},
+			TRANSACTIONS_UNSUBSCRIBE => Ok(Value::Bool(subscriptions.transactions.swap(false, Ordering::SeqCst))),
+			_ => Err(Error::method_not_found()),
+		}
+	}
+
+	/// Returns header of the main chain block with given hash.
+	fn header(&self, hash: &GlobalH256) -> Option<HeaderNotification> {
+		let height = match self.storage.block_number(hash) {
+			Some(height) => height,
+			None => return None,
+		};
+
+		self.storage.block_header_bytes(BlockRef::Hash(hash.clone())).map(|header| HeaderNotification {
+			height: height,
+			hex: Bytes::new(header.take()),
+		})
+	}
+
+	/// Waits until the best block differs from the block with given hash, or the long-polling timeout expires.
+	fn wait_for_new_block(&self, hash: &GlobalH256, stop: &AtomicBool) -> Option<HeaderNotification> {
+		let deadline = Instant::now() + Duration::from_millis(LONGPOLL_TIMEOUT_MS);
+		let mut lock = self.new_block_lock.lock();
+		loop {
+			let now = Instant::now();
+			if self.storage.best_block().hash != *hash || now >= deadline || stop.load(Ordering::SeqCst) {
+				break;
+			}
+
+			// best block is also re-read periodically, since notifications are not sent while synchronizing
+			let timeout = cmp::min(deadline - now, Duration::from_millis(READ_INTERVAL_MS));
+			self.new_block.wait_for(&mut lock, timeout);
+		}
+		drop(lock);
+
+		self.header(&self.storage.best_block().hash)
+	}
+
+	fn add_client(&self, client: Arc<Client>) -> usize {
+		let id = self.next_client_id.fetch_add(1, Ordering::SeqCst);
+		self.clients.lock().insert(id, client);
+		id
+	}
+
+	fn remove_client(&self, id: usize) {
+		self.clients.lock().remove(&id);
+	}
+
+	fn notify_block(&self, hash: &GlobalH256) {
+		{
+			let _lock = self.new_block_lock.lock();
+			self.new_block.notify_all();
+		}
+
+		match self.header(hash) {
+			Some(header) => self.notify(HEADERS_SUBSCRIBE, header, |subscriptions| &subscriptions.headers),
+			None => warn!(target: "rpc", "Block {} is not found in the storage", hash.to_reversed_str()),
+		}
+	}
+
+	fn notify_transaction(&self, hash: &GlobalH256) {
+		self.notify(TRANSACTIONS_SUBSCRIBE, hash.to_reversed_str(), |subscriptions| &subscriptions.transactions);
+	}
+
+	/// Sends notification to the subscribed clients. Clients, which fail to receive it, are disconnected.
+	fn notify<T, F>(&self, method: &str, params: T, is_subscribed: F) where T: Serialize, F: Fn(&ClientSubscriptions) -> &AtomicBool {
+		let clients: Vec<(usize, Arc<Client>)> = self.clients.lock().iter()
+			.filter(|&(_, client)| is_subscribed(&client.subscriptions).load(Ordering::SeqCst))
+			.map(|(id, client)| (*id, client.clone()))
+			.collect();
+		if clients.is_empty() {
+			return;
+		}
+
+		let notification = SubscriptionNotification {
+			jsonrpc: "2.0",
+			method: method,
+			params: [params],
+		};
+		let notification = serde_json::to_string(&notification).expect("Notifications are always serializable; qed");
+		for (id, client) in clients {
+			if let Err(err) = client.send(OPCODE_TEXT, notification.as_bytes()) {
+				debug!(target: "rpc", "Disconnecting WebSocket client, which failed to receive notification: {}", err);
+				self.remove_client(id);
+				let _ = client.stream.lock().shutdown(Shutdown::Both);
+			}
+		}
+	}
+}
+
+/// Numbers of the served connections by IP address.
+#[derive(Debug, Default)]
+struct ConnectionsCounter {
+	connections: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl ConnectionsCounter {
+	/// Counts new connection from given address, unless there are too many connections.
+	fn acquire(&self, ip: IpAddr) -> Result<(), &'static [u8]> {
+		let mut connections = self.connections.lock();
+		if connections.values().sum::<usize>() >= MAX_CONNECTIONS {
+			return Err(b"Too many connections\r\n");
+		}
+
+		let ip_connections = connections.entry(ip).or_insert(0);
+		if *ip_connections >= MAX_CONNECTIONS_PER_IP {
+			return Err(b"Too many connections from this address\r\n");
+		}
+		*ip_connections += 1;
+		Ok(())
+	}
+
+	fn release(&self, ip: IpAddr) {
+		let mut connections = self.connections.lock();
+		let is_last = match connections.get_mut(&ip) {
+			Some(ip_connections) => {
+				*ip_connections -= 1;
+				*ip_connections == 0
+			},
+			None => false,
+		};
+		if is_last {
+			connections.remove(&ip);
+		}
+	}
+}
+
+/// WebSocket server, stopped when dropped.
+pub struct WsServer {
+	handler: Arc<WsHandler>,
+	stop: Arc<AtomicBool>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WsServer {
+	pub fn start(address: SocketAddr, handler: WsHandler) -> Result<Self, String> {
+		let listener = TcpListener::bind(address)
+			.and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+			.map_err(|err| format!("Failed to start WebSocket server at {}: {}", address, err))?;
+		let handler = Arc::new(handler);
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread_handler = handler.clone();
+		let thread_stop = stop.clone();
+		let thread = thread::Builder::new()
+			.name("WebSocket server".to_owned())
+			.spawn(move || WsServer::worker(listener, thread_handler, thread_stop))
+			.map_err(|err| format!("Failed to start WebSocket server thread: {}", err))?;
+
+		Ok(WsServer {
+			handler: handler,
+			stop: stop,
+			thread: Some(thread),
+		})
+	}
+
+	fn worker(listener: TcpListener, handler: Arc<WsHandler>, stop: Arc<AtomicBool>) {
+		let connections = Arc::new(ConnectionsCounter::default());
+		while !stop.load(Ordering::SeqCst) {
+			match listener.accept() {
+				Ok((mut stream, address)) => {
+					let ip = address.ip();
+					if let Err(reason) = connections.acquire(ip) {
+						if let Err(err) = write_response(&mut stream, STATUS_SERVICE_UNAVAILABLE, "text/plain", reason) {
+							debug!(target: "rpc", "WebSocket server response failed: {}", err);
+						}
+						continue;
+					}
+
+					let handler = handler.clone();
+					let stop = stop.clone();
+					let thread_connections = connections.clone();
+					let spawn_result = thread::Builder::new()
+						.name("WebSocket connection".to_owned())
+						.spawn(move || {
+							if let Err(err) = handle_connection(stream, &handler, &stop) {
+								debug!(target: "rpc", "WebSocket connection failed: {}", err);
+							}
+							thread_connections.release(ip);
+						});
+					if let Err(err) = spawn_result {
+						warn!(target: "rpc", "Failed to start WebSocket connection thread: {}", err);
+						connections.release(ip);
+					}
+				},
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(ACCEPT_INTERVAL_MS)),
+				Err(err) => warn!(target: "rpc", "WebSocket server failed to accept connection: {}", err),
+			}
+		}
+	}
+}
+
+impl Drop for WsServer {
+	fn drop(&mut self) {
+		if let Some(thread) = self.thread.take() {
+			self.stop.store(true, Ordering::SeqCst);
+			thread.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+enum WsNotifierTask {
+	NewBlock(GlobalH256),
+	NewTransaction(GlobalH256),
+	Stop,
+}
+
+/// Forwards synchronization events to the clients of the WebSocket server.
+pub struct WsNotifier {
+	tx: Sender<WsNotifierTask>,
+	is_synchronizing: AtomicBool,
+	worker_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WsNotifier {
+	pub fn new(server: &WsServer) -> Result<Self, String> {
+		let (tx, rx) = channel();
+		let handler = server.handler.clone();
+		let worker_thread = thread::Builder::new()
+			.name("WebSocket notification thread".to_owned())
+			.spawn(move || WsNotifier::worker(rx, handler))
+			.map_err(|err| format!("Failed to start WebSocket notification thread: {}", err))?;
+
+		Ok(WsNotifier {
+			tx: tx,
+			is_synchronizing: AtomicBool::default(),
+			worker_thread: Some(worker_thread),
+		})
+	}
+
+	fn worker(rx: Receiver<WsNotifierTask>, handler: Arc<WsHandler>) {
+		for task in rx {
+			match task {
+				WsNotifierTask::NewBlock(hash) => handler.notify_block(&hash),
+				WsNotifierTask::NewTransaction(hash) => handler.notify_transaction(&hash),
+				WsNotifierTask::Stop => break,
+			}
+		}
+		trace!(target: "rpc", "WebSocket notification thread stopped");
+	}
+}
+
+impl SyncListener for WsNotifier {
+	fn synchronization_state_switched(&self, is_synchronizing: bool) {
+		self.is_synchronizing.store(is_synchronizing, Ordering::SeqCst);
+	}
+
+	fn best_storage_block_inserted(&self, block_hash: &GlobalH256) {
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			self.tx.send(WsNotifierTask::NewBlock(block_hash.clone()))
+				.expect("WebSocket notification thread have the same lifetime as `WsNotifier`")
+		}
+	}
+
+	fn transaction_accepted(&self, transaction: &IndexedTransaction) {
+		if !self.is_synchronizing.load(Ordering::SeqCst) {
+			self.tx.send(WsNotifierTask::NewTransaction(transaction.hash.clone()))
+				.expect("WebSocket notification thread have the same lifetime as `WsNotifier`")
+		}
+	}
+}
+
+impl Drop for WsNotifier {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.worker_thread.take() {
+			let _ = self.tx.send(WsNotifierTask::Stop);
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+/// Head of the HTTP request.
+#[derive(Debug, PartialEq)]
+struct RequestHead {
+	method: String,
+	path: String,
+	headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+	fn parse(head: &str) -> Option<Self> {
+		let mut lines = head.lines();
+		let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+		let (method, path) = match (request_line.next(), request_line.next()) {
+			(Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+			_ => return None,
+		};
+
+		let headers = lines
+			.filter_map(|line| {
+				let mut parts = line.splitn(2, ':');
+				match (parts.next(), parts.next()) {
+					(Some(name), Some(value)) => Some((name.trim().to_lowercase(), value.trim().to_owned())),
+					_ => None,
+				}
+			})
+			.collect();
+
+		Some(RequestHead {
+			method: method,
+			path: path,
+			headers: headers,
+		})
+	}
+
+	/// Returns value of the header with given lowercase name.
+	fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|&&(ref header, _)| header == name).map(|&(_, ref value)| value.as_str())
+	}
+
+	/// Is it the WebSocket opening handshake?
+	fn is_upgrade(&self) -> bool {
+		self.header("upgrade").map_or(false, |upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+	}
+}
+
+fn handle_connection(mut stream: TcpStream, handler: &WsHandler, stop: &AtomicBool) -> Result<(), io::Error> {
+	stream.set_nonblocking(false)?;
+	stream.set_read_timeout(Some(Duration::from_millis(REQUEST_TIMEOUT_MS)))?;
+	stream.set_write_timeout(Some(Duration::from_millis(WRITE_TIMEOUT_MS)))?;
+
+	let mut data = Vec::new();
+	let mut buffer = [0u8; 1024];
+	let head_length = loop {
+		if let Some(position) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+			break position + 4;
+		}
+		if data.len() >= MAX_REQUEST_SIZE {
+			return write_response(&mut stream, STATUS_BAD_REQUEST, "text/plain", b"Request is too large\r\n");
+		}
+
+		let read = stream.read(&mut buffer)?;
+		if read == 0 {
+			return Ok(());
+		}
+		data.extend_from_slice(&buffer[..read]);
+	};
+
+	let head = match RequestHead::parse(&String::from_utf8_lossy(&data[..head_length])) {
+		Some(head) => head,
+		None => return write_response(&mut stream, STATUS_BAD_REQUEST, "text/plain", b"Invalid request\r\n"),
+	};
+	if head.method != "GET" {
+		return write_response(&mut stream, STATUS_METHOD_NOT_ALLOWED, "text/plain", b"Only GET requests are supported\r\n");
+	}
+
+	if head.path == "/" {
+		if !head.is_upgrade() || head.header("sec-websocket-version") != Some("13") {
+			return write_response(&mut stream, STATUS_BAD_REQUEST, "text/plain", b"WebSocket version 13 handshake is expected\r\n");
+		}
+		let key = match head.header("sec-websocket-key") {
+			Some(key) => key.to_owned(),
+			None => return write_response(&mut stream, STATUS_BAD_REQUEST, "text/plain", b"Sec-WebSocket-Key header is missing\r\n"),
+		};
+		// frames, sent right after the handshake, may already be read
+		let data = data.split_off(head_length);
+		return serve_websocket(stream, &key, data, handler, stop);
+	}
+
+	if head.path.starts_with("/longpoll/") {
+		let hash: GlobalH256 = match head.path["/longpoll/".len()..].parse() {
+			Ok(hash) => hash,
+			Err(_) => return write_response(&mut stream, STATUS_BAD_REQUEST, "text/plain", b"Invalid block hash\r\n"),
+		};
+		return match handler.wait_for_new_block(&hash.reversed(), stop) {
+			Some(header) => {
+				let mut body = serde_json::to_vec(&header).expect("Block headers are always serializable; qed");
+				body.push(b'\n');
+				write_response(&mut stream, STATUS_OK, "application/json", &body)
+			},
+			None => write_response(&mut stream, STATUS_SERVICE_UNAVAILABLE, "text/plain", b"Best block header is not found\r\n"),
+		};
+	}
+
+	write_response(&mut stream, STATUS_NOT_FOUND, "text/plain", b"Not found\r\n")
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<(), io::Error> {
+	stream.set_nonblocking(false)?;
+	stream.set_write_timeout(Some(Duration::from_millis(WRITE_TIMEOUT_MS)))?;
+	write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		status, content_type, body.len())?;
+	stream.write_all(body)?;
+	stream.flush()
+}
+
+/// Completes the opening handshake and serves the WebSocket connection until it is closed.
+fn serve_websocket(mut stream: TcpStream, key: &str, data: Vec<u8>, handler: &WsHandler, stop: &AtomicBool) -> Result<(), io::Error> {
+	write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+		websocket_accept(key))?;
+	stream.flush()?;
+
+	// read timeout is shared by both handles of the socket, it only affects reads
+	let mut reader = stream.try_clone()?;
+	reader.set_read_timeout(Some(Duration::from_millis(READ_INTERVAL_MS)))?;
+	let client = Arc::new(Client {
+		stream: Mutex::new(stream),
+		subscriptions: ClientSubscriptions::default(),
+	});
+
+	let id = handler.add_client(client.clone());
+	let result = websocket_session(&mut reader, &client, data, handler, stop);
+	handler.remove_client(id);
+	result
+}
+
+fn websocket_session(reader: &mut TcpStream, client: &Client, mut data: Vec<u8>, handler: &WsHandler, stop: &AtomicBool) -> Result<(), io::Error> {
+	// opcode and payload of the partially received message
+	let mut message: Option<(u8, Vec<u8>)> = None;
+	let mut buffer = [0u8; 4096];
+	loop {
+		loop {
+			let (frame, length) = match decode_frame(&data) {
+				Ok(Some(frame)) => frame,
+				Ok(None) => break,
+				Err(code) => return client.close(code),
+			};
+			data.drain(..length);
+
+			let fin = frame.fin;
+			match frame.opcode {
+				OPCODE_PING => client.send(OPCODE_PONG, &frame.payload)?,
+				OPCODE_PONG => (),
+				OPCODE_CLOSE => return client.send(OPCODE_CLOSE, &frame.payload[..cmp::min(frame.payload.len(), 2)]),
+				OPCODE_CONTINUATION => match message {
+					Some((_, ref mut payload)) => payload.extend_from_slice(&frame.payload),
+					None => return client.close(CLOSE_PROTOCOL_ERROR),
+				},
+				OPCODE_TEXT | OPCODE_BINARY if message.is_none() => message = Some((frame.opcode, frame.payload)),
+				_ => return client.close(CLOSE_PROTOCOL_ERROR),
+			}
+
+			if message.as_ref().map_or(false, |&(_, ref payload)| payload.len() > MAX_MESSAGE_SIZE) {
+				return client.close(CLOSE_MESSAGE_TOO_BIG);
+			}
+
+			if !fin {
+				continue;
+			}
+
+			match message.take() {
+				Some((OPCODE_TEXT, payload)) => match String::from_utf8(payload) {
+					Ok(request) => if let Some(response) = handler.handle(&client.subscriptions, &request) {
+						client.send(OPCODE_TEXT, response.as_bytes())?;
+					},
+					Err(_) => return client.close(CLOSE_INVALID_DATA),
+				},
+				Some(_) => return client.close(CLOSE_UNSUPPORTED_DATA),
+				None => (),
+			}
+		}
+
+		if stop.load(Ordering::SeqCst) {
+			return client.close(CLOSE_GOING_AWAY);
+		}
+
+		match reader.read(&mut buffer) {
+			Ok(0) => return Ok(()),
+			Ok(read) => data.extend_from_slice(&buffer[..read]),
+			Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => (),
+			Err(err) => return Err(err),
+		}
+	}
+}
+
+/// Value of the `Sec-WebSocket-Accept` header, sent in response to the given `Sec-WebSocket-Key`.
+fn websocket_accept(key: &str) -> String {
+	sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes())[..].to_base64(STANDARD)
+}
+
+/// WebSocket frame.
+#[derive(Debug, PartialEq)]
+struct Frame {
+	fin: bool,
+	opcode: u8,
+	payload: Vec<u8>,
+}
+
+/// Decodes masked client frame from the start of `data`, returning the frame and its length.
+/// Returns None if more data is required and the close status code if the frame is invalid.
+fn decode_frame(data: &[u8]) -> Result<Option<(Frame, usize)>, u16> {
+	if data.len() < 2 {
+		return Ok(None);
+	}
+
+	// no extensions are negotiated, so reserved bits must be zero
+	if data[0] & 0x70 != 0 {
+		return Err(CLOSE_PROTOCOL_ERROR);
+	}
+	// all client frames must be masked
+	if data[1] & 0x80 == 0 {
+		return Err(CLOSE_PROTOCOL_ERROR);
+	}
+
+	let (length, offset) = match data[1] & 0x7f {
+		126 if data.len() < 4 => return Ok(None),
+		126 => ((data[2] as u64) << 8 | data[3] as u64, 4),
+		127 if data.len() < 10 => return Ok(None),
+		127 => (data[2..10].iter().fold(0u64, |length, byte| length << 8 | *byte as u64), 10),
+		length => (length as u64, 2),
+	};
+	if length > MAX_MESSAGE_SIZE as u64 {
+		return Err(CLOSE_MESSAGE_TOO_BIG);
+	}
+
+	let length = length as usize;
+	let payload_offset = offset + 4;
+	if data.len() < payload_offset + length {
+		return Ok(None);
+	}
+
+	let mask = &data[offset..payload_offset];
+	let payload = data[payload_offset..payload_offset + length].iter()
+		.enumerate()
+		.map(|(index, byte)| byte ^ mask[index % 4])
+		.collect();
+
+	let frame = Frame {
+		fin: data[0] & 0x80 != 0,
+		opcode: data[0] & 0x0f,
+		payload: payload,
+	};
+	Ok(Some((frame, payload_offset + length)))
+}
+
+/// Encodes unfragmented unmasked server frame.
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+	let mut frame = vec![0x80 | opcode];
+	match payload.len() {
+		length if length < 126 => frame.push(length as u8),
+		length if length <= 0xffff => {
+			frame.push(126);
+			frame.push((length >> 8) as u8);
+			frame.push(length as u8);
+		},
+		length => {
+			frame.push(127);
+			frame.extend((0..8).rev().map(|byte| ((length as u64) >> (byte * 8)) as u8));
+		},
+	}
+	frame.extend_from_slice(payload);
+	frame
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
+	use std::sync::atomic::Ordering;
+	use serde_json;
+	use db::BlockChainDatabase;
+	use super::{WsHandler, ClientSubscriptions, ConnectionsCounter, RequestHead, Frame, websocket_accept, decode_frame, encode_frame,
+		OPCODE_TEXT, OPCODE_PING, CLOSE_PROTOCOL_ERROR, CLOSE_MESSAGE_TOO_BIG, MAX_CONNECTIONS, MAX_CONNECTIONS_PER_IP};
+
+	fn handler() -> WsHandler {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), test_data::block_h1().into(), test_data::block_h2().into()]));
+		WsHandler::new(storage)
+	}
+
+	fn json(response: Option<String>) -> serde_json::Value {
+		serde_json::from_str(&response.unwrap()).unwrap()
+	}
+
+	#[test]
+	fn websocket_accept_key() {
+		// example from RFC 6455
+		assert_eq!(websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGOZhRiCVAWCY=");
+	}
+
+	#[test]
+	fn request_head_parse() {
+		let head = RequestHead::parse("GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: WebSocket\r\nSec-WebSocket-Version: 13\r\n\r\n").unwrap();
+		assert_eq!(head.method, "GET");
+		assert_eq!(head.path, "/");
+		assert_eq!(head.header("sec-websocket-version"), Some("13"));
+		assert_eq!(head.header("sec-websocket-key"), None);
+		assert!(head.is_upgrade());
+		assert!(!RequestHead::parse("GET /longpoll/00 HTTP/1.1\r\n\r\n").unwrap().is_upgrade());
+		assert_eq!(RequestHead::parse("\r\n\r\n"), None);
+	}
+
+	#[test]
+	fn decode_masked_frame() {
+		// masked "Hello" from RFC 6455
+		let data = [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58, 0x89];
+		let frame = Frame {
+			fin: true,
+			opcode: OPCODE_TEXT,
+			payload: b"Hello".to_vec(),
+		};
+		assert_eq!(decode_frame(&data), Ok(Some((frame, 11))));
+		assert_eq!(decode_frame(&data[..10]), Ok(None));
+		assert_eq!(decode_frame(&data[..1]), Ok(None));
+
+		// unmasked "Hello"
+		assert_eq!(decode_frame(&[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]), Err(CLOSE_PROTOCOL_ERROR));
+		// 16-bit length
+		assert_eq!(decode_frame(&[0x82, 0xfe, 0x01]), Ok(None));
+		assert_eq!(decode_frame(&[0x82, 0xfe, 0x01, 0x00, 0, 0, 0, 0]), Ok(None));
+		// 64-bit length, exceeding the limit
+		assert_eq!(decode_frame(&[0x82, 0xff, 0, 0, 0, 0, 0, 0x02, 0, 0]), Err(CLOSE_MESSAGE_TOO_BIG));
+	}
+
+	#[test]
+	fn encode_unmasked_frame() {
+		assert_eq!(encode_frame(OPCODE_TEXT, b"Hello"), vec![0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]);
+		assert_eq!(encode_frame(OPCODE_PING, &[]), vec![0x89, 0x00]);
+
+		let frame = encode_frame(OPCODE_TEXT, &[0u8; 256]);
+		assert_eq!(&frame[..4], &[0x81, 0x7e, 0x01, 0x00]);
+		assert_eq!(frame.len(), 260);
+
+		let frame = encode_frame(OPCODE_TEXT, &[0u8; 65536]);
+		assert_eq!(&frame[..10], &[0x81, 0x7f, 0, 0, 0, 0, 0, 0x01, 0, 0]);
+		assert_eq!(frame.len(), 65546);
+	}
+
+	#[test]
+	fn headers_subscribe() {
+		let handler = handler();
+		let subscriptions = ClientSubscriptions::default();
+
+		let response = json(handler.handle(&subscriptions, r#"{"jsonrpc": "2.0", "method": "blockchain.headers.subscribe", "params": [], "id": 1}"#));
+		assert_eq!(response["id"], 1);
+		assert_eq!(response["result"]["height"], 2);
+		assert_eq!(response["result"]["hex"].as_str().unwrap().len(), 160);
+		assert!(subscriptions.headers.load(Ordering::SeqCst));
+		assert!(!subscriptions.transactions.load(Ordering::SeqCst));
+
+		let response = json(handler.handle(&subscriptions, r#"{"jsonrpc": "2.0", "method": "blockchain.headers.unsubscribe", "params": [], "id": 2}"#));
+		assert_eq!(response["result"], true);
+		assert!(!subscriptions.headers.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn transactions_subscribe() {
+		let handler = handler();
+		let subscriptions = ClientSubscriptions::default();
+
+		let response = json(handler.handle(&subscriptions, r#"[
+			{"jsonrpc": "2.0", "method": "mempool.tx.unsubscribe", "params": [], "id": 1},
+			{"jsonrpc": "2.0", "method": "mempool.tx.subscribe", "params": [], "id": 2}
+		]"#));
+		assert_eq!(response[0]["result"], false);
+		assert_eq!(response[1]["result"], true);
+		assert!(subscriptions.transactions.load(Ordering::SeqCst));
+
+		// notifications are not answered
+		assert_eq!(handler.handle(&subscriptions, r#"{"jsonrpc": "2.0", "method": "mempool.tx.unsubscribe", "params": []}"#), None);
+		assert!(!subscriptions.transactions.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn connections_are_limited() {
+		let counter = ConnectionsCounter::default();
+		let ip = "127.0.0.1".parse().unwrap();
+		for _ in 0..MAX_CONNECTIONS_PER_IP {
+			assert!(counter.acquire(ip).is_ok());
+		}
+		assert!(counter.acquire(ip).is_err());
+		counter.release(ip);
+		assert!(counter.acquire(ip).is_ok());
+
+		for n in 0..(MAX_CONNECTIONS - MAX_CONNECTIONS_PER_IP) {
+			assert!(counter.acquire(format!("10.0.{}.{}", n / 4, n % 4).parse().unwrap()).is_ok());
+		}
+		assert!(counter.acquire("10.1.0.0".parse().unwrap()).is_err());
+	}
+
+	#[test]
+	fn invalid_requests() {
+		let handler = handler();
+		let subscriptions = ClientSubscriptions::default();
+
+		let response = json(handler.handle(&subscriptions, r#"{"jsonrpc": "2.0", "method": "getbestblockhash", "params": [], "id": 1}"#));
+		assert_eq!(response["error"]["code"], -32601);
+
+		let response = json(handler.handle(&subscriptions, "{"));
+		assert_eq!(response["error"]["code"], -32700);
+	}
+}
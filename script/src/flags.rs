@@ -65,6 +65,18 @@ pub struct VerificationFlags {
 
 	/// Making v1-v16 witness program non-standard
 	pub verify_discourage_upgradable_witness_program: bool,
+
+	/// Support Taproot and tapscript (BIP341, BIP342)
+	pub verify_taproot: bool,
+
+	/// Making unknown Taproot leaf versions non-standard
+	pub verify_discourage_upgradable_taproot_version: bool,
+
+	/// Making scripts with OP_SUCCESSx opcodes non-standard
+	pub verify_discourage_op_success: bool,
+
+	/// Making unknown public key versions (in tapscript) non-standard
+	pub verify_discourage_upgradable_pubkeytype: bool,
 }
 
 impl VerificationFlags {
@@ -107,5 +119,25 @@ impl VerificationFlags {
 		self.verify_discourage_upgradable_witness_program = value;
 		self
 	}
+
+	pub fn verify_taproot(mut self, value: bool) -> Self {
+		self.verify_taproot = value;
+		self
+	}
+
+	pub fn verify_discourage_upgradable_taproot_version(mut self, value: bool) -> Self {
+		self.verify_discourage_upgradable_taproot_version = value;
+		self
+	}
+
+	pub fn verify_discourage_op_success(mut self, value: bool) -> Self {
+		self.verify_discourage_op_success = value;
+		self
+	}
+
+	pub fn verify_discourage_upgradable_pubkeytype(mut self, value: bool) -> Self {
+		self.verify_discourage_upgradable_pubkeytype = value;
+		self
+	}
 }
 
@@ -1,10 +1,15 @@
 use std::{cmp, mem};
 use bytes::Bytes;
-use keys::{Signature, Public};
+use keys::{Signature, Public, XOnlyPublic, SchnorrSignature};
 use chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
 use crypto::{sha1, sha256, dhash160, dhash256, ripemd160};
-use sign::{SignatureVersion, Sighash};
-use script::MAX_SCRIPT_ELEMENT_SIZE;
+use ser::{serialize, serialized_list_size};
+use sign::{SignatureVersion, Sighash, ScriptExecutionData};
+use script::{MAX_SCRIPT_ELEMENT_SIZE, MAX_STACK_SIZE};
+use taproot::{
+	tapleaf_hash, verify_taproot_commitment, is_valid_control_block_size, ANNEX_TAG, TAPROOT_LEAF_MASK,
+	TAPROOT_LEAF_TAPSCRIPT, VALIDATION_WEIGHT_PER_SIGOP_PASSED, VALIDATION_WEIGHT_OFFSET,
+};
 use {
 	script, Builder, Script, ScriptWitness, Num, VerificationFlags, Opcode, Error, SignatureChecker, Stack
 };
@@ -32,6 +37,88 @@ fn check_signature(
 	checker.check_signature(&signature, &public, script_code, hash_type, version)
 }
 
+/// Checks Schnorr signature of Taproot key path spending, or of tapscript signature opcode.
+fn check_schnorr_signature(
+	checker: &SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	version: SignatureVersion,
+	execution: &ScriptExecutionData,
+) -> Result<(), Error> {
+	let (signature, sighashtype) = match signature.len() {
+		64 => (signature, 0),
+		// SIGHASH_DEFAULT must be encoded by omitting the hash type byte
+		65 if signature[64] != 0 => (&signature[..64], signature[64] as u32),
+		65 => return Err(Error::SchnorrSigHashtype),
+		_ => return Err(Error::SchnorrSigSize),
+	};
+
+	match sighashtype {
+		0x00...0x03 | 0x81...0x83 => (),
+		_ => return Err(Error::SchnorrSigHashtype),
+	}
+
+	let signature = SchnorrSignature::from(signature);
+	let public = XOnlyPublic::from(public);
+	if !checker.check_schnorr_signature(&signature, &public, sighashtype, version, execution) {
+		return Err(Error::SchnorrSig);
+	}
+
+	Ok(())
+}
+
+/// Executes tapscript OP_CHECKSIG, OP_CHECKSIGVERIFY or OP_CHECKSIGADD signature check (BIP342).
+/// Returns false if signature is empty.
+fn eval_tapscript_signature(
+	checker: &SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	flags: &VerificationFlags,
+	execution: &mut ScriptExecutionData,
+	sig_checks: &mut usize,
+) -> Result<bool, Error> {
+	let success = !signature.is_empty();
+	if success {
+		execution.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+		if execution.validation_weight_left < 0 {
+			return Err(Error::TapscriptValidationWeight);
+		}
+	}
+
+	match public.len() {
+		0 => return Err(Error::PubkeyType),
+		32 => if success {
+			*sig_checks += 1;
+			try!(check_schnorr_signature(checker, signature, public, SignatureVersion::Tapscript, execution));
+		},
+		// unknown public key types are reserved for soft forks
+		_ => if flags.verify_discourage_upgradable_pubkeytype {
+			return Err(Error::DiscourageUpgradablePubkeyType);
+		},
+	}
+
+	Ok(success)
+}
+
+/// Returns true if tapscript contains OP_SUCCESSx opcode.
+fn has_success_opcode(script: &Script) -> Result<bool, Error> {
+	let mut pc = 0;
+	while pc < script.len() {
+		if Opcode::is_success(script[pc]) {
+			return Ok(true);
+		}
+
+		pc += match script.get_instruction(pc) {
+			Ok(instruction) => instruction.step,
+			// unknown opcodes, which are not OP_SUCCESSx, fail only when executed
+			Err(Error::BadOpcode) if Opcode::from_u8(script[pc]).is_none() => 1,
+			Err(err) => return Err(err),
+		};
+	}
+
+	Ok(false)
+}
+
 fn is_public_key(v: &[u8]) -> bool {
 	match v.len() {
 		33 if v[0] == 2 || v[0] == 3 => true,
@@ -249,6 +336,20 @@ pub fn verify_script(
 	flags: &VerificationFlags,
 	checker: &SignatureChecker,
 	version: SignatureVersion,
+) -> Result<(), Error> {
+	verify_script_with_sig_checks(script_sig, script_pubkey, witness, flags, checker, version, &mut 0)
+}
+
+/// Verifies script signature and pubkey, adding number of executed signature checks to `sig_checks`.
+/// See https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/2020-05-15-sigchecks.md
+pub fn verify_script_with_sig_checks(
+	script_sig: &Script,
+	script_pubkey: &Script,
+	witness: &ScriptWitness,
+	flags: &VerificationFlags,
+	checker: &SignatureChecker,
+	version: SignatureVersion,
+	sig_checks: &mut usize,
 ) -> Result<(), Error> {
 	if flags.verify_sigpushonly && !script_sig.is_push_only() {
 		return Err(Error::SignaturePushOnly);
@@ -258,13 +359,13 @@ pub fn verify_script(
 	let mut stack_copy = Stack::new();
 	let mut had_witness = false;
 
-	try!(eval_script(&mut stack, script_sig, flags, checker, version));
+	try!(eval_script_with_sig_checks(&mut stack, script_sig, flags, checker, version, sig_checks));
 
 	if flags.verify_p2sh {
 		stack_copy = stack.clone();
 	}
 
-	let res = try!(eval_script(&mut stack, script_pubkey, flags, checker, version));
+	let res = try!(eval_script_with_sig_checks(&mut stack, script_pubkey, flags, checker, version, sig_checks));
 	if !res {
 		return Err(Error::EvalFalse);
 	}
@@ -279,7 +380,7 @@ pub fn verify_script(
 
 			had_witness = true;
 			verify_cleanstack = false;
-			if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+			if !verify_witness_program(witness, witness_version, witness_program, false, flags, checker, sig_checks)? {
 				return Err(Error::EvalFalse);
 			}
 		}
@@ -300,7 +401,7 @@ pub fn verify_script(
 
 		let pubkey2: Script = try!(stack.pop()).into();
 
-		let res = try!(eval_script(&mut stack, &pubkey2, flags, checker, version));
+		let res = try!(eval_script_with_sig_checks(&mut stack, &pubkey2, flags, checker, version, sig_checks));
 		if !res {
 			return Err(Error::EvalFalse);
 		}
@@ -313,7 +414,7 @@ pub fn verify_script(
 
 				had_witness = true;
 				verify_cleanstack = false;
-				if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+				if !verify_witness_program(witness, witness_version, witness_program, true, flags, checker, sig_checks)? {
 					return Err(Error::EvalFalse);
 				}
 			}
@@ -350,10 +451,16 @@ fn verify_witness_program(
 	witness: &ScriptWitness,
 	witness_version: u8,
 	witness_program: &[u8],
+	is_p2sh: bool,
 	flags: &VerificationFlags,
 	checker: &SignatureChecker,
+	sig_checks: &mut usize,
 ) -> Result<bool, Error> {
 	if witness_version != 0 {
+		if witness_version == 1 && witness_program.len() == 32 && !is_p2sh && flags.verify_taproot {
+			return verify_taproot_program(witness, witness_program, flags, checker, sig_checks);
+		}
+
 		if flags.verify_discourage_upgradable_witness_program {
 			return Err(Error::DiscourageUpgradableWitnessProgram);
 		}
@@ -363,7 +470,7 @@ fn verify_witness_program(
 
 	let witness_stack = witness;
 	let witness_stack_len = witness_stack.len();
-	let (mut stack, script_pubkey): (Stack<_>, Script) = match witness_program.len() {
+	let (stack, script_pubkey): (Stack<_>, Script) = match witness_program.len() {
 		32 => {
 			if witness_stack_len == 0 {
 				return Err(Error::WitnessProgramWitnessEmpty);
@@ -397,11 +504,92 @@ fn verify_witness_program(
 		_ => return Err(Error::WitnessProgramWrongLength),
 	};
 
+	execute_witness_script(stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0, &mut ScriptExecutionData::default(), sig_checks)
+}
+
+/// Verifies Taproot output spending (BIP341)
+fn verify_taproot_program(
+	witness: &ScriptWitness,
+	witness_program: &[u8],
+	flags: &VerificationFlags,
+	checker: &SignatureChecker,
+	sig_checks: &mut usize,
+) -> Result<bool, Error> {
+	let mut stack: &[Bytes] = witness;
+	if stack.is_empty() {
+		return Err(Error::WitnessProgramWitnessEmpty);
+	}
+
+	let mut execution = ScriptExecutionData::default();
+	if stack.len() >= 2 && stack[stack.len() - 1].first() == Some(&ANNEX_TAG) {
+		execution.annex_hash = Some(sha256(&serialize(&stack[stack.len() - 1])));
+		stack = &stack[..stack.len() - 1];
+	}
+
+	if stack.len() == 1 {
+		// key path spending
+		try!(check_schnorr_signature(checker, &stack[0], witness_program, SignatureVersion::Taproot, &execution));
+		return Ok(true);
+	}
+
+	// script path spending
+	let control = &stack[stack.len() - 1];
+	let script = &stack[stack.len() - 2];
+	let stack = &stack[..stack.len() - 2];
+	if !is_valid_control_block_size(control.len()) {
+		return Err(Error::TaprootWrongControlSize);
+	}
+
+	let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+	let leaf_hash = tapleaf_hash(leaf_version, script);
+	if !verify_taproot_commitment(control, &XOnlyPublic::from(witness_program), &leaf_hash) {
+		return Err(Error::WitnessProgramMismatch);
+	}
+
+	if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+		if flags.verify_discourage_upgradable_taproot_version {
+			return Err(Error::DiscourageUpgradableTaprootVersion);
+		}
+
+		return Ok(true);
+	}
+
+	execution.tapleaf_hash = Some(leaf_hash);
+	execution.validation_weight_left = serialized_list_size::<Bytes, Bytes>(witness) as i64 + VALIDATION_WEIGHT_OFFSET;
+	let script = Script::new(script.clone());
+	execute_witness_script(stack.to_vec().into(), &script, flags, checker, SignatureVersion::Tapscript, &mut execution, sig_checks)
+}
+
+/// Executes witness script with the initial stack, taken from the witness
+fn execute_witness_script(
+	mut stack: Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &SignatureChecker,
+	version: SignatureVersion,
+	execution: &mut ScriptExecutionData,
+	sig_checks: &mut usize,
+) -> Result<bool, Error> {
+	if version == SignatureVersion::Tapscript {
+		// OP_SUCCESSx make tapscript valid, even if it can't be executed
+		if try!(has_success_opcode(script)) {
+			if flags.verify_discourage_op_success {
+				return Err(Error::DiscourageOpSuccess);
+			}
+
+			return Ok(true);
+		}
+
+		if stack.len() > MAX_STACK_SIZE {
+			return Err(Error::StackSize);
+		}
+	}
+
 	if stack.iter().any(|s| s.len() > MAX_SCRIPT_ELEMENT_SIZE) {
 		return Err(Error::PushSize);
 	}
 
-	if !eval_script(&mut stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0)? {
+	if !eval_script_with_execution_data(&mut stack, script, flags, checker, version, execution, sig_checks)? {
 		return Ok(false);
 	}
 
@@ -414,7 +602,6 @@ fn verify_witness_program(
 }
 
 /// Evaluautes the script
-#[cfg_attr(feature="cargo-clippy", allow(match_same_arms))]
 pub fn eval_script(
 	stack: &mut Stack<Bytes>,
 	script: &Script,
@@ -422,27 +609,155 @@ pub fn eval_script(
 	checker: &SignatureChecker,
 	version: SignatureVersion
 ) -> Result<bool, Error> {
-	if script.len() > script::MAX_SCRIPT_SIZE {
-		return Err(Error::ScriptSize);
+	eval_script_with_sig_checks(stack, script, flags, checker, version, &mut 0)
+}
+
+/// Evaluautes the script, adding number of executed signature checks to `sig_checks`
+pub fn eval_script_with_sig_checks(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &SignatureChecker,
+	version: SignatureVersion,
+	sig_checks: &mut usize,
+) -> Result<bool, Error> {
+	eval_script_with_execution_data(stack, script, flags, checker, version, &mut ScriptExecutionData::default(), sig_checks)
+}
+
+/// Evaluautes the script. Tapscript execution state is kept in `execution`.
+fn eval_script_with_execution_data(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &SignatureChecker,
+	version: SignatureVersion,
+	execution: &mut ScriptExecutionData,
+	sig_checks: &mut usize,
+) -> Result<bool, Error> {
+	let mut interpreter = Interpreter::new(stack, script, flags, checker, version);
+	interpreter.execution = execution.clone();
+	while try!(interpreter.step()).is_some() {}
+	*execution = interpreter.execution.clone();
+	*sig_checks += interpreter.sig_checks;
+	interpreter.finish()
+}
+
+/// Single instruction of the script, processed by the `Interpreter`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct InterpreterStep {
+	/// Offset of the instruction in the script.
+	pub offset: usize,
+	/// Position of the opcode, as used by OP_CODESEPARATOR.
+	pub position: u32,
+	/// Opcode of the instruction.
+	pub opcode: Opcode,
+	/// False if the instruction is a part of the unexecuted branch of conditional.
+	pub executing: bool,
+}
+
+/// Script interpreter, executing the script instruction by instruction.
+///
+/// Stacks could be inspected in between the steps, which is useful to trace why the script fails.
+pub struct Interpreter<'a> {
+	stack: &'a mut Stack<Bytes>,
+	altstack: Stack<Bytes>,
+	script: &'a Script,
+	flags: &'a VerificationFlags,
+	checker: &'a SignatureChecker,
+	version: SignatureVersion,
+	execution: ScriptExecutionData,
+	sig_checks: usize,
+	pc: usize,
+	op_count: usize,
+	opcode_position: u32,
+	begincode: usize,
+	exec_stack: Vec<bool>,
+}
+
+impl<'a> Interpreter<'a> {
+	pub fn new(
+		stack: &'a mut Stack<Bytes>,
+		script: &'a Script,
+		flags: &'a VerificationFlags,
+		checker: &'a SignatureChecker,
+		version: SignatureVersion,
+	) -> Self {
+		Interpreter {
+			stack: stack,
+			altstack: Stack::new(),
+			script: script,
+			flags: flags,
+			checker: checker,
+			version: version,
+			execution: ScriptExecutionData::default(),
+			sig_checks: 0,
+			pc: 0,
+			op_count: 0,
+			opcode_position: 0,
+			begincode: 0,
+			exec_stack: Vec::new(),
+		}
 	}
 
-	let mut pc = 0;
-	let mut op_count = 0;
-	let mut begincode = 0;
-	let mut exec_stack = Vec::<bool>::new();
-	let mut altstack = Stack::<Bytes>::new();
+	/// Main stack.
+	pub fn stack(&self) -> &Stack<Bytes> {
+		&*self.stack
+	}
 
-	while pc < script.len() {
-		let executing = exec_stack.iter().all(|x| *x);
-		let instruction = match script.get_instruction(pc) {
-			Ok(i) => i,
-			Err(Error::BadOpcode) if !executing => {
-				pc += 1;
-				continue;
-			},
-			Err(err) => return Err(err),
+	/// Alternative stack, used by OP_TOALTSTACK and OP_FROMALTSTACK.
+	pub fn altstack(&self) -> &Stack<Bytes> {
+		&self.altstack
+	}
+
+	/// Flags, the script is verified with.
+	pub fn flags(&self) -> &VerificationFlags {
+		self.flags
+	}
+
+	/// Number of signature checks, executed so far.
+	pub fn sig_checks(&self) -> usize {
+		self.sig_checks
+	}
+
+	/// Executes the next instruction of the script. Returns `None` once the whole script is executed.
+	#[cfg_attr(feature="cargo-clippy", allow(match_same_arms))]
+	pub fn step(&mut self) -> Result<Option<InterpreterStep>, Error> {
+		let is_tapscript = self.version == SignatureVersion::Tapscript;
+		if !is_tapscript && self.script.len() > script::MAX_SCRIPT_SIZE {
+			return Err(Error::ScriptSize);
+		}
+
+		let script = self.script;
+		let flags = self.flags;
+		let checker = self.checker;
+		let version = self.version;
+		let stack = &mut *self.stack;
+		let altstack = &mut self.altstack;
+		let exec_stack = &mut self.exec_stack;
+		let execution = &mut self.execution;
+		let sig_checks = &mut self.sig_checks;
+
+		let (executing, offset, position, instruction) = loop {
+			if self.pc >= script.len() {
+				return Ok(None);
+			}
+
+			let executing = exec_stack.iter().all(|x| *x);
+			let position = self.opcode_position;
+			self.opcode_position += 1;
+			match script.get_instruction(self.pc) {
+				Ok(i) => break (executing, self.pc, position, i),
+				Err(Error::BadOpcode) if !executing => self.pc += 1,
+				Err(err) => return Err(err),
+			}
 		};
 		let opcode = instruction.opcode;
+		let result = InterpreterStep {
+			offset: offset,
+			position: position,
+			opcode: opcode,
+			executing: executing,
+		};
 
 		if let Some(data) = instruction.data {
 			if data.len() > script::MAX_SCRIPT_ELEMENT_SIZE {
@@ -454,9 +769,10 @@ pub fn eval_script(
 			}
 		}
 
-		if opcode.is_countable() {
-			op_count += 1;
-			if op_count > script::MAX_OPS_PER_SCRIPT {
+		// tapscript has no opcodes limit
+		if opcode.is_countable() && !is_tapscript {
+			self.op_count += 1;
+			if self.op_count > script::MAX_OPS_PER_SCRIPT {
 				return Err(Error::OpCount);
 			}
 		}
@@ -465,9 +781,9 @@ pub fn eval_script(
 			return Err(Error::DisabledOpcode(opcode));
 		}
 
-		pc += instruction.step;
+		self.pc += instruction.step;
 		if !(executing || (Opcode::OP_IF <= opcode && opcode <= Opcode::OP_ENDIF)) {
-			continue;
+			return Ok(Some(result));
 		}
 
 		match opcode {
@@ -644,7 +960,12 @@ pub fn eval_script(
 			Opcode::OP_IF | Opcode::OP_NOTIF => {
 				let mut exec_value = false;
 				if executing {
-					exec_value = cast_to_bool(&try!(stack.pop().map_err(|_| Error::UnbalancedConditional)));
+					let value = try!(stack.pop().map_err(|_| Error::UnbalancedConditional));
+					// minimal IF argument is a consensus rule in tapscript
+					if is_tapscript && !(value.is_empty() || (value.len() == 1 && value[0] == 1)) {
+						return Err(Error::TapscriptMinimalIf);
+					}
+					exec_value = cast_to_bool(&value);
 					if opcode == Opcode::OP_NOTIF {
 						exec_value = !exec_value;
 					}
@@ -892,16 +1213,51 @@ pub fn eval_script(
 				stack.push(v.to_vec().into());
 			},
 			Opcode::OP_CODESEPARATOR => {
-				begincode = pc;
+				self.begincode = self.pc;
+				execution.codeseparator_position = position;
+			},
+			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY if is_tapscript => {
+				let pubkey = try!(stack.pop());
+				let signature = try!(stack.pop());
+				let success = try!(eval_tapscript_signature(checker, &signature, &pubkey, flags, execution, sig_checks));
+				match opcode {
+					// false is an empty vector, so it can be passed to OP_CHECKSIGADD with minimal encoding
+					Opcode::OP_CHECKSIG => {
+						if success {
+							stack.push(vec![1].into());
+						} else {
+							stack.push(Bytes::default());
+						}
+					},
+					Opcode::OP_CHECKSIGVERIFY if !success => {
+						return Err(Error::CheckSigVerify);
+					},
+					_ => {},
+				}
+			},
+			Opcode::OP_CHECKSIGADD => {
+				if !is_tapscript {
+					return Err(Error::BadOpcode);
+				}
+
+				let pubkey = try!(stack.pop());
+				let n = try!(Num::from_slice(&try!(stack.pop()), flags.verify_minimaldata, 4));
+				let signature = try!(stack.pop());
+				let success = try!(eval_tapscript_signature(checker, &signature, &pubkey, flags, execution, sig_checks));
+				if success {
+					stack.push((n + 1.into()).to_bytes());
+				} else {
+					stack.push(n.to_bytes());
+				}
 			},
 			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY => {
 				let pubkey = try!(stack.pop());
 				let signature = try!(stack.pop());
 				let sighash = parse_hash_type(version, &signature);
-				let mut subscript = script.subscript(begincode);
+				let mut subscript = script.subscript(self.begincode);
 				match version {
 					SignatureVersion::ForkId if sighash.fork_id => (),
-					SignatureVersion::WitnessV0 => (),
+					SignatureVersion::WitnessV0 | SignatureVersion::Taproot | SignatureVersion::Tapscript => (),
 					SignatureVersion::Base | SignatureVersion::ForkId => {
 						let signature_script = Builder::default().push_data(&*signature).into_script();
 						subscript = subscript.find_and_delete(&*signature_script);
@@ -911,6 +1267,11 @@ pub fn eval_script(
 				try!(check_signature_encoding(&signature, flags, version));
 				try!(check_pubkey_encoding(&pubkey, flags));
 
+				// null signature is never checked
+				if !signature.is_empty() {
+					*sig_checks += 1;
+				}
+
 				let success = check_signature(checker, signature.into(), pubkey.into(), &subscript, version);
 				match opcode {
 					Opcode::OP_CHECKSIG => {
@@ -927,6 +1288,10 @@ pub fn eval_script(
 				}
 			},
 			Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
+				if is_tapscript {
+					return Err(Error::TapscriptCheckMultisig);
+				}
+
 				let keys_count = try!(Num::from_slice(&try!(stack.pop()), flags.verify_minimaldata, 4));
 				if keys_count < 0.into() || keys_count > script::MAX_PUBKEYS_PER_MULTISIG.into() {
 					return Err(Error::PubkeyCount);
@@ -943,13 +1308,13 @@ pub fn eval_script(
 				let sigs_count: usize = sigs_count.into();
 				let sigs: Vec<_> = try!((0..sigs_count).into_iter().map(|_| stack.pop()).collect());
 
-				let mut subscript = script.subscript(begincode);
+				let mut subscript = script.subscript(self.begincode);
 
 				for signature in &sigs {
 					let sighash = parse_hash_type(version, &signature);
 					match version {
 						SignatureVersion::ForkId if sighash.fork_id => (),
-						SignatureVersion::WitnessV0 => (),
+						SignatureVersion::WitnessV0 | SignatureVersion::Taproot | SignatureVersion::Tapscript => (),
 						SignatureVersion::Base | SignatureVersion::ForkId => {
 							let signature_script = Builder::default().push_data(&*signature).into_script();
 							subscript = subscript.find_and_delete(&*signature_script);
@@ -981,6 +1346,11 @@ pub fn eval_script(
 					return Err(Error::SignatureNullDummy);
 				}
 
+				// in legacy (ECDSA) mode every key may be checked, unless all signatures are null
+				if sigs.iter().any(|sig| !sig.is_empty()) {
+					*sig_checks += keys_count;
+				}
+
 				match opcode {
 					Opcode::OP_CHECKMULTISIG => {
 						if success {
@@ -1009,33 +1379,42 @@ pub fn eval_script(
 			},
 		}
 
-		if stack.len() + altstack.len() > 1000 {
+		if stack.len() + altstack.len() > MAX_STACK_SIZE {
 			return Err(Error::StackSize);
 		}
-	}
 
-	if !exec_stack.is_empty() {
-		return Err(Error::UnbalancedConditional);
+		Ok(Some(result))
 	}
 
-	let success = !stack.is_empty() && {
-		let last = try!(stack.last());
-		cast_to_bool(last)
-	};
+	/// Checks the final state of the interpreter, once `step` has returned `None`.
+	pub fn finish(&self) -> Result<bool, Error> {
+		if !self.exec_stack.is_empty() {
+			return Err(Error::UnbalancedConditional);
+		}
 
-	Ok(success)
+		let success = !self.stack.is_empty() && {
+			let last = try!(self.stack.last());
+			cast_to_bool(last)
+		};
+
+		Ok(success)
+	}
 }
 
 #[cfg(test)]
 mod tests {
+	use std::sync::Arc;
 	use bytes::Bytes;
-	use chain::Transaction;
-	use sign::SignatureVersion;
+	use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint};
+	use hash::H256;
+	use keys::{Secret, XOnlyPublic, sign_schnorr, x_only_public, tweak_public, tweak_secret};
+	use sign::{SignatureVersion, ScriptExecutionData};
+	use taproot::{tapleaf_hash, taptweak_hash, TAPROOT_LEAF_TAPSCRIPT};
 	use {
 		Opcode, Script, ScriptWitness, VerificationFlags, Builder, Error, Num, TransactionInputSigner,
 		NoopSignatureChecker, TransactionSignatureChecker, Stack
 	};
-	use super::{eval_script, verify_script, is_public_key};
+	use super::{eval_script, eval_script_with_sig_checks, verify_script, is_public_key, Interpreter, InterpreterStep};
 
 	#[test]
 	fn tests_is_public_key() {
@@ -1974,7 +2353,7 @@ mod tests {
 		let tx: Transaction = "0100000001484d40d45b9ea0d652fca8258ab7caa42541eb52975857f96fb50cd732c8b481000000008a47304402202cb265bf10707bf49346c3515dd3d16fc454618c58ec0a0ff448a676c54ff71302206c6624d762a1fcef4618284ead8f08678ac05b13c84235f1654e6ad168233e8201410414e301b2328f17442c0b8310d787bf3d8a404cfbd0704f135b6ad4b2d3ee751310f981926e53a6e8c39bd7d3fefd576c543cce493cbac06388f2651d1aacbfcdffffffff0162640100000000001976a914c8e90996c7c6080ee06284600c684ed904d14c5c88ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 0,
 			input_amount: 0,
 		};
@@ -1991,7 +2370,7 @@ mod tests {
 		let tx: Transaction = "01000000013dcd7d87904c9cb7f4b79f36b5a03f96e2e729284c09856238d5353e1182b00200000000fd5e0100483045022100deeb1f13b5927b5e32d877f3c42a4b028e2e0ce5010fdb4e7f7b5e2921c1dcd2022068631cb285e8c1be9f061d2968a18c3163b780656f30a049effee640e80d9bff01483045022100ee80e164622c64507d243bd949217d666d8b16486e153ac6a1f8e04c351b71a502203691bef46236ca2b4f5e60a82a853a33d6712d6a1e7bf9a65e575aeb7328db8c014cc9524104a882d414e478039cd5b52a92ffb13dd5e6bd4515497439dffd691a0f12af9575fa349b5694ed3155b136f09e63975a1700c9f4d4df849323dac06cf3bd6458cd41046ce31db9bdd543e72fe3039a1f1c047dab87037c36a669ff90e28da1848f640de68c2fe913d363a51154a0c62d7adea1b822d05035077418267b1a1379790187410411ffd36c70776538d079fbae117dc38effafb33304af83ce4894589747aee1ef992f63280567f52f5ba870678b4ab4ff6c8ea600bd217870a8b4f1f09f3a8e8353aeffffffff0130d90000000000001976a914569076ba39fc4ff6a2291d9ea9196d8c08f9c7ab88ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 0,
 			input_amount: 0,
 		};
@@ -2008,7 +2387,7 @@ mod tests {
 		let tx: Transaction = "010000000173805864da01f15093f7837607ab8be7c3705e29a9d4a12c9116d709f8911e590100000049483045022052ffc1929a2d8bd365c6a2a4e3421711b4b1e1b8781698ca9075807b4227abcb0221009984107ddb9e3813782b095d0d84361ed4c76e5edaf6561d252ae162c2341cfb01ffffffff0200e1f50500000000434104baa9d36653155627c740b3409a734d4eaf5dcca9fb4f736622ee18efcf0aec2b758b2ec40db18fbae708f691edb2d4a2a3775eb413d16e2e3c0f8d4c69119fd1ac009ce4a60000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 0,
 			input_amount: 0,
 		};
@@ -2025,7 +2404,7 @@ mod tests {
 		let tx: Transaction = "01000000012316aac445c13ff31af5f3d1e2cebcada83e54ba10d15e01f49ec28bddc285aa000000008e4b3048022200002b83d59c1d23c08efd82ee0662fec23309c3adbcbd1f0b8695378db4b14e736602220000334a96676e58b1bb01784cb7c556dd8ce1c220171904da22e18fe1e7d1510db5014104d0fe07ff74c9ef5b00fed1104fad43ecf72dbab9e60733e4f56eacf24b20cf3b8cd945bcabcc73ba0158bf9ce769d43e94bd58c5c7e331a188922b3fe9ca1f5affffffff01c0c62d00000000001976a9147a2a3b481ca80c4ba7939c54d9278e50189d94f988ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 0,
 			input_amount: 0,
 		};
@@ -2042,7 +2421,7 @@ mod tests {
 		let tx: Transaction = "01000000024de8b0c4c2582db95fa6b3567a989b664484c7ad6672c85a3da413773e63fdb8000000006b48304502205b282fbc9b064f3bc823a23edcc0048cbb174754e7aa742e3c9f483ebe02911c022100e4b0b3a117d36cab5a67404dddbf43db7bea3c1530e0fe128ebc15621bd69a3b0121035aa98d5f77cd9a2d88710e6fc66212aff820026f0dad8f32d1f7ce87457dde50ffffffff4de8b0c4c2582db95fa6b3567a989b664484c7ad6672c85a3da413773e63fdb8010000006f004730440220276d6dad3defa37b5f81add3992d510d2f44a317fd85e04f93a1e2daea64660202200f862a0da684249322ceb8ed842fb8c859c0cb94c81e1c5308b4868157a428ee01ab51210232abdc893e7f0631364d7fd01cb33d24da45329a00357b3a7886211ab414d55a51aeffffffff02e0fd1c00000000001976a914380cb3c594de4e7e9b8e18db182987bebb5a4f7088acc0c62d000000000017142a9bc5447d664c1d0141392a842d23dba45c4f13b17500000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 1,
 			input_amount: 0,
 		};
@@ -2065,7 +2444,7 @@ mod tests {
 		let tx: Transaction = "01000000010c0e314bd7bb14721b3cfd8e487cd6866173354f87ca2cf4d13c8d3feb4301a6000000004a483045022100d92e4b61452d91a473a43cde4b469a472467c0ba0cbd5ebba0834e4f4762810402204802b76b7783db57ac1f61d2992799810e173e91055938750815b6d8a675902e014fffffffff0140548900000000001976a914a86e8ee2a05a44613904e18132e49b2448adc4e688ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 0,
 			input_amount: 0,
 		};
@@ -2089,6 +2468,48 @@ mod tests {
 		basic_test(&script, result, vec![vec![1].into()].into());
 	}
 
+	#[test]
+	fn test_interpreter_steps() {
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_1)
+			.push_opcode(Opcode::OP_IF)
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_TOALTSTACK)
+			.push_opcode(Opcode::OP_ELSE)
+			.push_opcode(Opcode::OP_3)
+			.push_opcode(Opcode::OP_ENDIF)
+			.into_script();
+		let flags = VerificationFlags::default();
+		let checker = NoopSignatureChecker;
+		let mut stack = Stack::new();
+		let mut interpreter = Interpreter::new(&mut stack, &script, &flags, &checker, SignatureVersion::Base);
+
+		let expected_steps = vec![
+			(Opcode::OP_1, true, vec![vec![1u8]], vec![]),
+			(Opcode::OP_IF, true, vec![], vec![]),
+			(Opcode::OP_2, true, vec![vec![2u8]], vec![]),
+			(Opcode::OP_TOALTSTACK, true, vec![], vec![vec![2u8]]),
+			(Opcode::OP_ELSE, true, vec![], vec![vec![2u8]]),
+			(Opcode::OP_3, false, vec![], vec![vec![2u8]]),
+			(Opcode::OP_ENDIF, false, vec![], vec![vec![2u8]]),
+		];
+		for (index, (opcode, executing, stack, altstack)) in expected_steps.into_iter().enumerate() {
+			assert_eq!(interpreter.step(), Ok(Some(InterpreterStep {
+				offset: index,
+				position: index as u32,
+				opcode: opcode,
+				executing: executing,
+			})));
+			let stack: Stack<Bytes> = stack.into_iter().map(Bytes::from).collect::<Vec<_>>().into();
+			let altstack: Stack<Bytes> = altstack.into_iter().map(Bytes::from).collect::<Vec<_>>().into();
+			assert_eq!(interpreter.stack(), &stack);
+			assert_eq!(interpreter.altstack(), &altstack);
+		}
+
+		assert_eq!(interpreter.step(), Ok(None));
+		assert_eq!(interpreter.finish(), Ok(false));
+	}
+
 	#[test]
 	fn test_skipping_sequencetimeverify() {
 		let script = Builder::default()
@@ -2116,7 +2537,7 @@ mod tests {
 		let tx: Transaction = "0100000002f9cbafc519425637ba4227f8d0a0b7160b4e65168193d5af39747891de98b5b5000000006b4830450221008dd619c563e527c47d9bd53534a770b102e40faa87f61433580e04e271ef2f960220029886434e18122b53d5decd25f1f4acb2480659fea20aabd856987ba3c3907e0121022b78b756e2258af13779c1a1f37ea6800259716ca4b7f0b87610e0bf3ab52a01ffffffff42e7988254800876b69f24676b3e0205b77be476512ca4d970707dd5c60598ab00000000fd260100483045022015bd0139bcccf990a6af6ec5c1c52ed8222e03a0d51c334df139968525d2fcd20221009f9efe325476eb64c3958e4713e9eefe49bf1d820ed58d2112721b134e2a1a53034930460221008431bdfa72bc67f9d41fe72e94c88fb8f359ffa30b33c72c121c5a877d922e1002210089ef5fc22dd8bfc6bf9ffdb01a9862d27687d424d1fefbab9e9c7176844a187a014c9052483045022015bd0139bcccf990a6af6ec5c1c52ed8222e03a0d51c334df139968525d2fcd20221009f9efe325476eb64c3958e4713e9eefe49bf1d820ed58d2112721b134e2a1a5303210378d430274f8c5ec1321338151e9f27f4c676a008bdf8638d07c0b6be9ab35c71210378d430274f8c5ec1321338151e9f27f4c676a008bdf8638d07c0b6be9ab35c7153aeffffffff01a08601000000000017a914d8dacdadb7462ae15cd906f1878706d0da8660e68700000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: Arc::new(signer),
 			input_index: 1,
 			input_amount: 0,
 		};
@@ -2147,7 +2568,7 @@ mod tests {
 		let checker = TransactionSignatureChecker {
 			input_index: 0,
 			input_amount: amount,
-			signer: TransactionInputSigner {
+			signer: Arc::new(TransactionInputSigner {
 				version: 1,
 				inputs: vec![
 					UnsignedTransactionInput {
@@ -2165,7 +2586,9 @@ mod tests {
 					},
 				],
 				lock_time: 0,
-			},
+				spent_outputs: vec![],
+				taproot_midstates: None,
+			}),
 		};
 
 		let script_pubkey = redeem_script;
@@ -2245,7 +2668,7 @@ mod tests {
 		let checker = TransactionSignatureChecker {
 			input_index: 0,
 			input_amount: amount,
-			signer: tx2.into(),
+			signer: Arc::new(tx2.into()),
 		};
 
 		verify_script(&script_sig,
@@ -2675,7 +3098,7 @@ mod tests {
 		let checker = TransactionSignatureChecker {
 			input_index: index,
 			input_amount: amount,
-			signer: tx.clone().into(),
+			signer: Arc::new(tx.clone().into()),
 		};
 
 		verify_script(&tx.inputs[index].script_sig.clone().into(),
@@ -3086,4 +3509,243 @@ mod tests {
 		let flags = VerificationFlags::default().verify_witness(true).verify_p2sh(true);
 		assert_eq!(Ok(()), run_witness_test_tx_test("a9149993a429037b5d912407a71c252019287b8d27a587".into(), &tx, &flags, 987654321, 0));
 	}
+
+	fn sig_checks(script: Script) -> usize {
+		let mut sig_checks = 0;
+		let mut stack = Stack::new();
+		let flags = VerificationFlags::default();
+		let _ = eval_script_with_sig_checks(&mut stack, &script, &flags, &NoopSignatureChecker, SignatureVersion::Base, &mut sig_checks);
+		sig_checks
+	}
+
+	#[test]
+	fn test_sig_checks() {
+		let public = [2; 33];
+		let signature = [0x30, 0x01];
+
+		// null signature is not checked
+		assert_eq!(sig_checks(Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_data(&public)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script()), 0);
+
+		assert_eq!(sig_checks(Builder::default()
+			.push_data(&signature)
+			.push_data(&public)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script()), 1);
+
+		// every key is counted in legacy multisig
+		assert_eq!(sig_checks(Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_data(&signature)
+			.push_opcode(Opcode::OP_1)
+			.push_data(&public)
+			.push_data(&public)
+			.push_data(&public)
+			.push_opcode(Opcode::OP_3)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script()), 3);
+
+		assert_eq!(sig_checks(Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_1)
+			.push_data(&public)
+			.push_data(&public)
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script()), 0);
+	}
+
+	fn taproot_script_pubkey(output_key: &XOnlyPublic) -> Script {
+		Builder::default()
+			.push_opcode(Opcode::OP_1)
+			.push_data(&**output_key)
+			.into_script()
+	}
+
+	fn taproot_checker(script_pubkey: &Script) -> TransactionSignatureChecker {
+		let tx = Transaction {
+			version: 2,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint { hash: H256::from(1), index: 0 },
+				script_sig: Bytes::default(),
+				sequence: 0xffffffff,
+				script_witness: vec![],
+			}],
+			outputs: vec![TransactionOutput {
+				value: 900,
+				script_pubkey: Bytes::default(),
+			}],
+			lock_time: 0,
+		};
+		let spent_outputs = vec![TransactionOutput {
+			value: 1000,
+			script_pubkey: script_pubkey.to_bytes(),
+		}];
+		TransactionSignatureChecker {
+			signer: Arc::new(TransactionInputSigner::from(tx).with_spent_outputs(spent_outputs)),
+			input_index: 0,
+			input_amount: 1000,
+		}
+	}
+
+	fn taproot_signature(checker: &TransactionSignatureChecker, secret: &Secret, version: SignatureVersion, execution: &ScriptExecutionData) -> Bytes {
+		let hash = checker.signer.signature_hash_taproot(0, 0, version, execution).unwrap();
+		let signature = sign_schnorr(secret, &hash, &H256::default()).unwrap();
+		signature.to_vec().into()
+	}
+
+	fn taproot_flags() -> VerificationFlags {
+		VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_taproot(true)
+	}
+
+	/// Returns script pubkey, committing to the single tapscript leaf, and control block of this leaf.
+	fn taproot_script_tree(internal_key: &XOnlyPublic, script: &Script) -> (Script, Bytes) {
+		let leaf_hash = tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script);
+		let (output_key, is_odd) = tweak_public(internal_key, &taptweak_hash(internal_key, Some(&leaf_hash))).unwrap();
+		let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | is_odd as u8];
+		control.extend_from_slice(&**internal_key);
+		(taproot_script_pubkey(&output_key), control.into())
+	}
+
+	fn tapscript_execution(script: &Script) -> ScriptExecutionData {
+		ScriptExecutionData {
+			tapleaf_hash: Some(tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script)),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_taproot_key_path_spending() {
+		let secret: Secret = "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef".into();
+		let internal_key = x_only_public(&secret).unwrap();
+		let tweak = taptweak_hash(&internal_key, None);
+		let (output_key, _) = tweak_public(&internal_key, &tweak).unwrap();
+		let script_pubkey = taproot_script_pubkey(&output_key);
+		let checker = taproot_checker(&script_pubkey);
+		let signature = taproot_signature(&checker, &tweak_secret(&secret, &tweak).unwrap(), SignatureVersion::Taproot, &ScriptExecutionData::default());
+
+		let flags = taproot_flags();
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &vec![signature.clone()], &flags, &checker, SignatureVersion::Base), Ok(()));
+
+		// SIGHASH_DEFAULT must not be encoded explicitly
+		let mut explicit_default = signature.to_vec();
+		explicit_default.push(0);
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &vec![explicit_default.into()], &flags, &checker, SignatureVersion::Base), Err(Error::SchnorrSigHashtype));
+
+		// signature of untweaked key
+		let untweaked = taproot_signature(&checker, &secret, SignatureVersion::Taproot, &ScriptExecutionData::default());
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &vec![untweaked.clone()], &flags, &checker, SignatureVersion::Base), Err(Error::SchnorrSig));
+
+		// witness v1 outputs are anyone-can-spend before taproot activation
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true);
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &vec![untweaked], &flags, &checker, SignatureVersion::Base), Ok(()));
+	}
+
+	#[test]
+	fn test_taproot_script_path_spending() {
+		let secret: Secret = "0000000000000000000000000000000000000000000000000000000000000003".into();
+		let public = x_only_public(&secret).unwrap();
+		let internal_key = x_only_public(&"b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef".into()).unwrap();
+		let script = Builder::default()
+			.push_data(&*public)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		let (script_pubkey, control) = taproot_script_tree(&internal_key, &script);
+		let checker = taproot_checker(&script_pubkey);
+		let signature = taproot_signature(&checker, &secret, SignatureVersion::Tapscript, &tapscript_execution(&script));
+
+		let flags = taproot_flags();
+		let witness = vec![signature.clone(), script.to_bytes(), control.clone()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Ok(()));
+
+		// empty signature fails OP_CHECKSIG without error
+		let witness = vec![Bytes::default(), script.to_bytes(), control.clone()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::EvalFalse));
+
+		// key path signature is not valid in tapscript
+		let key_path_signature = taproot_signature(&checker, &secret, SignatureVersion::Taproot, &ScriptExecutionData::default());
+		let witness = vec![key_path_signature, script.to_bytes(), control.clone()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::SchnorrSig));
+
+		// control block with wrong parity
+		let mut wrong_control = control.to_vec();
+		wrong_control[0] ^= 1;
+		let witness = vec![signature.clone(), script.to_bytes(), wrong_control.into()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::WitnessProgramMismatch));
+
+		let witness = vec![signature, script.to_bytes(), control[..32].to_vec().into()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::TaprootWrongControlSize));
+	}
+
+	#[test]
+	fn test_tapscript_checksigadd() {
+		let secrets: Vec<Secret> = vec![1.into(), 2.into(), 3.into()];
+		let internal_key = x_only_public(&4.into()).unwrap();
+		// 2-of-3 multisig
+		let script = secrets.iter().enumerate()
+			.fold(Builder::default(), |builder, (index, secret)| builder
+				.push_data(&*x_only_public(secret).unwrap())
+				.push_opcode(if index == 0 { Opcode::OP_CHECKSIG } else { Opcode::OP_CHECKSIGADD }))
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_NUMEQUAL)
+			.into_script();
+		let (script_pubkey, control) = taproot_script_tree(&internal_key, &script);
+		let checker = taproot_checker(&script_pubkey);
+		let execution = tapscript_execution(&script);
+		let signatures: Vec<Bytes> = secrets.iter()
+			.map(|secret| taproot_signature(&checker, secret, SignatureVersion::Tapscript, &execution))
+			.collect();
+
+		let flags = taproot_flags();
+		// signatures are consumed in reverse order
+		let witness = vec![signatures[2].clone(), Bytes::default(), signatures[0].clone(), script.to_bytes(), control.clone()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Ok(()));
+
+		let witness = vec![Bytes::default(), Bytes::default(), signatures[0].clone(), script.to_bytes(), control.clone()];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::EvalFalse));
+
+		// OP_CHECKSIGADD is not available outside of tapscript
+		let legacy = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_data(&[2; 33])
+			.push_opcode(Opcode::OP_CHECKSIGADD)
+			.into_script();
+		assert_eq!(eval_script(&mut Stack::default(), &legacy, &flags, &NoopSignatureChecker, SignatureVersion::WitnessV0), Err(Error::BadOpcode));
+	}
+
+	#[test]
+	fn test_tapscript_disabled_checkmultisig_and_op_success() {
+		let internal_key = x_only_public(&4.into()).unwrap();
+		let flags = taproot_flags();
+
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_0)
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script();
+		let (script_pubkey, control) = taproot_script_tree(&internal_key, &script);
+		let checker = taproot_checker(&script_pubkey);
+		let witness = vec![script.to_bytes(), control];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::TapscriptCheckMultisig));
+
+		// OP_SUCCESS80 makes script valid, even if it fails otherwise
+		let script: Script = vec![0x50, Opcode::OP_RETURN as u8].into();
+		let (script_pubkey, control) = taproot_script_tree(&internal_key, &script);
+		let checker = taproot_checker(&script_pubkey);
+		let witness = vec![script.to_bytes(), control];
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Ok(()));
+		let flags = flags.verify_discourage_op_success(true);
+		assert_eq!(verify_script(&Script::default(), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base), Err(Error::DiscourageOpSuccess));
+	}
 }
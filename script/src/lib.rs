@@ -9,11 +9,13 @@ mod builder;
 mod error;
 mod flags;
 mod interpreter;
+mod miniscript;
 mod num;
 mod opcode;
 mod script;
 mod sign;
 mod stack;
+mod taproot;
 mod verify;
 
 pub use primitives::{bytes, hash};
@@ -21,11 +23,13 @@ pub use primitives::{bytes, hash};
 pub use self::builder::Builder;
 pub use self::error::Error;
 pub use self::flags::VerificationFlags;
-pub use self::interpreter::{eval_script, verify_script};
+pub use self::interpreter::{eval_script, eval_script_with_sig_checks, verify_script, verify_script_with_sig_checks, Interpreter, InterpreterStep};
+pub use self::miniscript::{Miniscript, MiniscriptError, Policy, Type as MiniscriptType, BaseType as MiniscriptBaseType};
 pub use self::opcode::Opcode;
 pub use self::num::Num;
 pub use self::script::{Script, ScriptType, ScriptAddress, ScriptWitness, is_witness_commitment_script};
-pub use self::sign::{TransactionInputSigner, UnsignedTransactionInput, SignatureVersion};
+pub use self::sign::{TransactionInputSigner, UnsignedTransactionInput, SignatureVersion, ScriptExecutionData, TaprootMidstates, NO_CODESEPARATOR_POSITION};
 pub use self::stack::Stack;
+pub use self::taproot::{tapleaf_hash, tapbranch_hash, taptweak_hash, TAPROOT_LEAF_TAPSCRIPT};
 pub use self::verify::{SignatureChecker, NoopSignatureChecker, TransactionSignatureChecker};
 
@@ -0,0 +1,397 @@
+//! Decoding of scripts into miniscript.
+//!
+//! Every miniscript fragment is identified by the last opcodes of its encoding, so the script is
+//! parsed backwards. OP_xxxVERIFY opcodes are split into OP_xxx and OP_VERIFY before parsing.
+
+use keys::Public;
+use {Num, Opcode, Script};
+use super::{Miniscript, MiniscriptError, BaseType};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Op(Opcode),
+	Data(Vec<u8>),
+}
+
+struct Decoder {
+	tokens: Vec<Token>,
+	/// Number of tokens, which are not parsed yet.
+	position: usize,
+	/// Expression, which has been parsed, but wasn't consumed by the sequence: (end position, expression, start position).
+	pending: Option<(usize, Miniscript, usize)>,
+}
+
+pub fn decode(script: &Script) -> Result<Miniscript, MiniscriptError> {
+	let tokens = lex(script)?;
+	let mut decoder = Decoder {
+		position: tokens.len(),
+		tokens: tokens,
+		pending: None,
+	};
+
+	let miniscript = decoder.parse_sequence()?;
+	if decoder.position != 0 {
+		return Err(MiniscriptError::NonMiniscript);
+	}
+
+	let ty = miniscript.type_check()?;
+	if ty.base != BaseType::B {
+		return Err(MiniscriptError::TypeCheck(format!("top level expression has type {}", ty)));
+	}
+
+	Ok(miniscript)
+}
+
+fn lex(script: &Script) -> Result<Vec<Token>, MiniscriptError> {
+	let mut tokens = Vec::new();
+	let mut pc = 0;
+	while pc < script.len() {
+		let instruction = script.get_instruction(pc).map_err(|_| MiniscriptError::NonMiniscript)?;
+		pc += instruction.step;
+
+		let opcode = instruction.opcode;
+		match instruction.data {
+			Some(data) if opcode != Opcode::OP_0 => {
+				// miniscript never pushes more than 75 bytes
+				if opcode > Opcode::OP_PUSHBYTES_75 {
+					return Err(MiniscriptError::NonMiniscript);
+				}
+				tokens.push(Token::Data(data.to_vec()));
+			},
+			_ => {
+				let verified = match opcode {
+					Opcode::OP_EQUALVERIFY => Some(Opcode::OP_EQUAL),
+					Opcode::OP_NUMEQUALVERIFY => Some(Opcode::OP_NUMEQUAL),
+					Opcode::OP_CHECKSIGVERIFY => Some(Opcode::OP_CHECKSIG),
+					Opcode::OP_CHECKMULTISIGVERIFY => Some(Opcode::OP_CHECKMULTISIG),
+					_ => None,
+				};
+
+				match verified {
+					Some(verified) => {
+						tokens.push(Token::Op(verified));
+						tokens.push(Token::Op(Opcode::OP_VERIFY));
+					},
+					None => {
+						if opcode == Opcode::OP_VERIFY && tokens.last().map_or(false, has_verify_version) {
+							return Err(MiniscriptError::NonMinimalVerify);
+						}
+						tokens.push(Token::Op(opcode));
+					},
+				}
+			},
+		}
+	}
+
+	Ok(tokens)
+}
+
+fn has_verify_version(token: &Token) -> bool {
+	match *token {
+		Token::Op(Opcode::OP_EQUAL) | Token::Op(Opcode::OP_NUMEQUAL) |
+		Token::Op(Opcode::OP_CHECKSIG) | Token::Op(Opcode::OP_CHECKMULTISIG) => true,
+		_ => false,
+	}
+}
+
+/// Converts number push into number. Numbers are required to be minimally encoded.
+fn token_number(token: &Token) -> Result<usize, MiniscriptError> {
+	match *token {
+		Token::Op(opcode) if opcode >= Opcode::OP_1 && opcode <= Opcode::OP_16 =>
+			Ok(opcode as usize - Opcode::OP_1 as usize + 1),
+		Token::Data(ref data) => {
+			let number: i64 = Num::from_slice(data, true, 4).map_err(|_| MiniscriptError::NonMiniscript)?.into();
+			// numbers below 17 are pushed with OP_1..OP_16
+			if number <= 16 {
+				return Err(MiniscriptError::NonMiniscript);
+			}
+			Ok(number as usize)
+		},
+		_ => Err(MiniscriptError::NonMiniscript),
+	}
+}
+
+impl Decoder {
+	fn next(&mut self) -> Result<Token, MiniscriptError> {
+		if self.position == 0 {
+			return Err(MiniscriptError::NonMiniscript);
+		}
+
+		self.position -= 1;
+		Ok(self.tokens[self.position].clone())
+	}
+
+	fn next_opcode(&mut self) -> Result<Opcode, MiniscriptError> {
+		match self.next()? {
+			Token::Op(opcode) => Ok(opcode),
+			Token::Data(_) => Err(MiniscriptError::NonMiniscript),
+		}
+	}
+
+	/// Consumes the opcode if it is the next one.
+	fn skip(&mut self, opcode: Opcode) -> bool {
+		if self.position != 0 && self.tokens[self.position - 1] == Token::Op(opcode) {
+			self.position -= 1;
+			return true;
+		}
+
+		false
+	}
+
+	fn expect(&mut self, opcode: Opcode) -> Result<(), MiniscriptError> {
+		if !self.skip(opcode) {
+			return Err(MiniscriptError::NonMiniscript);
+		}
+
+		Ok(())
+	}
+
+	fn number(&mut self) -> Result<usize, MiniscriptError> {
+		token_number(&self.next()?)
+	}
+
+	fn locktime(&mut self) -> Result<u32, MiniscriptError> {
+		match self.number()? {
+			n if n < 0x8000_0000 => Ok(n as u32),
+			_ => Err(MiniscriptError::NonMiniscript),
+		}
+	}
+
+	fn key(&mut self) -> Result<Public, MiniscriptError> {
+		match self.next()? {
+			Token::Data(ref data) => Public::from_slice(data).map_err(|_| MiniscriptError::NonMiniscript),
+			Token::Op(_) => Err(MiniscriptError::NonMiniscript),
+		}
+	}
+
+	/// Consumes `OP_DUP OP_HASH160 <hash> OP_EQUAL` (followed by already consumed OP_VERIFY) of `pk_h`.
+	fn key_hash(&mut self) -> Option<Vec<u8>> {
+		if self.position < 4 {
+			return None;
+		}
+
+		let hash = {
+			let tokens = &self.tokens[self.position - 4..self.position];
+			match (&tokens[0], &tokens[1], &tokens[2], &tokens[3]) {
+				(&Token::Op(Opcode::OP_DUP), &Token::Op(Opcode::OP_HASH160), &Token::Data(ref hash), &Token::Op(Opcode::OP_EQUAL))
+					if hash.len() == 20 => hash.clone(),
+				_ => return None,
+			}
+		};
+
+		self.position -= 4;
+		Some(hash)
+	}
+
+	/// Parses the sequence of expressions, joined with `and_v`, and, optionally, wrapped with `s:`.
+	fn parse_sequence(&mut self) -> Result<Miniscript, MiniscriptError> {
+		let mut result = self.parse_single()?;
+		loop {
+			if self.skip(Opcode::OP_SWAP) {
+				return Ok(Miniscript::Swap(Box::new(result)));
+			}
+
+			let end = self.position;
+			match self.parse_single() {
+				Ok(left) => {
+					let is_verify = left.type_check().map(|ty| ty.base == BaseType::V).unwrap_or(false);
+					if !is_verify {
+						// the expression belongs to the enclosing fragment, keep it to avoid parsing it twice
+						self.pending = Some((end, left, self.position));
+						self.position = end;
+						return Ok(result);
+					}
+					result = Miniscript::AndV(Box::new(left), Box::new(result));
+				},
+				Err(_) => {
+					self.position = end;
+					return Ok(result);
+				},
+			}
+		}
+	}
+
+	/// Parses single expression, ending at the current position.
+	fn parse_single(&mut self) -> Result<Miniscript, MiniscriptError> {
+		if let Some((end, miniscript, start)) = self.pending.take() {
+			if end == self.position {
+				self.position = start;
+				return Ok(miniscript);
+			}
+		}
+
+		let result = match self.next()? {
+			Token::Op(Opcode::OP_0) => Miniscript::False,
+			Token::Op(Opcode::OP_1) => Miniscript::True,
+			Token::Data(ref data) if data.len() == 33 || data.len() == 65 =>
+				Miniscript::PkK(Public::from_slice(data).map_err(|_| MiniscriptError::NonMiniscript)?),
+			Token::Op(Opcode::OP_CHECKSIG) => Miniscript::Check(Box::new(self.parse_single()?)),
+			Token::Op(Opcode::OP_CHECKMULTISIG) => {
+				let n = self.number()?;
+				let mut keys = Vec::new();
+				for _ in 0..n {
+					keys.push(self.key()?);
+				}
+				keys.reverse();
+				let k = self.number()?;
+				Miniscript::Multi(k, keys)
+			},
+			Token::Op(Opcode::OP_VERIFY) => match self.key_hash() {
+				Some(hash) => Miniscript::RawPkH(hash[..].into()),
+				None => Miniscript::Verify(Box::new(self.parse_single()?)),
+			},
+			Token::Op(Opcode::OP_EQUAL) => match self.next()? {
+				Token::Data(ref hash) if hash.len() == 32 || hash.len() == 20 => {
+					let opcode = self.next_opcode()?;
+					self.expect(Opcode::OP_VERIFY)?;
+					self.expect(Opcode::OP_EQUAL)?;
+					if self.number()? != 32 {
+						return Err(MiniscriptError::NonMiniscript);
+					}
+					self.expect(Opcode::OP_SIZE)?;
+					match (opcode, hash.len()) {
+						(Opcode::OP_SHA256, 32) => Miniscript::Sha256(hash[..].into()),
+						(Opcode::OP_HASH256, 32) => Miniscript::Hash256(hash[..].into()),
+						(Opcode::OP_RIPEMD160, 20) => Miniscript::Ripemd160(hash[..].into()),
+						(Opcode::OP_HASH160, 20) => Miniscript::Hash160(hash[..].into()),
+						_ => return Err(MiniscriptError::NonMiniscript),
+					}
+				},
+				token => {
+					let k = token_number(&token)?;
+					let mut subs = Vec::new();
+					while self.skip(Opcode::OP_ADD) {
+						subs.push(self.parse_sequence()?);
+					}
+					subs.push(self.parse_single()?);
+					subs.reverse();
+					Miniscript::Thresh(k, subs)
+				},
+			},
+			Token::Op(Opcode::OP_CHECKSEQUENCEVERIFY) => Miniscript::Older(self.locktime()?),
+			Token::Op(Opcode::OP_CHECKLOCKTIMEVERIFY) => Miniscript::After(self.locktime()?),
+			Token::Op(Opcode::OP_0NOTEQUAL) => Miniscript::ZeroNotEqual(Box::new(self.parse_single()?)),
+			Token::Op(Opcode::OP_FROMALTSTACK) => {
+				let x = self.parse_sequence()?;
+				self.expect(Opcode::OP_TOALTSTACK)?;
+				Miniscript::Alt(Box::new(x))
+			},
+			Token::Op(Opcode::OP_BOOLAND) => {
+				let y = self.parse_sequence()?;
+				let x = self.parse_single()?;
+				Miniscript::AndB(Box::new(x), Box::new(y))
+			},
+			Token::Op(Opcode::OP_BOOLOR) => {
+				let z = self.parse_sequence()?;
+				let x = self.parse_single()?;
+				Miniscript::OrB(Box::new(x), Box::new(z))
+			},
+			Token::Op(Opcode::OP_ENDIF) => {
+				let last = self.parse_sequence()?;
+				match self.next_opcode()? {
+					Opcode::OP_ELSE => {
+						let first = self.parse_sequence()?;
+						match self.next_opcode()? {
+							// OP_IF [X] OP_ELSE [Z] OP_ENDIF
+							Opcode::OP_IF => Miniscript::OrI(Box::new(first), Box::new(last)),
+							// [X] OP_NOTIF [Z] OP_ELSE [Y] OP_ENDIF
+							Opcode::OP_NOTIF => {
+								let x = self.parse_single()?;
+								Miniscript::AndOr(Box::new(x), Box::new(last), Box::new(first))
+							},
+							_ => return Err(MiniscriptError::NonMiniscript),
+						}
+					},
+					Opcode::OP_IF => {
+						if self.skip(Opcode::OP_DUP) {
+							Miniscript::DupIf(Box::new(last))
+						} else {
+							self.expect(Opcode::OP_0NOTEQUAL)?;
+							self.expect(Opcode::OP_SIZE)?;
+							Miniscript::NonZero(Box::new(last))
+						}
+					},
+					Opcode::OP_NOTIF => {
+						let is_or_d = self.skip(Opcode::OP_IFDUP);
+						let x = self.parse_single()?;
+						if is_or_d {
+							Miniscript::OrD(Box::new(x), Box::new(last))
+						} else {
+							Miniscript::OrC(Box::new(x), Box::new(last))
+						}
+					},
+					_ => return Err(MiniscriptError::NonMiniscript),
+				}
+			},
+			_ => return Err(MiniscriptError::NonMiniscript),
+		};
+
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use {Script, Miniscript, MiniscriptError};
+
+	const KEY1: &'static str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+	const KEY2: &'static str = "03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb";
+	const KEY3: &'static str = "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13";
+
+	fn with_keys(s: &str) -> String {
+		s.replace("K1", KEY1).replace("K2", KEY2).replace("K3", KEY3)
+	}
+
+	fn script(hex: &str) -> Script {
+		let bytes: Bytes = with_keys(hex).parse().unwrap();
+		bytes.into()
+	}
+
+	#[test]
+	fn test_decode_roundtrip() {
+		let expressions = [
+			"pk(K1)",
+			"pkh(K1)",
+			"older(144)",
+			"after(500000)",
+			"multi(2,K1,K2,K3)",
+			"or_d(pk(K1),and_v(v:pk(K2),older(10)))",
+			"thresh(2,pk(K1),s:pk(K2),sln:older(12))",
+			"andor(pk(K1),older(1000),pk(K2))",
+			"or_b(pk(K1),a:pk(K2))",
+			"and_b(pk(K1),s:pk(K2))",
+			"or_i(and_v(v:pk(K1),older(10)),multi(1,K2,K3))",
+			"t:or_c(pk(K1),v:sha256(926a54995ca48600920a19bf7bc502ca5f2f7d07e6f804c4f00ebf0325084dbc))",
+			"and_v(v:multi(1,K1,K2),hash160(06afd46bcdfd22ef94ac122aa11f241244a37ecc))",
+			"or_d(pk(K1),j:and_v(v:pk(K2),pk(K3)))",
+			"or_d(pk(K1),d:v:older(5))",
+		];
+
+		for expression in &expressions {
+			let miniscript: Miniscript = with_keys(expression).parse().unwrap();
+			let script = miniscript.to_script();
+			let decoded = Miniscript::from_script(&script).unwrap();
+			assert_eq!(decoded.to_script(), script, "{}", expression);
+			assert_eq!(decoded.type_check(), miniscript.type_check(), "{}", expression);
+		}
+	}
+
+	#[test]
+	fn test_decode_key_hash() {
+		let decoded = Miniscript::from_script(&script("76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac")).unwrap();
+		assert_eq!(decoded.to_string(), "c:expr_raw_pkh(06afd46bcdfd22ef94ac122aa11f241244a37ecc)");
+	}
+
+	#[test]
+	fn test_decode_errors() {
+		// <K1> OP_CHECKSIG OP_VERIFY OP_1 should use OP_CHECKSIGVERIFY
+		assert_eq!(Miniscript::from_script(&script("21K1ac6951")), Err(MiniscriptError::NonMinimalVerify));
+		// P2SH script
+		assert_eq!(Miniscript::from_script(&script("a91406afd46bcdfd22ef94ac122aa11f241244a37ecc87")), Err(MiniscriptError::NonMiniscript));
+		// OP_5 pushed as data
+		assert_eq!(Miniscript::from_script(&script("0105b2")), Err(MiniscriptError::NonMiniscript));
+		// <K1> is K, not B
+		assert_eq!(Miniscript::from_script(&script("21K1")), Err(MiniscriptError::TypeCheck("top level expression has type Kondu".into())));
+	}
+}
@@ -0,0 +1,86 @@
+use super::MiniscriptError;
+
+/// Parsed `name(arg1,arg2,...)` expression, shared by miniscript and policy languages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression<'a> {
+	pub name: &'a str,
+	pub args: Vec<Expression<'a>>,
+}
+
+impl<'a> Expression<'a> {
+	/// Parses the whole string as a single expression.
+	pub fn parse(s: &'a str) -> Result<Self, MiniscriptError> {
+		let (expression, rest) = Expression::parse_prefix(s)?;
+		if !rest.is_empty() {
+			return Err(MiniscriptError::InvalidExpression(s.into()));
+		}
+
+		Ok(expression)
+	}
+
+	fn parse_prefix(s: &'a str) -> Result<(Self, &'a str), MiniscriptError> {
+		let name_end = s.find(|c: char| c == '(' || c == ',' || c == ')').unwrap_or_else(|| s.len());
+		let name = &s[..name_end];
+		if name.is_empty() {
+			return Err(MiniscriptError::InvalidExpression(s.into()));
+		}
+
+		let mut rest = &s[name_end..];
+		let mut args = Vec::new();
+		if rest.starts_with('(') {
+			rest = &rest[1..];
+			loop {
+				let (arg, arg_rest) = Expression::parse_prefix(rest)?;
+				args.push(arg);
+				if arg_rest.starts_with(',') {
+					rest = &arg_rest[1..];
+				} else if arg_rest.starts_with(')') {
+					rest = &arg_rest[1..];
+					break;
+				} else {
+					return Err(MiniscriptError::InvalidExpression(s.into()));
+				}
+			}
+		}
+
+		Ok((Expression { name: name, args: args }, rest))
+	}
+
+	/// Returns the only argument of the terminal expression, e.g. key of the `pk(key)`.
+	pub fn terminal_arg(&self) -> Result<&'a str, MiniscriptError> {
+		match self.args.len() {
+			1 if self.args[0].args.is_empty() => Ok(self.args[0].name),
+			_ => Err(MiniscriptError::InvalidArgumentsCount(self.name.into())),
+		}
+	}
+
+	/// Ensures that expression has exactly given number of arguments.
+	pub fn require_args(&self, count: usize) -> Result<(), MiniscriptError> {
+		if self.args.len() != count {
+			return Err(MiniscriptError::InvalidArgumentsCount(self.name.into()));
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use miniscript::MiniscriptError;
+	use super::Expression;
+
+	#[test]
+	fn test_parse_expression() {
+		assert_eq!(Expression::parse("or_d(pk(A),v:older(10))"), Ok(Expression {
+			name: "or_d",
+			args: vec![
+				Expression { name: "pk", args: vec![Expression { name: "A", args: vec![] }] },
+				Expression { name: "v:older", args: vec![Expression { name: "10", args: vec![] }] },
+			],
+		}));
+		assert_eq!(Expression::parse("0"), Ok(Expression { name: "0", args: vec![] }));
+		assert_eq!(Expression::parse("pk(A"), Err(MiniscriptError::InvalidExpression("pk(A".into())));
+		assert_eq!(Expression::parse("pk(A))"), Err(MiniscriptError::InvalidExpression("pk(A))".into())));
+		assert_eq!(Expression::parse("pk(,A)"), Err(MiniscriptError::InvalidExpression(",A)".into())));
+	}
+}
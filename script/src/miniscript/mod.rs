@@ -0,0 +1,811 @@
+//! Miniscript: structured representation of the subset of bitcoin scripts, which could be
+//! analyzed and composed (https://bitcoin.sipa.be/miniscript/).
+//!
+//! Scripts are assumed to be executed as P2WSH witness scripts.
+
+mod decode;
+mod expression;
+mod policy;
+
+use std::{fmt, str};
+use hash::{H160, H256};
+use keys::{Public, AddressHash};
+use {Builder, Num, Opcode, Script};
+use self::expression::Expression;
+
+pub use self::policy::Policy;
+
+/// Maximal number of keys in `multi`.
+pub const MAX_MULTI_KEYS: usize = 20;
+
+/// Miniscript parsing, type checking and compilation errors.
+#[derive(Debug, PartialEq)]
+pub enum MiniscriptError {
+	/// Expression isn't a valid `name(args)` expression.
+	InvalidExpression(String),
+	/// Unknown fragment or wrapper.
+	UnknownFragment(String),
+	/// Fragment has unexpected number of arguments.
+	InvalidArgumentsCount(String),
+	/// Public key can't be parsed.
+	InvalidKey(String),
+	/// Hash can't be parsed.
+	InvalidHash(String),
+	/// Number can't be parsed or is out of range.
+	InvalidNumber(String),
+	/// Threshold isn't in 1..=n range (or n is too large).
+	InvalidThreshold(usize, usize),
+	/// Fragment arguments have wrong types.
+	TypeCheck(String),
+	/// Script isn't an encoding of any miniscript.
+	NonMiniscript,
+	/// Script uses OP_VERIFY where OP_xxxVERIFY could be used.
+	NonMinimalVerify,
+	/// Policy can't be compiled into valid miniscript.
+	Uncompilable(String),
+}
+
+impl fmt::Display for MiniscriptError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MiniscriptError::InvalidExpression(ref s) => write!(f, "Invalid expression: {}", s),
+			MiniscriptError::UnknownFragment(ref s) => write!(f, "Unknown fragment: {}", s),
+			MiniscriptError::InvalidArgumentsCount(ref s) => write!(f, "Invalid number of arguments of {}", s),
+			MiniscriptError::InvalidKey(ref s) => write!(f, "Invalid public key: {}", s),
+			MiniscriptError::InvalidHash(ref s) => write!(f, "Invalid hash: {}", s),
+			MiniscriptError::InvalidNumber(ref s) => write!(f, "Invalid number: {}", s),
+			MiniscriptError::InvalidThreshold(k, n) => write!(f, "Invalid threshold {} of {}", k, n),
+			MiniscriptError::TypeCheck(ref s) => write!(f, "Type check failed: {}", s),
+			MiniscriptError::NonMiniscript => "Script isn't a miniscript".fmt(f),
+			MiniscriptError::NonMinimalVerify => "Script uses non-minimal OP_VERIFY".fmt(f),
+			MiniscriptError::Uncompilable(ref s) => write!(f, "Policy can't be compiled: {}", s),
+		}
+	}
+}
+
+/// Basic type of the miniscript expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BaseType {
+	/// Base: takes inputs from the top of the stack, pushes nonzero on satisfaction and exact 0 on dissatisfaction.
+	B,
+	/// Verify: takes inputs from the top of the stack, continues execution on satisfaction, can't be dissatisfied.
+	V,
+	/// Key: takes inputs from the top of the stack, pushes a public key, which signature is checked for satisfaction.
+	K,
+	/// Wrapped: takes inputs from one below the top of the stack, pushes result like B, keeping the top element on top.
+	W,
+}
+
+/// Type of the miniscript expression: basic type and correctness properties.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Type {
+	pub base: BaseType,
+	/// Zero-arg: always consumes exactly 0 stack elements.
+	pub z: bool,
+	/// One-arg: always consumes exactly 1 stack element.
+	pub o: bool,
+	/// Nonzero: satisfaction never needs zero top stack element.
+	pub n: bool,
+	/// Dissatisfiable: dissatisfaction can always be constructed.
+	pub d: bool,
+	/// Unit: pushes exactly 1 on satisfaction.
+	pub u: bool,
+}
+
+impl Type {
+	fn new(base: BaseType, z: bool, o: bool, n: bool, d: bool, u: bool) -> Self {
+		Type {
+			base: base,
+			z: z,
+			o: o,
+			n: n,
+			d: d,
+			u: u,
+		}
+	}
+
+	fn is(&self, base: BaseType) -> bool {
+		self.base == base
+	}
+}
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?}", self.base)?;
+		for &(property, name) in &[(self.z, "z"), (self.o, "o"), (self.n, "n"), (self.d, "d"), (self.u, "u")] {
+			if property {
+				f.write_str(name)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Miniscript expression.
+///
+/// `pk`, `pkh`, `and_n`, `t:`, `l:` and `u:` are parsed into their canonical forms.
+#[derive(Debug, PartialEq)]
+pub enum Miniscript {
+	/// `0`
+	False,
+	/// `1`
+	True,
+	/// `pk_k(key)`
+	PkK(Public),
+	/// `pk_h(key)`
+	PkH(Public),
+	/// `pk_h` with unknown key. It is only created, when script is decoded.
+	RawPkH(AddressHash),
+	/// `older(n)`
+	Older(u32),
+	/// `after(n)`
+	After(u32),
+	/// `sha256(h)`
+	Sha256(H256),
+	/// `hash256(h)`
+	Hash256(H256),
+	/// `ripemd160(h)`
+	Ripemd160(H160),
+	/// `hash160(h)`
+	Hash160(H160),
+	/// `andor(X,Y,Z)`
+	AndOr(Box<Miniscript>, Box<Miniscript>, Box<Miniscript>),
+	/// `and_v(X,Y)`
+	AndV(Box<Miniscript>, Box<Miniscript>),
+	/// `and_b(X,Y)`
+	AndB(Box<Miniscript>, Box<Miniscript>),
+	/// `or_b(X,Z)`
+	OrB(Box<Miniscript>, Box<Miniscript>),
+	/// `or_c(X,Z)`
+	OrC(Box<Miniscript>, Box<Miniscript>),
+	/// `or_d(X,Z)`
+	OrD(Box<Miniscript>, Box<Miniscript>),
+	/// `or_i(X,Z)`
+	OrI(Box<Miniscript>, Box<Miniscript>),
+	/// `thresh(k,X1,...,Xn)`
+	Thresh(usize, Vec<Miniscript>),
+	/// `multi(k,key1,...,keyn)`
+	Multi(usize, Vec<Public>),
+	/// `a:X`
+	Alt(Box<Miniscript>),
+	/// `s:X`
+	Swap(Box<Miniscript>),
+	/// `c:X`
+	Check(Box<Miniscript>),
+	/// `d:X`
+	DupIf(Box<Miniscript>),
+	/// `v:X`
+	Verify(Box<Miniscript>),
+	/// `j:X`
+	NonZero(Box<Miniscript>),
+	/// `n:X`
+	ZeroNotEqual(Box<Miniscript>),
+}
+
+impl Miniscript {
+	/// Decodes the script into miniscript.
+	pub fn from_script(script: &Script) -> Result<Self, MiniscriptError> {
+		decode::decode(script)
+	}
+
+	/// Computes type of the expression, checking that all fragments have arguments of required types.
+	pub fn type_check(&self) -> Result<Type, MiniscriptError> {
+		use self::BaseType::*;
+
+		let ty = match *self {
+			Miniscript::False => Type::new(B, true, false, false, true, true),
+			Miniscript::True => Type::new(B, true, false, false, false, true),
+			Miniscript::PkK(_) => Type::new(K, false, true, true, true, true),
+			Miniscript::PkH(_) | Miniscript::RawPkH(_) => Type::new(K, false, false, true, true, true),
+			Miniscript::Older(_) | Miniscript::After(_) => Type::new(B, true, false, false, false, false),
+			Miniscript::Sha256(_) | Miniscript::Hash256(_) | Miniscript::Ripemd160(_) | Miniscript::Hash160(_) =>
+				Type::new(B, false, true, true, true, true),
+			Miniscript::AndOr(ref x, ref y, ref z) => {
+				let (x, y, z) = (x.type_check()?, y.type_check()?, z.type_check()?);
+				require(x.is(B) && x.d && x.u && y.base == z.base && !y.is(W), "andor")?;
+				Type::new(y.base, x.z && y.z && z.z, (x.z && y.o && z.o) || (x.o && y.z && z.z), false, z.d, y.u && z.u)
+			},
+			Miniscript::AndV(ref x, ref y) => {
+				let (x, y) = (x.type_check()?, y.type_check()?);
+				require(x.is(V) && !y.is(W), "and_v")?;
+				Type::new(y.base, x.z && y.z, (x.z && y.o) || (x.o && y.z), x.n || (x.z && y.n), false, y.u)
+			},
+			Miniscript::AndB(ref x, ref y) => {
+				let (x, y) = (x.type_check()?, y.type_check()?);
+				require(x.is(B) && y.is(W), "and_b")?;
+				Type::new(B, x.z && y.z, (x.z && y.o) || (x.o && y.z), x.n || (x.z && y.n), x.d && y.d, true)
+			},
+			Miniscript::OrB(ref x, ref z) => {
+				let (x, z) = (x.type_check()?, z.type_check()?);
+				require(x.is(B) && x.d && z.is(W) && z.d, "or_b")?;
+				Type::new(B, x.z && z.z, (x.z && z.o) || (x.o && z.z), false, true, true)
+			},
+			Miniscript::OrC(ref x, ref z) => {
+				let (x, z) = (x.type_check()?, z.type_check()?);
+				require(x.is(B) && x.d && x.u && z.is(V), "or_c")?;
+				Type::new(V, x.z && z.z, x.o && z.z, false, false, false)
+			},
+			Miniscript::OrD(ref x, ref z) => {
+				let (x, z) = (x.type_check()?, z.type_check()?);
+				require(x.is(B) && x.d && x.u && z.is(B), "or_d")?;
+				Type::new(B, x.z && z.z, x.o && z.z, false, z.d, z.u)
+			},
+			Miniscript::OrI(ref x, ref z) => {
+				let (x, z) = (x.type_check()?, z.type_check()?);
+				require(x.base == z.base && !x.is(W), "or_i")?;
+				Type::new(x.base, false, x.z && z.z, false, x.d || z.d, x.u && z.u)
+			},
+			Miniscript::Thresh(k, ref subs) => {
+				check_threshold(k, subs.len(), usize::max_value())?;
+				let mut all_z = true;
+				let mut o_count = 0;
+				for (index, sub) in subs.iter().enumerate() {
+					let ty = sub.type_check()?;
+					let base = if index == 0 { B } else { W };
+					require(ty.is(base) && ty.d && ty.u, "thresh")?;
+					all_z = all_z && ty.z;
+					if !ty.z {
+						o_count += if ty.o { 1 } else { 2 };
+					}
+				}
+				Type::new(B, all_z, o_count == 1, false, true, true)
+			},
+			Miniscript::Multi(k, ref keys) => {
+				check_threshold(k, keys.len(), MAX_MULTI_KEYS)?;
+				Type::new(B, false, false, true, true, true)
+			},
+			Miniscript::Alt(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(B), "a:")?;
+				Type::new(W, false, false, false, x.d, x.u)
+			},
+			Miniscript::Swap(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(B) && x.o, "s:")?;
+				Type::new(W, false, false, false, x.d, x.u)
+			},
+			Miniscript::Check(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(K), "c:")?;
+				Type::new(B, false, x.o, x.n, x.d, true)
+			},
+			Miniscript::DupIf(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(V) && x.z, "d:")?;
+				// OP_IF argument isn't required to be minimal in P2WSH, so d:X isn't unit
+				Type::new(B, false, true, true, true, false)
+			},
+			Miniscript::Verify(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(B), "v:")?;
+				Type::new(V, x.z, x.o, x.n, false, false)
+			},
+			Miniscript::NonZero(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(B) && x.n, "j:")?;
+				Type::new(B, false, x.o, true, true, x.u)
+			},
+			Miniscript::ZeroNotEqual(ref x) => {
+				let x = x.type_check()?;
+				require(x.is(B), "n:")?;
+				Type::new(B, x.z, x.o, x.n, x.d, true)
+			},
+		};
+
+		Ok(ty)
+	}
+
+	/// Encodes miniscript into script. Miniscript should be type checked before.
+	pub fn to_script(&self) -> Script {
+		let mut encoder = Encoder::default();
+		self.encode(&mut encoder);
+		encoder.into_script()
+	}
+
+	fn encode(&self, e: &mut Encoder) {
+		match *self {
+			Miniscript::False => e.push_opcode(Opcode::OP_0),
+			Miniscript::True => e.push_opcode(Opcode::OP_1),
+			Miniscript::PkK(ref key) => e.push_data(key),
+			Miniscript::PkH(ref key) => e.push_key_hash(&key.address_hash()),
+			Miniscript::RawPkH(ref hash) => e.push_key_hash(hash),
+			Miniscript::Older(n) => {
+				e.push_int(n as usize);
+				e.push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY);
+			},
+			Miniscript::After(n) => {
+				e.push_int(n as usize);
+				e.push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY);
+			},
+			Miniscript::Sha256(ref hash) => e.push_hash_check(Opcode::OP_SHA256, &**hash),
+			Miniscript::Hash256(ref hash) => e.push_hash_check(Opcode::OP_HASH256, &**hash),
+			Miniscript::Ripemd160(ref hash) => e.push_hash_check(Opcode::OP_RIPEMD160, &**hash),
+			Miniscript::Hash160(ref hash) => e.push_hash_check(Opcode::OP_HASH160, &**hash),
+			Miniscript::AndOr(ref x, ref y, ref z) => {
+				x.encode(e);
+				e.push_opcode(Opcode::OP_NOTIF);
+				z.encode(e);
+				e.push_opcode(Opcode::OP_ELSE);
+				y.encode(e);
+				e.push_opcode(Opcode::OP_ENDIF);
+			},
+			Miniscript::AndV(ref x, ref y) => {
+				x.encode(e);
+				y.encode(e);
+			},
+			Miniscript::AndB(ref x, ref y) => {
+				x.encode(e);
+				y.encode(e);
+				e.push_opcode(Opcode::OP_BOOLAND);
+			},
+			Miniscript::OrB(ref x, ref z) => {
+				x.encode(e);
+				z.encode(e);
+				e.push_opcode(Opcode::OP_BOOLOR);
+			},
+			Miniscript::OrC(ref x, ref z) => {
+				x.encode(e);
+				e.push_opcode(Opcode::OP_NOTIF);
+				z.encode(e);
+				e.push_opcode(Opcode::OP_ENDIF);
+			},
+			Miniscript::OrD(ref x, ref z) => {
+				x.encode(e);
+				e.push_opcode(Opcode::OP_IFDUP);
+				e.push_opcode(Opcode::OP_NOTIF);
+				z.encode(e);
+				e.push_opcode(Opcode::OP_ENDIF);
+			},
+			Miniscript::OrI(ref x, ref z) => {
+				e.push_opcode(Opcode::OP_IF);
+				x.encode(e);
+				e.push_opcode(Opcode::OP_ELSE);
+				z.encode(e);
+				e.push_opcode(Opcode::OP_ENDIF);
+			},
+			Miniscript::Thresh(k, ref subs) => {
+				for (index, sub) in subs.iter().enumerate() {
+					sub.encode(e);
+					if index != 0 {
+						e.push_opcode(Opcode::OP_ADD);
+					}
+				}
+				e.push_int(k);
+				e.push_opcode(Opcode::OP_EQUAL);
+			},
+			Miniscript::Multi(k, ref keys) => {
+				e.push_int(k);
+				for key in keys {
+					e.push_data(key);
+				}
+				e.push_int(keys.len());
+				e.push_opcode(Opcode::OP_CHECKMULTISIG);
+			},
+			Miniscript::Alt(ref x) => {
+				e.push_opcode(Opcode::OP_TOALTSTACK);
+				x.encode(e);
+				e.push_opcode(Opcode::OP_FROMALTSTACK);
+			},
+			Miniscript::Swap(ref x) => {
+				e.push_opcode(Opcode::OP_SWAP);
+				x.encode(e);
+			},
+			Miniscript::Check(ref x) => {
+				x.encode(e);
+				e.push_opcode(Opcode::OP_CHECKSIG);
+			},
+			Miniscript::DupIf(ref x) => {
+				e.push_opcode(Opcode::OP_DUP);
+				e.push_opcode(Opcode::OP_IF);
+				x.encode(e);
+				e.push_opcode(Opcode::OP_ENDIF);
+			},
+			Miniscript::Verify(ref x) => {
+				x.encode(e);
+				e.push_verify();
+			},
+			Miniscript::NonZero(ref x) => {
+				e.push_opcode(Opcode::OP_SIZE);
+				e.push_opcode(Opcode::OP_0NOTEQUAL);
+				e.push_opcode(Opcode::OP_IF);
+				x.encode(e);
+				e.push_opcode(Opcode::OP_ENDIF);
+			},
+			Miniscript::ZeroNotEqual(ref x) => {
+				x.encode(e);
+				e.push_opcode(Opcode::OP_0NOTEQUAL);
+			},
+		}
+	}
+
+	fn from_expression(expression: &Expression) -> Result<Self, MiniscriptError> {
+		// wrappers are separated from the fragment name by colon, e.g. `sdv:older(144)`
+		if let Some(colon) = expression.name.find(':') {
+			let wrappers = &expression.name[..colon];
+			let inner = Expression {
+				name: &expression.name[colon + 1..],
+				args: expression.args.clone(),
+			};
+			let mut result = Miniscript::from_expression(&inner)?;
+			for wrapper in wrappers.chars().rev() {
+				result = match wrapper {
+					'a' => Miniscript::Alt(Box::new(result)),
+					's' => Miniscript::Swap(Box::new(result)),
+					'c' => Miniscript::Check(Box::new(result)),
+					'd' => Miniscript::DupIf(Box::new(result)),
+					'v' => Miniscript::Verify(Box::new(result)),
+					'j' => Miniscript::NonZero(Box::new(result)),
+					'n' => Miniscript::ZeroNotEqual(Box::new(result)),
+					't' => Miniscript::AndV(Box::new(result), Box::new(Miniscript::True)),
+					'l' => Miniscript::OrI(Box::new(Miniscript::False), Box::new(result)),
+					'u' => Miniscript::OrI(Box::new(result), Box::new(Miniscript::False)),
+					_ => return Err(MiniscriptError::UnknownFragment(expression.name.into())),
+				};
+			}
+			return Ok(result);
+		}
+
+		let args = &expression.args;
+		let result = match expression.name {
+			"0" => {
+				expression.require_args(0)?;
+				Miniscript::False
+			},
+			"1" => {
+				expression.require_args(0)?;
+				Miniscript::True
+			},
+			"pk_k" => Miniscript::PkK(parse_key(expression.terminal_arg()?)?),
+			"pk_h" => Miniscript::PkH(parse_key(expression.terminal_arg()?)?),
+			"pk" => Miniscript::Check(Box::new(Miniscript::PkK(parse_key(expression.terminal_arg()?)?))),
+			"pkh" => Miniscript::Check(Box::new(Miniscript::PkH(parse_key(expression.terminal_arg()?)?))),
+			"expr_raw_pkh" => Miniscript::RawPkH(parse_hash(expression.terminal_arg()?)?),
+			"older" => Miniscript::Older(parse_locktime(expression.terminal_arg()?)?),
+			"after" => Miniscript::After(parse_locktime(expression.terminal_arg()?)?),
+			"sha256" => Miniscript::Sha256(parse_hash(expression.terminal_arg()?)?),
+			"hash256" => Miniscript::Hash256(parse_hash(expression.terminal_arg()?)?),
+			"ripemd160" => Miniscript::Ripemd160(parse_hash(expression.terminal_arg()?)?),
+			"hash160" => Miniscript::Hash160(parse_hash(expression.terminal_arg()?)?),
+			"andor" => {
+				expression.require_args(3)?;
+				Miniscript::AndOr(parse_boxed(&args[0])?, parse_boxed(&args[1])?, parse_boxed(&args[2])?)
+			},
+			"and_n" => {
+				expression.require_args(2)?;
+				Miniscript::AndOr(parse_boxed(&args[0])?, parse_boxed(&args[1])?, Box::new(Miniscript::False))
+			},
+			"and_v" => {
+				expression.require_args(2)?;
+				Miniscript::AndV(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"and_b" => {
+				expression.require_args(2)?;
+				Miniscript::AndB(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"or_b" => {
+				expression.require_args(2)?;
+				Miniscript::OrB(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"or_c" => {
+				expression.require_args(2)?;
+				Miniscript::OrC(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"or_d" => {
+				expression.require_args(2)?;
+				Miniscript::OrD(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"or_i" => {
+				expression.require_args(2)?;
+				Miniscript::OrI(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"thresh" => {
+				let (k, subs) = threshold_args(expression)?;
+				let subs = subs.iter().map(Miniscript::from_expression).collect::<Result<Vec<_>, _>>()?;
+				Miniscript::Thresh(k, subs)
+			},
+			"multi" => {
+				let (k, keys) = threshold_args(expression)?;
+				let keys = keys.iter()
+					.map(|key| if key.args.is_empty() {
+						parse_key(key.name)
+					} else {
+						Err(MiniscriptError::InvalidKey(key.name.into()))
+					})
+					.collect::<Result<Vec<_>, _>>()?;
+				Miniscript::Multi(k, keys)
+			},
+			_ => return Err(MiniscriptError::UnknownFragment(expression.name.into())),
+		};
+
+		Ok(result)
+	}
+
+	/// Splits wrappers from the fragment. `c:pk_k` and `c:pk_h` are not split, since they are
+	/// displayed as `pk` and `pkh`.
+	fn split_wrappers(&self) -> (String, &Miniscript) {
+		let mut wrappers = String::new();
+		let mut node = self;
+		loop {
+			let (wrapper, inner) = match *node {
+				Miniscript::Alt(ref x) => ('a', &**x),
+				Miniscript::Swap(ref x) => ('s', &**x),
+				Miniscript::Check(ref x) => match **x {
+					Miniscript::PkK(_) | Miniscript::PkH(_) => break,
+					_ => ('c', &**x),
+				},
+				Miniscript::DupIf(ref x) => ('d', &**x),
+				Miniscript::Verify(ref x) => ('v', &**x),
+				Miniscript::NonZero(ref x) => ('j', &**x),
+				Miniscript::ZeroNotEqual(ref x) => ('n', &**x),
+				_ => break,
+			};
+			wrappers.push(wrapper);
+			node = inner;
+		}
+		(wrappers, node)
+	}
+}
+
+impl str::FromStr for Miniscript {
+	type Err = MiniscriptError;
+
+	/// Parses and type checks miniscript expression.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let expression = Expression::parse(s)?;
+		let miniscript = Miniscript::from_expression(&expression)?;
+		let ty = miniscript.type_check()?;
+		if !ty.is(BaseType::B) {
+			return Err(MiniscriptError::TypeCheck(format!("top level expression has type {}", ty)));
+		}
+
+		Ok(miniscript)
+	}
+}
+
+impl fmt::Display for Miniscript {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (wrappers, node) = self.split_wrappers();
+		if !wrappers.is_empty() {
+			write!(f, "{}:", wrappers)?;
+		}
+
+		match *node {
+			Miniscript::False => f.write_str("0"),
+			Miniscript::True => f.write_str("1"),
+			Miniscript::PkK(ref key) => write!(f, "pk_k({})", key),
+			Miniscript::PkH(ref key) => write!(f, "pk_h({})", key),
+			Miniscript::RawPkH(ref hash) => write!(f, "expr_raw_pkh({})", hash),
+			Miniscript::Older(n) => write!(f, "older({})", n),
+			Miniscript::After(n) => write!(f, "after({})", n),
+			Miniscript::Sha256(ref hash) => write!(f, "sha256({})", hash),
+			Miniscript::Hash256(ref hash) => write!(f, "hash256({})", hash),
+			Miniscript::Ripemd160(ref hash) => write!(f, "ripemd160({})", hash),
+			Miniscript::Hash160(ref hash) => write!(f, "hash160({})", hash),
+			Miniscript::AndOr(ref x, ref y, ref z) => write!(f, "andor({},{},{})", x, y, z),
+			Miniscript::AndV(ref x, ref y) => write!(f, "and_v({},{})", x, y),
+			Miniscript::AndB(ref x, ref y) => write!(f, "and_b({},{})", x, y),
+			Miniscript::OrB(ref x, ref z) => write!(f, "or_b({},{})", x, z),
+			Miniscript::OrC(ref x, ref z) => write!(f, "or_c({},{})", x, z),
+			Miniscript::OrD(ref x, ref z) => write!(f, "or_d({},{})", x, z),
+			Miniscript::OrI(ref x, ref z) => write!(f, "or_i({},{})", x, z),
+			Miniscript::Thresh(k, ref subs) => {
+				write!(f, "thresh({}", k)?;
+				for sub in subs {
+					write!(f, ",{}", sub)?;
+				}
+				f.write_str(")")
+			},
+			Miniscript::Multi(k, ref keys) => {
+				write!(f, "multi({}", k)?;
+				for key in keys {
+					write!(f, ",{}", key)?;
+				}
+				f.write_str(")")
+			},
+			Miniscript::Check(ref x) => match **x {
+				Miniscript::PkK(ref key) => write!(f, "pk({})", key),
+				Miniscript::PkH(ref key) => write!(f, "pkh({})", key),
+				_ => unreachable!("other c: wrappers are split by split_wrappers; qed"),
+			},
+			Miniscript::Alt(_) | Miniscript::Swap(_) | Miniscript::DupIf(_) | Miniscript::Verify(_) |
+			Miniscript::NonZero(_) | Miniscript::ZeroNotEqual(_) => unreachable!("wrappers are split by split_wrappers; qed"),
+		}
+	}
+}
+
+/// Script builder, which remembers position of the last opcode to merge it with OP_VERIFY.
+#[derive(Default)]
+struct Encoder {
+	data: Vec<u8>,
+	last_opcode: Option<usize>,
+}
+
+impl Encoder {
+	fn push_opcode(&mut self, opcode: Opcode) {
+		self.last_opcode = Some(self.data.len());
+		self.data.push(opcode as u8);
+	}
+
+	fn push_data(&mut self, data: &[u8]) {
+		self.last_opcode = None;
+		self.data.extend_from_slice(&Builder::default().push_data(data).into_bytes());
+	}
+
+	/// Pushes number, using OP_1..OP_16 for small numbers.
+	fn push_int(&mut self, n: usize) {
+		match n {
+			0 => self.push_opcode(Opcode::OP_0),
+			1...16 => self.push_opcode(Opcode::from_u8(Opcode::OP_1 as u8 + n as u8 - 1)
+				.expect("OP_1..OP_16 are sequential opcodes; qed")),
+			_ => self.push_data(&Num::from(n).to_bytes()),
+		}
+	}
+
+	fn push_key_hash(&mut self, hash: &AddressHash) {
+		self.push_opcode(Opcode::OP_DUP);
+		self.push_opcode(Opcode::OP_HASH160);
+		self.push_data(&**hash);
+		self.push_opcode(Opcode::OP_EQUALVERIFY);
+	}
+
+	fn push_hash_check(&mut self, opcode: Opcode, hash: &[u8]) {
+		self.push_opcode(Opcode::OP_SIZE);
+		self.push_int(32);
+		self.push_opcode(Opcode::OP_EQUALVERIFY);
+		self.push_opcode(opcode);
+		self.push_data(hash);
+		self.push_opcode(Opcode::OP_EQUAL);
+	}
+
+	/// Pushes OP_VERIFY, or replaces the last opcode with its VERIFY version.
+	fn push_verify(&mut self) {
+		let verify_opcode = match self.last_opcode {
+			Some(position) if position + 1 == self.data.len() => match Opcode::from_u8(self.data[position]) {
+				Some(Opcode::OP_EQUAL) => Some(Opcode::OP_EQUALVERIFY),
+				Some(Opcode::OP_NUMEQUAL) => Some(Opcode::OP_NUMEQUALVERIFY),
+				Some(Opcode::OP_CHECKSIG) => Some(Opcode::OP_CHECKSIGVERIFY),
+				Some(Opcode::OP_CHECKMULTISIG) => Some(Opcode::OP_CHECKMULTISIGVERIFY),
+				_ => None,
+			},
+			_ => None,
+		};
+
+		match verify_opcode {
+			Some(opcode) => {
+				self.data.pop();
+				self.push_opcode(opcode);
+			},
+			None => self.push_opcode(Opcode::OP_VERIFY),
+		}
+	}
+
+	fn into_script(self) -> Script {
+		Script::new(self.data.into())
+	}
+}
+
+fn require(condition: bool, fragment: &str) -> Result<(), MiniscriptError> {
+	if !condition {
+		return Err(MiniscriptError::TypeCheck(format!("arguments of {} have invalid types", fragment)));
+	}
+
+	Ok(())
+}
+
+fn check_threshold(k: usize, n: usize, max_n: usize) -> Result<(), MiniscriptError> {
+	if k == 0 || k > n || n > max_n {
+		return Err(MiniscriptError::InvalidThreshold(k, n));
+	}
+
+	Ok(())
+}
+
+fn parse_boxed(expression: &Expression) -> Result<Box<Miniscript>, MiniscriptError> {
+	Miniscript::from_expression(expression).map(Box::new)
+}
+
+fn threshold_args<'a, 'b>(expression: &'b Expression<'a>) -> Result<(usize, &'b [Expression<'a>]), MiniscriptError> {
+	let k = match expression.args.first() {
+		Some(k) if k.args.is_empty() => k.name.parse().map_err(|_| MiniscriptError::InvalidNumber(k.name.into()))?,
+		_ => return Err(MiniscriptError::InvalidArgumentsCount(expression.name.into())),
+	};
+
+	Ok((k, &expression.args[1..]))
+}
+
+fn parse_key(s: &str) -> Result<Public, MiniscriptError> {
+	let bytes: ::bytes::Bytes = s.parse().map_err(|_| MiniscriptError::InvalidKey(s.into()))?;
+	Public::from_slice(&bytes).map_err(|_| MiniscriptError::InvalidKey(s.into()))
+}
+
+fn parse_hash<T: str::FromStr>(s: &str) -> Result<T, MiniscriptError> {
+	s.parse().map_err(|_| MiniscriptError::InvalidHash(s.into()))
+}
+
+/// Parses `older` and `after` argument, which should be in 1..2^31 range.
+fn parse_locktime(s: &str) -> Result<u32, MiniscriptError> {
+	match s.parse::<u32>() {
+		Ok(n) if n >= 1 && n < 0x8000_0000 => Ok(n),
+		_ => Err(MiniscriptError::InvalidNumber(s.into())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+	use bytes::Bytes;
+	use super::{Miniscript, MiniscriptError};
+
+	const KEY1: &'static str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+	const KEY2: &'static str = "03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb";
+	const KEY3: &'static str = "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13";
+
+	fn miniscript(s: &str) -> Miniscript {
+		Miniscript::from_str(&s.replace("K1", KEY1).replace("K2", KEY2).replace("K3", KEY3)).unwrap()
+	}
+
+	fn assert_miniscript(s: &str, ty: &str, script: &str) {
+		let ms = miniscript(s);
+		assert_eq!(ms.type_check().unwrap().to_string(), ty);
+		let script_bytes: Bytes = script.replace("K1", KEY1).replace("K2", KEY2).replace("K3", KEY3).parse().unwrap();
+		assert_eq!(ms.to_script().to_bytes(), script_bytes);
+		// display is the inverse of parsing
+		assert_eq!(miniscript(&ms.to_string()), ms);
+	}
+
+	#[test]
+	fn test_miniscript_encoding() {
+		// pk(K1): <K1> OP_CHECKSIG
+		assert_miniscript("pk(K1)", "Bondu", "21K1ac");
+		// pkh(K1): OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+		assert_miniscript("pkh(K1)", "Bndu", "76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac");
+		// older(144): <144> OP_CHECKSEQUENCEVERIFY
+		assert_miniscript("older(144)", "Bz", "029000b2");
+		// or_d(pk(K1),and_v(v:pk(K2),older(10))): <K1> OP_CHECKSIG OP_IFDUP OP_NOTIF <K2> OP_CHECKSIGVERIFY OP_10 OP_CHECKSEQUENCEVERIFY OP_ENDIF
+		assert_miniscript("or_d(pk(K1),and_v(v:pk(K2),older(10)))", "B", "21K1ac736421K2ad5ab268");
+		// multi(2,K1,K2,K3): OP_2 <K1> <K2> <K3> OP_3 OP_CHECKMULTISIG
+		assert_miniscript("multi(2,K1,K2,K3)", "Bndu", "5221K121K221K353ae");
+		// thresh(2,pk(K1),s:pk(K2),sln:older(12)): ... OP_ADD ... OP_ADD OP_2 OP_EQUAL
+		assert_miniscript("thresh(2,pk(K1),s:pk(K2),sln:older(12))", "Bdu", "21K1ac7c21K2ac937c6300675cb29268935287");
+		// sha256(h): OP_SIZE <32> OP_EQUALVERIFY OP_SHA256 <h> OP_EQUAL
+		assert_miniscript(
+			"sha256(926a54995ca48600920a19bf7bc502ca5f2f7d07e6f804c4f00ebf0325084dbc)",
+			"Bondu",
+			"82012088a820926a54995ca48600920a19bf7bc502ca5f2f7d07e6f804c4f00ebf0325084dbc87"
+		);
+		// v:sha256 merges OP_EQUAL into OP_EQUALVERIFY
+		assert_miniscript(
+			"and_v(v:sha256(926a54995ca48600920a19bf7bc502ca5f2f7d07e6f804c4f00ebf0325084dbc),pk(K1))",
+			"Bnu",
+			"82012088a820926a54995ca48600920a19bf7bc502ca5f2f7d07e6f804c4f00ebf0325084dbc8821K1ac"
+		);
+	}
+
+	#[test]
+	fn test_miniscript_sugar() {
+		assert_eq!(miniscript("and_n(pk(K1),older(10))"), miniscript("andor(pk(K1),older(10),0)"));
+		assert_eq!(miniscript("t:or_c(pk(K1),v:pk(K2))"), miniscript("and_v(or_c(pk(K1),v:pk(K2)),1)"));
+		assert_eq!(miniscript("or_d(pk(K1),l:pk(K2))"), miniscript("or_d(pk(K1),or_i(0,pk(K2)))"));
+		assert_eq!(miniscript("or_d(pk(K1),u:pk(K2))"), miniscript("or_d(pk(K1),or_i(pk(K2),0))"));
+		assert_eq!(miniscript("c:pk_k(K1)"), miniscript("pk(K1)"));
+	}
+
+	#[test]
+	fn test_miniscript_errors() {
+		assert_eq!(Miniscript::from_str("older(0)"), Err(MiniscriptError::InvalidNumber("0".into())));
+		assert_eq!(Miniscript::from_str("pk(00)"), Err(MiniscriptError::InvalidKey("00".into())));
+		assert_eq!(Miniscript::from_str("unknown(1)"), Err(MiniscriptError::UnknownFragment("unknown".into())));
+		assert_eq!(Miniscript::from_str("x:older(1)"), Err(MiniscriptError::UnknownFragment("x:older".into())));
+		assert_eq!(Miniscript::from_str("thresh(3,older(1),older(2))"), Err(MiniscriptError::InvalidThreshold(3, 2)));
+		// pk_k is K, while top level expression should be B
+		assert_eq!(
+			Miniscript::from_str(&format!("pk_k({})", KEY1)),
+			Err(MiniscriptError::TypeCheck("top level expression has type Kondu".into()))
+		);
+		// older(10) isn't dissatisfiable, so it can't be the first argument of or_d
+		assert_eq!(
+			Miniscript::from_str(&format!("or_d(older(10),pk({}))", KEY1)),
+			Err(MiniscriptError::TypeCheck("arguments of or_d have invalid types".into()))
+		);
+	}
+}
@@ -0,0 +1,244 @@
+//! Spending policy language and its compilation into miniscript.
+
+use std::str;
+use hash::{H160, H256};
+use keys::Public;
+use super::{Miniscript, MiniscriptError, BaseType, MAX_MULTI_KEYS, check_threshold, threshold_args, parse_key, parse_hash, parse_locktime};
+use super::expression::Expression;
+
+/// Spending policy, e.g. `or(9@pk(A),and(pk(B),older(144)))`.
+#[derive(Debug, PartialEq)]
+pub enum Policy {
+	/// `pk(key)`
+	Key(Public),
+	/// `after(n)`
+	After(u32),
+	/// `older(n)`
+	Older(u32),
+	/// `sha256(h)`
+	Sha256(H256),
+	/// `hash256(h)`
+	Hash256(H256),
+	/// `ripemd160(h)`
+	Ripemd160(H160),
+	/// `hash160(h)`
+	Hash160(H160),
+	/// `and(X,Y)`
+	And(Box<Policy>, Box<Policy>),
+	/// `or(N@X,M@Y)`: branches with their relative probabilities
+	Or(usize, Box<Policy>, usize, Box<Policy>),
+	/// `thresh(k,X1,...,Xn)`
+	Thresh(usize, Vec<Policy>),
+}
+
+impl Policy {
+	/// Compiles policy into miniscript.
+	///
+	/// The compiler doesn't search for the cheapest satisfaction: it uses the fixed set of fragments,
+	/// preferring the more probable branch of `or` to be the cheaper one.
+	pub fn compile(self) -> Result<Miniscript, MiniscriptError> {
+		let miniscript = self.compile_node()?;
+		let ty = miniscript.type_check()?;
+		if ty.base != BaseType::B {
+			return Err(MiniscriptError::Uncompilable(format!("compiled expression has type {}", ty)));
+		}
+
+		Ok(miniscript)
+	}
+
+	fn compile_node(self) -> Result<Miniscript, MiniscriptError> {
+		let result = match self {
+			Policy::Key(key) => Miniscript::Check(Box::new(Miniscript::PkK(key))),
+			Policy::After(n) => Miniscript::After(n),
+			Policy::Older(n) => Miniscript::Older(n),
+			Policy::Sha256(hash) => Miniscript::Sha256(hash),
+			Policy::Hash256(hash) => Miniscript::Hash256(hash),
+			Policy::Ripemd160(hash) => Miniscript::Ripemd160(hash),
+			Policy::Hash160(hash) => Miniscript::Hash160(hash),
+			Policy::And(x, y) => and(x.compile_node()?, y.compile_node()?),
+			Policy::Or(x_prob, x, y_prob, y) => {
+				let (x, y) = (x.compile_node()?, y.compile_node()?);
+				// the first branch of or_d and or_i is the cheapest to satisfy
+				let (first, second) = if x_prob >= y_prob { (x, y) } else { (y, x) };
+				if is_bdu(&first)? {
+					Miniscript::OrD(Box::new(first), Box::new(second))
+				} else if is_bdu(&second)? {
+					Miniscript::OrD(Box::new(second), Box::new(first))
+				} else {
+					Miniscript::OrI(Box::new(first), Box::new(second))
+				}
+			},
+			Policy::Thresh(k, subs) => {
+				check_threshold(k, subs.len(), usize::max_value())?;
+				let all_keys = subs.iter().all(|sub| match *sub {
+					Policy::Key(_) => true,
+					_ => false,
+				});
+
+				if all_keys && subs.len() <= MAX_MULTI_KEYS {
+					let keys = subs.into_iter().map(|sub| match sub {
+						Policy::Key(key) => key,
+						_ => unreachable!("all subs are keys; qed"),
+					}).collect();
+					Miniscript::Multi(k, keys)
+				} else if k == subs.len() {
+					let mut subs = subs.into_iter().map(Policy::compile_node).collect::<Result<Vec<_>, _>>()?;
+					let mut result = subs.pop().expect("check_threshold ensures that there is at least one sub; qed");
+					while let Some(sub) = subs.pop() {
+						result = and(sub, result);
+					}
+					result
+				} else {
+					let mut compiled = Vec::with_capacity(subs.len());
+					for (index, sub) in subs.into_iter().enumerate() {
+						let sub = make_bdu(sub.compile_node()?)?;
+						if index == 0 {
+							compiled.push(sub);
+						} else if sub.type_check()?.o {
+							compiled.push(Miniscript::Swap(Box::new(sub)));
+						} else {
+							compiled.push(Miniscript::Alt(Box::new(sub)));
+						}
+					}
+					Miniscript::Thresh(k, compiled)
+				}
+			},
+		};
+
+		Ok(result)
+	}
+
+	fn from_expression(expression: &Expression) -> Result<Self, MiniscriptError> {
+		let args = &expression.args;
+		let result = match expression.name {
+			"pk" => Policy::Key(parse_key(expression.terminal_arg()?)?),
+			"after" => Policy::After(parse_locktime(expression.terminal_arg()?)?),
+			"older" => Policy::Older(parse_locktime(expression.terminal_arg()?)?),
+			"sha256" => Policy::Sha256(parse_hash(expression.terminal_arg()?)?),
+			"hash256" => Policy::Hash256(parse_hash(expression.terminal_arg()?)?),
+			"ripemd160" => Policy::Ripemd160(parse_hash(expression.terminal_arg()?)?),
+			"hash160" => Policy::Hash160(parse_hash(expression.terminal_arg()?)?),
+			"and" => {
+				expression.require_args(2)?;
+				Policy::And(parse_boxed(&args[0])?, parse_boxed(&args[1])?)
+			},
+			"or" => {
+				expression.require_args(2)?;
+				let (x_prob, x) = parse_branch(&args[0])?;
+				let (y_prob, y) = parse_branch(&args[1])?;
+				Policy::Or(x_prob, x, y_prob, y)
+			},
+			"thresh" => {
+				let (k, subs) = threshold_args(expression)?;
+				let subs = subs.iter().map(Policy::from_expression).collect::<Result<Vec<_>, _>>()?;
+				check_threshold(k, subs.len(), usize::max_value())?;
+				Policy::Thresh(k, subs)
+			},
+			_ => return Err(MiniscriptError::UnknownFragment(expression.name.into())),
+		};
+
+		Ok(result)
+	}
+}
+
+impl str::FromStr for Policy {
+	type Err = MiniscriptError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let expression = Expression::parse(s)?;
+		Policy::from_expression(&expression)
+	}
+}
+
+fn parse_boxed(expression: &Expression) -> Result<Box<Policy>, MiniscriptError> {
+	Policy::from_expression(expression).map(Box::new)
+}
+
+/// Parses `or` branch with optional `N@` probability prefix.
+fn parse_branch(expression: &Expression) -> Result<(usize, Box<Policy>), MiniscriptError> {
+	match expression.name.find('@') {
+		Some(at) => {
+			let probability = &expression.name[..at];
+			let probability = match probability.parse::<usize>() {
+				Ok(probability) if probability != 0 => probability,
+				_ => return Err(MiniscriptError::InvalidNumber(probability.into())),
+			};
+			let inner = Expression {
+				name: &expression.name[at + 1..],
+				args: expression.args.clone(),
+			};
+			Ok((probability, parse_boxed(&inner)?))
+		},
+		None => Ok((1, parse_boxed(expression)?)),
+	}
+}
+
+fn is_bdu(miniscript: &Miniscript) -> Result<bool, MiniscriptError> {
+	let ty = miniscript.type_check()?;
+	Ok(ty.base == BaseType::B && ty.d && ty.u)
+}
+
+/// `and_v(v:X,Y)`
+fn and(x: Miniscript, y: Miniscript) -> Miniscript {
+	Miniscript::AndV(Box::new(Miniscript::Verify(Box::new(x))), Box::new(y))
+}
+
+/// Wraps B expression, so that it becomes dissatisfiable unit, as required by `thresh` arguments.
+fn make_bdu(miniscript: Miniscript) -> Result<Miniscript, MiniscriptError> {
+	let ty = miniscript.type_check()?;
+	let miniscript = if ty.d {
+		miniscript
+	} else if ty.z {
+		Miniscript::DupIf(Box::new(Miniscript::Verify(Box::new(miniscript))))
+	} else if ty.n {
+		Miniscript::NonZero(Box::new(miniscript))
+	} else {
+		return Err(MiniscriptError::Uncompilable(format!("{} can't be made dissatisfiable", miniscript)));
+	};
+
+	if miniscript.type_check()?.u {
+		Ok(miniscript)
+	} else {
+		Ok(Miniscript::ZeroNotEqual(Box::new(miniscript)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+	use {Miniscript, MiniscriptError};
+	use super::Policy;
+
+	const KEY1: &'static str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+	const KEY2: &'static str = "03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb";
+	const KEY3: &'static str = "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13";
+
+	fn with_keys(s: &str) -> String {
+		s.replace("K1", KEY1).replace("K2", KEY2).replace("K3", KEY3)
+	}
+
+	fn assert_compiles(policy: &str, miniscript: &str) {
+		let compiled = Policy::from_str(&with_keys(policy)).unwrap().compile().unwrap();
+		assert_eq!(compiled, Miniscript::from_str(&with_keys(miniscript)).unwrap());
+	}
+
+	#[test]
+	fn test_policy_compile() {
+		assert_compiles("pk(K1)", "pk(K1)");
+		assert_compiles("and(pk(K1),older(144))", "and_v(v:pk(K1),older(144))");
+		assert_compiles("or(pk(K1),pk(K2))", "or_d(pk(K1),pk(K2))");
+		assert_compiles("or(1@pk(K1),9@and(pk(K2),older(144)))", "or_d(pk(K1),and_v(v:pk(K2),older(144)))");
+		assert_compiles("or(older(10),after(500000))", "or_i(older(10),after(500000))");
+		assert_compiles("thresh(2,pk(K1),pk(K2),pk(K3))", "multi(2,K1,K2,K3)");
+		assert_compiles("thresh(2,pk(K1),pk(K2),older(144))", "thresh(2,pk(K1),s:pk(K2),snd:v:older(144))");
+		assert_compiles("thresh(2,pk(K1),older(144))", "and_v(v:pk(K1),older(144))");
+	}
+
+	#[test]
+	fn test_policy_errors() {
+		assert_eq!(Policy::from_str("or(0@older(1),older(2))"), Err(MiniscriptError::InvalidNumber("0".into())));
+		assert_eq!(Policy::from_str("and(older(1))"), Err(MiniscriptError::InvalidArgumentsCount("and".into())));
+		assert_eq!(Policy::from_str("thresh(0,older(1))"), Err(MiniscriptError::InvalidThreshold(0, 1)));
+		assert_eq!(Policy::from_str("pk_k(00)"), Err(MiniscriptError::UnknownFragment("pk_k".into())));
+	}
+}
@@ -212,6 +212,9 @@ pub enum Opcode {
 	OP_NOP8 = 0xb7,
 	OP_NOP9 = 0xb8,
 	OP_NOP10 = 0xb9,
+
+	// tapscript (BIP342)
+	OP_CHECKSIGADD = 0xba,
 }
 
 impl fmt::Display for Opcode {
@@ -429,6 +432,7 @@ impl Opcode {
 			0xb7 => Some(OP_NOP8),
 			0xb8 => Some(OP_NOP9),
 			0xb9 => Some(OP_NOP10),
+			0xba => Some(OP_CHECKSIGADD),
 			_ => None,
 		}
 	}
@@ -443,6 +447,14 @@ impl Opcode {
 		}
 	}
 
+	/// Returns true if byte is one of OP_SUCCESSx opcodes, which make tapscript unconditionally valid (BIP342).
+	pub fn is_success(byte: u8) -> bool {
+		match byte {
+			80 | 98 | 126...129 | 131...134 | 137...138 | 141...142 | 149...153 | 187...254 => true,
+			_ => false,
+		}
+	}
+
 	/// Returns true if opcode is countable
 	pub fn is_countable(&self) -> bool {
 		*self > Opcode::OP_16
@@ -679,5 +691,19 @@ mod tests {
 		assert_eq!(Opcode::OP_NOP8, Opcode::from_u8(Opcode::OP_NOP8 as u8).unwrap());
 		assert_eq!(Opcode::OP_NOP9, Opcode::from_u8(Opcode::OP_NOP9 as u8).unwrap());
 		assert_eq!(Opcode::OP_NOP10, Opcode::from_u8(Opcode::OP_NOP10 as u8).unwrap());
+
+		// tapscript
+		assert_eq!(Opcode::OP_CHECKSIGADD, Opcode::from_u8(Opcode::OP_CHECKSIGADD as u8).unwrap());
+	}
+
+	#[test]
+	fn test_is_success() {
+		assert!(Opcode::is_success(Opcode::OP_RESERVED as u8));
+		assert!(Opcode::is_success(Opcode::OP_CAT as u8));
+		assert!(Opcode::is_success(0xbb));
+		assert!(Opcode::is_success(0xfe));
+		assert!(!Opcode::is_success(Opcode::OP_CHECKSIGADD as u8));
+		assert!(!Opcode::is_success(Opcode::OP_VERIF as u8));
+		assert!(!Opcode::is_success(0xff));
 	}
 }
@@ -17,6 +17,9 @@ pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
 /// Maximum script length in bytes
 pub const MAX_SCRIPT_SIZE: usize = 10000;
 
+/// Maximum number of elements on the stack and altstack
+pub const MAX_STACK_SIZE: usize = 1000;
+
 /// Classified script type
 #[derive(PartialEq, Debug)]
 pub enum ScriptType {
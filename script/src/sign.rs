@@ -2,9 +2,9 @@
 
 use bytes::Bytes;
 use keys::KeyPair;
-use crypto::dhash256;
+use crypto::{dhash256, sha256, tagged_hash};
 use hash::H256;
-use ser::Stream;
+use ser::{Stream, serialize};
 use chain::{Transaction, TransactionOutput, OutPoint, TransactionInput};
 use {Script, Builder};
 
@@ -13,6 +13,37 @@ pub enum SignatureVersion {
 	Base,
 	WitnessV0,
 	ForkId,
+	/// Taproot key path spending (BIP341)
+	Taproot,
+	/// Taproot script path spending (BIP342)
+	Tapscript,
+}
+
+/// Code separator position, used when no OP_CODESEPARATOR has been executed.
+pub const NO_CODESEPARATOR_POSITION: u32 = 0xffff_ffff;
+
+/// Taproot spending data, committed to by the signature hash in addition to the transaction (BIP341, BIP342).
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScriptExecutionData {
+	/// SHA256 of the serialized annex, if annex is present.
+	pub annex_hash: Option<H256>,
+	/// Hash of the executed tapscript leaf.
+	pub tapleaf_hash: Option<H256>,
+	/// Opcode position of the last executed OP_CODESEPARATOR.
+	pub codeseparator_position: u32,
+	/// Remaining signature validation weight budget of the tapscript.
+	pub validation_weight_left: i64,
+}
+
+impl Default for ScriptExecutionData {
+	fn default() -> Self {
+		ScriptExecutionData {
+			annex_hash: None,
+			tapleaf_hash: None,
+			codeseparator_position: NO_CODESEPARATOR_POSITION,
+			validation_weight_left: 0,
+		}
+	}
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -109,12 +140,55 @@ impl From<TransactionInput> for UnsignedTransactionInput {
 	}
 }
 
+/// BIP341 hashes of the transaction data. They are the same for signature hashes of all inputs,
+/// so they're computed once per transaction.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TaprootMidstates {
+	pub sha_prevouts: H256,
+	pub sha_amounts: H256,
+	pub sha_script_pubkeys: H256,
+	pub sha_sequences: H256,
+	pub sha_outputs: H256,
+}
+
+impl TaprootMidstates {
+	pub fn new(inputs: &[UnsignedTransactionInput], outputs: &[TransactionOutput], spent_outputs: &[TransactionOutput]) -> Self {
+		let mut prevouts = Stream::default();
+		let mut amounts = Stream::default();
+		let mut script_pubkeys = Stream::default();
+		let mut sequences = Stream::default();
+		for (input, spent_output) in inputs.iter().zip(spent_outputs) {
+			prevouts.append(&input.previous_output);
+			amounts.append(&spent_output.value);
+			script_pubkeys.append(&spent_output.script_pubkey);
+			sequences.append(&input.sequence);
+		}
+
+		let mut outputs_stream = Stream::default();
+		for output in outputs {
+			outputs_stream.append(output);
+		}
+
+		TaprootMidstates {
+			sha_prevouts: sha256(&prevouts.out()),
+			sha_amounts: sha256(&amounts.out()),
+			sha_script_pubkeys: sha256(&script_pubkeys.out()),
+			sha_sequences: sha256(&sequences.out()),
+			sha_outputs: sha256(&outputs_stream.out()),
+		}
+	}
+}
+
 #[derive(Debug)]
 pub struct TransactionInputSigner {
 	pub version: i32,
 	pub inputs: Vec<UnsignedTransactionInput>,
 	pub outputs: Vec<TransactionOutput>,
 	pub lock_time: u32,
+	/// Outputs, spent by every input of the transaction. Only required to verify Taproot inputs.
+	pub spent_outputs: Vec<TransactionOutput>,
+	/// BIP341 hashes, computed when spent outputs are set.
+	pub taproot_midstates: Option<TaprootMidstates>,
 }
 
 /// Used for resigning and loading test transactions
@@ -125,20 +199,121 @@ impl From<Transaction> for TransactionInputSigner {
 			inputs: t.inputs.into_iter().map(Into::into).collect(),
 			outputs: t.outputs,
 			lock_time: t.lock_time,
+			spent_outputs: Vec::new(),
+			taproot_midstates: None,
 		}
 	}
 }
 
 impl TransactionInputSigner {
+	/// Sets outputs, spent by every input of the transaction, and computes BIP341 hashes of the transaction.
+	pub fn with_spent_outputs(mut self, spent_outputs: Vec<TransactionOutput>) -> Self {
+		self.taproot_midstates = if spent_outputs.len() == self.inputs.len() {
+			Some(TaprootMidstates::new(&self.inputs, &self.outputs, &spent_outputs))
+		} else {
+			None
+		};
+		self.spent_outputs = spent_outputs;
+		self
+	}
+
 	pub fn signature_hash(&self, input_index: usize, input_amount: u64, script_pubkey: &Script, sigversion: SignatureVersion, sighashtype: u32) -> H256 {
 		let sighash = Sighash::from_u32(sigversion, sighashtype);
 		match sigversion {
 			SignatureVersion::ForkId if sighash.fork_id => self.signature_hash_fork_id(input_index, input_amount, script_pubkey, sighashtype, sighash),
 			SignatureVersion::Base | SignatureVersion::ForkId => self.signature_hash_original(input_index, script_pubkey, sighashtype, sighash),
 			SignatureVersion::WitnessV0 => self.signature_hash_witness0(input_index, input_amount, script_pubkey, sighashtype, sighash),
+			// Taproot hash commits to all spent outputs, use signature_hash_taproot
+			SignatureVersion::Taproot | SignatureVersion::Tapscript => 1u8.into(),
 		}
 	}
 
+	/// BIP341 signature hash. Spent outputs must be set using `with_spent_outputs`.
+	/// Returns None if hash type is undefined, or if the hash can't be computed.
+	pub fn signature_hash_taproot(
+		&self,
+		input_index: usize,
+		sighashtype: u32,
+		sigversion: SignatureVersion,
+		execution: &ScriptExecutionData,
+	) -> Option<H256> {
+		let ext_flag = match sigversion {
+			SignatureVersion::Taproot => 0u8,
+			SignatureVersion::Tapscript => 1u8,
+			SignatureVersion::Base | SignatureVersion::WitnessV0 | SignatureVersion::ForkId => return None,
+		};
+
+		match sighashtype {
+			0x00 | 0x01 | 0x02 | 0x03 | 0x81 | 0x82 | 0x83 => (),
+			_ => return None,
+		}
+
+		let midstates = match self.taproot_midstates {
+			Some(ref midstates) if input_index < self.inputs.len() => midstates,
+			_ => return None,
+		};
+
+		// SIGHASH_DEFAULT (0) has the same meaning as SIGHASH_ALL
+		let output_type = if sighashtype == 0 { SighashBase::All as u32 } else { sighashtype & 3 };
+		let anyone_can_pay = sighashtype & 0x80 != 0;
+		let input = &self.inputs[input_index];
+
+		let mut stream = Stream::default();
+		// epoch
+		stream.append(&0u8);
+		stream.append(&(sighashtype as u8));
+		stream.append(&self.version);
+		stream.append(&self.lock_time);
+
+		if !anyone_can_pay {
+			stream.append(&midstates.sha_prevouts);
+			stream.append(&midstates.sha_amounts);
+			stream.append(&midstates.sha_script_pubkeys);
+			stream.append(&midstates.sha_sequences);
+		}
+
+		if output_type == SighashBase::All as u32 {
+			stream.append(&midstates.sha_outputs);
+		}
+
+		let spend_type = ext_flag * 2 + if execution.annex_hash.is_some() { 1 } else { 0 };
+		stream.append(&spend_type);
+
+		if anyone_can_pay {
+			let spent_output = &self.spent_outputs[input_index];
+			stream.append(&input.previous_output);
+			stream.append(&spent_output.value);
+			stream.append(&spent_output.script_pubkey);
+			stream.append(&input.sequence);
+		} else {
+			stream.append(&(input_index as u32));
+		}
+
+		if let Some(ref annex_hash) = execution.annex_hash {
+			stream.append(annex_hash);
+		}
+
+		if output_type == SighashBase::Single as u32 {
+			match self.outputs.get(input_index) {
+				Some(output) => stream.append(&sha256(&serialize(output))),
+				None => return None,
+			};
+		}
+
+		if sigversion == SignatureVersion::Tapscript {
+			let tapleaf_hash = match execution.tapleaf_hash {
+				Some(ref tapleaf_hash) => tapleaf_hash,
+				None => return None,
+			};
+			stream.append(tapleaf_hash);
+			// key version
+			stream.append(&0u8);
+			stream.append(&execution.codeseparator_position);
+		}
+
+		Some(tagged_hash(b"TapSighash", &stream.out()))
+	}
+
 	/// input_index - index of input to sign
 	/// script_pubkey - script_pubkey of input's previous_output pubkey
 	pub fn signed_input(
@@ -318,7 +493,7 @@ mod tests {
 	use keys::{KeyPair, Private, Address};
 	use chain::{OutPoint, TransactionOutput, Transaction};
 	use script::Script;
-	use super::{Sighash, UnsignedTransactionInput, TransactionInputSigner, SighashBase, SignatureVersion};
+	use super::{Sighash, UnsignedTransactionInput, TransactionInputSigner, SighashBase, SignatureVersion, ScriptExecutionData};
 
 	// http://www.righto.com/2014/02/bitcoins-hard-way-using-raw-bitcoin.html
 	// https://blockchain.info/rawtx/81b4c832d70cb56ff957589752eb4125a4cab78a25a8fc52d6a09e5bd4404d48
@@ -358,6 +533,8 @@ mod tests {
 			lock_time: 0,
 			inputs: vec![unsigned_input],
 			outputs: vec![output],
+			spent_outputs: vec![],
+			taproot_midstates: None,
 		};
 
 		let hash = input_signer.signature_hash(0, 0, &previous_output, SignatureVersion::Base, SighashBase::All.into());
@@ -887,6 +1064,52 @@ mod tests {
 		run_test_sighash("fea256ce01272d125e577c0a09570a71366898280dda279b021000db1325f27edda41a53460100000002ab53c752c21c013c2b3a01000000000000000000", "65", 0, 1145543262, "076b9f844f6ae429de228a2c337c704df1652c292b6c6494882190638dad9efd");
 	}
 
+	// https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json (keyPathSpending)
+	#[test]
+	fn test_signature_hash_taproot_bip341() {
+		let tx: Transaction = "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d".into();
+		let spent_outputs = vec![
+			("512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343", 420000000),
+			("5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3", 462000000),
+			("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac", 294000000),
+			("5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e", 504000000),
+			("512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605", 630000000),
+			("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378000000),
+			("512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831", 672000000),
+			("5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5", 546000000),
+			("512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220", 588000000),
+		].into_iter().map(|(script_pubkey, value)| TransactionOutput {
+			value: value,
+			script_pubkey: script_pubkey.into(),
+		}).collect();
+		let signer = TransactionInputSigner::from(tx.clone()).with_spent_outputs(spent_outputs);
+
+		let midstates = signer.taproot_midstates.clone().unwrap();
+		assert_eq!(midstates.sha_amounts, "58a6964a4f5f8f0b642ded0a8a553be7622a719da71d1f5befcefcdee8e0fde6".into());
+		assert_eq!(midstates.sha_outputs, "a2e6dab7c1f0dcd297c8d61647fd17d821541ea69c3cc37dcbad7f90d4eb4bc5".into());
+		assert_eq!(midstates.sha_prevouts, "e3b33bb4ef3a52ad1fffb555c0d82828eb22737036eaeb02a235d82b909c4c3f".into());
+		assert_eq!(midstates.sha_script_pubkeys, "23ad0f61ad2bca5ba6a7693f50fce988e17c3780bf2b1e720cfbb38fbdd52e21".into());
+		assert_eq!(midstates.sha_sequences, "18959c7221ab5ce9e26c3cd67b22c24f8baa54bac281d8e6b05e400e6c3a957e".into());
+
+		let vectors: Vec<(usize, u32, H256)> = vec![
+			(0, 0x03, "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555".into()),
+			(1, 0x83, "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d".into()),
+			(3, 0x01, "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669".into()),
+			(4, 0x00, "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef".into()),
+			(6, 0x02, "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85".into()),
+			(7, 0x82, "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10".into()),
+			(8, 0x81, "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2".into()),
+		];
+		for (input_index, sighashtype, expected) in vectors {
+			let hash = signer.signature_hash_taproot(input_index, sighashtype, SignatureVersion::Taproot, &ScriptExecutionData::default());
+			assert_eq!(hash, Some(expected));
+		}
+
+		// hash can't be computed without spent outputs
+		let signer = TransactionInputSigner::from(tx);
+		assert_eq!(signer.signature_hash_taproot(0, 0, SignatureVersion::Taproot, &ScriptExecutionData::default()), None);
+	}
+
 	#[test]
 	fn test_sighash_forkid_from_u32() {
 		assert!(!Sighash::is_defined(SignatureVersion::Base, 0xFFFFFF82));
@@ -0,0 +1,95 @@
+//! Taproot commitments (BIP341)
+
+use crypto::tagged_hash;
+use keys::{XOnlyPublic, tweak_public};
+use hash::H256;
+use ser::Stream;
+
+/// Leaf version of tapscript (BIP342)
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+/// Mask, extracting leaf version from the first byte of control block
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+/// First byte of the last witness element, identifying it as annex
+pub const ANNEX_TAG: u8 = 0x50;
+/// Size of control block without merkle path: leaf version and internal key
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+/// Size of the merkle path node
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+/// Maximal depth of the script tree
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+/// Validation weight, consumed by every non-empty signature check in tapscript
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+/// Validation weight budget, added to the serialized witness size
+pub const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+/// Hash of the script tree leaf
+pub fn tapleaf_hash(leaf_version: u8, script: &[u8]) -> H256 {
+	let mut stream = Stream::default();
+	stream.append(&leaf_version);
+	stream.append_list(script);
+	tagged_hash(b"TapLeaf", &stream.out())
+}
+
+/// Hash of the script tree branch. Children are sorted, so the order of arguments doesn't matter.
+pub fn tapbranch_hash(a: &H256, b: &H256) -> H256 {
+	let (first, second) = if a < b { (a, b) } else { (b, a) };
+	let mut data = Vec::with_capacity(64);
+	data.extend_from_slice(&**first);
+	data.extend_from_slice(&**second);
+	tagged_hash(b"TapBranch", &data)
+}
+
+/// Tweak of the internal key, committing to the script tree root.
+pub fn taptweak_hash(internal_key: &XOnlyPublic, merkle_root: Option<&H256>) -> H256 {
+	let mut data = Vec::with_capacity(64);
+	data.extend_from_slice(&**internal_key);
+	if let Some(merkle_root) = merkle_root {
+		data.extend_from_slice(&**merkle_root);
+	}
+	tagged_hash(b"TapTweak", &data)
+}
+
+/// Returns true if control block has valid size
+pub fn is_valid_control_block_size(size: usize) -> bool {
+	size >= TAPROOT_CONTROL_BASE_SIZE &&
+		(size - TAPROOT_CONTROL_BASE_SIZE) % TAPROOT_CONTROL_NODE_SIZE == 0 &&
+		(size - TAPROOT_CONTROL_BASE_SIZE) / TAPROOT_CONTROL_NODE_SIZE <= TAPROOT_CONTROL_MAX_NODE_COUNT
+}
+
+/// Verifies that the output key commits to the leaf through the control block merkle path.
+/// Control block size must be valid.
+pub fn verify_taproot_commitment(control: &[u8], output_key: &XOnlyPublic, leaf_hash: &H256) -> bool {
+	let mut internal_key = XOnlyPublic::default();
+	internal_key.copy_from_slice(&control[1..TAPROOT_CONTROL_BASE_SIZE]);
+
+	let merkle_root = control[TAPROOT_CONTROL_BASE_SIZE..]
+		.chunks(TAPROOT_CONTROL_NODE_SIZE)
+		.fold(leaf_hash.clone(), |hash, node| tapbranch_hash(&hash, &H256::from(node)));
+
+	match tweak_public(&internal_key, &taptweak_hash(&internal_key, Some(&merkle_root))) {
+		Ok((tweaked, is_odd)) => &tweaked == output_key && is_odd == (control[0] & 1 == 1),
+		Err(_) => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use super::{tapbranch_hash, is_valid_control_block_size};
+
+	#[test]
+	fn test_tapbranch_hash_is_sorted() {
+		let a = H256::from(1);
+		let b = H256::from(2);
+		assert_eq!(tapbranch_hash(&a, &b), tapbranch_hash(&b, &a));
+	}
+
+	#[test]
+	fn test_control_block_size() {
+		assert!(!is_valid_control_block_size(32));
+		assert!(is_valid_control_block_size(33));
+		assert!(!is_valid_control_block_size(34));
+		assert!(is_valid_control_block_size(33 + 32 * 128));
+		assert!(!is_valid_control_block_size(33 + 32 * 129));
+	}
+}
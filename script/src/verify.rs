@@ -1,9 +1,10 @@
-use keys::{Public, Signature};
+use std::sync::Arc;
+use keys::{Public, Signature, XOnlyPublic, SchnorrSignature, verify_schnorr};
 use chain::constants::{
 	SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG,
 	SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG, LOCKTIME_THRESHOLD
 };
-use sign::SignatureVersion;
+use sign::{SignatureVersion, ScriptExecutionData};
 use {Script, TransactionInputSigner, Num};
 
 /// Checks transaction signature
@@ -17,6 +18,16 @@ pub trait SignatureChecker {
 		version: SignatureVersion
 	) -> bool;
 
+	/// Checks BIP340 Schnorr signature of the Taproot input (BIP341, BIP342)
+	fn check_schnorr_signature(
+		&self,
+		signature: &SchnorrSignature,
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execution: &ScriptExecutionData,
+	) -> bool;
+
 	fn check_lock_time(&self, lock_time: Num) -> bool;
 
 	fn check_sequence(&self, sequence: Num) -> bool;
@@ -29,6 +40,10 @@ impl SignatureChecker for NoopSignatureChecker {
 		false
 	}
 
+	fn check_schnorr_signature(&self, _: &SchnorrSignature, _: &XOnlyPublic, _: u32, _: SignatureVersion, _: &ScriptExecutionData) -> bool {
+		false
+	}
+
 	fn check_lock_time(&self, _: Num) -> bool {
 		false
 	}
@@ -40,7 +55,8 @@ impl SignatureChecker for NoopSignatureChecker {
 
 #[derive(Debug)]
 pub struct TransactionSignatureChecker {
-	pub signer: TransactionInputSigner,
+	/// Signer of the transaction, shared by checkers of all transaction inputs.
+	pub signer: Arc<TransactionInputSigner>,
 	pub input_index: usize,
 	pub input_amount: u64,
 }
@@ -58,6 +74,20 @@ impl SignatureChecker for TransactionSignatureChecker {
 		public.verify(&hash, signature).unwrap_or(false)
 	}
 
+	fn check_schnorr_signature(
+		&self,
+		signature: &SchnorrSignature,
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execution: &ScriptExecutionData,
+	) -> bool {
+		match self.signer.signature_hash_taproot(self.input_index, sighashtype, version, execution) {
+			Some(hash) => verify_schnorr(public, &hash, signature).unwrap_or(false),
+			None => false,
+		}
+	}
+
 	fn check_lock_time(&self, lock_time: Num) -> bool {
 		// There are two kinds of nLockTime: lock-by-blockheight
 		// and lock-by-blocktime, distinguished by whether
@@ -94,13 +94,17 @@ impl Serializable for CompactInteger {
 
 impl Deserializable for CompactInteger {
 	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
-		let result = match try!(reader.read::<u8>()) {
-			i @ 0...0xfc => i.into(),
-			0xfd => try!(reader.read::<u16>()).into(),
-			0xfe => try!(reader.read::<u32>()).into(),
-			_ => try!(reader.read::<u64>()).into(),
+		let (result, min): (CompactInteger, u64) = match try!(reader.read::<u8>()) {
+			i @ 0...0xfc => (i.into(), 0),
+			0xfd => (try!(reader.read::<u16>()).into(), 0xfd),
+			0xfe => (try!(reader.read::<u32>()).into(), 0x10000),
+			_ => (try!(reader.read::<u64>()).into(), 0x1_0000_0000),
 		};
 
+		if reader.is_strict() && result.0 < min {
+			return Err(ReaderError::MalformedData);
+		}
+
 		Ok(result)
 	}
 }
@@ -158,4 +162,22 @@ mod tests {
 		assert_eq!(reader.read::<CompactInteger>().unwrap(), 0x1_0000_0000u64.into());
 		assert_eq!(reader.read::<CompactInteger>().unwrap_err(), ReaderError::UnexpectedEnd);
 	}
+
+	#[test]
+	fn test_compact_integer_strict_reader() {
+		let buffer = vec![
+			0xfd, 0xfd, 0x00,
+			0xfd, 0xfc, 0x00,
+		];
+
+		let mut reader = Reader::new_strict(&buffer);
+		assert_eq!(reader.read::<CompactInteger>().unwrap(), 0xfdu64.into());
+		assert_eq!(reader.read::<CompactInteger>().unwrap_err(), ReaderError::MalformedData);
+
+		let mut reader = Reader::new(&buffer[3..]);
+		assert_eq!(reader.read::<CompactInteger>().unwrap(), 0xfcu64.into());
+
+		assert_eq!(Reader::new_strict(&[0xfe, 0xff, 0xff, 0x00, 0x00]).read::<CompactInteger>().unwrap_err(), ReaderError::MalformedData);
+		assert_eq!(Reader::new_strict(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]).read::<CompactInteger>().unwrap_err(), ReaderError::MalformedData);
+	}
 }
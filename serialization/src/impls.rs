@@ -2,6 +2,7 @@ use std::io;
 use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
 use bytes::Bytes;
 use compact::Compact;
+use primitives::bigint::U256;
 use hash::{H32, H48, H96, H160, H256, H264, H512, H520};
 use compact_integer::CompactInteger;
 use {Serializable, Stream, Deserializable, Reader, Error};
@@ -228,8 +229,8 @@ impl Serializable for Bytes {
 
 impl Deserializable for Bytes {
 	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, Error> where T: io::Read {
-		let len = try!(reader.read::<CompactInteger>());
-		let mut bytes = Bytes::new_with_len(len.into());
+		let len = try!(reader.read_len());
+		let mut bytes = Bytes::new_with_len(len);
 		try!(reader.read_slice(&mut bytes));
 		Ok(bytes)
 	}
@@ -247,10 +248,32 @@ impl Deserializable for Compact {
 	}
 }
 
+impl Serializable for U256 {
+	fn serialize(&self, stream: &mut Stream) {
+		let mut bytes = [0u8; 32];
+		self.to_big_endian(&mut bytes);
+		stream.append_slice(&bytes);
+	}
+
+	#[inline]
+	fn serialized_size(&self) -> usize {
+		32
+	}
+}
+
+impl Deserializable for U256 {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, Error> where T: io::Read {
+		let mut bytes = [0u8; 32];
+		try!(reader.read_slice(&mut bytes));
+		Ok(U256::from(&bytes[..]))
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use bytes::Bytes;
-	use {serialize, deserialize, deserialize_iterator, Stream, Reader, Error};
+	use primitives::bigint::U256;
+	use {serialize, deserialize, deserialize_strict, deserialize_iterator, Stream, Reader, Error};
 
 	#[test]
 	fn test_reader_read() {
@@ -311,6 +334,19 @@ mod tests {
 		assert_eq!(expected, deserialize(raw.as_ref()).unwrap());
 	}
 
+	#[test]
+	fn test_bytes_deserialize_strict() {
+		let raw: Bytes = "fd02000145".into();
+		assert_eq!(Bytes::from("0145"), deserialize(raw.as_ref()).unwrap());
+		assert_eq!(Error::MalformedData, deserialize_strict::<_, Bytes>(raw.as_ref()).unwrap_err());
+
+		let raw: Bytes = "fe01000002".into();
+		assert_eq!(Error::MalformedData, deserialize_strict::<_, Bytes>(raw.as_ref()).unwrap_err());
+
+		let raw: Bytes = "02014500".into();
+		assert_eq!(Error::UnreadData, deserialize_strict::<_, Bytes>(raw.as_ref()).unwrap_err());
+	}
+
 	#[test]
 	fn test_bytes_serialize() {
 		let expected: Bytes = "020145".into();
@@ -346,4 +382,12 @@ mod tests {
 		stream.append_slice(&slice);
 		assert_eq!(stream.out(), "64000000".into());
 	}
+
+	#[test]
+	fn test_u256_serialize() {
+		let value = U256::from(0x100010001u64);
+		let raw = serialize(&value);
+		assert_eq!(raw, "0000000000000000000000000000000000000000000000000000000100010001".into());
+		assert_eq!(deserialize::<_, U256>(raw.as_ref()).unwrap(), value);
+	}
 }
@@ -0,0 +1,127 @@
+//! Reader, which decodes Bitcoin structures from data chunks as they arrive.
+//!
+//! Large messages (blocks, transaction batches) are decoded item-by-item: header first,
+//! then the length prefix, then every entry separately. Item is returned as soon as all
+//! of its bytes are received, so the caller never has to buffer the whole payload.
+
+use {Deserializable, Reader, Error};
+
+/// Incremental reader of Bitcoin structures.
+#[derive(Debug)]
+pub struct IncrementalReader {
+	/// Received, but not yet decoded bytes start at `position`.
+	buffer: Vec<u8>,
+	/// Position of the first not-yet-decoded byte in the buffer.
+	position: usize,
+	/// Total number of bytes received.
+	received: usize,
+	/// Maximal number of bytes this reader accepts.
+	limit: usize,
+}
+
+impl IncrementalReader {
+	/// Create reader, accepting at most `limit` bytes.
+	pub fn new(limit: usize) -> Self {
+		IncrementalReader {
+			buffer: Vec::new(),
+			position: 0,
+			received: 0,
+			limit: limit,
+		}
+	}
+
+	/// Appends received bytes to the reader.
+	pub fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+		if self.limit - self.received < data.len() {
+			return Err(Error::MalformedData);
+		}
+
+		if self.position != 0 {
+			self.buffer.drain(..self.position);
+			self.position = 0;
+		}
+
+		self.received += data.len();
+		self.buffer.extend_from_slice(data);
+		Ok(())
+	}
+
+	/// Tries to decode next structure.
+	/// Returns `Ok(None)` if more bytes are required to decode it.
+	pub fn read<T>(&mut self) -> Result<Option<T>, Error> where T: Deserializable {
+		let (result, consumed) = {
+			let mut data = &self.buffer[self.position..];
+			let available = data.len();
+			let result = Reader::from_read(&mut data).read::<T>();
+			(result, available - data.len())
+		};
+
+		match result {
+			Ok(result) => {
+				self.position += consumed;
+				Ok(Some(result))
+			},
+			Err(Error::UnexpectedEnd) => Ok(None),
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Number of received, but not yet decoded bytes.
+	pub fn buffered(&self) -> usize {
+		self.buffer.len() - self.position
+	}
+
+	/// Total number of bytes received.
+	pub fn received(&self) -> usize {
+		self.received
+	}
+
+	/// Are all received bytes decoded?
+	pub fn is_finished(&self) -> bool {
+		self.buffered() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use {CompactInteger, Error};
+	use super::IncrementalReader;
+
+	#[test]
+	fn test_incremental_reader_waits_for_data() {
+		let mut reader = IncrementalReader::new(1024);
+		reader.feed(&[1, 0]).unwrap();
+		assert_eq!(reader.read::<u32>().unwrap(), None);
+		assert_eq!(reader.buffered(), 2);
+
+		reader.feed(&[0, 0, 2]).unwrap();
+		assert_eq!(reader.read::<u32>().unwrap(), Some(1));
+		assert_eq!(reader.read::<u32>().unwrap(), None);
+		assert_eq!(reader.buffered(), 1);
+
+		reader.feed(&[0, 0, 0]).unwrap();
+		assert_eq!(reader.read::<u32>().unwrap(), Some(2));
+		assert!(reader.is_finished());
+		assert_eq!(reader.received(), 8);
+	}
+
+	#[test]
+	fn test_incremental_reader_list_items() {
+		let mut reader = IncrementalReader::new(1024);
+		reader.feed(&[0xfd, 0x02]).unwrap();
+		assert_eq!(reader.read::<CompactInteger>().unwrap(), None);
+		reader.feed(&[0x00, 0x07]).unwrap();
+		assert_eq!(reader.read::<CompactInteger>().unwrap(), Some(2u64.into()));
+		assert_eq!(reader.read::<u8>().unwrap(), Some(7));
+		assert_eq!(reader.read::<u8>().unwrap(), None);
+	}
+
+	#[test]
+	fn test_incremental_reader_limit() {
+		let mut reader = IncrementalReader::new(4);
+		reader.feed(&[1, 2, 3]).unwrap();
+		assert_eq!(reader.feed(&[4, 5]).unwrap_err(), Error::MalformedData);
+		reader.feed(&[4]).unwrap();
+		assert_eq!(reader.read::<u32>().unwrap(), Some(0x04030201));
+	}
+}
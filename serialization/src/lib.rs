@@ -3,6 +3,7 @@ extern crate primitives;
 
 mod compact_integer;
 mod impls;
+mod incremental_reader;
 mod list;
 mod reader;
 mod stream;
@@ -10,8 +11,12 @@ mod stream;
 pub use primitives::{hash, bytes, compact};
 
 pub use compact_integer::CompactInteger;
+pub use incremental_reader::IncrementalReader;
 pub use list::List;
-pub use reader::{Reader, Deserializable, deserialize, deserialize_iterator, ReadIterator, Error};
+pub use reader::{
+	Reader, Deserializable, deserialize, deserialize_strict, deserialize_iterator, ReadIterator, Error,
+	MAX_VECTOR_SIZE,
+};
 pub use stream::{
 	Stream, Serializable, serialize, serialize_with_flags, serialize_list, serialized_list_size,
 	serialized_list_size_with_flags, SERIALIZE_TRANSACTION_WITNESS,
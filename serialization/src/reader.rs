@@ -1,8 +1,19 @@
 use std::{io, marker};
 use compact_integer::CompactInteger;
 
+/// Maximal length of vector, accepted by strict reader.
+pub const MAX_VECTOR_SIZE: usize = 0x0200_0000;
+
 pub fn deserialize<R, T>(buffer: R) -> Result<T, Error> where R: io::Read, T: Deserializable {
-	let mut reader = Reader::from_read(buffer);
+	deserialize_from(Reader::from_read(buffer))
+}
+
+/// Deserializes structure, accepting only its canonical encoding.
+pub fn deserialize_strict<R, T>(buffer: R) -> Result<T, Error> where R: io::Read, T: Deserializable {
+	deserialize_from(Reader::from_read_strict(buffer))
+}
+
+fn deserialize_from<R, T>(mut reader: Reader<R>) -> Result<T, Error> where R: io::Read, T: Deserializable {
 	let result = try!(reader.read());
 
 	if reader.is_finished() {
@@ -37,19 +48,25 @@ pub trait Deserializable {
 }
 
 /// Bitcoin structures reader.
+///
+/// In strict mode reader rejects non-minimal compact integers and
+/// oversized length prefixes, so that `serialize(deserialize(x)) == x`.
 #[derive(Debug)]
 pub struct Reader<T> {
 	buffer: T,
 	peeked: Option<u8>,
+	strict: bool,
 }
 
 impl<'a> Reader<&'a [u8]> {
 	/// Convenient way of creating for slice of bytes
 	pub fn new(buffer: &'a [u8]) -> Self {
-		Reader {
-			buffer: buffer,
-			peeked: None,
-		}
+		Reader::from_read(buffer)
+	}
+
+	/// Convenient way of creating strict reader for slice of bytes
+	pub fn new_strict(buffer: &'a [u8]) -> Self {
+		Reader::from_read_strict(buffer)
 	}
 }
 
@@ -77,15 +94,34 @@ impl<R> Reader<R> where R: io::Read {
 		Reader {
 			buffer: read,
 			peeked: None,
+			strict: false,
+		}
+	}
+
+	pub fn from_read_strict(read: R) -> Self {
+		Reader {
+			buffer: read,
+			peeked: None,
+			strict: true,
 		}
 	}
 
+	/// Does this reader accept only canonical encoding?
+	pub fn is_strict(&self) -> bool {
+		self.strict
+	}
+
 	pub fn read<T>(&mut self) -> Result<T, Error> where T: Deserializable {
 		T::deserialize(self)
 	}
 
 	pub fn read_with_proxy<T, F>(&mut self, proxy: F) -> Result<T, Error> where T: Deserializable, F: FnMut(&[u8]) {
-		let mut reader = Reader::from_read(Proxy::new(self, proxy));
+		let strict = self.strict;
+		let mut reader = Reader {
+			buffer: Proxy::new(self, proxy),
+			peeked: None,
+			strict: strict,
+		};
 		T::deserialize(&mut reader)
 	}
 
@@ -93,8 +129,18 @@ impl<R> Reader<R> where R: io::Read {
 		io::Read::read_exact(self, bytes).map_err(|_| Error::UnexpectedEnd)
 	}
 
-	pub fn read_list<T>(&mut self) -> Result<Vec<T>, Error> where T: Deserializable {
+	/// Reads length prefix of list or byte vector.
+	pub fn read_len(&mut self) -> Result<usize, Error> {
 		let len: usize = try!(self.read::<CompactInteger>()).into();
+		if self.strict && len > MAX_VECTOR_SIZE {
+			return Err(Error::MalformedData);
+		}
+
+		Ok(len)
+	}
+
+	pub fn read_list<T>(&mut self) -> Result<Vec<T>, Error> where T: Deserializable {
+		let len = try!(self.read_len());
 		let mut result = Vec::with_capacity(len);
 
 		for _ in 0..len {
@@ -105,7 +151,7 @@ impl<R> Reader<R> where R: io::Read {
 	}
 
 	pub fn read_list_max<T>(&mut self, max: usize) -> Result<Vec<T>, Error> where T: Deserializable {
-		let len: usize = try!(self.read::<CompactInteger>()).into();
+		let len = try!(self.read_len());
 		if len > max {
 			return Err(Error::MalformedData);
 		}
@@ -0,0 +1,50 @@
+use syn;
+
+/// Name of the attribute, which controls field serialization.
+const SERIALIZE_ATTRIBUTE: &'static str = "serialize";
+
+/// Field serialization options, parsed from `#[serialize(...)]` attributes.
+///
+/// - `#[serialize(compact)]` - integer (or every integer in `Vec`) is serialized as `CompactInteger`
+/// - `#[serialize(max = "N")]` - `Vec` with more than `N` items is rejected during deserialization
+/// - `#[serialize(skip)]` - field is not serialized and is set to `Default::default()` during deserialization
+#[derive(Default)]
+pub struct FieldAttributes {
+	pub compact: bool,
+	pub skip: bool,
+	pub max: Option<usize>,
+}
+
+pub fn field_attributes(field: &syn::Field) -> FieldAttributes {
+	let mut result = FieldAttributes::default();
+	for attr in &field.attrs {
+		let items = match attr.value {
+			syn::MetaItem::List(ref ident, ref items) if ident.as_ref() == SERIALIZE_ATTRIBUTE => items,
+			_ => continue,
+		};
+
+		for item in items {
+			match *item {
+				syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) if word.as_ref() == "compact" => result.compact = true,
+				syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) if word.as_ref() == "skip" => result.skip = true,
+				syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref name, syn::Lit::Str(ref value, _))) if name.as_ref() == "max" => {
+					result.max = Some(value.parse().expect("#[serialize(max = \"N\")] expects integer value"));
+				},
+				_ => panic!("unsupported #[serialize] attribute"),
+			}
+		}
+	}
+
+	result
+}
+
+/// Returns true if field is a `Vec`, which is serialized as list.
+pub fn is_vec(field: &syn::Field) -> bool {
+	match field.ty {
+		syn::Ty::Path(_, ref path) => {
+			let ident = &path.segments.first().expect("there must be at least 1 segment").ident;
+			&ident.to_string() == "Vec"
+		},
+		_ => panic!("serialization not supported"),
+	}
+}
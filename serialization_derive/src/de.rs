@@ -1,4 +1,5 @@
 use {syn, quote};
+use attributes::{field_attributes, is_vec};
 
 pub fn impl_deserializable(ast: &syn::DeriveInput) -> quote::Tokens {
 	let body = match ast.body {
@@ -48,16 +49,28 @@ fn deserialize_field(index: usize, field: &syn::Field) -> quote::Tokens {
 	};
 
 	let id = syn::Ident::new(ident.to_string());
+	let attributes = field_attributes(field);
 
-	match field.ty {
-		syn::Ty::Path(_, ref path) => {
-			let ident = &path.segments.first().expect("there must be at least 1 segment").ident;
-			if &ident.to_string() == "Vec" {
-				quote! { #id: reader.read_list()?, }
-			} else {
-				quote! { #id: reader.read()?, }
-			}
+	if attributes.skip {
+		return quote! { #id: Default::default(), };
+	}
+
+	if !is_vec(field) {
+		return match attributes.compact {
+			true => quote! { #id: reader.read::<serialization::CompactInteger>()?.into(), },
+			false => quote! { #id: reader.read()?, },
+		};
+	}
+
+	let read_list = match attributes.max {
+		Some(max) => quote! { read_list_max(#max) },
+		None => quote! { read_list() },
+	};
+
+	match attributes.compact {
+		true => quote! {
+			#id: reader.#read_list?.into_iter().map(|i: serialization::CompactInteger| i.into()).collect(),
 		},
-		_ => panic!("serialization not supported"),
+		false => quote! { #id: reader.#read_list?, },
 	}
 }
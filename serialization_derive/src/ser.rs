@@ -1,4 +1,5 @@
 use {syn, quote};
+use attributes::{field_attributes, is_vec};
 
 pub fn impl_serializable(ast: &syn::DeriveInput) -> quote::Tokens {
 	let body = match ast.body {
@@ -28,7 +29,7 @@ pub fn impl_serializable(ast: &syn::DeriveInput) -> quote::Tokens {
 			}
 
 			fn serialized_size(&self) -> usize {
-				#(#size_stmts)+*
+				0 #(+ #size_stmts)*
 			}
 		}
 	};
@@ -53,17 +54,17 @@ fn serialize_field_size(index: usize, field: &syn::Field) -> quote::Tokens {
 	};
 
 	let id = syn::Ident::new(format!("self.{}", ident));
+	let attributes = field_attributes(field);
 
-	match field.ty {
-		syn::Ty::Path(_, ref path) => {
-			let ident = &path.segments.first().expect("there must be at least 1 segment").ident;
-			if &ident.to_string() == "Vec" {
-				quote! { serialization::serialized_list_size(&#id) }
-			} else {
-				quote! { #id.serialized_size() }
-			}
+	match (is_vec(field), attributes.skip, attributes.compact) {
+		(_, true, _) => quote! { 0 },
+		(true, false, true) => quote! {
+			serialization::Serializable::serialized_size(&serialization::CompactInteger::from(#id.len())) +
+				#id.iter().map(|i| serialization::Serializable::serialized_size(&serialization::CompactInteger::from(*i))).sum::<usize>()
 		},
-		_ => panic!("serialization not supported"),
+		(true, false, false) => quote! { serialization::serialized_list_size(&#id) },
+		(false, false, true) => quote! { serialization::Serializable::serialized_size(&serialization::CompactInteger::from(#id)) },
+		(false, false, false) => quote! { serialization::Serializable::serialized_size(&#id) },
 	}
 }
 
@@ -78,17 +79,18 @@ fn serialize_field(index: usize, field: &syn::Field) -> quote::Tokens {
 	};
 
 	let id = syn::Ident::new(format!("self.{}", ident));
-
-	match field.ty {
-		syn::Ty::Path(_, ref path) => {
-			let ident = &path.segments.first().expect("there must be at least 1 segment").ident;
-			if &ident.to_string() == "Vec" {
-				quote! { stream.append_list(&#id); }
-			} else {
-				quote! { stream.append(&#id); }
+	let attributes = field_attributes(field);
+
+	match (is_vec(field), attributes.skip, attributes.compact) {
+		(_, true, _) => quote! {},
+		(true, false, true) => quote! {
+			{
+				let items: Vec<serialization::CompactInteger> = #id.iter().map(|i| serialization::CompactInteger::from(*i)).collect();
+				stream.append_list(&items);
 			}
 		},
-		_ => panic!("serialization not supported"),
+		(true, false, false) => quote! { stream.append_list(&#id); },
+		(false, false, true) => quote! { stream.append(&serialization::CompactInteger::from(#id)); },
+		(false, false, false) => quote! { stream.append(&#id); },
 	}
 }
-
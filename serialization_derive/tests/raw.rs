@@ -81,3 +81,49 @@ fn test_bar_serialize() {
 	let d = deserialize(expected.as_ref()).unwrap();
 	assert_eq!(bar, d);
 }
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Baz {
+	#[serialize(compact)]
+	a: usize,
+	#[serialize(compact, max = "2")]
+	b: Vec<u64>,
+	#[serialize(skip)]
+	c: Vec<u8>,
+}
+
+#[test]
+fn test_baz_serialize() {
+	let baz = Baz {
+		a: 0xfd,
+		b: vec![1, 0x10000],
+		c: vec![],
+	};
+
+	let expected = vec![
+		// compact a
+		0xfdu8, 0xfd, 0x00,
+		// number of items in b
+		2,
+		// compact items
+		1,
+		0xfe, 0x00, 0x00, 0x01, 0x00,
+	].into();
+
+	let result = serialize(&baz);
+	assert_eq!(result, expected);
+	assert_eq!(serialization::Serializable::serialized_size(&baz), 10);
+
+	let d = deserialize(expected.as_ref()).unwrap();
+	assert_eq!(baz, d);
+}
+
+#[test]
+fn test_baz_deserialize_max() {
+	let raw: Vec<u8> = vec![
+		0,
+		3, 1, 2, 3,
+	];
+
+	assert_eq!(deserialize::<_, Baz>(raw.as_ref()).unwrap_err(), serialization::Error::MalformedData);
+}
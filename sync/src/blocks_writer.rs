@@ -76,7 +76,8 @@ impl BlocksWriter {
 		let mut verification_queue: VecDeque<chain::IndexedBlock> = self.orphaned_blocks_pool.remove_blocks_for_parent(block.hash());
 		verification_queue.push_front(block);
 		while let Some(block) = verification_queue.pop_front() {
-			self.verifier.verify_block(block);
+			// there are no headers to check if block is an ancestor of the assumed-valid block
+			self.verifier.verify_block(block, false);
 			if let Some(err) = self.sink.error() {
 				return Err(err);
 			}
@@ -84,6 +85,11 @@ impl BlocksWriter {
 
 		Ok(())
 	}
+
+	/// Number of blocks, waiting for their parents
+	pub fn orphaned_blocks_len(&self) -> usize {
+		self.orphaned_blocks_pool.len()
+	}
 }
 
 impl BlocksWriterSink {
@@ -1,6 +1,7 @@
 use chain::{IndexedTransaction, IndexedBlock};
 use message::types;
-use p2p::{InboundSyncConnection, InboundSyncConnectionRef};
+use logs;
+use p2p::{InboundSyncConnection, InboundSyncConnectionRef, ConnectionType, PeerPermissions};
 use types::{PeersRef, LocalNodeRef, PeerIndex, RequestId};
 use utils::KnownHashType;
 
@@ -31,8 +32,8 @@ impl InboundConnection {
 }
 
 impl InboundSyncConnection for InboundConnection {
-	fn start_sync_session(&self, peer_name: String, version: types::Version) {
-		self.node.on_connect(self.peer_index, peer_name, version);
+	fn start_sync_session(&self, peer_name: String, version: types::Version, connection_type: ConnectionType, permissions: PeerPermissions) {
+		self.node.on_connect(self.peer_index, peer_name, version, connection_type, permissions);
 	}
 
 	fn close_session(&self) {
@@ -82,8 +83,8 @@ impl InboundSyncConnection for InboundConnection {
 		self.node.on_transaction(self.peer_index, tx);
 	}
 
-	fn on_block(&self, message: types::Block) {
-		let block: IndexedBlock = message.block.into();
+	fn on_block(&self, block: IndexedBlock) {
+		let _span = logs::span("on_block");
 		self.peers.hash_known_as(self.peer_index, block.hash().clone(), KnownHashType::Block);
 		self.node.on_block(self.peer_index, block);
 	}
@@ -166,12 +167,41 @@ impl InboundSyncConnection for InboundConnection {
 	fn on_notfound(&self, message: types::NotFound) {
 		self.node.on_notfound(self.peer_index, message);
 	}
+
+	fn on_get_cfilters(&self, message: types::GetCFilters) {
+		self.node.on_get_cfilters(self.peer_index, message);
+	}
+
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders) {
+		self.node.on_get_cfheaders(self.peer_index, message);
+	}
+
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt) {
+		self.node.on_get_cfcheckpt(self.peer_index, message);
+	}
+
+	fn on_sendtxrcncl(&self, message: types::SendTxRcncl) {
+		self.node.on_sendtxrcncl(self.peer_index, message);
+	}
+
+	fn on_reqrecon(&self, message: types::ReqRecon) {
+		self.node.on_reqrecon(self.peer_index, message);
+	}
+
+	fn on_sketch(&self, message: types::Sketch) {
+		self.node.on_sketch(self.peer_index, message);
+	}
+
+	fn on_reconcildiff(&self, message: types::ReconcilDiff) {
+		self.node.on_reconcildiff(self.peer_index, message);
+	}
 }
 
 #[cfg(test)]
 pub mod tests {
 	use std::collections::HashMap;
 	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, Ordering};
 	use parking_lot::Mutex;
 	use message::types;
 	use p2p::OutboundSyncConnection;
@@ -179,12 +209,14 @@ pub mod tests {
 
 	pub struct DummyOutboundSyncConnection {
 		pub messages: Mutex<HashMap<String, usize>>,
+		pub upload_target_reached: AtomicBool,
 	}
 
 	impl DummyOutboundSyncConnection {
 		pub fn new() -> Arc<DummyOutboundSyncConnection> {
 			Arc::new(DummyOutboundSyncConnection {
 				messages: Mutex::new(HashMap::new()),
+				upload_target_reached: AtomicBool::new(false),
 			})
 		}
 	}
@@ -212,7 +244,16 @@ pub mod tests {
 		fn send_get_block_txn(&self, _message: &types::GetBlockTxn) { *self.messages.lock().entry("getblocktxn".to_owned()).or_insert(0) += 1; }
 		fn send_block_txn(&self, _message: &types::BlockTxn) { *self.messages.lock().entry("blocktxn".to_owned()).or_insert(0) += 1; }
 		fn send_notfound(&self, _message: &types::NotFound) { *self.messages.lock().entry("notfound".to_owned()).or_insert(0) += 1; }
+		fn send_cfilter(&self, _message: &types::CFilter) { *self.messages.lock().entry("cfilter".to_owned()).or_insert(0) += 1; }
+		fn send_cfheaders(&self, _message: &types::CFHeaders) { *self.messages.lock().entry("cfheaders".to_owned()).or_insert(0) += 1; }
+		fn send_cfcheckpt(&self, _message: &types::CFCheckpt) { *self.messages.lock().entry("cfcheckpt".to_owned()).or_insert(0) += 1; }
+		fn send_sendtxrcncl(&self, _message: &types::SendTxRcncl) { *self.messages.lock().entry("sendtxrcncl".to_owned()).or_insert(0) += 1; }
+		fn send_reqrecon(&self, _message: &types::ReqRecon) { *self.messages.lock().entry("reqrecon".to_owned()).or_insert(0) += 1; }
+		fn send_sketch(&self, _message: &types::Sketch) { *self.messages.lock().entry("sketch".to_owned()).or_insert(0) += 1; }
+		fn send_reconcildiff(&self, _message: &types::ReconcilDiff) { *self.messages.lock().entry("reconcildiff".to_owned()).or_insert(0) += 1; }
+		fn is_upload_target_reached(&self) -> bool { self.upload_target_reached.load(Ordering::SeqCst) }
 		fn ignored(&self, _id: RequestId) {}
-		fn close(&self) {}
+		fn close(&self) { *self.messages.lock().entry("close".to_owned()).or_insert(0) += 1; }
+		fn misbehaving(&self, _score: u32) { *self.messages.lock().entry("misbehaving".to_owned()).or_insert(0) += 1; }
 	}
 }
@@ -1,5 +1,4 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
-use p2p::{LocalSyncNode, LocalSyncNodeRef, OutboundSyncConnectionRef, InboundSyncConnectionRef};
+use p2p::{PeerId, LocalSyncNode, LocalSyncNodeRef, OutboundSyncConnectionRef, InboundSyncConnectionRef};
 use message::Services;
 use inbound_connection::InboundConnection;
 use types::{PeersRef, LocalNodeRef};
@@ -10,8 +9,6 @@ pub struct InboundConnectionFactory {
 	peers: PeersRef,
 	/// Reference to synchronization node
 	node: LocalNodeRef,
-	/// Throughout counter of synchronization peers
-	counter: AtomicUsize,
 }
 
 impl InboundConnectionFactory {
@@ -20,7 +17,6 @@ impl InboundConnectionFactory {
 		InboundConnectionFactory {
 			peers: peers,
 			node: node,
-			counter: AtomicUsize::new(0),
 		}
 	}
 
@@ -31,8 +27,8 @@ impl InboundConnectionFactory {
 }
 
 impl LocalSyncNode for InboundConnectionFactory {
-	fn create_sync_session(&self, _best_block_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
-		let peer_index = self.counter.fetch_add(1, Ordering::SeqCst) + 1;
+	fn create_sync_session(&self, peer_index: PeerId, _best_block_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
+		// synchronization peer has the same index as p2p peer
 		trace!(target: "sync", "Creating new sync session with peer#{}", peer_index);
 		// remember outbound connection
 		self.peers.insert(peer_index, services, outbound_connection);
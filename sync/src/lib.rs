@@ -19,6 +19,7 @@ extern crate script;
 extern crate serialization as ser;
 extern crate rand;
 extern crate network;
+extern crate logs;
 
 mod blocks_writer;
 mod inbound_connection;
@@ -43,9 +44,13 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 use message::Services;
 use network::{Magic, ConsensusParams};
+use chain::IndexedTransaction;
 use primitives::hash::H256;
 use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 
+/// Maximal number of transactions in the package, which is tested or accepted to the memory pool.
+pub const MAX_PACKAGE_COUNT: usize = 25;
+
 /// Sync errors.
 #[derive(Debug)]
 pub enum Error {
@@ -64,15 +69,82 @@ pub struct VerificationParameters {
 	pub verification_level: verification::VerificationLevel,
 	/// Blocks verification edge: all blocks before this are validated using verification_level.
 	/// All blocks after this (inclusive) are validated using VerificationLevel::Full level.
+	/// With VerificationLevel::Full, scripts of the edge ancestors are not verified during synchronization.
 	pub verification_edge: H256,
 }
 
-/// Synchronization events listener
+/// Synchronization status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncStatus {
+	/// Is synchronization in progress?
+	pub is_synchronizing: bool,
+	/// Best block in the storage.
+	pub best_block: db::BestBlock,
+	/// Best known block header. Equals to the best block, unless there are headers, which blocks aren't stored yet.
+	pub best_header: db::BestBlock,
+}
+
+/// Synchronization-related information on the connected peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerSyncInformation {
+	/// Number of blocks, requested from the peer, but not yet received.
+	pub blocks_in_flight: usize,
+	/// Minimal fee rate (in satoshis per 1000 bytes) of transactions, announced to the peer, as requested by `feefilter` message.
+	pub min_fee_rate: u64,
+}
+
+/// Result of the memory pool acceptance test of the transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestAcceptResult {
+	/// Hash of the transaction.
+	pub hash: H256,
+	/// Witness hash of the transaction.
+	pub witness_hash: H256,
+	/// Virtual size of the transaction.
+	pub vsize: usize,
+	/// Fee, paid by the transaction, or the reason of its rejection.
+	pub result: Result<u64, String>,
+}
+
+/// Result of the package acceptance to the memory pool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageAcceptResult {
+	/// Results of the package transactions verification.
+	pub transactions: Vec<TestAcceptResult>,
+	/// Reason of the package rejection. Package transactions are accepted only if it is None.
+	pub reject_reason: Option<String>,
+}
+
+impl PackageAcceptResult {
+	fn accepted(transactions: Vec<TestAcceptResult>) -> Self {
+		PackageAcceptResult {
+			transactions: transactions,
+			reject_reason: None,
+		}
+	}
+
+	fn rejected(transactions: Vec<TestAcceptResult>, reject_reason: &str) -> Self {
+		PackageAcceptResult {
+			transactions: transactions,
+			reject_reason: Some(reject_reason.to_owned()),
+		}
+	}
+}
+
+/// Synchronization events listener. Events are reported in the order they happen. When canon chain
+/// changes, disconnected blocks are reported first, then connected blocks and then the new best block.
 pub trait SyncListener: Send + 'static {
 	/// Called when node switches to synchronization state
 	fn synchronization_state_switched(&self, is_synchronizing: bool);
 	/// Called when new best storage block is inserted
 	fn best_storage_block_inserted(&self, block_hash: &H256);
+	/// Called when block is connected to the canon chain. Blocks are connected starting with the oldest block
+	fn block_connected(&self, _block_hash: &H256) {}
+	/// Called when block is disconnected from the canon chain. Blocks are disconnected starting with the old
+	/// best block. Disconnected block is still stored as the side chain block
+	fn block_disconnected(&self, _block_hash: &H256) {}
+	/// Called when transaction is accepted to the memory pool
+	fn transaction_accepted(&self, _transaction: &IndexedTransaction) {}
 }
 
 /// Create blocks writer.
@@ -87,8 +159,8 @@ pub fn create_sync_peers() -> PeersRef {
 	Arc::new(PeersImpl::default())
 }
 
-/// Creates local sync node for given `db`
-pub fn create_local_sync_node(consensus: ConsensusParams, db: db::SharedStore, peers: PeersRef, verification_params: VerificationParameters) -> LocalNodeRef {
+/// Creates local sync node for given `db`. Memory pool holds transactions with total size up to `max_memory_pool_size` bytes
+pub fn create_local_sync_node(consensus: ConsensusParams, db: db::SharedStore, peers: PeersRef, verification_params: VerificationParameters, max_memory_pool_size: usize, transaction_reconciliation: bool, peer_bloom_filters: bool, network_time: Arc<p2p::NetworkTime>) -> LocalNodeRef {
 	use miner::MemoryPool;
 	use synchronization_chain::Chain as SyncChain;
 	use synchronization_executor::LocalSynchronizationTaskExecutor as SyncExecutor;
@@ -97,30 +169,39 @@ pub fn create_local_sync_node(consensus: ConsensusParams, db: db::SharedStore, p
 	use synchronization_client::SynchronizationClient;
 	use synchronization_client_core::{SynchronizationClientCore, CoreVerificationSink, Config as SynchronizationConfig};
 	use synchronization_verifier::AsyncVerifier;
-	use utils::SynchronizationState;
+	use utils::{SynchronizationState, NetworkTimeSource};
 	use types::SynchronizationStateRef;
+	use verification::TimeSource;
 
 	let network = consensus.network;
 	let sync_client_config = SynchronizationConfig {
 		// during regtests, peer is providing us with bad blocks => we shouldn't close connection because of this
 		close_connection_on_bad_block: network != Magic::Regtest,
+		verification_edge: verification_params.verification_edge.clone(),
+		minimum_chain_work: network.default_minimum_chain_work(),
 	};
 
-	let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+	let memory_pool = Arc::new(RwLock::new(MemoryPool::with_max_size(max_memory_pool_size)));
 	let sync_state = SynchronizationStateRef::new(SynchronizationState::with_storage(db.clone()));
 	let sync_chain = SyncChain::new(db.clone(), consensus.clone(), memory_pool.clone());
-	if sync_chain.is_segwit_active() {
+	// on regtest segwit is active from genesis, but we still want to accept blocks from non-segwit test tools
+	if sync_chain.is_segwit_active() && network != Magic::Regtest {
 		peers.require_peer_services(Services::default().with_witness(true));
 	}
 
-	let chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone()));
+	// blocks are verified and mined using network-adjusted time
+	let time_source: Arc<TimeSource> = Arc::new(NetworkTimeSource::new(network_time));
+	let chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone()).with_time_source(time_source.clone()));
 	let sync_executor = SyncExecutor::new(peers.clone());
 	let sync_server = Arc::new(ServerImpl::new(peers.clone(), db.clone(), memory_pool.clone(), sync_executor.clone()));
 	let sync_client_core = SynchronizationClientCore::new(sync_client_config, sync_state.clone(), peers.clone(), sync_executor.clone(), sync_chain, chain_verifier.clone());
 	let verifier_sink = Arc::new(CoreVerificationSink::new(sync_client_core.clone()));
 	let verifier = AsyncVerifier::new(chain_verifier, db.clone(), memory_pool.clone(), verifier_sink, verification_params);
 	let sync_client = SynchronizationClient::new(sync_state.clone(), sync_client_core, verifier);
-	Arc::new(SyncNode::new(consensus, db, memory_pool, peers, sync_state, sync_executor, sync_client, sync_server))
+	Arc::new(SyncNode::new(consensus, db, memory_pool, peers, sync_state, sync_executor, sync_client, sync_server)
+		.with_transaction_reconciliation(transaction_reconciliation)
+		.with_peer_bloom_filters(peer_bloom_filters)
+		.with_time_source(time_source))
 }
 
 /// Create inbound synchronization connections factory for given local sync node.
@@ -1,21 +1,33 @@
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use parking_lot::{Mutex, Condvar};
-use time;
 use futures::{Future, lazy, finished};
-use chain::{Transaction, IndexedTransaction, IndexedBlock};
-use message::types;
-use miner::BlockAssembler;
+use chain::{Transaction, IndexedTransaction, IndexedBlock, IndexedBlockHeader};
+use message::{types, Payload};
+use message::common::{BlockTransactionsRequest, InventoryVector, InventoryType};
+use miner::{BlockAssembler, MemoryPoolOrderingStrategy as OrderingStrategy};
 use network::ConsensusParams;
+use p2p::{ConnectionType, Direction, PeerPermissions};
+use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Verify, VerificationLevel, TimeSource, SystemTime, Deployments, is_valid_proof_of_work};
 use synchronization_client::{Client};
 use synchronization_executor::{Task as SynchronizationTask, TaskExecutor};
 use synchronization_server::{Server, ServerTask};
 use synchronization_verifier::{TransactionVerificationSink};
 use primitives::hash::H256;
-use miner::BlockTemplate;
+use miner::{BlockTemplate, FeeEstimator, FeeEstimate, FeeEstimateHorizon, RawFeeEstimate, MemoryPoolEntryInformation, MemoryPoolInformation};
 use synchronization_peers::{TransactionAnnouncementType, BlockAnnouncementType};
+use utils::{PartialCompactBlock, MemoryPoolTransactionOutputProvider, PackageTransactionOutputProvider};
+use db::TransactionOutputProvider;
+use super::{SyncStatus, PeerSyncInformation, TestAcceptResult, PackageAcceptResult, MAX_PACKAGE_COUNT};
 use types::{PeerIndex, RequestId, StorageRef, MemoryPoolRef, PeersRef, ExecutorRef,
 	ClientRef, ServerRef, SynchronizationStateRef, SyncListenerRef};
 
+/// Index of the pseudo-peer, which provides blocks, submitted over RPC
+const SUBMITTED_BLOCKS_PEER_INDEX: PeerIndex = ::std::usize::MAX;
+
+/// Maximal number of peers, asked to announce new blocks with `cmpctblock` messages (BIP152 high-bandwidth mode)
+const MAX_HIGH_BANDWIDTH_PEERS: usize = 3;
+
 /// Local synchronization node
 pub struct LocalNode<T: TaskExecutor, U: Server, V: Client> {
 	/// Network we are working on
@@ -34,6 +46,27 @@ pub struct LocalNode<T: TaskExecutor, U: Server, V: Client> {
 	client: ClientRef<V>,
 	/// Synchronization server
 	server: ServerRef<U>,
+	/// Compact blocks, waiting for missing transactions from peers
+	compact_blocks: Mutex<HashMap<PeerIndex, PartialCompactBlock>>,
+	/// Peers, announcing new blocks with `cmpctblock` messages. Peer, which has provided new block most recently, goes last
+	high_bandwidth_peers: Mutex<VecDeque<PeerIndex>>,
+	/// Offer transactions reconciliation (BIP330) to peers
+	transaction_reconciliation: bool,
+	/// Serve bloom filters (BIP37) and memory pool requests to all peers, not only to whitelisted
+	peer_bloom_filters: bool,
+	/// Source of the current time for verification and mining
+	time_source: Arc<TimeSource>,
+	/// Deployments cache, shared by block templates
+	deployments: Deployments,
+}
+
+/// Checks that the package consists of the child transaction, preceded by its parents.
+fn is_child_with_parents(transactions: &[IndexedTransaction]) -> bool {
+	match transactions.split_last() {
+		Some((child, parents)) => parents.iter()
+			.all(|parent| child.raw.inputs.iter().any(|input| input.previous_output.hash == parent.hash)),
+		None => false,
+	}
 }
 
 /// Transaction accept verification sink
@@ -61,18 +94,77 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 			executor: executor,
 			client: client,
 			server: server,
+			compact_blocks: Mutex::new(HashMap::new()),
+			high_bandwidth_peers: Mutex::new(VecDeque::new()),
+			transaction_reconciliation: false,
+			peer_bloom_filters: false,
+			time_source: Arc::new(SystemTime),
+			deployments: Deployments::new(),
 		}
 	}
 
+	/// Reconcile transactions with peers, which are also supporting reconciliation, instead of announcing them with inventory
+	pub fn with_transaction_reconciliation(mut self, transaction_reconciliation: bool) -> Self {
+		self.transaction_reconciliation = transaction_reconciliation;
+		self
+	}
+
+	/// Serve bloom filters and memory pool requests to all peers. Otherwise only peers with
+	/// `bloomfilter` and `mempool` permissions are served
+	pub fn with_peer_bloom_filters(mut self, peer_bloom_filters: bool) -> Self {
+		self.peer_bloom_filters = peer_bloom_filters;
+		self
+	}
+
+	/// Use given source of the current time (network-adjusted time) instead of the local system clock
+	pub fn with_time_source(mut self, time_source: Arc<TimeSource>) -> Self {
+		self.time_source = time_source;
+		self
+	}
+
 	/// When new peer connects to the node
-	pub fn on_connect(&self, peer_index: PeerIndex, peer_name: String, version: types::Version) {
+	pub fn on_connect(&self, peer_index: PeerIndex, peer_name: String, version: types::Version, connection_type: ConnectionType, permissions: PeerPermissions) {
 		trace!(target: "sync", "Starting new sync session with peer#{}: {}", peer_index, peer_name);
 
+		self.peers.set_permissions(peer_index, permissions);
+
 		// light clients may not want transactions broadcasting until filter for connection is set
-		if !version.relay_transactions() {
+		// && transactions are never relayed over block-relay-only connections
+		let relay_transactions = version.relay_transactions() && connection_type != ConnectionType::BlockRelayOnly;
+		if !relay_transactions {
 			self.peers.set_transaction_announcement_type(peer_index, TransactionAnnouncementType::DoNotAnnounce);
 		}
 
+		// ask peer to announce new blocks with headers messages, so that we could fetch them directly
+		if version.version() >= types::SendHeaders::version() {
+			self.executor.execute(SynchronizationTask::SendHeaders(peer_index));
+		}
+
+		// announce supported compact blocks versions, preferred first. Version 2 compact blocks include witness data.
+		// peer is asked to announce new blocks with cmpctblock messages later, when it provides us with new block
+		if version.version() >= types::SendCompact::version() {
+			self.executor.execute(SynchronizationTask::SendCompact(peer_index, types::SendCompact {
+				first: false,
+				second: 2,
+			}));
+			self.executor.execute(SynchronizationTask::SendCompact(peer_index, types::SendCompact {
+				first: false,
+				second: 1,
+			}));
+		}
+
+		// memory pool minimum fee rate is periodically announced to the peer
+		if relay_transactions && version.version() >= types::FeeFilter::version() {
+			self.peers.enable_fee_filter_announcement(peer_index);
+		}
+
+		// offer transactions reconciliation. Reconciliation rounds are initiated by the outbound connection side
+		if self.transaction_reconciliation && relay_transactions {
+			if let Some(sendtxrcncl) = self.peers.enable_transaction_reconciliation(peer_index, connection_type.direction() == Direction::Outbound) {
+				self.executor.execute(SynchronizationTask::SendTxRcncl(peer_index, sendtxrcncl));
+			}
+		}
+
 		// start synchronization session with peer
 		self.client.on_connect(peer_index);
 	}
@@ -81,6 +173,9 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 	pub fn on_disconnect(&self, peer_index: PeerIndex) {
 		trace!(target: "sync", "Stopping sync session with peer#{}", peer_index);
 
+		self.compact_blocks.lock().remove(&peer_index);
+		self.high_bandwidth_peers.lock().retain(|index| *index != peer_index);
+
 		// stop synchronization session with peer
 		self.client.on_disconnect(peer_index);
 	}
@@ -114,6 +209,9 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 	/// When block is received
 	pub fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
 		trace!(target: "sync", "Got `block` message from peer#{}. Block hash: {}", peer_index, block.header.hash.to_reversed_str());
+		if !self.state.synchronizing() {
+			self.select_high_bandwidth_peer(peer_index);
+		}
 		self.client.on_block(peer_index, block);
 	}
 
@@ -169,6 +267,11 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 
 	/// When peer is requesting for memory pool contents
 	pub fn on_mempool(&self, peer_index: PeerIndex, _message: types::MemPool) {
+		if !self.peer_bloom_filters && !self.peers.permissions(peer_index).mempool {
+			self.peers.disconnect(peer_index, "'mempool' message from peer without mempool permission");
+			return;
+		}
+
 		if self.state.synchronizing() {
 			trace!(target: "sync", "Ignored `mempool` message from peer#{}", peer_index);
 			return;
@@ -189,22 +292,56 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 		self.server.execute(ServerTask::GetBlockTxn(peer_index, message));
 	}
 
+	/// When peer asks us for compact block filters
+	pub fn on_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		trace!(target: "sync", "Got `getcfilters` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFilters(peer_index, message));
+	}
+
+	/// When peer asks us for compact block filter headers
+	pub fn on_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		trace!(target: "sync", "Got `getcfheaders` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFHeaders(peer_index, message));
+	}
+
+	/// When peer asks us for compact block filter checkpoints
+	pub fn on_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		trace!(target: "sync", "Got `getcfcheckpt` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFCheckpt(peer_index, message));
+	}
+
 	/// When peer sets bloom filter for connection
 	pub fn on_filterload(&self, peer_index: PeerIndex, message: types::FilterLoad) {
 		trace!(target: "sync", "Got `filterload` message from peer#{}", peer_index);
-		self.peers.set_bloom_filter(peer_index, message);
+		if self.check_bloom_filter_permission(peer_index) {
+			self.peers.set_bloom_filter(peer_index, message);
+		}
 	}
 
 	/// When peer updates bloom filter for connection
 	pub fn on_filteradd(&self, peer_index: PeerIndex, message: types::FilterAdd) {
 		trace!(target: "sync", "Got `filteradd` message from peer#{}", peer_index);
-		self.peers.update_bloom_filter(peer_index, message);
+		if self.check_bloom_filter_permission(peer_index) {
+			self.peers.update_bloom_filter(peer_index, message);
+		}
 	}
 
 	/// When peer removes bloom filter from connection
 	pub fn on_filterclear(&self, peer_index: PeerIndex, _message: types::FilterClear) {
 		trace!(target: "sync", "Got `filterclear` message from peer#{}", peer_index);
-		self.peers.clear_bloom_filter(peer_index);
+		if self.check_bloom_filter_permission(peer_index) {
+			self.peers.clear_bloom_filter(peer_index);
+		}
+	}
+
+	/// Checks that peer may use bloom filters. Disconnects the peer otherwise (BIP111)
+	fn check_bloom_filter_permission(&self, peer_index: PeerIndex) -> bool {
+		if self.peer_bloom_filters || self.peers.permissions(peer_index).bloomfilter {
+			return true;
+		}
+
+		self.peers.disconnect(peer_index, "bloom filter message from peer without bloomfilter permission");
+		false
 	}
 
 	/// When peer sets up a minimum fee rate filter for connection
@@ -219,18 +356,61 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 		self.peers.set_block_announcement_type(peer_index, BlockAnnouncementType::SendHeaders);
 	}
 
+	/// When peer accepts our transactions reconciliation offer
+	pub fn on_sendtxrcncl(&self, peer_index: PeerIndex, message: types::SendTxRcncl) {
+		trace!(target: "sync", "Got `sendtxrcncl` message from peer#{}", peer_index);
+		if !self.peers.register_transaction_reconciliation(peer_index, &message) {
+			trace!(target: "sync", "Ignoring transactions reconciliation offer from peer#{}", peer_index);
+		}
+	}
+
+	/// When peer starts transactions reconciliation round
+	pub fn on_reqrecon(&self, peer_index: PeerIndex, message: types::ReqRecon) {
+		trace!(target: "sync", "Got `reqrecon` message from peer#{}", peer_index);
+		if let Some(sketch) = self.peers.respond_transaction_reconciliation(peer_index, &message) {
+			self.executor.execute(SynchronizationTask::Sketch(peer_index, sketch));
+		}
+	}
+
+	/// When peer responds with the sketch of its reconciliation set
+	pub fn on_sketch(&self, peer_index: PeerIndex, message: types::Sketch) {
+		trace!(target: "sync", "Got `sketch` message from peer#{}", peer_index);
+		if let Some((reconcildiff, hashes)) = self.peers.finish_transaction_reconciliation(peer_index, &message) {
+			// announce transactions, missing from the peer set
+			if !hashes.is_empty() {
+				let inventory = hashes.into_iter().map(InventoryVector::tx).collect();
+				self.executor.execute(SynchronizationTask::Inventory(peer_index, types::Inv::with_inventory(inventory)));
+			}
+			self.executor.execute(SynchronizationTask::ReconcilDiff(peer_index, reconcildiff));
+		}
+	}
+
+	/// When peer finishes transactions reconciliation round
+	pub fn on_reconcildiff(&self, peer_index: PeerIndex, message: types::ReconcilDiff) {
+		trace!(target: "sync", "Got `reconcildiff` message from peer#{}", peer_index);
+		// announce transactions, missing from the peer set
+		let hashes = self.peers.take_reconciled_transactions(peer_index, &message);
+		if !hashes.is_empty() {
+			let inventory = hashes.into_iter().map(InventoryVector::tx).collect();
+			self.executor.execute(SynchronizationTask::Inventory(peer_index, types::Inv::with_inventory(inventory)));
+		}
+	}
+
 	/// When peer asks us to announce new blocks using cpmctblock message
 	pub fn on_send_compact(&self, peer_index: PeerIndex, message: types::SendCompact) {
 		trace!(target: "sync", "Got `sendcmpct` message from peer#{}", peer_index);
 
-		// The second integer SHALL be interpreted as a little-endian version number. Nodes sending a sendcmpct message MUST currently set this value to 1.
-		// TODO: version 2 supports segregated witness transactions
-		if message.second != 1 {
+		// The second integer SHALL be interpreted as a little-endian version number. Version 2 supports segregated witness transactions
+		if message.second != 1 && message.second != 2 {
 			return;
 		}
 
+		// compact blocks, announced by the peer, are using the highest version, supported by both sides
+		self.peers.set_compact_block_version(peer_index, message.second);
+
 		// Upon receipt of a "sendcmpct" message with the first and second integers set to 1, the node SHOULD announce new blocks by sending a cmpctblock message.
-		if message.first {
+		// TODO: we're only building version 1 compact blocks
+		if message.first && message.second == 1 {
 			self.peers.set_block_announcement_type(peer_index, BlockAnnouncementType::SendCompactBlock);
 		}
 
@@ -244,21 +424,141 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 	pub fn on_merkleblock(&self, peer_index: PeerIndex, _message: types::MerkleBlock) {
 		trace!(target: "sync", "Got `merkleblock` message from peer#{}", peer_index);
 		// we never setup filter on connections => misbehaving
-		self.peers.misbehaving(peer_index, "Got unrequested 'merkleblock' message");
+		self.peers.misbehaving(peer_index, 10, "Got unrequested 'merkleblock' message");
 	}
 
 	/// When peer sents us a compact block
-	pub fn on_compact_block(&self, peer_index: PeerIndex, _message: types::CompactBlock) {
-		trace!(target: "sync", "Got `cmpctblock` message from peer#{}", peer_index);
-		// we never ask compact block from peers => misbehaving
-		self.peers.misbehaving(peer_index, "Got unrequested 'cmpctblock' message");
+	pub fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock) {
+		// compact blocks are only useful when memory pool is filled => ignore while synchronizing
+		if self.state.synchronizing() {
+			trace!(target: "sync", "Ignored `cmpctblock` message from peer#{}", peer_index);
+			return;
+		}
+
+		let header: IndexedBlockHeader = message.header.header.clone().into();
+		trace!(target: "sync", "Got `cmpctblock` message from peer#{}. Block hash: {}", peer_index, header.hash.to_reversed_str());
+		if self.storage.contains_block(header.hash.clone().into()) {
+			return;
+		}
+
+		// do not waste time on scanning memory pool for block with invalid header
+		let pow_hash = self.consensus.proof_of_work.pow_hash(&header);
+		if !is_valid_proof_of_work(self.consensus.pow_limit, header.raw.bits, &pow_hash) {
+			self.peers.misbehaving(peer_index, 100, &format!("Got 'cmpctblock' message with invalid proof of work: {}", header.hash.to_reversed_str()));
+			return;
+		}
+
+		// transactions of the block are looked up in memory pool. Version 2 short ids are computed from witness hashes
+		let use_witness_hash = self.peers.compact_block_version(peer_index) == Some(2);
+		let block = {
+			let memory_pool = self.memory_pool.read();
+			let candidates = memory_pool.iter(OrderingStrategy::ByTimestamp).map(|entry| (&entry.hash, &*entry.transaction.raw));
+			PartialCompactBlock::new(message.header, use_witness_hash, candidates)
+		};
+
+		match block {
+			Ok(block) => self.on_partial_compact_block(peer_index, block),
+			Err(reason) => self.peers.misbehaving(peer_index, 100, &reason),
+		}
 	}
 
 	/// When peer sents us specific transactions for specific block
-	pub fn on_block_txn(&self, peer_index: PeerIndex, _message: types::BlockTxn) {
+	pub fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn) {
 		trace!(target: "sync", "Got `blocktxn` message from peer#{}", peer_index);
-		// we never ask for this => misbehaving
-		self.peers.misbehaving(peer_index, "Got unrequested 'blocktxn' message");
+
+		let block = self.compact_blocks.lock().remove(&peer_index);
+		let mut block = match block {
+			Some(block) => block,
+			None => {
+				// we only ask for transactions of compact blocks => misbehaving
+				self.peers.misbehaving(peer_index, 10, "Got unrequested 'blocktxn' message");
+				return;
+			},
+		};
+
+		if block.hash() != &message.request.blockhash {
+			self.peers.misbehaving(peer_index, 10, &format!("Got 'blocktxn' message for non-requested block: {}", message.request.blockhash.to_reversed_str()));
+			return;
+		}
+
+		if let Err(reason) = block.fill(message.request.transactions) {
+			self.peers.misbehaving(peer_index, 100, &reason);
+			return;
+		}
+
+		self.on_partial_compact_block(peer_index, block);
+	}
+
+	/// Requests missing transactions of compact block, or processes reconstructed block
+	fn on_partial_compact_block(&self, peer_index: PeerIndex, block: PartialCompactBlock) {
+		let missing_indexes = block.missing_indexes();
+		if !missing_indexes.is_empty() {
+			self.executor.execute(SynchronizationTask::GetBlockTxn(peer_index, types::GetBlockTxn {
+				request: BlockTransactionsRequest {
+					blockhash: block.hash().clone(),
+					indexes: missing_indexes,
+				},
+			}));
+			self.compact_blocks.lock().insert(peer_index, block);
+			return;
+		}
+
+		let block_hash = block.hash().clone();
+		match block.into_block() {
+			Some(block) => {
+				self.select_high_bandwidth_peer(peer_index);
+				self.client.on_block(peer_index, block);
+			},
+			None => {
+				// short ids have collided, or witness has been malleated => fallback to requesting full block.
+				// block itself could be valid, so it isn't marked as dead end
+				trace!(target: "sync", "Failed to reconstruct compact block {} from peer#{}", block_hash.to_reversed_str(), peer_index);
+				let inv_type = if self.peers.is_segwit_enabled(peer_index) {
+					InventoryType::MessageWitnessBlock
+				} else {
+					InventoryType::MessageBlock
+				};
+				self.executor.execute(SynchronizationTask::GetData(peer_index, types::GetData::with_inventory(vec![
+					InventoryVector {
+						inv_type: inv_type,
+						hash: block_hash,
+					},
+				])));
+			},
+		}
+	}
+
+	/// Asks peer, which has provided us with new block, to announce new blocks with `cmpctblock` messages.
+	/// Peer, which has provided new block least recently, is switched back to low-bandwidth mode
+	fn select_high_bandwidth_peer(&self, peer_index: PeerIndex) {
+		let version = match self.peers.compact_block_version(peer_index) {
+			Some(version) => version,
+			None => return,
+		};
+
+		let mut high_bandwidth_peers = self.high_bandwidth_peers.lock();
+		if let Some(position) = high_bandwidth_peers.iter().position(|index| *index == peer_index) {
+			high_bandwidth_peers.remove(position);
+			high_bandwidth_peers.push_back(peer_index);
+			return;
+		}
+
+		if high_bandwidth_peers.len() == MAX_HIGH_BANDWIDTH_PEERS {
+			if let Some(removed_peer_index) = high_bandwidth_peers.pop_front() {
+				if let Some(removed_version) = self.peers.compact_block_version(removed_peer_index) {
+					self.executor.execute(SynchronizationTask::SendCompact(removed_peer_index, types::SendCompact {
+						first: false,
+						second: removed_version,
+					}));
+				}
+			}
+		}
+
+		high_bandwidth_peers.push_back(peer_index);
+		self.executor.execute(SynchronizationTask::SendCompact(peer_index, types::SendCompact {
+			first: true,
+			second: version,
+		}));
 	}
 
 	/// Verify and then schedule new transaction
@@ -273,6 +573,119 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 		sink_data.wait()
 	}
 
+	/// Verifies transactions of the package, as if they were accepted to the memory pool one by one,
+	/// but never inserts them. Transactions may spend outputs of preceding package transactions.
+	pub fn test_accept_transactions(&self, transactions: Vec<Transaction>) -> Vec<TestAcceptResult> {
+		let verifier = self.chain_verifier();
+		let height = self.storage.best_block().number + 1;
+		let time = verifier.current_time();
+		let mut package: Vec<IndexedTransaction> = Vec::new();
+		transactions.into_iter()
+			.map(|transaction| {
+				let transaction: IndexedTransaction = transaction.into();
+				let result = self.verify_package_transaction(&verifier, &package, height, time, &transaction, true);
+				let test_result = TestAcceptResult {
+					hash: transaction.hash.clone(),
+					witness_hash: transaction.raw.witness_hash(),
+					vsize: transaction.raw.vsize(),
+					result: result,
+				};
+				if test_result.result.is_ok() {
+					package.push(transaction);
+				}
+				test_result
+			})
+			.collect()
+	}
+
+	/// Verifies transactions of the package and adds them to the memory pool, only if all of them are valid.
+	/// Package must consist of the child transaction, preceded by its parents. Memory pool minimum fee is
+	/// checked for the whole package, so the child may pay for parents, which can't enter memory pool alone.
+	pub fn accept_package(&self, transactions: Vec<Transaction>) -> PackageAcceptResult {
+		let transactions: Vec<IndexedTransaction> = transactions.into_iter().map(Into::into).collect();
+		if transactions.len() > MAX_PACKAGE_COUNT {
+			return PackageAcceptResult::rejected(Vec::new(), "package-too-many-transactions");
+		}
+		if !is_child_with_parents(&transactions) {
+			return PackageAcceptResult::rejected(Vec::new(), "package-not-child-with-parents");
+		}
+
+		let verifier = self.chain_verifier();
+		let height = self.storage.best_block().number + 1;
+		let time = verifier.current_time();
+		let mut package: Vec<IndexedTransaction> = Vec::new();
+		let mut package_fee = 0u64;
+		let mut package_vsize = 0usize;
+		let mut results = Vec::with_capacity(transactions.len());
+		for transaction in transactions {
+			// parents, which are already in the memory pool, are not verified again
+			let memory_pool_fee = self.memory_pool_entry(&transaction.hash).map(|entry| entry.miner_fee as u64);
+			let result = match memory_pool_fee {
+				Some(fee) => Ok(fee),
+				None => self.verify_package_transaction(&verifier, &package, height, time, &transaction, false),
+			};
+			if let (None, &Ok(fee)) = (memory_pool_fee, &result) {
+				package_fee += fee;
+				package_vsize += transaction.raw.vsize();
+				package.push(transaction.clone());
+			}
+			results.push(TestAcceptResult {
+				hash: transaction.hash.clone(),
+				witness_hash: transaction.raw.witness_hash(),
+				vsize: transaction.raw.vsize(),
+				result: result,
+			});
+		}
+
+		if results.iter().any(|result| result.result.is_err()) {
+			return PackageAcceptResult::rejected(results, "transaction failed");
+		}
+		if package.is_empty() {
+			return PackageAcceptResult::accepted(results);
+		}
+
+		let minimum_fee_rate = self.memory_pool.read().minimum_fee_rate();
+		if package_fee < minimum_fee_rate * package_vsize as u64 / 1000 {
+			return PackageAcceptResult::rejected(results, "package-fee-too-low");
+		}
+
+		trace!(target: "sync", "Package of {} transactions is accepted", package.len());
+		match self.client.accept_verified_package(package, package_fee / package_vsize as u64) {
+			Ok(_) => PackageAcceptResult::accepted(results),
+			Err(err) => PackageAcceptResult::rejected(results, &err),
+		}
+	}
+
+	/// Verifies single transaction of the package. Returns fee of the transaction or reason of its rejection.
+	fn chain_verifier(&self) -> ChainVerifier {
+		ChainVerifier::new(self.storage.clone(), self.consensus.clone())
+			.with_time_source(self.time_source.clone())
+	}
+
+	fn verify_package_transaction(&self, verifier: &ChainVerifier, package: &[IndexedTransaction], height: u32, time: u32, transaction: &IndexedTransaction, check_minimum_fee: bool) -> Result<u64, String> {
+		if self.memory_pool.read().contains(&transaction.hash) || package.iter().any(|tx| tx.hash == transaction.hash) {
+			return Err("txn-already-in-mempool".into());
+		}
+
+		let memory_pool_provider = if check_minimum_fee {
+			MemoryPoolTransactionOutputProvider::for_transaction(self.storage.clone(), &self.memory_pool, &transaction.raw)
+		} else {
+			MemoryPoolTransactionOutputProvider::for_package_transaction(self.storage.clone(), &self.memory_pool, &transaction.raw)
+		}.map_err(|error| error.reject_reason().to_owned())?;
+		let provider = PackageTransactionOutputProvider::new(package, &memory_pool_provider);
+		verifier.verify_mempool_transaction(self.storage.as_block_header_provider(), &provider, height, time, transaction)
+			.map_err(|error| {
+				trace!(target: "sync", "Tested transaction {} is rejected: {:?}", transaction.hash.to_reversed_str(), error);
+				error.reject_reason().to_owned()
+			})?;
+
+		// all inputs are known, since the transaction is verified
+		let inputs_sum = transaction.raw.inputs.iter()
+			.filter_map(|input| provider.transaction_output(&input.previous_output, usize::max_value()))
+			.fold(0, |sum, output| sum + output.value);
+		Ok(inputs_sum - transaction.raw.total_spends())
+	}
+
 	/// Get block template for mining
 	pub fn get_block_template(&self) -> BlockTemplate {
 		let height = self.storage.best_block().number;
@@ -282,13 +695,154 @@ impl<T, U, V> LocalNode<T, U, V> where T: TaskExecutor, U: Server, V: Client {
 			max_block_sigops: self.consensus.fork.max_block_sigops(height, max_block_size) as u32,
 		};
 		let memory_pool = &*self.memory_pool.read();
-		block_assembler.create_new_block(&self.storage, memory_pool, time::get_time().sec as u32, &self.consensus)
+		block_assembler.create_new_block(&self.storage, memory_pool, &self.deployments, self.time_source.now(), &self.consensus)
+	}
+
+	/// Get state of the block template: best block hash and memory pool transactions counter.
+	/// Template should be refreshed, when state changes.
+	pub fn block_template_state(&self) -> (H256, u64) {
+		(self.storage.best_block().hash, self.memory_pool.read().transactions_counter())
+	}
+
+	/// Verify block proposal (BIP23). Block is verified as the child of the best block, but it is never
+	/// inserted into the database. Returns None if block is valid, or BIP23 reason of rejection.
+	pub fn verify_block_proposal(&self, block: IndexedBlock) -> Option<String> {
+		if self.storage.contains_block(block.hash().clone().into()) {
+			return Some("duplicate".into());
+		}
+
+		let previous_header_hash = &block.header.raw.previous_header_hash;
+		if !self.storage.contains_block(previous_header_hash.clone().into()) {
+			return Some("bad-prevblk".into());
+		}
+		if *previous_header_hash != self.storage.best_block().hash {
+			return Some("inconclusive-not-best-prevblk".into());
+		}
+
+		let verifier = self.chain_verifier();
+		verifier.verify_block_proposal(&block)
+			.err()
+			.map(|error| error.proposal_reject_reason().to_owned())
+	}
+
+	/// Fully verifies submitted block and then passes it to the synchronization client, which
+	/// stores and relays it. Returns BIP22 reject reason if the block is rejected.
+	pub fn submit_block(&self, block: IndexedBlock) -> Option<String> {
+		if self.storage.contains_block(block.hash().clone().into()) {
+			return Some("duplicate".into());
+		}
+
+		// side chain blocks are accepted too, but the parent must be already stored
+		if !self.storage.contains_block(block.header.raw.previous_header_hash.clone().into()) {
+			return Some("bad-prevblk".into());
+		}
+
+		let verifier = self.chain_verifier();
+		if let Err(error) = verifier.verify(VerificationLevel::Full, &block) {
+			trace!(target: "sync", "Submitted block {} is rejected: {:?}", block.hash().to_reversed_str(), error);
+			return Some(error.reject_reason().to_owned());
+		}
+
+		trace!(target: "sync", "Submitted block {} is accepted", block.hash().to_reversed_str());
+		self.client.on_block(SUBMITTED_BLOCKS_PEER_INDEX, block);
+		None
+	}
+
+	/// Marks stored block invalid, so that the chain with the most work without this block becomes canon.
+	pub fn invalidate_block(&self, hash: &H256) -> Result<(), String> {
+		self.client.invalidate_block(hash)
+	}
+
+	/// Removes invalid marks of the stored block, its ancestors and descendants, so that the valid
+	/// chain with the most work becomes canon.
+	pub fn reconsider_block(&self, hash: &H256) -> Result<(), String> {
+		self.client.reconsider_block(hash)
+	}
+
+	/// Treats stored block as if it has been seen before the canon block with the same chain work.
+	pub fn precious_block(&self, hash: &H256) -> Result<(), String> {
+		self.client.precious_block(hash)
+	}
+
+	/// Get memory pool transactions. Ancestors are always returned before descendant transactions.
+	pub fn memory_pool_transactions(&self) -> Vec<Transaction> {
+		self.memory_pool.read().iter(OrderingStrategy::ByTimestamp)
+			.map(|entry| (*entry.transaction.raw).clone())
+			.collect()
+	}
+
+	/// Get memory pool transaction with given hash
+	pub fn memory_pool_transaction(&self, hash: &H256) -> Option<Transaction> {
+		self.memory_pool.read().read_by_hash(hash).cloned()
+	}
+
+	/// Get hashes of all memory pool transactions
+	pub fn memory_pool_hashes(&self) -> Vec<H256> {
+		self.memory_pool.read().get_transactions_ids()
+	}
+
+	/// Get information on memory pool state
+	pub fn memory_pool_information(&self) -> MemoryPoolInformation {
+		self.memory_pool.read().information()
+	}
+
+	/// Get information on memory pool transaction
+	pub fn memory_pool_entry(&self, hash: &H256) -> Option<MemoryPoolEntryInformation> {
+		self.memory_pool.read().entry_information(hash)
+	}
+
+	/// Get hashes of in-pool ancestors of memory pool transaction
+	pub fn memory_pool_ancestors(&self, hash: &H256) -> Option<Vec<H256>> {
+		self.memory_pool.read().ancestors(hash)
+	}
+
+	/// Get hashes of in-pool descendants of memory pool transaction
+	pub fn memory_pool_descendants(&self, hash: &H256) -> Option<Vec<H256>> {
+		self.memory_pool.read().descendants(hash)
+	}
+
+	/// Estimate fee rate, required for transaction to be confirmed within `target` blocks
+	pub fn estimate_smart_fee(&self, target: u32, conservative: bool) -> Option<FeeEstimate> {
+		self.memory_pool.read().fee_estimator().estimate_smart_fee(target, conservative)
+	}
+
+	/// Estimate fee rate, required for transaction to be confirmed within `target` blocks, using single time horizon
+	pub fn estimate_raw_fee(&self, target: u32, threshold: f64, horizon: FeeEstimateHorizon) -> Option<RawFeeEstimate> {
+		self.memory_pool.read().fee_estimator().estimate_raw_fee(target, threshold, horizon)
+	}
+
+	/// Get copy of the fee estimator, e.g. to save its statistics
+	pub fn fee_estimator(&self) -> FeeEstimator {
+		self.memory_pool.read().fee_estimator().clone()
+	}
+
+	/// Replace fee estimator with the one, restored from saved statistics.
+	/// Should be called before any transaction is accepted.
+	pub fn set_fee_estimator(&self, fee_estimator: FeeEstimator) {
+		*self.memory_pool.write().fee_estimator_mut() = fee_estimator;
 	}
 
 	/// Install synchronization events listener
 	pub fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.client.install_sync_listener(listener);
 	}
+
+	/// Get synchronization-related information on the connected peer
+	pub fn peer_sync_information(&self, peer_index: PeerIndex) -> PeerSyncInformation {
+		PeerSyncInformation {
+			blocks_in_flight: self.client.blocks_in_flight(peer_index),
+			min_fee_rate: self.peers.fee_filter(peer_index),
+		}
+	}
+
+	/// Get current synchronization status
+	pub fn sync_status(&self) -> SyncStatus {
+		SyncStatus {
+			is_synchronizing: self.state.synchronizing(),
+			best_block: self.storage.best_block(),
+			best_header: self.client.best_block_header(),
+		}
+	}
 }
 
 impl TransactionAcceptSink {
@@ -338,10 +892,11 @@ pub mod tests {
 	use synchronization_client::SynchronizationClient;
 	use synchronization_client_core::{Config, SynchronizationClientCore, CoreVerificationSink};
 	use synchronization_chain::Chain;
-	use message::types;
-	use message::common::{InventoryVector, InventoryType};
+	use std::collections::HashSet;
+	use message::{types, Services};
+	use message::common::{InventoryVector, InventoryType, BlockTransactionsRequest, BlockTransactions};
 	use network::{ConsensusParams, ConsensusFork, Magic};
-	use chain::Transaction;
+	use chain::{Transaction, IndexedBlock};
 	use db::{BlockChainDatabase};
 	use miner::MemoryPool;
 	use super::LocalNode;
@@ -349,10 +904,14 @@ pub mod tests {
 	use synchronization_server::tests::DummyServer;
 	use synchronization_verifier::tests::DummyVerifier;
 	use primitives::bytes::Bytes;
+	use primitives::hash::H256;
+	use primitives::bigint::U256;
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 	use std::iter::repeat;
-	use synchronization_peers::PeersImpl;
-	use utils::SynchronizationState;
+	use synchronization_peers::{PeersImpl, PeersContainer, PeersOptions};
+	use inbound_connection::tests::DummyOutboundSyncConnection;
+	use p2p::{ConnectionType, PeerPermissions};
+	use utils::{SynchronizationState, build_compact_block};
 	use types::SynchronizationStateRef;
 
 	pub fn default_filterload() -> types::FilterLoad {
@@ -378,7 +937,7 @@ pub mod tests {
 		let sync_peers = Arc::new(PeersImpl::default());
 		let executor = DummyTaskExecutor::new();
 		let server = Arc::new(DummyServer::new());
-		let config = Config { close_connection_on_bad_block: true };
+		let config = Config { close_connection_on_bad_block: true, verification_edge: H256::default(), minimum_chain_work: U256::zero() };
 		let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork)));
 		let client_core = SynchronizationClientCore::new(config, sync_state.clone(), sync_peers.clone(), executor.clone(), chain, chain_verifier);
 		let mut verifier = match verifier {
@@ -394,7 +953,7 @@ pub mod tests {
 	#[test]
 	fn local_node_serves_block() {
 		let (_, server, local_node) = create_local_node(None);
-		let peer_index = 0; local_node.on_connect(peer_index, "test".into(), types::Version::default());
+		let peer_index = 0; local_node.on_connect(peer_index, "test".into(), types::Version::default(), ConnectionType::OutboundFullRelay, PeerPermissions::default());
 		// peer requests genesis block
 		let genesis_block_hash = test_data::genesis().hash();
 		let inventory = vec![
@@ -416,7 +975,7 @@ pub mod tests {
 		let (executor, _, local_node) = create_local_node(None);
 
 		// transaction will be relayed to this peer
-		let peer_index1 = 0; local_node.on_connect(peer_index1, "test".into(), types::Version::default());
+		let peer_index1 = 0; local_node.on_connect(peer_index1, "test".into(), types::Version::default(), ConnectionType::OutboundFullRelay, PeerPermissions::default());
 		executor.take_tasks();
 
 		let genesis = test_data::genesis();
@@ -426,7 +985,8 @@ pub mod tests {
 		let result = local_node.accept_transaction(transaction.clone());
 		assert_eq!(result, Ok(transaction_hash.clone()));
 
-		assert_eq!(executor.take_tasks(), vec![Task::RelayNewTransaction(transaction.into(), 83333333)]);
+		assert_eq!(executor.take_tasks(), vec![Task::RelayNewTransaction(transaction.clone().into(), 83333333)]);
+		assert_eq!(local_node.memory_pool_transactions(), vec![transaction]);
 	}
 
 	#[test]
@@ -441,7 +1001,7 @@ pub mod tests {
 
 		let (executor, _, local_node) = create_local_node(Some(verifier));
 
-		let peer_index1 = 0; local_node.on_connect(peer_index1, "test".into(), types::Version::default());
+		let peer_index1 = 0; local_node.on_connect(peer_index1, "test".into(), types::Version::default(), ConnectionType::OutboundFullRelay, PeerPermissions::default());
 		executor.take_tasks();
 
 		let result = local_node.accept_transaction(transaction);
@@ -449,4 +1009,234 @@ pub mod tests {
 
 		assert_eq!(executor.take_tasks(), vec![]);
 	}
+
+	#[test]
+	fn local_node_asks_for_compact_blocks() {
+		let (executor, _, local_node) = create_local_node(None);
+		let version = types::Version::V0(types::version::V0 {
+			version: 70015,
+			services: Services::default().with_witness(true),
+			..Default::default()
+		});
+
+		local_node.on_connect(0, "test".into(), version, ConnectionType::OutboundFullRelay, PeerPermissions::default());
+		let tasks = executor.take_tasks();
+		assert!(tasks.contains(&Task::SendCompact(0, types::SendCompact { first: false, second: 2 })));
+		assert!(tasks.contains(&Task::SendCompact(0, types::SendCompact { first: false, second: 1 })));
+	}
+
+	#[test]
+	fn local_node_limits_high_bandwidth_compact_block_peers() {
+		let (executor, _, local_node) = create_local_node(None);
+		for peer_index in 0..4 {
+			local_node.peers.insert(peer_index, Services::default(), DummyOutboundSyncConnection::new());
+			local_node.on_send_compact(peer_index, types::SendCompact { first: false, second: 1 });
+			local_node.on_send_compact(peer_index, types::SendCompact { first: false, second: 2 });
+		}
+		assert_eq!(local_node.peers.compact_block_version(0), Some(2));
+
+		for peer_index in 0..3 {
+			local_node.select_high_bandwidth_peer(peer_index);
+		}
+		assert_eq!(executor.take_tasks(), vec![
+			Task::SendCompact(0, types::SendCompact { first: true, second: 2 }),
+			Task::SendCompact(1, types::SendCompact { first: true, second: 2 }),
+			Task::SendCompact(2, types::SendCompact { first: true, second: 2 }),
+		]);
+
+		// peer, which is already in high-bandwidth mode, is not asked again
+		local_node.select_high_bandwidth_peer(0);
+		assert_eq!(executor.take_tasks(), vec![]);
+
+		// least recently selected peer is switched to low-bandwidth mode
+		local_node.select_high_bandwidth_peer(3);
+		assert_eq!(executor.take_tasks(), vec![
+			Task::SendCompact(1, types::SendCompact { first: false, second: 2 }),
+			Task::SendCompact(3, types::SendCompact { first: true, second: 2 }),
+		]);
+	}
+
+	#[test]
+	fn local_node_rejects_compact_block_with_invalid_proof_of_work() {
+		let (executor, _, local_node) = create_local_node(None);
+		local_node.peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		// test block header bits are above mainnet proof of work limit
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().output().value(10).build().build()
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build()
+			.into();
+		let message = types::CompactBlock { header: build_compact_block(&block, vec![0].into_iter().collect()) };
+		local_node.on_compact_block(0, message);
+		assert_eq!(executor.take_tasks(), vec![]);
+		assert!(local_node.peers.enumerate().is_empty());
+	}
+
+	#[test]
+	fn local_node_asks_for_headers_announcements() {
+		let (executor, _, local_node) = create_local_node(None);
+		let version = types::Version::V0(types::version::V0 {
+			version: 70012,
+			..Default::default()
+		});
+
+		local_node.on_connect(0, "test".into(), version, ConnectionType::OutboundFullRelay, PeerPermissions::default());
+		let tasks = executor.take_tasks();
+		assert!(tasks.contains(&Task::SendHeaders(0)));
+		assert!(!tasks.iter().any(|task| match *task { Task::SendCompact(_, _) => true, _ => false }));
+	}
+
+	#[test]
+	fn local_node_offers_transaction_reconciliation() {
+		let (executor, _, local_node) = create_local_node(None);
+		let local_node = local_node.with_transaction_reconciliation(true);
+		local_node.peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		local_node.on_connect(0, "test".into(), types::Version::default(), ConnectionType::OutboundFullRelay, PeerPermissions::default());
+		let tasks = executor.take_tasks();
+		assert!(tasks.iter().any(|task| match *task { Task::SendTxRcncl(0, ref message) => message.version == 1, _ => false }));
+	}
+
+	#[test]
+	fn local_node_does_not_reconcile_transactions_over_block_relay_only_connection() {
+		let (executor, _, local_node) = create_local_node(None);
+		let local_node = local_node.with_transaction_reconciliation(true);
+		local_node.peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		local_node.on_connect(0, "test".into(), types::Version::default(), ConnectionType::BlockRelayOnly, PeerPermissions::default());
+		let tasks = executor.take_tasks();
+		assert!(!tasks.iter().any(|task| match *task { Task::SendTxRcncl(_, _) => true, _ => false }));
+	}
+
+	#[test]
+	fn local_node_serves_bloom_filters_to_whitelisted_peers_only() {
+		let (_, _, local_node) = create_local_node(None);
+		let permissions = PeerPermissions { bloomfilter: true, ..PeerPermissions::default() };
+		local_node.peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		local_node.peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		local_node.on_connect(0, "test".into(), types::Version::default(), ConnectionType::Inbound, PeerPermissions::default());
+		local_node.on_connect(1, "test".into(), types::Version::default(), ConnectionType::Inbound, permissions);
+
+		local_node.on_filterload(0, default_filterload());
+		local_node.on_filterload(1, default_filterload());
+		assert_eq!(local_node.peers.enumerate(), vec![1]);
+	}
+
+	#[test]
+	fn local_node_does_not_punish_noban_peers() {
+		let (_, _, local_node) = create_local_node(None);
+		local_node.peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		local_node.on_connect(0, "test".into(), types::Version::default(), ConnectionType::Inbound, PeerPermissions::implicit());
+
+		local_node.peers.misbehaving(0, 100, "test");
+		local_node.peers.dos(0, "test");
+		assert_eq!(local_node.peers.enumerate(), vec![0]);
+	}
+
+	#[test]
+	fn local_node_reconstructs_compact_block() {
+		let genesis = test_data::genesis();
+		let block = test_data::block_builder()
+			.transaction().coinbase().output().value(10).build().build()
+			.transaction().output().value(20).build().build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+		let prefilled: HashSet<_> = vec![0].into_iter().collect();
+		let compact_block = build_compact_block(&block.clone().into(), prefilled);
+
+		// test block header is only valid for unit tests network
+		let (executor, _, mut local_node) = create_local_node(None);
+		local_node.consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+		let peer_index = 0; local_node.on_connect(peer_index, "test".into(), types::Version::default(), ConnectionType::OutboundFullRelay, PeerPermissions::default());
+		executor.take_tasks();
+
+		// transaction is not in memory pool => it is requested from peer
+		local_node.on_compact_block(peer_index, types::CompactBlock { header: compact_block });
+		assert_eq!(executor.take_tasks(), vec![Task::GetBlockTxn(peer_index, types::GetBlockTxn {
+			request: BlockTransactionsRequest {
+				blockhash: block.hash(),
+				indexes: vec![1],
+			},
+		})]);
+
+		// block is reconstructed after transaction is received
+		local_node.on_block_txn(peer_index, types::BlockTxn {
+			request: BlockTransactions {
+				blockhash: block.hash(),
+				transactions: vec![block.transactions[1].clone()],
+			},
+		});
+		assert!(local_node.compact_blocks.lock().is_empty());
+		assert!(executor.take_tasks().iter().all(|task| match *task {
+			Task::GetData(_, _) => false,
+			_ => true,
+		}));
+	}
+
+	#[test]
+	fn local_node_verifies_block_proposal() {
+		let (_, _, local_node) = create_local_node(None);
+		assert_eq!(local_node.verify_block_proposal(test_data::block_h1().into()), None);
+		assert_eq!(local_node.verify_block_proposal(test_data::genesis().into()), Some("duplicate".into()));
+		assert_eq!(local_node.verify_block_proposal(test_data::block_h2().into()), Some("bad-prevblk".into()));
+
+		// proof of work isn't checked, but the rest of the block is
+		let mut block = test_data::block_h1();
+		block.block_header.merkle_root_hash = Default::default();
+		assert_eq!(local_node.verify_block_proposal(block.into()), Some("bad-txnmrklroot".into()));
+	}
+
+	#[test]
+	fn local_node_submits_block() {
+		let (_, _, local_node) = create_local_node(None);
+		assert_eq!(local_node.submit_block(test_data::block_h2().into()), Some("bad-prevblk".into()));
+
+		// unlike proposals, proof of work is checked
+		let mut block = test_data::block_h1();
+		block.block_header.nonce += 1;
+		assert_eq!(local_node.submit_block(block.into()), Some("high-hash".into()));
+
+		assert_eq!(local_node.submit_block(test_data::block_h1().into()), None);
+		assert_eq!(local_node.storage.best_block().number, 1);
+		assert_eq!(local_node.submit_block(test_data::block_h1().into()), Some("duplicate".into()));
+	}
+
+	#[test]
+	fn local_node_tests_transactions_acceptance() {
+		let (_, _, local_node) = create_local_node(None);
+		let genesis = test_data::genesis();
+		let in_pool: Transaction = test_data::TransactionBuilder::with_output(1).add_input(&genesis.transactions[0], 0).into();
+		local_node.memory_pool.write().insert_verified(in_pool.clone().into());
+		let missing_inputs: Transaction = test_data::TransactionBuilder::with_output(2).add_input(&test_data::block_h1().transactions[0], 0).into();
+
+		let results = local_node.test_accept_transactions(vec![in_pool.clone(), missing_inputs.clone()]);
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].hash, in_pool.hash());
+		assert_eq!(results[0].result, Err("txn-already-in-mempool".into()));
+		assert_eq!(results[1].hash, missing_inputs.hash());
+		assert_eq!(results[1].vsize, missing_inputs.vsize());
+		assert_eq!(results[1].result, Err("bad-txns-inputs-missingorspent".into()));
+
+		// nothing is inserted into the memory pool
+		assert_eq!(local_node.memory_pool_transactions(), vec![in_pool]);
+	}
+
+	#[test]
+	fn local_node_rejects_package() {
+		let (_, _, local_node) = create_local_node(None);
+		let block_h1 = test_data::block_h1();
+		let parent: Transaction = test_data::TransactionBuilder::with_output(1).add_input(&block_h1.transactions[0], 0).into();
+		let child: Transaction = test_data::TransactionBuilder::with_output(2).add_input(&parent, 0).into();
+		let unrelated: Transaction = test_data::TransactionBuilder::with_output(3).add_input(&block_h1.transactions[0], 0).into();
+
+		let result = local_node.accept_package(vec![unrelated, child.clone()]);
+		assert!(result.transactions.is_empty());
+		assert_eq!(result.reject_reason, Some("package-not-child-with-parents".into()));
+
+		// parent spends unknown output, so the child is rejected too
+		let result = local_node.accept_package(vec![parent.clone(), child.clone()]);
+		assert_eq!(result.transactions.len(), 2);
+		assert_eq!(result.transactions[0].result, Err("bad-txns-inputs-missingorspent".into()));
+		assert_eq!(result.transactions[1].result, Err("bad-txns-inputs-missingorspent".into()));
+		assert_eq!(result.reject_reason, Some("transaction failed".into()));
+		assert!(local_node.memory_pool_transactions().is_empty());
+	}
 }
@@ -3,13 +3,15 @@ use std::fmt;
 use linked_hash_map::LinkedHashMap;
 use chain::{BlockHeader, Transaction, IndexedBlockHeader, IndexedBlock, IndexedTransaction};
 use db;
-use miner::{MemoryPoolOrderingStrategy, MemoryPoolInformation};
+use miner::{MemoryPoolOrderingStrategy, MemoryPoolInformation, checked_transaction_fee};
 use network::ConsensusParams;
 use primitives::bytes::Bytes;
 use primitives::hash::H256;
+use primitives::bigint::U256;
 use utils::{BestHeadersChain, BestHeadersChainInformation, HashQueueChain, HashPosition};
 use types::{BlockHeight, StorageRef, MemoryPoolRef};
 use verification::Deployments;
+use logs;
 
 /// Index of 'verifying' queue
 const VERIFYING_QUEUE: usize = 0;
@@ -19,12 +21,16 @@ const REQUESTED_QUEUE: usize = 1;
 const SCHEDULED_QUEUE: usize = 2;
 /// Number of hash queues
 const NUMBER_OF_QUEUES: usize = 3;
+/// Scripts are only assumed to be valid if block is at least two weeks older than the best header
+const ASSUME_VALID_MIN_AGE_S: u32 = 14 * 24 * 60 * 60;
 
 /// Block insertion result
 #[derive(Default, PartialEq)]
 pub struct BlockInsertionResult {
 	/// Hashes of blocks, which were canonized during this insertion procedure. Order matters
 	pub canonized_blocks_hashes: Vec<H256>,
+	/// Hashes of blocks, which were decanonized during this insertion procedure, starting with the old best block
+	pub decanonized_blocks_hashes: Vec<H256>,
 	/// Transaction to 'reverify'. Order matters
 	pub transactions_to_reverify: Vec<IndexedTransaction>,
 }
@@ -33,6 +39,7 @@ impl fmt::Debug for BlockInsertionResult {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("BlockInsertionResult")
 			.field("canonized_blocks_hashes", &self.canonized_blocks_hashes.iter().map(H256::reversed).collect::<Vec<_>>())
+			.field("decanonized_blocks_hashes", &self.decanonized_blocks_hashes.iter().map(H256::reversed).collect::<Vec<_>>())
 			.field("transactions_to_reverify", &self.transactions_to_reverify)
 			.finish()
 	}
@@ -43,6 +50,7 @@ impl BlockInsertionResult {
 	pub fn with_canonized_blocks(canonized_blocks_hashes: Vec<H256>) -> Self {
 		BlockInsertionResult {
 			canonized_blocks_hashes: canonized_blocks_hashes,
+			decanonized_blocks_hashes: Vec::new(),
 			transactions_to_reverify: Vec::new(),
 		}
 	}
@@ -155,7 +163,7 @@ impl Chain {
 		let deployments = Deployments::new();
 		let is_segwit_active = deployments.segwit(best_storage_block.number, storage.as_block_header_provider(), &consensus);
 
-		Chain {
+		let mut chain = Chain {
 			genesis_block_hash: genesis_block_hash,
 			best_storage_block: best_storage_block,
 			storage: storage,
@@ -167,7 +175,41 @@ impl Chain {
 			dead_end_blocks: HashSet::new(),
 			deployments: deployments,
 			is_segwit_active: is_segwit_active,
+		};
+		chain.schedule_stored_headers();
+		chain
+	}
+
+	/// Schedule blocks of the best headers from the header tree, so that headers-first synchronization
+	/// is resumed after restart. Only headers, descending from the best storage block, are scheduled
+	fn schedule_stored_headers(&mut self) {
+		let mut entry = self.storage.best_header_entry();
+		if entry.number <= self.best_storage_block.number {
+			return;
 		}
+
+		let mut headers = Vec::with_capacity((entry.number - self.best_storage_block.number) as usize);
+		while entry.number > self.best_storage_block.number {
+			if entry.status.has_block {
+				return;
+			}
+
+			let parent_hash = entry.header.previous_header_hash.clone();
+			headers.push(IndexedBlockHeader::from(entry.header));
+			entry = match self.storage.header_entry(&parent_hash) {
+				Some(entry) => entry,
+				None => return,
+			};
+		}
+
+		if headers.last().map(|header| &header.raw.previous_header_hash) != Some(&self.best_storage_block.hash) {
+			return;
+		}
+
+		headers.reverse();
+		trace!(target: "sync", "Scheduling {} blocks of the stored headers", headers.len());
+		self.hash_chain.push_back_n_at(SCHEDULED_QUEUE, headers.iter().map(|h| h.hash.clone()).collect());
+		self.headers_chain.insert_n(headers);
 	}
 
 	/// Get information on current blockchain state
@@ -278,6 +320,36 @@ impl Chain {
 		self.headers_chain.by_hash(hash)
 	}
 
+	/// Get total work of the best headers chain
+	pub fn best_block_header_work(&self) -> U256 {
+		let storage_work = self.storage.chain_work(&self.best_storage_block.hash).unwrap_or_default();
+		storage_work + self.headers_chain.best_work()
+	}
+
+	/// Are scripts of the block assumed to be valid? They are if the block is an ancestor of the verification
+	/// edge (or the edge itself) in the best headers chain, this chain has at least `minimum_chain_work`
+	/// and the block is at least two weeks older than the best header.
+	pub fn is_assumed_valid(&self, hash: &H256, verification_edge: &H256, minimum_chain_work: &U256) -> bool {
+		let (header, height) = match (self.headers_chain.by_hash(hash), self.headers_chain.height(hash)) {
+			(Some(header), Some(height)) => (header, height),
+			_ => return false,
+		};
+		match self.headers_chain.height(verification_edge) {
+			Some(edge_height) if height <= edge_height => (),
+			_ => return false,
+		}
+
+		let best_header = match self.headers_chain.by_hash(&self.headers_chain.best_block_hash()) {
+			Some(best_header) => best_header,
+			None => return false,
+		};
+		if best_header.raw.time.saturating_sub(header.raw.time) < ASSUME_VALID_MIN_AGE_S {
+			return false;
+		}
+
+		self.best_block_header_work() >= *minimum_chain_work
+	}
+
 	/// Get block state
 	pub fn block_state(&self, hash: &H256) -> BlockState {
 		match self.hash_chain.contains_in(hash) {
@@ -312,6 +384,11 @@ impl Chain {
 
 	/// Schedule blocks hashes for requesting
 	pub fn schedule_blocks_headers(&mut self, headers: Vec<IndexedBlockHeader>) {
+		// verified headers are kept in the header tree, so that they're known before blocks are stored.
+		// Headers, descending from blocks, which are still verified, are only kept in memory
+		if let Err(error) = self.storage.insert_headers(&headers) {
+			trace!(target: "sync", "Headers are not stored in the header tree: {:?}", error);
+		}
 		self.hash_chain.push_back_n_at(SCHEDULED_QUEUE, headers.iter().map(|h| h.hash.clone()).collect());
 		self.headers_chain.insert_n(headers);
 	}
@@ -350,8 +427,20 @@ impl Chain {
 		self.dead_end_blocks.insert(hash.clone());
 	}
 
+	/// Mark block with invalid header as dead end. Header and its descendants are also marked invalid
+	/// in the header tree. Blocks with valid headers could be malleated, so they're only marked dead end
+	pub fn mark_invalid_header(&mut self, hash: &H256) {
+		self.mark_dead_end_block(hash);
+		// invalid header isn't known to the header tree, unless it has been verified before
+		match self.storage.invalidate_header(hash) {
+			Ok(()) | Err(db::Error::UnknownBlock) => (),
+			Err(error) => trace!(target: "sync", "Header {} is not marked invalid in the header tree: {:?}", hash.to_reversed_str(), error),
+		}
+	}
+
 	/// Insert new best block to storage
 	pub fn insert_best_block(&mut self, block: IndexedBlock) -> Result<BlockInsertionResult, db::Error> {
+		let _span = logs::span("commit_block");
 		assert_eq!(Some(self.storage.best_block().hash), self.storage.block_hash(self.storage.best_block().number));
 		let block_origin = self.storage.block_origin(&block.header)?;
 		trace!(target: "sync", "insert_best_block {:?} origin: {:?}", block.hash().reversed(), block_origin);
@@ -379,18 +468,25 @@ impl Chain {
 				// all transactions from this block were accepted
 				// => delete accepted transactions from verification queue and from the memory pool
 				// + also remove transactions which spent outputs which have been spent by transactions from the block
+				// (fee estimator records them as failed to confirm)
 				let mut memory_pool = self.memory_pool.write();
+				let block_transactions_hashes = block.transactions.iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>();
+				memory_pool.fee_estimator_mut().process_block(self.best_storage_block.number, &block_transactions_hashes);
+				memory_pool.note_new_best_block();
 				for tx in &block.transactions {
 					memory_pool.remove_by_hash(&tx.hash);
 					self.verifying_transactions.remove(&tx.hash);
 					for tx_input in &tx.raw.inputs {
-						memory_pool.remove_by_prevout(&tx_input.previous_output);
+						for conflicting_tx in memory_pool.remove_by_prevout(&tx_input.previous_output).unwrap_or_default() {
+							memory_pool.fee_estimator_mut().remove_transaction(&conflicting_tx.hash, true);
+						}
 					}
 				}
 				// no transactions to reverify, because we have just appended new transactions to the blockchain
 
 				Ok(BlockInsertionResult {
 					canonized_blocks_hashes: vec![block.hash().clone()],
+					decanonized_blocks_hashes: Vec::new(),
 					transactions_to_reverify: Vec::new(),
 				})
 			},
@@ -411,51 +507,22 @@ impl Chain {
 
 				// all transactions from this block were accepted
 				// + all transactions from previous blocks of this fork were accepted
-				// => delete accepted transactions from verification queue and from the memory pool
 				let this_block_transactions_hashes = block.transactions.iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>();
 				let mut canonized_blocks_hashes = origin.canonized_route.clone();
 				let new_main_blocks_transactions_hashes = origin.canonized_route.into_iter()
 					.flat_map(|block_hash| self.storage.block_transaction_hashes(block_hash.into()))
 					.collect::<Vec<_>>();
 
-				let mut memory_pool = self.memory_pool.write();
-				for transaction_accepted in this_block_transactions_hashes.into_iter().chain(new_main_blocks_transactions_hashes.into_iter()) {
-					memory_pool.remove_by_hash(&transaction_accepted);
-					self.verifying_transactions.remove(&transaction_accepted);
-				}
-
-				// reverify all transactions from old main branch' blocks
-				let old_main_blocks_transactions = origin.decanonized_route.into_iter()
-					.flat_map(|block_hash| self.storage.indexed_block_transactions(block_hash.into()))
+				let accepted_transactions_hashes = this_block_transactions_hashes.into_iter()
+					.chain(new_main_blocks_transactions_hashes.into_iter())
 					.collect::<Vec<_>>();
 
-				trace!(target: "sync", "insert_best_block, old_main_blocks_transactions: {:?}",
-					   old_main_blocks_transactions.iter().map(|tx| tx.hash.reversed()).collect::<Vec<H256>>());
-
-				// reverify memory pool transactions, sorted by timestamp
-				let memory_pool_transactions_count = memory_pool.information().transactions_count;
-				let memory_pool_transactions: Vec<IndexedTransaction> = memory_pool
-					.remove_n_with_strategy(memory_pool_transactions_count, MemoryPoolOrderingStrategy::ByTimestamp)
-					.into_iter()
-					.map(|t| t.into())
-					.collect();
-
-				// reverify verifying transactions
-				let verifying_transactions: Vec<IndexedTransaction> = self.verifying_transactions
-					.iter()
-					.map(|(_, t)| t.clone())
-					.collect();
-				self.verifying_transactions.clear();
-
 				canonized_blocks_hashes.push(block.hash().clone());
 
 				let result = BlockInsertionResult {
 					canonized_blocks_hashes: canonized_blocks_hashes,
-					// order matters: db transactions, then ordered mempool transactions, then ordered verifying transactions
-					transactions_to_reverify: old_main_blocks_transactions.into_iter()
-						.chain(memory_pool_transactions.into_iter())
-						.chain(verifying_transactions.into_iter())
-						.collect(),
+					decanonized_blocks_hashes: origin.decanonized_route.iter().rev().cloned().collect(),
+					transactions_to_reverify: self.reorganize_memory_pool(accepted_transactions_hashes, origin.decanonized_route),
 				};
 
 				trace!(target: "sync", "result: {:?}", result);
@@ -478,6 +545,103 @@ impl Chain {
 		}
 	}
 
+	/// Mark stored block invalid and switch to the valid chain with the most work
+	pub fn invalidate_block(&mut self, hash: &H256) -> Result<BlockInsertionResult, db::Error> {
+		let reorganization = self.storage.invalidate_block(hash)?;
+		Ok(self.on_storage_reorganized(reorganization))
+	}
+
+	/// Remove invalid marks of the stored block, its ancestors and descendants and switch to the valid chain with the most work
+	pub fn reconsider_block(&mut self, hash: &H256) -> Result<BlockInsertionResult, db::Error> {
+		let reorganization = self.storage.reconsider_block(hash)?;
+		// descendants of the reconsidered block could have been rejected while it was invalid
+		self.dead_end_blocks.clear();
+		Ok(self.on_storage_reorganized(reorganization))
+	}
+
+	/// Prefer side chain block to the canon block with the same chain work
+	pub fn precious_block(&mut self, hash: &H256) -> Result<BlockInsertionResult, db::Error> {
+		let reorganization = self.storage.precious_block(hash)?;
+		Ok(self.on_storage_reorganized(reorganization))
+	}
+
+	/// Update in-memory state after canon chain has been reorganized in the storage
+	fn on_storage_reorganized(&mut self, reorganization: db::ChainReorganization) -> BlockInsertionResult {
+		// remember new best block hash
+		self.best_storage_block = self.storage.best_block();
+		self.is_segwit_active = self.deployments.segwit(self.best_storage_block.number, self.storage.as_block_header_provider(), &self.consensus);
+
+		// scheduled and requested blocks may descend from the decanonized blocks
+		// => leave currently verifying blocks only
+		self.forget_all_blocks_with_state(BlockState::Requested);
+		self.forget_all_blocks_with_state(BlockState::Scheduled);
+		if self.length_of_blocks_state(BlockState::Verifying) == 0 {
+			self.headers_chain = BestHeadersChain::new(self.best_storage_block.hash.clone());
+		}
+
+		let accepted_transactions_hashes = reorganization.canonized.iter()
+			.flat_map(|block_hash| self.storage.block_transaction_hashes(block_hash.clone().into()))
+			.collect::<Vec<_>>();
+		// decanonized blocks are listed starting with the old best block
+		let decanonized_route = reorganization.decanonized.iter().rev().cloned().collect();
+
+		let result = BlockInsertionResult {
+			transactions_to_reverify: self.reorganize_memory_pool(accepted_transactions_hashes, decanonized_route),
+			canonized_blocks_hashes: reorganization.canonized,
+			decanonized_blocks_hashes: reorganization.decanonized,
+		};
+
+		trace!(target: "sync", "on_storage_reorganized, result: {:?}", result);
+
+		result
+	}
+
+	/// Delete accepted transactions from verification queue and from the memory pool.
+	/// Returns transactions to reverify: transactions of decanonized blocks (starting with the oldest block),
+	/// then memory pool transactions, then verifying transactions
+	fn reorganize_memory_pool(&mut self, accepted_transactions_hashes: Vec<H256>, decanonized_blocks_hashes: Vec<H256>) -> Vec<IndexedTransaction> {
+		let mut memory_pool = self.memory_pool.write();
+		memory_pool.fee_estimator_mut().process_block(self.best_storage_block.number, &accepted_transactions_hashes);
+		memory_pool.note_new_best_block();
+		for transaction_accepted in accepted_transactions_hashes {
+			memory_pool.remove_by_hash(&transaction_accepted);
+			self.verifying_transactions.remove(&transaction_accepted);
+		}
+
+		// reverify all transactions from old main branch' blocks
+		let old_main_blocks_transactions = decanonized_blocks_hashes.into_iter()
+			.flat_map(|block_hash| self.storage.indexed_block_transactions(block_hash.into()))
+			.collect::<Vec<_>>();
+
+		trace!(target: "sync", "reorganize_memory_pool, old_main_blocks_transactions: {:?}",
+			   old_main_blocks_transactions.iter().map(|tx| tx.hash.reversed()).collect::<Vec<H256>>());
+
+		// reverify memory pool transactions, sorted by timestamp
+		let memory_pool_transactions_count = memory_pool.information().transactions_count;
+		let memory_pool_transactions: Vec<IndexedTransaction> = memory_pool
+			.remove_n_with_strategy(memory_pool_transactions_count, MemoryPoolOrderingStrategy::ByTimestamp)
+			.into_iter()
+			.map(|t| t.into())
+			.collect();
+		// reverified transactions are tracked by fee estimator again, starting from the new best block
+		for transaction in &memory_pool_transactions {
+			memory_pool.fee_estimator_mut().remove_transaction(&transaction.hash, false);
+		}
+
+		// reverify verifying transactions
+		let verifying_transactions: Vec<IndexedTransaction> = self.verifying_transactions
+			.iter()
+			.map(|(_, t)| t.clone())
+			.collect();
+		self.verifying_transactions.clear();
+
+		// order matters: db transactions, then ordered mempool transactions, then ordered verifying transactions
+		old_main_blocks_transactions.into_iter()
+			.chain(memory_pool_transactions.into_iter())
+			.chain(verifying_transactions.into_iter())
+			.collect()
+	}
+
 	/// Forget in-memory block
 	pub fn forget_block(&mut self, hash: &H256) -> HashPosition {
 		self.headers_chain.remove(hash);
@@ -614,17 +778,46 @@ impl Chain {
 			.or_else(|| self.memory_pool.read().read_by_hash(hash).cloned().map(|t| t.into()))
 	}
 
-	/// Insert transaction to memory pool
-	pub fn insert_verified_transaction(&mut self, transaction: IndexedTransaction) {
-		// we have verified transaction, but possibly this transaction replaces
-		// existing transaction from memory pool
-		// => remove previous transactions before
+	/// Insert transaction to memory pool. If memory pool is full, transactions with the lowest fee rate are evicted.
+	/// Returns false if inserted transaction itself has been evicted.
+	pub fn insert_verified_transaction(&mut self, transaction: IndexedTransaction) -> bool {
+		self.insert_verified_package(vec![transaction])
+	}
+
+	/// Insert transactions of the package (parents before children) to memory pool. Memory pool is trimmed
+	/// after all transactions are inserted, so parents are evicted together with children, which pay for them.
+	/// Returns false if any of inserted transactions has been evicted.
+	pub fn insert_verified_package(&mut self, transactions: Vec<IndexedTransaction>) -> bool {
+		let hashes: Vec<H256> = transactions.iter().map(|transaction| transaction.hash.clone()).collect();
+		for transaction in transactions {
+			// we have verified transaction, but possibly this transaction replaces
+			// existing transaction from memory pool
+			// => remove previous transactions before
+			let fee = checked_transaction_fee(&*self, &transaction.raw);
+			let mut memory_pool = self.memory_pool.write();
+			for input in &transaction.raw.inputs {
+				for replaced_transaction in memory_pool.remove_by_prevout(&input.previous_output).unwrap_or_default() {
+					memory_pool.fee_estimator_mut().remove_transaction(&replaced_transaction.hash, true);
+				}
+			}
+			// now insert transaction itself
+			memory_pool.set_best_block_height(self.best_storage_block.number);
+			match fee {
+				Some(fee) => {
+					memory_pool.fee_estimator_mut().process_transaction(transaction.hash.clone(), self.best_storage_block.number, fee, transaction.raw.vsize());
+					memory_pool.insert_verified_with_fee(transaction, fee);
+				},
+				None => memory_pool.insert_verified(transaction),
+			}
+		}
+
+		// evict transactions with the lowest fee rate if memory pool is full
 		let mut memory_pool = self.memory_pool.write();
-		for input in &transaction.raw.inputs {
-			memory_pool.remove_by_prevout(&input.previous_output);
+		for evicted_transaction in memory_pool.trim_to_size() {
+			trace!(target: "sync", "Evicting transaction {} from the full memory pool", evicted_transaction.hash.to_reversed_str());
+			memory_pool.fee_estimator_mut().remove_transaction(&evicted_transaction.hash, false);
 		}
-		// now insert transaction itself
-		memory_pool.insert_verified(transaction);
+		hashes.iter().all(|hash| memory_pool.contains(hash))
 	}
 
 	/// Calculate block locator hashes for hash queue
@@ -741,11 +934,12 @@ mod tests {
 	use std::sync::Arc;
 	use parking_lot::RwLock;
 	use chain::{Transaction, IndexedBlockHeader};
-	use db::BlockChainDatabase;
+	use db::{BlockChainDatabase, HeaderTree};
 	use miner::MemoryPool;
 	use network::{Magic, ConsensusParams, ConsensusFork};
 	use primitives::hash::H256;
-	use super::{Chain, BlockState, TransactionState, BlockInsertionResult};
+	use primitives::bigint::U256;
+	use super::{Chain, BlockState, TransactionState, BlockInsertionResult, ASSUME_VALID_MIN_AGE_S};
 	use utils::HashPosition;
 
 	#[test]
@@ -818,6 +1012,76 @@ mod tests {
 		assert_eq!(db.best_block().number, 1);
 	}
 
+	#[test]
+	fn chain_is_assumed_valid() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let mut headers: Vec<IndexedBlockHeader> = Vec::new();
+		let mut previous_hash = test_data::genesis().hash();
+		for &time in &[1000, 2000, 3000, ASSUME_VALID_MIN_AGE_S + 1500] {
+			let header: IndexedBlockHeader = test_data::block_builder().header().parent(previous_hash).time(time).build().build().block_header.into();
+			previous_hash = header.hash.clone();
+			headers.push(header);
+		}
+		let edge = headers[2].hash.clone();
+		let mut chain = Chain::new(db.clone(), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork), Arc::new(RwLock::new(MemoryPool::new())));
+
+		// headers of the verification edge are not yet known
+		assert!(!chain.is_assumed_valid(&headers[0].hash, &edge, &U256::zero()));
+
+		chain.schedule_blocks_headers(headers.clone());
+		assert!(chain.is_assumed_valid(&headers[0].hash, &edge, &U256::zero()));
+		// block is less than two weeks older than the best header
+		assert!(!chain.is_assumed_valid(&headers[1].hash, &edge, &U256::zero()));
+		// block is not an ancestor of the verification edge
+		assert!(!chain.is_assumed_valid(&headers[3].hash, &edge, &U256::zero()));
+		assert!(!chain.is_assumed_valid(&H256::from(0), &edge, &U256::zero()));
+		assert!(!chain.is_assumed_valid(&headers[0].hash, &H256::default(), &U256::zero()));
+		// best headers chain has not enough work
+		let minimum_chain_work = chain.best_block_header_work() + U256::one();
+		assert!(!chain.is_assumed_valid(&headers[0].hash, &edge, &minimum_chain_work));
+	}
+
+	#[test]
+	fn chain_stores_scheduled_headers() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let mut chain = Chain::new(db.clone(), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork), Arc::new(RwLock::new(MemoryPool::new())));
+
+		let blocks = test_data::build_n_empty_blocks_from_genesis(3, 0);
+		let headers: Vec<IndexedBlockHeader> = blocks.into_iter().map(|b| b.block_header.into()).collect();
+		chain.schedule_blocks_headers(headers.clone());
+		assert_eq!(db.header_tips(), vec![headers[2].hash.clone()]);
+		assert_eq!(db.header_entry(&headers[2].hash).unwrap().number, 3);
+		assert!(!db.header_entry(&headers[2].hash).unwrap().status.has_block);
+
+		// dead-end block with valid header stays valid in the header tree
+		chain.mark_dead_end_block(&headers[1].hash);
+		assert!(!db.header_entry(&headers[1].hash).unwrap().status.invalid);
+
+		// invalid header and its descendants are invalid in the header tree
+		chain.mark_invalid_header(&headers[1].hash);
+		assert!(!db.header_entry(&headers[0].hash).unwrap().status.invalid);
+		assert!(db.header_entry(&headers[2].hash).unwrap().status.invalid);
+		assert_eq!(db.best_header_entry().number, 0);
+	}
+
+	#[test]
+	fn chain_schedules_stored_headers() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let blocks = test_data::build_n_empty_blocks_from_genesis(3, 0);
+		let headers: Vec<IndexedBlockHeader> = blocks.into_iter().map(|b| b.block_header.into()).collect();
+		db.insert_headers(&headers).unwrap();
+
+		// headers, verified before restart, are scheduled
+		let chain = Chain::new(db.clone(), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork), Arc::new(RwLock::new(MemoryPool::new())));
+		assert_eq!(chain.information().scheduled, 3);
+		assert_eq!(chain.block_state(&headers[2].hash), BlockState::Scheduled);
+
+		// invalid headers are not
+		db.invalidate_header(&headers[0].hash).unwrap();
+		let chain = Chain::new(db, ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork), Arc::new(RwLock::new(MemoryPool::new())));
+		assert_eq!(chain.information().scheduled, 0);
+	}
+
 	#[test]
 	fn chain_block_locator_hashes() {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
@@ -1034,6 +1298,36 @@ mod tests {
 		assert!(result.transactions_to_reverify.iter().any(|ref tx| &tx.hash == &tx2_hash));
 	}
 
+	#[test]
+	fn invalidated_block_is_replaced_with_side_chain_block() {
+		let b0 = test_data::block_builder().header().build().build();
+		let b1 = test_data::block_builder().header().nonce(1).parent(b0.hash()).build().build();
+		let b2 = test_data::block_builder().header().nonce(2).parent(b0.hash()).build().build();
+		let b1_hash = b1.hash();
+		let b2_hash = b2.hash();
+
+		let tx1: Transaction = test_data::TransactionBuilder::with_version(1).into();
+		let tx1_hash = tx1.hash();
+
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
+		let mut chain = Chain::new(db, ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork), Arc::new(RwLock::new(MemoryPool::new())));
+		chain.insert_best_block(b1.into()).expect("no error");
+		chain.insert_best_block(b2.into()).expect("no error");
+		chain.insert_verified_transaction(tx1.into());
+		assert_eq!(chain.best_storage_block().hash, b1_hash);
+
+		let result = chain.invalidate_block(&b1_hash).expect("no error");
+		assert_eq!(result.canonized_blocks_hashes, vec![b2_hash.clone()]);
+		assert_eq!(result.transactions_to_reverify.len(), 1);
+		assert_eq!(result.transactions_to_reverify[0].hash, tx1_hash);
+		assert_eq!(chain.best_storage_block().hash, b2_hash);
+
+		// reconsidered block isn't longer than the canon chain
+		let result = chain.reconsider_block(&b1_hash).expect("no error");
+		assert_eq!(result, BlockInsertionResult::default());
+		assert_eq!(chain.best_storage_block().hash, b2_hash);
+	}
+
 	#[test]
 	fn fork_chain_block_transaction_is_removed_from_on_block_insert() {
 		let genesis = test_data::genesis();
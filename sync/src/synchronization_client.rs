@@ -1,7 +1,10 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
 use parking_lot::Mutex;
 use chain::{IndexedTransaction, Transaction, IndexedBlock};
+use db;
 use message::types;
+use primitives::hash::H256;
 use synchronization_executor::TaskExecutor;
 use synchronization_verifier::{Verifier, TransactionVerificationSink};
 use synchronization_client_core::{ClientCore, SynchronizationClientCore};
@@ -130,7 +133,13 @@ pub trait Client : Send + Sync + 'static {
 	fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound);
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: EmptyBoxFuture);
 	fn accept_transaction(&self, transaction: Transaction, sink: Box<TransactionVerificationSink>) -> Result<(), String>;
+	fn accept_verified_package(&self, transactions: Vec<IndexedTransaction>, fee_rate: u64) -> Result<(), String>;
+	fn invalidate_block(&self, hash: &H256) -> Result<(), String>;
+	fn reconsider_block(&self, hash: &H256) -> Result<(), String>;
+	fn precious_block(&self, hash: &H256) -> Result<(), String>;
 	fn install_sync_listener(&self, listener: SyncListenerRef);
+	fn best_block_header(&self) -> db::BestBlock;
+	fn blocks_in_flight(&self, peer_index: PeerIndex) -> usize;
 }
 
 /// Synchronization client facade
@@ -170,12 +179,21 @@ impl<T, U> Client for SynchronizationClient<T, U> where T: TaskExecutor, U: Veri
 			// verification tasks must be scheduled in the same order as they were built in on_block
 			// => here we use verification_lock for this
 			let _verification_lock = self.verification_lock.lock();
-			let blocks_to_verify = self.core.lock().on_block(peer_index, block);
+			let blocks_to_verify = {
+				let mut core = self.core.lock();
+				let blocks_to_verify = core.on_block(peer_index, block);
+				blocks_to_verify.map(|blocks_to_verify| blocks_to_verify.into_iter()
+					.map(|block| {
+						let assume_valid = core.is_assumed_valid(block.hash());
+						(block, assume_valid)
+					})
+					.collect::<VecDeque<_>>())
+			};
 
 			// verify blocks
 			if let Some(mut blocks_to_verify) = blocks_to_verify {
-				while let Some(block) = blocks_to_verify.pop_front() {
-					self.verifier.verify_block(block);
+				while let Some((block, assume_valid)) = blocks_to_verify.pop_front() {
+					self.verifier.verify_block(block, assume_valid);
 				}
 			}
 		}
@@ -224,9 +242,39 @@ impl<T, U> Client for SynchronizationClient<T, U> where T: TaskExecutor, U: Veri
 		Ok(())
 	}
 
+	fn accept_verified_package(&self, transactions: Vec<IndexedTransaction>, fee_rate: u64) -> Result<(), String> {
+		self.core.lock().accept_verified_package(transactions, fee_rate)
+	}
+
+	fn invalidate_block(&self, hash: &H256) -> Result<(), String> {
+		let transactions_to_verify = try!(self.core.lock().invalidate_block(hash));
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
+
+	fn reconsider_block(&self, hash: &H256) -> Result<(), String> {
+		let transactions_to_verify = try!(self.core.lock().reconsider_block(hash));
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
+
+	fn precious_block(&self, hash: &H256) -> Result<(), String> {
+		let transactions_to_verify = try!(self.core.lock().precious_block(hash));
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
+
 	fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.core.lock().install_sync_listener(listener);
 	}
+
+	fn best_block_header(&self) -> db::BestBlock {
+		self.core.lock().chain().best_block_header()
+	}
+
+	fn blocks_in_flight(&self, peer_index: PeerIndex) -> usize {
+		self.core.lock().peers_tasks().blocks_in_transit(peer_index)
+	}
 }
 
 impl<T, U> SynchronizationClient<T, U> where T: TaskExecutor, U: Verifier {
@@ -239,4 +287,12 @@ impl<T, U> SynchronizationClient<T, U> where T: TaskExecutor, U: Verifier {
 			verifier: verifier,
 		})
 	}
+
+	/// Schedule verification of transactions, resurrected after the canon chain reorganization
+	fn verify_transactions(&self, mut transactions_to_verify: VecDeque<IndexedTransaction>) {
+		let next_block_height = self.shared_state.best_storage_block_height() + 1;
+		while let Some(tx) = transactions_to_verify.pop_front() {
+			self.verifier.verify_transaction(next_block_height, tx);
+		}
+	}
 }
@@ -6,10 +6,14 @@ use futures::Future;
 use parking_lot::Mutex;
 use time::precise_time_s;
 use chain::{IndexedBlockHeader, IndexedTransaction, Transaction, IndexedBlock};
+use chain::constants::SATOSHIS_IN_COIN;
+use db::MIN_BLOCKS_TO_KEEP;
 use message::{types, Services};
 use message::common::{InventoryType, InventoryVector};
 use miner::transaction_fee_rate;
 use primitives::hash::H256;
+use primitives::bigint::U256;
+use ser::Serializable;
 use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 use synchronization_chain::{Chain, BlockState, TransactionState, BlockInsertionResult};
 use synchronization_executor::{Task, TaskExecutor};
@@ -21,12 +25,17 @@ use utils::{AverageSpeedMeter, MessageBlockHeadersProvider, OrphanBlocksPool, Or
 #[cfg(test)] use synchronization_peers_tasks::{Information as PeersTasksInformation};
 #[cfg(test)] use synchronization_chain::{Information as ChainInformation};
 
-/// Approximate maximal number of blocks hashes in scheduled queue.
-const MAX_SCHEDULED_HASHES: BlockHeight = 4 * 1024;
-/// Approximate maximal number of blocks hashes in requested queue.
-const MAX_REQUESTED_BLOCKS: BlockHeight = 256;
-/// Approximate maximal number of blocks in verifying queue.
-const MAX_VERIFYING_BLOCKS: BlockHeight = 256;
+/// Maximal number of blocks, which are either requested or verifying, ahead of the best stored block.
+const BLOCK_DOWNLOAD_WINDOW: BlockHeight = 512;
+/// Maximal number of blocks, requested from single peer at once.
+const MAX_BLOCKS_IN_TRANSIT_PER_PEER: BlockHeight = 128;
+/// Initial time to wait for the first block of the full download window before treating peer as stalling.
+const BLOCK_STALLING_TIMEOUT_S: f64 = 2_f64;
+/// Maximal time to wait for the first block of the full download window. Timeout is doubled after every
+/// stall, so that slow connections of the node itself don't lead to disconnecting all peers.
+const MAX_BLOCK_STALLING_TIMEOUT_S: f64 = 64_f64;
+/// Stalling timeout is multiplied by this factor when requested block is received, down to the initial timeout.
+const BLOCK_STALLING_TIMEOUT_DECAY: f64 = 0.85_f64;
 /// Minimum number of blocks to request from peer
 const MIN_BLOCKS_IN_REQUEST: BlockHeight = 32;
 /// Maximum number of blocks to request from peer
@@ -45,6 +54,14 @@ const MIN_BLOCK_DUPLICATION_INTERVAL_S: f64 = 10_f64;
 const MAX_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 4;
 /// Minimal number of blocks in duplicate requests.
 const MIN_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 8;
+/// Maximal size of orphan transaction, which is kept in the orphan pool.
+const MAX_ORPHAN_TRANSACTION_SIZE: usize = 100_000;
+/// Minimal time between `feefilter` announcements of the slightly changed fee rate.
+const FEE_FILTER_INTERVAL_S: f64 = 600_f64;
+/// Fee rate, announced while synchronizing. Transactions are ignored then, so peers shouldn't announce them at all.
+const MAX_FEE_FILTER_RATE: u64 = 21_000_000 * SATOSHIS_IN_COIN;
+/// Maximal number of announced blocks, which are requested directly from the announcing peer.
+const MAX_DIRECT_FETCH_BLOCKS: BlockHeight = 16;
 
 /// Information on current synchronization state.
 #[cfg(test)]
@@ -73,9 +90,14 @@ pub trait ClientCore {
 	fn on_notfound(&mut self, peer_index: PeerIndex, message: types::NotFound);
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: EmptyBoxFuture);
 	fn accept_transaction(&mut self, transaction: Transaction, sink: Box<TransactionVerificationSink>) -> Result<VecDeque<IndexedTransaction>, String>;
+	fn accept_verified_package(&mut self, transactions: Vec<IndexedTransaction>, fee_rate: u64) -> Result<(), String>;
+	fn invalidate_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String>;
+	fn reconsider_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String>;
+	fn precious_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String>;
 	fn install_sync_listener(&mut self, listener: SyncListenerRef);
 	fn execute_synchronization_tasks(&mut self, forced_blocks_requests: Option<Vec<H256>>, final_blocks_requests: Option<Vec<H256>>);
 	fn try_switch_to_saturated_state(&mut self) -> bool;
+	fn is_assumed_valid(&self, hash: &H256) -> bool;
 }
 
 /// Synchronization client configuration options.
@@ -83,6 +105,10 @@ pub trait ClientCore {
 pub struct Config {
 	/// If true, connection to peer who has provided us with bad block is closed
 	pub close_connection_on_bad_block: bool,
+	/// Scripts of this block ancestors in the best headers chain may be assumed to be valid
+	pub verification_edge: H256,
+	/// Minimal work of the best headers chain, required to assume that scripts are valid
+	pub minimum_chain_work: U256,
 }
 
 /// Synchronization client.
@@ -123,10 +149,14 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
 	sync_speed_meter: AverageSpeedMeter,
 	/// Configuration
 	config: Config,
-	/// Synchronization events listener
-	listener: Option<SyncListenerRef>,
+	/// Synchronization events listeners
+	listeners: Vec<SyncListenerRef>,
 	/// Time of last duplicated blocks request.
 	last_dup_time: f64,
+	/// Time of last `feefilter` announcement.
+	last_fee_filter_time: f64,
+	/// Current time to wait for the first block of the full download window.
+	block_stalling_timeout: f64,
 }
 
 /// Verification sink for synchronization client core
@@ -148,12 +178,10 @@ pub enum State {
 
 /// Blocks request limits.
 pub struct BlocksRequestLimits {
-	/// Approximate maximal number of blocks hashes in scheduled queue.
-	pub max_scheduled_hashes: BlockHeight,
-	/// Approximate maximal number of blocks hashes in requested queue.
-	pub max_requested_blocks: BlockHeight,
-	/// Approximate maximal number of blocks in verifying queue.
-	pub max_verifying_blocks: BlockHeight,
+	/// Maximal number of blocks, which are either requested or verifying.
+	pub block_download_window: BlockHeight,
+	/// Maximal number of blocks, requested from single peer at once.
+	pub max_blocks_in_transit_per_peer: BlockHeight,
 	/// Minimum number of blocks to request from peer
 	pub min_blocks_in_request: BlockHeight,
 	/// Maximum number of blocks to request from peer
@@ -239,7 +267,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 						BlockState::Unknown => !self.orphaned_blocks_pool.contains_unknown_block(&item.hash),
 						BlockState::DeadEnd if !self.config.close_connection_on_bad_block => true,
 						BlockState::DeadEnd if self.config.close_connection_on_bad_block => {
-							self.peers.misbehaving(peer_index, &format!("Provided dead-end block {:?}", item.hash.to_reversed_str()));
+							self.peers.misbehaving(peer_index, 100, &format!("Provided dead-end block {:?}", item.hash.to_reversed_str()));
 							false
 						},
 						_ => false,
@@ -250,7 +278,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 						| InventoryType::MessageWitnessTx => false,
 					// unknown inventory type
 					InventoryType::Error => {
-						self.peers.misbehaving(peer_index, &format!("Provided unknown inventory type {:?}", item.hash.to_reversed_str()));
+						self.peers.misbehaving(peer_index, 10, &format!("Provided unknown inventory type {:?}", item.hash.to_reversed_str()));
 						false
 					}
 				}
@@ -302,6 +330,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		let header0 = headers[0].clone();
 		if self.chain.block_state(&header0.raw.previous_header_hash) == BlockState::Unknown {
 			warn!(target: "sync", "Previous header of the first header from peer#{} `headers` message is unknown. First: {}. Previous: {}", peer_index, header0.hash.to_reversed_str(), header0.raw.previous_header_hash.to_reversed_str());
+			// if peer has announced new block, but we have missed some of its ancestors
+			// => ask peer for headers, connecting this block to our chain
+			if !self.state.is_synchronizing() && self.chain.block_state(&header0.hash) == BlockState::Unknown {
+				let block_locator_hashes = self.chain.block_locator_hashes();
+				self.executor.execute(Task::GetHeaders(peer_index, types::GetHeaders::with_block_locator_hashes(block_locator_hashes)));
+				self.peers_tasks.on_headers_requested(peer_index);
+			}
 			return;
 		}
 
@@ -331,11 +366,11 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// validate blocks headers before scheduling
 		let last_known_hash = if first_unknown_index > 0 { headers[first_unknown_index - 1].hash.clone() } else { header0.raw.previous_header_hash.clone() };
 		if self.config.close_connection_on_bad_block && self.chain.block_state(&last_known_hash) == BlockState::DeadEnd {
-			self.peers.misbehaving(peer_index, &format!("Provided after dead-end block {}", last_known_hash.to_reversed_str()));
+			self.peers.misbehaving(peer_index, 100, &format!("Provided after dead-end block {}", last_known_hash.to_reversed_str()));
 			return;
 		}
 		match self.verify_headers(peer_index, last_known_hash, &headers[first_unknown_index..num_headers]) {
-			BlocksHeadersVerificationResult::Error(error_index) => self.chain.mark_dead_end_block(&headers[first_unknown_index + error_index].hash),
+			BlocksHeadersVerificationResult::Error(error_index) => self.chain.mark_invalid_header(&headers[first_unknown_index + error_index].hash),
 			BlocksHeadersVerificationResult::Skip => (),
 			BlocksHeadersVerificationResult::Success => {
 				// report progress
@@ -352,7 +387,8 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				self.chain.schedule_blocks_headers(new_headers);
 
 				// switch to synchronization state
-				if !self.state.is_synchronizing() {
+				let was_synchronizing = self.state.is_synchronizing();
+				if !was_synchronizing {
 					if self.chain.length_of_blocks_state(BlockState::Scheduled) +
 						self.chain.length_of_blocks_state(BlockState::Requested) == 1 {
 						self.switch_to_nearly_saturated_state();
@@ -365,6 +401,12 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				self.peers_tasks.useful_peer(peer_index);
 				// and execute tasks
 				self.execute_synchronization_tasks(None, None);
+
+				// if new blocks are announced && they are left unrequested, because peers are busy
+				// => request them directly from the announcing peer
+				if !was_synchronizing {
+					self.direct_fetch_blocks(peer_index);
+				}
 			},
 		}
 	}
@@ -376,6 +418,9 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// prepare list of blocks to verify + make all required changes to the chain
 		let mut result: Option<VecDeque<IndexedBlock>> = None;
 		let block_state = self.chain.block_state(&block.header.hash);
+		if block_state == BlockState::Requested {
+			self.block_stalling_timeout = (self.block_stalling_timeout * BLOCK_STALLING_TIMEOUT_DECAY).max(BLOCK_STALLING_TIMEOUT_S);
+		}
 		match block_state {
 			BlockState::Verifying | BlockState::Stored => {
 				// remember peer as useful
@@ -385,7 +430,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 			BlockState::Unknown | BlockState::Scheduled | BlockState::Requested | BlockState::DeadEnd => {
 				if block_state == BlockState::DeadEnd {
 					if self.config.close_connection_on_bad_block {
-						self.peers.misbehaving(peer_index, &format!("Provided dead-end block {}", block.header.hash.to_reversed_str()));
+						self.peers.misbehaving(peer_index, 100, &format!("Provided dead-end block {}", block.header.hash.to_reversed_str()));
 						return None;
 					}
 					warn!(target: "sync", "Peer#{} has provided dead-end block {}", peer_index, block.header.hash.to_reversed_str());
@@ -397,7 +442,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 					BlockState::Unknown | BlockState::DeadEnd => {
 						if parent_block_state == BlockState::DeadEnd {
 							if self.config.close_connection_on_bad_block {
-								self.peers.misbehaving(peer_index, &format!("Provided dead-end block {}", block.header.hash.to_reversed_str()));
+								self.peers.misbehaving(peer_index, 100, &format!("Provided dead-end block {}", block.header.hash.to_reversed_str()));
 								return None;
 							}
 							warn!(target: "sync", "Peer#{} has provided dead-end block {}", peer_index, block.header.hash.to_reversed_str());
@@ -474,8 +519,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 
 	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>> {
 		// check if this transaction is already known
-		if self.orphaned_transactions_pool.contains(&transaction.hash) ||
-			self.chain.transaction_state(&transaction.hash) != TransactionState::Unknown {
+		let transaction_state = self.chain.transaction_state(&transaction.hash);
+		if self.orphaned_transactions_pool.contains(&transaction.hash) || transaction_state != TransactionState::Unknown {
+			// memory pool transaction from the peer with `forcerelay` permission is relayed again
+			if transaction_state == TransactionState::InMemory && self.peers.permissions(peer_index).forcerelay {
+				let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
+				self.executor.execute(Task::RelayNewTransaction(transaction, transaction_fee_rate));
+			}
 			return None;
 		}
 
@@ -509,7 +559,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 			let removed_tasks = self.peers_tasks.reset_blocks_tasks(peer_index);
 			self.peers_tasks.unuseful_peer(peer_index);
 			if self.state.is_synchronizing() {
-				self.peers.misbehaving(peer_index, &format!("Responded with NotFound(unrequested_block)"));
+				self.peers.misbehaving(peer_index, 10, &format!("Responded with NotFound(unrequested_block)"));
 			}
 
 			// if peer has had some blocks tasks, rerequest these blocks
@@ -547,10 +597,29 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	fn accept_verified_package(&mut self, transactions: Vec<IndexedTransaction>, fee_rate: u64) -> Result<(), String> {
+		if self.state.is_synchronizing() {
+			return Err("Cannot append package as node is not yet fully synchronized".to_owned());
+		}
+
+		if !self.chain.insert_verified_package(transactions.clone()) {
+			return Err("Memory pool is full".to_owned());
+		}
+
+		for transaction in transactions {
+			for listener in &self.listeners {
+				listener.transaction_accepted(&transaction);
+			}
+
+			// parents are relayed with the fee rate of the whole package, so that they
+			// reach peers, whose fee filter would reject them alone
+			self.executor.execute(Task::RelayNewTransaction(transaction, fee_rate));
+		}
+		Ok(())
+	}
+
 	fn install_sync_listener(&mut self, listener: SyncListenerRef) {
-		// currently single, single-setup listener is supported
-		assert!(self.listener.is_none());
-		self.listener = Some(listener);
+		self.listeners.push(listener);
 	}
 
 	/// Schedule new synchronization tasks, if any.
@@ -567,6 +636,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 			limits.max_blocks_in_request = 16;
 		}
 
+		// if download window is stalled by the peer, which is asked for its first block
+		// => blocks, requested from this peer, must be requested from other peers
+		let forced_blocks_requests = match self.reset_stalling_peer(&limits) {
+			Some(stalled_blocks_requests) => Some(forced_blocks_requests.into_iter().flat_map(|hashes| hashes).chain(stalled_blocks_requests).collect()),
+			None => forced_blocks_requests,
+		};
+
 		// if some blocks requests are forced => we should ask peers even if there are no idle peers
 		let verifying_hashes_len = self.chain.length_of_blocks_state(BlockState::Verifying);
 		if let Some(forced_blocks_requests) = forced_blocks_requests {
@@ -592,23 +668,27 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 
 		let mut blocks_requests: Option<Vec<H256>> = None;
-		let blocks_idle_peers: Vec<_> = self.peers_tasks.idle_peers_for_blocks().iter().cloned().collect();
+		// peers, serving only recent blocks (BIP159), are not asked for blocks, which are too far from the best header
+		let is_far_from_best_header = self.chain.best_block_header().number
+			.saturating_sub(self.chain.best_storage_block().number) >= MIN_BLOCKS_TO_KEEP;
+		let peers = &self.peers;
+		let blocks_idle_peers: Vec<_> = self.peers_tasks.idle_peers_for_blocks().iter().cloned()
+			.filter(|peer_index| !is_far_from_best_header || !peers.is_network_limited(*peer_index))
+			.collect();
 		{
-			// check if we can query some blocks headers
+			// headers are downloaded ahead of blocks => query blocks headers from all idle peers
+			// blocks are only requested when their headers are already validated and scheduled
 			let headers_idle_peers: Vec<_> = self.peers_tasks.idle_peers_for_headers().iter().cloned().collect();
 			if !headers_idle_peers.is_empty() {
-				let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
-				if scheduled_hashes_len < MAX_SCHEDULED_HASHES {
-					for header_peer in &headers_idle_peers {
-						self.peers_tasks.on_headers_requested(*header_peer);
-					}
-
-					let block_locator_hashes = self.chain.block_locator_hashes();
-					let headers_tasks = headers_idle_peers
-						.iter()
-						.map(move |peer_index| Task::GetHeaders(*peer_index, types::GetHeaders::with_block_locator_hashes(block_locator_hashes.clone())));
-					tasks.extend(headers_tasks);
+				for header_peer in &headers_idle_peers {
+					self.peers_tasks.on_headers_requested(*header_peer);
 				}
+
+				let block_locator_hashes = self.chain.block_locator_hashes();
+				let headers_tasks = headers_idle_peers
+					.iter()
+					.map(move |peer_index| Task::GetHeaders(*peer_index, types::GetHeaders::with_block_locator_hashes(block_locator_hashes.clone())));
+				tasks.extend(headers_tasks);
 			}
 
 			let blocks_idle_peers_len = blocks_idle_peers.len() as BlockHeight;
@@ -682,9 +762,12 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				{
 					// TODO: only request minimal number of blocks, if other urgent blocks are requested
 					let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
-					if requested_hashes_len + verifying_hashes_len < MAX_REQUESTED_BLOCKS + MAX_VERIFYING_BLOCKS && scheduled_hashes_len != 0 {
+					let window_len = requested_hashes_len + verifying_hashes_len;
+					if window_len < limits.block_download_window && scheduled_hashes_len != 0 {
 						let chunk_size = min(limits.max_blocks_in_request, max(scheduled_hashes_len / blocks_idle_peers_len, limits.min_blocks_in_request));
-						let hashes_to_request_len = chunk_size * blocks_idle_peers_len;
+						let chunk_size = min(limits.max_blocks_in_transit_per_peer, chunk_size);
+						// never request blocks beyond the download window
+						let hashes_to_request_len = min(chunk_size * blocks_idle_peers_len, limits.block_download_window - window_len);
 						let hashes_to_request = self.chain.request_blocks_hashes(hashes_to_request_len);
 						match blocks_requests {
 							Some(ref mut blocks_requests) => blocks_requests.extend(hashes_to_request),
@@ -719,6 +802,25 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 
 		switch_to_saturated
 	}
+
+	fn is_assumed_valid(&self, hash: &H256) -> bool {
+		self.chain.is_assumed_valid(hash, &self.config.verification_edge, &self.config.minimum_chain_work)
+	}
+
+	fn invalidate_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String> {
+		let insert_result = try!(self.chain.invalidate_block(hash));
+		Ok(self.on_chain_reorganized(insert_result))
+	}
+
+	fn reconsider_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String> {
+		let insert_result = try!(self.chain.reconsider_block(hash));
+		Ok(self.on_chain_reorganized(insert_result))
+	}
+
+	fn precious_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String> {
+		let insert_result = try!(self.chain.precious_block(hash));
+		Ok(self.on_chain_reorganized(insert_result))
+	}
 }
 
 impl<T> CoreVerificationSink<T> where T: TaskExecutor {
@@ -779,8 +881,10 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				block_speed_meter: AverageSpeedMeter::with_inspect_items(SYNC_SPEED_BLOCKS_TO_INSPECT),
 				sync_speed_meter: AverageSpeedMeter::with_inspect_items(BLOCKS_SPEED_BLOCKS_TO_INSPECT),
 				config: config,
-				listener: None,
+				listeners: Vec::new(),
 				last_dup_time: 0f64,
+				last_fee_filter_time: 0f64,
+				block_stalling_timeout: BLOCK_STALLING_TIMEOUT_S,
 			}
 		));
 
@@ -861,6 +965,34 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	/// Announce memory pool minimum fee rate to peers with `feefilter` messages (BIP133).
+	/// Significant changes are announced immediately, others - at most once per `FEE_FILTER_INTERVAL_S`.
+	pub fn announce_fee_filter(&mut self) {
+		let fee_rate = if self.state.is_synchronizing() {
+			MAX_FEE_FILTER_RATE
+		} else {
+			self.chain.memory_pool().read().minimum_fee_rate()
+		};
+
+		let now = precise_time_s();
+		let is_interval_passed = now - self.last_fee_filter_time >= FEE_FILTER_INTERVAL_S;
+		for (peer_index, announced_fee_rate) in self.peers.fee_filter_announcements() {
+			if fee_rate == announced_fee_rate {
+				continue;
+			}
+
+			let is_significant_change = fee_rate < announced_fee_rate / 4 * 3 || fee_rate > announced_fee_rate / 3 * 4;
+			if is_interval_passed || is_significant_change {
+				self.executor.execute(Task::FeeFilter(peer_index, types::FeeFilter::with_fee_rate(fee_rate)));
+				self.peers.set_announced_fee_rate(peer_index, fee_rate);
+			}
+		}
+
+		if is_interval_passed {
+			self.last_fee_filter_time = now;
+		}
+	}
+
 	/// Forget blocks, which have been requested several times, but no one has responded
 	pub fn forget_failed_blocks(&mut self, blocks_to_forget: &[H256]) {
 		if blocks_to_forget.is_empty() {
@@ -880,7 +1012,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		for (header_index, header) in headers.iter().enumerate() {
 			// check that this header is direct child of previous header
 			if &header.raw.previous_header_hash != last_known_hash {
-				self.peers.misbehaving(peer_index, &format!("Neighbour headers in `headers` message are unlinked: Prev: {}, PrevLink: {}, Curr: {}", last_known_hash.to_reversed_str(), header.raw.previous_header_hash.to_reversed_str(), header.hash.to_reversed_str()));
+				self.peers.misbehaving(peer_index, 20, &format!("Neighbour headers in `headers` message are unlinked: Prev: {}, PrevLink: {}, Curr: {}", last_known_hash.to_reversed_str(), header.raw.previous_header_hash.to_reversed_str(), header.hash.to_reversed_str()));
 				return BlocksHeadersVerificationResult::Skip;
 			}
 
@@ -890,7 +1022,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			match self.chain.block_state(&header.hash) {
 				BlockState::Unknown => (),
 				BlockState::DeadEnd if self.config.close_connection_on_bad_block => {
-					self.peers.misbehaving(peer_index, &format!("Provided dead-end block {:?}", header.hash.to_reversed_str()));
+					self.peers.misbehaving(peer_index, 100, &format!("Provided dead-end block {:?}", header.hash.to_reversed_str()));
 					return BlocksHeadersVerificationResult::Skip;
 				},
 				_ => {
@@ -904,7 +1036,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			if self.verify_headers {
 				if let Err(error) = self.chain_verifier.verify_block_header(&headers_provider, &header.hash, &header.raw) {
 					if self.config.close_connection_on_bad_block {
-						self.peers.misbehaving(peer_index, &format!("Error verifying header {} from `headers`: {:?}", header.hash.to_reversed_str(), error));
+						self.peers.misbehaving(peer_index, 100, &format!("Error verifying header {} from `headers`: {:?}", header.hash.to_reversed_str(), error));
 					} else {
 						warn!(target: "sync", "Error verifying header {} from `headers` message: {:?}", header.hash.to_reversed_str(), error);
 					}
@@ -919,10 +1051,65 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		BlocksHeadersVerificationResult::Success
 	}
 
+	/// Process canon chain reorganization, made by invalidating, reconsidering or preferring blocks.
+	/// Returns transactions to verify
+	fn on_chain_reorganized(&mut self, insert_result: BlockInsertionResult) -> VecDeque<IndexedTransaction> {
+		self.shared_state.update_best_storage_block_height(self.chain.best_storage_block().number);
+
+		// notify listeners
+		self.notify_canon_chain_changed(&insert_result);
+
+		// blocks of the longest valid chain are requested again
+		let block_locator_hashes = self.chain.block_locator_hashes();
+		for peer in self.peers_tasks.all_peers() {
+			self.executor.execute(Task::GetHeaders(*peer, types::GetHeaders::with_block_locator_hashes(block_locator_hashes.clone())));
+		}
+		self.execute_synchronization_tasks(None, None);
+
+		// do not relay resurrected transactions again
+		let mut transactions_to_verify = VecDeque::new();
+		for tx in insert_result.transactions_to_reverify {
+			if let Some(tx_orphans) = self.process_peer_transaction(None, tx, false) {
+				transactions_to_verify.extend(tx_orphans);
+			}
+		}
+		transactions_to_verify
+	}
+
+	/// Notify listeners about canon chain changes: disconnected blocks (starting with the old best block),
+	/// then connected blocks (starting with the oldest block) and then the new best block
+	fn notify_canon_chain_changed(&self, insert_result: &BlockInsertionResult) {
+		if insert_result.canonized_blocks_hashes.is_empty() && insert_result.decanonized_blocks_hashes.is_empty() {
+			return;
+		}
+
+		let best_block_hash = self.chain.best_storage_block().hash;
+		for listener in &self.listeners {
+			for block_hash in &insert_result.decanonized_blocks_hashes {
+				listener.block_disconnected(block_hash);
+			}
+			for block_hash in &insert_result.canonized_blocks_hashes {
+				listener.block_connected(block_hash);
+			}
+			listener.best_storage_block_inserted(&best_block_hash);
+		}
+	}
+
 	/// Process new peer transaction
-	fn process_peer_transaction(&mut self, _peer_index: Option<PeerIndex>, transaction: IndexedTransaction, relay: bool) -> Option<VecDeque<IndexedTransaction>> {
+	fn process_peer_transaction(&mut self, peer_index: Option<PeerIndex>, transaction: IndexedTransaction, relay: bool) -> Option<VecDeque<IndexedTransaction>> {
 		match self.try_append_transaction(transaction.clone(), relay) {
 			Err(AppendTransactionError::Orphan(unknown_parents)) => {
+				// do not waste memory on large orphans
+				if transaction.raw.serialized_size() > MAX_ORPHAN_TRANSACTION_SIZE {
+					trace!(target: "sync", "Ignoring large orphan transaction {}", transaction.hash.to_reversed_str());
+					return None;
+				}
+
+				// ask peer, which has provided the orphan, for its unknown parents
+				if let Some(peer_index) = peer_index {
+					self.request_orphan_parents(peer_index, &unknown_parents);
+				}
+
 				self.orphaned_transactions_pool.insert(transaction, unknown_parents);
 				None
 			},
@@ -931,6 +1118,25 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	fn request_orphan_parents(&self, peer_index: PeerIndex, unknown_parents: &HashSet<H256>) {
+		let inv_type = if self.chain.is_segwit_active() && self.peers.is_segwit_enabled(peer_index) {
+			InventoryType::MessageWitnessTx
+		} else {
+			InventoryType::MessageTx
+		};
+		// parents, which are orphans themselves, are already known
+		let inventory: Vec<_> = unknown_parents.iter()
+			.filter(|hash| !self.orphaned_transactions_pool.contains(hash))
+			.map(|hash| InventoryVector {
+				inv_type: inv_type,
+				hash: hash.clone(),
+			})
+			.collect();
+		if !inventory.is_empty() {
+			self.executor.execute(Task::GetData(peer_index, types::GetData::with_inventory(inventory)));
+		}
+	}
+
 	fn try_append_transaction(&mut self, transaction: IndexedTransaction, relay: bool) -> Result<VecDeque<IndexedTransaction>, AppendTransactionError> {
 		// if we are in synchronization state, we will ignore this message
 		if self.state.is_synchronizing() {
@@ -961,6 +1167,26 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		Ok(transactions)
 	}
 
+	/// Request few announced blocks from the announcing peer, even if it isn't idle.
+	fn direct_fetch_blocks(&mut self, peer_index: PeerIndex) {
+		let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
+		if scheduled_hashes_len == 0 || scheduled_hashes_len > MAX_DIRECT_FETCH_BLOCKS {
+			return;
+		}
+
+		let limits = BlocksRequestLimits::default();
+		let blocks_in_transit = self.peers_tasks.blocks_in_transit(peer_index) as BlockHeight;
+		if blocks_in_transit + scheduled_hashes_len > limits.max_blocks_in_transit_per_peer {
+			return;
+		}
+
+		trace!(target: "sync", "Directly fetching {} announced blocks from peer#{}", scheduled_hashes_len, peer_index);
+		let hashes = self.chain.request_blocks_hashes(scheduled_hashes_len);
+		for task in self.prepare_blocks_requests_tasks(&limits, vec![peer_index], hashes) {
+			self.executor.execute(task);
+		}
+	}
+
 	fn prepare_blocks_requests_tasks(&mut self, limits: &BlocksRequestLimits, mut peers: Vec<PeerIndex>, mut hashes: Vec<H256>) -> Vec<Task> {
 		use std::mem::swap;
 
@@ -973,10 +1199,16 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		let is_segwit_active = self.chain.is_segwit_active();
 		let inv_type = if is_segwit_active { InventoryType::MessageWitnessBlock } else { InventoryType::MessageBlock };
 		for (peer_index, peer) in peers.into_iter().enumerate() {
+			// do not ask peer for more blocks than its in-flight window allows
 			// we have to request all blocks => we will request last peer for all remaining blocks
-			let peer_chunk_size = if peer_index == last_peer_index { hashes.len() } else { min(hashes.len(), chunk_size as usize) };
+			let blocks_in_transit = self.peers_tasks.blocks_in_transit(peer) as BlockHeight;
+			let peer_window = if blocks_in_transit < limits.max_blocks_in_transit_per_peer { limits.max_blocks_in_transit_per_peer - blocks_in_transit } else { 0 };
+			let peer_chunk_size = if peer_index == last_peer_index { hashes.len() } else { min(hashes.len(), min(chunk_size, peer_window) as usize) };
 			if peer_chunk_size == 0 {
-				break;
+				if hashes.is_empty() {
+					break;
+				}
+				continue;
 			}
 
 			let mut chunk_hashes = hashes.split_off(peer_chunk_size);
@@ -999,13 +1231,45 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		tasks
 	}
 
+	/// Check if the first block of the full download window is awaited from the peer for too long.
+	/// If so, exclude this peer from synchronization and return blocks, requested from it.
+	fn reset_stalling_peer(&mut self, limits: &BlocksRequestLimits) -> Option<Vec<H256>> {
+		// window can only be stalled if there are peers to download new blocks from
+		if !self.state.is_synchronizing() || self.peers_tasks.idle_peers_for_blocks().is_empty()
+			|| self.chain.length_of_blocks_state(BlockState::Scheduled) == 0 {
+			return None;
+		}
+
+		let window_len = self.chain.length_of_blocks_state(BlockState::Requested) + self.chain.length_of_blocks_state(BlockState::Verifying);
+		if window_len < limits.block_download_window {
+			return None;
+		}
+
+		let first_requested_block = match self.chain.best_n_of_blocks_state(BlockState::Requested, 1).pop() {
+			Some(first_requested_block) => first_requested_block,
+			None => return None,
+		};
+		let stalling_peer = match self.peers_tasks.stalling_peer(&first_requested_block, self.block_stalling_timeout) {
+			Some(stalling_peer) => stalling_peer,
+			None => return None,
+		};
+
+		warn!(target: "sync", "Peer#{} is stalling block download for {}s. Excluding from synchronization.", stalling_peer, self.block_stalling_timeout);
+		let stalled_blocks_requests = self.peers_tasks.reset_blocks_tasks(stalling_peer);
+		self.peers_tasks.unuseful_peer(stalling_peer);
+		// stalling is not a misbehavior => peer is not banned
+		self.peers.disconnect(stalling_peer, "Stalling block download");
+		self.block_stalling_timeout = (self.block_stalling_timeout * 2_f64).min(MAX_BLOCK_STALLING_TIMEOUT_S);
+		Some(stalled_blocks_requests)
+	}
+
 	/// Switch to synchronization state
 	fn switch_to_synchronization_state(&mut self) {
 		if self.state.is_synchronizing() {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(true);
 		}
 
@@ -1019,7 +1283,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1033,7 +1297,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1100,12 +1364,8 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 					self.peers.require_peer_services(Services::default().with_witness(true));
 				}
 
-				// notify listener
-				if let Some(best_block_hash) = insert_result.canonized_blocks_hashes.last() {
-					if let Some(ref listener) = self.listener {
-						listener.best_storage_block_inserted(best_block_hash);
-					}
-				}
+				// notify listeners
+				self.notify_canon_chain_changed(&insert_result);
 
 				// awake threads, waiting for this block insertion
 				self.awake_waiting_threads(&block_hash);
@@ -1115,8 +1375,8 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 
 				// relay block to our peers
 				if needs_relay && (self.state.is_saturated() || self.state.is_nearly_saturated()) {
-					for block_hash in insert_result.canonized_blocks_hashes {
-						if let Some(block) = self.chain.storage().block(block_hash.into()) {
+					for block_hash in &insert_result.canonized_blocks_hashes {
+						if let Some(block) = self.chain.storage().block(block_hash.clone().into()) {
 							self.executor.execute(Task::RelayNewBlock(block.into()));
 						}
 					}
@@ -1132,6 +1392,18 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 						verification_tasks.extend(tx_tasks);
 					};
 				}
+
+				// orphans, which are waiting for transactions from canonized blocks, could be verified now
+				if !self.state.is_synchronizing() && !self.orphaned_transactions_pool.is_empty() {
+					let confirmed_transactions: Vec<H256> = insert_result.canonized_blocks_hashes.iter()
+						.flat_map(|block_hash| self.chain.storage().block_transaction_hashes(block_hash.clone().into()))
+						.collect();
+					for tx in self.orphaned_transactions_pool.remove_confirmed_transactions(&confirmed_transactions) {
+						self.chain.verify_transaction(tx.clone());
+						verification_tasks.push(VerificationTask::VerifyTransaction(next_block_height, tx));
+					}
+				}
+
 				Some(verification_tasks)
 			},
 			Err(e) => {
@@ -1182,7 +1454,15 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 
 		// transaction was in verification queue => insert to memory pool
-		self.chain.insert_verified_transaction(transaction.clone());
+		if !self.chain.insert_verified_transaction(transaction.clone()) {
+			self.on_transaction_verification_error("Memory pool is full", &transaction.hash);
+			return;
+		}
+
+		// notify listeners
+		for listener in &self.listeners {
+			listener.transaction_accepted(&transaction);
+		}
 
 		// calculate transaction fee rate
 		let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
@@ -1245,9 +1525,8 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 impl Default for BlocksRequestLimits {
 	fn default() -> Self {
 		BlocksRequestLimits {
-			max_scheduled_hashes: MAX_SCHEDULED_HASHES,
-			max_requested_blocks: MAX_REQUESTED_BLOCKS,
-			max_verifying_blocks: MAX_VERIFYING_BLOCKS,
+			block_download_window: BLOCK_DOWNLOAD_WINDOW,
+			max_blocks_in_transit_per_peer: MAX_BLOCKS_IN_TRANSIT_PER_PEER,
 			min_blocks_in_request: MIN_BLOCKS_IN_REQUEST,
 			max_blocks_in_request: MAX_BLOCKS_IN_REQUEST,
 		}
@@ -1259,17 +1538,21 @@ pub mod tests {
 	extern crate test_data;
 
 	use std::sync::Arc;
+	use std::thread;
+	use std::time::Duration;
 	use parking_lot::{Mutex, RwLock};
-	use chain::{Block, Transaction};
+	use chain::{Block, Transaction, IndexedTransaction, IndexedBlockHeader};
 	use db::BlockChainDatabase;
 	use message::common::InventoryVector;
 	use message::{Services, types};
 	use miner::MemoryPool;
 	use network::{ConsensusParams, ConsensusFork, Magic};
+	use p2p::PeerPermissions;
 	use primitives::hash::H256;
+	use primitives::bigint::U256;
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 	use inbound_connection::tests::DummyOutboundSyncConnection;
-	use synchronization_chain::Chain;
+	use synchronization_chain::{Chain, TransactionState};
 	use synchronization_client::{SynchronizationClient, Client};
 	use synchronization_peers::PeersImpl;
 	use synchronization_executor::Task;
@@ -1277,13 +1560,16 @@ pub mod tests {
 	use synchronization_verifier::tests::DummyVerifier;
 	use utils::SynchronizationState;
 	use types::{PeerIndex, StorageRef, SynchronizationStateRef, ClientCoreRef};
-	use super::{Config, SynchronizationClientCore, ClientCore, CoreVerificationSink};
+	use super::{Config, SynchronizationClientCore, ClientCore, CoreVerificationSink, State, BlocksRequestLimits,
+		MAX_FEE_FILTER_RATE, BLOCK_STALLING_TIMEOUT_S, MAX_BLOCK_STALLING_TIMEOUT_S, BLOCK_STALLING_TIMEOUT_DECAY};
 	use super::super::SyncListener;
 
 	#[derive(Default)]
 	struct DummySyncListenerData {
 		pub is_synchronizing: bool,
 		pub best_blocks: Vec<H256>,
+		pub transactions: Vec<H256>,
+		pub chain_changes: Vec<(bool, H256)>,
 	}
 
 	struct DummySyncListener {
@@ -1306,6 +1592,18 @@ pub mod tests {
 		fn best_storage_block_inserted(&self, block_hash: &H256) {
 			self.data.lock().best_blocks.push(block_hash.clone());
 		}
+
+		fn transaction_accepted(&self, transaction: &IndexedTransaction) {
+			self.data.lock().transactions.push(transaction.hash.clone());
+		}
+
+		fn block_connected(&self, block_hash: &H256) {
+			self.data.lock().chain_changes.push((true, block_hash.clone()));
+		}
+
+		fn block_disconnected(&self, block_hash: &H256) {
+			self.data.lock().chain_changes.push((false, block_hash.clone()));
+		}
 	}
 
 	fn create_sync(storage: Option<StorageRef>, verifier: Option<DummyVerifier>) -> (Arc<DummyTaskExecutor>, ClientCoreRef<SynchronizationClientCore<DummyTaskExecutor>>, Arc<SynchronizationClient<DummyTaskExecutor, DummyVerifier>>) {
@@ -1318,7 +1616,7 @@ pub mod tests {
 		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
 		let chain = Chain::new(storage.clone(), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork), memory_pool.clone());
 		let executor = DummyTaskExecutor::new();
-		let config = Config { close_connection_on_bad_block: true };
+		let config = Config { close_connection_on_bad_block: true, verification_edge: H256::default(), minimum_chain_work: U256::zero() };
 
 		let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork)));
 		let client_core = SynchronizationClientCore::new(config, sync_state.clone(), sync_peers.clone(), executor.clone(), chain, chain_verifier.clone());
@@ -1613,14 +1911,32 @@ pub mod tests {
 		let b169 = test_data::block_h169();
 		sync.on_headers(1, types::Headers::with_headers(vec![b169.block_header]));
 
+		// unconnected headers are ignored, but peer is asked for headers, connecting them to our chain
 		let tasks = executor.take_tasks();
-		assert_eq!(tasks, vec![]);
+		assert_eq!(tasks, vec![request_block_headers_genesis(1)]);
 
 		let mut core = core.lock(); let chain = core.chain();
 		assert_eq!(chain.information().headers.best, 0);
 		assert_eq!(chain.information().headers.total, 0);
 	}
 
+	#[test]
+	fn synchronization_directly_fetches_announced_blocks() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		let b1 = test_data::block_h1();
+		let b2 = test_data::block_h2();
+		sync.on_headers(1, types::Headers::with_headers(vec![b1.block_header.clone()]));
+		assert!(core.lock().information().state.is_nearly_saturated());
+		executor.take_tasks();
+
+		// peer#1 is busy with b1, but it is asked for announced b2 anyway
+		sync.on_headers(1, types::Headers::with_headers(vec![b2.block_header.clone()]));
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![request_blocks(1, vec![b2.hash()])]);
+		assert_eq!(core.lock().information().chain.requested, 2);
+	}
+
 	#[test]
 	fn synchronization_works_for_forks_from_db_best_block() {
 		let genesis = test_data::genesis();
@@ -1988,6 +2304,45 @@ pub mod tests {
 		assert_eq!(core.lock().information().orphaned_transactions, 0);
 	}
 
+	#[test]
+	fn orphaned_transaction_unknown_parents_are_requested() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		let tx: Transaction = test_data::TransactionBuilder::with_default_input(0).into();
+		let parent_hash = tx.inputs[0].previous_output.hash.clone();
+		sync.on_transaction(1, tx.into());
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![Task::GetData(1, types::GetData::with_inventory(vec![InventoryVector::tx(parent_hash)]))]);
+	}
+
+	#[test]
+	fn orphaned_transaction_is_verified_when_input_is_confirmed() {
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder()
+			.transaction().coinbase()
+				.output().value(10).build()
+				.build()
+			.transaction()
+				.output().value(20).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+		let tx: Transaction = test_data::TransactionBuilder::with_output(10).set_input(&b1.transactions[1], 0).into();
+
+		let (_, core, sync) = create_sync(None, None);
+
+		sync.on_transaction(1, tx.clone().into());
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+
+		// parent is confirmed => orphan is verified
+		sync.on_block(1, b1.into());
+		assert_eq!(core.lock().information().chain.stored, 2);
+		assert_eq!(core.lock().information().orphaned_transactions, 0);
+		assert_eq!(core.lock().chain().transaction_state(&tx.hash()), TransactionState::Verifying);
+	}
+
 	#[test]
 	// https://github.com/ethcore/parity-bitcoin/issues/121
 	fn when_previous_block_verification_failed_fork_is_not_requested() {
@@ -2097,6 +2452,49 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::RelayNewTransaction(tx.into(), 0)]);
 	}
 
+	#[test]
+	fn relay_known_transaction_from_forcerelay_peer() {
+		let (executor, core, sync) = create_sync(None, None);
+		let peers = core.lock().peers();
+		peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		peers.insert(2, Services::default(), DummyOutboundSyncConnection::new());
+		peers.set_permissions(2, PeerPermissions { forcerelay: true, ..PeerPermissions::default() });
+		sync.on_connect(1);
+		sync.on_connect(2);
+		executor.take_tasks();
+
+		let tx: Transaction = test_data::TransactionBuilder::with_output(20).into();
+		sync.on_transaction(1, tx.clone().into());
+		assert_eq!(executor.take_tasks(), vec![Task::RelayNewTransaction(tx.clone().into(), 0)]);
+
+		// known transaction is ignored
+		sync.on_transaction(1, tx.clone().into());
+		assert!(executor.take_tasks().is_empty());
+
+		// unless it comes from the peer with forcerelay permission
+		sync.on_transaction(2, tx.clone().into());
+		assert_eq!(executor.take_tasks(), vec![Task::RelayNewTransaction(tx.into(), 0)]);
+	}
+
+	#[test]
+	fn relay_verified_package_with_package_fee_rate() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		let dchain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_output(20).store(dchain)
+			.reset().set_input(&dchain.at(0), 0).add_output(10).store(dchain);
+
+		sync.on_connect(1);
+		executor.take_tasks();
+
+		assert_eq!(sync.accept_verified_package(vec![dchain.at(0).into(), dchain.at(1).into()], 5), Ok(()));
+
+		// parent is relayed with the fee rate of the whole package
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![Task::RelayNewTransaction(dchain.at(0).into(), 5), Task::RelayNewTransaction(dchain.at(1).into(), 5)]);
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+	}
+
 	#[test]
 	fn receive_same_unknown_block_twice() {
 		let (_, _, sync) = create_sync(None, None);
@@ -2397,4 +2795,127 @@ pub mod tests {
 		assert_eq!(data.lock().is_synchronizing, false);
 		assert_eq!(data.lock().best_blocks.len(), 3);
 	}
+
+	#[test]
+	fn sync_listeners_are_notified_on_accepted_transaction() {
+		let (_, _, sync) = create_sync(None, None);
+
+		// install two sync listeners
+		let data1 = Arc::new(Mutex::new(DummySyncListenerData::default()));
+		let data2 = Arc::new(Mutex::new(DummySyncListenerData::default()));
+		sync.install_sync_listener(Box::new(DummySyncListener::new(data1.clone())));
+		sync.install_sync_listener(Box::new(DummySyncListener::new(data2.clone())));
+
+		// accepted transaction => both listeners are notified
+		let tx: Transaction = test_data::TransactionBuilder::with_output(20).into();
+		sync.on_transaction(1, tx.clone().into());
+		assert_eq!(data1.lock().transactions, vec![tx.hash()]);
+		assert_eq!(data2.lock().transactions, vec![tx.hash()]);
+	}
+
+	#[test]
+	fn sync_listeners_are_notified_on_reorganization() {
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder().header().parent(genesis.hash()).nonce(1).build().build();
+		let b2 = test_data::block_builder().header().parent(genesis.hash()).nonce(2).build().build();
+		let b3 = test_data::block_builder().header().parent(b2.hash()).build().build();
+
+		let (_, _, sync) = create_sync(None, None);
+		let data = Arc::new(Mutex::new(DummySyncListenerData::default()));
+		sync.install_sync_listener(Box::new(DummySyncListener::new(data.clone())));
+
+		// b1 is connected
+		sync.on_block(0, b1.clone().into());
+		assert_eq!(data.lock().chain_changes, vec![(true, b1.hash())]);
+		assert_eq!(data.lock().best_blocks, vec![b1.hash()]);
+
+		// b2 is side chain block => nothing changes
+		sync.on_block(0, b2.clone().into());
+		assert_eq!(data.lock().chain_changes.len(), 1);
+		assert_eq!(data.lock().best_blocks.len(), 1);
+
+		// b3 causes reorganization => b1 is disconnected before b2 and b3 are connected
+		sync.on_block(0, b3.clone().into());
+		assert_eq!(data.lock().chain_changes, vec![(true, b1.hash()), (false, b1.hash()), (true, b2.hash()), (true, b3.hash())]);
+		assert_eq!(data.lock().best_blocks, vec![b1.hash(), b3.hash()]);
+	}
+
+	#[test]
+	fn fee_filter_announced_to_peers() {
+		let (executor, core, _) = create_sync(None, None);
+		let peers = core.lock().peers();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.enable_fee_filter_announcement(0);
+		peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+
+		// any fee rate is accepted => nothing to announce
+		core.lock().announce_fee_filter();
+		assert!(executor.take_tasks().is_empty());
+
+		// peers shouldn't announce transactions while synchronizing
+		core.lock().switch_to_synchronization_state();
+		core.lock().announce_fee_filter();
+		assert_eq!(executor.take_tasks(), vec![Task::FeeFilter(0, types::FeeFilter::with_fee_rate(MAX_FEE_FILTER_RATE))]);
+		core.lock().announce_fee_filter();
+		assert!(executor.take_tasks().is_empty());
+
+		core.lock().switch_to_saturated_state();
+		core.lock().announce_fee_filter();
+		assert_eq!(executor.take_tasks(), vec![Task::FeeFilter(0, types::FeeFilter::with_fee_rate(0))]);
+	}
+
+	#[test]
+	fn synchronization_disconnects_stalling_peer_without_ban() {
+		let (_, core, _) = create_sync(None, None);
+		let mut core = core.lock();
+		let connection = DummyOutboundSyncConnection::new();
+		core.peers.insert(1, Services::default(), connection.clone());
+		core.peers_tasks.useful_peer(1);
+		core.peers_tasks.useful_peer(2);
+
+		// peer#1 is asked for the first blocks of the full download window, peer#2 is idle
+		let headers: Vec<IndexedBlockHeader> = test_data::build_n_empty_blocks_from_genesis(3, 1).into_iter()
+			.map(|block| block.block_header.into())
+			.collect();
+		core.chain.schedule_blocks_headers(headers);
+		let requested = core.chain.request_blocks_hashes(2);
+		core.peers_tasks.on_blocks_requested(1, &requested);
+		core.state = State::Synchronizing(0f64, 0);
+		let limits = BlocksRequestLimits { block_download_window: 2, ..Default::default() };
+
+		// peer#1 is not stalling yet
+		assert_eq!(core.reset_stalling_peer(&limits), None);
+
+		core.block_stalling_timeout = 0.001;
+		thread::sleep(Duration::from_millis(10));
+		let mut stalled = core.reset_stalling_peer(&limits).unwrap();
+		stalled.sort();
+		let mut expected = requested.clone();
+		expected.sort();
+		assert_eq!(stalled, expected);
+
+		// peer#1 is disconnected, but not punished && next peer gets more time
+		assert!(!core.peers.enumerate().contains(&1));
+		assert_eq!(connection.messages.lock().get("close"), Some(&1));
+		assert_eq!(connection.messages.lock().get("misbehaving"), None);
+		assert!((core.block_stalling_timeout - 0.002).abs() < 1e-9);
+	}
+
+	#[test]
+	fn synchronization_stalling_timeout_is_bounded() {
+		let (_, core, sync) = create_sync(None, None);
+		let block1 = test_data::block_h1();
+		let block2 = test_data::block_h2();
+		sync.on_headers(1, types::Headers::with_headers(vec![block1.block_header.clone(), block2.block_header.clone()]));
+
+		// timeout decays when requested block is received
+		core.lock().block_stalling_timeout = MAX_BLOCK_STALLING_TIMEOUT_S;
+		sync.on_block(1, block1.into());
+		assert!((core.lock().block_stalling_timeout - MAX_BLOCK_STALLING_TIMEOUT_S * BLOCK_STALLING_TIMEOUT_DECAY).abs() < 1e-9);
+
+		// but never below the initial timeout
+		core.lock().block_stalling_timeout = BLOCK_STALLING_TIMEOUT_S;
+		sync.on_block(1, block2.into());
+		assert_eq!(core.lock().block_stalling_timeout, BLOCK_STALLING_TIMEOUT_S);
+	}
 }
@@ -1,4 +1,8 @@
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use parking_lot::{Mutex, Condvar};
+use time::precise_time_s;
 use chain::{IndexedBlock, IndexedTransaction};
 use message::common::InventoryVector;
 use message::types;
@@ -6,6 +10,9 @@ use synchronization_peers::{BlockAnnouncementType, TransactionAnnouncementType};
 use types::{PeerIndex, PeersRef, RequestId};
 use utils::KnownHashType;
 
+/// Interval between checks of the queued transactions announcements (in ms)
+const TRANSACTION_ANNOUNCEMENT_INTERVAL_MS: u64 = 100;
+
 /// Synchronization task executor
 pub trait TaskExecutor : Send + Sync + 'static {
 	fn execute(&self, task: Task);
@@ -22,6 +29,20 @@ pub enum Task {
 	GetHeaders(PeerIndex, types::GetHeaders),
 	/// Get memory pool
 	MemoryPool(PeerIndex),
+	/// Request missing transactions of compact block
+	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Ask peer to announce new blocks with headers messages
+	SendHeaders(PeerIndex),
+	/// Ask peer to announce new blocks with cmpctblock messages
+	SendCompact(PeerIndex, types::SendCompact),
+	/// Ask peer not to announce transactions with fee rate below given
+	FeeFilter(PeerIndex, types::FeeFilter),
+	/// Offer transactions reconciliation to peer
+	SendTxRcncl(PeerIndex, types::SendTxRcncl),
+	/// Send sketch of transactions reconciliation set
+	Sketch(PeerIndex, types::Sketch),
+	/// Finish transactions reconciliation round
+	ReconcilDiff(PeerIndex, types::ReconcilDiff),
 	/// Send block
 	Block(PeerIndex, IndexedBlock),
 	/// Send merkleblock
@@ -42,6 +63,12 @@ pub enum Task {
 	Inventory(PeerIndex, types::Inv),
 	/// Send headers
 	Headers(PeerIndex, types::Headers, Option<RequestId>),
+	/// Send compact block filter
+	CFilter(PeerIndex, types::CFilter),
+	/// Send compact block filter headers
+	CFHeaders(PeerIndex, types::CFHeaders),
+	/// Send compact block filter checkpoints
+	CFCheckpt(PeerIndex, types::CFCheckpt),
 	/// Relay new block to peers
 	RelayNewBlock(IndexedBlock),
 	/// Relay new transaction to peers
@@ -52,12 +79,25 @@ pub enum Task {
 pub struct LocalSynchronizationTaskExecutor {
 	/// Active synchronization peers
 	peers: PeersRef,
+	/// Queued transactions announcements worker
+	announcement_worker: TransactionAnnouncementWorker,
+}
+
+/// Worker, which periodically sends queued transactions announcements to peers
+struct TransactionAnnouncementWorker {
+	/// Stop flag.
+	is_stopping: Arc<Mutex<bool>>,
+	/// Stop event.
+	stopping_event: Arc<Condvar>,
+	/// Announcement thread.
+	thread: Option<thread::JoinHandle<()>>,
 }
 
 impl LocalSynchronizationTaskExecutor {
 	pub fn new(peers: PeersRef) -> Arc<Self> {
 		Arc::new(LocalSynchronizationTaskExecutor {
-			peers: peers,
+			peers: peers.clone(),
+			announcement_worker: TransactionAnnouncementWorker::new(peers),
 		})
 	}
 
@@ -92,6 +132,55 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_get_block_txn(&self, peer_index: PeerIndex, getblocktxn: types::GetBlockTxn) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Querying {} transactions of compact block {} from peer#{}", getblocktxn.request.indexes.len(), getblocktxn.request.blockhash.to_reversed_str(), peer_index);
+			connection.send_get_block_txn(&getblocktxn);
+		}
+	}
+
+	fn execute_sendheaders(&self, peer_index: PeerIndex) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Asking peer#{} to announce blocks with headers", peer_index);
+			connection.send_sendheaders(&types::SendHeaders);
+		}
+	}
+
+	fn execute_send_compact(&self, peer_index: PeerIndex, sendcompact: types::SendCompact) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Asking peer#{} to announce blocks with version {} compact blocks", peer_index, sendcompact.second);
+			connection.send_send_compact(&sendcompact);
+		}
+	}
+
+	fn execute_feefilter(&self, peer_index: PeerIndex, feefilter: types::FeeFilter) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Asking peer#{} not to announce transactions with fee rate below {}", peer_index, feefilter.fee_rate);
+			connection.send_feefilter(&feefilter);
+		}
+	}
+
+	fn execute_sendtxrcncl(&self, peer_index: PeerIndex, sendtxrcncl: types::SendTxRcncl) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Offering transactions reconciliation to peer#{}", peer_index);
+			connection.send_sendtxrcncl(&sendtxrcncl);
+		}
+	}
+
+	fn execute_sketch(&self, peer_index: PeerIndex, sketch: types::Sketch) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending {}-bytes reconciliation sketch to peer#{}", sketch.skdata.len(), peer_index);
+			connection.send_sketch(&sketch);
+		}
+	}
+
+	fn execute_reconcildiff(&self, peer_index: PeerIndex, reconcildiff: types::ReconcilDiff) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Finishing transactions reconciliation with peer#{}: success = {}, asked = {}", peer_index, reconcildiff.success, reconcildiff.ask_shortids.len());
+			connection.send_reconcildiff(&reconcildiff);
+		}
+	}
+
 	fn execute_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending block {} to peer#{}", block.hash().to_reversed_str(), peer_index);
@@ -137,7 +226,7 @@ impl LocalSynchronizationTaskExecutor {
 			trace!(target: "sync", "Sending transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, transaction.hash, KnownHashType::Transaction);
 			let transaction = types::Tx {
-				transaction: transaction.raw,
+				transaction: transaction.into_raw(),
 			};
 			connection.send_transaction(&transaction);
 		}
@@ -148,7 +237,7 @@ impl LocalSynchronizationTaskExecutor {
 			trace!(target: "sync", "Sending witness transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, transaction.hash, KnownHashType::Transaction);
 			let transaction = types::Tx {
-				transaction: transaction.raw,
+				transaction: transaction.into_raw(),
 			};
 			connection.send_witness_transaction(&transaction);
 		}
@@ -185,6 +274,27 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_cfilter(&self, peer_index: PeerIndex, cfilter: types::CFilter) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfilter for block {} to peer#{}", cfilter.block_hash.to_reversed_str(), peer_index);
+			connection.send_cfilter(&cfilter);
+		}
+	}
+
+	fn execute_cfheaders(&self, peer_index: PeerIndex, cfheaders: types::CFHeaders) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfheaders to peer#{} with {} items", peer_index, cfheaders.filter_hashes.len());
+			connection.send_cfheaders(&cfheaders);
+		}
+	}
+
+	fn execute_cfcheckpt(&self, peer_index: PeerIndex, cfcheckpt: types::CFCheckpt) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending cfcheckpt to peer#{} with {} items", peer_index, cfcheckpt.filter_headers.len());
+			connection.send_cfcheckpt(&cfcheckpt);
+		}
+	}
+
 	fn execute_relay_block(&self, block: IndexedBlock) {
 		for peer_index in self.peers.enumerate() {
 			match self.peers.filter_block(peer_index, &block) {
@@ -207,17 +317,87 @@ impl LocalSynchronizationTaskExecutor {
 	}
 
 	fn execute_relay_transaction(&self, transaction: IndexedTransaction, fee_rate: u64) {
+		// transactions are announced by the announcement worker
 		for peer_index in self.peers.enumerate() {
-			match self.peers.filter_transaction(peer_index, &transaction, Some(fee_rate)) {
-				TransactionAnnouncementType::SendInventory => self.execute_inventory(peer_index, types::Inv::with_inventory(vec![
-					InventoryVector::tx(transaction.hash.clone()),
-				])),
+			match self.peers.filter_transaction(peer_index, &transaction.hash, &transaction.raw, Some(fee_rate)) {
+				TransactionAnnouncementType::SendInventory => self.peers.queue_transaction_announcement(peer_index, &transaction),
 				TransactionAnnouncementType::DoNotAnnounce => (),
 			}
 		}
 	}
 }
 
+impl TransactionAnnouncementWorker {
+	fn new(peers: PeersRef) -> Self {
+		let is_stopping = Arc::new(Mutex::new(false));
+		let stopping_event = Arc::new(Condvar::new());
+		TransactionAnnouncementWorker {
+			is_stopping: is_stopping.clone(),
+			stopping_event: stopping_event.clone(),
+			thread: Some(thread::Builder::new()
+				.name("Sync transactions announcement thread".to_string())
+				.spawn(move || TransactionAnnouncementWorker::worker_proc(is_stopping, stopping_event, peers))
+				.expect("Error creating transactions announcement thread"))
+		}
+	}
+
+	fn worker_proc(is_stopping: Arc<Mutex<bool>>, stopping_event: Arc<Condvar>, peers: PeersRef) {
+		loop {
+			let mut lock = is_stopping.lock();
+			if *lock {
+				break;
+			}
+
+			if !stopping_event.wait_for(&mut lock, Duration::from_millis(TRANSACTION_ANNOUNCEMENT_INTERVAL_MS)).timed_out() {
+				if *lock {
+					break;
+				}
+
+				// spurious wakeup?
+				continue;
+			}
+			drop(lock);
+
+			announce_queued_transactions(&peers, precise_time_s());
+		}
+
+		trace!(target: "sync", "Stopping sync transactions announcement thread");
+	}
+}
+
+impl Drop for TransactionAnnouncementWorker {
+	fn drop(&mut self) {
+		if let Some(join_handle) = self.thread.take() {
+			*self.is_stopping.lock() = true;
+			self.stopping_event.notify_all();
+			join_handle.join().expect("Clean shutdown.");
+		}
+	}
+}
+
+/// Announce queued transactions to peers, which announcement time has come.
+/// Every peer receives single `inv` message with all transactions, queued since the previous announcement.
+/// Reconciliation rounds are started with peers, which are reconciling transactions with us.
+fn announce_queued_transactions(peers: &PeersRef, now: f64) {
+	for peer_index in peers.enumerate() {
+		let connection = match peers.connection(peer_index) {
+			Some(connection) => connection,
+			None => continue,
+		};
+
+		let hashes = peers.take_transaction_announcements(peer_index, now);
+		if !hashes.is_empty() {
+			trace!(target: "sync", "Announcing {} transactions to peer#{}", hashes.len(), peer_index);
+			connection.send_inventory(&types::Inv::with_inventory(hashes.into_iter().map(InventoryVector::tx).collect()));
+		}
+
+		if let Some(reqrecon) = peers.request_transaction_reconciliation(peer_index, now) {
+			trace!(target: "sync", "Requesting transactions reconciliation from peer#{}", peer_index);
+			connection.send_reqrecon(&reqrecon);
+		}
+	}
+}
+
 impl TaskExecutor for LocalSynchronizationTaskExecutor {
 	fn execute(&self, task: Task) {
 		match task {
@@ -225,6 +405,13 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::GetData(peer_index, getdata) => self.execute_getdata(peer_index, getdata),
 			Task::GetHeaders(peer_index, getheaders) => self.execute_getheaders(peer_index, getheaders),
 			Task::MemoryPool(peer_index) => self.execute_memorypool(peer_index),
+			Task::GetBlockTxn(peer_index, getblocktxn) => self.execute_get_block_txn(peer_index, getblocktxn),
+			Task::SendHeaders(peer_index) => self.execute_sendheaders(peer_index),
+			Task::SendCompact(peer_index, sendcompact) => self.execute_send_compact(peer_index, sendcompact),
+			Task::FeeFilter(peer_index, feefilter) => self.execute_feefilter(peer_index, feefilter),
+			Task::SendTxRcncl(peer_index, sendtxrcncl) => self.execute_sendtxrcncl(peer_index, sendtxrcncl),
+			Task::Sketch(peer_index, sketch) => self.execute_sketch(peer_index, sketch),
+			Task::ReconcilDiff(peer_index, reconcildiff) => self.execute_reconcildiff(peer_index, reconcildiff),
 			Task::Block(peer_index, block) => self.execute_block(peer_index, block),
 			Task::MerkleBlock(peer_index, block) => self.execute_merkleblock(peer_index, block),
 			Task::CompactBlock(peer_index, block) => self.execute_compact_block(peer_index, block),
@@ -235,6 +422,9 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::NotFound(peer_index, notfound) => self.execute_notfound(peer_index, notfound),
 			Task::Inventory(peer_index, inventory) => self.execute_inventory(peer_index, inventory),
 			Task::Headers(peer_index, headers, request_id) => self.execute_headers(peer_index, headers, request_id),
+			Task::CFilter(peer_index, cfilter) => self.execute_cfilter(peer_index, cfilter),
+			Task::CFHeaders(peer_index, cfheaders) => self.execute_cfheaders(peer_index, cfheaders),
+			Task::CFCheckpt(peer_index, cfcheckpt) => self.execute_cfcheckpt(peer_index, cfcheckpt),
 			Task::RelayNewBlock(block) => self.execute_relay_block(block),
 			Task::RelayNewTransaction(transaction, fee_rate) => self.execute_relay_transaction(transaction, fee_rate),
 		}
@@ -253,7 +443,7 @@ pub mod tests {
 	use message::{Services, types};
 	use inbound_connection::tests::DummyOutboundSyncConnection;
 	use local_node::tests::{default_filterload, make_filteradd};
-	use synchronization_peers::{PeersImpl, PeersContainer, PeersFilters, PeersOptions, BlockAnnouncementType};
+	use synchronization_peers::{PeersImpl, PeersContainer, PeersFilters, PeersOptions, BlockAnnouncementType, TransactionAnnouncementType};
 
 	pub struct DummyTaskExecutor {
 		tasks: Mutex<Vec<Task>>,
@@ -333,6 +523,7 @@ pub mod tests {
 	fn relay_new_transaction_with_bloom_filter() {
 		let peers = Arc::new(PeersImpl::default());
 		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+		let peers_ref: PeersRef = peers.clone();
 
 		let tx1: Transaction = test_data::TransactionBuilder::with_output(10).into();
 		let tx2: Transaction = test_data::TransactionBuilder::with_output(20).into();
@@ -368,6 +559,7 @@ pub mod tests {
 
 		// tx1 is relayed to peers: 1, 3, 4
 		executor.execute(Task::RelayNewTransaction(tx1.into(), 0));
+		announce_queued_transactions(&peers_ref, 1e12);
 
 		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
 		assert_eq!(*c2.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
@@ -376,6 +568,7 @@ pub mod tests {
 
 		// tx2 is relayed to peers: 2, 3, 4
 		executor.execute(Task::RelayNewTransaction(tx2.into(), 0));
+		announce_queued_transactions(&peers_ref, 2e12);
 
 		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
 		assert_eq!(*c2.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
@@ -383,10 +576,39 @@ pub mod tests {
 		assert_eq!(*c4.messages.lock().entry("inventory".to_owned()).or_insert(0), 2);
 	}
 
+	#[test]
+	fn relay_new_transaction_after_filterload_to_light_client() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+		let peers_ref: PeersRef = peers.clone();
+
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_output(20).into();
+		let tx2_hash = tx2.hash();
+
+		// light client has connected with relay = false
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		peers.set_transaction_announcement_type(1, TransactionAnnouncementType::DoNotAnnounce);
+
+		// tx1 is not relayed before filter is loaded
+		executor.execute(Task::RelayNewTransaction(tx1.into(), 0));
+		announce_queued_transactions(&peers_ref, 1e12);
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+
+		// tx2 is relayed, when it matches loaded filter
+		peers.set_bloom_filter(1, default_filterload());
+		peers.update_bloom_filter(1, make_filteradd(&*tx2_hash));
+		executor.execute(Task::RelayNewTransaction(tx2.into(), 0));
+		announce_queued_transactions(&peers_ref, 2e12);
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
+	}
+
 	#[test]
 	fn relay_new_transaction_with_feefilter() {
 		let peers = Arc::new(PeersImpl::default());
 		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+		let peers_ref: PeersRef = peers.clone();
 
 		let c2 = DummyOutboundSyncConnection::new();
 		peers.insert(2, Services::default(), c2.clone());
@@ -398,9 +620,38 @@ pub mod tests {
 		peers.insert(4, Services::default(), c4.clone());
 
 		executor.execute(Task::RelayNewTransaction(test_data::genesis().transactions[0].clone().into(), 3500));
+		announce_queued_transactions(&peers_ref, 1e12);
 
 		assert_eq!(*c2.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
 		assert_eq!(*c3.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
 		assert_eq!(*c4.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
 	}
+
+	#[test]
+	fn relay_new_transactions_with_single_inventory() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+		let peers_ref: PeersRef = peers.clone();
+
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_output(20).into();
+
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		// next announcement is scheduled far in the future, so that announcement thread never sends it
+		announce_queued_transactions(&peers_ref, 1e12);
+
+		// both transactions are announced with single message
+		executor.execute(Task::RelayNewTransaction(tx1.clone().into(), 0));
+		executor.execute(Task::RelayNewTransaction(tx2.into(), 0));
+		executor.execute(Task::RelayNewTransaction(tx1.clone().into(), 0));
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+		announce_queued_transactions(&peers_ref, 2e12);
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
+
+		// announced transaction isn't announced again
+		executor.execute(Task::RelayNewTransaction(tx1.into(), 0));
+		announce_queued_transactions(&peers_ref, 3e12);
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
+	}
 }
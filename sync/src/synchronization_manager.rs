@@ -84,6 +84,8 @@ impl ManagementWorker {
 			let mut core = core.lock();
 			// trace synchronization state
 			core.print_synchronization_information();
+			// announce memory pool minimum fee rate to peers
+			core.announce_fee_filter();
 			// execute management tasks if not saturated
 			if core.state().is_synchronizing() || core.state().is_nearly_saturated() {
 				let (blocks_to_request, blocks_to_forget) = manage_synchronization_peers_blocks(&peers_config, core.peers(), core.peers_tasks());
@@ -206,7 +208,7 @@ pub fn manage_synchronization_peers_blocks(config: &ManagePeersConfig, peers: Pe
 		if peers_tasks.on_peer_block_failure(worst_peer_index) {
 			warn!(target: "sync", "Too many failures for peer#{}. Excluding from synchronization.", worst_peer_index);
 			peers_tasks.unuseful_peer(worst_peer_index);
-			peers.misbehaving(worst_peer_index, &format!("Too many failures."));
+			peers.misbehaving(worst_peer_index, 0, &format!("Too many failures."));
 		}
 	}
 
@@ -235,7 +237,7 @@ pub fn manage_synchronization_peers_headers(config: &ManagePeersConfig, peers: P
 		// if peer failed many times => forget it
 		if peers_tasks.on_peer_headers_failure(worst_peer_index) {
 			warn!(target: "sync", "Too many header failures for peer#{}. Excluding from synchronization.", worst_peer_index);
-			peers.misbehaving(worst_peer_index, &format!("Too many header failures."));
+			peers.misbehaving(worst_peer_index, 0, &format!("Too many header failures."));
 		}
 	}
 }
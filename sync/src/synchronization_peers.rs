@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 use parking_lot::RwLock;
-use chain::{IndexedBlock, IndexedTransaction};
+use chain::{IndexedBlock, IndexedTransaction, Transaction};
 use message::{types, Services};
-use p2p::OutboundSyncConnectionRef;
+use p2p::{OutboundSyncConnectionRef, PeerPermissions};
 use primitives::hash::H256;
 use types::PeerIndex;
-use utils::{KnownHashType, ConnectionFilter};
+use utils::{KnownHashType, ConnectionFilter, TransactionAnnouncementQueue, TransactionReconciliation};
+
+/// Misbehavior score of the DoS attempt, which is enough to ban the peer.
+const DOS_SCORE: u32 = 100;
 
 /// Block announcement type
 #[derive(Debug, Clone, Copy)]
@@ -54,10 +57,14 @@ pub trait PeersContainer {
 	fn insert(&self, peer_index: PeerIndex, services: Services, connection: OutboundSyncConnectionRef);
 	/// Remove peer connection
 	fn remove(&self, peer_index: PeerIndex);
-	/// Close and remove peer connection due to misbehaving
-	fn misbehaving(&self, peer_index: PeerIndex, reason: &str);
-	/// Close and remove peer connection due to detected DOS attempt
+	/// Close and remove peer connection due to misbehaving.
+	/// Peer is banned once sum of its misbehavior scores reaches 100. Peers with `noban` permission are never punished.
+	fn misbehaving(&self, peer_index: PeerIndex, score: u32, reason: &str);
+	/// Close and remove peer connection due to detected DOS attempt. Peer is banned immediately.
+	/// Peers with `noban` permission are never punished.
 	fn dos(&self, peer_index: PeerIndex, reason: &str);
+	/// Close and remove peer connection, which has requested the service, we do not provide to this peer.
+	fn disconnect(&self, peer_index: PeerIndex, reason: &str);
 }
 
 /// Filters for peers connections
@@ -70,10 +77,12 @@ pub trait PeersFilters {
 	fn clear_bloom_filter(&self, peer_index: PeerIndex);
 	/// Set up fee filter for the connection
 	fn set_fee_filter(&self, peer_index: PeerIndex, filter: types::FeeFilter);
+	/// Get minimal fee rate of transactions, announced over the connection
+	fn fee_filter(&self, peer_index: PeerIndex) -> u64;
 	/// Is block passing filters for the connection
 	fn filter_block(&self, peer_index: PeerIndex, block: &IndexedBlock) -> BlockAnnouncementType;
 	/// Is block passing filters for the connection
-	fn filter_transaction(&self, peer_index: PeerIndex, transaction: &IndexedTransaction, transaction_fee_rate: Option<u64>) -> TransactionAnnouncementType;
+	fn filter_transaction(&self, peer_index: PeerIndex, hash: &H256, transaction: &Transaction, transaction_fee_rate: Option<u64>) -> TransactionAnnouncementType;
 	/// Remember known hash
 	fn hash_known_as(&self, peer_index: PeerIndex, hash: H256, hash_type: KnownHashType);
 	/// Is given hash known by peer as hash of given type
@@ -82,16 +91,49 @@ pub trait PeersFilters {
 	fn build_compact_block(&self, peer_index: PeerIndex, block: &IndexedBlock) -> Option<types::CompactBlock>;
 	/// Build merkle block using filter for given peer
 	fn build_merkle_block(&self, peer_index: PeerIndex, block: &IndexedBlock) -> Option<MerkleBlockArtefacts>;
+	/// Queue transaction to be announced to the peer. If reconciliation is used, transaction is added to the reconciliation set
+	fn queue_transaction_announcement(&self, peer_index: PeerIndex, transaction: &IndexedTransaction);
+	/// Take queued transactions, which are to be announced to the peer at given time
+	fn take_transaction_announcements(&self, peer_index: PeerIndex, now: f64) -> Vec<H256>;
+	/// Start transactions reconciliation round with the peer, if it is time to
+	fn request_transaction_reconciliation(&self, peer_index: PeerIndex, now: f64) -> Option<types::ReqRecon>;
+	/// Build sketch of transactions reconciliation set, when the peer has started reconciliation round
+	fn respond_transaction_reconciliation(&self, peer_index: PeerIndex, message: &types::ReqRecon) -> Option<types::Sketch>;
+	/// Finish transactions reconciliation round, when peer has sent sketch of its set. Returns `reconcildiff` message and transactions to announce
+	fn finish_transaction_reconciliation(&self, peer_index: PeerIndex, message: &types::Sketch) -> Option<(types::ReconcilDiff, Vec<H256>)>;
+	/// Take transactions to announce, when peer has finished transactions reconciliation round
+	fn take_reconciled_transactions(&self, peer_index: PeerIndex, message: &types::ReconcilDiff) -> Vec<H256>;
 }
 
 /// Options for peers connections
 pub trait PeersOptions {
 	/// Is node supporting SegWit?
 	fn is_segwit_enabled(&self, peer_index: PeerIndex) -> bool;
+	/// Is node serving only recent blocks (BIP159)?
+	fn is_network_limited(&self, peer_index: PeerIndex) -> bool;
+	/// Set up permissions, granted to the whitelisted peer
+	fn set_permissions(&self, peer_index: PeerIndex, permissions: PeerPermissions);
+	/// Get permissions, granted to the peer
+	fn permissions(&self, peer_index: PeerIndex) -> PeerPermissions;
+	/// Remember compact blocks version (BIP152), announced by the peer with `sendcmpct` message.
+	/// Highest supported version is used, when peer announces several versions
+	fn set_compact_block_version(&self, peer_index: PeerIndex, version: u64);
+	/// Compact blocks version, negotiated with the peer. None if peer hasn't announced any supported version
+	fn compact_block_version(&self, peer_index: PeerIndex) -> Option<u64>;
 	/// Set up new block announcement type for the connection
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType);
 	/// Set up new transaction announcement type for the connection
 	fn set_transaction_announcement_type(&self, peer_index: PeerIndex, announcement_type: TransactionAnnouncementType);
+	/// Start announcing memory pool minimum fee rate to the peer with `feefilter` messages (BIP133)
+	fn enable_fee_filter_announcement(&self, peer_index: PeerIndex);
+	/// Enumerate peers, accepting `feefilter` messages, along with the last fee rate, announced to them
+	fn fee_filter_announcements(&self) -> Vec<(PeerIndex, u64)>;
+	/// Remember fee rate, announced to the peer
+	fn set_announced_fee_rate(&self, peer_index: PeerIndex, fee_rate: u64);
+	/// Offer transactions reconciliation (BIP330) to the peer. Returns `sendtxrcncl` message to send
+	fn enable_transaction_reconciliation(&self, peer_index: PeerIndex, is_initiator: bool) -> Option<types::SendTxRcncl>;
+	/// Start transactions reconciliation, when peer has accepted our offer. Returns false if reconciliation hasn't been offered
+	fn register_transaction_reconciliation(&self, peer_index: PeerIndex, message: &types::SendTxRcncl) -> bool;
 }
 
 /// Single connected peer data
@@ -100,12 +142,22 @@ struct Peer {
 	pub connection: OutboundSyncConnectionRef,
 	/// Peer services
 	pub services: Services,
+	/// Permissions, granted to the whitelisted peer
+	pub permissions: PeerPermissions,
 	/// Connection filter
 	pub filter: ConnectionFilter,
+	/// Transactions, waiting to be announced
+	pub transaction_announcements: TransactionAnnouncementQueue,
+	/// Transactions reconciliation. None if reconciliation hasn't been offered to the peer
+	pub transaction_reconciliation: Option<TransactionReconciliation>,
+	/// Compact blocks version, announced by the peer
+	pub compact_block_version: Option<u64>,
 	/// Block announcement type
 	pub block_announcement_type: BlockAnnouncementType,
 	/// Transaction announcement type
 	pub transaction_announcement_type: TransactionAnnouncementType,
+	/// Fee rate, announced to the peer with `feefilter` message. None if peer doesn't accept these messages
+	pub announced_fee_rate: Option<u64>,
 }
 
 /// Default implementation of connectd peers container
@@ -121,11 +173,27 @@ impl Peer {
 		Peer {
 			connection: connection,
 			services: services,
+			permissions: PeerPermissions::default(),
 			filter: ConnectionFilter::default(),
+			transaction_announcements: TransactionAnnouncementQueue::default(),
+			transaction_reconciliation: None,
+			compact_block_version: None,
 			block_announcement_type: BlockAnnouncementType::SendInventory,
 			transaction_announcement_type: TransactionAnnouncementType::SendInventory,
+			announced_fee_rate: None,
 		}
 	}
+
+	/// Filter out transactions, already known to the peer. Remaining transactions are remembered as known
+	pub fn filter_transaction_announcements(&mut self, hashes: Vec<H256>) -> Vec<H256> {
+		let hashes: Vec<_> = hashes.into_iter()
+			.filter(|hash| !self.filter.is_hash_known_as(hash, KnownHashType::Transaction))
+			.collect();
+		for hash in &hashes {
+			self.filter.hash_known_as(hash.clone(), KnownHashType::Transaction);
+		}
+		hashes
+	}
 }
 
 impl Peers for PeersImpl {
@@ -163,16 +231,33 @@ impl PeersContainer for PeersImpl {
 		}
 	}
 
-	fn misbehaving(&self, peer_index: PeerIndex, reason: &str) {
+	fn misbehaving(&self, peer_index: PeerIndex, score: u32, reason: &str) {
+		if self.permissions(peer_index).noban {
+			warn!(target: "sync", "Not punishing whitelisted peer#{} for misbehavior (score {}): {}", peer_index, score, reason);
+			return;
+		}
+
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
-			warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior: {}", peer_index, reason);
-			peer.connection.close();
+			warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior (score {}): {}", peer_index, score, reason);
+			peer.connection.misbehaving(score);
 		}
 	}
 
 	fn dos(&self, peer_index: PeerIndex, reason: &str) {
+		if self.permissions(peer_index).noban {
+			warn!(target: "sync", "Not punishing whitelisted peer#{} for DoS: {}", peer_index, reason);
+			return;
+		}
+
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
 			warn!(target: "sync", "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
+			peer.connection.misbehaving(DOS_SCORE);
+		}
+	}
+
+	fn disconnect(&self, peer_index: PeerIndex, reason: &str) {
+		if let Some(peer) = self.peers.write().remove(&peer_index) {
+			trace!(target: "sync", "Disconnecting from peer#{}: {}", peer_index, reason);
 			peer.connection.close();
 		}
 	}
@@ -182,6 +267,8 @@ impl PeersFilters for PeersImpl {
 	fn set_bloom_filter(&self, peer_index: PeerIndex, filter: types::FilterLoad) {
 		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
 			peer.filter.load(filter);
+			// light clients, which have connected with `relay` = false, are waiting for transactions once filter is loaded (BIP37)
+			peer.transaction_announcement_type = TransactionAnnouncementType::SendInventory;
 		}
 	}
 
@@ -194,6 +281,7 @@ impl PeersFilters for PeersImpl {
 	fn clear_bloom_filter(&self, peer_index: PeerIndex) {
 		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
 			peer.filter.clear();
+			peer.transaction_announcement_type = TransactionAnnouncementType::SendInventory;
 		}
 	}
 
@@ -203,6 +291,10 @@ impl PeersFilters for PeersImpl {
 		}
 	}
 
+	fn fee_filter(&self, peer_index: PeerIndex) -> u64 {
+		self.peers.read().get(&peer_index).map(|peer| peer.filter.min_fee_rate()).unwrap_or_default()
+	}
+
 	fn filter_block(&self, peer_index: PeerIndex, block: &IndexedBlock) -> BlockAnnouncementType {
 		if let Some(peer) = self.peers.read().get(&peer_index) {
 			if peer.filter.filter_block(&block.header.hash) {
@@ -213,9 +305,9 @@ impl PeersFilters for PeersImpl {
 		BlockAnnouncementType::DoNotAnnounce
 	}
 
-	fn filter_transaction(&self, peer_index: PeerIndex, transaction: &IndexedTransaction, transaction_fee_rate: Option<u64>) -> TransactionAnnouncementType {
+	fn filter_transaction(&self, peer_index: PeerIndex, hash: &H256, transaction: &Transaction, transaction_fee_rate: Option<u64>) -> TransactionAnnouncementType {
 		if let Some(peer) = self.peers.read().get(&peer_index) {
-			if peer.filter.filter_transaction(transaction, transaction_fee_rate) {
+			if peer.filter.filter_transaction(hash, transaction, transaction_fee_rate) {
 				return peer.transaction_announcement_type
 			}
 		}
@@ -244,6 +336,68 @@ impl PeersFilters for PeersImpl {
 		self.peers.read().get(&peer_index)
 			.and_then(|peer| peer.filter.build_merkle_block(block))
 	}
+
+	fn queue_transaction_announcement(&self, peer_index: PeerIndex, transaction: &IndexedTransaction) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			match peer.transaction_reconciliation {
+				Some(ref mut reconciliation) if reconciliation.is_registered() => {
+					// transactions, received from the peer, are also in its reconciliation set
+					if !peer.filter.is_hash_known_as(&transaction.hash, KnownHashType::Transaction) {
+						reconciliation.push(transaction.hash.clone(), &transaction.witness_hash);
+					}
+				},
+				_ => peer.transaction_announcements.push(transaction.hash.clone()),
+			}
+		}
+	}
+
+	fn take_transaction_announcements(&self, peer_index: PeerIndex, now: f64) -> Vec<H256> {
+		let mut peers = self.peers.write();
+		let peer = match peers.get_mut(&peer_index) {
+			Some(peer) => peer,
+			None => return Vec::new(),
+		};
+
+		// peer could have announced transaction to us, while it has been waiting in the queue
+		let hashes = peer.transaction_announcements.take(now);
+		peer.filter_transaction_announcements(hashes)
+	}
+
+	fn request_transaction_reconciliation(&self, peer_index: PeerIndex, now: f64) -> Option<types::ReqRecon> {
+		self.peers.write().get_mut(&peer_index)
+			.and_then(|peer| peer.transaction_reconciliation.as_mut())
+			.and_then(|reconciliation| reconciliation.request(now))
+	}
+
+	fn respond_transaction_reconciliation(&self, peer_index: PeerIndex, message: &types::ReqRecon) -> Option<types::Sketch> {
+		self.peers.write().get_mut(&peer_index)
+			.and_then(|peer| peer.transaction_reconciliation.as_mut())
+			.and_then(|reconciliation| reconciliation.respond(message))
+	}
+
+	fn finish_transaction_reconciliation(&self, peer_index: PeerIndex, message: &types::Sketch) -> Option<(types::ReconcilDiff, Vec<H256>)> {
+		let mut peers = self.peers.write();
+		let peer = match peers.get_mut(&peer_index) {
+			Some(peer) => peer,
+			None => return None,
+		};
+
+		let result = peer.transaction_reconciliation.as_mut().and_then(|reconciliation| reconciliation.finish(message));
+		result.map(|(reconcildiff, hashes)| (reconcildiff, peer.filter_transaction_announcements(hashes)))
+	}
+
+	fn take_reconciled_transactions(&self, peer_index: PeerIndex, message: &types::ReconcilDiff) -> Vec<H256> {
+		let mut peers = self.peers.write();
+		let peer = match peers.get_mut(&peer_index) {
+			Some(peer) => peer,
+			None => return Vec::new(),
+		};
+
+		let hashes = peer.transaction_reconciliation.as_mut()
+			.map(|reconciliation| reconciliation.reconciled(message))
+			.unwrap_or_default();
+		peer.filter_transaction_announcements(hashes)
+	}
 }
 
 impl PeersOptions for PeersImpl {
@@ -254,6 +408,40 @@ impl PeersOptions for PeersImpl {
 			.unwrap_or_default()
 	}
 
+	fn is_network_limited(&self, peer_index: PeerIndex) -> bool {
+		self.peers.read()
+			.get(&peer_index)
+			.map(|peer| !peer.services.network() && peer.services.network_limited())
+			.unwrap_or_default()
+	}
+
+	fn set_permissions(&self, peer_index: PeerIndex, permissions: PeerPermissions) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			peer.permissions = permissions;
+		}
+	}
+
+	fn permissions(&self, peer_index: PeerIndex) -> PeerPermissions {
+		self.peers.read()
+			.get(&peer_index)
+			.map(|peer| peer.permissions)
+			.unwrap_or_default()
+	}
+
+	fn set_compact_block_version(&self, peer_index: PeerIndex, version: u64) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			if peer.compact_block_version.map(|current| current < version).unwrap_or(true) {
+				peer.compact_block_version = Some(version);
+			}
+		}
+	}
+
+	fn compact_block_version(&self, peer_index: PeerIndex) -> Option<u64> {
+		self.peers.read()
+			.get(&peer_index)
+			.and_then(|peer| peer.compact_block_version)
+	}
+
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType) {
 		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
 			peer.block_announcement_type = announcement_type;
@@ -265,4 +453,41 @@ impl PeersOptions for PeersImpl {
 			peer.transaction_announcement_type = announcement_type;
 		}
 	}
+
+	fn enable_fee_filter_announcement(&self, peer_index: PeerIndex) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			// peer assumes zero fee rate until first `feefilter` message is received
+			peer.announced_fee_rate = Some(0);
+		}
+	}
+
+	fn fee_filter_announcements(&self) -> Vec<(PeerIndex, u64)> {
+		self.peers.read().iter()
+			.filter_map(|(peer_index, peer)| peer.announced_fee_rate.map(|fee_rate| (*peer_index, fee_rate)))
+			.collect()
+	}
+
+	fn set_announced_fee_rate(&self, peer_index: PeerIndex, fee_rate: u64) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			if peer.announced_fee_rate.is_some() {
+				peer.announced_fee_rate = Some(fee_rate);
+			}
+		}
+	}
+
+	fn enable_transaction_reconciliation(&self, peer_index: PeerIndex, is_initiator: bool) -> Option<types::SendTxRcncl> {
+		self.peers.write().get_mut(&peer_index).map(|peer| {
+			let reconciliation = TransactionReconciliation::new(is_initiator);
+			let sendtxrcncl = reconciliation.sendtxrcncl();
+			peer.transaction_reconciliation = Some(reconciliation);
+			sendtxrcncl
+		})
+	}
+
+	fn register_transaction_reconciliation(&self, peer_index: PeerIndex, message: &types::SendTxRcncl) -> bool {
+		self.peers.write().get_mut(&peer_index)
+			.and_then(|peer| peer.transaction_reconciliation.as_mut())
+			.map(|reconciliation| reconciliation.register(message))
+			.unwrap_or_default()
+	}
 }
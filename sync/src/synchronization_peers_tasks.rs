@@ -149,6 +149,22 @@ impl PeersTasks {
 			.map(|br| &br.blocks)
 	}
 
+	/// Get number of blocks, requested from the peer, but not yet received
+	pub fn blocks_in_transit(&self, peer_index: PeerIndex) -> usize {
+		self.blocks_requests
+			.get(&peer_index)
+			.map(|br| br.blocks.len())
+			.unwrap_or_default()
+	}
+
+	/// Get peer, which is asked for given block and has not responded with any block for given period
+	pub fn stalling_peer(&self, block_hash: &H256, timeout_s: f64) -> Option<PeerIndex> {
+		let now = precise_time_s();
+		self.blocks_requests.iter()
+			.find(|&(_, br)| br.blocks.contains(block_hash))
+			.and_then(|(peer_index, br)| if now - br.timestamp > timeout_s { Some(*peer_index) } else { None })
+	}
+
 	/// Get peer statistics
 	pub fn get_peer_stats(&self, peer_index: PeerIndex) -> Option<&PeerStats> {
 		self.stats.get(&peer_index)
@@ -609,4 +625,35 @@ mod tests {
 		assert_eq!(peers_for_blocks[0], 2);
 		assert_eq!(peers_for_blocks[1], 1);
 	}
+	#[test]
+	fn peer_blocks_in_transit() {
+		let mut peers = PeersTasks::default();
+		assert_eq!(peers.blocks_in_transit(1), 0);
+		peers.on_blocks_requested(1, &vec![H256::from(1), H256::from(2)]);
+		assert_eq!(peers.blocks_in_transit(1), 2);
+		peers.on_block_received(1, &H256::from(1));
+		assert_eq!(peers.blocks_in_transit(1), 1);
+		peers.on_block_received(1, &H256::from(2));
+		assert_eq!(peers.blocks_in_transit(1), 0);
+	}
+
+	#[test]
+	fn peer_stalling_block_download() {
+		let mut peers = PeersTasks::default();
+		peers.on_blocks_requested(1, &vec![H256::from(1), H256::from(2)]);
+		peers.on_blocks_requested(2, &vec![H256::from(3)]);
+		assert_eq!(peers.stalling_peer(&H256::from(1), 60_f64), None);
+
+		use std::thread;
+		use std::time::Duration;
+		thread::sleep(Duration::from_millis(50));
+
+		assert_eq!(peers.stalling_peer(&H256::from(1), 0.01_f64), Some(1));
+		assert_eq!(peers.stalling_peer(&H256::from(3), 0.01_f64), Some(2));
+		assert_eq!(peers.stalling_peer(&H256::from(4), 0.01_f64), None);
+
+		// peer is responding => it is not stalling
+		peers.on_block_received(1, &H256::from(2));
+		assert_eq!(peers.stalling_peer(&H256::from(1), 0.01_f64), None);
+	}
 }
@@ -1,3 +1,4 @@
+use std::cmp::max;
 use std::collections::{VecDeque, HashMap, HashSet};
 use std::collections::hash_map::Entry;
 use std::sync::Arc;
@@ -5,12 +6,17 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use parking_lot::{Mutex, Condvar};
 use chain::IndexedTransaction;
+use db::{BlockFilterProvider, BlockPruning, BASIC_FILTER_TYPE, filter_hash};
 use message::{types, common};
 use primitives::hash::H256;
 use synchronization_executor::{Task, TaskExecutor};
+use synchronization_peers::TransactionAnnouncementType;
 use types::{PeerIndex, RequestId, BlockHeight, StorageRef, ExecutorRef, MemoryPoolRef, PeersRef};
 use utils::KnownHashType;
 
+/// Blocks, which are older than the best block by this interval (in seconds), are historical
+const HISTORICAL_BLOCK_AGE: u32 = 7 * 24 * 60 * 60;
+
 /// Synchronization server task
 #[derive(Debug, PartialEq)]
 pub enum ServerTask {
@@ -26,6 +32,12 @@ pub enum ServerTask {
 	Mempool(PeerIndex),
 	/// Serve 'getblocktxn' request
 	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Serve 'getcfilters' request
+	GetCFilters(PeerIndex, types::GetCFilters),
+	/// Serve 'getcfheaders' request
+	GetCFHeaders(PeerIndex, types::GetCFHeaders),
+	/// Serve 'getcfcheckpt' request
+	GetCFCheckpt(PeerIndex, types::GetCFCheckpt),
 }
 
 /// Synchronization server
@@ -81,7 +93,10 @@ impl ServerTask {
 				| ServerTask::GetBlocks(peer_index, _)
 				| ServerTask::GetHeaders(peer_index, _, _)
 				| ServerTask::Mempool(peer_index)
-				| ServerTask::GetBlockTxn(peer_index, _) => peer_index,
+				| ServerTask::GetBlockTxn(peer_index, _)
+				| ServerTask::GetCFilters(peer_index, _)
+				| ServerTask::GetCFHeaders(peer_index, _)
+				| ServerTask::GetCFCheckpt(peer_index, _) => peer_index,
 		}
 	}
 }
@@ -235,6 +250,9 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			ServerTask::GetHeaders(peer_index, message, request_id) => self.serve_get_headers(peer_index, message, request_id),
 			ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index),
 			ServerTask::GetBlockTxn(peer_index, message) => self.serve_get_block_txn(peer_index, message),
+			ServerTask::GetCFilters(peer_index, message) => self.serve_get_cfilters(peer_index, message),
+			ServerTask::GetCFHeaders(peer_index, message) => self.serve_get_cfheaders(peer_index, message),
+			ServerTask::GetCFCheckpt(peer_index, message) => self.serve_get_cfcheckpt(peer_index, message),
 		}
 
 		None
@@ -263,6 +281,16 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		};
 
 		match next_item.inv_type {
+			common::InventoryType::MessageBlock | common::InventoryType::MessageFilteredBlock |
+				common::InventoryType::MessageCompactBlock | common::InventoryType::MessageWitnessBlock
+				if self.is_upload_target_reached(peer_index) && self.is_historical_block(&next_item.hash) => {
+				// disconnect peer to free up its connection slot for peers, requesting recent blocks
+				trace!(target: "sync", "Disconnecting from peer#{}: upload target reached && historical block {} is requested", peer_index, next_item.hash.to_reversed_str());
+				if let Some(connection) = self.peers.connection(peer_index) {
+					connection.close();
+				}
+				return None;
+			},
 			common::InventoryType::MessageTx => {
 				// only transaction from memory pool can be requested
 				if let Some(transaction) = self.memory_pool.read().read_by_hash(&next_item.hash) {
@@ -338,7 +366,9 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 
 	fn serve_get_blocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
 		if let Some(block_height) = self.locate_best_common_block(&message.hash_stop, &message.block_locator_hashes) {
-			let inventory: Vec<_> = (block_height + 1..block_height + 1 + (types::GETBLOCKS_MAX_RESPONSE_HASHES as BlockHeight))
+			// pruned blocks can't be served => they are not announced
+			let block_height = max(block_height + 1, self.storage.pruned_blocks());
+			let inventory: Vec<_> = (block_height..block_height + (types::GETBLOCKS_MAX_RESPONSE_HASHES as BlockHeight))
 				.map(|block_height| self.storage.block_hash(block_height))
 				.take_while(Option::is_some)
 				.map(Option::unwrap)
@@ -353,7 +383,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 				trace!(target: "sync", "'getblocks' request from peer#{} is ignored as there are no new blocks for peer", peer_index);
 			}
 		} else {
-			self.peers.misbehaving(peer_index, "Got 'getblocks' message without known blocks");
+			self.peers.misbehaving(peer_index, 10, "Got 'getblocks' message without known blocks");
 			return;
 		}
 	}
@@ -373,15 +403,23 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			trace!(target: "sync", "'getheaders' response to peer#{} is ready with {} headers", peer_index, headers.len());
 			self.executor.execute(Task::Headers(peer_index, types::Headers::with_headers(headers), Some(request_id)));
 		} else {
-			self.peers.misbehaving(peer_index, "Got 'headers' message without known blocks");
+			self.peers.misbehaving(peer_index, 10, "Got 'headers' message without known blocks");
 			return;
 		}
 	}
 
 	fn serve_mempool(&self, peer_index: PeerIndex) {
-		let inventory: Vec<_> = self.memory_pool.read()
+		// only transactions, matching connection filter (including bloom filter of the light client) are announced
+		let memory_pool = self.memory_pool.read();
+		let inventory: Vec<_> = memory_pool
 			.get_transactions_ids()
 			.into_iter()
+			.filter(|hash| memory_pool.read_by_hash(hash)
+				.map(|transaction| match self.peers.filter_transaction(peer_index, hash, transaction, None) {
+					TransactionAnnouncementType::SendInventory => true,
+					TransactionAnnouncementType::DoNotAnnounce => false,
+				})
+				.unwrap_or(false))
 			.map(common::InventoryVector::tx)
 			.collect();
 		// empty inventory messages are invalid according to regtests, while empty headers messages are valid
@@ -397,7 +435,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		// according to protocol documentation, we only should only respond
 		// if requested block has been recently sent in 'cmpctblock'
 		if !self.peers.is_hash_known_as(peer_index, &message.request.blockhash, KnownHashType::CompactBlock) {
-			self.peers.misbehaving(peer_index, &format!("Got 'getblocktxn' message for non-sent block: {}", message.request.blockhash.to_reversed_str()));
+			self.peers.misbehaving(peer_index, 10, &format!("Got 'getblocktxn' message for non-sent block: {}", message.request.blockhash.to_reversed_str()));
 			return;
 		}
 
@@ -406,7 +444,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		let requested_len = message.request.indexes.len();
 		if requested_len > block_transactions_len {
 			// peer has requested more transactions, than there are
-			self.peers.misbehaving(peer_index, &format!("Got 'getblocktxn' message with {} transactions, when there are: {}", requested_len, block_transactions_len));
+			self.peers.misbehaving(peer_index, 100, &format!("Got 'getblocktxn' message with {} transactions, when there are: {}", requested_len, block_transactions_len));
 			return;
 		}
 
@@ -415,12 +453,12 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		for transaction_index in message.request.indexes {
 			if transaction_index >= block_transactions_len {
 				// peer has requested index, larger than index of last transaction
-				self.peers.misbehaving(peer_index, &format!("Got 'getblocktxn' message with index {}, larger than index of last transaction {}", transaction_index, block_transactions_len - 1));
+				self.peers.misbehaving(peer_index, 100, &format!("Got 'getblocktxn' message with index {}, larger than index of last transaction {}", transaction_index, block_transactions_len - 1));
 				return;
 			}
 			if !requested_indexes.insert(transaction_index) {
 				// peer has requested same index several times
-				self.peers.misbehaving(peer_index, &format!("Got 'getblocktxn' message where same index {} has been requested several times", transaction_index));
+				self.peers.misbehaving(peer_index, 100, &format!("Got 'getblocktxn' message where same index {} has been requested several times", transaction_index));
 				return;
 			}
 
@@ -445,6 +483,116 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		}));
 	}
 
+	fn serve_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		let stop_height = match self.cfilters_range(peer_index, "getcfilters", message.filter_type, message.start_height, &message.stop_hash, types::GETCFILTERS_MAX_RESPONSE_FILTERS) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let mut filters = Vec::with_capacity((stop_height - message.start_height + 1) as usize);
+		for block_height in message.start_height..stop_height + 1 {
+			match self.storage.block_hash(block_height).and_then(|block_hash| self.storage.block_filter(&block_hash).map(|filter| (block_hash, filter))) {
+				Some((block_hash, filter)) => filters.push(types::CFilter {
+					filter_type: message.filter_type,
+					block_hash: block_hash,
+					filter: filter,
+				}),
+				None => {
+					warn!(target: "sync", "'getcfilters' request from peer#{} is ignored as we have failed to find filter of block {}", peer_index, block_height);
+					return;
+				},
+			}
+		}
+
+		trace!(target: "sync", "'getcfilters' response to peer#{} is ready with {} filters", peer_index, filters.len());
+		for filter in filters {
+			self.executor.execute(Task::CFilter(peer_index, filter));
+		}
+	}
+
+	fn serve_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		let stop_height = match self.cfilters_range(peer_index, "getcfheaders", message.filter_type, message.start_height, &message.stop_hash, types::GETCFHEADERS_MAX_RESPONSE_HASHES) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let previous_filter_header = match message.start_height {
+			0 => Some(H256::default()),
+			start_height => self.storage.block_hash(start_height - 1)
+				.and_then(|block_hash| self.storage.block_filter_header(&block_hash)),
+		};
+		let filter_hashes: Option<Vec<_>> = (message.start_height..stop_height + 1)
+			.map(|block_height| self.storage.block_hash(block_height)
+				.and_then(|block_hash| self.storage.block_filter(&block_hash))
+				.map(|filter| filter_hash(&filter)))
+			.collect();
+
+		match (previous_filter_header, filter_hashes) {
+			(Some(previous_filter_header), Some(filter_hashes)) => {
+				trace!(target: "sync", "'getcfheaders' response to peer#{} is ready with {} hashes", peer_index, filter_hashes.len());
+				self.executor.execute(Task::CFHeaders(peer_index, types::CFHeaders {
+					filter_type: message.filter_type,
+					stop_hash: message.stop_hash,
+					previous_filter_header: previous_filter_header,
+					filter_hashes: filter_hashes,
+				}));
+			},
+			_ => warn!(target: "sync", "'getcfheaders' request from peer#{} is ignored as we have failed to find filters in storage", peer_index),
+		}
+	}
+
+	fn serve_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		let stop_height = match self.cfilters_range(peer_index, "getcfcheckpt", message.filter_type, 0, &message.stop_hash, BlockHeight::max_value()) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let filter_headers: Option<Vec<_>> = (1..stop_height / types::CFCHECKPT_INTERVAL + 1)
+			.map(|checkpoint| self.storage.block_hash(checkpoint * types::CFCHECKPT_INTERVAL)
+				.and_then(|block_hash| self.storage.block_filter_header(&block_hash)))
+			.collect();
+
+		match filter_headers {
+			Some(filter_headers) => {
+				trace!(target: "sync", "'getcfcheckpt' response to peer#{} is ready with {} headers", peer_index, filter_headers.len());
+				self.executor.execute(Task::CFCheckpt(peer_index, types::CFCheckpt {
+					filter_type: message.filter_type,
+					stop_hash: message.stop_hash,
+					filter_headers: filter_headers,
+				}));
+			},
+			None => warn!(target: "sync", "'getcfcheckpt' request from peer#{} is ignored as we have failed to find filter headers in storage", peer_index),
+		}
+	}
+
+	/// Checks compact block filters request and returns height of the stop block.
+	fn cfilters_range(&self, peer_index: PeerIndex, command: &str, filter_type: u8, start_height: BlockHeight, stop_hash: &H256, max_blocks: BlockHeight) -> Option<BlockHeight> {
+		if !self.storage.is_cfindex_enabled() {
+			self.peers.misbehaving(peer_index, 10, &format!("Got '{}' message, while compact block filters are not served", command));
+			return None;
+		}
+
+		if filter_type != BASIC_FILTER_TYPE {
+			self.peers.misbehaving(peer_index, 10, &format!("Got '{}' message with unsupported filter type {}", command, filter_type));
+			return None;
+		}
+
+		let stop_height = match self.storage.block_number(stop_hash) {
+			Some(stop_height) => stop_height,
+			None => {
+				self.peers.misbehaving(peer_index, 10, &format!("Got '{}' message with unknown stop block {}", command, stop_hash.to_reversed_str()));
+				return None;
+			},
+		};
+
+		if start_height > stop_height || stop_height - start_height >= max_blocks {
+			self.peers.misbehaving(peer_index, 10, &format!("Got '{}' message with invalid range {}..{}", command, start_height, stop_height));
+			return None;
+		}
+
+		Some(stop_height)
+	}
+
 	fn locate_best_common_block(&self, hash_stop: &H256, locator: &[H256]) -> Option<BlockHeight> {
 		for block_hash in locator.iter().chain(&[hash_stop.clone()]) {
 			if let Some(block_number) = self.storage.block_number(block_hash) {
@@ -471,6 +619,20 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 
 		None
 	}
+
+	fn is_upload_target_reached(&self, peer_index: PeerIndex) -> bool {
+		self.peers.connection(peer_index)
+			.map(|connection| connection.is_upload_target_reached())
+			.unwrap_or(false)
+	}
+
+	fn is_historical_block(&self, block_hash: &H256) -> bool {
+		let best_block_hash = self.storage.best_block().hash;
+		match (self.storage.block_header(best_block_hash.into()), self.storage.block_header(block_hash.clone().into())) {
+			(Some(best_block_header), Some(block_header)) => best_block_header.time.saturating_sub(block_header.time) > HISTORICAL_BLOCK_AGE,
+			_ => false,
+		}
+	}
 }
 
 #[cfg(test)]
@@ -479,8 +641,9 @@ pub mod tests {
 
 	use std::mem::replace;
 	use std::sync::Arc;
+	use std::sync::atomic::Ordering;
 	use parking_lot::{Mutex, RwLock};
-	use db::{BlockChainDatabase};
+	use db::{BlockChainDatabase, BlockFilterProvider, filter_hash};
 	use message::types;
 	use message::common::{self, Services, InventoryVector, InventoryType};
 	use primitives::hash::H256;
@@ -561,6 +724,41 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::Block(0, test_data::genesis().into())]);
 	}
 
+	#[test]
+	fn server_getdata_does_not_serve_historical_blocks_when_upload_target_reached() {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		let sync_executor = DummyTaskExecutor::new();
+		let executor = ServerTaskExecutor::new(peers.clone(), storage.clone(), memory_pool.clone(), sync_executor.clone());
+
+		// b1 is mined 8 days after genesis => genesis is historical block
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder().header().parent(genesis.hash()).time(genesis.block_header.time + 8 * 24 * 60 * 60).build()
+			.transaction().output().value(10).build().build()
+			.build();
+		storage.insert(b1.clone().into()).expect("no error");
+		storage.canonize(&b1.hash()).unwrap();
+
+		let connection = DummyOutboundSyncConnection::new();
+		connection.upload_target_reached.store(true, Ordering::SeqCst);
+		peers.insert(0, Services::default(), connection);
+
+		// recent block is served
+		let mut loop_task = ServerTask::GetData(0, types::GetData::with_inventory(vec![InventoryVector::block(b1.hash())]));
+		while let Some(new_task) = executor.execute(loop_task) {
+			loop_task = new_task;
+		}
+		assert_eq!(sync_executor.take_tasks(), vec![Task::Block(0, b1.into())]);
+
+		// historical block isn't served
+		let mut loop_task = ServerTask::GetData(0, types::GetData::with_inventory(vec![InventoryVector::block(genesis.hash())]));
+		while let Some(new_task) = executor.execute(loop_task) {
+			loop_task = new_task;
+		}
+		assert_eq!(sync_executor.take_tasks(), vec![]);
+	}
+
 	#[test]
 	fn server_getblocks_do_not_responds_inventory_when_synchronized() {
 		let (_, _, executor, _, server) = create_synchronization_server();
@@ -644,7 +842,8 @@ pub mod tests {
 
 	#[test]
 	fn server_mempool_responds_inventory_when_non_empty_memory_pool() {
-		let (_, memory_pool, executor, _, server) = create_synchronization_server();
+		let (_, memory_pool, executor, peers, server) = create_synchronization_server();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
 		// when memory pool is non-empty
 		let transaction = Transaction::default();
 		let transaction_hash = transaction.hash();
@@ -660,6 +859,30 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::Inventory(0, types::Inv::with_inventory(inventory))]);
 	}
 
+	#[test]
+	fn server_mempool_responds_filtered_inventory_when_bloom_filter_is_set() {
+		let (_, memory_pool, executor, peers, server) = create_synchronization_server();
+		// when memory pool has two transactions
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_output(20).into();
+		let tx2_hash = tx2.hash();
+		memory_pool.write().insert_verified(tx1.into());
+		memory_pool.write().insert_verified(tx2.into());
+		// when light client is only interested in tx2
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.set_bloom_filter(0, default_filterload());
+		peers.update_bloom_filter(0, make_filteradd(&*tx2_hash));
+		// when asking for memory pool transactions ids
+		server.execute(ServerTask::Mempool(0));
+		// => respond with tx2 inventory only
+		let inventory = vec![InventoryVector {
+			inv_type: InventoryType::MessageTx,
+			hash: tx2_hash,
+		}];
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(tasks, vec![Task::Inventory(0, types::Inv::with_inventory(inventory))]);
+	}
+
 	#[test]
 	fn server_get_block_txn_responds_when_good_request() {
 		let (_, _, executor, peers, server) = create_synchronization_server();
@@ -939,4 +1162,67 @@ pub mod tests {
 			_ => panic!("unexpected"),
 		}
 	}
+
+	#[test]
+	fn server_serves_compact_block_filters() {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = BlockChainDatabase::init_test_chain(vec![]).with_cfindex(true);
+		let genesis = test_data::genesis();
+		let genesis_hash = genesis.hash();
+		storage.insert(genesis.into()).unwrap();
+		storage.canonize(&genesis_hash).unwrap();
+		let storage = Arc::new(storage);
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		let sync_executor = DummyTaskExecutor::new();
+		let executor = ServerTaskExecutor::new(peers.clone(), storage.clone(), memory_pool.clone(), sync_executor.clone());
+		let filter = storage.block_filter(&genesis_hash).unwrap();
+
+		executor.execute(ServerTask::GetCFilters(0, types::GetCFilters {
+			filter_type: 0,
+			start_height: 0,
+			stop_hash: genesis_hash.clone(),
+		}));
+		executor.execute(ServerTask::GetCFHeaders(0, types::GetCFHeaders {
+			filter_type: 0,
+			start_height: 0,
+			stop_hash: genesis_hash.clone(),
+		}));
+		executor.execute(ServerTask::GetCFCheckpt(0, types::GetCFCheckpt {
+			filter_type: 0,
+			stop_hash: genesis_hash.clone(),
+		}));
+
+		let tasks = DummyTaskExecutor::wait_tasks(sync_executor);
+		assert_eq!(tasks, vec![
+			Task::CFilter(0, types::CFilter {
+				filter_type: 0,
+				block_hash: genesis_hash.clone(),
+				filter: filter.clone(),
+			}),
+			Task::CFHeaders(0, types::CFHeaders {
+				filter_type: 0,
+				stop_hash: genesis_hash.clone(),
+				previous_filter_header: H256::default(),
+				filter_hashes: vec![filter_hash(&filter)],
+			}),
+			Task::CFCheckpt(0, types::CFCheckpt {
+				filter_type: 0,
+				stop_hash: genesis_hash,
+				filter_headers: vec![],
+			}),
+		]);
+	}
+
+	#[test]
+	fn server_does_not_serve_compact_block_filters_when_disabled() {
+		let (_, _, executor, peers, server) = create_synchronization_server();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		server.execute(ServerTask::GetCFilters(0, types::GetCFilters {
+			filter_type: 0,
+			start_height: 0,
+			stop_hash: test_data::genesis().hash(),
+		}));
+		let tasks = DummyTaskExecutor::wait_tasks_for(executor, 100);
+		assert_eq!(tasks, vec![]);
+	}
 }
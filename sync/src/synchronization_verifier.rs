@@ -4,7 +4,6 @@ use std::sync::mpsc::{channel, Sender, Receiver};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use parking_lot::Mutex;
-use time::get_time;
 use chain::{IndexedBlock, IndexedTransaction};
 use network::ConsensusParams;
 use primitives::hash::H256;
@@ -37,8 +36,8 @@ pub trait VerificationSink : BlockVerificationSink + TransactionVerificationSink
 /// Verification thread tasks
 #[derive(Debug)]
 pub enum VerificationTask {
-	/// Verify single block
-	VerifyBlock(IndexedBlock),
+	/// Verify single block. Scripts of the assumed-valid block are not verified
+	VerifyBlock(IndexedBlock, bool),
 	/// Verify single transaction
 	VerifyTransaction(BlockHeight, IndexedTransaction),
 	/// Stop verification thread
@@ -47,8 +46,8 @@ pub enum VerificationTask {
 
 /// Synchronization verifier
 pub trait Verifier : Send + Sync + 'static {
-	/// Verify block
-	fn verify_block(&self, block: IndexedBlock);
+	/// Verify block. Scripts of the assumed-valid block are not verified
+	fn verify_block(&self, block: IndexedBlock, assume_valid: bool);
 	/// Verify transaction
 	fn verify_transaction(&self, height: BlockHeight, transaction: IndexedTransaction);
 }
@@ -83,7 +82,7 @@ impl ChainVerifierWrapper {
 	}
 
 	/// Verify block.
-	pub fn verify_block(&self, block: &IndexedBlock) -> Result<(), VerificationError> {
+	pub fn verify_block(&self, block: &IndexedBlock, assume_valid: bool) -> Result<(), VerificationError> {
 		let enforce_full_verification = if block.hash() == &self.verification_params.verification_edge {
 			self.enforce_full_verification.store(true, Ordering::Relaxed);
 			true
@@ -95,6 +94,11 @@ impl ChainVerifierWrapper {
 		} else {
 			self.verification_params.verification_level
 		};
+		// all other rules are still checked for the assumed-valid block
+		let verification_level = match verification_level {
+			VerificationLevel::Full if assume_valid => VerificationLevel::Header,
+			verification_level => verification_level,
+		};
 
 		self.verifier.verify(verification_level, block)
 	}
@@ -148,9 +152,9 @@ impl AsyncVerifier {
 
 		while let Some(task) = tasks_queue.pop_front() {
 			match task {
-				VerificationTask::VerifyBlock(block) => {
+				VerificationTask::VerifyBlock(block, assume_valid) => {
 					// verify block
-					match verifier.verify_block(&block) {
+					match verifier.verify_block(&block, assume_valid) {
 						Ok(_) => {
 							if let Some(tasks) = sink.on_block_verification_success(block) {
 								tasks_queue.extend(tasks);
@@ -169,8 +173,8 @@ impl AsyncVerifier {
 							continue; // with new verification sub-task
 						},
 						Ok(tx_output_provider) => {
-							let time: u32 = get_time().sec as u32;
-							match verifier.verifier.verify_mempool_transaction(storage.as_block_header_provider(), &tx_output_provider, height, time, &transaction.raw) {
+							let time = verifier.verifier.current_time();
+							match verifier.verifier.verify_mempool_transaction(storage.as_block_header_provider(), &tx_output_provider, height, time, &transaction) {
 								Ok(_) => sink.on_transaction_verification_success(transaction.into()),
 								Err(e) => sink.on_transaction_verification_error(&format!("{:?}", e), &transaction.hash),
 							}
@@ -201,9 +205,9 @@ impl Drop for AsyncVerifier {
 
 impl Verifier for AsyncVerifier {
 	/// Verify block
-	fn verify_block(&self, block: IndexedBlock) {
+	fn verify_block(&self, block: IndexedBlock, assume_valid: bool) {
 		self.verification_work_sender.lock()
-			.send(VerificationTask::VerifyBlock(block))
+			.send(VerificationTask::VerifyBlock(block, assume_valid))
 			.expect("Verification thread have the same lifetime as `AsyncVerifier`");
 	}
 
@@ -237,8 +241,8 @@ impl<T> SyncVerifier<T> where T: VerificationSink {
 
 impl<T> Verifier for SyncVerifier<T> where T: VerificationSink {
 	/// Verify block
-	fn verify_block(&self, block: IndexedBlock) {
-		match self.verifier.verify_block(&block) {
+	fn verify_block(&self, block: IndexedBlock, assume_valid: bool) {
+		match self.verifier.verify_block(&block, assume_valid) {
 			Ok(_) => {
 				// SyncVerifier is used for bulk blocks import only
 				// => there are no memory pool
@@ -314,13 +318,13 @@ pub mod tests {
 	}
 
 	impl Verifier for DummyVerifier {
-		fn verify_block(&self, block: IndexedBlock) {
+		fn verify_block(&self, block: IndexedBlock, assume_valid: bool) {
 			match self.sink {
 				Some(ref sink) => match self.errors.get(&block.hash()) {
 					Some(err) => sink.on_block_verification_error(&err, &block.hash()),
 					None => {
 						if self.actual_checks.contains(block.hash()) {
-							AsyncVerifier::execute_single_task(sink, self.storage.as_ref().unwrap(), self.memory_pool.as_ref().unwrap(), self.verifier.as_ref().unwrap(), VerificationTask::VerifyBlock(block));
+							AsyncVerifier::execute_single_task(sink, self.storage.as_ref().unwrap(), self.memory_pool.as_ref().unwrap(), self.verifier.as_ref().unwrap(), VerificationTask::VerifyBlock(block, assume_valid));
 						} else {
 							sink.on_block_verification_success(block);
 						}
@@ -366,7 +370,7 @@ pub mod tests {
 		});
 		assert_eq!(wrapper.enforce_full_verification.load(Ordering::Relaxed), false);
 		let block: IndexedBlock = test_data::block_h1().into();
-		let _ = wrapper.verify_block(&block);
+		let _ = wrapper.verify_block(&block, false);
 		assert_eq!(wrapper.enforce_full_verification.load(Ordering::Relaxed), true);
 	}
 
@@ -411,14 +415,17 @@ pub mod tests {
 			verification_level: VerificationLevel::Header,
 			verification_edge: 1.into(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_transaction_block), Ok(()));
+		assert_eq!(wrapper.verify_block(&bad_transaction_block, false), Ok(()));
 
 		// Error when tx script is checked
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::Full,
 			verification_edge: 1.into(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_transaction_block), Err(VerificationError::Transaction(1, TransactionError::Signature(0, ScriptError::InvalidStackOperation))));
+		assert_eq!(wrapper.verify_block(&bad_transaction_block, false), Err(VerificationError::Transaction(1, TransactionError::Signature(0, ScriptError::InvalidStackOperation))));
+
+		// Ok(()) when block is assumed to be valid
+		assert_eq!(wrapper.verify_block(&bad_transaction_block, true), Ok(()));
 	}
 
 	#[test]
@@ -432,13 +439,16 @@ pub mod tests {
 			verification_level: VerificationLevel::NoVerification,
 			verification_edge: 1.into(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_block), Ok(()));
+		assert_eq!(wrapper.verify_block(&bad_block, false), Ok(()));
 
 		// Error when everything is verified
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::Full,
 			verification_edge: 1.into(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_block), Err(VerificationError::Empty));
+		assert_eq!(wrapper.verify_block(&bad_block, false), Err(VerificationError::Empty));
+
+		// Error when block is assumed to be valid
+		assert_eq!(wrapper.verify_block(&bad_block, true), Err(VerificationError::Empty));
 	}
 }
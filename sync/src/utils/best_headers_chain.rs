@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use chain::IndexedBlockHeader;
 use primitives::hash::H256;
+use primitives::bigint::U256;
 use super::{HashQueue, HashPosition};
 
 /// Best headers chain information
@@ -22,6 +23,8 @@ pub struct BestHeadersChain {
 	headers: HashMap<H256, IndexedBlockHeader>,
 	/// Best chain
 	best: HashQueue,
+	/// Total work of the best chain headers
+	best_work: U256,
 }
 
 impl BestHeadersChain {
@@ -31,6 +34,7 @@ impl BestHeadersChain {
 			storage_best_hash: storage_best_hash,
 			headers: HashMap::new(),
 			best: HashQueue::new(),
+			best_work: U256::zero(),
 		}
 	}
 
@@ -66,6 +70,11 @@ impl BestHeadersChain {
 			.unwrap_or_default()
 	}
 
+	/// Get total work of the best chain headers (work of the storage blocks is not included)
+	pub fn best_work(&self) -> U256 {
+		self.best_work
+	}
+
 	/// Get hash of best block
 	pub fn best_block_hash(&self) -> H256 {
 		self.best.back()
@@ -78,6 +87,7 @@ impl BestHeadersChain {
 		// append to the best chain
 		if self.best_block_hash() == header.raw.previous_header_hash {
 			let header_hash = header.hash.clone();
+			self.best_work = self.best_work + header.raw.bits.block_work();
 			self.headers.insert(header_hash.clone(), header);
 			self.best.push_back(header_hash);
 			return;
@@ -93,7 +103,8 @@ impl BestHeadersChain {
 
 	/// Remove block header with given hash and all its children
 	pub fn remove(&mut self, hash: &H256) {
-		if self.headers.remove(hash).is_some() {
+		if let Some(header) = self.headers.remove(hash) {
+			self.best_work = self.best_work - header.raw.bits.block_work();
 			match self.best.remove(hash) {
 				HashPosition::Front => self.clear(),
 				HashPosition::Inside(position) => self.clear_after(position),
@@ -113,7 +124,9 @@ impl BestHeadersChain {
 	pub fn block_inserted_to_storage(&mut self, hash: &H256, storage_best_hash: &H256) {
 		if self.best.front().map(|h| &h == hash).unwrap_or(false) {
 			self.best.pop_front();
-			self.headers.remove(hash);
+			if let Some(header) = self.headers.remove(hash) {
+				self.best_work = self.best_work - header.raw.bits.block_work();
+			}
 		}
 		self.storage_best_hash = storage_best_hash.clone();
 	}
@@ -122,6 +135,7 @@ impl BestHeadersChain {
 	pub fn clear(&mut self) {
 		self.headers.clear();
 		self.best.clear();
+		self.best_work = U256::zero();
 	}
 
 	/// Remove headers after position
@@ -130,7 +144,10 @@ impl BestHeadersChain {
 			self.clear()
 		} else {
 			while self.best.len() > position {
-				self.headers.remove(&self.best.pop_back().expect("len() > position; qed"));
+				let hash = self.best.pop_back().expect("len() > position; qed");
+				if let Some(header) = self.headers.remove(&hash) {
+					self.best_work = self.best_work - header.raw.bits.block_work();
+				}
 			}
 		}
 	}
@@ -141,6 +158,7 @@ mod tests {
 	extern crate test_data;
 
 	use primitives::hash::H256;
+	use primitives::bigint::U256;
 	use super::BestHeadersChain;
 
 	#[test]
@@ -228,4 +246,26 @@ mod tests {
 		assert_eq!(chain.information().best, 1);
 		assert_eq!(chain.information().total, 1);
 	}
+
+	#[test]
+	fn best_chain_work() {
+		let mut chain = BestHeadersChain::new(test_data::genesis().hash());
+		let b1 = test_data::block_h1().block_header;
+		let b2 = test_data::block_h2().block_header;
+		let b3 = test_data::block_h3().block_header;
+		let work = b1.bits.block_work();
+		assert_eq!(chain.best_work(), U256::zero());
+
+		chain.insert_n(vec![b1.clone().into(), b2.clone().into(), b3.clone().into()]);
+		assert_eq!(chain.best_work(), work * U256::from(3));
+
+		chain.remove(&b3.hash());
+		assert_eq!(chain.best_work(), work * U256::from(2));
+
+		chain.block_inserted_to_storage(&b1.hash(), &b1.hash());
+		assert_eq!(chain.best_work(), work);
+
+		chain.remove(&b2.hash());
+		assert_eq!(chain.best_work(), U256::zero());
+	}
 }
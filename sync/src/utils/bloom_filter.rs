@@ -1,8 +1,9 @@
 use parking_lot::Mutex;
 use bit_vec::BitVec;
 use murmur3::murmur3_32;
-use chain::{IndexedTransaction, OutPoint};
+use chain::{Transaction, OutPoint};
 use message::types;
+use primitives::hash::H256;
 use ser::serialize;
 use script::Script;
 
@@ -75,7 +76,7 @@ impl BloomFilter {
 	}
 
 	/// Filters transaction using bloom filter data
-	pub fn filter_transaction(&self, tx: &IndexedTransaction) -> bool {
+	pub fn filter_transaction(&self, hash: &H256, transaction: &Transaction) -> bool {
 		// check with bloom filter, if set
 		match self.bloom {
 			/// if no filter is set for the connection => match everything
@@ -86,7 +87,7 @@ impl BloomFilter {
 				let mut is_match = false;
 
 				// match if filter contains any arbitrary script data element in any scriptPubKey in tx
-				for (output_index, output) in tx.raw.outputs.iter().enumerate() {
+				for (output_index, output) in transaction.outputs.iter().enumerate() {
 					let script = Script::new(output.script_pubkey.clone());
 					let is_update_needed = self.filter_flags == types::FilterFlags::All
 						|| (self.filter_flags == types::FilterFlags::PubKeyOnly && (script.is_pay_to_public_key() || script.is_multisig_script()));
@@ -97,7 +98,7 @@ impl BloomFilter {
 
 								if is_update_needed {
 									bloom.insert(&serialize(&OutPoint {
-										hash: tx.hash.clone(),
+										hash: hash.clone(),
 										index: output_index as u32,
 									}));
 								}
@@ -112,12 +113,12 @@ impl BloomFilter {
 				}
 
 				// match if filter contains transaction itself
-				if bloom.contains(&*tx.hash) {
+				if bloom.contains(&**hash) {
 					return true;
 				}
 
 				// match if filter contains an outpoint this transaction spends
-				for input in &tx.raw.inputs {
+				for input in &transaction.inputs {
 					// check if match previous output
 					let previous_output = serialize(&input.previous_output);
 					is_match = bloom.contains(&*previous_output);
@@ -222,13 +223,13 @@ mod tests {
 
 		let mut filter = BloomFilter::with_filter_load(default_filterload());
 
-		assert!(!filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(!filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 
 		filter.update_bloom_filter(make_filteradd(&*tx1.hash));
 
-		assert!(filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 	}
 
 	#[test]
@@ -241,13 +242,13 @@ mod tests {
 
 		let mut filter = BloomFilter::with_filter_load(default_filterload());
 
-		assert!(!filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(!filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 
 		filter.update_bloom_filter(make_filteradd(&tx1_out_data));
 
-		assert!(filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 	}
 
 	#[test]
@@ -259,13 +260,13 @@ mod tests {
 
 		let mut filter = BloomFilter::with_filter_load(default_filterload());
 
-		assert!(!filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(!filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 
 		filter.update_bloom_filter(make_filteradd(&tx1_previous_output));
 
-		assert!(filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 	}
 
 	#[test]
@@ -278,13 +279,13 @@ mod tests {
 
 		let mut filter = BloomFilter::with_filter_load(default_filterload());
 
-		assert!(!filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(!filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 
 		filter.update_bloom_filter(make_filteradd(&tx1_input_data));
 
-		assert!(filter.filter_transaction(&tx1));
-		assert!(!filter.filter_transaction(&tx2));
+		assert!(filter.filter_transaction(&tx1.hash, &tx1.raw));
+		assert!(!filter.filter_transaction(&tx2.hash, &tx2.raw));
 	}
 
 	#[test]
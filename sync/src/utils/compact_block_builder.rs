@@ -26,7 +26,7 @@ pub fn build_compact_block(block: &IndexedBlock, prefilled_transactions_indexes:
 			prefilled_transactions_size += transaction_size;
 			prefilled_transactions.push(PrefilledTransaction {
 				index: transaction_index,
-				transaction: transaction.raw.clone(),
+				transaction: (*transaction.raw).clone(),
 			})
 		} else {
 			short_ids.push(short_transaction_id(key0, key1, &transaction.hash));
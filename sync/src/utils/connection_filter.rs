@@ -1,10 +1,10 @@
 use bit_vec::BitVec;
-use chain::{IndexedBlock, IndexedTransaction};
+use chain::{IndexedBlock, Transaction, PartialMerkleTree};
 use message::types;
 use primitives::bytes::Bytes;
 use primitives::hash::H256;
 use synchronization_peers::MerkleBlockArtefacts;
-use utils::{KnownHashFilter, KnownHashType, BloomFilter, FeeRateFilter, build_compact_block, build_partial_merkle_tree};
+use utils::{KnownHashFilter, KnownHashType, BloomFilter, FeeRateFilter, build_compact_block};
 
 /// Filter, which controls data relayed over connection.
 #[derive(Debug, Default)]
@@ -34,10 +34,10 @@ impl ConnectionFilter {
 	}
 
 	/// Check if transaction should be sent to this connection && optionally update filter
-	pub fn filter_transaction(&self, transaction: &IndexedTransaction, transaction_fee_rate: Option<u64>) -> bool {
-		self.known_hash_filter.filter_transaction(&transaction.hash)
+	pub fn filter_transaction(&self, hash: &H256, transaction: &Transaction, transaction_fee_rate: Option<u64>) -> bool {
+		self.known_hash_filter.filter_transaction(hash)
 			&& self.fee_rate_filter.filter_transaction(transaction_fee_rate)
-			&& self.bloom_filter.filter_transaction(transaction)
+			&& self.bloom_filter.filter_transaction(hash, transaction)
 	}
 
 	/// Load filter
@@ -60,10 +60,16 @@ impl ConnectionFilter {
 		self.fee_rate_filter.set_min_fee_rate(message);
 	}
 
+	/// Minimal fee rate of transactions, announced over connection
+	pub fn min_fee_rate(&self) -> u64 {
+		self.fee_rate_filter.min_fee_rate()
+	}
+
 	/// Convert block to compact block using this filter
 	pub fn build_compact_block(&self, block: &IndexedBlock) -> types::CompactBlock {
+		// coinbase transaction and transactions, which peer isn't aware of, are prefilled
 		let unknown_transaction_indexes = block.transactions.iter().enumerate()
-			.filter(|&(_, tx)| self.known_hash_filter.contains(&tx.hash, KnownHashType::Transaction))
+			.filter(|&(idx, tx)| idx == 0 || !self.known_hash_filter.contains(&tx.hash, KnownHashType::Transaction))
 			.map(|(idx, _)| idx)
 			.collect();
 		types::CompactBlock {
@@ -93,7 +99,7 @@ impl ConnectionFilter {
 		// calculate hashes && match flags for all transactions
 		let (all_hashes, all_flags) = block.transactions.iter()
 			.fold((Vec::<H256>::with_capacity(all_len), BitVec::with_capacity(all_len)), |(mut all_hashes, mut all_flags), t| {
-				let flag = self.bloom_filter.filter_transaction(t);
+				let flag = self.bloom_filter.filter_transaction(&t.hash, &t.raw);
 				all_flags.push(flag);
 				all_hashes.push(t.hash.clone());
 				if flag {
@@ -103,20 +109,9 @@ impl ConnectionFilter {
 			});
 
 		// build partial merkle tree
-		let partial_merkle_tree = build_partial_merkle_tree(all_hashes, all_flags);
+		let partial_merkle_tree = PartialMerkleTree::build(all_hashes, all_flags);
+		result.merkleblock.flags = partial_merkle_tree.flags_bytes().into();
 		result.merkleblock.hashes.extend(partial_merkle_tree.hashes);
-		// to_bytes() converts [true, false, true] to 0b10100000
-		// while protocol requires [true, false, true] to be serialized as 0x00000101
-		result.merkleblock.flags = partial_merkle_tree.flags.to_bytes().into_iter()
-			.map(|b|
-				((b & 0b10000000) >> 7) |
-				((b & 0b01000000) >> 5) |
-				((b & 0b00100000) >> 3) |
-				((b & 0b00010000) >> 1) |
-				((b & 0b00001000) << 1) |
-				((b & 0b00000100) << 3) |
-				((b & 0b00000010) << 5) |
-				((b & 0b00000001) << 7)).collect::<Vec<u8>>().into();
 		Some(result)
 	}
 }
@@ -139,7 +134,8 @@ pub mod tests {
 
 	#[test]
 	fn filter_default_accepts_transaction() {
-		assert!(ConnectionFilter::default().filter_transaction(&test_data::genesis().transactions[0].clone().into(), Some(0)));
+		let tx: IndexedTransaction = test_data::genesis().transactions[0].clone().into();
+		assert!(ConnectionFilter::default().filter_transaction(&tx.hash, &tx.raw, Some(0)));
 	}
 
 	#[test]
@@ -155,18 +151,21 @@ pub mod tests {
 	#[test]
 	fn filter_rejects_transaction_known() {
 		let mut filter = ConnectionFilter::default();
-		filter.hash_known_as(test_data::block_h1().transactions[0].hash(), KnownHashType::Transaction);
-		assert!(!filter.filter_transaction(&test_data::block_h1().transactions[0].clone().into(), None));
-		assert!(filter.filter_transaction(&test_data::block_h2().transactions[0].clone().into(), None));
+		let tx1: IndexedTransaction = test_data::block_h1().transactions[0].clone().into();
+		let tx2: IndexedTransaction = test_data::block_h2().transactions[0].clone().into();
+		filter.hash_known_as(tx1.hash.clone(), KnownHashType::Transaction);
+		assert!(!filter.filter_transaction(&tx1.hash, &tx1.raw, None));
+		assert!(filter.filter_transaction(&tx2.hash, &tx2.raw, None));
 	}
 
 	#[test]
 	fn filter_rejects_transaction_feerate() {
 		let mut filter = ConnectionFilter::default();
+		let tx: IndexedTransaction = test_data::block_h1().transactions[0].clone().into();
 		filter.set_fee_rate(types::FeeFilter::with_fee_rate(1000));
-		assert!(filter.filter_transaction(&test_data::block_h1().transactions[0].clone().into(), None));
-		assert!(filter.filter_transaction(&test_data::block_h1().transactions[0].clone().into(), Some(1500)));
-		assert!(!filter.filter_transaction(&test_data::block_h1().transactions[0].clone().into(), Some(500)));
+		assert!(filter.filter_transaction(&tx.hash, &tx.raw, None));
+		assert!(filter.filter_transaction(&tx.hash, &tx.raw, Some(1500)));
+		assert!(!filter.filter_transaction(&tx.hash, &tx.raw, Some(500)));
 	}
 
 	#[test]
@@ -179,12 +178,12 @@ pub mod tests {
 			tweak: 5,
 			flags: types::FilterFlags::None,
 		});
-		assert!(!filter.filter_transaction(&tx, None));
+		assert!(!filter.filter_transaction(&tx.hash, &tx.raw, None));
 		filter.add(types::FilterAdd {
 			data: (&*tx.hash as &[u8]).into(),
 		});
-		assert!(filter.filter_transaction(&tx, None));
+		assert!(filter.filter_transaction(&tx.hash, &tx.raw, None));
 		filter.clear();
-		assert!(filter.filter_transaction(&tx, None));
+		assert!(filter.filter_transaction(&tx.hash, &tx.raw, None));
 	}
 }
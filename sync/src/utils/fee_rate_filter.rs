@@ -13,6 +13,11 @@ impl FeeRateFilter {
 		self.fee_rate = message.fee_rate;
 	}
 
+	/// Minimal fee rate, this filter accepts
+	pub fn min_fee_rate(&self) -> u64 {
+		self.fee_rate
+	}
+
 	/// Filter transaction using its fee rate
 	pub fn filter_transaction(&self, tx_fee_rate: Option<u64>) -> bool {
 		tx_fee_rate
@@ -1,12 +1,12 @@
 use std::collections::HashMap;
-use chain::{Transaction, TransactionOutput, OutPoint};
+use chain::{Transaction, IndexedTransaction, TransactionOutput, OutPoint};
 use db::TransactionOutputProvider;
 use miner::{DoubleSpendCheckResult, HashedOutPoint, NonFinalDoubleSpendSet};
 use verification::TransactionError;
 use super::super::types::{MemoryPoolRef, StorageRef};
 
 /// Transaction output observer, which looks into both storage && into memory pool.
-/// It also allows to replace non-final transactions in the memory pool.
+/// It also allows to replace non-final and replaceable (BIP125) transactions in the memory pool.
 pub struct MemoryPoolTransactionOutputProvider {
 	/// Storage provider
 	storage_provider: StorageRef,
@@ -20,8 +20,25 @@ pub struct MemoryPoolTransactionOutputProvider {
 impl MemoryPoolTransactionOutputProvider {
 	/// Create new provider for verifying given transaction
 	pub fn for_transaction(storage: StorageRef, memory_pool: &MemoryPoolRef, transaction: &Transaction) -> Result<Self, TransactionError> {
+		MemoryPoolTransactionOutputProvider::new(storage, memory_pool, transaction, true)
+	}
+
+	/// Create new provider for verifying given transaction of the package. Memory pool minimum fee
+	/// isn't checked, because it is paid by the whole package.
+	pub fn for_package_transaction(storage: StorageRef, memory_pool: &MemoryPoolRef, transaction: &Transaction) -> Result<Self, TransactionError> {
+		MemoryPoolTransactionOutputProvider::new(storage, memory_pool, transaction, false)
+	}
+
+	fn new(storage: StorageRef, memory_pool: &MemoryPoolRef, transaction: &Transaction, check_minimum_fee: bool) -> Result<Self, TransactionError> {
 		// we have to check if there are another in-mempool transactions which spent same outputs here
 		let memory_pool = memory_pool.read();
+		// transaction must pay the memory pool minimum fee, which is raised when memory pool is full
+		if check_minimum_fee {
+			memory_pool.check_minimum_fee(transaction, |prevout| memory_pool
+				.transaction_output(prevout, usize::max_value())
+				.or_else(|| storage.transaction_output(prevout, usize::max_value()))
+				.map(|output| output.value))?;
+		}
 		let check_result = memory_pool.check_double_spend(transaction);
 		match check_result {
 			// input of transaction is already spent by another final transaction from memory pool
@@ -36,16 +53,23 @@ impl MemoryPoolTransactionOutputProvider {
 					)).collect(),
 				nonfinal_spends: None,
 			}),
-			// there are non-final transactions, which are spending same inputs in memory pool
-			DoubleSpendCheckResult::NonFinalDoubleSpend(nonfinal_spends) => Ok(MemoryPoolTransactionOutputProvider {
-				storage_provider: storage,
-				mempool_inputs: transaction.inputs.iter()
-					.map(|input| (
-						input.previous_output.clone().into(),
-						memory_pool.transaction_output(&input.previous_output, usize::max_value()),
-					)).collect(),
-				nonfinal_spends: Some(nonfinal_spends),
-			}),
+			// there are non-final or replaceable transactions, which are spending same inputs in memory pool
+			DoubleSpendCheckResult::NonFinalDoubleSpend(nonfinal_spends) => {
+				memory_pool.check_replacement(transaction, &nonfinal_spends, |prevout| memory_pool
+					.transaction_output(prevout, usize::max_value())
+					.or_else(|| storage.transaction_output(prevout, usize::max_value()))
+					.map(|output| output.value))?;
+
+				Ok(MemoryPoolTransactionOutputProvider {
+					storage_provider: storage,
+					mempool_inputs: transaction.inputs.iter()
+						.map(|input| (
+							input.previous_output.clone().into(),
+							memory_pool.transaction_output(&input.previous_output, usize::max_value()),
+						)).collect(),
+					nonfinal_spends: Some(nonfinal_spends),
+				})
+			},
 		}
 	}
 }
@@ -89,16 +113,51 @@ impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
 	}
 }
 
+/// Transaction output observer for transactions of the package, which are verified one by one.
+/// Outputs of the preceding package transactions are available to the following transactions.
+pub struct PackageTransactionOutputProvider<'a> {
+	/// Already verified transactions of the package
+	package: &'a [IndexedTransaction],
+	/// Memory pool provider of the transaction
+	memory_pool_provider: &'a MemoryPoolTransactionOutputProvider,
+}
+
+impl<'a> PackageTransactionOutputProvider<'a> {
+	pub fn new(package: &'a [IndexedTransaction], memory_pool_provider: &'a MemoryPoolTransactionOutputProvider) -> Self {
+		PackageTransactionOutputProvider {
+			package: package,
+			memory_pool_provider: memory_pool_provider,
+		}
+	}
+}
+
+impl<'a> TransactionOutputProvider for PackageTransactionOutputProvider<'a> {
+	fn transaction_output(&self, prevout: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
+		self.package.iter()
+			.find(|transaction| transaction.hash == prevout.hash)
+			.and_then(|transaction| transaction.raw.outputs.get(prevout.index as usize).cloned())
+			.or_else(|| self.memory_pool_provider.transaction_output(prevout, transaction_index))
+	}
+
+	fn is_spent(&self, prevout: &OutPoint) -> bool {
+		// package transactions must not conflict with each other
+		self.package.iter()
+			.any(|transaction| transaction.raw.inputs.iter().any(|input| input.previous_output == *prevout))
+			|| self.memory_pool_provider.is_spent(prevout)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	extern crate test_data;
 
 	use std::sync::Arc;
 	use parking_lot::RwLock;
-	use chain::OutPoint;
+	use chain::{OutPoint, IndexedTransaction};
 	use db::{TransactionOutputProvider, BlockChainDatabase};
 	use miner::MemoryPool;
-	use super::MemoryPoolTransactionOutputProvider;
+	use verification::TransactionError;
+	use super::{MemoryPoolTransactionOutputProvider, PackageTransactionOutputProvider};
 
 	#[test]
 	fn when_transaction_depends_on_removed_nonfinal_transaction() {
@@ -134,4 +193,49 @@ mod tests {
 		assert_eq!(provider.transaction_output(&OutPoint { hash: dchain.at(1).hash(), index: 0, }, 0), None);
 		assert_eq!(provider.transaction_output(&OutPoint { hash: dchain.at(2).hash(), index: 0, }, 0), None);
 	}
+
+	#[test]
+	fn when_transaction_replaces_replaceable_transaction() {
+		let dchain = &mut test_data::ChainBuilder::new();
+
+		test_data::TransactionBuilder::with_output(10_000).store(dchain)							// t0
+			.reset().set_input(&dchain.at(0), 0).add_output(9_000).signal_replacement().store(dchain)	// replaceable: t0[0] -> t1
+			.reset().set_input(&dchain.at(0), 0).add_output(9_000).store(dchain)						// same fee: t0[0] -> t2
+			.reset().set_input(&dchain.at(0), 0).add_output(8_000).store(dchain);						// good replacement: t0[0] -> t3
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		{
+			memory_pool.write().insert_verified(dchain.at(0).into());
+			memory_pool.write().insert_verified(dchain.at(1).into());
+		}
+
+		match MemoryPoolTransactionOutputProvider::for_transaction(storage.clone(), &memory_pool, &dchain.at(2)) {
+			Err(err) => assert_eq!(err, TransactionError::InsufficientReplacementFeeRate(dchain.at(1).hash())),
+			Ok(_) => panic!("unexpected"),
+		}
+		assert!(MemoryPoolTransactionOutputProvider::for_transaction(storage, &memory_pool, &dchain.at(3)).is_ok());
+	}
+
+	#[test]
+	fn when_transaction_spends_package_transaction() {
+		let dchain = &mut test_data::ChainBuilder::new();
+
+		test_data::TransactionBuilder::with_output(10).store(dchain)					// t0
+			.reset().set_input(&dchain.at(0), 0).add_output(20).store(dchain)			// package parent: t0[0] -> t1
+			.reset().set_input(&dchain.at(1), 0).add_output(30).store(dchain);			// package child: t1[0] -> t2
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
+		memory_pool.write().insert_verified(dchain.at(0).into());
+
+		let package: Vec<IndexedTransaction> = vec![dchain.at(1).into()];
+		let memory_pool_provider = MemoryPoolTransactionOutputProvider::for_transaction(storage, &memory_pool, &dchain.at(2)).unwrap();
+		let provider = PackageTransactionOutputProvider::new(&package, &memory_pool_provider);
+		assert_eq!(provider.transaction_output(&OutPoint { hash: dchain.at(0).hash(), index: 0, }, 0), Some(dchain.at(0).outputs[0].clone()));
+		assert_eq!(provider.transaction_output(&OutPoint { hash: dchain.at(1).hash(), index: 0, }, 0), Some(dchain.at(1).outputs[0].clone()));
+		assert_eq!(provider.transaction_output(&OutPoint { hash: dchain.at(1).hash(), index: 1, }, 0), None);
+		assert_eq!(provider.is_spent(&OutPoint { hash: dchain.at(0).hash(), index: 0, }), true);
+		assert_eq!(provider.is_spent(&OutPoint { hash: dchain.at(1).hash(), index: 0, }), false);
+	}
 }
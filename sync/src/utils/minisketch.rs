@@ -0,0 +1,348 @@
+use byteorder::{LittleEndian, ByteOrder};
+
+/// Irreducible polynomial x^32 + x^7 + x^3 + x^2 + 1 (without the leading term), defining GF(2^32)
+const FIELD_MODULUS: u64 = 0x8d;
+/// Size of the single serialized element
+const ELEMENT_SIZE: usize = 4;
+
+/// PinSketch of the set of non-zero 32-bit elements (the same construction, which is used by minisketch library).
+/// Sketch of the capacity `c` consists of odd power sums s1, s3, ..., s(2c-1) of set elements over GF(2^32).
+/// Sum of two sketches is the sketch of the symmetric difference of their sets, which could be decoded
+/// if it has at most `c` elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minisketch {
+	/// Odd power sums of the set elements
+	syndromes: Vec<u32>,
+}
+
+impl Minisketch {
+	/// Create empty sketch of given capacity
+	pub fn new(capacity: usize) -> Self {
+		Minisketch {
+			syndromes: vec![0; capacity],
+		}
+	}
+
+	/// Deserialize sketch. Capacity is derived from the data length
+	pub fn from_bytes(data: &[u8]) -> Option<Self> {
+		if data.len() % ELEMENT_SIZE != 0 {
+			return None;
+		}
+
+		Some(Minisketch {
+			syndromes: data.chunks(ELEMENT_SIZE).map(LittleEndian::read_u32).collect(),
+		})
+	}
+
+	/// Serialize sketch
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut data = vec![0; self.syndromes.len() * ELEMENT_SIZE];
+		for (chunk, syndrome) in data.chunks_mut(ELEMENT_SIZE).zip(self.syndromes.iter()) {
+			LittleEndian::write_u32(chunk, *syndrome);
+		}
+		data
+	}
+
+	/// Maximal number of elements, which could be decoded from the sketch
+	pub fn capacity(&self) -> usize {
+		self.syndromes.len()
+	}
+
+	/// Add element to the sketch. Adding the same element twice removes it from the sketch
+	pub fn add(&mut self, element: u32) {
+		assert!(element != 0, "zero element can not be added to the sketch");
+
+		let squared = mul(element, element);
+		let mut power = element;
+		for syndrome in &mut self.syndromes {
+			*syndrome ^= power;
+			power = mul(power, squared);
+		}
+	}
+
+	/// Merge with other sketch of the same capacity. Result is the sketch of the symmetric difference
+	pub fn merge(&mut self, other: &Minisketch) {
+		assert_eq!(self.capacity(), other.capacity());
+
+		for (syndrome, other_syndrome) in self.syndromes.iter_mut().zip(other.syndromes.iter()) {
+			*syndrome ^= *other_syndrome;
+		}
+	}
+
+	/// Decode set elements. Returns None if the set has more than `max_elements` elements.
+	/// The sketch of the larger set could be decoded into wrong elements, unless `max_elements` is less than the capacity
+	pub fn decode(&self, max_elements: usize) -> Option<Vec<u32>> {
+		let capacity = self.capacity();
+
+		// even power sums are squares of the half power sums: s(2k) = s(k)^2
+		let mut syndromes = vec![0; 2 * capacity];
+		for i in 0..syndromes.len() {
+			syndromes[i] = if i % 2 == 0 {
+				self.syndromes[i / 2]
+			} else {
+				let half = syndromes[i / 2];
+				mul(half, half)
+			};
+		}
+
+		// find polynomial, whose roots are inverses of the set elements
+		let locator = berlekamp_massey(&syndromes);
+		let degree = locator.len() - 1;
+		if degree > max_elements {
+			return None;
+		}
+		if degree == 0 {
+			return Some(Vec::new());
+		}
+
+		// reversed locator polynomial is monic && its roots are the set elements
+		let polynomial: Vec<_> = locator.into_iter().rev().collect();
+		if polynomial[0] == 0 {
+			return None;
+		}
+
+		let mut elements = Vec::with_capacity(degree);
+		if !find_roots(polynomial, 0, &mut elements) || elements.len() != degree {
+			return None;
+		}
+
+		// check that decoded elements are producing the same sketch
+		let mut sketch = Minisketch::new(capacity);
+		for element in &elements {
+			sketch.add(*element);
+		}
+		if sketch != *self {
+			return None;
+		}
+
+		Some(elements)
+	}
+}
+
+/// Multiplication in GF(2^32)
+fn mul(a: u32, b: u32) -> u32 {
+	let a = a as u64;
+	let mut product = 0u64;
+	for i in 0..32 {
+		if (b >> i) & 1 == 1 {
+			product ^= a << i;
+		}
+	}
+
+	for i in (32..64).rev() {
+		if (product >> i) & 1 == 1 {
+			product ^= (1 << i) | (FIELD_MODULUS << (i - 32));
+		}
+	}
+
+	product as u32
+}
+
+/// Multiplicative inverse in GF(2^32): a^(2^32 - 2)
+fn inv(a: u32) -> u32 {
+	assert!(a != 0, "zero has no inverse");
+
+	let mut result = 1;
+	let mut base = a;
+	let mut exponent = (1u64 << 32) - 2;
+	while exponent != 0 {
+		if exponent & 1 == 1 {
+			result = mul(result, base);
+		}
+		base = mul(base, base);
+		exponent >>= 1;
+	}
+	result
+}
+
+/// Berlekamp-Massey algorithm. Returns the shortest linear feedback shift register, generating the sequence
+fn berlekamp_massey(sequence: &[u32]) -> Vec<u32> {
+	let mut current = vec![1];
+	let mut previous = vec![1];
+	let mut length = 0;
+	let mut shift = 1;
+	let mut previous_discrepancy = 1;
+	for n in 0..sequence.len() {
+		let mut discrepancy = sequence[n];
+		for i in 1..current.len() {
+			discrepancy ^= mul(current[i], sequence[n - i]);
+		}
+
+		if discrepancy == 0 {
+			shift += 1;
+			continue;
+		}
+
+		let coefficient = mul(discrepancy, inv(previous_discrepancy));
+		let current_copy = current.clone();
+		if current.len() < previous.len() + shift {
+			current.resize(previous.len() + shift, 0);
+		}
+		for (i, previous_coefficient) in previous.iter().enumerate() {
+			current[i + shift] ^= mul(coefficient, *previous_coefficient);
+		}
+
+		if 2 * length <= n {
+			length = n + 1 - length;
+			previous = current_copy;
+			previous_discrepancy = discrepancy;
+			shift = 1;
+		} else {
+			shift += 1;
+		}
+	}
+
+	current.resize(length + 1, 0);
+	current
+}
+
+/// Find roots of the monic polynomial, which is the product of distinct linear factors (Berlekamp trace algorithm)
+fn find_roots(polynomial: Vec<u32>, first_basis_index: usize, roots: &mut Vec<u32>) -> bool {
+	let degree = polynomial.len() - 1;
+	match degree {
+		0 => return true,
+		1 => {
+			roots.push(polynomial[0]);
+			return true;
+		},
+		_ => (),
+	}
+
+	for basis_index in first_basis_index..32 {
+		// Tr(b * x) mod polynomial = (b * x) + (b * x)^2 + ... + (b * x)^(2^31)
+		let mut term = vec![0, 1 << basis_index];
+		let mut trace = term.clone();
+		for _ in 1..32 {
+			term = poly_mul_mod(&term, &term, &polynomial);
+			poly_add(&mut trace, &term);
+		}
+
+		// roots with Tr(b * root) = 0 are roots of the common divisor
+		let factor = poly_gcd(polynomial.clone(), trace);
+		let factor_degree = factor.len() - 1;
+		if factor_degree != 0 && factor_degree != degree {
+			let (quotient, _) = poly_div_mod(polynomial, &factor);
+			return find_roots(factor, basis_index + 1, roots) && find_roots(quotient, basis_index + 1, roots);
+		}
+	}
+
+	false
+}
+
+/// Remove zero high-order coefficients
+fn poly_trim(a: &mut Vec<u32>) {
+	while a.last() == Some(&0) {
+		a.pop();
+	}
+}
+
+fn poly_add(a: &mut Vec<u32>, b: &[u32]) {
+	if a.len() < b.len() {
+		a.resize(b.len(), 0);
+	}
+	for (coefficient, b_coefficient) in a.iter_mut().zip(b.iter()) {
+		*coefficient ^= *b_coefficient;
+	}
+	poly_trim(a);
+}
+
+/// Polynomials division. Divisor must be trimmed and non-zero
+fn poly_div_mod(mut a: Vec<u32>, divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+	poly_trim(&mut a);
+	let divisor_degree = divisor.len() - 1;
+	let leading_inverse = inv(divisor[divisor_degree]);
+	let mut quotient = vec![0; a.len().saturating_sub(divisor_degree)];
+	while a.len() > divisor_degree {
+		let shift = a.len() - 1 - divisor_degree;
+		let factor = mul(a[a.len() - 1], leading_inverse);
+		quotient[shift] = factor;
+		for (i, coefficient) in divisor.iter().enumerate() {
+			a[shift + i] ^= mul(factor, *coefficient);
+		}
+		poly_trim(&mut a);
+	}
+	poly_trim(&mut quotient);
+	(quotient, a)
+}
+
+fn poly_mul_mod(a: &[u32], b: &[u32], modulus: &[u32]) -> Vec<u32> {
+	if a.is_empty() || b.is_empty() {
+		return Vec::new();
+	}
+
+	let mut product = vec![0; a.len() + b.len() - 1];
+	for (i, a_coefficient) in a.iter().enumerate() {
+		for (j, b_coefficient) in b.iter().enumerate() {
+			product[i + j] ^= mul(*a_coefficient, *b_coefficient);
+		}
+	}
+	poly_div_mod(product, modulus).1
+}
+
+/// Monic greatest common divisor of polynomials. First polynomial must be non-zero
+fn poly_gcd(mut a: Vec<u32>, mut b: Vec<u32>) -> Vec<u32> {
+	poly_trim(&mut a);
+	poly_trim(&mut b);
+	while !b.is_empty() {
+		let (_, remainder) = poly_div_mod(a, &b);
+		a = b;
+		b = remainder;
+	}
+
+	let leading_inverse = inv(a[a.len() - 1]);
+	a.into_iter().map(|coefficient| mul(coefficient, leading_inverse)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Minisketch, mul, inv};
+
+	fn sketch(capacity: usize, elements: &[u32]) -> Minisketch {
+		let mut sketch = Minisketch::new(capacity);
+		for element in elements {
+			sketch.add(*element);
+		}
+		sketch
+	}
+
+	#[test]
+	fn minisketch_field_inverse() {
+		for element in &[1, 2, 0x8d, 0xdeadbeef, 0xffffffff] {
+			assert_eq!(mul(*element, inv(*element)), 1);
+		}
+	}
+
+	#[test]
+	fn minisketch_decodes_set() {
+		let elements = [7, 0x12345678, 0xffffffff, 42];
+		let mut decoded = sketch(8, &elements).decode(8).unwrap();
+		decoded.sort();
+		assert_eq!(decoded, vec![7, 42, 0x12345678, 0xffffffff]);
+		assert_eq!(sketch(8, &[]).decode(8), Some(vec![]));
+	}
+
+	#[test]
+	fn minisketch_decodes_symmetric_difference() {
+		let common: Vec<u32> = (1..100).map(|i| i * 0x01010101).collect();
+		let mut local = common.clone();
+		local.extend(vec![0xaaaa, 0xbbbb]);
+		let mut remote = common;
+		remote.push(0xcccc);
+
+		let mut difference = sketch(5, &local);
+		difference.merge(&Minisketch::from_bytes(&sketch(5, &remote).to_bytes()).unwrap());
+		let mut decoded = difference.decode(5).unwrap();
+		decoded.sort();
+		assert_eq!(decoded, vec![0xaaaa, 0xbbbb, 0xcccc]);
+		assert_eq!(difference.decode(2), None);
+	}
+
+	#[test]
+	fn minisketch_serialization() {
+		let original = sketch(3, &[1, 2, 3]);
+		let serialized = original.to_bytes();
+		assert_eq!(serialized.len(), 12);
+		assert_eq!(Minisketch::from_bytes(&serialized), Some(original));
+		assert_eq!(Minisketch::from_bytes(&[1, 2, 3]), None);
+	}
+}
@@ -8,10 +8,14 @@ mod hash_queue;
 mod known_hash_filter;
 mod memory_pool_transaction_provider;
 mod message_block_headers_provider;
+mod minisketch;
+mod network_time_source;
 mod orphan_blocks_pool;
 mod orphan_transactions_pool;
-mod partial_merkle_tree;
+mod partial_compact_block;
 mod synchronization_state;
+mod transaction_announcement_queue;
+mod transaction_reconciliation;
 
 pub use self::average_speed_meter::AverageSpeedMeter;
 pub use self::best_headers_chain::{BestHeadersChain, Information as BestHeadersChainInformation};
@@ -21,12 +25,16 @@ pub use self::connection_filter::ConnectionFilter;
 pub use self::fee_rate_filter::FeeRateFilter;
 pub use self::hash_queue::{HashQueue, HashQueueChain, HashPosition};
 pub use self::known_hash_filter::{KnownHashType, KnownHashFilter};
-pub use self::memory_pool_transaction_provider::MemoryPoolTransactionOutputProvider;
+pub use self::memory_pool_transaction_provider::{MemoryPoolTransactionOutputProvider, PackageTransactionOutputProvider};
 pub use self::message_block_headers_provider::MessageBlockHeadersProvider;
+pub use self::minisketch::Minisketch;
+pub use self::network_time_source::NetworkTimeSource;
 pub use self::orphan_blocks_pool::OrphanBlocksPool;
 pub use self::orphan_transactions_pool::{OrphanTransactionsPool, OrphanTransaction};
-pub use self::partial_merkle_tree::{PartialMerkleTree, build_partial_merkle_tree};
+pub use self::partial_compact_block::PartialCompactBlock;
 pub use self::synchronization_state::SynchronizationState;
+pub use self::transaction_announcement_queue::TransactionAnnouncementQueue;
+pub use self::transaction_reconciliation::TransactionReconciliation;
 
 /// Block height type
 pub type BlockHeight = u32;
@@ -0,0 +1,22 @@
+use std::sync::Arc;
+use p2p::NetworkTime;
+use verification::TimeSource;
+
+/// Network-adjusted time, used to verify blocks and to build block templates.
+pub struct NetworkTimeSource {
+	network_time: Arc<NetworkTime>,
+}
+
+impl NetworkTimeSource {
+	pub fn new(network_time: Arc<NetworkTime>) -> Self {
+		NetworkTimeSource {
+			network_time: network_time,
+		}
+	}
+}
+
+impl TimeSource for NetworkTimeSource {
+	fn now(&self) -> u32 {
+		self.network_time.adjusted_time()
+	}
+}
@@ -41,6 +41,11 @@ impl OrphanTransactionsPool {
 		self.by_hash.len()
 	}
 
+	/// Is pool empty?
+	pub fn is_empty(&self) -> bool {
+		self.by_hash.is_empty()
+	}
+
 	/// Get unknown transactions in the insertion order
 	pub fn transactions(&self) -> &LinkedHashMap<H256, OrphanTransaction> {
 		&self.by_hash
@@ -111,6 +116,29 @@ impl OrphanTransactionsPool {
 		}
 		removed
 	}
+
+	/// Forget transactions, which are confirmed in the block, and return all orphans, which are not orphans anymore
+	pub fn remove_confirmed_transactions(&mut self, hashes: &[H256]) -> Vec<IndexedTransaction> {
+		// confirmed orphans are neither verified, nor returned
+		for hash in hashes {
+			if let Some(orphan) = self.by_hash.remove(hash) {
+				for unknown_parent in &orphan.unknown_parents {
+					if let Entry::Occupied(mut children_entry) = self.by_parent.entry(unknown_parent.clone()) {
+						children_entry.get_mut().remove(hash);
+						if children_entry.get().is_empty() {
+							children_entry.remove_entry();
+						}
+					}
+				}
+			}
+		}
+
+		let mut removed: Vec<IndexedTransaction> = Vec::new();
+		for hash in hashes {
+			removed.extend(self.remove_transactions_for_parent(hash));
+		}
+		removed
+	}
 }
 
 impl OrphanTransaction {
@@ -222,4 +250,29 @@ mod tests {
 
 		pool.remove_transactions(&[chain.at(2).hash(), chain.at(1).hash()]);
 	}
+
+	#[test]
+	fn orphan_transaction_pool_remove_confirmed_transactions() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(100).store(chain)			// t1
+			.into_input(0).add_output(200).store(chain)				// t1 -> t2
+			.into_input(0).add_output(300).store(chain)				// t1 -> t2 -> t3
+			.set_default_input(0).set_output(400).store(chain)		// t4
+			.into_input(0).set_output(500).store(chain);			// t4 -> t5
+		let t2_unknown: HashSet<H256> = chain.at(1).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+		let t3_unknown: HashSet<H256> = chain.at(2).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+		let t5_unknown: HashSet<H256> = chain.at(4).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+
+		let mut pool = OrphanTransactionsPool::new();
+		pool.insert(chain.at(1).into(), t2_unknown); // t2
+		pool.insert(chain.at(2).into(), t3_unknown); // t3
+		pool.insert(chain.at(4).into(), t5_unknown); // t5
+
+		// t1 and t2 are confirmed => t2 is forgotten, t3 is not orphan anymore
+		let removed = pool.remove_confirmed_transactions(&[chain.at(0).hash(), chain.at(1).hash()]);
+		assert_eq!(pool.len(), 1);
+		let removed: Vec<H256> = removed.into_iter().map(|tx| tx.hash).collect();
+		assert_eq!(removed, vec![chain.at(2).hash()]);
+		assert!(pool.contains(&chain.at(4).hash()));
+	}
 }
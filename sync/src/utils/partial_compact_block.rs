@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction, ShortTransactionID, Transaction};
+use message::common::BlockHeaderAndIDs;
+use primitives::hash::H256;
+use verification::check_witness_commitment;
+use super::compact_block_builder::{short_transaction_id_keys, short_transaction_id};
+
+/// Maximal number of transactions in compact block (BIP152)
+const MAX_COMPACT_BLOCK_TRANSACTIONS: usize = 0x10000;
+
+/// Block, which is being reconstructed from `cmpctblock` message
+#[derive(Debug)]
+pub struct PartialCompactBlock {
+	/// Block header
+	header: IndexedBlockHeader,
+	/// Block transactions. None if transaction is not yet known
+	transactions: Vec<Option<IndexedTransaction>>,
+}
+
+impl PartialCompactBlock {
+	/// Reconstructs block from prefilled transactions and `candidates` (memory pool transactions).
+	/// When `use_witness_hash` is true, short ids are computed from witness hashes (compact blocks version 2).
+	/// Transactions with colliding short ids are left unknown, so that they're requested from peer.
+	pub fn new<'a, I>(message: BlockHeaderAndIDs, use_witness_hash: bool, candidates: I) -> Result<Self, String>
+		where I: Iterator<Item=(&'a H256, &'a Transaction)> {
+		let transactions_len = message.short_ids.len() + message.prefilled_transactions.len();
+		if transactions_len == 0 || transactions_len > MAX_COMPACT_BLOCK_TRANSACTIONS {
+			return Err(format!("Invalid number of transactions in compact block: {}", transactions_len));
+		}
+
+		let mut transactions: Vec<Option<IndexedTransaction>> = vec![None; transactions_len];
+		for prefilled_transaction in message.prefilled_transactions {
+			if prefilled_transaction.index >= transactions_len {
+				return Err(format!("Invalid prefilled transaction index in compact block: {}", prefilled_transaction.index));
+			}
+			transactions[prefilled_transaction.index] = Some(prefilled_transaction.transaction.into());
+		}
+
+		// short ids are assigned to transactions, which are not prefilled, in order
+		let (key0, key1) = short_transaction_id_keys(message.nonce, &message.header);
+		let unknown_indexes: Vec<_> = transactions.iter().enumerate()
+			.filter(|&(_, transaction)| transaction.is_none())
+			.map(|(index, _)| index)
+			.collect();
+		let mut short_ids: HashMap<ShortTransactionID, Option<usize>> = HashMap::with_capacity(message.short_ids.len());
+		for (short_id, index) in message.short_ids.into_iter().zip(unknown_indexes) {
+			// when two block transactions share the same short id, both are requested from peer
+			let is_collision = short_ids.contains_key(&short_id);
+			short_ids.insert(short_id, if is_collision { None } else { Some(index) });
+		}
+
+		for (hash, transaction) in candidates {
+			let witness_hash;
+			let id_hash = if use_witness_hash && transaction.has_witness() {
+				witness_hash = transaction.witness_hash();
+				&witness_hash
+			} else {
+				hash
+			};
+
+			let short_id = short_transaction_id(key0, key1, id_hash);
+			let index = match short_ids.get(&short_id) {
+				Some(&Some(index)) => index,
+				_ => continue,
+			};
+
+			if transactions[index].is_some() {
+				// several candidates have the same short id => request transaction from peer
+				transactions[index] = None;
+				short_ids.insert(short_id, None);
+			} else {
+				transactions[index] = Some(IndexedTransaction::new(hash.clone(), transaction.clone()));
+			}
+		}
+
+		Ok(PartialCompactBlock {
+			header: message.header.into(),
+			transactions: transactions,
+		})
+	}
+
+	/// Hash of the block
+	pub fn hash(&self) -> &H256 {
+		&self.header.hash
+	}
+
+	/// Indexes of transactions, which must be requested from peer
+	pub fn missing_indexes(&self) -> Vec<usize> {
+		self.transactions.iter().enumerate()
+			.filter(|&(_, transaction)| transaction.is_none())
+			.map(|(index, _)| index)
+			.collect()
+	}
+
+	/// Fills missing transactions with transactions from `blocktxn` message
+	pub fn fill(&mut self, transactions: Vec<Transaction>) -> Result<(), String> {
+		let missing_indexes = self.missing_indexes();
+		if missing_indexes.len() != transactions.len() {
+			return Err(format!("Got {} transactions in 'blocktxn' message, when {} were requested", transactions.len(), missing_indexes.len()));
+		}
+
+		for (index, transaction) in missing_indexes.into_iter().zip(transactions) {
+			self.transactions[index] = Some(transaction.into());
+		}
+		Ok(())
+	}
+
+	/// Returns reconstructed block if all transactions are known and they match the block header
+	/// and the coinbase witness commitment. Otherwise full block must be requested from peer
+	pub fn into_block(self) -> Option<IndexedBlock> {
+		let PartialCompactBlock { header, transactions } = self;
+		let transactions: Vec<_> = match transactions.into_iter().collect() {
+			Some(transactions) => transactions,
+			None => return None,
+		};
+
+		// block could be reconstructed with wrong transactions if short ids collide.
+		// transactions with the same txid, but different (malleated) witness are detected with witness commitment
+		let block = IndexedBlock::new(header, transactions);
+		if block.merkle_root() == block.header.raw.merkle_root_hash && check_witness_commitment(&block).is_ok() {
+			Some(block)
+		} else {
+			None
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::collections::HashSet;
+	use chain::{Block, Transaction};
+	use message::common::BlockHeaderAndIDs;
+	use primitives::bytes::Bytes;
+	use primitives::hash::H256;
+	use super::PartialCompactBlock;
+	use super::super::build_compact_block;
+
+	fn block_with_transactions() -> (Block, Vec<Transaction>) {
+		let block = test_data::block_builder()
+			.transaction().coinbase().output().value(10).build().build()
+			.transaction().input().hash(H256::from(1)).build().output().value(20).build().build()
+			.transaction().output().value(30).build().build()
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build();
+		let transactions = block.transactions.clone();
+		(block, transactions)
+	}
+
+	fn compact_block(block: &Block) -> BlockHeaderAndIDs {
+		let prefilled: HashSet<_> = vec![0].into_iter().collect();
+		build_compact_block(&block.clone().into(), prefilled)
+	}
+
+	#[test]
+	fn compact_block_is_reconstructed_from_memory_pool() {
+		let (block, transactions) = block_with_transactions();
+		let hashes: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+		let candidates = hashes.iter().zip(transactions.iter()).skip(1);
+
+		let partial = PartialCompactBlock::new(compact_block(&block), false, candidates).unwrap();
+		assert!(partial.missing_indexes().is_empty());
+		assert_eq!(partial.into_block().map(|block| block.to_raw_block()), Some(block));
+	}
+
+	#[test]
+	fn compact_block_is_reconstructed_with_requested_transactions() {
+		let (block, transactions) = block_with_transactions();
+		let hashes: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+		let candidates = hashes.iter().zip(transactions.iter()).skip(2);
+
+		let mut partial = PartialCompactBlock::new(compact_block(&block), false, candidates).unwrap();
+		assert_eq!(partial.missing_indexes(), vec![1]);
+		assert!(partial.fill(vec![]).is_err());
+		assert!(partial.fill(vec![transactions[1].clone()]).is_ok());
+		assert_eq!(partial.into_block().map(|block| block.to_raw_block()), Some(block));
+	}
+
+	#[test]
+	fn compact_block_with_wrong_transactions_is_not_reconstructed() {
+		let (block, transactions) = block_with_transactions();
+		let hashes: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+		let candidates = hashes.iter().zip(transactions.iter()).skip(2);
+
+		let mut partial = PartialCompactBlock::new(compact_block(&block), false, candidates).unwrap();
+		assert!(partial.fill(vec![transactions[2].clone()]).is_ok());
+		assert!(partial.into_block().is_none());
+	}
+
+	#[test]
+	fn compact_block_with_malleated_witness_is_not_reconstructed() {
+		let (block, transactions) = block_with_transactions();
+		let mut malleated = transactions[1].clone();
+		malleated.inputs[0].script_witness = vec![Bytes::from(vec![1u8])];
+		assert_eq!(malleated.hash(), transactions[1].hash());
+
+		let hashes: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+		let candidates = hashes.iter().zip(transactions.iter()).skip(2);
+
+		let mut partial = PartialCompactBlock::new(compact_block(&block), false, candidates).unwrap();
+		assert!(partial.fill(vec![malleated]).is_ok());
+		assert!(partial.into_block().is_none());
+	}
+
+	#[test]
+	fn malformed_compact_block_is_rejected() {
+		let (block, _) = block_with_transactions();
+		let mut message = compact_block(&block);
+		message.prefilled_transactions[0].index = 3;
+		assert!(PartialCompactBlock::new(message, false, vec![].into_iter()).is_err());
+	}
+}
@@ -0,0 +1,109 @@
+use std::cmp;
+use linked_hash_map::LinkedHashMap;
+use rand::{thread_rng, Rng};
+use primitives::hash::H256;
+
+/// Average interval between transactions announcements to the same peer (in seconds)
+pub const AVERAGE_ANNOUNCEMENT_INTERVAL_S: f64 = 5_f64;
+/// Maximal number of transactions, announced to the peer at once
+pub const MAX_ANNOUNCEMENT_LEN: usize = 1000;
+/// Maximal number of transactions, waiting to be announced to the peer
+pub const MAX_QUEUE_LEN: usize = 50_000;
+
+/// Queue of transactions, waiting to be announced to the peer. Transactions are announced in batches
+/// after Poisson-distributed delays, so that it's harder for peers to guess the origin of the transaction.
+#[derive(Debug, Default)]
+pub struct TransactionAnnouncementQueue {
+	/// Insertion-time ordered hashes of transactions
+	hashes: LinkedHashMap<H256, ()>,
+	/// Time of the next announcement (in seconds since epoch)
+	next_announcement_time: f64,
+}
+
+impl TransactionAnnouncementQueue {
+	/// Queue transaction announcement. Transaction, which is already queued, keeps its position
+	pub fn push(&mut self, hash: H256) {
+		if !self.hashes.contains_key(&hash) {
+			self.hashes.insert(hash, ());
+			// forget oldest transaction, if limits overflow
+			if self.hashes.len() > MAX_QUEUE_LEN {
+				self.hashes.pop_front();
+			}
+		}
+	}
+
+	/// Returns number of queued transactions
+	#[cfg(test)]
+	pub fn len(&self) -> usize {
+		self.hashes.len()
+	}
+
+	/// Take oldest queued transactions, if announcement time has come. Next announcement is scheduled then
+	pub fn take(&mut self, now: f64) -> Vec<H256> {
+		if now < self.next_announcement_time {
+			return Vec::new();
+		}
+
+		self.next_announcement_time = now + poisson_delay(AVERAGE_ANNOUNCEMENT_INTERVAL_S);
+
+		let len = cmp::min(self.hashes.len(), MAX_ANNOUNCEMENT_LEN);
+		(0..len).filter_map(|_| self.hashes.pop_front().map(|(hash, _)| hash)).collect()
+	}
+}
+
+/// Random delay of Poisson process with given average interval
+fn poisson_delay(average_interval: f64) -> f64 {
+	let uniform: f64 = thread_rng().gen();
+	-(1_f64 - uniform).ln() * average_interval
+}
+
+#[cfg(test)]
+mod tests {
+	use primitives::hash::H256;
+	use super::{TransactionAnnouncementQueue, MAX_ANNOUNCEMENT_LEN, MAX_QUEUE_LEN};
+
+	fn hash(index: usize) -> H256 {
+		let mut hash = H256::default();
+		hash[0] = index as u8;
+		hash[1] = (index >> 8) as u8;
+		hash[2] = (index >> 16) as u8;
+		hash
+	}
+
+	#[test]
+	fn transaction_announcement_queue_deduplicates() {
+		let mut queue = TransactionAnnouncementQueue::default();
+		queue.push(H256::from(1));
+		queue.push(H256::from(2));
+		queue.push(H256::from(1));
+		assert_eq!(queue.len(), 2);
+		assert_eq!(queue.take(0f64), vec![H256::from(1), H256::from(2)]);
+		assert_eq!(queue.len(), 0);
+	}
+
+	#[test]
+	fn transaction_announcement_queue_waits_for_announcement_time() {
+		let mut queue = TransactionAnnouncementQueue::default();
+		queue.push(H256::from(1));
+		assert_eq!(queue.take(1000f64), vec![H256::from(1)]);
+
+		// next announcement is delayed
+		queue.push(H256::from(2));
+		assert!(queue.take(1000f64).is_empty());
+		assert_eq!(queue.take(1000000f64), vec![H256::from(2)]);
+	}
+
+	#[test]
+	fn transaction_announcement_queue_limits() {
+		let mut queue = TransactionAnnouncementQueue::default();
+		for i in 0..MAX_QUEUE_LEN + 1 {
+			queue.push(hash(i));
+		}
+		assert_eq!(queue.len(), MAX_QUEUE_LEN);
+
+		let announced = queue.take(0f64);
+		assert_eq!(announced.len(), MAX_ANNOUNCEMENT_LEN);
+		assert_eq!(announced[0], hash(1));
+		assert_eq!(queue.len(), MAX_QUEUE_LEN - MAX_ANNOUNCEMENT_LEN);
+	}
+}
@@ -0,0 +1,317 @@
+use std::{cmp, mem};
+use std::collections::HashMap;
+use bitcrypto::{tagged_hash, siphash24};
+use byteorder::{LittleEndian, ByteOrder};
+use linked_hash_map::LinkedHashMap;
+use rand::{thread_rng, Rng};
+use message::types;
+use primitives::hash::H256;
+use super::Minisketch;
+
+/// Version of the transactions reconciliation protocol
+pub const RECONCILIATION_VERSION: u32 = 1;
+/// Interval between reconciliation rounds, initiated with the same peer (in seconds)
+pub const RECONCILIATION_INTERVAL_S: f64 = 8_f64;
+/// Maximal number of transactions, waiting to be reconciled with the peer
+pub const MAX_RECONCILIATION_SET_LEN: usize = 3000;
+/// Maximal capacity of the sketch. If sets are differing more, transactions are announced with inventory.
+/// Sets difference is decoded with one spare element of the capacity, so that wrong decoding is unlikely
+pub const MAX_SKETCH_CAPACITY: usize = 128;
+/// Coefficient of the sets difference estimation: difference = |local - remote| + q * min(local, remote) + 1
+const RECONCILIATION_Q: f64 = 0.25;
+/// Precision of `q` coefficient in `reqrecon` message
+const Q_PRECISION: f64 = 32767_f64;
+
+/// Transactions reconciliation with the single peer (BIP330).
+/// Outbound connection side initiates reconciliation rounds:
+/// 1) initiator sends `reqrecon` with its set size;
+/// 2) responder sends `sketch` of its set;
+/// 3) initiator decodes sets difference, announces transactions, missing from the responder set and
+///    asks for transactions, missing from its own set, with `reconcildiff` message;
+/// 4) responder announces asked transactions.
+/// If sets difference can't be decoded, both sides announce all their transactions.
+#[derive(Debug)]
+pub struct TransactionReconciliation {
+	/// Is this node initiating reconciliation rounds?
+	is_initiator: bool,
+	/// Salt, sent to the peer in `sendtxrcncl` message
+	local_salt: u64,
+	/// Short ids keys. None until peer has sent its `sendtxrcncl` message
+	keys: Option<(u64, u64)>,
+	/// Transactions, waiting to be reconciled, by short ids
+	set: LinkedHashMap<u32, H256>,
+	/// Transactions, which are reconciled in the current round, by short ids
+	snapshot: Option<HashMap<u32, H256>>,
+	/// Time of the next reconciliation round (in seconds since epoch)
+	next_reconciliation_time: f64,
+}
+
+impl TransactionReconciliation {
+	/// Create reconciliation with new random salt
+	pub fn new(is_initiator: bool) -> Self {
+		TransactionReconciliation {
+			is_initiator: is_initiator,
+			local_salt: thread_rng().gen(),
+			keys: None,
+			set: LinkedHashMap::new(),
+			snapshot: None,
+			next_reconciliation_time: 0_f64,
+		}
+	}
+
+	/// Message, which signals our support of reconciliation
+	pub fn sendtxrcncl(&self) -> types::SendTxRcncl {
+		types::SendTxRcncl {
+			version: RECONCILIATION_VERSION,
+			salt: self.local_salt,
+		}
+	}
+
+	/// Is peer also supporting reconciliation?
+	pub fn is_registered(&self) -> bool {
+		self.keys.is_some()
+	}
+
+	/// Register reconciliation, when `sendtxrcncl` is received from peer
+	pub fn register(&mut self, message: &types::SendTxRcncl) -> bool {
+		if self.keys.is_some() || message.version < RECONCILIATION_VERSION {
+			return false;
+		}
+
+		self.keys = Some(short_id_keys(self.local_salt, message.salt));
+		true
+	}
+
+	/// Add transaction to the reconciliation set
+	pub fn push(&mut self, hash: H256, witness_hash: &H256) {
+		let short_id = match self.keys {
+			Some((key0, key1)) => short_id(key0, key1, witness_hash),
+			None => return,
+		};
+
+		self.set.insert(short_id, hash);
+		// forget oldest transaction, if limits overflow
+		if self.set.len() > MAX_RECONCILIATION_SET_LEN {
+			self.set.pop_front();
+		}
+	}
+
+	/// Start new reconciliation round, if it is time to (initiator only)
+	pub fn request(&mut self, now: f64) -> Option<types::ReqRecon> {
+		if !self.is_initiator || !self.is_registered() || self.snapshot.is_some() || now < self.next_reconciliation_time {
+			return None;
+		}
+
+		self.next_reconciliation_time = now + RECONCILIATION_INTERVAL_S;
+		let snapshot = self.take_snapshot();
+		Some(types::ReqRecon {
+			set_size: cmp::min(snapshot.len(), ::std::u16::MAX as usize) as u16,
+			q: (RECONCILIATION_Q * Q_PRECISION) as u16,
+		})
+	}
+
+	/// Respond to `reqrecon` with the sketch of our set (responder only)
+	pub fn respond(&mut self, message: &types::ReqRecon) -> Option<types::Sketch> {
+		if self.is_initiator || !self.is_registered() || self.snapshot.is_some() {
+			return None;
+		}
+
+		let snapshot = self.take_snapshot();
+		let capacity = if snapshot.is_empty() {
+			0
+		} else {
+			let q = message.q as f64 / Q_PRECISION;
+			estimate_capacity(snapshot.len(), message.set_size as usize, q)
+		};
+
+		Some(types::Sketch {
+			skdata: build_sketch(capacity, snapshot).to_bytes().into(),
+		})
+	}
+
+	/// Decode sets difference from the responder sketch (initiator only).
+	/// Returns `reconcildiff` message and transactions to announce
+	pub fn finish(&mut self, message: &types::Sketch) -> Option<(types::ReconcilDiff, Vec<H256>)> {
+		if !self.is_initiator {
+			return None;
+		}
+
+		let snapshot = match self.snapshot.take() {
+			Some(snapshot) => snapshot,
+			None => return None,
+		};
+
+		let remote_sketch = match Minisketch::from_bytes(&message.skdata) {
+			Some(ref sketch) if sketch.capacity() > MAX_SKETCH_CAPACITY => None,
+			sketch => sketch,
+		};
+
+		// empty sketch means that responder has no transactions at all
+		let difference = remote_sketch.and_then(|remote_sketch| if remote_sketch.capacity() == 0 {
+			Some(snapshot.keys().cloned().collect())
+		} else {
+			let mut sketch = build_sketch(remote_sketch.capacity(), &snapshot);
+			sketch.merge(&remote_sketch);
+			sketch.decode(remote_sketch.capacity() - 1)
+		});
+
+		match difference {
+			Some(difference) => {
+				let (local, remote): (Vec<_>, Vec<_>) = difference.into_iter().partition(|short_id| snapshot.contains_key(short_id));
+				let announce = local.into_iter().filter_map(|short_id| snapshot.get(&short_id).cloned()).collect();
+				Some((types::ReconcilDiff {
+					success: true,
+					ask_shortids: remote,
+				}, announce))
+			},
+			None => Some((types::ReconcilDiff {
+				success: false,
+				ask_shortids: Vec::new(),
+			}, snapshot.into_iter().map(|(_, hash)| hash).collect())),
+		}
+	}
+
+	/// Select transactions to announce after initiator has decoded sets difference (responder only)
+	pub fn reconciled(&mut self, message: &types::ReconcilDiff) -> Vec<H256> {
+		if self.is_initiator {
+			return Vec::new();
+		}
+
+		let snapshot = match self.snapshot.take() {
+			Some(snapshot) => snapshot,
+			None => return Vec::new(),
+		};
+
+		if message.success {
+			message.ask_shortids.iter().filter_map(|short_id| snapshot.get(short_id).cloned()).collect()
+		} else {
+			snapshot.into_iter().map(|(_, hash)| hash).collect()
+		}
+	}
+
+	fn take_snapshot(&mut self) -> &HashMap<u32, H256> {
+		let snapshot = mem::replace(&mut self.set, LinkedHashMap::new()).into_iter().collect();
+		self.snapshot = Some(snapshot);
+		self.snapshot.as_ref().expect("assigned one line above; qed")
+	}
+}
+
+/// Compute short ids keys from salts of both peers
+fn short_id_keys(local_salt: u64, remote_salt: u64) -> (u64, u64) {
+	let mut salts = [0u8; 16];
+	LittleEndian::write_u64(&mut salts[0..8], cmp::min(local_salt, remote_salt));
+	LittleEndian::write_u64(&mut salts[8..16], cmp::max(local_salt, remote_salt));
+	let hash = tagged_hash(b"Tx Relay Salting", &salts);
+	(LittleEndian::read_u64(&hash[0..8]), LittleEndian::read_u64(&hash[8..16]))
+}
+
+/// Compute non-zero 32-bit short id of the transaction
+fn short_id(key0: u64, key1: u64, witness_hash: &H256) -> u32 {
+	let hash = siphash24(key0, key1, &**witness_hash);
+	1 + (hash % 0xffff_ffff) as u32
+}
+
+/// Estimate sets difference
+fn estimate_capacity(local_set_size: usize, remote_set_size: usize, q: f64) -> usize {
+	let set_size_difference = cmp::max(local_set_size, remote_set_size) - cmp::min(local_set_size, remote_set_size);
+	let capacity = set_size_difference + (q * cmp::min(local_set_size, remote_set_size) as f64) as usize + 1;
+	cmp::min(capacity, MAX_SKETCH_CAPACITY)
+}
+
+fn build_sketch(capacity: usize, set: &HashMap<u32, H256>) -> Minisketch {
+	let mut sketch = Minisketch::new(capacity);
+	for short_id in set.keys() {
+		sketch.add(*short_id);
+	}
+	sketch
+}
+
+#[cfg(test)]
+mod tests {
+	use message::types;
+	use primitives::hash::H256;
+	use super::TransactionReconciliation;
+
+	fn registered_pair() -> (TransactionReconciliation, TransactionReconciliation) {
+		let mut initiator = TransactionReconciliation::new(true);
+		let mut responder = TransactionReconciliation::new(false);
+		let initiator_message = initiator.sendtxrcncl();
+		assert!(initiator.register(&responder.sendtxrcncl()));
+		assert!(responder.register(&initiator_message));
+		(initiator, responder)
+	}
+
+	#[test]
+	fn transaction_reconciliation_requires_registration() {
+		let mut initiator = TransactionReconciliation::new(true);
+		initiator.push(H256::from(1), &H256::from(1));
+		assert_eq!(initiator.request(0f64), None);
+
+		let message = types::SendTxRcncl { version: 0, salt: 10 };
+		assert!(!initiator.register(&message));
+		let message = types::SendTxRcncl { version: 1, salt: 10 };
+		assert!(initiator.register(&message));
+		assert!(!initiator.register(&message));
+		assert!(initiator.request(0f64).is_some());
+	}
+
+	#[test]
+	fn transaction_reconciliation_round() {
+		let (mut initiator, mut responder) = registered_pair();
+		for i in 1..10 {
+			initiator.push(H256::from(i), &H256::from(i + 100));
+			responder.push(H256::from(i), &H256::from(i + 100));
+		}
+		initiator.push(H256::from(20), &H256::from(120));
+		responder.push(H256::from(30), &H256::from(130));
+		responder.push(H256::from(31), &H256::from(131));
+
+		let reqrecon = initiator.request(0f64).unwrap();
+		assert_eq!(reqrecon.set_size, 10);
+		// next round is only started after this one is completed
+		assert_eq!(initiator.request(1000f64), None);
+
+		let sketch = responder.respond(&reqrecon).unwrap();
+		let (reconcildiff, initiator_announcements) = initiator.finish(&sketch).unwrap();
+		assert!(reconcildiff.success);
+		assert_eq!(reconcildiff.ask_shortids.len(), 2);
+		assert_eq!(initiator_announcements, vec![H256::from(20)]);
+
+		let mut responder_announcements = responder.reconciled(&reconcildiff);
+		responder_announcements.sort();
+		assert_eq!(responder_announcements, vec![H256::from(30), H256::from(31)]);
+	}
+
+	#[test]
+	fn transaction_reconciliation_falls_back_to_announcements() {
+		let (mut initiator, mut responder) = registered_pair();
+		initiator.push(H256::from(1), &H256::from(1));
+		for i in 10..20 {
+			responder.push(H256::from(i), &H256::from(i));
+		}
+
+		let reqrecon = initiator.request(0f64).unwrap();
+		// responder underestimates sets difference
+		let reqrecon = types::ReqRecon { set_size: 10, q: reqrecon.q };
+		let sketch = responder.respond(&reqrecon).unwrap();
+		let (reconcildiff, initiator_announcements) = initiator.finish(&sketch).unwrap();
+		assert!(!reconcildiff.success);
+		assert_eq!(initiator_announcements, vec![H256::from(1)]);
+		assert_eq!(responder.reconciled(&reconcildiff).len(), 10);
+	}
+
+	#[test]
+	fn transaction_reconciliation_with_empty_responder_set() {
+		let (mut initiator, mut responder) = registered_pair();
+		initiator.push(H256::from(1), &H256::from(1));
+
+		let reqrecon = initiator.request(0f64).unwrap();
+		let sketch = responder.respond(&reqrecon).unwrap();
+		assert!(sketch.skdata.is_empty());
+		let (reconcildiff, initiator_announcements) = initiator.finish(&sketch).unwrap();
+		assert!(reconcildiff.success);
+		assert!(reconcildiff.ask_shortids.is_empty());
+		assert_eq!(initiator_announcements, vec![H256::from(1)]);
+		assert!(responder.reconciled(&reconcildiff).is_empty());
+	}
+}
@@ -41,10 +41,7 @@ impl Into<Transaction> for TransactionBuilder {
 
 impl Into<IndexedTransaction> for TransactionBuilder {
 	fn into(self) -> IndexedTransaction {
-		IndexedTransaction {
-			hash: self.transaction.hash(),
-			raw: self.transaction,
-		}
+		IndexedTransaction::new(self.transaction.hash(), self.transaction)
 	}
 }
 
@@ -135,6 +132,11 @@ impl TransactionBuilder {
 		self
 	}
 
+	pub fn signal_replacement(mut self) -> Self {
+		self.transaction.inputs[0].sequence = 0xfffffffd;
+		self
+	}
+
 	pub fn store(self, chain: &mut ChainBuilder) -> Self {
 		chain.transactions.push(self.transaction.clone());
 		self
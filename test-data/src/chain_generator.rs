@@ -0,0 +1,437 @@
+//! Programmable chain generator.
+//!
+//! Generates long valid chains, where every block spends outputs of previous blocks.
+//! Generated blocks pass full verification on the `Unitest` network (and on other networks,
+//! if header version and bits are configured accordingly).
+//!
+//! ```ignore
+//! let mut generator = ChainGenerator::with_genesis_outputs(10, 100_000)
+//! 	.transactions_per_block(2)
+//! 	.fee(1_000)
+//! 	.reorg_at(50, 3);
+//! // 100 canon blocks and 4 blocks of the side chain, which becomes canon at height 50
+//! let blocks = generator.generate(100);
+//! ```
+
+use std::collections::BTreeMap;
+use primitives::bigint::U256;
+use primitives::bytes::Bytes;
+use primitives::compact::Compact;
+use primitives::hash::H256;
+use chain::{Block, BlockHeader, Transaction, TransactionInput, TransactionOutput, OutPoint};
+use crypto::{sha256, dhash256};
+use script::{Builder as ScriptBuilder, Opcode};
+
+/// Number of blocks, after which coinbase outputs can be spent.
+pub const COINBASE_MATURITY: u32 = 100;
+/// Timestamp of the generated genesis block.
+pub const GENESIS_TIME: u32 = 1_500_000_000;
+/// Time between generated blocks.
+pub const BLOCK_INTERVAL: u32 = 600;
+/// Default coinbase value (block reward before the first halving).
+pub const DEFAULT_COINBASE_VALUE: u64 = 50 * 100_000_000;
+/// Witness commitment output header.
+const WITNESS_COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Output, which can be spent by the generated transactions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendableOutput {
+	pub outpoint: OutPoint,
+	pub value: u64,
+	/// Number of block, which contains the output.
+	pub height: u32,
+	pub is_coinbase: bool,
+	/// Output is P2WSH and must be spent with the witness.
+	pub is_witness: bool,
+}
+
+/// Chain generator.
+#[derive(Debug, Clone)]
+pub struct ChainGenerator {
+	/// Canon chain, starting with genesis block.
+	blocks: Vec<Block>,
+	/// Unspent outputs after every canon block.
+	outputs: Vec<Vec<SpendableOutput>>,
+	coinbase_value: u64,
+	fee: u64,
+	transactions_per_block: usize,
+	outputs_per_transaction: usize,
+	segwit: bool,
+	version: u32,
+	bits: Compact,
+	/// Scheduled reorgs: height => depth.
+	reorgs: BTreeMap<u32, u32>,
+	/// Makes blocks of different branches distinct.
+	branch: u32,
+}
+
+impl Default for ChainGenerator {
+	fn default() -> Self {
+		ChainGenerator::new()
+	}
+}
+
+impl ChainGenerator {
+	/// Creates generator with the genesis block, containing only coinbase transaction.
+	pub fn new() -> Self {
+		ChainGenerator::with_genesis_outputs(0, 0)
+	}
+
+	/// Creates generator with the genesis block, containing transaction with `count` outputs of `value`,
+	/// which can be spent immediately (genesis transactions are never verified).
+	pub fn with_genesis_outputs(count: usize, value: u64) -> Self {
+		let mut transactions = vec![coinbase_transaction(0, 0, DEFAULT_COINBASE_VALUE, false)];
+		if count != 0 {
+			transactions.push(Transaction {
+				version: 1,
+				inputs: Vec::new(),
+				outputs: (0..count).map(|_| TransactionOutput {
+					value: value,
+					script_pubkey: spendable_script(false),
+				}).collect(),
+				lock_time: 0,
+			});
+		}
+
+		let mut generator = ChainGenerator {
+			blocks: Vec::new(),
+			outputs: Vec::new(),
+			coinbase_value: DEFAULT_COINBASE_VALUE,
+			fee: 1_000,
+			transactions_per_block: 1,
+			outputs_per_transaction: 2,
+			segwit: false,
+			version: 4,
+			bits: Compact::max_value(),
+			reorgs: BTreeMap::new(),
+			branch: 0,
+		};
+		let genesis = generator.seal(0, H256::default(), transactions);
+		generator.push_block(genesis);
+		generator
+	}
+
+	/// Value of the coinbase output. Transaction fees are added to it.
+	pub fn coinbase_value(mut self, value: u64) -> Self {
+		self.coinbase_value = value;
+		self
+	}
+
+	/// Fee, paid by every generated transaction.
+	pub fn fee(mut self, fee: u64) -> Self {
+		self.fee = fee;
+		self
+	}
+
+	/// Maximal number of non-coinbase transactions in every block.
+	/// Blocks contain less transactions, if there are not enough spendable outputs.
+	pub fn transactions_per_block(mut self, count: usize) -> Self {
+		self.transactions_per_block = count;
+		self
+	}
+
+	/// Number of outputs of every generated transaction.
+	pub fn outputs_per_transaction(mut self, count: usize) -> Self {
+		assert!(count != 0, "transaction must have at least one output");
+		self.outputs_per_transaction = count;
+		self
+	}
+
+	/// Generate P2WSH outputs, which are spent with witness. Blocks with witness transactions
+	/// contain the witness commitment. Segwit must be active on the verifying side.
+	pub fn segwit(mut self, segwit: bool) -> Self {
+		self.segwit = segwit;
+		self
+	}
+
+	/// Version of the generated headers.
+	pub fn version(mut self, version: u32) -> Self {
+		self.version = version;
+		self
+	}
+
+	/// Difficulty of the generated headers. Headers are mined to satisfy it.
+	pub fn bits(mut self, bits: Compact) -> Self {
+		self.bits = bits;
+		self
+	}
+
+	/// Schedules reorg: when canon chain reaches `height`, side chain is forked `depth` blocks
+	/// below it and `depth + 1` side chain blocks are generated, so it becomes the canon chain.
+	pub fn reorg_at(mut self, height: u32, depth: u32) -> Self {
+		assert!(depth != 0 && depth <= height, "reorg must replace at least one non-genesis block");
+		self.reorgs.insert(height, depth);
+		self
+	}
+
+	/// Genesis block.
+	pub fn genesis(&self) -> &Block {
+		&self.blocks[0]
+	}
+
+	/// Height of the canon chain.
+	pub fn height(&self) -> u32 {
+		self.blocks.len() as u32 - 1
+	}
+
+	/// Best block of the canon chain.
+	pub fn best_block(&self) -> &Block {
+		self.blocks.last().expect("there's always genesis block; qed")
+	}
+
+	/// Canon chain, starting with genesis block.
+	pub fn blocks(&self) -> &[Block] {
+		&self.blocks
+	}
+
+	/// Unspent outputs of the canon chain.
+	pub fn unspent_outputs(&self) -> &[SpendableOutput] {
+		self.outputs.last().expect("there's always genesis block; qed")
+	}
+
+	/// Unspent outputs, which can be spent in the next block.
+	pub fn spendable_outputs(&self) -> Vec<SpendableOutput> {
+		let height = self.height() + 1;
+		self.unspent_outputs().iter()
+			.filter(|output| !output.is_coinbase || height - output.height >= COINBASE_MATURITY)
+			.cloned()
+			.collect()
+	}
+
+	/// Generates `count` canon blocks, performing scheduled reorgs on the way.
+	/// Returns blocks in the order they should be inserted, including side chain blocks of reorgs.
+	pub fn generate(&mut self, count: u32) -> Vec<Block> {
+		let mut blocks = Vec::new();
+		for _ in 0..count {
+			let block = self.next_block();
+			self.push_block(block.clone());
+			blocks.push(block);
+
+			let height = self.height();
+			if let Some(depth) = self.reorgs.remove(&height) {
+				blocks.extend(self.reorg(depth));
+			}
+		}
+		blocks
+	}
+
+	/// Forks side chain `depth` blocks below the best block and generates `depth + 1` side chain blocks.
+	/// Side chain becomes the canon chain. Returns side chain blocks.
+	pub fn reorg(&mut self, depth: u32) -> Vec<Block> {
+		assert!(depth != 0 && depth <= self.height(), "reorg must replace at least one non-genesis block");
+		let ancestor = (self.height() - depth) as usize;
+		self.blocks.truncate(ancestor + 1);
+		self.outputs.truncate(ancestor + 1);
+		self.branch += 1;
+
+		(0..depth + 1).map(|_| {
+			let block = self.next_block();
+			self.push_block(block.clone());
+			block
+		}).collect()
+	}
+
+	/// Builds next block of the canon chain.
+	fn next_block(&self) -> Block {
+		let height = self.height() + 1;
+		let mut transactions = Vec::new();
+		let mut fees = 0;
+		for output in self.spendable_outputs().into_iter()
+			.filter(|output| output.value > self.fee)
+			.take(self.transactions_per_block) {
+			transactions.push(self.spending_transaction(&output));
+			fees += self.fee;
+		}
+
+		let mut coinbase = coinbase_transaction(height, self.branch, self.coinbase_value + fees, self.segwit);
+		if transactions.iter().any(Transaction::has_witness) {
+			let mut block_transactions = vec![coinbase.clone()];
+			block_transactions.extend(transactions.iter().cloned());
+			// witness hash of coinbase is always zero, so commitment doesn't depend on coinbase
+			let witness_merkle_root = Block::new(BlockHeader::default(), block_transactions).witness_merkle_root();
+			let reserved_value = [0u8; 32];
+			let mut commitment_data = witness_merkle_root.to_vec();
+			commitment_data.extend_from_slice(&reserved_value);
+			let mut commitment_script = WITNESS_COMMITMENT_HEADER.to_vec();
+			commitment_script.extend_from_slice(&*dhash256(&commitment_data));
+			coinbase.outputs.push(TransactionOutput {
+				value: 0,
+				script_pubkey: commitment_script.into(),
+			});
+			coinbase.inputs[0].script_witness = vec![reserved_value.to_vec().into()];
+		}
+
+		transactions.insert(0, coinbase);
+		let parent = self.best_block().hash();
+		self.seal(height, parent, transactions)
+	}
+
+	/// Builds transaction, spending the output.
+	fn spending_transaction(&self, output: &SpendableOutput) -> Transaction {
+		let outputs_count = self.outputs_per_transaction as u64;
+		let value = output.value - self.fee;
+		Transaction {
+			version: 1,
+			inputs: vec![TransactionInput {
+				previous_output: output.outpoint.clone(),
+				script_sig: Bytes::new(),
+				sequence: 0xffffffff,
+				script_witness: if output.is_witness { vec![witness_script()] } else { vec![] },
+			}],
+			outputs: (0..outputs_count).map(|index| TransactionOutput {
+				// the first output takes the remainder
+				value: value / outputs_count + if index == 0 { value % outputs_count } else { 0 },
+				script_pubkey: spendable_script(self.segwit),
+			}).collect(),
+			lock_time: 0,
+		}
+	}
+
+	/// Builds block header, satisfying configured difficulty.
+	fn seal(&self, height: u32, parent: H256, transactions: Vec<Transaction>) -> Block {
+		let mut block = Block::new(BlockHeader {
+			version: self.version,
+			previous_header_hash: parent,
+			merkle_root_hash: H256::default(),
+			time: GENESIS_TIME + height * BLOCK_INTERVAL,
+			bits: self.bits,
+			nonce: 0,
+		}, transactions);
+		block.block_header.merkle_root_hash = block.merkle_root();
+
+		let target: U256 = self.bits.into();
+		while U256::from(&*block.block_header.hash().reversed() as &[u8]) > target {
+			block.block_header.nonce += 1;
+		}
+		block
+	}
+
+	/// Appends block to the canon chain and updates unspent outputs.
+	fn push_block(&mut self, block: Block) {
+		let height = self.blocks.len() as u32;
+		let mut outputs = self.outputs.last().cloned().unwrap_or_default();
+		for transaction in &block.transactions {
+			let spent: Vec<_> = transaction.inputs.iter().map(|input| &input.previous_output).collect();
+			outputs.retain(|output| !spent.contains(&&output.outpoint));
+
+			let hash = transaction.hash();
+			let is_coinbase = transaction.is_coinbase();
+			outputs.extend(transaction.outputs.iter().enumerate()
+				.filter(|&(_, output)| output.value != 0)
+				.map(|(index, output)| SpendableOutput {
+					outpoint: OutPoint {
+						hash: hash.clone(),
+						index: index as u32,
+					},
+					value: output.value,
+					height: height,
+					is_coinbase: is_coinbase,
+					is_witness: output.script_pubkey == spendable_script(true),
+				}));
+		}
+
+		self.blocks.push(block);
+		self.outputs.push(outputs);
+	}
+}
+
+/// Coinbase transaction, unique for the height and the branch.
+fn coinbase_transaction(height: u32, branch: u32, value: u64, segwit: bool) -> Transaction {
+	let script_sig = ScriptBuilder::default()
+		.push_num(height.into())
+		.push_num(branch.into())
+		.into_bytes();
+
+	Transaction {
+		version: 1,
+		inputs: vec![TransactionInput {
+			previous_output: OutPoint::null(),
+			script_sig: script_sig,
+			sequence: 0xffffffff,
+			script_witness: vec![],
+		}],
+		outputs: vec![TransactionOutput {
+			value: value,
+			script_pubkey: spendable_script(segwit),
+		}],
+		lock_time: 0,
+	}
+}
+
+/// Witness script of P2WSH outputs: anyone can spend.
+fn witness_script() -> Bytes {
+	ScriptBuilder::default().push_opcode(Opcode::OP_1).into_bytes()
+}
+
+/// Output script, which can be spent without signature.
+fn spendable_script(segwit: bool) -> Bytes {
+	if segwit {
+		ScriptBuilder::default()
+			.push_opcode(Opcode::OP_0)
+			.push_data(&*sha256(&witness_script()))
+			.into_bytes()
+	} else {
+		witness_script()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ChainGenerator, COINBASE_MATURITY};
+
+	#[test]
+	fn generated_chain_is_linked() {
+		let mut generator = ChainGenerator::new();
+		let blocks = generator.generate(10);
+		assert_eq!(blocks.len(), 10);
+		assert_eq!(generator.height(), 10);
+		let mut parent = generator.genesis().hash();
+		for block in &blocks {
+			assert_eq!(block.header().previous_header_hash, parent);
+			assert_eq!(block.header().merkle_root_hash, block.merkle_root());
+			parent = block.hash();
+		}
+	}
+
+	#[test]
+	fn generated_transactions_spend_outputs() {
+		let mut generator = ChainGenerator::with_genesis_outputs(4, 100_000)
+			.transactions_per_block(2)
+			.outputs_per_transaction(3)
+			.fee(1_000);
+		let blocks = generator.generate(2);
+		assert_eq!(blocks[0].transactions.len(), 3);
+		assert_eq!(blocks[0].transactions[1].outputs.len(), 3);
+		assert_eq!(blocks[0].transactions[1].total_spends(), 99_000);
+		assert_eq!(blocks[0].transactions[0].total_spends(), 50 * 100_000_000 + 2_000);
+		// coinbase outputs are not mature yet
+		assert!(generator.spendable_outputs().iter().all(|output| !output.is_coinbase));
+
+		generator.generate(COINBASE_MATURITY);
+		assert!(generator.spendable_outputs().iter().any(|output| output.is_coinbase));
+	}
+
+	#[test]
+	fn generated_segwit_transactions() {
+		let mut generator = ChainGenerator::with_genesis_outputs(1, 100_000).segwit(true);
+		let blocks = generator.generate(2);
+		// genesis output is not segwit
+		assert!(!blocks[0].transactions[1].has_witness());
+		assert!(blocks[1].transactions[1].has_witness());
+		// coinbase has witness commitment and reserved value
+		assert_eq!(blocks[1].transactions[0].outputs.len(), 2);
+		assert_eq!(blocks[1].transactions[0].inputs[0].script_witness.len(), 1);
+	}
+
+	#[test]
+	fn generated_reorg() {
+		let mut generator = ChainGenerator::new().reorg_at(5, 2);
+		let blocks = generator.generate(10);
+		// 10 canon blocks + 3 side chain blocks, replacing 2 canon blocks
+		assert_eq!(blocks.len(), 13);
+		assert_eq!(generator.height(), 11);
+		assert_eq!(blocks[5].header().previous_header_hash, blocks[2].hash());
+		assert!(blocks[3] != blocks[5]);
+		assert_eq!(generator.blocks()[4], blocks[5]);
+	}
+}
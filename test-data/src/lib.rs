@@ -3,6 +3,7 @@
 extern crate time;
 
 extern crate chain;
+extern crate bitcrypto as crypto;
 extern crate primitives;
 extern crate serialization as ser;
 extern crate script;
@@ -10,10 +11,12 @@ extern crate script;
 use chain::Block;
 
 pub mod chain_builder;
+pub mod chain_generator;
 pub mod block;
 pub mod invoke;
 
 pub use chain_builder::{ChainBuilder, TransactionBuilder};
+pub use chain_generator::{ChainGenerator, SpendableOutput};
 pub use block::{block_builder, block_hash_builder, build_n_empty_blocks, build_n_empty_blocks_from_genesis, build_n_empty_blocks_from};
 
 pub fn block1() -> Block {
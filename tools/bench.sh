@@ -1,2 +1,2 @@
 #!/bin/bash
-cargo run --manifest-path ./bencher/Cargo.toml --release
+cargo run --manifest-path ./bencher/Cargo.toml --release --bin bencher
@@ -24,10 +24,12 @@ cargo clippy -p miner
 cargo clippy -p network
 cargo clippy -p p2p
 cargo clippy -p primitives
+cargo clippy -p psbt
 cargo clippy -p rpc
 cargo clippy -p script
 cargo clippy -p serialization
 cargo clippy -p sync
 cargo clippy -p test-data
 cargo clippy -p verification
+cargo clippy -p wallet
 
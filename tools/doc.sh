@@ -12,9 +12,11 @@ cargo doc --no-deps\
 	-p pbtc\
 	-p p2p\
 	-p primitives\
+	-p psbt\
 	-p rpc\
 	-p script\
 	-p serialization\
 	-p sync\
 	-p test-data\
-	-p verification
+	-p verification\
+	-p wallet
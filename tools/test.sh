@@ -12,10 +12,12 @@ cargo test\
 	-p pbtc\
 	-p p2p\
 	-p primitives\
+	-p psbt\
 	-p rpc\
 	-p script\
 	-p serialization\
 	-p serialization_derive\
 	-p sync\
 	-p test-data\
-	-p verification
+	-p verification\
+	-p wallet
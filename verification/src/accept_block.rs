@@ -1,8 +1,11 @@
-use network::{ConsensusParams, ConsensusFork};
+use std::sync::Arc;
+use network::{ConsensusParams, TransactionOrdering};
 use crypto::dhash256;
 use db::{TransactionOutputProvider, BlockHeaderProvider};
-use script;
-use ser::Stream;
+use chain::{IndexedBlock, Transaction, TransactionInput, TransactionOutput, OutPoint, merkle_root};
+use primitives::bytes::Bytes;
+use script::{self, Script, Builder, Opcode, verify_script, VerificationFlags, TransactionSignatureChecker, SignatureVersion};
+use ser::{Stream, Reader};
 use sigops::{transaction_sigops, transaction_sigops_cost}	;
 use work::block_reward_satoshi;
 use duplex_store::DuplexTransactionOutputProvider;
@@ -16,9 +19,11 @@ pub struct BlockAcceptor<'a> {
 	pub finality: BlockFinality<'a>,
 	pub serialized_size: BlockSerializedSize<'a>,
 	pub sigops: BlockSigops<'a>,
+	pub transaction_ordering: BlockTransactionOrdering<'a>,
 	pub coinbase_claim: BlockCoinbaseClaim<'a>,
 	pub coinbase_script: BlockCoinbaseScript<'a>,
 	pub witness: BlockWitness<'a>,
+	pub signet: BlockSignet<'a>,
 }
 
 impl<'a> BlockAcceptor<'a> {
@@ -27,6 +32,7 @@ impl<'a> BlockAcceptor<'a> {
 		consensus: &'a ConsensusParams,
 		block: CanonBlock<'a>,
 		height: u32,
+		median_time_past: u32,
 		deployments: &'a BlockDeployments<'a>,
 		headers: &'a BlockHeaderProvider,
 	) -> Self {
@@ -35,18 +41,22 @@ impl<'a> BlockAcceptor<'a> {
 			serialized_size: BlockSerializedSize::new(block, consensus, deployments, height),
 			coinbase_script: BlockCoinbaseScript::new(block, consensus, height),
 			coinbase_claim: BlockCoinbaseClaim::new(block, store, height),
-			sigops: BlockSigops::new(block, store, consensus, height),
+			sigops: BlockSigops::new(block, store, consensus, height, median_time_past),
+			transaction_ordering: BlockTransactionOrdering::new(block, consensus, median_time_past),
 			witness: BlockWitness::new(block, deployments),
+			signet: BlockSignet::new(block, consensus, height),
 		}
 	}
 
 	pub fn check(&self) -> Result<(), Error> {
 		self.finality.check()?;
 		self.sigops.check()?;
+		self.transaction_ordering.check()?;
 		self.serialized_size.check()?;
 		self.coinbase_claim.check()?;
 		self.coinbase_script.check()?;
 		self.witness.check()?;
+		self.signet.check()?;
 		Ok(())
 	}
 }
@@ -111,7 +121,7 @@ impl<'a> BlockSerializedSize<'a> {
 		// before SegWit: it is main check for size
 		// after SegWit: without witness data, block size should be <= 1_000_000
 		// after BitcoinCash fork: block size is increased to 8_000_000
-		// after SegWit2x fork: without witness data, block size should be <= 2_000_000
+		// after CustomLimits fork: without witness data, block size should be <= custom limit
 		if size < self.consensus.fork.min_block_size(self.height) ||
 			size > self.consensus.fork.max_block_size(self.height) {
 			return Err(Error::Size(size));
@@ -119,10 +129,10 @@ impl<'a> BlockSerializedSize<'a> {
 
 		// there's no need to define weight for pre-SegWit blocks
 		if self.segwit_active {
-			let size_with_witness = self.block.size_with_witness();
-			let weight = size * (ConsensusFork::witness_scale_factor() - 1) + size_with_witness;
-			if weight > self.consensus.fork.max_block_weight(self.height) {
-				return Err(Error::Weight);
+			let weight = self.block.weight();
+			let max_weight = self.consensus.fork.max_block_weight(self.height);
+			if weight > max_weight {
+				return Err(Error::ExceedsMaxBlockWeight { weight: weight, max_weight: max_weight });
 			}
 		}
 		Ok(())
@@ -135,11 +145,13 @@ pub struct BlockSigops<'a> {
 	consensus: &'a ConsensusParams,
 	height: u32,
 	bip16_active: bool,
+	sig_checks_active: bool,
 }
 
 impl<'a> BlockSigops<'a> {
-	fn new(block: CanonBlock<'a>, store: &'a TransactionOutputProvider, consensus: &'a ConsensusParams, height: u32) -> Self {
+	fn new(block: CanonBlock<'a>, store: &'a TransactionOutputProvider, consensus: &'a ConsensusParams, height: u32, median_time_past: u32) -> Self {
 		let bip16_active = block.header.raw.time >= consensus.bip16_time;
+		let sig_checks_active = consensus.fork.max_block_sig_checks(height, median_time_past).is_some();
 
 		BlockSigops {
 			block: block,
@@ -147,10 +159,16 @@ impl<'a> BlockSigops<'a> {
 			consensus: consensus,
 			height: height,
 			bip16_active: bip16_active,
+			sig_checks_active: sig_checks_active,
 		}
 	}
 
 	fn check(&self) -> Result<(), Error> {
+		// sigops are replaced with signature checks, which are verified along with transactions scripts
+		if self.sig_checks_active {
+			return Ok(());
+		}
+
 		let store = DuplexTransactionOutputProvider::new(self.store, &*self.block);
 		let (sigops, sigops_cost) = self.block.transactions.iter()
 			.map(|tx| {
@@ -164,7 +182,7 @@ impl<'a> BlockSigops<'a> {
 		// before SegWit: 20_000
 		// after SegWit: cost of sigops is sigops * 4 and max cost is 80_000 => max sigops is still 20_000
 		// after BitcoinCash fork: 20_000 sigops for each full/partial 1_000_000 bytes of block
-		// after SegWit2x fork: cost of sigops is sigops * 4 and max cost is 160_000 => max sigops is 40_000
+		// after CustomLimits fork: max sigops and sigops cost are scaled along with block size and weight limits
 		let size = self.block.size();
 		if sigops > self.consensus.fork.max_block_sigops(self.height, size) {
 			return Err(Error::MaximumSigops);
@@ -174,7 +192,7 @@ impl<'a> BlockSigops<'a> {
 		// before SegWit: no witnesses => cost is sigops * 4 and max cost is 80_000
 		// after SegWit: it is main check for sigops
 		// after BitcoinCash fork: no witnesses => cost is sigops * 4 and max cost depends on block size
-		// after SegWit2x: it is basic check for sigops, limits are increased
+		// after CustomLimits fork: it is basic check for sigops, limits are scaled
 		if sigops_cost > self.consensus.fork.max_block_sigops_cost(self.height, size) {
 			Err(Error::MaximumSigopsCost)
 		} else {
@@ -183,6 +201,38 @@ impl<'a> BlockSigops<'a> {
 	}
 }
 
+pub struct BlockTransactionOrdering<'a> {
+	block: CanonBlock<'a>,
+	ordering: TransactionOrdering,
+}
+
+impl<'a> BlockTransactionOrdering<'a> {
+	fn new(block: CanonBlock<'a>, consensus: &'a ConsensusParams, median_time_past: u32) -> Self {
+		BlockTransactionOrdering {
+			block: block,
+			ordering: consensus.fork.transaction_ordering(median_time_past),
+		}
+	}
+
+	fn check(&self) -> Result<(), Error> {
+		match self.ordering {
+			// topological order is verified when transactions inputs are looked up
+			TransactionOrdering::Topological => Ok(()),
+			TransactionOrdering::Canonical => {
+				let is_sorted = self.block.transactions.iter()
+					.skip(1)
+					.zip(self.block.transactions.iter().skip(2))
+					.all(|(prev, next)| prev.hash < next.hash);
+				if is_sorted {
+					Ok(())
+				} else {
+					Err(Error::NonCanonicalTransactionOrder)
+				}
+			},
+		}
+	}
+}
+
 pub struct BlockCoinbaseClaim<'a> {
 	block: CanonBlock<'a>,
 	store: &'a TransactionOutputProvider,
@@ -305,46 +355,233 @@ impl<'a> BlockWitness<'a> {
 			return Ok(());
 		}
 
-		// check witness from coinbase transaction
-		let mut has_witness = false;
-		if let Some(coinbase) = self.block.transactions.first() {
-			let commitment = coinbase.raw.outputs.iter().rev()
-				.find(|output| script::is_witness_commitment_script(&output.script_pubkey));
-			if let Some(commitment) = commitment {
-				let witness_merkle_root = self.block.raw().witness_merkle_root();
-				if coinbase.raw.inputs.get(0).map(|i| i.script_witness.len()).unwrap_or_default() != 1 ||
-					coinbase.raw.inputs[0].script_witness[0].len() != 32 {
-					return Err(Error::WitnessInvalidNonceSize);
-				}
+		check_witness_commitment(self.block.raw())
+	}
+}
 
-				let mut stream = Stream::new();
-				stream.append(&witness_merkle_root);
-				stream.append_slice(&coinbase.raw.inputs[0].script_witness[0]);
-				let hash_witness = dhash256(&stream.out());
+/// Checks that witness data of block transactions matches the coinbase witness commitment.
+/// Transactions with witness are only allowed in blocks with the commitment.
+pub fn check_witness_commitment(block: &IndexedBlock) -> Result<(), Error> {
+	// check witness from coinbase transaction
+	let mut has_witness = false;
+	if let Some(coinbase) = block.transactions.first() {
+		let commitment = coinbase.raw.outputs.iter().rev()
+			.find(|output| script::is_witness_commitment_script(&output.script_pubkey));
+		if let Some(commitment) = commitment {
+			let witness_merkle_root = block.witness_merkle_root();
+			if coinbase.raw.inputs.get(0).map(|i| i.script_witness.len()).unwrap_or_default() != 1 ||
+				coinbase.raw.inputs[0].script_witness[0].len() != 32 {
+				return Err(Error::WitnessInvalidNonceSize);
+			}
 
-				if hash_witness != commitment.script_pubkey[6..].into() {
-					return Err(Error::WitnessMerkleCommitmentMismatch);
-				}
+			let mut stream = Stream::new();
+			stream.append(&witness_merkle_root);
+			stream.append_slice(&coinbase.raw.inputs[0].script_witness[0]);
+			let hash_witness = dhash256(&stream.out());
 
-				has_witness = true;
+			if hash_witness != commitment.script_pubkey[6..].into() {
+				return Err(Error::WitnessMerkleCommitmentMismatch);
 			}
+
+			has_witness = true;
 		}
+	}
+
+	// witness commitment is required when block contains transactions with witness
+	if !has_witness && block.transactions.iter().any(|tx| tx.raw.has_witness()) {
+		return Err(Error::UnexpectedWitness);
+	}
+
+	Ok(())
+}
+
+/// Signet solution is pushed to the witness commitment output, prefixed with this header.
+const SIGNET_HEADER: [u8; 4] = [0xec, 0xc7, 0xda, 0xa2];
 
-		// witness commitment is required when block contains transactions with witness
-		if !has_witness && self.block.transactions.iter().any(|tx| tx.raw.has_witness()) {
-			return Err(Error::UnexpectedWitness);
+pub struct BlockSignet<'a> {
+	block: CanonBlock<'a>,
+	challenge: Option<&'a Bytes>,
+	height: u32,
+}
+
+impl<'a> BlockSignet<'a> {
+	fn new(block: CanonBlock<'a>, consensus: &'a ConsensusParams, height: u32) -> Self {
+		BlockSignet {
+			block: block,
+			challenge: consensus.signet_challenge.as_ref(),
+			height: height,
 		}
+	}
 
-		Ok(())
+	fn check(&self) -> Result<(), Error> {
+		// genesis block is not signed
+		let challenge = match self.challenge {
+			Some(challenge) if self.height != 0 => challenge,
+			_ => return Ok(()),
+		};
+
+		// signet block must have witness commitment, even if it has no solution
+		let coinbase = self.block.transactions.first().ok_or(Error::SignetSolution)?;
+		let commitment_index = coinbase.raw.outputs.iter()
+			.rposition(|output| script::is_witness_commitment_script(&output.script_pubkey))
+			.ok_or(Error::SignetSolution)?;
+
+		// solution is removed from the coinbase before it is committed to
+		let mut modified_coinbase = (*coinbase.raw).clone();
+		let solution = take_signet_solution(&mut modified_coinbase.outputs[commitment_index].script_pubkey);
+		let (script_sig, script_witness) = match solution {
+			Some(solution) => parse_signet_solution(&solution)?,
+			// empty solution is valid for trivial challenges (e.g. OP_TRUE)
+			None => (Bytes::default(), Vec::new()),
+		};
+
+		let mut hashes = vec![modified_coinbase.hash()];
+		hashes.extend(self.block.transactions.iter().skip(1).map(|tx| tx.hash.clone()));
+		let signet_merkle_root = merkle_root(&hashes);
+
+		let header = &self.block.header.raw;
+		let mut block_data = Stream::new();
+		block_data
+			.append(&header.version)
+			.append(&header.previous_header_hash)
+			.append(&signet_merkle_root)
+			.append(&header.time);
+
+		let to_spend = Transaction {
+			version: 0,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint::null(),
+				script_sig: Builder::default()
+					.push_opcode(Opcode::OP_0)
+					.push_data(&block_data.out())
+					.into_bytes(),
+				sequence: 0,
+				script_witness: vec![],
+			}],
+			outputs: vec![TransactionOutput {
+				value: 0,
+				script_pubkey: challenge.clone(),
+			}],
+			lock_time: 0,
+		};
+
+		let to_sign = Transaction {
+			version: 0,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint {
+					hash: to_spend.hash(),
+					index: 0,
+				},
+				script_sig: script_sig.clone(),
+				sequence: 0,
+				script_witness: script_witness.clone(),
+			}],
+			outputs: vec![TransactionOutput {
+				value: 0,
+				script_pubkey: Builder::default().push_opcode(Opcode::OP_RETURN).into_bytes(),
+			}],
+			lock_time: 0,
+		};
+
+		let checker = TransactionSignatureChecker {
+			signer: Arc::new(to_sign.into()),
+			input_index: 0,
+			input_amount: 0,
+		};
+
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_dersig(true)
+			.verify_nulldummy(true)
+			.verify_witness(true);
+
+		let script_sig: Script = script_sig.into();
+		let challenge: Script = challenge.clone().into();
+		verify_script(&script_sig, &challenge, &script_witness, &flags, &checker, SignatureVersion::Base)
+			.map_err(Error::SignetSignature)
+	}
+}
+
+/// Removes signet solution from the witness commitment script.
+/// Pushes are re-encoded, exactly like it is done by bitcoin core.
+fn take_signet_solution(commitment: &mut Bytes) -> Option<Bytes> {
+	let mut solution = None;
+	let mut replacement = Builder::default();
+	{
+		let script: Script = commitment.clone().into();
+		for instruction in script.iter() {
+			let instruction = match instruction {
+				Ok(instruction) => instruction,
+				Err(_) => break,
+			};
+
+			match instruction.data {
+				Some(data) if !data.is_empty() => {
+					if solution.is_none() && data.len() > SIGNET_HEADER.len() && &data[..SIGNET_HEADER.len()] == &SIGNET_HEADER[..] {
+						solution = Some(data[SIGNET_HEADER.len()..].to_vec().into());
+						replacement = replacement.push_data(&SIGNET_HEADER);
+					} else {
+						replacement = replacement.push_data(data);
+					}
+				},
+				_ => replacement = replacement.push_opcode(instruction.opcode),
+			}
+		}
+	}
+
+	if solution.is_some() {
+		*commitment = replacement.into_bytes();
+	}
+
+	solution
+}
+
+/// Signet solution is serialized script_sig, followed by serialized witness stack.
+fn parse_signet_solution(solution: &[u8]) -> Result<(Bytes, Vec<Bytes>), Error> {
+	let mut reader = Reader::new(solution);
+	let script_sig: Bytes = reader.read().map_err(|_| Error::SignetSolution)?;
+	let script_witness: Vec<Bytes> = reader.read_list().map_err(|_| Error::SignetSolution)?;
+	if !reader.is_finished() {
+		return Err(Error::SignetSolution);
 	}
+
+	Ok((script_sig, script_witness))
 }
 
 #[cfg(test)]
 mod tests {
 	extern crate test_data;
 
+	use chain::IndexedBlock;
+	use network::{Magic, ConsensusParams, ConsensusFork, BitcoinCashConsensusParams};
+	use primitives::bytes::Bytes;
+	use script::Error as SignatureError;
 	use {Error, CanonBlock};
-	use super::BlockCoinbaseScript;
+	use super::{BlockCoinbaseScript, BlockSignet, BlockTransactionOrdering};
+
+	const WITNESS_COMMITMENT: &'static str = "6a24aa21a9ed0000000000000000000000000000000000000000000000000000000000000000";
+
+	fn signet_block(commitment: Option<Bytes>) -> IndexedBlock {
+		let coinbase = test_data::block_builder().transaction().coinbase();
+		let coinbase = match commitment {
+			Some(commitment) => coinbase.output().value(0).script_pubkey_bytes(commitment).build(),
+			None => coinbase.output().value(0).build(),
+		};
+
+		coinbase.build()
+			.merkled_header().build()
+			.build()
+			.into()
+	}
+
+	fn check_signet(block: &IndexedBlock, challenge: &'static str) -> Result<(), Error> {
+		let challenge: Bytes = challenge.into();
+		BlockSignet {
+			block: CanonBlock::new(block),
+			challenge: Some(&challenge),
+			height: 1,
+		}.check()
+	}
 
 	#[test]
 	fn test_block_coinbase_script() {
@@ -374,4 +611,61 @@ mod tests {
 
 		assert_eq!(coinbase_script_validator2.check(), Err(Error::CoinbaseScript));
 	}
+
+	#[test]
+	fn test_block_signet_trivial_challenge() {
+		let block = signet_block(Some(WITNESS_COMMITMENT.into()));
+		// OP_TRUE challenge is satisfied by empty solution
+		assert_eq!(check_signet(&block, "51"), Ok(()));
+		// OP_FALSE challenge can't be satisfied
+		assert_eq!(check_signet(&block, "00"), Err(Error::SignetSignature(SignatureError::EvalFalse)));
+	}
+
+	#[test]
+	fn test_block_signet_solution() {
+		// solution: script_sig = OP_TRUE, empty witness
+		let commitment = format!("{}07ecc7daa2015100", WITNESS_COMMITMENT);
+		let block = signet_block(Some(commitment.parse().unwrap()));
+		assert_eq!(check_signet(&block, "51"), Ok(()));
+		// challenge OP_VERIFY is only satisfied by script_sig from the solution
+		assert_eq!(check_signet(&block, "6951"), Ok(()));
+		assert_eq!(check_signet(&signet_block(Some(WITNESS_COMMITMENT.into())), "6951"), Err(Error::SignetSignature(SignatureError::InvalidStackOperation)));
+	}
+
+	#[test]
+	fn test_block_signet_invalid_solution() {
+		// no witness commitment
+		assert_eq!(check_signet(&signet_block(None), "51"), Err(Error::SignetSolution));
+
+		// extra data after the solution
+		let commitment = format!("{}08ecc7daa201510000", WITNESS_COMMITMENT);
+		assert_eq!(check_signet(&signet_block(Some(commitment.parse().unwrap())), "51"), Err(Error::SignetSolution));
+
+		// truncated solution
+		let commitment = format!("{}06ecc7daa20151", WITNESS_COMMITMENT);
+		assert_eq!(check_signet(&signet_block(Some(commitment.parse().unwrap())), "51"), Err(Error::SignetSolution));
+	}
+
+	#[test]
+	fn test_block_transaction_ordering() {
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction().coinbase().build()
+			.transaction().lock_time(1).build()
+			.transaction().lock_time(2).build()
+			.transaction().lock_time(3).build()
+			.merkled_header().build()
+			.build()
+			.into();
+
+		let mut sorted = block.clone();
+		sorted.transactions[1..].sort_by(|a, b| a.hash.cmp(&b.hash));
+		let mut unsorted = sorted.clone();
+		unsorted.transactions.swap(1, 3);
+
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(Magic::Mainnet)));
+		assert_eq!(BlockTransactionOrdering::new(CanonBlock::new(&sorted), &consensus, 1542300000).check(), Ok(()));
+		assert_eq!(BlockTransactionOrdering::new(CanonBlock::new(&unsorted), &consensus, 1542300000).check(),
+			Err(Error::NonCanonicalTransactionOrder));
+		assert_eq!(BlockTransactionOrdering::new(CanonBlock::new(&unsorted), &consensus, 1542299999).check(), Ok(()));
+	}
 }
@@ -1,19 +1,23 @@
 use rayon::prelude::{IntoParallelRefIterator, IndexedParallelIterator, ParallelIterator};
 use db::Store;
 use network::ConsensusParams;
+use logs;
 use error::Error;
 use canon::CanonBlock;
 use accept_block::BlockAcceptor;
 use accept_header::HeaderAcceptor;
 use accept_transaction::TransactionAcceptor;
 use deployments::BlockDeployments;
+use script_queue::BlockScriptQueue;
 use duplex_store::DuplexTransactionOutputProvider;
+use timestamp::median_timestamp;
 use VerificationLevel;
 
 pub struct ChainAcceptor<'a> {
 	pub block: BlockAcceptor<'a>,
 	pub header: HeaderAcceptor<'a>,
 	pub transactions: Vec<TransactionAcceptor<'a>>,
+	max_block_sig_checks: Option<usize>,
 }
 
 impl<'a> ChainAcceptor<'a> {
@@ -21,9 +25,10 @@ impl<'a> ChainAcceptor<'a> {
 		trace!(target: "verification", "Block verification {}", block.hash().to_reversed_str());
 		let output_store = DuplexTransactionOutputProvider::new(store.as_transaction_output_provider(), block.raw());
 		let headers = store.as_block_header_provider();
+		let median_time_past = median_timestamp(&block.header.raw, headers);
 
 		ChainAcceptor {
-			block: BlockAcceptor::new(store.as_transaction_output_provider(), consensus, block, height, deployments, headers),
+			block: BlockAcceptor::new(store.as_transaction_output_provider(), consensus, block, height, median_time_past, deployments, headers),
 			header: HeaderAcceptor::new(headers, consensus, block.header(), height, deployments),
 			transactions: block.transactions()
 				.into_iter()
@@ -31,20 +36,24 @@ impl<'a> ChainAcceptor<'a> {
 				.map(|(tx_index, tx)| TransactionAcceptor::new(
 						store.as_transaction_meta_provider(),
 						output_store,
+						headers,
 						consensus,
 						tx,
 						verification_level,
 						block.hash(),
 						height,
 						block.header.raw.time,
+						median_time_past,
 						tx_index,
 						deployments,
 				))
 				.collect(),
+			max_block_sig_checks: consensus.fork.max_block_sig_checks(height, median_time_past),
 		}
 	}
 
 	pub fn check(&self) -> Result<(), Error> {
+		let _span = logs::span("accept");
 		try!(self.block.check());
 		try!(self.header.check());
 		try!(self.check_transactions());
@@ -54,7 +63,16 @@ impl<'a> ChainAcceptor<'a> {
 	fn check_transactions(&self) -> Result<(), Error> {
 		self.transactions.par_iter()
 			.enumerate()
-			.fold(|| Ok(()), |result, (index, tx)| result.and_then(|_| tx.check().map_err(|err| Error::Transaction(index, err))))
-			.reduce(|| Ok(()), |acc, check| acc.and(check))
+			.map(|(index, tx)| tx.check().map_err(|err| Error::Transaction(index, err)))
+			.reduce(|| Ok(()), |acc, check| acc.and(check))?;
+
+		// scripts of all transactions are verified by the single queue, so that large transactions
+		// are verified on all script threads along with the rest of the block
+		let sig_checks = BlockScriptQueue::new(self.transactions.iter().map(|tx| &tx.eval))?.verify()?;
+
+		match self.max_block_sig_checks {
+			Some(max_block_sig_checks) if sig_checks > max_block_sig_checks => Err(Error::MaximumSigChecks),
+			_ => Ok(()),
+		}
 	}
 }
@@ -10,6 +10,7 @@ pub struct HeaderAcceptor<'a> {
 	pub version: HeaderVersion<'a>,
 	pub work: HeaderWork<'a>,
 	pub median_timestamp: HeaderMedianTimestamp<'a>,
+	pub checkpoint: HeaderCheckpoint<'a>,
 }
 
 impl<'a> HeaderAcceptor<'a> {
@@ -24,10 +25,12 @@ impl<'a> HeaderAcceptor<'a> {
 			work: HeaderWork::new(header, store, height, consensus),
 			median_timestamp: HeaderMedianTimestamp::new(header, store, deployments),
 			version: HeaderVersion::new(header, height, consensus),
+			checkpoint: HeaderCheckpoint::new(header, height, consensus),
 		}
 	}
 
 	pub fn check(&self) -> Result<(), Error> {
+		try!(self.checkpoint.check());
 		try!(self.version.check());
 		try!(self.work.check());
 		try!(self.median_timestamp.check());
@@ -116,3 +119,26 @@ impl<'a> HeaderMedianTimestamp<'a> {
 		}
 	}
 }
+
+pub struct HeaderCheckpoint<'a> {
+	header: CanonHeader<'a>,
+	height: u32,
+	consensus: &'a ConsensusParams,
+}
+
+impl<'a> HeaderCheckpoint<'a> {
+	fn new(header: CanonHeader<'a>, height: u32, consensus: &'a ConsensusParams) -> Self {
+		HeaderCheckpoint {
+			header: header,
+			height: height,
+			consensus: consensus,
+		}
+	}
+
+	fn check(&self) -> Result<(), Error> {
+		match self.consensus.checkpoint(self.height) {
+			Some(hash) if *hash != self.header.hash => Err(Error::Checkpoint),
+			_ => Ok(()),
+		}
+	}
+}
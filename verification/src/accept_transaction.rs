@@ -1,8 +1,12 @@
+use std::cmp;
+use std::sync::Arc;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use primitives::hash::H256;
 use primitives::bytes::Bytes;
-use db::{TransactionMetaProvider, TransactionOutputProvider};
-use network::{ConsensusParams, ConsensusFork};
-use script::{Script, verify_script, VerificationFlags, TransactionSignatureChecker, TransactionInputSigner, SignatureVersion};
+use chain::constants::{SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_TYPE_FLAG, SEQUENCE_LOCKTIME_MASK};
+use db::{TransactionMetaProvider, TransactionOutputProvider, BlockHeaderProvider};
+use network::{ConsensusParams, ConsensusFork, TransactionOrdering};
+use script::{Script, verify_script_with_sig_checks, VerificationFlags, TransactionSignatureChecker, TransactionInputSigner, SignatureVersion};
 use duplex_store::DuplexTransactionOutputProvider;
 use deployments::BlockDeployments;
 use script::Builder;
@@ -10,13 +14,23 @@ use sigops::transaction_sigops;
 use canon::CanonTransaction;
 use constants::{COINBASE_MATURITY};
 use error::TransactionError;
+use timestamp::median_timestamp_inclusive;
+use logs;
 use VerificationLevel;
 
+/// Inputs of transactions, spending more outputs than this, are verified in parallel, in chunks of this size.
+pub const PARALLEL_INPUTS_CHUNK: usize = 64;
+
+/// Time-based relative lock-time is measured in units of 2^9 = 512 seconds (BIP68).
+const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
+
 pub struct TransactionAcceptor<'a> {
 	pub premature_witness: TransactionPrematureWitness<'a>,
 	pub bip30: TransactionBip30<'a>,
+	pub min_size: TransactionMinSize<'a>,
 	pub missing_inputs: TransactionMissingInputs<'a>,
 	pub maturity: TransactionMaturity<'a>,
+	pub sequence_locks: TransactionSequenceLocks<'a>,
 	pub overspent: TransactionOverspent<'a>,
 	pub double_spent: TransactionDoubleSpend<'a>,
 	pub return_replay_protection: TransactionReturnReplayProtection<'a>,
@@ -30,44 +44,59 @@ impl<'a> TransactionAcceptor<'a> {
 		// previous transaction outputs
 		// in case of block validation, that's database and currently processed block
 		output_store: DuplexTransactionOutputProvider<'a>,
+		headers: &'a BlockHeaderProvider,
 		consensus: &'a ConsensusParams,
 		transaction: CanonTransaction<'a>,
 		verification_level: VerificationLevel,
 		block_hash: &'a H256,
 		height: u32,
 		time: u32,
+		median_time_past: u32,
 		transaction_index: usize,
 		deployments: &'a BlockDeployments<'a>,
 	) -> Self {
 		trace!(target: "verification", "Tx verification {}", transaction.hash.to_reversed_str());
+		// with canonical ordering, transaction may spend outputs of any other block transaction
+		let spendable_index = match consensus.fork.transaction_ordering(median_time_past) {
+			TransactionOrdering::Topological => transaction_index,
+			TransactionOrdering::Canonical => usize::max_value(),
+		};
+
 		TransactionAcceptor {
 			premature_witness: TransactionPrematureWitness::new(transaction, deployments),
 			bip30: TransactionBip30::new_for_sync(transaction, meta_store, consensus, block_hash, height),
-			missing_inputs: TransactionMissingInputs::new(transaction, output_store, transaction_index),
+			min_size: TransactionMinSize::new(transaction, consensus, median_time_past),
+			missing_inputs: TransactionMissingInputs::new(transaction, output_store, spendable_index),
 			maturity: TransactionMaturity::new(transaction, meta_store, height),
+			sequence_locks: TransactionSequenceLocks::new(transaction, meta_store, headers, height, median_time_past, deployments),
 			overspent: TransactionOverspent::new(transaction, output_store),
 			double_spent: TransactionDoubleSpend::new(transaction, output_store),
 			return_replay_protection: TransactionReturnReplayProtection::new(transaction, consensus, height),
-			eval: TransactionEval::new(transaction, output_store, consensus, verification_level, height, time, deployments),
+			eval: TransactionEval::new(transaction, output_store, consensus, verification_level, height, time, median_time_past, deployments),
 		}
 	}
 
+	/// Transaction scripts are not verified here. Scripts of all block transactions are verified
+	/// together by the `BlockScriptQueue`.
 	pub fn check(&self) -> Result<(), TransactionError> {
 		try!(self.premature_witness.check());
 		try!(self.bip30.check());
+		try!(self.min_size.check());
 		try!(self.missing_inputs.check());
 		try!(self.maturity.check());
+		try!(self.sequence_locks.check());
 		try!(self.overspent.check());
 		try!(self.double_spent.check());
 		try!(self.return_replay_protection.check());
-		try!(self.eval.check());
 		Ok(())
 	}
 }
 
 pub struct MemoryPoolTransactionAcceptor<'a> {
+	pub min_size: TransactionMinSize<'a>,
 	pub missing_inputs: TransactionMissingInputs<'a>,
 	pub maturity: TransactionMaturity<'a>,
+	pub sequence_locks: TransactionSequenceLocks<'a>,
 	pub overspent: TransactionOverspent<'a>,
 	pub sigops: TransactionSigops<'a>,
 	pub double_spent: TransactionDoubleSpend<'a>,
@@ -81,31 +110,38 @@ impl<'a> MemoryPoolTransactionAcceptor<'a> {
 		meta_store: &'a TransactionMetaProvider,
 		// in case of memory pool it should be db and memory pool
 		output_store: DuplexTransactionOutputProvider<'a>,
+		headers: &'a BlockHeaderProvider,
 		consensus: &'a ConsensusParams,
 		transaction: CanonTransaction<'a>,
 		height: u32,
 		time: u32,
+		median_time_past: u32,
 		deployments: &'a BlockDeployments<'a>,
 	) -> Self {
 		trace!(target: "verification", "Mempool-Tx verification {}", transaction.hash.to_reversed_str());
 		let transaction_index = 0;
 		let max_block_sigops = consensus.fork.max_block_sigops(height, consensus.fork.max_block_size(height));
 		MemoryPoolTransactionAcceptor {
+			min_size: TransactionMinSize::new(transaction, consensus, median_time_past),
 			missing_inputs: TransactionMissingInputs::new(transaction, output_store, transaction_index),
 			maturity: TransactionMaturity::new(transaction, meta_store, height),
+			// outputs of memory pool transactions are treated as outputs of the next block transactions
+			sequence_locks: TransactionSequenceLocks::new(transaction, meta_store, headers, height, median_time_past, deployments),
 			overspent: TransactionOverspent::new(transaction, output_store),
-			sigops: TransactionSigops::new(transaction, output_store, consensus, max_block_sigops, time),
+			sigops: TransactionSigops::new(transaction, output_store, consensus, max_block_sigops, time, median_time_past),
 			double_spent: TransactionDoubleSpend::new(transaction, output_store),
 			return_replay_protection: TransactionReturnReplayProtection::new(transaction, consensus, height),
-			eval: TransactionEval::new(transaction, output_store, consensus, VerificationLevel::Full, height, time, deployments),
+			eval: TransactionEval::new(transaction, output_store, consensus, VerificationLevel::Full, height, time, median_time_past, deployments),
 		}
 	}
 
 	pub fn check(&self) -> Result<(), TransactionError> {
 		// Bip30 is not checked because we don't need to allow tx pool acceptance of an unspent duplicate.
 		// Tx pool validation is not strinctly a matter of consensus.
+		try!(self.min_size.check());
 		try!(self.missing_inputs.check());
 		try!(self.maturity.check());
+		try!(self.sequence_locks.check());
 		try!(self.overspent.check());
 		try!(self.sigops.check());
 		try!(self.double_spent.check());
@@ -157,6 +193,28 @@ impl<'a> TransactionBip30<'a> {
 	}
 }
 
+pub struct TransactionMinSize<'a> {
+	transaction: CanonTransaction<'a>,
+	min_size: usize,
+}
+
+impl<'a> TransactionMinSize<'a> {
+	fn new(transaction: CanonTransaction<'a>, consensus: &'a ConsensusParams, median_time_past: u32) -> Self {
+		TransactionMinSize {
+			transaction: transaction,
+			min_size: consensus.fork.min_transaction_size(median_time_past),
+		}
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		if self.transaction.raw.serialized_size() < self.min_size {
+			Err(TransactionError::MinSize)
+		} else {
+			Ok(())
+		}
+	}
+}
+
 pub struct TransactionMissingInputs<'a> {
 	transaction: CanonTransaction<'a>,
 	store: DuplexTransactionOutputProvider<'a>,
@@ -218,6 +276,82 @@ impl<'a> TransactionMaturity<'a> {
 	}
 }
 
+/// Relative lock-time of transaction inputs (BIP68)
+///
+/// Inputs of version 2 transactions may only be spent, when the output is old enough: either the
+/// number of blocks, or the time (in 512 seconds units, measured by median time past) since the block,
+/// which includes the spent transaction, is encoded in the input sequence number.
+/// Outputs of transactions, which are not found in the store, are treated as outputs of the
+/// currently verified block (or the next block, in case of memory pool).
+pub struct TransactionSequenceLocks<'a> {
+	transaction: CanonTransaction<'a>,
+	store: &'a TransactionMetaProvider,
+	headers: &'a BlockHeaderProvider,
+	height: u32,
+	median_time_past: u32,
+	csv_active: bool,
+}
+
+impl<'a> TransactionSequenceLocks<'a> {
+	fn new(
+		transaction: CanonTransaction<'a>,
+		store: &'a TransactionMetaProvider,
+		headers: &'a BlockHeaderProvider,
+		height: u32,
+		median_time_past: u32,
+		deployments: &'a BlockDeployments<'a>,
+	) -> Self {
+		TransactionSequenceLocks {
+			transaction: transaction,
+			store: store,
+			headers: headers,
+			height: height,
+			median_time_past: median_time_past,
+			csv_active: deployments.csv(),
+		}
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		if !self.csv_active || self.transaction.raw.version < 2 || self.transaction.raw.is_coinbase() {
+			return Ok(());
+		}
+
+		for (index, input) in self.transaction.raw.inputs.iter().enumerate() {
+			if input.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+				continue;
+			}
+
+			let coin_height = self.store.transaction_meta(&input.previous_output.hash)
+				.map_or(self.height, |meta| meta.height());
+			let lock = (input.sequence & SEQUENCE_LOCKTIME_MASK) as u64;
+			let is_locked = if input.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+				// time is measured from the median time past of the block, preceding the block with spent output
+				let coin_time = self.median_time_past_at(coin_height.saturating_sub(1)) as u64;
+				coin_time + (lock << SEQUENCE_LOCKTIME_GRANULARITY) > self.median_time_past as u64
+			} else {
+				coin_height as u64 + lock > self.height as u64
+			};
+
+			if is_locked {
+				return Err(TransactionError::SequenceLock(index));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns median time past of the block at given height (inclusive).
+	fn median_time_past_at(&self, height: u32) -> u32 {
+		if height + 1 == self.height {
+			return self.median_time_past;
+		}
+
+		self.headers.block_header(height.into())
+			.map(|header| median_timestamp_inclusive(header.hash(), self.headers))
+			.unwrap_or_default()
+	}
+}
+
 pub struct TransactionOverspent<'a> {
 	transaction: CanonTransaction<'a>,
 	store: DuplexTransactionOutputProvider<'a>,
@@ -256,20 +390,29 @@ pub struct TransactionSigops<'a> {
 	consensus_params: &'a ConsensusParams,
 	max_sigops: usize,
 	time: u32,
+	sig_checks_active: bool,
 }
 
 impl<'a> TransactionSigops<'a> {
-	fn new(transaction: CanonTransaction<'a>, store: DuplexTransactionOutputProvider<'a>, consensus_params: &'a ConsensusParams, max_sigops: usize, time: u32) -> Self {
+	fn new(transaction: CanonTransaction<'a>, store: DuplexTransactionOutputProvider<'a>, consensus_params: &'a ConsensusParams, max_sigops: usize, time: u32, median_time_past: u32) -> Self {
+		let sig_checks_active = consensus_params.fork.max_transaction_sig_checks(median_time_past).is_some();
+
 		TransactionSigops {
 			transaction: transaction,
 			store: store,
 			consensus_params: consensus_params,
 			max_sigops: max_sigops,
 			time: time,
+			sig_checks_active: sig_checks_active,
 		}
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
+		// sigops are replaced with signature checks, which are counted during script execution
+		if self.sig_checks_active {
+			return Ok(());
+		}
+
 		let bip16_active = self.time >= self.consensus_params.bip16_time;
 		let sigops = transaction_sigops(&self.transaction.raw, &self.store, bip16_active);
 		if sigops > self.max_sigops {
@@ -291,7 +434,9 @@ pub struct TransactionEval<'a> {
 	verify_dersig: bool,
 	verify_witness: bool,
 	verify_nulldummy: bool,
+	verify_taproot: bool,
 	signature_version: SignatureVersion,
+	max_sig_checks: Option<usize>,
 }
 
 impl<'a> TransactionEval<'a> {
@@ -302,23 +447,26 @@ impl<'a> TransactionEval<'a> {
 		verification_level: VerificationLevel,
 		height: u32,
 		time: u32,
+		median_time_past: u32,
 		deployments: &'a BlockDeployments,
 	) -> Self {
 		let verify_p2sh = time >= params.bip16_time;
 		let verify_strictenc = match params.fork {
-			ConsensusFork::BitcoinCash(fork_height) if height >= fork_height => true,
+			ConsensusFork::BitcoinCash(ref fork) if height >= fork.height => true,
 			_ => false,
 		};
 		let verify_locktime = height >= params.bip65_height;
 		let verify_dersig = height >= params.bip66_height;
 		let signature_version = match params.fork {
-			ConsensusFork::BitcoinCash(fork_height) if height >= fork_height => SignatureVersion::ForkId,
-			ConsensusFork::NoFork | ConsensusFork::BitcoinCash(_) | ConsensusFork::SegWit2x(_) => SignatureVersion::Base,
+			ConsensusFork::BitcoinCash(ref fork) if height >= fork.height => SignatureVersion::ForkId,
+			ConsensusFork::NoFork | ConsensusFork::BitcoinCash(_) | ConsensusFork::CustomLimits(_) => SignatureVersion::Base,
 		};
 
 		let verify_checksequence = deployments.csv();
 		let verify_witness = deployments.segwit();
 		let verify_nulldummy = verify_witness;
+		let verify_taproot = deployments.taproot();
+		let max_sig_checks = params.fork.max_transaction_sig_checks(median_time_past);
 
 		TransactionEval {
 			transaction: transaction,
@@ -331,53 +479,96 @@ impl<'a> TransactionEval<'a> {
 			verify_dersig: verify_dersig,
 			verify_witness: verify_witness,
 			verify_nulldummy: verify_nulldummy,
+			verify_taproot: verify_taproot,
 			signature_version: signature_version,
+			max_sig_checks: max_sig_checks,
 		}
 	}
 
-	fn check(&self) -> Result<(), TransactionError> {
+	/// Returns number of executed signature checks.
+	fn check(&self) -> Result<usize, TransactionError> {
+		let signer = match self.signer()? {
+			Some(signer) => signer,
+			None => return Ok(0),
+		};
+
+		let _span = logs::span("scripts");
+		let inputs = self.transaction.raw.inputs.len();
+		let chunks = (inputs + PARALLEL_INPUTS_CHUNK - 1) / PARALLEL_INPUTS_CHUNK;
+		let sig_checks = if chunks > 1 {
+			(0..chunks).into_par_iter()
+				.map(|chunk| self.check_inputs(&signer, chunk * PARALLEL_INPUTS_CHUNK, cmp::min((chunk + 1) * PARALLEL_INPUTS_CHUNK, inputs)))
+				.reduce(|| Ok(0), |acc, check| acc.and_then(|sig_checks| check.map(|other_sig_checks| sig_checks + other_sig_checks)))?
+		} else {
+			self.check_inputs(&signer, 0, inputs)?
+		};
+
+		self.check_sig_checks(sig_checks)
+	}
+
+	/// Returns signer of the transaction with outputs it spends, or `None` if transaction scripts are not verified.
+	/// Signer is shared by all inputs of the transaction, so that BIP341 hashes are computed once.
+	pub fn signer(&self) -> Result<Option<Arc<TransactionInputSigner>>, TransactionError> {
 		if self.verification_level == VerificationLevel::Header
 			|| self.verification_level == VerificationLevel::NoVerification {
-			return Ok(());
+			return Ok(None);
 		}
 
 		if self.transaction.raw.is_coinbase() {
-			return Ok(());
+			return Ok(None);
 		}
 
-		let signer: TransactionInputSigner = self.transaction.raw.clone().into();
+		// taproot signatures commit to all spent outputs
+		let spent_outputs = self.transaction.raw.inputs.iter()
+			.map(|input| self.store.transaction_output(&input.previous_output, usize::max_value())
+				.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone())))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let signer = TransactionInputSigner::from((*self.transaction.raw).clone()).with_spent_outputs(spent_outputs);
+		Ok(Some(Arc::new(signer)))
+	}
+
+	/// Checks total number of signature checks, executed by transaction scripts.
+	pub fn check_sig_checks(&self, sig_checks: usize) -> Result<usize, TransactionError> {
+		match self.max_sig_checks {
+			Some(max_sig_checks) if sig_checks > max_sig_checks => Err(TransactionError::MaxSigChecks),
+			_ => Ok(sig_checks),
+		}
+	}
 
+	/// Verifies scripts of inputs in `from..to` range. Returns number of executed signature checks.
+	pub fn check_inputs(&self, signer: &Arc<TransactionInputSigner>, from: usize, to: usize) -> Result<usize, TransactionError> {
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.clone(),
 			input_index: 0,
 			input_amount: 0,
 		};
 
-		for (index, input) in self.transaction.raw.inputs.iter().enumerate() {
-			let output = self.store.transaction_output(&input.previous_output, usize::max_value())
-				.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone()))?;
-
+		let flags = VerificationFlags::default()
+			.verify_p2sh(self.verify_p2sh)
+			.verify_strictenc(self.verify_strictenc)
+			.verify_locktime(self.verify_locktime)
+			.verify_checksequence(self.verify_checksequence)
+			.verify_dersig(self.verify_dersig)
+			.verify_nulldummy(self.verify_nulldummy)
+			.verify_witness(self.verify_witness)
+			.verify_taproot(self.verify_taproot);
+
+		let mut sig_checks = 0;
+		for (index, input) in self.transaction.raw.inputs.iter().enumerate().take(to).skip(from) {
+			let output = &signer.spent_outputs[index];
 			checker.input_index = index;
 			checker.input_amount = output.value;
 
 			let script_witness = &input.script_witness;
 			let input: Script = input.script_sig.clone().into();
-			let output: Script = output.script_pubkey.into();
-
-			let flags = VerificationFlags::default()
-				.verify_p2sh(self.verify_p2sh)
-				.verify_strictenc(self.verify_strictenc)
-				.verify_locktime(self.verify_locktime)
-				.verify_checksequence(self.verify_checksequence)
-				.verify_dersig(self.verify_dersig)
-				.verify_nulldummy(self.verify_nulldummy)
-				.verify_witness(self.verify_witness);
-
-			try!(verify_script(&input, &output, &script_witness, &flags, &checker, self.signature_version)
+			let output: Script = output.script_pubkey.clone().into();
+
+			try!(verify_script_with_sig_checks(&input, &output, &script_witness, &flags, &checker, self.signature_version, &mut sig_checks)
 				.map_err(|e| TransactionError::Signature(index, e)));
 		}
 
-		Ok(())
+		Ok(sig_checks)
 	}
 }
 
@@ -429,9 +620,9 @@ impl<'a> TransactionReturnReplayProtection<'a> {
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
-		if let ConsensusFork::BitcoinCash(fork_block) = self.consensus.fork {
+		if let ConsensusFork::BitcoinCash(ref fork) = self.consensus.fork {
 			// Transactions with such OP_RETURNs shall be considered valid again for block 530,001 and onwards
-			if self.height >= fork_block && self.height <= 530_000 {
+			if self.height >= fork.height && self.height <= 530_000 {
 				if (*self.transaction).raw.outputs.iter()
 					.any(|out| out.script_pubkey == *BITCOIN_CASH_RETURN_REPLAY_PROTECTION_SCRIPT) {
 					return Err(TransactionError::ReturnReplayProtection)
@@ -469,12 +660,70 @@ impl<'a> TransactionPrematureWitness<'a> {
 
 #[cfg(test)]
 mod tests {
-	use chain::{IndexedTransaction, Transaction, TransactionOutput};
-	use network::{Magic, ConsensusParams, ConsensusFork};
+	use primitives::hash::H256;
+	use primitives::bytes::Bytes;
+	use chain::{IndexedTransaction, Transaction, TransactionInput, TransactionOutput, OutPoint, BlockHeader};
+	use db::{TransactionMeta, TransactionMetaProvider, BlockHeaderProvider, BlockRef};
+	use network::{Magic, ConsensusParams, ConsensusFork, BitcoinCashConsensusParams};
 	use script::Builder;
 	use canon::CanonTransaction;
 	use error::TransactionError;
-	use super::TransactionReturnReplayProtection;
+	use super::{TransactionReturnReplayProtection, TransactionMinSize, TransactionSequenceLocks};
+
+	/// All transactions are confirmed at height 100, all blocks have the same timestamp.
+	struct TestStore;
+
+	impl TransactionMetaProvider for TestStore {
+		fn transaction_meta(&self, hash: &H256) -> Option<TransactionMeta> {
+			if hash == &H256::from(1) {
+				Some(TransactionMeta::new(100, 1))
+			} else {
+				None
+			}
+		}
+	}
+
+	impl BlockHeaderProvider for TestStore {
+		fn block_header_bytes(&self, _block_ref: BlockRef) -> Option<Bytes> {
+			None
+		}
+
+		fn block_header(&self, _block_ref: BlockRef) -> Option<BlockHeader> {
+			Some(BlockHeader {
+				version: 1,
+				previous_header_hash: H256::from(2),
+				merkle_root_hash: Default::default(),
+				time: 1_000_000,
+				bits: 0.into(),
+				nonce: 0,
+			})
+		}
+	}
+
+	fn sequence_locked_transaction(version: i32, previous_hash: u8, sequence: u32) -> IndexedTransaction {
+		Transaction {
+			version: version,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint { hash: H256::from(previous_hash), index: 0 },
+				script_sig: Default::default(),
+				sequence: sequence,
+				script_witness: vec![],
+			}],
+			outputs: vec![TransactionOutput::default()],
+			lock_time: 0,
+		}.into()
+	}
+
+	fn check_sequence_locks(transaction: &IndexedTransaction, height: u32, median_time_past: u32) -> Result<(), TransactionError> {
+		TransactionSequenceLocks {
+			transaction: CanonTransaction::new(transaction),
+			store: &TestStore,
+			headers: &TestStore,
+			height: height,
+			median_time_past: median_time_past,
+			csv_active: true,
+		}.check()
+	}
 
 	#[test]
 	fn return_replay_protection_works() {
@@ -492,7 +741,10 @@ mod tests {
 
 		assert_eq!(transaction.raw.outputs[0].script_pubkey.len(), 46 + 2);
 
-		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(100));
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams {
+			height: 100,
+			..BitcoinCashConsensusParams::new(Magic::Mainnet)
+		}));
 		let checker = TransactionReturnReplayProtection::new(CanonTransaction::new(&transaction), &consensus, 100);
 		assert_eq!(checker.check(), Err(TransactionError::ReturnReplayProtection));
 		let checker = TransactionReturnReplayProtection::new(CanonTransaction::new(&transaction), &consensus, 50);
@@ -502,4 +754,54 @@ mod tests {
 		let checker = TransactionReturnReplayProtection::new(CanonTransaction::new(&transaction), &consensus, 100);
 		assert_eq!(checker.check(), Ok(()));
 	}
+
+	#[test]
+	fn transaction_min_size_works() {
+		let transaction: IndexedTransaction = Transaction {
+			version: 1,
+			inputs: vec![],
+			outputs: vec![TransactionOutput {
+				value: 0,
+				script_pubkey: Builder::default().return_bytes(b"too small").into_bytes(),
+			}],
+			lock_time: 0,
+		}.into();
+
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(Magic::Mainnet)));
+		let checker = TransactionMinSize::new(CanonTransaction::new(&transaction), &consensus, 1542300000);
+		assert_eq!(checker.check(), Err(TransactionError::MinSize));
+		let checker = TransactionMinSize::new(CanonTransaction::new(&transaction), &consensus, 1542299999);
+		assert_eq!(checker.check(), Ok(()));
+
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		let checker = TransactionMinSize::new(CanonTransaction::new(&transaction), &consensus, 1542300000);
+		assert_eq!(checker.check(), Ok(()));
+	}
+
+	#[test]
+	fn transaction_sequence_locks_works() {
+		// output, confirmed at height 100, can be spent at height 110
+		let transaction = sequence_locked_transaction(2, 1, 10);
+		assert_eq!(check_sequence_locks(&transaction, 109, 0), Err(TransactionError::SequenceLock(0)));
+		assert_eq!(check_sequence_locks(&transaction, 110, 0), Ok(()));
+
+		// relative lock-time is ignored for version 1 transactions and when disable flag is set
+		let transaction = sequence_locked_transaction(1, 1, 10);
+		assert_eq!(check_sequence_locks(&transaction, 109, 0), Ok(()));
+		let transaction = sequence_locked_transaction(2, 1, 10 | (1 << 31));
+		assert_eq!(check_sequence_locks(&transaction, 109, 0), Ok(()));
+
+		// output, confirmed at height 100 (median time past 1_000_000), can be spent 2 * 512 seconds later
+		let transaction = sequence_locked_transaction(2, 1, 2 | (1 << 22));
+		assert_eq!(check_sequence_locks(&transaction, 200, 1_001_023), Err(TransactionError::SequenceLock(0)));
+		assert_eq!(check_sequence_locks(&transaction, 200, 1_001_024), Ok(()));
+
+		// unconfirmed outputs are treated as outputs of the verified block
+		let transaction = sequence_locked_transaction(2, 3, 0);
+		assert_eq!(check_sequence_locks(&transaction, 200, 0), Ok(()));
+		let transaction = sequence_locked_transaction(2, 3, 1);
+		assert_eq!(check_sequence_locks(&transaction, 200, 0), Err(TransactionError::SequenceLock(0)));
+		let transaction = sequence_locked_transaction(2, 3, 1 | (1 << 22));
+		assert_eq!(check_sequence_locks(&transaction, 200, 1_000_000), Err(TransactionError::SequenceLock(0)));
+	}
 }
@@ -0,0 +1,236 @@
+//! Consistency check of the stored canon chain.
+//!
+//! Blocks are re-read from the database, verified and cross-checked against indexes.
+//! Full check re-applies blocks to the chain state, which is rolled back in the
+//! database overlay, so the database itself is never modified.
+
+use std::{cmp, fmt};
+use hash::H256;
+use chain::IndexedBlock;
+use db::{CanonStore, ForkChain, BlockRef, SideChainOrigin};
+use network::ConsensusParams;
+use canon::CanonBlock;
+use verify_chain::ChainVerifier;
+use accept_chain::ChainAcceptor;
+use deployments::{Deployments, BlockDeployments};
+use error::Error;
+use VerificationLevel;
+
+/// Thoroughness of the check. Every level includes checks of all previous levels.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum CheckLevel {
+	/// Blocks can be read and are indexed under their hashes.
+	Read,
+	/// Blocks pass context-free verification.
+	Block,
+	/// Transactions index matches blocks contents.
+	Index,
+	/// Blocks pass full verification against the chain state they were applied to.
+	Full,
+}
+
+/// Reason of the block check failure.
+#[derive(Debug, PartialEq)]
+pub enum CheckError {
+	/// Block data is missing or can't be decoded.
+	MissingBlock,
+	/// Block hash doesn't match hash and number indexes.
+	BlockIndex,
+	/// Transaction is not indexed as a part of this block.
+	TransactionIndex(H256),
+	/// Block verification has failed.
+	Verification(Error),
+}
+
+impl fmt::Display for CheckError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CheckError::MissingBlock => write!(f, "block is missing or corrupted"),
+			CheckError::BlockIndex => write!(f, "block hash doesn't match block index"),
+			CheckError::TransactionIndex(ref hash) => write!(f, "transaction {} is not indexed", hash.to_reversed_str()),
+			CheckError::Verification(ref err) => write!(f, "verification failed: {:?}", err),
+		}
+	}
+}
+
+/// Canon chain block, which hasn't passed the check.
+#[derive(Debug, PartialEq)]
+pub struct BadBlock {
+	pub number: u32,
+	pub hash: H256,
+	pub error: CheckError,
+}
+
+/// Checks last `blocks` blocks of the canon chain (all unpruned blocks, if `blocks` is 0).
+/// Returns failed blocks, ordered by number. Genesis and pruned blocks are never checked.
+pub fn check_chain(store: &CanonStore, consensus: &ConsensusParams, blocks: u32, level: CheckLevel) -> Vec<BadBlock> {
+	let best_block = store.best_block();
+	let first_available = cmp::max(1, store.pruned_blocks());
+	let available_blocks = (best_block.number + 1).saturating_sub(first_available);
+	let first = if blocks == 0 || blocks > available_blocks {
+		first_available
+	} else {
+		best_block.number - blocks + 1
+	};
+
+	let current_time = ::time::get_time().sec as u32;
+	let mut bad_blocks = Vec::new();
+	// blocks are applied to the chain state, rolled back to the block before `first`, one by one,
+	// so that only the block, which is currently checked, is kept in memory
+	let mut fork = None;
+	if level == CheckLevel::Full {
+		match rollback_stored_blocks(store, first, best_block.number) {
+			Ok(rolled_back) => fork = Some(rolled_back),
+			Err(bad_block) => bad_blocks.push(bad_block),
+		}
+	}
+
+	let deployments = Deployments::new();
+	for number in first..best_block.number + 1 {
+		let accepted = check_stored_block(store, consensus, number, level, current_time)
+			.and_then(|block| match fork {
+				Some(ref fork) => accept_stored_block(&**fork, consensus, &deployments, number, &block),
+				None => Ok(()),
+			});
+
+		if let Err(bad_block) = accepted {
+			bad_blocks.push(bad_block);
+			// blocks, following the bad one, can't be applied to the chain state
+			fork = None;
+		}
+	}
+
+	bad_blocks.sort_by_key(|bad_block| bad_block.number);
+	bad_blocks
+}
+
+fn check_stored_block(store: &CanonStore, consensus: &ConsensusParams, number: u32, level: CheckLevel, current_time: u32) -> Result<IndexedBlock, BadBlock> {
+	let bad_block = |hash: &H256, error| BadBlock {
+		number: number,
+		hash: hash.clone(),
+		error: error,
+	};
+
+	let hash = store.block_hash(number).ok_or_else(|| bad_block(&H256::default(), CheckError::BlockIndex))?;
+	let block = store.indexed_block(BlockRef::Number(number)).ok_or_else(|| bad_block(&hash, CheckError::MissingBlock))?;
+	if *block.hash() != hash || store.block_number(&hash) != Some(number) {
+		return Err(bad_block(&hash, CheckError::BlockIndex));
+	}
+
+	if level >= CheckLevel::Block {
+		ChainVerifier::new(&block, consensus, current_time).check()
+			.map_err(|err| bad_block(&hash, CheckError::Verification(err)))?;
+	}
+
+	if level >= CheckLevel::Index {
+		for tx in &block.transactions {
+			let is_indexed = store.transaction_meta(&tx.hash).map_or(false, |meta| meta.height() == number)
+				&& store.contains_transaction(&tx.hash);
+			if !is_indexed {
+				return Err(bad_block(&hash, CheckError::TransactionIndex(tx.hash.clone())));
+			}
+		}
+	}
+
+	Ok(block)
+}
+
+/// Rolls chain state back to the block before `first` in the database overlay.
+fn rollback_stored_blocks<'a>(store: &'a CanonStore, first: u32, best: u32) -> Result<Box<ForkChain + 'a>, BadBlock> {
+	let origin = SideChainOrigin {
+		ancestor: first - 1,
+		canonized_route: Vec::new(),
+		decanonized_route: (first..best + 1).filter_map(|number| store.block_hash(number)).collect(),
+		block_number: best,
+	};
+	store.fork(origin).map_err(|err| BadBlock {
+		number: best,
+		hash: store.best_block().hash,
+		error: CheckError::Verification(Error::Database(err)),
+	})
+}
+
+/// Verifies block against the rolled back chain state and applies it again.
+fn accept_stored_block(fork: &ForkChain, consensus: &ConsensusParams, deployments: &Deployments, number: u32, block: &IndexedBlock) -> Result<(), BadBlock> {
+	let bad_block = |error| BadBlock {
+		number: number,
+		hash: block.hash().clone(),
+		error: CheckError::Verification(error),
+	};
+
+	{
+		let block_deployments = BlockDeployments::new(deployments, number, fork.store().as_block_header_provider(), consensus);
+		let acceptor = ChainAcceptor::new(fork.store(), consensus, VerificationLevel::Full, CanonBlock::new(block), number, &block_deployments);
+		acceptor.check().map_err(&bad_block)?;
+	}
+
+	fork.store().canonize(block.hash()).map_err(|err| bad_block(Error::Database(err)))
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::IndexedBlock;
+	use db::BlockChainDatabase;
+	use db::kv::{SharedMemoryDatabase, KeyValueDatabase, Transaction, Key, KeyValue};
+	use ser::serialize;
+	use network::{Magic, ConsensusParams, ConsensusFork};
+	use super::{check_chain, CheckLevel, CheckError, BadBlock};
+
+	fn open_test_chain(db: SharedMemoryDatabase) -> BlockChainDatabase<SharedMemoryDatabase> {
+		let store = BlockChainDatabase::open(db);
+		for block in vec![test_data::block_h0(), test_data::block_h1(), test_data::block_h2()] {
+			let block: IndexedBlock = block.into();
+			store.insert(block.clone()).unwrap();
+			store.canonize(block.hash()).unwrap();
+		}
+		store
+	}
+
+	#[test]
+	fn check_valid_chain() {
+		let store = open_test_chain(SharedMemoryDatabase::default());
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		assert_eq!(check_chain(&store, &consensus, 0, CheckLevel::Full), vec![]);
+		assert_eq!(check_chain(&store, &consensus, 1, CheckLevel::Full), vec![]);
+		// database is not modified by full check
+		assert_eq!(store.best_block().number, 2);
+	}
+
+	#[test]
+	fn check_chain_with_broken_transaction_index() {
+		let db = SharedMemoryDatabase::default();
+		let store = open_test_chain(db.clone());
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		let coinbase_hash = test_data::block_h1().transactions[0].hash();
+		let mut update = Transaction::new();
+		update.delete(Key::TransactionMeta(coinbase_hash.clone()));
+		db.write(update).unwrap();
+
+		assert_eq!(check_chain(&store, &consensus, 0, CheckLevel::Block), vec![]);
+		assert_eq!(check_chain(&store, &consensus, 0, CheckLevel::Index), vec![BadBlock {
+			number: 1,
+			hash: test_data::block_h1().hash(),
+			error: CheckError::TransactionIndex(coinbase_hash),
+		}]);
+		// block 2 is not checked
+		assert_eq!(check_chain(&store, &consensus, 1, CheckLevel::Index), vec![]);
+	}
+
+	#[test]
+	fn check_pruned_chain() {
+		let db = SharedMemoryDatabase::default();
+		let store = open_test_chain(db.clone());
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		let mut update = Transaction::new();
+		update.insert(KeyValue::Meta("pruned_blocks", serialize(&2u32)));
+		update.delete(Key::BlockTransactions(test_data::block_h1().hash()));
+		db.write(update).unwrap();
+
+		// pruned block 1 is not checked, even if more blocks are requested
+		assert_eq!(check_chain(&store, &consensus, 0, CheckLevel::Full), vec![]);
+		assert_eq!(check_chain(&store, &consensus, 3, CheckLevel::Full), vec![]);
+		assert_eq!(store.best_block().number, 2);
+	}
+}
@@ -1,9 +1,11 @@
 //! Bitcoin chain verifier
 
+use std::sync::Arc;
 use hash::H256;
-use chain::{IndexedBlock, IndexedBlockHeader, BlockHeader, Transaction};
-use db::{SharedStore, TransactionOutputProvider, BlockHeaderProvider, BlockOrigin};
+use chain::{IndexedBlock, IndexedBlockHeader, BlockHeader, IndexedTransaction};
+use db::{SharedStore, TransactionOutputProvider, BlockHeaderProvider, BlockOrigin, Error as DBError};
 use network::ConsensusParams;
+use logs;
 use error::{Error, TransactionError};
 use canon::{CanonBlock, CanonTransaction};
 use duplex_store::{DuplexTransactionOutputProvider, NoopStore};
@@ -13,12 +15,14 @@ use verify_transaction::MemoryPoolTransactionVerifier;
 use accept_chain::ChainAcceptor;
 use accept_transaction::MemoryPoolTransactionAcceptor;
 use deployments::{Deployments, BlockDeployments};
+use timestamp::{median_timestamp_inclusive, TimeSource, SystemTime};
 use {Verify, VerificationLevel};
 
 pub struct BackwardsCompatibleChainVerifier {
 	store: SharedStore,
 	consensus: ConsensusParams,
 	deployments: Deployments,
+	time_source: Arc<TimeSource>,
 }
 
 impl BackwardsCompatibleChainVerifier {
@@ -27,18 +31,34 @@ impl BackwardsCompatibleChainVerifier {
 			store: store,
 			consensus: consensus,
 			deployments: Deployments::new(),
+			time_source: Arc::new(SystemTime),
 		}
 	}
 
+	/// Use given source of the current time instead of the local system clock
+	pub fn with_time_source(mut self, time_source: Arc<TimeSource>) -> Self {
+		self.time_source = time_source;
+		self
+	}
+
+	/// Returns current time, which is used to verify blocks and transactions
+	pub fn current_time(&self) -> u32 {
+		self.time_source.now()
+	}
+
 	fn verify_block(&self, verification_level: VerificationLevel, block: &IndexedBlock) -> Result<(), Error> {
 		if verification_level == VerificationLevel::NoVerification {
 			return Ok(());
 		}
 
-		let current_time = ::time::get_time().sec as u32;
+		let _span = logs::span("verify_block");
+		let current_time = self.current_time();
 		// first run pre-verification
-		let chain_verifier = ChainVerifier::new(block, self.consensus.network, current_time);
-		chain_verifier.check()?;
+		{
+			let _span = logs::span("pre_verify");
+			let chain_verifier = ChainVerifier::new(block, &self.consensus, current_time);
+			chain_verifier.check()?;
+		}
 
 		assert_eq!(Some(self.store.best_block().hash), self.store.block_hash(self.store.best_block().number));
 		let block_origin = self.store.block_origin(&block.header)?;
@@ -79,6 +99,26 @@ impl BackwardsCompatibleChainVerifier {
 		Ok(())
 	}
 
+	/// Verifies block proposal (BIP23): block, which is built on top of the best block, but
+	/// has no valid proof of work yet. Block is not inserted into the database.
+	pub fn verify_block_proposal(&self, block: &IndexedBlock) -> Result<(), Error> {
+		let current_time = self.current_time();
+		let chain_verifier = ChainVerifier::new(block, &self.consensus, current_time);
+		chain_verifier.check_without_proof_of_work()?;
+
+		let best_block = self.store.best_block();
+		if block.header.raw.previous_header_hash != best_block.hash {
+			return Err(DBError::UnknownParent.into());
+		}
+
+		let block_number = best_block.number + 1;
+		let header_provider = self.store.as_store().as_block_header_provider();
+		let deployments = BlockDeployments::new(&self.deployments, block_number, header_provider, &self.consensus);
+		let canon_block = CanonBlock::new(block);
+		let chain_acceptor = ChainAcceptor::new(self.store.as_store(), &self.consensus, VerificationLevel::Full, canon_block, block_number, &deployments);
+		chain_acceptor.check()
+	}
+
 	pub fn verify_block_header(
 		&self,
 		_block_header_provider: &BlockHeaderProvider,
@@ -87,9 +127,9 @@ impl BackwardsCompatibleChainVerifier {
 	) -> Result<(), Error> {
 		// let's do only preverifcation
 		// TODO: full verification
-		let current_time = ::time::get_time().sec as u32;
+		let current_time = self.current_time();
 		let header = IndexedBlockHeader::new(hash.clone(), header.clone());
-		let header_verifier = HeaderVerifier::new(&header, self.consensus.network, current_time);
+		let header_verifier = HeaderVerifier::new(&header, &self.consensus, current_time);
 		header_verifier.check()
 	}
 
@@ -99,25 +139,34 @@ impl BackwardsCompatibleChainVerifier {
 		prevout_provider: &T,
 		height: u32,
 		time: u32,
-		transaction: &Transaction,
+		transaction: &IndexedTransaction,
 	) -> Result<(), TransactionError> where T: TransactionOutputProvider {
-		let indexed_tx = transaction.clone().into();
+		// transaction is going to be included in the block, which follows current best block
+		let median_time_past = match height {
+			0 => 0,
+			_ => block_header_provider.block_header((height - 1).into())
+				.map(|best_header| median_timestamp_inclusive(best_header.hash(), block_header_provider))
+				.unwrap_or_default(),
+		};
+
 		// let's do preverification first
 		let deployments = BlockDeployments::new(&self.deployments, height, block_header_provider, &self.consensus);
-		let tx_verifier = MemoryPoolTransactionVerifier::new(&indexed_tx, &self.consensus, &deployments);
+		let tx_verifier = MemoryPoolTransactionVerifier::new(transaction, &self.consensus, &deployments, height, time, median_time_past);
 		try!(tx_verifier.check());
 
-		let canon_tx = CanonTransaction::new(&indexed_tx);
+		let canon_tx = CanonTransaction::new(transaction);
 		// now let's do full verification
 		let noop = NoopStore;
 		let output_store = DuplexTransactionOutputProvider::new(prevout_provider, &noop);
 		let tx_acceptor = MemoryPoolTransactionAcceptor::new(
 			self.store.as_transaction_meta_provider(),
 			output_store,
+			block_header_provider,
 			&self.consensus,
 			canon_tx,
 			height,
 			time,
+			median_time_past,
 			&deployments,
 		);
 		tx_acceptor.check()
@@ -142,12 +191,65 @@ mod tests {
 	extern crate test_data;
 
 	use std::sync::Arc;
-	use chain::IndexedBlock;
-	use db::{BlockChainDatabase, Error as DBError};
+	use chain::{IndexedBlock, IndexedTransaction, Transaction};
+	use db::{BlockChainDatabase, BlockChain, BlockOrigin, ForkChain, Error as DBError};
 	use network::{Magic, ConsensusParams, ConsensusFork};
 	use script;
+	use self::test_data::ChainGenerator;
 	use super::BackwardsCompatibleChainVerifier as ChainVerifier;
-	use {Verify, Error, TransactionError, VerificationLevel};
+	use {Verify, Error, TransactionError, VerificationLevel, TimeSource};
+
+	struct FixedTime(u32);
+
+	impl TimeSource for FixedTime {
+		fn now(&self) -> u32 {
+			self.0
+		}
+	}
+
+	/// Verifies generated blocks and inserts them into the storage, following reorgs of the generator.
+	fn verify_generated_chain(generator: &mut ChainGenerator, count: u32, consensus: ConsensusParams) {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]));
+		let verifier = ChainVerifier::new(storage.clone(), consensus);
+		for block in generator.generate(count) {
+			let block: IndexedBlock = block.into();
+			assert_eq!(verifier.verify(VerificationLevel::Full, &block), Ok(()));
+			match storage.block_origin(&block.header).unwrap() {
+				BlockOrigin::KnownBlock => unreachable!(),
+				BlockOrigin::CanonChain { .. } => {
+					storage.insert(block.clone()).unwrap();
+					storage.canonize(block.hash()).unwrap();
+				},
+				BlockOrigin::SideChain(_) => storage.insert(block).unwrap(),
+				BlockOrigin::SideChainBecomingCanonChain(origin) => {
+					let fork = storage.fork(origin).unwrap();
+					fork.store().insert(block.clone()).unwrap();
+					fork.store().canonize(block.hash()).unwrap();
+					storage.switch_to_fork(fork).unwrap();
+				},
+			}
+		}
+		assert_eq!(storage.best_block().hash, generator.best_block().hash());
+	}
+
+	#[test]
+	fn verify_generated_chain_with_reorg() {
+		// long enough to spend mature coinbase outputs
+		let mut generator = ChainGenerator::with_genesis_outputs(4, 1_000_000)
+			.transactions_per_block(3)
+			.reorg_at(20, 3);
+		verify_generated_chain(&mut generator, 110, ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork));
+	}
+
+	#[test]
+	fn verify_generated_segwit_chain() {
+		let mut consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+		consensus.segwit_deployment.as_mut().unwrap().activation = Some(0);
+		let mut generator = ChainGenerator::with_genesis_outputs(4, 1_000_000)
+			.transactions_per_block(2)
+			.segwit(true);
+		verify_generated_chain(&mut generator, 10, consensus);
+	}
 
 	#[test]
 	fn verify_orphan() {
@@ -165,6 +267,74 @@ mod tests {
 		assert!(verifier.verify(VerificationLevel::Full, &b1.into()).is_ok());
 	}
 
+	#[test]
+	fn verify_uses_time_source() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let b1 = test_data::block_h1();
+		let b1_time = b1.block_header.time;
+		let verifier = ChainVerifier::new(storage.clone(), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork))
+			.with_time_source(Arc::new(FixedTime(b1_time - 3 * 60 * 60)));
+		assert_eq!(Err(Error::FuturisticTimestamp), verifier.verify(VerificationLevel::Full, &b1.clone().into()));
+
+		let verifier = ChainVerifier::new(storage, ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork))
+			.with_time_source(Arc::new(FixedTime(b1_time)));
+		assert_eq!(verifier.current_time(), b1_time);
+		assert!(verifier.verify(VerificationLevel::Full, &b1.into()).is_ok());
+	}
+
+	#[test]
+	fn mempool_transaction_lock_time_uses_median_time_past() {
+		let genesis_time = 1_500_000_000;
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(1).build()
+				.build()
+			.transaction()
+				.output().value(50).build()
+				.build()
+			.merkled_header().time(genesis_time).build()
+			.build();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]));
+
+		// lock-time is after median time past, but before current time
+		let mut transaction: Transaction = test_data::TransactionBuilder::with_version(1)
+			.add_input(&genesis.transactions()[1], 0)
+			.add_output(10)
+			.lock()
+			.into();
+		transaction.lock_time = genesis_time + 100;
+		let transaction: IndexedTransaction = transaction.into();
+		let time = genesis_time + 200;
+
+		let verify = |csv_activation: u32, time: u32| {
+			let mut consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+			consensus.csv_deployment.as_mut().unwrap().activation = Some(csv_activation);
+			let verifier = ChainVerifier::new(storage.clone(), consensus);
+			verifier.verify_mempool_transaction(&*storage, &*storage, 1, time, &transaction)
+		};
+
+		// CSV isn't active in the next block => current time is used
+		assert!(verify(2, time).is_ok());
+		assert_eq!(verify(2, genesis_time + 50), Err(TransactionError::NonFinal));
+		// CSV is active in the next block => median time past is used
+		assert_eq!(verify(1, time), Err(TransactionError::NonFinal));
+	}
+
+	#[test]
+	fn verify_checkpoint() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let b1 = test_data::block_h1();
+		let mut consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+		consensus.checkpoints.push((1, test_data::block_h2().hash()));
+		let verifier = ChainVerifier::new(storage.clone(), consensus);
+		assert_eq!(Err(Error::Checkpoint), verifier.verify(VerificationLevel::Full, &b1.clone().into()));
+
+		let mut consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+		consensus.checkpoints.push((1, b1.hash()));
+		let verifier = ChainVerifier::new(storage, consensus);
+		assert!(verifier.verify(VerificationLevel::Full, &b1.into()).is_ok());
+	}
 
 	#[test]
 	fn first_tx() {
@@ -362,6 +532,46 @@ mod tests {
 		assert!(verifier.verify(VerificationLevel::Full, &block.into()).is_ok());
 	}
 
+	#[test]
+	fn invalid_script_of_block_transaction() {
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(1).build()
+				.build()
+			.transaction()
+				.output().value(50).build()
+				.output().value(50).script_pubkey("00").build()
+				.build()
+			.merkled_header().build()
+			.build();
+
+		let storage = BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]);
+		let reference_tx = genesis.transactions()[1].hash();
+
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(2).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx.clone()).index(0).build()
+				.output().value(40).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx).index(1).build()
+				.output().value(40).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build()
+			.into();
+
+		let verifier = ChainVerifier::new(Arc::new(storage), ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork));
+		let expected = Err(Error::Transaction(2, TransactionError::Signature(0, script::Error::EvalFalse)));
+		assert_eq!(expected, verifier.verify(VerificationLevel::Full, &block));
+		assert_eq!(Ok(()), verifier.verify(VerificationLevel::Header, &block));
+	}
+
 	#[test]
 	fn absoulte_sigops_overflow_block() {
 		let genesis = test_data::block_builder()
@@ -6,12 +6,18 @@ use hash::H256;
 use db::{BlockHeaderProvider, BlockRef, BlockAncestors, BlockIterator};
 use timestamp::median_timestamp;
 
+/// BIP9 deployment state
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum ThresholdState {
+pub enum ThresholdState {
+	/// Deployment start time is not yet reached
 	Defined,
+	/// Blocks are signalling the deployment
 	Started,
+	/// Deployment will become active after the current period
 	LockedIn,
+	/// Deployment rules are enforced
 	Active,
+	/// Deployment has timed out
 	Failed,
 }
 
@@ -29,7 +35,7 @@ impl ThresholdState {
 		}
 	}
 
-	fn is_active(&self) -> bool {
+	pub fn is_active(&self) -> bool {
 		match *self {
 			ThresholdState::Active => true,
 			_ => false,
@@ -49,7 +55,7 @@ struct DeploymentState {
 }
 
 /// Last known deployment states
-type DeploymentStateCache = HashMap<&'static str, DeploymentState>;
+type DeploymentStateCache = HashMap<String, DeploymentState>;
 
 #[derive(Default, Debug)]
 pub struct Deployments {
@@ -68,24 +74,37 @@ impl Deployments {
 		Deployments::default()
 	}
 
+	/// Returns threshold state of given deployment at given block
+	pub fn threshold_state(&self, deployment: &Deployment, number: u32, headers: &BlockHeaderProvider, consensus: &ConsensusParams) -> ThresholdState {
+		let threshold = deployment.threshold.unwrap_or(consensus.rule_change_activation_threshold);
+		let mut cache = self.cache.lock();
+		threshold_state(&mut cache, &deployment, number, headers, consensus.miner_confirmation_window, threshold)
+	}
+
 	/// Returns true if csv deployment is active
 	pub fn csv(&self, number: u32, headers: &BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
-		match consensus.csv_deployment {
-			Some(csv) => {
-				let mut cache = self.cache.lock();
-				threshold_state(&mut cache, csv, number, headers, consensus.miner_confirmation_window, consensus.rule_change_activation_threshold).is_active()
-			},
-			None => false
-		}
+		self.is_active(consensus.csv_deployment.as_ref(), number, headers, consensus)
 	}
 
 	/// Returns true if SegWit deployment is active
 	pub fn segwit(&self, number: u32, headers: &BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
-		match consensus.segwit_deployment {
-			Some(segwit) => {
-				let mut cache = self.cache.lock();
-				threshold_state(&mut cache, segwit, number, headers, consensus.miner_confirmation_window, consensus.rule_change_activation_threshold).is_active()
-			},
+		self.is_active(consensus.segwit_deployment.as_ref(), number, headers, consensus)
+	}
+
+	/// Returns true if Taproot deployment is active
+	pub fn taproot(&self, number: u32, headers: &BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		self.is_active(consensus.taproot_deployment.as_ref(), number, headers, consensus)
+	}
+
+	/// Returns true if deployment, defined by the node configuration, is active
+	pub fn custom(&self, name: &str, number: u32, headers: &BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		let deployment = consensus.custom_deployments.iter().find(|deployment| deployment.name == name);
+		self.is_active(deployment, number, headers, consensus)
+	}
+
+	fn is_active(&self, deployment: Option<&Deployment>, number: u32, headers: &BlockHeaderProvider, consensus: &ConsensusParams) -> bool {
+		match deployment {
+			Some(deployment) => self.threshold_state(deployment, number, headers, consensus).is_active(),
 			None => false
 		}
 	}
@@ -108,10 +127,18 @@ impl<'a> BlockDeployments<'a> {
 	pub fn segwit(&self) -> bool {
 		self.deployments.segwit(self.number, self.headers, self.consensus)
 	}
+
+	pub fn taproot(&self) -> bool {
+		self.deployments.taproot(self.number, self.headers, self.consensus)
+	}
+
+	pub fn custom(&self, name: &str) -> bool {
+		self.deployments.custom(name, self.number, self.headers, self.consensus)
+	}
 }
 
 /// Calculates threshold state of given deployment
-fn threshold_state(cache: &mut DeploymentStateCache, deployment: Deployment, number: u32, headers: &BlockHeaderProvider, miner_confirmation_window: u32, rule_change_activation_threshold: u32) -> ThresholdState {
+fn threshold_state(cache: &mut DeploymentStateCache, deployment: &Deployment, number: u32, headers: &BlockHeaderProvider, miner_confirmation_window: u32, rule_change_activation_threshold: u32) -> ThresholdState {
 	// deployments are checked using previous block index
 	if let Some(activation) = deployment.activation {
 		if activation <= number {
@@ -134,7 +161,7 @@ fn threshold_state(cache: &mut DeploymentStateCache, deployment: Deployment, num
 		None => return ThresholdState::Defined,
 	};
 
-	match cache.entry(deployment.name) {
+	match cache.entry(deployment.name.clone()) {
 		// by checking hash, we make sure we are on the same branch
 		Entry::Occupied(ref entry) if entry.get().block_number == number && entry.get().block_hash == hash => {
 			entry.get().state
@@ -174,7 +201,7 @@ fn first_of_the_period(block: u32, miner_confirmation_window: u32) -> u32 {
 	}
 }
 
-fn count_deployment_matches(block_number: u32, blocks: &BlockHeaderProvider, deployment: Deployment, window: u32) -> usize {
+fn count_deployment_matches(block_number: u32, blocks: &BlockHeaderProvider, deployment: &Deployment, window: u32) -> usize {
 	BlockAncestors::new(BlockRef::Number(block_number), blocks)
 		.take(window as usize)
 		.filter(|header| deployment.matches(header.version))
@@ -182,7 +209,7 @@ fn count_deployment_matches(block_number: u32, blocks: &BlockHeaderProvider, dep
 }
 
 struct ThresholdIterator<'a> {
-	deployment: Deployment,
+	deployment: &'a Deployment,
 	block_iterator: BlockIterator<'a>,
 	headers: &'a BlockHeaderProvider,
 	miner_confirmation_window: u32,
@@ -191,7 +218,7 @@ struct ThresholdIterator<'a> {
 }
 
 impl<'a> ThresholdIterator<'a> {
-	fn new(deployment: Deployment, headers: &'a BlockHeaderProvider, to_check: u32, miner_confirmation_window: u32, rule_change_activation_threshold: u32, state: ThresholdState) -> Self {
+	fn new(deployment: &'a Deployment, headers: &'a BlockHeaderProvider, to_check: u32, miner_confirmation_window: u32, rule_change_activation_threshold: u32, state: ThresholdState) -> Self {
 		ThresholdIterator {
 			deployment: deployment,
 			block_iterator: BlockIterator::new(to_check, miner_confirmation_window, headers),
@@ -233,7 +260,10 @@ impl<'a> Iterator for ThresholdIterator<'a> {
 				}
 			},
 			ThresholdState::LockedIn => {
-				self.last_state = ThresholdState::Active;
+				// block_number is the last block of the previous period
+				if block_number + 1 >= self.deployment.min_activation_height {
+					self.last_state = ThresholdState::Active;
+				}
 			},
 			ThresholdState::Failed | ThresholdState::Active => {
 				return None
@@ -256,10 +286,10 @@ mod tests {
 	use std::collections::HashMap;
 	use chain::BlockHeader;
 	use db::{BlockHeaderProvider, BlockRef};
-	use network::Deployment;
+	use network::{Deployment, ConsensusParams, ConsensusFork, Magic};
 	use hash::H256;
 	use primitives::bytes::Bytes;
-	use super::{DeploymentStateCache, ThresholdState, first_of_the_period, threshold_state};
+	use super::{Deployments, DeploymentStateCache, ThresholdState, first_of_the_period, threshold_state};
 
 	const MINER_CONFIRMATION_WINDOW: u32 = 1000;
 	const RULE_CHANGE_ACTIVATION_THRESHOLD: u32 = 900;
@@ -314,16 +344,18 @@ mod tests {
 			DeploymentStateCache::default(),
 			DeploymentHeaderProvider::default(),
 			Deployment {
-				name: "test",
+				name: "test".into(),
 				bit: 0,
 				start_time: make_test_time(10000),
 				timeout: make_test_time(20000),
+				min_activation_height: 0,
+				threshold: None,
 				activation: None,
 			},
 		);
 
-		assert_eq!(threshold_state(&mut cache, deployment, 0, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), ThresholdState::Defined);
-		assert_eq!(threshold_state(&mut DeploymentStateCache::default(), deployment, 0, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), ThresholdState::Defined);
+		assert_eq!(threshold_state(&mut cache, &deployment, 0, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), ThresholdState::Defined);
+		assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, 0, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), ThresholdState::Defined);
 
 		(cache, headers, deployment)
 	}
@@ -361,8 +393,8 @@ mod tests {
 		for (height, time, version, state) in test_cases {
 			headers.mine(height, time, version);
 
-			assert_eq!(threshold_state(&mut cache, deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
-			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut cache, &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
 		}
 	}
 
@@ -384,8 +416,8 @@ mod tests {
 		for (height, time, version, state) in test_cases {
 			headers.mine(height, time, version);
 
-			assert_eq!(threshold_state(&mut cache, deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
-			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut cache, &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
 		}
 	}
 
@@ -408,8 +440,8 @@ mod tests {
 		for (height, time, version, state) in test_cases {
 			headers.mine(height, time, version);
 
-			assert_eq!(threshold_state(&mut cache, deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
-			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut cache, &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
 		}
 	}
 
@@ -435,7 +467,7 @@ mod tests {
 			headers.mine(height, time, version);
 
 			let req_old = headers.request_count.load(Ordering::Relaxed);
-			assert_eq!(threshold_state(&mut cache, deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut cache, &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
 			let req_new = headers.request_count.load(Ordering::Relaxed);
 
 			// also check that same-period states are read from cache
@@ -445,7 +477,29 @@ mod tests {
 				assert!(req_old < req_new);
 			}
 
-			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+		}
+	}
+
+	#[test]
+	fn test_threshold_state_lockedin_waits_for_min_activation_height() {
+		let (mut cache, mut headers, mut deployment) = prepare_deployments();
+		deployment.min_activation_height = 5000;
+		let test_cases = vec![
+			(1000,	make_test_time(10000) - 1,	0x20000001,	ThresholdState::Defined),
+			(2000,	make_test_time(10000),		0x20000001,	ThresholdState::Started),
+			(3000,	make_test_time(10010),		0x20000001,	ThresholdState::LockedIn),
+			(4000,	make_test_time(10020),		0x20000000,	ThresholdState::LockedIn),
+			(4999,	make_test_time(10030),		0x20000000,	ThresholdState::LockedIn),
+			(5000,	make_test_time(10040),		0x20000000,	ThresholdState::Active),
+			(6000,	make_test_time(10050),		0x20000000,	ThresholdState::Active),
+		];
+
+		for (height, time, version, state) in test_cases {
+			headers.mine(height, time, version);
+
+			assert_eq!(threshold_state(&mut cache, &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
 		}
 	}
 
@@ -467,8 +521,27 @@ mod tests {
 		for (height, time, version, state) in test_cases {
 			headers.mine(height, time, version);
 
-			assert_eq!(threshold_state(&mut cache, deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
-			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut cache, &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
+			assert_eq!(threshold_state(&mut DeploymentStateCache::default(), &deployment, height, &headers, MINER_CONFIRMATION_WINDOW, RULE_CHANGE_ACTIVATION_THRESHOLD), state);
 		}
 	}
+
+	#[test]
+	fn test_mainnet_taproot_locks_in_with_90_percent_signals() {
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		let deployment = consensus.taproot_deployment.clone().unwrap();
+		let signal = 0x20000000 | (1 << deployment.bit);
+
+		let state_after = |signalling: u32| {
+			let mut headers = DeploymentHeaderProvider::default();
+			headers.mine(2016, deployment.start_time - 1, 0x20000000);
+			headers.mine(4032, deployment.start_time, 0x20000000);
+			headers.mine(4032 + signalling, deployment.start_time, signal);
+			headers.mine(6048, deployment.start_time, 0x20000000);
+			Deployments::new().threshold_state(&deployment, 6048, &headers, &consensus)
+		};
+
+		assert_eq!(state_after(1814), ThresholdState::Started);
+		assert_eq!(state_after(1815), ThresholdState::LockedIn);
+	}
 }
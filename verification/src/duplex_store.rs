@@ -3,6 +3,7 @@
 
 use chain::{OutPoint, TransactionOutput};
 use db::TransactionOutputProvider;
+use logs;
 
 #[derive(Clone, Copy)]
 pub struct DuplexTransactionOutputProvider<'a> {
@@ -21,6 +22,7 @@ impl<'a> DuplexTransactionOutputProvider<'a> {
 
 impl<'a> TransactionOutputProvider for DuplexTransactionOutputProvider<'a> {
 	fn transaction_output(&self, prevout: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
+		let _span = logs::span("utxo_lookup");
 		self.first.transaction_output(prevout, transaction_index)
 			.or_else(|| self.second.transaction_output(prevout, transaction_index))
 	}
@@ -35,16 +35,22 @@ pub enum Error {
 	MaximumSigops,
 	/// Maximum sigops operations cost  exceeded
 	MaximumSigopsCost,
+	/// Maximum number of signature checks exceeded
+	MaximumSigChecks,
+	/// Block transactions are not sorted by their hashes (canonical order)
+	NonCanonicalTransactionOrder,
 	/// Coinbase signature is not in the range 2-100
 	CoinbaseSignatureLength(usize),
 	/// Block size is invalid
 	Size(usize),
-	/// Block weight is invalid
-	Weight,
+	/// Block weight {weight} exceeds maximal block weight {max_weight}
+	ExceedsMaxBlockWeight { weight: usize, max_weight: usize },
 	/// Block transactions are not final.
 	NonFinalBlock,
 	/// Old version block.
 	OldVersionBlock,
+	/// Block hash doesn't match the checkpoint at its height
+	Checkpoint,
 	/// Sum of the transaction fees in block + coinbase reward exceeds u64::max
 	TransactionFeeAndRewardOverflow,
 	/// Sum of the transaction fees in block exceeds u64::max
@@ -57,10 +63,73 @@ pub enum Error {
 	WitnessMerkleCommitmentMismatch,
 	/// SegWit: unexpected witness
 	UnexpectedWitness,
+	/// Signet: block solution is missing or malformed
+	SignetSolution,
+	/// Signet: block solution does not satisfy the challenge
+	SignetSignature(SignatureError),
 	/// Database error
 	Database(DBError),
 }
 
+/// Block proposal reject reasons, defined by BIP22 and BIP23.
+const PROPOSAL_REJECT_REASONS: &'static [&'static str] = &[
+	"bad-blk-sigops", "bad-cb-flag", "bad-cb-height", "bad-cb-length", "bad-cb-missing", "bad-cb-multiple", "bad-cb-prefix",
+	"bad-diffbits", "bad-prevblk", "bad-txnmrklroot", "bad-txns", "bad-version", "duplicate", "high-hash", "inconclusive",
+	"time-invalid", "time-too-new", "time-too-old",
+];
+
+impl Error {
+	/// Reason of the block proposal rejection (BIP23). Reasons, which aren't defined by BIP22 and BIP23,
+	/// are reported as `bad-txns` if some transaction is invalid, or as `rejected` otherwise.
+	pub fn proposal_reject_reason(&self) -> &'static str {
+		let reason = self.reject_reason();
+		if PROPOSAL_REJECT_REASONS.contains(&reason) {
+			return reason;
+		}
+
+		match *self {
+			Error::Transaction(_, _) => "bad-txns",
+			_ if reason.starts_with("bad-txns-") => "bad-txns",
+			_ => "rejected",
+		}
+	}
+
+	/// Reason of the block rejection, as it is reported to miners and to the peers (BIP22).
+	pub fn reject_reason(&self) -> &'static str {
+		match *self {
+			Error::Duplicate => "duplicate",
+			Error::DuplicatedTransactions => "bad-txns-duplicate",
+			Error::Empty | Error::Size(_) => "bad-blk-length",
+			Error::Pow => "high-hash",
+			Error::FuturisticTimestamp => "time-too-new",
+			Error::Timestamp => "time-too-old",
+			Error::Coinbase => "bad-cb-missing",
+			Error::Transaction(_, ref err) => err.reject_reason(),
+			Error::Difficulty { .. } => "bad-diffbits",
+			Error::MerkleRoot => "bad-txnmrklroot",
+			Error::CoinbaseOverspend { .. } | Error::TransactionFeeAndRewardOverflow => "bad-cb-amount",
+			Error::CoinbaseScript => "bad-cb-height",
+			Error::MaximumSigops | Error::MaximumSigopsCost => "bad-blk-sigops",
+			Error::MaximumSigChecks => "bad-blk-sigchecks",
+			Error::NonCanonicalTransactionOrder => "tx-ordering",
+			Error::CoinbaseSignatureLength(_) => "bad-cb-length",
+			Error::ExceedsMaxBlockWeight { .. } => "bad-blk-weight",
+			Error::NonFinalBlock => "bad-txns-nonfinal",
+			Error::OldVersionBlock => "bad-version",
+			Error::Checkpoint => "checkpoint-mismatch",
+			Error::TransactionFeesOverflow => "bad-txns-accumulated-fee-outofrange",
+			Error::ReferencedInputsSumOverflow => "bad-txns-inputvalues-outofrange",
+			Error::WitnessInvalidNonceSize => "bad-witness-nonce-size",
+			Error::WitnessMerkleCommitmentMismatch => "bad-witness-merkle-match",
+			Error::UnexpectedWitness => "unexpected-witness",
+			Error::SignetSolution | Error::SignetSignature(_) => "bad-signet-blksig",
+			Error::Database(DBError::UnknownParent) | Error::Database(DBError::InvalidParent) => "bad-prevblk",
+			// block itself may be valid
+			Error::Database(_) => "inconclusive",
+		}
+	}
+}
+
 impl From<DBError> for Error {
 	fn from(err: DBError) -> Self {
 		Error::Database(err)
@@ -76,16 +145,24 @@ pub enum TransactionError {
 	NullNonCoinbase,
 	/// Coinbase signature is not in the range 2-100
 	CoinbaseSignatureLength(usize),
-	/// Transaction size exceeds block size limit
-	MaxSize,
+	/// Weight of the transaction without witness data exceeds block weight limit
+	TransactionTooLarge { weight: usize },
 	/// Transaction has more sigops than it's allowed
 	MaxSigops,
+	/// Transaction has more signature checks than it's allowed
+	MaxSigChecks,
+	/// Transaction size is below minimal size
+	MinSize,
 	/// Transaction is a part of memory pool, but is a coinbase
 	MemoryPoolCoinbase,
 	/// Not found corresponding output for transaction input
 	Input(usize),
 	/// Referenced coinbase output for the transaction input is not mature enough
 	Maturity,
+	/// Relative lock-time of the transaction input {self.0} isn't satisfied yet (BIP68)
+	SequenceLock(usize),
+	/// Lock-time of the transaction isn't satisfied in the next block
+	NonFinal,
 	/// Signature invalid for given input
 	Signature(usize, SignatureError),
 	/// Unknown previous transaction referenced
@@ -108,5 +185,75 @@ pub enum TransactionError {
 	ReturnReplayProtection,
 	/// Transaction with witness is received before SegWit is activated.
 	PrematureWitness,
+	/// Replacement transaction spends output of unconfirmed transaction, which isn't spent by replaced transactions (BIP125).
+	ReplacementSpendsUnconfirmed(H256),
+	/// Replacement transaction evicts too many memory pool transactions (BIP125).
+	TooManyReplacements(usize),
+	/// Replacement transaction fee rate isn't higher than fee rate of conflicting transaction (BIP125).
+	InsufficientReplacementFeeRate(H256),
+	/// Replacement transaction pays fee {self.0}, which is lower than required fee {self.1} (BIP125).
+	InsufficientReplacementFee(u64, u64),
+	/// Transaction pays fee {self.0}, which is lower than fee {self.1}, required by the memory pool minimum fee rate.
+	InsufficientMemoryPoolFee(u64, u64),
 }
 
+impl TransactionError {
+	/// Reason of the transaction rejection, as it is reported to the peers.
+	pub fn reject_reason(&self) -> &'static str {
+		match *self {
+			TransactionError::Empty => "bad-txns-vin-empty",
+			TransactionError::NullNonCoinbase => "bad-txns-prevout-null",
+			TransactionError::CoinbaseSignatureLength(_) => "bad-cb-length",
+			TransactionError::TransactionTooLarge { .. } => "bad-txns-oversize",
+			TransactionError::MaxSigops => "bad-txns-too-many-sigops",
+			TransactionError::MaxSigChecks => "bad-txns-too-many-sigchecks",
+			TransactionError::MinSize => "bad-txns-undersize",
+			TransactionError::MemoryPoolCoinbase => "coinbase",
+			TransactionError::Input(_) | TransactionError::UnknownReference(_) | TransactionError::UsingSpentOutput(_, _) =>
+				"bad-txns-inputs-missingorspent",
+			TransactionError::Maturity => "bad-txns-premature-spend-of-coinbase",
+			TransactionError::SequenceLock(_) => "bad-txns-nonfinal",
+			TransactionError::NonFinal => "non-final",
+			TransactionError::Signature(_, _) | TransactionError::SignatureMallformed(_) => "mandatory-script-verify-flag-failed",
+			TransactionError::Overspend => "bad-txns-in-belowout",
+			TransactionError::Sigops(_) | TransactionError::SigopsP2SH(_) => "bad-blk-sigops",
+			TransactionError::MisplacedCoinbase => "bad-cb-multiple",
+			TransactionError::UnspentTransactionWithTheSameHash => "bad-txns-BIP30",
+			TransactionError::ReturnReplayProtection => "bad-txns-replay-protected",
+			TransactionError::PrematureWitness => "no-witness-yet",
+			TransactionError::ReplacementSpendsUnconfirmed(_) => "replacement-adds-unconfirmed",
+			TransactionError::TooManyReplacements(_) => "too many potential replacements",
+			TransactionError::InsufficientReplacementFeeRate(_) | TransactionError::InsufficientReplacementFee(_, _) => "insufficient fee",
+			TransactionError::InsufficientMemoryPoolFee(_, _) => "mempool min fee not met",
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use db::Error as DBError;
+	use super::{Error, TransactionError};
+
+	#[test]
+	fn test_reject_reason() {
+		assert_eq!(Error::MerkleRoot.reject_reason(), "bad-txnmrklroot");
+		assert_eq!(Error::Pow.reject_reason(), "high-hash");
+		assert_eq!(Error::ExceedsMaxBlockWeight { weight: 4_000_001, max_weight: 4_000_000 }.reject_reason(), "bad-blk-weight");
+		assert_eq!(Error::Transaction(0, TransactionError::TransactionTooLarge { weight: 4_000_004 }).reject_reason(), "bad-txns-oversize");
+		assert_eq!(Error::Transaction(1, TransactionError::Overspend).reject_reason(), "bad-txns-in-belowout");
+		assert_eq!(Error::Database(DBError::UnknownParent).reject_reason(), "bad-prevblk");
+		assert_eq!(Error::Database(DBError::InvalidParent).reject_reason(), "bad-prevblk");
+		assert_eq!(Error::Database(DBError::CannotCanonize).reject_reason(), "inconclusive");
+	}
+
+	#[test]
+	fn test_proposal_reject_reason() {
+		assert_eq!(Error::Pow.proposal_reject_reason(), "high-hash");
+		assert_eq!(Error::Transaction(1, TransactionError::Sigops(20_001)).proposal_reject_reason(), "bad-blk-sigops");
+		assert_eq!(Error::Transaction(1, TransactionError::Overspend).proposal_reject_reason(), "bad-txns");
+		assert_eq!(Error::Transaction(1, TransactionError::NonFinal).proposal_reject_reason(), "bad-txns");
+		assert_eq!(Error::DuplicatedTransactions.proposal_reject_reason(), "bad-txns");
+		assert_eq!(Error::ExceedsMaxBlockWeight { weight: 4_000_001, max_weight: 4_000_000 }.proposal_reject_reason(), "rejected");
+		assert_eq!(Error::Database(DBError::CannotCanonize).proposal_reject_reason(), "inconclusive");
+	}
+}
@@ -58,6 +58,7 @@ extern crate lazy_static;
 extern crate log;
 extern crate parking_lot;
 extern crate rayon;
+extern crate num_cpus;
 
 extern crate db;
 extern crate chain;
@@ -66,13 +67,16 @@ extern crate primitives;
 extern crate serialization as ser;
 extern crate script;
 extern crate bitcrypto as crypto;
+extern crate logs;
 
 pub mod constants;
 mod canon;
 mod deployments;
 mod duplex_store;
 mod error;
+mod script_queue;
 mod sigops;
+mod threads;
 mod timestamp;
 mod work;
 
@@ -91,10 +95,13 @@ mod accept_transaction;
 // backwards compatibility
 mod chain_verifier;
 
+// stored chain check
+mod chain_check;
+
 pub use primitives::{bigint, hash, compact};
 
 pub use canon::{CanonBlock, CanonHeader, CanonTransaction};
-pub use accept_block::BlockAcceptor;
+pub use accept_block::{BlockAcceptor, check_witness_commitment};
 pub use accept_chain::ChainAcceptor;
 pub use accept_header::HeaderAcceptor;
 pub use accept_transaction::{TransactionAcceptor, MemoryPoolTransactionAcceptor};
@@ -106,10 +113,13 @@ pub use verify_transaction::{TransactionVerifier, MemoryPoolTransactionVerifier}
 
 pub use chain_verifier::BackwardsCompatibleChainVerifier;
 pub use error::{Error, TransactionError};
-pub use sigops::transaction_sigops;
-pub use timestamp::median_timestamp;
-pub use work::{work_required, is_valid_proof_of_work, is_valid_proof_of_work_hash, block_reward_satoshi};
-pub use deployments::Deployments;
+pub use sigops::{transaction_sigops, transaction_sigops_cost};
+pub use threads::{init_script_threads, script_threads, MAX_SCRIPT_THREADS};
+pub use timestamp::{median_timestamp, median_timestamp_inclusive, TimeSource, SystemTime};
+pub use work::{work_required, is_valid_proof_of_work, is_valid_proof_of_work_hash, block_reward_satoshi,
+	DifficultyAdjustment, difficulty_adjustment};
+pub use deployments::{Deployments, BlockDeployments, ThresholdState};
+pub use chain_check::{check_chain, CheckLevel, CheckError, BadBlock};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// Blocks verification level.
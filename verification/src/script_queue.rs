@@ -0,0 +1,89 @@
+//! Block script verification queue.
+//!
+//! Inputs of all block transactions are split into jobs of at most `PARALLEL_INPUTS_CHUNK` inputs.
+//! Jobs are verified in parallel on the script verification threads, so that the block with a few
+//! large transactions keeps all threads busy, same as the block with many small transactions.
+//! Outputs, spent by the transactions, are read from the store in parallel too.
+//! Signatures are verified using the shared libsecp256k1 context.
+
+use std::cmp;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use script::TransactionInputSigner;
+use accept_transaction::{TransactionEval, PARALLEL_INPUTS_CHUNK};
+use error::Error;
+use logs;
+
+/// Verification of scripts of `from..to` inputs of the block transaction.
+struct ScriptJob {
+	transaction_index: usize,
+	from: usize,
+	to: usize,
+}
+
+/// Script verification queue of the single block.
+pub struct BlockScriptQueue<'a> {
+	/// Block transactions with their signers. Transactions, which scripts aren't verified, have no signer.
+	transactions: Vec<(&'a TransactionEval<'a>, Option<Arc<TransactionInputSigner>>)>,
+	jobs: Vec<ScriptJob>,
+}
+
+impl<'a> BlockScriptQueue<'a> {
+	/// Queues scripts of all given block transactions.
+	pub fn new<I>(transactions: I) -> Result<Self, Error> where I: IntoIterator<Item = &'a TransactionEval<'a>> {
+		let mut queue = BlockScriptQueue {
+			transactions: Vec::new(),
+			jobs: Vec::new(),
+		};
+
+		let transactions: Vec<&'a TransactionEval<'a>> = transactions.into_iter().collect();
+		let signers: Vec<_> = transactions.par_iter()
+			.map(|eval| eval.signer())
+			.collect();
+
+		for (transaction_index, (eval, signer)) in transactions.into_iter().zip(signers).enumerate() {
+			let signer = signer
+				.map_err(|err| Error::Transaction(transaction_index, err))?;
+
+			let inputs = signer.as_ref().map(|signer| signer.inputs.len()).unwrap_or_default();
+			let mut from = 0;
+			while from < inputs {
+				let to = cmp::min(from + PARALLEL_INPUTS_CHUNK, inputs);
+				queue.jobs.push(ScriptJob {
+					transaction_index: transaction_index,
+					from: from,
+					to: to,
+				});
+				from = to;
+			}
+
+			queue.transactions.push((eval, signer));
+		}
+
+		Ok(queue)
+	}
+
+	/// Verifies all queued scripts. Returns total number of executed signature checks.
+	pub fn verify(&self) -> Result<usize, Error> {
+		let _span = logs::span("scripts");
+		let sig_checks: Vec<AtomicUsize> = self.transactions.iter().map(|_| AtomicUsize::new(0)).collect();
+		self.jobs.par_iter()
+			.map(|job| {
+				let &(eval, ref signer) = &self.transactions[job.transaction_index];
+				let signer = signer.as_ref().expect("jobs are only queued for transactions with signer; qed");
+				eval.check_inputs(signer, job.from, job.to)
+					.map(|job_sig_checks| { sig_checks[job.transaction_index].fetch_add(job_sig_checks, Ordering::Relaxed); })
+					.map_err(|err| Error::Transaction(job.transaction_index, err))
+			})
+			.reduce(|| Ok(()), |acc, check| acc.and(check))?;
+
+		let mut total_sig_checks = 0;
+		for (index, (&(eval, _), tx_sig_checks)) in self.transactions.iter().zip(sig_checks).enumerate() {
+			total_sig_checks += eval.check_sig_checks(tx_sig_checks.into_inner())
+				.map_err(|err| Error::Transaction(index, err))?;
+		}
+
+		Ok(total_sig_checks)
+	}
+}
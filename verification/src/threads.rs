@@ -0,0 +1,53 @@
+//! Script verification threads.
+//!
+//! Scripts of block transactions (and inputs of large transactions) are verified in parallel
+//! on the global rayon thread pool. Its size is configured once on startup.
+
+use num_cpus;
+use rayon;
+
+/// Maximal number of script verification threads.
+pub const MAX_SCRIPT_THREADS: usize = 16;
+
+/// Returns number of script verification threads for the given `par` value.
+/// Works like `bitcoind -par`: `0` uses all cores, negative value leaves that many cores free.
+pub fn script_threads(par: i32, cores: usize) -> usize {
+	let threads = if par > 0 {
+		par as usize
+	} else {
+		// widened, so that negation of i32::MIN doesn't overflow
+		cores.saturating_sub((par as i64).abs() as usize)
+	};
+
+	match threads {
+		0 => 1,
+		threads if threads > MAX_SCRIPT_THREADS => MAX_SCRIPT_THREADS,
+		threads => threads,
+	}
+}
+
+/// Initializes script verification thread pool. Must be called before any block is verified.
+/// Returns number of started threads.
+pub fn init_script_threads(par: i32) -> Result<usize, String> {
+	let threads = script_threads(par, num_cpus::get());
+	rayon::initialize(rayon::Configuration::new().num_threads(threads))
+		.map_err(|err| format!("Failed to start script verification threads: {}", err))?;
+	Ok(threads)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{script_threads, MAX_SCRIPT_THREADS};
+
+	#[test]
+	fn test_script_threads() {
+		assert_eq!(script_threads(0, 8), 8);
+		assert_eq!(script_threads(4, 8), 4);
+		assert_eq!(script_threads(-2, 8), 6);
+		assert_eq!(script_threads(-10, 8), 1);
+		assert_eq!(script_threads(::std::i32::MIN, 8), 1);
+		assert_eq!(script_threads(1, 8), 1);
+		assert_eq!(script_threads(0, 64), MAX_SCRIPT_THREADS);
+		assert_eq!(script_threads(100, 8), MAX_SCRIPT_THREADS);
+	}
+}
@@ -2,6 +2,22 @@ use chain::BlockHeader;
 use db::{BlockHeaderProvider, BlockAncestors};
 use primitives::hash::H256;
 
+/// Source of the current time, which is used to check that block timestamps aren't too far in the future.
+pub trait TimeSource: Send + Sync {
+	/// Returns current time (in seconds since epoch)
+	fn now(&self) -> u32;
+}
+
+/// Local system clock.
+#[derive(Debug, Default)]
+pub struct SystemTime;
+
+impl TimeSource for SystemTime {
+	fn now(&self) -> u32 {
+		::time::get_time().sec as u32
+	}
+}
+
 /// Returns median timestamp, of given header ancestors.
 /// The header should be later expected to have higher timestamp
 /// than this median timestamp
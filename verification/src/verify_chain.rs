@@ -1,6 +1,6 @@
 use rayon::prelude::{IntoParallelRefIterator, IndexedParallelIterator, ParallelIterator};
 use chain::IndexedBlock;
-use network::Magic;
+use network::ConsensusParams;
 use error::Error;
 use verify_block::BlockVerifier;
 use verify_header::HeaderVerifier;
@@ -13,11 +13,11 @@ pub struct ChainVerifier<'a> {
 }
 
 impl<'a> ChainVerifier<'a> {
-	pub fn new(block: &'a IndexedBlock, network: Magic, current_time: u32) -> Self {
+	pub fn new(block: &'a IndexedBlock, consensus: &ConsensusParams, current_time: u32) -> Self {
 		trace!(target: "verification", "Block pre-verification {}", block.hash().to_reversed_str());
 		ChainVerifier {
 			block: BlockVerifier::new(block),
-			header: HeaderVerifier::new(&block.header, network, current_time),
+			header: HeaderVerifier::new(&block.header, consensus, current_time),
 			transactions: block.transactions.iter().map(TransactionVerifier::new).collect(),
 		}
 	}
@@ -29,6 +29,14 @@ impl<'a> ChainVerifier<'a> {
 		Ok(())
 	}
 
+	/// Checks everything, except proof of work. Used to check block proposals.
+	pub fn check_without_proof_of_work(&self) -> Result<(), Error> {
+		try!(self.block.check());
+		try!(self.header.check_without_proof_of_work());
+		try!(self.check_transactions());
+		Ok(())
+	}
+
 	fn check_transactions(&self) -> Result<(), Error> {
 		self.transactions.par_iter()
 			.enumerate()
@@ -1,6 +1,7 @@
 use primitives::compact::Compact;
+use primitives::hash::H256;
 use chain::IndexedBlockHeader;
-use network::Magic;
+use network::ConsensusParams;
 use work::is_valid_proof_of_work;
 use error::Error;
 use constants::BLOCK_MAX_FUTURE;
@@ -11,9 +12,9 @@ pub struct HeaderVerifier<'a> {
 }
 
 impl<'a> HeaderVerifier<'a> {
-	pub fn new(header: &'a IndexedBlockHeader, network: Magic, current_time: u32) -> Self {
+	pub fn new(header: &'a IndexedBlockHeader, consensus: &ConsensusParams, current_time: u32) -> Self {
 		HeaderVerifier {
-			proof_of_work: HeaderProofOfWork::new(header, network),
+			proof_of_work: HeaderProofOfWork::new(header, consensus),
 			timestamp: HeaderTimestamp::new(header, current_time, BLOCK_MAX_FUTURE as u32),
 		}
 	}
@@ -23,23 +24,30 @@ impl<'a> HeaderVerifier<'a> {
 		try!(self.timestamp.check());
 		Ok(())
 	}
+
+	/// Checks everything, except proof of work. Used to check block proposals.
+	pub fn check_without_proof_of_work(&self) -> Result<(), Error> {
+		self.timestamp.check()
+	}
 }
 
 pub struct HeaderProofOfWork<'a> {
 	header: &'a IndexedBlockHeader,
+	pow_hash: H256,
 	max_work_bits: Compact,
 }
 
 impl<'a> HeaderProofOfWork<'a> {
-	fn new(header: &'a IndexedBlockHeader, network: Magic) -> Self {
+	fn new(header: &'a IndexedBlockHeader, consensus: &ConsensusParams) -> Self {
 		HeaderProofOfWork {
 			header: header,
-			max_work_bits: network.max_bits(),
+			pow_hash: consensus.proof_of_work.pow_hash(header),
+			max_work_bits: consensus.pow_limit,
 		}
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		if is_valid_proof_of_work(self.max_work_bits, self.header.raw.bits, &self.header.hash) {
+		if is_valid_proof_of_work(self.max_work_bits, self.header.raw.bits, &self.pow_hash) {
 			Ok(())
 		} else {
 			Err(Error::Pow)
@@ -70,3 +78,34 @@ impl<'a> HeaderTimestamp<'a> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
+	use chain::IndexedBlockHeader;
+	use network::{ConsensusParams, ConsensusFork, Magic, ProofOfWork};
+	use primitives::hash::H256;
+	use error::Error;
+	use super::HeaderProofOfWork;
+
+	#[derive(Debug)]
+	struct ConstantProofOfWork(H256);
+
+	impl ProofOfWork for ConstantProofOfWork {
+		fn pow_hash(&self, _header: &IndexedBlockHeader) -> H256 {
+			self.0.clone()
+		}
+	}
+
+	#[test]
+	fn header_proof_of_work_uses_consensus_function() {
+		let header: IndexedBlockHeader = test_data::genesis().block_header.into();
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(), Ok(()));
+
+		let consensus = consensus.with_proof_of_work(Arc::new(ConstantProofOfWork([0xff; 32].into())));
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(), Err(Error::Pow));
+	}
+}
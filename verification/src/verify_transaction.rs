@@ -1,5 +1,4 @@
 use std::ops;
-use ser::Serializable;
 use chain::IndexedTransaction;
 use network::{ConsensusParams, ConsensusFork};
 use deployments::BlockDeployments;
@@ -39,10 +38,18 @@ pub struct MemoryPoolTransactionVerifier<'a> {
 	pub size: TransactionSize<'a>,
 	pub premature_witness: TransactionPrematureWitness<'a>,
 	pub sigops: TransactionSigops<'a>,
+	pub finality: TransactionFinality<'a>,
 }
 
 impl<'a> MemoryPoolTransactionVerifier<'a> {
-	pub fn new(transaction: &'a IndexedTransaction, consensus: &'a ConsensusParams, deployments: &'a BlockDeployments<'a>) -> Self {
+	pub fn new(
+		transaction: &'a IndexedTransaction,
+		consensus: &'a ConsensusParams,
+		deployments: &'a BlockDeployments<'a>,
+		height: u32,
+		time: u32,
+		median_time_past: u32,
+	) -> Self {
 		trace!(target: "verification", "Mempool-Tx pre-verification {}", transaction.hash.to_reversed_str());
 		MemoryPoolTransactionVerifier {
 			empty: TransactionEmpty::new(transaction),
@@ -51,6 +58,7 @@ impl<'a> MemoryPoolTransactionVerifier<'a> {
 			size: TransactionSize::new(transaction, consensus),
 			premature_witness: TransactionPrematureWitness::new(transaction, &deployments),
 			sigops: TransactionSigops::new(transaction, ConsensusFork::absolute_maximum_block_sigops()),
+			finality: TransactionFinality::new(transaction, deployments, height, time, median_time_past),
 		}
 	}
 
@@ -61,6 +69,7 @@ impl<'a> MemoryPoolTransactionVerifier<'a> {
 		try!(self.size.check());
 		try!(self.premature_witness.check());
 		try!(self.sigops.check());
+		try!(self.finality.check());
 		Ok(())
 	}
 }
@@ -163,9 +172,10 @@ impl<'a> TransactionSize<'a> {
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
-		let size = self.transaction.raw.serialized_size();
-		if size > self.consensus.fork.max_transaction_size() {
-			Err(TransactionError::MaxSize)
+		// witness data is not counted here, so the transaction fits into the block even without SegWit
+		let weight = self.transaction.raw.base_size() * ConsensusFork::witness_scale_factor();
+		if weight > self.consensus.fork.max_transaction_weight() {
+			Err(TransactionError::TransactionTooLarge { weight: weight })
 		} else {
 			Ok(())
 		}
@@ -218,3 +228,36 @@ impl<'a> TransactionPrematureWitness<'a> {
 		}
 	}
 }
+
+/// Checks that the transaction could be included in the next block.
+pub struct TransactionFinality<'a> {
+	transaction: &'a IndexedTransaction,
+	height: u32,
+	time_cutoff: u32,
+}
+
+impl<'a> TransactionFinality<'a> {
+	fn new(transaction: &'a IndexedTransaction, deployments: &'a BlockDeployments<'a>, height: u32, time: u32, median_time_past: u32) -> Self {
+		// BIP113: once CSV is active, time-based lock-time is compared to the median time past,
+		// same as it is done for transactions of the next block
+		let time_cutoff = if deployments.csv() {
+			median_time_past
+		} else {
+			time
+		};
+
+		TransactionFinality {
+			transaction: transaction,
+			height: height,
+			time_cutoff: time_cutoff,
+		}
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		if self.transaction.raw.is_final_in_block(self.height, self.time_cutoff) {
+			Ok(())
+		} else {
+			Err(TransactionError::NonFinal)
+		}
+	}
+}
@@ -1,16 +1,20 @@
 use std::cmp;
 use primitives::compact::Compact;
 use primitives::hash::H256;
-use primitives::bigint::U256;
-use network::{Magic, ConsensusParams, ConsensusFork};
+use primitives::bigint::{U256, Uint};
+use chain::BlockHeader;
+use network::{ConsensusParams, DifficultyAlgorithm};
 use db::{BlockHeaderProvider, BlockRef};
 use timestamp::median_timestamp_inclusive;
 
 use constants::{
-	DOUBLE_SPACING_SECONDS,
+	DOUBLE_SPACING_SECONDS, TARGET_SPACING_SECONDS,
 	TARGET_TIMESPAN_SECONDS, MIN_TIMESPAN, MAX_TIMESPAN, RETARGETING_INTERVAL
 };
 
+/// Number of blocks, used to compute work in BitcoinCash cw-144 algorithm.
+const CW144_WINDOW: u32 = 144;
+
 pub fn is_retarget_height(height: u32) -> bool {
 	height % RETARGETING_INTERVAL == 0
 }
@@ -21,9 +25,9 @@ fn range_constrain(value: i64, min: i64, max: i64) -> i64 {
 
 /// Returns true if hash is lower or equal than target represented by compact bits
 pub fn is_valid_proof_of_work_hash(bits: Compact, hash: &H256) -> bool {
-	let target = match bits.to_u256() {
-		Ok(target) => target,
-		_err => return false,
+	let target = match bits.to_target() {
+		Some(target) => target,
+		None => return false,
 	};
 
 	let value = U256::from(&*hash.reversed() as &[u8]);
@@ -38,9 +42,9 @@ pub fn is_valid_proof_of_work(max_work_bits: Compact, bits: Compact, hash: &H256
 		_err => return false,
 	};
 
-	let target = match bits.to_u256() {
-		Ok(target) => target,
-		_err => return false,
+	let target = match bits.to_target() {
+		Some(target) => target,
+		None => return false,
 	};
 
 	let value = U256::from(&*hash.reversed() as &[u8]);
@@ -57,64 +61,191 @@ pub fn retarget_timespan(retarget_timestamp: u32, last_timestamp: u32) -> u32 {
 
 /// Returns work required for given header
 pub fn work_required(parent_hash: H256, time: u32, height: u32, store: &BlockHeaderProvider, consensus: &ConsensusParams) -> Compact {
-	let max_bits = consensus.network.max_bits();
 	if height == 0 {
-		return max_bits;
+		return consensus.pow_limit;
 	}
 
-	let parent_header = store.block_header(parent_hash.clone().into()).expect("self.height != 0; qed");
+	let parent_header = store.block_header(parent_hash.into()).expect("self.height != 0; qed");
+	difficulty_adjustment(consensus.difficulty_algorithm(height))
+		.work_required(&parent_header, time, height, store, consensus)
+}
 
-	if is_retarget_height(height) {
-		let retarget_ref = (height - RETARGETING_INTERVAL).into();
-		let retarget_header = store.block_header(retarget_ref).expect("self.height != 0 && self.height % RETARGETING_INTERVAL == 0; qed");
+/// Difficulty adjustment algorithm.
+pub trait DifficultyAdjustment {
+	/// Returns work required for the block at given height, which is a child of `parent_header`.
+	fn work_required(&self, parent_header: &BlockHeader, time: u32, height: u32, store: &BlockHeaderProvider, consensus: &ConsensusParams) -> Compact;
+}
+
+/// Returns implementation of given difficulty adjustment algorithm.
+pub fn difficulty_adjustment(algorithm: DifficultyAlgorithm) -> &'static DifficultyAdjustment {
+	match algorithm {
+		DifficultyAlgorithm::NoRetargeting => &NoRetargeting,
+		DifficultyAlgorithm::Bitcoin => &BitcoinRetargeting,
+		DifficultyAlgorithm::BitcoinCashEmergency => &BitcoinCashEmergencyRetargeting,
+		DifficultyAlgorithm::BitcoinCashCw144 => &BitcoinCashCw144Retargeting,
+	}
+}
 
-		// timestamp of block(height - RETARGETING_INTERVAL)
-		let retarget_timestamp = retarget_header.time;
-		// timestamp of parent block
-		let last_timestamp = parent_header.time;
-		// bits of last block
-		let last_bits = parent_header.bits;
+/// Difficulty is never changed.
+pub struct NoRetargeting;
 
-		return work_required_retarget(max_bits, retarget_timestamp, last_timestamp, last_bits);
+impl DifficultyAdjustment for NoRetargeting {
+	fn work_required(&self, parent_header: &BlockHeader, _time: u32, _height: u32, _store: &BlockHeaderProvider, _consensus: &ConsensusParams) -> Compact {
+		parent_header.bits
 	}
+}
+
+/// Difficulty is adjusted every RETARGETING_INTERVAL blocks.
+pub struct BitcoinRetargeting;
 
-	if consensus.network == Magic::Testnet {
-		return work_required_testnet(parent_hash, time, height, store, Magic::Testnet)
+impl DifficultyAdjustment for BitcoinRetargeting {
+	fn work_required(&self, parent_header: &BlockHeader, time: u32, height: u32, store: &BlockHeaderProvider, consensus: &ConsensusParams) -> Compact {
+		if is_retarget_height(height) {
+			return work_required_interval(parent_header, height, store, consensus);
+		}
+
+		if consensus.pow_allow_min_difficulty_blocks {
+			return work_required_testnet(parent_header.hash(), time, height, store, consensus.pow_limit);
+		}
+
+		parent_header.bits
 	}
+}
 
-	match consensus.fork {
-		_ if parent_header.bits == max_bits => parent_header.bits,
-		ConsensusFork::BitcoinCash(fork_height) if height >= fork_height => {
-			// REQ-7 Difficulty adjustement in case of hashrate drop
-			// In case the MTP of the tip of the chain is 12h or more after the MTP 6 block before the tip,
-			// the proof of work target is increased by a quarter, or 25%, which corresponds to a difficulty
-			// reduction of 20%.
-			let ancient_block_ref = (height - 6 - 1).into();
-			let ancient_header = store.block_header(ancient_block_ref)
-				.expect("parent_header.bits != max_bits; difficulty is max_bits for first RETARGETING_INTERVAL height; RETARGETING_INTERVAL > 7; qed");
-
-			let ancient_timestamp = median_timestamp_inclusive(ancient_header.hash(), store);
-			let parent_timestamp = median_timestamp_inclusive(parent_header.hash(), store);
-			let timestamp_diff = parent_timestamp.checked_sub(ancient_timestamp).unwrap_or_default();
-			if timestamp_diff < 43_200 {
-				// less than 12h => no difficulty change needed
-				return parent_header.bits;
-			}
+/// Difficulty is adjusted every RETARGETING_INTERVAL blocks and is decreased when hashrate drops.
+pub struct BitcoinCashEmergencyRetargeting;
+
+impl DifficultyAdjustment for BitcoinCashEmergencyRetargeting {
+	fn work_required(&self, parent_header: &BlockHeader, time: u32, height: u32, store: &BlockHeaderProvider, consensus: &ConsensusParams) -> Compact {
+		let max_bits = consensus.pow_limit;
+		if is_retarget_height(height) {
+			return work_required_interval(parent_header, height, store, consensus);
+		}
+
+		if consensus.pow_allow_min_difficulty_blocks {
+			return work_required_testnet(parent_header.hash(), time, height, store, max_bits);
+		}
+
+		if parent_header.bits == max_bits {
+			return parent_header.bits;
+		}
+
+		// REQ-7 Difficulty adjustement in case of hashrate drop
+		// In case the MTP of the tip of the chain is 12h or more after the MTP 6 block before the tip,
+		// the proof of work target is increased by a quarter, or 25%, which corresponds to a difficulty
+		// reduction of 20%.
+		let ancient_block_ref = (height - 6 - 1).into();
+		let ancient_header = store.block_header(ancient_block_ref)
+			.expect("parent_header.bits != max_bits; difficulty is max_bits for first RETARGETING_INTERVAL height; RETARGETING_INTERVAL > 7; qed");
+
+		let ancient_timestamp = median_timestamp_inclusive(ancient_header.hash(), store);
+		let parent_timestamp = median_timestamp_inclusive(parent_header.hash(), store);
+		let timestamp_diff = parent_timestamp.checked_sub(ancient_timestamp).unwrap_or_default();
+		if timestamp_diff < 43_200 {
+			// less than 12h => no difficulty change needed
+			return parent_header.bits;
+		}
+
+		let mut new_bits: U256 = parent_header.bits.into();
+		let max_bits: U256 = max_bits.into();
+		new_bits = new_bits + (new_bits >> 2);
+		if new_bits > max_bits {
+			new_bits = max_bits
+		}
 
-			let mut new_bits: U256 = parent_header.bits.into();
-			let max_bits: U256 = max_bits.into();
-			new_bits = new_bits + (new_bits >> 2);
-			if new_bits > max_bits {
-				new_bits = max_bits
+		new_bits.into()
+	}
+}
+
+/// Difficulty is adjusted every block, so that work of the last 144 blocks matches
+/// the work expected to be done in 144 * TARGET_SPACING_SECONDS.
+/// Specification: https://github.com/Bitcoin-UAHF/spec/blob/master/nov-13-hardfork-spec.md
+pub struct BitcoinCashCw144Retargeting;
+
+impl DifficultyAdjustment for BitcoinCashCw144Retargeting {
+	fn work_required(&self, parent_header: &BlockHeader, time: u32, _height: u32, store: &BlockHeaderProvider, consensus: &ConsensusParams) -> Compact {
+		let max_bits = consensus.pow_limit;
+		if consensus.pow_allow_min_difficulty_blocks && time > parent_header.time + DOUBLE_SPACING_SECONDS {
+			return max_bits;
+		}
+
+		// headers[i] is the header of the block at height - 1 - i
+		let mut headers = Vec::with_capacity(CW144_WINDOW as usize + 3);
+		headers.push(parent_header.clone());
+		while headers.len() < CW144_WINDOW as usize + 3 {
+			let previous_hash = headers[headers.len() - 1].previous_header_hash.clone();
+			match store.block_header(previous_hash.into()) {
+				Some(header) => headers.push(header),
+				// not enough blocks to compute the work => keep difficulty unchanged
+				None => return parent_header.bits,
 			}
+		}
+
+		let last = cw144_suitable_block(&headers, 0);
+		let first = cw144_suitable_block(&headers, CW144_WINDOW as usize);
+
+		let work = headers[last..first].iter()
+			.fold(U256::zero(), |work, header| work + header.bits.block_work());
+
+		let timespan = headers[last].time as i64 - headers[first].time as i64;
+		let timespan = range_constrain(
+			timespan,
+			(CW144_WINDOW / 2 * TARGET_SPACING_SECONDS) as i64,
+			(CW144_WINDOW * 2 * TARGET_SPACING_SECONDS) as i64
+		) as u32;
+
+		let work = work * TARGET_SPACING_SECONDS.into() / timespan.into();
+		let target = (!work + U256::one()) / work;
+		let maximum: U256 = max_bits.into();
+		if target > maximum {
+			max_bits
+		} else {
+			target.into()
+		}
+	}
+}
 
-			new_bits.into()
-		},
-		_ => parent_header.bits,
+/// Returns index of the block with median timestamp among headers[index..index + 3].
+fn cw144_suitable_block(headers: &[BlockHeader], index: usize) -> usize {
+	let mut blocks = [index + 2, index + 1, index];
+	if headers[blocks[0]].time > headers[blocks[2]].time {
+		blocks.swap(0, 2);
+	}
+	if headers[blocks[0]].time > headers[blocks[1]].time {
+		blocks.swap(0, 1);
 	}
+	if headers[blocks[1]].time > headers[blocks[2]].time {
+		blocks.swap(1, 2);
+	}
+	blocks[1]
+}
+
+fn work_required_interval(parent_header: &BlockHeader, height: u32, store: &BlockHeaderProvider, consensus: &ConsensusParams) -> Compact {
+	let retarget_ref = (height - RETARGETING_INTERVAL).into();
+	let retarget_header = store.block_header(retarget_ref).expect("self.height != 0 && self.height % RETARGETING_INTERVAL == 0; qed");
+
+	// timestamp of block(height - RETARGETING_INTERVAL) or, if the full interval is measured,
+	// of its parent. There's no block before genesis, so the first interval is always measured from genesis
+	let retarget_timestamp = if consensus.pow_retarget_full_interval && height != RETARGETING_INTERVAL {
+		store.block_header(retarget_header.previous_header_hash.clone().into())
+			.expect("height > RETARGETING_INTERVAL; retarget_header isn't genesis; qed")
+			.time
+	} else {
+		retarget_header.time
+	};
+	// timestamp of parent block
+	let last_timestamp = parent_header.time;
+	// bits of last block. On testnet it could be the minimal difficulty block, so BIP94 uses bits of the first block
+	let last_bits = if consensus.pow_retarget_first_block_bits {
+		retarget_header.bits
+	} else {
+		parent_header.bits
+	};
+
+	work_required_retarget(consensus.pow_limit, retarget_timestamp, last_timestamp, last_bits)
 }
 
-pub fn work_required_testnet(parent_hash: H256, time: u32, height: u32, store: &BlockHeaderProvider, network: Magic) -> Compact {
+pub fn work_required_testnet(parent_hash: H256, time: u32, height: u32, store: &BlockHeaderProvider, max_bits: Compact) -> Compact {
 	assert!(height != 0, "cannot calculate required work for genesis block");
 
 	let mut bits = Vec::new();
@@ -123,7 +254,7 @@ pub fn work_required_testnet(parent_hash: H256, time: u32, height: u32, store: &
 	let parent_header = store.block_header(block_ref.clone()).expect("height != 0; qed");
 	let max_time_gap = parent_header.time + DOUBLE_SPACING_SECONDS;
 	if time > max_time_gap {
-		return network.max_bits();
+		return max_bits;
 	}
 
 	// TODO: optimize it, so it does not make 2016!!! redundant queries each time
@@ -137,12 +268,12 @@ pub fn work_required_testnet(parent_hash: H256, time: u32, height: u32, store: &
 	}
 
 	for (index, bit) in bits.into_iter().enumerate() {
-		if bit != network.max_bits() || is_retarget_height(height - index as u32 - 1) {
+		if bit != max_bits || is_retarget_height(height - index as u32 - 1) {
 			return bit;
 		}
 	}
 
-	network.max_bits()
+	max_bits
 }
 
 /// Algorithm used for retargeting work every 2 weeks
@@ -172,10 +303,36 @@ mod tests {
 	use primitives::bytes::Bytes;
 	use primitives::hash::H256;
 	use primitives::compact::Compact;
-	use network::{Magic, ConsensusParams, ConsensusFork};
+	use network::{Magic, ConsensusParams, ConsensusFork, BitcoinCashConsensusParams, BITCOIN_CASH_DAA_MAINNET_BLOCK};
 	use db::{BlockHeaderProvider, BlockRef};
 	use chain::BlockHeader;
-	use super::{work_required, is_valid_proof_of_work_hash, is_valid_proof_of_work, block_reward_satoshi};
+	use super::{work_required, work_required_retarget, is_valid_proof_of_work_hash, is_valid_proof_of_work, block_reward_satoshi};
+
+	#[derive(Default)]
+	struct MemoryBlockHeaderProvider {
+		pub by_height: Vec<BlockHeader>,
+		pub by_hash: HashMap<H256, usize>,
+	}
+
+	impl MemoryBlockHeaderProvider {
+		pub fn insert(&mut self, header: BlockHeader) {
+			self.by_hash.insert(header.hash(), self.by_height.len());
+			self.by_height.push(header);
+		}
+	}
+
+	impl BlockHeaderProvider for MemoryBlockHeaderProvider {
+		fn block_header_bytes(&self, _block_ref: BlockRef) -> Option<Bytes> {
+			unimplemented!()
+		}
+
+		fn block_header(&self, block_ref: BlockRef) -> Option<BlockHeader> {
+			match block_ref {
+				BlockRef::Hash(ref hash) => self.by_hash.get(hash).map(|h| &self.by_height[*h]).cloned(),
+				BlockRef::Number(height) => self.by_height.get(height as usize).cloned(),
+			}
+		}
+	}
 
 	fn is_valid_pow(max: Compact, bits: u32, hash: &'static str) -> bool {
 		is_valid_proof_of_work_hash(bits.into(), &H256::from_reversed_str(hash)) &&
@@ -211,34 +368,11 @@ mod tests {
 	// https://github.com/bitcoinclassic/bitcoinclassic/blob/8bf1fb856df44d1b790b0b835e4c1969be736e25/src/test/pow_tests.cpp#L108
 	#[test]
 	fn bitcoin_cash_req7() {
-		#[derive(Default)]
-		struct MemoryBlockHeaderProvider {
-			pub by_height: Vec<BlockHeader>,
-			pub by_hash: HashMap<H256, usize>,
-		}
-
-		impl MemoryBlockHeaderProvider {
-			pub fn insert(&mut self, header: BlockHeader) {
-				self.by_hash.insert(header.hash(), self.by_height.len());
-				self.by_height.push(header);
-			}
-		}
-
-		impl BlockHeaderProvider for MemoryBlockHeaderProvider {
-			fn block_header_bytes(&self, _block_ref: BlockRef) -> Option<Bytes> {
-				unimplemented!()
-			}
-
-			fn block_header(&self, block_ref: BlockRef) -> Option<BlockHeader> {
-				match block_ref {
-					BlockRef::Hash(ref hash) => self.by_hash.get(hash).map(|h| &self.by_height[*h]).cloned(),
-					BlockRef::Number(height) => self.by_height.get(height as usize).cloned(),
-				}
-			}
-		}
-
 		let main_consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
-		let uahf_consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(1000));
+		let uahf_consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams {
+			height: 1000,
+			..BitcoinCashConsensusParams::new(Magic::Mainnet)
+		}));
 		let mut header_provider = MemoryBlockHeaderProvider::default();
 		header_provider.insert(BlockHeader {
 				version: 0,
@@ -282,4 +416,152 @@ mod tests {
 		let uahf_bits: u32 = work_required(header.hash(), 0, 1010, &header_provider, &uahf_consensus).into();
 		assert_eq!(uahf_bits, 0x1d00ffff_u32);
 	}
+
+	#[test]
+	fn work_required_regtest_does_not_retarget() {
+		let regtest_consensus = ConsensusParams::new(Magic::Regtest, ConsensusFork::NoFork);
+		let unitest_consensus = ConsensusParams::new(Magic::Unitest, ConsensusFork::NoFork);
+		let mut header_provider = MemoryBlockHeaderProvider::default();
+		header_provider.insert(BlockHeader {
+				version: 0,
+				previous_header_hash: 0.into(),
+				merkle_root_hash: 0.into(),
+				time: 1296688602,
+				bits: 0x1e0fffff.into(),
+				nonce: 0,
+			});
+
+		// blocks are mined every second => retarget would increase difficulty
+		for height in 1..2016 {
+			let mut header = header_provider.block_header((height - 1).into()).unwrap();
+			header.previous_header_hash = header.hash();
+			header.time = header.time + 1;
+			header_provider.insert(header);
+		}
+
+		let parent_hash = header_provider.block_header(2015.into()).unwrap().hash();
+		let regtest_bits: u32 = work_required(parent_hash.clone(), 0, 2016, &header_provider, &regtest_consensus).into();
+		assert_eq!(regtest_bits, 0x1e0fffff_u32);
+		let unitest_bits: u32 = work_required(parent_hash, 0, 2016, &header_provider, &unitest_consensus).into();
+		assert!(unitest_bits != 0x1e0fffff_u32);
+	}
+
+	#[test]
+	fn work_required_testnet_min_difficulty_blocks() {
+		let testnet_consensus = ConsensusParams::new(Magic::Testnet, ConsensusFork::NoFork);
+		let mainnet_consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::NoFork);
+		let mut header_provider = MemoryBlockHeaderProvider::default();
+		header_provider.insert(BlockHeader {
+				version: 0,
+				previous_header_hash: 0.into(),
+				merkle_root_hash: 0.into(),
+				time: 1296688602,
+				bits: 0x1c0fffff.into(),
+				nonce: 0,
+			});
+
+		for height in 1..10 {
+			let mut header = header_provider.block_header((height - 1).into()).unwrap();
+			header.previous_header_hash = header.hash();
+			header.time = header.time + 10 * 60;
+			header_provider.insert(header);
+		}
+
+		// block, which is more than 20 minutes newer than its parent, may be mined with minimal difficulty
+		let parent = header_provider.block_header(9.into()).unwrap();
+		let bits: u32 = work_required(parent.hash(), parent.time + 20 * 60 + 1, 10, &header_provider, &testnet_consensus).into();
+		assert_eq!(bits, 0x1d00ffff_u32);
+		let bits: u32 = work_required(parent.hash(), parent.time + 20 * 60, 10, &header_provider, &testnet_consensus).into();
+		assert_eq!(bits, 0x1c0fffff_u32);
+		let bits: u32 = work_required(parent.hash(), parent.time + 20 * 60 + 1, 10, &header_provider, &mainnet_consensus).into();
+		assert_eq!(bits, 0x1c0fffff_u32);
+
+		// difficulty of the next block is the difficulty of the last block, mined without the exception
+		let mut header = parent.clone();
+		header.previous_header_hash = parent.hash();
+		header.time = parent.time + 20 * 60 + 1;
+		header.bits = 0x1d00ffff.into();
+		header_provider.insert(header.clone());
+		let bits: u32 = work_required(header.hash(), header.time + 10 * 60, 11, &header_provider, &testnet_consensus).into();
+		assert_eq!(bits, 0x1c0fffff_u32);
+	}
+
+	#[test]
+	fn work_required_retarget_interval() {
+		let mut header_provider = MemoryBlockHeaderProvider::default();
+		header_provider.insert(BlockHeader {
+				version: 0,
+				previous_header_hash: 0.into(),
+				merkle_root_hash: 0.into(),
+				time: 1296688602,
+				bits: 0x1c0fffff.into(),
+				nonce: 0,
+			});
+
+		// blocks are mined every 10 minutes, the last block of the interval is mined with minimal difficulty
+		for height in 1..4032 {
+			let mut header = header_provider.block_header((height - 1).into()).unwrap();
+			header.previous_header_hash = header.hash();
+			header.time = header.time + 10 * 60;
+			if height == 4031 {
+				header.bits = 0x1d00ffff.into();
+			}
+			header_provider.insert(header);
+		}
+
+		let parent = header_provider.block_header(4031.into()).unwrap();
+		let first = header_provider.block_header(2016.into()).unwrap();
+		let mut consensus = ConsensusParams::new(Magic::Testnet, ConsensusFork::NoFork);
+
+		// timespan of 2015 spacings is measured and the difficulty of the last block is adjusted
+		let bits = work_required(parent.hash(), 0, 4032, &header_provider, &consensus);
+		assert_eq!(bits, work_required_retarget(consensus.pow_limit, first.time, parent.time, parent.bits));
+
+		consensus.pow_retarget_first_block_bits = true;
+		let bits = work_required(parent.hash(), 0, 4032, &header_provider, &consensus);
+		assert_eq!(bits, work_required_retarget(consensus.pow_limit, first.time, parent.time, first.bits));
+		assert!(bits != first.bits);
+
+		// timespan of 2016 spacings matches the target timespan => difficulty isn't changed
+		consensus.pow_retarget_full_interval = true;
+		let bits: u32 = work_required(parent.hash(), 0, 4032, &header_provider, &consensus).into();
+		assert_eq!(bits, 0x1c0fffff_u32);
+	}
+
+	fn cw144_bits(spacing: u32) -> u32 {
+		let consensus = ConsensusParams::new(Magic::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams {
+			height: 1000,
+			..BitcoinCashConsensusParams::new(Magic::Mainnet)
+		}));
+		let mut header_provider = MemoryBlockHeaderProvider::default();
+		header_provider.insert(BlockHeader {
+				version: 0,
+				previous_header_hash: 0.into(),
+				merkle_root_hash: 0.into(),
+				time: 1500000000,
+				bits: 0x1c100000.into(),
+				nonce: 0,
+			});
+
+		for height in 1..147 {
+			let mut header = header_provider.block_header((height - 1).into()).unwrap();
+			header.previous_header_hash = header.hash();
+			header.time = header.time + spacing;
+			header_provider.insert(header);
+		}
+
+		let parent_hash = header_provider.block_header(146.into()).unwrap().hash();
+		work_required(parent_hash, 0, BITCOIN_CASH_DAA_MAINNET_BLOCK, &header_provider, &consensus).into()
+	}
+
+	#[test]
+	fn bitcoin_cash_cw144() {
+		// blocks are mined on schedule => difficulty is unchanged
+		assert_eq!(cw144_bits(600), 0x1c100000);
+		// blocks are mined twice faster => difficulty is doubled
+		assert_eq!(cw144_bits(300), 0x1c080000);
+		// timespan is constrained to [72 * 600; 288 * 600]
+		assert_eq!(cw144_bits(60), 0x1c080000);
+		assert_eq!(cw144_bits(6000), 0x1c200000);
+	}
 }
@@ -0,0 +1,207 @@
+//! Output script descriptors.
+//!
+//! Subset of the descriptors language (BIP380), which is enough to describe single key outputs:
+//!
+//! - `pkh(KEY)`: P2PKH output of the public key (BIP381);
+//! - `wpkh(KEY)`: P2WPKH output of the compressed public key (BIP382);
+//! - `sh(wpkh(KEY))`: P2WPKH output, nested in P2SH (BIP381, BIP382);
+//! - `addr(ADDRESS)`: output, paying to the address (BIP385);
+//! - `raw(HEX)`: output with the given script (BIP385).
+//!
+//! Keys are hex-encoded public keys. Descriptor may be followed by `#` and the checksum,
+//! which is verified when present.
+
+use std::fmt;
+use std::str::FromStr;
+use crypto::dhash160;
+use keys::{Address, Type, Network, Public};
+use keys::hex::{ToHex, FromHex};
+use primitives::bytes::Bytes;
+use script::{Builder, Opcode};
+
+pub use keys::descriptor_checksum;
+
+/// Output script descriptor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Descriptor {
+	/// P2PKH output of the public key
+	Pkh(Bytes),
+	/// P2WPKH output of the compressed public key
+	Wpkh(Bytes),
+	/// P2WPKH output of the compressed public key, nested in P2SH
+	ShWpkh(Bytes),
+	/// Output, paying to the address
+	Addr(Address),
+	/// Output with the given script
+	Raw(Bytes),
+}
+
+impl Descriptor {
+	/// Public key of the descriptor, if output is spendable by the single key
+	pub fn public_key(&self) -> Option<&Bytes> {
+		match *self {
+			Descriptor::Pkh(ref key) | Descriptor::Wpkh(ref key) | Descriptor::ShWpkh(ref key) => Some(key),
+			Descriptor::Addr(_) | Descriptor::Raw(_) => None,
+		}
+	}
+
+	/// Script of the described output
+	pub fn script_pubkey(&self) -> Bytes {
+		match *self {
+			Descriptor::Pkh(ref key) => Builder::build_p2pkh(&dhash160(key)).to_bytes(),
+			Descriptor::Wpkh(ref key) => witness_program(key),
+			Descriptor::ShWpkh(ref key) => Builder::build_p2sh(&dhash160(&witness_program(key))).to_bytes(),
+			Descriptor::Addr(ref address) => match address.kind {
+				Type::P2PKH => Builder::build_p2pkh(&address.hash).to_bytes(),
+				Type::P2SH => Builder::build_p2sh(&address.hash).to_bytes(),
+			},
+			Descriptor::Raw(ref script) => script.clone(),
+		}
+	}
+
+	/// Address of the described output. Segwit addresses are not supported, so `wpkh` outputs have no address.
+	pub fn address(&self, network: Network) -> Option<Address> {
+		match *self {
+			Descriptor::Pkh(ref key) => Some(Address {
+				kind: Type::P2PKH,
+				network: network,
+				hash: dhash160(key),
+			}),
+			Descriptor::ShWpkh(ref key) => Some(Address {
+				kind: Type::P2SH,
+				network: network,
+				hash: dhash160(&witness_program(key)),
+			}),
+			Descriptor::Addr(ref address) => Some(address.clone()),
+			Descriptor::Wpkh(_) | Descriptor::Raw(_) => None,
+		}
+	}
+
+	/// Descriptor without the checksum
+	fn body(&self) -> String {
+		match *self {
+			Descriptor::Pkh(ref key) => format!("pkh({})", key[..].to_hex()),
+			Descriptor::Wpkh(ref key) => format!("wpkh({})", key[..].to_hex()),
+			Descriptor::ShWpkh(ref key) => format!("sh(wpkh({}))", key[..].to_hex()),
+			Descriptor::Addr(ref address) => format!("addr({})", address),
+			Descriptor::Raw(ref script) => format!("raw({})", script[..].to_hex()),
+		}
+	}
+}
+
+impl fmt::Display for Descriptor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let body = self.body();
+		let checksum = descriptor_checksum(&body).expect("descriptor body only contains hex, base58 and parentheses; qed");
+		write!(f, "{}#{}", body, checksum)
+	}
+}
+
+impl FromStr for Descriptor {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let descriptor = match s.find('#') {
+			Some(position) => {
+				let (descriptor, checksum) = (&s[..position], &s[position + 1..]);
+				if descriptor_checksum(descriptor).as_ref().map(String::as_str) != Some(checksum) {
+					return Err(format!("Invalid descriptor checksum '{}'", checksum));
+				}
+				descriptor
+			},
+			None => s,
+		};
+
+		let (function, argument) = split_function(descriptor)
+			.ok_or_else(|| format!("Invalid descriptor '{}'", descriptor))?;
+		match function {
+			"pkh" => Ok(Descriptor::Pkh(parse_key(argument, false)?)),
+			"wpkh" => Ok(Descriptor::Wpkh(parse_key(argument, true)?)),
+			"sh" => match split_function(argument) {
+				Some(("wpkh", key)) => Ok(Descriptor::ShWpkh(parse_key(key, true)?)),
+				_ => Err(format!("Unsupported descriptor '{}', only sh(wpkh(KEY)) is supported", descriptor)),
+			},
+			"addr" => argument.parse()
+				.map(Descriptor::Addr)
+				.map_err(|_| format!("Invalid address '{}'", argument)),
+			"raw" => argument.from_hex()
+				.map(|script| Descriptor::Raw(script.into()))
+				.map_err(|_| format!("Invalid script '{}'", argument)),
+			_ => Err(format!("Unsupported descriptor '{}'", descriptor)),
+		}
+	}
+}
+
+/// Splits `function(argument)` into function name and argument.
+fn split_function(s: &str) -> Option<(&str, &str)> {
+	match s.find('(') {
+		Some(open) if s.ends_with(')') => Some((&s[..open], &s[open + 1..s.len() - 1])),
+		_ => None,
+	}
+}
+
+fn parse_key(key: &str, compressed_only: bool) -> Result<Bytes, String> {
+	let bytes = key.from_hex().map_err(|_| format!("Invalid public key '{}'", key))?;
+	Public::from_slice(&bytes).map_err(|_| format!("Invalid public key '{}'", key))?;
+	if compressed_only && bytes.len() != 33 {
+		return Err(format!("Public key '{}' must be compressed", key));
+	}
+	Ok(bytes.into())
+}
+
+/// Version 0 witness program of the public key.
+fn witness_program(key: &[u8]) -> Bytes {
+	Builder::default()
+		.push_opcode(Opcode::OP_0)
+		.push_bytes(&*dhash160(key))
+		.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+	use keys::Network;
+	use super::{Descriptor, descriptor_checksum};
+
+	#[test]
+	fn test_descriptor_checksum() {
+		assert_eq!(descriptor_checksum("raw(deadbeef)"), Some("89f8spxm".to_owned()));
+		assert_eq!(descriptor_checksum("pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)"), Some("8fhd9pwu".to_owned()));
+		assert_eq!(descriptor_checksum("wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"), Some("8zl0zxma".to_owned()));
+		assert_eq!(descriptor_checksum("sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))"), Some("qkrrc7je".to_owned()));
+		assert_eq!(descriptor_checksum("addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)"), Some("632p52jr".to_owned()));
+		assert_eq!(descriptor_checksum("raw(\u{e9})"), None);
+	}
+
+	#[test]
+	fn test_descriptor_parse() {
+		let descriptor: Descriptor = "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)".parse().unwrap();
+		assert_eq!(descriptor.to_string(), "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)#8fhd9pwu");
+		assert_eq!(descriptor.to_string().parse::<Descriptor>(), Ok(descriptor));
+
+		assert!("raw(deadbeef)#89f8spxm".parse::<Descriptor>().is_ok());
+		assert!("raw(deadbeef)#89f8spxn".parse::<Descriptor>().is_err());
+		assert!("raw(deadbee)".parse::<Descriptor>().is_err());
+		assert!("pkh(02c6047f)".parse::<Descriptor>().is_err());
+		assert!("sh(pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5))".parse::<Descriptor>().is_err());
+		assert!("combo(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)".parse::<Descriptor>().is_err());
+		assert!("wpkh(04a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7893aba425419bc27a3b6c7e693a24c696f794c2ed877a1593cbee53b037368d7)".parse::<Descriptor>().is_err());
+	}
+
+	#[test]
+	fn test_descriptor_script_pubkey() {
+		let descriptor: Descriptor = "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)".parse().unwrap();
+		assert_eq!(descriptor.script_pubkey(), "76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac".into());
+
+		let descriptor: Descriptor = "wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556)".parse().unwrap();
+		assert_eq!(descriptor.script_pubkey(), "00147fda9cf020c16cacf529c87d8de89bfc70b8c9cb".into());
+		assert_eq!(descriptor.address(Network::Mainnet), None);
+
+		let descriptor: Descriptor = "sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))".parse().unwrap();
+		assert_eq!(descriptor.script_pubkey(), "a914cc6ffbc0bf31af759451068f90ba7a0272b6b33287".into());
+		assert_eq!(descriptor.address(Network::Mainnet).unwrap().hash, "cc6ffbc0bf31af759451068f90ba7a0272b6b332".into());
+
+		let descriptor: Descriptor = "addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)".parse().unwrap();
+		assert_eq!(descriptor.address(Network::Mainnet).unwrap().to_string(), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+		assert_eq!(descriptor.script_pubkey(), "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac".into());
+	}
+}
@@ -0,0 +1,21 @@
+//! Bitcoin wallet.
+
+#[macro_use]
+extern crate log;
+extern crate parking_lot;
+extern crate bitcrypto as crypto;
+extern crate chain;
+extern crate db;
+extern crate keys;
+extern crate primitives;
+extern crate script;
+extern crate serialization as ser;
+
+#[cfg(test)]
+extern crate test_data;
+
+mod descriptor;
+mod wallet;
+
+pub use descriptor::{Descriptor, descriptor_checksum};
+pub use wallet::{Wallet, ChainChange, WalletOutput, UnspentOutput, MAX_REORG_DEPTH, WALLET_RECORDS};
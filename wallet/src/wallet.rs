@@ -0,0 +1,563 @@
+//! Wallet.
+//!
+//! Wallet tracks canon chain outputs, paying to its descriptors. Blocks are scanned in order,
+//! starting from the best block at the moment the wallet has been created, so outputs of the
+//! earlier blocks are never found. When canon chain is reorganized, decanonized blocks are
+//! reverted before blocks of the new canon chain are scanned.
+//!
+//! Wallet state (descriptors, keys, outputs and the last scanned block) is stored in the blocks
+//! database. Private keys of descriptors, created by the wallet itself, are stored unencrypted.
+
+use std::collections::HashMap;
+use std::io;
+use parking_lot::RwLock;
+use chain::{IndexedBlock, OutPoint};
+use db::{BestBlock, BlockRef, SharedStore, Store, BlockHeaderProvider, BlockProvider, IndexedBlockProvider, WalletStore};
+use keys::{Address, KeyPair, Network, Private};
+use keys::generator::{Generator, Random};
+use primitives::bytes::Bytes;
+use primitives::hash::H256;
+use ser::{serialize, deserialize, Serializable, Deserializable, Error as ReaderError, Stream, Reader, List};
+use descriptor::Descriptor;
+
+/// Number of blocks, spent outputs are kept for, so that they could be restored when spending block is reverted.
+pub const MAX_REORG_DEPTH: u32 = 100;
+
+const DESCRIPTORS_RECORD: &'static str = "descriptors";
+const KEYS_RECORD: &'static str = "keys";
+const OUTPUTS_RECORD: &'static str = "outputs";
+const BEST_BLOCK_RECORD: &'static str = "best_block";
+
+/// Names of all wallet database records.
+pub const WALLET_RECORDS: &'static [&'static str] = &[DESCRIPTORS_RECORD, KEYS_RECORD, OUTPUTS_RECORD, BEST_BLOCK_RECORD];
+
+/// Canon chain output, paying to the wallet descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletOutput {
+	pub outpoint: OutPoint,
+	pub value: u64,
+	pub script_pubkey: Bytes,
+	/// Height of the block, which includes the transaction
+	pub height: u32,
+	/// Height of the block, which includes the spending transaction
+	pub spent_height: Option<u32>,
+}
+
+/// Unspent wallet output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnspentOutput {
+	pub outpoint: OutPoint,
+	pub value: u64,
+	pub script_pubkey: Bytes,
+	/// Descriptor, which output is paying to
+	pub descriptor: Descriptor,
+	/// Address of the output, if descriptor has one
+	pub address: Option<Address>,
+	/// Number of blocks, including the output
+	pub confirmations: u32,
+	/// Is private key of the output known to the wallet?
+	pub spendable: bool,
+}
+
+/// Canon chain change, reported by the synchronization.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainChange {
+	/// Block has been connected to the canon chain
+	Connected(H256),
+	/// Block has been disconnected from the canon chain
+	Disconnected(H256),
+}
+
+#[derive(Default)]
+struct WalletState {
+	descriptors: Vec<Descriptor>,
+	/// Key pairs by public key
+	keys: HashMap<Bytes, KeyPair>,
+	/// Descriptor index by output script
+	scripts: HashMap<Bytes, usize>,
+	outputs: HashMap<OutPoint, WalletOutput>,
+	/// Last scanned block
+	best_block: BestBlock,
+}
+
+/// Wallet
+pub struct Wallet {
+	store: SharedStore,
+	network: Network,
+	state: RwLock<WalletState>,
+}
+
+impl Wallet {
+	/// Opens the wallet, stored in the database. New wallet starts scanning after the current best block.
+	pub fn open(store: SharedStore, network: Network) -> Result<Self, String> {
+		let mut state = WalletState::default();
+		for descriptor in read_record::<List<Bytes>>(&store, DESCRIPTORS_RECORD)?.map_or_else(Vec::new, List::into) {
+			let descriptor = String::from_utf8(descriptor.into()).ok()
+				.and_then(|descriptor| descriptor.parse().ok())
+				.ok_or_else(|| corrupted_record(DESCRIPTORS_RECORD))?;
+			state.add_descriptor(descriptor);
+		}
+		for key in read_record::<List<Bytes>>(&store, KEYS_RECORD)?.map_or_else(Vec::new, List::into) {
+			let key_pair = String::from_utf8(key.into()).ok()
+				.and_then(|key| key.parse::<Private>().ok())
+				.and_then(|private| KeyPair::from_private(private).ok())
+				.ok_or_else(|| corrupted_record(KEYS_RECORD))?;
+			state.keys.insert(key_pair.public()[..].into(), key_pair);
+		}
+		for output in read_record::<List<WalletOutput>>(&store, OUTPUTS_RECORD)?.map_or_else(Vec::new, List::into) {
+			state.outputs.insert(output.outpoint.clone(), output);
+		}
+		state.best_block = match store.wallet_record(BEST_BLOCK_RECORD) {
+			Some(record) => {
+				let mut reader = Reader::new(&record);
+				BestBlock {
+					number: reader.read().map_err(|_| corrupted_record(BEST_BLOCK_RECORD))?,
+					hash: reader.read().map_err(|_| corrupted_record(BEST_BLOCK_RECORD))?,
+				}
+			},
+			None => store.best_block(),
+		};
+
+		Ok(Wallet {
+			store: store,
+			network: network,
+			state: RwLock::new(state),
+		})
+	}
+
+	/// Last scanned block
+	pub fn best_block(&self) -> BestBlock {
+		self.state.read().best_block.clone()
+	}
+
+	/// All wallet descriptors
+	pub fn descriptors(&self) -> Vec<Descriptor> {
+		self.state.read().descriptors.clone()
+	}
+
+	/// Starts tracking outputs of the descriptor. Blocks, scanned before, are not rescanned.
+	pub fn import_descriptor(&self, descriptor: Descriptor) -> Result<(), String> {
+		let mut state = self.state.write();
+		if state.add_descriptor(descriptor) {
+			self.persist(&state)?;
+		}
+		Ok(())
+	}
+
+	/// Generates new key and returns P2PKH address of it.
+	pub fn new_address(&self) -> Result<Address, String> {
+		let key_pair = Random::new(self.network).generate()
+			.and_then(|key_pair| KeyPair::from_private(Private {
+				network: self.network,
+				secret: key_pair.private().secret.clone(),
+				compressed: true,
+			}))
+			.map_err(|err| format!("Failed to generate key: {}", err))?;
+		let descriptor = Descriptor::Pkh(key_pair.public()[..].into());
+		let address = descriptor.address(self.network).expect("pkh descriptor always has an address; qed");
+
+		let mut state = self.state.write();
+		state.keys.insert(key_pair.public()[..].into(), key_pair);
+		state.add_descriptor(descriptor);
+		self.persist(&state)?;
+		Ok(address)
+	}
+
+	/// Total value of unspent outputs with at least `min_confirmations` confirmations
+	pub fn balance(&self, min_confirmations: u32) -> u64 {
+		self.unspent(min_confirmations).into_iter().map(|output| output.value).sum()
+	}
+
+	/// Unspent outputs with at least `min_confirmations` confirmations, the oldest first
+	pub fn unspent(&self, min_confirmations: u32) -> Vec<UnspentOutput> {
+		let state = self.state.read();
+		let mut unspent: Vec<_> = state.outputs.values()
+			.filter(|output| output.spent_height.is_none())
+			.map(|output| {
+				let descriptor = state.scripts.get(&output.script_pubkey)
+					.map(|index| state.descriptors[*index].clone())
+					.expect("outputs are only tracked for wallet descriptors; qed");
+				UnspentOutput {
+					outpoint: output.outpoint.clone(),
+					value: output.value,
+					script_pubkey: output.script_pubkey.clone(),
+					address: descriptor.address(self.network),
+					spendable: descriptor.public_key().map_or(false, |key| state.keys.contains_key(key)),
+					descriptor: descriptor,
+					confirmations: state.best_block.number + 1 - output.height,
+				}
+			})
+			.filter(|output| output.confirmations >= min_confirmations)
+			.collect();
+		unspent.sort_by(|a, b| b.confirmations.cmp(&a.confirmations)
+			.then_with(|| a.outpoint.hash.cmp(&b.outpoint.hash))
+			.then_with(|| a.outpoint.index.cmp(&b.outpoint.index)));
+		unspent
+	}
+
+	/// Scans canon chain blocks, which haven't been scanned yet. Decanonized blocks are reverted first.
+	pub fn synchronize(&self) -> Result<(), String> {
+		let mut state = self.state.write();
+		let initial_best_block = state.best_block.clone();
+		self.synchronize_state(&mut state)?;
+
+		if state.best_block != initial_best_block {
+			self.persist(&state)?;
+		}
+		Ok(())
+	}
+
+	/// Applies canon chain changes in the order they have happened. If change doesn't follow the last
+	/// scanned block (i.e. it has been scanned already), wallet is synchronized with the canon chain instead.
+	pub fn apply_chain_changes(&self, changes: &[ChainChange]) -> Result<(), String> {
+		let mut state = self.state.write();
+		let initial_best_block = state.best_block.clone();
+		for change in changes {
+			if !self.apply_chain_change(&mut state, change)? {
+				self.synchronize_state(&mut state)?;
+				break;
+			}
+		}
+
+		if state.best_block != initial_best_block {
+			self.persist(&state)?;
+		}
+		Ok(())
+	}
+
+	/// Applies single canon chain change. Returns false if change doesn't follow the last scanned block.
+	fn apply_chain_change(&self, state: &mut WalletState, change: &ChainChange) -> Result<bool, String> {
+		match *change {
+			ChainChange::Connected(ref hash) => {
+				let header = self.store.block_header(BlockRef::Hash(hash.clone()))
+					.ok_or_else(|| format!("Block {} is not found in the database", hash.to_reversed_str()))?;
+				if header.previous_header_hash != state.best_block.hash {
+					return Ok(false);
+				}
+
+				let number = state.best_block.number + 1;
+				if !state.descriptors.is_empty() {
+					let block = self.store.indexed_block(BlockRef::Hash(hash.clone()))
+						.ok_or_else(|| format!("Block {} is not found in the database", hash.to_reversed_str()))?;
+					state.process_block(&block, number);
+				}
+				state.best_block = BestBlock {
+					number: number,
+					hash: hash.clone(),
+				};
+			},
+			ChainChange::Disconnected(ref hash) => {
+				if *hash != state.best_block.hash {
+					return Ok(false);
+				}
+
+				let header = self.store.block_header(BlockRef::Hash(hash.clone()))
+					.ok_or_else(|| format!("Wallet block {} is not found in the database", hash.to_reversed_str()))?;
+				let number = state.best_block.number;
+				trace!(target: "wallet", "Reverting block {} at height {}", hash.to_reversed_str(), number);
+				state.revert_block(number);
+				state.best_block = BestBlock {
+					number: number - 1,
+					hash: header.previous_header_hash,
+				};
+			},
+		}
+		Ok(true)
+	}
+
+	/// Scans canon chain blocks, which haven't been scanned yet, without persisting the state.
+	fn synchronize_state(&self, state: &mut WalletState) -> Result<(), String> {
+		loop {
+			let number = state.best_block.number;
+			if self.store.block_hash(number).as_ref() != Some(&state.best_block.hash) {
+				let header = self.store.block_header(BlockRef::Hash(state.best_block.hash.clone()))
+					.ok_or_else(|| format!("Wallet block {} is not found in the database", state.best_block.hash.to_reversed_str()))?;
+				trace!(target: "wallet", "Reverting block {} at height {}", state.best_block.hash.to_reversed_str(), number);
+				state.revert_block(number);
+				state.best_block = BestBlock {
+					number: number - 1,
+					hash: header.previous_header_hash,
+				};
+				continue;
+			}
+
+			let best_block = self.store.best_block();
+			if number >= best_block.number {
+				break;
+			}
+
+			if state.descriptors.is_empty() {
+				state.best_block = best_block;
+				break;
+			}
+
+			let block = self.store.indexed_block(BlockRef::Number(number + 1))
+				.ok_or_else(|| format!("Block {} is not available in the database", number + 1))?;
+			if block.header.raw.previous_header_hash != state.best_block.hash {
+				// canon chain has been reorganized after the check
+				continue;
+			}
+
+			state.process_block(&block, number + 1);
+			state.best_block = BestBlock {
+				number: number + 1,
+				hash: block.hash().clone(),
+			};
+		}
+		Ok(())
+	}
+
+	/// Writes all wallet records to the database.
+	fn persist(&self, state: &WalletState) -> Result<(), String> {
+		let descriptors: Vec<Bytes> = state.descriptors.iter()
+			.map(|descriptor| descriptor.to_string().into_bytes().into())
+			.collect();
+		let keys: Vec<Bytes> = state.keys.values()
+			.map(|key_pair| key_pair.private().to_string().into_bytes().into())
+			.collect();
+		let outputs: Vec<WalletOutput> = state.outputs.values().cloned().collect();
+		let mut best_block = Stream::new();
+		best_block.append(&state.best_block.number).append(&state.best_block.hash);
+
+		self.store.set_wallet_records(vec![
+			(DESCRIPTORS_RECORD, serialize(&List::from(descriptors))),
+			(KEYS_RECORD, serialize(&List::from(keys))),
+			(OUTPUTS_RECORD, serialize(&List::from(outputs))),
+			(BEST_BLOCK_RECORD, best_block.out()),
+		]).map_err(String::from)
+	}
+}
+
+impl WalletState {
+	/// Adds descriptor. Returns false if descriptor is already known.
+	fn add_descriptor(&mut self, descriptor: Descriptor) -> bool {
+		if self.descriptors.contains(&descriptor) {
+			return false;
+		}
+
+		self.scripts.insert(descriptor.script_pubkey(), self.descriptors.len());
+		self.descriptors.push(descriptor);
+		true
+	}
+
+	fn process_block(&mut self, block: &IndexedBlock, height: u32) {
+		for transaction in &block.transactions {
+			for input in &transaction.raw.inputs {
+				if let Some(output) = self.outputs.get_mut(&input.previous_output) {
+					output.spent_height = Some(height);
+				}
+			}
+
+			for (index, output) in transaction.raw.outputs.iter().enumerate() {
+				if !self.scripts.contains_key(&output.script_pubkey) {
+					continue;
+				}
+
+				let outpoint = OutPoint {
+					hash: transaction.hash.clone(),
+					index: index as u32,
+				};
+				trace!(target: "wallet", "Received {} satoshis in {}:{}", output.value, outpoint.hash.to_reversed_str(), outpoint.index);
+				self.outputs.insert(outpoint.clone(), WalletOutput {
+					outpoint: outpoint,
+					value: output.value,
+					script_pubkey: output.script_pubkey.clone(),
+					height: height,
+					spent_height: None,
+				});
+			}
+		}
+
+		// outputs, spent deeper than reorganizations are handled, are forgotten
+		self.outputs.retain(|_, output| output.spent_height.map_or(true, |spent_height| spent_height + MAX_REORG_DEPTH > height));
+	}
+
+	fn revert_block(&mut self, height: u32) {
+		self.outputs.retain(|_, output| output.height < height);
+		for output in self.outputs.values_mut() {
+			if output.spent_height == Some(height) {
+				output.spent_height = None;
+			}
+		}
+	}
+}
+
+fn read_record<T>(store: &SharedStore, name: &'static str) -> Result<Option<T>, String> where T: Deserializable {
+	match store.wallet_record(name) {
+		Some(record) => deserialize(&record[..]).map(Some).map_err(|_| corrupted_record(name)),
+		None => Ok(None),
+	}
+}
+
+fn corrupted_record(name: &str) -> String {
+	format!("Wallet record '{}' is corrupted", name)
+}
+
+impl Serializable for WalletOutput {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.outpoint)
+			.append(&self.value)
+			.append(&self.script_pubkey)
+			.append(&self.height);
+		match self.spent_height {
+			Some(spent_height) => stream
+				.append(&1u8)
+				.append(&spent_height),
+			None => stream.append(&0u8),
+		};
+	}
+}
+
+impl Deserializable for WalletOutput {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let outpoint = reader.read()?;
+		let value = reader.read()?;
+		let script_pubkey = reader.read()?;
+		let height = reader.read()?;
+		let spent_height = match reader.read::<u8>()? {
+			0 => None,
+			1 => Some(reader.read()?),
+			_ => return Err(ReaderError::MalformedData),
+		};
+
+		Ok(WalletOutput {
+			outpoint: outpoint,
+			value: value,
+			script_pubkey: script_pubkey,
+			height: height,
+			spent_height: spent_height,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use chain::{IndexedBlock, OutPoint};
+	use db::{BlockChainDatabase, BlockOrigin, SharedStore, Store, BlockChain, ForkChain, Forkable, BlockProvider, TransactionOutputProvider};
+	use keys::{Network, Type};
+	use test_data::ChainGenerator;
+	use descriptor::Descriptor;
+	use super::{Wallet, ChainChange};
+
+	#[test]
+	fn wallet_follows_canon_chain() {
+		let mut generator = ChainGenerator::with_genesis_outputs(4, 1_000_000)
+			.transactions_per_block(2)
+			.reorg_at(8, 2);
+		let genesis: IndexedBlock = generator.genesis().clone().into();
+		let script = genesis.transactions[0].raw.outputs[0].script_pubkey.clone();
+		let store: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis]));
+		let wallet = Wallet::open(store.clone(), Network::Testnet).unwrap();
+		wallet.import_descriptor(Descriptor::Raw(script.clone())).unwrap();
+
+		for block in generator.generate(10) {
+			let block: IndexedBlock = block.into();
+			match store.block_origin(&block.header).unwrap() {
+				BlockOrigin::KnownBlock => unreachable!(),
+				BlockOrigin::CanonChain { .. } => {
+					store.insert(block.clone()).unwrap();
+					store.canonize(block.hash()).unwrap();
+				},
+				BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+				BlockOrigin::SideChainBecomingCanonChain(origin) => {
+					let fork = store.fork(origin).unwrap();
+					fork.store().insert(block.clone()).unwrap();
+					fork.store().canonize(block.hash()).unwrap();
+					store.switch_to_fork(fork).unwrap();
+				},
+			}
+			wallet.synchronize().unwrap();
+		}
+		store.rollback_best().unwrap();
+		wallet.synchronize().unwrap();
+		assert_eq!(wallet.best_block(), store.best_block());
+
+		// outputs of the genesis block are not tracked, because wallet has been created after it
+		let mut canon_balance = 0;
+		for number in 1..store.best_block().number + 1 {
+			let block = store.block(number.into()).unwrap();
+			for transaction in &block.transactions {
+				for (index, output) in transaction.outputs.iter().enumerate() {
+					let outpoint = OutPoint { hash: transaction.hash(), index: index as u32 };
+					if output.script_pubkey == script && !store.is_spent(&outpoint) {
+						canon_balance += output.value;
+					}
+				}
+			}
+		}
+		assert!(canon_balance > 0);
+		assert_eq!(wallet.balance(0), canon_balance);
+		assert!(wallet.balance(3) < canon_balance);
+		assert!(wallet.unspent(0).iter().all(|output| !output.spendable && output.address.is_none()));
+
+		// wallet is restored from the database
+		let wallet = Wallet::open(store.clone(), Network::Testnet).unwrap();
+		assert_eq!(wallet.best_block(), store.best_block());
+		assert_eq!(wallet.balance(0), canon_balance);
+	}
+
+	#[test]
+	fn wallet_applies_chain_changes() {
+		let mut generator = ChainGenerator::with_genesis_outputs(4, 1_000_000)
+			.transactions_per_block(2)
+			.reorg_at(8, 2);
+		let genesis: IndexedBlock = generator.genesis().clone().into();
+		let script = genesis.transactions[0].raw.outputs[0].script_pubkey.clone();
+		let store: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis]));
+		let wallet = Wallet::open(store.clone(), Network::Testnet).unwrap();
+		wallet.import_descriptor(Descriptor::Raw(script.clone())).unwrap();
+		let synchronized_wallet = Wallet::open(store.clone(), Network::Testnet).unwrap();
+		synchronized_wallet.import_descriptor(Descriptor::Raw(script)).unwrap();
+
+		for block in generator.generate(10) {
+			let block: IndexedBlock = block.into();
+			let mut changes = Vec::new();
+			match store.block_origin(&block.header).unwrap() {
+				BlockOrigin::KnownBlock => unreachable!(),
+				BlockOrigin::CanonChain { .. } => {
+					store.insert(block.clone()).unwrap();
+					store.canonize(block.hash()).unwrap();
+					changes.push(ChainChange::Connected(block.hash().clone()));
+				},
+				BlockOrigin::SideChain(_) => store.insert(block).unwrap(),
+				BlockOrigin::SideChainBecomingCanonChain(origin) => {
+					changes.extend(origin.decanonized_route.iter().rev().cloned().map(ChainChange::Disconnected));
+					changes.extend(origin.canonized_route.iter().cloned().map(ChainChange::Connected));
+					changes.push(ChainChange::Connected(block.hash().clone()));
+					let fork = store.fork(origin).unwrap();
+					fork.store().insert(block.clone()).unwrap();
+					fork.store().canonize(block.hash()).unwrap();
+					store.switch_to_fork(fork).unwrap();
+				},
+			}
+			wallet.apply_chain_changes(&changes).unwrap();
+			synchronized_wallet.synchronize().unwrap();
+			assert_eq!(wallet.best_block(), store.best_block());
+			assert_eq!(wallet.balance(0), synchronized_wallet.balance(0));
+		}
+
+		// changes, which have been already applied, are ignored
+		let best_block = store.best_block();
+		wallet.apply_chain_changes(&[ChainChange::Connected(best_block.hash.clone())]).unwrap();
+		assert_eq!(wallet.best_block(), best_block);
+		assert_eq!(wallet.balance(0), synchronized_wallet.balance(0));
+	}
+
+	#[test]
+	fn wallet_new_address() {
+		let generator = ChainGenerator::new();
+		let store: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![generator.genesis().clone().into()]));
+		let wallet = Wallet::open(store.clone(), Network::Testnet).unwrap();
+		let address = wallet.new_address().unwrap();
+		assert_eq!(address.kind, Type::P2PKH);
+		assert_eq!(address.network, Network::Testnet);
+
+		let wallet = Wallet::open(store, Network::Testnet).unwrap();
+		let descriptors = wallet.descriptors();
+		assert_eq!(descriptors.len(), 1);
+		assert_eq!(descriptors[0].address(Network::Testnet), Some(address));
+		assert_eq!(wallet.balance(0), 0);
+	}
+}